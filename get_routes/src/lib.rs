@@ -4,10 +4,21 @@ extern crate syn;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, ItemFn};
+use syn::{parse_macro_input, Ident, ItemFn};
 
+/// Registers a function as the handler of a route. Handlers writing to the graph are
+/// marked with `#[handler(writes)]`, so read-only replicas can reject them.
 #[proc_macro_attribute]
-pub fn handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn handler(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let writes = match parse_macro_input!(attr as Option<Ident>) {
+        Some(flag) if flag == "writes" => quote! { .writes() },
+        Some(flag) => {
+            return syn::Error::new(flag.span(), "expected `writes`")
+                .to_compile_error()
+                .into()
+        }
+        None => quote! {},
+    };
     let input_fn = parse_macro_input!(item as ItemFn);
     let fn_name = &input_fn.sig.ident;
     let fn_name_str = fn_name.to_string();
@@ -26,6 +37,36 @@ pub fn handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
                     ::helixdb::helix_gateway::router::router::Handler::new(
                         #fn_name_str,
                         #fn_name
+                    )#writes
+                )
+            }
+        };
+    };
+    expanded.into()
+}
+
+
+/// Registers an async function as the handler of a route. The handler is run as a task of
+/// its own on the gateway's runtime, so it can wait on I/O without holding up a worker.
+#[proc_macro_attribute]
+pub fn async_handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(item as ItemFn);
+    let fn_name = &input_fn.sig.ident;
+    let fn_name_str = fn_name.to_string();
+    // Create a unique static name for each handler
+    let static_name = quote::format_ident!("__ASYNC_HANDLER_REGISTRATION_{}", fn_name.to_string().to_uppercase());
+
+    let expanded = quote! {
+        #input_fn
+
+        #[doc(hidden)]
+        #[used]
+        static #static_name: () = {
+            inventory::submit! {
+                ::helixdb::helix_gateway::router::router::AsyncHandlerSubmission(
+                    ::helixdb::helix_gateway::router::router::AsyncHandler::new(
+                        #fn_name_str,
+                        |input| ::std::boxed::Box::pin(#fn_name(input))
                     )
                 )
             }
@@ -34,6 +75,35 @@ pub fn handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
     expanded.into()
 }
 
+/// Registers a function running a query inside a caller owned write transaction, so the
+/// query can be run through the transaction endpoint. Takes the name of the query.
+#[proc_macro_attribute]
+pub fn tx_handler(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let query_name = parse_macro_input!(attr as Ident);
+    let input_fn = parse_macro_input!(item as ItemFn);
+    let fn_name = &input_fn.sig.ident;
+    let query_name_str = query_name.to_string();
+    // Create a unique static name for each handler
+    let static_name = quote::format_ident!("__TX_HANDLER_REGISTRATION_{}", fn_name.to_string().to_uppercase());
+
+    let expanded = quote! {
+        #input_fn
+
+        #[doc(hidden)]
+        #[used]
+        static #static_name: () = {
+            inventory::submit! {
+                ::helixdb::helix_gateway::router::router::TxHandlerSubmission(
+                    ::helixdb::helix_gateway::router::router::TxHandler::new(
+                        #query_name_str,
+                        #fn_name
+                    )
+                )
+            }
+        };
+    };
+    expanded.into()
+}
 
 #[proc_macro_attribute]
 pub fn local_handler(_attr: TokenStream, item: TokenStream) -> TokenStream {
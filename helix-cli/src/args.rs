@@ -32,6 +32,9 @@ pub enum CommandType {
     /// Lint a Helix project
     Check(LintCommand),
 
+    /// Format the schema and query files of a Helix project
+    Fmt(FmtCommand),
+
     /// Install the Helix repo
     Install(InstallCommand),
 
@@ -62,6 +65,45 @@ pub enum CommandType {
     /// Delete an instance and all its data
     Delete(DeleteCommand),
 
+    /// Migrate the data of an instance to the current schema
+    Migrate(MigrateCommand),
+
+    /// Manage read-only replicas of instances
+    Replica(ReplicaCommand),
+
+    /// Run HelixQL interactively against an instance
+    Shell(ShellCommand),
+
+    /// Push the queries of a project to a running instance without rebuilding it
+    Push(PushCommand),
+
+    /// Print the logs of an instance
+    Logs(LogsCommand),
+
+    /// Show the health and metrics of running instances
+    Status(StatusCommand),
+
+    /// Rebuild an instance whenever the files of a project change
+    Dev(DevCommand),
+
+    /// Load nodes, edges and vectors from a file into an instance
+    Seed(SeedCommand),
+
+    /// Export the nodes and edges of an instance as GraphML, JSONL or CSV
+    Export(ExportCommand),
+
+    /// Load the nodes and edges of a GraphML, JSONL or CSV export into an instance
+    Import(ImportCommand),
+
+    /// Check the references between the stored items of an instance and repair orphans
+    Fsck(FsckCommand),
+
+    /// Benchmark a running instance and compare the results with a baseline
+    Bench(BenchCommand),
+
+    /// Replay a weighted mix of queries against a running instance
+    Loadtest(LoadtestCommand),
+
     /// Get the current version of the cli and db
     Version(VersionCommand),
 }
@@ -109,7 +151,7 @@ pub struct CompileCommand {
     #[clap(short, long, help = "The output path")]
     pub output: Option<String>,
 
-    #[clap(short, long, help = "The target language")]
+    #[clap(short, long, alias = "target", help = "The target language")]
     pub gen: OutputLanguage,
     // #[clap(short, long, help = "The target platform")]
     // pub target: Option<String>,
@@ -123,6 +165,22 @@ pub struct CompileCommand {
 pub struct LintCommand {
     #[clap(short, long, help = "The path to the project")]
     pub path: Option<String>,
+
+    #[clap(
+        long,
+        help = "Print the diagnostics as a JSON array instead of rendering them"
+    )]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+#[clap(name = "fmt", about = "Format the schema and query files of a Helix project")]
+pub struct FmtCommand {
+    #[clap(short, long, help = "The path to the project, or a single .hx file")]
+    pub path: Option<String>,
+
+    #[clap(long, help = "List the files that aren't formatted instead of writing them")]
+    pub check: bool,
 }
 
 #[derive(Debug, Args)]
@@ -134,6 +192,13 @@ pub struct InstallCommand {}
 pub struct InitCommand {
     #[clap(short, long, help = "The path to the project")]
     pub path: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "Start from a template: social-graph, rag-chatbot or recommendations"
+    )]
+    pub template: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -170,11 +235,18 @@ pub struct StartCommand {
 #[derive(Debug, Args)]
 #[clap(name = "ingest", about = "Ingest data into Helix")]
 pub struct IngestCommand {
-    /// Type of database to ingest from ('sqlite' or 'pg')
-    #[clap(short = 't', long = "type", value_parser = ["sqlite", "pg"])]
+    /// Type of database to ingest from ('sqlite', 'pg' or 'neo4j'), or 'file' for CSV and
+    /// Parquet files described by a mapping file
+    #[clap(
+        short = 't',
+        long = "type",
+        alias = "db-type",
+        value_parser = ["sqlite", "pg", "neo4j", "file"]
+    )]
     pub db_type: String,
 
-    /// Database connection string or path
+    /// Database connection string or path, the CSV export of a Neo4j database or the
+    /// mapping file of the files to ingest
     #[clap(short, long = "db", help = "Database connection string or path")]
     pub db_url: String,
 
@@ -186,7 +258,7 @@ pub struct IngestCommand {
     )]
     pub instance: String,
 
-    /// Batch size for ingestion (only used for PostgreSQL)
+    /// Batch size for ingestion
     #[clap(
         short = 'b',
         long = "batch",
@@ -195,18 +267,45 @@ pub struct IngestCommand {
     )]
     pub batch_size: usize,
 
-    /// Output directory for JSONL files
+    /// Output directory for JSONL files, the schema generated for a Neo4j database, and
+    /// the schema, mapping and mapping report generated for a SQL database
     #[clap(
         short = 'o',
         long = "output",
         default_value = "./",
-        help = "Output directory for JSONL files"
+        help = "Output directory for JSONL and schema files"
     )]
     pub output_dir: Option<String>,
 
     /// Use SSL for PostgreSQL
     #[clap(short = 's', long = "ssl", help = "Use SSL for PostgreSQL")]
     pub use_ssl: bool,
+
+    /// Keep the instance in sync with a PostgreSQL database through this logical
+    /// replication slot, created with a snapshot of the tables if it doesn't exist
+    #[clap(long = "slot", help = "Logical replication slot to sync from")]
+    pub slot: Option<String>,
+
+    /// Output plugin of the replication slot
+    #[clap(
+        long = "plugin",
+        default_value = "pgoutput",
+        value_parser = ["pgoutput", "wal2json"]
+    )]
+    pub plugin: String,
+
+    /// Publication streamed by a pgoutput slot, created for all tables if it doesn't exist
+    #[clap(long = "publication", default_value = "helix")]
+    pub publication: String,
+
+    /// Resume an interrupted Neo4j or file ingestion job after the last batch it loaded
+    #[clap(long = "resume", value_name = "JOB_ID")]
+    pub resume: Option<String>,
+
+    /// Load a SQLite or PostgreSQL database with a mapping written and edited by an
+    /// earlier ingestion, rather than reviewing a new one
+    #[clap(long = "mapping", value_name = "FILE")]
+    pub mapping: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -236,6 +335,350 @@ pub struct DeleteCommand {
     pub instance: String,
 }
 
+#[derive(Debug, Args)]
+#[clap(
+    name = "migrate",
+    about = "Migrate the data of a stopped instance to the schema of a Helix project"
+)]
+pub struct MigrateCommand {
+    #[clap(help = "Instance ID to migrate")]
+    pub instance: String,
+
+    #[clap(short, long, help = "The path to the project")]
+    pub path: Option<String>,
+
+    #[clap(long, help = "Show the migration without applying it")]
+    pub dry_run: bool,
+
+    #[clap(
+        long,
+        help = "Rename a field instead of dropping and adding it, as Label.from=to"
+    )]
+    pub rename: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "replica",
+    about = "Manage read-only replicas of Helix instances"
+)]
+pub struct ReplicaCommand {
+    #[clap(subcommand)]
+    pub command: ReplicaCommandType,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ReplicaCommandType {
+    /// Start a replica following the write-ahead log of a primary
+    Add(ReplicaAddCommand),
+}
+
+#[derive(Debug, Args)]
+#[clap(name = "add", about = "Start a read-only replica of a Helix instance")]
+pub struct ReplicaAddCommand {
+    #[clap(help = "Instance ID of the primary, or its address with --build")]
+    pub primary: String,
+
+    #[clap(
+        long,
+        help = "Instance ID whose queries the replica serves, if the primary is an address"
+    )]
+    pub build: Option<String>,
+
+    #[clap(short, long, help = "Port to run the replica on")]
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "shell",
+    about = "Run HelixQL interactively against a running instance"
+)]
+pub struct ShellCommand {
+    #[clap(help = "Instance ID to run queries on")]
+    pub instance: String,
+
+    #[clap(
+        short,
+        long,
+        help = "The path to the project whose schema the queries use"
+    )]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "push",
+    about = "Push the queries of a project to a running instance without rebuilding it"
+)]
+pub struct PushCommand {
+    #[clap(help = "Instance ID to push the queries to")]
+    pub instance: String,
+
+    #[clap(short, long, help = "The path to the project")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[clap(name = "logs", about = "Print the logs of an instance")]
+pub struct LogsCommand {
+    #[clap(help = "Instance ID to print the logs of")]
+    pub instance: String,
+
+    #[clap(short, long, help = "Keep printing lines as they are written")]
+    pub follow: bool,
+
+    #[clap(
+        long,
+        help = "Only print the runs of the instance going on since a time (RFC 3339) or duration ago (e.g. 10m, 2h)"
+    )]
+    pub since: Option<String>,
+
+    #[clap(long, help = "Only print the errors of the instance")]
+    pub error_only: bool,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "status",
+    about = "Show the health and metrics of running instances"
+)]
+pub struct StatusCommand {
+    #[clap(help = "Instance ID to show the status of, all running instances if unset")]
+    pub instance: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "dev",
+    about = "Rebuild an instance whenever the files of a project change"
+)]
+pub struct DevCommand {
+    #[clap(short, long, help = "The path to the project")]
+    pub path: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "Instance ID to keep up to date, the only instance if unset"
+    )]
+    pub instance: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "seed",
+    about = "Load nodes, edges and vectors from a file into an instance"
+)]
+pub struct SeedCommand {
+    #[clap(short, long, help = "JSON, JSONL or CSV file of the items to load")]
+    pub file: String,
+
+    #[clap(short, long, help = "Instance ID to load the items into")]
+    pub instance: String,
+
+    #[clap(
+        short,
+        long,
+        help = "The path to the project, whose config opens a stopped instance"
+    )]
+    pub path: Option<String>,
+
+    #[clap(
+        short,
+        long = "batch",
+        default_value = "1000",
+        help = "Items loaded per transaction"
+    )]
+    pub batch_size: usize,
+
+    #[clap(short, long, help = "Label of the items of a file without labels")]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "export",
+    about = "Export the nodes and edges of an instance as GraphML, JSONL or CSV"
+)]
+pub struct ExportCommand {
+    #[clap(short, long, help = "Instance ID to export")]
+    pub instance: String,
+
+    #[clap(
+        short,
+        long,
+        help = "File to write, or directory of nodes.csv and edges.csv for CSV"
+    )]
+    pub output: String,
+
+    #[clap(
+        long,
+        help = "graphml, jsonl or csv, by default the extension of the output"
+    )]
+    pub format: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "The path to the project, whose config opens the instance"
+    )]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "import",
+    about = "Load the nodes and edges of a GraphML, JSONL or CSV export into an instance"
+)]
+pub struct ImportCommand {
+    #[clap(
+        short,
+        long,
+        help = "File to load, or directory of nodes.csv and edges.csv for CSV"
+    )]
+    pub file: String,
+
+    #[clap(short, long, help = "Instance ID to load the items into")]
+    pub instance: String,
+
+    #[clap(
+        long,
+        help = "graphml, jsonl or csv, by default the extension of the file"
+    )]
+    pub format: Option<String>,
+
+    #[clap(
+        short,
+        long,
+        help = "The path to the project, whose config opens a stopped instance"
+    )]
+    pub path: Option<String>,
+
+    #[clap(
+        short,
+        long = "batch",
+        default_value = "1000",
+        help = "Items loaded per transaction"
+    )]
+    pub batch_size: usize,
+
+    #[clap(short, long, help = "Label of the items without labels")]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "fsck",
+    about = "Check the references between the stored items of an instance and repair orphans"
+)]
+pub struct FsckCommand {
+    #[clap(help = "Instance ID to check")]
+    pub instance: String,
+
+    #[clap(
+        short,
+        long,
+        help = "The path to the project, whose config opens the instance"
+    )]
+    pub path: Option<String>,
+
+    #[clap(long, help = "Remove orphaned entries of a stopped instance")]
+    pub repair: bool,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "bench",
+    about = "Benchmark a running instance and compare the results with a baseline"
+)]
+pub struct BenchCommand {
+    #[clap(help = "Instance ID to benchmark, whose data the benchmarks add to")]
+    pub instance: String,
+
+    #[clap(long, help = "Node label to add, traverse and return nodes of")]
+    pub node: Option<String>,
+
+    #[clap(long, help = "Edge label from the node label to itself to traverse")]
+    pub edge: Option<String>,
+
+    #[clap(long, help = "Vector label to add and search vectors of")]
+    pub vector: Option<String>,
+
+    #[clap(
+        long,
+        help = "Deployed query searching the vector label, taking the vector as vec and the number of results as k"
+    )]
+    pub search: Option<String>,
+
+    #[clap(long, default_value = "128", help = "Dimensions of the vectors searched")]
+    pub dimensions: usize,
+
+    #[clap(
+        short = 'n',
+        long,
+        default_value = "100",
+        help = "Requests timed per benchmark"
+    )]
+    pub iterations: usize,
+
+    #[clap(long, help = "File to save the results to, to be used as a baseline")]
+    pub save: Option<String>,
+
+    #[clap(long, help = "Results of an earlier run to compare with")]
+    pub baseline: Option<String>,
+
+    #[clap(
+        long,
+        default_value = "10",
+        help = "Percent the median latency can grow by before failing against the baseline"
+    )]
+    pub threshold: f64,
+}
+
+#[derive(Debug, Args)]
+#[clap(
+    name = "loadtest",
+    about = "Replay a weighted mix of queries against a running instance"
+)]
+pub struct LoadtestCommand {
+    #[clap(help = "Instance ID to send the queries to")]
+    pub instance: String,
+
+    #[clap(
+        short,
+        long,
+        help = "JSON file of the queries to send, their weights and parameters"
+    )]
+    pub mix: String,
+
+    #[clap(
+        short,
+        long,
+        default_value = "8",
+        help = "Requests sent at once"
+    )]
+    pub concurrency: usize,
+
+    #[clap(
+        short,
+        long,
+        default_value = "30",
+        help = "Seconds to send requests for"
+    )]
+    pub duration: u64,
+
+    #[clap(short = 'n', long, help = "Requests after which to stop")]
+    pub requests: Option<usize>,
+
+    #[clap(
+        long,
+        default_value = "42",
+        help = "Seed of the generated parameters, for runs to send the same requests"
+    )]
+    pub seed: u64,
+}
+
 #[derive(Debug, Subcommand, Clone, ValueEnum)]
 #[clap(name = "output")]
 pub enum OutputLanguage {
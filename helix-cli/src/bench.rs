@@ -0,0 +1,472 @@
+use crate::{instance_manager::InstanceInfo, types::CliError};
+use helixdb::{
+    helix_engine::vector_core::vector::HVector,
+    helix_gateway::{
+        ingest::ingest::{
+            IngestBatch, IngestEdge, IngestNode, IngestResult, IngestVector, INGEST_PATH,
+        },
+        query::query::QUERY_PATH,
+    },
+    protocol::value::Value,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use reqwest::{blocking::Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    time::{Duration, Instant},
+};
+
+/// Nodes added by each request timed when adding nodes
+const BATCH_SIZE: usize = 100;
+/// Fanouts of the trees the 2-hop traversals are timed on
+const FANOUTS: [usize; 3] = [4, 16, 64];
+/// Vectors added before timing the vector search
+const VECTORS: usize = 1000;
+/// Nearest vectors searched for
+const K: usize = 10;
+/// Nodes returned by each request timed when serializing responses
+const RETURNED: usize = 1000;
+/// Seed of the random vectors, so every run searches the same ones
+const SEED: u64 = 42;
+
+/// Labels of the schema of the instance the workloads use, a workload whose labels
+/// aren't given is skipped
+pub struct BenchOptions {
+    /// Nodes added, traversed and returned
+    pub node: Option<String>,
+    /// Edges from nodes of the node label to nodes of the same label, for the traversals
+    pub edge: Option<String>,
+    pub vector: Option<String>,
+    /// Deployed query searching the vector label, as the query endpoint doesn't run
+    /// vector searches. It takes the vector as `vec` and the number of results as `k`.
+    pub search: Option<String>,
+    pub dimensions: usize,
+    /// Requests timed per workload
+    pub iterations: usize,
+}
+
+/// Latency percentiles of the requests of a workload
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BenchResult {
+    pub name: String,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    /// Items added, reached or returned per second
+    pub throughput: f64,
+    /// Share of the exact nearest vectors the vector search found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recall: Option<f64>,
+}
+
+impl BenchResult {
+    fn new(name: String, mut timings: Vec<Duration>, items: usize) -> Self {
+        timings.sort();
+        let total = timings.iter().sum::<Duration>().as_secs_f64();
+        Self {
+            name,
+            p50_ms: percentile(&timings, 0.50),
+            p95_ms: percentile(&timings, 0.95),
+            p99_ms: percentile(&timings, 0.99),
+            throughput: match total > 0.0 {
+                true => (items * timings.len()) as f64 / total,
+                false => 0.0,
+            },
+            recall: None,
+        }
+    }
+}
+
+/// Latency in milliseconds below which the share `p` of the sorted timings are
+pub fn percentile(sorted: &[Duration], p: f64) -> f64 {
+    let index = ((sorted.len() as f64 * p).ceil() as usize).saturating_sub(1);
+    sorted
+        .get(index)
+        .map_or(0.0, |timing| timing.as_secs_f64() * 1000.0)
+}
+
+/// Results of a run, saved to compare later runs with
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BenchReport {
+    pub results: Vec<BenchResult>,
+}
+
+/// A workload whose median latency grew beyond the threshold
+pub struct Regression {
+    pub name: String,
+    pub baseline_ms: f64,
+    pub current_ms: f64,
+}
+
+impl BenchReport {
+    pub fn load(path: &str) -> Result<Self, CliError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), CliError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{:<24} {:>10} {:>10} {:>10} {:>14}",
+            "workload", "p50 ms", "p95 ms", "p99 ms", "items/s"
+        );
+        for result in &self.results {
+            println!(
+                "{:<24} {:>10.2} {:>10.2} {:>10.2} {:>14.1}",
+                result.name, result.p50_ms, result.p95_ms, result.p99_ms, result.throughput
+            );
+            if let Some(recall) = result.recall {
+                println!("└── recall@{}: {:.3}", K, recall);
+            }
+        }
+    }
+
+    /// Workloads whose median latency is more than `threshold` percent above the one of
+    /// the baseline, those missing from either run aren't compared
+    pub fn regressions(&self, baseline: &BenchReport, threshold: f64) -> Vec<Regression> {
+        let baseline = baseline
+            .results
+            .iter()
+            .map(|result| (result.name.as_str(), result.p50_ms))
+            .collect::<HashMap<_, _>>();
+        self.results
+            .iter()
+            .filter_map(|result| {
+                let baseline_ms = *baseline.get(result.name.as_str())?;
+                (result.p50_ms > baseline_ms * (1.0 + threshold / 100.0)).then(|| Regression {
+                    name: result.name.clone(),
+                    baseline_ms,
+                    current_ms: result.p50_ms,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Times the workloads of the engine's benchmarks against a running instance, through
+/// its ingestion and `/query` endpoints.
+///
+/// The workloads add the items they run on to the instance and leave them there, so
+/// they are best run against an instance deployed to be benchmarked.
+pub struct Bench {
+    instance: InstanceInfo,
+    options: BenchOptions,
+    client: Client,
+}
+
+impl Bench {
+    pub fn new(instance: InstanceInfo, options: BenchOptions) -> Self {
+        Self {
+            instance,
+            options,
+            client: Client::new(),
+        }
+    }
+
+    pub fn run(&self) -> Result<BenchReport, CliError> {
+        let mut results = Vec::new();
+        if let Some(node) = &self.options.node {
+            results.push(self.add_nodes(node)?);
+            if let Some(edge) = &self.options.edge {
+                for fanout in FANOUTS {
+                    results.push(self.two_hop(node, edge, fanout)?);
+                }
+            }
+            results.push(self.serialize(node)?);
+        }
+        match (&self.options.vector, &self.options.search) {
+            (Some(vector), Some(search)) => results.push(self.vector_search(vector, search)?),
+            (None, None) => {}
+            _ => {
+                return Err(CliError::from(
+                    "Searching vectors needs both the label to add them to and the query searching them",
+                ))
+            }
+        }
+        if results.is_empty() {
+            return Err(CliError::from(
+                "No workloads to run, give the labels to use with --node, --edge and --vector",
+            ));
+        }
+        Ok(BenchReport { results })
+    }
+
+    /// Adds batches of nodes, each in a request of its own
+    fn add_nodes(&self, label: &str) -> Result<BenchResult, CliError> {
+        println!("Timing adding nodes...");
+        let mut timings = Vec::with_capacity(self.options.iterations);
+        for i in 0..self.options.iterations {
+            let batch = IngestBatch {
+                nodes: (0..BATCH_SIZE)
+                    .map(|j| node(label, i * BATCH_SIZE + j))
+                    .collect(),
+                ..Default::default()
+            };
+            let start = Instant::now();
+            self.ingest(&batch)?;
+            timings.push(start.elapsed());
+        }
+        Ok(BenchResult::new(
+            "add_node".to_string(),
+            timings,
+            BATCH_SIZE,
+        ))
+    }
+
+    /// Traverses from the root of a tree of `fanout` nodes with `fanout` children each
+    /// to the `fanout²` nodes at its bottom
+    fn two_hop(&self, label: &str, edge: &str, fanout: usize) -> Result<BenchResult, CliError> {
+        println!("Timing 2-hop traversals with a fanout of {}...", fanout);
+        let root = self.add_tree_level(label, edge, &[], 1)?.remove(0);
+        let children = self.add_tree_level(label, edge, std::slice::from_ref(&root), fanout)?;
+        self.add_tree_level(label, edge, &children, fanout)?;
+
+        let query = format!(
+            "QUERY bench(id: ID) =>\n    reached <- N<{}>(id)::Out<{}>::Out<{}>\n    RETURN reached",
+            label, edge, edge
+        );
+        let params = serde_json::json!({ "id": root });
+        let timings = (0..self.options.iterations)
+            .map(|_| Ok(self.query(&query, &params)?.0))
+            .collect::<Result<Vec<_>, CliError>>()?;
+        Ok(BenchResult::new(
+            format!("two_hop/{}", fanout),
+            timings,
+            fanout * fanout,
+        ))
+    }
+
+    /// Adds `fanout` nodes below each of the parents, returning their uuids
+    fn add_tree_level(
+        &self,
+        label: &str,
+        edge: &str,
+        parents: &[String],
+        fanout: usize,
+    ) -> Result<Vec<String>, CliError> {
+        let count = parents.len().max(1) * fanout;
+        let nodes = self.ingest(&IngestBatch {
+            nodes: (0..count).map(|i| node(label, i)).collect(),
+            ..Default::default()
+        })?;
+        let nodes = nodes.nodes.into_iter().flatten().collect::<Vec<_>>();
+        let edges = parents
+            .iter()
+            .flat_map(|parent| std::iter::repeat_n(parent, fanout))
+            .zip(&nodes)
+            .map(|(from, to)| IngestEdge {
+                label: edge.to_string(),
+                from: from.clone(),
+                to: to.clone(),
+                properties: HashMap::new(),
+                unique: false,
+            })
+            .collect::<Vec<_>>();
+        if !edges.is_empty() {
+            self.ingest(&IngestBatch {
+                edges,
+                ..Default::default()
+            })?;
+        }
+        Ok(nodes)
+    }
+
+    /// Returns nodes of the label, the time taken being mostly spent serializing them
+    fn serialize(&self, label: &str) -> Result<BenchResult, CliError> {
+        println!("Timing serializing responses...");
+        let query = format!(
+            "QUERY bench() =>\n    nodes <- N<{}>::RANGE(0, {})\n    RETURN nodes",
+            label, RETURNED
+        );
+        let params = serde_json::json!({});
+        let mut returned = 0;
+        let mut timings = Vec::with_capacity(self.options.iterations);
+        for _ in 0..self.options.iterations {
+            let (timing, body) = self.query(&query, &params)?;
+            returned = body["nodes"].as_array().map_or(0, |nodes| nodes.len());
+            timings.push(timing);
+        }
+        Ok(BenchResult::new(
+            format!("serialize_nodes/{}", returned),
+            timings,
+            returned,
+        ))
+    }
+
+    /// Searches for the nearest vectors of random ones, comparing what was found with
+    /// the nearest vectors found by an exact scan
+    fn vector_search(&self, label: &str, search: &str) -> Result<BenchResult, CliError> {
+        println!("Timing vector search...");
+        let mut rng = StdRng::seed_from_u64(SEED);
+        let mut random_vector = || {
+            (0..self.options.dimensions)
+                .map(|_| rng.random_range(-1.0..1.0))
+                .collect::<Vec<f64>>()
+        };
+        let vectors = (0..VECTORS).map(|_| random_vector()).collect::<Vec<_>>();
+        let mut stored = Vec::with_capacity(VECTORS);
+        for chunk in vectors.chunks(BATCH_SIZE) {
+            let result = self.ingest(&IngestBatch {
+                vectors: chunk
+                    .iter()
+                    .map(|data| IngestVector {
+                        label: label.to_string(),
+                        data: data.clone(),
+                        properties: HashMap::new(),
+                    })
+                    .collect(),
+                ..Default::default()
+            })?;
+            stored.extend(
+                result
+                    .vectors
+                    .into_iter()
+                    .flatten()
+                    .zip(chunk.iter().cloned()),
+            );
+        }
+
+        let mut timings = Vec::with_capacity(self.options.iterations);
+        let mut found = 0;
+        for _ in 0..self.options.iterations {
+            let data = random_vector();
+            let params = serde_json::json!({ "vec": data, "k": K });
+            let (timing, body) = self.search(search, &params)?;
+            timings.push(timing);
+            let exact = nearest(&stored, &data);
+            // the found vectors are the one list the query returns, whatever its name
+            found += body
+                .as_object()
+                .and_then(|body| body.values().find_map(JsonValue::as_array))
+                .map_or(0, |found| {
+                    found
+                        .iter()
+                        .filter(|vector| vector["id"].as_str().is_some_and(|id| exact.contains(id)))
+                        .count()
+                });
+        }
+        let mut result = BenchResult::new(format!("vector_search/{}", VECTORS), timings, 1);
+        result.recall = Some(found as f64 / (self.options.iterations * K).max(1) as f64);
+        Ok(result)
+    }
+
+    fn ingest(&self, batch: &IngestBatch) -> Result<IngestResult, CliError> {
+        let url = format!("http://127.0.0.1:{}{}", self.instance.port, INGEST_PATH);
+        let response = self
+            .client
+            .post(&url)
+            .json(batch)
+            .send()
+            .map_err(|e| CliError::New(format!("Failed to reach instance: {}", e)))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .map_err(|e| CliError::New(format!("Failed to read response: {}", e)))?;
+        if !status.is_success() {
+            return Err(CliError::New(format!("{} {}", status, body)));
+        }
+        let result: IngestResult = serde_json::from_str(&body)?;
+        match result.errors.first() {
+            Some(error) => Err(CliError::New(format!(
+                "Failed to add benchmark items: {}",
+                error.error
+            ))),
+            None => Ok(result),
+        }
+    }
+
+    /// Runs an ad-hoc query, returning how long the instance took to answer and the
+    /// answer
+    fn query(&self, query: &str, params: &JsonValue) -> Result<(Duration, JsonValue), CliError> {
+        let request = serde_json::json!({ "query": query, "params": params });
+        match self.post(QUERY_PATH, &request)? {
+            (_, StatusCode::NOT_FOUND, _) => Err(CliError::from(
+                "The instance doesn't serve /query, set \"query_endpoint\": true in config.hx.json and redeploy it",
+            )),
+            (elapsed, status, body) => Ok((elapsed, parse(status, &body)?)),
+        }
+    }
+
+    /// Runs a deployed query, returning how long the instance took to answer and the
+    /// answer
+    fn search(&self, name: &str, params: &JsonValue) -> Result<(Duration, JsonValue), CliError> {
+        match self.post(&format!("/{}", name), params)? {
+            (_, StatusCode::NOT_FOUND, _) => Err(CliError::New(format!(
+                "No query {} is deployed on the instance",
+                name
+            ))),
+            (elapsed, status, body) => Ok((elapsed, parse(status, &body)?)),
+        }
+    }
+
+    fn post(
+        &self,
+        path: &str,
+        body: &JsonValue,
+    ) -> Result<(Duration, StatusCode, String), CliError> {
+        let url = format!("http://127.0.0.1:{}{}", self.instance.port, path);
+        let start = Instant::now();
+        let response = self
+            .client
+            .post(&url)
+            .json(body)
+            .send()
+            .map_err(|e| CliError::New(format!("Failed to reach instance: {}", e)))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .map_err(|e| CliError::New(format!("Failed to read response: {}", e)))?;
+        Ok((start.elapsed(), status, body))
+    }
+}
+
+fn parse(status: StatusCode, body: &str) -> Result<JsonValue, CliError> {
+    if !status.is_success() {
+        return Err(CliError::New(format!("{} {}", status, body)));
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+/// A node of the label with the properties of a typical user, which the schema doesn't
+/// have to declare as ingested items aren't checked against it
+fn node(label: &str, i: usize) -> IngestNode {
+    IngestNode {
+        label: label.to_string(),
+        properties: HashMap::from([
+            ("name".to_string(), Value::from(format!("user {}", i))),
+            (
+                "email".to_string(),
+                Value::from(format!("user{}@example.com", i)),
+            ),
+            ("age".to_string(), Value::from((i % 90) as i64)),
+        ]),
+        external_id: None,
+    }
+}
+
+/// Uuids of the K stored vectors nearest to the query, found by comparing it with all
+/// of them
+fn nearest(stored: &[(String, Vec<f64>)], query: &[f64]) -> HashSet<String> {
+    let query = HVector::from_slice(0, query.to_vec());
+    let mut distances = stored
+        .iter()
+        .filter_map(|(id, data)| {
+            let distance = HVector::from_slice(0, data.clone())
+                .distance_to(&query)
+                .ok()?;
+            Some((id, distance))
+        })
+        .collect::<Vec<_>>();
+    distances.sort_by(|a, b| a.1.total_cmp(&b.1));
+    distances
+        .into_iter()
+        .take(K)
+        .map(|(id, _)| id.clone())
+        .collect()
+}
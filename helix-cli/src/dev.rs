@@ -0,0 +1,177 @@
+use crate::{
+    instance_manager::{InstanceInfo, InstanceManager},
+    logs::{LogFollower, POLL_INTERVAL},
+    styled_string::StyledString,
+    types::CliError,
+    utils::{check_and_read_files, generate, push_queries},
+};
+use helixdb::helix_engine::graph_core::config::Config;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How long the files of the project must stay unchanged before a change is picked up,
+/// so saving several files at once is handled as one change
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// Watches the files of a project and keeps an instance running its latest queries,
+/// printing the logs of the instance in the meantime.
+///
+/// Changes to queries are pushed to the instance if it serves the push endpoint, and
+/// otherwise redeployed. Changes to the schema or config are always redeployed, as they
+/// are built into the instance.
+pub struct DevServer {
+    path: String,
+    instance: InstanceInfo,
+    instance_manager: InstanceManager,
+    /// Modification times of the watched files when they were last built
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl DevServer {
+    pub fn new(
+        path: String,
+        instance: InstanceInfo,
+        instance_manager: InstanceManager,
+    ) -> Result<Self, CliError> {
+        let modified = watched_files(&path)?;
+        Ok(Self {
+            path,
+            instance,
+            instance_manager,
+            modified,
+        })
+    }
+
+    /// Watches the project until interrupted
+    pub fn run(&mut self) -> Result<(), CliError> {
+        if !self.instance.running {
+            println!("{}", "Starting Helix instance".green().bold());
+            self.instance = self
+                .instance_manager
+                .start_instance(&self.instance.id, None)?;
+        }
+        println!(
+            "{} {} {}",
+            "Watching".green().bold(),
+            self.path.bold(),
+            format!("for changes, running on port {}", self.instance.port)
+        );
+
+        let mut logs = LogFollower::new(&self.instance_manager, &self.instance.id, false);
+        logs.skip_written()?;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            logs.print_new()?;
+
+            if watched_files(&self.path)? == self.modified {
+                continue;
+            }
+            // picked up once the files stop changing
+            let mut modified = watched_files(&self.path)?;
+            loop {
+                thread::sleep(SETTLE_TIME);
+                let settled = watched_files(&self.path)?;
+                if settled == modified {
+                    break;
+                }
+                modified = settled;
+            }
+            let changed = modified
+                .iter()
+                .filter(|(path, time)| self.modified.get(*path) != Some(*time))
+                .map(|(path, _)| path.clone())
+                .chain(
+                    self.modified
+                        .keys()
+                        .filter(|path| !modified.contains_key(*path))
+                        .cloned(),
+                )
+                .collect::<Vec<_>>();
+            self.modified = modified;
+
+            for path in changed.iter() {
+                println!("{} {}", "Changed".yellow().bold(), path.display());
+            }
+            if let Err(e) = self.rebuild(&changed) {
+                println!("{} {}", "Error:".red().bold(), e);
+            }
+        }
+    }
+
+    /// Checks the queries and brings the instance up to date with the changed files
+    fn rebuild(&mut self, changed: &[PathBuf]) -> Result<(), CliError> {
+        let files = check_and_read_files(&self.path)?;
+        generate(&files)?;
+
+        let built_in = changed.iter().any(|path| {
+            path.file_name()
+                .is_some_and(|name| name == "schema.hx" || name == "config.hx.json")
+        });
+        let config = Config::from_config_file(Path::new(&self.path).join("config.hx.json"))
+            .map_err(|e| CliError::New(format!("Failed to load config: {}", e)))?;
+        if !built_in && config.query_endpoint {
+            match push_queries(&self.instance, &files) {
+                Ok(queries) => {
+                    println!(
+                        "{} {} {} {}",
+                        "Pushed".green().bold(),
+                        queries.len(),
+                        "queries".green().bold(),
+                        "(lost when the instance restarts)".yellow()
+                    );
+                    return Ok(());
+                }
+                Err(e) => println!(
+                    "{} {}, redeploying instead",
+                    "Failed to push queries:".yellow().bold(),
+                    e
+                ),
+            }
+        } else if !built_in {
+            println!(
+                "{}",
+                "Set \"query_endpoint\": true in config.hx.json to reload queries without rebuilding"
+                    .yellow()
+            );
+        }
+        self.redeploy()
+    }
+
+    /// Rebuilds and restarts the instance through `helix redeploy`
+    fn redeploy(&mut self) -> Result<(), CliError> {
+        let status = Command::new(std::env::current_exe()?)
+            .arg("redeploy")
+            .arg(&self.instance.id)
+            .arg("--path")
+            .arg(&self.path)
+            .status()?;
+        if !status.success() {
+            return Err(CliError::from("Failed to redeploy the instance"));
+        }
+        // restarting may have moved the instance to another port
+        self.instance = self
+            .instance_manager
+            .get_instance(&self.instance.id)?
+            .ok_or_else(|| CliError::from("The instance was deleted"))?;
+        Ok(())
+    }
+}
+
+/// Modification times of the files of the project the instance is built from
+fn watched_files(path: &str) -> Result<HashMap<PathBuf, SystemTime>, CliError> {
+    fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.ends_with(".hx") || name == "config.hx.json"
+        })
+        .map(|entry| Ok((entry.path(), entry.metadata()?.modified()?)))
+        .collect()
+}
@@ -12,6 +12,17 @@ use std::{
 use dirs;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+#[cfg(unix)]
+use std::time::{Duration, Instant};
+
+/// Longer than the gateway waits for requests to finish when shutting down
+#[cfg(unix)]
+const STOP_TIMEOUT: Duration = Duration::from_secs(35);
+
+/// Start of the line written to the logs of an instance each time it is started,
+/// followed by the time it was started and [`LOG_MARKER_END`]
+pub const LOG_MARKER: &str = "==> started at ";
+pub const LOG_MARKER_END: &str = " <==";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct InstanceInfo {
@@ -23,6 +34,12 @@ pub struct InstanceInfo {
     pub binary_path: PathBuf,
     pub label: String,
     pub running: bool,
+    /// Address of the primary if the instance is a read-only replica
+    #[serde(default)]
+    pub replica_of: Option<String>,
+    /// When the queries the instance runs were last deployed
+    #[serde(default)]
+    pub deployed_at: Option<String>,
 }
 
 pub struct InstanceManager {
@@ -53,6 +70,28 @@ impl InstanceManager {
         source_binary: &Path,
         port: u16,
         endpoints: Vec<String>,
+    ) -> io::Result<InstanceInfo> {
+        self.init_instance(source_binary, port, endpoints, None)
+    }
+
+    /// Starts a read-only replica following the primary at `primary`, serving the queries
+    /// of `source_binary`
+    pub fn init_start_replica(
+        &self,
+        source_binary: &Path,
+        port: u16,
+        endpoints: Vec<String>,
+        primary: String,
+    ) -> io::Result<InstanceInfo> {
+        self.init_instance(source_binary, port, endpoints, Some(primary))
+    }
+
+    fn init_instance(
+        &self,
+        source_binary: &Path,
+        port: u16,
+        endpoints: Vec<String>,
+        replica_of: Option<String>,
     ) -> io::Result<InstanceInfo> {
         let instance_id = Uuid::new_v4().to_string();
         let cached_binary = self.cache_dir.join(&instance_id);
@@ -63,20 +102,7 @@ impl InstanceManager {
         let data_dir = self.cache_dir.join("data").join(&instance_id);
         fs::create_dir_all(&data_dir)?;
 
-        let log_file = self.logs_dir.join(format!("instance_{}.log", instance_id));
-        let log_file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(true)
-            .open(log_file)?;
-        let error_log_file = self
-            .logs_dir
-            .join(format!("instance_{}_error.log", instance_id));
-        let error_log_file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(true)
-            .open(error_log_file)?;
+        let (log_file, error_log_file) = self.open_logs(&instance_id)?;
 
         let mut command = Command::new(&cached_binary);
         command.env("PORT", port.to_string());
@@ -86,6 +112,9 @@ impl InstanceManager {
             .env("HELIX_PORT", port.to_string())
             .stdout(Stdio::from(log_file))
             .stderr(Stdio::from(error_log_file));
+        if let Some(primary) = &replica_of {
+            command.env("HELIX_REPLICA_OF", primary);
+        }
 
         let child = command.spawn()?;
 
@@ -98,6 +127,8 @@ impl InstanceManager {
             binary_path: cached_binary,
             label: "".to_string(),
             running: true,
+            replica_of,
+            deployed_at: Some(chrono::Local::now().to_rfc3339()),
         };
 
         let mut instances = self.list_instances()?;
@@ -125,12 +156,8 @@ impl InstanceManager {
             })?;
         }
 
-        let log_file = self.logs_dir.join(format!("instance_{}.log", instance_id));
-        let log_file = OpenOptions::new()
-            .write(true)
-            .append(true)
-            .create(true)
-            .open(log_file)
+        let (log_file, error_log_file) = self
+            .open_logs(instance_id)
             .map_err(|e| CliError::New(format!("Failed to open log file: {}", e)))?;
 
         let port = match find_available_port(instance.port) {
@@ -147,10 +174,11 @@ impl InstanceManager {
             .env("HELIX_DAEMON", "1")
             .env("HELIX_DATA_DIR", data_dir.to_str().unwrap())
             .env("HELIX_PORT", instance.port.to_string())
-            .stdout(Stdio::from(log_file.try_clone().map_err(|e| {
-                CliError::New(format!("Failed to clone log file: {}", e))
-            })?))
-        .stderr(Stdio::from(log_file));
+            .stdout(Stdio::from(log_file))
+            .stderr(Stdio::from(error_log_file));
+        if let Some(primary) = &instance.replica_of {
+            command.env("HELIX_REPLICA_OF", primary);
+        }
 
         let child = command.spawn().map_err(|e| {
             CliError::New(format!("Failed to spawn process for {}: {}", instance_id, e))
@@ -158,8 +186,10 @@ impl InstanceManager {
 
         instance.pid = child.id();
         instance.running = true;
+        // the endpoints only change when the instance is redeployed
         if let Some(endpoints) = endpoints {
             instance.available_endpoints = endpoints;
+            instance.deployed_at = Some(chrono::Local::now().to_rfc3339());
         }
 
         self.update_instance(&instance)?;
@@ -167,6 +197,36 @@ impl InstanceManager {
         Ok(instance)
     }
 
+    /// Path of the log the instance writes its output to, or its errors to if `errors`
+    pub fn log_path(&self, instance_id: &str, errors: bool) -> PathBuf {
+        match errors {
+            true => self
+                .logs_dir
+                .join(format!("instance_{}_error.log", instance_id)),
+            false => self.logs_dir.join(format!("instance_{}.log", instance_id)),
+        }
+    }
+
+    /// Opens the logs of an instance being started for its output and errors, marking
+    /// the start of the run in both so `helix logs --since` can tell the runs apart
+    fn open_logs(&self, instance_id: &str) -> io::Result<(File, File)> {
+        let marker = format!(
+            "{}{}{}\n",
+            LOG_MARKER,
+            chrono::Local::now().to_rfc3339(),
+            LOG_MARKER_END
+        );
+        let open = |errors: bool| -> io::Result<File> {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.log_path(instance_id, errors))?;
+            file.write_all(marker.as_bytes())?;
+            Ok(file)
+        };
+        Ok((open(false)?, open(true)?))
+    }
+
     pub fn get_instance(&self, instance_id: &str) -> io::Result<Option<InstanceInfo>> {
         let instances = self.list_instances()?;
         Ok(instances.into_iter().find(|i| i.id == instance_id))
@@ -196,9 +256,17 @@ impl InstanceManager {
                 return Ok(false);
             }
             instances[pos].running = false;
+            // saved first so the instance isn't listed as running if waiting is interrupted
+            self.save_instances(&instances)?;
             #[cfg(unix)]
-            unsafe {
-                libc::kill(instances[pos].pid as i32, libc::SIGTERM);
+            {
+                let pid = instances[pos].pid as i32;
+                unsafe {
+                    libc::kill(pid, libc::SIGTERM);
+                }
+                // the instance finishes its requests and flushes its data before exiting,
+                // so it isn't started again on the same data directory while still running
+                wait_for_exit(pid);
             }
             #[cfg(windows)]
             {
@@ -210,7 +278,6 @@ impl InstanceManager {
                     unsafe { TerminateProcess(handle, 0) };
                 }
             }
-            let _ = self.save_instances(&instances)?;
             return Ok(true);
         }
         Ok(false)
@@ -271,3 +338,13 @@ impl InstanceManager {
         }
     }
 }
+
+/// Waits until the process has exited or `STOP_TIMEOUT` has passed
+#[cfg(unix)]
+fn wait_for_exit(pid: i32) {
+    let start = Instant::now();
+    // signal 0 only checks whether the process still exists
+    while unsafe { libc::kill(pid, 0) } == 0 && start.elapsed() < STOP_TIMEOUT {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
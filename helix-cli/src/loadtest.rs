@@ -0,0 +1,337 @@
+use crate::{bench::percentile, instance_manager::InstanceInfo, types::CliError};
+use rand::{
+    distr::{weighted::WeightedIndex, Alphanumeric, Distribution},
+    rngs::StdRng,
+    Rng, SeedableRng,
+};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue};
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Query mix replayed against an instance, read from a JSON file.
+///
+/// Each request picks one of the queries with a chance proportional to its weight and
+/// sends it the parameters, which are either values sent as they are or generators of
+/// a value for every request.
+///
+/// ```json
+/// { "queries": [
+///     { "endpoint": "getUser", "weight": 8, "params": {
+///         "name": { "choice": ["alice", "bob"] }, "limit": 10 } },
+///     { "endpoint": "addUser", "weight": 1, "params": {
+///         "name": { "string": 12 }, "age": { "int": [18, 90] } } },
+///     { "endpoint": "getPost", "weight": 4, "params": { "id": { "uuid": null } } },
+///     { "endpoint": "searchDocs", "weight": 1, "params": {
+///         "vec": { "vector": 128 }, "k": 10 } }
+/// ] }
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct QueryMix {
+    pub queries: Vec<MixedQuery>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MixedQuery {
+    /// Name of the deployed query
+    pub endpoint: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default)]
+    pub params: BTreeMap<String, Param>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Param {
+    Generated(Generator),
+    Constant(JsonValue),
+}
+
+/// Generates the value of a parameter for each request
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Generator {
+    /// Integer between the bounds, both included
+    Int([i64; 2]),
+    /// Float between the bounds
+    Float([f64; 2]),
+    /// One of the values
+    Choice(Vec<JsonValue>),
+    /// Alphanumeric string of the length
+    String(usize),
+    /// Random uuid
+    Uuid(()),
+    /// Embedding of the dimensions with values between -1 and 1
+    Vector(usize),
+}
+
+impl Generator {
+    fn generate(&self, rng: &mut StdRng) -> JsonValue {
+        match self {
+            Generator::Int([min, max]) => JsonValue::from(rng.random_range(*min..=*max)),
+            Generator::Float([min, max]) => JsonValue::from(rng.random_range(*min..*max)),
+            Generator::Choice(values) => values[rng.random_range(0..values.len())].clone(),
+            Generator::String(len) => JsonValue::from(
+                (0..*len)
+                    .map(|_| rng.sample(Alphanumeric) as char)
+                    .collect::<String>(),
+            ),
+            Generator::Uuid(()) => JsonValue::from(uuid::Uuid::from_u128(rng.random()).to_string()),
+            Generator::Vector(dimensions) => JsonValue::from(
+                (0..*dimensions)
+                    .map(|_| rng.random_range(-1.0..1.0))
+                    .collect::<Vec<f64>>(),
+            ),
+        }
+    }
+}
+
+impl QueryMix {
+    pub fn load(path: &str) -> Result<Self, CliError> {
+        let mix: QueryMix = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if mix.queries.is_empty() {
+            return Err(CliError::from("The query mix has no queries"));
+        }
+        for query in &mix.queries {
+            for (name, param) in &query.params {
+                let invalid = match param {
+                    Param::Generated(Generator::Int([min, max])) => min > max,
+                    Param::Generated(Generator::Float([min, max])) => min >= max,
+                    Param::Generated(Generator::Choice(values)) => values.is_empty(),
+                    _ => false,
+                };
+                if invalid {
+                    return Err(CliError::New(format!(
+                        "Parameter {} of {} can't generate any value",
+                        name, query.endpoint
+                    )));
+                }
+            }
+        }
+        Ok(mix)
+    }
+
+    fn params(&self, query: usize, rng: &mut StdRng) -> JsonValue {
+        JsonValue::Object(
+            self.queries[query]
+                .params
+                .iter()
+                .map(|(name, param)| {
+                    let value = match param {
+                        Param::Generated(generator) => generator.generate(rng),
+                        Param::Constant(value) => value.clone(),
+                    };
+                    (name.clone(), value)
+                })
+                .collect::<Map<_, _>>(),
+        )
+    }
+}
+
+pub struct LoadTestOptions {
+    /// Requests sent at once, each worker waiting for the answer to its request
+    /// before sending the next
+    pub concurrency: usize,
+    pub duration: Duration,
+    /// Requests after which to stop even if the duration isn't over
+    pub requests: Option<usize>,
+    pub seed: u64,
+}
+
+/// A request sent to the instance
+struct Sample {
+    query: usize,
+    latency: Duration,
+    /// Status of a failed request, or why it couldn't be sent
+    error: Option<String>,
+}
+
+/// Latencies and errors of the requests to one query of the mix
+pub struct EndpointStats {
+    pub endpoint: String,
+    pub requests: usize,
+    pub errors: usize,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+pub struct LoadTestReport {
+    pub endpoints: Vec<EndpointStats>,
+    pub total: EndpointStats,
+    pub elapsed: Duration,
+    /// Error => requests that failed with it
+    pub errors: BTreeMap<String, usize>,
+}
+
+impl LoadTestReport {
+    fn new(mix: &QueryMix, samples: Vec<Sample>, elapsed: Duration) -> Self {
+        let mut errors = BTreeMap::new();
+        for error in samples.iter().filter_map(|sample| sample.error.as_ref()) {
+            *errors.entry(error.clone()).or_insert(0) += 1;
+        }
+        let endpoints = mix
+            .queries
+            .iter()
+            .enumerate()
+            .map(|(i, query)| {
+                let samples = samples.iter().filter(|sample| sample.query == i);
+                EndpointStats::new(query.endpoint.clone(), samples)
+            })
+            .collect();
+        Self {
+            endpoints,
+            total: EndpointStats::new("total".to_string(), samples.iter()),
+            elapsed,
+            errors,
+        }
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{:<24} {:>9} {:>8} {:>10} {:>10} {:>10} {:>10}",
+            "endpoint", "requests", "errors", "p50 ms", "p90 ms", "p99 ms", "max ms"
+        );
+        for stats in self.endpoints.iter().chain([&self.total]) {
+            println!(
+                "{:<24} {:>9} {:>7.2}% {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+                stats.endpoint,
+                stats.requests,
+                stats.error_rate() * 100.0,
+                stats.p50_ms,
+                stats.p90_ms,
+                stats.p99_ms,
+                stats.max_ms
+            );
+        }
+        println!(
+            "{:.1} requests/s over {:.1}s",
+            self.total.requests as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON),
+            self.elapsed.as_secs_f64()
+        );
+        for (error, count) in &self.errors {
+            println!("└── {} x {}", count, error);
+        }
+    }
+}
+
+impl EndpointStats {
+    fn new<'a>(endpoint: String, samples: impl Iterator<Item = &'a Sample>) -> Self {
+        let mut errors = 0;
+        let mut latencies = samples
+            .inspect(|sample| errors += sample.error.is_some() as usize)
+            .map(|sample| sample.latency)
+            .collect::<Vec<_>>();
+        latencies.sort();
+        Self {
+            endpoint,
+            requests: latencies.len(),
+            errors,
+            p50_ms: percentile(&latencies, 0.50),
+            p90_ms: percentile(&latencies, 0.90),
+            p99_ms: percentile(&latencies, 0.99),
+            max_ms: percentile(&latencies, 1.0),
+        }
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        match self.requests {
+            0 => 0.0,
+            requests => self.errors as f64 / requests as f64,
+        }
+    }
+}
+
+/// Replays a query mix against the deployed queries of a running instance from
+/// concurrent workers, until the duration is over or the requests were sent
+pub struct LoadTest {
+    instance: InstanceInfo,
+    mix: Arc<QueryMix>,
+    options: LoadTestOptions,
+}
+
+impl LoadTest {
+    pub fn new(instance: InstanceInfo, mix: QueryMix, options: LoadTestOptions) -> Self {
+        Self {
+            instance,
+            mix: Arc::new(mix),
+            options,
+        }
+    }
+
+    pub fn run(&self) -> Result<LoadTestReport, CliError> {
+        let weights = WeightedIndex::new(self.mix.queries.iter().map(|query| query.weight))
+            .map_err(|e| CliError::New(format!("Invalid query weights: {}", e)))?;
+        let client = Client::new();
+        let sent = Arc::new(AtomicUsize::new(0));
+        let start = Instant::now();
+        let deadline = start + self.options.duration;
+
+        let workers = (0..self.options.concurrency.max(1))
+            .map(|worker| {
+                let mix = Arc::clone(&self.mix);
+                let weights = weights.clone();
+                let client = client.clone();
+                let sent = Arc::clone(&sent);
+                let requests = self.options.requests;
+                let port = self.instance.port;
+                let mut rng = StdRng::seed_from_u64(self.options.seed.wrapping_add(worker as u64));
+                thread::spawn(move || {
+                    let mut samples = Vec::new();
+                    while Instant::now() < deadline
+                        && requests
+                            .is_none_or(|requests| sent.fetch_add(1, Ordering::Relaxed) < requests)
+                    {
+                        let query = weights.sample(&mut rng);
+                        let params = mix.params(query, &mut rng);
+                        let url =
+                            format!("http://127.0.0.1:{}/{}", port, mix.queries[query].endpoint);
+                        let sent_at = Instant::now();
+                        let error = match client.post(&url).json(&params).send() {
+                            // the body is read for the latency to cover the whole answer
+                            Ok(response) => match (response.status(), response.bytes()) {
+                                (status, Ok(_)) if status.is_success() => None,
+                                (status, Ok(_)) => Some(status.to_string()),
+                                (_, Err(e)) => Some(format!("failed to read response: {}", e)),
+                            },
+                            Err(e) if e.is_connect() => Some("failed to connect".to_string()),
+                            Err(e) if e.is_timeout() => Some("timed out".to_string()),
+                            Err(e) => Some(e.to_string()),
+                        };
+                        samples.push(Sample {
+                            query,
+                            latency: sent_at.elapsed(),
+                            error,
+                        });
+                    }
+                    samples
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut samples = Vec::new();
+        for worker in workers {
+            samples.extend(
+                worker
+                    .join()
+                    .map_err(|_| CliError::from("A load test worker panicked"))?,
+            );
+        }
+        Ok(LoadTestReport::new(&self.mix, samples, start.elapsed()))
+    }
+}
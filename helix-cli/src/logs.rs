@@ -0,0 +1,240 @@
+use crate::{
+    instance_manager::{InstanceManager, LOG_MARKER, LOG_MARKER_END},
+    styled_string::StyledString,
+    types::CliError,
+};
+use chrono::{DateTime, Duration, FixedOffset, Local};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::PathBuf,
+    thread,
+    time::Duration as StdDuration,
+};
+
+/// How often the logs are checked for new lines when following them
+pub const POLL_INTERVAL: StdDuration = StdDuration::from_millis(250);
+
+/// The lines of a run of an instance, from the marker written when it was started up
+/// to the next one
+struct Run {
+    /// Unknown for the lines written before the logs were marked
+    started_at: Option<DateTime<FixedOffset>>,
+    lines: Vec<String>,
+}
+
+/// One of the logs of an instance, remembering how far it has been printed for
+/// following it
+struct Log {
+    path: PathBuf,
+    errors: bool,
+    offset: u64,
+}
+
+impl Log {
+    fn new(path: PathBuf, errors: bool) -> Self {
+        Self {
+            path,
+            errors,
+            offset: 0,
+        }
+    }
+
+    /// Reads the complete lines written since the last read
+    fn read_new(&mut self) -> Result<Vec<String>, CliError> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            // the log is only created once the instance is started
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(CliError::Io(e)),
+        };
+        let len = file.metadata()?.len();
+        // the log was deleted and written anew
+        if len < self.offset {
+            self.offset = 0;
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        // a line still being written is read once it is finished
+        let end = match bytes.iter().rposition(|byte| *byte == b'\n') {
+            Some(end) => end + 1,
+            None => return Ok(Vec::new()),
+        };
+        self.offset += end as u64;
+        Ok(String::from_utf8_lossy(&bytes[..end])
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Prints a line, errors in red. Both logs mark the start of a run, with both of
+    /// them printed it is only printed from the output
+    fn print(&self, line: &str, both: bool) {
+        if self.errors && both && started_at(line).is_some() {
+            return;
+        }
+        match self.errors {
+            true => println!("{}", line.red()),
+            false => println!("{}", line),
+        }
+    }
+}
+
+/// Options of `helix logs`
+pub struct LogsOptions {
+    pub follow: bool,
+    pub since: Option<DateTime<FixedOffset>>,
+    pub error_only: bool,
+}
+
+/// The logs of an instance, printing the lines written to them since they were last
+/// read
+pub struct LogFollower {
+    logs: Vec<Log>,
+}
+
+impl LogFollower {
+    pub fn new(instance_manager: &InstanceManager, instance_id: &str, error_only: bool) -> Self {
+        let mut logs = Vec::new();
+        if !error_only {
+            logs.push(Log::new(
+                instance_manager.log_path(instance_id, false),
+                false,
+            ));
+        }
+        logs.push(Log::new(instance_manager.log_path(instance_id, true), true));
+        Self { logs }
+    }
+
+    /// Skips the lines written so far, only printing those written from now on
+    pub fn skip_written(&mut self) -> Result<(), CliError> {
+        for log in self.logs.iter_mut() {
+            log.read_new()?;
+        }
+        Ok(())
+    }
+
+    /// Prints the lines written since the logs were last read
+    pub fn print_new(&mut self) -> Result<(), CliError> {
+        let both = self.logs.len() > 1;
+        for log in self.logs.iter_mut() {
+            for line in log.read_new()? {
+                log.print(&line, both);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prints the logs of an instance, its errors in red, and keeps printing the lines
+/// written to them if following them.
+///
+/// Output and errors are written to separate logs without the time of each line, so
+/// they are printed run by run, the output of a run followed by its errors. `since`
+/// skips the runs that had ended by then.
+pub fn print_logs(
+    instance_manager: &InstanceManager,
+    instance_id: &str,
+    options: &LogsOptions,
+) -> Result<(), CliError> {
+    let mut follower = LogFollower::new(instance_manager, instance_id, options.error_only);
+    let logs = &mut follower.logs;
+
+    let runs = logs
+        .iter_mut()
+        .map(|log| Ok(split_runs(log.read_new()?)))
+        .collect::<Result<Vec<_>, CliError>>()?;
+    // runs are printed from the last one started by `since`
+    let starts = runs
+        .iter()
+        .flatten()
+        .filter_map(|run| run.started_at)
+        .collect::<Vec<_>>();
+    let first_start = options.since.and_then(|since| {
+        starts
+            .iter()
+            .filter(|start| **start <= since)
+            .max()
+            .copied()
+    });
+    let included = |run: &Run| match (options.since, run.started_at) {
+        (None, _) => true,
+        (Some(_), Some(started_at)) => Some(started_at) >= first_start,
+        // the unmarked lines came before every marked run
+        (Some(_), None) => first_start.is_none(),
+    };
+
+    let mut order = runs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, runs)| runs.iter().map(move |run| (run.started_at, i, run)))
+        .filter(|(_, _, run)| included(run))
+        .collect::<Vec<_>>();
+    order.sort_by_key(|(started_at, i, _)| (*started_at, *i));
+    let both = logs.len() > 1;
+    for (_, i, run) in order {
+        for line in run.lines.iter() {
+            logs[i].print(line, both);
+        }
+    }
+
+    if !options.follow {
+        return Ok(());
+    }
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        follower.print_new()?;
+    }
+}
+
+/// Splits the lines of a log into the runs of the instance, by the markers written
+/// to it when the instance is started
+fn split_runs(lines: Vec<String>) -> Vec<Run> {
+    let mut runs = vec![Run {
+        started_at: None,
+        lines: Vec::new(),
+    }];
+    for line in lines {
+        match started_at(&line) {
+            Some(started_at) => runs.push(Run {
+                started_at: Some(started_at),
+                lines: vec![line],
+            }),
+            None => runs.last_mut().unwrap().lines.push(line),
+        }
+    }
+    runs
+}
+
+/// The time a run was started at if the line is the marker of its start
+fn started_at(line: &str) -> Option<DateTime<FixedOffset>> {
+    line.strip_prefix(LOG_MARKER)
+        .and_then(|rest| rest.strip_suffix(LOG_MARKER_END))
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+}
+
+/// Parses the `--since` of `helix logs`, either an RFC 3339 time or a duration before
+/// now such as `30s`, `10m`, `2h` or `1d`
+pub fn parse_since(since: &str) -> Result<DateTime<FixedOffset>, CliError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(since) {
+        return Ok(time);
+    }
+    let invalid = || {
+        CliError::New(format!(
+            "Invalid --since {}, expected a time such as 2025-01-31T12:00:00Z or a duration such as 10m",
+            since
+        ))
+    };
+    let (unit_at, _) = since.char_indices().last().ok_or_else(invalid)?;
+    let (amount, unit) = since.split_at(unit_at);
+    let amount = amount.parse::<i64>().map_err(|_| invalid())?;
+    let duration = match unit {
+        "s" => Duration::seconds(amount),
+        "m" => Duration::minutes(amount),
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        _ => return Err(invalid()),
+    };
+    Ok((Local::now() - duration).fixed_offset())
+}
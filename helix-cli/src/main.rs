@@ -1,15 +1,36 @@
 use crate::{
-    args::{CommandType, HelixCLI},
+    args::{CommandType, HelixCLI, ReplicaCommandType},
+    bench::{Bench, BenchOptions, BenchReport},
+    dev::DevServer,
     instance_manager::InstanceManager,
-    styled_string::StyledString,
+    loadtest::{LoadTest, LoadTestOptions, QueryMix},
+    logs::{parse_since, print_logs, LogsOptions},
+    seed::{read_export, read_items, SeedTarget, Seeder},
+    shell::Shell,
+    styled_string::{unstyled, StyledString},
+    templates::Template,
+    tester::TestRunner,
     types::*,
     utils::*,
 };
 use args::OutputLanguage;
 use clap::Parser;
 use helixdb::{
-    helix_engine::graph_core::config::Config,
-    ingestion_engine::{postgres_ingestion::PostgresIngestor, sql_ingestion::SqliteIngestor},
+    helix_engine::{
+        graph_core::config::Config,
+        interchange::interchange::{export, GraphFormat},
+        migration::migration::{FieldRename, SchemaSnapshot},
+        storage_core::storage_core::HelixGraphStorage,
+    },
+    helixc::{
+        analyzer::{analyzer::Diagnostic, json},
+        parser::formatter::format_hx,
+    },
+    ingestion_engine::{
+        file_ingestion::FileIngestor, neo4j_ingestion::Neo4jIngestor,
+        postgres_cdc::ReplicationOptions, postgres_ingestion::PostgresIngestor,
+        sql_ingestion::SqliteIngestor,
+    },
 };
 use spinners::{Spinner, Spinners};
 use std::{
@@ -18,11 +39,24 @@ use std::{
     io::Write as iWrite,
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 pub mod args;
+mod bench;
+mod dev;
 mod instance_manager;
+mod loadtest;
+mod logs;
+mod seed;
+mod shell;
 mod styled_string;
+mod templates;
+mod tester;
 mod types;
 mod utils;
 
@@ -154,6 +188,10 @@ fn main() {
 
             let mut sp = Spinner::new(Spinners::Dots9, "Building Helix".into());
 
+            // the schema is built into the instance, which migrates its data to it on startup
+            let schema_path = PathBuf::from(&output).join("src/schema.hx");
+            fs::write(schema_path, read_schema(&path).unwrap()).unwrap();
+
             // copy config.hx.json to ~/.helix/repo/helix-db/helix-container/config.hx.json
             let config_path = PathBuf::from(&output).join("src/config.hx.json");
             fs::copy(PathBuf::from(path + "/config.hx.json"), config_path).unwrap();
@@ -474,6 +512,10 @@ fn main() {
 
             let mut sp = Spinner::new(Spinners::Dots9, "Building Helix".into());
 
+            // the schema is built into the instance, which migrates its data to it on startup
+            let schema_path = PathBuf::from(&output).join("src/schema.hx");
+            fs::write(schema_path, read_schema(&path).unwrap()).unwrap();
+
             // copy config.hx.json to ~/.helix/repo/helix-db/helix-container/config.hx.json
             let config_path = PathBuf::from(&output).join("src/config.hx.json");
             fs::copy(PathBuf::from(path + "/config.hx.json"), config_path).unwrap();
@@ -769,6 +811,34 @@ fn main() {
             }
         }
 
+        CommandType::Check(command) if command.json => {
+            // nothing but the diagnostics goes to stdout for tools to parse it
+            let path = command.path.as_deref().unwrap_or(DB_DIR);
+            let diagnostics = check_and_read_files(path).and_then(|files| diagnose(&files));
+            let failed = match diagnostics {
+                Ok(diagnostics) => {
+                    println!("{}", json::render(&diagnostics, None));
+                    diagnostics.iter().any(Diagnostic::is_error)
+                }
+                // parse errors aren't located in the files
+                Err(e) => {
+                    let error = serde_json::json!([{
+                        "file": null,
+                        "range": null,
+                        "severity": "error",
+                        "message": unstyled(&e.to_string()),
+                        "hint": null,
+                        "fix": null,
+                    }]);
+                    println!("{}", serde_json::to_string_pretty(&error).unwrap());
+                    true
+                }
+            };
+            if failed {
+                std::process::exit(1);
+            }
+        }
+
         CommandType::Check(command) => {
             let path = if let Some(p) = &command.path {
                 p
@@ -817,6 +887,78 @@ fn main() {
             ));
         }
 
+        CommandType::Fmt(command) => {
+            let path = if let Some(p) = &command.path {
+                p
+            } else {
+                println!(
+                    "{} '{}'",
+                    "No path provided, defaulting to".yellow().bold(),
+                    DB_DIR.yellow().bold()
+                );
+                DB_DIR
+            };
+
+            let files = match hx_files(path) {
+                Ok(files) => files,
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    std::process::exit(1);
+                }
+            };
+            if files.is_empty() {
+                println!("{}", "No .hx files found".yellow().bold());
+                return;
+            }
+
+            let mut unformatted = 0;
+            for file in &files {
+                let name = file.to_string_lossy();
+                let formatted = match fs::read_to_string(file)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| {
+                        format_hx(&name, &content)
+                            .map(|formatted| (formatted != content).then_some(formatted))
+                            .map_err(|e| e.to_string())
+                    }) {
+                    Ok(Some(formatted)) => formatted,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        println!("{} {}: {}", "Error:".red().bold(), name, e);
+                        std::process::exit(1);
+                    }
+                };
+                unformatted += 1;
+                if command.check {
+                    println!("{} {}", "Would reformat".yellow().bold(), name);
+                } else if let Err(e) = fs::write(file, formatted) {
+                    println!("{} {}: {}", "Error:".red().bold(), name, e);
+                    std::process::exit(1);
+                } else {
+                    println!("{} {}", "Formatted".green().bold(), name);
+                }
+            }
+
+            // CI needs a failing exit code when a file isn't formatted
+            if command.check && unformatted > 0 {
+                println!(
+                    "{}",
+                    format!("{} of {} files need formatting", unformatted, files.len())
+                        .red()
+                        .bold()
+                );
+                std::process::exit(1);
+            }
+            if unformatted == 0 {
+                println!(
+                    "{}",
+                    format!("{} files already formatted", files.len())
+                        .green()
+                        .bold()
+                );
+            }
+        }
+
         CommandType::Install(_) => {
             match Command::new("cargo").output() {
                 Ok(_) => {}
@@ -906,8 +1048,43 @@ fn main() {
             }
         }
 
-        CommandType::Test(_command) => {
-            unimplemented!("helix test coming soon!");
+        CommandType::Test(command) => {
+            let path = get_cfg_deploy_path(command.path).unwrap();
+            // CI needs a failing exit code for anything short of every test passing
+            let files = match check_and_read_files(&path) {
+                Ok(files) => files,
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    std::process::exit(1);
+                }
+            };
+            let (content, analyzed_source) = match generate(&files) {
+                Ok(code) => code,
+                Err(e) => {
+                    println!("{} {}", "Error compiling queries:".red().bold(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            let report = match TestRunner::new(&path, content.source, analyzed_source)
+                .and_then(|runner| runner.run(command.test.as_deref()))
+            {
+                Ok(report) => report,
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    std::process::exit(1);
+                }
+            };
+            let summary = format!("{} passed, {} failed", report.passed, report.failed);
+            if report.failed > 0 {
+                println!("{}", summary.red().bold());
+                std::process::exit(1);
+            }
+            if report.passed == 0 {
+                println!("{}", "No tests found".yellow().bold());
+                return;
+            }
+            println!("{}", summary.green().bold());
         }
 
         CommandType::Init(command) => {
@@ -917,6 +1094,14 @@ fn main() {
                 None => PathBuf::from(DB_DIR),
             };
             let path_str = path.to_str().unwrap();
+            let template = match command.template.as_deref().map(Template::find) {
+                Some(Ok(template)) => Some(template),
+                Some(Err(e)) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+                None => None,
+            };
 
             let _ = match check_and_read_files(path_str) {
                 Ok(files) if !files.is_empty() => {
@@ -933,6 +1118,29 @@ fn main() {
 
             fs::create_dir_all(&path).unwrap();
 
+            if let Some(template) = template {
+                if let Err(e) = template.write(&path) {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+                println!(
+                    "{} {} {}",
+                    "Helix project initialised from".green().bold(),
+                    template.name.green().bold(),
+                    format!("at {}", path.display())
+                );
+                println!("{}", template.description);
+                for file in template.files() {
+                    println!("└── {}", file);
+                }
+                println!(
+                    "Run {} to test its queries, then {} to serve them",
+                    format!("helix test --path {}", path_str).bold(),
+                    format!("helix deploy --path {}", path_str).bold()
+                );
+                return;
+            }
+
             let schema_path = path.join("schema.hx");
             fs::write(&schema_path, DEFAULT_SCHEMA).unwrap();
 
@@ -1055,153 +1263,1420 @@ fn main() {
             }
         }
 
-        CommandType::Ingest(command) => {
-            match command.db_type.as_str() {
-                "sqlite" => {
-                    let path_str = command.db_url; // Database path for SQLite
-                    let instance = command.instance;
+        CommandType::Migrate(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
 
-                    let path = Path::new(&path_str);
-                    if !path.exists() {
-                        println!(
-                            "{} '{}' {}",
-                            "The file".red().bold(),
-                            path.display().to_string().red().bold(),
-                            "does not exist".red().bold()
-                        );
-                        return;
+            match instance_manager.get_instance(iid) {
+                Ok(Some(instance)) if instance.running => {
+                    println!(
+                        "{} {}",
+                        "Stop the instance before migrating it:".red().bold(),
+                        format!("helix stop {}", iid).bold()
+                    );
+                    return;
+                }
+                Ok(Some(_)) => println!("{}", "Helix instance found!".green().bold()),
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            }
+
+            let path = get_cfg_deploy_path(command.path).unwrap();
+
+            let schema = match read_schema(&path) {
+                Ok(schema) => schema,
+                Err(e) => {
+                    println!("{}", "Failed to read schema file".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            let schema = match SchemaSnapshot::parse(&schema) {
+                Ok(schema) => schema,
+                Err(e) => {
+                    println!("{}", "Failed to parse schema".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            let renames = match command
+                .rename
+                .iter()
+                .map(|rename| rename.parse::<FieldRename>())
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(renames) => renames,
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            let config = match Config::from_config_file(PathBuf::from(&path).join("config.hx.json"))
+            {
+                Ok(config) => config,
+                Err(e) => {
+                    println!("{}", "Failed to load config".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let home_dir = std::env::var("HOME").expect("Failed to get HOME environment variable");
+            let instance_path = format!("{}/.helix/cached_builds/data/{}/user", home_dir, iid);
+            let storage = match HelixGraphStorage::new(&instance_path, config) {
+                Ok(storage) => storage,
+                Err(e) => {
+                    println!("{}", "Failed to open instance data".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let report = match storage.migrate(&schema, &renames, command.dry_run) {
+                Ok(report) => report,
+                Err(e) => {
+                    println!("{}", "Failed to migrate instance".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            match report.from_version {
+                None => println!(
+                    "{} {}",
+                    "Recorded schema as version".green().bold(),
+                    report.version
+                ),
+                Some(from) if from == report.version => {
+                    println!(
+                        "{} {}",
+                        "Schema is up to date at version".green().bold(),
+                        from
+                    )
+                }
+                Some(from) => {
+                    let message = match report.dry_run {
+                        true => "Dry run of migration from version",
+                        false => "Migrated from version",
+                    };
+                    println!(
+                        "{} {} {} {}",
+                        message.green().bold(),
+                        from,
+                        "to".green().bold(),
+                        report.version
+                    );
+                    for step in report.steps.iter() {
+                        println!("└── {}", step);
                     }
+                    println!("└── {} nodes and edges affected", report.affected);
+                }
+            }
+        }
 
-                    let valid_extensions = [".sqlite", ".db", ".sqlite3"];
-                    let is_valid_extension = path
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .map(|ext| valid_extensions.iter().any(|&valid_ext| valid_ext == ext))
-                        .unwrap_or(false);
+        CommandType::Replica(command) => match command.command {
+            ReplicaCommandType::Add(command) => {
+                let instance_manager = InstanceManager::new().unwrap();
 
-                    if !is_valid_extension {
+                // the replica runs the build of the primary, or of the given instance if
+                // the primary isn't a local instance
+                let build_id = command.build.as_deref().unwrap_or(&command.primary);
+                let build = match instance_manager.get_instance(build_id) {
+                    Ok(Some(instance)) => instance,
+                    Ok(None) => {
                         println!(
-                            "{} '{}' {}",
-                            "The file".red().bold(),
-                            path.display().to_string().red().bold(),
-                            "must have a .sqlite, .db, or .sqlite3 file extension"
-                                .red()
-                                .bold(),
+                            "{} {}",
+                            "No Helix instance found with id".red().bold(),
+                            build_id.red().bold()
                         );
+                        if command.build.is_none() {
+                            println!(
+                                "└── Pass --build with the instance whose queries the replica serves if the primary is an address"
+                            );
+                        }
+                        return;
+                    }
+                    Err(e) => {
+                        println!("{} {}", "Error:".red().bold(), e);
                         return;
                     }
+                };
+                let primary = match command.build {
+                    Some(_) => command.primary,
+                    None => format!("127.0.0.1:{}", build.port),
+                };
 
-                    let instance_manager = InstanceManager::new().unwrap();
-                    match instance_manager.list_instances() {
-                        Ok(instances) => {
-                            if instances.is_empty() {
-                                println!(
-                                    "{}",
-                                    "There are no running Helix instances!".red().bold()
-                                );
-                                return;
-                            }
-                            let mut is_valid_instance = false;
-                            for iter_instance in instances {
-                                if iter_instance.id == instance {
-                                    is_valid_instance = true;
-                                    break;
-                                }
-                            }
-                            if !is_valid_instance {
-                                println!("No Helix instance found with id: '{}'!", instance);
-                                return;
-                            } else {
-                                println!("Helix instance found with id: '{}'!", instance);
-                            }
-                        }
-                        Err(e) => {
-                            println!("Error while searching for Helix instances: {}", e);
-                        }
+                let start_port = command.port.unwrap_or(6969);
+                let Some(port) = find_available_port(start_port) else {
+                    println!(
+                        "{} {}",
+                        "No available ports found starting from".red().bold(),
+                        start_port
+                    );
+                    return;
+                };
+
+                let mut sp = Spinner::new(Spinners::Dots9, "Starting Helix replica".into());
+                match instance_manager.init_start_replica(
+                    &build.binary_path,
+                    port,
+                    build.available_endpoints,
+                    primary,
+                ) {
+                    Ok(instance) => {
+                        sp.stop_with_message(format!(
+                            "{}",
+                            "Successfully started Helix replica".green().bold()
+                        ));
+                        print_instnace(&instance);
+                    }
+                    Err(e) => {
+                        sp.stop_with_message(format!(
+                            "{}",
+                            "Failed to start Helix replica".red().bold()
+                        ));
+                        println!("└── {} {}", "Error:".red().bold(), e);
                     }
+                }
+            }
+        },
+
+        CommandType::Shell(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
 
-                    let _ingestor = SqliteIngestor::new(&path_str, None, 5).unwrap();
-                    // TODO: Add ingestion logic
+            let instance = match instance_manager.get_instance(iid) {
+                Ok(Some(instance)) if instance.running => instance,
+                Ok(Some(_)) => {
+                    println!(
+                        "{} {}",
+                        "Start the instance to open a shell on it:".red().bold(),
+                        format!("helix start {}", iid).bold()
+                    );
+                    return;
                 }
-                "pg" | "postgres" => {
-                    let mut sp = Spinner::new(
-                        Spinners::Dots9,
-                        "Connecting to PostgreSQL database...".into(),
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
                     );
-                    // Create output directory if specified
-                    let output_dir = command.output_dir.as_deref().unwrap_or("./");
-                    if !Path::new(output_dir).exists() {
-                        fs::create_dir_all(output_dir).unwrap_or_else(|e| {
-                            sp.stop_with_message(format!(
-                                "{}",
-                                "Failed to create output directory".red().bold()
-                            ));
-                            println!("└── {}", e);
-                            return;
-                        });
-                    }
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
 
-                    // Run the PostgreSQL ingestion
-                    let rt = tokio::runtime::Runtime::new().unwrap();
-                    let _result = rt.block_on(async {
-                        let ingestor = PostgresIngestor::new(&command.db_url, Some(command.instance.clone()), command.batch_size, command.use_ssl).await;
+            let path = get_cfg_deploy_path(command.path).unwrap();
+            let schema = match read_schema(&path) {
+                Ok(schema) => schema,
+                Err(e) => {
+                    println!("{}", "Failed to read schema file".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
 
-                        match ingestor {
-                            Ok(mut ingestor) => {
-                                sp.stop_with_message(format!("{}", "Connected to PostgreSQL database".red().bold()));
+            if let Err(e) = Shell::new(instance, schema).run() {
+                println!("{} {}", "Error:".red().bold(), e);
+            }
+        }
 
-                                let mut sp = Spinner::new(Spinners::Dots9, "Dumping data to JSONL files".into());
-                                match ingestor.dump_to_json(output_dir).await {
-                                    Ok(_) => {
-                                        sp.stop_with_message(format!("{}", "Successfully dumped data to JSONL files".red().bold()));
-
-                                        // Create schema file
-                                        let schema_path = Path::new(output_dir).join("schema.hx");
-                                        println!("Schema file created at: {}", schema_path.display());
-
-                                        println!("PostgreSQL ingestion completed successfully!");
-                                        println!("Press ENTER to open the Helix dashboard in your browser...");
-                                        let mut input = String::new();
-                                        std::io::stdin().read_line(&mut input).unwrap();
-
-                                        #[cfg(target_os = "macos")]
-                                        {
-                                            if let Err(_) = std::process::Command::new("open")
-                                                .arg("https://helix-db.com/dashboard")
-                                                .spawn()
-                                            {
-                                                println!("Failed to open url");
-                                                println!("Please visit https://helix-db.com/dashboard"); // TODO: this is outdated
-                                            }
-                                        }
-
-                                        #[cfg(not(target_os = "macos"))]
-                                        {
-                                            if let Err(e) = open::that("https://helix-db.com/dashboard") {
-                                                println!("Failed to open url");
-                                                println!("Please visit https://helix-db.com/dashboard");
-                                            }
-                                        }
-                                    },
-                                    Err(e) => {
-                                        sp.stop_with_message(format!("{}", "Failed to dump data".red().bold()));
-                                        println!("└── {}", e);
-                                        return;
-                                    }
-                                }
-                            },
+        CommandType::Push(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
+
+            let instance = match instance_manager.get_instance(iid) {
+                Ok(Some(instance)) if instance.running => instance,
+                Ok(Some(_)) => {
+                    println!(
+                        "{} {}",
+                        "Start the instance to push queries to it:".red().bold(),
+                        format!("helix start {}", iid).bold()
+                    );
+                    return;
+                }
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let path = get_cfg_deploy_path(command.path).unwrap();
+            let files = match check_and_read_files(&path) {
+                Ok(files) if !files.is_empty() => files,
+                Ok(_) => {
+                    println!("{}", "No queries found, nothing to push".red().bold());
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            // the instance checks them as well, but its diagnostics aren't rendered
+            let mut sp = Spinner::new(Spinners::Dots9, "Checking Helix queries".into());
+            if let Err(e) = generate(&files) {
+                sp.stop_with_message(format!("{}", "Error checking queries".red().bold()));
+                println!("└── {}", e);
+                return;
+            }
+            sp.stop_with_message(format!(
+                "{} {} {}",
+                "Successfully checked".green().bold(),
+                files.len(),
+                "query files".green().bold()
+            ));
+
+            let mut sp = Spinner::new(Spinners::Dots9, "Pushing Helix queries".into());
+            match push_queries(&instance, &files) {
+                Ok(queries) => {
+                    sp.stop_with_message(format!(
+                        "{} {} {} {}",
+                        "Successfully pushed".green().bold(),
+                        queries.len(),
+                        "queries to".green().bold(),
+                        iid.green().bold()
+                    ));
+                    for query in queries {
+                        println!("└── /{}", query);
+                    }
+                    println!(
+                        "{}",
+                        "Pushed queries are lost when the instance restarts, redeploy to build them in"
+                            .yellow()
+                    );
+                }
+                Err(e) => {
+                    sp.stop_with_message(format!("{}", "Failed to push queries".red().bold()));
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                }
+            }
+        }
+
+        CommandType::Dev(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let instances = match instance_manager.list_instances() {
+                Ok(instances) => instances,
+                Err(e) => {
+                    println!("{} {}", "Failed to list instances:".red().bold(), e);
+                    return;
+                }
+            };
+            let instance = match &command.instance {
+                Some(iid) => match instances.into_iter().find(|i| &i.id == iid) {
+                    Some(instance) => instance,
+                    None => {
+                        println!(
+                            "{} {}",
+                            "No Helix instance found with id".red().bold(),
+                            iid.red().bold()
+                        );
+                        return;
+                    }
+                },
+                None => match <[_; 1]>::try_from(instances) {
+                    Ok([instance]) => instance,
+                    Err(instances) if instances.is_empty() => {
+                        println!(
+                            "{} {}",
+                            "No Helix instance to develop against, deploy the project first:"
+                                .red()
+                                .bold(),
+                            "helix deploy".bold()
+                        );
+                        return;
+                    }
+                    Err(_) => {
+                        println!(
+                            "{} {}",
+                            "There are several Helix instances, pick one with"
+                                .red()
+                                .bold(),
+                            "helix dev --instance <id>".bold()
+                        );
+                        return;
+                    }
+                },
+            };
+
+            let path = get_cfg_deploy_path(command.path).unwrap();
+            let result = DevServer::new(path, instance, instance_manager)
+                .and_then(|mut server| server.run());
+            if let Err(e) = result {
+                println!("{} {}", "Error:".red().bold(), e);
+            }
+        }
+
+        CommandType::Status(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let instances = match instance_manager.list_instances() {
+                Ok(instances) => instances,
+                Err(e) => {
+                    println!("{} {}", "Failed to list instances:".red().bold(), e);
+                    return;
+                }
+            };
+            let instances = match &command.instance {
+                Some(iid) => match instances.into_iter().find(|i| &i.id == iid) {
+                    Some(instance) if instance.running => vec![instance],
+                    Some(_) => {
+                        println!(
+                            "{} {}",
+                            "Instance isn't running, start it with".yellow().bold(),
+                            format!("helix start {}", iid).bold()
+                        );
+                        return;
+                    }
+                    None => {
+                        println!(
+                            "{} {}",
+                            "No Helix instance found with id".red().bold(),
+                            iid.red().bold()
+                        );
+                        return;
+                    }
+                },
+                None => instances.into_iter().filter(|i| i.running).collect(),
+            };
+            if instances.is_empty() {
+                println!("No running Helix instances");
+                return;
+            }
+            for instance in instances {
+                print_status(&instance, fetch_status(&instance));
+                println!();
+            }
+        }
+
+        CommandType::Logs(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
+
+            match instance_manager.get_instance(iid) {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let since = match command.since.as_deref().map(parse_since).transpose() {
+                Ok(since) => since,
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            let options = LogsOptions {
+                follow: command.follow,
+                since,
+                error_only: command.error_only,
+            };
+            if let Err(e) = print_logs(&instance_manager, iid, &options) {
+                println!("{} {}", "Error:".red().bold(), e);
+            }
+        }
+
+        CommandType::Seed(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
+
+            let instance = match instance_manager.get_instance(iid) {
+                Ok(Some(instance)) => instance,
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            if let Some(primary) = &instance.replica_of {
+                println!(
+                    "{} {}",
+                    "Replicas are read-only, seed their primary at".red().bold(),
+                    primary.bold()
+                );
+                return;
+            }
+
+            let items = match read_items(Path::new(&command.file), command.label.as_deref()) {
+                Ok(items) => items,
+                Err(e) => {
+                    println!("{}", "Failed to read seed file".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let target = if instance.running {
+                SeedTarget::Running(instance)
+            } else {
+                let path = get_cfg_deploy_path(command.path).unwrap();
+                let config =
+                    match Config::from_config_file(PathBuf::from(&path).join("config.hx.json")) {
+                        Ok(config) => config,
+                        Err(e) => {
+                            println!("{}", "Failed to load config".red().bold());
+                            println!("└── {} {}", "Error:".red().bold(), e);
+                            return;
+                        }
+                    };
+                let instance_path = instance_manager
+                    .cache_dir
+                    .join("data")
+                    .join(iid)
+                    .join("user");
+                match HelixGraphStorage::new(instance_path.to_str().unwrap(), config) {
+                    Ok(storage) => SeedTarget::Offline(std::sync::Arc::new(storage)),
+                    Err(e) => {
+                        println!("{}", "Failed to open instance data".red().bold());
+                        println!("└── {} {}", "Error:".red().bold(), e);
+                        return;
+                    }
+                }
+            };
+
+            println!("Seeding {} items from {}", items.len(), command.file);
+            let report = match Seeder::new(target, command.batch_size).seed(items) {
+                Ok(report) => report,
+                Err(e) => {
+                    println!("{}", "Failed to seed instance".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            println!(
+                "{} {} {}",
+                "Loaded".green().bold(),
+                report.loaded,
+                "items".green().bold()
+            );
+            if !report.errors.is_empty() {
+                println!(
+                    "{} {} {}",
+                    "Failed to load".red().bold(),
+                    report.errors.len(),
+                    "items".red().bold()
+                );
+                report.print_errors();
+                std::process::exit(1);
+            }
+        }
+
+        CommandType::Export(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
+
+            match instance_manager.get_instance(iid) {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            }
+
+            let output = PathBuf::from(&command.output);
+            let format = match &command.format {
+                Some(format) => format.parse::<GraphFormat>().map_err(|e| e.to_string()),
+                None => GraphFormat::from_path(&output).ok_or_else(|| {
+                    "Pass --format graphml, jsonl or csv for an output without one of these extensions"
+                        .to_string()
+                }),
+            };
+            let format = match format {
+                Ok(format) => format,
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let path = get_cfg_deploy_path(command.path).unwrap();
+            let config = match Config::from_config_file(PathBuf::from(&path).join("config.hx.json"))
+            {
+                Ok(config) => config,
+                Err(e) => {
+                    println!("{}", "Failed to load config".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            // a running instance is exported from a snapshot of its data, taken alongside
+            // the readers and writer of the instance
+            let instance_path = instance_manager
+                .cache_dir
+                .join("data")
+                .join(iid)
+                .join("user");
+            let storage = match HelixGraphStorage::new(instance_path.to_str().unwrap(), config) {
+                Ok(storage) => storage,
+                Err(e) => {
+                    println!("{}", "Failed to open instance data".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let counts = match export(&storage, format, &output) {
+                Ok(counts) => counts,
+                Err(e) => {
+                    println!("{}", "Failed to export instance".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            println!(
+                "{} {} {} {} {} {}",
+                "Exported".green().bold(),
+                counts.nodes,
+                "nodes and".green().bold(),
+                counts.edges,
+                "edges to".green().bold(),
+                command.output
+            );
+        }
+
+        CommandType::Import(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
+
+            let instance = match instance_manager.get_instance(iid) {
+                Ok(Some(instance)) => instance,
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            if let Some(primary) = &instance.replica_of {
+                println!(
+                    "{} {}",
+                    "Replicas are read-only, import into".red().bold(),
+                    primary.bold()
+                );
+                return;
+            }
+
+            let file = Path::new(&command.file);
+            let format = match &command.format {
+                Some(format) => format.parse::<GraphFormat>().map_err(|e| e.to_string()),
+                None => GraphFormat::from_path(file).ok_or_else(|| {
+                    "Pass --format graphml, jsonl or csv for a file without one of these extensions"
+                        .to_string()
+                }),
+            };
+            let format = match format {
+                Ok(format) => format,
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            let items = match read_export(file, format, command.label.as_deref()) {
+                Ok(items) => items,
+                Err(e) => {
+                    println!("{}", "Failed to read export".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let target = if instance.running {
+                SeedTarget::Running(instance)
+            } else {
+                let path = get_cfg_deploy_path(command.path).unwrap();
+                let config =
+                    match Config::from_config_file(PathBuf::from(&path).join("config.hx.json")) {
+                        Ok(config) => config,
+                        Err(e) => {
+                            println!("{}", "Failed to load config".red().bold());
+                            println!("└── {} {}", "Error:".red().bold(), e);
+                            return;
+                        }
+                    };
+                let instance_path = instance_manager
+                    .cache_dir
+                    .join("data")
+                    .join(iid)
+                    .join("user");
+                match HelixGraphStorage::new(instance_path.to_str().unwrap(), config) {
+                    Ok(storage) => SeedTarget::Offline(std::sync::Arc::new(storage)),
+                    Err(e) => {
+                        println!("{}", "Failed to open instance data".red().bold());
+                        println!("└── {} {}", "Error:".red().bold(), e);
+                        return;
+                    }
+                }
+            };
+
+            println!("Importing {} items from {}", items.len(), command.file);
+            let report = match Seeder::new(target, command.batch_size).seed(items) {
+                Ok(report) => report,
+                Err(e) => {
+                    println!("{}", "Failed to import into instance".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            println!(
+                "{} {} {}",
+                "Loaded".green().bold(),
+                report.loaded,
+                "items".green().bold()
+            );
+            if !report.errors.is_empty() {
+                println!(
+                    "{} {} {}",
+                    "Failed to load".red().bold(),
+                    report.errors.len(),
+                    "items".red().bold()
+                );
+                report.print_errors();
+                std::process::exit(1);
+            }
+        }
+
+        CommandType::Fsck(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
+
+            match instance_manager.get_instance(iid) {
+                Ok(Some(instance)) if instance.running && command.repair => {
+                    println!(
+                        "{} {}",
+                        "Stop the instance before repairing it:".red().bold(),
+                        format!("helix stop {}", iid).bold()
+                    );
+                    return;
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            }
+
+            let path = get_cfg_deploy_path(command.path).unwrap();
+            let config = match Config::from_config_file(PathBuf::from(&path).join("config.hx.json"))
+            {
+                Ok(config) => config,
+                Err(e) => {
+                    println!("{}", "Failed to load config".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            // a running instance is checked in a read transaction alongside its own
+            let instance_path = instance_manager
+                .cache_dir
+                .join("data")
+                .join(iid)
+                .join("user");
+            let storage = match HelixGraphStorage::new(instance_path.to_str().unwrap(), config) {
+                Ok(storage) => storage,
+                Err(e) => {
+                    println!("{}", "Failed to open instance data".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let report = match storage.check_integrity(command.repair) {
+                Ok(report) => report,
+                Err(e) => {
+                    println!("{}", "Failed to check instance".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            println!(
+                "{} {} {} {} {}",
+                "Checked".green().bold(),
+                report.nodes,
+                "nodes and".green().bold(),
+                report.edges,
+                "edges".green().bold()
+            );
+            if report.issues.is_empty() {
+                println!("└── {}", "No issues found".green().bold());
+                return;
+            }
+            for issue in report.issues.iter() {
+                println!("└── {}", issue);
+            }
+            let remaining = report.issues.len() - report.repaired;
+            if report.repaired > 0 {
+                println!(
+                    "{} {} {}",
+                    "Repaired".green().bold(),
+                    report.repaired,
+                    "issues".green().bold()
+                );
+            }
+            if remaining > 0 {
+                println!(
+                    "{} {} {}",
+                    "Found".red().bold(),
+                    remaining,
+                    "issues".red().bold()
+                );
+                if !command.repair {
+                    println!("└── Run with --repair on the stopped instance to remove the orphaned entries");
+                }
+                std::process::exit(1);
+            }
+        }
+
+        CommandType::Bench(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
+
+            let instance = match instance_manager.get_instance(iid) {
+                Ok(Some(instance)) => instance,
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            if let Some(primary) = &instance.replica_of {
+                println!(
+                    "{} {}",
+                    "Replicas are read-only, benchmark their primary at".red().bold(),
+                    primary.bold()
+                );
+                return;
+            }
+            if !instance.running {
+                println!("{}", "Instance is not running".red().bold());
+                return;
+            }
+
+            let baseline = match command.baseline.as_deref().map(BenchReport::load) {
+                Some(Ok(baseline)) => Some(baseline),
+                Some(Err(e)) => {
+                    println!("{}", "Failed to read baseline".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+                None => None,
+            };
+
+            let options = BenchOptions {
+                node: command.node,
+                edge: command.edge,
+                vector: command.vector,
+                search: command.search,
+                dimensions: command.dimensions,
+                iterations: command.iterations,
+            };
+            let report = match Bench::new(instance, options).run() {
+                Ok(report) => report,
+                Err(e) => {
+                    println!("{}", "Failed to benchmark instance".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            report.print();
+
+            if let Some(path) = &command.save {
+                if let Err(e) = report.save(path) {
+                    println!("{}", "Failed to save results".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            }
+
+            if let Some(baseline) = baseline {
+                let regressions = report.regressions(&baseline, command.threshold);
+                if regressions.is_empty() {
+                    println!(
+                        "{}",
+                        "No regressions against the baseline".green().bold()
+                    );
+                } else {
+                    println!(
+                        "{} {} {}",
+                        "Found".red().bold(),
+                        regressions.len(),
+                        "regressions".red().bold()
+                    );
+                    for regression in regressions {
+                        println!(
+                            "└── {}: {:.2} ms -> {:.2} ms",
+                            regression.name, regression.baseline_ms, regression.current_ms
+                        );
+                    }
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        CommandType::Loadtest(command) => {
+            let instance_manager = InstanceManager::new().unwrap();
+            let iid = &command.instance;
+
+            let instance = match instance_manager.get_instance(iid) {
+                Ok(Some(instance)) => instance,
+                Ok(None) => {
+                    println!(
+                        "{} {}",
+                        "No Helix instance found with id".red().bold(),
+                        iid.red().bold()
+                    );
+                    return;
+                }
+                Err(e) => {
+                    println!("{} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+            if !instance.running {
+                println!("{}", "Instance is not running".red().bold());
+                return;
+            }
+
+            let mix = match QueryMix::load(&command.mix) {
+                Ok(mix) => mix,
+                Err(e) => {
+                    println!("{}", "Failed to read query mix".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                    return;
+                }
+            };
+
+            let options = LoadTestOptions {
+                concurrency: command.concurrency,
+                duration: Duration::from_secs(command.duration),
+                requests: command.requests,
+                seed: command.seed,
+            };
+            println!(
+                "Sending {} queries from {} workers for up to {}s",
+                mix.queries.len(),
+                options.concurrency,
+                command.duration
+            );
+            match LoadTest::new(instance, mix, options).run() {
+                Ok(report) => report.print(),
+                Err(e) => {
+                    println!("{}", "Failed to load test instance".red().bold());
+                    println!("└── {} {}", "Error:".red().bold(), e);
+                }
+            }
+        }
+
+        CommandType::Ingest(command) => {
+            if command.resume.is_some() && !matches!(command.db_type.as_str(), "neo4j" | "file") {
+                println!(
+                    "{}",
+                    "Only Neo4j and file ingestions can be resumed".red().bold()
+                );
+                return;
+            }
+            if command.mapping.is_some()
+                && (command.slot.is_some() || !matches!(command.db_type.as_str(), "sqlite" | "pg"))
+            {
+                println!(
+                    "{}",
+                    "Only SQLite and PostgreSQL ingestions are loaded with a mapping"
+                        .red()
+                        .bold()
+                );
+                return;
+            }
+            match command.db_type.as_str() {
+                "sqlite" => {
+                    let path_str = command.db_url; // Database path for SQLite
+                    let instance = command.instance;
+
+                    let path = Path::new(&path_str);
+                    if !path.exists() {
+                        println!(
+                            "{} '{}' {}",
+                            "The file".red().bold(),
+                            path.display().to_string().red().bold(),
+                            "does not exist".red().bold()
+                        );
+                        return;
+                    }
+
+                    let valid_extensions = ["sqlite", "db", "sqlite3"];
+                    let is_valid_extension = path
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .map(|ext| valid_extensions.iter().any(|&valid_ext| valid_ext == ext))
+                        .unwrap_or(false);
+
+                    if !is_valid_extension {
+                        println!(
+                            "{} '{}' {}",
+                            "The file".red().bold(),
+                            path.display().to_string().red().bold(),
+                            "must have a .sqlite, .db, or .sqlite3 file extension"
+                                .red()
+                                .bold(),
+                        );
+                        return;
+                    }
+
+                    let instance_manager = InstanceManager::new().unwrap();
+                    let instance = match instance_manager.get_instance(&instance) {
+                        Ok(Some(instance)) if instance.running => instance,
+                        Ok(Some(_)) => {
+                            println!(
+                                "{} {}",
+                                "Start the instance before ingesting into it:".red().bold(),
+                                format!("helix start {}", instance).bold()
+                            );
+                            return;
+                        }
+                        Ok(None) => {
+                            println!("No Helix instance found with id: '{}'!", instance);
+                            return;
+                        }
+                        Err(e) => {
+                            println!("Error while searching for Helix instances: {}", e);
+                            return;
+                        }
+                    };
+
+                    let url = format!("http://127.0.0.1:{}", instance.port);
+                    let mut ingestor =
+                        match SqliteIngestor::new(&path_str, Some(url), command.batch_size) {
+                            Ok(ingestor) => ingestor,
+                            Err(e) => {
+                                println!("{}", "Failed to open SQLite database".red().bold());
+                                println!("└── {}", e);
+                                return;
+                            }
+                        };
+
+                    let output_dir = command.output_dir.as_deref().unwrap_or("./");
+                    let mapping = match ingestor
+                        .tables()
+                        .map_err(|e| CliError::from(e.to_string()))
+                        .and_then(|tables| {
+                            review_sql_mapping(&tables, command.mapping.as_deref(), output_dir)
+                        }) {
+                        Ok(mapping) => mapping,
+                        Err(e) => {
+                            println!("{}", "Failed to map tables".red().bold());
+                            println!("└── {}", e);
+                            return;
+                        }
+                    };
+
+                    let report = match ingestor.load(&mapping) {
+                        Ok(report) => report,
+                        Err(e) => {
+                            println!("{}", "Failed to ingest SQLite database".red().bold());
+                            println!("└── {}", e);
+                            return;
+                        }
+                    };
+                    print_sql_report(&report);
+                }
+                "pg" | "postgres" if command.slot.is_some() => {
+                    let instance_manager = InstanceManager::new().unwrap();
+                    let instance = match instance_manager.get_instance(&command.instance) {
+                        Ok(Some(instance)) if instance.running => instance,
+                        Ok(Some(_)) => {
+                            println!(
+                                "{} {}",
+                                "Start the instance before syncing into it:".red().bold(),
+                                format!("helix start {}", command.instance).bold()
+                            );
+                            return;
+                        }
+                        Ok(None) => {
+                            println!("No Helix instance found with id: '{}'!", command.instance);
+                            return;
+                        }
+                        Err(e) => {
+                            println!("Error while searching for Helix instances: {}", e);
+                            return;
+                        }
+                    };
+
+                    let url = format!("http://127.0.0.1:{}", instance.port);
+                    let options = ReplicationOptions {
+                        slot: command.slot.clone().unwrap(),
+                        plugin: command.plugin.parse().unwrap(),
+                        publication: command.publication.clone(),
+                        poll_interval: Duration::from_secs(1),
+                    };
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(async {
+                        let mut ingestor = match PostgresIngestor::new(
+                            &command.db_url,
+                            Some(url),
+                            command.batch_size,
+                            command.use_ssl,
+                        )
+                        .await
+                        {
+                            Ok(ingestor) => ingestor,
+                            Err(e) => {
+                                println!("{}", "Failed to connect to PostgreSQL".red().bold());
+                                println!("└── {}", e);
+                                return;
+                            }
+                        };
+
+                        let shutdown = Arc::new(AtomicBool::new(false));
+                        let stop = Arc::clone(&shutdown);
+                        tokio::spawn(async move {
+                            if tokio::signal::ctrl_c().await.is_ok() {
+                                stop.store(true, Ordering::Relaxed);
+                            }
+                        });
+                        println!(
+                            "{} {} {}",
+                            "Syncing PostgreSQL into".green().bold(),
+                            command.instance,
+                            "(Ctrl-C to stop)"
+                        );
+                        if let Err(e) = ingestor.replicate(&options, &shutdown).await {
+                            println!("{}", "Failed to sync PostgreSQL".red().bold());
+                            println!("└── {}", e);
+                        }
+                    });
+                }
+                "pg" | "postgres" => {
+                    let instance_manager = InstanceManager::new().unwrap();
+                    let instance = match instance_manager.get_instance(&command.instance) {
+                        Ok(Some(instance)) if instance.running => instance,
+                        Ok(Some(_)) => {
+                            println!(
+                                "{} {}",
+                                "Start the instance before ingesting into it:".red().bold(),
+                                format!("helix start {}", command.instance).bold()
+                            );
+                            return;
+                        }
+                        Ok(None) => {
+                            println!("No Helix instance found with id: '{}'!", command.instance);
+                            return;
+                        }
+                        Err(e) => {
+                            println!("Error while searching for Helix instances: {}", e);
+                            return;
+                        }
+                    };
+
+                    let mut sp = Spinner::new(
+                        Spinners::Dots9,
+                        "Connecting to PostgreSQL database...".into(),
+                    );
+                    let url = format!("http://127.0.0.1:{}", instance.port);
+                    let output_dir = command.output_dir.as_deref().unwrap_or("./");
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(async {
+                        let mut ingestor = match PostgresIngestor::new(
+                            &command.db_url,
+                            Some(url),
+                            command.batch_size,
+                            command.use_ssl,
+                        )
+                        .await
+                        {
+                            Ok(ingestor) => ingestor,
+                            Err(e) => {
+                                sp.stop_with_message(format!(
+                                    "{}",
+                                    "Failed to connect to PostgreSQL".red().bold()
+                                ));
+                                println!("└── {}", e);
+                                return;
+                            }
+                        };
+                        sp.stop_with_message(format!(
+                            "{}",
+                            "Connected to PostgreSQL database".green().bold()
+                        ));
+
+                        let mapping = match ingestor
+                            .tables()
+                            .await
+                            .map_err(|e| CliError::from(e.to_string()))
+                            .and_then(|tables| {
+                                review_sql_mapping(&tables, command.mapping.as_deref(), output_dir)
+                            }) {
+                            Ok(mapping) => mapping,
                             Err(e) => {
-                                sp.stop_with_message(format!("{}", "Failed to connect to PostgreSQL".red().bold()));
+                                println!("{}", "Failed to map tables".red().bold());
                                 println!("└── {}", e);
                                 return;
                             }
+                        };
+
+                        match ingestor.load(&mapping).await {
+                            Ok(report) => print_sql_report(&report),
+                            Err(e) => {
+                                println!("{}", "Failed to ingest PostgreSQL database".red().bold());
+                                println!("└── {}", e);
+                            }
                         }
                     });
                 }
+                "neo4j" => {
+                    let instance_manager = InstanceManager::new().unwrap();
+                    let instance = match instance_manager.get_instance(&command.instance) {
+                        Ok(Some(instance)) if instance.running => instance,
+                        Ok(Some(_)) => {
+                            println!(
+                                "{} {}",
+                                "Start the instance before ingesting into it:".red().bold(),
+                                format!("helix start {}", command.instance).bold()
+                            );
+                            return;
+                        }
+                        Ok(None) => {
+                            println!("No Helix instance found with id: '{}'!", command.instance);
+                            return;
+                        }
+                        Err(e) => {
+                            println!("Error while searching for Helix instances: {}", e);
+                            return;
+                        }
+                    };
+
+                    let url = format!("http://127.0.0.1:{}", instance.port);
+                    let mut ingestor =
+                        match Neo4jIngestor::new(&command.db_url, Some(url), command.batch_size) {
+                            Ok(ingestor) => ingestor,
+                            Err(e) => {
+                                println!("{}", "Failed to read Neo4j export".red().bold());
+                                println!("└── {}", e);
+                                return;
+                            }
+                        };
+
+                    let output_dir = command.output_dir.as_deref().unwrap_or("./");
+                    let schema_path = match ingestor
+                        .extract_schema()
+                        .and_then(|_| ingestor.create_schema(output_dir))
+                    {
+                        Ok(path) => path,
+                        Err(e) => {
+                            println!("{}", "Failed to generate schema".red().bold());
+                            println!("└── {}", e);
+                            return;
+                        }
+                    };
+                    println!("Schema file created at: {}", schema_path.display());
+
+                    match &command.resume {
+                        Some(id) => match ingestor.resume(id) {
+                            Ok(job) => println!(
+                                "Resuming job {} after {} nodes and {} edges loaded in {} batches",
+                                job.id, job.nodes, job.edges, job.batches
+                            ),
+                            Err(e) => {
+                                println!("{}", "Failed to resume ingestion job".red().bold());
+                                println!("└── {}", e);
+                                return;
+                            }
+                        },
+                        None => {
+                            let id = ingestor.start_job();
+                            println!(
+                                "Started ingestion job {}, resume it with {} if it is interrupted",
+                                id,
+                                format!("--resume {}", id).bold()
+                            );
+                        }
+                    }
+
+                    let report = match ingestor.ingest() {
+                        Ok(report) => report,
+                        Err(e) => {
+                            println!("{}", "Failed to ingest Neo4j export".red().bold());
+                            println!("└── {}", e);
+                            return;
+                        }
+                    };
+                    println!(
+                        "{} {} {} {} {}",
+                        "Loaded".green().bold(),
+                        report.nodes,
+                        "nodes and".green().bold(),
+                        report.relationships,
+                        "relationships".green().bold()
+                    );
+                    if !report.errors.is_empty() {
+                        println!(
+                            "{} {} {}",
+                            "Failed to load".red().bold(),
+                            report.errors.len(),
+                            "items".red().bold()
+                        );
+                        for error in report.errors.iter().take(10) {
+                            println!("└── {}", error);
+                        }
+                        if report.errors.len() > 10 {
+                            println!("    ... and {} more", report.errors.len() - 10);
+                        }
+                    }
+                }
+                "file" => {
+                    let instance_manager = InstanceManager::new().unwrap();
+                    let instance = match instance_manager.get_instance(&command.instance) {
+                        Ok(Some(instance)) if instance.running => instance,
+                        Ok(Some(_)) => {
+                            println!(
+                                "{} {}",
+                                "Start the instance before ingesting into it:".red().bold(),
+                                format!("helix start {}", command.instance).bold()
+                            );
+                            return;
+                        }
+                        Ok(None) => {
+                            println!("No Helix instance found with id: '{}'!", command.instance);
+                            return;
+                        }
+                        Err(e) => {
+                            println!("Error while searching for Helix instances: {}", e);
+                            return;
+                        }
+                    };
+
+                    let url = format!("http://127.0.0.1:{}", instance.port);
+                    let mut ingestor =
+                        match FileIngestor::new(&command.db_url, Some(url), command.batch_size) {
+                            Ok(ingestor) => ingestor,
+                            Err(e) => {
+                                println!("{}", "Failed to read mapping".red().bold());
+                                println!("└── {}", e);
+                                return;
+                            }
+                        };
+
+                    match &command.resume {
+                        Some(id) => match ingestor.resume(id) {
+                            Ok(job) => println!(
+                                "Resuming job {} after {} nodes and {} edges loaded in {} batches",
+                                job.id, job.nodes, job.edges, job.batches
+                            ),
+                            Err(e) => {
+                                println!("{}", "Failed to resume ingestion job".red().bold());
+                                println!("└── {}", e);
+                                return;
+                            }
+                        },
+                        None => {
+                            let id = ingestor.start_job();
+                            println!(
+                                "Started ingestion job {}, resume it with {} if it is interrupted",
+                                id,
+                                format!("--resume {}", id).bold()
+                            );
+                        }
+                    }
+
+                    let report = match ingestor.ingest() {
+                        Ok(report) => report,
+                        Err(e) => {
+                            println!("{}", "Failed to ingest files".red().bold());
+                            println!("└── {}", e);
+                            return;
+                        }
+                    };
+                    println!(
+                        "{} {} {} {} {}",
+                        "Loaded".green().bold(),
+                        report.nodes,
+                        "nodes and".green().bold(),
+                        report.edges,
+                        "edges".green().bold()
+                    );
+                    if !report.errors.is_empty() {
+                        println!(
+                            "{} {} {}",
+                            "Failed to load".red().bold(),
+                            report.errors.len(),
+                            "rows".red().bold()
+                        );
+                        for error in report.errors.iter().take(10) {
+                            println!("└── {}", error);
+                        }
+                        if report.errors.len() > 10 {
+                            println!("    ... and {} more", report.errors.len() - 10);
+                        }
+                    }
+                }
                 _ => {
                     println!(
                         "{}",
-                        "Invalid database type. Must be either 'sqlite' or 'pg/postgres'"
+                        "Invalid database type. Must be either 'sqlite', 'pg/postgres', 'neo4j' or 'file'"
                             .red()
                             .bold()
                     );
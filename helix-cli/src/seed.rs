@@ -0,0 +1,527 @@
+use crate::{instance_manager::InstanceInfo, styled_string::StyledString, types::CliError};
+use helixdb::{
+    helix_engine::{
+        interchange::{
+            graphml::read_graphml,
+            interchange::{GraphFormat, CSV_EDGES_FILE, CSV_NODES_FILE},
+        },
+        storage_core::storage_core::HelixGraphStorage,
+    },
+    helix_gateway::ingest::ingest::{
+        ingest, IngestBatch, IngestEdge, IngestNode, IngestResult, IngestVector, ItemKind,
+        INGEST_PATH,
+    },
+    protocol::value::Value,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::blocking::Client;
+use serde_json::{Map, Value as JsonValue};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+/// Errors printed in full after seeding, the others are only counted
+const SHOWN_ERRORS: usize = 10;
+
+/// A node, edge or vector of a seed file.
+///
+/// Items are written like the nodes and edges of test fixtures, vectors have their
+/// embedding as `data`. The `id` of an item names it for the edges referring to it by
+/// `from` and `to`, which may also be the uuids of items already in the graph.
+///
+/// ```json
+/// { "id": "alice", "label": "User", "properties": { "name": "Alice" } }
+/// { "label": "Follows", "from": "alice", "to": "bob" }
+/// { "id": "doc1", "label": "Embedding", "data": [0.1, 0.2], "properties": {} }
+/// ```
+#[derive(Debug)]
+pub struct SeedItem {
+    kind: ItemKind,
+    /// Where the item is in the file, for reporting it
+    position: String,
+    id: Option<String>,
+    label: String,
+    properties: HashMap<String, Value>,
+    from: Option<String>,
+    to: Option<String>,
+    data: Vec<f64>,
+}
+
+/// Where the items are loaded
+pub enum SeedTarget {
+    /// Sent to the ingestion endpoint of a running instance
+    Running(InstanceInfo),
+    /// Written to the data of a stopped instance
+    Offline(Arc<HelixGraphStorage>),
+}
+
+impl SeedTarget {
+    fn load(&self, batch: &IngestBatch) -> Result<IngestResult, CliError> {
+        match self {
+            SeedTarget::Running(instance) => {
+                let url = format!("http://127.0.0.1:{}{}", instance.port, INGEST_PATH);
+                let response = Client::new()
+                    .post(&url)
+                    .json(batch)
+                    .send()
+                    .map_err(|e| CliError::New(format!("Failed to reach instance: {}", e)))?;
+                let status = response.status();
+                let body = response
+                    .text()
+                    .map_err(|e| CliError::New(format!("Failed to read response: {}", e)))?;
+                if !status.is_success() {
+                    return Err(CliError::New(format!("{} {}", status, body)));
+                }
+                Ok(serde_json::from_str(&body)?)
+            }
+            SeedTarget::Offline(storage) => {
+                ingest(storage, batch).map_err(|e| CliError::New(e.to_string()))
+            }
+        }
+    }
+}
+
+/// Counts of a run of `helix seed`
+#[derive(Debug, Default)]
+pub struct SeedReport {
+    pub loaded: usize,
+    /// Position of each failed item => why it failed
+    pub errors: Vec<(String, String)>,
+}
+
+impl SeedReport {
+    /// Prints how many items failed and why, the same error only once
+    pub fn print_errors(&self) {
+        let mut counts = HashMap::new();
+        for (_, error) in self.errors.iter() {
+            *counts.entry(error.as_str()).or_insert(0) += 1;
+        }
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        println!("{}", "Errors:".red().bold());
+        for (error, count) in counts.iter() {
+            println!("└── {} × {}", count, error);
+        }
+        for (position, error) in self.errors.iter().take(SHOWN_ERRORS) {
+            println!("    {} {}", format!("{}:", position).bold(), error);
+        }
+        if self.errors.len() > SHOWN_ERRORS {
+            println!("    ... and {} more", self.errors.len() - SHOWN_ERRORS);
+        }
+    }
+}
+
+/// Loads the items of a seed file in batches, nodes first, then vectors, then the
+/// edges connecting them
+pub struct Seeder {
+    target: SeedTarget,
+    batch_size: usize,
+    /// `id` of a loaded item => its uuid
+    ids: HashMap<String, String>,
+    /// `id`s of the items that failed to load
+    failed: HashSet<String>,
+}
+
+impl Seeder {
+    pub fn new(target: SeedTarget, batch_size: usize) -> Self {
+        Self {
+            target,
+            batch_size: batch_size.max(1),
+            ids: HashMap::new(),
+            failed: HashSet::new(),
+        }
+    }
+
+    pub fn seed(&mut self, items: Vec<SeedItem>) -> Result<SeedReport, CliError> {
+        let progress = ProgressBar::new(items.len() as u64);
+        progress.set_style(
+            ProgressStyle::with_template("{bar:40.green/white} {pos}/{len} {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar()),
+        );
+
+        let mut report = SeedReport::default();
+        for kind in [ItemKind::Node, ItemKind::Vector, ItemKind::Edge] {
+            let items = items
+                .iter()
+                .filter(|item| item.kind == kind)
+                .collect::<Vec<_>>();
+            progress.set_message(match kind {
+                ItemKind::Node => "nodes",
+                ItemKind::Vector => "vectors",
+                ItemKind::Edge => "edges",
+            });
+            for chunk in items.chunks(self.batch_size) {
+                self.load_batch(chunk, &mut report)?;
+                progress.inc(chunk.len() as u64);
+            }
+        }
+        progress.finish_and_clear();
+        Ok(report)
+    }
+
+    /// Loads a batch of items of the same kind
+    fn load_batch(&mut self, items: &[&SeedItem], report: &mut SeedReport) -> Result<(), CliError> {
+        let mut batch = IngestBatch::default();
+        // the items sent, by their position in the batch
+        let mut sent = Vec::with_capacity(items.len());
+        for item in items {
+            match item.kind {
+                ItemKind::Node => batch.nodes.push(IngestNode {
+                    label: item.label.clone(),
+                    properties: item.properties.clone(),
+                    external_id: None,
+                }),
+                ItemKind::Vector => batch.vectors.push(IngestVector {
+                    label: item.label.clone(),
+                    data: item.data.clone(),
+                    properties: item.properties.clone(),
+                }),
+                ItemKind::Edge => {
+                    let (from, to) = match (self.resolve(&item.from), self.resolve(&item.to)) {
+                        (Ok(from), Ok(to)) => (from, to),
+                        (Err(e), _) | (_, Err(e)) => {
+                            self.fail(item);
+                            report.errors.push((item.position.clone(), e));
+                            continue;
+                        }
+                    };
+                    batch.edges.push(IngestEdge {
+                        label: item.label.clone(),
+                        from,
+                        to,
+                        properties: item.properties.clone(),
+                        unique: false,
+                    });
+                }
+            }
+            sent.push(*item);
+        }
+        if sent.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.target.load(&batch)?;
+        let ids = match items[0].kind {
+            ItemKind::Node => result.nodes,
+            ItemKind::Vector => result.vectors,
+            ItemKind::Edge => result.edges,
+        };
+        for (item, id) in sent.iter().zip(ids) {
+            if let (Some(id), Some(name)) = (id, &item.id) {
+                self.ids.insert(name.clone(), id);
+            }
+        }
+        report.loaded += sent.len() - result.errors.len();
+        for error in result.errors {
+            if let Some(item) = sent.get(error.index) {
+                self.fail(item);
+                report.errors.push((item.position.clone(), error.error));
+            }
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, item: &SeedItem) {
+        if let Some(id) = &item.id {
+            self.failed.insert(id.clone());
+        }
+    }
+
+    /// The uuid an edge refers to a node by, either the `id` of a loaded item or a uuid
+    fn resolve(&self, reference: &Option<String>) -> Result<String, String> {
+        let reference = reference
+            .as_ref()
+            .ok_or_else(|| "edges need both `from` and `to`".to_string())?;
+        match self.ids.get(reference) {
+            Some(id) => Ok(id.clone()),
+            None if self.failed.contains(reference) => {
+                Err(format!("`{}` failed to load", reference))
+            }
+            None if uuid::Uuid::parse_str(reference).is_ok() => Ok(reference.clone()),
+            None => Err(format!("unknown item `{}`", reference)),
+        }
+    }
+}
+
+/// Reads the items of a seed file, by its extension:
+///
+/// - `.json`: an object of `nodes`, `edges` and `vectors`, or an array of items
+/// - `.jsonl`: an item per line
+/// - `.csv`: an item per row, with a column per property besides `id`, `label`, `from`,
+///   `to` and `data`. Rows without a `label` column take the given label.
+pub fn read_items(path: &Path, label: Option<&str>) -> Result<Vec<SeedItem>, CliError> {
+    let content = fs::read_to_string(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => match serde_json::from_str::<JsonValue>(&content)? {
+            JsonValue::Object(mut lists) => {
+                let mut items = Vec::new();
+                for (list, kind) in [
+                    ("nodes", ItemKind::Node),
+                    ("edges", ItemKind::Edge),
+                    ("vectors", ItemKind::Vector),
+                ] {
+                    let values = match lists.remove(list) {
+                        Some(JsonValue::Array(values)) => values,
+                        Some(_) => return Err(CliError::New(format!("`{}` must be a list", list))),
+                        None => continue,
+                    };
+                    for (i, value) in values.into_iter().enumerate() {
+                        let position = format!("{}[{}]", list, i);
+                        items.push(json_item(value, Some(kind), position, label)?);
+                    }
+                }
+                Ok(items)
+            }
+            JsonValue::Array(values) => values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| json_item(value, None, format!("item {}", i + 1), label))
+                .collect(),
+            _ => Err(CliError::from(
+                "A JSON seed file must be an object of nodes, edges and vectors or a list of items",
+            )),
+        },
+        Some("jsonl") => jsonl_items(&content, label),
+        Some("csv") => csv_items(&content, label),
+        _ => Err(CliError::from(
+            "Seed files must be .json, .jsonl or .csv files",
+        )),
+    }
+}
+
+/// Reads the items of an export of `helix export` or another graph tool.
+///
+/// A CSV export is a directory of `nodes.csv` and `edges.csv`, edges of GraphML
+/// documents refer to nodes by their GraphML ids.
+pub fn read_export(
+    path: &Path,
+    format: GraphFormat,
+    label: Option<&str>,
+) -> Result<Vec<SeedItem>, CliError> {
+    match format {
+        GraphFormat::Jsonl => jsonl_items(&fs::read_to_string(path)?, label),
+        GraphFormat::Csv if path.is_dir() => {
+            let mut items = Vec::new();
+            for file in [CSV_NODES_FILE, CSV_EDGES_FILE] {
+                let content = fs::read_to_string(path.join(file))?;
+                let file_items = csv_items(&content, label)
+                    .map_err(|e| CliError::New(format!("{}: {}", file, e)))?;
+                items.extend(file_items.into_iter().map(|mut item| {
+                    item.position = format!("{} {}", file, item.position);
+                    item
+                }));
+            }
+            Ok(items)
+        }
+        GraphFormat::Csv => csv_items(&fs::read_to_string(path)?, label),
+        GraphFormat::GraphMl => {
+            let graph = read_graphml(&fs::read_to_string(path)?)
+                .map_err(|e| CliError::New(e.to_string()))?;
+            let item_label = |position: &str, item_label: Option<String>| {
+                item_label
+                    .or_else(|| label.map(str::to_string))
+                    .ok_or_else(|| CliError::New(format!("{}: items need a `label`", position)))
+            };
+            let mut items = Vec::with_capacity(graph.nodes.len() + graph.edges.len());
+            for node in graph.nodes {
+                let position = format!("node {}", node.id);
+                items.push(SeedItem {
+                    kind: ItemKind::Node,
+                    label: item_label(&position, node.label)?,
+                    position,
+                    id: Some(node.id),
+                    properties: node.properties,
+                    from: None,
+                    to: None,
+                    data: Vec::new(),
+                });
+            }
+            for (i, edge) in graph.edges.into_iter().enumerate() {
+                let position = match &edge.id {
+                    Some(id) => format!("edge {}", id),
+                    None => format!("edge {}", i + 1),
+                };
+                items.push(SeedItem {
+                    kind: ItemKind::Edge,
+                    label: item_label(&position, edge.label)?,
+                    position,
+                    id: edge.id,
+                    properties: edge.properties,
+                    from: Some(edge.source),
+                    to: Some(edge.target),
+                    data: Vec::new(),
+                });
+            }
+            Ok(items)
+        }
+    }
+}
+
+fn jsonl_items(content: &str, label: Option<&str>) -> Result<Vec<SeedItem>, CliError> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let position = format!("line {}", i + 1);
+            let value = serde_json::from_str(line)
+                .map_err(|e| CliError::New(format!("{}: {}", position, e)))?;
+            json_item(value, None, position, label)
+        })
+        .collect()
+}
+
+/// Reads an item written as JSON, its kind given by the list it is in or otherwise by
+/// its fields
+fn json_item(
+    value: JsonValue,
+    kind: Option<ItemKind>,
+    position: String,
+    label: Option<&str>,
+) -> Result<SeedItem, CliError> {
+    let invalid = |e: String| CliError::New(format!("{}: {}", position, e));
+    let JsonValue::Object(mut fields) = value else {
+        return Err(invalid("items must be objects".to_string()));
+    };
+    let mut string = |field: &str| match fields.remove(field) {
+        None | Some(JsonValue::Null) => None,
+        Some(JsonValue::String(s)) => Some(s),
+        Some(value) => Some(value.to_string()),
+    };
+    let id = string("id");
+    let from = string("from");
+    let to = string("to");
+    let label = match string("label") {
+        Some(label) => label,
+        None => label
+            .map(str::to_string)
+            .ok_or_else(|| invalid("items need a `label`".to_string()))?,
+    };
+    let data = match fields.remove("data") {
+        Some(data) => Some(
+            serde_json::from_value::<Vec<f64>>(data)
+                .map_err(|_| invalid("`data` must be a list of numbers".to_string()))?,
+        ),
+        None => None,
+    };
+    let properties = match fields.remove("properties") {
+        Some(JsonValue::Object(properties)) => properties,
+        None | Some(JsonValue::Null) => Map::new(),
+        Some(_) => return Err(invalid("`properties` must be an object".to_string())),
+    };
+    let properties = properties
+        .into_iter()
+        .map(|(key, value)| {
+            serde_json::from_value::<Value>(value)
+                .map(|value| (key.clone(), value))
+                .map_err(|e| invalid(format!("property `{}`: {}", key, e)))
+        })
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    let kind = kind.unwrap_or(match (&data, &from, &to) {
+        (Some(_), _, _) => ItemKind::Vector,
+        (None, Some(_), _) | (None, _, Some(_)) => ItemKind::Edge,
+        (None, None, None) => ItemKind::Node,
+    });
+    if kind == ItemKind::Vector && data.is_none() {
+        return Err(invalid("vectors need their `data`".to_string()));
+    }
+    Ok(SeedItem {
+        kind,
+        position,
+        id,
+        label,
+        properties,
+        from,
+        to,
+        data: data.unwrap_or_default(),
+    })
+}
+
+/// Reads the rows of a CSV file as items, typing the values of their properties as
+/// numbers and booleans where they parse as such
+fn csv_items(content: &str, label: Option<&str>) -> Result<Vec<SeedItem>, CliError> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let header = match lines.next() {
+        Some((_, header)) => csv_fields(header),
+        None => return Ok(Vec::new()),
+    };
+    lines
+        .map(|(i, line)| {
+            let position = format!("line {}", i + 1);
+            let values = csv_fields(line);
+            if values.len() != header.len() {
+                return Err(CliError::New(format!(
+                    "{}: expected {} columns, got {}",
+                    position,
+                    header.len(),
+                    values.len()
+                )));
+            }
+            let mut fields = Map::new();
+            let mut properties = Map::new();
+            for (column, value) in header.iter().zip(values) {
+                match column.as_str() {
+                    "id" | "label" | "from" | "to" if !value.is_empty() => {
+                        fields.insert(column.clone(), JsonValue::String(value));
+                    }
+                    "data" if !value.is_empty() => {
+                        let data = serde_json::from_str(&value).map_err(|_| {
+                            CliError::New(format!("{}: `data` must be a JSON list", position))
+                        })?;
+                        fields.insert(column.clone(), data);
+                    }
+                    "id" | "label" | "from" | "to" | "data" => {}
+                    // empty cells leave the property unset
+                    _ if value.is_empty() => {}
+                    _ => {
+                        properties.insert(column.clone(), csv_value(value));
+                    }
+                }
+            }
+            fields.insert("properties".to_string(), JsonValue::Object(properties));
+            json_item(JsonValue::Object(fields), None, position, label)
+        })
+        .collect()
+}
+
+fn csv_value(value: String) -> JsonValue {
+    if let Ok(integer) = value.parse::<i64>() {
+        return JsonValue::from(integer);
+    }
+    if let Ok(float) = value.parse::<f64>() {
+        return JsonValue::from(float);
+    }
+    match value.as_str() {
+        "true" => JsonValue::Bool(true),
+        "false" => JsonValue::Bool(false),
+        _ => JsonValue::String(value),
+    }
+}
+
+/// Splits a line of a CSV file into its fields, unquoting the quoted ones
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
@@ -0,0 +1,289 @@
+use crate::{instance_manager::InstanceInfo, styled_string::StyledString, types::CliError};
+use helixdb::helixc::{
+    analyzer::analyzer::{analyze, Diagnostic},
+    parser::helix_parser::{Content, HelixParser, HxFile, Source},
+};
+use reqwest::blocking::Client;
+use rustyline::{error::ReadlineError, DefaultEditor};
+use serde_json::{Map, Value as JsonValue};
+
+/// Name of the query the statements typed into the shell are wrapped in
+const SHELL_QUERY: &str = "shell";
+/// Longest cell of a table, longer values are cut short
+const MAX_CELL: usize = 48;
+
+const HELP: &str = "Type HelixQL statements ending with a RETURN, or a whole QUERY.
+Statements run as the body of a query without parameters, a QUERY gets the
+parameters set with :params.
+
+  :params {json}  set the parameters of the next queries
+  :params         show the parameters
+  :help           show this help
+  :quit           leave the shell";
+
+/// Interactive shell running ad-hoc HelixQL against a running instance.
+///
+/// Queries are checked against the schema of the project before they are sent, so
+/// mistakes are reported with the same diagnostics as `helix check`. The instance runs
+/// them through its `/query` endpoint, without them being deployed.
+pub struct Shell {
+    instance: InstanceInfo,
+    schema: String,
+    client: Client,
+    params: Map<String, JsonValue>,
+}
+
+impl Shell {
+    pub fn new(instance: InstanceInfo, schema: String) -> Self {
+        Self {
+            instance,
+            schema,
+            client: Client::new(),
+            params: Map::new(),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), CliError> {
+        let mut editor = DefaultEditor::new().map_err(|e| CliError::New(e.to_string()))?;
+        let history = dirs::home_dir().map(|home| home.join(".helix/shell_history"));
+        if let Some(history) = &history {
+            // there is no history before the first session
+            let _ = editor.load_history(history);
+        }
+
+        println!(
+            "{} {} {}",
+            "Connected to".green().bold(),
+            self.instance.id.green().bold(),
+            format!("on port {}, :help for help", self.instance.port)
+        );
+        let mut buffer = String::new();
+        loop {
+            let prompt = match buffer.is_empty() {
+                true => "helix> ",
+                false => "   ... ",
+            };
+            let line = match editor.readline(prompt) {
+                Ok(line) => line,
+                // ctrl-c drops the query being typed
+                Err(ReadlineError::Interrupted) => {
+                    buffer.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(CliError::New(e.to_string())),
+            };
+            let trimmed = line.trim();
+            if buffer.is_empty() {
+                if trimmed.is_empty() {
+                    continue;
+                }
+                if trimmed.starts_with(':') {
+                    let _ = editor.add_history_entry(trimmed);
+                    match self.command(trimmed) {
+                        Some(()) => continue,
+                        None => break,
+                    }
+                }
+            }
+            buffer.push_str(&line);
+            buffer.push('\n');
+            // queries end with their RETURN statement
+            if !line.split_whitespace().any(|word| word == "RETURN") {
+                continue;
+            }
+            let query = std::mem::take(&mut buffer);
+            let _ = editor.add_history_entry(query.trim_end());
+            if let Err(e) = self.execute(&query) {
+                println!("{} {}", "Error:".red().bold(), e);
+            }
+        }
+
+        if let Some(history) = &history {
+            let _ = editor.save_history(history);
+        }
+        Ok(())
+    }
+
+    /// Runs a command of the shell, `None` if it leaves the shell
+    fn command(&mut self, command: &str) -> Option<()> {
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+        match name {
+            ":quit" | ":q" | ":exit" => return None,
+            ":help" | ":h" => println!("{}", HELP),
+            ":params" if argument.trim().is_empty() => {
+                println!("{}", JsonValue::Object(self.params.clone()))
+            }
+            ":params" => match serde_json::from_str(argument) {
+                Ok(JsonValue::Object(params)) => self.params = params,
+                Ok(_) => println!("{}", "Parameters must be a JSON object".red().bold()),
+                Err(e) => println!("{} {}", "Invalid parameters:".red().bold(), e),
+            },
+            _ => println!(
+                "{} {}, :help for help",
+                "Unknown command".red().bold(),
+                name
+            ),
+        }
+        Some(())
+    }
+
+    /// Checks the query against the schema and runs it on the instance
+    fn execute(&self, input: &str) -> Result<(), CliError> {
+        let (query, params) = match input.trim_start().starts_with("QUERY") {
+            true => (input.to_string(), JsonValue::Object(self.params.clone())),
+            false => (
+                format!("QUERY {}() =>\n{}", SHELL_QUERY, input),
+                JsonValue::Object(Map::new()),
+            ),
+        };
+        self.check(&query)?;
+
+        let url = format!("http://127.0.0.1:{}/query", self.instance.port);
+        let response = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "query": query, "params": params }))
+            .send()
+            .map_err(|e| CliError::New(format!("Failed to reach instance: {}", e)))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .map_err(|e| CliError::New(format!("Failed to read response: {}", e)))?;
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(CliError::from(
+                "The instance doesn't serve /query, set \"query_endpoint\": true in config.hx.json and redeploy it",
+            ));
+        }
+        if !status.is_success() {
+            return Err(CliError::New(format!("{} {}", status, body)));
+        }
+        match serde_json::from_str::<JsonValue>(&body) {
+            Ok(JsonValue::Object(results)) => {
+                for (name, value) in results.iter() {
+                    println!("{}", name.bold());
+                    print_value(value);
+                }
+            }
+            Ok(value) => print_value(&value),
+            Err(_) => println!("{}", body),
+        }
+        Ok(())
+    }
+
+    /// Parses and analyzes the query along with the schema, printing the diagnostics of
+    /// the query if there are any
+    fn check(&self, query: &str) -> Result<(), CliError> {
+        let content = Content {
+            content: String::new(),
+            source: Source::default(),
+            files: vec![
+                HxFile {
+                    name: "schema.hx".to_string(),
+                    content: self.schema.clone(),
+                },
+                HxFile {
+                    name: "shell.hx".to_string(),
+                    content: query.to_string(),
+                },
+            ],
+        };
+        let source =
+            HelixParser::parse_source(&content).map_err(|e| CliError::New(e.to_string()))?;
+        let (diagnostics, generated) = analyze(&source);
+        for diag in &diagnostics {
+            let filepath = diag.filepath.clone().unwrap_or("shell.hx".to_string());
+            println!("{}", diag.render(&generated.src, &filepath));
+        }
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(CliError::CompileFailed);
+        }
+        Ok(())
+    }
+}
+
+/// Prints lists of objects, such as nodes, as tables with a column per property and
+/// objects as tables of one row
+fn print_value(value: &JsonValue) {
+    match value {
+        JsonValue::Array(items) if items.iter().all(JsonValue::is_object) && !items.is_empty() => {
+            let mut columns: Vec<&String> = Vec::new();
+            for item in items {
+                for key in item.as_object().unwrap().keys() {
+                    if !columns.contains(&key) {
+                        columns.push(key);
+                    }
+                }
+            }
+            let rows = items
+                .iter()
+                .map(|item| {
+                    columns
+                        .iter()
+                        .map(|column| cell(item.get(column.as_str())))
+                        .collect()
+                })
+                .collect::<Vec<Vec<String>>>();
+            print_table(&columns, &rows);
+            println!("({} rows)", items.len());
+        }
+        JsonValue::Object(fields) if !fields.is_empty() => {
+            let columns = fields.keys().collect::<Vec<_>>();
+            let row = fields.values().map(|value| cell(Some(value))).collect();
+            print_table(&columns, &[row]);
+        }
+        value => println!("{}", cell(Some(value))),
+    }
+}
+
+fn cell(value: Option<&JsonValue>) -> String {
+    let text = match value {
+        None | Some(JsonValue::Null) => String::new(),
+        Some(JsonValue::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    };
+    match text.chars().count() > MAX_CELL {
+        true => format!("{}…", text.chars().take(MAX_CELL - 1).collect::<String>()),
+        false => text,
+    }
+}
+
+fn print_table(columns: &[&String], rows: &[Vec<String>]) {
+    let widths = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([column.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    let line = |left: &str, middle: &str, right: &str| {
+        let segments = widths
+            .iter()
+            .map(|width| "─".repeat(width + 2))
+            .collect::<Vec<_>>();
+        format!("{}{}{}", left, segments.join(middle), right)
+    };
+    let row = |cells: Vec<&str>| {
+        let cells = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!(" {}{} ", cell, " ".repeat(width - cell.chars().count())))
+            .collect::<Vec<_>>();
+        format!("│{}│", cells.join("│"))
+    };
+
+    println!("{}", line("┌", "┬", "┐"));
+    println!(
+        "{}",
+        row(columns.iter().map(|column| column.as_str()).collect()).bold()
+    );
+    println!("{}", line("├", "┼", "┤"));
+    for cells in rows {
+        println!("{}", row(cells.iter().map(String::as_str).collect()));
+    }
+    println!("{}", line("└", "┴", "┘"));
+}
@@ -53,3 +53,19 @@ impl StyledString for str {
         format!("\x1b[4m{}\x1b[0m", self)
     }
 }
+
+/// The text without the escape codes styling it
+pub fn unstyled(text: &str) -> String {
+    let mut unstyled = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // codes run from the escape to the `m` ending them
+            '\x1b' => {
+                chars.by_ref().find(|&c| c == 'm');
+            }
+            c => unstyled.push(c),
+        }
+    }
+    unstyled
+}
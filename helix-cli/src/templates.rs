@@ -0,0 +1,92 @@
+use crate::types::CliError;
+use std::{fs, path::Path};
+
+/// A starting point for a project, written by `helix init --template <name>`
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Path in the project => content
+    files: &'static [(&'static str, &'static str)],
+}
+
+/// Includes the files of a template from `templates/<name>`
+macro_rules! template_files {
+    ($name:literal: $($path:literal),* $(,)?) => {
+        &[$(($path, include_str!(concat!("../templates/", $name, "/", $path)))),*]
+    };
+}
+
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "social-graph",
+        description: "Users following each other and a feed of their posts",
+        files: template_files!("social-graph":
+            "schema.hx",
+            "queries.hx",
+            "config.hx.json",
+            "tests/social.json",
+            "tests/fixtures/users.hx",
+            "client.ts",
+        ),
+    },
+    Template {
+        name: "rag-chatbot",
+        description: "Documents embedded for retrieval and the conversations answered from them",
+        files: template_files!("rag-chatbot":
+            "schema.hx",
+            "queries.hx",
+            "config.hx.json",
+            "tests/rag.json",
+            "tests/fixtures/conversation.json",
+            "client.ts",
+        ),
+    },
+    Template {
+        name: "recommendations",
+        description: "Products recommended from the purchases of similar customers",
+        files: template_files!("recommendations":
+            "schema.hx",
+            "queries.hx",
+            "config.hx.json",
+            "tests/recommendations.json",
+            "tests/fixtures/catalog.json",
+            "client.ts",
+        ),
+    },
+];
+
+impl Template {
+    pub fn find(name: &str) -> Result<&'static Template, CliError> {
+        TEMPLATES
+            .iter()
+            .find(|template| template.name == name)
+            .ok_or_else(|| {
+                CliError::New(format!(
+                    "Unknown template {}, expected one of: {}",
+                    name,
+                    TEMPLATES
+                        .iter()
+                        .map(|template| template.name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+
+    /// Writes the files of the template into the project
+    pub fn write(&self, path: &Path) -> Result<(), CliError> {
+        for (file, content) in self.files {
+            let file = path.join(file);
+            if let Some(dir) = file.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(file, content)?;
+        }
+        Ok(())
+    }
+
+    /// Paths of the files written into the project
+    pub fn files(&self) -> impl Iterator<Item = &'static str> {
+        self.files.iter().map(|(path, _)| *path)
+    }
+}
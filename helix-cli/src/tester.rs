@@ -0,0 +1,600 @@
+use crate::{styled_string::StyledString, types::CliError, utils::read_schema};
+use helixdb::{
+    helix_engine::{
+        graph_core::{config::Config, interpreter::Interpreter},
+        migration::migration::SchemaSnapshot,
+        storage_core::{namespaces::add_schema_labels, storage_core::HelixGraphStorage},
+    },
+    helixc::{
+        analyzer::analyzer::{analyze, Diagnostic},
+        generator::generator_types::{Query, Source as GeneratedSource},
+        parser::helix_parser::{Content, FieldType, HelixParser, HxFile, Source},
+    },
+};
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Directory of a project holding its test files
+pub const TESTS_DIR: &str = "tests";
+
+/// A file of tests, `tests/*.json` in the project.
+///
+/// ```json
+/// {
+///   "fixtures": ["fixtures/users.json", "fixtures/follows.hx"],
+///   "tests": [{
+///     "name": "finds users by name",
+///     "query": "getUser",
+///     "params": { "name": "Alice" },
+///     "expect": {
+///       "user": { "$len": 1 },
+///       "user.0.id": { "$ref": "alice" },
+///       "user.0.age": { "$gte": 18 }
+///     }
+///   }, {
+///     "name": "rejects ids that aren't uuids",
+///     "query": "getUserById",
+///     "params": { "id": "nope" },
+///     "error": "must be of type"
+///   }]
+/// }
+/// ```
+#[derive(Deserialize, Debug)]
+struct TestFile {
+    /// Paths of the fixtures loaded before each test, relative to the test file
+    #[serde(default)]
+    fixtures: Vec<String>,
+    tests: Vec<TestCase>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestCase {
+    name: String,
+    /// Name of the project's query the test runs
+    query: String,
+    #[serde(default)]
+    params: JsonValue,
+    /// Path into the returned values => value or matchers it must have
+    #[serde(default)]
+    expect: Map<String, JsonValue>,
+    /// Part of the error the query must fail with
+    error: Option<String>,
+}
+
+/// Nodes and edges of a JSON fixture. The `id` of a node or edge names it for the
+/// edges, parameters and expectations referring to it with `{"$ref": "<id>"}`.
+///
+/// ```json
+/// {
+///   "nodes": [{ "id": "alice", "label": "User", "properties": { "name": "Alice" } }],
+///   "edges": [{ "label": "Follows", "from": "alice", "to": "bob", "properties": {} }]
+/// }
+/// ```
+#[derive(Deserialize, Debug, Default)]
+struct JsonFixture {
+    #[serde(default)]
+    nodes: Vec<FixtureItem>,
+    #[serde(default)]
+    edges: Vec<FixtureItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FixtureItem {
+    id: Option<String>,
+    label: String,
+    #[serde(default)]
+    properties: Map<String, JsonValue>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+/// Counts of a run of the tests of a project
+#[derive(Debug, Default)]
+pub struct TestReport {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+/// Runs the tests of a project against the interpreter, each on a fresh in-memory
+/// graph with the fixtures of its file loaded.
+///
+/// Fixtures are either JSON files of nodes and edges, or `.hx` files of queries
+/// without parameters run in order. The values a seed query returns can be referred to
+/// by their name, as the id of their first item.
+pub struct TestRunner {
+    path: PathBuf,
+    /// Config of the project, serialized as each test opens a graph of its own with it
+    config: String,
+    schema: String,
+    snapshot: SchemaSnapshot,
+    /// Parsed schema, typing the properties of JSON fixtures
+    source: Source,
+    queries: HashMap<String, Query>,
+    /// Queries inserting the items of JSON fixtures by their text, shared by the
+    /// items setting the same properties
+    fixture_queries: RefCell<HashMap<String, Arc<Query>>>,
+}
+
+impl TestRunner {
+    /// Prepares the tests of the project at the path, whose queries have been analyzed
+    /// into `generated`
+    pub fn new(path: &str, source: Source, generated: GeneratedSource) -> Result<Self, CliError> {
+        let schema = read_schema(path)?;
+        let snapshot = SchemaSnapshot::parse(&schema).map_err(|e| CliError::New(e.to_string()))?;
+        let mut config = Config::from_config_file(Path::new(path).join("config.hx.json"))
+            .map_err(|e| CliError::New(format!("Failed to load config: {}", e)))?;
+        add_schema_labels(&mut config.namespaces, &schema)
+            .map_err(|e| CliError::New(e.to_string()))?;
+        Ok(Self {
+            path: PathBuf::from(path),
+            config: sonic_rs::to_string(&config)?,
+            schema,
+            snapshot,
+            source,
+            queries: generated
+                .queries
+                .into_iter()
+                .map(|query| (query.name.clone(), query))
+                .collect(),
+            fixture_queries: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Runs the tests of every test file of the project, or only those whose name
+    /// contains `filter`, printing the result of each
+    pub fn run(&self, filter: Option<&str>) -> Result<TestReport, CliError> {
+        let dir = self.path.join(TESTS_DIR);
+        let mut files = fs::read_dir(&dir)
+            .map_err(|e| CliError::New(format!("Failed to read {}: {}", dir.display(), e)))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>();
+        files.sort();
+
+        let mut report = TestReport::default();
+        for file in files {
+            let content = fs::read_to_string(&file)?;
+            let test_file: TestFile = serde_json::from_str(&content).map_err(|e| {
+                CliError::New(format!("Invalid test file {}: {}", file.display(), e))
+            })?;
+            let tests = test_file
+                .tests
+                .iter()
+                .filter(|test| filter.is_none_or(|filter| test.name.contains(filter)))
+                .collect::<Vec<_>>();
+            if tests.is_empty() {
+                continue;
+            }
+
+            println!("{}", file.display().to_string().bold());
+            let dir = file.parent().unwrap_or(Path::new("."));
+            for test in tests {
+                match self.run_test(dir, &test_file.fixtures, test) {
+                    Ok(()) => {
+                        report.passed += 1;
+                        println!("  {} {}", "✓".green().bold(), test.name);
+                    }
+                    Err(failures) => {
+                        report.failed += 1;
+                        println!("  {} {}", "✗".red().bold(), test.name);
+                        for failure in failures {
+                            println!("    └── {}", failure);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Runs a test on a fresh graph, failing with everything it got wrong
+    fn run_test(
+        &self,
+        dir: &Path,
+        fixtures: &[String],
+        test: &TestCase,
+    ) -> Result<(), Vec<String>> {
+        let storage = Arc::new(self.storage().map_err(|e| vec![e.to_string()])?);
+        let mut refs = HashMap::new();
+        for fixture in fixtures {
+            self.load_fixture(&storage, &dir.join(fixture), &mut refs)
+                .map_err(|e| vec![format!("fixture {}: {}", fixture, e)])?;
+        }
+
+        let query = self
+            .queries
+            .get(&test.query)
+            .ok_or_else(|| vec![format!("no query named `{}`", test.query)])?;
+        let params = resolve(&test.params, &refs).map_err(|e| vec![e])?;
+        let result =
+            Interpreter::new(storage, query, &params).and_then(|interpreter| interpreter.run());
+        match (result, &test.error) {
+            (Ok(_), Some(error)) => Err(vec![format!("expected an error containing {:?}", error)]),
+            (Err(e), None) => Err(vec![format!("query failed: {}", e)]),
+            (Err(e), Some(error)) => match e.to_string().contains(error.as_str()) {
+                true => Ok(()),
+                false => Err(vec![format!(
+                    "expected an error containing {:?}, got: {}",
+                    error, e
+                )]),
+            },
+            (Ok(values), None) => {
+                let values = serde_json::to_value(&values).map_err(|e| vec![e.to_string()])?;
+                let failures = test
+                    .expect
+                    .iter()
+                    .filter_map(|(path, expected)| {
+                        let expected = match resolve(expected, &refs) {
+                            Ok(expected) => expected,
+                            Err(e) => return Some(e),
+                        };
+                        check(lookup(&values, path), &expected)
+                            .err()
+                            .map(|e| format!("{}: {}", path, e))
+                    })
+                    .collect::<Vec<_>>();
+                match failures.is_empty() {
+                    true => Ok(()),
+                    false => Err(failures),
+                }
+            }
+        }
+    }
+
+    /// Opens an empty in-memory graph configured like the project
+    fn storage(&self) -> Result<HelixGraphStorage, CliError> {
+        let config: Config = sonic_rs::from_str(&self.config)?;
+        let storage =
+            HelixGraphStorage::new_in_memory(config).map_err(|e| CliError::New(e.to_string()))?;
+        // records the schema, whose constraints are enforced from then on
+        storage
+            .migrate(&self.snapshot, &[], false)
+            .map_err(|e| CliError::New(e.to_string()))?;
+        Ok(storage)
+    }
+
+    fn load_fixture(
+        &self,
+        storage: &Arc<HelixGraphStorage>,
+        path: &Path,
+        refs: &mut HashMap<String, JsonValue>,
+    ) -> Result<(), CliError> {
+        let content = fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let fixture: JsonFixture =
+                    serde_json::from_str(&content).map_err(|e| CliError::New(e.to_string()))?;
+                for node in fixture.nodes.iter() {
+                    self.insert(storage, node, false, refs)?;
+                }
+                for edge in fixture.edges.iter() {
+                    self.insert(storage, edge, true, refs)?;
+                }
+                Ok(())
+            }
+            Some("hx") => {
+                let generated = self.analyze(&path.to_string_lossy(), content)?;
+                for query in generated.queries.iter() {
+                    if !query.parameters.is_empty() {
+                        return Err(CliError::New(format!(
+                            "seed query `{}` can't take parameters",
+                            query.name
+                        )));
+                    }
+                    let values = Interpreter::new(Arc::clone(storage), query, &JsonValue::Null)
+                        .and_then(|interpreter| interpreter.run())
+                        .map_err(|e| CliError::New(format!("{}: {}", query.name, e)))?;
+                    for (name, value) in values {
+                        let value = serde_json::to_value(&value)?;
+                        if let Some(id) = first_id(&value) {
+                            refs.insert(name, id);
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(CliError::from("fixtures must be .json or .hx files")),
+        }
+    }
+
+    /// Inserts a node or edge of a JSON fixture by running an `AddN` or `AddE` query
+    /// setting its properties, so they are typed and checked against the schema like
+    /// those of any other query
+    fn insert(
+        &self,
+        storage: &Arc<HelixGraphStorage>,
+        item: &FixtureItem,
+        is_edge: bool,
+        refs: &mut HashMap<String, JsonValue>,
+    ) -> Result<(), CliError> {
+        let fields = match is_edge {
+            true => self
+                .source
+                .edge_schemas
+                .iter()
+                .find(|schema| schema.name.1 == item.label)
+                .map(|schema| schema.properties.clone().unwrap_or_default()),
+            false => self
+                .source
+                .node_schemas
+                .iter()
+                .find(|schema| schema.name.1 == item.label)
+                .map(|schema| schema.fields.clone()),
+        }
+        .ok_or_else(|| CliError::New(format!("no schema named `{}`", item.label)))?;
+
+        let mut params = item
+            .properties
+            .keys()
+            .map(|name| {
+                let field = fields
+                    .iter()
+                    .find(|field| &field.name == name)
+                    .ok_or_else(|| {
+                        CliError::New(format!("`{}` has no property `{}`", item.label, name))
+                    })?;
+                Ok(format!("{}: {}", name, type_name(&field.field_type)))
+            })
+            .collect::<Result<Vec<_>, CliError>>()?;
+        let properties = match item.properties.is_empty() {
+            true => String::new(),
+            false => format!(
+                "({{{}}})",
+                item.properties
+                    .keys()
+                    .map(|name| format!("{}: {}", name, name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut values = item.properties.clone();
+        let add = match is_edge {
+            true => {
+                let endpoint = |end: &Option<String>, side: &str| {
+                    let name = end.as_ref().ok_or_else(|| {
+                        CliError::New(format!("edge `{}` needs `{}`", item.label, side))
+                    })?;
+                    refs.get(name)
+                        .cloned()
+                        .ok_or_else(|| CliError::New(format!("unknown fixture `{}`", name)))
+                };
+                values.insert("from_id".to_string(), endpoint(&item.from, "from")?);
+                values.insert("to_id".to_string(), endpoint(&item.to, "to")?);
+                params.extend(["from_id: ID".to_string(), "to_id: ID".to_string()]);
+                format!(
+                    "AddE<{}>{}::From(from_id)::To(to_id)",
+                    item.label, properties
+                )
+            }
+            false => format!("AddN<{}>{}", item.label, properties),
+        };
+        let text = format!(
+            "QUERY fixture({}) =>\n    item <- {}\n    RETURN item",
+            params.join(", "),
+            add
+        );
+
+        let query = self.fixture_query(text)?;
+        let returned = Interpreter::new(Arc::clone(storage), &query, &JsonValue::Object(values))
+            .and_then(|interpreter| interpreter.run())
+            .map_err(|e| CliError::New(format!("{}: {}", item.label, e)))?;
+        if let (Some(name), Some(value)) = (&item.id, returned.get("item")) {
+            if let Some(id) = first_id(&serde_json::to_value(value)?) {
+                refs.insert(name.clone(), id);
+            }
+        }
+        Ok(())
+    }
+
+    fn fixture_query(&self, text: String) -> Result<Arc<Query>, CliError> {
+        if let Some(query) = self.fixture_queries.borrow().get(&text) {
+            return Ok(Arc::clone(query));
+        }
+        let mut generated = self.analyze("fixture.hx", text.clone())?;
+        let query = Arc::new(generated.queries.remove(0));
+        self.fixture_queries
+            .borrow_mut()
+            .insert(text, Arc::clone(&query));
+        Ok(query)
+    }
+
+    /// Parses and analyzes the queries of a file along with the schema
+    fn analyze(&self, name: &str, content: String) -> Result<GeneratedSource, CliError> {
+        let content = Content {
+            content: String::new(),
+            source: Source::default(),
+            files: vec![
+                HxFile {
+                    name: "schema.hx".to_string(),
+                    content: self.schema.clone(),
+                },
+                HxFile {
+                    name: name.to_string(),
+                    content,
+                },
+            ],
+        };
+        let source =
+            HelixParser::parse_source(&content).map_err(|e| CliError::New(e.to_string()))?;
+        let (diagnostics, generated) = analyze(&source);
+        for diag in &diagnostics {
+            let filepath = diag.filepath.clone().unwrap_or(name.to_string());
+            println!("{}", diag.render(&generated.src, &filepath));
+        }
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(CliError::CompileFailed);
+        }
+        Ok(generated)
+    }
+}
+
+/// The type of a property as written in the parameters of a query
+fn type_name(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Array(element) => format!("[{}]", type_name(element)),
+        field_type => field_type.to_string(),
+    }
+}
+
+/// The id of a returned node or edge, or of the first of a list of them
+fn first_id(value: &JsonValue) -> Option<JsonValue> {
+    match value {
+        JsonValue::Array(items) => items.first().and_then(first_id),
+        JsonValue::Object(fields) => fields.get("id").cloned(),
+        _ => None,
+    }
+}
+
+/// Replaces the `{"$ref": "<name>"}` objects of a value by the ids of the fixtures
+/// they name
+fn resolve(value: &JsonValue, refs: &HashMap<String, JsonValue>) -> Result<JsonValue, String> {
+    match value {
+        JsonValue::Object(fields) => match fields.get("$ref") {
+            Some(JsonValue::String(name)) if fields.len() == 1 => refs
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("unknown fixture `{}`", name)),
+            _ => Ok(JsonValue::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), resolve(value, refs)?)))
+                    .collect::<Result<_, String>>()?,
+            )),
+        },
+        JsonValue::Array(items) => Ok(JsonValue::Array(
+            items
+                .iter()
+                .map(|item| resolve(item, refs))
+                .collect::<Result<_, _>>()?,
+        )),
+        value => Ok(value.clone()),
+    }
+}
+
+/// Follows a path of field names and list indices separated by dots, such as
+/// `users.0.name`, into the returned values
+fn lookup<'a>(values: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.')
+        .try_fold(values, |value, segment| match value {
+            JsonValue::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            JsonValue::Object(fields) => fields.get(segment),
+            _ => None,
+        })
+}
+
+/// Checks a returned value against what a test expects of it, either a value it must
+/// equal or an object of matchers:
+///
+/// - `$eq`, `$ne`: equal, not equal
+/// - `$gt`, `$gte`, `$lt`, `$lte`: compare numbers, or strings such as dates
+/// - `$len`: length of a list, string or object
+/// - `$contains`: item of a list, part of a string or fields of an object
+/// - `$exists`: whether the path leads to a value
+fn check(actual: Option<&JsonValue>, expected: &JsonValue) -> Result<(), String> {
+    let matchers = match expected {
+        JsonValue::Object(fields)
+            if !fields.is_empty() && fields.keys().all(|key| key.starts_with('$')) =>
+        {
+            fields
+        }
+        expected => return check_matcher("$eq", actual, expected),
+    };
+    matchers
+        .iter()
+        .try_for_each(|(matcher, expected)| check_matcher(matcher, actual, expected))
+}
+
+fn check_matcher(
+    matcher: &str,
+    actual: Option<&JsonValue>,
+    expected: &JsonValue,
+) -> Result<(), String> {
+    if matcher == "$exists" {
+        return match actual.is_some() == expected.as_bool().unwrap_or(true) {
+            true => Ok(()),
+            false => Err(format!(
+                "expected $exists {}, got {}",
+                expected,
+                show(actual)
+            )),
+        };
+    }
+    let Some(value) = actual else {
+        return Err(format!("expected {} {}, got nothing", matcher, expected));
+    };
+    let passed = match matcher {
+        "$eq" => equal(value, expected),
+        "$ne" => !equal(value, expected),
+        "$gt" | "$gte" | "$lt" | "$lte" => {
+            let ordering = match (value, expected) {
+                (JsonValue::Number(a), JsonValue::Number(b)) => a
+                    .as_f64()
+                    .unwrap_or(0.0)
+                    .partial_cmp(&b.as_f64().unwrap_or(0.0)),
+                (JsonValue::String(a), JsonValue::String(b)) => Some(a.cmp(b)),
+                _ => None,
+            };
+            ordering.is_some_and(|ordering| match matcher {
+                "$gt" => ordering.is_gt(),
+                "$gte" => ordering.is_ge(),
+                "$lt" => ordering.is_lt(),
+                _ => ordering.is_le(),
+            })
+        }
+        "$len" => {
+            let len = match value {
+                JsonValue::Array(items) => Some(items.len()),
+                JsonValue::String(s) => Some(s.chars().count()),
+                JsonValue::Object(fields) => Some(fields.len()),
+                _ => None,
+            };
+            len.is_some_and(|len| Some(len as u64) == expected.as_u64())
+        }
+        "$contains" => match (value, expected) {
+            (JsonValue::Array(items), expected) => {
+                items.iter().any(|item| check(Some(item), expected).is_ok())
+            }
+            (JsonValue::String(s), JsonValue::String(part)) => s.contains(part.as_str()),
+            (JsonValue::Object(_), JsonValue::Object(fields)) => fields
+                .iter()
+                .all(|(key, expected)| check(value.get(key), expected).is_ok()),
+            _ => false,
+        },
+        matcher => return Err(format!("unknown matcher {}", matcher)),
+    };
+    match passed {
+        true => Ok(()),
+        false => Err(format!("expected {} {}, got {}", matcher, expected, value)),
+    }
+}
+
+/// Whether two values are equal, numbers being compared by value so `1` equals `1.0`
+fn equal(a: &JsonValue, b: &JsonValue) -> bool {
+    match (a, b) {
+        (JsonValue::Number(a), JsonValue::Number(b)) => a.as_f64() == b.as_f64(),
+        (JsonValue::Array(a), JsonValue::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| equal(a, b))
+        }
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| equal(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn show(value: Option<&JsonValue>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "nothing".to_string(),
+    }
+}
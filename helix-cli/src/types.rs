@@ -46,6 +46,12 @@ impl From<sonic_rs::Error> for CliError {
     }
 }
 
+impl From<serde_json::Error> for CliError {
+    fn from(e: serde_json::Error) -> Self {
+        CliError::New(e.to_string())
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Version {
     major: u32,
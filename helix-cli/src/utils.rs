@@ -3,15 +3,20 @@ use crate::{
     styled_string::StyledString,
     types::*,
 };
+use helixdb::helix_gateway::status::status::{Status, STATUS_PATH};
+use helixdb::ingestion_engine::sql_mapping::{SqlMapping, SqlReport, SqlTable, MAPPING_FILE};
 use helixdb::helixc::{
-    analyzer::analyzer::analyze,
+    analyzer::analyzer::{analyze, Diagnostic},
     generator::{generator_types::Source as GeneratedSource, tsdisplay::ToTypeScript},
-    parser::helix_parser::{Content, HelixParser, HxFile, Source},
+    parser::{
+        formatter::format_schemas,
+        helix_parser::{Content, HelixParser, HxFile, Source},
+    },
 };
 use std::{
     error::Error,
-    fs::{self, DirEntry, File},
-    io::{ErrorKind, Write},
+    fs::{self, DirEntry},
+    io::ErrorKind,
     net::{SocketAddr, TcpListener},
     path::{Path, PathBuf},
     process::{Stdio, Command},
@@ -200,6 +205,21 @@ pub fn check_and_read_files(path: &str) -> Result<Vec<DirEntry>, CliError> {
     Ok(files)
 }
 
+/// The `.hx` files to format, the file itself or the files of a project directory
+pub fn hx_files(path: &str) -> Result<Vec<PathBuf>, CliError> {
+    let path = Path::new(path);
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|file| file.extension().is_some_and(|ext| ext == "hx"))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
 pub fn to_snake_case(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
     let mut prev_is_uppercase = false;
@@ -229,15 +249,40 @@ fn generate_content(files: &Vec<DirEntry>) -> Result<Content, CliError> {
         })
         .collect();
 
-    let content = Content {
+    let mut content = Content {
         content: String::new(),
         files,
         source: Source::default(),
     };
+    content
+        .resolve_imports()
+        .map_err(|e| CliError::from(format!("{}", e)))?;
 
     Ok(content)
 }
 
+/// The schema of the project at the path, with the schemas of the files `schema.hx`
+/// imports written into it, for the schema to be read on its own
+pub fn read_schema(path: &str) -> Result<String, CliError> {
+    let file = Path::new(path).join("schema.hx");
+    let mut content = Content {
+        content: String::new(),
+        files: vec![HxFile {
+            name: file.to_string_lossy().into_owned(),
+            content: fs::read_to_string(&file)?,
+        }],
+        source: Source::default(),
+    };
+    content
+        .resolve_imports()
+        .map_err(|e| CliError::from(format!("{}", e)))?;
+    if content.files.len() == 1 {
+        return Ok(content.files.remove(0).content);
+    }
+    let source = parse_content(&content)?;
+    format_schemas(&source).map_err(|e| CliError::from(format!("{}", e)))
+}
+
 fn parse_content(content: &Content) -> Result<Source, CliError> {
     let source = match HelixParser::parse_source(&content) {
         Ok(source) => source,
@@ -251,17 +296,24 @@ fn parse_content(content: &Content) -> Result<Source, CliError> {
 
 fn analyze_source(source: Source) -> Result<GeneratedSource, CliError> {
     let (diagnostics, source) = analyze(&source);
-    if !diagnostics.is_empty() {
-        for diag in diagnostics {
-            let filepath = diag.filepath.clone().unwrap_or("queries.hx".to_string());
-            println!("{}", diag.render(&source.src, &filepath));
-        }
+    for diag in &diagnostics {
+        let filepath = diag.filepath.clone().unwrap_or("queries.hx".to_string());
+        println!("{}", diag.render(&source.src, &filepath));
+    }
+    if diagnostics.iter().any(Diagnostic::is_error) {
         return Err(CliError::CompileFailed);
     }
 
     Ok(source)
 }
 
+/// The diagnostics of the schema and queries of a project, without rendering them
+pub fn diagnose(files: &Vec<DirEntry>) -> Result<Vec<Diagnostic>, CliError> {
+    let content = generate_content(files)?;
+    let source = parse_content(&content)?;
+    Ok(analyze(&source).0)
+}
+
 pub fn generate(files: &Vec<DirEntry>) -> Result<(Content, GeneratedSource), CliError> {
     let mut content = generate_content(&files)?;
     content.source = parse_content(&content)?;
@@ -269,6 +321,154 @@ pub fn generate(files: &Vec<DirEntry>) -> Result<(Content, GeneratedSource), Cli
     Ok((content, analyzed_source))
 }
 
+/// Uploads the query files of a project to the `/push` endpoint of a running instance,
+/// which serves them in place of its deployed queries until it restarts. Returns the
+/// names of the queries the instance now serves.
+pub fn push_queries(instance: &InstanceInfo, files: &[DirEntry]) -> Result<Vec<String>, CliError> {
+    // the schema is built into the instance, pushing it is refused
+    let files = files
+        .iter()
+        .filter(|file| file.file_name() != "schema.hx")
+        .map(|file| {
+            let content = fs::read_to_string(file.path())?;
+            Ok(serde_json::json!({
+                "name": file.file_name().to_string_lossy(),
+                "content": content,
+            }))
+        })
+        .collect::<Result<Vec<_>, CliError>>()?;
+
+    let url = format!("http://127.0.0.1:{}/push", instance.port);
+    let response = Client::new()
+        .post(&url)
+        .json(&serde_json::json!({ "files": files }))
+        .send()
+        .map_err(|e| CliError::New(format!("Failed to reach instance: {}", e)))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .map_err(|e| CliError::New(format!("Failed to read response: {}", e)))?;
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(CliError::from(
+            "The instance doesn't serve /push, set \"query_endpoint\": true in config.hx.json and redeploy it",
+        ));
+    }
+    if !status.is_success() {
+        return Err(CliError::New(format!("{} {}", status, body)));
+    }
+
+    let json: JsonValue = serde_json::from_str(&body)
+        .map_err(|e| CliError::New(format!("Invalid response: {}", e)))?;
+    Ok(json
+        .get("queries")
+        .and_then(JsonValue::as_array)
+        .map(|queries| {
+            queries
+                .iter()
+                .filter_map(|query| query.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Fetches the health and metrics of a running instance from its `/status` endpoint
+pub fn fetch_status(instance: &InstanceInfo) -> Result<Status, CliError> {
+    let url = format!("http://127.0.0.1:{}{}", instance.port, STATUS_PATH);
+    let response = Client::new()
+        .get(&url)
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .map_err(|e| CliError::New(format!("Failed to reach instance: {}", e)))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(CliError::New(format!("Instance responded with {}", status)));
+    }
+    let body = response
+        .text()
+        .map_err(|e| CliError::New(format!("Failed to read response: {}", e)))?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// Prints an instance along with its status, or why it couldn't be fetched
+pub fn print_status(instance: &InstanceInfo, status: Result<Status, CliError>) {
+    let name = match instance.label.is_empty() {
+        true => instance.id.clone(),
+        false => format!("{} ({})", instance.id, instance.label),
+    };
+    match &status {
+        Ok(status) if status.map.alert => {
+            println!("{} {}", name.yellow().bold(), status.status.yellow().bold())
+        }
+        Ok(status) => println!("{} {}", name.green().bold(), status.status.green().bold()),
+        Err(_) => println!("{} {}", name.red().bold(), "unreachable".red().bold()),
+    }
+    println!("└── Port: {}", instance.port);
+    println!("└── PID: {}", instance.pid);
+    if let Some(deployed_at) = &instance.deployed_at {
+        println!("└── Deployed: {}", deployed_at);
+    }
+    match status {
+        Ok(status) => {
+            println!(
+                "└── Uptime: {} (since {})",
+                format_duration(status.uptime_secs),
+                status.started_at
+            );
+            println!(
+                "└── Requests: {} ({:.2}/s over the last minute)",
+                status.requests, status.requests_per_sec
+            );
+            println!(
+                "└── Queue: {} waiting, {} turned away ({:.2}ms average wait, {:.2}ms at most)",
+                status.queue.waiting,
+                status.queue.shed,
+                status.queue.avg_wait_ms,
+                status.queue.max_wait_ms
+            );
+            println!("└── Storage: {}", format_bytes(status.storage_bytes));
+            let map = format!(
+                "└── Map: {} of {} used ({:.1}%), grown {} times",
+                format_bytes(status.map.used_bytes),
+                format_bytes(status.map.map_bytes),
+                status.map.used_ratio * 100.0,
+                status.map.resizes
+            );
+            match status.map.alert {
+                true => println!("{}", map.yellow()),
+                false => println!("{}", map),
+            }
+            println!("└── Version: {}", status.version);
+        }
+        Err(e) => println!("└── {} {}", "Error:".red().bold(), e),
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    match unit {
+        "B" => format!("{} B", bytes),
+        unit => format!("{:.1} {}", size, unit),
+    }
+}
+
 pub fn print_instnace(instance: &InstanceInfo) {
     let rg: bool = instance.running;
     println!(
@@ -291,6 +491,9 @@ pub fn print_instnace(instance: &InstanceInfo) {
     );
     println!("└── Label: {}", instance.label.underline());
     println!("└── Port: {}", instance.port);
+    if let Some(primary) = &instance.replica_of {
+        println!("└── Replica of: {}", primary);
+    }
     println!("└── Available endpoints:");
     instance
         .available_endpoints
@@ -298,19 +501,13 @@ pub fn print_instnace(instance: &InstanceInfo) {
         .for_each(|ep| println!("    └── /{}", ep));
 }
 
+/// Writes the TypeScript client of the queries, with interfaces of the schema, to
+/// `queries.ts` in the output directory
 pub fn gen_typescript(source: &GeneratedSource, output_path: &str) -> Result<(), CliError> {
-    let mut file = File::create(PathBuf::from(output_path).join("interface.d.ts"))?;
-
-    for node in &source.nodes {
-        write!(file, "{}", node.to_typescript())?;
-    }
-    for edge in &source.edges {
-        write!(file, "{}", edge.to_typescript())?;
-    }
-    for vector in &source.vectors {
-        write!(file, "{}", vector.to_typescript())?;
-    }
-
+    fs::write(
+        PathBuf::from(output_path).join("queries.ts"),
+        source.to_typescript(),
+    )?;
     Ok(())
 }
 
@@ -359,6 +556,80 @@ pub fn get_remote_helix_version() -> Result<Version, Box<dyn Error>> {
     Ok(Version::parse(&tag_name)?)
 }
 
+/// The mapping of the tables of a SQL database to load: the one at `path` when given,
+/// or the one inferred from the tables. An inferred mapping is written to the output
+/// directory along with its `schema.hx` and report, for the user to edit before it is
+/// loaded.
+pub fn review_sql_mapping(
+    tables: &[SqlTable],
+    path: Option<&str>,
+    output_dir: &str,
+) -> Result<SqlMapping, CliError> {
+    if let Some(path) = path {
+        let mapping = SqlMapping::read(path).map_err(|e| e.to_string())?;
+        mapping.validate(tables).map_err(|e| e.to_string())?;
+        return Ok(mapping);
+    }
+
+    fs::create_dir_all(output_dir)?;
+    let mapping = SqlMapping::infer(tables);
+    let files = mapping.write(output_dir).map_err(|e| e.to_string())?;
+    println!("{}", mapping.report());
+    for file in files.iter() {
+        println!("Created {}", file.display());
+    }
+
+    let path = Path::new(output_dir).join(MAPPING_FILE);
+    let path = path.to_str().unwrap_or(MAPPING_FILE);
+    loop {
+        println!(
+            "Edit {} to change what is loaded, then press ENTER to load it (Ctrl-C to cancel)",
+            path.bold()
+        );
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+
+        match SqlMapping::read(path).and_then(|edited| edited.validate(tables).map(|_| edited)) {
+            Ok(edited) if edited == mapping => return Ok(mapping),
+            Ok(edited) => {
+                // the schema and report follow the edits
+                edited.write(output_dir).map_err(|e| e.to_string())?;
+                println!("Updated the schema and report of the edited mapping");
+                return Ok(edited);
+            }
+            Err(e) => {
+                println!("{}", "Invalid mapping".red().bold());
+                println!("└── {}", e);
+            }
+        }
+    }
+}
+
+pub fn print_sql_report(report: &SqlReport) {
+    println!(
+        "{} {} {} {} {}",
+        "Loaded".green().bold(),
+        report.nodes,
+        "nodes and".green().bold(),
+        report.edges,
+        "edges".green().bold()
+    );
+    if !report.errors.is_empty() {
+        println!(
+            "{} {} {}",
+            "Failed to load".red().bold(),
+            report.errors.len(),
+            "items".red().bold()
+        );
+        for error in report.errors.iter().take(10) {
+            println!("└── {}", error);
+        }
+        if report.errors.len() > 10 {
+            println!("    ... and {} more", report.errors.len() - 10);
+        }
+    }
+}
+
 pub fn get_n_helix_cli() -> Result<(), Box<dyn Error>> {
     // TODO: running this through rust doesn't identify GLIBC so has to compile from source
     let status = Command::new("sh")
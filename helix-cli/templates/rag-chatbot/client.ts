@@ -0,0 +1,61 @@
+// Calls the queries of the project over HTTP, each query is served at
+// POST /<query name> with its parameters as the JSON body.
+//
+// Deploy the project with `helix deploy`, then run this with
+// `npx tsx client.ts` (Node 18+ for fetch). Replace `embed` with a
+// call to the embedding model of your choice.
+
+const HELIX_URL = "http://localhost:6969";
+
+async function query<T>(name: string, params: Record<string, unknown>): Promise<T> {
+  const response = await fetch(`${HELIX_URL}/${name}`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(params),
+  });
+  if (!response.ok) {
+    throw new Error(`${name} failed: ${await response.text()}`);
+  }
+  return response.json() as Promise<T>;
+}
+
+// A stand-in for an embedding model, counting letters so that similar
+// texts get similar vectors
+async function embed(text: string): Promise<number[]> {
+  const vector = new Array(26).fill(0);
+  for (const char of text.toLowerCase()) {
+    const i = char.charCodeAt(0) - 97;
+    if (i >= 0 && i < 26) vector[i] += 1;
+  }
+  const norm = Math.hypot(...vector) || 1;
+  return vector.map((value) => value / norm);
+}
+
+type Document = { id: string; url: string; title: string };
+type Chunk = { id: string; content: string; score: number };
+type Message = { id: string; role: string; content: string };
+
+const { document } = await query<{ document: Document }>("addDocument", {
+  url: "https://docs.helix-db.com",
+  title: "Helix guide",
+});
+for (const content of ["Nodes are connected by edges.", "Vectors are searched by similarity."]) {
+  await query("addChunk", { document_id: document.id, vector: await embed(content), content });
+}
+
+const { conversation } = await query<{ conversation: { id: string } }>("startConversation", {
+  title: "Getting started",
+});
+const question = "How are vectors searched?";
+await query<{ message: Message }>("addMessage", {
+  conversation_id: conversation.id,
+  role: "user",
+  content: question,
+});
+
+// The context to answer the question with, to pass to your LLM
+const { chunks } = await query<{ chunks: Chunk[] }>("searchChunks", {
+  vector: await embed(question),
+  k: 2,
+});
+console.log(chunks.map((chunk) => chunk.content));
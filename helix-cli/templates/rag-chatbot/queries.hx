@@ -0,0 +1,33 @@
+QUERY addDocument(url: String, title: String) =>
+    document <- AddN<Document>({url: url, title: title})
+    RETURN document
+
+QUERY addChunk(document_id: ID, vector: [F64], content: String) =>
+    chunk <- AddV<Chunk>(vector, {content: content})
+    AddE<HasChunk>::From(document_id)::To(chunk)
+    RETURN chunk
+
+// The k chunks closest to the embedding of a question
+QUERY searchChunks(vector: [F64], k: I64) =>
+    chunks <- SearchV<Chunk>(vector, k)
+    RETURN chunks
+
+// The documents the chunks closest to a question come from, to cite
+// them in the answer
+QUERY searchDocuments(vector: [F64], k: I64) =>
+    chunks <- SearchV<Chunk>(vector, k)
+    documents <- chunks::In<HasChunk>
+    RETURN documents
+
+QUERY startConversation(title: String) =>
+    conversation <- AddN<Conversation>({title: title})
+    RETURN conversation
+
+QUERY addMessage(conversation_id: ID, role: String, content: String) =>
+    message <- AddN<Message>({role: role, content: content})
+    AddE<HasMessage>::From(conversation_id)::To(message)
+    RETURN message
+
+QUERY getMessages(conversation_id: ID) =>
+    messages <- N<Conversation>(conversation_id)::Out<HasMessage>::OrderBy(created_at, Asc)
+    RETURN messages
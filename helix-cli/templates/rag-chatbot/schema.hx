@@ -0,0 +1,35 @@
+// Documents split into embedded chunks for retrieval, and the
+// conversations answered from them.
+//
+// The chunks are embedded by your application, with the model of
+// your choice, all vectors must have the same dimensions.
+
+N::Document {
+    UNIQUE INDEX url: String,
+    title: String,
+}
+
+V::Chunk {
+    content: String,
+}
+
+E::HasChunk {
+    From: Document,
+    To: Chunk,
+}
+
+N::Conversation {
+    title: String,
+    started_at: DateTime DEFAULT NOW,
+}
+
+N::Message {
+    role: String,
+    content: String,
+    created_at: DateTime DEFAULT NOW,
+}
+
+E::HasMessage {
+    From: Conversation,
+    To: Message,
+}
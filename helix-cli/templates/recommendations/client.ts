@@ -0,0 +1,49 @@
+// Calls the queries of the project over HTTP, each query is served at
+// POST /<query name> with its parameters as the JSON body.
+//
+// Deploy the project with `helix deploy`, then run this with
+// `npx tsx client.ts` (Node 18+ for fetch).
+
+const HELIX_URL = "http://localhost:6969";
+
+async function query<T>(name: string, params: Record<string, unknown>): Promise<T> {
+  const response = await fetch(`${HELIX_URL}/${name}`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(params),
+  });
+  if (!response.ok) {
+    throw new Error(`${name} failed: ${await response.text()}`);
+  }
+  return response.json() as Promise<T>;
+}
+
+type Customer = { id: string; email: string; name: string };
+type Product = { id: string; name: string; category: string; price: number };
+
+const { customer: ada } = await query<{ customer: Customer }>("createCustomer", {
+  email: "ada@example.com",
+  name: "Ada",
+});
+const { customer: grace } = await query<{ customer: Customer }>("createCustomer", {
+  email: "grace@example.com",
+  name: "Grace",
+});
+const { product: keyboard } = await query<{ product: Product }>("createProduct", {
+  name: "Keyboard",
+  category: "hardware",
+  price: 89.0,
+});
+const { product: monitor } = await query<{ product: Product }>("createProduct", {
+  name: "Monitor",
+  category: "hardware",
+  price: 249.0,
+});
+
+await query("purchase", { customer_id: ada.id, product_id: keyboard.id, quantity: 1 });
+await query("purchase", { customer_id: grace.id, product_id: keyboard.id, quantity: 1 });
+await query("purchase", { customer_id: grace.id, product_id: monitor.id, quantity: 2 });
+
+// Grace bought the same keyboard as Ada, so her monitor is recommended to Ada
+const { products } = await query<{ products: Product[] }>("recommend", { customer_id: ada.id });
+console.log(products.map((product) => product.name));
@@ -0,0 +1,30 @@
+QUERY createCustomer(email: String, name: String) =>
+    customer <- AddN<Customer>({email: email, name: name})
+    RETURN customer
+
+QUERY createProduct(name: String, category: String, price: F64) =>
+    product <- AddN<Product>({name: name, category: category, price: price})
+    RETURN product
+
+QUERY purchase(customer_id: ID, product_id: ID, quantity: U32) =>
+    purchase <- AddE<Purchased>({quantity: quantity})::From(customer_id)::To(product_id)
+    RETURN purchase
+
+QUERY getPurchases(email: String) =>
+    products <- N<Customer>({email: email})::Out<Purchased>
+    RETURN products
+
+QUERY getProductsInCategory(category: String) =>
+    products <- N<Product>::WHERE(_::{category}::EQ(category))::OrderBy(price, Asc)
+    RETURN products
+
+// Products bought by the customers who bought the same products as
+// the customer, the more customers in common the more often a
+// product is returned
+QUERY recommend(customer_id: ID) =>
+    products <- N<Customer>(customer_id)::Out<Purchased>::In<Purchased>::Out<Purchased>
+    RETURN products
+
+QUERY getBuyers(product_id: ID) =>
+    customers <- N<Product>(product_id)::In<Purchased>
+    RETURN customers
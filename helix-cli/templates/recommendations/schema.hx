@@ -0,0 +1,22 @@
+// Products and the customers buying them, for recommending the
+// products bought by customers with similar purchases.
+
+N::Customer {
+    UNIQUE INDEX email: String,
+    name: String,
+}
+
+N::Product {
+    name: String,
+    category: String,
+    price: F64,
+}
+
+E::Purchased {
+    From: Customer,
+    To: Product,
+    Properties: {
+        quantity: U32,
+        purchased_at: DateTime DEFAULT NOW,
+    }
+}
@@ -0,0 +1,31 @@
+// Calls the queries of the project over HTTP, each query is served at
+// POST /<query name> with its parameters as the JSON body.
+//
+// Deploy the project with `helix deploy`, then run this with
+// `npx tsx client.ts` (Node 18+ for fetch).
+
+const HELIX_URL = "http://localhost:6969";
+
+async function query<T>(name: string, params: Record<string, unknown>): Promise<T> {
+  const response = await fetch(`${HELIX_URL}/${name}`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(params),
+  });
+  if (!response.ok) {
+    throw new Error(`${name} failed: ${await response.text()}`);
+  }
+  return response.json() as Promise<T>;
+}
+
+type User = { id: string; name: string; age: number };
+type Post = { id: string; content: string; created_at: string };
+
+const { user: alice } = await query<{ user: User }>("createUser", { name: "Alice", age: 31 });
+const { user: bob } = await query<{ user: User }>("createUser", { name: "Bob", age: 27 });
+
+await query("follow", { follower_id: alice.id, followed_id: bob.id });
+await query("createPost", { user_id: bob.id, content: "Hello, Helix!" });
+
+const { posts } = await query<{ posts: Post[] }>("getFeed", { user_id: alice.id });
+console.log(posts);
@@ -0,0 +1,28 @@
+QUERY createUser(name: String, age: U32) =>
+    user <- AddN<User>({name: name, age: age})
+    RETURN user
+
+QUERY getUser(name: String) =>
+    user <- N<User>({name: name})
+    RETURN user
+
+QUERY follow(follower_id: ID, followed_id: ID) =>
+    follows <- AddE<Follows>::From(follower_id)::To(followed_id)
+    RETURN follows
+
+QUERY getFollowers(user_id: ID) =>
+    followers <- N<User>(user_id)::In<Follows>
+    RETURN followers
+
+QUERY getFollowing(user_id: ID) =>
+    following <- N<User>(user_id)::Out<Follows>
+    RETURN following
+
+QUERY createPost(user_id: ID, content: String) =>
+    post <- AddN<Post>({content: content})
+    AddE<Posted>::From(user_id)::To(post)
+    RETURN post
+
+QUERY getFeed(user_id: ID) =>
+    posts <- N<User>(user_id)::Out<Follows>::Out<Posted>::OrderBy(created_at, Desc)
+    RETURN posts
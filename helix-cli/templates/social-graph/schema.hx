@@ -0,0 +1,24 @@
+// A social network of users following each other and posting.
+//
+// Users are looked up by their unique name, their posts are
+// ordered by when they were created.
+
+N::User {
+    UNIQUE INDEX name: String,
+    age: U32,
+}
+
+N::Post {
+    content: String,
+    created_at: DateTime DEFAULT NOW,
+}
+
+E::Follows {
+    From: User,
+    To: User,
+}
+
+E::Posted {
+    From: User,
+    To: Post,
+}
@@ -0,0 +1,13 @@
+// Seeds the graph the tests run against, the values returned are
+// referred to by their name as {"$ref": "alice"}.
+
+QUERY seed() =>
+    alice <- AddN<User>({name: "Alice", age: 31})
+    bob <- AddN<User>({name: "Bob", age: 27})
+    carol <- AddN<User>({name: "Carol", age: 45})
+    AddE<Follows>::From(alice)::To(bob)
+    AddE<Follows>::From(alice)::To(carol)
+    AddE<Follows>::From(bob)::To(carol)
+    post <- AddN<Post>({content: "Hello from Carol"})
+    AddE<Posted>::From(carol)::To(post)
+    RETURN alice, bob, carol, post
@@ -1,29 +1,75 @@
+use helixdb::helix_cluster::node::ClusterNode;
 use helixdb::helix_engine::graph_core::config::Config;
 use helixdb::helix_engine::graph_core::graph_core::{HelixGraphEngine, HelixGraphEngineOpts};
+use helixdb::helix_engine::migration::migration::SchemaSnapshot;
+use helixdb::helix_engine::storage_core::namespaces::add_schema_labels;
 use helixdb::helix_gateway::mcp::mcp::{MCPHandlerFn, MCPHandlerSubmission};
 use helixdb::helix_gateway::{
+    auth::auth::Authenticator,
+    bolt::bolt::BoltServer,
+    connection::limits::ConnectionLimits,
     gateway::{GatewayOpts, HelixGateway},
-    router::router::{HandlerFn, HandlerSubmission},
+    grpc::grpc::GrpcService,
+    router::router::{
+        AsyncHandlerFn, AsyncHandlerSubmission, HandlerFn, HandlerSubmission, HelixRouter,
+        TxHandlerFn, TxHandlerSubmission,
+    },
 };
+use helixdb::helix_replication::replica::Replica;
 use helixdb::helix_runtime::tokio_runtime::TokioRuntime;
-use helixdb::helix_transport::tokio_transport::TokioTransport;
+use helixdb::helix_sharding::shard_router::ShardRouter;
+use helixdb::helix_storage::StorageBackend;
+use helixdb::helix_transport::{
+    dual_transport::DualTransport, tokio_transport::TokioTransport, ws_transport::WsTransport,
+    Transport,
+};
 use inventory;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::Arc,
+};
 
 mod queries;
 
+// schema the queries were compiled against, written by the cli on deploy
+const SCHEMA: &str = include_str!("schema.hx");
+
 #[tokio::main]
 async fn main() {
     // read from config.hx.json
     let home = dirs::home_dir().expect("Could not retrieve home directory");
     let config_path = home.join(".helix/repo/helix-db/helix-container/src/config.hx.json");
-    let config = match Config::from_config_file(config_path) {
+    let mut config = match Config::from_config_file(config_path) {
         Ok(config) => config,
         Err(e) => {
             println!("Error loading config: {}", e);
             Config::default()
         }
     };
+    // the gateway checks credentials and limits, the engine doesn't need them
+    let auth = config.auth.take();
+    let limits = ConnectionLimits::from_config(&config.limits);
+    let query_endpoint = config.query_endpoint;
+    // HELIX_REPLICA_OF makes the instance a read-only replica of the primary at that address
+    let mut replication = std::mem::take(&mut config.replication);
+    if let Ok(primary) = std::env::var("HELIX_REPLICA_OF") {
+        replication.primary = Some(primary);
+    }
+    let cluster = config.cluster.take();
+    // the engine keeps the rest of the sharding config to place new nodes
+    let shard_api_key = config
+        .sharding
+        .as_ref()
+        .and_then(|sharding| sharding.api_key.clone());
+    if cluster.is_some() && replication.primary.is_some() {
+        panic!("A cluster node can't also be a replica");
+    }
+
+    // the labels of the schema's namespaces get ids and indices of their namespace
+    if let Err(e) = add_schema_labels(&mut config.namespaces, SCHEMA) {
+        println!("Error reading the namespaces of the schema: {}", e);
+    }
 
     let path = match std::env::var("HELIX_DATA_DIR") {
         Ok(val) => std::path::PathBuf::from(val).join("user"),
@@ -37,22 +83,66 @@ async fn main() {
         Ok(val) => val.parse::<u16>().unwrap(),
         Err(_) => 6969,
     };
+    // HELIX_STORAGE=memory keeps the database in a temporary directory that is removed
+    // on exit, on /dev/shm when available
+    let backend = match std::env::var("HELIX_STORAGE") {
+        Ok(val) if val.eq_ignore_ascii_case("memory") => StorageBackend::InMemory,
+        _ => StorageBackend::Lmdb,
+    };
     println!("Running with the following setup:");
     println!("\tconfig: {:?}", config);
     println!("\tpath: {}", path.display());
     println!("\tport: {}", port);
+    println!("\tstorage: {:?}", backend);
+    if let Some(primary) = &replication.primary {
+        println!("\treplica of: {}", primary);
+    }
+    if let Some(cluster) = &cluster {
+        println!("\tcluster node: {} at {}", cluster.node_id, cluster.address);
+    }
     let path_str = path.to_str().expect("Could not convert path to string");
     let opts = HelixGraphEngineOpts {
         path: path_str.to_string(),
         config,
+        backend,
     };
     let graph = Arc::new(HelixGraphEngine::new(opts).unwrap());
 
+    // migrate existing data to the deployed schema, replicas receive the migrated data
+    // from their primary. Cluster nodes don't know whether they lead the cluster yet, and
+    // migrating each of them would give them diverging logs
+    if !SCHEMA.trim().is_empty() && replication.primary.is_none() && cluster.is_none() {
+        match SchemaSnapshot::parse(SCHEMA)
+            .and_then(|schema| graph.storage.migrate(&schema, &[], false))
+        {
+            Ok(report) => {
+                println!("Schema version: {}", report.version);
+                for step in report.steps.iter() {
+                    println!("\tmigrated: {}", step);
+                }
+            }
+            Err(e) => println!("Error migrating schema: {}", e),
+        }
+    }
+
     // generates routes from handler proc macro
     println!("Starting route collection...");
     let submissions: Vec<_> = inventory::iter::<HandlerSubmission>.into_iter().collect();
     println!("Found {} submissions", submissions.len());
 
+    // routes of queries writing to the graph, which replicas and cluster followers don't serve
+    let write_routes = submissions
+        .iter()
+        .filter(|submission| submission.0.writes)
+        .map(|submission| ("POST".to_string(), format!("/{}", submission.0.name)))
+        .collect::<HashSet<_>>();
+    // the same queries, by name, audited when run through the transaction endpoint
+    let write_queries = submissions
+        .iter()
+        .filter(|submission| submission.0.writes)
+        .map(|submission| submission.0.name.to_string())
+        .collect::<HashSet<_>>();
+
     let routes = HashMap::from_iter(
         submissions
             .into_iter()
@@ -72,6 +162,28 @@ async fn main() {
             .collect::<Vec<((String, String), HandlerFn)>>(),
     );
 
+    // handlers run as tasks of their own, for queries waiting on I/O
+    let mut async_routes = HashMap::from_iter(
+        inventory::iter::<AsyncHandlerSubmission>
+            .into_iter()
+            .map(|submission| {
+                println!(
+                    "Processing submission for async handler: {}",
+                    submission.0.name
+                );
+                let handler = &submission.0;
+                let func: AsyncHandlerFn = Arc::new(move |input| (handler.func)(input));
+                (("POST".to_string(), format!("/{}", handler.name)), func)
+            })
+            .collect::<Vec<((String, String), AsyncHandlerFn)>>(),
+    );
+    // shards answer scans and vector searches over the whole graph
+    if let Some(shards) = graph.storage.shards.clone() {
+        println!("Shard {} of {}", shards.shard, shards.len());
+        async_routes
+            .extend(Arc::new(ShardRouter::new(shards, shard_api_key, TokioTransport)).routes());
+    }
+
     let mcp_submissions: Vec<_> = inventory::iter::<MCPHandlerSubmission>
         .into_iter()
         .collect();
@@ -94,21 +206,167 @@ async fn main() {
             .collect::<Vec<((String, String), MCPHandlerFn)>>(),
     );
 
+    // queries that can be run together through the transaction endpoint
+    let tx_routes = HashMap::from_iter(
+        inventory::iter::<TxHandlerSubmission>
+            .into_iter()
+            .map(|submission| (submission.0.name.to_string(), submission.0.func))
+            .collect::<Vec<(String, TxHandlerFn)>>(),
+    );
+
     println!("Routes: {:?}", routes.keys());
+    let mut router = HelixRouter::new(Some(routes), Some(mcp_routes))
+        .with_async_routes(async_routes)
+        .with_tx_routes(tx_routes)
+        .with_write_queries(write_queries);
+    if let Some(auth) = auth {
+        router = router.with_auth(Authenticator::new(&auth).expect("Invalid auth config"));
+    }
+    // ad-hoc and pushed queries, of `helix shell` and `helix push`, are checked against
+    // the deployed schema
+    if query_endpoint {
+        router = router.with_query_endpoint(SCHEMA);
+    }
+    if let Some(primary) = replication.primary {
+        let primary = tokio::net::lookup_host(&primary)
+            .await
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .expect("Invalid primary address");
+        router = router.with_read_only(write_routes);
+        let replica = Replica::new(
+            primary,
+            replication.api_key,
+            Arc::clone(&graph.storage),
+            TokioTransport,
+        );
+        tokio::spawn(replica.run());
+    } else if let Some(cluster) = cluster {
+        let node = Arc::new(
+            ClusterNode::new(Arc::clone(&graph.storage), &cluster)
+                .expect("Failed to join the cluster"),
+        );
+        node.start(TokioTransport)
+            .expect("Failed to start the cluster node");
+        router = router.with_cluster(node, write_routes);
+    }
+    let address = format!("0.0.0.0:{}", port);
+    // HELIX_GRPC_PORT serves the queries over gRPC as well
+    let grpc_address = std::env::var("HELIX_GRPC_PORT").ok().map(|val| {
+        let grpc_port = val.parse::<u16>().unwrap();
+        println!("\tgrpc port: {}", grpc_port);
+        format!("0.0.0.0:{}", grpc_port).parse().unwrap()
+    });
+    // HELIX_BOLT_PORT serves read only Cypher to Neo4j drivers
+    let bolt_address = std::env::var("HELIX_BOLT_PORT").ok().map(|val| {
+        let bolt_port = val.parse::<u16>().unwrap();
+        println!("\tbolt port: {}", bolt_port);
+        format!("0.0.0.0:{}", bolt_port).parse().unwrap()
+    });
+
+    // serve websocket clients alongside plain tcp ones if a websocket port is set
+    match std::env::var("HELIX_WS_PORT") {
+        Ok(val) => {
+            let ws_port = val.parse::<u16>().unwrap();
+            println!("\tws port: {}", ws_port);
+            let ws_addr = format!("0.0.0.0:{}", ws_port).parse().unwrap();
+            let transport = DualTransport::new(TokioTransport, WsTransport, ws_addr);
+            serve(
+                &address,
+                grpc_address,
+                bolt_address,
+                graph,
+                router,
+                limits,
+                transport,
+            )
+            .await;
+        }
+        Err(_) => {
+            serve(
+                &address,
+                grpc_address,
+                bolt_address,
+                graph,
+                router,
+                limits,
+                TokioTransport,
+            )
+            .await
+        }
+    }
+}
+
+async fn serve<T: Transport>(
+    address: &str,
+    grpc_address: Option<SocketAddr>,
+    bolt_address: Option<SocketAddr>,
+    graph: Arc<HelixGraphEngine>,
+    router: HelixRouter,
+    limits: ConnectionLimits,
+    transport: T,
+) {
     // create gateway
-    let gateway = HelixGateway::new(
-        &format!("0.0.0.0:{}", port),
+    let gateway = HelixGateway::with_router(
+        address,
         graph,
         GatewayOpts::DEFAULT_POOL_SIZE,
-        Some(routes),
-        Some(mcp_routes),
+        router,
+        limits,
         TokioRuntime::default(),
-        TokioTransport,
+        transport,
     )
     .await;
 
+    // the gRPC server shares the router, and with it the auth and middleware
+    if let Some(grpc_address) = grpc_address {
+        let service = GrpcService::new(
+            Arc::clone(&gateway.graph),
+            Arc::clone(&gateway.connection_handler.router),
+        );
+        tokio::spawn(async move {
+            if let Err(e) = service.serve(grpc_address).await {
+                eprintln!("Error serving gRPC: {}", e);
+            }
+        });
+    }
+    if let Some(bolt_address) = bolt_address {
+        let server = BoltServer::new(
+            Arc::clone(&gateway.graph),
+            Arc::clone(&gateway.connection_handler.router),
+        );
+        tokio::spawn(async move {
+            if let Err(e) = server.serve(bolt_address, TokioTransport).await {
+                eprintln!("Error serving Bolt: {}", e);
+            }
+        });
+    }
+
     // start server
     println!("Starting server...");
     let handle = gateway.connection_handler.accept_conns().await.unwrap();
-    handle.await;
+    tokio::select! {
+        _ = handle => {}
+        _ = shutdown_signal() => {
+            match gateway.shutdown(GatewayOpts::SHUTDOWN_TIMEOUT).await {
+                Ok(()) => println!("Shut down"),
+                Err(e) => eprintln!("Error shutting down: {}", e),
+            }
+        }
+    }
+}
+
+/// Resolves once the process is asked to stop, by SIGTERM from `helix stop` or ctrl-c
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = sigterm.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
 }
@@ -0,0 +1,28 @@
+//! Throughput of adding nodes, committed in batches of different sizes
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+fn add_node(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_node");
+    for batch in [1, 100, 1000] {
+        let bench = common::storage();
+        let mut added = 0;
+        group.throughput(Throughput::Elements(batch as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(batch), &batch, |b, &batch| {
+            b.iter(|| {
+                let mut txn = bench.storage.graph_env.write_txn().unwrap();
+                for _ in 0..batch {
+                    common::add_user(&bench.storage, &mut txn, added);
+                    added += 1;
+                }
+                txn.commit().unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_node);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+//! Graphs the benchmarks run against, each stored in a temporary directory removed
+//! once it is dropped.
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use helixdb::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                },
+                tr_val::Traversable,
+            },
+        },
+        storage_core::storage_core::HelixGraphStorage,
+    },
+    helix_storage::heed3::RwTxn,
+    props,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tempfile::TempDir;
+
+/// Seed of the random data, so every run measures the same graph
+const SEED: u64 = 42;
+
+pub struct BenchStorage {
+    pub storage: Arc<HelixGraphStorage>,
+    _dir: TempDir,
+}
+
+pub fn storage() -> BenchStorage {
+    let dir = TempDir::new().unwrap();
+    let storage = HelixGraphStorage::new(dir.path().to_str().unwrap(), Config::default()).unwrap();
+    BenchStorage {
+        storage: Arc::new(storage),
+        _dir: dir,
+    }
+}
+
+/// Adds a node with the properties of a typical user
+pub fn add_user(storage: &Arc<HelixGraphStorage>, txn: &mut RwTxn, i: usize) -> u128 {
+    G::new_mut(Arc::clone(storage), txn)
+        .add_n(
+            "user",
+            Some(props! {
+                "name" => format!("user {}", i),
+                "email" => format!("user{}@example.com", i),
+                "age" => (i % 90) as i64,
+            }),
+            None,
+        )
+        .collect_to_val()
+        .id()
+}
+
+/// Adds a tree of `fanout` users following `fanout` users each below a root user,
+/// returning the root
+pub fn fanout_graph(storage: &Arc<HelixGraphStorage>, fanout: usize) -> u128 {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let root = add_user(storage, &mut txn, 0);
+    let follow = |txn: &mut RwTxn, from: u128, to: u128| {
+        G::new_mut(Arc::clone(storage), txn)
+            .add_e("follows", None, from, to, false, EdgeType::Node)
+            .collect_to_val();
+    };
+    for i in 0..fanout {
+        let child = add_user(storage, &mut txn, i + 1);
+        follow(&mut txn, root, child);
+        for j in 0..fanout {
+            let grandchild = add_user(storage, &mut txn, (i + 1) * fanout + j + 1);
+            follow(&mut txn, child, grandchild);
+        }
+    }
+    txn.commit().unwrap();
+    root
+}
+
+/// Vectors with components uniformly drawn from [-1, 1)
+pub fn random_vectors(count: usize, dimensions: usize) -> Vec<Vec<f64>> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    (0..count)
+        .map(|_| {
+            (0..dimensions)
+                .map(|_| rng.random_range(-1.0..1.0))
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,54 @@
+//! Throughput of turning the nodes a query returns into the JSON body of its response,
+//! as the generated queries do
+
+use std::{cell::RefCell, collections::HashMap};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use helixdb::{
+    helix_engine::graph_core::ops::tr_val::TraversalVal,
+    protocol::{items::Node, return_values::ReturnValue, value::Value},
+};
+
+fn nodes(count: usize) -> Vec<TraversalVal> {
+    (0..count)
+        .map(|i| {
+            TraversalVal::Node(Node {
+                id: i as u128,
+                label: "user".to_string(),
+                properties: Some(HashMap::from([
+                    ("name".to_string(), Value::from(format!("user {}", i))),
+                    (
+                        "email".to_string(),
+                        Value::from(format!("user{}@example.com", i)),
+                    ),
+                    ("age".to_string(), Value::from((i % 90) as i64)),
+                    ("score".to_string(), Value::from(i as f64 / 3.0)),
+                ])),
+                score: None,
+            })
+        })
+        .collect()
+}
+
+fn serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize_nodes");
+    for count in [10, 100, 1000] {
+        let nodes = nodes(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &nodes, |b, nodes| {
+            b.iter(|| {
+                let remappings = RefCell::new(HashMap::new());
+                let users = ReturnValue::from_traversal_value_array_with_mixin(
+                    nodes.clone(),
+                    remappings.borrow_mut(),
+                );
+                let response = HashMap::from([("users".to_string(), users)]);
+                sonic_rs::to_vec(&response).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+//! Latency of 2-hop traversals, `N(id)::Out<follows>::Out<follows>`, reaching
+//! `fanout²` nodes
+
+mod common;
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use helixdb::helix_engine::graph_core::ops::{
+    g::G,
+    out::out::OutAdapter,
+    source::{add_e::EdgeType, n_from_id::NFromIdAdapter},
+};
+
+fn two_hop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("two_hop");
+    for fanout in [4, 16, 64] {
+        let bench = common::storage();
+        let root = common::fanout_graph(&bench.storage, fanout);
+        let txn = bench.storage.graph_env.read_txn().unwrap();
+        group.throughput(Throughput::Elements((fanout * fanout) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(fanout), &root, |b, root| {
+            b.iter(|| {
+                let reached = G::new(Arc::clone(&bench.storage), &txn)
+                    .n_from_id(root)
+                    .out("follows", &EdgeType::Node)
+                    .out("follows", &EdgeType::Node)
+                    .collect_to::<Vec<_>>();
+                assert_eq!(reached.len(), fanout * fanout);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, two_hop);
+criterion_main!(benches);
@@ -0,0 +1,101 @@
+//! Latency of searching the HNSW index for the 10 nearest vectors, along with the
+//! recall of the search against an exact scan, which is printed before the latency is
+//! measured as it doesn't change between iterations
+
+mod common;
+
+use std::{collections::HashSet, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use helixdb::{
+    helix_engine::{
+        graph_core::ops::{
+            g::G,
+            tr_val::Traversable,
+            vectors::{insert::InsertVAdapter, search::SearchVAdapter},
+        },
+        vector_core::vector::HVector,
+    },
+    helix_storage::heed3::RoTxn,
+};
+
+type Filter = fn(&HVector, &RoTxn) -> bool;
+
+const DIMENSIONS: usize = 128;
+const K: usize = 10;
+const QUERIES: usize = 50;
+
+fn vector_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vector_search");
+    for count in [1_000, 5_000] {
+        let bench = common::storage();
+        let vectors = common::random_vectors(count + QUERIES, DIMENSIONS);
+        let (vectors, queries) = vectors.split_at(count);
+
+        let mut txn = bench.storage.graph_env.write_txn().unwrap();
+        let stored = vectors
+            .iter()
+            .map(|data| {
+                let id = G::new_mut(Arc::clone(&bench.storage), &mut txn)
+                    .insert_v::<Filter>(data, "doc", None)
+                    .collect_to_val()
+                    .id();
+                let mut vector = HVector::from_slice(0, data.clone());
+                vector.id = id;
+                vector
+            })
+            .collect::<Vec<_>>();
+        txn.commit().unwrap();
+
+        let txn = bench.storage.graph_env.read_txn().unwrap();
+        let search = |query: &Vec<f64>| {
+            G::new(Arc::clone(&bench.storage), &txn)
+                .search_v::<Filter>(query, K, None)
+                .collect_to::<Vec<_>>()
+        };
+
+        let found = queries
+            .iter()
+            .map(|query| {
+                let exact = nearest(&stored, query);
+                search(query)
+                    .iter()
+                    .filter(|item| exact.contains(&item.id()))
+                    .count()
+            })
+            .sum::<usize>();
+        println!(
+            "vector_search/{}: recall@{} {:.3}",
+            count,
+            K,
+            found as f64 / (QUERIES * K) as f64
+        );
+
+        let mut next = 0;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &queries,
+            |b, queries| {
+                b.iter(|| {
+                    next = (next + 1) % queries.len();
+                    search(&queries[next])
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Ids of the K vectors nearest to the query, found by comparing it with all of them
+fn nearest(vectors: &[HVector], query: &[f64]) -> HashSet<u128> {
+    let query = HVector::from_slice(0, query.to_vec());
+    let mut distances = vectors
+        .iter()
+        .map(|vector| (vector.get_id(), vector.distance_to(&query).unwrap()))
+        .collect::<Vec<_>>();
+    distances.sort_by(|a, b| a.1.total_cmp(&b.1));
+    distances.into_iter().take(K).map(|(id, _)| id).collect()
+}
+
+criterion_group!(benches, vector_search);
+criterion_main!(benches);
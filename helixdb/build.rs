@@ -0,0 +1,10 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        // protoc is vendored so building doesn't need it to be installed
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+        std::env::set_var("PROTOC_INCLUDE", protoc_bin_vendored::include_path()?);
+        tonic_build::compile_protos("proto/helix.proto")?;
+    }
+    Ok(())
+}
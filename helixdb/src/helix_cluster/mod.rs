@@ -0,0 +1,5 @@
+pub mod node;
+pub mod rpc;
+
+#[cfg(test)]
+mod node_tests;
@@ -0,0 +1,941 @@
+use crate::helix_cluster::rpc::{
+    call, ClusterStatus, Heartbeat, HeartbeatResponse, MemberRequest, MemberStatus, Role,
+    VoteRequest, VoteResponse, CLUSTER_PATH, HEARTBEAT_PATH, MEMBERS_PATH, VOTE_PATH,
+};
+use crate::helix_engine::{
+    graph_core::config::{ClusterConfig, ClusterMember},
+    storage_core::{
+        storage_core::HelixGraphStorage,
+        wal::{Quorum, WalEntry, WalOp},
+    },
+    types::GraphError,
+};
+use crate::helix_replication::replica::Replica;
+use crate::helix_storage::heed3::{byteorder::BE, types::*, Database, RoTxn, RwTxn};
+use crate::helix_transport::Transport;
+use crate::protocol::{request::Request, response::Response};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Condvar, Mutex, Weak,
+};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::sync::Notify;
+use tokio::task::block_in_place;
+
+const DB_CLUSTER_META: &str = "cluster_meta";
+const DB_CLUSTER_LOG: &str = "cluster_log"; // seq -> entry sent by the leader, not yet applied
+const STATE_KEY: &str = "state";
+
+/// A node of a cluster electing a leader that takes the writes, following Raft.
+///
+/// Entries of the write-ahead log are tagged with the term of the leader that wrote them.
+/// The leader sends the entries of a write transaction to the other nodes before
+/// committing it, and only commits it once a majority of the cluster stored them, rolling
+/// it back otherwise. The other nodes keep the entries in a log of their own until the
+/// leader lets them know they are committed and only apply them then, so entries
+/// conflicting with those of a newer leader can be dropped. Nodes that stop hearing from
+/// the leader stand for election, and only get the vote of nodes whose log isn't more up
+/// to date than theirs, so the new leader has every committed entry. It commits the
+/// entries of earlier terms it has by journaling an entry of its own term after them,
+/// before it takes writes.
+///
+/// Followers too far behind for the entries the leader sends to follow on from their log
+/// catch up by replicating the leader's log file, which only holds committed entries.
+///
+/// Only writes committed through [`WriteAheadLog::commit`] are replicated, as those run by
+/// the gateway are, so the graph of a cluster node shouldn't be written to otherwise.
+///
+/// [`WriteAheadLog::commit`]: crate::helix_engine::storage_core::wal::WriteAheadLog::commit
+pub struct ClusterNode {
+    id: u64,
+    address: String,
+    api_key: Option<String>,
+    election_timeout: Duration,
+    storage: Arc<HelixGraphStorage>,
+    meta_db: Database<Str, Bytes>,
+    log_db: Database<U64<BE>, Bytes>,
+    state: Mutex<RaftState>,
+    /// Notified whenever followers acknowledge entries or the node stops leading
+    acked: Condvar,
+    /// Notified when the leader has entries to send or committed some
+    wake: Notify,
+    stopped: AtomicBool,
+}
+
+struct RaftState {
+    term: u64,
+    voted_for: Option<u64>,
+    role: Role,
+    leader: Option<u64>,
+    members: Vec<ClusterMember>,
+    last_contact: Instant,
+    /// Randomized for every election so candidates rarely split the vote
+    timeout: Duration,
+    /// Member id => seq of the last entry the member stored, tracked by the leader
+    match_seq: HashMap<u64, u64>,
+    /// Set once the leader committed an entry of its term, it only takes writes from then
+    ready: bool,
+    /// seq and term of the last entry the leader committed
+    committed: (u64, u64),
+    /// Entries of the write the leader is committing
+    inflight: Vec<WalEntry>,
+}
+
+/// State that has to survive a restart so a node never votes twice in a term
+#[derive(Serialize, Deserialize, PartialEq)]
+struct PersistentState {
+    term: u64,
+    voted_for: Option<u64>,
+    members: Vec<ClusterMember>,
+}
+
+impl RaftState {
+    fn reset_timer(&mut self, election_timeout: Duration) {
+        self.last_contact = Instant::now();
+        self.timeout = election_timeout.mul_f64(1.0 + rand::random::<f64>());
+    }
+
+    fn address_of(&self, id: u64) -> Option<String> {
+        self.members
+            .iter()
+            .find(|member| member.id == id)
+            .map(|member| member.address.clone())
+    }
+
+    fn majority(&self) -> usize {
+        self.members.len() / 2 + 1
+    }
+
+    /// Members that stored the entry `seq`, including the leader itself
+    fn replicated(&self, leader: u64, seq: u64) -> usize {
+        self.members
+            .iter()
+            .filter(|member| {
+                member.id == leader || self.match_seq.get(&member.id).is_some_and(|s| *s >= seq)
+            })
+            .count()
+    }
+
+    fn persistent(&self) -> PersistentState {
+        PersistentState {
+            term: self.term,
+            voted_for: self.voted_for,
+            members: self.members.clone(),
+        }
+    }
+
+    fn not_leader(&self) -> GraphError {
+        GraphError::NotLeader {
+            leader: self.leader.and_then(|leader| self.address_of(leader)),
+        }
+    }
+}
+
+impl ClusterNode {
+    /// How often the leader sends heartbeats, and other nodes check on the leader
+    pub const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(100);
+    pub const DEFAULT_ELECTION_TIMEOUT: Duration = Duration::from_secs(1);
+    /// Longest time a write waits for a majority of the cluster to store it
+    pub const COMMIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Joins the cluster with the members it was left with, or those of the config the
+    /// first time the node starts. The write-ahead log must be enabled.
+    pub fn new(
+        storage: Arc<HelixGraphStorage>,
+        config: &ClusterConfig,
+    ) -> Result<ClusterNode, GraphError> {
+        if !storage.wal.is_enabled() {
+            return Err(GraphError::New(
+                "Cluster nodes need the write-ahead log to be enabled".to_string(),
+            ));
+        }
+        let mut wtxn = storage.graph_env.write_txn()?;
+        let meta_db: Database<Str, Bytes> = storage
+            .graph_env
+            .database_options()
+            .types::<Str, Bytes>()
+            .name(DB_CLUSTER_META)
+            .create(&mut wtxn)?;
+        let log_db: Database<U64<BE>, Bytes> = storage
+            .graph_env
+            .database_options()
+            .types::<U64<BE>, Bytes>()
+            .name(DB_CLUSTER_LOG)
+            .create(&mut wtxn)?;
+        let state = match meta_db.get(&wtxn, STATE_KEY)? {
+            Some(bytes) => bincode::deserialize::<PersistentState>(bytes)?,
+            None => {
+                let mut members = config.members.clone();
+                if !members.iter().any(|member| member.id == config.node_id) {
+                    members.push(ClusterMember {
+                        id: config.node_id,
+                        address: config.address.clone(),
+                    });
+                }
+                PersistentState {
+                    term: 0,
+                    voted_for: None,
+                    members,
+                }
+            }
+        };
+        meta_db.put(&mut wtxn, STATE_KEY, &bincode::serialize(&state)?)?;
+        wtxn.commit()?;
+
+        let election_timeout = config
+            .election_timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or(Self::DEFAULT_ELECTION_TIMEOUT);
+        let mut state = RaftState {
+            term: state.term,
+            voted_for: state.voted_for,
+            role: Role::Follower,
+            leader: None,
+            members: state.members,
+            last_contact: Instant::now(),
+            timeout: election_timeout,
+            match_seq: HashMap::new(),
+            ready: false,
+            committed: (0, 0),
+            inflight: Vec::new(),
+        };
+        state.reset_timer(election_timeout);
+
+        Ok(ClusterNode {
+            id: config.node_id,
+            address: config.address.clone(),
+            api_key: config.api_key.clone(),
+            election_timeout,
+            storage,
+            meta_db,
+            log_db,
+            state: Mutex::new(state),
+            acked: Condvar::new(),
+            wake: Notify::new(),
+            stopped: AtomicBool::new(false),
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Starts taking part in elections and following the leader, on a thread of its own
+    /// so the cluster keeps running while the gateway's workers wait on writes. Writes to
+    /// the graph are committed through the node from then on.
+    pub fn start<T: Transport + Clone>(self: &Arc<Self>, transport: T) -> Result<(), GraphError> {
+        let quorum: Weak<dyn Quorum> = Arc::downgrade(self) as Weak<dyn Quorum>;
+        self.storage.wal.set_quorum(quorum)?;
+        let node = Arc::clone(self);
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build cluster runtime");
+            runtime.block_on(async {
+                tokio::join!(
+                    node.run_elections(&transport),
+                    node.follow_leader(&transport)
+                );
+            });
+        });
+        Ok(())
+    }
+
+    /// Stops taking part in the cluster, which elects another leader if this node led it
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+        self.demote(&mut state);
+        self.wake.notify_one();
+    }
+
+    pub fn role(&self) -> Role {
+        self.state.lock().unwrap().role
+    }
+
+    /// Address of the leader, if the node knows of one
+    pub fn leader(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.leader.and_then(|leader| state.address_of(leader))
+    }
+
+    /// Fails with [`GraphError::NotLeader`] unless the node currently leads the cluster
+    /// and takes writes
+    pub fn check_leader(&self) -> Result<(), GraphError> {
+        let state = self.state.lock().unwrap();
+        match state.role {
+            Role::Leader if state.ready => Ok(()),
+            Role::Leader => Err(GraphError::NotLeader { leader: None }),
+            _ => Err(state.not_leader()),
+        }
+    }
+
+    pub fn status(&self) -> Result<ClusterStatus, GraphError> {
+        let last_seq = self.last_seq()?;
+        let state = self.state.lock().unwrap();
+        Ok(ClusterStatus {
+            id: self.id,
+            role: state.role,
+            term: state.term,
+            leader: state.leader,
+            last_seq,
+            members: state
+                .members
+                .iter()
+                .map(|member| MemberStatus {
+                    id: member.id,
+                    address: member.address.clone(),
+                    last_seq: match member.id == self.id {
+                        true => Some(last_seq),
+                        false => state.match_seq.get(&member.id).copied(),
+                    },
+                })
+                .collect(),
+        })
+    }
+
+    /// Handles the requests on the cluster paths, from other nodes and the admin API
+    pub fn handle(&self, request: &Request, response: &mut Response) -> Result<(), GraphError> {
+        if self.stopped.load(Ordering::Relaxed) {
+            return Err(GraphError::New(format!(
+                "Node {} left the cluster",
+                self.id
+            )));
+        }
+        let body = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", CLUSTER_PATH) => sonic_rs::to_vec(&self.status()?)?,
+            ("POST", VOTE_PATH) => {
+                sonic_rs::to_vec(&self.on_vote(&sonic_rs::from_slice(&request.body)?)?)?
+            }
+            ("POST", HEARTBEAT_PATH) => {
+                sonic_rs::to_vec(&self.on_heartbeat(&sonic_rs::from_slice(&request.body)?)?)?
+            }
+            ("POST", MEMBERS_PATH) => {
+                self.add_member(&sonic_rs::from_slice(&request.body)?)?;
+                sonic_rs::to_vec(&self.status()?)?
+            }
+            ("DELETE", MEMBERS_PATH) => {
+                self.remove_member(&sonic_rs::from_slice(&request.body)?)?;
+                sonic_rs::to_vec(&self.status()?)?
+            }
+            _ => {
+                response.status = 404;
+                response.body = b"404 - Not Found".to_vec();
+                return Ok(());
+            }
+        };
+        response
+            .headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        response.body = body;
+        Ok(())
+    }
+
+    /// Adds a member to the cluster. Members should be added one at a time, once the
+    /// previous one caught up with the leader.
+    pub fn add_member(&self, request: &MemberRequest) -> Result<(), GraphError> {
+        self.check_leader()?;
+        let Some(address) = &request.address else {
+            return Err(GraphError::New(
+                "The address of the new member is missing".to_string(),
+            ));
+        };
+        self.update(|state, _| {
+            if state.members.iter().any(|member| member.id == request.id) {
+                return Err(GraphError::New(format!(
+                    "Node {} is already a member of the cluster",
+                    request.id
+                )));
+            }
+            state.members.push(ClusterMember {
+                id: request.id,
+                address: address.clone(),
+            });
+            Ok(())
+        })?;
+        println!("Added node {} at {} to the cluster", request.id, address);
+        Ok(())
+    }
+
+    /// Removes a member other than the leader from the cluster
+    pub fn remove_member(&self, request: &MemberRequest) -> Result<(), GraphError> {
+        self.check_leader()?;
+        if request.id == self.id {
+            return Err(GraphError::New(
+                "The leader can't remove itself from the cluster".to_string(),
+            ));
+        }
+        self.update(|state, _| {
+            let Some(i) = state
+                .members
+                .iter()
+                .position(|member| member.id == request.id)
+            else {
+                return Err(GraphError::New(format!(
+                    "Node {} isn't a member of the cluster",
+                    request.id
+                )));
+            };
+            state.members.remove(i);
+            state.match_seq.remove(&request.id);
+            // the majority writes wait on may have shrunk
+            self.acked.notify_all();
+            Ok(())
+        })?;
+        println!("Removed node {} from the cluster", request.id);
+        Ok(())
+    }
+
+    pub fn on_vote(&self, request: &VoteRequest) -> Result<VoteResponse, GraphError> {
+        {
+            let state = self.state.lock().unwrap();
+            let rejected = VoteResponse {
+                term: state.term,
+                granted: false,
+            };
+            // nodes removed from the cluster don't learn of it, and mustn't disrupt it
+            if !state
+                .members
+                .iter()
+                .any(|member| member.id == request.candidate)
+            {
+                return Ok(rejected);
+            }
+            if request.term < state.term
+                || (request.term == state.term
+                    && state.voted_for.is_some_and(|id| id != request.candidate))
+            {
+                return Ok(rejected);
+            }
+        }
+        self.observe_term(request.term)?;
+        self.update(|state, txn| {
+            let last = self.log_end(txn)?;
+            let granted = request.term == state.term
+                && state.voted_for.is_none_or(|id| id == request.candidate)
+                && (request.last_term, request.last_seq) >= (last.1, last.0);
+            if granted {
+                state.voted_for = Some(request.candidate);
+                state.reset_timer(self.election_timeout);
+            }
+            Ok(VoteResponse {
+                term: state.term,
+                granted,
+            })
+        })
+    }
+
+    pub fn on_heartbeat(&self, heartbeat: &Heartbeat) -> Result<HeartbeatResponse, GraphError> {
+        {
+            let state = self.state.lock().unwrap();
+            if heartbeat.term < state.term {
+                return Ok(HeartbeatResponse {
+                    term: state.term,
+                    success: false,
+                    last_seq: 0,
+                });
+            }
+        }
+        self.observe_term(heartbeat.term)?;
+        self.update(|state, txn| {
+            if heartbeat.term < state.term {
+                return Ok(HeartbeatResponse {
+                    term: state.term,
+                    success: false,
+                    last_seq: self.log_end(txn)?.0,
+                });
+            }
+            if state.role != Role::Follower {
+                self.step_down(state, heartbeat.term);
+            }
+            state.members = heartbeat.members.clone();
+            state.leader = Some(heartbeat.leader);
+            state.reset_timer(self.election_timeout);
+            let success = self.append_entries(txn, heartbeat)?;
+            Ok(HeartbeatResponse {
+                term: state.term,
+                success,
+                last_seq: self.log_end(txn)?.0,
+            })
+        })
+    }
+
+    /// Stores the entries sent by the leader if they follow on from the log of the node,
+    /// dropping the ones they conflict with, and applies the ones the leader committed
+    fn append_entries(&self, txn: &mut RwTxn, heartbeat: &Heartbeat) -> Result<bool, GraphError> {
+        let applied = self.storage.wal.last_seq(txn)?;
+        // entries replicated from the leader's log file meanwhile
+        self.log_db.delete_range(txn, &(..=applied))?;
+        match self.matches(txn, heartbeat.prev_seq, heartbeat.prev_term)? {
+            Some(true) => {}
+            // the entry isn't committed, nor are the ones after it
+            Some(false) => {
+                self.log_db.delete_range(txn, &(heartbeat.prev_seq..))?;
+                return Ok(false);
+            }
+            None => return Ok(false),
+        }
+        for entry in heartbeat.entries.iter() {
+            if entry.seq <= applied {
+                continue;
+            }
+            match self.log_db.get(txn, &entry.seq)? {
+                Some(bytes) if bincode::deserialize::<WalEntry>(bytes)?.term == entry.term => {
+                    continue
+                }
+                Some(_) => {
+                    self.log_db.delete_range(txn, &(entry.seq..))?;
+                }
+                None => {}
+            }
+            self.log_db
+                .put(txn, &entry.seq, &bincode::serialize(entry)?)?;
+        }
+
+        // the entries after the ones sent may still be those of an earlier leader
+        let verified = heartbeat.prev_seq + heartbeat.entries.len() as u64;
+        let commit = heartbeat.commit_seq.min(verified);
+        if commit > applied {
+            let entries = self.log_entries(txn, applied + 1..=commit)?;
+            self.storage.apply_replicated_in(txn, &entries)?;
+            self.log_db.delete_range(txn, &(..=commit))?;
+        }
+        Ok(true)
+    }
+
+    /// Whether the log of the node has the entry `seq` with the given term, `None` if it
+    /// doesn't reach it
+    fn matches(&self, txn: &RoTxn, seq: u64, term: u64) -> Result<Option<bool>, GraphError> {
+        let applied = self.storage.wal.last_seq(txn)?;
+        // applied entries are committed, so they are the same on every node
+        if seq < applied {
+            return Ok(Some(true));
+        }
+        if seq == applied {
+            return Ok(Some(self.storage.wal.last_term(txn)? == term));
+        }
+        match self.log_db.get(txn, &seq)? {
+            Some(bytes) => Ok(Some(bincode::deserialize::<WalEntry>(bytes)?.term == term)),
+            None => Ok(None),
+        }
+    }
+
+    /// seq and term of the last entry of the node's log
+    fn log_end(&self, txn: &RoTxn) -> Result<(u64, u64), GraphError> {
+        match self.log_db.last(txn)? {
+            Some((seq, bytes)) => Ok((seq, bincode::deserialize::<WalEntry>(bytes)?.term)),
+            None => Ok((
+                self.storage.wal.last_seq(txn)?,
+                self.storage.wal.last_term(txn)?,
+            )),
+        }
+    }
+
+    fn log_entries(
+        &self,
+        txn: &RoTxn,
+        range: std::ops::RangeInclusive<u64>,
+    ) -> Result<Vec<WalEntry>, GraphError> {
+        self.log_db
+            .range(txn, &range)?
+            .map(|result| Ok(bincode::deserialize::<WalEntry>(result?.1)?))
+            .collect()
+    }
+
+    /// Stops leading, so writes waiting on the cluster give up
+    fn demote(&self, state: &mut RaftState) {
+        if state.role == Role::Leader {
+            println!(
+                "Node {} stepped down as leader in term {}",
+                self.id, state.term
+            );
+        }
+        state.role = Role::Follower;
+        state.ready = false;
+        state.match_seq.clear();
+        self.acked.notify_all();
+    }
+
+    /// Becomes a follower in `term`, which may be the current one
+    fn step_down(&self, state: &mut RaftState, term: u64) {
+        if term > state.term {
+            state.term = term;
+            state.voted_for = None;
+            state.leader = None;
+        }
+        self.demote(state);
+    }
+
+    /// Steps down to follow a newer term. The node stops leading before waiting on the
+    /// write transaction the term is saved in, which a write it is committing holds.
+    fn observe_term(&self, term: u64) -> Result<(), GraphError> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if term <= state.term {
+                return Ok(());
+            }
+            self.demote(&mut state);
+        }
+        self.update(|state, _| {
+            self.step_down(state, term);
+            Ok(())
+        })
+    }
+
+    /// Changes the state within a write transaction, saving what has to survive a
+    /// restart in it. The transaction is taken before the lock on the state, as it is by
+    /// writes committed through the node.
+    fn update<R, F>(&self, f: F) -> Result<R, GraphError>
+    where
+        F: FnOnce(&mut RaftState, &mut RwTxn) -> Result<R, GraphError>,
+    {
+        let _hold = self.storage.map_size.hold();
+        let mut wtxn = self.storage.graph_env.write_txn()?;
+        let mut state = self.state.lock().unwrap();
+        let before = state.persistent();
+        let result = f(&mut state, &mut wtxn)?;
+        let after = state.persistent();
+        if after != before {
+            self.meta_db
+                .put(&mut wtxn, STATE_KEY, &bincode::serialize(&after)?)?;
+        }
+        wtxn.commit()?;
+        Ok(result)
+    }
+
+    fn last_seq(&self) -> Result<u64, GraphError> {
+        let _hold = self.storage.map_size.hold();
+        let txn = self.storage.graph_env.read_txn()?;
+        self.storage.wal.last_seq(&txn)
+    }
+
+    /// Sends the entries of the transaction to the followers and commits it once a
+    /// majority of the cluster stored them
+    fn commit_replicated(&self, txn: RwTxn) -> Result<(), GraphError> {
+        let mut state = self.state.lock().unwrap();
+        if state.role != Role::Leader {
+            return Err(state.not_leader());
+        }
+        let term = state.term;
+        let entries = self
+            .storage
+            .wal
+            .pending_db
+            .range(&txn, &(state.committed.0 + 1..))?
+            .map(|result| Ok(bincode::deserialize::<WalEntry>(result?.1)?))
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        let Some(last) = entries.last().map(|entry| (entry.seq, entry.term)) else {
+            return Ok(txn.commit()?);
+        };
+        state.inflight = entries;
+        drop(state);
+        self.wake.notify_one();
+
+        let state = self.state.lock().unwrap();
+        let (mut state, result) = self
+            .acked
+            .wait_timeout_while(state, Self::COMMIT_TIMEOUT, |state| {
+                state.role == Role::Leader
+                    && state.term == term
+                    && state.replicated(self.id, last.0) < state.majority()
+            })
+            .unwrap();
+        state.inflight.clear();
+        if state.role != Role::Leader || state.term != term {
+            return Err(state.not_leader());
+        }
+        if result.timed_out() {
+            // followers may have stored the entries, so none may be written under the same
+            // seq and term again
+            self.step_down(&mut state, term);
+            return Err(GraphError::New(format!(
+                "Entry {} wasn't stored by a majority of the cluster within {:?}, the write was rolled back",
+                last.0,
+                Self::COMMIT_TIMEOUT
+            )));
+        }
+        if let Err(e) = txn.commit() {
+            self.step_down(&mut state, term);
+            return Err(e.into());
+        }
+        state.committed = last;
+        drop(state);
+        // lets the followers apply the entries
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// Commits the entries of earlier terms the new leader has, by journaling an entry of
+    /// its term after them, and takes writes once they are
+    fn establish(&self, term: u64) -> Result<(), GraphError> {
+        let _hold = self.storage.map_size.hold();
+        let mut txn = self.storage.graph_env.write_txn()?;
+        let applied = self.storage.wal.last_seq(&txn)?;
+        let entries = self.log_entries(&txn, applied + 1..=u64::MAX)?;
+        self.storage.apply_replicated_in(&mut txn, &entries)?;
+        self.log_db.clear(&mut txn)?;
+        self.storage.wal.set_term(term);
+        self.storage.wal.log(&mut txn, || Ok(WalOp::Noop))?;
+        self.commit_replicated(txn)?;
+
+        let mut state = self.state.lock().unwrap();
+        if state.role == Role::Leader && state.term == term {
+            state.ready = true;
+            println!("Node {} takes writes in term {}", self.id, term);
+        }
+        Ok(())
+    }
+
+    /// Sends heartbeats while leading, and stands for election once the leader has been
+    /// silent for longer than the election timeout
+    async fn run_elections<T: Transport>(self: &Arc<Self>, transport: &T) {
+        while !self.stopped.load(Ordering::Relaxed) {
+            tokio::select! {
+                _ = tokio::time::sleep(Self::HEARTBEAT_INTERVAL) => {}
+                _ = self.wake.notified() => {}
+            }
+            let (role, timed_out, member) = {
+                let state = self.state.lock().unwrap();
+                (
+                    state.role,
+                    state.last_contact.elapsed() >= state.timeout,
+                    state.members.iter().any(|member| member.id == self.id),
+                )
+            };
+            let result = match role {
+                _ if self.stopped.load(Ordering::Relaxed) => Ok(()),
+                Role::Leader => self.send_heartbeats(transport).await,
+                _ if timed_out && member => self.stand_for_election(transport).await,
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                eprintln!("Cluster error on node {}: {}", self.id, e);
+            }
+        }
+    }
+
+    async fn send_heartbeats<T: Transport>(&self, transport: &T) -> Result<(), GraphError> {
+        let (heartbeat, peers) = {
+            let state = self.state.lock().unwrap();
+            let heartbeat = Heartbeat {
+                term: state.term,
+                leader: self.id,
+                members: state.members.clone(),
+                prev_seq: state.committed.0,
+                prev_term: state.committed.1,
+                entries: state.inflight.clone(),
+                commit_seq: state.committed.0,
+            };
+            (heartbeat, self.peers(&state))
+        };
+        let responses = join_all(peers.iter().map(|peer| {
+            call::<_, _, HeartbeatResponse>(
+                transport,
+                &peer.address,
+                HEARTBEAT_PATH,
+                self.api_key.as_deref(),
+                &heartbeat,
+            )
+        }))
+        .await;
+
+        let newer_term = {
+            let mut state = self.state.lock().unwrap();
+            if state.role != Role::Leader || state.term != heartbeat.term {
+                return Ok(());
+            }
+            // unreachable members are retried with the next heartbeat, and members too far
+            // behind catch up from the log file
+            let stored = heartbeat.prev_seq + heartbeat.entries.len() as u64;
+            let mut newer_term = None;
+            for (peer, response) in peers.iter().zip(responses) {
+                match response {
+                    Ok(response) if response.term > state.term => {
+                        newer_term = Some(response.term);
+                    }
+                    Ok(response) if response.success => {
+                        state.match_seq.insert(peer.id, stored);
+                    }
+                    _ => {}
+                }
+            }
+            self.acked.notify_all();
+            newer_term
+        };
+        match newer_term {
+            Some(term) => self.observe_term(term),
+            None => Ok(()),
+        }
+    }
+
+    async fn stand_for_election<T: Transport>(
+        self: &Arc<Self>,
+        transport: &T,
+    ) -> Result<(), GraphError> {
+        let (request, peers) = self.update(|state, txn| {
+            state.term += 1;
+            state.role = Role::Candidate;
+            state.voted_for = Some(self.id);
+            state.leader = None;
+            state.reset_timer(self.election_timeout);
+            let (last_seq, last_term) = self.log_end(txn)?;
+            let request = VoteRequest {
+                term: state.term,
+                candidate: self.id,
+                last_seq,
+                last_term,
+            };
+            Ok((request, self.peers(state)))
+        })?;
+        let responses = join_all(peers.iter().map(|peer| {
+            call::<_, _, VoteResponse>(
+                transport,
+                &peer.address,
+                VOTE_PATH,
+                self.api_key.as_deref(),
+                &request,
+            )
+        }))
+        .await;
+
+        let committed = {
+            let _hold = self.storage.map_size.hold();
+            let txn = self.storage.graph_env.read_txn()?;
+            (
+                self.storage.wal.last_seq(&txn)?,
+                self.storage.wal.last_term(&txn)?,
+            )
+        };
+        let mut state = self.state.lock().unwrap();
+        if state.role != Role::Candidate || state.term != request.term {
+            return Ok(());
+        }
+        // the candidate votes for itself
+        let mut votes = 1;
+        let mut newer_term = None;
+        for response in responses.into_iter().flatten() {
+            if response.term > state.term {
+                newer_term = Some(response.term);
+            }
+            if response.granted {
+                votes += 1;
+            }
+        }
+        if let Some(term) = newer_term {
+            drop(state);
+            return self.observe_term(term);
+        }
+        if votes >= state.majority() {
+            state.role = Role::Leader;
+            state.leader = Some(self.id);
+            state.match_seq.clear();
+            state.ready = false;
+            state.committed = committed;
+            state.inflight.clear();
+            println!(
+                "Node {} elected leader for term {} with {} of {} votes",
+                self.id,
+                state.term,
+                votes,
+                state.members.len()
+            );
+            // waits on the followers, which the heartbeats of this thread reach
+            let node = Arc::clone(self);
+            let term = state.term;
+            std::thread::spawn(move || {
+                if let Err(e) = node.establish(term) {
+                    eprintln!(
+                        "Node {} couldn't take writes in term {}: {}",
+                        node.id, term, e
+                    );
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Replicates the log file of the current leader while the node follows one
+    async fn follow_leader<T: Transport + Clone>(&self, transport: &T) {
+        while !self.stopped.load(Ordering::Relaxed) {
+            let Some(leader) = self.leader_to_follow() else {
+                tokio::time::sleep(Self::HEARTBEAT_INTERVAL).await;
+                continue;
+            };
+            let result = async {
+                let addr = tokio::net::lookup_host(&leader)
+                    .await?
+                    .next()
+                    .ok_or_else(|| GraphError::New(format!("Couldn't resolve {}", leader)))?;
+                let replica = Replica::new(
+                    addr,
+                    self.api_key.clone(),
+                    Arc::clone(&self.storage),
+                    transport.clone(),
+                );
+                tokio::select! {
+                    result = replica.follow() => result,
+                    _ = self.leader_changed(&leader) => Ok(()),
+                }
+            }
+            .await;
+            if let Err(e) = result {
+                eprintln!("Lost connection to leader {}: {}", leader, e);
+                tokio::time::sleep(Replica::<T>::RETRY_INTERVAL).await;
+            }
+        }
+    }
+
+    /// Address of the leader while the node is one of its followers
+    fn leader_to_follow(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        match (state.role, state.leader) {
+            _ if self.stopped.load(Ordering::Relaxed) => None,
+            (Role::Follower, Some(leader)) if leader != self.id => state.address_of(leader),
+            _ => None,
+        }
+    }
+
+    async fn leader_changed(&self, leader: &str) {
+        loop {
+            tokio::time::sleep(Self::HEARTBEAT_INTERVAL).await;
+            if self.leader_to_follow().as_deref() != Some(leader) {
+                return;
+            }
+        }
+    }
+
+    fn peers(&self, state: &RaftState) -> Vec<ClusterMember> {
+        state
+            .members
+            .iter()
+            .filter(|member| member.id != self.id)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Quorum for ClusterNode {
+    fn commit(&self, txn: RwTxn) -> Result<(), GraphError> {
+        // other tasks queued on the blocked worker of the runtime are handed to another
+        // thread, so they aren't held up for as long as the write waits
+        match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                block_in_place(|| self.commit_replicated(txn))
+            }
+            _ => self.commit_replicated(txn),
+        }
+    }
+
+    fn is_leader(&self) -> bool {
+        self.role() == Role::Leader
+    }
+}
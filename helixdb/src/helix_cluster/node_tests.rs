@@ -0,0 +1,447 @@
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    helix_cluster::{
+        node::ClusterNode,
+        rpc::{call_with_timeout, Heartbeat, Role, VoteRequest},
+    },
+    helix_engine::{
+        graph_core::{
+            config::{ClusterConfig, ClusterMember, Config},
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+            ops::{
+                g::G,
+                source::{add_n::AddNAdapter, n_from_type::NFromTypeAdapter},
+                tr_val::TraversalVal,
+            },
+        },
+        storage_core::{
+            storage_methods::StorageMethods,
+            wal::{WalEntry, WalOp},
+        },
+        types::GraphError,
+    },
+    helix_gateway::{
+        connection::limits::ConnectionLimits,
+        gateway::HelixGateway,
+        router::router::{HandlerInput, HelixRouter},
+    },
+    helix_runtime::tokio_runtime::TokioRuntime,
+    helix_storage::heed3::RwTxn,
+    helix_transport::tokio_transport::TokioTransport,
+    props,
+    protocol::{items::Node, response::Response, value::Value},
+};
+
+fn engine() -> Arc<HelixGraphEngine> {
+    let mut config = Config::default();
+    config.wal.enabled = true;
+    Arc::new(
+        HelixGraphEngine::new(HelixGraphEngineOpts {
+            config,
+            ..HelixGraphEngineOpts::in_memory()
+        })
+        .unwrap(),
+    )
+}
+
+/// An address nothing is listening on
+fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+fn config(node_id: u64, members: &[ClusterMember]) -> ClusterConfig {
+    ClusterConfig {
+        node_id,
+        address: members[node_id as usize - 1].address.clone(),
+        members: members.to_vec(),
+        api_key: None,
+        election_timeout_ms: Some(300),
+    }
+}
+
+fn members(size: u64) -> Vec<ClusterMember> {
+    (1..=size)
+        .map(|id| ClusterMember {
+            id,
+            address: free_addr().to_string(),
+        })
+        .collect()
+}
+
+fn add_person(input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {
+    input.run_write(response, add_person_in_txn)
+}
+
+fn add_person_in_txn(
+    input: &HandlerInput,
+    txn: &mut RwTxn,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let name: String = sonic_rs::from_slice(&input.request.body)?;
+    G::new_mut(Arc::clone(&input.graph.storage), txn)
+        .add_n("person", Some(props! { "name" => name.clone() }), None)
+        .collect_to_val();
+    response.body = sonic_rs::to_vec(&name)?;
+    Ok(())
+}
+
+fn people(graph: &HelixGraphEngine) -> Vec<String> {
+    let txn = graph.storage.graph_env.read_txn().unwrap();
+    let mut names = G::new(Arc::clone(&graph.storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>()
+        .into_iter()
+        .filter_map(|val| match val {
+            TraversalVal::Node(node) => match node.properties?.remove("name")? {
+                Value::String(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+struct Member {
+    node: Arc<ClusterNode>,
+    graph: Arc<HelixGraphEngine>,
+    _gateway: HelixGateway<TokioRuntime, TokioTransport>,
+}
+
+/// Starts the node of a member of the cluster behind a gateway of its own
+async fn start(id: u64, members: &[ClusterMember]) -> Member {
+    let graph = engine();
+    let config = config(id, members);
+    let node = Arc::new(ClusterNode::new(Arc::clone(&graph.storage), &config).unwrap());
+    let mut router = HelixRouter::new(None, None);
+    router.add_route("POST", "/add_person", add_person);
+    let write_routes = HashSet::from([("POST".to_string(), "/add_person".to_string())]);
+    let router = router.with_cluster(Arc::clone(&node), write_routes);
+    let gateway = HelixGateway::with_router(
+        &config.address,
+        Arc::clone(&graph),
+        2,
+        router,
+        ConnectionLimits::default(),
+        TokioRuntime,
+        TokioTransport,
+    )
+    .await;
+    drop(gateway.connection_handler.accept_conns().await.unwrap());
+    node.start(TokioTransport).unwrap();
+    Member {
+        node,
+        graph,
+        _gateway: gateway,
+    }
+}
+
+async fn cluster(size: u64) -> Vec<Member> {
+    let members = members(size);
+    let mut cluster = Vec::new();
+    for member in members.iter() {
+        cluster.push(start(member.id, &members).await);
+    }
+    cluster
+}
+
+async fn eventually<F: FnMut() -> bool>(what: &str, mut check: F) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !check() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// Waits for one of the given nodes to lead the cluster and take writes
+async fn leader<'a>(members: &[&'a Member]) -> &'a Member {
+    let mut leader = None;
+    eventually("a leader", || {
+        leader = members
+            .iter()
+            .find(|member| member.node.check_leader().is_ok())
+            .copied();
+        leader.is_some()
+    })
+    .await;
+    leader.unwrap()
+}
+
+async fn write(member: &Member, name: &str) -> Result<(), GraphError> {
+    call_with_timeout::<_, _, String>(
+        &TokioTransport,
+        member.node.address(),
+        "/add_person",
+        None,
+        &name,
+        ClusterNode::COMMIT_TIMEOUT * 2,
+    )
+    .await
+    .map(|_| ())
+}
+
+fn stop(cluster: &[Member]) {
+    for member in cluster {
+        member.node.stop();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_elects_a_single_leader() {
+    let cluster = cluster(3).await;
+    let leader = leader(&cluster.iter().collect::<Vec<_>>()).await;
+
+    eventually("the followers to know the leader", || {
+        cluster
+            .iter()
+            .all(|member| member.node.leader().as_deref() == Some(leader.node.address()))
+    })
+    .await;
+    let leaders = cluster
+        .iter()
+        .filter(|member| member.node.role() == Role::Leader)
+        .count();
+    assert_eq!(leaders, 1);
+    stop(&cluster);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_writes_are_replicated() {
+    let cluster = cluster(3).await;
+    let leader = leader(&cluster.iter().collect::<Vec<_>>()).await;
+
+    write(leader, "alice").await.unwrap();
+    assert_eq!(people(&leader.graph), ["alice"]);
+    eventually("the followers to apply the write", || {
+        cluster
+            .iter()
+            .all(|member| people(&member.graph) == ["alice"])
+    })
+    .await;
+
+    // followers don't take writes
+    for follower in cluster
+        .iter()
+        .filter(|member| member.node.id() != leader.node.id())
+    {
+        assert!(write(follower, "bob").await.is_err());
+    }
+    stop(&cluster);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_failover() {
+    let cluster = cluster(3).await;
+    let old = leader(&cluster.iter().collect::<Vec<_>>()).await;
+    let old_term = old.node.status().unwrap().term;
+    write(old, "alice").await.unwrap();
+
+    old.node.stop();
+    let rest = cluster
+        .iter()
+        .filter(|member| member.node.id() != old.node.id())
+        .collect::<Vec<_>>();
+    let new = leader(&rest).await;
+    assert!(new.node.status().unwrap().term > old_term);
+    // the acknowledged write survives the leader
+    assert_eq!(people(&new.graph), ["alice"]);
+
+    write(new, "bob").await.unwrap();
+    eventually("the follower to apply the writes", || {
+        rest.iter()
+            .all(|member| people(&member.graph) == ["alice", "bob"])
+    })
+    .await;
+    assert!(write(old, "carol").await.is_err());
+    stop(&cluster);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_write_rolled_back_without_quorum() {
+    let cluster = cluster(3).await;
+    let leader = leader(&cluster.iter().collect::<Vec<_>>()).await;
+    write(leader, "alice").await.unwrap();
+    for follower in cluster
+        .iter()
+        .filter(|member| member.node.id() != leader.node.id())
+    {
+        follower.node.stop();
+    }
+
+    assert!(write(leader, "bob").await.is_err());
+    assert_eq!(people(&leader.graph), ["alice"]);
+    // entries of the term the write was sent in may be on other nodes
+    assert!(leader.node.check_leader().is_err());
+    stop(&cluster);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_lagging_member_catches_up() {
+    let members = members(3);
+    let mut cluster = vec![start(1, &members).await, start(2, &members).await];
+    let leader = leader(&cluster.iter().collect::<Vec<_>>()).await;
+    write(leader, "alice").await.unwrap();
+    write(leader, "bob").await.unwrap();
+
+    // replicates the leader's log file up to where the entries it is sent follow on
+    cluster.push(start(3, &members).await);
+    eventually("the new member to catch up", || {
+        people(&cluster[2].graph) == ["alice", "bob"]
+    })
+    .await;
+    let leader = self::leader(&cluster.iter().collect::<Vec<_>>()).await;
+    write(leader, "carol").await.unwrap();
+    eventually("the new member to apply the write", || {
+        people(&cluster[2].graph) == ["alice", "bob", "carol"]
+    })
+    .await;
+    stop(&cluster);
+}
+
+fn entry(graph: &HelixGraphEngine, seq: u64, term: u64, id: u128) -> WalEntry {
+    let node = Node {
+        id,
+        label: "person".to_string(),
+        properties: None,
+        score: None,
+    };
+    WalEntry {
+        seq,
+        term,
+        timestamp: 0,
+        op: WalOp::put_node(&graph.storage.encryption, &node).unwrap(),
+    }
+}
+
+fn heartbeat(
+    members: &[ClusterMember],
+    term: u64,
+    leader: u64,
+    prev: (u64, u64),
+    entries: Vec<WalEntry>,
+    commit_seq: u64,
+) -> Heartbeat {
+    Heartbeat {
+        term,
+        leader,
+        members: members.to_vec(),
+        prev_seq: prev.0,
+        prev_term: prev.1,
+        entries,
+        commit_seq,
+    }
+}
+
+fn has_node(graph: &HelixGraphEngine, id: u128) -> bool {
+    let txn = graph.storage.graph_env.read_txn().unwrap();
+    match graph.storage.get_node(&txn, &id) {
+        Ok(_) => true,
+        Err(GraphError::NodeNotFound) => false,
+        Err(e) => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn test_entries_applied_once_committed() {
+    let members = members(3);
+    let graph = engine();
+    let node = ClusterNode::new(Arc::clone(&graph.storage), &config(1, &members)).unwrap();
+
+    let entries = vec![entry(&graph, 1, 1, 1), entry(&graph, 2, 1, 2)];
+    let response = node
+        .on_heartbeat(&heartbeat(&members, 1, 2, (0, 0), entries, 0))
+        .unwrap();
+    assert!(response.success);
+    assert_eq!(response.last_seq, 2);
+    assert!(!has_node(&graph, 1) && !has_node(&graph, 2));
+
+    let response = node
+        .on_heartbeat(&heartbeat(&members, 1, 2, (2, 1), Vec::new(), 2))
+        .unwrap();
+    assert!(response.success);
+    assert!(has_node(&graph, 1) && has_node(&graph, 2));
+}
+
+#[test]
+fn test_conflicting_entries_are_dropped() {
+    let members = members(3);
+    let graph = engine();
+    let node = ClusterNode::new(Arc::clone(&graph.storage), &config(1, &members)).unwrap();
+
+    // entries of a leader that were never committed
+    let entries = vec![entry(&graph, 1, 1, 1), entry(&graph, 2, 1, 2)];
+    assert!(
+        node.on_heartbeat(&heartbeat(&members, 1, 2, (0, 0), entries, 1))
+            .unwrap()
+            .success
+    );
+    assert!(has_node(&graph, 1));
+
+    // a newer leader doesn't have the second one
+    let entries = vec![entry(&graph, 2, 2, 3)];
+    let response = node
+        .on_heartbeat(&heartbeat(&members, 2, 3, (1, 1), entries, 2))
+        .unwrap();
+    assert!(response.success);
+    assert_eq!(response.term, 2);
+    assert!(!has_node(&graph, 2));
+    assert!(has_node(&graph, 3));
+
+    // entries that don't follow on from the log are rejected
+    let entries = vec![entry(&graph, 6, 2, 4)];
+    let response = node
+        .on_heartbeat(&heartbeat(&members, 2, 3, (5, 2), entries, 6))
+        .unwrap();
+    assert!(!response.success);
+    assert_eq!(response.last_seq, 2);
+    assert!(!has_node(&graph, 4));
+
+    // and so are those of deposed leaders
+    let entries = vec![entry(&graph, 3, 1, 5)];
+    let response = node
+        .on_heartbeat(&heartbeat(&members, 1, 2, (2, 1), entries, 3))
+        .unwrap();
+    assert!(!response.success);
+    assert_eq!(response.term, 2);
+    assert!(!has_node(&graph, 5));
+}
+
+#[test]
+fn test_votes_for_up_to_date_candidates() {
+    let members = members(3);
+    let graph = engine();
+    let node = ClusterNode::new(Arc::clone(&graph.storage), &config(1, &members)).unwrap();
+    let entries = vec![entry(&graph, 1, 2, 1)];
+    node.on_heartbeat(&heartbeat(&members, 2, 2, (0, 0), entries, 1))
+        .unwrap();
+
+    let vote = |candidate, term, last_seq, last_term| {
+        node.on_vote(&VoteRequest {
+            term,
+            candidate,
+            last_seq,
+            last_term,
+        })
+        .unwrap()
+    };
+    // a longer log of an older term is less up to date
+    let response = vote(2, 3, 5, 1);
+    assert!(!response.granted);
+    assert_eq!(response.term, 3);
+    assert!(vote(3, 3, 1, 2).granted);
+    // one vote per term
+    assert!(!vote(2, 3, 1, 2).granted);
+    assert!(vote(3, 3, 1, 2).granted);
+    // nodes outside of the cluster don't get votes
+    assert!(!vote(7, 4, 1, 2).granted);
+}
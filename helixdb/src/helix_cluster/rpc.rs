@@ -0,0 +1,190 @@
+use crate::helix_engine::{
+    graph_core::config::ClusterMember, storage_core::wal::WalEntry, types::GraphError,
+};
+use crate::helix_gateway::auth::auth::API_KEY_HEADER;
+use crate::helix_transport::Transport;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+/// Path of the status of the cluster, as seen by the node
+pub const CLUSTER_PATH: &str = "/cluster";
+/// Path candidates ask the other nodes for their vote on
+pub const VOTE_PATH: &str = "/cluster/vote";
+/// Path the leader asserts its leadership on
+pub const HEARTBEAT_PATH: &str = "/cluster/heartbeat";
+/// Path members are added to and removed from the cluster on
+pub const MEMBERS_PATH: &str = "/cluster/members";
+/// Header with the address of the leader on writes rejected by followers
+pub const LEADER_HEADER: &str = "X-Helix-Leader";
+/// Longest time a node waits for the answer of another
+pub const RPC_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Whether the request is for the cluster rather than the graph
+pub fn is_cluster_path(path: &str) -> bool {
+    path == CLUSTER_PATH || path.starts_with("/cluster/")
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoteRequest {
+    pub term: u64,
+    pub candidate: u64,
+    /// seq and term of the last entry of the candidate's log, nodes only vote for
+    /// candidates whose log is at least as up to date as theirs
+    pub last_seq: u64,
+    pub last_term: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoteResponse {
+    pub term: u64,
+    pub granted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Heartbeat {
+    pub term: u64,
+    pub leader: u64,
+    pub members: Vec<ClusterMember>,
+    /// seq and term of the entry of the leader's log `entries` follow on from
+    pub prev_seq: u64,
+    pub prev_term: u64,
+    /// Entries of the write the leader is committing, empty otherwise
+    pub entries: Vec<WalEntry>,
+    /// seq of the last entry the leader committed
+    pub commit_seq: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HeartbeatResponse {
+    pub term: u64,
+    /// Whether the follower stored the entries, as they follow on from its log
+    pub success: bool,
+    /// seq of the last entry of the follower's log
+    pub last_seq: u64,
+}
+
+/// Body of the admin requests changing the members, `address` is only needed to add one
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemberRequest {
+    pub id: u64,
+    pub address: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClusterStatus {
+    pub id: u64,
+    pub role: Role,
+    pub term: u64,
+    pub leader: Option<u64>,
+    pub last_seq: u64,
+    pub members: Vec<MemberStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemberStatus {
+    pub id: u64,
+    pub address: String,
+    /// Last entry the member is known to have stored, only tracked by the leader
+    pub last_seq: Option<u64>,
+}
+
+/// Sends `body` as JSON to `path` on the node at `address` and parses its JSON answer
+pub async fn call<T: Transport, Req: Serialize, Resp: DeserializeOwned>(
+    transport: &T,
+    address: &str,
+    path: &str,
+    api_key: Option<&str>,
+    body: &Req,
+) -> Result<Resp, GraphError> {
+    call_with_timeout(transport, address, path, api_key, body, RPC_TIMEOUT).await
+}
+
+/// [`call`] waiting up to `timeout` for the answer, for requests doing more work than
+/// the ones between the nodes of a cluster
+pub async fn call_with_timeout<T: Transport, Req: Serialize, Resp: DeserializeOwned>(
+    transport: &T,
+    address: &str,
+    path: &str,
+    api_key: Option<&str>,
+    body: &Req,
+    timeout: Duration,
+) -> Result<Resp, GraphError> {
+    tokio::time::timeout(timeout, send(transport, address, path, api_key, body))
+        .await
+        .map_err(|_| GraphError::New(format!("Timed out waiting for {}", address)))?
+}
+
+async fn send<T: Transport, Req: Serialize, Resp: DeserializeOwned>(
+    transport: &T,
+    address: &str,
+    path: &str,
+    api_key: Option<&str>,
+    body: &Req,
+) -> Result<Resp, GraphError> {
+    let addr = tokio::net::lookup_host(address)
+        .await?
+        .next()
+        .ok_or_else(|| GraphError::New(format!("Couldn't resolve {}", address)))?;
+    let body = sonic_rs::to_vec(body)?;
+    let stream = transport.connect(addr).await?;
+    let mut stream = BufReader::new(stream);
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        path,
+        address,
+        body.len()
+    );
+    if let Some(api_key) = api_key {
+        request.push_str(&format!("{}: {}\r\n", API_KEY_HEADER, api_key));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.flush().await?;
+
+    // the status line followed by the headers
+    let mut status_line = String::new();
+    stream.read_line(&mut status_line).await?;
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if stream.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = Vec::new();
+    match content_length {
+        Some(len) => {
+            body.resize(len, 0);
+            stream.read_exact(&mut body).await?;
+        }
+        None => {
+            stream.read_to_end(&mut body).await?;
+        }
+    }
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(GraphError::New(format!(
+            "{} answered {}: {}",
+            address,
+            status_line.trim(),
+            String::from_utf8_lossy(&body).trim()
+        )));
+    }
+    Ok(sonic_rs::from_slice(&body)?)
+}
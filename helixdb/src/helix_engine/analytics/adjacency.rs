@@ -0,0 +1,155 @@
+use crate::helix_storage::heed3::RoTxn;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::helix_engine::{
+    stats::stats::Direction, storage_core::storage_core::HelixGraphStorage, types::GraphError,
+};
+
+/// Edges between the nodes of a read snapshot in compressed sparse row form.
+///
+/// Nodes are numbered densely in the order of their ids, so the edges of node `i` are
+/// `out_targets[out_offsets[i]..out_offsets[i + 1]]` and take 4 bytes each instead of
+/// the 36 bytes of an adjacency entry. Edges to vectors are left out.
+pub struct Adjacency {
+    /// Id of the snapshot the adjacency was read from
+    pub snapshot: u64,
+    pub edge_label: Option<String>,
+    pub node_ids: Vec<u128>,
+    out_offsets: Vec<u32>,
+    out_targets: Vec<u32>,
+    in_offsets: Vec<u32>,
+    in_targets: Vec<u32>,
+}
+
+impl Adjacency {
+    /// Reads the nodes and the edges with the given label, or all edges if `None`
+    pub fn build(
+        storage: &HelixGraphStorage,
+        txn: &RoTxn,
+        edge_label: Option<&str>,
+    ) -> Result<Adjacency, GraphError> {
+        let mut node_ids = Vec::with_capacity(storage.nodes_db.len(txn)? as usize);
+        for result in storage.nodes_db.lazily_decode_data().iter(txn)? {
+            let (id, _) = result?;
+            node_ids.push(id);
+        }
+        let index = node_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i as u32))
+            .collect::<HashMap<_, _>>();
+
+        let label_hash = edge_label.map(|label| storage.dictionary.label_key(label));
+        let mut edges = Vec::new();
+        for result in storage.out_edges_db.iter(txn)? {
+            let (key, value) = result?;
+            if label_hash.is_some_and(|label_hash| key[16..20] != label_hash) {
+                continue;
+            }
+            let from = u128::from_be_bytes(key[0..16].try_into().unwrap());
+            let (to, _) = HelixGraphStorage::unpack_adj_edge_data(value)?;
+            if let (Some(from), Some(to)) = (index.get(&from), index.get(&to)) {
+                edges.push((*from, *to));
+            }
+        }
+        for (from, label, (to, _)) in storage.adjacency_blocks.all_edges(txn, Direction::Out)? {
+            if label_hash.is_some_and(|label_hash| label != label_hash) {
+                continue;
+            }
+            if let (Some(from), Some(to)) = (index.get(&from), index.get(&to)) {
+                edges.push((*from, *to));
+            }
+        }
+
+        let (out_offsets, out_targets) = Self::compress(node_ids.len(), edges.iter().copied());
+        let (in_offsets, in_targets) =
+            Self::compress(node_ids.len(), edges.iter().map(|(from, to)| (*to, *from)));
+        Ok(Adjacency {
+            snapshot: txn.id() as u64,
+            edge_label: edge_label.map(str::to_string),
+            node_ids,
+            out_offsets,
+            out_targets,
+            in_offsets,
+            in_targets,
+        })
+    }
+
+    /// Groups `(source, target)` pairs by source with a counting sort
+    fn compress(
+        nodes: usize,
+        edges: impl Iterator<Item = (u32, u32)> + Clone,
+    ) -> (Vec<u32>, Vec<u32>) {
+        let mut offsets = vec![0u32; nodes + 1];
+        for (source, _) in edges.clone() {
+            offsets[source as usize + 1] += 1;
+        }
+        for i in 0..nodes {
+            offsets[i + 1] += offsets[i];
+        }
+        let mut next = offsets.clone();
+        let mut targets = vec![0u32; offsets[nodes] as usize];
+        for (source, target) in edges {
+            targets[next[source as usize] as usize] = target;
+            next[source as usize] += 1;
+        }
+        (offsets, targets)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.out_targets.len()
+    }
+
+    /// Nodes the out edges of node `i` lead to
+    #[inline(always)]
+    pub fn out_neighbours(&self, i: usize) -> &[u32] {
+        &self.out_targets[self.out_offsets[i] as usize..self.out_offsets[i + 1] as usize]
+    }
+
+    /// Nodes the in edges of node `i` come from
+    #[inline(always)]
+    pub fn in_neighbours(&self, i: usize) -> &[u32] {
+        &self.in_targets[self.in_offsets[i] as usize..self.in_offsets[i + 1] as usize]
+    }
+}
+
+/// Keeps the most recently built [`Adjacency`] around so repeated analytics over the same
+/// snapshot don't read the whole graph again.
+///
+/// Any write commits a new snapshot, after which the adjacency is rebuilt on next use.
+/// Adjacencies read inside a write transaction are never cached, as its id is reused if
+/// it is aborted.
+#[derive(Default)]
+pub struct AdjacencyCache {
+    cached: Mutex<Option<Arc<Adjacency>>>,
+}
+
+impl AdjacencyCache {
+    pub fn get(
+        &self,
+        storage: &HelixGraphStorage,
+        txn: &RoTxn,
+        edge_label: Option<&str>,
+    ) -> Result<Arc<Adjacency>, GraphError> {
+        let snapshot = txn.id() as u64;
+        if snapshot > storage.graph_env.info().last_txn_id as u64 {
+            return Ok(Arc::new(Adjacency::build(storage, txn, edge_label)?));
+        }
+        let mut cached = self.cached.lock().unwrap();
+        if let Some(adjacency) = cached.as_ref() {
+            if adjacency.snapshot == snapshot && adjacency.edge_label.as_deref() == edge_label {
+                return Ok(Arc::clone(adjacency));
+            }
+        }
+        let adjacency = Arc::new(Adjacency::build(storage, txn, edge_label)?);
+        *cached = Some(Arc::clone(&adjacency));
+        Ok(adjacency)
+    }
+}
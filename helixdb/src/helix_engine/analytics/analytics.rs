@@ -0,0 +1,215 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::collections::{HashMap, VecDeque};
+
+use super::adjacency::Adjacency;
+
+/// Damping factor of [`page_rank`], the probability of following an edge rather than
+/// jumping to a random node
+pub const DEFAULT_DAMPING: f64 = 0.85;
+
+/// Seed of the random order and tie breaks of [`label_propagation`]
+const LABEL_PROPAGATION_SEED: u64 = 0x6c70_6131;
+
+/// PageRank of every node, indexed like [`Adjacency::node_ids`].
+///
+/// The rank of nodes without out edges is spread evenly over all nodes, so the ranks
+/// always sum up to 1.
+pub fn page_rank(adjacency: &Adjacency, iterations: usize, damping: f64) -> Vec<f64> {
+    let n = adjacency.node_count();
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut ranks = vec![1.0 / n as f64; n];
+    let mut next = vec![0.0; n];
+    for _ in 0..iterations {
+        let dangling: f64 = (0..n)
+            .filter(|i| adjacency.out_neighbours(*i).is_empty())
+            .map(|i| ranks[i])
+            .sum();
+        let base = (1.0 - damping) / n as f64 + damping * dangling / n as f64;
+        for (i, rank) in next.iter_mut().enumerate() {
+            *rank = base
+                + damping
+                    * adjacency
+                        .in_neighbours(i)
+                        .iter()
+                        .map(|j| {
+                            let j = *j as usize;
+                            ranks[j] / adjacency.out_neighbours(j).len() as f64
+                        })
+                        .sum::<f64>();
+        }
+        std::mem::swap(&mut ranks, &mut next);
+    }
+    ranks
+}
+
+/// Approximate betweenness centrality of every node, indexed like
+/// [`Adjacency::node_ids`].
+///
+/// Runs Brandes' algorithm from `samples` source nodes spread evenly over the graph and
+/// scales the result up to all nodes. With at least as many samples as nodes the result
+/// is exact.
+pub fn betweenness(adjacency: &Adjacency, samples: usize) -> Vec<f64> {
+    let n = adjacency.node_count();
+    let mut centrality = vec![0.0; n];
+    if n == 0 || samples == 0 {
+        return centrality;
+    }
+    let samples = samples.min(n);
+
+    let mut distance = vec![-1i64; n];
+    let mut paths = vec![0.0f64; n];
+    let mut dependency = vec![0.0f64; n];
+    let mut order = Vec::with_capacity(n);
+    let mut queue = VecDeque::new();
+    for sample in 0..samples {
+        let source = sample * n / samples;
+        distance.fill(-1);
+        paths.fill(0.0);
+        dependency.fill(0.0);
+        order.clear();
+
+        distance[source] = 0;
+        paths[source] = 1.0;
+        queue.push_back(source);
+        while let Some(v) = queue.pop_front() {
+            order.push(v);
+            for w in adjacency.out_neighbours(v) {
+                let w = *w as usize;
+                if distance[w] < 0 {
+                    distance[w] = distance[v] + 1;
+                    queue.push_back(w);
+                }
+                if distance[w] == distance[v] + 1 {
+                    paths[w] += paths[v];
+                }
+            }
+        }
+
+        // accumulate dependencies from the furthest nodes back to the source
+        for w in order.iter().rev() {
+            let w = *w;
+            for v in adjacency.in_neighbours(w) {
+                let v = *v as usize;
+                if distance[v] >= 0 && distance[v] + 1 == distance[w] {
+                    dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+                }
+            }
+            if w != source {
+                centrality[w] += dependency[w];
+            }
+        }
+    }
+
+    let scale = n as f64 / samples as f64;
+    centrality.iter_mut().for_each(|c| *c *= scale);
+    centrality
+}
+
+/// Weakly connected component of every node, indexed like [`Adjacency::node_ids`].
+///
+/// Components are numbered from 0 in the order of their first node.
+pub fn connected_components(adjacency: &Adjacency) -> Vec<u64> {
+    let n = adjacency.node_count();
+    let mut parent = (0..n).collect::<Vec<_>>();
+
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for v in 0..n {
+        for w in adjacency.out_neighbours(v) {
+            let (a, b) = (find(&mut parent, v), find(&mut parent, *w as usize));
+            // keep the lower node as root so roots are the first node of their component
+            match a.cmp(&b) {
+                std::cmp::Ordering::Less => parent[b] = a,
+                std::cmp::Ordering::Greater => parent[a] = b,
+                std::cmp::Ordering::Equal => {}
+            }
+        }
+    }
+
+    let mut component = vec![u64::MAX; n];
+    let mut components = 0;
+    for v in 0..n {
+        let root = find(&mut parent, v);
+        if component[root] == u64::MAX {
+            component[root] = components;
+            components += 1;
+        }
+        component[v] = component[root];
+    }
+    component
+}
+
+/// Community of every node found by label propagation, indexed like
+/// [`Adjacency::node_ids`].
+///
+/// Every node starts in its own community and then, in shuffled order, joins the
+/// community most of its neighbours are in, ignoring edge direction. Ties keep the
+/// current community or else pick one at random. Stops once no node moves or after
+/// `iterations` rounds. The shuffling is seeded, so the same graph always gives the same
+/// communities, numbered from 0 in the order of their first node.
+pub fn label_propagation(adjacency: &Adjacency, iterations: usize) -> Vec<u64> {
+    let n = adjacency.node_count();
+    let mut rng = StdRng::seed_from_u64(LABEL_PROPAGATION_SEED);
+    let mut labels = (0..n as u32).collect::<Vec<_>>();
+    let mut order = (0..n).collect::<Vec<_>>();
+    let mut counts = HashMap::new();
+    let mut candidates = Vec::new();
+    for _ in 0..iterations {
+        order.shuffle(&mut rng);
+        let mut moved = false;
+        for v in order.iter().copied() {
+            counts.clear();
+            for w in adjacency
+                .out_neighbours(v)
+                .iter()
+                .chain(adjacency.in_neighbours(v))
+            {
+                if *w as usize != v {
+                    *counts.entry(labels[*w as usize]).or_insert(0usize) += 1;
+                }
+            }
+            let Some(max) = counts.values().max().copied() else {
+                continue;
+            };
+            if counts.get(&labels[v]) == Some(&max) {
+                continue;
+            }
+            candidates.clear();
+            candidates.extend(
+                counts
+                    .iter()
+                    .filter(|(_, count)| **count == max)
+                    .map(|(label, _)| *label),
+            );
+            // the map iterates in arbitrary order
+            candidates.sort_unstable();
+            labels[v] = candidates[rng.random_range(0..candidates.len())];
+            moved = true;
+        }
+        if !moved {
+            break;
+        }
+    }
+
+    let mut community = vec![u64::MAX; n];
+    let mut communities = 0;
+    labels
+        .iter()
+        .map(|label| {
+            let label = *label as usize;
+            if community[label] == u64::MAX {
+                community[label] = communities;
+                communities += 1;
+            }
+            community[label]
+        })
+        .collect()
+}
@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        analytics::analytics::{
+            betweenness, connected_components, label_propagation, page_rank, DEFAULT_DAMPING,
+        },
+        graph_core::{
+            config::Config,
+            ops::{
+                analytics::analytics::{AnalyticsAdapter, CommunitiesAdapter},
+                g::G,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                },
+                tr_val::{Traversable, TraversalVal},
+            },
+        },
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    },
+    protocol::value::Value,
+};
+
+fn setup_test_db() -> (Arc<HelixGraphStorage>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().to_str().unwrap();
+    let storage = HelixGraphStorage::new(db_path, Config::default()).unwrap();
+    (Arc::new(storage), temp_dir)
+}
+
+/// Adds `count` nodes and an edge for every `(from, to)` pair of node positions
+fn add_graph(
+    storage: &Arc<HelixGraphStorage>,
+    count: usize,
+    edges: &[(usize, usize, &str)],
+) -> Vec<u128> {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let nodes = (0..count)
+        .map(|_| {
+            G::new_mut(Arc::clone(storage), &mut txn)
+                .add_n("person", None, None)
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    for (from, to, label) in edges {
+        G::new_mut(Arc::clone(storage), &mut txn)
+            .add_e(label, None, nodes[*from], nodes[*to], false, EdgeType::Node)
+            .collect_to::<Vec<_>>();
+    }
+    txn.commit().unwrap();
+    nodes
+}
+
+/// Position of every node in the adjacency, which numbers nodes in id order
+fn positions(storage: &HelixGraphStorage, nodes: &[u128]) -> Vec<usize> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    let adjacency = storage.adjacency_cache.get(storage, &txn, None).unwrap();
+    nodes
+        .iter()
+        .map(|id| adjacency.node_ids.iter().position(|n| n == id).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_page_rank() {
+    let (storage, _temp_dir) = setup_test_db();
+    // everyone follows 0, which follows 1, and 4 has no edges at all
+    let nodes = add_graph(
+        &storage,
+        5,
+        &[
+            (1, 0, "follows"),
+            (2, 0, "follows"),
+            (3, 0, "follows"),
+            (0, 1, "follows"),
+        ],
+    );
+    let positions = positions(&storage, &nodes);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let adjacency = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    let ranks = page_rank(&adjacency, 50, DEFAULT_DAMPING);
+    assert!((ranks.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    let rank = |node: usize| ranks[positions[node]];
+    assert!(rank(0) > rank(1));
+    assert!(rank(1) > rank(2));
+    assert!((rank(2) - rank(4)).abs() < 1e-9);
+
+    // the step returns every node, highest rank first
+    let ranked = G::new(Arc::clone(&storage), &txn)
+        .page_rank(Some("follows"), 50)
+        .collect_to::<Vec<_>>();
+    assert_eq!(ranked.len(), 5);
+    assert_eq!(ranked[0].id(), nodes[0]);
+    assert_eq!(ranked[1].id(), nodes[1]);
+    match &ranked[0] {
+        TraversalVal::Node(node) => assert_eq!(
+            node.properties.as_ref().unwrap().get("page_rank"),
+            Some(&Value::F64(rank(0)))
+        ),
+        _ => panic!("Expected Node value"),
+    }
+}
+
+#[test]
+fn test_betweenness() {
+    let (storage, _temp_dir) = setup_test_db();
+    // 0 -> 1 -> 2 -> 3, every path between the ends passes 1 and 2
+    let nodes = add_graph(
+        &storage,
+        4,
+        &[(0, 1, "knows"), (1, 2, "knows"), (2, 3, "knows")],
+    );
+    let positions = positions(&storage, &nodes);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let adjacency = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    let exact = betweenness(&adjacency, 4);
+    let centrality = |node: usize| exact[positions[node]];
+    assert_eq!(centrality(0), 0.0);
+    assert_eq!(centrality(1), 2.0);
+    assert_eq!(centrality(2), 2.0);
+    assert_eq!(centrality(3), 0.0);
+
+    // sampling every node is exact, more samples than nodes don't change that
+    assert_eq!(betweenness(&adjacency, 10), exact);
+}
+
+#[test]
+fn test_connected_components() {
+    let (storage, _temp_dir) = setup_test_db();
+    // {0, 1, 2} through edges in either direction, {3, 4} and {5}, and 4 -> 5 only with
+    // a different label
+    let nodes = add_graph(
+        &storage,
+        6,
+        &[
+            (0, 1, "knows"),
+            (2, 1, "knows"),
+            (3, 4, "knows"),
+            (4, 5, "likes"),
+        ],
+    );
+    let positions = positions(&storage, &nodes);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let adjacency = storage
+        .adjacency_cache
+        .get(&storage, &txn, Some("knows"))
+        .unwrap();
+    let components = connected_components(&adjacency);
+    let component = |node: usize| components[positions[node]];
+    assert_eq!(component(0), component(1));
+    assert_eq!(component(0), component(2));
+    assert_eq!(component(3), component(4));
+    assert_ne!(component(0), component(3));
+    assert_ne!(component(4), component(5));
+    assert_eq!(*components.iter().max().unwrap(), 2);
+
+    let adjacency = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    let components = connected_components(&adjacency);
+    assert_eq!(components[positions[4]], components[positions[5]]);
+}
+
+#[test]
+fn test_label_propagation() {
+    let (storage, _temp_dir) = setup_test_db();
+    // two triangles {0, 1, 2} and {3, 4, 5} joined by a single edge, and 6 on its own
+    let nodes = add_graph(
+        &storage,
+        7,
+        &[
+            (0, 1, "knows"),
+            (1, 2, "knows"),
+            (2, 0, "knows"),
+            (3, 4, "knows"),
+            (4, 5, "knows"),
+            (5, 3, "knows"),
+            (2, 3, "knows"),
+        ],
+    );
+    let positions = positions(&storage, &nodes);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let adjacency = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    let communities = label_propagation(&adjacency, 10);
+    let community = |node: usize| communities[positions[node]];
+    assert_eq!(community(0), community(1));
+    assert_eq!(community(0), community(2));
+    assert_eq!(community(3), community(4));
+    assert_eq!(community(3), community(5));
+    assert_ne!(community(0), community(3));
+    assert_ne!(community(6), community(0));
+    assert_ne!(community(6), community(3));
+    assert_eq!(*communities.iter().max().unwrap(), 2);
+    assert_eq!(label_propagation(&adjacency, 10), communities);
+    drop(txn);
+
+    // the step writes every community back in the same transaction
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let updated = G::new_mut(Arc::clone(&storage), &mut txn)
+        .detect_communities(Some("knows"), 10)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+    assert_eq!(updated.len(), 7);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    for (node, id) in nodes.iter().enumerate() {
+        let stored = storage.get_node(&txn, id).unwrap();
+        assert_eq!(
+            stored.properties.unwrap().get("community_id"),
+            Some(&Value::U64(community(node)))
+        );
+    }
+}
+
+#[test]
+fn test_adjacency_cache() {
+    let (storage, _temp_dir) = setup_test_db();
+    let nodes = add_graph(&storage, 3, &[(0, 1, "knows"), (1, 2, "likes")]);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let adjacency = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    assert_eq!(adjacency.node_count(), 3);
+    assert_eq!(adjacency.edge_count(), 2);
+    let cached = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    assert!(Arc::ptr_eq(&adjacency, &cached));
+    let knows = storage
+        .adjacency_cache
+        .get(&storage, &txn, Some("knows"))
+        .unwrap();
+    assert_eq!(knows.edge_count(), 1);
+    drop(txn);
+
+    // a new snapshot is read again
+    add_graph(&storage, 1, &[]);
+    let txn = storage.graph_env.read_txn().unwrap();
+    let adjacency = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    assert_eq!(adjacency.node_count(), 4);
+    drop(txn);
+
+    // uncommitted writes are seen but not cached
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e("knows", None, nodes[2], nodes[0], false, EdgeType::Node)
+        .collect_to::<Vec<_>>();
+    let uncommitted = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    assert_eq!(uncommitted.edge_count(), 3);
+    txn.abort();
+    let txn = storage.graph_env.read_txn().unwrap();
+    let adjacency = storage.adjacency_cache.get(&storage, &txn, None).unwrap();
+    assert_eq!(adjacency.edge_count(), 2);
+}
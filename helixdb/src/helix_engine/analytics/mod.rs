@@ -0,0 +1,5 @@
+pub mod adjacency;
+pub mod analytics;
+
+#[cfg(test)]
+pub mod analytics_tests;
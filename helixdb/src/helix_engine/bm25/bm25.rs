@@ -414,6 +414,10 @@ impl BM25Flatten for HashMap<String, Value> {
     fn flatten_bm25(&self) -> String {
         let mut s = String::with_capacity(self.len() * 2);
         for (k, v) in self.iter() {
+            // arrays and objects have no text form to index
+            if matches!(v, Value::Array(_) | Value::Object(_) | Value::Empty) {
+                continue;
+            }
             s.push_str(&k);
             s.push_str(&v.to_string());
         }
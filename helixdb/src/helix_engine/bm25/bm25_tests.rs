@@ -5,7 +5,22 @@ mod tests {
         storage_core::storage_core::HelixGraphStorage,
         graph_core::config::Config,
     };
-    use crate::helix_storage::heed3::{EnvOpenOptions, Env};
+    use crate::helix_engine::{
+        graph_core::ops::{
+            bm25::hybrid_search::{fuse, HybridSearchAdapter, RRF_K},
+            g::G,
+            source::{
+                add_e::{AddEAdapter, EdgeType},
+                add_n::AddNAdapter,
+            },
+            tr_val::{Traversable, TraversalVal},
+            vectors::insert::InsertVAdapter,
+        },
+        vector_core::vector::HVector,
+    };
+    use crate::helix_storage::heed3::{Env, EnvOpenOptions, RoTxn};
+    use crate::props;
+    use std::sync::Arc;
     use tempfile::tempdir;
 
     fn setup_test_env() -> (Env, tempfile::TempDir) {
@@ -496,4 +511,78 @@ mod tests {
         
         wtxn.commit().unwrap();
     }
-} 
+
+    #[test]
+    fn test_fuse_reciprocal_ranks() {
+        let fused = fuse(&[vec![1, 2, 3], vec![3, 1]], 2);
+        assert_eq!(
+            fused,
+            vec![
+                (1, 1.0 / (RRF_K + 1.0) + 1.0 / (RRF_K + 2.0)),
+                (3, 1.0 / (RRF_K + 3.0) + 1.0 / (RRF_K + 1.0)),
+            ]
+        );
+        assert!(fuse(&[vec![], vec![]], 5).is_empty());
+    }
+
+    #[test]
+    fn test_hybrid_search_step() {
+        let (storage, _temp_dir) = setup_helix_storage();
+        let storage = Arc::new(storage);
+        type Filter = fn(&HVector, &RoTxn) -> bool;
+
+        let mut wtxn = storage.graph_env.write_txn().unwrap();
+        let mut docs = Vec::new();
+        for (title, embedding) in [
+            ("storing graph data in rust", [1.0, 0.0, 0.2]),
+            ("cooking pasta at home", [0.0, 1.0, 0.2]),
+            ("notes on graph theory", [0.2, 0.0, 1.0]),
+        ] {
+            let doc = G::new_mut(Arc::clone(&storage), &mut wtxn)
+                .add_n("Doc", Some(props! { "title" => title }), None)
+                .collect_to_val();
+            let vector = G::new_mut(Arc::clone(&storage), &mut wtxn)
+                .insert_v::<Filter>(&embedding.to_vec(), "Embedding", None)
+                .collect_to_val();
+            G::new_mut(Arc::clone(&storage), &mut wtxn)
+                .add_e(
+                    "HasEmbedding",
+                    None,
+                    doc.id(),
+                    vector.id(),
+                    false,
+                    EdgeType::Vec,
+                )
+                .collect_to_val();
+            docs.push(doc.id());
+        }
+        // matches the text but isn't a `Doc`
+        G::new_mut(Arc::clone(&storage), &mut wtxn)
+            .add_n(
+                "Person",
+                Some(props! { "bio" => "a graph enthusiast" }),
+                None,
+            )
+            .collect_to_val();
+        wtxn.commit().unwrap();
+
+        let rtxn = storage.graph_env.read_txn().unwrap();
+        let results = G::new(Arc::clone(&storage), &rtxn)
+            .hybrid_search("Doc", &[1.0, 0.0, 0.2], "graph", 3)
+            .collect_to::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+
+        let mut last = f64::INFINITY;
+        for result in &results {
+            let TraversalVal::Node(node) = result else {
+                panic!("expected a node, got {:?}", result);
+            };
+            assert_eq!(node.label, "Doc");
+            let score = node.score.unwrap();
+            assert!(score <= last);
+            last = score;
+        }
+        // only found by its vector, so ranked below the documents both searches found
+        assert_eq!(results[2].id(), docs[1]);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::helix_storage::heed3::{byteorder::BE, types::*, Database, Env, RoTxn, RwTxn};
+use flume::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use crate::{
+    helix_engine::{
+        graph_core::config::CdcRetentionConfig, storage_core::storage_core::HelixGraphStorage,
+        types::GraphError,
+    },
+    protocol::{id::ID, return_values::ReturnValue, value::Value},
+};
+
+const DB_CDC_LOG: &str = "cdc_log"; // seq -> change event
+
+/// The kind of mutation a change event records
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// The kind of item a change event was recorded for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeTarget {
+    Node,
+    Edge,
+    Vector,
+}
+
+/// A single mutation recorded in the change log
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    /// Position of the event in the log, assigned when the event is recorded
+    pub seq: u64,
+    pub op: ChangeOp,
+    pub target: ChangeTarget,
+    pub id: u128,
+    pub label: String,
+    /// Milliseconds since the unix epoch
+    pub timestamp: i64,
+}
+
+impl ChangeEvent {
+    pub fn new(op: ChangeOp, target: ChangeTarget, id: u128, label: &str) -> Self {
+        Self {
+            seq: 0,
+            op,
+            target,
+            id,
+            label: label.to_string(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        }
+    }
+}
+
+impl From<ChangeEvent> for ReturnValue {
+    fn from(event: ChangeEvent) -> Self {
+        let op = match event.op {
+            ChangeOp::Insert => "insert",
+            ChangeOp::Update => "update",
+            ChangeOp::Delete => "delete",
+        };
+        let target = match event.target {
+            ChangeTarget::Node => "node",
+            ChangeTarget::Edge => "edge",
+            ChangeTarget::Vector => "vector",
+        };
+        ReturnValue::Object(HashMap::from([
+            ("seq".to_string(), ReturnValue::from(Value::U64(event.seq))),
+            ("op".to_string(), ReturnValue::from(op)),
+            ("target".to_string(), ReturnValue::from(target)),
+            ("id".to_string(), ReturnValue::from(ID::from(event.id))),
+            ("label".to_string(), ReturnValue::from(event.label)),
+            (
+                "timestamp".to_string(),
+                ReturnValue::from(Value::I64(event.timestamp)),
+            ),
+        ]))
+    }
+}
+
+/// Receiving end of a subscription to the change log.
+///
+/// Dropping the subscription unsubscribes it.
+pub struct Subscription {
+    pub label_filter: Option<String>,
+    pub receiver: Receiver<ChangeEvent>,
+}
+
+struct Subscriber {
+    label_filter: Option<String>,
+    sender: Sender<ChangeEvent>,
+}
+
+impl Subscriber {
+    fn matches(&self, event: &ChangeEvent) -> bool {
+        match &self.label_filter {
+            Some(label) => *label == event.label,
+            None => true,
+        }
+    }
+}
+
+/// Append-only log of node, edge and vector mutations.
+///
+/// Events are written inside the write transaction of the mutation they describe, so
+/// aborted transactions never show up in the log. Committed events are fanned out to
+/// subscribers by [`ChangeLog::publish`], and pruned past the configured retention by
+/// [`ChangeLog::prune`].
+pub struct ChangeLog {
+    pub log_db: Database<U64<BE>, Bytes>,
+    enabled: AtomicBool,
+    last_published: AtomicU64,
+    subscribers: Mutex<Vec<Subscriber>>,
+    retention: Option<Duration>,
+    max_entries: Option<u64>,
+    prune_interval: Duration,
+}
+
+impl ChangeLog {
+    /// Number of events buffered per subscriber before new events are dropped for it
+    pub const SUBSCRIBER_BUFFER: usize = 4096;
+
+    /// Number of events read from the log at once when publishing
+    pub const PUBLISH_BATCH: usize = 1024;
+
+    pub const DEFAULT_PRUNE_INTERVAL_SECS: u64 = 60 * 60;
+
+    pub fn new(
+        graph_env: &Env,
+        wtxn: &mut RwTxn,
+        enabled: bool,
+        retention: &CdcRetentionConfig,
+    ) -> Result<ChangeLog, GraphError> {
+        let log_db: Database<U64<BE>, Bytes> = graph_env
+            .database_options()
+            .types::<U64<BE>, Bytes>()
+            .name(DB_CDC_LOG)
+            .create(wtxn)?;
+
+        // subscribers only receive events committed after the log was opened
+        let head = match log_db.last(wtxn)? {
+            Some((seq, _)) => seq,
+            None => 0,
+        };
+
+        Ok(ChangeLog {
+            log_db,
+            enabled: AtomicBool::new(enabled),
+            last_published: AtomicU64::new(head),
+            subscribers: Mutex::new(Vec::new()),
+            retention: retention.retention_secs.map(Duration::from_secs),
+            max_entries: retention.max_entries,
+            prune_interval: Duration::from_secs(
+                retention
+                    .prune_interval_secs
+                    .unwrap_or(Self::DEFAULT_PRUNE_INTERVAL_SECS)
+                    .max(1),
+            ),
+        })
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether events are pruned in the background
+    pub fn prunes(&self) -> bool {
+        self.retention.is_some() || self.max_entries.is_some()
+    }
+
+    pub fn retention(&self) -> Option<Duration> {
+        self.retention
+    }
+
+    pub fn max_entries(&self) -> Option<u64> {
+        self.max_entries
+    }
+
+    pub fn prune_interval(&self) -> Duration {
+        self.prune_interval
+    }
+
+    /// Appends an event to the log as part of the given write transaction.
+    ///
+    /// Does nothing if the change log is disabled.
+    pub fn record(&self, txn: &mut RwTxn, mut event: ChangeEvent) -> Result<(), GraphError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        event.seq = match self.log_db.last(txn)? {
+            Some((seq, _)) => seq + 1,
+            None => 1,
+        };
+        let bytes = bincode::serialize(&event)?;
+        self.log_db.put(txn, &event.seq, &bytes)?;
+        Ok(())
+    }
+
+    /// Returns up to `limit` committed events recorded after the sequence number `after`
+    pub fn read_since(
+        &self,
+        txn: &RoTxn,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<ChangeEvent>, GraphError> {
+        let mut events = Vec::new();
+        for result in self.log_db.range(txn, &(after.saturating_add(1)..))? {
+            if events.len() >= limit {
+                break;
+            }
+            let (_, bytes) = result?;
+            events.push(bincode::deserialize::<ChangeEvent>(bytes)?);
+        }
+        Ok(events)
+    }
+
+    /// The sequence number of the last event in the log
+    pub fn head(&self, txn: &RoTxn) -> Result<u64, GraphError> {
+        Ok(match self.log_db.last(txn)? {
+            Some((seq, _)) => seq,
+            None => 0,
+        })
+    }
+
+    /// Subscribes to events committed from now on.
+    ///
+    /// ## Arguments
+    ///
+    /// * `label_filter` - Only receive events for items with this label
+    pub fn subscribe(&self, label_filter: Option<&str>) -> Subscription {
+        let (sender, receiver) = flume::bounded(Self::SUBSCRIBER_BUFFER);
+        let label_filter = label_filter.map(|label| label.to_string());
+        self.subscribers.lock().unwrap().push(Subscriber {
+            label_filter: label_filter.clone(),
+            sender,
+        });
+        Subscription {
+            label_filter,
+            receiver,
+        }
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        !self.subscribers.lock().unwrap().is_empty()
+    }
+
+    /// Sends events committed since the last call to all matching subscribers, reading
+    /// them from the log [`Self::PUBLISH_BATCH`] at a time.
+    ///
+    /// Subscriptions that have been dropped are removed. Returns the number of events
+    /// that were published.
+    pub fn publish(&self, txn: &RoTxn) -> Result<usize, GraphError> {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| !subscriber.sender.is_disconnected());
+        let mut published = 0;
+        loop {
+            let after = self.last_published.load(Ordering::Acquire);
+            let events = self.read_since(txn, after, Self::PUBLISH_BATCH)?;
+            let Some(last) = events.last() else {
+                return Ok(published);
+            };
+            self.last_published.store(last.seq, Ordering::Release);
+            for event in events.iter() {
+                for subscriber in subscribers.iter().filter(|s| s.matches(event)) {
+                    // slow subscribers miss events rather than blocking the publisher
+                    let _ = subscriber.sender.try_send(event.clone());
+                }
+            }
+            published += events.len();
+        }
+    }
+
+    /// Removes the events recorded before `cutoff`, milliseconds since the epoch, and
+    /// those beyond the `max_entries` most recent ones. Returns the number removed.
+    ///
+    /// Events that haven't been published yet are kept, as is the last event, whose
+    /// seq the next one follows.
+    pub fn prune(
+        &self,
+        txn: &mut RwTxn,
+        cutoff: Option<i64>,
+        max_entries: Option<u64>,
+    ) -> Result<usize, GraphError> {
+        let head = self.head(txn)?;
+        let keep_from = head.min(self.last_published.load(Ordering::Acquire) + 1);
+        let len = self.log_db.len(txn)?;
+        let mut excess = max_entries.map_or(0, |max| len.saturating_sub(max));
+        let mut last_pruned = None;
+        // events are appended in the order they were recorded, so the expired ones
+        // come first
+        for result in self.log_db.range(txn, &(..keep_from))? {
+            let (seq, bytes) = result?;
+            let expired = match cutoff {
+                Some(cutoff) => bincode::deserialize::<ChangeEvent>(bytes)?.timestamp < cutoff,
+                None => false,
+            };
+            if excess == 0 && !expired {
+                break;
+            }
+            excess = excess.saturating_sub(1);
+            last_pruned = Some(seq);
+        }
+        match last_pruned {
+            Some(last) => self.truncate(txn, last),
+            None => Ok(0),
+        }
+    }
+
+    /// Removes all events up to and including `seq` from the log
+    pub fn truncate(&self, txn: &mut RwTxn, seq: u64) -> Result<usize, GraphError> {
+        Ok(self.log_db.delete_range(txn, &(..=seq))?)
+    }
+}
+
+impl HelixGraphStorage {
+    /// Prunes the change log with the configured retention and number of events kept,
+    /// in a single write transaction. Returns the number of events removed.
+    pub fn prune_change_log(&self) -> Result<usize, GraphError> {
+        let cutoff = self
+            .cdc
+            .retention()
+            .map(|retention| chrono::Utc::now().timestamp_millis() - retention.as_millis() as i64);
+        let mut txn = self.graph_env.write_txn()?;
+        let pruned = self.cdc.prune(&mut txn, cutoff, self.cdc.max_entries())?;
+        txn.commit()?;
+        Ok(pruned)
+    }
+}
@@ -0,0 +1,259 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        cdc::cdc::{ChangeLog, ChangeOp, ChangeTarget},
+        graph_core::{
+            config::{CdcRetentionConfig, Config},
+            ops::{
+                g::G,
+                source::{add_e::{AddEAdapter, EdgeType}, add_n::AddNAdapter},
+                tr_val::Traversable,
+            },
+        },
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    },
+    props,
+};
+
+fn setup_test_db(cdc: bool) -> (Arc<HelixGraphStorage>, TempDir) {
+    setup_test_db_with_retention(cdc, CdcRetentionConfig::default())
+}
+
+fn setup_test_db_with_retention(
+    cdc: bool,
+    retention: CdcRetentionConfig,
+) -> (Arc<HelixGraphStorage>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().to_str().unwrap();
+    let mut config = Config::default();
+    config.cdc = cdc;
+    config.cdc_retention = retention;
+    let storage = HelixGraphStorage::new(db_path, config).unwrap();
+    (Arc::new(storage), temp_dir)
+}
+
+fn add_people(storage: &Arc<HelixGraphStorage>, count: usize) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for _ in 0..count {
+        G::new_mut(Arc::clone(storage), &mut txn)
+            .add_n("person", None, None)
+            .collect_to::<Vec<_>>();
+    }
+    txn.commit().unwrap();
+}
+
+fn seqs(storage: &HelixGraphStorage) -> Vec<u64> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    let events = storage.cdc.read_since(&txn, 0, usize::MAX).unwrap();
+    events.iter().map(|event| event.seq).collect()
+}
+
+fn publish(storage: &HelixGraphStorage) -> usize {
+    let txn = storage.graph_env.read_txn().unwrap();
+    storage.cdc.publish(&txn).unwrap()
+}
+
+#[test]
+fn test_disabled_log_records_nothing() {
+    let (storage, _temp_dir) = setup_test_db(false);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props! { "name" => "John"}), None)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.cdc.head(&txn).unwrap(), 0);
+}
+
+#[test]
+fn test_mutations_are_recorded_in_order() {
+    let (storage, _temp_dir) = setup_test_db(true);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let person1 = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to::<Vec<_>>();
+    let person2 = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to::<Vec<_>>();
+    let edge = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e(
+            "knows",
+            None,
+            person1.first().unwrap().id(),
+            person2.first().unwrap().id(),
+            false,
+            EdgeType::Node,
+        )
+        .collect_to::<Vec<_>>();
+    storage
+        .drop_node(&mut txn, &person1.first().unwrap().id())
+        .unwrap();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let events = storage.cdc.read_since(&txn, 0, usize::MAX).unwrap();
+    let summary = events
+        .iter()
+        .map(|event| (event.seq, event.op, event.target, event.id))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            (1, ChangeOp::Insert, ChangeTarget::Node, person1.first().unwrap().id()),
+            (2, ChangeOp::Insert, ChangeTarget::Node, person2.first().unwrap().id()),
+            (3, ChangeOp::Insert, ChangeTarget::Edge, edge.first().unwrap().id()),
+            (4, ChangeOp::Delete, ChangeTarget::Edge, edge.first().unwrap().id()),
+            (5, ChangeOp::Delete, ChangeTarget::Node, person1.first().unwrap().id()),
+        ]
+    );
+    assert_eq!(storage.cdc.read_since(&txn, 3, usize::MAX).unwrap().len(), 2);
+}
+
+#[test]
+fn test_aborted_mutations_are_not_recorded() {
+    let (storage, _temp_dir) = setup_test_db(true);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to::<Vec<_>>();
+    txn.abort();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.cdc.head(&txn).unwrap(), 0);
+}
+
+#[test]
+fn test_subscribe_with_label_filter() {
+    let (storage, _temp_dir) = setup_test_db(true);
+    let people = storage.cdc.subscribe(Some("person"));
+    let everything = storage.cdc.subscribe(None);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to::<Vec<_>>();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("company", None, None)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.cdc.publish(&txn).unwrap(), 2);
+    // already published events aren't sent again
+    assert_eq!(storage.cdc.publish(&txn).unwrap(), 0);
+
+    let people = people.receiver.try_iter().collect::<Vec<_>>();
+    assert_eq!(people.len(), 1);
+    assert_eq!(people[0].label, "person");
+    assert_eq!(everything.receiver.try_iter().count(), 2);
+}
+
+#[test]
+fn test_dropped_subscriptions_are_removed() {
+    let (storage, _temp_dir) = setup_test_db(true);
+    let subscription = storage.cdc.subscribe(None);
+    assert!(storage.cdc.has_subscribers());
+    drop(subscription);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    storage.cdc.publish(&txn).unwrap();
+    assert!(!storage.cdc.has_subscribers());
+}
+
+#[test]
+fn test_publishes_in_batches() {
+    let (storage, _temp_dir) = setup_test_db(true);
+    let subscription = storage.cdc.subscribe(None);
+    let count = ChangeLog::PUBLISH_BATCH * 2 + 5;
+    add_people(&storage, count);
+
+    assert_eq!(publish(&storage), count);
+    assert_eq!(publish(&storage), 0);
+    let received = subscription
+        .receiver
+        .try_iter()
+        .map(|event| event.seq)
+        .collect::<Vec<_>>();
+    assert_eq!(received, (1..=count as u64).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_prune_keeps_max_entries() {
+    let retention = CdcRetentionConfig {
+        max_entries: Some(2),
+        ..Default::default()
+    };
+    let (storage, _temp_dir) = setup_test_db_with_retention(true, retention);
+    assert!(storage.cdc.prunes());
+    add_people(&storage, 5);
+    publish(&storage);
+
+    assert_eq!(storage.prune_change_log().unwrap(), 3);
+    assert_eq!(seqs(&storage), vec![4, 5]);
+    assert_eq!(storage.prune_change_log().unwrap(), 0);
+
+    // seqs carry on from the last event
+    add_people(&storage, 1);
+    assert_eq!(seqs(&storage), vec![4, 5, 6]);
+}
+
+#[test]
+fn test_prune_keeps_unpublished_events() {
+    let retention = CdcRetentionConfig {
+        max_entries: Some(1),
+        ..Default::default()
+    };
+    let (storage, _temp_dir) = setup_test_db_with_retention(true, retention);
+    let subscription = storage.cdc.subscribe(None);
+    add_people(&storage, 3);
+
+    assert_eq!(storage.prune_change_log().unwrap(), 0);
+    assert_eq!(publish(&storage), 3);
+    assert_eq!(subscription.receiver.try_iter().count(), 3);
+    assert_eq!(storage.prune_change_log().unwrap(), 2);
+    assert_eq!(seqs(&storage), vec![3]);
+}
+
+#[test]
+fn test_prune_by_age_keeps_last_event() {
+    let retention = CdcRetentionConfig {
+        retention_secs: Some(0),
+        ..Default::default()
+    };
+    let (storage, _temp_dir) = setup_test_db_with_retention(true, retention);
+    add_people(&storage, 3);
+    publish(&storage);
+    thread::sleep(Duration::from_millis(5));
+
+    assert_eq!(storage.prune_change_log().unwrap(), 2);
+    assert_eq!(seqs(&storage), vec![3]);
+
+    add_people(&storage, 1);
+    publish(&storage);
+    thread::sleep(Duration::from_millis(5));
+    assert_eq!(storage.prune_change_log().unwrap(), 1);
+    assert_eq!(seqs(&storage), vec![4]);
+}
+
+#[test]
+fn test_retention_is_off_by_default() {
+    let (storage, _temp_dir) = setup_test_db(true);
+    assert!(!storage.cdc.prunes());
+    add_people(&storage, 3);
+    publish(&storage);
+    assert_eq!(storage.prune_change_log().unwrap(), 0);
+    assert_eq!(seqs(&storage), vec![1, 2, 3]);
+}
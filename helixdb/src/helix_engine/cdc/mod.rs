@@ -0,0 +1,4 @@
+pub mod cdc;
+
+#[cfg(test)]
+pub mod cdc_tests;
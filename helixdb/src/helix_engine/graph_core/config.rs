@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
@@ -14,11 +14,385 @@ pub struct VectorConfig {
 
     // Size of dynamic candidate list for graph search
     pub ef_search: Option<usize>,
+
+    // Quantization of the vectors of each vector schema, by name. Searches compare the
+    // quantized vectors and re-rank the best candidates against the full vectors.
+    #[serde(default)]
+    pub quantization: Option<HashMap<String, Quantization>>,
+
+    // Number of candidates re-ranked against the full vectors for each result of a
+    // search when vectors are quantized, defaults to 4
+    pub rerank_factor: Option<usize>,
+}
+
+/// How the vectors of a vector schema are compressed in the HNSW index
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Quantization {
+    // a byte per dimension, scaled between the smallest and largest value of the vector
+    Sq8,
+    // a byte per subvector, the closest of up to 256 centroids trained on the first
+    // `train_size` vectors of the schema, which defaults to 1024
+    Pq {
+        subvectors: usize,
+        train_size: Option<usize>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GraphConfig {
     pub secondary_indices: Option<Vec<String>>,
+
+    // secondary indices that may only map a value to a single node, these don't have
+    // to be listed in `secondary_indices` as well
+    #[serde(default)]
+    pub unique_indices: Option<Vec<String>>,
+
+    // secondary indices over edge properties, every edge with the property is indexed
+    // when it is added
+    #[serde(default)]
+    pub edge_secondary_indices: Option<Vec<String>>,
+
+    // labels of the nodes whose past states are kept, each write of such a node records
+    // a version that `::AsOf` reads back
+    #[serde(default)]
+    pub versioned_labels: Option<Vec<String>>,
+}
+
+/// When the write-ahead log is flushed from the OS page cache to disk
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    // fsync after every write to the log
+    Always,
+    // fsync at most once every `fsync_interval_ms`
+    #[default]
+    Interval,
+    // leave flushing to the OS
+    Never,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct WalConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub fsync: FsyncPolicy,
+
+    // Minimum time between two fsyncs of the log with the `interval` policy
+    pub fsync_interval_ms: Option<u64>,
+
+    // Directory the log is written to, defaults to the database directory
+    pub dir: Option<String>,
+
+    // Only replay entries up to this unix timestamp in milliseconds on startup.
+    // The log is truncated after the last replayed entry.
+    pub recover_to: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ParallelConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Minimum number of items adjacent to a single node before `Out`/`In` steps fetch
+    // them on the worker threads, defaults to 1024
+    pub threshold: Option<usize>,
+
+    // Number of worker threads, defaults to the number of cores
+    pub threads: Option<usize>,
+}
+
+/// Grows the LMDB memory map in the background before it fills up
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MapResizeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Seconds between two checks of the map usage, defaults to 60
+    pub check_interval_secs: Option<u64>,
+
+    // Share of the map used beyond which it is grown, defaults to 0.8
+    pub grow_at: Option<f64>,
+
+    // Factor the map size is multiplied by when grown, defaults to 2
+    pub growth_factor: Option<f64>,
+
+    // Largest size in GB the map is grown to, defaults to 9998
+    pub max_size_gb: Option<usize>,
+
+    // Milliseconds a resize waits for open transactions to end before it is retried at
+    // the next check, defaults to 5000
+    pub drain_timeout_ms: Option<u64>,
+}
+
+/// Soft deletion of nodes and edges, which a drop moves to the trash instead of removing
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SoftDeleteConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Seconds elements stay in the trash before they are purged, defaults to 7 days
+    pub retention_secs: Option<u64>,
+
+    // Seconds between two purges of the elements past their retention, defaults to 3600
+    pub purge_interval_secs: Option<u64>,
+}
+
+/// Append-only log of the mutating queries run against the graph, with who ran them and
+/// the ids of the elements they wrote
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AuditConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Seconds entries are kept for, entries are kept until pruned through the admin
+    // API if unset
+    pub retention_secs: Option<u64>,
+
+    // Number of most recent entries kept, all are kept if unset
+    pub max_entries: Option<u64>,
+
+    // Seconds between two prunes of the entries past the retention, defaults to 3600
+    pub prune_interval_secs: Option<u64>,
+}
+
+/// Pruning of the change data capture log, which keeps every event if nothing is set
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+pub struct CdcRetentionConfig {
+    // Seconds events are kept for
+    pub retention_secs: Option<u64>,
+
+    // Number of most recent events kept
+    pub max_entries: Option<u64>,
+
+    // Seconds between two prunes of the events past the retention, defaults to 3600
+    pub prune_interval_secs: Option<u64>,
+}
+
+/// In-memory bloom filter of the node ids, answering edge insertion checks for nodes
+/// that don't exist without reading the nodes table
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ExistenceFilterConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Number of nodes the filter is sized for, defaults to 1000000 or twice the nodes
+    // already stored if that's more
+    pub expected_nodes: Option<usize>,
+
+    // Share of the ids of missing nodes still looked up at the expected number of nodes,
+    // defaults to 0.01
+    pub false_positive_rate: Option<f64>,
+}
+
+/// Batches the writes of concurrent queries into shared write transactions
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct GroupCommitConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Most writes run and committed in a single transaction, defaults to 64
+    pub max_batch: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CompressionConfig {
+    // Encoded nodes and edges larger than this many bytes are stored zstd compressed,
+    // compression is disabled if unset
+    pub threshold_bytes: Option<usize>,
+
+    // zstd compression level, defaults to 3
+    pub level: Option<i32>,
+
+    // Rewrite all stored nodes and edges with the current settings on startup
+    #[serde(default)]
+    pub migrate: bool,
+}
+
+/// AES-256-GCM encryption of the stored nodes and edges. Ids, adjacency lists and the
+/// keys of the indices stay in plaintext, so indexed properties and the write-ahead log
+/// aren't covered.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct EncryptionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Environment variable holding the key as 64 hex characters, defaults to
+    // HELIX_ENCRYPTION_KEY
+    pub key_env: Option<String>,
+
+    // Shell command printing the key, e.g. decrypting it with a KMS, run instead of
+    // reading the environment variable if set
+    pub key_command: Option<String>,
+
+    // Rewrite all stored nodes and edges on startup, encrypting those written before
+    // encryption was enabled
+    #[serde(default)]
+    pub migrate: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AdjacencyConfig {
+    // Adjacency lists with at least this many edges of a label are compacted into
+    // delta encoded blocks, defaults to 1024
+    pub min_degree: Option<usize>,
+
+    // Most edges stored in a single block, defaults to 256
+    pub block_size: Option<usize>,
+
+    // Compact the adjacency lists of high degree nodes on startup
+    #[serde(default)]
+    pub compact: bool,
+}
+
+/// How requests to the gateway are authenticated. A request is accepted if it carries
+/// one of the API keys or a valid JWT.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AuthConfig {
+    // keys accepted in the `x-api-key` header
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+
+    // JWTs accepted in the `Authorization: Bearer` header
+    pub jwt: Option<JwtConfig>,
+
+    // keys accepted in the `x-api-key` header that can also use the `/admin` endpoints
+    #[serde(default)]
+    pub admin_keys: Vec<String>,
+
+    // role of a token allowed to use the `/admin` endpoints, see `JwtConfig::role_claim`
+    pub admin_role: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JwtConfig {
+    pub algorithm: JwtAlgorithm,
+
+    // the shared secret for HS256, or the PEM encoded public key for RS256
+    pub key: String,
+
+    // reject tokens not issued by this issuer, or that don't name an issuer
+    pub issuer: Option<String>,
+
+    // reject tokens not meant for this audience, or that don't name an audience
+    pub audience: Option<String>,
+
+    // claim holding the role or roles granting access to `@sensitive` fields, defaults
+    // to `role`
+    pub role_claim: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    HS256,
+    RS256,
+}
+
+/// Limits on the requests clients can send to the gateway
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LimitsConfig {
+    // requests with larger bodies are rejected with a 413, defaults to 32 MiB
+    pub max_body_bytes: Option<usize>,
+
+    // requests over the rate limit of their IP address are rejected with a 429
+    pub rate_limit: Option<RateLimitConfig>,
+
+    // connections waiting for a worker beyond which new ones are rejected with a 503,
+    // defaults to 1000
+    pub max_queued_connections: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    // sustained number of requests allowed per second from a single IP address
+    pub requests_per_second: f64,
+
+    // number of requests that can be sent at once after a quiet period, defaults to
+    // `requests_per_second`
+    pub burst: Option<u32>,
+}
+
+/// Limits on the work a single query can do, a query going over one of them fails
+/// without committing its writes. Queries are unlimited if none are set.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct QueryLimitsConfig {
+    // items the traversal steps of a query can visit
+    pub max_visited: Option<u64>,
+
+    // milliseconds a query can run for before it is cancelled, its request failing
+    // with a 408
+    pub max_duration_ms: Option<u64>,
+
+    // estimated bytes of the items the traversals of a query can collect
+    pub max_collected_bytes: Option<usize>,
+}
+
+/// Makes the instance a read-only replica following the write-ahead log of a primary
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ReplicationConfig {
+    // address of the primary's gateway, the instance is a primary if unset
+    pub primary: Option<String>,
+
+    // key sent in the `x-api-key` header if the primary requires authentication
+    pub api_key: Option<String>,
+}
+
+/// Makes the instance a node of a cluster that elects a leader to take the writes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClusterConfig {
+    // id of the node, unique within the cluster
+    pub node_id: u64,
+
+    // address of the node's gateway the other nodes connect to
+    pub address: String,
+
+    // nodes the cluster starts out with, including this one. Nodes added through the
+    // admin API are remembered by the cluster instead
+    pub members: Vec<ClusterMember>,
+
+    // key sent in the `x-api-key` header to the other nodes if they require
+    // authentication
+    pub api_key: Option<String>,
+
+    // shortest time in milliseconds without hearing from the leader before a node stands
+    // for election, defaults to 1000
+    pub election_timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClusterMember {
+    pub id: u64,
+    pub address: String,
+}
+
+/// Splits the nodes of the graph across instances by a hash of their id
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShardingConfig {
+    // index of the instance in `shards`
+    pub shard: usize,
+
+    // addresses of the gateways of all shards, in the same order on every shard
+    pub shards: Vec<String>,
+
+    // key sent in the `x-api-key` header to the other shards if they require
+    // authentication
+    pub api_key: Option<String>,
+}
+
+/// An application sharing the instance, whose nodes, edges and vectors are kept apart
+/// from those of the others
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct NamespaceConfig {
+    // node, edge and vector labels of the namespace, the ones declared in its
+    // `NAMESPACE` block of the deployed schema are added at startup
+    #[serde(default)]
+    pub labels: Vec<String>,
+
+    // settings of the vector index of the namespace, the ones of `vector_config` if unset
+    #[serde(default)]
+    pub vector_config: Option<VectorConfig>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -37,6 +411,104 @@ pub struct Config {
 
     // should use mcp
     pub mcp: bool,
+
+    // should record mutations to the change data capture log
+    #[serde(default)]
+    pub cdc: bool,
+
+    // how long the events of the change data capture log are kept
+    #[serde(default)]
+    pub cdc_retention: CdcRetentionConfig,
+
+    // should maintain graph statistics used for cardinality estimation
+    #[serde(default)]
+    pub stats: bool,
+
+    // write-ahead log used for point-in-time recovery
+    #[serde(default)]
+    pub wal: WalConfig,
+
+    // transparent compression of large nodes and edges
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    // encryption of the stored nodes and edges
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+
+    // compacted storage of the edges of high degree nodes
+    #[serde(default)]
+    pub adjacency: AdjacencyConfig,
+
+    // fetch the items adjacent to high fanout nodes in parallel
+    #[serde(default)]
+    pub parallel: ParallelConfig,
+
+    // grow the memory map of the database before it fills up
+    #[serde(default)]
+    pub map_resize: MapResizeConfig,
+
+    // keep a bloom filter of the node ids to skip lookups of missing nodes
+    #[serde(default)]
+    pub existence_filter: ExistenceFilterConfig,
+
+    // move dropped nodes and edges to a trash they can be restored from
+    #[serde(default)]
+    pub soft_delete: SoftDeleteConfig,
+
+    // record the mutating queries in an audit log, the ids of the elements they wrote
+    // are taken from the write-ahead log and only recorded if it is enabled
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    // commit the writes of concurrent queries together
+    #[serde(default)]
+    pub group_commit: GroupCommitConfig,
+
+    // reject node properties that don't match the deployed schema
+    #[serde(default)]
+    pub strict_schema: bool,
+
+    // authenticate requests to the gateway, all requests are accepted if unset
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+
+    // body size and rate limits of the requests to the gateway
+    #[serde(default)]
+    pub limits: LimitsConfig,
+
+    // execution limits of each query
+    #[serde(default)]
+    pub query_limits: QueryLimitsConfig,
+
+    // store labels and property names as ids from a dictionary rather than as strings
+    #[serde(default)]
+    pub intern_strings: bool,
+
+    // snapshots pinned with `HelixGraphEngine::snapshot` at once, defaults to 32
+    #[serde(default)]
+    pub max_snapshots: Option<usize>,
+
+    // follow a primary as a read-only replica
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+
+    // take part in a cluster electing the node that takes the writes
+    #[serde(default)]
+    pub cluster: Option<ClusterConfig>,
+
+    // hold the nodes of one shard of a graph split across instances
+    #[serde(default)]
+    pub sharding: Option<ShardingConfig>,
+
+    // applications sharing the instance, by namespace
+    #[serde(default)]
+    pub namespaces: HashMap<String, NamespaceConfig>,
+
+    // serve the /query and /push endpoints running ad-hoc and pushed queries without
+    // deploying them
+    #[serde(default)]
+    pub query_endpoint: bool,
 }
 
 impl Config {
@@ -46,12 +518,41 @@ impl Config {
                 m: Some(m),
                 ef_construction: Some(ef_construction),
                 ef_search: Some(ef_search),
+                quantization: None,
+                rerank_factor: None,
             },
             graph_config: GraphConfig {
                 secondary_indices: None,
+                unique_indices: None,
+                edge_secondary_indices: None,
+                versioned_labels: None,
             },
             db_max_size_gb: Some(db_max_size_gb),
             mcp: true,
+            cdc: false,
+            cdc_retention: CdcRetentionConfig::default(),
+            stats: false,
+            wal: WalConfig::default(),
+            compression: CompressionConfig::default(),
+            encryption: EncryptionConfig::default(),
+            adjacency: AdjacencyConfig::default(),
+            parallel: ParallelConfig::default(),
+            map_resize: MapResizeConfig::default(),
+            existence_filter: ExistenceFilterConfig::default(),
+            soft_delete: SoftDeleteConfig::default(),
+            audit: AuditConfig::default(),
+            group_commit: GroupCommitConfig::default(),
+            strict_schema: false,
+            auth: None,
+            limits: LimitsConfig::default(),
+            query_limits: QueryLimitsConfig::default(),
+            intern_strings: false,
+            max_snapshots: None,
+            replication: ReplicationConfig::default(),
+            cluster: None,
+            sharding: None,
+            namespaces: HashMap::new(),
+            query_endpoint: false,
         }
     }
 
@@ -75,9 +576,12 @@ impl Config {
         "ef_search": 768
     },
     "graph_config": {
-        "secondary_indices": []
+        "secondary_indices": [],
+        "unique_indices": [],
+        "edge_secondary_indices": []
     },
-    "db_max_size_gb": 10
+    "db_max_size_gb": 10,
+    "mcp": true
 }
 "#
         .to_string()
@@ -91,12 +595,41 @@ impl Default for Config {
                 m: Some(16),
                 ef_construction: Some(128),
                 ef_search: Some(768),
+                quantization: None,
+                rerank_factor: None,
             },
             graph_config: GraphConfig {
                 secondary_indices: None,
+                unique_indices: None,
+                edge_secondary_indices: None,
+                versioned_labels: None,
             },
             db_max_size_gb: Some(10),
             mcp: true,
+            cdc: false,
+            cdc_retention: CdcRetentionConfig::default(),
+            stats: false,
+            wal: WalConfig::default(),
+            compression: CompressionConfig::default(),
+            encryption: EncryptionConfig::default(),
+            adjacency: AdjacencyConfig::default(),
+            parallel: ParallelConfig::default(),
+            map_resize: MapResizeConfig::default(),
+            existence_filter: ExistenceFilterConfig::default(),
+            soft_delete: SoftDeleteConfig::default(),
+            audit: AuditConfig::default(),
+            group_commit: GroupCommitConfig::default(),
+            strict_schema: false,
+            auth: None,
+            limits: LimitsConfig::default(),
+            query_limits: QueryLimitsConfig::default(),
+            intern_strings: false,
+            max_snapshots: None,
+            replication: ReplicationConfig::default(),
+            cluster: None,
+            sharding: None,
+            namespaces: HashMap::new(),
+            query_endpoint: false,
         }
     }
 }
@@ -2,15 +2,19 @@ use crate::helix_engine::storage_core::storage_core::HelixGraphStorage;
 use crate::helix_engine::storage_core::storage_methods::StorageMethods;
 use crate::helix_engine::types::GraphError;
 use crate::helix_gateway::mcp::mcp::{McpBackend, McpConnections};
+use crate::helix_storage::StorageBackend;
 use crate::props;
 use crate::protocol::filterable::{Filterable, FilterableType};
 use crate::protocol::remapping::{Remapping, ResponseRemapping};
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::str;
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
 
 use super::config::VectorConfig;
+use super::snapshot::Snapshot;
 use crate::helixc::parser::helix_parser::{
     BooleanOp, Expression, GraphStep, HelixParser, IdType, Source, StartNode, Statement, Step,
     Traversal,
@@ -42,6 +46,9 @@ pub struct HelixGraphEngine {
 pub struct HelixGraphEngineOpts {
     pub path: String,
     pub config: Config,
+    /// Whether the LMDB environment is persisted at `path` or kept in a temporary
+    /// directory for the lifetime of the engine
+    pub backend: StorageBackend,
 }
 
 impl HelixGraphEngineOpts {
@@ -49,23 +56,69 @@ impl HelixGraphEngineOpts {
         Self {
             path: String::new(),
             config: Config::default(),
+            backend: StorageBackend::default(),
         }
     }
     pub fn with_path(path: String) -> Self {
         Self {
             path,
             config: Config::default(),
+            backend: StorageBackend::default(),
+        }
+    }
+    pub fn in_memory() -> Self {
+        Self {
+            path: String::new(),
+            config: Config::default(),
+            backend: StorageBackend::InMemory,
         }
     }
 }
 
 impl HelixGraphEngine {
+    /// Creates an engine on top of already opened storage.
+    ///
+    /// MCP is only available for engines created through [`HelixGraphEngine::new`].
+    pub fn with_storage(storage: Arc<HelixGraphStorage>) -> HelixGraphEngine {
+        Self {
+            storage,
+            mcp_backend: None,
+            mcp_connections: None,
+        }
+    }
+
     pub fn new(opts: HelixGraphEngineOpts) -> Result<HelixGraphEngine, GraphError> {
         let should_use_mcp = opts.config.mcp;
-        let storage = match HelixGraphStorage::new(opts.path.as_str(), opts.config) {
+        let should_use_cdc = opts.config.cdc;
+        let should_use_wal = opts.config.wal.enabled;
+        let should_resize_map = opts.config.map_resize.enabled;
+        let should_purge_trash = opts.config.soft_delete.enabled;
+        let storage = match HelixGraphStorage::open_backend(
+            opts.backend,
+            opts.path.as_str(),
+            opts.config,
+        ) {
             Ok(db) => Arc::new(db),
             Err(err) => return Err(err),
         };
+        if should_use_cdc {
+            Self::spawn_change_publisher(Arc::downgrade(&storage));
+        }
+        if should_use_cdc && storage.cdc.prunes() {
+            Self::spawn_change_log_pruner(Arc::downgrade(&storage));
+        }
+        if should_use_wal {
+            Self::spawn_wal_shipper(Arc::downgrade(&storage));
+        }
+        if should_resize_map {
+            Self::spawn_map_resizer(Arc::downgrade(&storage));
+        }
+        if should_purge_trash {
+            Self::spawn_trash_purger(Arc::downgrade(&storage));
+        }
+        if storage.audit.prunes() {
+            Self::spawn_audit_pruner(Arc::downgrade(&storage));
+        }
         let (mcp_backend, mcp_connections) = if should_use_mcp {
             let mcp_backend = Arc::new(McpBackend::new(storage.clone()));
             let mcp_connections = Arc::new(Mutex::new(McpConnections::new()));
@@ -80,6 +133,144 @@ impl HelixGraphEngine {
         })
     }
 
+    /// Pins the latest committed state of the graph as a snapshot several queries can
+    /// read, e.g. to page through a result set consistently over several requests.
+    ///
+    /// The snapshot is released once its last handle is dropped.
+    pub fn snapshot(&self) -> Result<Snapshot, GraphError> {
+        Snapshot::pin(&self.storage)
+    }
+
+    /// Interval at which committed change events are fanned out to subscribers
+    pub const CDC_PUBLISH_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Spawns a thread that tails the change log and publishes committed events to
+    /// subscribers. The thread exits once the storage has been dropped.
+    fn spawn_change_publisher(storage: Weak<HelixGraphStorage>) {
+        thread::spawn(move || loop {
+            let Some(storage) = storage.upgrade() else {
+                break;
+            };
+            let hold = storage.map_size.hold();
+            // publishing also advances past events nobody is subscribed to yet, so new
+            // subscribers only receive events committed after they subscribed
+            let published = storage
+                .graph_env
+                .read_txn()
+                .map_err(GraphError::from)
+                .and_then(|txn| storage.cdc.publish(&txn));
+            if let Err(e) = published {
+                eprintln!("Error publishing change events: {:?}", e);
+            }
+            drop(hold);
+            drop(storage);
+            thread::sleep(Self::CDC_PUBLISH_INTERVAL);
+        });
+    }
+
+    /// Spawns a thread that prunes the change log down to its retention window and number
+    /// of events kept, checking at the configured interval. The thread exits once the
+    /// storage has been dropped.
+    fn spawn_change_log_pruner(storage: Weak<HelixGraphStorage>) {
+        thread::spawn(move || loop {
+            let Some(storage) = storage.upgrade() else {
+                break;
+            };
+            let hold = storage.map_size.hold();
+            match storage.prune_change_log() {
+                Ok(0) => {}
+                Ok(pruned) => println!("Pruned {} events from the change log", pruned),
+                Err(e) => eprintln!("Error pruning the change log: {:?}", e),
+            }
+            drop(hold);
+            let interval = storage.cdc.prune_interval();
+            drop(storage);
+            thread::sleep(interval);
+        });
+    }
+
+    /// Interval at which committed writes are appended to the write-ahead log file
+    pub const WAL_SHIP_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Spawns a thread that moves committed writes from the database to the write-ahead
+    /// log file. The thread exits once the storage has been dropped, which ships the rest.
+    fn spawn_wal_shipper(storage: Weak<HelixGraphStorage>) {
+        thread::spawn(move || loop {
+            let Some(storage) = storage.upgrade() else {
+                break;
+            };
+            let hold = storage.map_size.hold();
+            if let Err(e) = storage.wal.ship(&storage.graph_env) {
+                eprintln!("Error writing write-ahead log: {:?}", e);
+            }
+            drop(hold);
+            drop(storage);
+            thread::sleep(Self::WAL_SHIP_INTERVAL);
+        });
+    }
+
+    /// Spawns a thread that grows the memory map of the database once it's used beyond
+    /// the configured share, checking at the configured interval. The thread exits once
+    /// the storage has been dropped.
+    fn spawn_map_resizer(storage: Weak<HelixGraphStorage>) {
+        thread::spawn(move || loop {
+            let Some(storage) = storage.upgrade() else {
+                break;
+            };
+            match storage.map_size.grow(&storage.graph_env, false) {
+                Ok(Some(size)) => println!("Grew the memory map to {} bytes", size),
+                Ok(None) => {}
+                Err(e) => eprintln!("Error growing the memory map: {:?}", e),
+            }
+            let interval = storage.map_size.check_interval();
+            drop(storage);
+            thread::sleep(interval);
+        });
+    }
+
+    /// Spawns a thread that purges the nodes and edges that outlived the retention window
+    /// of the trash, checking at the configured interval. Replicas and cluster followers
+    /// leave purging to their primary or leader, whose purges they apply from its log.
+    /// The thread exits once the storage has been dropped.
+    fn spawn_trash_purger(storage: Weak<HelixGraphStorage>) {
+        thread::spawn(move || loop {
+            let Some(storage) = storage.upgrade() else {
+                break;
+            };
+            let hold = storage.map_size.hold();
+            match storage.wal.is_primary().then(|| storage.purge_expired()) {
+                None | Some(Ok(0)) => {}
+                Some(Ok(purged)) => println!("Purged {} expired elements from the trash", purged),
+                Some(Err(e)) => eprintln!("Error purging the trash: {:?}", e),
+            }
+            drop(hold);
+            let interval = storage.trash.purge_interval();
+            drop(storage);
+            thread::sleep(interval);
+        });
+    }
+
+    /// Spawns a thread that prunes the audit log down to its retention window and number
+    /// of entries kept, checking at the configured interval. The thread exits once the
+    /// storage has been dropped.
+    fn spawn_audit_pruner(storage: Weak<HelixGraphStorage>) {
+        thread::spawn(move || loop {
+            let Some(storage) = storage.upgrade() else {
+                break;
+            };
+            let hold = storage.map_size.hold();
+            match storage.prune_audit_log() {
+                Ok(0) => {}
+                Ok(pruned) => println!("Pruned {} entries from the audit log", pruned),
+                Err(e) => eprintln!("Error pruning the audit log: {:?}", e),
+            }
+            drop(hold);
+            let interval = storage.audit.prune_interval();
+            drop(storage);
+            thread::sleep(interval);
+        });
+    }
+
     // pub fn print_result_as_json(&self, traversal: &TraversalBuilder<dyn Transaction>) {
     //     let current_step = &traversal.current_step;
     //     let json_result = json!(current_step);
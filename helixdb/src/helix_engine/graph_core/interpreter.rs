@@ -0,0 +1,1160 @@
+use crate::helix_engine::{
+    graph_core::{
+        ops::{
+            g::G,
+            in_::{
+                in_::InAdapter, in_count::InCountAdapter, in_e::InEdgesAdapter, to_n::ToNAdapter,
+            },
+            out::{
+                from_n::FromNAdapter, out::OutAdapter, out_count::OutCountAdapter,
+                out_e::OutEdgesAdapter,
+            },
+            source::{
+                add_e::{AddEAdapter, EdgeType},
+                add_n::AddNAdapter,
+                e_from_id::EFromIdAdapter,
+                e_from_index::EFromIndexAdapter,
+                e_from_type::EFromTypeAdapter,
+                n_from_id::NFromIdAdapter,
+                n_from_index::NFromIndexAdapter,
+                n_from_type::NFromTypeAdapter,
+                n_from_type_ordered::NFromTypeOrderedAdapter,
+            },
+            tr_val::{Traversable, TraversalVal},
+            util::{
+                as_of::AsOfAdapter,
+                dedup::DedupAdapter,
+                drop::Drop,
+                order_by::{HelixOrder, OrderByAdapter},
+                props::PropsAdapter,
+                range::RangeAdapter,
+                update::UpdateAdapter,
+            },
+        },
+        query_limits,
+    },
+    storage_core::storage_core::HelixGraphStorage,
+    types::GraphError,
+};
+use crate::helix_storage::heed3::{RoTxn, RwTxn, WithTls};
+use crate::helixc::generator::{
+    bool_op::BoolOp,
+    expression::GeneratedExpression,
+    generator_types::{
+        BoExp, ForEach, ForLoopInVariable, ForVariable, Parameter, Query,
+        ReturnValue as GeneratedReturnValue, ReturnValueExpr, Statement,
+    },
+    source_steps::SourceStep,
+    traversal_steps::{
+        PathSegment as GeneratedPathSegment, Step, Traversal, TraversalType, Where, WhereRef,
+    },
+    utils::{GenRef, GeneratedType, GeneratedValue, Order, RustType},
+};
+use crate::helixc::parser::helix_parser::{ArithmeticOp, ExpressionFunction};
+use crate::protocol::{
+    date::Date,
+    expression,
+    items::ulid,
+    return_values::ReturnValue,
+    value::{PathSegment, Value},
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use std::{cell::RefCell, cmp::Ordering, collections::HashMap, sync::Arc};
+
+/// What a variable of a query holds, the items of a traversal or a single value such
+/// as a count
+#[derive(Debug, Clone)]
+pub enum Binding {
+    Items(Vec<TraversalVal>),
+    Value(Value),
+}
+
+/// The transaction a query runs in, a write transaction for queries that mutate the
+/// graph
+enum Txn<'env> {
+    Ro(RoTxn<'env, WithTls>),
+    Rw(RwTxn<'env>),
+}
+
+impl<'env> Txn<'env> {
+    fn ro(&self) -> &RoTxn<'env> {
+        match self {
+            Txn::Ro(txn) => txn,
+            Txn::Rw(txn) => txn,
+        }
+    }
+
+    fn rw(&mut self) -> Result<&mut RwTxn<'env>, GraphError> {
+        match self {
+            Txn::Rw(txn) => Ok(txn),
+            Txn::Ro(_) => Err(GraphError::New(
+                "Writes need a query that mutates the graph".to_string(),
+            )),
+        }
+    }
+}
+
+/// Runs a query the analyzer generated the IR of by walking the IR, instead of
+/// compiling the code generated from it.
+///
+/// The statements run against the same graph_core steps the generated code calls, so
+/// both return the same results. Vector and full text searches, analytics, paths and
+/// remappings of the returned values aren't interpreted yet, queries using them fail
+/// with an error naming the step.
+pub struct Interpreter<'q> {
+    storage: Arc<HelixGraphStorage>,
+    query: &'q Query,
+    /// Parameters of the query, cast to their declared types
+    params: HashMap<String, Value>,
+    vars: HashMap<String, Binding>,
+    /// Parameters as sent and who sent them, recorded in the audit log if the query
+    /// mutates the graph
+    parameters: Vec<u8>,
+    caller: Option<String>,
+}
+
+impl<'q> Interpreter<'q> {
+    /// Casts the JSON parameters to the types the query declares
+    pub fn new(
+        storage: Arc<HelixGraphStorage>,
+        query: &'q Query,
+        params: &JsonValue,
+    ) -> Result<Self, GraphError> {
+        let parameters = match query.is_mut {
+            true => serde_json::to_vec(params)
+                .map_err(|e| GraphError::New(format!("Invalid parameters: {}", e)))?,
+            false => Vec::new(),
+        };
+        let params = query
+            .parameters
+            .iter()
+            .map(|param| {
+                let value = params.get(&param.name).cloned().unwrap_or(JsonValue::Null);
+                Ok((param.name.clone(), cast(query, param, value)?))
+            })
+            .collect::<Result<_, GraphError>>()?;
+        Ok(Self {
+            storage,
+            query,
+            params,
+            vars: HashMap::new(),
+            parameters,
+            caller: None,
+        })
+    }
+
+    /// Sets who runs the query, for the audit log
+    pub fn with_caller(mut self, caller: Option<String>) -> Self {
+        self.caller = caller;
+        self
+    }
+
+    /// Runs the statements of the query in a single transaction, committed if the query
+    /// mutates the graph, and returns its values by name like the generated handler
+    pub fn run(mut self) -> Result<HashMap<String, ReturnValue>, GraphError> {
+        let storage = Arc::clone(&self.storage);
+        let query = self.query;
+        let mut txn = match query.is_mut {
+            true => Txn::Rw(storage.graph_env.write_txn()?),
+            false => Txn::Ro(storage.graph_env.read_txn()?),
+        };
+        let audit_since = match &txn {
+            Txn::Rw(txn) if storage.audit.is_enabled() => Some(storage.wal.last_seq(txn)?),
+            _ => None,
+        };
+        for statement in &query.statements {
+            self.statement(&mut txn, statement)?;
+        }
+
+        let mut return_vals = HashMap::new();
+        for return_value in &query.return_values {
+            let Some(name) = return_value.name() else {
+                continue;
+            };
+            let value = match self.return_value(&mut txn, return_value)? {
+                Binding::Items(items) => ReturnValue::from_traversal_value_array_with_mixin(
+                    items,
+                    RefCell::new(HashMap::new()).borrow_mut(),
+                ),
+                Binding::Value(value) => ReturnValue::from(value),
+            };
+            return_vals.insert(name.clone(), value);
+        }
+        if let Txn::Rw(mut txn) = txn {
+            if let Some(since) = audit_since {
+                storage.record_audit(
+                    &mut txn,
+                    since,
+                    &query.name,
+                    &self.parameters,
+                    self.caller.take(),
+                )?;
+            }
+            query_limits::check()?;
+            storage.wal.commit(txn)?;
+        }
+        Ok(return_vals)
+    }
+
+    fn return_value(
+        &mut self,
+        txn: &mut Txn,
+        return_value: &GeneratedReturnValue,
+    ) -> Result<Binding, GraphError> {
+        match &return_value.value {
+            ReturnValueExpr::Traversal(tr) => self.traversal(txn, tr),
+            ReturnValueExpr::Identifier(identifier) => self.lookup(identifier),
+            ReturnValueExpr::Value(value) => self.gen_ref(value).map(Binding::Value),
+        }
+    }
+
+    fn statement(&mut self, txn: &mut Txn, statement: &Statement) -> Result<Binding, GraphError> {
+        match statement {
+            Statement::Assignment(assignment) => {
+                let value = self.statement(txn, &assignment.value)?;
+                self.vars
+                    .insert(assignment.variable.inner().clone(), value.clone());
+                Ok(value)
+            }
+            Statement::Traversal(tr) => self.traversal(txn, tr),
+            Statement::Drop(drop) => {
+                let items = self.traversal(txn, &drop.expression)?.into_items();
+                Drop::<Vec<_>>::drop_traversal(
+                    items.into_iter().map(Ok).collect(),
+                    Arc::clone(&self.storage),
+                    txn.rw()?,
+                )?;
+                Ok(Binding::Items(Vec::new()))
+            }
+            Statement::ForEach(for_each) => self.for_each(txn, for_each),
+            Statement::Literal(value) => self.gen_ref(value).map(Binding::Value),
+            Statement::Identifier(identifier) => self.lookup(identifier),
+            Statement::BoExp(expr) => Ok(Binding::Value(Value::Boolean(self.bo_exp(txn, expr)?))),
+            Statement::Expression(expr) => self.conditional(txn, expr).map(Binding::Value),
+            Statement::Empty => Ok(Binding::Items(Vec::new())),
+        }
+    }
+
+    /// Runs the statements of the loop for each element of an array parameter or
+    /// variable, with the fields of object elements as parameters
+    fn for_each(&mut self, txn: &mut Txn, for_each: &ForEach) -> Result<Binding, GraphError> {
+        let name = match &for_each.in_variable {
+            ForLoopInVariable::Identifier(name) | ForLoopInVariable::Parameter(name) => {
+                strip(name.inner())
+            }
+            ForLoopInVariable::Empty => return Err(unsupported("loop without a variable")),
+        };
+        let name = name.strip_prefix("data.").unwrap_or(name);
+        let elements = match (self.params.get(name), self.vars.get(name)) {
+            (Some(Value::Array(values)), _) => values.clone(),
+            (_, Some(Binding::Items(items))) => items.iter().cloned().map(item_value).collect(),
+            _ => {
+                return Err(GraphError::New(format!(
+                    "`{}` can't be looped over, it isn't an array",
+                    name
+                )))
+            }
+        };
+
+        let params = self.params.clone();
+        for element in elements {
+            match (&for_each.for_variables, element) {
+                (ForVariable::ObjectDestructure(_), Value::Object(fields)) => {
+                    self.params.extend(fields);
+                }
+                (ForVariable::Identifier(variable), element) => {
+                    self.params
+                        .insert(variable.inner().clone(), element.clone());
+                    self.vars
+                        .insert(variable.inner().clone(), Binding::Value(element));
+                }
+                _ => {
+                    return Err(GraphError::New(format!(
+                        "The elements of `{}` aren't objects",
+                        name
+                    )))
+                }
+            }
+            for statement in &for_each.statements {
+                self.statement(txn, statement)?;
+            }
+        }
+        self.params = params;
+        Ok(Binding::Items(Vec::new()))
+    }
+
+    fn traversal(&mut self, txn: &mut Txn, tr: &Traversal) -> Result<Binding, GraphError> {
+        let items = match &tr.traversal_type {
+            TraversalType::Ref | TraversalType::Mut | TraversalType::Update(_) => {
+                self.source(txn, tr.source_step.inner())?
+            }
+            TraversalType::FromVar(var) => match tr.source_step.inner() {
+                SourceStep::Identifier(_) | SourceStep::Anonymous | SourceStep::Empty => {
+                    self.items(var.inner())?
+                }
+                source => self.source(txn, source)?,
+            },
+            TraversalType::Nested(var) | TraversalType::NestedFrom(var) => {
+                self.items(var.inner())?
+            }
+            TraversalType::Empty => return Err(unsupported("empty traversal")),
+        };
+
+        let mut binding = Binding::Items(items);
+        for step in &tr.steps {
+            binding = self.step(txn, binding, step.inner())?;
+        }
+
+        match &tr.traversal_type {
+            TraversalType::Update(properties) => {
+                let properties = self.properties(properties)?;
+                let items =
+                    G::new_mut_from(Arc::clone(&self.storage), txn.rw()?, binding.into_items())
+                        .update(properties)
+                        .try_collect_to::<Vec<_>>()?;
+                Ok(Binding::Items(items))
+            }
+            _ => Ok(binding),
+        }
+    }
+
+    fn source(
+        &mut self,
+        txn: &mut Txn,
+        source: &SourceStep,
+    ) -> Result<Vec<TraversalVal>, GraphError> {
+        let storage = Arc::clone(&self.storage);
+        let items = match source {
+            SourceStep::Identifier(var) => self.items(var.inner())?,
+            SourceStep::AddN(add_n) => {
+                let properties = self.properties(&add_n.properties)?;
+                let indices = add_n
+                    .secondary_indices
+                    .as_ref()
+                    .map(|indices| indices.iter().map(String::as_str).collect::<Vec<_>>());
+                G::new_mut(storage, txn.rw()?)
+                    .add_n(add_n.label.inner(), properties, indices.as_deref())
+                    .try_collect_to::<Vec<_>>()?
+            }
+            SourceStep::AddE(add_e) => {
+                let properties = self.properties(&add_e.properties)?;
+                let from = self.id(&add_e.from)?;
+                let to = self.id(&add_e.to)?;
+                G::new_mut(storage, txn.rw()?)
+                    .add_e(
+                        add_e.label.inner(),
+                        properties,
+                        from,
+                        to,
+                        true,
+                        EdgeType::Node,
+                    )
+                    .try_collect_to::<Vec<_>>()?
+            }
+            SourceStep::NFromID(n_from_id) => {
+                let id = self.gen_ref_id(&n_from_id.id)?;
+                G::new(storage, txn.ro())
+                    .n_from_id(&id)
+                    .collect_to::<Vec<_>>()
+            }
+            SourceStep::NFromIndex(n_from_index) => {
+                let key = self.gen_ref(&n_from_index.key)?;
+                G::new(storage, txn.ro())
+                    .n_from_index(n_from_index.index.inner(), &key)
+                    .collect_to::<Vec<_>>()
+            }
+            SourceStep::NFromType(n_from_type) => G::new(storage, txn.ro())
+                .n_from_type(n_from_type.label.inner())
+                .collect_to::<Vec<_>>(),
+            SourceStep::NFromTypeWhere(n_from_type) => {
+                let items = G::new(storage, txn.ro())
+                    .n_from_type(n_from_type.label.inner())
+                    .collect_to::<Vec<_>>();
+                let where_ = Where::Ref(WhereRef {
+                    expr: (*n_from_type.filter).clone(),
+                });
+                self.filter(txn, &where_, items)?
+            }
+            SourceStep::NFromTypeOrdered(ordered) => {
+                let limit = self.limit(&ordered.limit)?;
+                G::new(storage, txn.ro())
+                    .n_from_type_ordered(
+                        ordered.label.inner(),
+                        ordered.property.inner(),
+                        order(&ordered.order),
+                        limit,
+                    )
+                    .collect_to::<Vec<_>>()
+            }
+            SourceStep::EFromID(e_from_id) => {
+                let id = self.gen_ref_id(&e_from_id.id)?;
+                G::new(storage, txn.ro())
+                    .e_from_id(&id)
+                    .collect_to::<Vec<_>>()
+            }
+            SourceStep::EFromIndex(e_from_index) => {
+                let key = self.gen_ref(&e_from_index.key)?;
+                G::new(storage, txn.ro())
+                    .e_from_index(e_from_index.index.inner(), &key)
+                    .collect_to::<Vec<_>>()
+            }
+            SourceStep::EFromType(e_from_type) => G::new(storage, txn.ro())
+                .e_from_type(e_from_type.label.inner())
+                .collect_to::<Vec<_>>(),
+            SourceStep::EFromTypeWhere(e_from_type) => {
+                let items = G::new(storage, txn.ro())
+                    .e_from_type(e_from_type.label.inner())
+                    .collect_to::<Vec<_>>();
+                let where_ = Where::Ref(WhereRef {
+                    expr: (*e_from_type.filter).clone(),
+                });
+                self.filter(txn, &where_, items)?
+            }
+            SourceStep::Anonymous => self.items("val")?,
+            SourceStep::AddV(_) => return Err(unsupported("AddV")),
+            SourceStep::SearchV(_) | SourceStep::SearchVector(_) => {
+                return Err(unsupported("SearchV"))
+            }
+            SourceStep::SearchBM25(_) => return Err(unsupported("SearchBM25")),
+            SourceStep::HybridSearch(_) => return Err(unsupported("HybridSearch")),
+            SourceStep::Analytics(_) => return Err(unsupported("analytics")),
+            SourceStep::Empty => return Err(unsupported("traversal without a source")),
+        };
+        Ok(items)
+    }
+
+    fn step(
+        &mut self,
+        txn: &mut Txn,
+        binding: Binding,
+        step: &Step,
+    ) -> Result<Binding, GraphError> {
+        let items = match (step, binding) {
+            (Step::BoolOp(op), Binding::Value(value)) => {
+                let result = self.bool_op(op, &value)?;
+                return Ok(Binding::Value(Value::Boolean(result)));
+            }
+            (_, Binding::Items(items)) => items,
+            (step, Binding::Value(_)) => {
+                return Err(GraphError::New(format!(
+                    "{:?} can't follow a step returning a single value",
+                    step
+                )))
+            }
+        };
+
+        let storage = Arc::clone(&self.storage);
+        let items = match step {
+            Step::Out(out) => {
+                let edge_type = edge_type(&out.edge_type);
+                G::new_from(storage, txn.ro(), items)
+                    .out(out.label.inner(), &edge_type)
+                    .collect_to::<Vec<_>>()
+            }
+            Step::In(in_) => {
+                let edge_type = edge_type(&in_.edge_type);
+                G::new_from(storage, txn.ro(), items)
+                    .in_(in_.label.inner(), &edge_type)
+                    .collect_to::<Vec<_>>()
+            }
+            Step::OutE(out_e) => G::new_from(storage, txn.ro(), items)
+                .out_e(out_e.label.inner())
+                .collect_to::<Vec<_>>(),
+            Step::InE(in_e) => G::new_from(storage, txn.ro(), items)
+                .in_e(in_e.label.inner())
+                .collect_to::<Vec<_>>(),
+            Step::FromN => G::new_from(storage, txn.ro(), items)
+                .from_n()
+                .collect_to::<Vec<_>>(),
+            Step::ToN => G::new_from(storage, txn.ro(), items)
+                .to_n()
+                .collect_to::<Vec<_>>(),
+            Step::OutCount(out_count) => {
+                let count =
+                    G::new_from(storage, txn.ro(), items).out_count(out_count.label.inner())?;
+                return Ok(Binding::Value(Value::from(count)));
+            }
+            Step::InCount(in_count) => {
+                let count = G::new_from(storage, txn.ro(), items).in_count(in_count.label.inner())?;
+                return Ok(Binding::Value(Value::from(count)));
+            }
+            Step::Count => return Ok(Binding::Value(Value::from(items.len()))),
+            Step::Where(where_) => self.filter(txn, where_, items)?,
+            Step::Range(range) => {
+                let start = as_usize(&self.gen_ref(&range.start)?)?;
+                let end = as_usize(&self.gen_ref(&range.end)?)?;
+                G::new_from(storage, txn.ro(), items)
+                    .range(start, end)
+                    .collect_to::<Vec<_>>()
+            }
+            Step::OrderBy(order_by) => {
+                let limit = self.limit(&order_by.limit)?;
+                G::new_from(storage, txn.ro(), items)
+                    .order_by(order_by.property.inner(), order(&order_by.order), limit)
+                    .collect_to::<Vec<_>>()
+            }
+            Step::AsOf(as_of) => G::new_from(storage, txn.ro(), items)
+                .as_of(self.gen_ref(&as_of.at)?)
+                .collect_to::<Vec<_>>(),
+            Step::Dedup => G::new_from(storage, txn.ro(), items)
+                .dedup()
+                .collect_to::<Vec<_>>(),
+            Step::BoolOp(op) => {
+                // a property compared for each item, true if there is one and all match
+                let mut result = !items.is_empty();
+                for item in items {
+                    result &= self.bool_op(op, &item_value(item))?;
+                }
+                return Ok(Binding::Value(Value::Boolean(result)));
+            }
+            Step::PropertyFetch(property) => G::new_from(storage, txn.ro(), items)
+                .check_property(property.inner())
+                .collect_to::<Vec<_>>(),
+            Step::PropertyPath(path) => {
+                let segments = path
+                    .path
+                    .iter()
+                    .map(|segment| match segment {
+                        GeneratedPathSegment::Field(field) => PathSegment::Field(field.inner()),
+                        GeneratedPathSegment::Index(index) => PathSegment::Index(*index),
+                    })
+                    .collect::<Vec<_>>();
+                G::new_from(storage, txn.ro(), items)
+                    .check_property_path(path.property.inner(), &segments)
+                    .collect_to::<Vec<_>>()
+            }
+            Step::Score => G::new_from(storage, txn.ro(), items)
+                .check_score()
+                .collect_to::<Vec<_>>(),
+            Step::Remapping(_) => return Err(unsupported("remapping")),
+            Step::ShortestPath(_) | Step::ShortestPathWeighted(_) => {
+                return Err(unsupported("ShortestPath"))
+            }
+            Step::SearchVector(_) => return Err(unsupported("SearchV")),
+        };
+        Ok(Binding::Items(items))
+    }
+
+    /// The items passing the filter of a `WHERE` step
+    fn filter(
+        &mut self,
+        txn: &mut Txn,
+        where_: &Where,
+        items: Vec<TraversalVal>,
+    ) -> Result<Vec<TraversalVal>, GraphError> {
+        let mut kept = Vec::with_capacity(items.len());
+        for item in items {
+            if self.matches(txn, where_, &item)? {
+                kept.push(item);
+            }
+        }
+        Ok(kept)
+    }
+
+    /// Whether an item passes the filter of a `WHERE` step, with the item bound to the
+    /// `val` the nested traversals of the filter start from
+    fn matches(
+        &mut self,
+        txn: &mut Txn,
+        where_: &Where,
+        item: &TraversalVal,
+    ) -> Result<bool, GraphError> {
+        let outer = self
+            .vars
+            .insert("val".to_string(), Binding::Items(vec![item.clone()]));
+        let result = match where_ {
+            Where::Exists(exists) => self.traversal(txn, &exists.tr).map(|b| b.is_truthy()),
+            Where::Ref(where_ref) => self.bo_exp(txn, &where_ref.expr),
+            Where::Mut(where_mut) => self.bo_exp(txn, &where_mut.expr),
+        };
+        match outer {
+            Some(outer) => self.vars.insert("val".to_string(), outer),
+            None => self.vars.remove("val"),
+        };
+        result
+    }
+
+    fn bo_exp(&mut self, txn: &mut Txn, expr: &BoExp) -> Result<bool, GraphError> {
+        match expr {
+            BoExp::And(exprs) => {
+                for expr in exprs {
+                    if !self.bo_exp(txn, expr)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            BoExp::Or(exprs) => {
+                for expr in exprs {
+                    if self.bo_exp(txn, expr)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            BoExp::Exists(tr) | BoExp::Expr(tr) => Ok(self.traversal(txn, tr)?.is_truthy()),
+            BoExp::Not(expr) => Ok(!self.bo_exp(txn, expr)?),
+            BoExp::Compared { expr, op } => {
+                let value = self.expression(expr)?;
+                self.compared_op(op, &value)
+            }
+        }
+    }
+
+    fn bool_op(&self, op: &BoolOp, value: &Value) -> Result<bool, GraphError> {
+        if let GeneratedValue::Computed(_) = op.value() {
+            return self.compared_op(op, value);
+        }
+        let result = match op {
+            BoolOp::Gt(gt) => value.total_cmp(&self.gen_value(&gt.value)?).is_gt(),
+            BoolOp::Gte(gte) => value.total_cmp(&self.gen_value(&gte.value)?).is_ge(),
+            BoolOp::Lt(lt) => value.total_cmp(&self.gen_value(&lt.value)?).is_lt(),
+            BoolOp::Lte(lte) => value.total_cmp(&self.gen_value(&lte.value)?).is_le(),
+            BoolOp::Eq(eq) => value.total_cmp(&self.gen_value(&eq.value)?).is_eq(),
+            BoolOp::Neq(neq) => value.total_cmp(&self.gen_value(&neq.value)?).is_ne(),
+            BoolOp::Contains(contains) => match (value, self.gen_value(&contains.value)?) {
+                (Value::String(s), Value::String(part)) => s.contains(&part),
+                (Value::Array(values), element) => {
+                    values.iter().any(|v| v.total_cmp(&element).is_eq())
+                }
+                _ => false,
+            },
+        };
+        Ok(result)
+    }
+
+    /// Compares like the generated code does when a side of the comparison is computed,
+    /// by value across numeric types and never matching an empty value
+    fn compared_op(&self, op: &BoolOp, value: &Value) -> Result<bool, GraphError> {
+        let matches: fn(Ordering) -> bool = match op {
+            BoolOp::Gt(_) => Ordering::is_gt,
+            BoolOp::Gte(_) => Ordering::is_ge,
+            BoolOp::Lt(_) => Ordering::is_lt,
+            BoolOp::Lte(_) => Ordering::is_le,
+            BoolOp::Eq(_) => Ordering::is_eq,
+            BoolOp::Neq(_) => Ordering::is_ne,
+            BoolOp::Contains(_) => return Err(unsupported("CONTAINS of a computed value")),
+        };
+        let other = self.gen_value(op.value())?;
+        Ok(expression::compare(value, &other).is_some_and(matches))
+    }
+
+    /// Evaluates an expression that may branch on conditions, which can traverse the graph
+    fn conditional(
+        &mut self,
+        txn: &mut Txn,
+        expr: &GeneratedExpression,
+    ) -> Result<Value, GraphError> {
+        match expr {
+            GeneratedExpression::Conditional {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                let branch = if self.bo_exp(txn, condition)? {
+                    then
+                } else {
+                    otherwise
+                };
+                self.conditional(txn, branch)
+            }
+            GeneratedExpression::Condition { condition, .. } => {
+                Ok(Value::Boolean(self.bo_exp(txn, condition)?))
+            }
+            expr => self.expression(expr),
+        }
+    }
+
+    /// Evaluates an arithmetic or string expression, reading properties of the first item
+    /// of the variable they are fetched from
+    fn expression(&self, expr: &GeneratedExpression) -> Result<Value, GraphError> {
+        let value = match expr {
+            GeneratedExpression::Property { variable, property } => {
+                match self.items(variable.inner())?.first() {
+                    Some(item) => expression::property(item, property.inner()),
+                    None => Value::Empty,
+                }
+            }
+            GeneratedExpression::Value(value) => self.gen_value(value)?,
+            GeneratedExpression::Arithmetic { op, lhs, rhs } => {
+                let (lhs, rhs) = (self.expression(lhs)?, self.expression(rhs)?);
+                match op {
+                    ArithmeticOp::Add => expression::add(lhs, rhs),
+                    ArithmeticOp::Sub => expression::sub(lhs, rhs),
+                    ArithmeticOp::Mul => expression::mul(lhs, rhs),
+                    ArithmeticOp::Div => expression::div(lhs, rhs),
+                }
+            }
+            GeneratedExpression::Function { function, arg } => {
+                let arg = self.expression(arg)?;
+                match function {
+                    ExpressionFunction::Lower => expression::lower(arg),
+                    ExpressionFunction::Upper => expression::upper(arg),
+                    ExpressionFunction::Len => expression::len(arg),
+                    ExpressionFunction::Round => expression::round(arg),
+                }
+            }
+            GeneratedExpression::Conditional { .. } => {
+                return Err(unsupported("IF within an expression"))
+            }
+            GeneratedExpression::Condition { .. } => {
+                return Err(unsupported("condition within an expression"))
+            }
+        };
+        Ok(value)
+    }
+
+    /// What a variable holds, or the value of a parameter or literal
+    fn lookup(&self, identifier: &GenRef<String>) -> Result<Binding, GraphError> {
+        let binding = match identifier {
+            GenRef::Literal(_) | GenRef::Id(_) | GenRef::Unknown => None,
+            identifier => self.vars.get(strip(identifier.inner())),
+        };
+        match binding {
+            Some(binding) => Ok(binding.clone()),
+            None => self.gen_ref(identifier).map(Binding::Value),
+        }
+    }
+
+    /// Items of a variable, a single value for variables holding one
+    fn items(&self, name: &str) -> Result<Vec<TraversalVal>, GraphError> {
+        let name = strip(name);
+        match self.vars.get(name) {
+            Some(Binding::Items(items)) => Ok(items.clone()),
+            Some(Binding::Value(value)) => Ok(vec![TraversalVal::Value(value.clone())]),
+            None => Err(GraphError::New(format!("Unknown variable `{}`", name))),
+        }
+    }
+
+    fn properties(
+        &self,
+        properties: &Option<Vec<(String, GeneratedValue)>>,
+    ) -> Result<Option<Vec<(String, Value)>>, GraphError> {
+        let Some(properties) = properties else {
+            return Ok(None);
+        };
+        let mut values = Vec::with_capacity(properties.len());
+        for (name, value) in properties {
+            // defaults the schema doesn't set are left out
+            if let GeneratedValue::Unknown = value {
+                continue;
+            }
+            values.push((name.clone(), self.gen_value(value)?));
+        }
+        Ok(Some(values))
+    }
+
+    fn limit(&self, limit: &Option<GenRef<String>>) -> Result<Option<usize>, GraphError> {
+        limit
+            .as_ref()
+            .map(|limit| as_usize(&self.gen_ref(limit)?))
+            .transpose()
+    }
+
+    fn gen_value(&self, value: &GeneratedValue) -> Result<Value, GraphError> {
+        match value {
+            GeneratedValue::Literal(value)
+            | GeneratedValue::Identifier(value)
+            | GeneratedValue::Primitive(value)
+            | GeneratedValue::Parameter(value) => self.gen_ref(value),
+            GeneratedValue::Computed(expr) => self.expression(expr),
+            GeneratedValue::Unknown => Ok(Value::Empty),
+        }
+    }
+
+    fn gen_ref(&self, value: &GenRef<String>) -> Result<Value, GraphError> {
+        match value {
+            GenRef::Literal(s) => Ok(Value::String(s.clone())),
+            GenRef::Id(name) => self.param(name),
+            GenRef::Unknown => Ok(Value::Empty),
+            value => self.expr(value.inner()),
+        }
+    }
+
+    /// Evaluates an expression of the generated code, as the IR holds the values of a
+    /// query: parameters (`data.name`), variables (`user`, `user.id()`), literals and
+    /// the few constructors the analyzer writes for values
+    fn expr(&self, expr: &str) -> Result<Value, GraphError> {
+        let expr = strip(expr);
+        if let Some(date) = expr
+            .strip_suffix(".parse::<DateTime<Utc>>().unwrap()")
+            .and_then(unquote)
+        {
+            return date
+                .parse::<DateTime<Utc>>()
+                .map(Value::DateTime)
+                .map_err(|e| GraphError::New(format!("Invalid date `{}`: {}", date, e)));
+        }
+        if let Some(s) = unquote(expr) {
+            return Ok(Value::String(s.to_string()));
+        }
+        match expr {
+            "chrono::Utc::now()" => return Ok(Value::DateTime(Utc::now())),
+            "uuid::Uuid::new_v4().to_string()" => {
+                return Ok(Value::String(uuid::Uuid::new_v4().to_string()))
+            }
+            "helixdb::protocol::items::ulid()" => return Ok(Value::String(ulid())),
+            "Value::Empty" => return Ok(Value::Empty),
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            _ => {}
+        }
+        if let Some(inner) = call(expr, "Value::from(") {
+            return self.expr(inner);
+        }
+        if let Some(inner) = call(expr, "Value::Array(vec![").and_then(|s| s.strip_suffix(']')) {
+            return self.array(inner);
+        }
+        if let Some(inner) = expr.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            return self.array(inner);
+        }
+        if let Some(inner) =
+            call(expr, "Value::Object(HashMap::from([").and_then(|s| s.strip_suffix("])"))
+        {
+            let mut fields = HashMap::new();
+            for field in split(inner, ',') {
+                let pair = field.strip_prefix('(').and_then(|f| f.strip_suffix(')'));
+                match pair.map(|pair| split(pair, ',')).as_deref() {
+                    Some([key, value]) => match self.expr(key)? {
+                        Value::String(key) => {
+                            fields.insert(key, self.expr(value)?);
+                        }
+                        key => return Err(GraphError::New(format!("Invalid key `{:?}`", key))),
+                    },
+                    _ => return Err(GraphError::New(format!("Invalid field `{}`", field))),
+                }
+            }
+            return Ok(Value::Object(fields));
+        }
+        let terms = split(expr, '+');
+        if terms.len() > 1 {
+            let values = terms
+                .iter()
+                .map(|term| self.expr(term))
+                .collect::<Result<Vec<_>, _>>()?;
+            return add(&values);
+        }
+        if let Some(name) = expr.strip_prefix("data.") {
+            return self.param(name);
+        }
+        if let Some(var) = expr.strip_suffix(".id()") {
+            return self.var_id(var).map(Value::U128);
+        }
+        if let Ok(i) = expr.parse::<i32>() {
+            return Ok(Value::I32(i));
+        }
+        if let Ok(i) = expr.parse::<i64>() {
+            return Ok(Value::I64(i));
+        }
+        if let Ok(f) = expr.parse::<f64>() {
+            return Ok(Value::F64(f));
+        }
+        match self.vars.get(expr) {
+            Some(Binding::Value(value)) => Ok(value.clone()),
+            Some(Binding::Items(items)) => match items.as_slice() {
+                [TraversalVal::Value(value)] => Ok(value.clone()),
+                _ => Err(GraphError::New(format!("`{}` isn't a single value", expr))),
+            },
+            None => Err(GraphError::New(format!("Unknown value `{}`", expr))),
+        }
+    }
+
+    fn array(&self, elements: &str) -> Result<Value, GraphError> {
+        split(elements, ',')
+            .into_iter()
+            .filter(|element| !element.trim().is_empty())
+            .map(|element| self.expr(element))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array)
+    }
+
+    /// A parameter, or a field of an object parameter (`data.address.city`)
+    fn param(&self, name: &str) -> Result<Value, GraphError> {
+        let mut path = strip(name).split('.');
+        let name = path.next().unwrap_or_default();
+        let mut value = self
+            .params
+            .get(name)
+            .ok_or_else(|| GraphError::New(format!("Missing parameter `{}`", name)))?;
+        for field in path {
+            value = match value {
+                Value::Object(fields) => fields.get(field).unwrap_or(&Value::Empty),
+                _ => &Value::Empty,
+            };
+        }
+        Ok(value.clone())
+    }
+
+    fn id(&self, value: &GeneratedValue) -> Result<u128, GraphError> {
+        match value {
+            GeneratedValue::Literal(value)
+            | GeneratedValue::Identifier(value)
+            | GeneratedValue::Primitive(value)
+            | GeneratedValue::Parameter(value) => self.gen_ref_id(value),
+            GeneratedValue::Computed(_) => {
+                Err(GraphError::New("Ids can't be computed".to_string()))
+            }
+            GeneratedValue::Unknown => Err(GraphError::New("Missing id".to_string())),
+        }
+    }
+
+    fn gen_ref_id(&self, value: &GenRef<String>) -> Result<u128, GraphError> {
+        let value = match value {
+            GenRef::Literal(s) => Value::String(s.clone()),
+            GenRef::Id(name) => self.param(name)?,
+            GenRef::Unknown => return Err(GraphError::New("Missing id".to_string())),
+            value => {
+                let expr = strip(value.inner());
+                if self.vars.contains_key(expr) || expr.ends_with(".id()") {
+                    return self.var_id(expr.trim_end_matches(".id()"));
+                }
+                self.expr(expr)?
+            }
+        };
+        match value {
+            Value::String(s) => uuid::Uuid::parse_str(&s)
+                .map(|uuid| uuid.as_u128())
+                .map_err(|e| GraphError::New(format!("Invalid id `{}`: {}", s, e))),
+            Value::U128(id) => Ok(id),
+            value => Err(GraphError::New(format!("Invalid id `{:?}`", value))),
+        }
+    }
+
+    /// Id of the first item of a variable
+    fn var_id(&self, name: &str) -> Result<u128, GraphError> {
+        match self.vars.get(strip(name)) {
+            Some(Binding::Items(items)) => items
+                .iter()
+                .find(|item| {
+                    matches!(
+                        item,
+                        TraversalVal::Node(_) | TraversalVal::Edge(_) | TraversalVal::Vector(_)
+                    )
+                })
+                .map(|item| item.id())
+                .ok_or_else(|| GraphError::New(format!("`{}` is empty", name))),
+            Some(Binding::Value(value)) => Err(GraphError::New(format!(
+                "`{}` holds {:?}, not an item with an id",
+                name, value
+            ))),
+            None => Err(GraphError::New(format!("Unknown variable `{}`", name))),
+        }
+    }
+}
+
+impl Binding {
+    fn into_items(self) -> Vec<TraversalVal> {
+        match self {
+            Binding::Items(items) => items,
+            Binding::Value(value) => vec![TraversalVal::Value(value)],
+        }
+    }
+
+    /// Whether a traversal used as a condition holds, true for `true` and for any
+    /// items
+    fn is_truthy(&self) -> bool {
+        match self {
+            Binding::Items(items) => !items.is_empty(),
+            Binding::Value(Value::Boolean(b)) => *b,
+            Binding::Value(Value::Empty) => false,
+            Binding::Value(_) => true,
+        }
+    }
+}
+
+/// The value of an item of a traversal, such as a fetched property, or its id
+fn item_value(item: TraversalVal) -> Value {
+    match item {
+        TraversalVal::Value(value) => value,
+        TraversalVal::Node(node) => Value::U128(node.id),
+        TraversalVal::Edge(edge) => Value::U128(edge.id),
+        TraversalVal::Vector(vector) => Value::U128(vector.id),
+        _ => Value::Empty,
+    }
+}
+
+/// Casts a JSON parameter to the type the query declares for it
+fn cast(query: &Query, param: &Parameter, value: JsonValue) -> Result<Value, GraphError> {
+    cast_type(query, &param.name, &param.field_type, value)
+}
+
+fn cast_type(
+    query: &Query,
+    name: &str,
+    field_type: &GeneratedType,
+    value: JsonValue,
+) -> Result<Value, GraphError> {
+    let mismatch = || {
+        GraphError::New(format!(
+            "Parameter `{}` must be of type {}",
+            name, field_type
+        ))
+    };
+    let value = match (field_type, value) {
+        (GeneratedType::RustType(rust_type), value) => {
+            let int = value.as_i64();
+            let uint = value.as_u64();
+            match rust_type {
+                RustType::String => Value::String(value.as_str().ok_or_else(mismatch)?.to_string()),
+                RustType::Bool => Value::Boolean(value.as_bool().ok_or_else(mismatch)?),
+                RustType::F32 => Value::F32(value.as_f64().ok_or_else(mismatch)? as f32),
+                RustType::F64 => Value::F64(value.as_f64().ok_or_else(mismatch)?),
+                RustType::I8 => {
+                    Value::I8(int.and_then(|i| i.try_into().ok()).ok_or_else(mismatch)?)
+                }
+                RustType::I16 => {
+                    Value::I16(int.and_then(|i| i.try_into().ok()).ok_or_else(mismatch)?)
+                }
+                RustType::I32 => {
+                    Value::I32(int.and_then(|i| i.try_into().ok()).ok_or_else(mismatch)?)
+                }
+                RustType::I64 => Value::I64(int.ok_or_else(mismatch)?),
+                RustType::U8 => {
+                    Value::U8(uint.and_then(|i| i.try_into().ok()).ok_or_else(mismatch)?)
+                }
+                RustType::U16 => {
+                    Value::U16(uint.and_then(|i| i.try_into().ok()).ok_or_else(mismatch)?)
+                }
+                RustType::U32 => {
+                    Value::U32(uint.and_then(|i| i.try_into().ok()).ok_or_else(mismatch)?)
+                }
+                RustType::U64 => Value::U64(uint.ok_or_else(mismatch)?),
+                RustType::U128 => Value::U128(uint.ok_or_else(mismatch)? as u128),
+                RustType::Uuid => {
+                    let id = value.as_str().ok_or_else(mismatch)?;
+                    uuid::Uuid::parse_str(id).map_err(|_| mismatch())?;
+                    Value::String(id.to_string())
+                }
+                RustType::Date => {
+                    Value::from(Date::new(&Value::from(value)).map_err(|_| mismatch())?)
+                }
+            }
+        }
+        (GeneratedType::Vec(element_type), JsonValue::Array(values)) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| cast_type(query, name, element_type, value))
+                .collect::<Result<_, _>>()?,
+        ),
+        (GeneratedType::Object(object), JsonValue::Object(mut fields)) => {
+            let parameters = query
+                .sub_parameters
+                .iter()
+                .find(|(name, _)| name == object.inner())
+                .map(|(_, parameters)| parameters)
+                .ok_or_else(mismatch)?;
+            Value::Object(
+                parameters
+                    .iter()
+                    .map(|param| {
+                        let value = fields.remove(&param.name).unwrap_or(JsonValue::Null);
+                        Ok((param.name.clone(), cast(query, param, value)?))
+                    })
+                    .collect::<Result<_, GraphError>>()?,
+            )
+        }
+        (GeneratedType::Variable(_), value) => Value::from(value),
+        _ => return Err(mismatch()),
+    };
+    Ok(value)
+}
+
+fn unsupported(step: &str) -> GraphError {
+    GraphError::New(format!(
+        "{} isn't supported by the interpreter, deploy the query to run it",
+        step
+    ))
+}
+
+fn order(order: &Order) -> HelixOrder {
+    match order {
+        Order::Asc => HelixOrder::Asc,
+        Order::Desc => HelixOrder::Desc,
+    }
+}
+
+fn edge_type(edge_type: &GenRef<String>) -> EdgeType {
+    match edge_type.inner().ends_with("Vec") {
+        true => EdgeType::Vec,
+        false => EdgeType::Node,
+    }
+}
+
+fn as_usize(value: &Value) -> Result<usize, GraphError> {
+    match value.as_f64() {
+        Some(f) if f >= 0.0 => Ok(f as usize),
+        _ => Err(GraphError::New(format!(
+            "Expected a count, got {:?}",
+            value
+        ))),
+    }
+}
+
+fn add(values: &[Value]) -> Result<Value, GraphError> {
+    let is_int = values
+        .iter()
+        .all(|value| !matches!(value, Value::F32(_) | Value::F64(_)));
+    let mut sum = 0.0;
+    for value in values {
+        sum += value
+            .as_f64()
+            .ok_or_else(|| GraphError::New(format!("Can't add {:?}", value)))?;
+    }
+    match is_int {
+        true => Ok(Value::I64(sum as i64)),
+        false => Ok(Value::F64(sum)),
+    }
+}
+
+/// Strips the borrows, clones and casts the generated code wraps values in
+fn strip(expr: &str) -> &str {
+    let mut expr = expr.trim();
+    loop {
+        let stripped = expr
+            .trim_start_matches('&')
+            .trim_start_matches('*')
+            .trim_start_matches("mut ")
+            .trim_end_matches(".clone()")
+            .trim_end_matches(" as usize")
+            .trim_end_matches(".to_string()")
+            .trim();
+        if stripped == expr {
+            return expr;
+        }
+        expr = stripped;
+    }
+}
+
+/// The contents of a string literal
+fn unquote(expr: &str) -> Option<&str> {
+    expr.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .filter(|s| !s.contains('"'))
+}
+
+/// The arguments of a call to `function`, which includes the opening bracket
+fn call<'e>(expr: &'e str, function: &str) -> Option<&'e str> {
+    expr.strip_prefix(function)?.strip_suffix(')')
+}
+
+/// Splits on a separator outside of brackets and string literals
+fn split(expr: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0, false, 0);
+    for (i, c) in expr.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' | '[' | '{' if !quoted => depth += 1,
+            ')' | ']' | '}' if !quoted => depth -= 1,
+            c if c == separator && !quoted && depth == 0 => {
+                parts.push(expr[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(expr[start..].trim());
+    parts
+}
@@ -0,0 +1,229 @@
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+            interpreter::Interpreter,
+            ops::{
+                g::G,
+                out::out::OutAdapter,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                    n_from_id::NFromIdAdapter,
+                    n_from_type::NFromTypeAdapter,
+                },
+                tr_val::{Traversable, TraversalVal},
+            },
+        },
+        storage_core::storage_core::HelixGraphStorage,
+        types::GraphError,
+    },
+    helixc::{
+        analyzer::analyzer::analyze,
+        generator::generator_types::Query,
+        parser::helix_parser::{write_to_temp_file, HelixParser},
+    },
+    props,
+    protocol::{return_values::ReturnValue, value::Value},
+};
+
+const QUERIES: &str = r#"
+N::User { name: String, age: I64 }
+E::Follows { From: User, To: User }
+
+QUERY followed(id: ID) =>
+    users <- N<User>(id)::Out<Follows>
+    RETURN users
+
+QUERY olderThan(age: I64) =>
+    users <- N<User>::WHERE(_::{age}::GT(age))
+    RETURN users
+
+QUERY countUsers() =>
+    count <- N<User>::COUNT
+    RETURN count
+
+QUERY addUser(name: String, age: I64) =>
+    user <- AddN<User>({name: name, age: age})
+    RETURN user
+"#;
+
+/// The queries analyzed like the interpreter gets them, along with the generated code
+/// stripped of whitespace
+fn queries() -> (HashMap<String, Query>, String) {
+    let source = HelixParser::parse_source(&write_to_temp_file(vec![QUERIES])).unwrap();
+    let (diagnostics, generated) = analyze(&source);
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let code = generated.to_string().split_whitespace().collect();
+    let queries = generated
+        .queries
+        .into_iter()
+        .map(|query| (query.name.clone(), query))
+        .collect();
+    (queries, code)
+}
+
+/// alice(30) follows bob(25) and carol(35), returning their ids
+fn graph() -> (Arc<HelixGraphStorage>, Vec<u128>) {
+    let engine = HelixGraphEngine::new(HelixGraphEngineOpts::in_memory()).unwrap();
+    let storage = Arc::clone(&engine.storage);
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let ids = [("alice", 30), ("bob", 25), ("carol", 35)]
+        .into_iter()
+        .map(|(name, age)| {
+            G::new_mut(Arc::clone(&storage), &mut txn)
+                .add_n(
+                    "User",
+                    Some(props! { "name" => name, "age" => age as i64 }),
+                    None,
+                )
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    for to in &ids[1..] {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_e("Follows", None, ids[0], *to, true, EdgeType::Node)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+    (storage, ids)
+}
+
+fn interpret(
+    storage: &Arc<HelixGraphStorage>,
+    query: &Query,
+    params: JsonValue,
+) -> Result<JsonValue, GraphError> {
+    let values = Interpreter::new(Arc::clone(storage), query, &params)?.run()?;
+    Ok(to_json(&values))
+}
+
+/// Serializes the values the way handlers answer with them
+fn to_json(values: &HashMap<String, ReturnValue>) -> JsonValue {
+    serde_json::from_slice(&sonic_rs::to_vec(values).unwrap()).unwrap()
+}
+
+fn returning(name: &str, values: Vec<TraversalVal>) -> JsonValue {
+    let remapping_vals = RefCell::new(HashMap::new());
+    to_json(&HashMap::from([(
+        name.to_string(),
+        ReturnValue::from_traversal_value_array_with_mixin(values, remapping_vals.borrow_mut()),
+    )]))
+}
+
+fn names(values: &JsonValue, name: &str) -> Vec<String> {
+    let mut names = values[name]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|value| value["name"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_traversal_matches_compiled_query() {
+    let (queries, code) = queries();
+    assert!(code.contains(".n_from_id(&data.id).out(\"Follows\",&EdgeType::Node)"));
+    let (storage, ids) = graph();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let users = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&ids[0])
+        .out("Follows", &EdgeType::Node)
+        .collect_to::<Vec<_>>();
+    let compiled = returning("users", users);
+    drop(txn);
+
+    let interpreted = interpret(
+        &storage,
+        &queries["followed"],
+        json!({ "id": uuid::Uuid::from_u128(ids[0]).to_string() }),
+    )
+    .unwrap();
+    assert_eq!(interpreted, compiled);
+    assert_eq!(names(&interpreted, "users"), ["bob", "carol"]);
+}
+
+#[test]
+fn test_filter_matches_compiled_query() {
+    let (queries, code) = queries();
+    assert!(code.contains(".n_from_type_where(\"User\""));
+    let (storage, _) = graph();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let users = G::new(Arc::clone(&storage), &txn)
+        .n_from_type_where("User", |val, _| {
+            Ok(val.check_property("age").map_or(false, |v| *v > &28i64))
+        })
+        .collect_to::<Vec<_>>();
+    let compiled = returning("users", users);
+    drop(txn);
+
+    let interpreted = interpret(&storage, &queries["olderThan"], json!({ "age": 28 })).unwrap();
+    assert_eq!(interpreted, compiled);
+    assert_eq!(names(&interpreted, "users"), ["alice", "carol"]);
+}
+
+#[test]
+fn test_count_matches_compiled_query() {
+    let (queries, code) = queries();
+    assert!(code.contains(".n_from_type(\"User\").count()"));
+    let (storage, _) = graph();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let count = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("User")
+        .count();
+    let compiled = to_json(&HashMap::from([(
+        "count".to_string(),
+        ReturnValue::from(Value::from(count)),
+    )]));
+    drop(txn);
+
+    let interpreted = interpret(&storage, &queries["countUsers"], JsonValue::Null).unwrap();
+    assert_eq!(interpreted, compiled);
+    assert_eq!(interpreted, json!({ "count": 3 }));
+}
+
+#[test]
+fn test_mutation_committed_like_compiled_query() {
+    let (queries, code) = queries();
+    assert!(code.contains(".add_n(\"User\""));
+    let (storage, _) = graph();
+
+    let added = interpret(
+        &storage,
+        &queries["addUser"],
+        json!({ "name": "dave", "age": 40 }),
+    )
+    .unwrap();
+    assert_eq!(added["user"][0]["name"], "dave");
+    assert_eq!(added["user"][0]["age"], 40);
+
+    // the added node is read back the same way by both
+    let txn = storage.graph_env.read_txn().unwrap();
+    let users = G::new(Arc::clone(&storage), &txn)
+        .n_from_type_where("User", |val, _| {
+            Ok(val.check_property("age").map_or(false, |v| *v > &38i64))
+        })
+        .collect_to::<Vec<_>>();
+    let compiled = returning("users", users);
+    drop(txn);
+    let interpreted = interpret(&storage, &queries["olderThan"], json!({ "age": 38 })).unwrap();
+    assert_eq!(interpreted, compiled);
+    assert_eq!(names(&interpreted, "users"), ["dave"]);
+}
+
+#[test]
+fn test_parameters_checked_against_declared_types() {
+    let (queries, _) = queries();
+    let (storage, _) = graph();
+    assert!(interpret(&storage, &queries["olderThan"], json!({ "age": "old" })).is_err());
+}
@@ -1,7 +1,16 @@
 pub mod config;
 pub mod graph_core;
+#[cfg(feature = "compiler")]
+pub mod interpreter;
 pub mod ops;
+pub mod projection;
+pub mod query_limits;
+pub mod snapshot;
 pub mod traversal_iter;
 
+#[cfg(all(test, feature = "compiler"))]
+mod interpreter_tests;
 #[cfg(test)]
 mod traversal_tests;
+#[cfg(test)]
+mod snapshot_tests;
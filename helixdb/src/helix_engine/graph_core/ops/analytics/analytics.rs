@@ -0,0 +1,253 @@
+use crate::helix_storage::heed3::{RoTxn, RwTxn};
+
+use super::super::tr_val::TraversalVal;
+use crate::{
+    helix_engine::{
+        analytics::{
+            adjacency::Adjacency,
+            analytics::{
+                betweenness, connected_components, label_propagation, page_rank, DEFAULT_DAMPING,
+            },
+        },
+        cdc::cdc::{ChangeEvent, ChangeOp, ChangeTarget},
+        graph_core::traversal_iter::{RoTraversalIterator, RwTraversalIterator},
+        storage_core::{
+            storage_core::HelixGraphStorage, storage_methods::StorageMethods, wal::WalOp,
+        },
+        types::GraphError,
+    },
+    protocol::value::Value,
+};
+use std::{collections::HashMap, sync::Arc};
+
+pub struct AnalyticsIterator<'a> {
+    txn: &'a RoTxn<'a>,
+    iter: std::vec::IntoIter<Result<(u128, Value), GraphError>>,
+    storage: Arc<HelixGraphStorage>,
+    property: &'static str,
+}
+
+impl<'a> Iterator for AnalyticsIterator<'a> {
+    type Item = Result<TraversalVal, GraphError>;
+
+    /// Returns the next node with its result set as a property
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, value) = match self.iter.next()? {
+            Ok(result) => result,
+            Err(e) => return Some(Err(e)),
+        };
+        match self.storage.get_node(self.txn, &id) {
+            Ok(mut node) => {
+                node.properties
+                    .get_or_insert_with(HashMap::new)
+                    .insert(self.property.to_string(), value);
+                Some(Ok(TraversalVal::Node(node)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+pub trait AnalyticsAdapter<'a>: Iterator<Item = Result<TraversalVal, GraphError>> {
+    /// Returns all nodes with their PageRank set as the `page_rank` property, highest first
+    ///
+    /// # Arguments
+    ///
+    /// * `edge_label` - Only follow edges with this label, any edge if `None`
+    /// * `iterations` - The number of power iterations to run
+    fn page_rank(
+        self,
+        edge_label: Option<&str>,
+        iterations: usize,
+    ) -> RoTraversalIterator<'a, AnalyticsIterator<'a>>;
+
+    /// Returns all nodes with their approximate betweenness centrality set as the
+    /// `betweenness` property, highest first
+    ///
+    /// # Arguments
+    ///
+    /// * `edge_label` - Only follow edges with this label, any edge if `None`
+    /// * `samples` - The number of source nodes to sample shortest paths from
+    fn betweenness(
+        self,
+        edge_label: Option<&str>,
+        samples: usize,
+    ) -> RoTraversalIterator<'a, AnalyticsIterator<'a>>;
+
+    /// Returns all nodes with the number of their weakly connected component set as the
+    /// `component` property
+    fn connected_components(
+        self,
+        edge_label: Option<&str>,
+    ) -> RoTraversalIterator<'a, AnalyticsIterator<'a>>;
+}
+
+/// Pairs scores with their node ids, highest score first
+fn ranked(adjacency: &Adjacency, scores: Vec<f64>) -> Vec<Result<(u128, Value), GraphError>> {
+    let mut ranked = adjacency
+        .node_ids
+        .iter()
+        .copied()
+        .zip(scores)
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    ranked
+        .into_iter()
+        .map(|(id, score)| Ok((id, Value::F64(score))))
+        .collect()
+}
+
+impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> RoTraversalIterator<'a, I> {
+    /// Runs an algorithm over the adjacency of the snapshot and returns its results
+    fn analytics<F>(
+        self,
+        edge_label: Option<&str>,
+        property: &'static str,
+        run: F,
+    ) -> RoTraversalIterator<'a, AnalyticsIterator<'a>>
+    where
+        F: FnOnce(&Adjacency) -> Vec<Result<(u128, Value), GraphError>>,
+    {
+        let results = match self
+            .storage
+            .adjacency_cache
+            .get(&self.storage, self.txn, edge_label)
+        {
+            Ok(adjacency) => run(&adjacency),
+            Err(e) => vec![Err(e)],
+        };
+
+        RoTraversalIterator {
+            inner: AnalyticsIterator {
+                txn: self.txn,
+                iter: results.into_iter(),
+                storage: Arc::clone(&self.storage),
+                property,
+            },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> AnalyticsAdapter<'a>
+    for RoTraversalIterator<'a, I>
+{
+    fn page_rank(
+        self,
+        edge_label: Option<&str>,
+        iterations: usize,
+    ) -> RoTraversalIterator<'a, AnalyticsIterator<'a>> {
+        self.analytics(edge_label, "page_rank", |adjacency| {
+            ranked(adjacency, page_rank(adjacency, iterations, DEFAULT_DAMPING))
+        })
+    }
+
+    fn betweenness(
+        self,
+        edge_label: Option<&str>,
+        samples: usize,
+    ) -> RoTraversalIterator<'a, AnalyticsIterator<'a>> {
+        self.analytics(edge_label, "betweenness", |adjacency| {
+            ranked(adjacency, betweenness(adjacency, samples))
+        })
+    }
+
+    fn connected_components(
+        self,
+        edge_label: Option<&str>,
+    ) -> RoTraversalIterator<'a, AnalyticsIterator<'a>> {
+        self.analytics(edge_label, "component", |adjacency| {
+            adjacency
+                .node_ids
+                .iter()
+                .copied()
+                .zip(connected_components(adjacency))
+                .map(|(id, component)| Ok((id, Value::U64(component))))
+                .collect()
+        })
+    }
+}
+
+pub trait CommunitiesAdapter<'scope, 'env>:
+    Iterator<Item = Result<TraversalVal, GraphError>>
+{
+    /// Detects communities with label propagation and writes the community of every node
+    /// to its `community_id` property within the write transaction of the traversal.
+    /// Returns all updated nodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `edge_label` - Only follow edges with this label, any edge if `None`
+    /// * `iterations` - The maximum number of propagation rounds to run
+    fn detect_communities(
+        self,
+        edge_label: Option<&str>,
+        iterations: usize,
+    ) -> RwTraversalIterator<'scope, 'env, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
+}
+
+/// Sets a single property of a node and records the update like any other
+fn set_node_property(
+    storage: &HelixGraphStorage,
+    txn: &mut RwTxn,
+    id: &u128,
+    key: &str,
+    value: Value,
+) -> Result<TraversalVal, GraphError> {
+    let mut node = storage.get_node(txn, id)?;
+    storage.check_unique(txn, key, &value, id)?;
+    if let Some(db) = storage.secondary_indices.get(key) {
+        storage.put_index_entry(txn, key, db, &bincode::serialize(&value)?, id)?;
+    }
+    node.properties
+        .get_or_insert_with(HashMap::new)
+        .insert(key.to_string(), value);
+    let bytes = storage.encode_node(txn, &node)?;
+    storage
+        .nodes_db
+        .put(txn, HelixGraphStorage::node_key(id), &bytes)?;
+    storage.record_version(txn, &node, chrono::Utc::now().timestamp_millis())?;
+    storage.cdc.record(
+        txn,
+        ChangeEvent::new(ChangeOp::Update, ChangeTarget::Node, node.id, &node.label),
+    )?;
+    storage.wal.log(txn, || WalOp::put_node(&storage.encryption, &node))?;
+    Ok(TraversalVal::Node(node))
+}
+
+impl<'scope, 'env, I: Iterator<Item = Result<TraversalVal, GraphError>>>
+    CommunitiesAdapter<'scope, 'env> for RwTraversalIterator<'scope, 'env, I>
+{
+    fn detect_communities(
+        self,
+        edge_label: Option<&str>,
+        iterations: usize,
+    ) -> RwTraversalIterator<'scope, 'env, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    {
+        let storage = Arc::clone(&self.storage);
+        let results = match storage.adjacency_cache.get(&storage, self.txn, edge_label) {
+            Ok(adjacency) => adjacency
+                .node_ids
+                .iter()
+                .zip(label_propagation(&adjacency, iterations))
+                .map(|(id, community)| {
+                    set_node_property(
+                        &storage,
+                        self.txn,
+                        id,
+                        "community_id",
+                        Value::U64(community),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        };
+
+        RwTraversalIterator {
+            inner: results.into_iter(),
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::helix_storage::heed3::RoTxn;
+
+use super::super::tr_val::TraversalVal;
+use crate::helix_engine::{
+    bm25::bm25::BM25,
+    graph_core::traversal_iter::RoTraversalIterator,
+    stats::stats::Direction,
+    storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    types::{GraphError, VectorError},
+    vector_core::{hnsw::HNSW, vector::HVector},
+};
+use std::collections::HashMap;
+
+/// Dampens the weight of the first ranks in reciprocal rank fusion
+pub const RRF_K: f64 = 60.0;
+/// Results fetched from each search per result returned, so nodes ranked low by one
+/// search can still be lifted by the other
+const CANDIDATE_FACTOR: usize = 2;
+
+pub trait HybridSearchAdapter<'a>: Iterator<Item = Result<TraversalVal, GraphError>> {
+    /// Searches the nodes of a label by both a vector and text, and merges the two
+    /// rankings with reciprocal rank fusion.
+    ///
+    /// Vectors rank the nodes they are joined to by an edge in either direction, and the
+    /// text ranks the nodes by their BM25 score. The fused score of each node is set on
+    /// the nodes returned, best first.
+    fn hybrid_search(
+        self,
+        label: &str,
+        vector: &[f64],
+        query: &str,
+        k: usize,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
+}
+
+impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> HybridSearchAdapter<'a>
+    for RoTraversalIterator<'a, I>
+{
+    fn hybrid_search(
+        self,
+        label: &str,
+        vector: &[f64],
+        query: &str,
+        k: usize,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        // the two searches share the transaction, which LMDB doesn't let other threads use
+        let results = vector_ranking(&self.storage, self.txn, label, vector, k)
+            .and_then(|by_vector| {
+                let by_text = text_ranking(&self.storage, self.txn, label, query, k)?;
+                Ok(fuse(&[by_vector, by_text], k))
+            })
+            .and_then(|fused| {
+                fused
+                    .into_iter()
+                    .map(|(id, score)| {
+                        let mut node = self.storage.get_node(self.txn, &id)?;
+                        node.score = Some(score);
+                        Ok(TraversalVal::Node(node))
+                    })
+                    .collect::<Result<Vec<_>, GraphError>>()
+            });
+
+        let iter = match results {
+            Ok(nodes) => nodes.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        };
+
+        RoTraversalIterator {
+            inner: iter.into_iter(),
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+}
+
+/// Nodes of the label joined to the closest vectors, in the order of their closest vector
+fn vector_ranking(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    label: &str,
+    vector: &[f64],
+    k: usize,
+) -> Result<Vec<u128>, GraphError> {
+    let vectors = match storage.vectors.search::<fn(&HVector, &RoTxn) -> bool>(
+        txn,
+        vector,
+        k * CANDIDATE_FACTOR,
+        None,
+        false,
+    ) {
+        Ok(vectors) => vectors,
+        // no vectors have been inserted yet
+        Err(VectorError::EntryPointNotFound) => return Ok(vec![]),
+        Err(e) => return Err(GraphError::from(e)),
+    };
+    let mut ranking = Vec::with_capacity(vectors.len());
+    for vector in vectors {
+        for direction in [Direction::Out, Direction::In] {
+            for (_, (node_id, _)) in storage.node_adjacency(txn, &vector.id, direction)? {
+                if ranking.contains(&node_id) {
+                    continue;
+                }
+                match storage.get_node(txn, &node_id) {
+                    Ok(node) if node.label == label => ranking.push(node_id),
+                    // edges between vectors and edges to other labels
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(ranking)
+}
+
+/// Nodes of the label in the order of their BM25 score for the query
+fn text_ranking(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    label: &str,
+    query: &str,
+    k: usize,
+) -> Result<Vec<u128>, GraphError> {
+    let mut ranking = Vec::new();
+    for (id, _) in storage.bm25.search(txn, query, k * CANDIDATE_FACTOR)? {
+        if matches!(storage.get_node(txn, &id), Ok(node) if node.label == label) {
+            ranking.push(id);
+        }
+    }
+    Ok(ranking)
+}
+
+/// Scores each id by the sum of `1 / (RRF_K + rank)` over the rankings it is in, and
+/// keeps the `k` best
+pub fn fuse(rankings: &[Vec<u128>], k: usize) -> Vec<(u128, f64)> {
+    let mut scores: HashMap<u128, f64> = HashMap::new();
+    for ranking in rankings {
+        for (rank, id) in ranking.iter().enumerate() {
+            *scores.entry(*id).or_default() += 1.0 / (RRF_K + rank as f64 + 1.0);
+        }
+    }
+    let mut fused = scores.into_iter().collect::<Vec<_>>();
+    // ties are broken by id so the order doesn't depend on the hash map
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    fused.truncate(k);
+    fused
+}
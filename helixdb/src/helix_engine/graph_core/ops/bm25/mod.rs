@@ -1 +1,2 @@
-pub mod search_bm25;
\ No newline at end of file
+pub mod hybrid_search;
+pub mod search_bm25;
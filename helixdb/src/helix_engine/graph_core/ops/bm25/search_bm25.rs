@@ -22,16 +22,20 @@ impl<'scope, 'inner> Iterator for SearchBM25<'scope, 'inner> {
     type Item = Result<TraversalVal, GraphError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next = self.iter.next()?;
-        match self.storage.get_node(self.txn, &next.0) {
-            Ok(node) => {
-                if node.label == self.label {
-                    Some(Ok(TraversalVal::Node(node)))
-                } else {
-                    return None;
+        loop {
+            let next = self.iter.next()?;
+            return match self.storage.get_node(self.txn, &next.0) {
+                Ok(node) => {
+                    if node.label == self.label {
+                        Some(Ok(TraversalVal::Node(node)))
+                    } else {
+                        None
+                    }
                 }
-            }
-            Err(e) => Some(Err(e)),
+                // nodes in the trash keep their documents
+                Err(GraphError::NodeNotFound) => continue,
+                Err(e) => Some(Err(e)),
+            };
         }
     }
 }
@@ -52,7 +52,7 @@ impl G {
     /// ```rust
     /// let storage = Arc::new(HelixGraphStorage::new());
     /// let txn = storage.graph_env.read_txn().unwrap();
-    /// let traversal = G::new_from(storage, &txn, vec![TraversalVal::Node(Node { id: 1, label: "Person".to_string(), properties: None })]);
+    /// let traversal = G::new_from(storage, &txn, vec![TraversalVal::Node(Node { id: 1, label: "Person".to_string(), properties: None, score: None })]);
     /// ```
     pub fn new_from<'a>(
         storage: Arc<HelixGraphStorage>,
@@ -108,7 +108,7 @@ impl G {
     /// ```rust
     /// let storage = Arc::new(HelixGraphStorage::new());
     /// let txn = storage.graph_env.write_txn().unwrap();
-    /// let traversal = G::new_mut_from(storage, &mut txn, vec![TraversalVal::Node(Node { id: 1, label: "Person".to_string(), properties: None })]);
+    /// let traversal = G::new_mut_from(storage, &mut txn, vec![TraversalVal::Node(Node { id: 1, label: "Person".to_string(), properties: None, score: None })]);
     /// ```
     pub fn new_mut_from<'scope, 'env>(
         storage: Arc<HelixGraphStorage>,
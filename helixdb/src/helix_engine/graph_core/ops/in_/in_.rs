@@ -7,21 +7,21 @@ use crate::{
             },
             traversal_iter::RoTraversalIterator,
         },
-        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+        stats::stats::Direction,
+        storage_core::{
+            adjacency_blocks::AdjacentEdges,
+            storage_core::{HelixGraphStorage, ADJACENCY_BATCH_SIZE},
+            storage_methods::StorageMethods,
+        },
         types::GraphError,
     },
-    protocol::label_hash::hash_label,
 };
-use crate::helix_storage::heed3::{types::Bytes, RoTxn};
+use crate::helix_storage::heed3::RoTxn;
+use itertools::{Either, Itertools};
 use std::sync::Arc;
 
 pub struct InNodesIterator<'a, T> {
-    pub iter: crate::helix_storage::heed3::RoIter<
-        'a,
-        Bytes,
-        crate::helix_storage::heed3::types::LazyDecode<Bytes>,
-        crate::helix_storage::heed3::iteration_method::MoveOnCurrentKeyDuplicates,
-    >,
+    pub iter: AdjacentEdges<'a>,
     pub storage: Arc<HelixGraphStorage>,
     pub txn: &'a T,
     pub edge_type: &'a EdgeType,
@@ -31,32 +31,24 @@ impl<'a> Iterator for InNodesIterator<'a, RoTxn<'a>> {
     type Item = Result<TraversalVal, GraphError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(Ok((_, data))) = self.iter.next() {
-            match data.decode() {
-                Ok(data) => {
-                    let (node_id, _) = match HelixGraphStorage::unpack_adj_edge_data(&data) {
-                        Ok(data) => data,
-                        Err(e) => {
-                            println!("Error unpacking edge data: {:?}", e);
-                            return Some(Err(e));
-                        }
-                    };
-                    match self.edge_type {
-                        EdgeType::Node => {
-                            if let Ok(node) = self.storage.get_node(self.txn, &node_id) {
-                                return Some(Ok(TraversalVal::Node(node)));
-                            }
-                        }
-                        EdgeType::Vec => {
-                            if let Ok(vector) = self.storage.get_vector(self.txn, &node_id) {
-                                return Some(Ok(TraversalVal::Vector(vector)));
-                            }
-                        }
+        for entry in self.iter.by_ref() {
+            let (node_id, _) = match entry {
+                Ok(edge) => edge,
+                Err(e) => {
+                    println!("Error unpacking edge data: {:?}", e);
+                    return Some(Err(e));
+                }
+            };
+            match self.edge_type {
+                EdgeType::Node => {
+                    if let Ok(node) = self.storage.get_node(self.txn, &node_id) {
+                        return Some(Ok(TraversalVal::Node(node)));
                     }
                 }
-                Err(e) => {
-                    println!("Error decoding edge data: {:?}", e);
-                    return Some(Err(GraphError::DecodeError(e.to_string())));
+                EdgeType::Vec => {
+                    if let Ok(vector) = self.storage.get_vector(self.txn, &node_id) {
+                        return Some(Ok(TraversalVal::Vector(vector)));
+                    }
                 }
             }
         }
@@ -90,34 +82,41 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>> + 'a> InAdapter<'a
         let db = Arc::clone(&self.storage);
         let storage = Arc::clone(&self.storage);
         let txn = self.txn;
+        let edge_label_hash = db.dictionary.label_key(edge_label);
+
         let iter = self
             .inner
-            .filter_map(move |item| {
-                let edge_label_hash = hash_label(edge_label, None);
-                let prefix = HelixGraphStorage::in_edge_key(
-                    &match item {
-                        Ok(item) => item.id(),
-                        Err(_) => return None,
-                    },
-                    &edge_label_hash,
-                );
-                match db
-                    .in_edges_db
-                    .lazily_decode_data()
-                    .get_duplicates(txn, &prefix)
-                {
-                    Ok(Some(iter)) => Some(InNodesIterator {
-                        iter,
-                        storage: Arc::clone(&db),
-                        txn,
-                        edge_type,
-                    }),
-                    Ok(None) => None,
+            .batching(|items| {
+                let ids = items
+                    .filter_map(|item| item.ok().map(|item| item.id()))
+                    .take(ADJACENCY_BATCH_SIZE)
+                    .collect::<Vec<_>>();
+                (!ids.is_empty()).then_some(ids)
+            })
+            .filter_map(move |ids| {
+                let edges = match db.adjacency_batch(txn, &ids, &edge_label_hash, Direction::In) {
+                    Ok(groups) => groups.into_iter().flatten().collect::<Vec<_>>(),
                     Err(e) => {
                         println!("Error getting in edges: {:?}", e);
                         // return Err(e);
-                        None
+                        return None;
+                    }
+                };
+                match &db.parallel {
+                    Some(parallel) => {
+                        let ids = edges
+                            .iter()
+                            .map(|(item_id, _)| *item_id)
+                            .collect::<Vec<_>>();
+                        let items = parallel.fetch_items(&db, txn, &ids, edge_type);
+                        Some(Either::Right(items.into_iter().map(Ok)))
                     }
+                    None => Some(Either::Left(InNodesIterator {
+                        iter: AdjacentEdges::new(edges, None),
+                        storage: Arc::clone(&db),
+                        txn,
+                        edge_type,
+                    })),
                 }
             })
             .flatten();
@@ -0,0 +1,33 @@
+use crate::helix_engine::{
+    graph_core::{
+        ops::tr_val::{Traversable, TraversalVal},
+        traversal_iter::RoTraversalIterator,
+    },
+    stats::stats::Direction,
+    types::GraphError,
+};
+use crate::helix_storage::heed3::RoTxn;
+
+pub trait InCountAdapter<'a, T>: Iterator<Item = Result<TraversalVal, GraphError>> {
+    /// Returns the number of incoming edges with the given label of the items in the traversal.
+    ///
+    /// Equivalent to `in_(edge_label, ..).count()`, but the counts are looked up from the
+    /// adjacency lists so the nodes at the other end are never read.
+    fn in_count(self, edge_label: &'a str) -> Result<usize, GraphError>;
+}
+
+impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> InCountAdapter<'a, RoTxn<'a>>
+    for RoTraversalIterator<'a, I>
+{
+    #[inline]
+    fn in_count(self, edge_label: &'a str) -> Result<usize, GraphError> {
+        let storage = self.storage;
+        let txn = self.txn;
+        self.inner
+            .map(|item| {
+                let degree = storage.degree(txn, &item?.id(), edge_label, Direction::In)?;
+                Ok(degree as usize)
+            })
+            .sum()
+    }
+}
@@ -1,24 +1,22 @@
-use crate::{
-    helix_engine::{
-        graph_core::{
-            ops::tr_val::{Traversable, TraversalVal},
-            traversal_iter::RoTraversalIterator,
-        },
-        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
-        types::GraphError,
+use crate::helix_engine::{
+    graph_core::{
+        ops::tr_val::{Traversable, TraversalVal},
+        traversal_iter::RoTraversalIterator,
     },
-    protocol::label_hash::hash_label,
+    stats::stats::Direction,
+    storage_core::{
+        adjacency_blocks::AdjacentEdges,
+        storage_core::{HelixGraphStorage, ADJACENCY_BATCH_SIZE},
+        storage_methods::StorageMethods,
+    },
+    types::GraphError,
 };
-use crate::helix_storage::heed3::{types::Bytes, RoTxn};
+use crate::helix_storage::heed3::RoTxn;
+use itertools::Itertools;
 use std::sync::Arc;
 
 pub struct InEdgesIterator<'a, T> {
-    pub iter: crate::helix_storage::heed3::RoIter<
-        'a,
-        Bytes,
-        crate::helix_storage::heed3::types::LazyDecode<Bytes>,
-        crate::helix_storage::heed3::iteration_method::MoveOnCurrentKeyDuplicates,
-    >,
+    pub iter: AdjacentEdges<'a>,
     pub storage: Arc<HelixGraphStorage>,
     pub txn: &'a T,
 }
@@ -27,24 +25,16 @@ impl<'a> Iterator for InEdgesIterator<'a, RoTxn<'a>> {
     type Item = Result<TraversalVal, GraphError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(Ok((_, data))) = self.iter.next() {
-            match data.decode() {
-                Ok(data) => {
-                    let (_, edge_id) = match HelixGraphStorage::unpack_adj_edge_data(&data) {
-                        Ok(data) => data,
-                        Err(e) => {
-                            println!("Error unpacking edge data: {:?}", e);
-                            return Some(Err(e));
-                        }
-                    };
-                    if let Ok(edge) = self.storage.get_edge(self.txn, &edge_id) {
-                        return Some(Ok(TraversalVal::Edge(edge)));
-                    }
-                }
+        for entry in self.iter.by_ref() {
+            let (_, edge_id) = match entry {
+                Ok(edge) => edge,
                 Err(e) => {
-                    println!("Error decoding edge data: {:?}", e);
-                    return Some(Err(GraphError::DecodeError(e.to_string())));
+                    println!("Error unpacking edge data: {:?}", e);
+                    return Some(Err(e));
                 }
+            };
+            if let Ok(edge) = self.storage.get_edge(self.txn, &edge_id) {
+                return Some(Ok(TraversalVal::Edge(edge)));
             }
         }
         None
@@ -75,29 +65,23 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> InEdgesAdapter<'a
         let db = Arc::clone(&self.storage);
         let storage = Arc::clone(&self.storage);
         let txn = self.txn;
+        let edge_label_hash = db.dictionary.label_key(edge_label);
         let iter = self
             .inner
-            .filter_map(move |item| {
-                let edge_label_hash = hash_label(edge_label, None);
-
-                let prefix = HelixGraphStorage::in_edge_key(
-                    &match item {
-                        Ok(item) => item.id(),
-                        Err(_) => return None,
-                    },
-                    &edge_label_hash,
-                );
-                match db
-                    .in_edges_db
-                    .lazily_decode_data()
-                    .get_duplicates(txn, &prefix)
-                {
-                    Ok(Some(iter)) => Some(InEdgesIterator {
-                        iter,
+            .batching(|items| {
+                let ids = items
+                    .filter_map(|item| item.ok().map(|item| item.id()))
+                    .take(ADJACENCY_BATCH_SIZE)
+                    .collect::<Vec<_>>();
+                (!ids.is_empty()).then_some(ids)
+            })
+            .filter_map(move |ids| {
+                match db.adjacency_batch(txn, &ids, &edge_label_hash, Direction::In) {
+                    Ok(groups) => Some(InEdgesIterator {
+                        iter: AdjacentEdges::new(groups.into_iter().flatten().collect(), None),
                         storage: Arc::clone(&db),
                         txn,
                     }),
-                    Ok(None) => None,
                     Err(e) => {
                         println!("Error getting in edges: {:?}", e);
                         // return Err(e);
@@ -1,3 +1,4 @@
 pub mod in_;
+pub mod in_count;
 pub mod in_e;
 pub mod to_n;
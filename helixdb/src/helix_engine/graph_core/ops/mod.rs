@@ -1,3 +1,4 @@
+pub mod analytics;
 pub mod bm25;
 pub mod g;
 pub mod in_;
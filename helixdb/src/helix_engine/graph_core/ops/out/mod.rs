@@ -1,3 +1,4 @@
 pub mod out;
+pub mod out_count;
 pub mod out_e;
 pub mod from_n;
\ No newline at end of file
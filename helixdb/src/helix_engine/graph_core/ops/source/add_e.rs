@@ -3,14 +3,13 @@ use std::fmt::Display;
 use super::super::tr_val::TraversalVal;
 use crate::{
     helix_engine::{
+        cdc::cdc::{ChangeEvent, ChangeOp, ChangeTarget},
+        storage_core::wal::WalOp,
         graph_core::traversal_iter::RwTraversalIterator,
-        storage_core::storage_core::HelixGraphStorage, types::GraphError, vector_core::hnsw::HNSW,
-    },
-    protocol::{
-        items::{v6_uuid, Edge},
-        label_hash::hash_label,
-        value::Value,
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+        types::GraphError,
     },
+    protocol::{items::Edge, value::Value},
 };
 use crate::helix_storage::heed3::PutFlags;
 use serde::{Deserialize, Serialize};
@@ -72,7 +71,7 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> AddEAdapter<'
         edge_type: EdgeType,
     ) -> RwTraversalIterator<'a, 'b, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
         let edge = Edge {
-            id: v6_uuid(),
+            id: self.storage.new_edge_id(label),
             label: label.to_string(),
             properties: properties.map(|props| props.into_iter().collect()),
             from_node,
@@ -81,44 +80,22 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> AddEAdapter<'
 
         let mut result: Result<TraversalVal, GraphError> = Ok(TraversalVal::Empty);
 
-        // if should_check {
-        //     match edge_types {
-        //         (EdgeType::Node, EdgeType::Node) => {
-        //             if !(self.node_vec_exists(&from_node, EdgeType::Node)
-        //                 && self.node_vec_exists(&to_node, EdgeType::Node))
-        //             {
-        //                 result = Err(GraphError::NodeNotFound);
-        //             }
-        //         }
-        //         (EdgeType::Vec, EdgeType::Vec) => {
-        //             if !(self.node_vec_exists(&from_node, EdgeType::Vec)
-        //                 && self.node_vec_exists(&to_node, EdgeType::Vec))
-        //             {
-        //                 result = Err(GraphError::NodeNotFound);
-        //             }
-        //         }
-        //         (EdgeType::Node, EdgeType::Vec) => {
-        //             if !(self.node_vec_exists(&from_node, EdgeType::Node)
-        //                 && self.node_vec_exists(&to_node, EdgeType::Vec))
-        //             {
-        //                 result = Err(GraphError::NodeNotFound);
-        //             }
-        //         }
-        //         (EdgeType::Vec, EdgeType::Node) => {
-        //             if !(self.node_vec_exists(&from_node, EdgeType::Vec)
-        //                 && self.node_vec_exists(&to_node, EdgeType::Node))
-        //             {
-        //                 result = Err(GraphError::NodeNotFound);
-        //             }
-        //         }
-        //     }
-        // }
-
-        match edge.encode_edge() {
+        if should_check
+            && !(self.node_vec_exists(&from_node, EdgeType::Node)
+                && (edge_type == EdgeType::Vec || self.node_vec_exists(&to_node, EdgeType::Node)))
+        {
+            return RwTraversalIterator {
+                inner: std::iter::once(Err(GraphError::NodeNotFound)),
+                storage: self.storage,
+                txn: self.txn,
+            };
+        }
+
+        match self.storage.encode_edge(self.txn, &edge) {
             Ok(bytes) => {
                 if let Err(e) = self.storage.edges_db.put_with_flags(
                     self.txn,
-                    PutFlags::APPEND,
+                    self.storage.new_item_flags(),
                     &HelixGraphStorage::edge_key(&edge.id),
                     &bytes,
                 ) {
@@ -128,7 +105,20 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> AddEAdapter<'
             Err(e) => result = Err(GraphError::from(e)),
         }
 
-        let label_hash = hash_label(edge.label.as_str(), None);
+        let label_hash = match self
+            .storage
+            .dictionary
+            .intern_label_key(self.txn, &edge.label)
+        {
+            Ok(label_hash) => label_hash,
+            Err(e) => {
+                return RwTraversalIterator {
+                    inner: std::iter::once(Err(e)),
+                    storage: self.storage,
+                    txn: self.txn,
+                }
+            }
+        };
 
         match self.storage.out_edges_db.put_with_flags(
             self.txn,
@@ -156,6 +146,34 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> AddEAdapter<'
             }
         }
 
+        if result.is_ok() {
+            if let Err(e) = self.storage.index_edge_properties(self.txn, &edge) {
+                result = Err(e);
+            }
+        }
+
+        if result.is_ok() {
+            if let Err(e) = self.storage.cdc.record(
+                self.txn,
+                ChangeEvent::new(ChangeOp::Insert, ChangeTarget::Edge, edge.id, &edge.label),
+            ) {
+                result = Err(e);
+            }
+        }
+
+        if result.is_ok() {
+            if let Err(e) = self.storage.stats.edge_added(self.txn, &edge) {
+                result = Err(e);
+            }
+        }
+
+        if result.is_ok() {
+            let encryption = &self.storage.encryption;
+            if let Err(e) = self.storage.wal.log(self.txn, || WalOp::put_edge(encryption, &edge)) {
+                result = Err(e);
+            }
+        }
+
         let result = match result {
             Ok(_) => Ok(TraversalVal::Edge(edge)),
             Err(_) => Err(GraphError::EdgeNotFound),
@@ -169,23 +187,18 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> AddEAdapter<'
     }
 
     fn node_vec_exists(&self, node_vec_id: &u128, edge_type: EdgeType) -> bool {
-        let exists = match edge_type {
+        // nodes owned by other shards of the graph can't be checked locally
+        if let Some(shards) = &self.storage.shards {
+            if !shards.is_local(*node_vec_id) {
+                return true;
+            }
+        }
+        match edge_type {
             EdgeType::Node => self
                 .storage
-                .nodes_db
-                .get(self.txn, &HelixGraphStorage::node_key(&node_vec_id))
-                .map_or(false, |node| node.is_some()),
-            EdgeType::Vec => self
-                .storage
-                .vectors
-                .get_vector(self.txn, *node_vec_id, 0, false)
-                .is_ok(),
-        };
-
-        if !exists {
-            return false;
+                .check_exists(self.txn, node_vec_id)
+                .unwrap_or(false),
+            EdgeType::Vec => self.storage.get_vector(self.txn, node_vec_id).is_ok(),
         }
-
-        true
     }
 }
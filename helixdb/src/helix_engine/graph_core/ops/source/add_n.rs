@@ -2,16 +2,19 @@ use super::super::tr_val::TraversalVal;
 use crate::{
     helix_engine::{
         bm25::bm25::{BM25Flatten, BM25},
+        cdc::cdc::{ChangeEvent, ChangeOp, ChangeTarget},
+        storage_core::wal::WalOp,
         graph_core::traversal_iter::RwTraversalIterator,
         types::GraphError,
     },
     protocol::{
         filterable::Filterable,
-        items::{v6_uuid, Node},
+        items::Node,
         value::Value,
     },
 };
 use crate::helix_storage::heed3::PutFlags;
+use std::collections::HashMap;
 
 pub struct AddNIterator {
     inner: std::iter::Once<Result<TraversalVal, GraphError>>,
@@ -43,24 +46,71 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> AddNAdapter<'
         properties: Option<Vec<(String, Value)>>,
         secondary_indices: Option<&'a [&str]>,
     ) -> RwTraversalIterator<'a, 'b, std::iter::Once<Result<TraversalVal, GraphError>>> {
-        let node = Node {
-            id: v6_uuid(),
+        let mut node = Node {
+            id: self.storage.new_node_id(label),
             label: label.to_string(), // TODO: just &str or Cow<'a, str>
             properties: properties.map(|props| props.into_iter().collect()),
+            score: None,
         };
 
+        // the schema fills in defaults and timestamps that weren't set
+        let properties = node.properties.get_or_insert_with(HashMap::new);
+        self.storage
+            .schema_history
+            .fill_node(label, properties, true);
+        if properties.is_empty() {
+            node.properties = None;
+        }
+
         let secondary_indices = secondary_indices.unwrap_or(&[]).to_vec();
+
+        if let Err(e) = self
+            .storage
+            .schema_history
+            .check_node(label, node.properties.as_ref())
+        {
+            return RwTraversalIterator {
+                inner: std::iter::once(Err(e)),
+                storage: self.storage,
+                txn: self.txn,
+            };
+        }
+
+        // nothing may be written before unique indices are checked
+        for index in secondary_indices.iter() {
+            let checked = match node.check_property(index) {
+                Ok(value) => self.storage.check_unique(self.txn, index, value, &node.id),
+                Err(_) => Ok(()),
+            };
+            if let Err(e) = checked {
+                return RwTraversalIterator {
+                    inner: std::iter::once(Err(e)),
+                    storage: self.storage,
+                    txn: self.txn,
+                };
+            }
+        }
+
         let mut result: Result<TraversalVal, GraphError> = Ok(TraversalVal::Empty);
 
-        match node.encode_node() {
+        match self.storage.encode_node(self.txn, &node) {
             Ok(bytes) => {
                 if let Err(e) = self.storage.nodes_db.put_with_flags(
                     self.txn,
-                    PutFlags::APPEND,
+                    self.storage.new_item_flags(),
                     &node.id,
                     &bytes,
                 ) {
                     result = Err(GraphError::from(e));
+                } else {
+                    self.storage.node_written(&node.id);
+                    if let Err(e) = self.storage.record_version(
+                        self.txn,
+                        &node,
+                        chrono::Utc::now().timestamp_millis(),
+                    ) {
+                        result = Err(e);
+                    }
                 }
             }
             Err(e) => result = Err(GraphError::from(e)),
@@ -81,9 +131,12 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> AddNAdapter<'
                         Ok(serialized) => {
                             // possibly append dup
 
-                            if let Err(e) = db.put(self.txn, &serialized, &node.id) {
+                            if let Err(e) = self
+                                .storage
+                                .put_index_entry(self.txn, index, db, &serialized, &node.id)
+                            {
                                 println!("{} Error adding node to secondary index: {:?}", line!(), e);
-                                result = Err(GraphError::from(e));
+                                result = Err(e);
                             }
                         }
                         Err(e) => result = Err(GraphError::from(e)),
@@ -114,6 +167,28 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> AddNAdapter<'
             }
         }
 
+        if result.is_ok() {
+            if let Err(e) = self.storage.cdc.record(
+                self.txn,
+                ChangeEvent::new(ChangeOp::Insert, ChangeTarget::Node, node.id, &node.label),
+            ) {
+                result = Err(e);
+            }
+        }
+
+        if result.is_ok() {
+            if let Err(e) = self.storage.stats.node_added(self.txn, &node.label) {
+                result = Err(e);
+            }
+        }
+
+        if result.is_ok() {
+            let encryption = &self.storage.encryption;
+            if let Err(e) = self.storage.wal.log(self.txn, || WalOp::put_node(encryption, &node)) {
+                result = Err(e);
+            }
+        }
+
         if result.is_ok() {
             result = Ok(TraversalVal::Node(node.clone()));
         } else {
@@ -1,10 +1,10 @@
 use crate::{
     helix_engine::{
         graph_core::{ops::tr_val::TraversalVal, traversal_iter::RwTraversalIterator},
-        storage_core::storage_core::HelixGraphStorage,
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
         types::GraphError,
     },
-    protocol::{items::Edge, label_hash::hash_label},
+    protocol::items::Edge,
 };
 use crate::helix_storage::heed3::PutFlags;
 
@@ -49,29 +49,21 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> BulkAddEAdapt
         // EDGES
         for (e_from, e_to, e_id) in edges.iter() {
             if should_check_nodes
-                && (self
-                    .storage
-                    .nodes_db
-                    .get(self.txn, &HelixGraphStorage::node_key(&e_from))
-                    .map_or(false, |node| node.is_none())
-                    || self
-                        .storage
-                        .nodes_db
-                        .get(self.txn, &HelixGraphStorage::node_key(&e_to))
-                        .map_or(false, |node| node.is_none()))
+                && (!self.storage.check_exists(self.txn, e_from).unwrap_or(true)
+                    || !self.storage.check_exists(self.txn, e_to).unwrap_or(true))
             {
                 result = Err(GraphError::NodeNotFound);
             }
-            match {
-                Edge {
+            match self.storage.encode_edge(
+                self.txn,
+                &Edge {
                     id: *e_id,
                     label: "knows".to_string(),
                     properties: None,
                     from_node: *e_from,
                     to_node: *e_to,
-                }
-                .encode_edge()
-            } {
+                },
+            ) {
                 Ok(bytes) => {
                     if let Err(e) = self.storage.edges_db.put_with_flags(
                         self.txn,
@@ -95,6 +87,17 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> BulkAddEAdapt
             }
         }
 
+        let label_hash = match self.storage.dictionary.intern_label_key(self.txn, "knows") {
+            Ok(label_hash) => label_hash,
+            Err(e) => {
+                return RwTraversalIterator {
+                    inner: std::iter::once(Err(e)),
+                    storage: self.storage,
+                    txn: self.txn,
+                }
+            }
+        };
+
         count = 0;
         println!("Adding out edges");
         // OUT EDGES
@@ -121,7 +124,7 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> BulkAddEAdapt
             match self.storage.out_edges_db.put_with_flags(
                 self.txn,
                 out_flag,
-                &HelixGraphStorage::out_edge_key(&from_node, &hash_label("knows", None)),
+                &HelixGraphStorage::out_edge_key(&from_node, &label_hash),
                 &HelixGraphStorage::pack_edge_data(&to_node, &id),
             ) {
                 Ok(_) => {}
@@ -162,7 +165,7 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> BulkAddEAdapt
             match self.storage.in_edges_db.put_with_flags(
                 self.txn,
                 in_flag,
-                &HelixGraphStorage::in_edge_key(&to_node, &hash_label("knows", None)),
+                &HelixGraphStorage::in_edge_key(&to_node, &label_hash),
                 &HelixGraphStorage::pack_edge_data(&from_node, &id),
             ) {
                 Ok(_) => {}
@@ -48,12 +48,13 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> BulkAddNAdapt
                     id: *node,
                     label: "user".to_string(),
                     properties: None,
+                    score: None,
                 };
 
                 let id = node.id;
                 // insert node
 
-                match node.encode_node() {
+                match self.storage.encode_node(self.txn, &node) {
                     Ok(bytes) => {
                         if let Err(e) = self.storage.nodes_db.put_with_flags(
                             self.txn,
@@ -62,6 +63,15 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> BulkAddNAdapt
                             &bytes,
                         ) {
                             result = Err(GraphError::from(e));
+                        } else {
+                            self.storage.node_written(&id);
+                            if let Err(e) = self.storage.record_version(
+                                self.txn,
+                                &node,
+                                chrono::Utc::now().timestamp_millis(),
+                            ) {
+                                result = Err(e);
+                            }
                         }
                     }
                     Err(e) => result = Err(GraphError::from(e)),
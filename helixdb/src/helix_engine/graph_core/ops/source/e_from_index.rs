@@ -0,0 +1,59 @@
+use crate::{
+    helix_engine::{
+        graph_core::{ops::tr_val::TraversalVal, traversal_iter::RoTraversalIterator},
+        types::GraphError,
+    },
+    protocol::value::Value,
+};
+
+pub struct EFromIndex {
+    iter: std::vec::IntoIter<Result<TraversalVal, GraphError>>,
+}
+
+impl Iterator for EFromIndex {
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+pub trait EFromIndexAdapter<'a, K: Into<Value> + Clone>:
+    Iterator<Item = Result<TraversalVal, GraphError>>
+{
+    /// Returns a new iterator that will return the edges from an edge secondary index.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The name of the edge secondary index, which is the indexed property.
+    /// * `key` - The value to look up in the index.
+    ///
+    /// The index must be listed in `edge_secondary_indices` of the config.
+    fn e_from_index(self, index: &'a str, key: &'a K) -> RoTraversalIterator<'a, EFromIndex>;
+}
+
+impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>, K: Into<Value> + Clone + 'a>
+    EFromIndexAdapter<'a, K> for RoTraversalIterator<'a, I>
+{
+    #[inline]
+    fn e_from_index(self, index: &'a str, key: &'a K) -> RoTraversalIterator<'a, EFromIndex> {
+        let iter = match self
+            .storage
+            .edge_from_index(self.txn, index, &key.clone().into())
+        {
+            Ok(edges) => edges
+                .into_iter()
+                .map(|edge| Ok(TraversalVal::Edge(edge)))
+                .collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        };
+
+        RoTraversalIterator {
+            inner: EFromIndex {
+                iter: iter.into_iter(),
+            },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+}
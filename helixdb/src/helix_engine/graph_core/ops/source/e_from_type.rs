@@ -1,45 +1,113 @@
+use std::sync::Arc;
+
 use crate::{
     helix_engine::{
-        graph_core::{ops::tr_val::TraversalVal, traversal_iter::RoTraversalIterator},
+        graph_core::{ops::tr_val::TraversalVal, projection, traversal_iter::RoTraversalIterator},
+        storage_core::storage_core::HelixGraphStorage,
         types::GraphError,
     },
-    protocol::items::Edge,
+    protocol::item_view::EdgeView,
 };
 use crate::helix_storage::heed3::{
     byteorder::BE,
     types::{Bytes, U128},
+    RoTxn,
 };
 
 pub struct EFromType<'a> {
-    pub iter: crate::helix_storage::heed3::RoIter<'a, U128<BE>, crate::helix_storage::heed3::types::LazyDecode<Bytes>>,
+    pub iter: crate::helix_storage::heed3::RoRange<'a, U128<BE>, crate::helix_storage::heed3::types::LazyDecode<Bytes>>,
     pub label: &'a str,
+    pub storage: Arc<HelixGraphStorage>,
+    pub txn: &'a RoTxn<'a>,
 }
 
 impl<'a> Iterator for EFromType<'a> {
     type Item = Result<TraversalVal, GraphError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(value) = self.iter.next() {
+        for value in self.iter.by_ref() {
             let (key, value) = value.unwrap();
+            match self.storage.trash.contains(self.txn, &key) {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => return Some(Err(e)),
+            }
             match value.decode() {
-                Ok(value) => match Edge::decode_edge(&value, key) {
-                    Ok(edge) => match &edge.label {
-                        label if label == self.label => return Some(Ok(TraversalVal::Edge(edge))),
-                        _ => continue,
-                    },
+                // the label is read without decoding the rest of the edge
+                Ok(value) => match self.storage.edge_view(value, key) {
+                    Ok(edge) if edge.label() == self.label => {
+                        return Some(projection::edge(&edge).map(TraversalVal::Edge))
+                    }
+                    Ok(_) => continue,
+                    Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
+                },
+                Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
+            }
+        }
+        None
+    }
+}
+
+pub struct EFromTypeWhere<'a, F> {
+    pub iter: crate::helix_storage::heed3::RoRange<
+        'a,
+        U128<BE>,
+        crate::helix_storage::heed3::types::LazyDecode<Bytes>,
+    >,
+    pub label: &'a str,
+    pub storage: Arc<HelixGraphStorage>,
+    pub txn: &'a RoTxn<'a>,
+    pub f: F,
+}
+
+impl<'a, F> Iterator for EFromTypeWhere<'a, F>
+where
+    F: Fn(&EdgeView, &RoTxn) -> Result<bool, GraphError>,
+{
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for value in self.iter.by_ref() {
+            let (key, value) = value.unwrap();
+            match self.storage.trash.contains(self.txn, &key) {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            let edge = match value.decode() {
+                Ok(value) => match self.storage.edge_view(value, key) {
+                    Ok(edge) if edge.label() == self.label => edge,
+                    Ok(_) => continue,
                     Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
                 },
                 Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
+            };
+            // only the edges passing the filter are decoded
+            match (self.f)(&edge, self.txn) {
+                Ok(true) => return Some(projection::edge(&edge).map(TraversalVal::Edge)),
+                Ok(false) => continue,
+                Err(e) => return Some(Err(e)),
             }
         }
         None
     }
 }
+
 pub trait EFromTypeAdapter<'a>: Iterator<Item = Result<TraversalVal, GraphError>> {
     fn e_from_type(
         self,
         label: &'a str,
     ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
+
+    /// Returns an iterator containing the edges with the given label that pass the
+    /// filter, only decoding the edges it keeps
+    fn e_from_type_where<F>(
+        self,
+        label: &'a str,
+        f: F,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    where
+        F: Fn(&EdgeView, &RoTxn) -> Result<bool, GraphError>;
 }
 impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> EFromTypeAdapter<'a>
     for RoTraversalIterator<'a, I>
@@ -49,14 +117,50 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> EFromTypeAdapter<
         self,
         label: &'a str,
     ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        // only the edges of the label's namespace are read
+        let range = self.storage.namespaces.id_range(label);
+        let iter = self
+            .storage
+            .edges_db
+            .lazily_decode_data()
+            .range(self.txn, &range)
+            .unwrap();
+        RoTraversalIterator {
+            inner: EFromType {
+                iter,
+                label,
+                storage: Arc::clone(&self.storage),
+                txn: self.txn,
+            },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+
+    #[inline]
+    fn e_from_type_where<F>(
+        self,
+        label: &'a str,
+        f: F,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    where
+        F: Fn(&EdgeView, &RoTxn) -> Result<bool, GraphError>,
+    {
+        let range = self.storage.namespaces.id_range(label);
         let iter = self
             .storage
             .edges_db
             .lazily_decode_data()
-            .iter(self.txn)
+            .range(self.txn, &range)
             .unwrap();
         RoTraversalIterator {
-            inner: EFromType { iter, label },
+            inner: EFromTypeWhere {
+                iter,
+                label,
+                storage: Arc::clone(&self.storage),
+                txn: self.txn,
+                f,
+            },
             storage: self.storage,
             txn: self.txn,
         }
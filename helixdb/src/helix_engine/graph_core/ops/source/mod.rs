@@ -3,10 +3,12 @@ pub mod add_n;
 
 
 pub mod e_from_id;
+pub mod e_from_index;
 pub mod e_from_type;
 pub mod n_from_id;
 pub mod n_from_index;
 pub mod n_from_type;
+pub mod n_from_type_ordered;
 
 #[cfg(test)]
 pub mod bulk_add_e;
@@ -26,6 +26,8 @@ impl<'a> Iterator for NFromIndex<'a> {
             match value.decode() {
                 Ok(value) => match self.storage.get_node(self.txn, &value) {
                     Ok(node) => return Some(Ok(TraversalVal::Node(node))),
+                    // entries of nodes in the trash stay in the index
+                    Err(GraphError::NodeNotFound) => continue,
                     Err(e) => {
                         println!("{} Error getting node: {:?}", line!(), e);
                         return Some(Err(GraphError::ConversionError(e.to_string())));
@@ -1,43 +1,186 @@
+use std::sync::Arc;
+
 use crate::{
     helix_engine::{
-        graph_core::{ops::tr_val::TraversalVal, traversal_iter::RoTraversalIterator},
+        graph_core::{ops::tr_val::TraversalVal, projection, traversal_iter::RoTraversalIterator},
+        storage_core::storage_core::HelixGraphStorage,
         types::GraphError,
     },
-    protocol::items::Node,
+    protocol::{item_view::NodeView, value::Value},
 };
 use crate::helix_storage::heed3::{
     byteorder::BE,
     types::{Bytes, U128},
+    RoTxn,
 };
+use itertools::Either;
 
 pub struct NFromType<'a> {
-    pub iter: crate::helix_storage::heed3::RoIter<'a, U128<BE>, crate::helix_storage::heed3::types::LazyDecode<Bytes>>,
+    pub iter: crate::helix_storage::heed3::RoRange<'a, U128<BE>, crate::helix_storage::heed3::types::LazyDecode<Bytes>>,
     pub label: &'a str,
+    pub storage: Arc<HelixGraphStorage>,
+    pub txn: &'a RoTxn<'a>,
 }
 
 impl<'a> Iterator for NFromType<'a> {
     type Item = Result<TraversalVal, GraphError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(value) = self.iter.next() {
+        for value in self.iter.by_ref() {
             let (key_, value) = value.unwrap();
+            match self.storage.trash.contains(self.txn, &key_) {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => return Some(Err(e)),
+            }
             match value.decode() {
-                Ok(value) => match Node::decode_node(&value, key_) {
-                    Ok(node) => match &node.label {
-                        label if label == self.label => return Some(Ok(TraversalVal::Node(node))),
-                        _ => continue,
-                    },
-                    Err(e) => {
-                        println!("{} Error decoding node: {:?}", line!(), e);
-                        return Some(Err(GraphError::ConversionError(e.to_string())));
+                // the label is read without decoding the rest of the node
+                Ok(value) => match self.storage.node_view(value, key_) {
+                    Ok(node) if node.label() == self.label => {
+                        return Some(projection::node(&node).map(TraversalVal::Node))
                     }
+                    Ok(_) => continue,
+                    Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
+                },
+                Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
+            }
+        }
+        None
+    }
+}
+
+pub struct NFromTypeWhere<'a, F> {
+    pub iter: crate::helix_storage::heed3::RoRange<
+        'a,
+        U128<BE>,
+        crate::helix_storage::heed3::types::LazyDecode<Bytes>,
+    >,
+    pub label: &'a str,
+    pub storage: Arc<HelixGraphStorage>,
+    pub txn: &'a RoTxn<'a>,
+    pub f: F,
+}
+
+impl<'a, F> Iterator for NFromTypeWhere<'a, F>
+where
+    F: Fn(&NodeView, &RoTxn) -> Result<bool, GraphError>,
+{
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for value in self.iter.by_ref() {
+            let (key_, value) = value.unwrap();
+            match self.storage.trash.contains(self.txn, &key_) {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            let node = match value.decode() {
+                Ok(value) => match self.storage.node_view(value, key_) {
+                    Ok(node) if node.label() == self.label => node,
+                    Ok(_) => continue,
+                    Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
                 },
                 Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
+            };
+            // only the nodes passing the filter are decoded
+            match (self.f)(&node, self.txn) {
+                Ok(true) => return Some(projection::node(&node).map(TraversalVal::Node)),
+                Ok(false) => continue,
+                Err(e) => return Some(Err(e)),
             }
         }
         None
     }
 }
+
+/// Nodes of a label read from a secondary index by value that pass the filter
+pub struct NFromIndexWhere<'a, F> {
+    pub ids: std::vec::IntoIter<u128>,
+    pub label: &'a str,
+    pub storage: Arc<HelixGraphStorage>,
+    pub txn: &'a RoTxn<'a>,
+    pub f: F,
+    /// Error reading the index, returned before any node
+    pub error: Option<GraphError>,
+}
+
+impl<'a, F> NFromIndexWhere<'a, F> {
+    fn new(
+        storage: Arc<HelixGraphStorage>,
+        txn: &'a RoTxn<'a>,
+        label: &'a str,
+        index: &str,
+        value: &Value,
+        f: F,
+    ) -> Self {
+        let ids = storage
+            .secondary_indices
+            .get(index)
+            .ok_or_else(|| GraphError::New(format!("Secondary Index {} not found", index)))
+            .and_then(|db| {
+                let mut ids = Vec::new();
+                if let Some(entries) = db.get_duplicates(txn, &bincode::serialize(value)?)? {
+                    for result in entries {
+                        ids.push(result?.1);
+                    }
+                }
+                Ok(ids)
+            });
+        let (ids, error) = match ids {
+            Ok(ids) => (ids, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        NFromIndexWhere {
+            ids: ids.into_iter(),
+            label,
+            storage,
+            txn,
+            f,
+            error,
+        }
+    }
+}
+
+impl<'a, F> Iterator for NFromIndexWhere<'a, F>
+where
+    F: Fn(&NodeView, &RoTxn) -> Result<bool, GraphError>,
+{
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        for id in self.ids.by_ref() {
+            // nodes in the trash keep their entries
+            match self.storage.trash.contains(self.txn, &id) {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            let bytes = match self.storage.nodes_db.get(self.txn, &id) {
+                Ok(Some(bytes)) => bytes,
+                Ok(None) => continue,
+                Err(e) => return Some(Err(GraphError::from(e))),
+            };
+            // entries aren't removed when a node is updated or dropped, and the index
+            // holds nodes of every label, so the filter is checked in full
+            let node = match self.storage.node_view(bytes, id) {
+                Ok(node) if node.label() == self.label => node,
+                Ok(_) => continue,
+                Err(e) => return Some(Err(GraphError::ConversionError(e.to_string()))),
+            };
+            match (self.f)(&node, self.txn) {
+                Ok(true) => return Some(projection::node(&node).map(TraversalVal::Node)),
+                Ok(false) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
 pub trait NFromTypeAdapter<'a>: Iterator<Item = Result<TraversalVal, GraphError>> {
     /// Returns an iterator containing the nodes with the given label.
     ///
@@ -46,6 +189,37 @@ pub trait NFromTypeAdapter<'a>: Iterator<Item = Result<TraversalVal, GraphError>
         self,
         label: &'a str,
     ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
+
+    /// Returns an iterator containing the nodes with the given label that pass the
+    /// filter.
+    ///
+    /// The filter reads the properties of the stored nodes as it needs them, so the nodes
+    /// it leaves out are never fully decoded.
+    fn n_from_type_where<F>(
+        self,
+        label: &'a str,
+        f: F,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    where
+        F: Fn(&NodeView, &RoTxn) -> Result<bool, GraphError>;
+
+    /// Returns an iterator containing the nodes with the given label that pass the
+    /// filter, where the filter requires the nodes to have the given values in the given
+    /// secondary indices.
+    ///
+    /// The graph statistics decide whether the nodes are looked up in one of the indices
+    /// or the label is scanned, see [`GraphStats::pick_index_lookup`]. Either way the
+    /// filter is checked for every node returned.
+    ///
+    /// [`GraphStats::pick_index_lookup`]: crate::helix_engine::stats::stats::GraphStats::pick_index_lookup
+    fn n_from_type_where_indexed<F>(
+        self,
+        label: &'a str,
+        lookups: &[(&str, Value)],
+        f: F,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    where
+        F: Fn(&NodeView, &RoTxn) -> Result<bool, GraphError>;
 }
 impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> NFromTypeAdapter<'a>
     for RoTraversalIterator<'a, I>
@@ -55,16 +229,94 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> NFromTypeAdapter<
         self,
         label: &'a str,
     ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        // only the nodes of the label's namespace are read
+        let range = self.storage.namespaces.id_range(label);
         let iter = self
             .storage
             .nodes_db
             .lazily_decode_data()
-            .iter(self.txn)
+            .range(self.txn, &range)
             .unwrap();
         RoTraversalIterator {
-            inner: NFromType { iter, label },
+            inner: NFromType {
+                iter,
+                label,
+                storage: Arc::clone(&self.storage),
+                txn: self.txn,
+            },
             storage: self.storage,
             txn: self.txn,
         }
     }
+
+    #[inline]
+    fn n_from_type_where<F>(
+        self,
+        label: &'a str,
+        f: F,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    where
+        F: Fn(&NodeView, &RoTxn) -> Result<bool, GraphError>,
+    {
+        let range = self.storage.namespaces.id_range(label);
+        let iter = self
+            .storage
+            .nodes_db
+            .lazily_decode_data()
+            .range(self.txn, &range)
+            .unwrap();
+        RoTraversalIterator {
+            inner: NFromTypeWhere {
+                iter,
+                label,
+                storage: Arc::clone(&self.storage),
+                txn: self.txn,
+                f,
+            },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+
+    #[inline]
+    fn n_from_type_where_indexed<F>(
+        self,
+        label: &'a str,
+        lookups: &[(&str, Value)],
+        f: F,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    where
+        F: Fn(&NodeView, &RoTxn) -> Result<bool, GraphError>,
+    {
+        let storage = Arc::clone(&self.storage);
+        let txn = self.txn;
+        let indices = lookups.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+        let inner = match storage.stats.pick_index_lookup(txn, label, &indices) {
+            Ok(Some(i)) => {
+                let (index, value) = &lookups[i];
+                Either::Left(NFromIndexWhere::new(
+                    Arc::clone(&storage),
+                    txn,
+                    label,
+                    index,
+                    value,
+                    f,
+                ))
+            }
+            Ok(None) => Either::Right(self.n_from_type_where(label, f).inner),
+            Err(e) => Either::Left(NFromIndexWhere {
+                ids: Vec::new().into_iter(),
+                label,
+                storage: Arc::clone(&storage),
+                txn,
+                f,
+                error: Some(e),
+            }),
+        };
+        RoTraversalIterator {
+            inner,
+            storage,
+            txn,
+        }
+    }
 }
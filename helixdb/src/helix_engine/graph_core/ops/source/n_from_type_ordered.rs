@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use crate::helix_storage::heed3::{
+    byteorder::BE,
+    types::{Bytes, DecodeIgnore, U128},
+    Database, RoTxn,
+};
+
+use super::n_from_type::{NFromType, NFromTypeAdapter};
+use crate::{
+    helix_engine::{
+        graph_core::{
+            ops::{
+                tr_val::TraversalVal,
+                util::order_by::{sort_by_property, HelixOrder},
+            },
+            traversal_iter::RoTraversalIterator,
+        },
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+        types::GraphError,
+    },
+    protocol::{filterable::Filterable, value::Value},
+};
+
+/// Nodes of a label in order of an indexed property, read lazily from the index so only
+/// as many nodes are decoded as are consumed
+pub struct IndexedNodes<'a> {
+    txn: &'a RoTxn<'a>,
+    storage: Arc<HelixGraphStorage>,
+    db: Database<Bytes, U128<BE>>,
+    label: &'a str,
+    property: &'a str,
+    /// Distinct index keys in order, with the values they encode
+    keys: std::vec::IntoIter<(Vec<u8>, Value)>,
+    value: Option<Value>,
+    ids: std::vec::IntoIter<u128>,
+    /// Nodes without the property, which come after all indexed ones
+    unindexed: Option<NFromType<'a>>,
+}
+
+impl<'a> IndexedNodes<'a> {
+    fn new(
+        txn: &'a RoTxn<'a>,
+        storage: Arc<HelixGraphStorage>,
+        db: Database<Bytes, U128<BE>>,
+        label: &'a str,
+        property: &'a str,
+        order: HelixOrder,
+    ) -> Result<Self, GraphError> {
+        // index keys are encoded values, which don't sort like the values themselves
+        let mut keys = Vec::new();
+        for result in db
+            .remap_data_type::<DecodeIgnore>()
+            .iter(txn)?
+            .move_between_keys()
+        {
+            let (key, _) = result?;
+            keys.push((key.to_vec(), bincode::deserialize::<Value>(key)?));
+        }
+        keys.sort_by(|(_, a), (_, b)| order.compare(Some(a), Some(b)));
+
+        Ok(IndexedNodes {
+            txn,
+            storage,
+            db,
+            label,
+            property,
+            keys: keys.into_iter(),
+            value: None,
+            ids: Vec::new().into_iter(),
+            unindexed: None,
+        })
+    }
+
+    fn next_node(&mut self) -> Result<Option<TraversalVal>, GraphError> {
+        loop {
+            if let Some(id) = self.ids.next() {
+                let node = match self.storage.get_node(self.txn, &id) {
+                    Ok(node) => node,
+                    // nodes in the trash keep their entries
+                    Err(GraphError::NodeNotFound) => continue,
+                    Err(e) => return Err(e),
+                };
+                // entries aren't removed when a node is updated or dropped
+                if node.label == self.label
+                    && node.check_property(self.property).ok() == self.value.as_ref()
+                {
+                    return Ok(Some(TraversalVal::Node(node)));
+                }
+                continue;
+            }
+
+            if let Some((key, value)) = self.keys.next() {
+                let mut ids = Vec::new();
+                if let Some(entries) = self.db.get_duplicates(self.txn, &key)? {
+                    for result in entries {
+                        ids.push(result?.1);
+                    }
+                }
+                self.ids = ids.into_iter();
+                self.value = Some(value);
+                continue;
+            }
+
+            let unindexed = match self.unindexed.as_mut() {
+                Some(unindexed) => unindexed,
+                None => self.unindexed.insert(NFromType {
+                    iter: self
+                        .storage
+                        .nodes_db
+                        .lazily_decode_data()
+                        .range(self.txn, &self.storage.namespaces.id_range(self.label))?,
+                    label: self.label,
+                    storage: Arc::clone(&self.storage),
+                    txn: self.txn,
+                }),
+            };
+            for item in unindexed.by_ref() {
+                let item = item?;
+                if let TraversalVal::Node(node) = &item {
+                    if node.check_property(self.property).is_err() {
+                        return Ok(Some(item));
+                    }
+                }
+            }
+            return Ok(None);
+        }
+    }
+}
+
+impl<'a> Iterator for IndexedNodes<'a> {
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_node().transpose()
+    }
+}
+
+pub enum NFromTypeOrdered<'a> {
+    /// Walks the secondary index of the property
+    Indexed(IndexedNodes<'a>),
+    /// Sorted in memory, as the property isn't indexed
+    Sorted(std::vec::IntoIter<Result<TraversalVal, GraphError>>),
+}
+
+impl<'a> Iterator for NFromTypeOrdered<'a> {
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            NFromTypeOrdered::Indexed(iter) => iter.next(),
+            NFromTypeOrdered::Sorted(iter) => iter.next(),
+        }
+    }
+}
+
+pub trait NFromTypeOrderedAdapter<'a>: Iterator<Item = Result<TraversalVal, GraphError>> {
+    /// Returns an iterator containing the nodes with the given label sorted by a
+    /// property, nodes without it last.
+    ///
+    /// If the property has a secondary index the nodes are read from it in order,
+    /// otherwise they are all read and sorted like
+    /// [`order_by`](crate::helix_engine::graph_core::ops::util::order_by::OrderByAdapter::order_by).
+    /// The index is assumed to cover every node with the property.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the nodes
+    /// * `property` - The property to sort by
+    /// * `order` - Whether to sort ascending or descending
+    /// * `limit` - Keep only this many nodes from the front when sorting in memory
+    fn n_from_type_ordered(
+        self,
+        label: &'a str,
+        property: &'a str,
+        order: HelixOrder,
+        limit: Option<usize>,
+    ) -> RoTraversalIterator<'a, NFromTypeOrdered<'a>>;
+}
+
+impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> NFromTypeOrderedAdapter<'a>
+    for RoTraversalIterator<'a, I>
+{
+    fn n_from_type_ordered(
+        self,
+        label: &'a str,
+        property: &'a str,
+        order: HelixOrder,
+        limit: Option<usize>,
+    ) -> RoTraversalIterator<'a, NFromTypeOrdered<'a>> {
+        let storage = Arc::clone(&self.storage);
+        let txn = self.txn;
+        let inner = match storage.secondary_indices.get(property) {
+            Some(db) => {
+                match IndexedNodes::new(txn, Arc::clone(&storage), *db, label, property, order) {
+                    Ok(nodes) => NFromTypeOrdered::Indexed(nodes),
+                    Err(e) => NFromTypeOrdered::Sorted(vec![Err(e)].into_iter()),
+                }
+            }
+            None => NFromTypeOrdered::Sorted(
+                sort_by_property(self.n_from_type(label), property, order, limit).into_iter(),
+            ),
+        };
+
+        RoTraversalIterator {
+            inner,
+            storage,
+            txn,
+        }
+    }
+}
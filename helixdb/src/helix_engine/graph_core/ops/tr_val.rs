@@ -7,7 +7,7 @@ use crate::{
         value::Value,
     },
 };
-use std::hash::Hash;
+use std::{collections::HashMap, hash::Hash};
 
 #[derive(Clone, Debug)]
 pub enum TraversalVal {
@@ -48,6 +48,57 @@ impl PartialEq for TraversalVal {
     }
 }
 
+impl TraversalVal {
+    /// Rough number of bytes the value takes in memory, counted towards the collection
+    /// limit of a query
+    pub fn estimated_size(&self) -> usize {
+        let own = match self {
+            TraversalVal::Node(node) => node.label.len() + properties_size(&node.properties),
+            TraversalVal::Edge(edge) => edge.label.len() + properties_size(&edge.properties),
+            TraversalVal::Vector(vector) => {
+                std::mem::size_of_val(vector.get_data()) + properties_size(&vector.properties)
+            }
+            TraversalVal::Path((nodes, edges)) => {
+                let nodes = nodes.iter().map(|node| {
+                    std::mem::size_of::<Node>()
+                        + node.label.len()
+                        + properties_size(&node.properties)
+                });
+                let edges = edges.iter().map(|edge| {
+                    std::mem::size_of::<Edge>()
+                        + edge.label.len()
+                        + properties_size(&edge.properties)
+                });
+                nodes.chain(edges).sum()
+            }
+            TraversalVal::Value(value) => value_size(value),
+            TraversalVal::Count(_) | TraversalVal::Empty => 0,
+        };
+        std::mem::size_of::<TraversalVal>() + own
+    }
+}
+
+fn properties_size(properties: &Option<HashMap<String, Value>>) -> usize {
+    properties
+        .iter()
+        .flatten()
+        .map(|(key, value)| key.len() + value_size(value))
+        .sum()
+}
+
+fn value_size(value: &Value) -> usize {
+    let own = match value {
+        Value::String(s) => s.len(),
+        Value::Array(values) => values.iter().map(value_size).sum(),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| key.len() + value_size(value))
+            .sum(),
+        _ => 0,
+    };
+    std::mem::size_of::<Value>() + own
+}
+
 pub trait Traversable {
     fn id(&self) -> u128;
     fn label(&self) -> String;
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use crate::{
+    helix_engine::{
+        graph_core::{ops::tr_val::TraversalVal, traversal_iter::RoTraversalIterator},
+        storage_core::storage_core::HelixGraphStorage,
+        types::GraphError,
+    },
+    helix_storage::heed3::RoTxn,
+    protocol::value::Value,
+};
+
+pub struct AsOf<'a, I> {
+    iter: I,
+    storage: Arc<HelixGraphStorage>,
+    txn: &'a RoTxn<'a>,
+    /// Milliseconds since the epoch, unset if the time given isn't a point in time
+    at: Option<i64>,
+    /// Why the time isn't a point in time, returned in place of the items
+    error: Option<GraphError>,
+}
+
+impl<'a, I> Iterator for AsOf<'a, I>
+where
+    I: Iterator<Item = Result<TraversalVal, GraphError>>,
+{
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        let at = self.at?;
+        for item in self.iter.by_ref() {
+            let node = match item {
+                Ok(TraversalVal::Node(node)) => node,
+                other => return Some(other),
+            };
+            match self.storage.node_as_of(self.txn, &node, at) {
+                Ok(Some(node)) => return Some(Ok(TraversalVal::Node(node))),
+                // the node didn't exist yet or had been dropped by then
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+pub trait AsOfAdapter<'a>: Iterator<Item = Result<TraversalVal, GraphError>> {
+    /// Returns the nodes as they were at the given point in time, leaving out the nodes
+    /// that didn't exist then.
+    ///
+    /// The time is a date, a date string or seconds since the epoch. Only the nodes of
+    /// versioned labels have a history, others fail the traversal.
+    #[allow(clippy::wrong_self_convention)]
+    fn as_of(
+        self,
+        at: impl Into<Value>,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
+}
+
+impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> AsOfAdapter<'a>
+    for RoTraversalIterator<'a, I>
+{
+    #[inline]
+    fn as_of(
+        self,
+        at: impl Into<Value>,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        let at = at.into();
+        // numbers other than the 64 bit integers a date reads are seconds as well
+        let millis = match at.as_date_time() {
+            Some(at) => Some(at.timestamp_millis()),
+            None => at.as_f64().map(|secs| (secs * 1000.0) as i64),
+        };
+        let (at, error) = match millis {
+            Some(at) => (Some(at), None),
+            None => (
+                None,
+                Some(GraphError::New(format!("`{:?}` is not a point in time", at))),
+            ),
+        };
+        RoTraversalIterator {
+            inner: AsOf {
+                iter: self.inner,
+                storage: Arc::clone(&self.storage),
+                txn: self.txn,
+                at,
+                error,
+            },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+}
@@ -3,8 +3,11 @@ use crate::helix_engine::{
     storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
     types::GraphError,
 };
-use crate::helix_storage::heed3::RwTxn;
-use std::sync::Arc;
+use crate::helix_storage::heed3::{RoTxn, RwTxn};
+use std::{ops::Bound, sync::Arc};
+
+/// Number of elements [`Drop::drop_where`] deletes per write transaction by default
+pub const DEFAULT_DROP_BATCH_SIZE: usize = 10_000;
 
 pub struct Drop<I> {
     pub iter: I,
@@ -40,3 +43,117 @@ impl<'a> Drop<Vec<Result<TraversalVal, GraphError>>> {
             })
     }
 }
+
+/// The elements a [`Drop::drop_where`] scans for ones to delete
+#[derive(Debug, Clone, Copy)]
+pub enum DropSource<'a> {
+    /// Nodes with the label
+    Nodes(&'a str),
+    /// Edges with the label
+    Edges(&'a str),
+}
+
+impl<'a> Drop<DropSource<'a>> {
+    /// Deletes every element of the source the predicate holds for and returns how many
+    /// were deleted. Edges removed along with a deleted node aren't counted.
+    ///
+    /// Unlike [`drop_traversal`](Drop::drop_traversal) nothing is collected up front. The
+    /// source is scanned in id order and the predicate is checked as each element is read,
+    /// deleting at most `batch_size` elements per write transaction. Each transaction is
+    /// committed before the scan resumes after the last id it read, so an error leaves
+    /// the earlier batches deleted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let deleted = Drop::drop_where(
+    ///     DropSource::Nodes("session"),
+    ///     |item| Ok(item.check_property("expired")? == &Value::Boolean(true)),
+    ///     Arc::clone(&storage),
+    ///     DEFAULT_DROP_BATCH_SIZE,
+    /// )?;
+    /// ```
+    pub fn drop_where<F>(
+        source: DropSource<'a>,
+        predicate: F,
+        storage: Arc<HelixGraphStorage>,
+        batch_size: usize,
+    ) -> Result<usize, GraphError>
+    where
+        F: Fn(&TraversalVal) -> Result<bool, GraphError>,
+    {
+        let batch_size = batch_size.max(1);
+        let mut after = None;
+        let mut deleted = 0;
+        loop {
+            let mut txn = storage.graph_env.write_txn()?;
+            let (ids, last) =
+                Self::next_batch(source, &predicate, &storage, &txn, after, batch_size)?;
+            for id in &ids {
+                match source {
+                    DropSource::Nodes(_) => storage.drop_node(&mut txn, id)?,
+                    DropSource::Edges(_) => storage.drop_edge(&mut txn, id)?,
+                }
+            }
+            txn.commit()?;
+            deleted += ids.len();
+
+            match last {
+                Some(last) => after = Some(last),
+                None => return Ok(deleted),
+            }
+        }
+    }
+
+    /// Scans the source from after the given id until `batch_size` elements match,
+    /// returning their ids and the id to resume after, or `None` once the source is
+    /// exhausted
+    fn next_batch<F>(
+        source: DropSource<'a>,
+        predicate: &F,
+        storage: &HelixGraphStorage,
+        txn: &RoTxn,
+        after: Option<u128>,
+        batch_size: usize,
+    ) -> Result<(Vec<u128>, Option<u128>), GraphError>
+    where
+        F: Fn(&TraversalVal) -> Result<bool, GraphError>,
+    {
+        let db = match source {
+            DropSource::Nodes(_) => storage.nodes_db,
+            DropSource::Edges(_) => storage.edges_db,
+        };
+        let start = match after {
+            Some(id) => Bound::Excluded(id),
+            None => Bound::Unbounded,
+        };
+
+        let mut ids = Vec::with_capacity(batch_size);
+        for result in db.range(txn, &(start, Bound::Unbounded))? {
+            let (id, bytes) = result?;
+            let item = match source {
+                DropSource::Nodes(label) => {
+                    let node = storage.decode_node(bytes, id)?;
+                    if node.label != label {
+                        continue;
+                    }
+                    TraversalVal::Node(node)
+                }
+                DropSource::Edges(label) => {
+                    let edge = storage.decode_edge(bytes, id)?;
+                    if edge.label != label {
+                        continue;
+                    }
+                    TraversalVal::Edge(edge)
+                }
+            };
+            if predicate(&item)? {
+                ids.push(id);
+                if ids.len() == batch_size {
+                    return Ok((ids, Some(id)));
+                }
+            }
+        }
+        Ok((ids, None))
+    }
+}
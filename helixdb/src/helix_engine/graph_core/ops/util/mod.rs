@@ -1,8 +1,10 @@
+pub mod as_of;
 pub mod dedup;
 pub mod drop;
 pub mod filter_mut;
 pub mod filter_ref;
 pub mod map;
+pub mod order_by;
 pub mod paths;
 pub mod props;
 pub mod range;
@@ -0,0 +1,154 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            ops::tr_val::{Traversable, TraversalVal},
+            traversal_iter::RoTraversalIterator,
+        },
+        types::GraphError,
+    },
+    protocol::value::Value,
+};
+
+/// Direction of an [`OrderByAdapter::order_by`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelixOrder {
+    Asc,
+    Desc,
+}
+
+impl HelixOrder {
+    /// Compares two optional property values in this direction, items without the
+    /// property always come last
+    pub fn compare(&self, a: Option<&Value>, b: Option<&Value>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => match self {
+                HelixOrder::Asc => a.total_cmp(b),
+                HelixOrder::Desc => b.total_cmp(a),
+            },
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// An item with the value it is sorted by and its position in the traversal, which
+/// keeps items with equal values in traversal order
+struct Keyed {
+    key: Option<Value>,
+    position: usize,
+    item: TraversalVal,
+}
+
+/// Sorts items by a property, keeping only the first `limit` if set.
+///
+/// With a limit, at most twice as many items as needed are held at a time, the rest are
+/// dropped as soon as they can't make the cut.
+pub fn sort_by_property(
+    items: impl Iterator<Item = Result<TraversalVal, GraphError>>,
+    property: &str,
+    order: HelixOrder,
+    limit: Option<usize>,
+) -> Vec<Result<TraversalVal, GraphError>> {
+    let compare = |a: &Keyed, b: &Keyed| {
+        order
+            .compare(a.key.as_ref(), b.key.as_ref())
+            .then(a.position.cmp(&b.position))
+    };
+
+    let mut errors = Vec::new();
+    let mut keyed = Vec::new();
+    for (position, item) in items.enumerate() {
+        let item = match item {
+            Ok(item) => item,
+            Err(e) => {
+                errors.push(Err(e));
+                continue;
+            }
+        };
+        keyed.push(Keyed {
+            key: item.check_property(property).ok().cloned(),
+            position,
+            item,
+        });
+        if let Some(limit) = limit {
+            if limit > 0 && keyed.len() >= 2 * limit {
+                keyed.select_nth_unstable_by(limit - 1, compare);
+                keyed.truncate(limit);
+            }
+        }
+    }
+
+    if let Some(limit) = limit {
+        if limit < keyed.len() {
+            if limit > 0 {
+                keyed.select_nth_unstable_by(limit - 1, compare);
+            }
+            keyed.truncate(limit);
+        }
+    }
+    keyed.sort_unstable_by(compare);
+
+    errors
+        .into_iter()
+        .chain(keyed.into_iter().map(|keyed| Ok(keyed.item)))
+        .collect()
+}
+
+pub struct OrderBy {
+    iter: std::vec::IntoIter<Result<TraversalVal, GraphError>>,
+}
+
+impl Iterator for OrderBy {
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+pub trait OrderByAdapter<'a>: Iterator {
+    /// OrderBy returns the items sorted by a property, items without it last
+    ///
+    /// # Arguments
+    ///
+    /// * `property` - The property to sort by
+    /// * `order` - Whether to sort ascending or descending
+    /// * `limit` - Keep only this many items from the front, for when a range follows
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let traversal = G::new(storage, &txn)
+    ///     .n_from_type("person")
+    ///     .order_by("age", HelixOrder::Desc, Some(10))
+    ///     .range(0, 10);
+    /// ```
+    fn order_by(
+        self,
+        property: &str,
+        order: HelixOrder,
+        limit: Option<usize>,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
+}
+
+impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> OrderByAdapter<'a>
+    for RoTraversalIterator<'a, I>
+{
+    fn order_by(
+        self,
+        property: &str,
+        order: HelixOrder,
+        limit: Option<usize>,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        RoTraversalIterator {
+            inner: OrderBy {
+                iter: sort_by_property(self.inner, property, order, limit).into_iter(),
+            },
+            storage: Arc::clone(&self.storage),
+            txn: self.txn,
+        }
+    }
+}
@@ -1,16 +1,10 @@
-use crate::{
-    helix_engine::{
-        graph_core::{ops::tr_val::TraversalVal, traversal_iter::RoTraversalIterator},
-        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
-        types::GraphError,
-    },
-    protocol::{items::Edge, label_hash::hash_label},
+use crate::helix_engine::{
+    graph_core::{ops::tr_val::TraversalVal, traversal_iter::RoTraversalIterator},
+    storage_core::{storage_core::HelixGraphStorage, storage_methods::SearchMethods},
+    types::GraphError,
 };
 use crate::helix_storage::heed3::RoTxn;
-use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    sync::Arc,
-};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub enum PathType {
@@ -22,6 +16,9 @@ pub struct ShortestPathIterator<'a, I> {
     iter: I,
     path_type: PathType,
     edge_label: Option<&'a str>,
+    max_depth: Option<usize>,
+    weight_property: Option<&'a str>,
+    heuristic_property: Option<&'a str>,
     storage: Arc<HelixGraphStorage>,
     txn: &'a RoTxn<'a>,
 }
@@ -31,7 +28,7 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> Iterator
 {
     type Item = Result<TraversalVal, GraphError>;
 
-    /// Returns the next outgoing node by decoding the edge id and then getting the edge and node
+    /// Returns the shortest path between the next node and the fixed end of the path
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
             Some(Ok(TraversalVal::Node(node))) => {
@@ -40,71 +37,30 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> Iterator
                     PathType::To(to) => (node.id, to),
                 };
 
-                let mut queue = VecDeque::with_capacity(32);
-                let mut visited = HashSet::with_capacity(64);
-                let mut parent: HashMap<u128, (u128, Edge)> = HashMap::with_capacity(32);
-                queue.push_back(from);
-                visited.insert(from);
-
-                let reconstruct_path = |parent: &HashMap<u128, (u128, Edge)>,
-                                        start_id: &u128,
-                                        end_id: &u128|
-                 -> Result<TraversalVal, GraphError> {
-                    let mut nodes = Vec::with_capacity(parent.len());
-                    let mut edges = Vec::with_capacity(parent.len() - 1);
-
-                    let mut current = end_id;
-
-                    while current != start_id {
-                        nodes.push(self.storage.get_node(self.txn, current)?);
-
-                        let (prev_node, edge) = &parent[current];
-                        edges.push(edge.clone());
-                        current = prev_node;
+                let path = match (self.weight_property, self.heuristic_property) {
+                    (Some(weight_property), Some(heuristic_property)) => {
+                        self.storage.shortest_path_weighted(
+                            self.txn,
+                            self.edge_label,
+                            &from,
+                            &to,
+                            weight_property,
+                            Some(heuristic_property),
+                        )
                     }
-
-                    nodes.push(self.storage.get_node(self.txn, start_id)?);
-
-                    nodes.reverse();
-                    edges.reverse();
-
-                    Ok(TraversalVal::Path((nodes, edges)))
+                    _ => self.storage.shortest_path(
+                        self.txn,
+                        self.edge_label,
+                        &from,
+                        &to,
+                        self.max_depth,
+                        self.weight_property,
+                    ),
                 };
-
-                while let Some(current_id) = queue.pop_front() {
-                    let out_prefix = self.edge_label.map_or_else(
-                        || current_id.to_be_bytes().to_vec(),
-                        |label| {
-                            HelixGraphStorage::out_edge_key(&current_id, &hash_label(label, None))
-                                .to_vec()
-                        },
-                    );
-
-                    let iter = self
-                        .storage
-                        .out_edges_db
-                        .prefix_iter(self.txn, &out_prefix)
-                        .unwrap();
-
-                    for result in iter {
-                        let (_, value) = result.unwrap(); // TODO: handle error
-                        let (to_node, edge_id) =
-                            HelixGraphStorage::unpack_adj_edge_data(value).unwrap(); // TODO: handle error
-
-                        if !visited.contains(&to_node) {
-                            visited.insert(to_node);
-                            let edge = self.storage.get_edge(self.txn, &edge_id).unwrap(); // TODO: handle error
-                            parent.insert(to_node, (current_id, edge));
-
-                            if to_node == to {
-                                return Some(reconstruct_path(&parent, &from, &to));
-                            }
-
-                            queue.push_back(to_node);
-                        }
-                    }
+                match path {
+                    Ok(path) => Some(Ok(TraversalVal::Path(path))),
+                    Err(e) => Some(Err(e)),
                 }
-                Some(Err(GraphError::ShortestPathNotFound))
             }
             Some(other) => Some(other),
             None => None,
@@ -120,22 +76,64 @@ pub trait ShortestPathAdapter<'a, I>: Iterator<Item = Result<TraversalVal, Graph
     /// * `edge_label` - The label of the edge to use
     /// * `from` - The starting node
     /// * `to` - The ending node
+    /// * `max_depth` - The maximum number of edges in the path
+    /// * `weight_property` - Edge property to weigh edges by, the path with the lowest total
+    ///   weight is returned instead of the one with the fewest edges. Edges without the
+    ///   property weigh 1.
     ///
     /// # Example
     ///
     /// ```rust
-    /// let node1 = Node { id: 1, label: "Person".to_string(), properties: None };
-    /// let node2 = Node { id: 2, label: "Person".to_string(), properties: None };
-    /// let traversal = G::new(storage, &txn).shortest_path(Some("knows"), Some(&node1.id), Some(&node2.id));
+    /// let node1 = Node { id: 1, label: "Person".to_string(), properties: None, score: None };
+    /// let node2 = Node { id: 2, label: "Person".to_string(), properties: None, score: None };
+    /// let traversal = G::new(storage, &txn).shortest_path(Some("knows"), Some(&node1.id), Some(&node2.id), None, None);
     /// ```
     fn shortest_path(
         self,
         edge_label: Option<&'a str>,
         from: Option<&'a u128>,
         to: Option<&'a u128>,
+        max_depth: Option<usize>,
+        weight_property: Option<&'a str>,
     ) -> RoTraversalIterator<'a, ShortestPathIterator<'a, I>>
     where
         I: 'a;
+
+    /// ShortestPathWeighted finds the path between two nodes with the lowest total weight
+    ///
+    /// # Arguments
+    ///
+    /// * `edge_label` - The label of the edge to use
+    /// * `from` - The starting node
+    /// * `to` - The ending node
+    /// * `weight_property` - The edge property holding the weight of an edge
+    /// * `heuristic_property` - Node property holding a position to guide the search
+    ///   towards the ending node with (A*)
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let traversal = G::new(storage, &txn)
+    ///     .shortest_path_weighted(Some("road"), None, Some(&city.id), "distance", Some("coordinates"));
+    /// ```
+    fn shortest_path_weighted(
+        self,
+        edge_label: Option<&'a str>,
+        from: Option<&'a u128>,
+        to: Option<&'a u128>,
+        weight_property: &'a str,
+        heuristic_property: Option<&'a str>,
+    ) -> RoTraversalIterator<'a, ShortestPathIterator<'a, I>>
+    where
+        I: 'a;
+}
+
+fn path_type(from: Option<&u128>, to: Option<&u128>) -> PathType {
+    match (from, to) {
+        (Some(from), None) => PathType::From(*from),
+        (None, Some(to)) => PathType::To(*to),
+        _ => panic!("Invalid shortest path"),
+    }
 }
 
 impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>> + 'a> ShortestPathAdapter<'a, I>
@@ -147,6 +145,39 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>> + 'a> ShortestPath
         edge_label: Option<&'a str>,
         from: Option<&'a u128>,
         to: Option<&'a u128>,
+        max_depth: Option<usize>,
+        weight_property: Option<&'a str>,
+    ) -> RoTraversalIterator<'a, ShortestPathIterator<'a, I>>
+    where
+        I: 'a,
+    {
+        let storage = Arc::clone(&self.storage);
+        let txn = self.txn;
+
+        RoTraversalIterator {
+            inner: ShortestPathIterator {
+                iter: self.inner,
+                path_type: path_type(from, to),
+                edge_label,
+                max_depth,
+                weight_property,
+                heuristic_property: None,
+                storage,
+                txn,
+            },
+            storage: Arc::clone(&self.storage),
+            txn: self.txn,
+        }
+    }
+
+    #[inline]
+    fn shortest_path_weighted(
+        self,
+        edge_label: Option<&'a str>,
+        from: Option<&'a u128>,
+        to: Option<&'a u128>,
+        weight_property: &'a str,
+        heuristic_property: Option<&'a str>,
     ) -> RoTraversalIterator<'a, ShortestPathIterator<'a, I>>
     where
         I: 'a,
@@ -157,12 +188,11 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>> + 'a> ShortestPath
         RoTraversalIterator {
             inner: ShortestPathIterator {
                 iter: self.inner,
-                path_type: match (from, to) {
-                    (Some(from), None) => PathType::From(*from),
-                    (None, Some(to)) => PathType::To(*to),
-                    _ => panic!("Invalid shortest path"),
-                },
+                path_type: path_type(from, to),
                 edge_label,
+                max_depth: None,
+                weight_property: Some(weight_property),
+                heuristic_property,
                 storage,
                 txn,
             },
@@ -1,14 +1,23 @@
-use crate::helix_engine::{
-    graph_core::{
-        ops::tr_val::TraversalVal,
-        traversal_iter::{RoTraversalIterator, RwTraversalIterator},
+use crate::{
+    helix_engine::{
+        graph_core::{
+            ops::tr_val::TraversalVal,
+            traversal_iter::{RoTraversalIterator, RwTraversalIterator},
+        },
+        types::GraphError,
+    },
+    protocol::{
+        filterable::Filterable,
+        redaction,
+        value::{PathSegment, Value},
     },
-    types::GraphError,
 };
 
 pub struct PropsIterator<'a, I> {
     iter: I,
     prop: &'a str,
+    /// Fields and indices followed into the property's value
+    path: &'a [PathSegment<'a>],
 }
 
 // TODO: get rid of clones in return values
@@ -20,9 +29,21 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
+            // sensitive fields the request has no access to read as unset
+            Some(Ok(TraversalVal::Node(node))) if redaction::is_hidden(&node.label, self.prop) => {
+                None
+            }
+            Some(Ok(TraversalVal::Edge(edge))) if redaction::is_hidden(&edge.label, self.prop) => {
+                None
+            }
+            Some(Ok(TraversalVal::Vector(vec))) if redaction::is_hidden(vec.label(), self.prop) => {
+                None
+            }
             Some(Ok(TraversalVal::Node(node))) => match node.properties {
                 Some(prop) => {
-                    let prop = prop.get(self.prop);
+                    let prop = prop
+                        .get(self.prop)
+                        .and_then(|prop| prop.get_path(self.path));
                     match prop {
                         Some(prop) => Some(Ok(TraversalVal::Value(prop.clone()))),
                         None => None,
@@ -32,7 +53,9 @@ where
             },
             Some(Ok(TraversalVal::Edge(edge))) => match edge.properties {
                 Some(prop) => {
-                    let prop = prop.get(self.prop);
+                    let prop = prop
+                        .get(self.prop)
+                        .and_then(|prop| prop.get_path(self.path));
                     match prop {
                         Some(prop) => Some(Ok(TraversalVal::Value(prop.clone()))),
                         None => None,
@@ -42,7 +65,9 @@ where
             },
             Some(Ok(TraversalVal::Vector(vec))) => match vec.properties {
                 Some(prop) => {
-                    let prop = prop.get(self.prop);
+                    let prop = prop
+                        .get(self.prop)
+                        .and_then(|prop| prop.get_path(self.path));
                     match prop {
                         Some(prop) => Some(Ok(TraversalVal::Value(prop.clone()))),
                         None => None,
@@ -54,11 +79,53 @@ where
         }
     }
 }
+pub struct ScoreIterator<I> {
+    iter: I,
+}
+
+impl<I> Iterator for ScoreIterator<I>
+where
+    I: Iterator<Item = Result<TraversalVal, GraphError>>,
+{
+    type Item = Result<TraversalVal, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.iter.by_ref() {
+            let score = match item {
+                Ok(TraversalVal::Node(node)) => node.score(),
+                Ok(TraversalVal::Edge(edge)) => edge.score(),
+                Ok(TraversalVal::Vector(vec)) => vec.score(),
+                Err(e) => return Some(Err(e)),
+                _ => None,
+            };
+            // elements that weren't ranked by a search have no score
+            if let Some(score) = score {
+                return Some(Ok(TraversalVal::Value(Value::F64(score))));
+            }
+        }
+        None
+    }
+}
+
 pub trait PropsAdapter<'a, I>: Iterator<Item = Result<TraversalVal, GraphError>> {
     fn check_property(
         self,
         prop: &'a str,
     ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
+
+    /// Returns the value reached by following `path` into the property, e.g. an
+    /// element of an array or a field of a nested object
+    fn check_property_path(
+        self,
+        prop: &'a str,
+        path: &'a [PathSegment<'a>],
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
+
+    /// Returns the score each element was ranked by, i.e. the distance of a vector to
+    /// the query or the fused score of a hybrid search
+    fn check_score(
+        self,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>;
 }
 
 impl<'a, I> PropsAdapter<'a, I> for RoTraversalIterator<'a, I>
@@ -74,11 +141,40 @@ where
             inner: PropsIterator {
                 iter: self.inner,
                 prop,
+                path: &[],
             },
             storage: self.storage,
             txn: self.txn,
         }
     }
+
+    #[inline]
+    fn check_property_path(
+        self,
+        prop: &'a str,
+        path: &'a [PathSegment<'a>],
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        RoTraversalIterator {
+            inner: PropsIterator {
+                iter: self.inner,
+                prop,
+                path,
+            },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+
+    #[inline]
+    fn check_score(
+        self,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        RoTraversalIterator {
+            inner: ScoreIterator { iter: self.inner },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
 }
 
 impl<'a, 'b, I> PropsAdapter<'a, I> for RwTraversalIterator<'a, 'b, I>
@@ -95,9 +191,38 @@ where
             inner: PropsIterator {
                 iter: self.inner,
                 prop,
+                path: &[],
             },
             storage: self.storage,
             txn: self.txn,
         }
     }
+
+    #[inline]
+    fn check_property_path(
+        self,
+        prop: &'a str,
+        path: &'a [PathSegment<'a>],
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        RoTraversalIterator {
+            inner: PropsIterator {
+                iter: self.inner,
+                prop,
+                path,
+            },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+
+    #[inline]
+    fn check_score(
+        self,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>> {
+        RoTraversalIterator {
+            inner: ScoreIterator { iter: self.inner },
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
 }
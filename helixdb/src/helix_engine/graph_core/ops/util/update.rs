@@ -2,8 +2,11 @@ use std::collections::HashMap;
 
 use crate::{
     helix_engine::{
+        cdc::cdc::{ChangeEvent, ChangeOp, ChangeTarget},
         graph_core::traversal_iter::RwTraversalIterator,
-        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+        storage_core::{
+            storage_core::HelixGraphStorage, storage_methods::StorageMethods, wal::WalOp,
+        },
         types::GraphError,
     },
     protocol::value::Value,
@@ -59,13 +62,35 @@ impl<'scope, 'env, I: Iterator<Item = Result<TraversalVal, GraphError>>> UpdateA
                                     properties.insert(k.clone(), v.clone());
                                 }
                             }
+                            storage.schema_history.fill_node(
+                                &old_node.label,
+                                &mut properties,
+                                false,
+                            );
+                            if let Err(e) = storage
+                                .schema_history
+                                .check_node(&old_node.label, Some(&properties))
+                                .and_then(|_| {
+                                    properties.iter().try_for_each(|(key, v)| {
+                                        storage.check_unique(self.txn, key, v, &node.id)
+                                    })
+                                })
+                            {
+                                vec.push(Err(e));
+                                continue;
+                            }
                             for (key, v) in properties.iter() {
                                 if let Some(db) = storage.secondary_indices.get(key) {
                                     match bincode::serialize(v) {
                                         Ok(serialized) => {
-                                            if let Err(e) = db.put(self.txn, &serialized, &node.id)
-                                            {
-                                                vec.push(Err(GraphError::from(e)));
+                                            if let Err(e) = storage.put_index_entry(
+                                                self.txn,
+                                                key,
+                                                db,
+                                                &serialized,
+                                                &node.id,
+                                            ) {
+                                                vec.push(Err(e));
                                             }
                                         }
                                         Err(e) => vec.push(Err(GraphError::from(e))),
@@ -80,13 +105,35 @@ impl<'scope, 'env, I: Iterator<Item = Result<TraversalVal, GraphError>>> UpdateA
                                     properties.insert(k.clone(), v.clone());
                                 }
                             }
+                            storage.schema_history.fill_node(
+                                &old_node.label,
+                                &mut properties,
+                                false,
+                            );
+                            if let Err(e) = storage
+                                .schema_history
+                                .check_node(&old_node.label, Some(&properties))
+                                .and_then(|_| {
+                                    properties.iter().try_for_each(|(key, v)| {
+                                        storage.check_unique(self.txn, key, v, &node.id)
+                                    })
+                                })
+                            {
+                                vec.push(Err(e));
+                                continue;
+                            }
                             for (key, v) in properties.iter() {
                                 if let Some(db) = storage.secondary_indices.get(key) {
                                     match bincode::serialize(v) {
                                         Ok(serialized) => {
-                                            if let Err(e) = db.put(self.txn, &serialized, &node.id)
-                                            {
-                                                vec.push(Err(GraphError::from(e)));
+                                            if let Err(e) = storage.put_index_entry(
+                                                self.txn,
+                                                key,
+                                                db,
+                                                &serialized,
+                                                &node.id,
+                                            ) {
+                                                vec.push(Err(e));
                                             }
                                         }
                                         Err(e) => vec.push(Err(GraphError::from(e))),
@@ -99,14 +146,35 @@ impl<'scope, 'env, I: Iterator<Item = Result<TraversalVal, GraphError>>> UpdateA
                                 old_node.properties = None;
                             }
                         }
-                        match old_node.encode_node() {
+                        match storage.encode_node(self.txn, &old_node) {
                             Ok(serialized) => {
                                 match storage.nodes_db.put(
                                     self.txn,
                                     &HelixGraphStorage::node_key(&node.id),
                                     &serialized,
                                 ) {
-                                    Ok(_) => vec.push(Ok(TraversalVal::Node(old_node))),
+                                    Ok(_) => match storage.cdc.record(
+                                        self.txn,
+                                        ChangeEvent::new(
+                                            ChangeOp::Update,
+                                            ChangeTarget::Node,
+                                            old_node.id,
+                                            &old_node.label,
+                                        ),
+                                    ).and_then(|_| {
+                                        storage.record_version(
+                                            self.txn,
+                                            &old_node,
+                                            chrono::Utc::now().timestamp_millis(),
+                                        )
+                                    }).and_then(|_| {
+                                        storage.wal.log(self.txn, || {
+                                            WalOp::put_node(&storage.encryption, &old_node)
+                                        })
+                                    }) {
+                                        Ok(_) => vec.push(Ok(TraversalVal::Node(old_node))),
+                                        Err(e) => vec.push(Err(e)),
+                                    },
                                     Err(e) => vec.push(Err(GraphError::from(e))),
                                 }
                             }
@@ -126,14 +194,29 @@ impl<'scope, 'env, I: Iterator<Item = Result<TraversalVal, GraphError>>> UpdateA
                                 old_edge.properties = Some(properties);
                             }
                         }
-                        match old_edge.encode_edge() {
+                        match storage.encode_edge(self.txn, &old_edge) {
                             Ok(serialized) => {
-                                match storage.nodes_db.put(
+                                match storage.edges_db.put(
                                     self.txn,
                                     &HelixGraphStorage::edge_key(&edge.id),
                                     &serialized,
                                 ) {
-                                    Ok(_) => vec.push(Ok(TraversalVal::Edge(old_edge))),
+                                    Ok(_) => match storage.cdc.record(
+                                        self.txn,
+                                        ChangeEvent::new(
+                                            ChangeOp::Update,
+                                            ChangeTarget::Edge,
+                                            old_edge.id,
+                                            &old_edge.label,
+                                        ),
+                                    ).and_then(|_| {
+                                        storage.wal.log(self.txn, || {
+                                            WalOp::put_edge(&storage.encryption, &old_edge)
+                                        })
+                                    }) {
+                                        Ok(_) => vec.push(Ok(TraversalVal::Edge(old_edge))),
+                                        Err(e) => vec.push(Err(e)),
+                                    },
                                     Err(e) => vec.push(Err(GraphError::from(e))),
                                 }
                             }
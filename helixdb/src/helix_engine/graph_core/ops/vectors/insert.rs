@@ -3,6 +3,7 @@ use crate::helix_storage::heed3::RoTxn;
 use super::super::tr_val::TraversalVal;
 use crate::{
     helix_engine::{
+        cdc::cdc::{ChangeEvent, ChangeOp, ChangeTarget},
         graph_core::traversal_iter::RwTraversalIterator,
         types::GraphError,
         vector_core::{hnsw::HNSW, vector::HVector},
@@ -56,15 +57,21 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> InsertVAdapte
     {
         let vector = self
             .storage
-            .vectors
-            .insert::<F>(self.txn, &query, fields);
+            .vectors_of(label)
+            .insert::<F>(self.txn, query, label, fields);
 
         let result = match vector {
-            Ok(vector) => Ok(TraversalVal::Vector(vector)),
+            Ok(vector) => self
+                .storage
+                .cdc
+                .record(
+                    self.txn,
+                    ChangeEvent::new(ChangeOp::Insert, ChangeTarget::Vector, vector.id, label),
+                )
+                .map(|_| TraversalVal::Vector(vector)),
             Err(e) => Err(GraphError::from(e)),
         };
 
-
         RwTraversalIterator {
             inner: std::iter::once(result),
             storage: self.storage,
@@ -85,9 +92,15 @@ impl<'a, 'b, I: Iterator<Item = Result<TraversalVal, GraphError>>> InsertVAdapte
         let iter = vecs
             .iter()
             .map(|vec| {
-                let vector = storage.vectors.insert::<F>(txn, &vec, fields.clone()); // TODO: remove clone
+                let vector = storage.vectors.insert::<F>(txn, vec, "", fields.clone()); // TODO: remove clone
                 match vector {
-                    Ok(vector) => Ok(TraversalVal::Vector(vector)),
+                    Ok(vector) => storage
+                        .cdc
+                        .record(
+                            txn,
+                            ChangeEvent::new(ChangeOp::Insert, ChangeTarget::Vector, vector.id, ""),
+                        )
+                        .map(|_| TraversalVal::Vector(vector)),
                     Err(e) => Err(GraphError::from(e)),
                 }
             })
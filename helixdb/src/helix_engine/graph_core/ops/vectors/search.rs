@@ -30,6 +30,18 @@ pub trait SearchVAdapter<'a>: Iterator<Item = Result<TraversalVal, GraphError>>
     ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
     where
         F: Fn(&HVector, &RoTxn) -> bool;
+
+    /// Searches the index of the label's namespace, rather than the one of the vectors
+    /// outside any namespace
+    fn search_v_of<F>(
+        self,
+        label: &str,
+        query: &[f64],
+        k: usize,
+        filter: Option<&[F]>,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    where
+        F: Fn(&HVector, &RoTxn) -> bool;
 }
 
 impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>> + 'a> SearchVAdapter<'a>
@@ -49,42 +61,35 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>> + 'a> SearchVAdapt
             .vectors
             .search(self.txn, &query, k, filter, false);
 
-        let iter = match vectors {
-            Ok(vectors) => vectors
-                .into_iter()
-                .map(|vector| Ok::<TraversalVal, GraphError>(TraversalVal::Vector(vector)))
-                .collect::<Vec<_>>()
-                .into_iter(),
-            Err(VectorError::VectorNotFound(id)) => {
-                let error = GraphError::VectorError(format!("vector not found for id {}", id));
-                once(Err(error)).collect::<Vec<_>>().into_iter()
-            }
-            Err(VectorError::InvalidVectorData) => {
-                let error = GraphError::VectorError("invalid vector data".to_string());
-                once(Err(error)).collect::<Vec<_>>().into_iter()
-            }
-            Err(VectorError::EntryPointNotFound) => {
-                let error =
-                    GraphError::VectorError("no entry point found for hnsw index".to_string());
-                once(Err(error)).collect::<Vec<_>>().into_iter()
-            }
-            Err(VectorError::ConversionError(e)) => {
-                let error = GraphError::VectorError(format!("conversion error: {}", e));
-                once(Err(error)).collect::<Vec<_>>().into_iter()
-            }
-            Err(VectorError::VectorCoreError(e)) => {
-                let error = GraphError::VectorError(format!("vector core error: {}", e));
-                once(Err(error)).collect::<Vec<_>>().into_iter()
-            }
-            Err(VectorError::InvalidVectorLength) => {
-                let error = GraphError::VectorError("invalid vector dimensions!".to_string());
-                once(Err(error)).collect::<Vec<_>>().into_iter()
-            }
-            .collect::<Vec<_>>()
-            .into_iter(),
+        let iter = SearchV {
+            iter: search_results(vectors),
         };
 
-        let iter = SearchV { iter };
+        RoTraversalIterator {
+            inner: iter,
+            storage: self.storage,
+            txn: self.txn,
+        }
+    }
+
+    fn search_v_of<F>(
+        self,
+        label: &str,
+        query: &[f64],
+        k: usize,
+        filter: Option<&[F]>,
+    ) -> RoTraversalIterator<'a, impl Iterator<Item = Result<TraversalVal, GraphError>>>
+    where
+        F: Fn(&HVector, &RoTxn) -> bool,
+    {
+        let vectors = self
+            .storage
+            .vectors_of(label)
+            .search(self.txn, query, k, filter, false);
+
+        let iter = SearchV {
+            iter: search_results(vectors),
+        };
 
         RoTraversalIterator {
             inner: iter,
@@ -93,3 +98,41 @@ impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>> + 'a> SearchVAdapt
         }
     }
 }
+
+fn search_results(
+    vectors: Result<Vec<HVector>, VectorError>,
+) -> std::vec::IntoIter<Result<TraversalVal, GraphError>> {
+    match vectors {
+        Ok(vectors) => vectors
+            .into_iter()
+            .map(|vector| Ok::<TraversalVal, GraphError>(TraversalVal::Vector(vector)))
+            .collect::<Vec<_>>()
+            .into_iter(),
+        Err(VectorError::VectorNotFound(id)) => {
+            let error = GraphError::VectorError(format!("vector not found for id {}", id));
+            once(Err(error)).collect::<Vec<_>>().into_iter()
+        }
+        Err(VectorError::InvalidVectorData) => {
+            let error = GraphError::VectorError("invalid vector data".to_string());
+            once(Err(error)).collect::<Vec<_>>().into_iter()
+        }
+        Err(VectorError::EntryPointNotFound) => {
+            let error = GraphError::VectorError("no entry point found for hnsw index".to_string());
+            once(Err(error)).collect::<Vec<_>>().into_iter()
+        }
+        Err(VectorError::ConversionError(e)) => {
+            let error = GraphError::VectorError(format!("conversion error: {}", e));
+            once(Err(error)).collect::<Vec<_>>().into_iter()
+        }
+        Err(VectorError::VectorCoreError(e)) => {
+            let error = GraphError::VectorError(format!("vector core error: {}", e));
+            once(Err(error)).collect::<Vec<_>>().into_iter()
+        }
+        Err(VectorError::InvalidVectorLength) => {
+            let error = GraphError::VectorError("invalid vector dimensions!".to_string());
+            once(Err(error)).collect::<Vec<_>>().into_iter()
+        }
+        .collect::<Vec<_>>()
+        .into_iter(),
+    }
+}
@@ -0,0 +1,62 @@
+//! Properties decoded for the nodes and edges read by a traversal.
+//!
+//! A traversal that only hands back some properties of its items, or a count of them,
+//! doesn't need the rest of their properties decoded. The generated queries run such
+//! traversals in a [`Projection::scope`] listing the properties they read, and the nodes
+//! and edges read by the current thread in the meantime are decoded with only those.
+//! Outside a scope, items are decoded in full.
+
+use std::cell::Cell;
+
+use crate::{
+    helix_engine::types::GraphError,
+    protocol::{
+        item_view::{EdgeView, NodeView},
+        items::{Edge, Node},
+    },
+};
+
+type Fields = &'static [&'static str];
+
+thread_local! {
+    static FIELDS: Cell<Option<Fields>> = const { Cell::new(None) };
+}
+
+pub struct Projection;
+
+impl Projection {
+    /// Runs `f` decoding only the given properties of the nodes and edges it reads, the
+    /// projection of any enclosing scope being restored afterwards
+    pub fn scope<T>(fields: Fields, f: impl FnOnce() -> T) -> T {
+        struct Restore(Option<Fields>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                FIELDS.with(|cell| cell.set(self.0));
+            }
+        }
+        let _restore = Restore(FIELDS.with(|cell| cell.replace(Some(fields))));
+        f()
+    }
+}
+
+/// Properties decoded for the items read by the current thread, `None` if all are
+#[inline]
+pub fn fields() -> Option<Fields> {
+    FIELDS.with(|cell| cell.get())
+}
+
+/// Decodes a viewed node with the properties of the current projection
+pub fn node(view: &NodeView) -> Result<Node, GraphError> {
+    match fields() {
+        Some(fields) => view.decode_only(fields),
+        None => view.decode(),
+    }
+}
+
+/// Decodes a viewed edge with the properties of the current projection
+pub fn edge(view: &EdgeView) -> Result<Edge, GraphError> {
+    match fields() {
+        Some(fields) => view.decode_only(fields),
+        None => view.decode(),
+    }
+}
@@ -0,0 +1,243 @@
+//! Execution limits of single queries, so one pathological query can't take down an
+//! instance.
+//!
+//! The limits are tracked for the thread running a query between [`QueryGuard::start`]
+//! and [`QueryGuard::finish`]. Once the query goes over one of them its traversals stop
+//! yielding items and it fails with [`GraphError::QueryLimitExceeded`]. Traversals run
+//! outside a guard, e.g. in tests or by embedders, aren't limited.
+//!
+//! Queries are also cancelled cooperatively: the traversals check the query's
+//! [`CancellationToken`] every [`CLOCK_INTERVAL`] items, and a query whose token was
+//! cancelled or whose deadline passed fails with [`GraphError::QueryCancelled`] along
+//! with what it did up to then.
+
+use std::{
+    cell::RefCell,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use super::{config::QueryLimitsConfig, ops::tr_val::TraversalVal};
+use crate::helix_engine::types::GraphError;
+
+/// Number of items visited between two checks of whether a query was cancelled
+pub const CLOCK_INTERVAL: u64 = 64;
+
+thread_local! {
+    static BUDGET: RefCell<Option<Budget>> = const { RefCell::new(None) };
+}
+
+/// Cancels the queries it is handed to, either once [`Self::cancel`] is called on it
+/// or one of its parents, or once its deadline passes
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    parent: Option<Box<CancellationToken>>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token cancelled along with this one, or once the deadline passes
+    pub fn child(&self, deadline: Option<Instant>) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            parent: Some(Box::new(self.clone())),
+            deadline,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_cancelled())
+    }
+}
+
+/// What a cancelled query did before it was cancelled, sent back to the client
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct QueryStats {
+    /// Items visited by the query's traversal steps
+    pub visited: u64,
+    /// Estimated bytes of the items collected by the query's traversals
+    pub collected_bytes: usize,
+    /// Milliseconds the query ran for
+    pub elapsed_ms: u64,
+}
+
+/// Why a query was stopped
+enum Stop {
+    Exceeded(String),
+    Cancelled,
+}
+
+struct Budget {
+    limits: QueryLimitsConfig,
+    token: CancellationToken,
+    started: Instant,
+    visited: u64,
+    collected_bytes: usize,
+    /// The first reason the query was stopped for
+    stopped: Option<Stop>,
+}
+
+impl Budget {
+    fn exceed(&mut self, limit: String) -> bool {
+        self.stopped.get_or_insert(Stop::Exceeded(limit));
+        false
+    }
+
+    fn stats(&self) -> QueryStats {
+        QueryStats {
+            visited: self.visited,
+            collected_bytes: self.collected_bytes,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+        }
+    }
+
+    fn visit(&mut self) -> bool {
+        if self.stopped.is_some() {
+            return false;
+        }
+        self.visited += 1;
+        if let Some(max) = self.limits.max_visited {
+            if self.visited > max {
+                return self.exceed(format!("visited more than {} items", max));
+            }
+        }
+        if self.visited.is_multiple_of(CLOCK_INTERVAL) && self.token.is_cancelled() {
+            self.stopped = Some(Stop::Cancelled);
+            return false;
+        }
+        true
+    }
+
+    fn collect(&mut self, item: &TraversalVal) -> bool {
+        if self.stopped.is_some() {
+            return false;
+        }
+        let Some(max) = self.limits.max_collected_bytes else {
+            return true;
+        };
+        self.collected_bytes += item.estimated_size();
+        if self.collected_bytes > max {
+            return self.exceed(format!("collected more than {} bytes of items", max));
+        }
+        true
+    }
+}
+
+/// Tracks the limits of the query run by the current thread until it is finished or
+/// dropped
+pub struct QueryGuard {
+    // the limits are tracked for the thread that started the query
+    _thread: PhantomData<*const ()>,
+}
+
+impl QueryGuard {
+    /// Starts tracking the limits of a query cancelled with the given token, replacing
+    /// those of any query the thread was running. The query is also cancelled once it
+    /// runs for longer than `max_duration_ms`.
+    pub fn start(limits: &QueryLimitsConfig, token: CancellationToken) -> QueryGuard {
+        let started = Instant::now();
+        let token = match limits.max_duration_ms {
+            Some(max) => token.child(Some(started + Duration::from_millis(max))),
+            None => token,
+        };
+        let budget = Budget {
+            limits: *limits,
+            token,
+            started,
+            visited: 0,
+            collected_bytes: 0,
+            stopped: None,
+        };
+        BUDGET.with(|cell| *cell.borrow_mut() = Some(budget));
+        QueryGuard {
+            _thread: PhantomData,
+        }
+    }
+
+    /// Stops tracking the limits, failing if the query went over one of them or was
+    /// cancelled
+    pub fn finish(self) -> Result<(), GraphError> {
+        check()
+    }
+}
+
+impl Drop for QueryGuard {
+    fn drop(&mut self) {
+        BUDGET.with(|cell| cell.borrow_mut().take());
+    }
+}
+
+/// Limits of a query taken off the thread running it, so the query can be continued on
+/// another thread
+pub struct DetachedBudget(Option<Budget>);
+
+impl DetachedBudget {
+    /// Tracks the limits on the current thread, replacing those of any query it was
+    /// running
+    pub fn attach(self) {
+        BUDGET.with(|cell| *cell.borrow_mut() = self.0);
+    }
+}
+
+/// Takes the limits of the query run by the current thread off it
+pub fn detach() -> DetachedBudget {
+    DetachedBudget(BUDGET.with(|cell| cell.borrow_mut().take()))
+}
+
+/// Fails if the query run by the current thread went over one of its limits or was
+/// cancelled, called before committing the writes of a query
+pub fn check() -> Result<(), GraphError> {
+    BUDGET.with(|cell| {
+        let cell = cell.borrow();
+        let Some(budget) = cell.as_ref() else {
+            return Ok(());
+        };
+        match &budget.stopped {
+            Some(Stop::Exceeded(limit)) => Err(GraphError::QueryLimitExceeded(limit.clone())),
+            Some(Stop::Cancelled) => Err(GraphError::QueryCancelled(budget.stats())),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Counts an item a traversal step is asked for, returning false once the query is over
+/// one of its limits
+#[inline]
+pub fn visit() -> bool {
+    BUDGET.with(|cell| match cell.borrow_mut().as_mut() {
+        Some(budget) => budget.visit(),
+        None => true,
+    })
+}
+
+/// Counts an item collected by a traversal, returning false once the query is over one
+/// of its limits
+#[inline]
+pub fn collect(item: &TraversalVal) -> bool {
+    BUDGET.with(|cell| match cell.borrow_mut().as_mut() {
+        Some(budget) => budget.collect(item),
+        None => true,
+    })
+}
@@ -0,0 +1,164 @@
+//! Read views of the graph pinned across queries.
+//!
+//! Every query reads the graph through a read transaction of its own, seeing the graph as
+//! it was when the transaction started: writes committed while it runs aren't visible
+//! to it, and it neither blocks nor is blocked by writers. Two queries usually read
+//! different snapshots though, so a result read over several requests, such as the
+//! pages of a large result set, may mix states of the graph.
+//!
+//! A [`Snapshot`] is a read view pinned by the caller, which any number of queries can
+//! read until its last handle is dropped. The pages of a pinned snapshot can't be reused
+//! by writers, so the database grows while snapshots are held, and the number pinned at
+//! once is capped by `max_snapshots` in the config.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use super::query_limits::{self, DetachedBudget};
+use crate::{
+    helix_engine::{storage_core::storage_core::HelixGraphStorage, types::GraphError},
+    helix_storage::heed3::RoTxn,
+};
+
+type Read = Box<dyn FnOnce(&Arc<HelixGraphStorage>, &RoTxn) + Send>;
+
+/// Number of snapshots pinned at once, capped by the config
+pub struct Snapshots {
+    max: usize,
+    pinned: Arc<AtomicUsize>,
+}
+
+/// Counts a snapshot as pinned until it is dropped
+struct Permit(Arc<AtomicUsize>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl Snapshots {
+    pub const DEFAULT_MAX: usize = 32;
+
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            max: max.unwrap_or(Self::DEFAULT_MAX),
+            pinned: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Number of snapshots currently pinned
+    pub fn pinned(&self) -> usize {
+        self.pinned.load(Ordering::Acquire)
+    }
+
+    /// Whether no more snapshots can be pinned until one is released
+    pub fn is_full(&self) -> bool {
+        self.pinned() >= self.max
+    }
+
+    fn acquire(&self) -> Result<Permit, GraphError> {
+        self.pinned
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pinned| {
+                (pinned < self.max).then_some(pinned + 1)
+            })
+            .map_err(|_| {
+                GraphError::New(format!(
+                    "Too many snapshots pinned, at most {} can be held at once",
+                    self.max
+                ))
+            })?;
+        Ok(Permit(Arc::clone(&self.pinned)))
+    }
+}
+
+/// Read view of the graph pinned at the moment it was taken.
+///
+/// The read transaction of the snapshot can't move between threads, so it is held by a
+/// thread of its own which runs the reads. Handles can be cloned and sent across
+/// threads, the snapshot being counted as released as soon as the last one is dropped.
+#[derive(Clone)]
+pub struct Snapshot {
+    inner: Arc<Pinned>,
+}
+
+struct Pinned {
+    /// Id of the read transaction, as given to the cursor cache
+    id: usize,
+    reads: flume::Sender<Read>,
+    _permit: Permit,
+}
+
+impl Snapshot {
+    /// Pins the latest committed state of the graph, failing if the maximum number of
+    /// snapshots are already pinned
+    pub fn pin(storage: &Arc<HelixGraphStorage>) -> Result<Snapshot, GraphError> {
+        let permit = storage.snapshots.acquire()?;
+        let (reads, queued) = flume::unbounded::<Read>();
+        let (opened, id) = flume::bounded(1);
+        let storage = Arc::clone(storage);
+        thread::Builder::new()
+            .name("helix-snapshot".to_string())
+            .spawn(move || {
+                // the map can't be resized while the snapshot is pinned
+                let _hold = storage.map_size.pin();
+                let txn = match storage.graph_env.read_txn() {
+                    Ok(txn) => txn,
+                    Err(e) => {
+                        let _ = opened.send(Err(GraphError::from(e)));
+                        return;
+                    }
+                };
+                let _ = opened.send(Ok(txn.id()));
+                // ends once every handle of the snapshot is dropped
+                for read in queued.iter() {
+                    read(&storage, &txn);
+                }
+            })?;
+        let id = id
+            .recv()
+            .map_err(|_| GraphError::New("Failed to pin the snapshot".to_string()))??;
+        Ok(Snapshot {
+            inner: Arc::new(Pinned {
+                id,
+                reads,
+                _permit: permit,
+            }),
+        })
+    }
+
+    /// Id of the snapshot's read transaction
+    pub fn id(&self) -> usize {
+        self.inner.id
+    }
+
+    /// Runs `read` against the snapshot, within the limits of the query the current
+    /// thread is running
+    pub fn read<T, F>(&self, read: F) -> Result<T, GraphError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Arc<HelixGraphStorage>, &RoTxn) -> Result<T, GraphError> + Send + 'static,
+    {
+        let (done, result) = flume::bounded::<(Result<T, GraphError>, DetachedBudget)>(1);
+        let budget = query_limits::detach();
+        self.inner
+            .reads
+            .send(Box::new(move |storage, txn| {
+                budget.attach();
+                let output = read(storage, txn);
+                let _ = done.send((output, query_limits::detach()));
+            }))
+            .map_err(|_| GraphError::New("The snapshot was released".to_string()))?;
+        // the thread of the snapshot dies with a read that panics
+        let (output, budget) = result
+            .recv()
+            .map_err(|_| GraphError::New("The read of the snapshot panicked".to_string()))?;
+        budget.attach();
+        output
+    }
+}
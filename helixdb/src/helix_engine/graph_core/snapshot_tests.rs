@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+            ops::{
+                g::G,
+                source::{add_n::AddNAdapter, n_from_type::NFromTypeAdapter},
+            },
+        },
+        storage_core::storage_core::HelixGraphStorage,
+        types::GraphError,
+    },
+    props,
+};
+
+fn open(dir: &TempDir, max_snapshots: Option<usize>) -> HelixGraphEngine {
+    let mut config = Config::default();
+    config.max_snapshots = max_snapshots;
+    let opts = HelixGraphEngineOpts {
+        config,
+        ..HelixGraphEngineOpts::with_path(dir.path().to_str().unwrap().to_string())
+    };
+    HelixGraphEngine::new(opts).unwrap()
+}
+
+fn add_person(storage: &Arc<HelixGraphStorage>, name: &str) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n("person", Some(props! { "name" => name }), None)
+        .collect_to_val();
+    txn.commit().unwrap();
+}
+
+fn count_people(storage: &Arc<HelixGraphStorage>) -> usize {
+    let txn = storage.graph_env.read_txn().unwrap();
+    G::new(Arc::clone(storage), &txn)
+        .n_from_type("person")
+        .count()
+}
+
+#[test]
+fn test_snapshot_isolated_from_later_writes() {
+    let dir = TempDir::new().unwrap();
+    let engine = open(&dir, None);
+    add_person(&engine.storage, "alice");
+
+    let snapshot = engine.snapshot().unwrap();
+    add_person(&engine.storage, "bob");
+
+    let read = || {
+        snapshot.read(|storage, txn| {
+            Ok(G::new(Arc::clone(storage), txn)
+                .n_from_type("person")
+                .count())
+        })
+    };
+    // every read of the snapshot sees the graph as it was when it was pinned
+    assert_eq!(read().unwrap(), 1);
+    assert_eq!(read().unwrap(), 1);
+    assert_eq!(count_people(&engine.storage), 2);
+}
+
+#[test]
+fn test_snapshot_read_across_threads() {
+    let dir = TempDir::new().unwrap();
+    let engine = open(&dir, None);
+    add_person(&engine.storage, "alice");
+    let snapshot = engine.snapshot().unwrap();
+
+    let handles = (0..4)
+        .map(|_| {
+            let snapshot = snapshot.clone();
+            std::thread::spawn(move || {
+                snapshot
+                    .read(|storage, txn| {
+                        Ok(G::new(Arc::clone(storage), txn)
+                            .n_from_type("person")
+                            .count())
+                    })
+                    .unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+    add_person(&engine.storage, "bob");
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 1);
+    }
+}
+
+#[test]
+fn test_snapshot_limit() {
+    let dir = TempDir::new().unwrap();
+    let engine = open(&dir, Some(2));
+
+    let first = engine.snapshot().unwrap();
+    let second = first.clone();
+    let third = engine.snapshot().unwrap();
+    assert_ne!(first.id(), 0);
+    assert_eq!(engine.storage.snapshots.pinned(), 2);
+    assert!(matches!(engine.snapshot(), Err(GraphError::New(_))));
+
+    // the snapshot is only released once all of its handles are dropped
+    drop(first);
+    assert!(engine.snapshot().is_err());
+    drop(second);
+    while engine.storage.snapshots.pinned() > 1 {
+        std::thread::yield_now();
+    }
+    let fourth = engine.snapshot().unwrap();
+    assert_eq!(engine.storage.snapshots.pinned(), 2);
+    drop((third, fourth));
+}
@@ -2,9 +2,17 @@ use std::sync::Arc;
 
 use crate::helix_storage::heed3::{RoTxn, RwTxn, WithTls};
 
-use super::ops::tr_val::TraversalVal;
-use crate::helix_engine::{storage_core::storage_core::HelixGraphStorage, types::GraphError};
+use super::{
+    config::ParallelConfig,
+    ops::{source::add_e::EdgeType, tr_val::TraversalVal},
+    query_limits,
+};
+use crate::helix_engine::{
+    storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    types::GraphError,
+};
 use itertools::Itertools;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
 
 pub struct RoTraversalIterator<'a, I> {
     pub inner: I,
@@ -20,31 +28,37 @@ where
     type Item = Result<TraversalVal, GraphError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // the traversals of a query over its limits stop, the query fails once done
+        if !query_limits::visit() {
+            return None;
+        }
         self.inner.next()
     }
 }
 
 impl<'a, I: Iterator<Item = Result<TraversalVal, GraphError>>> RoTraversalIterator<'a, I> {
     pub fn take_and_collect_to<B: FromIterator<TraversalVal>>(self, n: usize) -> B {
-        self.inner
-            .filter_map(|item| item.ok())
+        self.filter_map(|item| item.ok())
             .take(n)
+            .take_while(query_limits::collect)
             .collect::<B>()
     }
 
     pub fn collect_to<B: FromIterator<TraversalVal>>(self) -> B {
-        self.inner.filter_map(|item| item.ok()).collect::<B>()
+        self.filter_map(|item| item.ok())
+            .take_while(query_limits::collect)
+            .collect::<B>()
     }
 
     pub fn collect_dedup<B: FromIterator<TraversalVal>>(self) -> B {
-        self.inner
-            .filter_map(|item| item.ok())
+        self.filter_map(|item| item.ok())
             .unique()
+            .take_while(query_limits::collect)
             .collect::<B>()
     }
 
-    pub fn collect_to_obj(self) -> Option<TraversalVal> {
-        self.inner.filter_map(|item| item.ok()).take(1).next()
+    pub fn collect_to_obj(mut self) -> Option<TraversalVal> {
+        self.find_map(|item| item.ok())
     }
 }
 pub struct RwTraversalIterator<'scope, 'env, I> {
@@ -61,6 +75,10 @@ where
     type Item = Result<TraversalVal, GraphError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // the traversals of a query over its limits stop, the query fails once done
+        if !query_limits::visit() {
+            return None;
+        }
         self.inner.next()
     }
 }
@@ -77,7 +95,18 @@ impl<'scope, 'env, I: Iterator> RwTraversalIterator<'scope, 'env, I> {
     where
         I: Iterator<Item = Result<TraversalVal, GraphError>>,
     {
-        self.inner.filter_map(|item| item.ok()).collect::<B>()
+        self.filter_map(|item| item.ok())
+            .take_while(query_limits::collect)
+            .collect::<B>()
+    }
+
+    /// Collects the items, failing on the first error instead of skipping it
+    pub fn try_collect_to<B: FromIterator<TraversalVal>>(self) -> Result<B, GraphError>
+    where
+        I: Iterator<Item = Result<TraversalVal, GraphError>>,
+    {
+        self.take_while(|item| item.as_ref().map_or(true, query_limits::collect))
+            .collect::<Result<B, GraphError>>()
     }
 
     pub fn collect_to_val(self) -> TraversalVal
@@ -85,8 +114,8 @@ impl<'scope, 'env, I: Iterator> RwTraversalIterator<'scope, 'env, I> {
         I: Iterator<Item = Result<TraversalVal, GraphError>>,
     {
         match self
-            .inner
             .filter_map(|item| item.ok())
+            .take_while(query_limits::collect)
             .collect::<Vec<_>>()
             .first()
         {
@@ -95,6 +124,89 @@ impl<'scope, 'env, I: Iterator> RwTraversalIterator<'scope, 'env, I> {
         }
     }
 }
+
+/// Fetches the items adjacent to high fanout nodes on a pool of worker threads.
+///
+/// Read transactions can't be shared between threads, so every worker opens its own.
+/// Worker transactions have to read the same snapshot as the traversal, which only holds
+/// for read transactions on the latest snapshot. Fetching falls back to the traversal's
+/// own transaction otherwise, e.g. inside write transactions or if something was
+/// committed since the traversal started.
+pub struct ParallelFanout {
+    pool: ThreadPool,
+    threshold: usize,
+}
+
+impl ParallelFanout {
+    pub const DEFAULT_THRESHOLD: usize = 1024;
+
+    /// Returns `None` if parallel traversal is disabled in the config
+    pub fn new(config: &ParallelConfig) -> Result<Option<ParallelFanout>, GraphError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(config.threads.unwrap_or(0))
+            .thread_name(|i| format!("helix-traversal-{}", i))
+            .build()
+            .map_err(|e| GraphError::New(format!("Failed to start traversal workers: {}", e)))?;
+        Ok(Some(ParallelFanout {
+            pool,
+            threshold: config.threshold.unwrap_or(Self::DEFAULT_THRESHOLD),
+        }))
+    }
+
+    /// Fetches the nodes or vectors with the given ids, skipping the ones that don't exist
+    pub fn fetch_items(
+        &self,
+        storage: &HelixGraphStorage,
+        txn: &RoTxn,
+        ids: &[u128],
+        edge_type: &EdgeType,
+    ) -> Vec<TraversalVal> {
+        let fetch = |txn: &RoTxn, id: &u128| match edge_type {
+            EdgeType::Node => storage.get_node(txn, id).ok().map(TraversalVal::Node),
+            EdgeType::Vec => storage.get_vector(txn, id).ok().map(TraversalVal::Vector),
+        };
+        if ids.len() >= self.threshold {
+            if let Some(items) = self.fetch_parallel(storage, txn.id(), ids, &fetch) {
+                return items;
+            }
+        }
+        ids.iter().filter_map(|id| fetch(txn, id)).collect()
+    }
+
+    fn fetch_parallel<F>(
+        &self,
+        storage: &HelixGraphStorage,
+        txn_id: usize,
+        ids: &[u128],
+        fetch: &F,
+    ) -> Option<Vec<TraversalVal>>
+    where
+        F: Fn(&RoTxn, &u128) -> Option<TraversalVal> + Sync,
+    {
+        if storage.graph_env.info().last_txn_id != txn_id {
+            return None;
+        }
+        let chunk_size = ids.len().div_ceil(self.pool.current_num_threads() * 4);
+        let chunks = self.pool.install(|| {
+            ids.par_chunks(chunk_size.max(1))
+                .map(|chunk| {
+                    // a chunk is fetched without yielding to rayon, so a worker never
+                    // holds more than one read transaction
+                    let txn = storage.graph_env.read_txn().ok()?;
+                    if txn.id() != txn_id {
+                        return None;
+                    }
+                    Some(chunk.iter().filter_map(|id| fetch(&txn, id)).collect::<Vec<_>>())
+                })
+                .collect::<Option<Vec<_>>>()
+        })?;
+        Some(chunks.into_iter().flatten().collect())
+    }
+}
+
 // pub trait TraversalIteratorMut<'a> {
 //     type Inner: Iterator<Item = Result<TraversalVal, GraphError>>;
 
@@ -1,31 +1,41 @@
-use std::{sync::Arc, time::Instant};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
 use crate::{
     helix_engine::graph_core::ops::{
-        source::{bulk_add_e::BulkAddEAdapter, e_from_type::EFromTypeAdapter},
-        util::drop::Drop,
+        source::{
+            bulk_add_e::BulkAddEAdapter, e_from_index::EFromIndexAdapter,
+            e_from_type::EFromTypeAdapter,
+        },
+        util::drop::{Drop, DropSource, DEFAULT_DROP_BATCH_SIZE},
     },
     props,
 };
 use crate::{
     helix_engine::graph_core::ops::{
-        source::{n_from_index::NFromIndexAdapter, n_from_type::NFromTypeAdapter},
-        util::paths::ShortestPathAdapter,
+        source::{
+            n_from_index::NFromIndexAdapter, n_from_type::NFromTypeAdapter,
+            n_from_type_ordered::NFromTypeOrderedAdapter,
+        },
+        util::{
+            order_by::{HelixOrder, OrderByAdapter},
+            paths::ShortestPathAdapter,
+            props::PropsAdapter,
+        },
     },
     protocol::{
         filterable::Filterable,
         id::ID,
         items::{Edge, Node},
         traversal_value::TraversalValue,
-        value::Value,
+        value::{PathSegment, Value},
     },
 };
 use crate::{
     helix_engine::{
         graph_core::ops::{
             g::G,
-            in_::{in_e::InEdgesAdapter, to_n::ToNAdapter},
-            out::{from_n::FromNAdapter, out::OutAdapter},
+            in_::{in_count::InCountAdapter, in_e::InEdgesAdapter, to_n::ToNAdapter},
+            out::{from_n::FromNAdapter, out::OutAdapter, out_count::OutCountAdapter},
             source::{
                 add_n::AddNAdapter, bulk_add_n::BulkAddNAdapter, e_from_id::EFromIdAdapter,
                 n_from_id::NFromIdAdapter,
@@ -33,21 +43,33 @@ use crate::{
             tr_val::{Traversable, TraversalVal},
             util::{dedup::DedupAdapter, range::RangeAdapter},
         },
-        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+        storage_core::{
+            storage_core::HelixGraphStorage,
+            storage_methods::{SearchMethods, StorageMethods},
+        },
         types::GraphError,
     },
-    protocol::items::v6_uuid,
+    protocol::{item_view::NodeView, items::v6_uuid, return_values::ReturnValue},
 };
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use tempfile::TempDir;
 
+use super::graph_core::{HelixGraphEngine, HelixGraphEngineOpts};
+use super::{
+    config::QueryLimitsConfig,
+    projection::{self, Projection},
+    query_limits::{CancellationToken, QueryGuard, CLOCK_INTERVAL},
+};
+
 use super::ops::{
     in_::in_::InAdapter,
     out::out_e::OutEdgesAdapter,
     source::add_e::{AddEAdapter, EdgeType},
     util::{filter_ref::FilterRefAdapter, update::UpdateAdapter},
+    vectors::{insert::InsertVAdapter, search::SearchVAdapter},
 };
+use crate::{helix_engine::vector_core::vector::HVector, helix_storage::heed3::RoTxn};
 
 fn setup_test_db() -> (Arc<HelixGraphStorage>, TempDir) {
     let temp_dir = TempDir::new().unwrap();
@@ -84,6 +106,114 @@ fn test_add_n() {
     txn.commit().unwrap();
 }
 
+#[test]
+fn test_in_memory_engine() {
+    let engine = HelixGraphEngine::new(HelixGraphEngineOpts::in_memory()).unwrap();
+    let storage = Arc::clone(&engine.storage);
+    assert!(storage.is_in_memory());
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let nodes = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props! { "name" => "John"}), None)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let node = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&nodes.first().unwrap().id())
+        .collect_to::<Vec<_>>();
+    assert_eq!(node.first().unwrap().label(), "person");
+    drop(txn);
+
+    let path = storage.graph_env.path().to_path_buf();
+    assert!(path.exists());
+    drop(engine);
+    drop(storage);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_add_n_sharded() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = super::config::Config::default();
+    config.sharding = Some(super::config::ShardingConfig {
+        shard: 1,
+        shards: vec!["127.0.0.1:6969".to_string(), "127.0.0.1:6970".to_string()],
+        api_key: None,
+    });
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+    let shards = storage.shards.clone().unwrap();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for _ in 0..20 {
+        let node = G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_n("person", None, None)
+            .collect_to::<Vec<_>>();
+        assert_eq!(shards.shard_of(node.first().unwrap().id()), 1);
+    }
+    txn.commit().unwrap();
+}
+
+#[test]
+fn test_namespaces() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = super::config::Config::default();
+    for (namespace, label) in [("blog", "post"), ("shop", "product")] {
+        config.namespaces.insert(
+            namespace.to_string(),
+            super::config::NamespaceConfig {
+                labels: vec![label.to_string(), format!("{}_embedding", label)],
+                vector_config: None,
+            },
+        );
+    }
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for label in ["person", "post", "product", "post"] {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_n(label, None, None)
+            .collect_to::<Vec<_>>();
+    }
+    for (label, data) in [
+        ("post_embedding", vec![1.0, 0.0]),
+        ("product_embedding", vec![0.9, 0.1]),
+    ] {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .insert_v::<fn(&HVector, &RoTxn) -> bool>(&data, label, None)
+            .collect_to::<Vec<_>>();
+    }
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let posts = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("post")
+        .collect_to::<Vec<_>>();
+    assert_eq!(posts.len(), 2);
+    let blog = storage.namespaces.id_range("post");
+    assert!(posts.iter().all(|post| blog.contains(&post.id())));
+    assert!(!blog.contains(storage.namespaces.id_range("product").start()));
+    assert!(!blog.contains(storage.namespaces.id_range("person").end()));
+
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert_eq!(people.len(), 1);
+
+    // each namespace searches only its own vectors
+    let vectors = G::new(Arc::clone(&storage), &txn)
+        .search_v_of::<fn(&HVector, &RoTxn) -> bool>("product_embedding", &[1.0, 0.0], 10, None)
+        .collect_to::<Vec<_>>();
+    assert_eq!(vectors.len(), 1);
+    assert_eq!(
+        storage.namespaces.namespace_of_id(vectors[0].id()),
+        Some("shop")
+    );
+    assert!(storage.get_vector(&txn, &vectors[0].id()).is_ok());
+}
+
 #[test]
 fn test_add_e() {
     let (storage, _temp_dir) = setup_test_db();
@@ -187,6 +317,65 @@ fn test_out() {
     assert_eq!(nodes[0].id(), person2.id());
 }
 
+#[test]
+fn test_out_count_and_in_count() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    // Create graph: (person1)-[knows]->(person2), (person1)-[knows]->(person3),
+    // (person2)-[knows]->(person3), (person1)-[likes]->(person3)
+    let people = (0..3)
+        .map(|_| {
+            G::new_mut(Arc::clone(&storage), &mut txn)
+                .add_n("person", Some(props!()), None)
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    for (label, from, to) in [
+        ("knows", 0, 1),
+        ("knows", 0, 2),
+        ("knows", 1, 2),
+        ("likes", 0, 2),
+    ] {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_e(label, None, people[from], people[to], false, EdgeType::Node)
+            .collect_to::<Vec<_>>();
+    }
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let count = |id: u128, out: bool| {
+        let start = G::new(Arc::clone(&storage), &txn).n_from_id(&id);
+        match out {
+            true => start.out_count("knows").unwrap(),
+            false => start.in_count("knows").unwrap(),
+        }
+    };
+    assert_eq!(count(people[0], true), 2);
+    assert_eq!(count(people[2], true), 0);
+    assert_eq!(count(people[2], false), 2);
+    assert_eq!(count(people[0], false), 0);
+
+    // counts of all items are added up, like the count of their neighbours
+    let total = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .out_count("knows")
+        .unwrap();
+    let neighbours = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .out("knows", &EdgeType::Node)
+        .count();
+    assert_eq!(total, 3);
+    assert_eq!(total, neighbours);
+
+    // errors reading the items are returned rather than counted as no edges
+    let missing = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&u128::MAX)
+        .out_count("knows");
+    assert!(missing.is_err());
+}
+
 #[test]
 fn test_out_e() {
     let (storage, _temp_dir) = setup_test_db();
@@ -588,6 +777,366 @@ fn test_range_empty() {
     assert_eq!(count.len(), 0);
 }
 
+/// Adds persons with the given ages, or without an age for `None`
+fn add_aged_persons(
+    storage: &Arc<HelixGraphStorage>,
+    ages: &[Option<i32>],
+    indices: Option<&[&str]>,
+) -> Vec<u128> {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let ids = ages
+        .iter()
+        .map(|age| {
+            // nodes can't be indexed by a property they don't have
+            let props = age.map(|age| props! { "age" => age });
+            G::new_mut(Arc::clone(storage), &mut txn)
+                .add_n("person", props, age.and(indices))
+                .collect_to_val()
+                .id()
+        })
+        .collect();
+    txn.commit().unwrap();
+    ids
+}
+
+#[test]
+fn test_order_by() {
+    let (storage, _temp_dir) = setup_test_db();
+    let ids = add_aged_persons(
+        &storage,
+        &[Some(30), Some(25), Some(40), None, Some(35), Some(30)],
+        None,
+    );
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let desc = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .order_by("age", HelixOrder::Desc, None)
+        .collect_to::<Vec<_>>();
+    // equal ages keep their order, no age comes last
+    assert_eq!(
+        desc.iter().map(|n| n.id()).collect::<Vec<_>>(),
+        vec![ids[2], ids[4], ids[0], ids[5], ids[1], ids[3]]
+    );
+
+    let asc = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .order_by("age", HelixOrder::Asc, Some(3))
+        .range(1, 3)
+        .collect_to::<Vec<_>>();
+    assert_eq!(
+        asc.iter().map(|n| n.id()).collect::<Vec<_>>(),
+        vec![ids[0], ids[5]]
+    );
+}
+
+#[test]
+fn test_n_from_type_ordered() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = super::config::Config::default();
+    config.graph_config.secondary_indices = Some(vec!["age".to_string()]);
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+    let ids = add_aged_persons(
+        &storage,
+        &[Some(30), Some(25), Some(40), None, Some(35), Some(-5)],
+        Some(&["age"]),
+    );
+
+    // other labels in the index and entries left behind by updates are skipped
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("dog", Some(props! { "age" => 50 }), Some(&["age"]))
+        .collect_to_val();
+    let update_tr = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&ids[1])
+        .collect_to::<Vec<_>>();
+    G::new_mut_from(Arc::clone(&storage), &mut txn, update_tr)
+        .update(Some(props! { "age" => 45 }))
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let expected = vec![ids[1], ids[2], ids[4], ids[0], ids[5], ids[3]];
+    let ordered = G::new(Arc::clone(&storage), &txn)
+        .n_from_type_ordered("person", "age", HelixOrder::Desc, None)
+        .collect_to::<Vec<_>>();
+    assert_eq!(ordered.iter().map(|n| n.id()).collect::<Vec<_>>(), expected);
+
+    // same order as sorting in memory
+    let sorted = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .order_by("age", HelixOrder::Desc, None)
+        .collect_to::<Vec<_>>();
+    assert_eq!(sorted.iter().map(|n| n.id()).collect::<Vec<_>>(), expected);
+
+    let first = G::new(Arc::clone(&storage), &txn)
+        .n_from_type_ordered("person", "age", HelixOrder::Asc, Some(2))
+        .range(0, 2)
+        .collect_to::<Vec<_>>();
+    assert_eq!(
+        first.iter().map(|n| n.id()).collect::<Vec<_>>(),
+        vec![ids[5], ids[0]]
+    );
+
+    // without an index the nodes are sorted in memory
+    let unindexed = G::new(Arc::clone(&storage), &txn)
+        .n_from_type_ordered("person", "name", HelixOrder::Asc, None)
+        .collect_to::<Vec<_>>();
+    assert_eq!(unindexed.len(), 6);
+}
+
+#[test]
+fn test_n_from_type_where() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = super::config::Config::default();
+    // views read compressed nodes too
+    config.compression.threshold_bytes = Some(64);
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let mut ids = Vec::new();
+    for (name, age) in [("alice", 30), ("bob", 17), ("carol", 45)] {
+        let properties = props! {
+            "name" => name,
+            "age" => age,
+            "tags" => Value::Array(vec![Value::from("a"), Value::F64(1.5), Value::Empty]),
+            "address" => Value::Object(HashMap::from([
+                ("city".to_string(), Value::from("Paris")),
+                ("zip".to_string(), Value::U128(75001)),
+            ])),
+            "bio" => "x".repeat(100),
+            "active" => true,
+        };
+        let node = G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_n("person", Some(properties), None)
+            .collect_to_val();
+        ids.push(node.id());
+    }
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("dog", Some(props! { "age" => 50 }), None)
+        .collect_to_val();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    // every property read from a view matches the decoded node
+    for id in &ids {
+        let bytes = storage.nodes_db.get(&txn, id).unwrap().unwrap();
+        let view = NodeView::new(bytes, *id, &storage.dictionary).unwrap();
+        let node = view.decode().unwrap();
+        assert_eq!(view.label(), "person");
+        for (key, value) in node.properties.as_ref().unwrap() {
+            assert_eq!(view.check_property(key).unwrap(), value);
+        }
+        assert!(view.check_property("missing").is_err());
+    }
+
+    let adults = G::new(Arc::clone(&storage), &txn)
+        .n_from_type_where("person", |val, _| {
+            Ok(val.check_property("age").map_or(false, |v| *v >= 18))
+        })
+        .collect_to::<Vec<_>>();
+    assert_eq!(
+        adults.iter().map(|n| n.id()).collect::<Vec<_>>(),
+        vec![ids[0], ids[2]]
+    );
+    // the kept nodes are decoded in full
+    assert_eq!(
+        *adults[1].check_property("name").unwrap(),
+        Value::from("carol")
+    );
+}
+
+#[test]
+fn test_n_from_type_where_indexed() {
+    for stats in [true, false] {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = super::config::Config::default();
+        config.stats = stats;
+        config.graph_config.secondary_indices =
+            Some(vec!["email".to_string(), "country".to_string()]);
+        let storage =
+            Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+        let mut txn = storage.graph_env.write_txn().unwrap();
+        let mut ids = Vec::new();
+        for (email, country, age) in [("a@x", "NL", 30), ("b@x", "NL", 17), ("c@x", "DE", 45)] {
+            let node = G::new_mut(Arc::clone(&storage), &mut txn)
+                .add_n(
+                    "person",
+                    Some(props! { "email" => email, "country" => country, "age" => age }),
+                    Some(&["email", "country"]),
+                )
+                .collect_to_val();
+            ids.push(node.id());
+        }
+        // indices are shared by all labels
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_n(
+                "company",
+                Some(props! { "email" => "a@x", "country" => "NL" }),
+                Some(&["email", "country"]),
+            )
+            .collect_to_val();
+        // the entry of the old value stays in the index
+        let update_tr = G::new(Arc::clone(&storage), &txn)
+            .n_from_id(&ids[1])
+            .collect_to::<Vec<_>>();
+        G::new_mut_from(Arc::clone(&storage), &mut txn, update_tr)
+            .update(Some(props! { "country" => "DE" }))
+            .collect_to::<Vec<_>>();
+        txn.commit().unwrap();
+
+        let txn = storage.graph_env.read_txn().unwrap();
+        let lookup = |lookups: &[(&str, Value)], min_age: i32| {
+            G::new(Arc::clone(&storage), &txn)
+                .n_from_type_where_indexed("person", lookups, |val, _| {
+                    Ok(lookups.iter().all(|(property, value)| {
+                        val.check_property(property).map_or(false, |v| v == value)
+                    }) && val.check_property("age").map_or(false, |v| *v >= min_age))
+                })
+                .collect_to::<Vec<_>>()
+                .iter()
+                .map(|n| n.id())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lookup(&[("email", Value::from("a@x"))], 0), vec![ids[0]]);
+        assert_eq!(lookup(&[("country", Value::from("NL"))], 0), vec![ids[0]]);
+        assert_eq!(
+            lookup(&[("country", Value::from("DE"))], 0),
+            vec![ids[1], ids[2]]
+        );
+        assert_eq!(lookup(&[("country", Value::from("DE"))], 18), vec![ids[2]]);
+        assert_eq!(
+            lookup(
+                &[
+                    ("country", Value::from("NL")),
+                    ("email", Value::from("c@x"))
+                ],
+                0
+            ),
+            Vec::<u128>::new()
+        );
+        assert_eq!(
+            lookup(&[("email", Value::from("d@x"))], 0),
+            Vec::<u128>::new()
+        );
+
+        // a missing index is reported when it is looked up
+        let missing = G::new(Arc::clone(&storage), &txn)
+            .n_from_type_where_indexed("person", &[("name", Value::from("a"))], |_, _| Ok(true))
+            .collect::<Vec<_>>();
+        assert!(matches!(missing.as_slice(), [Err(GraphError::New(_))]));
+    }
+}
+
+#[test]
+fn test_projection() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let node = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n(
+            "person",
+            Some(props! { "name" => "alice", "age" => 30, "bio" => "x".repeat(100) }),
+            None,
+        )
+        .collect_to_val();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let (scanned, got) = Projection::scope(&["name", "missing"], || {
+        let scanned = G::new(Arc::clone(&storage), &txn)
+            .n_from_type("person")
+            .collect_to::<Vec<_>>();
+        (scanned, storage.get_node(&txn, &node.id()).unwrap())
+    });
+    let TraversalVal::Node(scanned) = &scanned[0] else {
+        panic!("expected a node");
+    };
+    // only the listed properties the node has are decoded
+    for node in [scanned, &got] {
+        let properties = node.properties.as_ref().unwrap();
+        assert_eq!(properties.len(), 1);
+        assert_eq!(properties["name"], Value::from("alice"));
+    }
+
+    // nodes are decoded in full again outside the scope
+    assert!(projection::fields().is_none());
+    let node = storage.get_node(&txn, &node.id()).unwrap();
+    assert_eq!(node.properties.unwrap().len(), 3);
+}
+
+#[test]
+fn test_date_index_order() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = super::config::Config::default();
+    config.graph_config.secondary_indices = Some(vec!["born".to_string()]);
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+
+    // dates before the epoch are negative
+    let dates = [
+        "2001-02-03T04:05:06Z",
+        "1950-06-01T00:00:00Z",
+        "1999-12-31T23:59:59.5Z",
+    ]
+    .map(|date| date.parse::<chrono::DateTime<chrono::Utc>>().unwrap());
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let ids = dates
+        .iter()
+        .map(|born| {
+            G::new_mut(Arc::clone(&storage), &mut txn)
+                .add_n("person", Some(props! { "born" => *born }), Some(&["born"]))
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    // the index keys themselves are in time order
+    let keys = storage.secondary_indices["born"]
+        .iter(&txn)
+        .unwrap()
+        .map(|entry| bincode::deserialize::<Value>(entry.unwrap().0).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        vec![
+            Value::DateTime(dates[1]),
+            Value::DateTime(dates[2]),
+            Value::DateTime(dates[0])
+        ]
+    );
+
+    let ordered = G::new(Arc::clone(&storage), &txn)
+        .n_from_type_ordered("person", "born", HelixOrder::Asc, None)
+        .collect_to::<Vec<_>>();
+    assert_eq!(
+        ordered.iter().map(|n| n.id()).collect::<Vec<_>>(),
+        vec![ids[1], ids[2], ids[0]]
+    );
+
+    let after = "1999-01-01".parse::<chrono::NaiveDate>().unwrap();
+    let after = after.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let traversal = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .filter_ref(|val, _| match val {
+            Ok(TraversalVal::Node(node)) => {
+                Ok(node.check_property("born").map_or(false, |v| *v > after))
+            }
+            _ => Ok(false),
+        })
+        .collect_to::<Vec<_>>();
+    assert_eq!(traversal.len(), 2);
+
+    // dates stored as strings compare by time too
+    assert!(Value::from("1998-06-01") < after);
+    assert_eq!(
+        Value::DateTime(dates[0]).total_cmp(&Value::from("2001-02-03T04:05:06+00:00")),
+        std::cmp::Ordering::Equal
+    );
+}
+
 #[test]
 fn test_count_empty() {
     let (storage, _temp_dir) = setup_test_db();
@@ -717,50 +1266,115 @@ fn test_e_from_id_nonexistent() {
 }
 
 #[test]
-fn test_n_from_id_chain_operations() {
-    let (storage, _temp_dir) = setup_test_db();
+fn test_e_from_index() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = super::config::Config::default();
+    config.graph_config.edge_secondary_indices = Some(vec!["since".to_string()]);
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
     let mut txn = storage.graph_env.write_txn().unwrap();
 
-    // Create test graph: (person1)-[knows]->(person2)-[likes]->(person3)
     let person1 = G::new_mut(Arc::clone(&storage), &mut txn)
-        .add_n("person", Some(props!()), None)
-        .collect_to::<Vec<_>>();
+        .add_n("person", None, None)
+        .collect_to_val();
     let person2 = G::new_mut(Arc::clone(&storage), &mut txn)
-        .add_n("person", Some(props!()), None)
-        .collect_to::<Vec<_>>();
-    let person3 = G::new_mut(Arc::clone(&storage), &mut txn)
-        .add_n("person", Some(props!()), None)
-        .collect_to::<Vec<_>>();
-    let person3 = G::new_mut(Arc::clone(&storage), &mut txn)
-        .add_n("person", Some(props!()), None)
-        .collect_to::<Vec<_>>();
-
-    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to_val();
+    // edges are indexed when they are added
+    let edge = G::new_mut(Arc::clone(&storage), &mut txn)
         .add_e(
             "knows",
-            Some(props!()),
+            Some(props!("since" => 2010)),
             person1.id(),
             person2.id(),
             false,
             EdgeType::Node,
         )
-        .collect_to::<Vec<_>>();
+        .collect_to_val();
     G::new_mut(Arc::clone(&storage), &mut txn)
         .add_e(
-            "likes",
-            Some(props!()),
+            "knows",
+            Some(props!("since" => 2020)),
             person2.id(),
-            person3.id(),
+            person1.id(),
             false,
             EdgeType::Node,
         )
-        .collect_to::<Vec<_>>();
-
+        .collect_to_val();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e(
+            "knows",
+            None,
+            person2.id(),
+            person1.id(),
+            false,
+            EdgeType::Node,
+        )
+        .collect_to_val();
     txn.commit().unwrap();
+
     let txn = storage.graph_env.read_txn().unwrap();
-    let nodes = G::new(Arc::clone(&storage), &txn)
-        .n_from_id(&person1.id())
-        .out("knows", &EdgeType::Node)
+    let edges = G::new(Arc::clone(&storage), &txn)
+        .e_from_index("since", &2010)
+        .collect_to::<Vec<_>>();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0].id(), edge.id());
+
+    let edges = G::new(Arc::clone(&storage), &txn)
+        .e_from_index("since", &1999)
+        .collect_to::<Vec<_>>();
+    assert!(edges.is_empty());
+
+    // the index isn't configured
+    let mut edges = G::new(Arc::clone(&storage), &txn).e_from_index("weight", &1);
+    assert!(matches!(edges.next(), Some(Err(_))));
+}
+
+#[test]
+fn test_n_from_id_chain_operations() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    // Create test graph: (person1)-[knows]->(person2)-[likes]->(person3)
+    let person1 = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!()), None)
+        .collect_to::<Vec<_>>();
+    let person2 = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!()), None)
+        .collect_to::<Vec<_>>();
+    let person3 = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!()), None)
+        .collect_to::<Vec<_>>();
+    let person3 = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!()), None)
+        .collect_to::<Vec<_>>();
+
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e(
+            "knows",
+            Some(props!()),
+            person1.id(),
+            person2.id(),
+            false,
+            EdgeType::Node,
+        )
+        .collect_to::<Vec<_>>();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e(
+            "likes",
+            Some(props!()),
+            person2.id(),
+            person3.id(),
+            false,
+            EdgeType::Node,
+        )
+        .collect_to::<Vec<_>>();
+
+    txn.commit().unwrap();
+    let txn = storage.graph_env.read_txn().unwrap();
+    let nodes = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&person1.id())
+        .out("knows", &EdgeType::Node)
         .out("likes", &EdgeType::Node)
         .collect_to::<Vec<_>>();
 
@@ -1307,6 +1921,139 @@ fn test_drop_edge() {
     assert_eq!(edges.len(), 0);
 }
 
+#[test]
+fn test_drop_keeps_edges_sharing_an_adjacency_key() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let people = (0..4)
+        .map(|_| {
+            G::new_mut(Arc::clone(&storage), &mut txn)
+                .add_n("person", None, None)
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    let (alice, bob, carol, dave) = (people[0], people[1], people[2], people[3]);
+    // every edge shares the "knows" adjacency key of its nodes with another edge
+    let mut knows = |from: u128, to: u128| {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_e("knows", None, from, to, false, EdgeType::Node)
+            .collect_to_val()
+            .id()
+    };
+    let alice_bob = knows(alice, bob);
+    let alice_carol = knows(alice, carol);
+    let carol_bob = knows(carol, bob);
+    knows(dave, alice);
+    let dave_bob = knows(dave, bob);
+    txn.commit().unwrap();
+
+    let edges = |txn: &RoTxn, id: &u128, out: bool| {
+        let traversal = G::new(Arc::clone(&storage), txn).n_from_id(id);
+        let mut ids = match out {
+            true => traversal.out_e("knows").collect_to::<Vec<_>>(),
+            false => traversal.in_e("knows").collect_to::<Vec<_>>(),
+        }
+        .into_iter()
+        .map(|edge| edge.id())
+        .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    };
+    let sorted = |mut ids: Vec<u128>| {
+        ids.sort();
+        ids
+    };
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let traversal = G::new(Arc::clone(&storage), &txn)
+        .e_from_id(&alice_bob)
+        .collect::<Vec<_>>();
+    Drop::<Vec<_>>::drop_traversal(traversal, Arc::clone(&storage), &mut txn).unwrap();
+    txn.commit().unwrap();
+    {
+        let txn = storage.graph_env.read_txn().unwrap();
+        assert_eq!(edges(&txn, &alice, true), vec![alice_carol]);
+        assert_eq!(edges(&txn, &bob, false), sorted(vec![carol_bob, dave_bob]));
+    }
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let traversal = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&alice)
+        .collect::<Vec<_>>();
+    Drop::<Vec<_>>::drop_traversal(traversal, Arc::clone(&storage), &mut txn).unwrap();
+    txn.commit().unwrap();
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(edges(&txn, &dave, true), vec![dave_bob]);
+    assert!(edges(&txn, &carol, false).is_empty());
+    assert_eq!(edges(&txn, &bob, false), sorted(vec![carol_bob, dave_bob]));
+    assert_eq!(edges(&txn, &carol, true), vec![carol_bob]);
+}
+
+#[test]
+fn test_drop_where() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    let nodes = (0..25)
+        .map(|age| {
+            G::new_mut(Arc::clone(&storage), &mut txn)
+                .add_n("person", Some(props!("age" => age)), None)
+                .collect_to_val()
+        })
+        .collect::<Vec<_>>();
+    let pet = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("pet", Some(props!("age" => 1)), None)
+        .collect_to_val();
+    for node in &nodes {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_e("owns", None, node.id(), pet.id(), false, EdgeType::Node)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+
+    // deleted over several batches, other labels are left alone
+    let deleted = Drop::drop_where(
+        DropSource::Nodes("person"),
+        |item| Ok(*item.check_property("age")? < 10),
+        Arc::clone(&storage),
+        4,
+    )
+    .unwrap();
+    assert_eq!(deleted, 10);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let remaining = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert_eq!(remaining.len(), 15);
+    assert!(remaining
+        .iter()
+        .all(|node| *node.check_property("age").unwrap() >= 10));
+    assert!(storage.get_node(&txn, &pet.id()).is_ok());
+    let owners = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&pet.id())
+        .in_e("owns")
+        .collect_to::<Vec<_>>();
+    assert_eq!(owners.len(), 15);
+    drop(txn);
+
+    let deleted = Drop::drop_where(
+        DropSource::Edges("owns"),
+        |_| Ok(true),
+        Arc::clone(&storage),
+        DEFAULT_DROP_BATCH_SIZE,
+    )
+    .unwrap();
+    assert_eq!(deleted, 15);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let owners = G::new(Arc::clone(&storage), &txn)
+        .e_from_type("owns")
+        .collect_to::<Vec<_>>();
+    assert!(owners.is_empty());
+}
+
 #[test]
 fn test_update_node() {
     let (storage, _temp_dir) = setup_test_db();
@@ -1341,6 +2088,157 @@ fn test_update_node() {
     );
 }
 
+fn setup_unique_test_db() -> (Arc<HelixGraphStorage>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().to_str().unwrap();
+    let mut config = super::config::Config::default();
+    config.graph_config.unique_indices = Some(vec!["email".to_string()]);
+    let storage = HelixGraphStorage::new(db_path, config).unwrap();
+    (Arc::new(storage), temp_dir)
+}
+
+#[test]
+fn test_add_n_unique_violation() {
+    let (storage, _temp_dir) = setup_unique_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!("email" => "john@example.com")), Some(&["email"]))
+        .try_collect_to::<Vec<_>>()
+        .unwrap();
+    // duplicates are caught within the same write transaction
+    let result = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!("email" => "john@example.com")), Some(&["email"]))
+        .try_collect_to::<Vec<_>>();
+    assert!(matches!(result, Err(GraphError::UniqueViolation { .. })));
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!("email" => "jane@example.com")), Some(&["email"]))
+        .try_collect_to::<Vec<_>>()
+        .unwrap();
+
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert_eq!(people.len(), 2);
+}
+
+#[test]
+fn test_update_unique_violation() {
+    let (storage, _temp_dir) = setup_unique_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    let john = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!("email" => "john@example.com")), Some(&["email"]))
+        .collect_to_val();
+    let jane = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!("email" => "jane@example.com")), Some(&["email"]))
+        .collect_to_val();
+    txn.commit().unwrap();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let update_tr = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&jane.id())
+        .collect_to::<Vec<_>>();
+    let result = G::new_mut_from(Arc::clone(&storage), &mut txn, update_tr)
+        .update(Some(props! { "email" => "john@example.com" }))
+        .try_collect_to::<Vec<_>>();
+    assert!(matches!(result, Err(GraphError::UniqueViolation { .. })));
+
+    // the old value is free again once the node holding it has moved on
+    let update_tr = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&john.id())
+        .collect_to::<Vec<_>>();
+    G::new_mut_from(Arc::clone(&storage), &mut txn, update_tr)
+        .update(Some(props! { "email" => "johnny@example.com" }))
+        .try_collect_to::<Vec<_>>()
+        .unwrap();
+    let update_tr = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&jane.id())
+        .collect_to::<Vec<_>>();
+    G::new_mut_from(Arc::clone(&storage), &mut txn, update_tr)
+        .update(Some(props! { "email" => "john@example.com" }))
+        .try_collect_to::<Vec<_>>()
+        .unwrap();
+    txn.commit().unwrap();
+}
+
+fn setup_parallel_test_db() -> (Arc<HelixGraphStorage>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().to_str().unwrap();
+    let mut config = super::config::Config::default();
+    config.parallel.enabled = true;
+    config.parallel.threshold = Some(4);
+    config.parallel.threads = Some(2);
+    let storage = HelixGraphStorage::new(db_path, config).unwrap();
+    (Arc::new(storage), temp_dir)
+}
+
+/// Adds a node with `fanout` outgoing "knows" edges, returning it and the other nodes
+fn add_fanout(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &mut crate::helix_storage::heed3::RwTxn,
+    fanout: usize,
+) -> (u128, Vec<u128>) {
+    let hub = G::new_mut(Arc::clone(storage), txn)
+        .add_n("person", None, None)
+        .collect_to_val()
+        .id();
+    let mut others = Vec::with_capacity(fanout);
+    for i in 0..fanout {
+        let other = G::new_mut(Arc::clone(storage), txn)
+            .add_n("person", Some(props!("index" => i as i32)), None)
+            .collect_to_val()
+            .id();
+        G::new_mut(Arc::clone(storage), txn)
+            .add_e("knows", None, hub, other, false, EdgeType::Node)
+            .collect_to_val();
+        others.push(other);
+    }
+    (hub, others)
+}
+
+#[test]
+fn test_out_in_parallel() {
+    let (storage, _temp_dir) = setup_parallel_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let (hub, mut others) = add_fanout(&storage, &mut txn, 50);
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let mut out = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&hub)
+        .out("knows", &EdgeType::Node)
+        .collect_to::<Vec<_>>()
+        .iter()
+        .map(|node| node.id())
+        .collect::<Vec<_>>();
+    out.sort();
+    others.sort();
+    assert_eq!(out, others);
+
+    let in_ = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&others[0])
+        .in_("knows", &EdgeType::Node)
+        .collect_to::<Vec<_>>();
+    assert_eq!(in_.len(), 1);
+    assert_eq!(in_[0].id(), hub);
+}
+
+#[test]
+fn test_out_parallel_sees_uncommitted_writes() {
+    let (storage, _temp_dir) = setup_parallel_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let (hub, others) = add_fanout(&storage, &mut txn, 20);
+
+    // workers can't see the uncommitted nodes, so the write txn has to be used
+    let out = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&hub)
+        .out("knows", &EdgeType::Node)
+        .collect_to::<Vec<_>>();
+    assert_eq!(out.len(), others.len());
+    txn.commit().unwrap();
+}
+
 #[test]
 fn test_shortest_path() {
     let (storage, _temp_dir) = setup_test_db();
@@ -1393,7 +2291,7 @@ fn test_shortest_path() {
     txn.commit().unwrap();
     let txn = storage.graph_env.read_txn().unwrap();
     let path = G::new_from(Arc::clone(&storage), &txn, vec![node1.clone()])
-        .shortest_path(Some("knows"), None, Some(&node4.id()))
+        .shortest_path(Some("knows"), None, Some(&node4.id()), None, None)
         .collect_to::<Vec<_>>();
     assert_eq!(path.len(), 1);
 
@@ -1421,6 +2319,282 @@ fn test_shortest_path() {
         }
     }
 }
+
+fn add_weighted_edge(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &mut crate::helix_storage::heed3::RwTxn,
+    from: u128,
+    to: u128,
+    weight: f64,
+) -> u128 {
+    G::new_mut(Arc::clone(storage), txn)
+        .add_e(
+            "knows",
+            Some(props!("weight" => weight)),
+            from,
+            to,
+            false,
+            EdgeType::Node,
+        )
+        .collect_to_val()
+        .id()
+}
+
+/// Adds `count` persons named by their position
+fn add_persons(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &mut crate::helix_storage::heed3::RwTxn,
+    count: usize,
+) -> Vec<u128> {
+    (0..count)
+        .map(|i| {
+            G::new_mut(Arc::clone(storage), txn)
+                .add_n("person", Some(props!("name" => i as i32)), None)
+                .collect_to_val()
+                .id()
+        })
+        .collect()
+}
+
+fn path_ids(path: Result<(Vec<Node>, Vec<Edge>), GraphError>) -> (Vec<u128>, Vec<u128>) {
+    let (nodes, edges) = path.unwrap();
+    (
+        nodes.iter().map(|node| node.id).collect(),
+        edges.iter().map(|edge| edge.id).collect(),
+    )
+}
+
+#[test]
+fn test_shortest_path_bidirectional() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    // 0 -> 1 -> 2 -> 3 -> 4 -> 5 and a shortcut 0 -> 6 -> 5
+    let nodes = add_persons(&storage, &mut txn, 7);
+    for i in 0..5 {
+        add_weighted_edge(&storage, &mut txn, nodes[i], nodes[i + 1], 1.0);
+    }
+    let shortcut1 = add_weighted_edge(&storage, &mut txn, nodes[0], nodes[6], 1.0);
+    let shortcut2 = add_weighted_edge(&storage, &mut txn, nodes[6], nodes[5], 1.0);
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let (path_nodes, path_edges) =
+        path_ids(storage.shortest_path(&txn, Some("knows"), &nodes[0], &nodes[5], None, None));
+    assert_eq!(path_nodes, vec![nodes[0], nodes[6], nodes[5]]);
+    assert_eq!(path_edges, vec![shortcut1, shortcut2]);
+
+    // edges are only followed in their direction
+    let result = storage.shortest_path(&txn, Some("knows"), &nodes[5], &nodes[0], None, None);
+    assert!(matches!(result, Err(GraphError::ShortestPathNotFound)));
+
+    // no edges of the label
+    let result = storage.shortest_path(&txn, Some("likes"), &nodes[0], &nodes[5], None, None);
+    assert!(matches!(result, Err(GraphError::ShortestPathNotFound)));
+
+    let (path_nodes, path_edges) =
+        path_ids(storage.shortest_path(&txn, None, &nodes[2], &nodes[2], None, None));
+    assert_eq!(path_nodes, vec![nodes[2]]);
+    assert!(path_edges.is_empty());
+}
+
+#[test]
+fn test_shortest_path_max_depth() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    let nodes = add_persons(&storage, &mut txn, 4);
+    for i in 0..3 {
+        add_weighted_edge(&storage, &mut txn, nodes[i], nodes[i + 1], 1.0);
+    }
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let result = storage.shortest_path(&txn, Some("knows"), &nodes[0], &nodes[3], Some(2), None);
+    assert!(matches!(result, Err(GraphError::ShortestPathNotFound)));
+
+    let path = G::new_from(
+        Arc::clone(&storage),
+        &txn,
+        vec![TraversalVal::Node(
+            storage.get_node(&txn, &nodes[0]).unwrap(),
+        )],
+    )
+    .shortest_path(Some("knows"), None, Some(&nodes[3]), Some(3), None)
+    .collect_to::<Vec<_>>();
+    match path.first() {
+        Some(TraversalVal::Path((path_nodes, path_edges))) => {
+            assert_eq!(path_nodes.len(), 4);
+            assert_eq!(path_edges.len(), 3);
+        }
+        _ => panic!("Expected Path value"),
+    }
+}
+
+#[test]
+fn test_shortest_path_weighted() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    // 0 -> 3 is heavier than 0 -> 1 -> 2 -> 3
+    let nodes = add_persons(&storage, &mut txn, 4);
+    let direct = add_weighted_edge(&storage, &mut txn, nodes[0], nodes[3], 10.0);
+    let edge1 = add_weighted_edge(&storage, &mut txn, nodes[0], nodes[1], 1.0);
+    let edge2 = add_weighted_edge(&storage, &mut txn, nodes[1], nodes[2], 2.5);
+    let edge3 = add_weighted_edge(&storage, &mut txn, nodes[2], nodes[3], 1.0);
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let (path_nodes, path_edges) = path_ids(storage.shortest_path(
+        &txn,
+        Some("knows"),
+        &nodes[0],
+        &nodes[3],
+        None,
+        Some("weight"),
+    ));
+    assert_eq!(path_nodes, vec![nodes[0], nodes[1], nodes[2], nodes[3]]);
+    assert_eq!(path_edges, vec![edge1, edge2, edge3]);
+
+    // unweighted, the direct edge is the shortest path
+    let (_, path_edges) =
+        path_ids(storage.shortest_path(&txn, Some("knows"), &nodes[0], &nodes[3], None, None));
+    assert_eq!(path_edges, vec![direct]);
+
+    // the lighter path is longer than allowed
+    let (_, path_edges) = path_ids(storage.shortest_path(
+        &txn,
+        Some("knows"),
+        &nodes[0],
+        &nodes[3],
+        Some(2),
+        Some("weight"),
+    ));
+    assert_eq!(path_edges, vec![direct]);
+    drop(txn);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    add_weighted_edge(&storage, &mut txn, nodes[1], nodes[3], -1.0);
+    let result = storage.shortest_path(
+        &txn,
+        Some("knows"),
+        &nodes[0],
+        &nodes[3],
+        None,
+        Some("weight"),
+    );
+    assert!(matches!(result, Err(GraphError::New(_))));
+}
+
+#[test]
+fn test_shortest_path_weighted_a_star() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    // roads weighted by their length between cities at the given coordinates
+    let positions = [(0.0, 0.0), (1.0, 0.0), (0.0, 3.0), (2.0, 0.0), (-4.0, 0.0)];
+    let cities = positions
+        .iter()
+        .map(|(x, y)| {
+            G::new_mut(Arc::clone(&storage), &mut txn)
+                .add_n(
+                    "city",
+                    Some(props!("position" => vec![Value::F64(*x), Value::F64(*y)])),
+                    None,
+                )
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    let road = |txn: &mut crate::helix_storage::heed3::RwTxn, from: usize, to: usize| {
+        let (x1, y1) = positions[from];
+        let (x2, y2) = positions[to];
+        let length = ((x1 - x2) * (x1 - x2) + (y1 - y2) * (y1 - y2)) as f64;
+        add_weighted_edge(&storage, txn, cities[from], cities[to], length.sqrt())
+    };
+    let road1 = road(&mut txn, 0, 1);
+    let road2 = road(&mut txn, 1, 3);
+    road(&mut txn, 0, 2);
+    road(&mut txn, 2, 3);
+    road(&mut txn, 0, 4);
+    road(&mut txn, 4, 3);
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let (path_nodes, path_edges) = path_ids(storage.shortest_path_weighted(
+        &txn,
+        Some("knows"),
+        &cities[0],
+        &cities[3],
+        "weight",
+        Some("position"),
+    ));
+    assert_eq!(path_nodes, vec![cities[0], cities[1], cities[3]]);
+    assert_eq!(path_edges, vec![road1, road2]);
+
+    // without a heuristic the result is the same
+    let (_, dijkstra_edges) = path_ids(storage.shortest_path_weighted(
+        &txn,
+        Some("knows"),
+        &cities[0],
+        &cities[3],
+        "weight",
+        None,
+    ));
+    assert_eq!(dijkstra_edges, path_edges);
+
+    let path = G::new_from(
+        Arc::clone(&storage),
+        &txn,
+        vec![TraversalVal::Node(
+            storage.get_node(&txn, &cities[3]).unwrap(),
+        )],
+    )
+    .shortest_path_weighted(
+        Some("knows"),
+        Some(&cities[0]),
+        None,
+        "weight",
+        Some("position"),
+    )
+    .collect_to::<Vec<_>>();
+    match path.first() {
+        Some(TraversalVal::Path((_, edges))) => {
+            assert_eq!(
+                edges.iter().map(|edge| edge.id).collect::<Vec<_>>(),
+                vec![road1, road2]
+            );
+        }
+        _ => panic!("Expected Path value"),
+    }
+}
+
+#[test]
+fn test_shortest_mutual_path() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    // mutual: 0 <-> 1 <-> 2 <-> 3, one way: 0 -> 4 -> 3
+    let nodes = add_persons(&storage, &mut txn, 5);
+    for (i, j) in [(0, 1), (1, 2), (2, 3)] {
+        add_weighted_edge(&storage, &mut txn, nodes[i], nodes[j], 1.0);
+        add_weighted_edge(&storage, &mut txn, nodes[j], nodes[i], 1.0);
+    }
+    add_weighted_edge(&storage, &mut txn, nodes[0], nodes[4], 1.0);
+    add_weighted_edge(&storage, &mut txn, nodes[4], nodes[3], 1.0);
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let (path_nodes, _) =
+        path_ids(storage.shortest_path(&txn, Some("knows"), &nodes[0], &nodes[3], None, None));
+    assert_eq!(path_nodes, vec![nodes[0], nodes[4], nodes[3]]);
+
+    let (path_nodes, path_edges) =
+        path_ids(storage.shortest_mutual_path(&txn, Some("knows"), &nodes[0], &nodes[3], None));
+    assert_eq!(path_nodes, vec![nodes[0], nodes[1], nodes[2], nodes[3]]);
+    assert_eq!(path_edges.len(), 3);
+}
+
 // #[test]
 // fn test_shortest_mutual_path() {
 //     let (storage, _temp_dir) = setup_test_db();
@@ -1637,6 +2811,40 @@ fn test_with_id_type() {
     assert_eq!(traversal[0].id(), input.id.inner());
 }
 
+#[test]
+fn test_ids_serialize_as_uuids() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    let node1 = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to_val();
+    let node2 = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to_val();
+    let edge = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e("knows", None, node1.id(), node2.id(), false, EdgeType::Node)
+        .collect_to_val();
+    txn.commit().unwrap();
+
+    let uuid = |id: u128| uuid::Uuid::from_u128(id).to_string();
+    let json = sonic_rs::to_string(&ReturnValue::from(edge.clone())).unwrap();
+    let json: HashMap<String, String> = sonic_rs::from_str(&json).unwrap();
+    assert_eq!(json["id"], uuid(edge.id()));
+    assert_eq!(json["from_node"], uuid(node1.id()));
+    assert_eq!(json["to_node"], uuid(node2.id()));
+
+    // ids round trip through JSON and become UUID strings when stored as values
+    let id = ID::from(node1.id());
+    assert_eq!(id.to_string(), uuid(node1.id()));
+    assert_eq!(sonic_rs::to_string(&id).unwrap(), format!("\"{}\"", id));
+    assert_eq!(
+        sonic_rs::from_str::<ID>(&sonic_rs::to_string(&id).unwrap()).unwrap(),
+        id
+    );
+    assert_eq!(Value::from(id), Value::String(uuid(node1.id())));
+}
+
 #[test]
 fn test_add_e_with_dup_flag() {
     let (storage, _temp_dir) = setup_test_db();
@@ -1757,3 +2965,234 @@ fn test_add_n_parallel() {
 
 // 3 614 375 936
 // 3 411 509 248
+
+#[test]
+fn test_check_property_path() {
+    let (storage, _temp_dir) = setup_test_db();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+
+    let address: Value = sonic_rs::from_str(r#"{"city": "Paris", "zip": 75000}"#).unwrap();
+    let node = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n(
+            "person",
+            Some(props! { "tags" => vec!["a", "b"], "address" => address }),
+            None,
+        )
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let id = node.first().unwrap().id();
+    let tag = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&id)
+        .check_property_path("tags", &[PathSegment::Index(1)])
+        .collect_to::<Vec<_>>();
+    assert_eq!(tag.len(), 1);
+    assert!(matches!(&tag[0], TraversalVal::Value(tag) if *tag == Value::from("b")));
+
+    let city = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&id)
+        .check_property_path("address", &[PathSegment::Field("city")])
+        .collect_to::<Vec<_>>();
+    assert_eq!(city.len(), 1);
+    assert!(matches!(&city[0], TraversalVal::Value(city) if *city == Value::from("Paris")));
+
+    let missing = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&id)
+        .check_property_path("tags", &[PathSegment::Index(2)])
+        .collect_to::<Vec<_>>();
+    assert!(missing.is_empty());
+}
+
+#[test]
+fn test_search_v_pre_filter() {
+    let (storage, _temp_dir) = setup_test_db();
+
+    let vectors = (0..200)
+        .map(|i| {
+            (0..8)
+                .map(|j| ((i * 8 + j) as f64).sin())
+                .collect::<Vec<f64>>()
+        })
+        .collect::<Vec<_>>();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for (i, data) in vectors.iter().enumerate() {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .insert_v::<fn(&HVector, &RoTxn) -> bool>(
+                data,
+                "doc",
+                Some(vec![("group".to_string(), Value::from(i as i64 % 10))]),
+            )
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let query = HVector::from_slice(0, vectors[0].clone());
+    // only a tenth of the vectors pass the filter, yet k of them are still found
+    let results = G::new(Arc::clone(&storage), &txn)
+        .search_v(
+            &vectors[0],
+            10,
+            Some(&[|val: &HVector, _: &RoTxn| {
+                val.check_property("group").map_or(false, |v| *v == 3i64)
+            }]),
+        )
+        .collect_to::<Vec<_>>();
+    assert_eq!(results.len(), 10);
+
+    let mut expected = vectors
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i % 10 == 3)
+        .map(|(_, data)| {
+            HVector::from_slice(0, data.clone())
+                .distance_to(&query)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    expected.sort_by(|a, b| a.total_cmp(b));
+    for (result, distance) in results.iter().zip(expected) {
+        let TraversalVal::Vector(result) = result else {
+            panic!("expected a vector");
+        };
+        assert_eq!(result.check_property("group").unwrap(), &Value::I64(3));
+        assert!((result.get_distance() - distance).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_check_score() {
+    let (storage, _temp_dir) = setup_test_db();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for data in [
+        vec![1.0, 0.0, 0.0],
+        vec![0.0, 1.0, 0.0],
+        vec![0.7, 0.7, 0.0],
+    ] {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .insert_v::<fn(&HVector, &RoTxn) -> bool>(&data, "doc", None)
+            .collect_to_val();
+    }
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", Some(props!()), None)
+        .collect_to_val();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let vectors = G::new(Arc::clone(&storage), &txn)
+        .search_v::<fn(&HVector, &RoTxn) -> bool>(&vec![1.0, 0.1, 0.0], 3, None)
+        .collect_to::<Vec<_>>();
+    let scores = G::new_from(Arc::clone(&storage), &txn, vectors.clone())
+        .check_score()
+        .collect_to::<Vec<_>>();
+    assert_eq!(scores.len(), 3);
+    for (vector, score) in vectors.iter().zip(scores) {
+        let TraversalVal::Vector(vector) = vector else {
+            panic!("expected a vector");
+        };
+        assert!(
+            matches!(score, TraversalVal::Value(Value::F64(score)) if score == vector.get_distance())
+        );
+    }
+
+    // nodes that weren't ranked by a search have no score
+    let scores = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .check_score()
+        .collect_to::<Vec<_>>();
+    assert!(scores.is_empty());
+}
+
+#[test]
+fn test_query_limits() {
+    let (storage, _temp_dir) = setup_test_db();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for _ in 0..100 {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_n("person", Some(props!()), None)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let limits = QueryLimitsConfig {
+        max_visited: Some(10),
+        ..Default::default()
+    };
+    let guard = QueryGuard::start(&limits, CancellationToken::new());
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert!(people.len() <= 10);
+    assert!(matches!(
+        guard.finish(),
+        Err(GraphError::QueryLimitExceeded(_))
+    ));
+
+    let limits = QueryLimitsConfig {
+        max_collected_bytes: Some(1),
+        ..Default::default()
+    };
+    let guard = QueryGuard::start(&limits, CancellationToken::new());
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert!(people.is_empty());
+    assert!(guard.finish().is_err());
+
+    // traversals run once the guard is finished aren't limited
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert_eq!(people.len(), 100);
+}
+
+#[test]
+fn test_query_cancellation() {
+    let (storage, _temp_dir) = setup_test_db();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for _ in 0..1000 {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_n("person", Some(props!()), None)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+
+    // cancelling the parent token cancels the query, which stops within a batch of items
+    let txn = storage.graph_env.read_txn().unwrap();
+    let root = CancellationToken::new();
+    let guard = QueryGuard::start(&QueryLimitsConfig::default(), root.child(None));
+    root.cancel();
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert!(people.len() < CLOCK_INTERVAL as usize);
+    match guard.finish() {
+        Err(GraphError::QueryCancelled(stats)) => {
+            assert_eq!(stats.visited, CLOCK_INTERVAL);
+        }
+        _ => panic!("expected the query to be cancelled"),
+    }
+
+    // so does running past its deadline
+    let guard = QueryGuard::start(
+        &QueryLimitsConfig::default(),
+        CancellationToken::new().child(Some(Instant::now())),
+    );
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert!(people.len() < 1000);
+    assert!(matches!(guard.finish(), Err(GraphError::QueryCancelled(_))));
+
+    let guard = QueryGuard::start(&QueryLimitsConfig::default(), CancellationToken::new());
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert_eq!(people.len(), 1000);
+    assert!(guard.finish().is_ok());
+}
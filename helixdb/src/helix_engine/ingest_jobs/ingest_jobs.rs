@@ -0,0 +1,158 @@
+use crate::helix_storage::heed3::{byteorder::BE, types::*, Database, Env, RoTxn, RwTxn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::helix_engine::types::GraphError;
+
+const DB_INGEST_JOBS: &str = "ingest_jobs"; // job id -> job
+const DB_INGEST_JOB_KEYS: &str = "ingest_job_keys"; // job id | 0 | node key -> node id
+
+/// `(label, id)` of a node in the source of a job
+pub type SourceKey = (String, String);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Completed,
+}
+
+/// Progress of an ingestion job, checkpointed in the transaction of every batch it
+/// loads so an interrupted job can be resumed after its last loaded batch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IngestJob {
+    pub id: String,
+    /// What the job ingests, e.g. the mapping file of the files it loads
+    pub source: String,
+    pub status: JobStatus,
+    /// Stream the job reads rows from (a file, or a mapping of one) => position of the
+    /// last row of it that was loaded
+    pub positions: BTreeMap<String, u64>,
+    pub batches: u64,
+    pub nodes: u64,
+    pub edges: u64,
+    pub vectors: u64,
+    /// Items and rows that couldn't be loaded, only the first
+    /// [`JobStore::MAX_ERRORS`] being kept
+    pub errors: Vec<String>,
+    pub error_count: u64,
+    /// Milliseconds since the unix epoch
+    pub started_at: i64,
+    pub updated_at: i64,
+}
+
+impl IngestJob {
+    pub fn new(id: &str, source: &str) -> Self {
+        let now = chrono::Utc::now().timestamp_millis();
+        Self {
+            id: id.to_string(),
+            source: source.to_string(),
+            status: JobStatus::Running,
+            positions: BTreeMap::new(),
+            batches: 0,
+            nodes: 0,
+            edges: 0,
+            vectors: 0,
+            errors: Vec::new(),
+            error_count: 0,
+            started_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Counts an error, keeping it if the job doesn't have too many already
+    pub fn push_error(&mut self, error: String) {
+        self.error_count += 1;
+        if self.errors.len() < JobStore::MAX_ERRORS {
+            self.errors.push(error);
+        }
+    }
+}
+
+/// The ingestion jobs of the instance, with the uuids of the nodes each running job
+/// loaded by their key in the source, for the edges of a resumed job to join on.
+///
+/// Keys are dropped once their job is completed.
+pub struct JobStore {
+    pub jobs_db: Database<Str, Bytes>,
+    pub keys_db: Database<Bytes, U128<BE>>,
+}
+
+impl JobStore {
+    /// Number of errors kept per job, later ones are only counted
+    pub const MAX_ERRORS: usize = 1000;
+
+    pub fn new(graph_env: &Env, wtxn: &mut RwTxn) -> Result<JobStore, GraphError> {
+        let jobs_db: Database<Str, Bytes> = graph_env
+            .database_options()
+            .types::<Str, Bytes>()
+            .name(DB_INGEST_JOBS)
+            .create(wtxn)?;
+        let keys_db: Database<Bytes, U128<BE>> = graph_env
+            .database_options()
+            .types::<Bytes, U128<BE>>()
+            .name(DB_INGEST_JOB_KEYS)
+            .create(wtxn)?;
+        Ok(JobStore { jobs_db, keys_db })
+    }
+
+    pub fn get(&self, txn: &RoTxn, id: &str) -> Result<Option<IngestJob>, GraphError> {
+        match self.jobs_db.get(txn, id)? {
+            Some(bytes) => Ok(Some(bincode::deserialize(bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Every job, by id
+    pub fn list(&self, txn: &RoTxn) -> Result<Vec<IngestJob>, GraphError> {
+        let mut jobs = Vec::new();
+        for result in self.jobs_db.iter(txn)? {
+            let (_, bytes) = result?;
+            jobs.push(bincode::deserialize(bytes)?);
+        }
+        Ok(jobs)
+    }
+
+    /// Stores a job, dropping its keys if it is completed
+    pub fn put(&self, txn: &mut RwTxn, job: &IngestJob) -> Result<(), GraphError> {
+        self.jobs_db.put(txn, &job.id, &bincode::serialize(job)?)?;
+        if job.status == JobStatus::Completed {
+            let keys = self
+                .keys_db
+                .prefix_iter(txn, &Self::key_prefix(&job.id))?
+                .map(|result| result.map(|(key, _)| key.to_vec()))
+                .collect::<Result<Vec<_>, _>>()?;
+            for key in keys {
+                self.keys_db.delete(txn, &key)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn put_key(
+        &self,
+        txn: &mut RwTxn,
+        job: &str,
+        key: &SourceKey,
+        id: u128,
+    ) -> Result<(), GraphError> {
+        let key = [Self::key_prefix(job), bincode::serialize(key)?].concat();
+        self.keys_db.put(txn, &key, &id)?;
+        Ok(())
+    }
+
+    /// The keys of the nodes a job loaded, with their uuids
+    pub fn keys(&self, txn: &RoTxn, job: &str) -> Result<Vec<(SourceKey, u128)>, GraphError> {
+        let prefix = Self::key_prefix(job);
+        let mut keys = Vec::new();
+        for result in self.keys_db.prefix_iter(txn, &prefix)? {
+            let (key, id) = result?;
+            keys.push((bincode::deserialize(&key[prefix.len()..])?, id));
+        }
+        Ok(keys)
+    }
+
+    fn key_prefix(job: &str) -> Vec<u8> {
+        [job.as_bytes(), &[0]].concat()
+    }
+}
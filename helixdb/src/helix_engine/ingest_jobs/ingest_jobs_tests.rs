@@ -0,0 +1,244 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::config::Config,
+        ingest_jobs::ingest_jobs::{JobStatus, JobStore},
+        storage_core::storage_core::HelixGraphStorage,
+    },
+    helix_gateway::ingest::ingest::{ingest, IngestBatch, IngestEdge, IngestNode, JobCheckpoint},
+    protocol::value::Value,
+};
+
+fn setup_test_db() -> (Arc<HelixGraphStorage>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().to_str().unwrap();
+    let storage = HelixGraphStorage::new(db_path, Config::default()).unwrap();
+    (Arc::new(storage), temp_dir)
+}
+
+fn node(label: &str) -> IngestNode {
+    IngestNode {
+        label: label.to_string(),
+        properties: HashMap::new(),
+        external_id: None,
+    }
+}
+
+fn checkpoint(stream: &str, position: u64) -> JobCheckpoint {
+    let mut checkpoint = JobCheckpoint::new("job", "users.csv");
+    checkpoint.advance(stream, position);
+    checkpoint
+}
+
+#[test]
+fn test_batches_are_checkpointed_with_their_job() {
+    let (storage, _temp_dir) = setup_test_db();
+
+    let mut job = checkpoint("users", 2);
+    job.keys = vec![Some(("User".to_string(), "u1".to_string())), None];
+    job.errors = vec!["users.csv:3: no id".to_string()];
+    let users = ingest(
+        &storage,
+        &IngestBatch {
+            nodes: vec![node("User"), node("User")],
+            job: Some(job),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // the failed edge is recorded with the job, the batch is loaded without it
+    let result = ingest(
+        &storage,
+        &IngestBatch {
+            edges: vec![
+                IngestEdge {
+                    label: "Follows".to_string(),
+                    from: users.nodes[0].clone().unwrap(),
+                    to: users.nodes[1].clone().unwrap(),
+                    properties: HashMap::new(),
+                    unique: false,
+                },
+                IngestEdge {
+                    label: "Follows".to_string(),
+                    from: "u1".to_string(),
+                    to: "u2".to_string(),
+                    properties: HashMap::new(),
+                    unique: false,
+                },
+            ],
+            job: Some(checkpoint("follows", 9)),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(result.errors.len(), 1);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let job = storage.ingest_jobs.get(&txn, "job").unwrap().unwrap();
+    assert_eq!(job.source, "users.csv");
+    assert_eq!(job.status, JobStatus::Running);
+    assert_eq!(
+        job.positions.into_iter().collect::<Vec<_>>(),
+        vec![("follows".to_string(), 9), ("users".to_string(), 2)]
+    );
+    assert_eq!((job.batches, job.nodes, job.edges), (2, 2, 1));
+    assert_eq!(job.error_count, 2);
+    assert_eq!(job.errors[0], "users.csv:3: no id");
+    assert!(job.errors[1].starts_with("Follows: "));
+
+    // only nodes with a key are kept
+    let keys = storage.ingest_jobs.keys(&txn, "job").unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].0, ("User".to_string(), "u1".to_string()));
+    assert_eq!(
+        Some(uuid::Uuid::from_u128(keys[0].1).to_string()),
+        users.nodes[0]
+    );
+    assert!(storage.ingest_jobs.get(&txn, "other").unwrap().is_none());
+}
+
+#[test]
+fn test_completed_jobs_drop_their_keys() {
+    let (storage, _temp_dir) = setup_test_db();
+
+    let mut job = checkpoint("users", 1);
+    job.keys = vec![Some(("User".to_string(), "u1".to_string()))];
+    ingest(
+        &storage,
+        &IngestBatch {
+            nodes: vec![node("User")],
+            job: Some(job),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    // keys of other jobs with a common prefix are kept
+    let mut other = JobCheckpoint::new("jo", "users.csv");
+    other.keys = vec![Some(("User".to_string(), "u1".to_string()))];
+    ingest(
+        &storage,
+        &IngestBatch {
+            nodes: vec![node("User")],
+            job: Some(other),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let mut done = JobCheckpoint::new("job", "users.csv");
+    done.done = true;
+    ingest(
+        &storage,
+        &IngestBatch {
+            job: Some(done.clone()),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let job = storage.ingest_jobs.get(&txn, "job").unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Completed);
+    assert_eq!(job.nodes, 1);
+    assert!(storage.ingest_jobs.keys(&txn, "job").unwrap().is_empty());
+    assert_eq!(storage.ingest_jobs.keys(&txn, "jo").unwrap().len(), 1);
+    assert_eq!(storage.ingest_jobs.list(&txn).unwrap().len(), 2);
+    drop(txn);
+
+    // batches of a completed job aren't loaded
+    assert!(ingest(
+        &storage,
+        &IngestBatch {
+            nodes: vec![node("User")],
+            job: Some(done),
+            ..Default::default()
+        },
+    )
+    .is_err());
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.nodes_db.len(&txn).unwrap(), 2);
+}
+
+#[test]
+fn test_errors_are_capped() {
+    let (storage, _temp_dir) = setup_test_db();
+
+    let mut job = checkpoint("users", 1);
+    job.errors = (0..JobStore::MAX_ERRORS + 5)
+        .map(|i| format!("users.csv:{}: no id", i))
+        .collect();
+    ingest(
+        &storage,
+        &IngestBatch {
+            job: Some(job),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let job = storage.ingest_jobs.get(&txn, "job").unwrap().unwrap();
+    assert_eq!(job.errors.len(), JobStore::MAX_ERRORS);
+    assert_eq!(job.error_count, JobStore::MAX_ERRORS as u64 + 5);
+}
+
+#[test]
+fn test_nodes_are_upserted_on_their_external_id() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = Config::default();
+    config.graph_config.unique_indices = Some(vec!["email".to_string()]);
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+    let user = |name: &str, external_id: &str| IngestNode {
+        label: "User".to_string(),
+        properties: HashMap::from([
+            ("email".to_string(), Value::from("ada@example.com")),
+            ("name".to_string(), Value::from(name)),
+        ]),
+        external_id: Some(external_id.to_string()),
+    };
+
+    // the second node of the batch updates the first
+    let first = ingest(
+        &storage,
+        &IngestBatch {
+            nodes: vec![user("Ada", "email"), user("Ada L.", "email")],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(first.nodes[0], first.nodes[1]);
+    let follows = || IngestEdge {
+        label: "Follows".to_string(),
+        from: first.nodes[0].clone().unwrap(),
+        to: first.nodes[0].clone().unwrap(),
+        properties: HashMap::new(),
+        unique: true,
+    };
+    let again = ingest(
+        &storage,
+        &IngestBatch {
+            nodes: vec![user("Ada Lovelace", "email"), user("Ada", "name")],
+            edges: vec![follows(), follows()],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(again.nodes[0], first.nodes[0]);
+    assert_eq!(again.edges[0], again.edges[1]);
+    // only properties with a unique index can be upserted on
+    assert_eq!(again.errors.len(), 1);
+    assert_eq!(again.errors[0].index, 1);
+    assert_eq!(
+        again.errors[0].error,
+        "Graph error: name has no unique index to upsert on"
+    );
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.nodes_db.len(&txn).unwrap(), 1);
+    assert_eq!(storage.edges_db.len(&txn).unwrap(), 1);
+}
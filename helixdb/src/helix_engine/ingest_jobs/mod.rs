@@ -0,0 +1,4 @@
+pub mod ingest_jobs;
+
+#[cfg(test)]
+pub mod ingest_jobs_tests;
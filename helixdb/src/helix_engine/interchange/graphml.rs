@@ -0,0 +1,598 @@
+use crate::{
+    helix_engine::{
+        interchange::interchange::{uuid_string, value_text, ExportCounts},
+        storage_core::storage_core::HelixGraphStorage,
+        types::GraphError,
+    },
+    helix_storage::heed3::RoTxn,
+    protocol::value::Value,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+};
+
+/// Key of the label of nodes, as named by TinkerPop
+const NODE_LABEL_KEY: &str = "labelV";
+/// Key of the label of edges, as named by TinkerPop
+const EDGE_LABEL_KEY: &str = "labelE";
+
+/// A node of a GraphML document
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphMlNode {
+    pub id: String,
+    pub label: Option<String>,
+    pub properties: HashMap<String, Value>,
+}
+
+/// An edge of a GraphML document, connecting nodes by their GraphML ids
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphMlEdge {
+    pub id: Option<String>,
+    pub label: Option<String>,
+    pub source: String,
+    pub target: String,
+    pub properties: HashMap<String, Value>,
+}
+
+/// The nodes and edges of a GraphML document, in the order they are written
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GraphMlGraph {
+    pub nodes: Vec<GraphMlNode>,
+    pub edges: Vec<GraphMlEdge>,
+}
+
+/// Writes the graph as a GraphML document.
+///
+/// Properties are declared as keys typed by the values found for them, a property
+/// holding values of different types is declared a string. Labels are written under
+/// the `labelV` and `labelE` keys TinkerPop reads them from, arrays and objects as JSON.
+pub fn write_graphml(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    out: &mut impl Write,
+) -> Result<ExportCounts, GraphError> {
+    let mut node_keys = BTreeMap::new();
+    for node in storage.get_all_nodes(txn)? {
+        add_key_types(&mut node_keys, &node?.properties);
+    }
+    let mut edge_keys = BTreeMap::new();
+    for edge in storage.get_all_edges(txn)? {
+        add_key_types(&mut edge_keys, &edge?.properties);
+    }
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    write_key(out, NODE_LABEL_KEY, "node", NODE_LABEL_KEY, "string")?;
+    write_key(out, EDGE_LABEL_KEY, "edge", EDGE_LABEL_KEY, "string")?;
+    for (name, attr_type) in node_keys.iter() {
+        write_key(out, &node_key_id(name), "node", name, attr_type)?;
+    }
+    for (name, attr_type) in edge_keys.iter() {
+        write_key(out, &edge_key_id(name), "edge", name, attr_type)?;
+    }
+    writeln!(out, r#"  <graph id="G" edgedefault="directed">"#)?;
+
+    let mut counts = ExportCounts::default();
+    for node in storage.get_all_nodes(txn)? {
+        let node = node?;
+        writeln!(out, r#"    <node id="{}">"#, uuid_string(node.id))?;
+        write_data(out, NODE_LABEL_KEY, &node.label)?;
+        for (name, value) in sorted(&node.properties) {
+            if !matches!(value, Value::Empty) {
+                write_data(out, &node_key_id(name), &value_text(value))?;
+            }
+        }
+        writeln!(out, "    </node>")?;
+        counts.nodes += 1;
+    }
+    for edge in storage.get_all_edges(txn)? {
+        let edge = edge?;
+        writeln!(
+            out,
+            r#"    <edge id="{}" source="{}" target="{}">"#,
+            uuid_string(edge.id),
+            uuid_string(edge.from_node),
+            uuid_string(edge.to_node)
+        )?;
+        write_data(out, EDGE_LABEL_KEY, &edge.label)?;
+        for (name, value) in sorted(&edge.properties) {
+            if !matches!(value, Value::Empty) {
+                write_data(out, &edge_key_id(name), &value_text(value))?;
+            }
+        }
+        writeln!(out, "    </edge>")?;
+        counts.edges += 1;
+    }
+
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    Ok(counts)
+}
+
+/// Node and edge properties are declared as separate keys, so a name used by both
+/// may have a different type for each
+fn node_key_id(name: &str) -> String {
+    format!("node.{}", name)
+}
+
+fn edge_key_id(name: &str) -> String {
+    format!("edge.{}", name)
+}
+
+fn sorted(properties: &Option<HashMap<String, Value>>) -> Vec<(&String, &Value)> {
+    let mut properties = properties
+        .iter()
+        .flat_map(|properties| properties.iter())
+        .collect::<Vec<_>>();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+    properties
+}
+
+fn write_key(
+    out: &mut impl Write,
+    id: &str,
+    target: &str,
+    name: &str,
+    attr_type: &str,
+) -> Result<(), GraphError> {
+    writeln!(
+        out,
+        r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#,
+        escape(id),
+        target,
+        escape(name),
+        attr_type
+    )?;
+    Ok(())
+}
+
+fn write_data(out: &mut impl Write, key: &str, text: &str) -> Result<(), GraphError> {
+    writeln!(
+        out,
+        r#"      <data key="{}">{}</data>"#,
+        escape(key),
+        escape(text)
+    )?;
+    Ok(())
+}
+
+/// Widens the GraphML type of each property to hold the values of the item
+fn add_key_types(
+    keys: &mut BTreeMap<String, &'static str>,
+    properties: &Option<HashMap<String, Value>>,
+) {
+    for (name, value) in properties.iter().flat_map(|properties| properties.iter()) {
+        let Some(value_type) = attr_type(value) else {
+            continue;
+        };
+        keys.entry(name.clone())
+            .and_modify(|key_type| *key_type = widen(key_type, value_type))
+            .or_insert(value_type);
+    }
+}
+
+fn attr_type(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::Empty => None,
+        Value::Boolean(_) => Some("boolean"),
+        Value::I8(_) | Value::I16(_) | Value::I32(_) | Value::U8(_) | Value::U16(_) => Some("int"),
+        Value::I64(_) | Value::U32(_) | Value::U64(_) => Some("long"),
+        Value::F32(_) => Some("float"),
+        Value::F64(_) => Some("double"),
+        // too large for a long
+        Value::U128(_) => Some("string"),
+        Value::String(_) | Value::DateTime(_) | Value::Array(_) | Value::Object(_) => {
+            Some("string")
+        }
+    }
+}
+
+fn widen(a: &'static str, b: &'static str) -> &'static str {
+    match (a, b) {
+        (a, b) if a == b => a,
+        ("int", "long") | ("long", "int") => "long",
+        ("int" | "long" | "float" | "double", "int" | "long" | "float" | "double") => "double",
+        _ => "string",
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A property key declared by a GraphML document
+struct Key {
+    name: String,
+    attr_type: String,
+    default: Option<String>,
+    /// `node`, `edge` or `all`
+    target: String,
+}
+
+/// An element being read, until it is closed
+enum Open {
+    Node(GraphMlNode),
+    Edge(GraphMlEdge),
+    Other,
+}
+
+/// Reads the nodes and edges of a GraphML document.
+///
+/// Data is typed by the `attr.type` of its key, and the `labelV`, `labelE` or `label`
+/// keys give the labels of the items. Keys with a default set it on the items without
+/// the key. Nested graphs are flattened into their parent, hyperedges and ports are
+/// ignored.
+pub fn read_graphml(input: &str) -> Result<GraphMlGraph, GraphError> {
+    let mut graph = GraphMlGraph::default();
+    let mut keys: HashMap<String, Key> = HashMap::new();
+    let mut open: Vec<Open> = Vec::new();
+    // key of the `data` or `default` element being read and its text
+    let mut text: Option<(String, String)> = None;
+    let mut key: Option<String> = None;
+
+    for token in Tokens::new(input) {
+        match token? {
+            Token::Start {
+                name,
+                attributes,
+                empty,
+            } => {
+                let attribute = |name: &str| {
+                    attributes
+                        .iter()
+                        .find(|(attribute, _)| *attribute == name)
+                        .map(|(_, value)| value.clone())
+                };
+                let required = |element: &str, name: &str| {
+                    attribute(name).ok_or_else(|| {
+                        GraphError::New(format!("GraphML <{}> without a `{}`", element, name))
+                    })
+                };
+                match name {
+                    "key" => {
+                        let id = required("key", "id")?;
+                        keys.insert(
+                            id.clone(),
+                            Key {
+                                name: attribute("attr.name").unwrap_or_else(|| id.clone()),
+                                attr_type: attribute("attr.type")
+                                    .unwrap_or_else(|| "string".to_string()),
+                                default: None,
+                                target: attribute("for").unwrap_or_else(|| "all".to_string()),
+                            },
+                        );
+                        if !empty {
+                            key = Some(id);
+                        }
+                    }
+                    "default" if !empty => {
+                        if let Some(key) = &key {
+                            text = Some((key.clone(), String::new()));
+                        }
+                    }
+                    "node" => open.push(Open::Node(GraphMlNode {
+                        id: required("node", "id")?,
+                        label: None,
+                        properties: HashMap::new(),
+                    })),
+                    "edge" => open.push(Open::Edge(GraphMlEdge {
+                        id: attribute("id"),
+                        label: None,
+                        source: required("edge", "source")?,
+                        target: required("edge", "target")?,
+                        properties: HashMap::new(),
+                    })),
+                    "data" if !empty => text = Some((required("data", "key")?, String::new())),
+                    "data" => {}
+                    _ => open.push(Open::Other),
+                }
+                if empty && matches!(name, "node" | "edge") {
+                    close(&mut graph, &mut open, &keys)?;
+                } else if empty && !matches!(name, "key" | "default" | "data") {
+                    open.pop();
+                }
+            }
+            Token::Text(content) => {
+                if let Some((_, text)) = &mut text {
+                    text.push_str(&content);
+                }
+            }
+            Token::End { name } => match name {
+                "key" => key = None,
+                "default" => {
+                    if let Some((id, default)) = text.take() {
+                        if let Some(key) = keys.get_mut(&id) {
+                            key.default = Some(default);
+                        }
+                    }
+                }
+                "data" => {
+                    if let Some((id, content)) = text.take() {
+                        set_data(open.last_mut(), &keys, &id, &content)?;
+                    }
+                }
+                "node" | "edge" => close(&mut graph, &mut open, &keys)?,
+                _ => {
+                    open.pop();
+                }
+            },
+        }
+    }
+    Ok(graph)
+}
+
+/// Finishes the node or edge being read, giving it the defaults of the keys it lacks
+fn close(
+    graph: &mut GraphMlGraph,
+    open: &mut Vec<Open>,
+    keys: &HashMap<String, Key>,
+) -> Result<(), GraphError> {
+    let (target, properties, label) = match open.last_mut() {
+        Some(Open::Node(node)) => ("node", &mut node.properties, &mut node.label),
+        Some(Open::Edge(edge)) => ("edge", &mut edge.properties, &mut edge.label),
+        _ => return Err(GraphError::from("Unbalanced GraphML elements")),
+    };
+    for key in keys.values() {
+        let Some(default) = &key.default else {
+            continue;
+        };
+        if key.target != target && key.target != "all" {
+            continue;
+        }
+        if is_label(&key.name) {
+            label.get_or_insert_with(|| default.clone());
+        } else if !properties.contains_key(&key.name) {
+            properties.insert(key.name.clone(), typed(&key.attr_type, default)?);
+        }
+    }
+    match open.pop() {
+        Some(Open::Node(node)) => graph.nodes.push(node),
+        Some(Open::Edge(edge)) => graph.edges.push(edge),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn set_data(
+    item: Option<&mut Open>,
+    keys: &HashMap<String, Key>,
+    id: &str,
+    content: &str,
+) -> Result<(), GraphError> {
+    let (properties, label) = match item {
+        Some(Open::Node(node)) => (&mut node.properties, &mut node.label),
+        Some(Open::Edge(edge)) => (&mut edge.properties, &mut edge.label),
+        // data of the graph itself
+        _ => return Ok(()),
+    };
+    let (name, attr_type) = match keys.get(id) {
+        Some(key) => (key.name.as_str(), key.attr_type.as_str()),
+        None => (id, "string"),
+    };
+    if is_label(name) {
+        *label = Some(content.to_string());
+    } else {
+        properties.insert(name.to_string(), typed(attr_type, content)?);
+    }
+    Ok(())
+}
+
+fn is_label(name: &str) -> bool {
+    matches!(name, NODE_LABEL_KEY | EDGE_LABEL_KEY | "label")
+}
+
+/// Reads the text of data as the type of its key
+fn typed(attr_type: &str, text: &str) -> Result<Value, GraphError> {
+    let invalid = || {
+        GraphError::New(format!(
+            "GraphML data `{}` isn't a valid {}",
+            text, attr_type
+        ))
+    };
+    Ok(match attr_type {
+        "int" | "long" => Value::I64(text.trim().parse().map_err(|_| invalid())?),
+        "float" | "double" => Value::F64(text.trim().parse().map_err(|_| invalid())?),
+        "boolean" => match text.trim() {
+            "true" | "1" => Value::Boolean(true),
+            "false" | "0" => Value::Boolean(false),
+            _ => return Err(invalid()),
+        },
+        _ => Value::String(text.to_string()),
+    })
+}
+
+/// A piece of an XML document
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    /// An opening tag, `empty` if it closes itself
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+        empty: bool,
+    },
+    End {
+        name: &'a str,
+    },
+    Text(String),
+}
+
+/// Splits an XML document into tags and text, skipping the declaration, comments,
+/// processing instructions and doctype. Namespace prefixes are dropped from names.
+struct Tokens<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    fn error(&self, message: &str) -> GraphError {
+        let line = self.input[..self.position].matches('\n').count() + 1;
+        GraphError::New(format!("Invalid GraphML at line {}: {}", line, message))
+    }
+
+    /// Skips past `end`, failing if the document ends first
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, GraphError> {
+        let rest = &self.input[self.position..];
+        match rest.find(end) {
+            Some(at) => {
+                self.position += at + end.len();
+                Ok(&rest[..at])
+            }
+            None => Err(self.error(&format!("missing `{}`", end))),
+        }
+    }
+
+    fn tag(&mut self) -> Result<Token<'a>, GraphError> {
+        let (content, empty) = {
+            let tag = self.skip_past(">")?;
+            match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            }
+        };
+        // skip the `<`
+        let content = &content[1..];
+        if let Some(name) = content.strip_prefix('/') {
+            return Ok(Token::End {
+                name: local_name(name.trim()),
+            });
+        }
+
+        let name_end = content
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(content.len());
+        let name = local_name(&content[..name_end]);
+        let mut attributes = Vec::new();
+        let mut rest_attributes = content[name_end..].trim_start();
+        while !rest_attributes.is_empty() {
+            let (attribute, value) = rest_attributes
+                .split_once('=')
+                .ok_or_else(|| self.error(&format!("invalid attribute of <{}>", name)))?;
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|quote| *quote == '"' || *quote == '\'')
+                .ok_or_else(|| self.error("attribute values must be quoted"))?;
+            let end = value[1..]
+                .find(quote)
+                .ok_or_else(|| self.error("unterminated attribute value"))?;
+            attributes.push((local_name(attribute.trim()), unescape(&value[1..end + 1])));
+            rest_attributes = value[end + 2..].trim_start();
+        }
+        Ok(Token::Start {
+            name,
+            attributes,
+            empty,
+        })
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token<'a>, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = &self.input[self.position..];
+            if rest.is_empty() {
+                return None;
+            }
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.position += end;
+                let text = &rest[..end];
+                if text.trim().is_empty() {
+                    continue;
+                }
+                return Some(Ok(Token::Text(unescape(text))));
+            }
+            let skipped = if rest.starts_with("<!--") {
+                Some("-->")
+            } else if rest.starts_with("<?") {
+                Some("?>")
+            } else if rest.starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                return Some(
+                    self.skip_past("]]>")
+                        .map(|text| Token::Text(text.to_string())),
+                );
+            } else if rest.starts_with("<!") {
+                Some(">")
+            } else {
+                None
+            };
+            match skipped {
+                Some(end) => {
+                    if let Err(e) = self.skip_past(end) {
+                        return Some(Err(e));
+                    }
+                }
+                None => return Some(self.tag()),
+            }
+        }
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Replaces the predefined entities and character references of XML text
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        unescaped.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
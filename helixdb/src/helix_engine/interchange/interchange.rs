@@ -0,0 +1,282 @@
+use crate::{
+    helix_engine::{
+        interchange::graphml, storage_core::storage_core::HelixGraphStorage, types::GraphError,
+    },
+    helix_storage::heed3::RoTxn,
+    protocol::{
+        items::{Edge, Node},
+        value::Value,
+    },
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/// File written for the nodes of a CSV export
+pub const CSV_NODES_FILE: &str = "nodes.csv";
+/// File written for the edges of a CSV export
+pub const CSV_EDGES_FILE: &str = "edges.csv";
+
+/// Format a graph is exported to and imported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// A GraphML document, as read by Gephi, yEd, NetworkX and TinkerPop
+    GraphMl,
+    /// A node or edge per line, as read by `helix seed`
+    Jsonl,
+    /// A directory of `nodes.csv` and `edges.csv`, with a column per property
+    Csv,
+}
+
+impl GraphFormat {
+    /// The format of an export file by its extension, a directory being a CSV export
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return Some(GraphFormat::Csv);
+        }
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ext.parse().ok())
+    }
+}
+
+impl FromStr for GraphFormat {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "graphml" | "xml" => Ok(GraphFormat::GraphMl),
+            "jsonl" | "ndjson" => Ok(GraphFormat::Jsonl),
+            "csv" => Ok(GraphFormat::Csv),
+            _ => Err(GraphError::New(format!(
+                "Unknown format `{}`, expected graphml, jsonl or csv",
+                s
+            ))),
+        }
+    }
+}
+
+/// Numbers of items written by an export
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExportCounts {
+    pub nodes: usize,
+    pub edges: usize,
+}
+
+/// Writes every node and edge of the graph to `output` from a single read transaction,
+/// so the export is consistent while the graph is being written.
+///
+/// Items are streamed from storage as they are written, the graph is never held in
+/// memory. For CSV `output` is the directory the two files are written to. Vectors
+/// aren't exported.
+pub fn export(
+    storage: &HelixGraphStorage,
+    format: GraphFormat,
+    output: &Path,
+) -> Result<ExportCounts, GraphError> {
+    let txn = storage.graph_env.read_txn()?;
+    match format {
+        GraphFormat::GraphMl => {
+            let mut out = BufWriter::new(File::create(output)?);
+            let counts = graphml::write_graphml(storage, &txn, &mut out)?;
+            out.flush()?;
+            Ok(counts)
+        }
+        GraphFormat::Jsonl => {
+            let mut out = BufWriter::new(File::create(output)?);
+            let counts = write_jsonl(storage, &txn, &mut out)?;
+            out.flush()?;
+            Ok(counts)
+        }
+        GraphFormat::Csv => {
+            fs::create_dir_all(output)?;
+            let mut nodes = BufWriter::new(File::create(output.join(CSV_NODES_FILE))?);
+            let mut edges = BufWriter::new(File::create(output.join(CSV_EDGES_FILE))?);
+            let counts = write_csv(storage, &txn, &mut nodes, &mut edges)?;
+            nodes.flush()?;
+            edges.flush()?;
+            Ok(counts)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonlItem<'a> {
+    id: String,
+    label: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: &'a Option<HashMap<String, Value>>,
+}
+
+/// Writes a line per node followed by a line per edge, edges referring to the nodes
+/// they connect by their uuids.
+///
+/// ```json
+/// {"id":"<uuid>","label":"User","properties":{"name":"Alice"}}
+/// {"id":"<uuid>","label":"Follows","from":"<uuid>","to":"<uuid>"}
+/// ```
+pub fn write_jsonl(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    out: &mut impl Write,
+) -> Result<ExportCounts, GraphError> {
+    let mut counts = ExportCounts::default();
+    for node in storage.get_all_nodes(txn)? {
+        let node = node?;
+        let item = JsonlItem {
+            id: uuid_string(node.id),
+            label: &node.label,
+            from: None,
+            to: None,
+            properties: &node.properties,
+        };
+        writeln!(out, "{}", sonic_rs::to_string(&item)?)?;
+        counts.nodes += 1;
+    }
+    for edge in storage.get_all_edges(txn)? {
+        let edge = edge?;
+        let item = JsonlItem {
+            id: uuid_string(edge.id),
+            label: &edge.label,
+            from: Some(uuid_string(edge.from_node)),
+            to: Some(uuid_string(edge.to_node)),
+            properties: &edge.properties,
+        };
+        writeln!(out, "{}", sonic_rs::to_string(&item)?)?;
+        counts.edges += 1;
+    }
+    Ok(counts)
+}
+
+/// Writes the nodes and edges as two tables, `id,label` and `id,label,from,to`
+/// followed by a column per property found on any of them, cells of the properties
+/// an item doesn't have left empty.
+///
+/// The properties are gathered in a first pass over the items, so the items are read
+/// twice rather than held in memory.
+pub fn write_csv(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    nodes_out: &mut impl Write,
+    edges_out: &mut impl Write,
+) -> Result<ExportCounts, GraphError> {
+    let mut counts = ExportCounts::default();
+
+    let mut columns = BTreeSet::new();
+    for node in storage.get_all_nodes(txn)? {
+        columns.extend(property_names(&node?.properties));
+    }
+    write_csv_row(
+        nodes_out,
+        ["id", "label"]
+            .into_iter()
+            .chain(columns.iter().map(String::as_str))
+            .map(str::to_string),
+    )?;
+    for node in storage.get_all_nodes(txn)? {
+        let Node {
+            id,
+            label,
+            properties,
+            ..
+        } = node?;
+        write_csv_row(
+            nodes_out,
+            [uuid_string(id), label]
+                .into_iter()
+                .chain(property_cells(&columns, &properties)),
+        )?;
+        counts.nodes += 1;
+    }
+
+    let mut columns = BTreeSet::new();
+    for edge in storage.get_all_edges(txn)? {
+        columns.extend(property_names(&edge?.properties));
+    }
+    write_csv_row(
+        edges_out,
+        ["id", "label", "from", "to"]
+            .into_iter()
+            .chain(columns.iter().map(String::as_str))
+            .map(str::to_string),
+    )?;
+    for edge in storage.get_all_edges(txn)? {
+        let Edge {
+            id,
+            label,
+            from_node,
+            to_node,
+            properties,
+        } = edge?;
+        write_csv_row(
+            edges_out,
+            [
+                uuid_string(id),
+                label,
+                uuid_string(from_node),
+                uuid_string(to_node),
+            ]
+            .into_iter()
+            .chain(property_cells(&columns, &properties)),
+        )?;
+        counts.edges += 1;
+    }
+    Ok(counts)
+}
+
+fn property_names(properties: &Option<HashMap<String, Value>>) -> Vec<String> {
+    properties
+        .iter()
+        .flat_map(|properties| properties.keys().cloned())
+        .collect()
+}
+
+fn property_cells<'a>(
+    columns: &'a BTreeSet<String>,
+    properties: &'a Option<HashMap<String, Value>>,
+) -> impl Iterator<Item = String> + 'a {
+    columns.iter().map(move |column| {
+        properties
+            .as_ref()
+            .and_then(|properties| properties.get(column))
+            .map(value_text)
+            .unwrap_or_default()
+    })
+}
+
+fn write_csv_row(
+    out: &mut impl Write,
+    cells: impl Iterator<Item = String>,
+) -> Result<(), GraphError> {
+    let row = cells
+        .map(|cell| match cell.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", cell.replace('"', "\"\"")),
+            false => cell,
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(out, "{}", row)?;
+    Ok(())
+}
+
+/// A property as text, arrays and objects as JSON
+pub(crate) fn value_text(value: &Value) -> String {
+    match value {
+        Value::Array(_) | Value::Object(_) => sonic_rs::to_string(value).unwrap_or_default(),
+        Value::Empty => String::new(),
+        value => value.to_string(),
+    }
+}
+
+pub(crate) fn uuid_string(id: u128) -> String {
+    uuid::Uuid::from_u128(id).to_string()
+}
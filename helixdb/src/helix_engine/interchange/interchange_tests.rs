@@ -0,0 +1,269 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                },
+                tr_val::Traversable,
+            },
+        },
+        interchange::{
+            graphml::{read_graphml, write_graphml},
+            interchange::{export, write_csv, write_jsonl, GraphFormat, CSV_EDGES_FILE},
+        },
+        storage_core::storage_core::HelixGraphStorage,
+    },
+    props,
+    protocol::value::Value,
+};
+
+fn setup_test_db() -> (Arc<HelixGraphStorage>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().to_str().unwrap();
+    let storage = HelixGraphStorage::new(db_path, Config::default()).unwrap();
+    (Arc::new(storage), temp_dir)
+}
+
+/// Alice follows Bob, returning their uuids and the uuid of the edge
+fn add_graph(storage: &Arc<HelixGraphStorage>) -> (String, String, String) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let alice = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n(
+            "User",
+            Some(props! { "name" => "Alice, \"Al\" <a>", "age" => 30 }),
+            None,
+        )
+        .collect_to_val();
+    let bob = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n("User", Some(props! { "name" => "Bob" }), None)
+        .collect_to_val();
+    let follows = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_e(
+            "Follows",
+            Some(props! { "since" => 2020 }),
+            alice.id(),
+            bob.id(),
+            false,
+            EdgeType::Node,
+        )
+        .collect_to_val();
+    txn.commit().unwrap();
+    (alice.uuid(), bob.uuid(), follows.uuid())
+}
+
+#[test]
+fn test_get_all_nodes_and_edges() {
+    let (storage, _temp_dir) = setup_test_db();
+    add_graph(&storage);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let nodes = storage
+        .get_all_nodes(&txn)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let edges = storage
+        .get_all_edges(&txn)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0].label, "Follows");
+}
+
+#[test]
+fn test_write_jsonl() {
+    let (storage, _temp_dir) = setup_test_db();
+    let (alice, bob, follows) = add_graph(&storage);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let mut out = Vec::new();
+    let counts = write_jsonl(&storage, &txn, &mut out).unwrap();
+    assert_eq!((counts.nodes, counts.edges), (2, 1));
+
+    let lines = String::from_utf8(out).unwrap();
+    let lines = lines
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    let alice_line = lines.iter().find(|line| line["id"] == alice).unwrap();
+    assert_eq!(alice_line["label"], "User");
+    assert_eq!(alice_line["properties"]["age"], 30);
+    assert!(alice_line.get("from").is_none());
+    assert_eq!(lines[2]["id"], follows);
+    assert_eq!(lines[2]["from"], alice);
+    assert_eq!(lines[2]["to"], bob);
+    assert_eq!(lines[2]["properties"]["since"], 2020);
+}
+
+#[test]
+fn test_write_csv() {
+    let (storage, _temp_dir) = setup_test_db();
+    let (alice, bob, follows) = add_graph(&storage);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    write_csv(&storage, &txn, &mut nodes, &mut edges).unwrap();
+
+    let nodes = String::from_utf8(nodes).unwrap();
+    let mut rows = nodes.lines();
+    assert_eq!(rows.next(), Some("id,label,age,name"));
+    let rows = rows.collect::<Vec<_>>();
+    assert!(rows.contains(&format!("{},User,30,\"Alice, \"\"Al\"\" <a>\"", alice).as_str()));
+    // properties a node doesn't have are left empty
+    assert!(rows.contains(&format!("{},User,,Bob", bob).as_str()));
+
+    assert_eq!(
+        String::from_utf8(edges).unwrap(),
+        format!(
+            "id,label,from,to,since\n{},Follows,{},{},2020\n",
+            follows, alice, bob
+        )
+    );
+}
+
+#[test]
+fn test_graphml_round_trip() {
+    let (storage, _temp_dir) = setup_test_db();
+    let (alice, bob, follows) = add_graph(&storage);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let mut out = Vec::new();
+    write_graphml(&storage, &txn, &mut out).unwrap();
+    let document = String::from_utf8(out).unwrap();
+    assert!(document.contains(r#"<key id="node.age" for="node" attr.name="age" attr.type="int"/>"#));
+
+    let graph = read_graphml(&document).unwrap();
+    assert_eq!(graph.nodes.len(), 2);
+    let node = graph.nodes.iter().find(|node| node.id == alice).unwrap();
+    assert_eq!(node.label.as_deref(), Some("User"));
+    assert_eq!(
+        node.properties.get("name"),
+        Some(&Value::String("Alice, \"Al\" <a>".to_string()))
+    );
+    assert_eq!(node.properties.get("age"), Some(&Value::I64(30)));
+
+    assert_eq!(graph.edges.len(), 1);
+    let edge = &graph.edges[0];
+    assert_eq!(edge.id.as_deref(), Some(follows.as_str()));
+    assert_eq!(edge.label.as_deref(), Some("Follows"));
+    assert_eq!(
+        (edge.source.as_str(), edge.target.as_str()),
+        (alice.as_str(), bob.as_str())
+    );
+    assert_eq!(edge.properties.get("since"), Some(&Value::I64(2020)));
+}
+
+#[test]
+fn test_read_graphml_of_other_tools() {
+    let document = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- written by hand -->
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns"
+    xmlns:y="http://www.yworks.com/xml/graphml">
+  <key id="d0" for="node" attr.name="label" attr.type="string"/>
+  <key id="d1" for="node" attr.name="weight" attr.type="double">
+    <default>1.5</default>
+  </key>
+  <key id="d2" for="edge" attr.name="active" attr.type="boolean"/>
+  <key id="d3" for="all" attr.name="note" attr.type="string"/>
+  <graph id="G" edgedefault="directed">
+    <data key="d3">about the graph</data>
+    <node id="n0">
+      <data key="d0">City</data>
+      <data key="d1">2.25</data>
+      <data key="d3"><![CDATA[a <b> & c]]></data>
+    </node>
+    <node id="n1"><data key="d0">City</data></node>
+    <node id='n2'/>
+    <edge source="n0" target="n1"><data key="d2">1</data></edge>
+    <edge id="e1" source="n1" target="n2"/>
+  </graph>
+</graphml>"#;
+    let graph = read_graphml(document).unwrap();
+
+    assert_eq!(graph.nodes.len(), 3);
+    assert_eq!(graph.nodes[0].label.as_deref(), Some("City"));
+    assert_eq!(
+        graph.nodes[0].properties,
+        HashMap::from([
+            ("weight".to_string(), Value::F64(2.25)),
+            ("note".to_string(), Value::String("a <b> & c".to_string())),
+        ])
+    );
+    // defaults are set on the items without the key
+    assert_eq!(
+        graph.nodes[1].properties.get("weight"),
+        Some(&Value::F64(1.5))
+    );
+    assert_eq!(graph.nodes[2].id, "n2");
+    assert_eq!(graph.nodes[2].label, None);
+
+    assert_eq!(graph.edges.len(), 2);
+    assert_eq!(graph.edges[0].id, None);
+    assert_eq!(
+        graph.edges[0].properties.get("active"),
+        Some(&Value::Boolean(true))
+    );
+    assert_eq!(graph.edges[1].id.as_deref(), Some("e1"));
+    assert_eq!(
+        (
+            graph.edges[1].source.as_str(),
+            graph.edges[1].target.as_str()
+        ),
+        ("n1", "n2")
+    );
+}
+
+#[test]
+fn test_read_graphml_errors() {
+    let missing_source = r#"<graphml><graph><edge target="n1"/></graph></graphml>"#;
+    assert!(read_graphml(missing_source).is_err());
+
+    let invalid_int = r#"<graphml>
+  <key id="d0" for="node" attr.name="age" attr.type="int"/>
+  <graph><node id="n0"><data key="d0">old</data></node></graph>
+</graphml>"#;
+    let e = read_graphml(invalid_int).unwrap_err();
+    assert!(e.to_string().contains("isn't a valid int"));
+
+    let unterminated = r#"<graphml><graph><node id="n0"#;
+    assert!(read_graphml(unterminated).is_err());
+}
+
+#[test]
+fn test_export_csv_to_directory() {
+    let (storage, temp_dir) = setup_test_db();
+    add_graph(&storage);
+
+    let output = temp_dir.path().join("export");
+    let counts = export(&storage, GraphFormat::Csv, &output).unwrap();
+    assert_eq!((counts.nodes, counts.edges), (2, 1));
+    let edges = std::fs::read_to_string(output.join(CSV_EDGES_FILE)).unwrap();
+    assert_eq!(edges.lines().count(), 2);
+    assert_eq!(GraphFormat::from_path(&output), Some(GraphFormat::Csv));
+}
+
+#[test]
+fn test_graph_format() {
+    assert_eq!(
+        "graphml".parse::<GraphFormat>().unwrap(),
+        GraphFormat::GraphMl
+    );
+    assert_eq!("JSONL".parse::<GraphFormat>().unwrap(), GraphFormat::Jsonl);
+    assert!("parquet".parse::<GraphFormat>().is_err());
+    assert_eq!(
+        GraphFormat::from_path(std::path::Path::new("graph.graphml")),
+        Some(GraphFormat::GraphMl)
+    );
+}
@@ -0,0 +1,5 @@
+pub mod graphml;
+pub mod interchange;
+
+#[cfg(test)]
+pub mod interchange_tests;
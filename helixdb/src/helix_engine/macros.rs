@@ -175,6 +175,54 @@ pub mod macros {
         }};
     }
 
+    #[macro_export]
+    /// Sets a field to a value computed by an expression, or `NONE` if it couldn't be
+    /// computed for the item
+    macro_rules! expression_remapping {
+        ($remapping_vals:expr, $var_name:expr, $new_name:expr => $value:expr) => {{
+            let new_value = match $value {
+                Value::Empty => ReturnValue::Empty,
+                value => ReturnValue::from(value),
+            };
+            let new_remapping = Remapping::new(false, Some($new_name.to_string()), Some(new_value));
+            $remapping_vals.borrow_mut().insert(
+                $var_name.id(),
+                ResponseRemapping::new(
+                    HashMap::from([($new_name.to_string(), new_remapping)]),
+                    false,
+                ),
+            );
+            Ok::<TraversalVal, GraphError>($var_name)
+        }};
+    }
+
+    #[macro_export]
+    /// Sets a field to the result of the first traversal that yields anything, later
+    /// traversals aren't evaluated. If none do the field is `NONE` rather than empty.
+    macro_rules! coalesce_remapping {
+        ($remapping_vals:expr, $var_name:expr, $new_name:expr => $($traversal:expr),+ $(,)?) => {{
+            let mut new_value = ReturnValue::Empty;
+            $(
+                if matches!(new_value, ReturnValue::Empty) {
+                    let traversal_result: Vec<TraversalVal> = $traversal;
+                    if !traversal_result.is_empty() {
+                        new_value = ReturnValue::from(traversal_result);
+                    }
+                }
+            )+
+            let new_remapping =
+                Remapping::new(false, Some($new_name.to_string()), Some(new_value));
+            $remapping_vals.borrow_mut().insert(
+                $var_name.id(),
+                ResponseRemapping::new(
+                    HashMap::from([($new_name.to_string(), new_remapping)]),
+                    false,
+                ),
+            );
+            Ok::<TraversalVal, GraphError>($var_name)
+        }};
+    }
+
     #[macro_export]
     macro_rules! exclude_field {
         ($remapping_vals:expr, $($field_to_exclude:expr),* $(,)?) => {{
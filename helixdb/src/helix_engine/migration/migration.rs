@@ -0,0 +1,967 @@
+use crate::helix_storage::heed3::{byteorder::BE, types::*, Database, Env, RoTxn, RwTxn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    helix_engine::{
+        cdc::cdc::{ChangeEvent, ChangeOp, ChangeTarget},
+        storage_core::{storage_core::HelixGraphStorage, wal::WalOp},
+        types::GraphError,
+    },
+    protocol::{items::ulid, value::Value},
+};
+
+#[cfg(feature = "compiler")]
+use crate::helixc::parser::helix_parser::{
+    Content, DefaultValue, Field, FieldType, HelixParser, HxFile, Source,
+};
+
+const DB_SCHEMA_VERSIONS: &str = "schema_versions"; // version -> schema version
+
+/// Field set to the current time on nodes inserted without it, if it is in the schema
+pub const CREATED_AT: &str = "created_at";
+/// Field set to the current time whenever a node is written, if it is in the schema
+pub const UPDATED_AT: &str = "updated_at";
+
+/// Value a field is filled in with when it isn't set, generated for every item
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum FieldDefault {
+    Value(Value),
+    /// The current time
+    Now,
+    /// A random UUID
+    Uuid,
+    /// A ULID, which sorts by the time it was generated at
+    Ulid,
+}
+
+impl FieldDefault {
+    pub fn value(&self) -> Value {
+        match self {
+            FieldDefault::Value(value) => value.clone(),
+            FieldDefault::Now => Value::DateTime(chrono::Utc::now()),
+            FieldDefault::Uuid => Value::String(uuid::Uuid::new_v4().to_string()),
+            FieldDefault::Ulid => Value::String(ulid()),
+        }
+    }
+}
+
+impl fmt::Display for FieldDefault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldDefault::Value(value) => write!(f, "{}", value),
+            FieldDefault::Now => write!(f, "NOW"),
+            FieldDefault::Uuid => write!(f, "UUID"),
+            FieldDefault::Ulid => write!(f, "ULID"),
+        }
+    }
+}
+
+/// Type of a field as declared in the schema
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SchemaType {
+    String,
+    F32,
+    F64,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Boolean,
+    Id,
+    Date,
+    Array(Box<SchemaType>),
+    Object(BTreeMap<String, SchemaType>),
+    /// Another type of the schema, referred to by name
+    Named(String),
+}
+
+impl SchemaType {
+    /// Whether a value may be stored in a field of this type.
+    ///
+    /// Integers and floats match any integer and float type respectively, as their
+    /// width depends on how they were parsed rather than on the schema.
+    pub fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (SchemaType::String, Value::String(_)) => true,
+            (SchemaType::F32 | SchemaType::F64, Value::F32(_) | Value::F64(_)) => true,
+            (
+                SchemaType::I8
+                | SchemaType::I16
+                | SchemaType::I32
+                | SchemaType::I64
+                | SchemaType::U8
+                | SchemaType::U16
+                | SchemaType::U32
+                | SchemaType::U64
+                | SchemaType::U128,
+                Value::I8(_)
+                | Value::I16(_)
+                | Value::I32(_)
+                | Value::I64(_)
+                | Value::U8(_)
+                | Value::U16(_)
+                | Value::U32(_)
+                | Value::U64(_)
+                | Value::U128(_),
+            ) => true,
+            (SchemaType::Boolean, Value::Boolean(_)) => true,
+            (SchemaType::Id, Value::String(id)) => uuid::Uuid::parse_str(id).is_ok(),
+            (SchemaType::Id, Value::U128(_)) => true,
+            (SchemaType::Date, Value::DateTime(_)) => true,
+            (SchemaType::Date, Value::String(date)) => {
+                date.parse::<chrono::NaiveDate>().is_ok()
+                    || date.parse::<chrono::DateTime<chrono::Utc>>().is_ok()
+            }
+            (SchemaType::Date, Value::I64(timestamp)) => {
+                chrono::DateTime::from_timestamp(*timestamp, 0).is_some()
+            }
+            (SchemaType::Date, Value::U64(timestamp)) => {
+                chrono::DateTime::from_timestamp(*timestamp as i64, 0).is_some()
+            }
+            (SchemaType::Array(inner), Value::Array(values)) => {
+                values.iter().all(|value| inner.matches(value))
+            }
+            (SchemaType::Object(fields), Value::Object(values)) => {
+                fields.len() == values.len()
+                    && fields.iter().all(|(name, field_type)| {
+                        values
+                            .get(name)
+                            .is_some_and(|value| field_type.matches(value))
+                    })
+            }
+            (SchemaType::Named(_), _) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SchemaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaType::String => write!(f, "String"),
+            SchemaType::F32 => write!(f, "F32"),
+            SchemaType::F64 => write!(f, "F64"),
+            SchemaType::I8 => write!(f, "I8"),
+            SchemaType::I16 => write!(f, "I16"),
+            SchemaType::I32 => write!(f, "I32"),
+            SchemaType::I64 => write!(f, "I64"),
+            SchemaType::U8 => write!(f, "U8"),
+            SchemaType::U16 => write!(f, "U16"),
+            SchemaType::U32 => write!(f, "U32"),
+            SchemaType::U64 => write!(f, "U64"),
+            SchemaType::U128 => write!(f, "U128"),
+            SchemaType::Boolean => write!(f, "Boolean"),
+            SchemaType::Id => write!(f, "ID"),
+            SchemaType::Date => write!(f, "Date"),
+            SchemaType::Array(inner) => write!(f, "[{}]", inner),
+            SchemaType::Object(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, field_type)| format!("{}: {}", name, field_type))
+                    .collect::<Vec<_>>();
+                write!(f, "{{{}}}", fields.join(", "))
+            }
+            SchemaType::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl From<&FieldType> for SchemaType {
+    fn from(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::String => SchemaType::String,
+            FieldType::F32 => SchemaType::F32,
+            FieldType::F64 => SchemaType::F64,
+            FieldType::I8 => SchemaType::I8,
+            FieldType::I16 => SchemaType::I16,
+            FieldType::I32 => SchemaType::I32,
+            FieldType::I64 => SchemaType::I64,
+            FieldType::U8 => SchemaType::U8,
+            FieldType::U16 => SchemaType::U16,
+            FieldType::U32 => SchemaType::U32,
+            FieldType::U64 => SchemaType::U64,
+            FieldType::U128 => SchemaType::U128,
+            FieldType::Boolean => SchemaType::Boolean,
+            FieldType::Uuid => SchemaType::Id,
+            FieldType::Date => SchemaType::Date,
+            FieldType::Array(inner) => SchemaType::Array(Box::new(inner.as_ref().into())),
+            FieldType::Identifier(name) => SchemaType::Named(name.clone()),
+            FieldType::Object(fields) => SchemaType::Object(
+                fields
+                    .iter()
+                    .map(|(name, field_type)| (name.clone(), field_type.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// A field as declared in the schema
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldSpec {
+    pub field_type: SchemaType,
+    pub indexed: bool,
+    pub default: Option<FieldDefault>,
+}
+
+/// Fields of every node and edge label of a schema, which migrations are derived from.
+///
+/// Vector schemas aren't covered, as vectors aren't migrated.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SchemaSnapshot {
+    /// Label -> field name -> field
+    pub nodes: BTreeMap<String, BTreeMap<String, FieldSpec>>,
+    pub edges: BTreeMap<String, BTreeMap<String, FieldSpec>>,
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "String",
+        Value::F32(_) => "F32",
+        Value::F64(_) => "F64",
+        Value::I8(_) => "I8",
+        Value::I16(_) => "I16",
+        Value::I32(_) => "I32",
+        Value::I64(_) => "I64",
+        Value::U8(_) => "U8",
+        Value::U16(_) => "U16",
+        Value::U32(_) => "U32",
+        Value::U64(_) => "U64",
+        Value::U128(_) => "U128",
+        Value::Boolean(_) => "Boolean",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+        Value::Empty => "empty",
+        Value::DateTime(_) => "Date",
+    }
+}
+
+impl SchemaSnapshot {
+    /// Fails with [`GraphError::SchemaViolation`] if the label isn't part of the schema,
+    /// or a property isn't one of its fields or doesn't match the type of the field.
+    ///
+    /// Fields may be left out, and empty values are accepted for any field.
+    pub fn check_node(
+        &self,
+        label: &str,
+        properties: Option<&HashMap<String, Value>>,
+    ) -> Result<(), GraphError> {
+        let fields = self.nodes.get(label).ok_or_else(|| {
+            GraphError::SchemaViolation(format!("N::{} isn't part of the schema", label))
+        })?;
+        for (name, value) in properties.into_iter().flatten() {
+            let field = fields.get(name).ok_or_else(|| {
+                GraphError::SchemaViolation(format!("N::{} has no field {}", label, name))
+            })?;
+            if !matches!(value, Value::Empty) && !field.field_type.matches(value) {
+                return Err(GraphError::SchemaViolation(format!(
+                    "N::{}.{} expects {}, got {} {}",
+                    label,
+                    name,
+                    field.field_type,
+                    value_type(value),
+                    value
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills in the properties of a node the schema generates: defaults and
+    /// [`CREATED_AT`] of fields that aren't set when the node is inserted, and
+    /// [`UPDATED_AT`] whenever it is written.
+    pub fn fill_node(&self, label: &str, properties: &mut HashMap<String, Value>, inserting: bool) {
+        let Some(fields) = self.nodes.get(label) else {
+            return;
+        };
+        for (name, field) in fields {
+            let missing = inserting && !properties.contains_key(name);
+            let value = match name.as_str() {
+                UPDATED_AT if inserting && !missing => continue,
+                UPDATED_AT => timestamp(&field.field_type),
+                CREATED_AT if missing => timestamp(&field.field_type),
+                _ => match &field.default {
+                    Some(default) if missing => default.value(),
+                    _ => continue,
+                },
+            };
+            properties.insert(name.clone(), value);
+        }
+    }
+}
+
+/// The current time as a value of a timestamp field, in seconds for integer fields and
+/// as an RFC3339 string for string fields
+fn timestamp(field_type: &SchemaType) -> Value {
+    let now = chrono::Utc::now();
+    match field_type {
+        SchemaType::I64 | SchemaType::U64 => Value::I64(now.timestamp()),
+        SchemaType::String => Value::String(now.to_rfc3339()),
+        _ => Value::DateTime(now),
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl SchemaSnapshot {
+    pub fn from_source(source: &Source) -> Self {
+        fn default(default: &DefaultValue) -> Option<FieldDefault> {
+            let value = match default {
+                DefaultValue::Empty => return None,
+                DefaultValue::Now => return Some(FieldDefault::Now),
+                DefaultValue::Uuid => return Some(FieldDefault::Uuid),
+                DefaultValue::Ulid => return Some(FieldDefault::Ulid),
+                DefaultValue::String(s) => Value::from(s.as_str()),
+                DefaultValue::F32(f) => Value::F32(*f),
+                DefaultValue::F64(f) => Value::F64(*f),
+                DefaultValue::I8(i) => Value::I8(*i),
+                DefaultValue::I16(i) => Value::I16(*i),
+                DefaultValue::I32(i) => Value::I32(*i),
+                DefaultValue::I64(i) => Value::I64(*i),
+                DefaultValue::U8(i) => Value::U8(*i),
+                DefaultValue::U16(i) => Value::U16(*i),
+                DefaultValue::U32(i) => Value::U32(*i),
+                DefaultValue::U64(i) => Value::U64(*i),
+                DefaultValue::U128(i) => Value::U128(*i),
+                DefaultValue::Boolean(b) => Value::Boolean(*b),
+            };
+            Some(FieldDefault::Value(value))
+        }
+
+        fn fields(fields: &[Field]) -> BTreeMap<String, FieldSpec> {
+            fields
+                .iter()
+                .map(|field| {
+                    (
+                        field.name.clone(),
+                        FieldSpec {
+                            field_type: SchemaType::from(&field.field_type),
+                            indexed: field.is_indexed(),
+                            default: field.defaults.as_ref().and_then(default),
+                        },
+                    )
+                })
+                .collect()
+        }
+
+        Self {
+            nodes: source
+                .node_schemas
+                .iter()
+                .map(|schema| (schema.name.1.clone(), fields(&schema.fields)))
+                .collect(),
+            edges: source
+                .edge_schemas
+                .iter()
+                .map(|schema| {
+                    let properties = schema.properties.as_deref().unwrap_or_default();
+                    (schema.name.1.clone(), fields(properties))
+                })
+                .collect(),
+        }
+    }
+
+    /// Parses the node and edge schemas of a `.hx` file, such as `schema.hx`
+    pub fn parse(schema: &str) -> Result<Self, GraphError> {
+        let content = Content {
+            content: String::new(),
+            source: Source::default(),
+            files: vec![HxFile {
+                name: "schema.hx".to_string(),
+                content: schema.to_string(),
+            }],
+        };
+        let source = HelixParser::parse_source(&content)
+            .map_err(|e| GraphError::New(format!("Invalid schema: {}", e)))?;
+        Ok(Self::from_source(&source))
+    }
+}
+
+/// A field to be renamed instead of dropped and added, parsed from `Label.from=to`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldRename {
+    pub label: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl FromStr for FieldRename {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || GraphError::New(format!("Invalid rename `{}`, expected Label.from=to", s));
+        let (field, to) = s.split_once('=').ok_or_else(invalid)?;
+        let (label, from) = field.split_once('.').ok_or_else(invalid)?;
+        if label.is_empty() || from.is_empty() || to.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            label: label.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+}
+
+/// A single change to the stored items of a label
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MigrationStep {
+    /// Sets the field on every item without it, if it has a default
+    AddField {
+        target: ChangeTarget,
+        label: String,
+        field: String,
+        default: Option<FieldDefault>,
+    },
+    RenameField {
+        target: ChangeTarget,
+        label: String,
+        from: String,
+        to: String,
+    },
+    DropField {
+        target: ChangeTarget,
+        label: String,
+        field: String,
+    },
+    /// Adds every item with the field to its secondary index
+    BackfillIndex {
+        target: ChangeTarget,
+        label: String,
+        field: String,
+    },
+}
+
+impl MigrationStep {
+    fn applies_to(&self, item_target: ChangeTarget, item_label: &str) -> bool {
+        let (target, label) = match self {
+            MigrationStep::AddField { target, label, .. }
+            | MigrationStep::RenameField { target, label, .. }
+            | MigrationStep::DropField { target, label, .. }
+            | MigrationStep::BackfillIndex { target, label, .. } => (target, label),
+        };
+        *target == item_target && label == item_label
+    }
+}
+
+fn prefix(target: &ChangeTarget) -> &'static str {
+    match target {
+        ChangeTarget::Node => "N",
+        ChangeTarget::Edge => "E",
+        ChangeTarget::Vector => "V",
+    }
+}
+
+impl fmt::Display for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationStep::AddField {
+                target,
+                label,
+                field,
+                default,
+            } => {
+                write!(f, "add {}::{}.{}", prefix(target), label, field)?;
+                match default {
+                    Some(default) => write!(f, " with default {}", default),
+                    None => Ok(()),
+                }
+            }
+            MigrationStep::RenameField {
+                target,
+                label,
+                from,
+                to,
+            } => write!(f, "rename {}::{}.{} to {}", prefix(target), label, from, to),
+            MigrationStep::DropField {
+                target,
+                label,
+                field,
+            } => write!(f, "drop {}::{}.{}", prefix(target), label, field),
+            MigrationStep::BackfillIndex {
+                target,
+                label,
+                field,
+            } => write!(
+                f,
+                "backfill index of {}::{}.{}",
+                prefix(target),
+                label,
+                field
+            ),
+        }
+    }
+}
+
+/// Fails if a kept field changed its type, which existing values can't be migrated to
+fn check_type(
+    target: &ChangeTarget,
+    label: &str,
+    field: &str,
+    before: &FieldSpec,
+    after: &FieldSpec,
+) -> Result<(), GraphError> {
+    if before.field_type == after.field_type {
+        return Ok(());
+    }
+    Err(GraphError::New(format!(
+        "{}::{}.{} changed type from {} to {}, which can't be migrated",
+        prefix(target),
+        label,
+        field,
+        before.field_type,
+        after.field_type
+    )))
+}
+
+fn diff_labels(
+    target: ChangeTarget,
+    old: &BTreeMap<String, BTreeMap<String, FieldSpec>>,
+    new: &BTreeMap<String, BTreeMap<String, FieldSpec>>,
+    renames: &[FieldRename],
+) -> Result<Vec<MigrationStep>, GraphError> {
+    let mut steps = Vec::new();
+    let mut backfills = Vec::new();
+    for (label, new_fields) in new {
+        // there are no items of new labels yet, and items of removed ones are left as is
+        let Some(old_fields) = old.get(label) else {
+            continue;
+        };
+        let backfill = |field: &str| MigrationStep::BackfillIndex {
+            target,
+            label: label.clone(),
+            field: field.to_string(),
+        };
+
+        let mut renamed = HashSet::new();
+        for rename in renames.iter().filter(|rename| rename.label == *label) {
+            let (Some(before), Some(after)) =
+                (old_fields.get(&rename.from), new_fields.get(&rename.to))
+            else {
+                continue;
+            };
+            if new_fields.contains_key(&rename.from) || old_fields.contains_key(&rename.to) {
+                continue;
+            }
+            check_type(&target, label, &rename.to, before, after)?;
+            steps.push(MigrationStep::RenameField {
+                target,
+                label: label.clone(),
+                from: rename.from.clone(),
+                to: rename.to.clone(),
+            });
+            if after.indexed {
+                backfills.push(backfill(&rename.to));
+            }
+            renamed.insert(rename.from.as_str());
+            renamed.insert(rename.to.as_str());
+        }
+
+        for field in old_fields.keys() {
+            if !new_fields.contains_key(field) && !renamed.contains(field.as_str()) {
+                steps.push(MigrationStep::DropField {
+                    target,
+                    label: label.clone(),
+                    field: field.clone(),
+                });
+            }
+        }
+
+        for (field, after) in new_fields {
+            if renamed.contains(field.as_str()) {
+                continue;
+            }
+            match old_fields.get(field) {
+                Some(before) => {
+                    check_type(&target, label, field, before, after)?;
+                    if after.indexed && !before.indexed {
+                        backfills.push(backfill(field));
+                    }
+                }
+                None => {
+                    steps.push(MigrationStep::AddField {
+                        target,
+                        label: label.clone(),
+                        field: field.clone(),
+                        default: after.default.clone(),
+                    });
+                    if after.indexed {
+                        backfills.push(backfill(field));
+                    }
+                }
+            }
+        }
+    }
+    steps.extend(backfills);
+    Ok(steps)
+}
+
+/// Returns the steps migrating items stored under the `old` schema to the `new` one.
+///
+/// Fields missing from the new schema are dropped and fields missing from the old one
+/// added, unless they are listed in `renames`. Fields can't change their type.
+pub fn diff(
+    old: &SchemaSnapshot,
+    new: &SchemaSnapshot,
+    renames: &[FieldRename],
+) -> Result<Vec<MigrationStep>, GraphError> {
+    let mut steps = diff_labels(ChangeTarget::Node, &old.nodes, &new.nodes, renames)?;
+    steps.extend(diff_labels(
+        ChangeTarget::Edge,
+        &old.edges,
+        &new.edges,
+        renames,
+    )?);
+
+    for rename in renames {
+        let found = steps.iter().any(|step| {
+            matches!(step, MigrationStep::RenameField { label, from, to, .. }
+                if *label == rename.label && *from == rename.from && *to == rename.to)
+        });
+        if !found {
+            return Err(GraphError::New(format!(
+                "Can't rename {}.{} to {}, the old schema has to contain only the old field and the new schema only the new one",
+                rename.label, rename.from, rename.to
+            )));
+        }
+    }
+    Ok(steps)
+}
+
+/// A schema a database was migrated to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SchemaVersion {
+    pub version: u64,
+    pub schema: SchemaSnapshot,
+    /// Steps that migrated the previous version to this one
+    pub steps: Vec<MigrationStep>,
+    /// Milliseconds since the unix epoch
+    pub applied_at: i64,
+}
+
+/// Outcome of [`HelixGraphStorage::migrate`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    /// Version before the migration, `None` if no schema was recorded yet
+    pub from_version: Option<u64>,
+    /// Version after the migration
+    pub version: u64,
+    pub steps: Vec<MigrationStep>,
+    /// Number of nodes and edges the steps applied to
+    pub affected: usize,
+    /// Set if nothing was committed
+    pub dry_run: bool,
+}
+
+/// History of the schema versions the database was migrated through.
+///
+/// In strict mode, nodes are checked against the schema of the latest version before
+/// they are written.
+pub struct SchemaHistory {
+    pub versions_db: Database<U64<BE>, Bytes>,
+    strict: bool,
+    /// Schema of the latest version
+    schema: RwLock<Option<Arc<SchemaSnapshot>>>,
+}
+
+impl SchemaHistory {
+    pub fn new(
+        graph_env: &Env,
+        wtxn: &mut RwTxn,
+        strict: bool,
+    ) -> Result<SchemaHistory, GraphError> {
+        let versions_db: Database<U64<BE>, Bytes> = graph_env
+            .database_options()
+            .types::<U64<BE>, Bytes>()
+            .name(DB_SCHEMA_VERSIONS)
+            .create(wtxn)?;
+        let schema = match versions_db.last(wtxn)? {
+            Some((_, bytes)) => Some(Arc::new(
+                bincode::deserialize::<SchemaVersion>(bytes)?.schema,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            versions_db,
+            strict,
+            schema: RwLock::new(schema),
+        })
+    }
+
+    #[inline(always)]
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Schema of the latest version, `None` if no schema was recorded yet
+    pub fn schema(&self) -> Option<Arc<SchemaSnapshot>> {
+        self.schema.read().unwrap().clone()
+    }
+
+    /// Fills in the properties of a node the schema generates, see
+    /// [`SchemaSnapshot::fill_node`]
+    pub fn fill_node(&self, label: &str, properties: &mut HashMap<String, Value>, inserting: bool) {
+        if let Some(schema) = self.schema() {
+            schema.fill_node(label, properties, inserting);
+        }
+    }
+
+    /// Checks the properties of a node against the schema in strict mode.
+    ///
+    /// Anything goes until a schema was recorded.
+    pub fn check_node(
+        &self,
+        label: &str,
+        properties: Option<&HashMap<String, Value>>,
+    ) -> Result<(), GraphError> {
+        if !self.strict {
+            return Ok(());
+        }
+        match self.schema() {
+            Some(schema) => schema.check_node(label, properties),
+            None => Ok(()),
+        }
+    }
+
+    /// The latest version, `None` if no schema was recorded yet
+    pub fn current(&self, txn: &RoTxn) -> Result<Option<SchemaVersion>, GraphError> {
+        match self.versions_db.last(txn)? {
+            Some((_, bytes)) => Ok(Some(bincode::deserialize(bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// All versions, oldest first
+    pub fn versions(&self, txn: &RoTxn) -> Result<Vec<SchemaVersion>, GraphError> {
+        self.versions_db
+            .iter(txn)?
+            .map(|result| {
+                let (_, bytes) = result?;
+                Ok(bincode::deserialize(bytes)?)
+            })
+            .collect()
+    }
+
+    fn record(&self, txn: &mut RwTxn, version: &SchemaVersion) -> Result<(), GraphError> {
+        self.versions_db
+            .put(txn, &version.version, &bincode::serialize(version)?)?;
+        Ok(())
+    }
+}
+
+/// Applies the steps for an item to its properties. Returns whether they changed and
+/// the fields to add to their indices.
+fn migrate_properties<'s>(
+    properties: &mut Option<HashMap<String, Value>>,
+    target: ChangeTarget,
+    label: &str,
+    steps: &'s [MigrationStep],
+) -> (bool, Vec<&'s str>) {
+    let mut changed = false;
+    let mut backfill = Vec::new();
+    let props = properties.get_or_insert_with(HashMap::new);
+    for step in steps.iter().filter(|step| step.applies_to(target, label)) {
+        match step {
+            MigrationStep::AddField {
+                field,
+                default: Some(default),
+                ..
+            } => {
+                if !props.contains_key(field) {
+                    props.insert(field.clone(), default.value());
+                    changed = true;
+                }
+            }
+            MigrationStep::AddField { default: None, .. } => {}
+            MigrationStep::RenameField { from, to, .. } => {
+                if let Some(value) = props.remove(from) {
+                    props.insert(to.clone(), value);
+                    changed = true;
+                }
+            }
+            MigrationStep::DropField { field, .. } => {
+                changed |= props.remove(field).is_some();
+            }
+            MigrationStep::BackfillIndex { field, .. } => {
+                if props.contains_key(field) {
+                    backfill.push(field.as_str());
+                }
+            }
+        }
+    }
+    if props.is_empty() {
+        *properties = None;
+    }
+    (changed, backfill)
+}
+
+impl HelixGraphStorage {
+    /// Migrates the stored nodes and edges from the schema of the current version to
+    /// `schema` and records it as the next version.
+    ///
+    /// The first schema is recorded as is, since there is nothing to diff it against. All
+    /// steps run in a single write transaction, which is aborted instead of committed for
+    /// a dry run, so the report of a dry run counts the items that would be affected.
+    pub fn migrate(
+        &self,
+        schema: &SchemaSnapshot,
+        renames: &[FieldRename],
+        dry_run: bool,
+    ) -> Result<MigrationReport, GraphError> {
+        let mut txn = self.graph_env.write_txn()?;
+        let current = self.schema_history.current(&txn)?;
+        let old = match &current {
+            Some(current) => current.schema.clone(),
+            None => SchemaSnapshot::default(),
+        };
+        let steps = diff(&old, schema, renames)?;
+
+        for step in steps.iter() {
+            if let MigrationStep::BackfillIndex {
+                target,
+                label,
+                field,
+            } = step
+            {
+                let configured = match target {
+                    ChangeTarget::Node => self.secondary_indices.contains_key(field),
+                    _ => self.edge_secondary_indices.contains_key(field),
+                };
+                if !configured {
+                    return Err(GraphError::New(format!(
+                        "{}::{}.{} is indexed in the schema, but {} isn't listed in the secondary indices of the config",
+                        prefix(target),
+                        label,
+                        field,
+                        field
+                    )));
+                }
+            }
+        }
+
+        let affected = self.apply_migration(&mut txn, &steps)?;
+        let from_version = current.as_ref().map(|current| current.version);
+        let version = match &current {
+            Some(current) if current.schema == *schema => current.version,
+            Some(current) => current.version + 1,
+            None => 1,
+        };
+        if from_version != Some(version) {
+            self.schema_history.record(
+                &mut txn,
+                &SchemaVersion {
+                    version,
+                    schema: schema.clone(),
+                    steps: steps.clone(),
+                    applied_at: chrono::Utc::now().timestamp_millis(),
+                },
+            )?;
+        }
+
+        if !dry_run {
+            txn.commit()?;
+            *self.schema_history.schema.write().unwrap() = Some(Arc::new(schema.clone()));
+        }
+        Ok(MigrationReport {
+            from_version,
+            version,
+            steps,
+            affected,
+            dry_run,
+        })
+    }
+
+    /// Rewrites every node and edge the steps apply to, recording the updates like any
+    /// other. Returns the number of items the steps applied to.
+    fn apply_migration(
+        &self,
+        txn: &mut RwTxn,
+        steps: &[MigrationStep],
+    ) -> Result<usize, GraphError> {
+        if steps.is_empty() {
+            return Ok(0);
+        }
+        let mut affected = 0;
+
+        let nodes = self
+            .nodes_db
+            .iter(txn)?
+            .map(|result| {
+                let (id, bytes) = result?;
+                self.decode_node(bytes, id)
+            })
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        for mut node in nodes {
+            let (changed, backfill) =
+                migrate_properties(&mut node.properties, ChangeTarget::Node, &node.label, steps);
+            if let Some(properties) = &node.properties {
+                for field in backfill.iter() {
+                    let (Some(value), Some(db)) =
+                        (properties.get(*field), self.secondary_indices.get(*field))
+                    else {
+                        continue;
+                    };
+                    self.check_unique(txn, field, value, &node.id)?;
+                    self.put_index_entry(txn, field, db, &bincode::serialize(value)?, &node.id)?;
+                }
+            }
+            if changed {
+                let bytes = self.encode_node(txn, &node)?;
+                self.nodes_db.put(txn, Self::node_key(&node.id), &bytes)?;
+                self.cdc.record(
+                    txn,
+                    ChangeEvent::new(ChangeOp::Update, ChangeTarget::Node, node.id, &node.label),
+                )?;
+                self.wal.log(txn, || WalOp::put_node(&self.encryption, &node))?;
+            }
+            if changed || !backfill.is_empty() {
+                affected += 1;
+            }
+        }
+
+        let edges = self
+            .edges_db
+            .iter(txn)?
+            .map(|result| {
+                let (id, bytes) = result?;
+                self.decode_edge(bytes, id)
+            })
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        for mut edge in edges {
+            let (changed, backfill) =
+                migrate_properties(&mut edge.properties, ChangeTarget::Edge, &edge.label, steps);
+            if let Some(properties) = &edge.properties {
+                for field in backfill.iter() {
+                    if let Some(value) = properties.get(*field) {
+                        self.put_edge_index_entry(txn, field, value, &edge.id)?;
+                    }
+                }
+            }
+            if changed {
+                let bytes = self.encode_edge(txn, &edge)?;
+                self.edges_db.put(txn, Self::edge_key(&edge.id), &bytes)?;
+                self.cdc.record(
+                    txn,
+                    ChangeEvent::new(ChangeOp::Update, ChangeTarget::Edge, edge.id, &edge.label),
+                )?;
+                self.wal.log(txn, || WalOp::put_edge(&self.encryption, &edge))?;
+            }
+            if changed || !backfill.is_empty() {
+                affected += 1;
+            }
+        }
+
+        Ok(affected)
+    }
+}
@@ -0,0 +1,448 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        cdc::cdc::ChangeTarget,
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                    n_from_id::NFromIdAdapter,
+                    n_from_index::NFromIndexAdapter,
+                },
+                tr_val::Traversable,
+                util::update::UpdateAdapter,
+            },
+        },
+        migration::migration::{
+            diff, FieldDefault, FieldRename, MigrationStep, SchemaSnapshot, SchemaType,
+        },
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+        types::GraphError,
+    },
+    props,
+    protocol::value::Value,
+};
+
+fn setup_test_db(indices: Vec<&str>) -> (Arc<HelixGraphStorage>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().to_str().unwrap();
+    let mut config = Config::default();
+    config.graph_config.secondary_indices =
+        Some(indices.into_iter().map(|index| index.to_string()).collect());
+    let storage = HelixGraphStorage::new(db_path, config).unwrap();
+    (Arc::new(storage), temp_dir)
+}
+
+fn schema(hx: &str) -> SchemaSnapshot {
+    SchemaSnapshot::parse(hx).unwrap()
+}
+
+#[test]
+fn test_parse_schema() {
+    let snapshot = schema(
+        r#"
+        N::User {
+            INDEX email: String,
+            age: I32 DEFAULT 18,
+            role: String DEFAULT "member",
+        }
+
+        E::Follows {
+            From: User,
+            To: User,
+            Properties: {
+                since: I64,
+            }
+        }
+        "#,
+    );
+
+    let user = &snapshot.nodes["User"];
+    assert!(user["email"].indexed);
+    assert_eq!(user["email"].field_type, SchemaType::String);
+    assert_eq!(user["email"].default, None);
+    assert_eq!(
+        user["role"].default,
+        Some(FieldDefault::Value(Value::from("member")))
+    );
+    assert!(user["age"].default.is_some());
+    assert!(snapshot.edges["Follows"].contains_key("since"));
+}
+
+#[test]
+fn test_diff_detects_field_changes() {
+    let old = schema("N::User { name: String, email: String, nickname: String }");
+    let new = schema(
+        r#"N::User { full_name: String, INDEX email: String, role: String DEFAULT "member" }"#,
+    );
+    let renames = vec!["User.name=full_name".parse::<FieldRename>().unwrap()];
+
+    let steps = diff(&old, &new, &renames).unwrap();
+    assert_eq!(
+        steps,
+        vec![
+            MigrationStep::RenameField {
+                target: ChangeTarget::Node,
+                label: "User".to_string(),
+                from: "name".to_string(),
+                to: "full_name".to_string(),
+            },
+            MigrationStep::DropField {
+                target: ChangeTarget::Node,
+                label: "User".to_string(),
+                field: "nickname".to_string(),
+            },
+            MigrationStep::AddField {
+                target: ChangeTarget::Node,
+                label: "User".to_string(),
+                field: "role".to_string(),
+                default: Some(FieldDefault::Value(Value::from("member"))),
+            },
+            MigrationStep::BackfillIndex {
+                target: ChangeTarget::Node,
+                label: "User".to_string(),
+                field: "email".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_diff_rejects_type_change() {
+    let old = schema("N::User { age: I32 }");
+    let new = schema("N::User { age: String }");
+    assert!(diff(&old, &new, &[]).is_err());
+}
+
+#[test]
+fn test_diff_rejects_unmatched_rename() {
+    let old = schema("N::User { name: String }");
+    let new = schema("N::User { name: String }");
+    let renames = vec!["User.name=full_name".parse::<FieldRename>().unwrap()];
+    assert!(diff(&old, &new, &renames).is_err());
+    assert!("User.name".parse::<FieldRename>().is_err());
+}
+
+#[test]
+fn test_first_schema_is_recorded_as_is() {
+    let (storage, _temp_dir) = setup_test_db(vec![]);
+    let snapshot = schema("N::User { name: String }");
+
+    let report = storage.migrate(&snapshot, &[], false).unwrap();
+    assert_eq!(report.from_version, None);
+    assert_eq!(report.version, 1);
+    assert!(report.steps.is_empty());
+
+    // migrating to the same schema again doesn't add a version
+    let report = storage.migrate(&snapshot, &[], false).unwrap();
+    assert_eq!(report.from_version, Some(1));
+    assert_eq!(report.version, 1);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let versions = storage.schema_history.versions(&txn).unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].schema, snapshot);
+}
+
+#[test]
+fn test_migrate_rewrites_items() {
+    let (storage, _temp_dir) = setup_test_db(vec![]);
+    storage
+        .migrate(
+            &schema(
+                r#"
+                N::User { name: String, nickname: String }
+                E::Follows { From: User, To: User, Properties: { since: I64 } }
+                "#,
+            ),
+            &[],
+            false,
+        )
+        .unwrap();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let user = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n(
+            "User",
+            Some(props! { "name" => "John", "nickname" => "J" }),
+            None,
+        )
+        .collect_to::<Vec<_>>();
+    let other = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("Post", Some(props! { "nickname" => "P" }), None)
+        .collect_to::<Vec<_>>();
+    let edge = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e(
+            "Follows",
+            Some(props! { "since" => 2020 }),
+            user.first().unwrap().id(),
+            user.first().unwrap().id(),
+            false,
+            EdgeType::Node,
+        )
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let report = storage
+        .migrate(
+            &schema(
+                r#"
+                N::User { full_name: String, role: String DEFAULT "member" }
+                E::Follows { From: User, To: User, Properties: { year: I64 } }
+                "#,
+            ),
+            &[
+                "User.name=full_name".parse().unwrap(),
+                "Follows.since=year".parse().unwrap(),
+            ],
+            false,
+        )
+        .unwrap();
+    assert_eq!(report.from_version, Some(1));
+    assert_eq!(report.version, 2);
+    assert_eq!(report.affected, 2);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let user = storage.get_node(&txn, &user.first().unwrap().id()).unwrap();
+    let properties = user.properties.unwrap();
+    assert_eq!(properties.get("full_name"), Some(&Value::from("John")));
+    assert_eq!(properties.get("role"), Some(&Value::from("member")));
+    assert!(!properties.contains_key("name"));
+    assert!(!properties.contains_key("nickname"));
+
+    // items of other labels are left as is
+    let other = storage
+        .get_node(&txn, &other.first().unwrap().id())
+        .unwrap();
+    assert!(other.properties.unwrap().contains_key("nickname"));
+
+    let edge = storage.get_edge(&txn, &edge.first().unwrap().id()).unwrap();
+    let properties = edge.properties.unwrap();
+    assert!(properties.contains_key("year"));
+    assert!(!properties.contains_key("since"));
+}
+
+#[test]
+fn test_dry_run_commits_nothing() {
+    let (storage, _temp_dir) = setup_test_db(vec![]);
+    storage
+        .migrate(&schema("N::User { name: String }"), &[], false)
+        .unwrap();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let user = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("User", Some(props! { "name" => "John" }), None)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let report = storage
+        .migrate(
+            &schema("N::User { name: String, age: I32 DEFAULT 18 }"),
+            &[],
+            true,
+        )
+        .unwrap();
+    assert!(report.dry_run);
+    assert_eq!(report.version, 2);
+    assert_eq!(report.affected, 1);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let user = storage.get_node(&txn, &user.first().unwrap().id()).unwrap();
+    assert!(!user.properties.unwrap().contains_key("age"));
+    assert_eq!(
+        storage
+            .schema_history
+            .current(&txn)
+            .unwrap()
+            .unwrap()
+            .version,
+        1
+    );
+}
+
+#[test]
+fn test_backfill_index() {
+    let (storage, _temp_dir) = setup_test_db(vec!["email"]);
+    storage
+        .migrate(&schema("N::User { email: String }"), &[], false)
+        .unwrap();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let user = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("User", Some(props! { "email" => "john@example.com" }), None)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    // written before the index was declared in the schema
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.secondary_indices["email"].clear(&mut txn).unwrap();
+    txn.commit().unwrap();
+
+    let report = storage
+        .migrate(&schema("N::User { INDEX email: String }"), &[], false)
+        .unwrap();
+    assert_eq!(report.affected, 1);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let found = G::new(Arc::clone(&storage), &txn)
+        .n_from_index("email", &"john@example.com")
+        .collect_to::<Vec<_>>();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id(), user.first().unwrap().id());
+}
+
+#[test]
+fn test_backfill_requires_configured_index() {
+    let (storage, _temp_dir) = setup_test_db(vec![]);
+    storage
+        .migrate(&schema("N::User { email: String }"), &[], false)
+        .unwrap();
+
+    assert!(storage
+        .migrate(&schema("N::User { INDEX email: String }"), &[], false)
+        .is_err());
+}
+
+#[test]
+fn test_schema_type_matches() {
+    assert!(SchemaType::I32.matches(&Value::I64(1)));
+    assert!(!SchemaType::I32.matches(&Value::F64(1.0)));
+    assert!(SchemaType::Id.matches(&Value::from(uuid::Uuid::new_v4().to_string())));
+    assert!(!SchemaType::Id.matches(&Value::from("john")));
+    assert!(SchemaType::Date.matches(&Value::from("2024-01-01T00:00:00Z")));
+    assert!(SchemaType::Array(Box::new(SchemaType::String))
+        .matches(&Value::Array(vec![Value::from("a"), Value::from("b")])));
+    assert!(!SchemaType::Array(Box::new(SchemaType::String))
+        .matches(&Value::Array(vec![Value::from("a"), Value::I32(1)])));
+}
+
+#[test]
+fn test_strict_mode_rejects_mismatched_nodes() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = Config::default();
+    config.strict_schema = true;
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+
+    // anything goes until a schema is deployed
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("Post", Some(props! { "title" => "Hello" }), None)
+        .try_collect_to::<Vec<_>>()
+        .unwrap();
+    txn.commit().unwrap();
+
+    storage
+        .migrate(&schema("N::User { name: String, age: I32 }"), &[], false)
+        .unwrap();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let user = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("User", Some(props! { "name" => "John", "age" => 30 }), None)
+        .collect_to_val();
+
+    let result = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n(
+            "User",
+            Some(props! { "name" => "Jane", "email" => "jane@example.com" }),
+            None,
+        )
+        .try_collect_to::<Vec<_>>();
+    assert!(matches!(result, Err(GraphError::SchemaViolation(_))));
+
+    let result = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n(
+            "User",
+            Some(props! { "name" => "Jane", "age" => "thirty" }),
+            None,
+        )
+        .try_collect_to::<Vec<_>>();
+    assert!(matches!(result, Err(GraphError::SchemaViolation(_))));
+
+    let result = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("Post", None, None)
+        .try_collect_to::<Vec<_>>();
+    assert!(matches!(result, Err(GraphError::SchemaViolation(_))));
+
+    let update_tr = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&user.id())
+        .collect_to::<Vec<_>>();
+    let result = G::new_mut_from(Arc::clone(&storage), &mut txn, update_tr)
+        .update(Some(props! { "age" => true }))
+        .try_collect_to::<Vec<_>>();
+    assert!(matches!(result, Err(GraphError::SchemaViolation(_))));
+}
+
+#[test]
+fn test_fills_generated_fields() {
+    let (storage, _temp_dir) = setup_test_db(vec![]);
+    storage
+        .migrate(
+            &schema(
+                r#"
+                N::User {
+                    key: ID DEFAULT UUID,
+                    slug: String DEFAULT ULID,
+                    role: String DEFAULT "member",
+                    created_at: Date,
+                    updated_at: Date,
+                }
+                "#,
+            ),
+            &[],
+            false,
+        )
+        .unwrap();
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let user = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("User", Some(props! { "role" => "admin" }), None)
+        .collect_to_val();
+    let other = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("User", None, None)
+        .collect_to_val();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let user = storage.get_node(&txn, &user.id()).unwrap();
+    let other = storage.get_node(&txn, &other.id()).unwrap();
+    drop(txn);
+    let properties = user.properties.unwrap();
+    let other_properties = other.properties.unwrap();
+    assert_eq!(properties["role"], Value::from("admin"));
+    assert_eq!(other_properties["role"], Value::from("member"));
+    assert!(SchemaType::Id.matches(&properties["key"]));
+    assert_ne!(properties["key"], other_properties["key"]);
+    match &properties["slug"] {
+        Value::String(slug) => assert_eq!(slug.len(), 26),
+        value => panic!("unexpected slug {:?}", value),
+    }
+    assert!(SchemaType::Date.matches(&properties["created_at"]));
+    assert!(SchemaType::Date.matches(&properties["updated_at"]));
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let update_tr = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&user.id)
+        .collect_to::<Vec<_>>();
+    G::new_mut_from(Arc::clone(&storage), &mut txn, update_tr)
+        .update(Some(props! { "role" => "member" }))
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let updated = storage
+        .get_node(&txn, &user.id)
+        .unwrap()
+        .properties
+        .unwrap();
+    assert_eq!(updated["key"], properties["key"]);
+    assert_eq!(updated["created_at"], properties["created_at"]);
+    assert_ne!(updated["updated_at"], properties["updated_at"]);
+}
@@ -0,0 +1,4 @@
+pub mod migration;
+
+#[cfg(test)]
+pub mod migration_tests;
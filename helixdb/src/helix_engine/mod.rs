@@ -1,6 +1,12 @@
+pub mod analytics;
 pub mod bm25;
+pub mod cdc;
 pub mod graph_core;
+pub mod ingest_jobs;
+pub mod interchange;
 pub mod macros;
+pub mod migration;
+pub mod stats;
 pub mod storage_core;
 pub mod types;
 pub mod vector_core;
@@ -0,0 +1,4 @@
+pub mod stats;
+
+#[cfg(test)]
+pub mod stats_tests;
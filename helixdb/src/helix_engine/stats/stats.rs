@@ -0,0 +1,386 @@
+use crate::helix_storage::heed3::{byteorder::BE, types::*, Database, Env, RoTxn, RwTxn};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{
+    helix_engine::types::GraphError,
+    protocol::{items::Edge, label_hash::hash_label},
+};
+
+const DB_STATS: &str = "stats"; // stat key -> count
+const DB_STATS_DEGREES: &str = "stats_degrees"; // node id | label hash | direction -> degree
+
+/// Direction of the edges a degree is counted for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Out,
+    In,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Out => "out",
+            Direction::In => "in",
+        }
+    }
+
+    fn as_byte(&self) -> u8 {
+        match self {
+            Direction::Out => 0,
+            Direction::In => 1,
+        }
+    }
+}
+
+/// Statistics of the graph used to estimate the cardinality of traversal steps.
+///
+/// Keeps per-label node and edge counts, log2 bucketed histograms of the out and in
+/// degrees of nodes per edge label, and the number of entries and distinct values of
+/// every secondary index. Like the change log, the statistics are updated inside the
+/// write transaction of the mutation, so they never include aborted writes.
+///
+/// Secondary index entries aren't removed when a node is updated or dropped, so index
+/// statistics count every value ever indexed and selectivity is an estimate.
+pub struct GraphStats {
+    pub stats_db: Database<Str, U64<BE>>,
+    pub degrees_db: Database<Bytes, U64<BE>>,
+    enabled: AtomicBool,
+}
+
+impl GraphStats {
+    pub fn new(graph_env: &Env, wtxn: &mut RwTxn, enabled: bool) -> Result<GraphStats, GraphError> {
+        let stats_db: Database<Str, U64<BE>> = graph_env
+            .database_options()
+            .types::<Str, U64<BE>>()
+            .name(DB_STATS)
+            .create(wtxn)?;
+        let degrees_db: Database<Bytes, U64<BE>> = graph_env
+            .database_options()
+            .types::<Bytes, U64<BE>>()
+            .name(DB_STATS_DEGREES)
+            .create(wtxn)?;
+
+        Ok(GraphStats {
+            stats_db,
+            degrees_db,
+            enabled: AtomicBool::new(enabled),
+        })
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    fn node_key(label: &str) -> String {
+        format!("node:{}", label)
+    }
+
+    #[inline(always)]
+    fn edge_key(label: &str) -> String {
+        format!("edge:{}", label)
+    }
+
+    #[inline(always)]
+    fn histogram_prefix(label: &str, direction: Direction) -> String {
+        format!("degree:{}:{}:", direction.as_str(), label)
+    }
+
+    #[inline(always)]
+    fn index_entries_key(index: &str) -> String {
+        format!("index_entries:{}", index)
+    }
+
+    #[inline(always)]
+    fn index_values_key(index: &str) -> String {
+        format!("index_values:{}", index)
+    }
+
+    // key = node id(16) | label hash(4) | direction(1)
+    #[inline(always)]
+    fn degree_key(node_id: &u128, label: &str, direction: Direction) -> [u8; 21] {
+        let mut key = [0u8; 21];
+        key[0..16].copy_from_slice(&node_id.to_be_bytes());
+        key[16..20].copy_from_slice(&hash_label(label, None));
+        key[20] = direction.as_byte();
+        key
+    }
+
+    /// Histogram bucket of a degree, bucket `b` holds degrees in `2^b..2^(b+1)`
+    #[inline(always)]
+    pub fn degree_bucket(degree: u64) -> u32 {
+        degree.max(1).ilog2()
+    }
+
+    fn adjust(&self, txn: &mut RwTxn, key: &str, delta: i64) -> Result<(), GraphError> {
+        let count = self.stats_db.get(txn, key)?.unwrap_or(0);
+        match count.saturating_add_signed(delta) {
+            0 => {
+                self.stats_db.delete(txn, key)?;
+            }
+            count => self.stats_db.put(txn, key, &count)?,
+        }
+        Ok(())
+    }
+
+    fn adjust_degree(
+        &self,
+        txn: &mut RwTxn,
+        node_id: &u128,
+        label: &str,
+        direction: Direction,
+        delta: i64,
+    ) -> Result<(), GraphError> {
+        let key = Self::degree_key(node_id, label, direction);
+        let old = self.degrees_db.get(txn, &key)?.unwrap_or(0);
+        let new = old.saturating_add_signed(delta);
+        let prefix = Self::histogram_prefix(label, direction);
+        if old > 0 {
+            self.adjust(txn, &format!("{}{}", prefix, Self::degree_bucket(old)), -1)?;
+        }
+        if new > 0 {
+            self.adjust(txn, &format!("{}{}", prefix, Self::degree_bucket(new)), 1)?;
+            self.degrees_db.put(txn, &key, &new)?;
+        } else {
+            self.degrees_db.delete(txn, &key)?;
+        }
+        Ok(())
+    }
+
+    /// Records a new node as part of the given write transaction.
+    ///
+    /// Does nothing if statistics are disabled, as do all other recording methods.
+    pub fn node_added(&self, txn: &mut RwTxn, label: &str) -> Result<(), GraphError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        self.adjust(txn, &Self::node_key(label), 1)
+    }
+
+    pub fn node_removed(&self, txn: &mut RwTxn, label: &str) -> Result<(), GraphError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        self.adjust(txn, &Self::node_key(label), -1)
+    }
+
+    pub fn edge_added(&self, txn: &mut RwTxn, edge: &Edge) -> Result<(), GraphError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        self.adjust(txn, &Self::edge_key(&edge.label), 1)?;
+        self.adjust_degree(txn, &edge.from_node, &edge.label, Direction::Out, 1)?;
+        self.adjust_degree(txn, &edge.to_node, &edge.label, Direction::In, 1)
+    }
+
+    pub fn edge_removed(&self, txn: &mut RwTxn, edge: &Edge) -> Result<(), GraphError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        self.adjust(txn, &Self::edge_key(&edge.label), -1)?;
+        self.adjust_degree(txn, &edge.from_node, &edge.label, Direction::Out, -1)?;
+        self.adjust_degree(txn, &edge.to_node, &edge.label, Direction::In, -1)
+    }
+
+    /// Records an entry written to a secondary index.
+    ///
+    /// ## Arguments
+    ///
+    /// * `index` - Name of the secondary index
+    /// * `new_value` - Whether no entry existed for the indexed value before
+    pub fn index_entry_added(
+        &self,
+        txn: &mut RwTxn,
+        index: &str,
+        new_value: bool,
+    ) -> Result<(), GraphError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        self.adjust(txn, &Self::index_entries_key(index), 1)?;
+        if new_value {
+            self.adjust(txn, &Self::index_values_key(index), 1)?;
+        }
+        Ok(())
+    }
+
+    /// Removes all statistics, e.g. before they are rebuilt from the stored graph
+    pub fn clear(&self, txn: &mut RwTxn) -> Result<(), GraphError> {
+        self.stats_db.clear(txn)?;
+        self.degrees_db.clear(txn)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self, txn: &RoTxn) -> Result<bool, GraphError> {
+        Ok(self.stats_db.is_empty(txn)?)
+    }
+
+    /// Number of nodes with the given label
+    pub fn node_count(&self, txn: &RoTxn, label: &str) -> Result<u64, GraphError> {
+        Ok(self.stats_db.get(txn, &Self::node_key(label))?.unwrap_or(0))
+    }
+
+    /// Number of edges with the given label
+    pub fn edge_count(&self, txn: &RoTxn, label: &str) -> Result<u64, GraphError> {
+        Ok(self.stats_db.get(txn, &Self::edge_key(label))?.unwrap_or(0))
+    }
+
+    /// Number of edges of the given label the node has in the direction
+    pub fn degree(
+        &self,
+        txn: &RoTxn,
+        node_id: &u128,
+        label: &str,
+        direction: Direction,
+    ) -> Result<u64, GraphError> {
+        Ok(self
+            .degrees_db
+            .get(txn, &Self::degree_key(node_id, label, direction))?
+            .unwrap_or(0))
+    }
+
+    /// Histogram of the degrees of nodes with at least one edge of the given label.
+    ///
+    /// Returns `(bucket, number of nodes)` pairs ordered by bucket, see
+    /// [`GraphStats::degree_bucket`].
+    pub fn degree_histogram(
+        &self,
+        txn: &RoTxn,
+        label: &str,
+        direction: Direction,
+    ) -> Result<Vec<(u32, u64)>, GraphError> {
+        let prefix = Self::histogram_prefix(label, direction);
+        let mut histogram = Vec::new();
+        for result in self.stats_db.prefix_iter(txn, &prefix)? {
+            let (key, count) = result?;
+            if let Ok(bucket) = key[prefix.len()..].parse::<u32>() {
+                histogram.push((bucket, count));
+            }
+        }
+        histogram.sort_unstable_by_key(|(bucket, _)| *bucket);
+        Ok(histogram)
+    }
+
+    /// Average number of edges of the given label per node that has any
+    pub fn avg_degree(
+        &self,
+        txn: &RoTxn,
+        label: &str,
+        direction: Direction,
+    ) -> Result<f64, GraphError> {
+        let nodes: u64 = self
+            .degree_histogram(txn, label, direction)?
+            .iter()
+            .map(|(_, count)| count)
+            .sum();
+        match nodes {
+            0 => Ok(0.0),
+            nodes => Ok(self.edge_count(txn, label)? as f64 / nodes as f64),
+        }
+    }
+
+    /// Fraction of distinct values among the entries of a secondary index.
+    ///
+    /// 1.0 means every value maps to a single node, lower values mean a lookup returns
+    /// more nodes. Returns `None` if nothing has been indexed yet.
+    pub fn index_selectivity(&self, txn: &RoTxn, index: &str) -> Result<Option<f64>, GraphError> {
+        let entries = self
+            .stats_db
+            .get(txn, &Self::index_entries_key(index))?
+            .unwrap_or(0);
+        let values = self
+            .stats_db
+            .get(txn, &Self::index_values_key(index))?
+            .unwrap_or(0);
+        match entries {
+            0 => Ok(None),
+            entries => Ok(Some(values as f64 / entries as f64)),
+        }
+    }
+
+    /// Estimated number of nodes a secondary index lookup of a single value returns
+    pub fn estimate_index_lookup(&self, txn: &RoTxn, index: &str) -> Result<f64, GraphError> {
+        Ok(match self.index_selectivity(txn, index)? {
+            Some(selectivity) if selectivity > 0.0 => 1.0 / selectivity,
+            _ => 0.0,
+        })
+    }
+
+    /// Picks the secondary index to look up the nodes of a label having a given value
+    /// in, rather than scanning all nodes of the label.
+    ///
+    /// Returns the position in `indices` of the index expected to return the fewest
+    /// nodes, or `None` if scanning the label is expected to read fewer. Indices are
+    /// shared by all labels, so a lookup may return nodes of other labels too. Without
+    /// statistics the first index is picked, as a lookup by value rarely reads more
+    /// nodes than a scan.
+    pub fn pick_index_lookup(
+        &self,
+        txn: &RoTxn,
+        label: &str,
+        indices: &[&str],
+    ) -> Result<Option<usize>, GraphError> {
+        if indices.is_empty() {
+            return Ok(None);
+        }
+        if !self.is_enabled() || self.is_empty(txn)? {
+            return Ok(Some(0));
+        }
+        let mut best: Option<(usize, f64)> = None;
+        for (i, index) in indices.iter().enumerate() {
+            let estimate = self.estimate_index_lookup(txn, index)?;
+            if best.is_none_or(|(_, best)| estimate < best) {
+                best = Some((i, estimate));
+            }
+        }
+        let scan = self.node_count(txn, label)? as f64;
+        Ok(best
+            .filter(|(_, estimate)| *estimate < scan)
+            .map(|(i, _)| i))
+    }
+
+    /// Estimated number of items an `Out`/`In` step over edges of the given label
+    /// returns for `input` nodes.
+    ///
+    /// The average is taken over the whole edge label, so the estimate assumes the
+    /// input nodes are a random sample of the nodes with such edges.
+    pub fn estimate_traversal(
+        &self,
+        txn: &RoTxn,
+        label: &str,
+        direction: Direction,
+        input: f64,
+    ) -> Result<f64, GraphError> {
+        Ok(input * self.avg_degree(txn, label, direction)?)
+    }
+
+    /// Picks the side to start a traversal between nodes of `from_label` and
+    /// `to_label` connected by edges of `edge_label` from.
+    ///
+    /// Returns [`Direction::Out`] if starting at the `from_label` nodes and following
+    /// out edges touches fewer items than starting at the `to_label` nodes and following
+    /// in edges, [`Direction::In`] otherwise.
+    pub fn cheaper_start(
+        &self,
+        txn: &RoTxn,
+        from_label: &str,
+        edge_label: &str,
+        to_label: &str,
+    ) -> Result<Direction, GraphError> {
+        let from = self.node_count(txn, from_label)? as f64;
+        let to = self.node_count(txn, to_label)? as f64;
+        let out_cost = from + self.estimate_traversal(txn, edge_label, Direction::Out, from)?;
+        let in_cost = to + self.estimate_traversal(txn, edge_label, Direction::In, to)?;
+        match out_cost <= in_cost {
+            true => Ok(Direction::Out),
+            false => Ok(Direction::In),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                    n_from_id::NFromIdAdapter,
+                },
+                tr_val::Traversable,
+                util::update::UpdateAdapter,
+            },
+        },
+        stats::stats::Direction,
+        storage_core::{
+            storage_core::HelixGraphStorage, storage_methods::StorageMethods, test_utils,
+        },
+    },
+    props,
+};
+
+fn stats_config(enabled: bool) -> Config {
+    let mut config = Config::default();
+    config.stats = enabled;
+    config.graph_config.secondary_indices = Some(vec!["country".to_string()]);
+    config
+}
+
+fn open(dir: &TempDir, enabled: bool) -> Arc<HelixGraphStorage> {
+    test_utils::open(dir, stats_config(enabled))
+}
+
+/// Adds `people` persons in two countries and one city that all of them live in, with
+/// the first person following every other one
+fn setup_graph(storage: &Arc<HelixGraphStorage>, people: usize) -> (Vec<u128>, u128) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let city = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n("city", None, None)
+        .collect_to::<Vec<_>>()[0]
+        .id();
+    let mut persons = Vec::new();
+    for i in 0..people {
+        let country = if i % 2 == 0 { "NL" } else { "DE" };
+        let person = G::new_mut(Arc::clone(storage), &mut txn)
+            .add_n(
+                "person",
+                Some(props! { "country" => country }),
+                Some(&["country"]),
+            )
+            .collect_to::<Vec<_>>()[0]
+            .id();
+        G::new_mut(Arc::clone(storage), &mut txn)
+            .add_e("lives_in", None, person, city, false, EdgeType::Node)
+            .collect_to::<Vec<_>>();
+        persons.push(person);
+    }
+    for person in persons.iter().skip(1) {
+        G::new_mut(Arc::clone(storage), &mut txn)
+            .add_e("follows", None, persons[0], *person, false, EdgeType::Node)
+            .collect_to::<Vec<_>>();
+    }
+    txn.commit().unwrap();
+    (persons, city)
+}
+
+#[test]
+fn test_counts_and_histograms() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, true);
+    setup_graph(&storage, 8);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let stats = &storage.stats;
+    assert_eq!(stats.node_count(&txn, "person").unwrap(), 8);
+    assert_eq!(stats.node_count(&txn, "city").unwrap(), 1);
+    assert_eq!(stats.edge_count(&txn, "lives_in").unwrap(), 8);
+    assert_eq!(stats.edge_count(&txn, "follows").unwrap(), 7);
+
+    // every person has a single out edge, the city has 8 in edges
+    assert_eq!(
+        stats.degree_histogram(&txn, "lives_in", Direction::Out).unwrap(),
+        vec![(0, 8)]
+    );
+    assert_eq!(
+        stats.degree_histogram(&txn, "lives_in", Direction::In).unwrap(),
+        vec![(3, 1)]
+    );
+    assert_eq!(
+        stats.degree_histogram(&txn, "follows", Direction::Out).unwrap(),
+        vec![(2, 1)]
+    );
+    assert_eq!(stats.avg_degree(&txn, "lives_in", Direction::In).unwrap(), 8.0);
+    assert_eq!(stats.avg_degree(&txn, "follows", Direction::In).unwrap(), 1.0);
+
+    // 8 entries with 2 distinct values
+    assert_eq!(stats.index_selectivity(&txn, "country").unwrap(), Some(0.25));
+    assert_eq!(stats.estimate_index_lookup(&txn, "country").unwrap(), 4.0);
+
+    // there is a single city, so starting from it is cheaper
+    assert_eq!(
+        stats.cheaper_start(&txn, "person", "lives_in", "city").unwrap(),
+        Direction::In
+    );
+}
+
+#[test]
+fn test_drops_are_recorded() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, true);
+    let (persons, _) = setup_graph(&storage, 8);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.drop_node(&mut txn, &persons[0]).unwrap();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let stats = &storage.stats;
+    assert_eq!(stats.node_count(&txn, "person").unwrap(), 7);
+    assert_eq!(stats.edge_count(&txn, "lives_in").unwrap(), 7);
+    assert_eq!(stats.edge_count(&txn, "follows").unwrap(), 0);
+    assert!(stats
+        .degree_histogram(&txn, "follows", Direction::Out)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        stats.degree_histogram(&txn, "lives_in", Direction::In).unwrap(),
+        vec![(2, 1)]
+    );
+}
+
+#[test]
+fn test_rebuild_matches_incremental() {
+    let incremental_dir = TempDir::new().unwrap();
+    let incremental = open(&incremental_dir, true);
+    setup_graph(&incremental, 5);
+
+    // enabling statistics for an existing database rebuilds them
+    let rebuilt_dir = TempDir::new().unwrap();
+    let storage = open(&rebuilt_dir, false);
+    setup_graph(&storage, 5);
+    drop(storage);
+    let rebuilt = open(&rebuilt_dir, true);
+
+    let txn = incremental.graph_env.read_txn().unwrap();
+    let incremental_stats = incremental
+        .stats
+        .stats_db
+        .iter(&txn)
+        .unwrap()
+        .map(|result| result.map(|(key, count)| (key.to_string(), count)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let txn = rebuilt.graph_env.read_txn().unwrap();
+    let rebuilt_stats = rebuilt
+        .stats
+        .stats_db
+        .iter(&txn)
+        .unwrap()
+        .map(|result| result.map(|(key, count)| (key.to_string(), count)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(!incremental_stats.is_empty());
+    assert_eq!(incremental_stats, rebuilt_stats);
+}
+
+#[test]
+fn test_pick_index_lookup() {
+    let dir = TempDir::new().unwrap();
+    let mut config = stats_config(true);
+    config.graph_config.secondary_indices = Some(vec!["country".to_string(), "email".to_string()]);
+    let storage = Arc::new(HelixGraphStorage::new(dir.path().to_str().unwrap(), config).unwrap());
+    let (persons, _) = setup_graph(&storage, 8);
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for (i, person) in persons.iter().enumerate() {
+        let person = G::new(Arc::clone(&storage), &txn)
+            .n_from_id(person)
+            .collect_to::<Vec<_>>();
+        G::new_mut_from(Arc::clone(&storage), &mut txn, person)
+            .update(Some(props! { "email" => format!("{}@example.com", i) }))
+            .collect_to::<Vec<_>>();
+    }
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let stats = &storage.stats;
+    // an email is held by a single person, a country by 4 of them
+    assert_eq!(
+        stats
+            .pick_index_lookup(&txn, "person", &["country", "email"])
+            .unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        stats
+            .pick_index_lookup(&txn, "person", &["country"])
+            .unwrap(),
+        Some(0)
+    );
+    // scanning the single city reads fewer nodes than looking up a country
+    assert_eq!(
+        stats.pick_index_lookup(&txn, "city", &["country"]).unwrap(),
+        None
+    );
+    assert_eq!(stats.pick_index_lookup(&txn, "person", &[]).unwrap(), None);
+
+    // without statistics the first index is looked up
+    stats.set_enabled(false);
+    assert_eq!(
+        stats
+            .pick_index_lookup(&txn, "city", &["country", "email"])
+            .unwrap(),
+        Some(0)
+    );
+}
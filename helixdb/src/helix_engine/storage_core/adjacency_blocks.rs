@@ -0,0 +1,404 @@
+//! Compacted adjacency lists of high degree nodes.
+//!
+//! Edges are added to the `out_edges` and `in_edges` tables as a 32 byte duplicate of the
+//! key of their node and label, and `Out`/`In` steps from nodes with many edges spend most
+//! of their time walking these. Compaction moves the edges of adjacency lists with at
+//! least `min_degree` of them into blocks of up to `block_size` edges sorted by adjacent
+//! node, every id stored as the LEB128 encoded difference to the one before it. Edges
+//! added afterwards go to the dupsort tables again until the next compaction, and reads
+//! return the compacted edges followed by those.
+//!
+//! The blocks of a list are keyed by their first adjacent node next to a header holding
+//! the number of compacted edges, so the degree of a compacted list is known without
+//! reading its blocks.
+
+use std::ops::Bound;
+
+use crate::{
+    helix_engine::{
+        graph_core::config::AdjacencyConfig, stats::stats::Direction,
+        storage_core::storage_core::HelixGraphStorage, types::GraphError,
+    },
+    helix_storage::heed3::{
+        iteration_method::MoveOnCurrentKeyDuplicates, types::Bytes, Database, Env, RoIter, RoTxn,
+        RwTxn,
+    },
+};
+
+const DB_ADJACENCY_BLOCKS: &str = "adjacency_blocks"; // direction | node id | label [| first adjacent id] -> block
+
+const DEFAULT_MIN_DEGREE: usize = 1024;
+const DEFAULT_BLOCK_SIZE: usize = 256;
+
+/// An edge of an adjacency list as `(adjacent node id, edge id)`
+pub type AdjacentEdge = (u128, u128);
+
+/// Blocks of the compacted adjacency lists
+pub struct AdjacencyBlocks {
+    pub blocks_db: Database<Bytes, Bytes>,
+    /// Fewest edges of a label a list needs to be compacted
+    pub min_degree: usize,
+    pub block_size: usize,
+}
+
+impl AdjacencyBlocks {
+    pub fn new(
+        graph_env: &Env,
+        wtxn: &mut RwTxn,
+        config: &AdjacencyConfig,
+    ) -> Result<AdjacencyBlocks, GraphError> {
+        let blocks_db: Database<Bytes, Bytes> = graph_env
+            .database_options()
+            .types::<Bytes, Bytes>()
+            .name(DB_ADJACENCY_BLOCKS)
+            .create(wtxn)?;
+
+        Ok(AdjacencyBlocks {
+            blocks_db,
+            min_degree: config.min_degree.unwrap_or(DEFAULT_MIN_DEGREE).max(1),
+            block_size: config.block_size.unwrap_or(DEFAULT_BLOCK_SIZE).max(1),
+        })
+    }
+
+    // key = direction(1) | node id(16) | label(4)
+    #[inline(always)]
+    fn header_key(direction: Direction, node_id: &u128, label: &[u8; 4]) -> [u8; 21] {
+        let mut key = [0u8; 21];
+        key[0] = direction_byte(direction);
+        key[1..17].copy_from_slice(&node_id.to_be_bytes());
+        key[17..21].copy_from_slice(label);
+        key
+    }
+
+    // key = direction(1) | node id(16) | label(4) | first adjacent node id(16)
+    #[inline(always)]
+    fn block_key(header: &[u8; 21], first: &u128) -> [u8; 37] {
+        let mut key = [0u8; 37];
+        key[0..21].copy_from_slice(header);
+        key[21..37].copy_from_slice(&first.to_be_bytes());
+        key
+    }
+
+    /// Number of compacted edges of the adjacency list
+    pub fn degree(
+        &self,
+        txn: &RoTxn,
+        direction: Direction,
+        node_id: &u128,
+        label: &[u8; 4],
+    ) -> Result<u64, GraphError> {
+        match self
+            .blocks_db
+            .get(txn, &Self::header_key(direction, node_id, label))?
+        {
+            Some(header) => read_count(header),
+            None => Ok(0),
+        }
+    }
+
+    /// Compacted edges of the adjacency list, ordered by adjacent node
+    pub fn edges(
+        &self,
+        txn: &RoTxn,
+        direction: Direction,
+        node_id: &u128,
+        label: &[u8; 4],
+    ) -> Result<Vec<AdjacentEdge>, GraphError> {
+        let header = Self::header_key(direction, node_id, label);
+        let mut edges = Vec::new();
+        for result in self.blocks_db.prefix_iter(txn, &header)? {
+            let (key, value) = result?;
+            match key.len() == header.len() {
+                true => edges.reserve(read_count(value)? as usize),
+                false => decode_block(value, &mut edges)?,
+            }
+        }
+        Ok(edges)
+    }
+
+    /// Compacted edges of all adjacency lists of the node, with the label of their list
+    pub fn node_edges(
+        &self,
+        txn: &RoTxn,
+        direction: Direction,
+        node_id: &u128,
+    ) -> Result<Vec<([u8; 4], AdjacentEdge)>, GraphError> {
+        let mut prefix = [0u8; 17];
+        prefix[0] = direction_byte(direction);
+        prefix[1..17].copy_from_slice(&node_id.to_be_bytes());
+        let mut edges = Vec::new();
+        let mut block = Vec::new();
+        for result in self.blocks_db.prefix_iter(txn, &prefix)? {
+            let (key, value) = result?;
+            if key.len() != 37 {
+                continue;
+            }
+            let label: [u8; 4] = key[17..21].try_into().unwrap();
+            decode_block(value, &mut block)?;
+            edges.extend(block.drain(..).map(|edge| (label, edge)));
+        }
+        Ok(edges)
+    }
+
+    /// Compacted edges of all adjacency lists in the direction, with the node and label
+    /// of their list
+    pub fn all_edges(
+        &self,
+        txn: &RoTxn,
+        direction: Direction,
+    ) -> Result<Vec<(u128, [u8; 4], AdjacentEdge)>, GraphError> {
+        let mut edges = Vec::new();
+        let mut block = Vec::new();
+        for result in self
+            .blocks_db
+            .prefix_iter(txn, &[direction_byte(direction)])?
+        {
+            let (key, value) = result?;
+            if key.len() != 37 {
+                continue;
+            }
+            let node_id = u128::from_be_bytes(key[1..17].try_into().unwrap());
+            let label: [u8; 4] = key[17..21].try_into().unwrap();
+            decode_block(value, &mut block)?;
+            edges.extend(block.drain(..).map(|edge| (node_id, label, edge)));
+        }
+        Ok(edges)
+    }
+
+    /// Replaces the compacted edges of the adjacency list
+    pub fn write(
+        &self,
+        txn: &mut RwTxn,
+        direction: Direction,
+        node_id: &u128,
+        label: &[u8; 4],
+        mut edges: Vec<AdjacentEdge>,
+    ) -> Result<(), GraphError> {
+        let header = Self::header_key(direction, node_id, label);
+        self.remove_keys(txn, &header)?;
+        if edges.is_empty() {
+            return Ok(());
+        }
+        edges.sort_unstable();
+        self.blocks_db
+            .put(txn, &header, &(edges.len() as u64).to_be_bytes())?;
+        let mut start = 0;
+        while start < edges.len() {
+            // edges to the same node share a block, so removals find them by that node
+            let mut end = (start + self.block_size).min(edges.len());
+            while end < edges.len() && edges[end].0 == edges[end - 1].0 {
+                end += 1;
+            }
+            let block = &edges[start..end];
+            self.blocks_db.put(
+                txn,
+                &Self::block_key(&header, &block[0].0),
+                &encode_block(block),
+            )?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Removes a compacted edge from the adjacency list, returns whether it was found
+    pub fn remove(
+        &self,
+        txn: &mut RwTxn,
+        direction: Direction,
+        node_id: &u128,
+        label: &[u8; 4],
+        edge: AdjacentEdge,
+    ) -> Result<bool, GraphError> {
+        let header = Self::header_key(direction, node_id, label);
+        let count = match self.blocks_db.get(txn, &header)? {
+            Some(header) => read_count(header)?,
+            None => return Ok(false),
+        };
+        // the last block starting at or before the adjacent node holds the edge
+        let upper = Self::block_key(&header, &edge.0);
+        let range = (Bound::Excluded(&header[..]), Bound::Included(&upper[..]));
+        let (key, mut edges) = match self.blocks_db.rev_range(txn, &range)?.next() {
+            Some(result) => {
+                let (key, value) = result?;
+                let mut edges = Vec::new();
+                decode_block(value, &mut edges)?;
+                (key.to_vec(), edges)
+            }
+            None => return Ok(false),
+        };
+        let Ok(position) = edges.binary_search(&edge) else {
+            return Ok(false);
+        };
+        edges.remove(position);
+        self.blocks_db.delete(txn, &key)?;
+        if let Some((first, _)) = edges.first() {
+            self.blocks_db
+                .put(txn, &Self::block_key(&header, first), &encode_block(&edges))?;
+        }
+        match count - 1 {
+            0 => {
+                self.blocks_db.delete(txn, &header)?;
+            }
+            count => self.blocks_db.put(txn, &header, &count.to_be_bytes())?,
+        }
+        Ok(true)
+    }
+
+    /// Removes the compacted adjacency lists of all labels of the node
+    pub fn remove_node(
+        &self,
+        txn: &mut RwTxn,
+        direction: Direction,
+        node_id: &u128,
+    ) -> Result<(), GraphError> {
+        let mut prefix = [0u8; 17];
+        prefix[0] = direction_byte(direction);
+        prefix[1..17].copy_from_slice(&node_id.to_be_bytes());
+        self.remove_keys(txn, &prefix)
+    }
+
+    fn remove_keys(&self, txn: &mut RwTxn, prefix: &[u8]) -> Result<(), GraphError> {
+        let keys = self
+            .blocks_db
+            .lazily_decode_data()
+            .prefix_iter(txn, prefix)?
+            .map(|result| result.map(|(key, _)| key.to_vec()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for key in keys {
+            self.blocks_db.delete(txn, &key)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether an edge of an adjacency list is left out, e.g. because it is in the trash
+type SkipEdge<'t> = Box<dyn Fn(&AdjacentEdge) -> Result<bool, GraphError> + 't>;
+
+/// Edges of an adjacency list, the compacted ones followed by those added since
+pub struct AdjacentEdges<'t> {
+    compacted: std::vec::IntoIter<AdjacentEdge>,
+    added: Option<RoIter<'t, Bytes, Bytes, MoveOnCurrentKeyDuplicates>>,
+    skip: Option<SkipEdge<'t>>,
+}
+
+impl<'t> AdjacentEdges<'t> {
+    pub fn new(
+        compacted: Vec<AdjacentEdge>,
+        added: Option<RoIter<'t, Bytes, Bytes, MoveOnCurrentKeyDuplicates>>,
+    ) -> AdjacentEdges<'t> {
+        AdjacentEdges {
+            compacted: compacted.into_iter(),
+            added,
+            skip: None,
+        }
+    }
+
+    /// Leaves out the edges the predicate holds for
+    pub fn skipping(
+        mut self,
+        skip: impl Fn(&AdjacentEdge) -> Result<bool, GraphError> + 't,
+    ) -> AdjacentEdges<'t> {
+        self.skip = Some(Box::new(skip));
+        self
+    }
+
+    fn next_edge(&mut self) -> Option<Result<AdjacentEdge, GraphError>> {
+        if let Some(edge) = self.compacted.next() {
+            return Some(Ok(edge));
+        }
+        match self.added.as_mut()?.next()? {
+            Ok((_, data)) => Some(HelixGraphStorage::unpack_adj_edge_data(data)),
+            Err(e) => Some(Err(GraphError::from(e))),
+        }
+    }
+}
+
+impl Iterator for AdjacentEdges<'_> {
+    type Item = Result<AdjacentEdge, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let edge = self.next_edge()?;
+            let Some(skip) = &self.skip else {
+                return Some(edge);
+            };
+            match edge.as_ref().map(skip) {
+                Ok(Ok(true)) => continue,
+                Ok(Err(e)) => return Some(Err(e)),
+                _ => return Some(edge),
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn direction_byte(direction: Direction) -> u8 {
+    match direction {
+        Direction::Out => 0,
+        Direction::In => 1,
+    }
+}
+
+fn read_count(header: &[u8]) -> Result<u64, GraphError> {
+    Ok(u64::from_be_bytes(
+        header
+            .try_into()
+            .map_err(|_| GraphError::SliceLengthError)?,
+    ))
+}
+
+// block = count | per edge: adjacent id - previous adjacent id | zigzag(edge id - previous edge id)
+fn encode_block(edges: &[AdjacentEdge]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(edges.len() * 16);
+    write_varint(&mut bytes, edges.len() as u128);
+    let (mut previous_node, mut previous_edge) = (0u128, 0u128);
+    for (node_id, edge_id) in edges {
+        // sorted by adjacent node, so its difference is never negative
+        write_varint(&mut bytes, node_id - previous_node);
+        // edge ids are close when the edges were added close in time
+        let delta = edge_id.wrapping_sub(previous_edge) as i128;
+        write_varint(&mut bytes, ((delta << 1) ^ (delta >> 127)) as u128);
+        (previous_node, previous_edge) = (*node_id, *edge_id);
+    }
+    bytes
+}
+
+fn decode_block(bytes: &[u8], edges: &mut Vec<AdjacentEdge>) -> Result<(), GraphError> {
+    let mut position = 0;
+    let count = read_varint(bytes, &mut position)? as usize;
+    edges.reserve(count);
+    let (mut node_id, mut edge_id) = (0u128, 0u128);
+    for _ in 0..count {
+        node_id = node_id.wrapping_add(read_varint(bytes, &mut position)?);
+        let zigzag = read_varint(bytes, &mut position)?;
+        let delta = ((zigzag >> 1) as i128) ^ -((zigzag & 1) as i128);
+        edge_id = edge_id.wrapping_add(delta as u128);
+        edges.push((node_id, edge_id));
+    }
+    Ok(())
+}
+
+#[inline(always)]
+fn write_varint(bytes: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+#[inline(always)]
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u128, GraphError> {
+    let mut value = 0u128;
+    for shift in (0..128).step_by(7) {
+        let byte = *bytes.get(*position).ok_or(GraphError::SliceLengthError)?;
+        *position += 1;
+        value |= ((byte & 0x7F) as u128) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(GraphError::DecodeError(
+        "Invalid length in adjacency block".to_string(),
+    ))
+}
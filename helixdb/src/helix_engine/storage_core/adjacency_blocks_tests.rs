@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                in_::in_::InAdapter,
+                out::{out::OutAdapter, out_e::OutEdgesAdapter},
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                    n_from_id::NFromIdAdapter,
+                },
+                tr_val::{Traversable, TraversalVal},
+            },
+        },
+        stats::stats::Direction,
+        storage_core::{
+            storage_core::HelixGraphStorage, storage_methods::StorageMethods, test_utils::open,
+        },
+    },
+};
+
+fn adjacency_config(min_degree: usize, block_size: usize) -> Config {
+    let mut config = Config::default();
+    config.adjacency.min_degree = Some(min_degree);
+    config.adjacency.block_size = Some(block_size);
+    config
+}
+
+/// Adds a hub following `count` new nodes, returning the ids of the hub and the followed
+/// nodes
+fn add_hub(storage: &Arc<HelixGraphStorage>, count: usize) -> (u128, Vec<u128>) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let hub = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n("user", None, None)
+        .collect_to_val()
+        .id();
+    let mut followed = Vec::with_capacity(count);
+    for _ in 0..count {
+        let user = G::new_mut(Arc::clone(storage), &mut txn)
+            .add_n("user", None, None)
+            .collect_to_val()
+            .id();
+        G::new_mut(Arc::clone(storage), &mut txn)
+            .add_e("follows", None, hub, user, false, EdgeType::Node)
+            .collect_to_val();
+        followed.push(user);
+    }
+    txn.commit().unwrap();
+    (hub, followed)
+}
+
+fn follow(storage: &Arc<HelixGraphStorage>, from: u128, to: u128) -> u128 {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let edge = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_e("follows", None, from, to, false, EdgeType::Node)
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+    edge
+}
+
+fn followed_by(storage: &Arc<HelixGraphStorage>, id: u128) -> Vec<u128> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    let mut ids = G::new(Arc::clone(storage), &txn)
+        .n_from_id(&id)
+        .out("follows", &EdgeType::Node)
+        .collect_to::<Vec<_>>()
+        .iter()
+        .map(|val| val.id())
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+fn degree(storage: &Arc<HelixGraphStorage>, id: u128, direction: Direction) -> u64 {
+    let txn = storage.graph_env.read_txn().unwrap();
+    storage.degree(&txn, &id, "follows", direction).unwrap()
+}
+
+#[test]
+fn test_compaction_keeps_adjacent_nodes() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, adjacency_config(10, 4));
+    let (hub, mut followed) = add_hub(&storage, 25);
+    let (small, _) = add_hub(&storage, 3);
+
+    // only the hub has enough edges, the followed nodes have one in edge each
+    assert_eq!(storage.compact_adjacency().unwrap(), 1);
+    followed.sort();
+    assert_eq!(followed_by(&storage, hub), followed);
+    assert_eq!(followed_by(&storage, small).len(), 3);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let label = storage.dictionary.label_key("follows");
+    assert_eq!(
+        storage
+            .adjacency_blocks
+            .degree(&txn, Direction::Out, &hub, &label)
+            .unwrap(),
+        25
+    );
+    assert_eq!(
+        storage
+            .adjacency_blocks
+            .degree(&txn, Direction::Out, &small, &label)
+            .unwrap(),
+        0
+    );
+    // compacted edges come back ordered by adjacent node
+    let compacted = storage
+        .adjacency(&txn, &hub, &label, Direction::Out)
+        .unwrap()
+        .map(|edge| edge.unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(compacted, followed);
+
+    let back = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&followed[0])
+        .in_("follows", &EdgeType::Node)
+        .collect_to::<Vec<_>>();
+    assert_eq!(back.len(), 1);
+    assert_eq!(back[0].id(), hub);
+}
+
+#[test]
+fn test_edges_added_after_compaction() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, adjacency_config(10, 4));
+    let (hub, mut followed) = add_hub(&storage, 12);
+    storage.compact_adjacency().unwrap();
+
+    let (other, _) = add_hub(&storage, 0);
+    follow(&storage, hub, other);
+    followed.push(other);
+    followed.sort();
+    assert_eq!(followed_by(&storage, hub), followed);
+    assert_eq!(degree(&storage, hub, Direction::Out), 13);
+
+    // compacting again takes in the added edge
+    assert_eq!(storage.compact_adjacency().unwrap(), 1);
+    let txn = storage.graph_env.read_txn().unwrap();
+    let label = storage.dictionary.label_key("follows");
+    assert_eq!(
+        storage
+            .adjacency_blocks
+            .degree(&txn, Direction::Out, &hub, &label)
+            .unwrap(),
+        13
+    );
+    let edges = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&hub)
+        .out_e("follows")
+        .collect_to::<Vec<_>>();
+    assert_eq!(edges.len(), 13);
+    assert!(edges.iter().all(|edge| matches!(edge, TraversalVal::Edge(_))));
+}
+
+#[test]
+fn test_drop_compacted_edges() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, adjacency_config(10, 4));
+    let (hub, followed) = add_hub(&storage, 12);
+    let (other, _) = add_hub(&storage, 0);
+    let edge = follow(&storage, other, hub);
+    storage.compact_adjacency().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let dropped = storage
+        .adjacency(&txn, &hub, &storage.dictionary.label_key("follows"), Direction::Out)
+        .unwrap()
+        .map(|edge| edge.unwrap())
+        .find(|(node_id, _)| *node_id == followed[3])
+        .unwrap();
+    drop(txn);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.drop_edge(&mut txn, &dropped.1).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(degree(&storage, hub, Direction::Out), 11);
+    assert!(!followed_by(&storage, hub).contains(&followed[3]));
+
+    // dropping the hub removes its compacted list and its edges from the other lists
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.drop_node(&mut txn, &hub).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(degree(&storage, hub, Direction::Out), 0);
+    assert_eq!(degree(&storage, other, Direction::Out), 0);
+    assert_eq!(degree(&storage, followed[0], Direction::In), 0);
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.get_edge(&txn, &edge).is_err());
+}
+
+#[test]
+fn test_degree_from_statistics() {
+    let dir = TempDir::new().unwrap();
+    let mut config = adjacency_config(10, 4);
+    config.stats = true;
+    let storage = open(&dir, config);
+    let (hub, followed) = add_hub(&storage, 15);
+    storage.compact_adjacency().unwrap();
+
+    assert_eq!(degree(&storage, hub, Direction::Out), 15);
+    assert_eq!(degree(&storage, followed[0], Direction::In), 1);
+    assert_eq!(degree(&storage, followed[0], Direction::Out), 0);
+}
+
+#[test]
+fn test_adjacency_batch_groups_by_node() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, adjacency_config(10, 4));
+    let (hub, _) = add_hub(&storage, 12);
+    storage.compact_adjacency().unwrap();
+    let (small, _) = add_hub(&storage, 3);
+    let (empty, _) = add_hub(&storage, 0);
+    follow(&storage, hub, small);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let label = storage.dictionary.label_key("follows");
+    let single = |id: u128, direction: Direction| {
+        storage
+            .adjacency(&txn, &id, &label, direction)
+            .unwrap()
+            .map(|edge| edge.unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // walks the range between the first and last node
+    let ids = [small, empty, hub, small];
+    let groups = storage
+        .adjacency_batch(&txn, &ids, &label, Direction::Out)
+        .unwrap();
+    assert_eq!(groups.len(), 4);
+    for (id, group) in ids.iter().zip(&groups) {
+        assert_eq!(*group, single(*id, Direction::Out));
+    }
+    assert_eq!(groups[0].len(), 3);
+    assert!(groups[1].is_empty());
+    assert_eq!(groups[2].len(), 13);
+
+    // too few nodes for the table, looks up each of them
+    let groups = storage
+        .adjacency_batch(&txn, &[small], &label, Direction::In)
+        .unwrap();
+    assert_eq!(groups, vec![single(small, Direction::In)]);
+    assert_eq!(groups[0].len(), 1);
+
+    assert!(storage
+        .adjacency_batch(&txn, &[], &label, Direction::Out)
+        .unwrap()
+        .is_empty());
+}
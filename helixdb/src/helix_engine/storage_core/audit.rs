@@ -0,0 +1,291 @@
+//! Append-only log of the mutating queries run against the graph.
+//!
+//! An entry is written in the write transaction of the query it records, so it commits
+//! or aborts along with the writes of the query. Each entry holds the name and
+//! parameters of the query, the caller the gateway authenticated and the ids of the
+//! elements the query wrote, which are read back from the entries it journaled in the
+//! write-ahead log. Entries are only removed by pruning them past the retention window
+//! or the number of entries kept.
+//!
+//! Entries are encrypted like nodes and edges if encryption is enabled, with their seq
+//! as associated data, as their parameters may hold sensitive values.
+
+use std::{collections::HashSet, ops::Bound, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helix_engine::{
+        graph_core::config::AuditConfig,
+        storage_core::{
+            encryption::Encryption,
+            storage_core::HelixGraphStorage,
+            wal::{WalEntry, WalOp},
+        },
+        types::GraphError,
+    },
+    helix_storage::heed3::{
+        byteorder::BE,
+        types::{Bytes, Str, U64},
+        Database, Env, RoTxn, RwTxn,
+    },
+};
+
+const DB_AUDIT: &str = "audit"; // seq -> entry
+const DB_AUDIT_META: &str = "audit_meta"; // bookkeeping of the log
+const LAST_SEQ_KEY: &str = "last_seq"; // seq of the last entry appended, pruned or not
+
+/// A mutating query as recorded in the audit log
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub seq: u64,
+    /// Milliseconds since the unix epoch
+    pub timestamp: i64,
+    pub query: String,
+    /// JSON parameters the query was run with
+    pub parameters: String,
+    /// Subject of the token or fingerprint of the API key the request was authenticated
+    /// with, unset if the gateway doesn't authenticate requests
+    pub caller: Option<String>,
+    /// Ids of the nodes and edges the query wrote, in the order it wrote them
+    pub affected: Vec<String>,
+}
+
+pub struct AuditLog {
+    pub audit_db: Database<U64<BE>, Bytes>,
+    pub meta_db: Database<Str, U64<BE>>,
+    enabled: bool,
+    retention: Option<Duration>,
+    max_entries: Option<u64>,
+    prune_interval: Duration,
+    encryption: Encryption,
+}
+
+impl AuditLog {
+    pub const DEFAULT_PRUNE_INTERVAL_SECS: u64 = 60 * 60;
+
+    pub fn new(
+        graph_env: &Env,
+        wtxn: &mut RwTxn,
+        config: &AuditConfig,
+        encryption: Encryption,
+    ) -> Result<AuditLog, GraphError> {
+        let audit_db: Database<U64<BE>, Bytes> = graph_env
+            .database_options()
+            .types::<U64<BE>, Bytes>()
+            .name(DB_AUDIT)
+            .create(wtxn)?;
+        let meta_db: Database<Str, U64<BE>> = graph_env
+            .database_options()
+            .types::<Str, U64<BE>>()
+            .name(DB_AUDIT_META)
+            .create(wtxn)?;
+        Ok(AuditLog {
+            audit_db,
+            meta_db,
+            enabled: config.enabled,
+            retention: config.retention_secs.map(Duration::from_secs),
+            max_entries: config.max_entries,
+            prune_interval: Duration::from_secs(
+                config
+                    .prune_interval_secs
+                    .unwrap_or(Self::DEFAULT_PRUNE_INTERVAL_SECS)
+                    .max(1),
+            ),
+            encryption,
+        })
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether entries are pruned in the background
+    pub fn prunes(&self) -> bool {
+        self.enabled && (self.retention.is_some() || self.max_entries.is_some())
+    }
+
+    pub fn retention(&self) -> Option<Duration> {
+        self.retention
+    }
+
+    pub fn max_entries(&self) -> Option<u64> {
+        self.max_entries
+    }
+
+    pub fn prune_interval(&self) -> Duration {
+        self.prune_interval
+    }
+
+    /// Appends an entry under the seq following the last one, which it is set to. Seqs
+    /// aren't reused once their entries are pruned.
+    pub fn append(&self, txn: &mut RwTxn, mut entry: AuditEntry) -> Result<u64, GraphError> {
+        entry.seq = self.meta_db.get(txn, LAST_SEQ_KEY)?.unwrap_or(0) + 1;
+        self.meta_db.put(txn, LAST_SEQ_KEY, &entry.seq)?;
+        let bytes = self
+            .encryption
+            .encrypt(entry.seq as u128, bincode::serialize(&entry)?)?;
+        self.audit_db.put(txn, &entry.seq, &bytes)?;
+        Ok(entry.seq)
+    }
+
+    /// Decodes a stored entry, decrypting it first if it was stored encrypted
+    fn decode(&self, seq: u64, bytes: &[u8]) -> Result<AuditEntry, GraphError> {
+        Ok(bincode::deserialize(
+            &self.encryption.decrypt(seq as u128, bytes)?,
+        )?)
+    }
+
+    /// Up to `limit` entries following the seq `after`, oldest first
+    pub fn list(
+        &self,
+        txn: &RoTxn,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, GraphError> {
+        let range = (Bound::Excluded(after), Bound::Unbounded);
+        let mut entries = Vec::new();
+        for result in self.audit_db.range(txn, &range)?.take(limit) {
+            let (seq, bytes) = result?;
+            entries.push(self.decode(seq, bytes)?);
+        }
+        Ok(entries)
+    }
+
+    /// Removes the entries recorded before `cutoff`, milliseconds since the epoch, and
+    /// the oldest entries beyond the `max_entries` most recent ones, but none recorded
+    /// since `kept_since`. Returns the number of entries removed.
+    pub fn prune(
+        &self,
+        txn: &mut RwTxn,
+        cutoff: Option<i64>,
+        max_entries: Option<u64>,
+        kept_since: Option<i64>,
+    ) -> Result<usize, GraphError> {
+        let len = self.audit_db.len(txn)?;
+        let mut excess = max_entries.map_or(0, |max| len.saturating_sub(max));
+        let mut last_pruned = None;
+        // entries are appended in the order they were recorded, so the expired ones
+        // come first
+        for result in self.audit_db.iter(txn)? {
+            let (seq, bytes) = result?;
+            let timestamp = match cutoff.is_some() || kept_since.is_some() {
+                true => self.decode(seq, bytes)?.timestamp,
+                false => 0,
+            };
+            let expired = cutoff.is_some_and(|cutoff| timestamp < cutoff);
+            let kept = kept_since.is_some_and(|kept_since| timestamp >= kept_since);
+            if kept || (excess == 0 && !expired) {
+                break;
+            }
+            excess = excess.saturating_sub(1);
+            last_pruned = Some(seq);
+        }
+        match last_pruned {
+            Some(last) => Ok(self.audit_db.delete_range(txn, &(..=last))?),
+            None => Ok(0),
+        }
+    }
+}
+
+impl HelixGraphStorage {
+    /// Runs a mutating query against the write transaction and records it in the audit
+    /// log once it succeeds, if the log is enabled. Failed queries aren't recorded, as
+    /// their writes don't commit.
+    ///
+    /// ## Arguments
+    ///
+    /// * `txn` - The write transaction the query writes to and the entry is written in
+    /// * `query` - The name of the query
+    /// * `parameters` - The JSON parameters of the query
+    /// * `caller` - Who ran the query, see [`AuditEntry::caller`]
+    /// * `run` - Runs the query
+    pub fn audited<T, F>(
+        &self,
+        txn: &mut RwTxn,
+        query: &str,
+        parameters: &[u8],
+        caller: Option<String>,
+        run: F,
+    ) -> Result<T, GraphError>
+    where
+        F: FnOnce(&mut RwTxn) -> Result<T, GraphError>,
+    {
+        if !self.audit.is_enabled() {
+            return run(txn);
+        }
+        let since = self.wal.last_seq(txn)?;
+        let result = run(txn)?;
+        self.record_audit(txn, since, query, parameters, caller)?;
+        Ok(result)
+    }
+
+    /// Records a query that wrote to the transaction in the audit log, with the ids of
+    /// the elements it journaled in the write-ahead log after the seq `since`
+    pub fn record_audit(
+        &self,
+        txn: &mut RwTxn,
+        since: u64,
+        query: &str,
+        parameters: &[u8],
+        caller: Option<String>,
+    ) -> Result<(), GraphError> {
+        let mut affected = Vec::new();
+        let mut seen = HashSet::new();
+        let range = (Bound::Excluded(since), Bound::Unbounded);
+        for result in self.wal.pending_db.range(txn, &range)? {
+            let (_, bytes) = result?;
+            let id = match bincode::deserialize::<WalEntry>(bytes)?.op {
+                WalOp::PutNode(id, _)
+                | WalOp::PutEdge(id, _)
+                | WalOp::DropNode(id)
+                | WalOp::DropEdge(id)
+                | WalOp::TrashNode(id)
+                | WalOp::TrashEdge(id)
+                | WalOp::Restore(id) => id,
+                WalOp::Noop => continue,
+            };
+            // an element written several times is listed once
+            if seen.insert(id) {
+                affected.push(uuid::Uuid::from_u128(id).to_string());
+            }
+        }
+        self.audit.append(
+            txn,
+            AuditEntry {
+                seq: 0,
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                query: query.to_string(),
+                parameters: String::from_utf8_lossy(parameters).into_owned(),
+                caller,
+                affected,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Prunes the audit log with the configured retention and number of entries kept,
+    /// in a single write transaction. Returns the number of entries removed.
+    pub fn prune_audit_log(&self) -> Result<usize, GraphError> {
+        self.prune_audit_log_with(self.audit.retention(), self.audit.max_entries(), None)
+    }
+
+    /// Prunes the entries older than `retention` and beyond the `max_entries` most
+    /// recent ones from the audit log, keeping those younger than `kept_for` either way
+    pub fn prune_audit_log_with(
+        &self,
+        retention: Option<Duration>,
+        max_entries: Option<u64>,
+        kept_for: Option<Duration>,
+    ) -> Result<usize, GraphError> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let since = |age: Duration| now - age.as_millis() as i64;
+        let mut txn = self.graph_env.write_txn()?;
+        let pruned =
+            self.audit
+                .prune(&mut txn, retention.map(since), max_entries, kept_for.map(since))?;
+        txn.commit()?;
+        Ok(pruned)
+    }
+}
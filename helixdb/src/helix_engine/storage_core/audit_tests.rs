@@ -0,0 +1,203 @@
+use std::{sync::Arc, time::Duration};
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                },
+                tr_val::Traversable,
+            },
+        },
+        storage_core::{
+            audit::AuditEntry, encryption::is_encrypted, storage_core::HelixGraphStorage,
+            test_utils::open,
+        },
+        types::GraphError,
+    },
+    helix_storage::heed3::RwTxn,
+};
+
+fn audit_config() -> Config {
+    let mut config = Config::default();
+    config.audit.enabled = true;
+    config.wal.enabled = true;
+    config
+}
+
+fn add_user(storage: &Arc<HelixGraphStorage>, txn: &mut RwTxn) -> u128 {
+    G::new_mut(Arc::clone(storage), txn)
+        .add_n("user", None, None)
+        .collect_to_val()
+        .id()
+}
+
+/// Runs a query adding two users following each other, returning the ids of the users
+/// and the edge
+fn add_pair(storage: &Arc<HelixGraphStorage>, caller: Option<&str>) -> Vec<u128> {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let ids = storage
+        .audited(
+            &mut txn,
+            "addPair",
+            br#"{"name":"pair"}"#,
+            caller.map(str::to_string),
+            |txn| {
+                let from = add_user(storage, txn);
+                let to = add_user(storage, txn);
+                let edge = G::new_mut(Arc::clone(storage), txn)
+                    .add_e("follows", None, from, to, true, EdgeType::Node)
+                    .collect_to_val()
+                    .id();
+                Ok(vec![from, to, edge])
+            },
+        )
+        .unwrap();
+    txn.commit().unwrap();
+    ids
+}
+
+fn entries(storage: &Arc<HelixGraphStorage>) -> Vec<AuditEntry> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    storage.audit.list(&txn, 0, usize::MAX).unwrap()
+}
+
+#[test]
+fn test_query_is_recorded_with_affected_ids() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, audit_config());
+    let first = add_pair(&storage, Some("alice"));
+    let second = add_pair(&storage, None);
+
+    let entries = entries(&storage);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].seq, 1);
+    assert_eq!(entries[0].query, "addPair");
+    assert_eq!(entries[0].parameters, r#"{"name":"pair"}"#);
+    assert_eq!(entries[0].caller.as_deref(), Some("alice"));
+    let ids = |ids: &[u128]| {
+        ids.iter()
+            .map(|id| uuid::Uuid::from_u128(*id).to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(entries[0].affected, ids(&first));
+    assert_eq!(entries[1].seq, 2);
+    assert_eq!(entries[1].caller, None);
+    assert_eq!(entries[1].affected, ids(&second));
+}
+
+#[test]
+fn test_failed_query_is_not_recorded() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, audit_config());
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let result = storage.audited(&mut txn, "addUser", b"{}", None, |txn| {
+        add_user(&storage, txn);
+        Err::<(), _>(GraphError::New("failed".to_string()))
+    });
+    assert!(result.is_err());
+    txn.abort();
+    assert!(entries(&storage).is_empty());
+}
+
+#[test]
+fn test_nothing_is_recorded_when_disabled() {
+    let dir = TempDir::new().unwrap();
+    let mut config = audit_config();
+    config.audit.enabled = false;
+    let storage = open(&dir, config);
+    add_pair(&storage, None);
+    assert!(entries(&storage).is_empty());
+}
+
+#[test]
+fn test_prune() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, audit_config());
+    for _ in 0..5 {
+        add_pair(&storage, None);
+    }
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let page = storage.audit.list(&txn, 2, 2).unwrap();
+    assert_eq!(
+        page.iter().map(|entry| entry.seq).collect::<Vec<_>>(),
+        vec![3, 4]
+    );
+    drop(txn);
+
+    assert_eq!(storage.prune_audit_log().unwrap(), 0);
+    assert_eq!(storage.prune_audit_log_with(None, Some(3), None).unwrap(), 2);
+    assert_eq!(
+        entries(&storage)
+            .iter()
+            .map(|entry| entry.seq)
+            .collect::<Vec<_>>(),
+        vec![3, 4, 5]
+    );
+    // new entries follow the pruned ones
+    add_pair(&storage, None);
+    assert_eq!(entries(&storage).last().unwrap().seq, 6);
+
+    assert_eq!(
+        storage
+            .prune_audit_log_with(Some(Duration::from_secs(3600)), None, None)
+            .unwrap(),
+        0
+    );
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(
+        storage
+            .prune_audit_log_with(Some(Duration::ZERO), None, None)
+            .unwrap(),
+        4
+    );
+    assert!(entries(&storage).is_empty());
+    add_pair(&storage, None);
+    assert_eq!(entries(&storage)[0].seq, 7);
+}
+
+#[test]
+fn test_entries_encrypted_at_rest() {
+    let dir = TempDir::new().unwrap();
+    let mut config = audit_config();
+    config.encryption.enabled = true;
+    config.encryption.key_command = Some(
+        "echo 000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".to_string(),
+    );
+    let storage = open(&dir, config);
+    add_pair(&storage, Some("alice"));
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let stored = storage.audit.audit_db.get(&txn, &1).unwrap().unwrap();
+    assert!(is_encrypted(stored));
+    for plain in [&b"pair"[..], b"alice", b"addPair"] {
+        assert!(!stored.windows(plain.len()).any(|window| window == plain));
+    }
+    drop(txn);
+
+    let entries = entries(&storage);
+    assert_eq!(entries[0].parameters, r#"{"name":"pair"}"#);
+    assert_eq!(entries[0].caller.as_deref(), Some("alice"));
+    // entries younger than what is kept either way aren't pruned
+    assert_eq!(
+        storage
+            .prune_audit_log_with(Some(Duration::ZERO), Some(0), Some(Duration::from_secs(60)))
+            .unwrap(),
+        0
+    );
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(
+        storage
+            .prune_audit_log_with(Some(Duration::ZERO), None, None)
+            .unwrap(),
+        1
+    );
+}
@@ -0,0 +1,411 @@
+use crate::helix_storage::heed3::byteorder::BE;
+use crate::helix_storage::heed3::{types::*, Database, MdbError, PutFlags, RoTxn};
+use crate::{
+    helix_engine::{
+        bm25::bm25::{BM25Flatten, BM25},
+        cdc::cdc::{ChangeEvent, ChangeOp, ChangeTarget},
+        storage_core::{storage_core::HelixGraphStorage, wal::WalOp},
+        types::GraphError,
+        vector_core::{hnsw::HNSW, vector::HVector},
+    },
+    protocol::{
+        filterable::Filterable,
+        items::{Edge, Node},
+        value::Value,
+    },
+};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Loads nodes, edges and vectors in bulk, for imports too large to add item by item.
+///
+/// Items are buffered and written in batches, each sorted by key and written in one
+/// write transaction. Secondary indices, edge indices and the vector index aren't
+/// touched while loading: [`BulkLoader::finish`] builds them once every item is
+/// written, scanning the graph for the index entries on worker threads while the
+/// loaded vectors are linked into the vector index. Statistics are rebuilt at the end.
+///
+/// Until the load is finished, the loaded items aren't found through indices or by
+/// vector searches. Unique indices are still checked as nodes are added.
+pub struct BulkLoader<'a> {
+    storage: &'a HelixGraphStorage,
+    batch_size: usize,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    vectors: Vec<BulkVector>,
+    /// Vectors written but not linked into the vector index yet
+    unlinked: Vec<(String, u128)>,
+    /// Unique index => encoded values taken by nodes of the load
+    taken: HashMap<String, HashSet<Vec<u8>>>,
+    report: BulkLoadReport,
+}
+
+struct BulkVector {
+    id: u128,
+    label: String,
+    data: Vec<f64>,
+    properties: Option<Vec<(String, Value)>>,
+}
+
+/// What a bulk load wrote
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BulkLoadReport {
+    pub nodes: u64,
+    pub edges: u64,
+    pub vectors: u64,
+    /// Entries written to secondary and edge indices when the load finished
+    pub index_entries: u64,
+}
+
+/// Entries of an index, sorted by encoded value and then by id
+type IndexEntries = Vec<(Vec<u8>, u128)>;
+
+impl<'a> BulkLoader<'a> {
+    pub const DEFAULT_BATCH_SIZE: usize = 100_000;
+
+    pub fn new(storage: &'a HelixGraphStorage, batch_size: usize) -> Self {
+        Self {
+            storage,
+            batch_size: batch_size.max(1),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            vectors: Vec::new(),
+            unlinked: Vec::new(),
+            taken: HashMap::new(),
+            report: BulkLoadReport::default(),
+        }
+    }
+
+    /// Buffers a node, returning its id. The schema fills in defaults and timestamps the
+    /// node doesn't set, the way it does for nodes added by queries.
+    pub fn add_node(
+        &mut self,
+        label: &str,
+        properties: Option<Vec<(String, Value)>>,
+    ) -> Result<u128, GraphError> {
+        let mut node = Node {
+            id: self.storage.new_node_id(label),
+            label: label.to_string(),
+            properties: properties.map(|props| props.into_iter().collect()),
+            score: None,
+        };
+        let properties = node.properties.get_or_insert_with(HashMap::new);
+        self.storage
+            .schema_history
+            .fill_node(label, properties, true);
+        if properties.is_empty() {
+            node.properties = None;
+        }
+        self.storage
+            .schema_history
+            .check_node(label, node.properties.as_ref())?;
+        self.check_unique(&node)?;
+
+        let id = node.id;
+        self.nodes.push(node);
+        self.flush_if_full()?;
+        Ok(id)
+    }
+
+    /// Buffers an edge between two nodes, returning its id. The nodes may be nodes of the
+    /// load that aren't written yet.
+    pub fn add_edge(
+        &mut self,
+        label: &str,
+        properties: Option<Vec<(String, Value)>>,
+        from_node: u128,
+        to_node: u128,
+    ) -> Result<u128, GraphError> {
+        let edge = Edge {
+            id: self.storage.new_edge_id(label),
+            label: label.to_string(),
+            properties: properties.map(|props| props.into_iter().collect()),
+            from_node,
+            to_node,
+        };
+        let id = edge.id;
+        self.edges.push(edge);
+        self.flush_if_full()?;
+        Ok(id)
+    }
+
+    /// Buffers a vector, returning its id
+    pub fn add_vector(
+        &mut self,
+        label: &str,
+        data: &[f64],
+        properties: Option<Vec<(String, Value)>>,
+    ) -> Result<u128, GraphError> {
+        let id = self.storage.vectors_of(label).new_vector_id();
+        self.vectors.push(BulkVector {
+            id,
+            label: label.to_string(),
+            data: data.to_vec(),
+            properties,
+        });
+        self.flush_if_full()?;
+        Ok(id)
+    }
+
+    /// Fails with [`GraphError::UniqueViolation`] if a node of the graph or of the load
+    /// already has the value the node has for a unique index
+    fn check_unique(&mut self, node: &Node) -> Result<(), GraphError> {
+        for index in self.storage.unique_indices.iter() {
+            let Ok(value) = node.check_property(index) else {
+                continue;
+            };
+            let key = bincode::serialize(value)?;
+            let taken = self.taken.entry(index.clone()).or_default();
+            if taken.contains(&key) {
+                return Err(GraphError::UniqueViolation {
+                    index: index.to_string(),
+                    value: value.to_string(),
+                });
+            }
+            // the index only holds nodes from before the load until it is finished
+            let txn = self.storage.graph_env.read_txn()?;
+            self.storage.check_unique(&txn, index, value, &node.id)?;
+            taken.insert(key);
+        }
+        Ok(())
+    }
+
+    fn flush_if_full(&mut self) -> Result<(), GraphError> {
+        match self.nodes.len() + self.edges.len() + self.vectors.len() >= self.batch_size {
+            true => self.flush(),
+            false => Ok(()),
+        }
+    }
+
+    /// Writes the buffered items in one write transaction, nodes and edges in id order
+    /// and their adjacency entries in key order
+    pub fn flush(&mut self) -> Result<(), GraphError> {
+        if self.nodes.is_empty() && self.edges.is_empty() && self.vectors.is_empty() {
+            return Ok(());
+        }
+        let storage = self.storage;
+        let mut txn = storage.graph_env.write_txn()?;
+
+        self.nodes.sort_unstable_by_key(|node| node.id);
+        for node in self.nodes.iter() {
+            let bytes = storage.encode_node(&mut txn, node)?;
+            storage.nodes_db.put_with_flags(
+                &mut txn,
+                storage.new_item_flags(),
+                &node.id,
+                &bytes,
+            )?;
+            storage.node_written(&node.id);
+            storage.record_version(&mut txn, node, chrono::Utc::now().timestamp_millis())?;
+            if let Some(properties) = &node.properties {
+                let mut data = properties.flatten_bm25();
+                data.push_str(&node.label);
+                storage.bm25.insert_doc(&mut txn, node.id, &data)?;
+            }
+            storage.cdc.record(
+                &mut txn,
+                ChangeEvent::new(ChangeOp::Insert, ChangeTarget::Node, node.id, &node.label),
+            )?;
+            storage.wal.log(&mut txn, || WalOp::put_node(&storage.encryption, node))?;
+        }
+
+        for vector in self.vectors.drain(..) {
+            storage.vectors_of(&vector.label).store(
+                &mut txn,
+                vector.id,
+                &vector.data,
+                &vector.label,
+                vector.properties,
+            )?;
+            storage.cdc.record(
+                &mut txn,
+                ChangeEvent::new(
+                    ChangeOp::Insert,
+                    ChangeTarget::Vector,
+                    vector.id,
+                    &vector.label,
+                ),
+            )?;
+            self.unlinked.push((vector.label, vector.id));
+            self.report.vectors += 1;
+        }
+
+        self.edges.sort_unstable_by_key(|edge| edge.id);
+        let mut out_edges = Vec::with_capacity(self.edges.len());
+        let mut in_edges = Vec::with_capacity(self.edges.len());
+        for edge in self.edges.iter() {
+            let bytes = storage.encode_edge(&mut txn, edge)?;
+            storage.edges_db.put_with_flags(
+                &mut txn,
+                storage.new_item_flags(),
+                HelixGraphStorage::edge_key(&edge.id),
+                &bytes,
+            )?;
+            storage.cdc.record(
+                &mut txn,
+                ChangeEvent::new(ChangeOp::Insert, ChangeTarget::Edge, edge.id, &edge.label),
+            )?;
+            storage.wal.log(&mut txn, || WalOp::put_edge(&storage.encryption, edge))?;
+
+            let label_hash = storage.dictionary.intern_label_key(&mut txn, &edge.label)?;
+            out_edges.push((
+                HelixGraphStorage::out_edge_key(&edge.from_node, &label_hash),
+                HelixGraphStorage::pack_edge_data(&edge.to_node, &edge.id),
+            ));
+            in_edges.push((
+                HelixGraphStorage::in_edge_key(&edge.to_node, &label_hash),
+                HelixGraphStorage::pack_edge_data(&edge.from_node, &edge.id),
+            ));
+        }
+        out_edges.sort_unstable();
+        for (key, data) in out_edges.iter() {
+            storage.out_edges_db.put(&mut txn, key, data)?;
+        }
+        in_edges.sort_unstable();
+        for (key, data) in in_edges.iter() {
+            storage.in_edges_db.put(&mut txn, key, data)?;
+        }
+
+        txn.commit()?;
+        self.report.nodes += self.nodes.len() as u64;
+        self.report.edges += self.edges.len() as u64;
+        self.nodes.clear();
+        self.edges.clear();
+        Ok(())
+    }
+
+    /// Writes what is left of the load and builds the indices it deferred.
+    ///
+    /// Every node and edge of the graph is indexed, not only the loaded ones, the way
+    /// the ingestion endpoint indexes every property with an index.
+    pub fn finish(mut self) -> Result<BulkLoadReport, GraphError> {
+        self.flush()?;
+        let storage = self.storage;
+        let batch_size = self.batch_size;
+        let unlinked = std::mem::take(&mut self.unlinked);
+
+        let (linked, entries) = rayon::join(
+            || link_vectors(storage, &unlinked, batch_size),
+            || {
+                let nodes = storage.secondary_indices.par_iter().map(|(index, db)| {
+                    Ok((
+                        db,
+                        index_entries(storage, |txn| node_entries(storage, txn, index))?,
+                    ))
+                });
+                let edges = storage
+                    .edge_secondary_indices
+                    .par_iter()
+                    .map(|(index, db)| {
+                        Ok((
+                            db,
+                            index_entries(storage, |txn| edge_entries(storage, txn, index))?,
+                        ))
+                    });
+                nodes.chain(edges).collect::<Result<Vec<_>, GraphError>>()
+            },
+        );
+        linked?;
+        for (db, entries) in entries? {
+            self.report.index_entries += put_entries(storage, db, &entries, batch_size)?;
+        }
+
+        if storage.stats.is_enabled() {
+            storage.rebuild_stats()?;
+        }
+        Ok(self.report)
+    }
+}
+
+/// Links written vectors into their vector index, a batch per write transaction
+fn link_vectors(
+    storage: &HelixGraphStorage,
+    vectors: &[(String, u128)],
+    batch_size: usize,
+) -> Result<(), GraphError> {
+    for batch in vectors.chunks(batch_size) {
+        let mut txn = storage.graph_env.write_txn()?;
+        for (label, id) in batch {
+            let index = storage.vectors_of(label);
+            let vector = index.get_vector(&txn, *id, 0, true)?;
+            index.link::<fn(&HVector, &RoTxn) -> bool>(&mut txn, vector)?;
+        }
+        txn.commit()?;
+    }
+    Ok(())
+}
+
+/// Collects the entries of an index in a read transaction of the calling thread, read
+/// transactions can't be shared between threads
+fn index_entries<F>(storage: &HelixGraphStorage, collect: F) -> Result<IndexEntries, GraphError>
+where
+    F: FnOnce(&RoTxn) -> Result<IndexEntries, GraphError>,
+{
+    let txn = storage.graph_env.read_txn()?;
+    let mut entries = collect(&txn)?;
+    entries.sort_unstable();
+    entries.dedup();
+    Ok(entries)
+}
+
+fn node_entries(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    index: &str,
+) -> Result<IndexEntries, GraphError> {
+    let mut entries = Vec::new();
+    for node in storage.get_all_nodes(txn)? {
+        let node = node?;
+        if let Ok(value) = node.check_property(index) {
+            entries.push((bincode::serialize(value)?, node.id));
+        }
+    }
+    Ok(entries)
+}
+
+fn edge_entries(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    index: &str,
+) -> Result<IndexEntries, GraphError> {
+    let mut entries = Vec::new();
+    for edge in storage.get_all_edges(txn)? {
+        let edge = edge?;
+        if let Some(value) = edge.properties.as_ref().and_then(|props| props.get(index)) {
+            entries.push((bincode::serialize(value)?, edge.id));
+        }
+    }
+    Ok(entries)
+}
+
+/// Writes sorted entries to an index, a batch per write transaction, returning how many
+/// weren't in it already. Entries are appended if the index is empty.
+fn put_entries(
+    storage: &HelixGraphStorage,
+    db: &Database<Bytes, U128<BE>>,
+    entries: &IndexEntries,
+    batch_size: usize,
+) -> Result<u64, GraphError> {
+    let txn = storage.graph_env.read_txn()?;
+    let append = db.is_empty(&txn)?;
+    drop(txn);
+    let mut written = 0;
+    for (i, batch) in entries.chunks(batch_size).enumerate() {
+        let mut txn = storage.graph_env.write_txn()?;
+        for (j, (key, id)) in batch.iter().enumerate() {
+            let previous = (i * batch_size + j).checked_sub(1).map(|k| &entries[k].0);
+            let flags = match (append, previous == Some(key)) {
+                (false, _) => PutFlags::NO_DUP_DATA,
+                (true, false) => PutFlags::APPEND,
+                (true, true) => PutFlags::APPEND_DUP,
+            };
+            match db.put_with_flags(&mut txn, flags, key, id) {
+                Ok(()) => written += 1,
+                Err(crate::helix_storage::heed3::Error::Mdb(MdbError::KeyExist)) => {}
+                Err(e) => return Err(GraphError::from(e)),
+            }
+        }
+        txn.commit()?;
+    }
+    Ok(written)
+}
@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::helix_storage::heed3::RoTxn;
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                in_::in_::InAdapter,
+                out::out::OutAdapter,
+                source::{
+                    add_e::EdgeType, add_n::AddNAdapter, n_from_id::NFromIdAdapter,
+                    n_from_index::NFromIndexAdapter,
+                },
+                tr_val::Traversable,
+            },
+        },
+        storage_core::{
+            bulk_load::{BulkLoadReport, BulkLoader},
+            storage_core::HelixGraphStorage,
+            test_utils,
+        },
+        types::GraphError,
+        vector_core::{hnsw::HNSW, vector::HVector},
+    },
+    props,
+    protocol::value::Value,
+};
+
+fn bulk_config() -> Config {
+    let mut config = Config::default();
+    config.stats = true;
+    config.graph_config.secondary_indices = Some(vec!["country".to_string()]);
+    config.graph_config.unique_indices = Some(vec!["email".to_string()]);
+    config.graph_config.edge_secondary_indices = Some(vec!["since".to_string()]);
+    config
+}
+
+fn open(dir: &TempDir) -> Arc<HelixGraphStorage> {
+    test_utils::open(dir, bulk_config())
+}
+
+fn people_in(storage: &Arc<HelixGraphStorage>, country: &str) -> usize {
+    let txn = storage.graph_env.read_txn().unwrap();
+    let country = country.to_string();
+    G::new(Arc::clone(storage), &txn)
+        .n_from_index("country", &country)
+        .filter_map(|node| node.ok())
+        .count()
+}
+
+#[test]
+fn test_indices_are_built_when_the_load_finishes() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir);
+
+    let mut loader = BulkLoader::new(&storage, 4);
+    let people = (0..10)
+        .map(|i| {
+            let country = if i % 2 == 0 { "NL" } else { "DE" };
+            loader
+                .add_node("person", Some(props! { "country" => country }))
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    for (i, person) in people.iter().enumerate().skip(1) {
+        loader
+            .add_edge(
+                "follows",
+                Some(props! { "since" => 2000 + i as i64 }),
+                people[0],
+                *person,
+            )
+            .unwrap();
+    }
+    let mut vectors = [[1.0, 0.0], [0.0, 1.0], [0.7, 0.7]]
+        .iter()
+        .map(|data| loader.add_vector("embedding", data, None).unwrap())
+        .collect::<Vec<_>>();
+
+    // the full batches are written, but not indexed
+    {
+        let txn = storage.graph_env.read_txn().unwrap();
+        assert_eq!(storage.nodes_db.len(&txn).unwrap(), 10);
+        assert!(storage.secondary_indices["country"].is_empty(&txn).unwrap());
+        assert!(storage
+            .vectors
+            .search::<fn(&HVector, &RoTxn) -> bool>(&txn, &[1.0, 0.0], 1, None, false)
+            .is_err());
+    }
+
+    let report = loader.finish().unwrap();
+    assert_eq!(
+        report,
+        BulkLoadReport {
+            nodes: 10,
+            edges: 9,
+            vectors: 3,
+            index_entries: 19,
+        }
+    );
+    assert_eq!(people_in(&storage, "NL"), 5);
+    assert_eq!(people_in(&storage, "DE"), 5);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let followed = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&people[0])
+        .out("follows", &EdgeType::Node)
+        .filter_map(|node| node.ok())
+        .count();
+    assert_eq!(followed, 9);
+    let followers = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&people[9])
+        .in_("follows", &EdgeType::Node)
+        .filter_map(|node| node.ok())
+        .collect::<Vec<_>>();
+    assert_eq!(followers.len(), 1);
+    assert_eq!(followers[0].id(), people[0]);
+    let since = bincode::serialize(&Value::I64(2009)).unwrap();
+    assert_eq!(
+        storage.edge_secondary_indices["since"]
+            .get_duplicates(&txn, &since)
+            .unwrap()
+            .unwrap()
+            .count(),
+        1
+    );
+
+    let mut found = storage
+        .vectors
+        .search::<fn(&HVector, &RoTxn) -> bool>(&txn, &[0.0, 1.0], 3, None, false)
+        .unwrap()
+        .iter()
+        .map(HVector::get_id)
+        .collect::<Vec<_>>();
+    found.sort_unstable();
+    vectors.sort_unstable();
+    assert_eq!(found, vectors);
+
+    assert_eq!(storage.stats.node_count(&txn, "person").unwrap(), 10);
+    assert_eq!(storage.stats.edge_count(&txn, "follows").unwrap(), 9);
+    assert_eq!(
+        storage.stats.index_selectivity(&txn, "country").unwrap(),
+        Some(0.2)
+    );
+}
+
+#[test]
+fn test_load_into_indexed_graph() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n(
+            "person",
+            Some(props! { "country" => "NL" }),
+            Some(&["country"]),
+        )
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let mut loader = BulkLoader::new(&storage, BulkLoader::DEFAULT_BATCH_SIZE);
+    for country in ["NL", "DE", "NL"] {
+        loader
+            .add_node("person", Some(props! { "country" => country }))
+            .unwrap();
+    }
+    // only the entries of the loaded nodes are new
+    assert_eq!(loader.finish().unwrap().index_entries, 3);
+    assert_eq!(people_in(&storage, "NL"), 3);
+    assert_eq!(people_in(&storage, "DE"), 1);
+}
+
+#[test]
+fn test_unique_values_are_checked_while_loading() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n(
+            "person",
+            Some(props! { "email" => "ada@example.com" }),
+            Some(&["email"]),
+        )
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let mut loader = BulkLoader::new(&storage, 2);
+    let add = |loader: &mut BulkLoader, email: &str| {
+        loader.add_node("person", Some(props! { "email" => email }))
+    };
+    assert!(matches!(
+        add(&mut loader, "ada@example.com"),
+        Err(GraphError::UniqueViolation { .. })
+    ));
+    add(&mut loader, "grace@example.com").unwrap();
+    assert!(matches!(
+        add(&mut loader, "grace@example.com"),
+        Err(GraphError::UniqueViolation { .. })
+    ));
+    let report = loader.finish().unwrap();
+    assert_eq!((report.nodes, report.index_entries), (1, 1));
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.nodes_db.len(&txn).unwrap(), 2);
+    assert_eq!(storage.secondary_indices["email"].len(&txn).unwrap(), 2);
+}
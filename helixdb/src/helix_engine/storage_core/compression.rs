@@ -0,0 +1,64 @@
+use crate::helix_engine::{graph_core::config::CompressionConfig, types::GraphError};
+use std::borrow::Cow;
+
+/// Magic number every zstd frame starts with.
+///
+/// Uncompressed nodes and edges start with the length of their label as a little endian
+/// u64, which never matches it, so compressed values can be told apart without a header
+/// and data written before compression was enabled stays readable.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Transparent compression of encoded nodes and edges above a size threshold
+#[derive(Debug, Clone, Copy)]
+pub struct Compression {
+    threshold: Option<usize>,
+    level: i32,
+}
+
+impl Compression {
+    pub const DEFAULT_LEVEL: i32 = 3;
+
+    pub fn new(config: &CompressionConfig) -> Compression {
+        Compression {
+            threshold: config.threshold_bytes,
+            level: config.level.unwrap_or(Self::DEFAULT_LEVEL),
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.threshold.is_some()
+    }
+
+    /// Compresses an encoded item if it is larger than the threshold.
+    ///
+    /// The item is kept as is if compressing doesn't make it smaller.
+    pub fn compress(&self, bytes: Vec<u8>) -> Result<Vec<u8>, GraphError> {
+        match self.threshold {
+            Some(threshold) if bytes.len() > threshold => {
+                let compressed = zstd::bulk::compress(&bytes, self.level)?;
+                match compressed.len() < bytes.len() {
+                    true => Ok(compressed),
+                    false => Ok(bytes),
+                }
+            }
+            _ => Ok(bytes),
+        }
+    }
+}
+
+#[inline(always)]
+pub fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ZSTD_MAGIC)
+}
+
+/// Returns the encoded item, decompressing it first if it was stored compressed
+pub fn decompress<'a>(bytes: impl Into<Cow<'a, [u8]>>) -> Result<Cow<'a, [u8]>, GraphError> {
+    let bytes = bytes.into();
+    if !is_compressed(&bytes) {
+        return Ok(bytes);
+    }
+    zstd::stream::decode_all(&bytes[..])
+        .map(Cow::Owned)
+        .map_err(|e| GraphError::ConversionError(format!("Error decompressing item: {}", e)))
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                    n_from_id::NFromIdAdapter,
+                },
+                tr_val::Traversable,
+                util::update::UpdateAdapter,
+            },
+        },
+        storage_core::{
+            compression::is_compressed,
+            storage_core::{HelixGraphStorage, MIGRATION_BATCH_SIZE},
+            test_utils::open,
+            storage_methods::StorageMethods,
+        },
+    },
+    props,
+    protocol::value::Value,
+};
+
+fn compression_config(threshold_bytes: Option<usize>, migrate: bool) -> Config {
+    let mut config = Config::default();
+    config.compression.threshold_bytes = threshold_bytes;
+    config.compression.migrate = migrate;
+    config
+}
+
+fn add_document(storage: &Arc<HelixGraphStorage>, text: &str) -> u128 {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let node = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n("document", Some(props! { "text" => text }), None)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+    node.first().unwrap().id()
+}
+
+fn node_is_compressed(storage: &HelixGraphStorage, id: &u128) -> bool {
+    let txn = storage.graph_env.read_txn().unwrap();
+    is_compressed(storage.nodes_db.get(&txn, id).unwrap().unwrap())
+}
+
+#[test]
+fn test_large_nodes_are_compressed() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, compression_config(Some(256), false));
+    let text = "lorem ipsum dolor sit amet ".repeat(100);
+
+    let small = add_document(&storage, "short");
+    let large = add_document(&storage, &text);
+    assert!(!node_is_compressed(&storage, &small));
+    assert!(node_is_compressed(&storage, &large));
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let node = storage.get_node(&txn, &large).unwrap();
+    assert_eq!(
+        node.properties.unwrap().get("text"),
+        Some(&Value::String(text))
+    );
+}
+
+#[test]
+fn test_updates_and_edges_are_compressed() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, compression_config(Some(256), false));
+    let text = "lorem ipsum dolor sit amet ".repeat(100);
+
+    let from = add_document(&storage, "short");
+    let to = add_document(&storage, "short");
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let edge = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e(
+            "cites",
+            Some(props! { "context" => text.clone() }),
+            from,
+            to,
+            false,
+            EdgeType::Node,
+        )
+        .collect_to::<Vec<_>>();
+    let edge_id = edge.first().unwrap().id();
+    let update_tr = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&from)
+        .collect_to::<Vec<_>>();
+    G::new_mut_from(Arc::clone(&storage), &mut txn, update_tr)
+        .update(Some(props! { "text" => text.clone() }))
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    assert!(node_is_compressed(&storage, &from));
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(is_compressed(storage.edges_db.get(&txn, &edge_id).unwrap().unwrap()));
+    let edge = storage.get_edge(&txn, &edge_id).unwrap();
+    assert_eq!(
+        edge.properties.unwrap().get("context"),
+        Some(&Value::String(text))
+    );
+}
+
+#[test]
+fn test_migrate_existing_data() {
+    let dir = TempDir::new().unwrap();
+    let text = "lorem ipsum dolor sit amet ".repeat(100);
+
+    let storage = open(&dir, compression_config(None, false));
+    let id = add_document(&storage, &text);
+    assert!(!node_is_compressed(&storage, &id));
+    drop(storage);
+
+    // uncompressed data stays readable without migrating
+    let storage = open(&dir, compression_config(Some(256), false));
+    assert!(!node_is_compressed(&storage, &id));
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.get_node(&txn, &id).is_ok());
+    drop(txn);
+    drop(storage);
+
+    let storage = open(&dir, compression_config(Some(256), true));
+    assert!(node_is_compressed(&storage, &id));
+    drop(storage);
+
+    // and back again once compression is disabled
+    let storage = open(&dir, compression_config(None, true));
+    assert!(!node_is_compressed(&storage, &id));
+    let txn = storage.graph_env.read_txn().unwrap();
+    let node = storage.get_node(&txn, &id).unwrap();
+    assert_eq!(
+        node.properties.unwrap().get("text"),
+        Some(&Value::String(text))
+    );
+}
+
+#[test]
+fn test_migrate_in_batches() {
+    let dir = TempDir::new().unwrap();
+    let text = "lorem ipsum dolor sit amet ".repeat(100);
+    let count = MIGRATION_BATCH_SIZE * 2 + 1;
+
+    let storage = open(&dir, compression_config(None, false));
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let ids = (0..count)
+        .map(|_| {
+            G::new_mut(Arc::clone(&storage), &mut txn)
+                .add_n("document", Some(props! { "text" => text.as_str() }), None)
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    txn.commit().unwrap();
+    drop(storage);
+
+    let storage = open(&dir, compression_config(Some(256), false));
+    assert_eq!(storage.migrate_compression().unwrap(), count);
+    assert!(ids.iter().all(|id| node_is_compressed(&storage, id)));
+}
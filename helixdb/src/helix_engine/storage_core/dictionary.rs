@@ -0,0 +1,237 @@
+//! Dictionary of the labels and property names stored in the graph.
+//!
+//! Every node and edge otherwise repeats its label and the names of its properties as
+//! strings. With interning enabled they are stored as the `u32` id the dictionary gives
+//! each distinct string, and the adjacency keys of a database created with interning
+//! hold the id of the edge label instead of its hash, so labels never collide in them.
+//!
+//! Ids are handed out in memory and written to the dictionary table in the transaction
+//! of the item that needs them. A string keeps its id for as long as the storage is
+//! open even if the transaction giving it one is aborted, so ids can be resolved
+//! without a transaction and never change once items were written with them.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    helix_engine::types::GraphError,
+    helix_storage::heed3::{
+        byteorder::BE,
+        types::{Str, U32},
+        Database, Env, RwTxn,
+    },
+    protocol::{
+        enums,
+        items::{Edge, Node},
+        label_hash::hash_label,
+        value::Value,
+    },
+};
+
+const DB_DICTIONARY: &str = "dictionary"; // id -> label or property name
+
+/// Marks items encoded with interned strings.
+///
+/// Like compressed ones, they are told apart from items encoded with strings by their
+/// first bytes, which would be a label longer than 4GB there.
+const INTERNED_MAGIC: [u8; 4] = [0x48, 0x4C, 0x58, 0xFF];
+
+/// Never given to a string. Its entry records that the adjacency keys of the database
+/// hold interned labels, and labels that were never interned are keyed with it, which
+/// no adjacency key holds.
+const INTERNED_KEYS: u32 = u32::MAX;
+
+#[derive(Default)]
+struct Interned {
+    ids: HashMap<Arc<str>, u32>,
+    /// Indexed by id, `None` for the ids of aborted transactions of a previous run
+    strings: Vec<Option<Arc<str>>>,
+}
+
+/// Ids of the labels and property names of the graph
+pub struct Dictionary {
+    pub dictionary_db: Database<U32<BE>, Str>,
+    interned: RwLock<Interned>,
+    /// Whether new items are written with interned strings
+    enabled: bool,
+    /// Whether the adjacency keys hold interned labels rather than label hashes, which
+    /// is decided when the database is created
+    keys: bool,
+}
+
+impl Dictionary {
+    /// Opens the dictionary, interning the adjacency keys if the graph is still empty
+    pub fn new(
+        graph_env: &Env,
+        wtxn: &mut RwTxn,
+        enabled: bool,
+        empty: bool,
+    ) -> Result<Dictionary, GraphError> {
+        let dictionary_db: Database<U32<BE>, Str> = graph_env
+            .database_options()
+            .types::<U32<BE>, Str>()
+            .name(DB_DICTIONARY)
+            .create(wtxn)?;
+
+        let mut interned = Interned::default();
+        let mut keys = false;
+        for result in dictionary_db.iter(wtxn)? {
+            let (id, string) = result?;
+            if id == INTERNED_KEYS {
+                keys = true;
+                continue;
+            }
+            let string: Arc<str> = Arc::from(string);
+            let index = id as usize;
+            if interned.strings.len() <= index {
+                interned.strings.resize(index + 1, None);
+            }
+            interned.strings[index] = Some(Arc::clone(&string));
+            interned.ids.insert(string, id);
+        }
+        if enabled && empty && !keys {
+            dictionary_db.put(wtxn, &INTERNED_KEYS, "")?;
+            keys = true;
+        }
+
+        Ok(Dictionary {
+            dictionary_db,
+            interned: RwLock::new(interned),
+            enabled,
+            keys,
+        })
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether the adjacency keys hold interned labels
+    #[inline(always)]
+    pub fn interns_keys(&self) -> bool {
+        self.keys
+    }
+
+    /// Id of the string, `None` if it was never interned
+    #[inline]
+    pub fn id(&self, string: &str) -> Option<u32> {
+        self.interned.read().unwrap().ids.get(string).copied()
+    }
+
+    /// The string with the id
+    pub fn string(&self, id: u32) -> Result<Arc<str>, GraphError> {
+        self.interned
+            .read()
+            .unwrap()
+            .strings
+            .get(id as usize)
+            .and_then(|string| string.clone())
+            .ok_or_else(|| {
+                GraphError::ConversionError(format!("No string was interned with id {}", id))
+            })
+    }
+
+    /// Id of the string, giving it one if it has none yet and recording it in the
+    /// transaction
+    pub fn intern(&self, txn: &mut RwTxn, string: &str) -> Result<u32, GraphError> {
+        let id = match self.id(string) {
+            Some(id) => id,
+            None => {
+                let mut interned = self.interned.write().unwrap();
+                match interned.ids.get(string) {
+                    Some(id) => *id,
+                    None => {
+                        let id = interned.strings.len() as u32;
+                        if id == INTERNED_KEYS {
+                            return Err(GraphError::New(
+                                "The dictionary is full, no more strings can be interned"
+                                    .to_string(),
+                            ));
+                        }
+                        let string: Arc<str> = Arc::from(string);
+                        interned.strings.push(Some(Arc::clone(&string)));
+                        interned.ids.insert(string, id);
+                        id
+                    }
+                }
+            }
+        };
+        // the transaction first giving the id may have been aborted
+        if self.dictionary_db.get(txn, &id)?.is_none() {
+            self.dictionary_db.put(txn, &id, string)?;
+        }
+        Ok(id)
+    }
+
+    /// The label as held by adjacency keys, for reading them
+    #[inline]
+    pub fn label_key(&self, label: &str) -> [u8; 4] {
+        match self.keys {
+            true => self.id(label).unwrap_or(INTERNED_KEYS).to_be_bytes(),
+            false => hash_label(label, None),
+        }
+    }
+
+    /// The label as held by adjacency keys, interning it for writing them
+    #[inline]
+    pub fn intern_label_key(&self, txn: &mut RwTxn, label: &str) -> Result<[u8; 4], GraphError> {
+        match self.keys {
+            true => Ok(self.intern(txn, label)?.to_be_bytes()),
+            false => Ok(hash_label(label, None)),
+        }
+    }
+
+    /// Encodes a node with its label and property names interned, enum fields as the
+    /// index of their variant
+    pub fn encode_node(&self, txn: &mut RwTxn, node: &Node) -> Result<Vec<u8>, GraphError> {
+        let mut bytes = INTERNED_MAGIC.to_vec();
+        bytes.extend_from_slice(&self.intern(txn, &node.label)?.to_le_bytes());
+        self.encode_properties(txn, &node.label, &node.properties, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Encodes an edge with its label and property names interned, enum fields as the
+    /// index of their variant
+    pub fn encode_edge(&self, txn: &mut RwTxn, edge: &Edge) -> Result<Vec<u8>, GraphError> {
+        let mut bytes = INTERNED_MAGIC.to_vec();
+        bytes.extend_from_slice(&self.intern(txn, &edge.label)?.to_le_bytes());
+        bytes.extend_from_slice(&edge.from_node.to_le_bytes());
+        bytes.extend_from_slice(&edge.to_node.to_le_bytes());
+        self.encode_properties(txn, &edge.label, &edge.properties, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Appends the properties laid out like a bincode encoded map, their names as ids
+    fn encode_properties(
+        &self,
+        txn: &mut RwTxn,
+        label: &str,
+        properties: &Option<HashMap<String, Value>>,
+        bytes: &mut Vec<u8>,
+    ) -> Result<(), GraphError> {
+        let Some(properties) = properties else {
+            bytes.push(0);
+            return Ok(());
+        };
+        let encoded = enums::encode(label, properties);
+        let properties = encoded.as_ref().unwrap_or(properties);
+        bytes.push(1);
+        bytes.extend_from_slice(&(properties.len() as u64).to_le_bytes());
+        for (name, value) in properties {
+            bytes.extend_from_slice(&self.intern(txn, name)?.to_le_bytes());
+            bincode::serialize_into(&mut *bytes, value).map_err(|e| {
+                GraphError::ConversionError(format!("Error serializing property: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the decompressed item was encoded with interned strings
+#[inline(always)]
+pub fn is_interned(bytes: &[u8]) -> bool {
+    bytes.starts_with(&INTERNED_MAGIC)
+}
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                out::out::OutAdapter,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                    n_from_id::NFromIdAdapter,
+                    n_from_type::NFromTypeAdapter,
+                },
+                tr_val::{Traversable, TraversalVal},
+            },
+        },
+        storage_core::{
+            compression::decompress, dictionary::is_interned, storage_core::HelixGraphStorage,
+            storage_methods::StorageMethods, test_utils::open,
+        },
+    },
+    props,
+    protocol::{filterable::Filterable, value::Value},
+};
+
+fn intern_config(intern_strings: bool) -> Config {
+    let mut config = Config::default();
+    config.intern_strings = intern_strings;
+    config
+}
+
+/// Adds two people knowing each other, returning the ids of the people and the edge
+fn add_people(storage: &Arc<HelixGraphStorage>) -> (u128, u128, u128) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let alice = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n(
+            "person",
+            Some(props! { "name" => "alice", "age" => 30 }),
+            None,
+        )
+        .collect_to_val()
+        .id();
+    let bob = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n(
+            "person",
+            Some(props! { "name" => "bob", "age" => 25 }),
+            None,
+        )
+        .collect_to_val()
+        .id();
+    let knows = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_e(
+            "knows",
+            Some(props! { "since" => 2020 }),
+            alice,
+            bob,
+            false,
+            EdgeType::Node,
+        )
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+    (alice, bob, knows)
+}
+
+fn friends_of(storage: &Arc<HelixGraphStorage>, id: u128) -> Vec<u128> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    G::new(Arc::clone(storage), &txn)
+        .n_from_id(&id)
+        .out("knows", &EdgeType::Node)
+        .collect_to::<Vec<_>>()
+        .iter()
+        .map(|node| node.id())
+        .collect()
+}
+
+fn node_is_interned(storage: &HelixGraphStorage, id: &u128) -> bool {
+    let txn = storage.graph_env.read_txn().unwrap();
+    is_interned(&decompress(storage.nodes_db.get(&txn, id).unwrap().unwrap()).unwrap())
+}
+
+#[test]
+fn test_interned_items_read_back() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, intern_config(true));
+    let (alice, bob, knows) = add_people(&storage);
+
+    assert!(storage.dictionary.interns_keys());
+    assert!(node_is_interned(&storage, &alice));
+    let txn = storage.graph_env.read_txn().unwrap();
+    let node = storage.get_node(&txn, &alice).unwrap();
+    assert_eq!(node.label, "person");
+    assert_eq!(*node.check_property("name").unwrap(), Value::from("alice"));
+    assert_eq!(*node.check_property("age").unwrap(), Value::from(30));
+    let edge = storage.get_edge(&txn, &knows).unwrap();
+    assert_eq!(
+        (edge.label.as_str(), edge.from_node, edge.to_node),
+        ("knows", alice, bob)
+    );
+    assert_eq!(*edge.check_property("since").unwrap(), Value::from(2020));
+
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert_eq!(people.len(), 2);
+    let adults = G::new(Arc::clone(&storage), &txn)
+        .n_from_type_where("person", |val, _| {
+            Ok(val.check_property("age").map_or(false, |v| *v >= 30))
+        })
+        .collect_to::<Vec<_>>();
+    assert_eq!(
+        adults.iter().map(|n| n.id()).collect::<Vec<_>>(),
+        vec![alice]
+    );
+    drop(txn);
+
+    // the adjacency keys hold the id of the label
+    assert_eq!(friends_of(&storage, alice), vec![bob]);
+    let txn = storage.graph_env.read_txn().unwrap();
+    let key = HelixGraphStorage::out_edge_key(
+        &alice,
+        &storage.dictionary.id("knows").unwrap().to_be_bytes(),
+    );
+    assert!(storage.out_edges_db.get(&txn, &key).unwrap().is_some());
+}
+
+#[test]
+fn test_interned_items_are_smaller() {
+    let plain_dir = TempDir::new().unwrap();
+    let plain = open(&plain_dir, intern_config(false));
+    let interned_dir = TempDir::new().unwrap();
+    let interned = open(&interned_dir, intern_config(true));
+
+    let size = |storage: &Arc<HelixGraphStorage>| {
+        let (alice, _, _) = add_people(storage);
+        let txn = storage.graph_env.read_txn().unwrap();
+        storage.nodes_db.get(&txn, &alice).unwrap().unwrap().len()
+    };
+    assert!(size(&interned) < size(&plain));
+    assert!(!plain.dictionary.interns_keys());
+}
+
+#[test]
+fn test_dictionary_survives_restart() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, intern_config(true));
+    let (alice, bob, _) = add_people(&storage);
+    let person = storage.dictionary.id("person").unwrap();
+
+    // ids given in aborted transactions are never stored
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("robot", Some(props! { "model" => "x" }), None)
+        .collect_to_val();
+    txn.abort();
+    drop(storage);
+
+    let storage = open(&dir, intern_config(true));
+    assert_eq!(storage.dictionary.id("person"), Some(person));
+    assert_eq!(storage.dictionary.id("robot"), None);
+    assert_eq!(friends_of(&storage, alice), vec![bob]);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let robot = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("robot", Some(props! { "model" => "x" }), None)
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+    let txn = storage.graph_env.read_txn().unwrap();
+    let node = storage.get_node(&txn, &robot).unwrap();
+    assert_eq!(node.label, "robot");
+    assert_eq!(*node.check_property("model").unwrap(), Value::from("x"));
+    assert_eq!(storage.get_node(&txn, &alice).unwrap().label, "person");
+}
+
+#[test]
+fn test_existing_database_keeps_hashed_keys() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, intern_config(false));
+    let (alice, bob, _) = add_people(&storage);
+    drop(storage);
+
+    // items are interned from now on but the adjacency keys stay hashed
+    let storage = open(&dir, intern_config(true));
+    assert!(!storage.dictionary.interns_keys());
+    assert!(!node_is_interned(&storage, &alice));
+    let (carol, dave, _) = add_people(&storage);
+    assert!(node_is_interned(&storage, &carol));
+    assert_eq!(friends_of(&storage, alice), vec![bob]);
+    assert_eq!(friends_of(&storage, carol), vec![dave]);
+
+    // and both stay readable once interning is disabled again
+    drop(storage);
+    let storage = open(&dir, intern_config(false));
+    let txn = storage.graph_env.read_txn().unwrap();
+    for id in [alice, carol] {
+        assert_eq!(storage.get_node(&txn, &id).unwrap().label, "person");
+    }
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert!(people
+        .iter()
+        .all(|person| matches!(person, TraversalVal::Node(node) if node.properties.is_some())));
+    assert_eq!(people.len(), 4);
+}
+
+#[test]
+fn test_interned_items_are_compressed() {
+    let dir = TempDir::new().unwrap();
+    let mut config = intern_config(true);
+    config.compression.threshold_bytes = Some(64);
+    let storage = open(&dir, config);
+    let text = "lorem ipsum dolor sit amet ".repeat(100);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let id = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("document", Some(props! { "text" => text.clone() }), None)
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+
+    assert!(node_is_interned(&storage, &id));
+    let txn = storage.graph_env.read_txn().unwrap();
+    let node = storage.get_node(&txn, &id).unwrap();
+    assert_eq!(*node.check_property("text").unwrap(), Value::String(text));
+}
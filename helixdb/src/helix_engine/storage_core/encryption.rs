@@ -0,0 +1,134 @@
+use std::{borrow::Cow, process::Command};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+
+use crate::helix_engine::{graph_core::config::EncryptionConfig, types::GraphError};
+
+/// Header every encrypted item starts with, followed by the nonce and the ciphertext.
+///
+/// Plain items start with the length of their label as a little endian u64 and
+/// compressed ones with the zstd magic number, neither of which match it, so items
+/// written before encryption was enabled stay readable.
+const ENCRYPTED_MAGIC: [u8; 4] = [0x48, 0x58, 0x45, 0xE1];
+
+const NONCE_LEN: usize = 12;
+
+/// Environment variable the key is read from unless configured otherwise
+pub const DEFAULT_KEY_ENV: &str = "HELIX_ENCRYPTION_KEY";
+
+/// Transparent AES-256-GCM encryption of encoded nodes, edges and audit log entries.
+///
+/// Each item is encrypted under a random nonce with its id as associated data, so an
+/// encrypted item can't be passed off as another one.
+#[derive(Clone)]
+pub struct Encryption {
+    cipher: Option<Aes256Gcm>,
+}
+
+impl Encryption {
+    pub fn new(config: &EncryptionConfig) -> Result<Encryption, GraphError> {
+        if !config.enabled {
+            return Ok(Encryption { cipher: None });
+        }
+        let key = match &config.key_command {
+            Some(command) => {
+                let output = Command::new("sh").arg("-c").arg(command).output()?;
+                if !output.status.success() {
+                    return Err(GraphError::New(format!(
+                        "Encryption key command failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+            None => {
+                let key_env = config.key_env.as_deref().unwrap_or(DEFAULT_KEY_ENV);
+                std::env::var(key_env).map_err(|_| {
+                    GraphError::New(format!("Encryption is enabled but {} isn't set", key_env))
+                })?
+            }
+        };
+        Ok(Encryption {
+            cipher: Some(Aes256Gcm::new(&parse_key(key.trim())?.into())),
+        })
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Encrypts an encoded item if encryption is enabled
+    pub fn encrypt(&self, id: u128, bytes: Vec<u8>) -> Result<Vec<u8>, GraphError> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(bytes);
+        };
+        let nonce = rand::random::<[u8; NONCE_LEN]>();
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &bytes,
+                    aad: &id.to_be_bytes(),
+                },
+            )
+            .map_err(|_| GraphError::New("Error encrypting item".to_string()))?;
+        let mut encrypted =
+            Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+        encrypted.extend_from_slice(&ENCRYPTED_MAGIC);
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&ciphertext);
+        Ok(encrypted)
+    }
+
+    /// Returns the encoded item, decrypting it first if it was stored encrypted
+    pub fn decrypt<'a>(&self, id: u128, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>, GraphError> {
+        if !is_encrypted(bytes) {
+            return Ok(Cow::Borrowed(bytes));
+        }
+        let Some(cipher) = &self.cipher else {
+            return Err(GraphError::DecodeError(
+                "Item is encrypted but encryption isn't enabled".to_string(),
+            ));
+        };
+        let (nonce, ciphertext) = bytes[ENCRYPTED_MAGIC.len()..]
+            .split_at_checked(NONCE_LEN)
+            .ok_or(GraphError::SliceLengthError)?;
+        cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &id.to_be_bytes(),
+                },
+            )
+            .map(Cow::Owned)
+            .map_err(|_| {
+                GraphError::DecodeError(
+                    "Error decrypting item, the key doesn't match or it was tampered with"
+                        .to_string(),
+                )
+            })
+    }
+}
+
+#[inline(always)]
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ENCRYPTED_MAGIC)
+}
+
+/// Parses a 256 bit key written as 64 hex characters
+fn parse_key(key: &str) -> Result<[u8; 32], GraphError> {
+    let invalid = || GraphError::New("Encryption key must be 64 hex characters".to_string());
+    if key.len() != 64 || !key.is_ascii() {
+        return Err(invalid());
+    }
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&key[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::{Config, FsyncPolicy},
+            ops::{
+                g::G,
+                out::out::OutAdapter,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                    n_from_id::NFromIdAdapter,
+                    n_from_type::NFromTypeAdapter,
+                },
+                tr_val::Traversable,
+            },
+        },
+        storage_core::{
+            compression::is_compressed, encryption::is_encrypted, storage_core::HelixGraphStorage,
+            storage_methods::StorageMethods, test_utils::{open, try_open},
+        },
+        types::GraphError,
+    },
+    props,
+    protocol::value::Value,
+};
+
+const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const OTHER_KEY: &str = "ff0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+/// Reads the key from a command, as environment variables are shared between tests
+fn encryption_config(key: Option<&str>, migrate: bool) -> Config {
+    let mut config = Config::default();
+    if let Some(key) = key {
+        config.encryption.enabled = true;
+        config.encryption.key_command = Some(format!("echo {}", key));
+    }
+    config.encryption.migrate = migrate;
+    config
+}
+
+fn add_person(storage: &Arc<HelixGraphStorage>, ssn: &str) -> u128 {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let id = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n("person", Some(props! { "ssn" => ssn }), None)
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+    id
+}
+
+fn stored_node(storage: &HelixGraphStorage, id: &u128) -> Vec<u8> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    storage.nodes_db.get(&txn, id).unwrap().unwrap().to_vec()
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle.as_bytes())
+}
+
+#[test]
+fn test_nodes_and_edges_are_encrypted() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, encryption_config(Some(KEY), false));
+    let alice = add_person(&storage, "123-45-6789");
+    let bob = add_person(&storage, "987-65-4321");
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let edge = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_e(
+            "knows",
+            Some(props! { "since" => "childhood" }),
+            alice,
+            bob,
+            true,
+            EdgeType::Node,
+        )
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+
+    let bytes = stored_node(&storage, &alice);
+    assert!(is_encrypted(&bytes));
+    assert!(!contains(&bytes, "123-45-6789"));
+    assert!(!contains(&bytes, "person"));
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let bytes = storage.edges_db.get(&txn, &edge).unwrap().unwrap();
+    assert!(is_encrypted(bytes));
+    assert!(!contains(bytes, "childhood"));
+
+    let node = storage.get_node(&txn, &alice).unwrap();
+    assert_eq!(
+        node.properties.unwrap().get("ssn"),
+        Some(&Value::String("123-45-6789".to_string()))
+    );
+    let edge = storage.get_edge(&txn, &edge).unwrap();
+    assert_eq!(
+        edge.properties.unwrap().get("since"),
+        Some(&Value::String("childhood".to_string()))
+    );
+    // the adjacency lists stay in plaintext and scans decrypt what they read
+    let known = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&alice)
+        .out("knows", &EdgeType::Node)
+        .collect_to::<Vec<_>>();
+    assert_eq!(
+        known.iter().map(|node| node.id()).collect::<Vec<_>>(),
+        vec![bob]
+    );
+    let people = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>();
+    assert_eq!(people.len(), 2);
+}
+
+#[test]
+fn test_encryption_with_compression() {
+    let dir = TempDir::new().unwrap();
+    let mut config = encryption_config(Some(KEY), false);
+    config.compression.threshold_bytes = Some(256);
+    let storage = open(&dir, config);
+    let ssn = "123-45-6789 ".repeat(100);
+    let id = add_person(&storage, &ssn);
+
+    // compressed before it is encrypted, as ciphertext doesn't compress
+    let bytes = stored_node(&storage, &id);
+    assert!(is_encrypted(&bytes) && !is_compressed(&bytes));
+    assert!(bytes.len() < ssn.len());
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let node = storage.get_node(&txn, &id).unwrap();
+    assert_eq!(
+        node.properties.unwrap().get("ssn"),
+        Some(&Value::String(ssn))
+    );
+}
+
+#[test]
+fn test_migrate_existing_data() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, encryption_config(None, false));
+    let id = add_person(&storage, "123-45-6789");
+    drop(storage);
+
+    // plain data stays readable without migrating
+    let storage = open(&dir, encryption_config(Some(KEY), false));
+    assert!(!is_encrypted(&stored_node(&storage, &id)));
+    let other = add_person(&storage, "987-65-4321");
+    assert!(is_encrypted(&stored_node(&storage, &other)));
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.get_node(&txn, &id).is_ok());
+    drop(txn);
+    drop(storage);
+
+    let storage = open(&dir, encryption_config(Some(KEY), true));
+    assert!(is_encrypted(&stored_node(&storage, &id)));
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.get_node(&txn, &id).is_ok());
+}
+
+#[test]
+fn test_encrypted_data_needs_the_key() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, encryption_config(Some(KEY), false));
+    let id = add_person(&storage, "123-45-6789");
+    drop(storage);
+
+    for config in [
+        encryption_config(Some(OTHER_KEY), false),
+        encryption_config(None, false),
+    ] {
+        let storage = open(&dir, config);
+        let txn = storage.graph_env.read_txn().unwrap();
+        assert!(matches!(
+            storage.get_node(&txn, &id),
+            Err(GraphError::DecodeError(_))
+        ));
+    }
+}
+
+#[test]
+fn test_write_ahead_log_is_encrypted() {
+    let dir = TempDir::new().unwrap();
+    let log_dir = TempDir::new().unwrap();
+    let backup_dir = TempDir::new().unwrap();
+    let restore_dir = TempDir::new().unwrap();
+    let config = || {
+        let mut config = encryption_config(Some(KEY), false);
+        config.wal.enabled = true;
+        config.wal.fsync = FsyncPolicy::Always;
+        config.wal.dir = Some(log_dir.path().to_str().unwrap().to_string());
+        config
+    };
+
+    let storage = open(&dir, config());
+    assert_eq!(storage.backup(backup_dir.path()).unwrap(), 0);
+    let id = add_person(&storage, "123-45-6789");
+    assert_eq!(storage.wal.ship(&storage.graph_env).unwrap(), 1);
+    let log = std::fs::read(storage.wal.path()).unwrap();
+    assert!(!log.is_empty());
+    assert!(!contains(&log, "123-45-6789"));
+    assert!(!contains(&log, "person"));
+    drop(storage);
+
+    // rolling the log forward decrypts what it replays
+    HelixGraphStorage::restore(backup_dir.path(), restore_dir.path()).unwrap();
+    let restored = open(&restore_dir, config());
+    let txn = restored.graph_env.read_txn().unwrap();
+    let node = restored.get_node(&txn, &id).unwrap();
+    assert_eq!(
+        node.properties.unwrap().get("ssn"),
+        Some(&Value::String("123-45-6789".to_string()))
+    );
+}
+
+#[test]
+fn test_invalid_keys_are_rejected() {
+    let dir = TempDir::new().unwrap();
+    assert!(try_open(&dir, encryption_config(Some("not a key"), false)).is_err());
+    assert!(try_open(&dir, encryption_config(Some(&KEY[..62]), false)).is_err());
+
+    let mut config = Config::default();
+    config.encryption.enabled = true;
+    config.encryption.key_env = Some("HELIX_TEST_UNSET_ENCRYPTION_KEY".to_string());
+    assert!(try_open(&dir, config).is_err());
+}
@@ -0,0 +1,90 @@
+//! In-memory bloom filter of the ids of the nodes in the graph.
+//!
+//! Edge insertion checks that the nodes it connects exist. With the filter enabled, an id
+//! it has never seen belongs to a node that certainly doesn't exist, so the check is
+//! answered without reading the nodes table. Ids it may have seen are still looked up,
+//! which keeps the check exact for nodes that were dropped or written by transactions
+//! that were aborted, since their ids can't be taken out of the filter.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use twox_hash::XxHash64;
+
+use crate::{
+    helix_engine::{graph_core::config::ExistenceFilterConfig, types::GraphError},
+    helix_storage::heed3::{
+        byteorder::BE,
+        types::{Bytes, U128},
+        Database, RoTxn,
+    },
+};
+
+pub struct ExistenceFilter {
+    bits: Vec<AtomicU64>,
+    hashes: u64,
+}
+
+impl ExistenceFilter {
+    pub const DEFAULT_EXPECTED_NODES: usize = 1_000_000;
+    pub const DEFAULT_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+    /// Returns `None` if the filter is disabled in the config, otherwise a filter holding
+    /// the ids of the nodes already stored
+    pub fn new(
+        config: &ExistenceFilterConfig,
+        txn: &RoTxn,
+        nodes_db: &Database<U128<BE>, Bytes>,
+    ) -> Result<Option<ExistenceFilter>, GraphError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let stored = nodes_db.len(txn)? as usize;
+        let expected = config
+            .expected_nodes
+            .unwrap_or(Self::DEFAULT_EXPECTED_NODES)
+            .max(stored.saturating_mul(2))
+            .max(1) as f64;
+        let rate = config
+            .false_positive_rate
+            .unwrap_or(Self::DEFAULT_FALSE_POSITIVE_RATE)
+            .clamp(1e-9, 0.5);
+
+        // optimal size and number of hashes for the expected nodes and rate
+        let ln2 = std::f64::consts::LN_2;
+        let words = ((-expected * rate.ln() / (ln2 * ln2)) / 64.0)
+            .ceil()
+            .max(1.0) as usize;
+        let hashes = ((words * 64) as f64 / expected * ln2).round().max(1.0) as u64;
+        let filter = ExistenceFilter {
+            bits: (0..words).map(|_| AtomicU64::new(0)).collect(),
+            hashes,
+        };
+
+        for entry in nodes_db.lazily_decode_data().iter(txn)? {
+            filter.insert(&entry?.0);
+        }
+        Ok(Some(filter))
+    }
+
+    /// Records a node written to the nodes table
+    pub fn insert(&self, id: &u128) {
+        for bit in self.bit_positions(id) {
+            self.bits[bit / 64].fetch_or(1 << (bit % 64), Ordering::Relaxed);
+        }
+    }
+
+    /// Whether a node with the id may exist, `false` if it certainly doesn't
+    pub fn may_contain(&self, id: &u128) -> bool {
+        self.bit_positions(id)
+            .all(|bit| self.bits[bit / 64].load(Ordering::Relaxed) & (1 << (bit % 64)) != 0)
+    }
+
+    // double hashing, the i-th position being h1 + i * h2
+    fn bit_positions(&self, id: &u128) -> impl Iterator<Item = usize> {
+        let bytes = id.to_be_bytes();
+        let first = XxHash64::oneshot(0, &bytes);
+        let second = XxHash64::oneshot(1, &bytes) | 1;
+        let len = (self.bits.len() * 64) as u64;
+        (0..self.hashes).map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % len) as usize)
+    }
+}
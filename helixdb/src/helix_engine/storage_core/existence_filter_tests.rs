@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::helix_engine::{
+    graph_core::{
+        config::Config,
+        ops::{
+            g::G,
+            source::add_e::{AddEAdapter, EdgeType},
+            tr_val::TraversalVal,
+        },
+    },
+    storage_core::{
+        storage_core::HelixGraphStorage,
+        storage_methods::StorageMethods,
+        test_utils::{add_users, open},
+    },
+    types::GraphError,
+};
+
+fn filter_config(enabled: bool) -> Config {
+    let mut config = Config::default();
+    config.existence_filter.enabled = enabled;
+    config.existence_filter.expected_nodes = Some(1000);
+    config
+}
+
+fn follow(
+    storage: &Arc<HelixGraphStorage>,
+    from: u128,
+    to: u128,
+) -> Result<Vec<TraversalVal>, GraphError> {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let result = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_e("follows", None, from, to, true, EdgeType::Node)
+        .try_collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+    result
+}
+
+#[test]
+fn test_filter_holds_stored_nodes() {
+    let dir = TempDir::new().unwrap();
+    let stored = add_users(&open(&dir, filter_config(false)), 50);
+
+    // the nodes written before the filter was enabled are loaded on startup
+    let storage = open(&dir, filter_config(true));
+    let added = add_users(&storage, 50);
+    let existence = storage.existence.as_ref().unwrap();
+    assert!(stored
+        .iter()
+        .chain(&added)
+        .all(|id| existence.may_contain(id)));
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.check_exists(&txn, &stored[0]).unwrap());
+    assert!(storage.check_exists(&txn, &added[0]).unwrap());
+
+    // sized for 1000 nodes at 1% false positives
+    let missing = (0..1000u128)
+        .map(|i| u128::MAX - i)
+        .filter(|id| existence.may_contain(id))
+        .count();
+    assert!(missing < 50, "{} false positives", missing);
+    assert!(!storage.check_exists(&txn, &(u128::MAX - 1)).unwrap());
+}
+
+#[test]
+fn test_add_e_checks_nodes_exist() {
+    for enabled in [false, true] {
+        let dir = TempDir::new().unwrap();
+        let storage = open(&dir, filter_config(enabled));
+        let users = add_users(&storage, 2);
+
+        assert!(matches!(
+            follow(&storage, users[0], users[1]).unwrap()[..],
+            [TraversalVal::Edge(_)]
+        ));
+        assert!(matches!(
+            follow(&storage, users[0], u128::MAX),
+            Err(GraphError::NodeNotFound)
+        ));
+        assert!(matches!(
+            follow(&storage, u128::MAX, users[1]),
+            Err(GraphError::NodeNotFound)
+        ));
+    }
+}
+
+#[test]
+fn test_dropped_nodes_are_looked_up() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, filter_config(true));
+    let users = add_users(&storage, 2);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.drop_node(&mut txn, &users[1]).unwrap();
+    txn.commit().unwrap();
+
+    // still in the filter, but the lookup finds the node gone
+    assert!(storage.existence.as_ref().unwrap().may_contain(&users[1]));
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(!storage.check_exists(&txn, &users[1]).unwrap());
+}
@@ -0,0 +1,158 @@
+//! Group commit of the writes of concurrent queries.
+//!
+//! LMDB only lets one write transaction be open at a time, so queries writing to the graph
+//! at the same time wait on each other to commit, each paying for a commit of its own.
+//! With group commit the queries queue their writes instead, and whichever of them gets
+//! to write first runs every write queued by then in a single transaction and hands each
+//! query back its result once it's committed.
+//!
+//! Each write runs in a nested transaction, so one failing only rolls back its own
+//! changes. The writes run in the order they were queued, each seeing the changes of the
+//! ones before it as if they had been committed one by one.
+
+use std::{
+    any::Any,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::GroupCommitConfig,
+            query_limits::{self, DetachedBudget},
+        },
+        storage_core::wal::WriteAheadLog,
+        types::GraphError,
+    },
+    helix_storage::heed3::{Env, RwTxn, WithTls},
+};
+
+type Output = Box<dyn Any + Send>;
+type Write = Box<dyn FnOnce(&mut RwTxn) -> Result<Output, GraphError> + Send>;
+type Done = (Result<Output, GraphError>, DetachedBudget);
+
+struct PendingWrite {
+    write: Write,
+    /// Limits of the query the write is part of, tracked while it runs
+    budget: DetachedBudget,
+    done: flume::Sender<Done>,
+}
+
+/// Batches the writes of concurrent queries into shared write transactions
+pub struct GroupCommit {
+    pending: Mutex<Vec<PendingWrite>>,
+    /// Held while a batch of writes is run and committed
+    writer: Mutex<()>,
+    max_batch: usize,
+}
+
+impl GroupCommit {
+    pub const DEFAULT_MAX_BATCH: usize = 64;
+
+    /// Returns `None` if group commit is disabled in the config
+    pub fn new(config: &GroupCommitConfig) -> Option<GroupCommit> {
+        config.enabled.then(|| GroupCommit {
+            pending: Mutex::new(Vec::new()),
+            writer: Mutex::new(()),
+            max_batch: config.max_batch.unwrap_or(Self::DEFAULT_MAX_BATCH).max(1),
+        })
+    }
+
+    /// Runs `write` in a write transaction shared with the writes of other queries,
+    /// returning its result once the transaction is committed.
+    ///
+    /// The write may be run by another thread, within the limits of the query the
+    /// current thread is running. The batch is committed through the write-ahead log, so
+    /// it waits on its quorum if it has one.
+    pub fn run<T, F>(
+        &self,
+        env: &Env<WithTls>,
+        wal: &WriteAheadLog,
+        write: F,
+    ) -> Result<T, GraphError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut RwTxn) -> Result<T, GraphError> + Send + 'static,
+    {
+        let (done, result) = flume::bounded(1);
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(PendingWrite {
+                write: Box::new(move |txn| write(txn).map(|output| Box::new(output) as Output)),
+                budget: query_limits::detach(),
+                done,
+            });
+
+        let (output, budget) = loop {
+            // the batches run before the writer is released, so the write is done by
+            // then unless it was queued after the batch was taken
+            let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+            match result.try_recv() {
+                Ok(done) => break done,
+                Err(flume::TryRecvError::Empty) => self.commit_batch(env, wal),
+                // the thread running the batch panicked
+                Err(flume::TryRecvError::Disconnected) => {
+                    return Err(GraphError::New(
+                        "The write was lost as the batch it was part of failed".to_string(),
+                    ))
+                }
+            }
+        };
+        budget.attach();
+        output.map(|output| *output.downcast::<T>().expect("output of the write"))
+    }
+
+    /// Runs and commits the oldest pending writes, up to a batch of them
+    fn commit_batch(&self, env: &Env<WithTls>, wal: &WriteAheadLog) {
+        let batch = {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            let len = pending.len().min(self.max_batch);
+            pending.drain(..len).collect::<Vec<_>>()
+        };
+        let mut txn = match env.write_txn() {
+            Ok(txn) => txn,
+            Err(e) => {
+                for write in batch {
+                    let error = GraphError::New(format!("Failed to start the batch: {}", e));
+                    let _ = write.done.send((Err(error), write.budget));
+                }
+                return;
+            }
+        };
+
+        let mut done = Vec::with_capacity(batch.len());
+        for write in batch {
+            write.budget.attach();
+            let result = apply(env, &mut txn, write.write);
+            done.push((write.done, result, query_limits::detach()));
+        }
+        let committed = wal.commit(txn);
+
+        for (sender, result, budget) in done {
+            let result = match (&committed, result) {
+                (Err(e), Ok(_)) => Err(GraphError::New(format!(
+                    "Failed to commit the batch of writes: {}",
+                    e
+                ))),
+                (_, result) => result,
+            };
+            let _ = sender.send((result, budget));
+        }
+    }
+}
+
+/// Runs a write in a transaction nested in the batch's, rolling it back if it fails
+fn apply(env: &Env<WithTls>, txn: &mut RwTxn, write: Write) -> Result<Output, GraphError> {
+    let mut nested = env.nested_write_txn(txn)?;
+    match write(&mut nested) {
+        Ok(output) => {
+            nested.commit()?;
+            Ok(output)
+        }
+        Err(e) => {
+            nested.abort();
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::{sync::Arc, thread};
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::{Config, QueryLimitsConfig},
+            ops::{
+                g::G,
+                source::{add_n::AddNAdapter, n_from_type::NFromTypeAdapter},
+                tr_val::TraversalVal,
+            },
+            query_limits::{CancellationToken, QueryGuard},
+        },
+        storage_core::{storage_core::HelixGraphStorage, test_utils},
+        types::GraphError,
+    },
+    props,
+};
+
+fn open(dir: &TempDir, max_batch: Option<usize>) -> Arc<HelixGraphStorage> {
+    let mut config = Config::default();
+    config.group_commit.enabled = true;
+    config.group_commit.max_batch = max_batch;
+    test_utils::open(dir, config)
+}
+
+/// Adds a person through group commit, returning their id
+fn add_person(storage: &Arc<HelixGraphStorage>, name: &str) -> Result<u128, GraphError> {
+    let (writer, name) = (Arc::clone(storage), name.to_string());
+    let group_commit = storage.group_commit.as_ref().unwrap();
+    group_commit.run(&storage.graph_env, &storage.wal, move |txn| {
+        match G::new_mut(writer, txn)
+            .add_n("person", Some(props! { "name" => name }), None)
+            .collect_to_val()
+        {
+            TraversalVal::Node(node) => Ok(node.id),
+            _ => Err(GraphError::New("person wasn't added".to_string())),
+        }
+    })
+}
+
+fn count_people(storage: &Arc<HelixGraphStorage>) -> usize {
+    let txn = storage.graph_env.read_txn().unwrap();
+    G::new(Arc::clone(storage), &txn)
+        .n_from_type("person")
+        .count()
+}
+
+#[test]
+fn test_group_commit_disabled_by_default() {
+    let dir = TempDir::new().unwrap();
+    let storage = HelixGraphStorage::new(dir.path().to_str().unwrap(), Config::default()).unwrap();
+    assert!(storage.group_commit.is_none());
+}
+
+#[test]
+fn test_group_commit_concurrent_writes() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, Some(4));
+
+    let handles = (0..16)
+        .map(|i| {
+            let storage = Arc::clone(&storage);
+            thread::spawn(move || {
+                (0..10)
+                    .map(|j| add_person(&storage, &format!("{}-{}", i, j)).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut ids = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+
+    // every write got its own result back
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 160);
+    assert_eq!(count_people(&storage), 160);
+}
+
+#[test]
+fn test_group_commit_failed_write_rolled_back() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, None);
+    let group_commit = storage.group_commit.as_ref().unwrap();
+
+    add_person(&storage, "alice").unwrap();
+    let failing = Arc::clone(&storage);
+    let result = group_commit.run(&storage.graph_env, &storage.wal, move |txn| {
+        G::new_mut(failing, txn)
+            .add_n("person", Some(props! { "name" => "bob" }), None)
+            .collect_to_val();
+        Err::<(), _>(GraphError::New("failed after writing".to_string()))
+    });
+    assert!(matches!(result, Err(GraphError::New(_))));
+    add_person(&storage, "carol").unwrap();
+
+    // only the writes of the failing query are rolled back
+    assert_eq!(count_people(&storage), 2);
+}
+
+#[test]
+fn test_group_commit_keeps_query_limits() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, None);
+    for i in 0..100 {
+        add_person(&storage, &i.to_string()).unwrap();
+    }
+
+    let limits = QueryLimitsConfig {
+        max_visited: Some(10),
+        ..Default::default()
+    };
+    let guard = QueryGuard::start(&limits, CancellationToken::new());
+    let reader = Arc::clone(&storage);
+    let read = storage
+        .group_commit
+        .as_ref()
+        .unwrap()
+        .run(&storage.graph_env, &storage.wal, move |txn| {
+            Ok(G::new(reader, txn).n_from_type("person").count())
+        })
+        .unwrap();
+
+    // the limits of the query followed its write and came back with it
+    assert!(read <= 10);
+    assert!(matches!(
+        guard.finish(),
+        Err(GraphError::QueryLimitExceeded(_))
+    ));
+}
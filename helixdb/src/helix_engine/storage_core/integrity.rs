@@ -0,0 +1,408 @@
+//! Verification of the references between the tables of the graph, run by `helix fsck`.
+//!
+//! Edges must connect stored nodes or vectors, adjacency lists must hold edges that
+//! connect their node, secondary index entries must point at items that still have the
+//! indexed value, the links of the vector indices must connect stored vectors, and the
+//! tombstones of the trash must mark stored elements. Elements in the trash are checked
+//! like any other, since they can still be restored. Writes
+//! never leave these broken on their own, but crashes of older versions, restored backups
+//! and manual edits of the data directory can.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    helix_engine::{
+        cdc::cdc::ChangeTarget, stats::stats::Direction,
+        storage_core::storage_core::HelixGraphStorage, types::GraphError,
+        vector_core::vector_core::VectorCore,
+    },
+    helix_storage::heed3::{RoTxn, RwTxn},
+    protocol::{items::Edge, value::Value},
+};
+
+/// Inconsistency between the tables of the graph found by
+/// [`HelixGraphStorage::check_integrity`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// Edge from or to a node or vector that doesn't exist
+    DanglingEdge { edge: u128, missing: u128 },
+    /// Entry of an adjacency list whose edge doesn't exist or doesn't connect the node of
+    /// the list to the adjacent node
+    OrphanAdjacency {
+        direction: Direction,
+        node: u128,
+        label: [u8; 4],
+        adjacent: u128,
+        edge: u128,
+    },
+    /// Entry of a secondary index whose node doesn't exist or no longer has the value
+    StaleIndexEntry {
+        index: String,
+        key: Vec<u8>,
+        node: u128,
+    },
+    /// Entry of an edge index whose edge doesn't exist or no longer has the value
+    StaleEdgeIndexEntry {
+        property: String,
+        key: Vec<u8>,
+        edge: u128,
+    },
+    /// Link of a vector index from or to a vector that isn't stored, in the index of a
+    /// namespace or the default one
+    DanglingLink {
+        namespace: Option<String>,
+        source: u128,
+        level: usize,
+        sink: u128,
+    },
+    /// Vector index holding vectors without an entry point, so searches find nothing
+    MissingEntryPoint { namespace: Option<String> },
+    /// Tombstone of a node or edge that doesn't exist
+    OrphanTombstone { id: u128 },
+}
+
+impl Issue {
+    /// Whether the issue is repaired by removing the orphaned entry. The entry point of
+    /// a vector index can only be restored by rebuilding the index.
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, Issue::MissingEntryPoint { .. })
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let index_name = |namespace: &Option<String>| match namespace {
+            Some(namespace) => format!("vector index of namespace {}", namespace),
+            None => "vector index".to_string(),
+        };
+        match self {
+            Issue::DanglingEdge { edge, missing } => write!(
+                f,
+                "edge {} connects {}, which doesn't exist",
+                uuid::Uuid::from_u128(*edge),
+                uuid::Uuid::from_u128(*missing)
+            ),
+            Issue::OrphanAdjacency {
+                direction,
+                node,
+                adjacent,
+                edge,
+                ..
+            } => write!(
+                f,
+                "{} adjacency list of {} holds edge {} to {}, which doesn't exist or connects other nodes",
+                match direction {
+                    Direction::Out => "outgoing",
+                    Direction::In => "incoming",
+                },
+                uuid::Uuid::from_u128(*node),
+                uuid::Uuid::from_u128(*edge),
+                uuid::Uuid::from_u128(*adjacent)
+            ),
+            Issue::StaleIndexEntry { index, node, .. } => write!(
+                f,
+                "index {} points at node {}, which doesn't exist or has another value",
+                index,
+                uuid::Uuid::from_u128(*node)
+            ),
+            Issue::StaleEdgeIndexEntry { property, edge, .. } => write!(
+                f,
+                "edge index {} points at edge {}, which doesn't exist or has another value",
+                property,
+                uuid::Uuid::from_u128(*edge)
+            ),
+            Issue::DanglingLink {
+                namespace,
+                source,
+                level,
+                sink,
+            } => write!(
+                f,
+                "{} links {} to {} at level {}, one of which isn't stored",
+                index_name(namespace),
+                uuid::Uuid::from_u128(*source),
+                uuid::Uuid::from_u128(*sink),
+                level
+            ),
+            Issue::MissingEntryPoint { namespace } => {
+                write!(f, "{} has no entry point", index_name(namespace))
+            }
+            Issue::OrphanTombstone { id } => write!(
+                f,
+                "trash holds {}, which doesn't exist",
+                uuid::Uuid::from_u128(*id)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IntegrityReport {
+    pub nodes: u64,
+    pub edges: u64,
+    pub issues: Vec<Issue>,
+    /// Number of issues repaired, 0 unless a repair was asked for
+    pub repaired: usize,
+}
+
+impl HelixGraphStorage {
+    /// Checks the references between the tables of the graph, see [`Issue`].
+    ///
+    /// With `repair` set, the orphaned entries are removed in the same write transaction
+    /// they were found in, and the statistics are rebuilt afterwards if they are enabled.
+    /// Repairs aren't journaled in the write-ahead log, so replicas are checked on their
+    /// own.
+    pub fn check_integrity(&self, repair: bool) -> Result<IntegrityReport, GraphError> {
+        if !repair {
+            let txn = self.graph_env.read_txn()?;
+            return self.find_issues(&txn);
+        }
+
+        let mut txn = self.graph_env.write_txn()?;
+        let mut report = self.find_issues(&txn)?;
+        for issue in report.issues.iter().filter(|issue| issue.is_repairable()) {
+            self.repair_issue(&mut txn, issue)?;
+            report.repaired += 1;
+        }
+        txn.commit()?;
+        if report.repaired > 0 && self.stats.is_enabled() {
+            self.rebuild_stats()?;
+        }
+        Ok(report)
+    }
+
+    fn find_issues(&self, txn: &RoTxn) -> Result<IntegrityReport, GraphError> {
+        let mut report = IntegrityReport {
+            nodes: self.nodes_db.len(txn)?,
+            edges: self.edges_db.len(txn)?,
+            ..Default::default()
+        };
+
+        for result in self.edges_db.iter(txn)? {
+            let (id, bytes) = result?;
+            let edge = self.decode_edge(bytes, id)?;
+            for endpoint in [edge.from_node, edge.to_node] {
+                if !self.endpoint_exists(txn, &endpoint)? {
+                    report.issues.push(Issue::DanglingEdge {
+                        edge: edge.id,
+                        missing: endpoint,
+                    });
+                    break;
+                }
+            }
+        }
+
+        for direction in [Direction::Out, Direction::In] {
+            let mut entries = self.adjacency_blocks.all_edges(txn, direction)?;
+            for result in self.adjacency_db(direction).iter(txn)? {
+                let (key, value) = result?;
+                let node = u128::from_be_bytes(
+                    key[0..16]
+                        .try_into()
+                        .map_err(|_| GraphError::SliceLengthError)?,
+                );
+                let label: [u8; 4] = key[16..20]
+                    .try_into()
+                    .map_err(|_| GraphError::SliceLengthError)?;
+                entries.push((node, label, Self::unpack_adj_edge_data(value)?));
+            }
+            for (node, label, (adjacent, edge_id)) in entries {
+                let connects = match self.stored_edge(txn, &edge_id)? {
+                    Some(edge) => match direction {
+                        Direction::Out => edge.from_node == node && edge.to_node == adjacent,
+                        Direction::In => edge.to_node == node && edge.from_node == adjacent,
+                    },
+                    None => false,
+                };
+                if !connects {
+                    report.issues.push(Issue::OrphanAdjacency {
+                        direction,
+                        node,
+                        label,
+                        adjacent,
+                        edge: edge_id,
+                    });
+                }
+            }
+        }
+
+        for (index, db) in self.secondary_indices.iter() {
+            for result in db.iter(txn)? {
+                let (key, node_id) = result?;
+                let indexed = match self.nodes_db.get(txn, &node_id)? {
+                    Some(bytes) => {
+                        let node = self.decode_node(bytes, node_id)?;
+                        holds_value(node.properties.as_ref(), index, key)?
+                    }
+                    None => false,
+                };
+                if !indexed {
+                    report.issues.push(Issue::StaleIndexEntry {
+                        index: index.clone(),
+                        key: key.to_vec(),
+                        node: node_id,
+                    });
+                }
+            }
+        }
+
+        for (property, db) in self.edge_secondary_indices.iter() {
+            for result in db.iter(txn)? {
+                let (key, edge_id) = result?;
+                let indexed = match self.stored_edge(txn, &edge_id)? {
+                    Some(Edge { properties, .. }) => {
+                        holds_value(properties.as_ref(), property, key)?
+                    }
+                    None => false,
+                };
+                if !indexed {
+                    report.issues.push(Issue::StaleEdgeIndexEntry {
+                        property: property.clone(),
+                        key: key.to_vec(),
+                        edge: edge_id,
+                    });
+                }
+            }
+        }
+
+        for (namespace, vectors) in self.vector_indices() {
+            for (source, level, sink) in vectors.dangling_links(txn)? {
+                report.issues.push(Issue::DanglingLink {
+                    namespace: namespace.clone(),
+                    source,
+                    level,
+                    sink,
+                });
+            }
+            if vectors.entry_point_missing(txn)? {
+                report.issues.push(Issue::MissingEntryPoint { namespace });
+            }
+        }
+
+        for (id, tombstone) in self.trash.list(txn)? {
+            let stored = match tombstone.target {
+                ChangeTarget::Node => self.nodes_db.get(txn, &id)?.is_some(),
+                _ => self.edges_db.get(txn, &id)?.is_some(),
+            };
+            if !stored {
+                report.issues.push(Issue::OrphanTombstone { id });
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn repair_issue(&self, txn: &mut RwTxn, issue: &Issue) -> Result<(), GraphError> {
+        match issue {
+            Issue::DanglingEdge { edge, .. } => {
+                let Some(bytes) = self.edges_db.get(txn, Self::edge_key(edge))? else {
+                    return Ok(());
+                };
+                let edge = self.decode_edge(bytes, *edge)?;
+                let label = self.dictionary.label_key(&edge.label);
+                self.edges_db.delete(txn, Self::edge_key(&edge.id))?;
+                self.remove_adjacent(
+                    txn,
+                    Direction::Out,
+                    &edge.from_node,
+                    &label,
+                    (edge.to_node, edge.id),
+                )?;
+                self.remove_adjacent(
+                    txn,
+                    Direction::In,
+                    &edge.to_node,
+                    &label,
+                    (edge.from_node, edge.id),
+                )?;
+            }
+            Issue::OrphanAdjacency {
+                direction,
+                node,
+                label,
+                adjacent,
+                edge,
+            } => self.remove_adjacent(txn, *direction, node, label, (*adjacent, *edge))?,
+            Issue::StaleIndexEntry { index, key, node } => {
+                if let Some(db) = self.secondary_indices.get(index) {
+                    db.delete_one_duplicate(txn, key, node)?;
+                }
+            }
+            Issue::StaleEdgeIndexEntry {
+                property,
+                key,
+                edge,
+            } => {
+                if let Some(db) = self.edge_secondary_indices.get(property) {
+                    db.delete_one_duplicate(txn, key, edge)?;
+                }
+            }
+            Issue::DanglingLink {
+                namespace,
+                source,
+                level,
+                sink,
+            } => {
+                let vectors = match namespace {
+                    Some(namespace) => self.namespace_vectors.get(namespace),
+                    None => Some(&self.vectors),
+                };
+                if let Some(vectors) = vectors {
+                    vectors.unlink(txn, *source, *level, *sink)?;
+                }
+            }
+            Issue::MissingEntryPoint { .. } => {}
+            Issue::OrphanTombstone { id } => {
+                self.trash.remove(txn, id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the node or vector an edge connects exists, in the trash or not. Nodes
+    /// owned by other shards of the graph can't be checked locally and count as existing.
+    fn endpoint_exists(&self, txn: &RoTxn, id: &u128) -> Result<bool, GraphError> {
+        if let Some(shards) = &self.shards {
+            if !shards.is_local(*id) {
+                return Ok(true);
+            }
+        }
+        if self.nodes_db.get(txn, id)?.is_some() {
+            return Ok(true);
+        }
+        match self.get_vector(txn, id) {
+            Ok(_) => Ok(true),
+            Err(GraphError::VectorError(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The stored edge, in the trash or not
+    fn stored_edge(&self, txn: &RoTxn, id: &u128) -> Result<Option<Edge>, GraphError> {
+        match self.edges_db.get(txn, id)? {
+            Some(bytes) => Ok(Some(self.decode_edge(bytes, *id)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The default vector index followed by the indices of the namespaces
+    fn vector_indices(&self) -> impl Iterator<Item = (Option<String>, &VectorCore)> {
+        std::iter::once((None, &self.vectors)).chain(
+            self.namespace_vectors
+                .iter()
+                .map(|(namespace, vectors)| (Some(namespace.clone()), vectors)),
+        )
+    }
+}
+
+/// Whether the properties hold the value an index entry was written under
+fn holds_value(
+    properties: Option<&HashMap<String, Value>>,
+    field: &str,
+    key: &[u8],
+) -> Result<bool, GraphError> {
+    match properties.and_then(|properties| properties.get(field)) {
+        Some(value) => Ok(bincode::serialize(value)? == key),
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                out::out::OutAdapter,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    n_from_id::NFromIdAdapter,
+                },
+                tr_val::Traversable,
+                util::update::UpdateAdapter,
+                vectors::insert::InsertVAdapter,
+            },
+        },
+        storage_core::{
+            integrity::Issue,
+            storage_core::HelixGraphStorage,
+            test_utils::{add_users, open},
+        },
+        vector_core::vector::HVector,
+    },
+    helix_storage::heed3::RoTxn,
+    props,
+};
+
+fn follow(storage: &Arc<HelixGraphStorage>, from: u128, to: u128) -> u128 {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let id = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_e("follows", None, from, to, true, EdgeType::Node)
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+    id
+}
+
+fn indexed_config() -> Config {
+    let mut config = Config::default();
+    config.graph_config.secondary_indices = Some(vec!["name".to_string()]);
+    config
+}
+
+#[test]
+fn test_consistent_graph_has_no_issues() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, indexed_config());
+    let users = add_users(&storage, 3);
+    follow(&storage, users[0], users[1]);
+    follow(&storage, users[1], users[2]);
+
+    let report = storage.check_integrity(false).unwrap();
+    assert_eq!(report.nodes, 3);
+    assert_eq!(report.edges, 2);
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+}
+
+#[test]
+fn test_repair_dangling_edges() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, indexed_config());
+    let users = add_users(&storage, 3);
+    let first = follow(&storage, users[0], users[1]);
+    let second = follow(&storage, users[1], users[2]);
+    let third = follow(&storage, users[0], users[2]);
+
+    // the node goes missing without its edges, and an edge without its adjacency entries
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.nodes_db.delete(&mut txn, &users[1]).unwrap();
+    storage.edges_db.delete(&mut txn, &third).unwrap();
+    txn.commit().unwrap();
+
+    let report = storage.check_integrity(false).unwrap();
+    assert!(report.issues.contains(&Issue::DanglingEdge {
+        edge: first,
+        missing: users[1]
+    }));
+    assert!(report.issues.contains(&Issue::DanglingEdge {
+        edge: second,
+        missing: users[1]
+    }));
+    assert_eq!(
+        report
+            .issues
+            .iter()
+            .filter(|issue| matches!(issue, Issue::OrphanAdjacency { edge, .. } if *edge == third))
+            .count(),
+        2
+    );
+    // the index entry of the missing node is stale as well
+    assert!(report
+        .issues
+        .iter()
+        .any(|issue| matches!(issue, Issue::StaleIndexEntry { node, .. } if *node == users[1])));
+    assert_eq!(report.issues.len(), 5);
+
+    // a check alone leaves the issues in place
+    assert_eq!(storage.check_integrity(false).unwrap().issues.len(), 5);
+
+    let report = storage.check_integrity(true).unwrap();
+    assert_eq!(report.repaired, 5);
+    let report = storage.check_integrity(false).unwrap();
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+    assert_eq!(report.edges, 0);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let followed = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&users[0])
+        .out("follows", &EdgeType::Node)
+        .collect_to::<Vec<_>>();
+    assert!(followed.is_empty());
+}
+
+#[test]
+fn test_repair_stale_index_entries() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, indexed_config());
+    let users = add_users(&storage, 2);
+
+    // updates leave the entry of the old value behind
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let user = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&users[0])
+        .collect_to::<Vec<_>>();
+    G::new_mut_from(Arc::clone(&storage), &mut txn, user)
+        .update(Some(props! { "name" => 10 }))
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let report = storage.check_integrity(false).unwrap();
+    assert_eq!(
+        report.issues,
+        vec![Issue::StaleIndexEntry {
+            index: "name".to_string(),
+            key: bincode::serialize(&crate::protocol::value::Value::I64(0)).unwrap(),
+            node: users[0],
+        }]
+    );
+
+    assert_eq!(storage.check_integrity(true).unwrap().repaired, 1);
+    assert!(storage.check_integrity(false).unwrap().issues.is_empty());
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.secondary_indices["name"].len(&txn).unwrap(), 2);
+}
+
+#[test]
+fn test_repair_dangling_vector_links() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, Config::default());
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let vectors = [vec![1.0, 0.0], vec![0.0, 1.0], vec![0.5, 0.5]]
+        .iter()
+        .map(|data| {
+            G::new_mut(Arc::clone(&storage), &mut txn)
+                .insert_v::<fn(&HVector, &RoTxn) -> bool>(data, "embedding", None)
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    txn.commit().unwrap();
+    assert!(storage.check_integrity(false).unwrap().issues.is_empty());
+
+    let missing = u128::MAX;
+    let key = [
+        vectors[0].to_be_bytes().as_slice(),
+        0usize.to_be_bytes().as_slice(),
+        missing.to_be_bytes().as_slice(),
+    ]
+    .concat();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage
+        .vectors
+        .out_edges_db
+        .put(&mut txn, &key, &())
+        .unwrap();
+    txn.commit().unwrap();
+
+    let report = storage.check_integrity(false).unwrap();
+    assert_eq!(
+        report.issues,
+        vec![Issue::DanglingLink {
+            namespace: None,
+            source: vectors[0],
+            level: 0,
+            sink: missing,
+        }]
+    );
+    assert_eq!(storage.check_integrity(true).unwrap().repaired, 1);
+    assert!(storage.check_integrity(false).unwrap().issues.is_empty());
+}
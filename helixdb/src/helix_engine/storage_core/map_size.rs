@@ -0,0 +1,259 @@
+//! Monitoring and growth of the LMDB memory map.
+//!
+//! LMDB fails writes with `MDB_MAP_FULL` once the data fills the memory map, whose size is
+//! set when the environment is opened. The map can only be resized while no transaction
+//! is open in the process, so the requests handled by the gateway and the threads of the
+//! instance reading or writing in the background [`MapSize::hold`] the map while they
+//! do. Growing the map stops new holds and waits for the current ones to be released,
+//! giving up until the next check if that takes longer than `drain_timeout_ms`.
+//!
+//! Pinned snapshots, including those of cached cursors, hold the map for as long as they
+//! live, which can be minutes. Growing the map gives up right away while any is pinned
+//! rather than stalling every request for the drain timeout only to fail.
+
+use std::{
+    cell::Cell,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helix_engine::{graph_core::config::MapResizeConfig, types::GraphError},
+    helix_storage::heed3::{Env, WithTls},
+};
+
+/// Map sizes are rounded down to a multiple of this, which is a multiple of the page
+/// size of every platform LMDB runs on
+const GRANULE: usize = 1024 * 1024;
+
+thread_local! {
+    /// Holds taken by the current thread, which don't wait for a resize to take more
+    static HELD: Cell<usize> = const { Cell::new(0) };
+}
+
+pub struct MapSize {
+    gate: Arc<Gate>,
+    enabled: bool,
+    check_interval: Duration,
+    grow_at: f64,
+    growth_factor: f64,
+    max_size: usize,
+    drain_timeout: Duration,
+    resizes: AtomicU64,
+}
+
+/// Usage of the memory map, reported by the status and admin endpoints
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MapUsage {
+    pub map_bytes: u64,
+    /// Size of the data file, which only grows as pages are written
+    pub used_bytes: u64,
+    pub used_ratio: f64,
+    /// Times the map was grown since the start
+    pub resizes: u64,
+    /// Set while the map is used beyond the share it is grown at
+    pub alert: bool,
+}
+
+impl MapSize {
+    pub const DEFAULT_CHECK_INTERVAL_SECS: u64 = 60;
+    pub const DEFAULT_GROW_AT: f64 = 0.8;
+    pub const DEFAULT_GROWTH_FACTOR: f64 = 2.0;
+    pub const DEFAULT_MAX_SIZE_GB: usize = 9998;
+    pub const DEFAULT_DRAIN_TIMEOUT_MS: u64 = 5000;
+
+    pub fn new(config: &MapResizeConfig) -> MapSize {
+        MapSize {
+            gate: Arc::new(Gate::default()),
+            enabled: config.enabled,
+            check_interval: Duration::from_secs(
+                config
+                    .check_interval_secs
+                    .unwrap_or(Self::DEFAULT_CHECK_INTERVAL_SECS)
+                    .max(1),
+            ),
+            grow_at: config
+                .grow_at
+                .unwrap_or(Self::DEFAULT_GROW_AT)
+                .clamp(0.0, 1.0),
+            growth_factor: config
+                .growth_factor
+                .unwrap_or(Self::DEFAULT_GROWTH_FACTOR)
+                .max(1.0),
+            max_size: config
+                .max_size_gb
+                .unwrap_or(Self::DEFAULT_MAX_SIZE_GB)
+                .min(Self::DEFAULT_MAX_SIZE_GB)
+                * 1024
+                * 1024
+                * 1024,
+            drain_timeout: Duration::from_millis(
+                config
+                    .drain_timeout_ms
+                    .unwrap_or(Self::DEFAULT_DRAIN_TIMEOUT_MS),
+            ),
+            resizes: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether the map is grown in the background, every [`Self::check_interval`]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn check_interval(&self) -> Duration {
+        self.check_interval
+    }
+
+    /// Keeps the map from being resized until the hold is dropped, waiting for a resize
+    /// in progress to finish unless the thread already holds the map
+    pub fn hold(&self) -> MapHold {
+        self.hold_with(false)
+    }
+
+    /// Holds the map for a snapshot pinned for an unbounded time, which makes resizes
+    /// fail right away rather than wait for it
+    pub fn pin(&self) -> MapHold {
+        self.hold_with(true)
+    }
+
+    fn hold_with(&self, pinned: bool) -> MapHold {
+        let mut state = self.gate.state.lock().unwrap();
+        if HELD.get() == 0 {
+            while state.draining {
+                state = self.gate.changed.wait(state).unwrap();
+            }
+        }
+        state.holders += 1;
+        state.pinned += pinned as usize;
+        HELD.set(HELD.get() + 1);
+        MapHold {
+            gate: Arc::clone(&self.gate),
+            thread: thread::current().id(),
+            pinned,
+        }
+    }
+
+    pub fn usage(&self, env: &Env<WithTls>) -> Result<MapUsage, GraphError> {
+        let map_bytes = env.info().map_size as u64;
+        let used_bytes = env.real_disk_size()?;
+        let used_ratio = used_bytes as f64 / map_bytes.max(1) as f64;
+        Ok(MapUsage {
+            map_bytes,
+            used_bytes,
+            used_ratio,
+            resizes: self.resizes.load(Ordering::Relaxed),
+            alert: used_ratio >= self.grow_at,
+        })
+    }
+
+    /// Grows the map if it's used beyond `grow_at`, or regardless of its usage if `force`
+    /// is set. Returns the new size of the map, `None` if it was left as is because it
+    /// has room left or is already at its largest size.
+    pub fn grow(&self, env: &Env<WithTls>, force: bool) -> Result<Option<usize>, GraphError> {
+        let usage = self.usage(env)?;
+        if !force && usage.used_ratio < self.grow_at {
+            return Ok(None);
+        }
+        let current = usage.map_bytes as usize;
+        let target =
+            ((current as f64 * self.growth_factor) as usize).min(self.max_size) / GRANULE * GRANULE;
+        if target <= current {
+            return Ok(None);
+        }
+
+        let _drained = self.gate.drain(self.drain_timeout)?;
+        // no transaction is open in the process while the gate is drained
+        unsafe { env.resize(target)? };
+        self.resizes.fetch_add(1, Ordering::Relaxed);
+        Ok(Some(target))
+    }
+}
+
+#[derive(Default)]
+struct Gate {
+    state: Mutex<GateState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct GateState {
+    holders: usize,
+    /// Holders that are pinned snapshots
+    pinned: usize,
+    draining: bool,
+}
+
+impl Gate {
+    /// Stops new holds and waits for the current ones to be released, failing right away
+    /// if a snapshot is pinned
+    fn drain(&self, timeout: Duration) -> Result<Drained<'_>, GraphError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        if state.draining {
+            return Err(GraphError::New(
+                "The map is already being resized".to_string(),
+            ));
+        }
+        if state.pinned > 0 {
+            return Err(GraphError::New(format!(
+                "Can't resize the map while {} snapshots are pinned, e.g. by open cursors",
+                state.pinned
+            )));
+        }
+        state.draining = true;
+        while state.holders > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                state.draining = false;
+                self.changed.notify_all();
+                return Err(GraphError::New(format!(
+                    "Timed out resizing the map, {} transactions are still open",
+                    state.holders
+                )));
+            }
+            state = self.changed.wait_timeout(state, deadline - now).unwrap().0;
+        }
+        Ok(Drained { gate: self })
+    }
+}
+
+/// Keeps the map from being resized, see [`MapSize::hold`]
+pub struct MapHold {
+    gate: Arc<Gate>,
+    /// Thread the hold was taken on, it may be released on another one in async code
+    thread: ThreadId,
+    pinned: bool,
+}
+
+impl Drop for MapHold {
+    fn drop(&mut self) {
+        if thread::current().id() == self.thread {
+            HELD.set(HELD.get().saturating_sub(1));
+        }
+        let mut state = self.gate.state.lock().unwrap();
+        state.holders -= 1;
+        state.pinned -= self.pinned as usize;
+        if state.holders == 0 {
+            self.gate.changed.notify_all();
+        }
+    }
+}
+
+/// Lets holds be taken again once dropped
+struct Drained<'a> {
+    gate: &'a Gate,
+}
+
+impl Drop for Drained<'_> {
+    fn drop(&mut self) {
+        self.gate.state.lock().unwrap().draining = false;
+        self.gate.changed.notify_all();
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tempfile::TempDir;
+
+use crate::helix_engine::{
+    graph_core::{
+        config::Config,
+        ops::{
+            g::G,
+            source::{add_n::AddNAdapter, n_from_id::NFromIdAdapter},
+            tr_val::Traversable,
+        },
+        snapshot::Snapshot,
+    },
+    storage_core::test_utils::open,
+};
+
+const GB: usize = 1024 * 1024 * 1024;
+
+fn resize_config(grow_at: f64, max_size_gb: usize) -> Config {
+    let mut config = Config::default();
+    config.db_max_size_gb = Some(1);
+    config.map_resize.enabled = true;
+    config.map_resize.grow_at = Some(grow_at);
+    config.map_resize.max_size_gb = Some(max_size_gb);
+    config.map_resize.drain_timeout_ms = Some(50);
+    config
+}
+
+#[test]
+fn test_grow_map() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, resize_config(0.0, 4));
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let id = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("user", None, None)
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+
+    let usage = storage.map_size.usage(&storage.graph_env).unwrap();
+    assert_eq!(usage.map_bytes, GB as u64);
+    assert!(usage.used_bytes > 0);
+    assert!(usage.alert);
+
+    assert_eq!(
+        storage.map_size.grow(&storage.graph_env, false).unwrap(),
+        Some(2 * GB)
+    );
+    assert_eq!(storage.graph_env.info().map_size, 2 * GB);
+    assert_eq!(
+        storage.map_size.usage(&storage.graph_env).unwrap().resizes,
+        1
+    );
+
+    // the data is still there and can be written to after the resize
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("user", None, None)
+        .collect_to_val();
+    txn.commit().unwrap();
+    let txn = storage.graph_env.read_txn().unwrap();
+    let found = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&id)
+        .collect_to::<Vec<_>>();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id(), id);
+}
+
+#[test]
+fn test_grow_up_to_max_size() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, resize_config(0.0, 3));
+    assert_eq!(
+        storage.map_size.grow(&storage.graph_env, false).unwrap(),
+        Some(2 * GB)
+    );
+    assert_eq!(
+        storage.map_size.grow(&storage.graph_env, false).unwrap(),
+        Some(3 * GB)
+    );
+    assert_eq!(
+        storage.map_size.grow(&storage.graph_env, true).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_grow_only_past_threshold() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, resize_config(0.8, 4));
+    assert!(!storage.map_size.usage(&storage.graph_env).unwrap().alert);
+    assert_eq!(
+        storage.map_size.grow(&storage.graph_env, false).unwrap(),
+        None
+    );
+    assert_eq!(
+        storage.map_size.grow(&storage.graph_env, true).unwrap(),
+        Some(2 * GB)
+    );
+}
+
+#[test]
+fn test_grow_waits_for_holds() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, resize_config(0.0, 4));
+
+    // a hold outliving the drain timeout puts the resize off
+    let hold = storage.map_size.hold();
+    let other = Arc::clone(&storage);
+    let grown = std::thread::spawn(move || other.map_size.grow(&other.graph_env, false))
+        .join()
+        .unwrap();
+    assert!(grown.is_err());
+    assert_eq!(storage.graph_env.info().map_size, GB);
+
+    // holds can still be taken once the resize gave up
+    drop(storage.map_size.hold());
+    drop(hold);
+    assert_eq!(
+        storage.map_size.grow(&storage.graph_env, false).unwrap(),
+        Some(2 * GB)
+    );
+}
+
+#[test]
+fn test_grow_gives_up_while_snapshot_pinned() {
+    let dir = TempDir::new().unwrap();
+    let mut config = resize_config(0.0, 4);
+    config.map_resize.drain_timeout_ms = Some(60_000);
+    let storage = open(&dir, config);
+
+    // snapshots are held for as long as their cursors live, so the resize doesn't wait
+    let snapshot = Snapshot::pin(&storage).unwrap();
+    let started = Instant::now();
+    let error = storage.map_size.grow(&storage.graph_env, false).unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(error.to_string().contains("1 snapshots are pinned"), "{}", error);
+    assert_eq!(storage.graph_env.info().map_size, GB);
+
+    // the snapshot is released by its thread shortly after its last handle is dropped
+    drop(snapshot);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let grown = loop {
+        match storage.map_size.grow(&storage.graph_env, false) {
+            Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            grown => break grown,
+        }
+    };
+    assert_eq!(grown.unwrap(), Some(2 * GB));
+}
+
+#[cfg(feature = "bolt")]
+#[test]
+fn test_grow_while_bolt_queries_read() {
+    use crate::helix_gateway::bolt::execute;
+    use std::collections::HashMap;
+
+    let dir = TempDir::new().unwrap();
+    let mut config = resize_config(0.0, 16);
+    config.map_resize.drain_timeout_ms = Some(5000);
+    let storage = open(&dir, config);
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    for _ in 0..50 {
+        G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_n("user", None, None)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+
+    // each query holds the map for as long as its read transaction is open, so the
+    // resizes wait for them rather than remapping under them
+    let readers = (0..4)
+        .map(|_| {
+            let storage = Arc::clone(&storage);
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    let result =
+                        execute::run(&storage, "MATCH (u:user) RETURN u", &HashMap::new())
+                            .unwrap();
+                    assert_eq!(result.records.len(), 50);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut grown = 0;
+    while grown < 4 {
+        if storage.map_size.grow(&storage.graph_env, true).unwrap().is_some() {
+            grown += 1;
+        }
+    }
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    assert_eq!(storage.graph_env.info().map_size, 16 * GB);
+}
@@ -1,2 +1,45 @@
+pub mod adjacency_blocks;
+pub mod audit;
+pub mod bulk_load;
+pub mod compression;
+pub mod dictionary;
+pub mod encryption;
+pub mod existence_filter;
+pub mod group_commit;
+pub mod integrity;
+pub mod map_size;
+pub mod namespaces;
 pub mod storage_core;
 pub mod storage_methods;
+pub mod trash;
+pub mod versions;
+pub mod wal;
+
+#[cfg(test)]
+pub mod adjacency_blocks_tests;
+#[cfg(test)]
+pub mod audit_tests;
+#[cfg(test)]
+pub mod bulk_load_tests;
+#[cfg(test)]
+pub mod compression_tests;
+#[cfg(test)]
+pub mod dictionary_tests;
+#[cfg(test)]
+pub mod encryption_tests;
+#[cfg(test)]
+pub mod existence_filter_tests;
+#[cfg(test)]
+pub mod group_commit_tests;
+#[cfg(test)]
+pub mod integrity_tests;
+#[cfg(test)]
+pub mod map_size_tests;
+#[cfg(test)]
+pub mod test_utils;
+#[cfg(test)]
+pub mod trash_tests;
+#[cfg(test)]
+pub mod versions_tests;
+#[cfg(test)]
+pub mod wal_tests;
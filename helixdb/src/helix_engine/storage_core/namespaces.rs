@@ -0,0 +1,155 @@
+use crate::helix_engine::{graph_core::config::NamespaceConfig, types::GraphError};
+use std::{collections::HashMap, ops::RangeInclusive};
+use twox_hash::XxHash64;
+
+#[cfg(feature = "compiler")]
+use crate::helixc::parser::helix_parser::{Content, HelixParser, HxFile, Source};
+
+/// Set in the tag of every namespace, the ids of the labels outside any namespace stay
+/// below it
+const NAMESPACED: u16 = 0x8000;
+/// Ids start with the 16 bit tag of their namespace
+const TAG_SHIFT: u32 = 112;
+
+/// Namespaces of the applications sharing the instance, and the labels declared in them.
+///
+/// The ids of the nodes, edges and vectors of a namespace start with the tag of the
+/// namespace, so each namespace occupies its own range of keys and scans over one of its
+/// labels never read the data of another. Labels outside any namespace keep their
+/// generated ids, which all fall below the ranges of the namespaces.
+#[derive(Debug, Clone, Default)]
+pub struct Namespaces {
+    /// Label -> namespace
+    labels: HashMap<String, String>,
+    /// Namespace -> tag
+    tags: HashMap<String, u16>,
+}
+
+impl Namespaces {
+    pub fn new(config: &HashMap<String, NamespaceConfig>) -> Result<Namespaces, GraphError> {
+        let mut namespaces = Namespaces::default();
+        let mut names = HashMap::new();
+        for (name, namespace) in config {
+            let tag = Self::tag(name);
+            if let Some(other) = names.insert(tag, name) {
+                return Err(GraphError::New(format!(
+                    "Namespaces {} and {} share a tag, rename one of them",
+                    other, name
+                )));
+            }
+            namespaces.tags.insert(name.clone(), tag);
+            for label in namespace.labels.iter() {
+                match namespaces.labels.insert(label.clone(), name.clone()) {
+                    Some(other) if other != *name => {
+                        return Err(GraphError::New(format!(
+                            "{} is declared in both namespaces {} and {}",
+                            label, other, name
+                        )))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(namespaces)
+    }
+
+    /// Tag the ids of the namespace start with, derived from its name so it stays the same
+    /// across restarts
+    pub fn tag(namespace: &str) -> u16 {
+        NAMESPACED | (XxHash64::oneshot(0, namespace.as_bytes()) as u16 & !NAMESPACED)
+    }
+
+    /// Moves a generated id into the range of the namespace with the tag.
+    ///
+    /// The low bits of the id are dropped rather than the high ones, so ids generated
+    /// from the time keep their order.
+    pub fn tag_id(tag: u16, id: u128) -> u128 {
+        ((tag as u128) << TAG_SHIFT) | (id >> (128 - TAG_SHIFT))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Namespace the label is declared in, `None` if it is outside any namespace
+    pub fn namespace_of(&self, label: &str) -> Option<&str> {
+        self.labels.get(label).map(|name| name.as_str())
+    }
+
+    /// Namespace the id was generated in
+    pub fn namespace_of_id(&self, id: u128) -> Option<&str> {
+        let tag = (id >> TAG_SHIFT) as u16;
+        if tag & NAMESPACED == 0 {
+            return None;
+        }
+        self.tags
+            .iter()
+            .find(|(_, t)| **t == tag)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// The generated id of a new item of the label, moved into the range of its namespace
+    pub fn id_for(&self, label: &str, id: u128) -> u128 {
+        match self.labels.get(label) {
+            Some(name) => Self::tag_id(self.tags[name], id),
+            None => id,
+        }
+    }
+
+    /// Range holding the ids of the items of the label and the other labels of its
+    /// namespace
+    pub fn id_range(&self, label: &str) -> RangeInclusive<u128> {
+        let (start, end) = match self.labels.get(label) {
+            Some(name) => {
+                let tag = self.tags[name];
+                (Self::tag_id(tag, 0), Self::tag_id(tag, u128::MAX))
+            }
+            None => (0, Self::tag_id(NAMESPACED, 0) - 1),
+        };
+        start..=end
+    }
+}
+
+/// Adds the labels declared in the `NAMESPACE` blocks of a `.hx` file, such as
+/// `schema.hx`, to the config of their namespace
+#[cfg(feature = "compiler")]
+pub fn add_schema_labels(
+    namespaces: &mut HashMap<String, NamespaceConfig>,
+    schema: &str,
+) -> Result<(), GraphError> {
+    let content = Content {
+        content: String::new(),
+        source: Source::default(),
+        files: vec![HxFile {
+            name: "schema.hx".to_string(),
+            content: schema.to_string(),
+        }],
+    };
+    let source = HelixParser::parse_source(&content)
+        .map_err(|e| GraphError::New(format!("Invalid schema: {}", e)))?;
+    let labels = source
+        .node_schemas
+        .iter()
+        .map(|schema| (&schema.namespace, &schema.name.1))
+        .chain(
+            source
+                .edge_schemas
+                .iter()
+                .map(|schema| (&schema.namespace, &schema.name.1)),
+        )
+        .chain(
+            source
+                .vector_schemas
+                .iter()
+                .map(|schema| (&schema.namespace, &schema.name)),
+        );
+    for (namespace, label) in labels {
+        if let Some(namespace) = namespace {
+            let labels = &mut namespaces.entry(namespace.clone()).or_default().labels;
+            if !labels.contains(label) {
+                labels.push(label.clone());
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,8 +1,32 @@
 use crate::{
     helix_engine::{
-        bm25::bm25::{HBM25Config, BM25},
-        graph_core::config::Config,
-        storage_core::storage_methods::StorageMethods,
+        analytics::adjacency::AdjacencyCache,
+        bm25::bm25::{BM25Flatten, HBM25Config, BM25},
+        cdc::cdc::{ChangeEvent, ChangeLog, ChangeOp, ChangeTarget},
+        graph_core::{
+            config::{Config, QueryLimitsConfig},
+            projection,
+            snapshot::Snapshots,
+            traversal_iter::ParallelFanout,
+        },
+        ingest_jobs::ingest_jobs::JobStore,
+        migration::migration::SchemaHistory,
+        stats::stats::{Direction, GraphStats},
+        storage_core::{
+            adjacency_blocks::{AdjacencyBlocks, AdjacentEdge, AdjacentEdges},
+            audit::AuditLog,
+            compression::Compression,
+            dictionary::Dictionary,
+            encryption::Encryption,
+            existence_filter::ExistenceFilter,
+            map_size::MapSize,
+            group_commit::GroupCommit,
+            namespaces::Namespaces,
+            storage_methods::{SearchMethods, StorageMethods},
+            trash::Trash,
+            versions::Versions,
+            wal::{WalEntry, WalOp, WriteAheadLog},
+        },
         types::GraphError,
         vector_core::{
             hnsw::HNSW,
@@ -10,19 +34,23 @@ use crate::{
             vector_core::{HNSWConfig, VectorCore},
         },
     },
+    helix_sharding::shard_map::ShardMap,
+    helix_storage::StorageBackend,
     protocol::{
         filterable::Filterable,
-        items::{Edge, Node},
-        label_hash::hash_label,
+        item_view::{EdgeView, NodeView},
+        items::{v6_uuid, Edge, Node},
         value::Value,
     },
 };
 
 use crate::helix_storage::heed3::byteorder::BE;
-use crate::helix_storage::heed3::{types::*, Database, DatabaseFlags, Env, EnvOpenOptions, RoTxn, RwTxn, WithTls};
-use std::collections::HashMap;
+use crate::helix_storage::heed3::{types::*, CompactionOption, Database, DatabaseFlags, Env, EnvFlags, EnvOpenOptions, MdbError, PutFlags, RoTxn, RwTxn, WithTls};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
 
 use super::storage_methods::{BasicStorageMethods, DBMethods};
 
@@ -32,6 +60,15 @@ const DB_EDGES: &str = "edges"; // For edge data (e:)
 const DB_OUT_EDGES: &str = "out_edges"; // For outgoing edge indices (o:)
 const DB_IN_EDGES: &str = "in_edges"; // For incoming edge indices (i:)
 
+/// Source nodes whose adjacency lists traversal steps read together
+pub const ADJACENCY_BATCH_SIZE: usize = 256;
+/// Entries a cursor steps over in about the time it takes to seek a key, batched adjacency
+/// reads walk the range between their first and last key while it is at most this many
+/// entries per key
+const WALK_ENTRIES_PER_SEEK: usize = 32;
+/// Items rewritten per write transaction when migrating to new storage settings
+pub const MIGRATION_BATCH_SIZE: usize = 1024;
+
 // Key prefixes for different types of data
 
 pub struct HelixGraphStorage {
@@ -42,13 +79,145 @@ pub struct HelixGraphStorage {
     pub out_edges_db: Database<Bytes, Bytes>,
     pub in_edges_db: Database<Bytes, Bytes>,
     pub secondary_indices: HashMap<String, Database<Bytes, U128<BE>>>,
+    /// Secondary indices that may only map a value to a single node
+    pub unique_indices: HashSet<String>,
+    /// Secondary indices over edge properties, by property name
+    pub edge_secondary_indices: HashMap<String, Database<Bytes, U128<BE>>>,
+    /// Index of the vectors outside any namespace
     pub vectors: VectorCore,
+    /// Indices of the vectors of each namespace
+    pub namespace_vectors: HashMap<String, VectorCore>,
+    pub namespaces: Namespaces,
     pub bm25: HBM25Config,
+    pub cdc: ChangeLog,
+    pub stats: GraphStats,
+    /// Schema versions the database was migrated through
+    pub schema_history: SchemaHistory,
+    pub wal: WriteAheadLog,
+    pub ingest_jobs: JobStore,
+    pub compression: Compression,
+    /// Encryption of stored nodes and edges
+    pub encryption: Encryption,
+    /// Ids of the labels and property names, used if interning is enabled
+    pub dictionary: Dictionary,
+    /// Compacted adjacency lists of high degree nodes
+    pub adjacency_blocks: AdjacencyBlocks,
+    /// Set if high fanout steps should fetch adjacent items in parallel
+    pub parallel: Option<ParallelFanout>,
+    /// Usage and growth of the memory map of the environment
+    pub map_size: MapSize,
+    /// Set if missing nodes should be ruled out by a bloom filter of the node ids
+    pub existence: Option<ExistenceFilter>,
+    /// Tombstones of the soft deleted nodes and edges
+    pub trash: Trash,
+    /// Past states of the nodes of versioned labels
+    pub versions: Versions,
+    /// Mutating queries run against the graph
+    pub audit: AuditLog,
+    /// Set if the writes of concurrent queries should be committed together
+    pub group_commit: Option<GroupCommit>,
+    /// Execution limits of each query run by the gateway
+    pub query_limits: QueryLimitsConfig,
+    /// Snapshots pinned across queries
+    pub snapshots: Snapshots,
+    /// Adjacency of the latest snapshot analytics ran over
+    pub adjacency_cache: AdjacencyCache,
+    /// Set if the graph is split across instances, new nodes get ids the local shard owns
+    pub shards: Option<ShardMap>,
+    // declared last so the environment is closed before its directory is removed
+    ephemeral_dir: Option<EphemeralDir>,
+}
+
+/// Directory backing in-memory storage, removed once the storage is dropped
+struct EphemeralDir(PathBuf);
+
+impl EphemeralDir {
+    fn new() -> Result<EphemeralDir, GraphError> {
+        // prefer a ram backed filesystem so the data never touches disk
+        let shm = Path::new("/dev/shm");
+        let base = if shm.is_dir() {
+            shm.to_path_buf()
+        } else {
+            std::env::temp_dir()
+        };
+        let path = base.join(format!("helix-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&path)?;
+        Ok(EphemeralDir(path))
+    }
+}
+
+impl Drop for EphemeralDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
 }
 
 impl HelixGraphStorage {
     pub fn new(path: &str, config: Config) -> Result<HelixGraphStorage, GraphError> {
         fs::create_dir_all(path)?;
+        Self::open_env(Path::new(path), config, None)
+    }
+
+    /// Creates storage that only lives as long as the returned value
+    pub fn new_in_memory(config: Config) -> Result<HelixGraphStorage, GraphError> {
+        let dir = EphemeralDir::new()?;
+        Self::open_env(&dir.0.clone(), config, Some(dir))
+    }
+
+    /// Opens the storage for the given backend, `path` is ignored for in-memory storage
+    pub fn open_backend(
+        backend: StorageBackend,
+        path: &str,
+        config: Config,
+    ) -> Result<HelixGraphStorage, GraphError> {
+        match backend {
+            StorageBackend::Lmdb => Self::new(path, config),
+            StorageBackend::InMemory => Self::new_in_memory(config),
+        }
+    }
+
+    /// Whether the storage is discarded once dropped
+    pub fn is_in_memory(&self) -> bool {
+        self.ephemeral_dir.is_some()
+    }
+
+    /// Generates the id of a new node of the label, in the range of the label's namespace
+    /// and owned by the local shard if the graph is sharded
+    pub fn new_node_id(&self, label: &str) -> u128 {
+        let generate = || self.namespaces.id_for(label, v6_uuid());
+        match &self.shards {
+            Some(shards) => shards.local_id(generate),
+            None => generate(),
+        }
+    }
+
+    /// Generates the id of a new edge of the label, in the range of the label's namespace
+    pub fn new_edge_id(&self, label: &str) -> u128 {
+        self.namespaces.id_for(label, v6_uuid())
+    }
+
+    /// Flags new nodes and edges are written with. Their ids only ever grow if there are
+    /// no namespaces, which generate ids in ranges of their own.
+    pub fn new_item_flags(&self) -> PutFlags {
+        match self.namespaces.is_empty() {
+            true => PutFlags::APPEND,
+            false => PutFlags::NO_OVERWRITE,
+        }
+    }
+
+    /// Index holding the vectors of the label
+    pub fn vectors_of(&self, label: &str) -> &VectorCore {
+        self.namespaces
+            .namespace_of(label)
+            .and_then(|namespace| self.namespace_vectors.get(namespace))
+            .unwrap_or(&self.vectors)
+    }
+
+    fn open_env(
+        path: &Path,
+        config: Config,
+        ephemeral_dir: Option<EphemeralDir>,
+    ) -> Result<HelixGraphStorage, GraphError> {
 
         let db_size = if config.db_max_size_gb.unwrap_or(100) >= 9999 {
             9998
@@ -57,16 +226,22 @@ impl HelixGraphStorage {
         };
 
         // Configure and open LMDB environment
-        let graph_env = unsafe {
-            EnvOpenOptions::new()
-                .map_size(db_size * 1024 * 1024 * 1024) // GB
-                .max_dbs(20)
-                .max_readers(200)
-                // .flags(EnvFlags::NO_META_SYNC)
-                // .flags(EnvFlags::MAP_ASYNC)
-                // .flags(EnvFlags::NO_SYNC)
-                .open(Path::new(path))?
-        };
+        let mut env_options = EnvOpenOptions::new();
+        env_options
+            .map_size(db_size * 1024 * 1024 * 1024) // GB
+            // every namespace adds the databases of its vector index
+            .max_dbs(64 + 4 * config.namespaces.len() as u32)
+            .max_readers(200);
+        // .flags(EnvFlags::NO_META_SYNC)
+        // .flags(EnvFlags::MAP_ASYNC)
+        // .flags(EnvFlags::NO_SYNC)
+        if ephemeral_dir.is_some() {
+            // nothing needs to survive a crash so syncing to disk is pointless
+            unsafe {
+                env_options.flags(EnvFlags::NO_SYNC | EnvFlags::NO_META_SYNC);
+            }
+        }
+        let graph_env = unsafe { env_options.open(path)? };
 
         let mut wtxn = graph_env.write_txn()?;
 
@@ -95,48 +270,868 @@ impl HelixGraphStorage {
             .create(&mut wtxn)?;
 
         // Create secondary indices
+        let unique_indices: HashSet<String> = config
+            .graph_config
+            .unique_indices
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
         let mut secondary_indices = HashMap::new();
-        if let Some(indexes) = config.graph_config.secondary_indices {
-            for index in indexes {
-                secondary_indices.insert(
-                    index.clone(),
-                    graph_env
-                        .database_options()
-                        .types::<Bytes, U128<BE>>()
-                        .flags(DatabaseFlags::DUP_SORT)
-                        .name(&index)
-                        .create(&mut wtxn)?,
-                );
+        let indexes = config.graph_config.secondary_indices.unwrap_or_default();
+        for index in indexes.into_iter().chain(unique_indices.iter().cloned()) {
+            if secondary_indices.contains_key(&index) {
+                continue;
             }
+            secondary_indices.insert(
+                index.clone(),
+                graph_env
+                    .database_options()
+                    .types::<Bytes, U128<BE>>()
+                    .flags(DatabaseFlags::DUP_SORT)
+                    .name(&index)
+                    .create(&mut wtxn)?,
+            );
+        }
+        let mut edge_secondary_indices = HashMap::new();
+        let indexes = config
+            .graph_config
+            .edge_secondary_indices
+            .unwrap_or_default();
+        for index in indexes {
+            // named apart from the node indices so both can exist for the same property
+            let db = graph_env
+                .database_options()
+                .types::<Bytes, U128<BE>>()
+                .flags(DatabaseFlags::DUP_SORT)
+                .name(&Self::edge_index_name(&index))
+                .create(&mut wtxn)?;
+            edge_secondary_indices.insert(index, db);
         }
 
+        let namespaces = Namespaces::new(&config.namespaces)?;
         let vectors = VectorCore::new(
             &graph_env,
             &mut wtxn,
-            HNSWConfig::new(
-                config.vector_config.m,
-                config.vector_config.ef_construction,
-                config.vector_config.ef_search,
-            ),
+            HNSWConfig::from_config(&config.vector_config),
         )?;
+        let mut namespace_vectors = HashMap::new();
+        for (name, namespace) in config.namespaces.iter() {
+            let vector_config = namespace
+                .vector_config
+                .as_ref()
+                .unwrap_or(&config.vector_config);
+            let hnsw_config = HNSWConfig::from_config(vector_config);
+            namespace_vectors.insert(
+                name.clone(),
+                VectorCore::new_in_namespace(&graph_env, &mut wtxn, hnsw_config, name)?,
+            );
+        }
         let bm25 = HBM25Config::new(&graph_env, &mut wtxn)?;
+        let cdc = ChangeLog::new(&graph_env, &mut wtxn, config.cdc, &config.cdc_retention)?;
+        let stats = GraphStats::new(&graph_env, &mut wtxn, config.stats)?;
+        let schema_history = SchemaHistory::new(&graph_env, &mut wtxn, config.strict_schema)?;
+        let wal = WriteAheadLog::new(&graph_env, &mut wtxn, path, &config.wal)?;
+        let ingest_jobs = JobStore::new(&graph_env, &mut wtxn)?;
+        let empty = nodes_db.is_empty(&wtxn)? && edges_db.is_empty(&wtxn)?;
+        let dictionary = Dictionary::new(&graph_env, &mut wtxn, config.intern_strings, empty)?;
+        let adjacency_blocks = AdjacencyBlocks::new(&graph_env, &mut wtxn, &config.adjacency)?;
+        let existence = ExistenceFilter::new(&config.existence_filter, &wtxn, &nodes_db)?;
+        let trash = Trash::new(&graph_env, &mut wtxn, &config.soft_delete)?;
+        let versions = Versions::new(
+            &graph_env,
+            &mut wtxn,
+            &config.graph_config.versioned_labels,
+        )?;
+        let encryption = Encryption::new(&config.encryption)?;
+        let audit = AuditLog::new(&graph_env, &mut wtxn, &config.audit, encryption.clone())?;
 
         wtxn.commit()?;
-        Ok(Self {
+        let storage = Self {
             graph_env,
             nodes_db,
             edges_db,
             out_edges_db,
             in_edges_db,
             secondary_indices,
+            unique_indices,
+            edge_secondary_indices,
             vectors,
+            namespace_vectors,
+            namespaces,
             bm25,
+            cdc,
+            stats,
+            schema_history,
+            wal,
+            ingest_jobs,
+            compression: Compression::new(&config.compression),
+            encryption,
+            dictionary,
+            adjacency_blocks,
+            parallel: ParallelFanout::new(&config.parallel)?,
+            map_size: MapSize::new(&config.map_resize),
+            existence,
+            trash,
+            versions,
+            audit,
+            group_commit: GroupCommit::new(&config.group_commit),
+            query_limits: config.query_limits,
+            snapshots: Snapshots::new(config.max_snapshots),
+            adjacency_cache: AdjacencyCache::default(),
+            shards: config.sharding.as_ref().map(ShardMap::new).transpose()?,
+            ephemeral_dir,
+        };
+
+        // roll forward to the end of the log (or the recovery point), e.g. after a backup
+        // has been restored
+        let replayed = storage.wal.replay(&storage.graph_env, config.wal.recover_to, |txn, entry| {
+            storage.apply_wal_entry(txn, entry)
+        })?;
+        if replayed > 0 {
+            println!("Replayed {} entries of the write-ahead log", replayed);
+        }
+
+        if config.compression.migrate || config.encryption.migrate {
+            let migrated = storage.migrate_compression()?;
+            println!(
+                "Rewrote {} nodes and edges with the current compression and encryption settings",
+                migrated
+            );
+        }
+
+        if config.adjacency.compact {
+            let compacted = storage.compact_adjacency()?;
+            println!("Compacted {} adjacency lists", compacted);
+        }
+
+        // statistics were just enabled for an existing database
+        if storage.stats.is_enabled() {
+            let txn = storage.graph_env.read_txn()?;
+            let missing = storage.stats.is_empty(&txn)? && !storage.nodes_db.is_empty(&txn)?;
+            drop(txn);
+            if missing {
+                storage.rebuild_stats()?;
+            }
+        }
+        Ok(storage)
+    }
+
+    /// Recomputes the graph statistics from the stored nodes, edges and secondary
+    /// indices.
+    ///
+    /// Needed after statistics have been disabled for a while, since writes in the
+    /// meantime weren't recorded. Leaves the statistics empty if they are disabled.
+    pub fn rebuild_stats(&self) -> Result<(), GraphError> {
+        let mut txn = self.graph_env.write_txn()?;
+        self.stats.clear(&mut txn)?;
+
+        let mut labels = Vec::new();
+        for result in self.nodes_db.iter(&txn)? {
+            let (id, bytes) = result?;
+            // the trash counts as deleted
+            if !self.trash.contains(&txn, &id)? {
+                labels.push(
+                    self.node_view(bytes, id)?
+                        .label()
+                        .to_string(),
+                );
+            }
+        }
+        for label in labels {
+            self.stats.node_added(&mut txn, &label)?;
+        }
+
+        let edges = self
+            .get_all_edges(&txn)?
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        for edge in edges {
+            self.stats.edge_added(&mut txn, &edge)?;
+        }
+
+        for (index, db) in self.secondary_indices.iter() {
+            // entries are sorted by value, so a new value starts wherever the key changes
+            let mut new_values = Vec::new();
+            let mut last: Option<Vec<u8>> = None;
+            for result in db.iter(&txn)? {
+                let (key, _) = result?;
+                new_values.push(last.as_deref() != Some(key));
+                last = Some(key.to_vec());
+            }
+            for new_value in new_values {
+                self.stats.index_entry_added(&mut txn, index, new_value)?;
+            }
+        }
+
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Encodes a node for the nodes table, interning its strings if enabled,
+    /// compressing it if it is large and encrypting it if enabled
+    #[inline(always)]
+    pub fn encode_node(&self, txn: &mut RwTxn, node: &Node) -> Result<Vec<u8>, GraphError> {
+        let bytes = match self.dictionary.is_enabled() {
+            true => self.dictionary.encode_node(txn, node)?,
+            false => node.encode_node()?,
+        };
+        self.encryption
+            .encrypt(node.id, self.compression.compress(bytes)?)
+    }
+
+    /// Encodes an edge for the edges table, interning its strings if enabled,
+    /// compressing it if it is large and encrypting it if enabled
+    #[inline(always)]
+    pub fn encode_edge(&self, txn: &mut RwTxn, edge: &Edge) -> Result<Vec<u8>, GraphError> {
+        let bytes = match self.dictionary.is_enabled() {
+            true => self.dictionary.encode_edge(txn, edge)?,
+            false => edge.encode_edge()?,
+        };
+        self.encryption
+            .encrypt(edge.id, self.compression.compress(bytes)?)
+    }
+
+    /// Decodes a node read from the nodes table
+    #[inline(always)]
+    pub fn decode_node(&self, bytes: &[u8], id: u128) -> Result<Node, GraphError> {
+        self.node_view(bytes, id)?.decode()
+    }
+
+    /// Decodes an edge read from the edges table
+    #[inline(always)]
+    pub fn decode_edge(&self, bytes: &[u8], id: u128) -> Result<Edge, GraphError> {
+        self.edge_view(bytes, id)?.decode()
+    }
+
+    /// Views a node read from the nodes table, decrypting it first if it is encrypted
+    #[inline(always)]
+    pub fn node_view<'a>(&'a self, bytes: &'a [u8], id: u128) -> Result<NodeView<'a>, GraphError> {
+        NodeView::new(self.encryption.decrypt(id, bytes)?, id, &self.dictionary)
+    }
+
+    /// Views an edge read from the edges table, decrypting it first if it is encrypted
+    #[inline(always)]
+    pub fn edge_view<'a>(&'a self, bytes: &'a [u8], id: u128) -> Result<EdgeView<'a>, GraphError> {
+        EdgeView::new(self.encryption.decrypt(id, bytes)?, id, &self.dictionary)
+    }
+
+    /// Rewrites every stored node and edge with the current compression, interning and
+    /// encryption settings.
+    ///
+    /// Reads handle compressed, interned, encrypted and plain items alike, so this is only
+    /// needed to compress, intern or encrypt data written before they were enabled, or to
+    /// undo compression and interning after disabling them. Encrypted items can't be read
+    /// once encryption is disabled. Returns the number of rewritten items.
+    pub fn migrate_compression(&self) -> Result<usize, GraphError> {
+        let nodes = self.migrate_table(
+            self.nodes_db,
+            |bytes, id| self.decode_node(bytes, id),
+            |txn, node| self.encode_node(txn, node),
+        )?;
+        let edges = self.migrate_table(
+            self.edges_db,
+            |bytes, id| self.decode_edge(bytes, id),
+            |txn, edge| self.encode_edge(txn, edge),
+        )?;
+        Ok(nodes + edges)
+    }
+
+    /// Rewrites every item of a table in id order, committing every
+    /// `MIGRATION_BATCH_SIZE` items and resuming after the last key of the batch before
+    fn migrate_table<T>(
+        &self,
+        db: Database<U128<BE>, Bytes>,
+        decode: impl Fn(&[u8], u128) -> Result<T, GraphError>,
+        encode: impl Fn(&mut RwTxn, &T) -> Result<Vec<u8>, GraphError>,
+    ) -> Result<usize, GraphError> {
+        let mut migrated = 0;
+        let mut last = None;
+        loop {
+            let mut txn = self.graph_env.write_txn()?;
+            let from = last.map_or(Bound::Unbounded, Bound::Excluded);
+            let batch = db
+                .range(&txn, &(from, Bound::Unbounded))?
+                .take(MIGRATION_BATCH_SIZE)
+                .map(|result| {
+                    let (id, bytes) = result?;
+                    Ok((id, decode(bytes, id)?))
+                })
+                .collect::<Result<Vec<_>, GraphError>>()?;
+            let Some(&(id, _)) = batch.last() else {
+                return Ok(migrated);
+            };
+            for (id, item) in &batch {
+                let bytes = encode(&mut txn, item)?;
+                db.put(&mut txn, id, &bytes)?;
+            }
+            txn.commit()?;
+            migrated += batch.len();
+            last = Some(id);
+        }
+    }
+
+    /// Edges of the node in its adjacency list of a label, as `(adjacent node id, edge id)`.
+    ///
+    /// Compacted edges come first, ordered by adjacent node, followed by the edges added
+    /// since the list was last compacted. Edges in the trash or to a node in it are left out.
+    pub fn adjacency<'t>(
+        &self,
+        txn: &'t RoTxn,
+        node_id: &u128,
+        label: &[u8; 4],
+        direction: Direction,
+    ) -> Result<AdjacentEdges<'t>, GraphError> {
+        let key = match direction {
+            Direction::Out => Self::out_edge_key(node_id, label),
+            Direction::In => Self::in_edge_key(node_id, label),
+        };
+        let edges = AdjacentEdges::new(
+            self.adjacency_blocks
+                .edges(txn, direction, node_id, label)?,
+            self.adjacency_db(direction).get_duplicates(txn, &key)?,
+        );
+        Ok(match self.trash.is_occupied() {
+            true => edges.skipping(self.trash.hider(txn)),
+            false => edges,
         })
     }
 
+    /// Edges of the adjacency lists of a label of several nodes, grouped by node in the order
+    /// of `node_ids`, leaving out the edges [`Self::adjacency`] leaves out.
+    ///
+    /// The keys are read in sorted order, with a single cursor walk over the range between
+    /// the first and last key when the nodes cover enough of the adjacency table, so wide
+    /// frontiers don't reposition the cursor for every node.
+    pub fn adjacency_batch(
+        &self,
+        txn: &RoTxn,
+        node_ids: &[u128],
+        label: &[u8; 4],
+        direction: Direction,
+    ) -> Result<Vec<Vec<AdjacentEdge>>, GraphError> {
+        let mut sorted = node_ids.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let (first, last) = match (sorted.first(), sorted.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Ok(Vec::new()),
+        };
+
+        let key = |node_id: &u128| match direction {
+            Direction::Out => Self::out_edge_key(node_id, label),
+            Direction::In => Self::in_edge_key(node_id, label),
+        };
+        let db = self.adjacency_db(direction);
+        let mut added = vec![Vec::new(); sorted.len()];
+        if db.len(txn)? as usize <= sorted.len() * WALK_ENTRIES_PER_SEEK {
+            let (first, last) = (key(&first), key(&last));
+            let mut position = 0;
+            for entry in db.range(
+                txn,
+                &(Bound::Included(&first[..]), Bound::Included(&last[..])),
+            )? {
+                let (entry_key, data) = entry?;
+                let node_id = u128::from_be_bytes(
+                    entry_key[0..16]
+                        .try_into()
+                        .map_err(|_| GraphError::SliceLengthError)?,
+                );
+                // nodes sorting before this entry have no edges left to read
+                while sorted[position] < node_id {
+                    position += 1;
+                }
+                if sorted[position] == node_id && entry_key[16..] == label[..] {
+                    added[position].push(Self::unpack_adj_edge_data(data)?);
+                }
+            }
+        } else {
+            for (position, node_id) in sorted.iter().enumerate() {
+                if let Some(entries) = db.get_duplicates(txn, &key(node_id))? {
+                    for entry in entries {
+                        added[position].push(Self::unpack_adj_edge_data(entry?.1)?);
+                    }
+                }
+            }
+        }
+
+        node_ids
+            .iter()
+            .map(|node_id| {
+                let mut edges = self
+                    .adjacency_blocks
+                    .edges(txn, direction, node_id, label)?;
+                if let Ok(position) = sorted.binary_search(node_id) {
+                    edges.extend_from_slice(&added[position]);
+                }
+                if self.trash.is_occupied() {
+                    let mut visible = Vec::with_capacity(edges.len());
+                    for edge in edges {
+                        if !self.trash.hides(txn, &edge)? {
+                            visible.push(edge);
+                        }
+                    }
+                    edges = visible;
+                }
+                Ok(edges)
+            })
+            .collect()
+    }
+
+    /// Edges of the node in its adjacency lists of all labels, with the label of their list
+    pub fn node_adjacency(
+        &self,
+        txn: &RoTxn,
+        node_id: &u128,
+        direction: Direction,
+    ) -> Result<Vec<([u8; 4], AdjacentEdge)>, GraphError> {
+        let mut edges = self
+            .adjacency_blocks
+            .node_edges(txn, direction, node_id)?;
+        for result in self
+            .adjacency_db(direction)
+            .prefix_iter(txn, &node_id.to_be_bytes())?
+        {
+            let (key, value) = result?;
+            let label: [u8; 4] = key[16..20]
+                .try_into()
+                .map_err(|_| GraphError::SliceLengthError)?;
+            edges.push((label, Self::unpack_adj_edge_data(value)?));
+        }
+        Ok(edges)
+    }
+
+    /// Number of edges of a label the node has in the direction.
+    ///
+    /// Looked up in the statistics if they are enabled. Otherwise the compacted edges are
+    /// counted from the header of their list, so only the edges added since the list was
+    /// last compacted are read.
+    pub fn degree(
+        &self,
+        txn: &RoTxn,
+        node_id: &u128,
+        edge_label: &str,
+        direction: Direction,
+    ) -> Result<u64, GraphError> {
+        if self.stats.is_enabled() {
+            return self.stats.degree(txn, node_id, edge_label, direction);
+        }
+        let label = self.dictionary.label_key(edge_label);
+        // edges in the trash are only left out by reading them
+        if self.trash.is_occupied() {
+            return Ok(self.adjacency(txn, node_id, &label, direction)?.count() as u64);
+        }
+        let key = match direction {
+            Direction::Out => Self::out_edge_key(node_id, &label),
+            Direction::In => Self::in_edge_key(node_id, &label),
+        };
+        let added = match self.adjacency_db(direction).get_duplicates(txn, &key)? {
+            Some(entries) => entries.count() as u64,
+            None => 0,
+        };
+        Ok(self
+            .adjacency_blocks
+            .degree(txn, direction, node_id, &label)?
+            + added)
+    }
+
+    /// Moves the edges of adjacency lists holding at least `adjacency.min_degree` edges
+    /// of a label into compacted blocks.
+    ///
+    /// Lists compacted before take in the edges added to them since. Returns the number
+    /// of compacted lists.
+    pub fn compact_adjacency(&self) -> Result<usize, GraphError> {
+        let mut txn = self.graph_env.write_txn()?;
+        let mut compacted = 0;
+        for direction in [Direction::Out, Direction::In] {
+            let db = self.adjacency_db(direction);
+            // number of edges added to each list since it was last compacted
+            let mut added: Vec<([u8; 20], u64)> = Vec::new();
+            for result in db.lazily_decode_data().iter(&txn)? {
+                let (key, _) = result?;
+                match added.last_mut() {
+                    Some((last, count)) if last[..] == key[..] => *count += 1,
+                    _ => added.push((
+                        key.try_into().map_err(|_| GraphError::SliceLengthError)?,
+                        1,
+                    )),
+                }
+            }
+
+            for (key, count) in added {
+                let node_id = u128::from_be_bytes(key[0..16].try_into().unwrap());
+                let label: [u8; 4] = key[16..20].try_into().unwrap();
+                let degree = self
+                    .adjacency_blocks
+                    .degree(&txn, direction, &node_id, &label)?
+                    + count;
+                if degree < self.adjacency_blocks.min_degree as u64 {
+                    continue;
+                }
+                let edges = self
+                    .adjacency(&txn, &node_id, &label, direction)?
+                    .collect::<Result<Vec<_>, GraphError>>()?;
+                self.adjacency_blocks
+                    .write(&mut txn, direction, &node_id, &label, edges)?;
+                db.delete(&mut txn, &key)?;
+                compacted += 1;
+            }
+        }
+        txn.commit()?;
+        Ok(compacted)
+    }
+
+    /// Records a node written to the nodes table in the existence filter
+    #[inline(always)]
+    pub fn node_written(&self, id: &u128) {
+        if let Some(existence) = &self.existence {
+            existence.insert(id);
+        }
+    }
+
+    pub(crate) fn adjacency_db(&self, direction: Direction) -> &Database<Bytes, Bytes> {
+        match direction {
+            Direction::Out => &self.out_edges_db,
+            Direction::In => &self.in_edges_db,
+        }
+    }
+
+    /// Applies entries of the write-ahead log of a primary in a single write transaction,
+    /// for replicas following it.
+    ///
+    /// Entries already contained in the database are skipped. Fails without applying
+    /// anything if an entry is missing between the last applied one and the entries.
+    /// The entries are journaled to the replica's own log if it is enabled, so it can
+    /// take over from the primary. Returns the seq of the last entry contained in the database afterwards.
+    pub fn apply_replicated(&self, entries: &[WalEntry]) -> Result<u64, GraphError> {
+        let mut txn = self.graph_env.write_txn()?;
+        let last_seq = self.apply_replicated_in(&mut txn, entries)?;
+        txn.commit()?;
+        Ok(last_seq)
+    }
+
+    /// [`HelixGraphStorage::apply_replicated`] within the given write transaction
+    pub fn apply_replicated_in(
+        &self,
+        txn: &mut RwTxn,
+        entries: &[WalEntry],
+    ) -> Result<u64, GraphError> {
+        let mut last_seq = self.wal.last_seq(txn)?;
+        for entry in entries {
+            if entry.seq <= last_seq {
+                continue;
+            }
+            if entry.seq != last_seq + 1 {
+                return Err(GraphError::StorageError(format!(
+                    "Missing write-ahead log entries {} to {}",
+                    last_seq + 1,
+                    entry.seq - 1
+                )));
+            }
+            self.apply_wal_entry(txn, entry)?;
+            self.wal.append(txn, entry)?;
+            last_seq = entry.seq;
+        }
+        Ok(last_seq)
+    }
+
+    /// Applies a write journaled in the write-ahead log
+    fn apply_wal_entry(&self, txn: &mut RwTxn, entry: &WalEntry) -> Result<(), GraphError> {
+        match &entry.op {
+            WalOp::PutNode(id, bytes) => {
+                let node = Node::decode_node(&self.encryption.decrypt(*id, bytes)?, *id)?;
+                let existed = self.nodes_db.get(txn, Self::node_key(id))?.is_some();
+                let bytes = self.encode_node(txn, &node)?;
+                self.nodes_db.put(txn, Self::node_key(id), &bytes)?;
+                self.node_written(id);
+                self.record_version(txn, &node, entry.timestamp)?;
+                if !existed {
+                    self.stats.node_added(txn, &node.label)?;
+                }
+                if let Some(properties) = &node.properties {
+                    for (index, db) in self.secondary_indices.iter() {
+                        if let Some(value) = properties.get(index) {
+                            self.put_index_entry(txn, index, db, &bincode::serialize(value)?, id)?;
+                        }
+                    }
+                    let mut data = properties.flatten_bm25();
+                    data.push_str(&node.label);
+                    match existed {
+                        true => self.bm25.update_doc(txn, *id, &data)?,
+                        false => self.bm25.insert_doc(txn, *id, &data)?,
+                    }
+                }
+            }
+            WalOp::PutEdge(id, bytes) => {
+                let edge = Edge::decode_edge(&self.encryption.decrypt(*id, bytes)?, *id)?;
+                let label_hash = self.dictionary.intern_label_key(txn, &edge.label)?;
+                if self.edges_db.get(txn, Self::edge_key(id))?.is_none() {
+                    self.stats.edge_added(txn, &edge)?;
+                }
+                let bytes = self.encode_edge(txn, &edge)?;
+                self.edges_db.put(txn, Self::edge_key(id), &bytes)?;
+                self.out_edges_db.put(
+                    txn,
+                    &Self::out_edge_key(&edge.from_node, &label_hash),
+                    &Self::pack_edge_data(&edge.to_node, id),
+                )?;
+                self.in_edges_db.put(
+                    txn,
+                    &Self::in_edge_key(&edge.to_node, &label_hash),
+                    &Self::pack_edge_data(&edge.from_node, id),
+                )?;
+                self.index_edge_properties(txn, &edge)?;
+            }
+            WalOp::DropNode(id) => {
+                // purged from the trash, the removal was recorded when it was trashed
+                if !self.trash.contains(txn, id)? {
+                    self.record_removal(txn, id, entry.timestamp)?;
+                }
+                match self.remove_node(txn, id) {
+                    Ok(()) | Err(GraphError::NodeNotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            WalOp::DropEdge(id) => match self.remove_edge(txn, id) {
+                Ok(()) | Err(GraphError::EdgeNotFound) => {}
+                Err(e) => return Err(e),
+            },
+            WalOp::TrashNode(id) => {
+                if !self.trash.contains(txn, id)? {
+                    self.record_removal(txn, id, entry.timestamp)?;
+                }
+                match self.trash_node(txn, id, entry.timestamp) {
+                    Ok(()) | Err(GraphError::NodeNotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            WalOp::TrashEdge(id) => match self.trash_edge(txn, id, entry.timestamp) {
+                Ok(()) | Err(GraphError::EdgeNotFound) => {}
+                Err(e) => return Err(e),
+            },
+            WalOp::Restore(id) => {
+                if self.trash.contains(txn, id)? {
+                    self.restore_from_trash(txn, id)?;
+                }
+            }
+            WalOp::Noop => {}
+        }
+        Ok(())
+    }
+
+    /// Writes committed transactions still pending in the write-ahead log to the log
+    /// file and forces both the log and the database to disk
+    pub fn flush(&self) -> Result<(), GraphError> {
+        self.wal.ship(&self.graph_env)?;
+        self.wal.sync()?;
+        self.graph_env.force_sync()?;
+        Ok(())
+    }
+
+    /// Copies the database to `dir` while it keeps serving requests.
+    ///
+    /// The write-ahead log is flushed first so it covers everything up to the backup.
+    /// Returns the seq of the last log entry contained in the backup. Writes committed
+    /// while copying may end up in the backup as well, replaying skips those.
+    pub fn backup(&self, dir: &Path) -> Result<u64, GraphError> {
+        self.wal.ship(&self.graph_env)?;
+        self.wal.sync()?;
+        fs::create_dir_all(dir)?;
+        // the read txn has to be closed before copying, which opens its own
+        let last_seq = {
+            let txn = self.graph_env.read_txn()?;
+            self.wal.last_seq(&txn)?
+        };
+        self.graph_env
+            .copy_to_path(dir.join("data.mdb"), CompactionOption::Enabled)?;
+        Ok(last_seq)
+    }
+
+    /// Restores a backup made with [`HelixGraphStorage::backup`] into the database
+    /// directory at `path`.
+    ///
+    /// The database must not be open. Opening it afterwards with the write-ahead log
+    /// enabled rolls it forward, to `recover_to` if set in the config.
+    pub fn restore(backup_dir: &Path, path: &Path) -> Result<(), GraphError> {
+        fs::create_dir_all(path)?;
+        fs::copy(backup_dir.join("data.mdb"), path.join("data.mdb"))?;
+        let lock = path.join("lock.mdb");
+        if lock.exists() {
+            fs::remove_file(lock)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a node to a secondary index under the encoded value `key`.
+    ///
+    /// The entry is only recorded in the statistics if the node wasn't indexed under
+    /// the value already.
+    pub fn put_index_entry(
+        &self,
+        txn: &mut RwTxn,
+        index: &str,
+        db: &Database<Bytes, U128<BE>>,
+        key: &[u8],
+        id: &u128,
+    ) -> Result<(), GraphError> {
+        let new_value = self.stats.is_enabled() && db.get(txn, key)?.is_none();
+        match db.put_with_flags(txn, PutFlags::NO_DUP_DATA, key, id) {
+            Ok(()) => self.stats.index_entry_added(txn, index, new_value),
+            Err(crate::helix_storage::heed3::Error::Mdb(MdbError::KeyExist)) => Ok(()),
+            Err(e) => Err(GraphError::from(e)),
+        }
+    }
+
+    /// Fails with [`GraphError::UniqueViolation`] if `index` is a unique index and a node
+    /// other than `id` already has `value` for it.
+    ///
+    /// Index entries aren't removed when a node is updated or dropped, so every entry is
+    /// checked against the node it points to before it counts as a duplicate.
+    pub fn check_unique(
+        &self,
+        txn: &RoTxn,
+        index: &str,
+        value: &Value,
+        id: &u128,
+    ) -> Result<(), GraphError> {
+        if !self.unique_indices.contains(index) {
+            return Ok(());
+        }
+        let Some(db) = self.secondary_indices.get(index) else {
+            return Ok(());
+        };
+        let Some(entries) = db.get_duplicates(txn, &bincode::serialize(value)?)? else {
+            return Ok(());
+        };
+        for result in entries {
+            let (_, other_id) = result?;
+            if other_id == *id {
+                continue;
+            }
+            match self.get_node(txn, &other_id) {
+                Ok(other) if other.check_property(index).is_ok_and(|v| v == value) => {
+                    return Err(GraphError::UniqueViolation {
+                        index: index.to_string(),
+                        value: value.to_string(),
+                    });
+                }
+                Ok(_) | Err(GraphError::NodeNotFound) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Name of the LMDB database backing the edge index over `property`
+    #[inline(always)]
+    pub fn edge_index_name(property: &str) -> String {
+        format!("edge_index_{}", property)
+    }
+
+    /// Adds an edge to the edge index over `index` under `value`
+    pub fn put_edge_index_entry(
+        &self,
+        txn: &mut RwTxn,
+        index: &str,
+        value: &Value,
+        id: &u128,
+    ) -> Result<(), GraphError> {
+        let db = self
+            .edge_secondary_indices
+            .get(index)
+            .ok_or_else(|| GraphError::New(format!("Secondary Index {} not found", index)))?;
+        match db.put_with_flags(txn, PutFlags::NO_DUP_DATA, &bincode::serialize(value)?, id) {
+            Ok(()) | Err(crate::helix_storage::heed3::Error::Mdb(MdbError::KeyExist)) => Ok(()),
+            Err(e) => Err(GraphError::from(e)),
+        }
+    }
+
+    /// Gets the edges whose property of the given edge index is `value`.
+    ///
+    /// Entries aren't removed when an edge is dropped or changed, so only edges that
+    /// still exist and still have the value are returned.
+    pub fn edge_from_index(
+        &self,
+        txn: &RoTxn,
+        index: &str,
+        value: &Value,
+    ) -> Result<Vec<Edge>, GraphError> {
+        let db = self
+            .edge_secondary_indices
+            .get(index)
+            .ok_or_else(|| GraphError::New(format!("Secondary Index {} not found", index)))?;
+        let mut edges = Vec::new();
+        if let Some(entries) = db.get_duplicates(txn, &bincode::serialize(value)?)? {
+            for entry in entries {
+                let (_, edge_id) = entry?;
+                match self.get_edge(txn, &edge_id) {
+                    Ok(edge)
+                        if edge
+                            .properties
+                            .as_ref()
+                            .and_then(|properties| properties.get(index))
+                            == Some(value) =>
+                    {
+                        edges.push(edge)
+                    }
+                    Ok(_) | Err(GraphError::EdgeNotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(edges)
+    }
+
+    /// Adds an edge to every edge index over one of its properties
+    pub fn index_edge_properties(&self, txn: &mut RwTxn, edge: &Edge) -> Result<(), GraphError> {
+        let Some(properties) = &edge.properties else {
+            return Ok(());
+        };
+        for index in self.edge_secondary_indices.keys() {
+            if let Some(value) = properties.get(index) {
+                self.put_edge_index_entry(txn, index, value, &edge.id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Every stored node outside the trash, in id order, decoded as it is read
+    pub fn get_all_nodes<'a>(
+        &'a self,
+        txn: &'a RoTxn,
+    ) -> Result<impl Iterator<Item = Result<Node, GraphError>> + 'a, GraphError> {
+        Ok(self.nodes_db.iter(txn)?.filter_map(move |result| {
+            let (id, bytes) = match result {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(e.into())),
+            };
+            match self.trash.contains(txn, &id) {
+                Ok(false) => Some(self.decode_node(bytes, id)),
+                Ok(true) => None,
+                Err(e) => Some(Err(e)),
+            }
+        }))
+    }
+
+    /// Every stored edge outside the trash, in id order, decoded as it is read
+    pub fn get_all_edges<'a>(
+        &'a self,
+        txn: &'a RoTxn,
+    ) -> Result<impl Iterator<Item = Result<Edge, GraphError>> + 'a, GraphError> {
+        Ok(self.edges_db.iter(txn)?.filter_map(move |result| {
+            let (id, bytes) = match result {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(e.into())),
+            };
+            match self.trash.contains(txn, &id) {
+                Ok(false) => Some(self.decode_edge(bytes, id)),
+                Ok(true) => None,
+                Err(e) => Some(Err(e)),
+            }
+        }))
+    }
+
     pub fn get_random_node(&self, txn: &RoTxn) -> Result<Node, GraphError> {
         match self.nodes_db.first(&txn)? {
-            Some((_, data)) => Ok(bincode::deserialize(data)?),
+            Some((id, data)) => self.decode_node(data, id),
             None => Err(GraphError::NodeNotFound),
         }
     }
@@ -235,6 +1230,17 @@ impl HelixGraphStorage {
     }
 
     pub fn get_vector(&self, txn: &RoTxn, id: &u128) -> Result<HVector, GraphError> {
+        // the random ids of the vectors outside any namespace can start with the tag of
+        // one, so those are looked up there as well
+        if let Some(vectors) = self
+            .namespaces
+            .namespace_of_id(*id)
+            .and_then(|namespace| self.namespace_vectors.get(namespace))
+        {
+            if let Ok(vector) = vectors.get_vector(txn, *id, 0, true) {
+                return Ok(vector);
+            }
+        }
         let vector = self.vectors.get_vector(txn, *id, 0, true)?;
         Ok(vector)
     }
@@ -303,9 +1309,13 @@ impl BasicStorageMethods for HelixGraphStorage {
 impl StorageMethods for HelixGraphStorage {
     #[inline(always)]
     fn check_exists(&self, txn: &RoTxn, id: &u128) -> Result<bool, GraphError> {
-        // let txn = txn.read_txn();
+        if let Some(existence) = &self.existence {
+            if !existence.may_contain(id) {
+                return Ok(false);
+            }
+        }
         let exists = self.nodes_db.get(txn, Self::node_key(id))?.is_some();
-        Ok(exists)
+        Ok(exists && !self.trash.contains(txn, id)?)
     }
 
     #[inline(always)]
@@ -314,10 +1324,14 @@ impl StorageMethods for HelixGraphStorage {
             Some(data) => data,
             None => return Err(GraphError::NodeNotFound),
         };
-        let node: Node = match Node::decode_node(&node, *id) {
-            Ok(node) => node,
-            Err(e) => return Err(e),
-        };
+        if self.trash.contains(txn, id)? {
+            return Err(GraphError::NodeNotFound);
+        }
+        // traversals reading only some properties decode just those
+        if let Some(fields) = projection::fields() {
+            return self.node_view(node, *id)?.decode_only(fields);
+        }
+        let node: Node = self.decode_node(node, *id)?;
         Ok(node)
     }
 
@@ -327,10 +1341,13 @@ impl StorageMethods for HelixGraphStorage {
             Some(data) => data,
             None => return Err(GraphError::EdgeNotFound),
         };
-        let edge: Edge = match Edge::decode_edge(&edge, *id) {
-            Ok(edge) => edge,
-            Err(e) => return Err(e),
-        };
+        if self.trash.contains(txn, id)? {
+            return Err(GraphError::EdgeNotFound);
+        }
+        if let Some(fields) = projection::fields() {
+            return self.edge_view(edge, *id)?.decode_only(fields);
+        }
+        let edge: Edge = self.decode_edge(edge, *id)?;
         Ok(edge)
     }
 
@@ -356,63 +1373,93 @@ impl StorageMethods for HelixGraphStorage {
     // }
 
     fn drop_node(&self, txn: &mut RwTxn, id: &u128) -> Result<(), GraphError> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.record_removal(txn, id, now)?;
+        if self.trash.is_enabled() {
+            self.trash_node(txn, id, now)?;
+            return self.wal.log(txn, || Ok(WalOp::TrashNode(*id)));
+        }
+        self.remove_node(txn, id)?;
+        self.wal.log(txn, || Ok(WalOp::DropNode(*id)))
+    }
+
+    fn drop_edge(&self, txn: &mut RwTxn, edge_id: &u128) -> Result<(), GraphError> {
+        if self.trash.is_enabled() {
+            self.trash_edge(txn, edge_id, chrono::Utc::now().timestamp_millis())?;
+            return self.wal.log(txn, || Ok(WalOp::TrashEdge(*edge_id)));
+        }
+        self.remove_edge(txn, edge_id)?;
+        self.wal.log(txn, || Ok(WalOp::DropEdge(*edge_id)))
+    }
+
+}
+
+impl HelixGraphStorage {
+    /// Removes a node and its edges without journaling it in the write-ahead log.
+    ///
+    /// Elements in the trash were recorded as deleted in the statistics and the change
+    /// log when they were moved there, so only their tombstones are removed along with them.
+    pub(crate) fn remove_node(&self, txn: &mut RwTxn, id: &u128) -> Result<(), GraphError> {
         // Get node to get its label
         //let node = self.get_node(txn, id)?;
 
         // Delete outgoing edges
-        let out_edges = {
-            let iter = self.out_edges_db.prefix_iter(&txn, &id.to_be_bytes())?;
-            let capacity = match iter.size_hint() {
-                (_, Some(upper)) => upper,
-                (lower, None) => lower,
-            };
-            let mut out_edges = Vec::with_capacity(capacity);
-
-            for result in iter {
-                let (key, value) = result?;
-                assert_eq!(key.len(), 20);
-                let mut label = [0u8; 4];
-                label.copy_from_slice(&key[16..20]);
-                let (_, edge_id) = Self::unpack_adj_edge_data(&value)?;
-                out_edges.push((edge_id, label));
-            }
-            out_edges
-        };
+        let out_edges = self.node_adjacency(txn, id, Direction::Out)?;
 
         // Delete incoming edges
+        let in_edges = self.node_adjacency(txn, id, Direction::In)?;
 
-        let in_edges = {
-            let iter = self.in_edges_db.prefix_iter(&txn, &id.to_be_bytes())?;
-            let capacity = match iter.size_hint() {
-                (_, Some(c)) => c,
-                (c, None) => c,
-            };
-            let mut in_edges = Vec::with_capacity(capacity);
-
-            for result in iter {
-                let (key, value) = result?;
-                assert_eq!(key.len(), 20);
-                let mut label = [0u8; 4];
-                label.copy_from_slice(&key[16..20]);
-                let (node_id, edge_id) = Self::unpack_adj_edge_data(&value)?;
-                in_edges.push((edge_id, label, node_id));
+        if self.cdc.is_enabled() || self.stats.is_enabled() {
+            // self loops show up in both adjacency lists
+            let mut seen = HashSet::new();
+            for edge_id in out_edges
+                .iter()
+                .chain(in_edges.iter())
+                .map(|(_, (_, edge_id))| edge_id)
+                .filter(|edge_id| seen.insert(**edge_id))
+            {
+                if self.trash.contains(txn, edge_id)? {
+                    continue;
+                }
+                let edge = self.get_edge(txn, edge_id)?;
+                self.cdc.record(
+                    txn,
+                    ChangeEvent::new(ChangeOp::Delete, ChangeTarget::Edge, *edge_id, &edge.label),
+                )?;
+                self.stats.edge_removed(txn, &edge)?;
             }
-
-            in_edges
-        };
+            if !self.trash.contains(txn, id)? {
+                let node = self.get_node(txn, id)?;
+                self.cdc.record(
+                    txn,
+                    ChangeEvent::new(ChangeOp::Delete, ChangeTarget::Node, *id, &node.label),
+                )?;
+                self.stats.node_removed(txn, &node.label)?;
+            }
+        }
+        for (_, (_, edge_id)) in out_edges.iter().chain(in_edges.iter()) {
+            self.trash.remove(txn, edge_id)?;
+        }
+        self.trash.remove(txn, id)?;
 
         // Delete all related data
-        for (out_edge_id, label_bytes) in out_edges.iter() {
+        for (label_bytes, (other_id, out_edge_id)) in out_edges.iter() {
             // Delete edge data
             self.edges_db.delete(txn, &Self::edge_key(out_edge_id))?;
             self.out_edges_db
                 .delete(txn, &Self::out_edge_key(id, label_bytes))?;
+            // only remove this edge from the other node's adjacency list
+            self.remove_adjacent(txn, Direction::In, other_id, label_bytes, (*id, *out_edge_id))?;
         }
-        for (in_edge_id, label_bytes, other_id) in in_edges.iter() {
+        for (label_bytes, (other_id, in_edge_id)) in in_edges.iter() {
             self.edges_db.delete(txn, &Self::edge_key(in_edge_id))?;
             self.in_edges_db
-                .delete(txn, &Self::in_edge_key(other_id, label_bytes))?;
+                .delete(txn, &Self::in_edge_key(id, label_bytes))?;
+            self.remove_adjacent(txn, Direction::Out, other_id, label_bytes, (*id, *in_edge_id))?;
         }
+        self.adjacency_blocks
+            .remove_node(txn, Direction::Out, id)?;
+        self.adjacency_blocks.remove_node(txn, Direction::In, id)?;
 
         // Delete node data and label
         self.nodes_db.delete(txn, Self::node_key(id))?;
@@ -420,21 +1467,429 @@ impl StorageMethods for HelixGraphStorage {
         Ok(())
     }
 
-    fn drop_edge(&self, txn: &mut RwTxn, edge_id: &u128) -> Result<(), GraphError> {
+    /// Removes an edge without journaling it in the write-ahead log
+    pub(crate) fn remove_edge(&self, txn: &mut RwTxn, edge_id: &u128) -> Result<(), GraphError> {
         // Get edge data first
         let edge_data = match self.edges_db.get(&txn, &Self::edge_key(edge_id))? {
             Some(data) => data,
             None => return Err(GraphError::EdgeNotFound),
         };
-        let edge: Edge = bincode::deserialize(edge_data)?;
-        let label_hash = hash_label(&edge.label, None);
+        let edge = self.decode_edge(edge_data, *edge_id)?;
+        let label_hash = self.dictionary.label_key(&edge.label);
+        // an edge in the trash was recorded as deleted when it was moved there
+        if !self.trash.remove(txn, edge_id)? {
+            self.cdc.record(
+                txn,
+                ChangeEvent::new(ChangeOp::Delete, ChangeTarget::Edge, *edge_id, &edge.label),
+            )?;
+            self.stats.edge_removed(txn, &edge)?;
+        }
         // Delete all edge-related data
         self.edges_db.delete(txn, &Self::edge_key(edge_id))?;
-        self.out_edges_db
-            .delete(txn, &Self::out_edge_key(&edge.from_node, &label_hash))?;
-        self.in_edges_db
-            .delete(txn, &Self::in_edge_key(&edge.to_node, &label_hash))?;
+        // other edges with the same label share the adjacency key
+        self.remove_adjacent(
+            txn,
+            Direction::Out,
+            &edge.from_node,
+            &label_hash,
+            (edge.to_node, *edge_id),
+        )?;
+        self.remove_adjacent(
+            txn,
+            Direction::In,
+            &edge.to_node,
+            &label_hash,
+            (edge.from_node, *edge_id),
+        )?;
+
+        Ok(())
+    }
 
+    /// Removes an edge from an adjacency list, whether or not it was compacted
+    pub(crate) fn remove_adjacent(
+        &self,
+        txn: &mut RwTxn,
+        direction: Direction,
+        node_id: &u128,
+        label: &[u8; 4],
+        (adjacent_id, edge_id): AdjacentEdge,
+    ) -> Result<(), GraphError> {
+        let key = match direction {
+            Direction::Out => Self::out_edge_key(node_id, label),
+            Direction::In => Self::in_edge_key(node_id, label),
+        };
+        let removed = self.adjacency_db(direction).delete_one_duplicate(
+            txn,
+            &key,
+            &Self::pack_edge_data(&adjacent_id, &edge_id),
+        )?;
+        if !removed {
+            self.adjacency_blocks
+                .remove(txn, direction, node_id, label, (adjacent_id, edge_id))?;
+        }
         Ok(())
     }
 }
+
+/// Nodes reached by a path search mapped to their distance in edges from the side the
+/// search started at and the node and edge they were reached through
+type PathParents = HashMap<u128, (usize, Option<(u128, u128)>)>;
+
+/// Entry of the weighted search queue, ordered so the node with the lowest estimated
+/// total distance is popped first
+struct QueuedNode {
+    /// Distance from the start plus the heuristic estimate of the remaining distance
+    estimate: f64,
+    distance: f64,
+    node_id: u128,
+}
+
+impl PartialEq for QueuedNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate.total_cmp(&other.estimate).is_eq()
+    }
+}
+
+impl Eq for QueuedNode {}
+
+impl PartialOrd for QueuedNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl SearchMethods for HelixGraphStorage {
+    fn shortest_path(
+        &self,
+        txn: &RoTxn<'_>,
+        edge_label: Option<&str>,
+        from_id: &u128,
+        to_id: &u128,
+        max_depth: Option<usize>,
+        weight_property: Option<&str>,
+    ) -> Result<(Vec<Node>, Vec<Edge>), GraphError> {
+        match weight_property {
+            Some(weight_property) => self.weighted_search(
+                txn,
+                edge_label,
+                from_id,
+                to_id,
+                max_depth,
+                weight_property,
+                None,
+            ),
+            None => self.bidirectional_bfs(txn, edge_label, from_id, to_id, max_depth, false),
+        }
+    }
+
+    fn shortest_mutual_path(
+        &self,
+        txn: &RoTxn<'_>,
+        edge_label: Option<&str>,
+        from_id: &u128,
+        to_id: &u128,
+        max_depth: Option<usize>,
+    ) -> Result<(Vec<Node>, Vec<Edge>), GraphError> {
+        self.bidirectional_bfs(txn, edge_label, from_id, to_id, max_depth, true)
+    }
+
+    fn shortest_path_weighted(
+        &self,
+        txn: &RoTxn<'_>,
+        edge_label: Option<&str>,
+        from_id: &u128,
+        to_id: &u128,
+        weight_property: &str,
+        heuristic_property: Option<&str>,
+    ) -> Result<(Vec<Node>, Vec<Edge>), GraphError> {
+        self.weighted_search(
+            txn,
+            edge_label,
+            from_id,
+            to_id,
+            None,
+            weight_property,
+            heuristic_property,
+        )
+    }
+}
+
+impl HelixGraphStorage {
+    /// Nodes adjacent to a node along with the ids of the connecting edges.
+    ///
+    /// With `mutual` set, only nodes that are connected in both directions are returned.
+    fn adjacent_nodes(
+        &self,
+        txn: &RoTxn,
+        node_id: &u128,
+        edge_label: Option<&str>,
+        direction: Direction,
+        mutual: bool,
+    ) -> Result<Vec<(u128, u128)>, GraphError> {
+        let adjacent = |direction: Direction| -> Result<Vec<(u128, u128)>, GraphError> {
+            match edge_label {
+                Some(label) => self
+                    .adjacency(txn, node_id, &self.dictionary.label_key(label), direction)?
+                    .collect(),
+                None => Ok(self
+                    .node_adjacency(txn, node_id, direction)?
+                    .into_iter()
+                    .map(|(_, edge)| edge)
+                    .collect()),
+            }
+        };
+
+        let nodes = adjacent(direction)?;
+        if !mutual {
+            return Ok(nodes);
+        }
+        let opposite = match direction {
+            Direction::Out => Direction::In,
+            Direction::In => Direction::Out,
+        };
+        let reverse = adjacent(opposite)?
+            .into_iter()
+            .map(|(adjacent_id, _)| adjacent_id)
+            .collect::<HashSet<_>>();
+        Ok(nodes
+            .into_iter()
+            .filter(|(adjacent_id, _)| reverse.contains(adjacent_id))
+            .collect())
+    }
+
+    /// Breadth-first search expanding from both ends, always growing the smaller frontier
+    /// by a whole level until the two searches meet
+    fn bidirectional_bfs(
+        &self,
+        txn: &RoTxn,
+        edge_label: Option<&str>,
+        from_id: &u128,
+        to_id: &u128,
+        max_depth: Option<usize>,
+        mutual: bool,
+    ) -> Result<(Vec<Node>, Vec<Edge>), GraphError> {
+        if from_id == to_id {
+            return Ok((vec![self.get_node(txn, from_id)?], Vec::new()));
+        }
+
+        let mut forward: PathParents = HashMap::from([(*from_id, (0, None))]);
+        let mut backward: PathParents = HashMap::from([(*to_id, (0, None))]);
+        let mut forward_frontier = vec![*from_id];
+        let mut backward_frontier = vec![*to_id];
+        let mut forward_depth = 0;
+        let mut backward_depth = 0;
+
+        while !forward_frontier.is_empty()
+            && !backward_frontier.is_empty()
+            && max_depth.is_none_or(|max_depth| forward_depth + backward_depth < max_depth)
+        {
+            let (frontier, visited, other, depth, direction) =
+                if forward_frontier.len() <= backward_frontier.len() {
+                    (
+                        &mut forward_frontier,
+                        &mut forward,
+                        &backward,
+                        &mut forward_depth,
+                        Direction::Out,
+                    )
+                } else {
+                    (
+                        &mut backward_frontier,
+                        &mut backward,
+                        &forward,
+                        &mut backward_depth,
+                        Direction::In,
+                    )
+                };
+            *depth += 1;
+
+            // finish the level before picking the meeting point, nodes of the other side
+            // can be at different distances
+            let mut meeting: Option<(usize, u128)> = None;
+            let mut next = Vec::new();
+            for node_id in frontier.iter() {
+                for (adjacent_id, edge_id) in
+                    self.adjacent_nodes(txn, node_id, edge_label, direction, mutual)?
+                {
+                    if visited.contains_key(&adjacent_id) {
+                        continue;
+                    }
+                    visited.insert(adjacent_id, (*depth, Some((*node_id, edge_id))));
+                    if let Some((other_depth, _)) = other.get(&adjacent_id) {
+                        let length = *depth + other_depth;
+                        if meeting.is_none_or(|(shortest, _)| length < shortest) {
+                            meeting = Some((length, adjacent_id));
+                        }
+                    }
+                    next.push(adjacent_id);
+                }
+            }
+            if let Some((_, meeting_id)) = meeting {
+                return self.reconstruct_path(txn, &forward, &backward, &meeting_id);
+            }
+            *frontier = next;
+        }
+        Err(GraphError::ShortestPathNotFound)
+    }
+
+    /// Position of a node used by the A* heuristic, either a single number or an array of
+    /// coordinates
+    fn heuristic_position(node: &Node, heuristic_property: &str) -> Option<Vec<f64>> {
+        match node.properties.as_ref()?.get(heuristic_property)? {
+            Value::Array(values) => values.iter().map(Value::as_f64).collect(),
+            value => value.as_f64().map(|value| vec![value]),
+        }
+    }
+
+    /// Dijkstra's algorithm over the out edges, weighing each edge by a property.
+    ///
+    /// With a heuristic property this becomes A*, the euclidean distance between the
+    /// positions of a node and the target estimates the remaining distance. Nodes
+    /// without a position, or with one of a different dimension than the target's, are
+    /// estimated at 0.
+    #[allow(clippy::too_many_arguments)]
+    fn weighted_search(
+        &self,
+        txn: &RoTxn,
+        edge_label: Option<&str>,
+        from_id: &u128,
+        to_id: &u128,
+        max_depth: Option<usize>,
+        weight_property: &str,
+        heuristic_property: Option<&str>,
+    ) -> Result<(Vec<Node>, Vec<Edge>), GraphError> {
+        let target = match heuristic_property {
+            Some(heuristic_property) => {
+                Self::heuristic_position(&self.get_node(txn, to_id)?, heuristic_property)
+            }
+            None => None,
+        };
+        let estimate = |node_id: &u128| -> Result<f64, GraphError> {
+            let (Some(target), Some(heuristic_property)) = (&target, heuristic_property) else {
+                return Ok(0.0);
+            };
+            let node = self.get_node(txn, node_id)?;
+            Ok(match Self::heuristic_position(&node, heuristic_property) {
+                Some(position) if position.len() == target.len() => position
+                    .iter()
+                    .zip(target.iter())
+                    .map(|(a, b)| (a - b).powi(2))
+                    .sum::<f64>()
+                    .sqrt(),
+                _ => 0.0,
+            })
+        };
+
+        let mut parents: PathParents = HashMap::from([(*from_id, (0, None))]);
+        let mut distances = HashMap::from([(*from_id, 0.0)]);
+        let mut queue = BinaryHeap::from([QueuedNode {
+            estimate: estimate(from_id)?,
+            distance: 0.0,
+            node_id: *from_id,
+        }]);
+
+        while let Some(QueuedNode {
+            distance, node_id, ..
+        }) = queue.pop()
+        {
+            if node_id == *to_id {
+                return self.reconstruct_path(txn, &parents, &HashMap::new(), to_id);
+            }
+            // a shorter route to the node was queued after this entry
+            if distances
+                .get(&node_id)
+                .is_some_and(|shortest| distance > *shortest)
+            {
+                continue;
+            }
+            let hops = parents[&node_id].0;
+            if max_depth.is_some_and(|max_depth| hops >= max_depth) {
+                continue;
+            }
+            for (adjacent_id, edge_id) in
+                self.adjacent_nodes(txn, &node_id, edge_label, Direction::Out, false)?
+            {
+                let edge = self.get_edge(txn, &edge_id)?;
+                let weight = edge
+                    .properties
+                    .as_ref()
+                    .and_then(|properties| properties.get(weight_property))
+                    .and_then(Value::as_f64)
+                    .unwrap_or(1.0);
+                if weight < 0.0 {
+                    return Err(GraphError::New(format!(
+                        "Edge {} has negative weight {}",
+                        uuid::Uuid::from_u128(edge_id),
+                        weight
+                    )));
+                }
+                let adjacent_distance = distance + weight;
+                if distances
+                    .get(&adjacent_id)
+                    .is_none_or(|shortest| adjacent_distance < *shortest)
+                {
+                    distances.insert(adjacent_id, adjacent_distance);
+                    parents.insert(adjacent_id, (hops + 1, Some((node_id, edge_id))));
+                    queue.push(QueuedNode {
+                        estimate: adjacent_distance + estimate(&adjacent_id)?,
+                        distance: adjacent_distance,
+                        node_id: adjacent_id,
+                    });
+                }
+            }
+        }
+        Err(GraphError::ShortestPathNotFound)
+    }
+
+    /// Walks the parents of both searches from the node they met at to the ends of the path
+    fn reconstruct_path(
+        &self,
+        txn: &RoTxn,
+        forward: &PathParents,
+        backward: &PathParents,
+        meeting_id: &u128,
+    ) -> Result<(Vec<Node>, Vec<Edge>), GraphError> {
+        let mut node_ids = vec![*meeting_id];
+        let mut edge_ids = Vec::new();
+        let mut current = *meeting_id;
+        while let Some((_, Some((previous, edge_id)))) = forward.get(&current) {
+            node_ids.push(*previous);
+            edge_ids.push(*edge_id);
+            current = *previous;
+        }
+        node_ids.reverse();
+        edge_ids.reverse();
+
+        current = *meeting_id;
+        while let Some((_, Some((next, edge_id)))) = backward.get(&current) {
+            node_ids.push(*next);
+            edge_ids.push(*edge_id);
+            current = *next;
+        }
+
+        let nodes = node_ids
+            .iter()
+            .map(|id| self.get_node(txn, id))
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        let edges = edge_ids
+            .iter()
+            .map(|id| self.get_edge(txn, id))
+            .collect::<Result<Vec<_>, GraphError>>()?;
+        Ok((nodes, edges))
+    }
+}
+
+impl Drop for HelixGraphStorage {
+    fn drop(&mut self) {
+        // make sure committed writes end up in the log before shutting down
+        if let Err(e) = self.wal.ship(&self.graph_env) {
+            eprintln!("Error flushing write-ahead log: {:?}", e);
+        }
+    }
+}
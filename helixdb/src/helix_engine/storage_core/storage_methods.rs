@@ -42,19 +42,52 @@ pub trait StorageMethods {
 
 pub trait SearchMethods {
     /// Find shortest path between two nodes
+    ///
+    /// ## Arguments
+    ///
+    /// * `edge_label` - Only follow edges with this label, any edge if `None`
+    /// * `max_depth` - Only consider paths of at most this many edges
+    /// * `weight_property` - Minimise the sum of this edge property instead of the number
+    ///   of edges. Edges without a numeric value for it weigh 1.
     fn shortest_path(
         &self,
         txn: &RoTxn<'_>,
-        edge_label: &str,
+        edge_label: Option<&str>,
         from_id: &u128,
         to_id: &u128,
+        max_depth: Option<usize>,
+        weight_property: Option<&str>,
     ) -> Result<(Vec<Node>, Vec<Edge>), GraphError>;
 
+    /// Find shortest path between two nodes that only follows edges which are matched by
+    /// an edge in the opposite direction
     fn shortest_mutual_path(
         &self,
         txn: &RoTxn<'_>,
-        edge_label: &str,
+        edge_label: Option<&str>,
+        from_id: &u128,
+        to_id: &u128,
+        max_depth: Option<usize>,
+    ) -> Result<(Vec<Node>, Vec<Edge>), GraphError>;
+
+    /// Find the path between two nodes with the lowest sum of an edge property
+    ///
+    /// ## Arguments
+    ///
+    /// * `edge_label` - Only follow edges with this label, any edge if `None`
+    /// * `weight_property` - The edge property to sum. Edges without a numeric value for
+    ///   it weigh 1, negative weights are an error.
+    /// * `heuristic_property` - Node property holding a number or an array of coordinates.
+    ///   If set, the search is guided by the euclidean distance between the positions of a
+    ///   node and the target (A*), which must never exceed the weight of the path between
+    ///   them for the result to be the shortest.
+    fn shortest_path_weighted(
+        &self,
+        txn: &RoTxn<'_>,
+        edge_label: Option<&str>,
         from_id: &u128,
         to_id: &u128,
+        weight_property: &str,
+        heuristic_property: Option<&str>,
     ) -> Result<(Vec<Node>, Vec<Edge>), GraphError>;
 }
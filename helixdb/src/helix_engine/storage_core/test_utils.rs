@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{g::G, source::add_n::AddNAdapter, tr_val::Traversable},
+        },
+        storage_core::storage_core::HelixGraphStorage,
+        types::GraphError,
+    },
+    props,
+};
+
+/// Opens the storage kept in `dir` with `config`
+pub fn try_open(dir: &TempDir, config: Config) -> Result<Arc<HelixGraphStorage>, GraphError> {
+    Ok(Arc::new(HelixGraphStorage::new(
+        dir.path().to_str().unwrap(),
+        config,
+    )?))
+}
+
+pub fn open(dir: &TempDir, config: Config) -> Arc<HelixGraphStorage> {
+    try_open(dir, config).unwrap()
+}
+
+/// Adds `count` users named after their position, indexed by name if the storage has an
+/// index on it, returning their ids
+pub fn add_users(storage: &Arc<HelixGraphStorage>, count: usize) -> Vec<u128> {
+    let indices: &[&str] = match storage.secondary_indices.contains_key("name") {
+        true => &["name"],
+        false => &[],
+    };
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let ids = (0..count)
+        .map(|i| {
+            G::new_mut(Arc::clone(storage), &mut txn)
+                .add_n("user", Some(props! { "name" => i as i64 }), Some(indices))
+                .collect_to_val()
+                .id()
+        })
+        .collect();
+    txn.commit().unwrap();
+    ids
+}
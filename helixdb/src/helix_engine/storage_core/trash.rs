@@ -0,0 +1,433 @@
+//! Soft deletion of nodes and edges.
+//!
+//! With soft deletion enabled, dropping a node or edge records a tombstone for it in the
+//! trash instead of removing it. Tombstoned elements stay in the nodes, edges and
+//! adjacency tables, but lookups, scans and adjacency reads skip them, so traversals see
+//! them as deleted. Dropping a node tombstones its edges along with it, and restoring the
+//! node restores those edges again.
+//!
+//! Elements are purged for good once they have been in the trash for longer than the
+//! retention window, or earlier through the admin API. Statistics and change events see
+//! an element deleted when it is moved to the trash and inserted when it is restored.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helix_engine::{
+        cdc::cdc::{ChangeEvent, ChangeOp, ChangeTarget},
+        graph_core::config::SoftDeleteConfig,
+        stats::stats::Direction,
+        storage_core::{
+            adjacency_blocks::AdjacentEdge, storage_core::HelixGraphStorage, wal::WalOp,
+        },
+        types::GraphError,
+    },
+    helix_storage::heed3::{
+        byteorder::BE,
+        types::{Bytes, U128},
+        Database, Env, RoTxn, RwTxn,
+    },
+    protocol::filterable::Filterable,
+};
+
+const DB_TRASH: &str = "trash"; // element id -> tombstone
+
+/// Marks a node or edge as deleted until it is restored or purged
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tombstone {
+    pub target: ChangeTarget,
+    pub label: String,
+    /// Milliseconds since the unix epoch
+    pub deleted_at: i64,
+    /// Node whose drop the edge was moved to the trash with, restored along with it
+    pub with: Option<u128>,
+}
+
+/// An element in the trash as listed by the admin API
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrashedItem {
+    pub id: String,
+    pub target: ChangeTarget,
+    pub label: String,
+    pub deleted_at: i64,
+    pub with: Option<String>,
+}
+
+impl TrashedItem {
+    pub fn new(id: u128, tombstone: Tombstone) -> Self {
+        Self {
+            id: uuid::Uuid::from_u128(id).to_string(),
+            target: tombstone.target,
+            label: tombstone.label,
+            deleted_at: tombstone.deleted_at,
+            with: tombstone
+                .with
+                .map(|node| uuid::Uuid::from_u128(node).to_string()),
+        }
+    }
+}
+
+pub struct Trash {
+    pub trash_db: Database<U128<BE>, Bytes>,
+    enabled: bool,
+    retention: Duration,
+    purge_interval: Duration,
+    /// Set once the trash may hold tombstones, so elements aren't looked up in it while
+    /// it's empty
+    occupied: AtomicBool,
+}
+
+impl Trash {
+    pub const DEFAULT_RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+    pub const DEFAULT_PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+    pub fn new(
+        graph_env: &Env,
+        wtxn: &mut RwTxn,
+        config: &SoftDeleteConfig,
+    ) -> Result<Trash, GraphError> {
+        let trash_db: Database<U128<BE>, Bytes> = graph_env
+            .database_options()
+            .types::<U128<BE>, Bytes>()
+            .name(DB_TRASH)
+            .create(wtxn)?;
+        let occupied = !trash_db.is_empty(wtxn)?;
+
+        Ok(Trash {
+            trash_db,
+            enabled: config.enabled,
+            retention: Duration::from_secs(
+                config
+                    .retention_secs
+                    .unwrap_or(Self::DEFAULT_RETENTION_SECS),
+            ),
+            purge_interval: Duration::from_secs(
+                config
+                    .purge_interval_secs
+                    .unwrap_or(Self::DEFAULT_PURGE_INTERVAL_SECS)
+                    .max(1),
+            ),
+            occupied: AtomicBool::new(occupied),
+        })
+    }
+
+    /// Whether drops move elements to the trash. Elements already in the trash stay
+    /// hidden when soft deletion is disabled, until they are restored or purged.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn retention(&self) -> Duration {
+        self.retention
+    }
+
+    pub fn purge_interval(&self) -> Duration {
+        self.purge_interval
+    }
+
+    /// Whether the trash may hold tombstones, `false` if it is certainly empty
+    #[inline(always)]
+    pub fn is_occupied(&self) -> bool {
+        self.occupied.load(Ordering::Relaxed)
+    }
+
+    /// Whether the element is in the trash
+    #[inline(always)]
+    pub fn contains(&self, txn: &RoTxn, id: &u128) -> Result<bool, GraphError> {
+        if !self.occupied.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        Ok(self.trash_db.get(txn, id)?.is_some())
+    }
+
+    /// Whether an edge of an adjacency list is hidden because it or its adjacent node is
+    /// in the trash
+    #[inline(always)]
+    pub fn hides(
+        &self,
+        txn: &RoTxn,
+        (adjacent_id, edge_id): &AdjacentEdge,
+    ) -> Result<bool, GraphError> {
+        Ok(self.contains(txn, edge_id)? || self.contains(txn, adjacent_id)?)
+    }
+
+    /// [`Self::hides`] as a predicate that outlives the borrow of the trash
+    pub fn hider<'t>(
+        &self,
+        txn: &'t RoTxn,
+    ) -> impl Fn(&AdjacentEdge) -> Result<bool, GraphError> + 't {
+        let trash_db = self.trash_db;
+        move |(adjacent_id, edge_id)| {
+            Ok(trash_db.get(txn, edge_id)?.is_some() || trash_db.get(txn, adjacent_id)?.is_some())
+        }
+    }
+
+    pub fn get(&self, txn: &RoTxn, id: &u128) -> Result<Option<Tombstone>, GraphError> {
+        match self.trash_db.get(txn, id)? {
+            Some(bytes) => Ok(Some(bincode::deserialize(bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put(&self, txn: &mut RwTxn, id: &u128, tombstone: &Tombstone) -> Result<(), GraphError> {
+        // set before the write commits, so no reader misses the tombstone
+        self.occupied.store(true, Ordering::Relaxed);
+        self.trash_db
+            .put(txn, id, &bincode::serialize(tombstone)?)?;
+        Ok(())
+    }
+
+    /// Takes the element out of the trash, returning whether it was in it
+    pub fn remove(&self, txn: &mut RwTxn, id: &u128) -> Result<bool, GraphError> {
+        if !self.occupied.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        Ok(self.trash_db.delete(txn, id)?)
+    }
+
+    /// Every element in the trash, in id order
+    pub fn list(&self, txn: &RoTxn) -> Result<Vec<(u128, Tombstone)>, GraphError> {
+        self.trash_db
+            .iter(txn)?
+            .map(|result| {
+                let (id, bytes) = result?;
+                Ok((id, bincode::deserialize(bytes)?))
+            })
+            .collect()
+    }
+}
+
+impl HelixGraphStorage {
+    /// Moves a node and its edges to the trash, see [`Trash`]
+    pub fn trash_node(
+        &self,
+        txn: &mut RwTxn,
+        id: &u128,
+        deleted_at: i64,
+    ) -> Result<(), GraphError> {
+        if self.trash.contains(txn, id)? {
+            return Err(GraphError::NodeNotFound);
+        }
+        let bytes = self
+            .nodes_db
+            .get(txn, Self::node_key(id))?
+            .ok_or(GraphError::NodeNotFound)?;
+        let node = self.decode_node(bytes, *id)?;
+
+        // self loops show up in both adjacency lists
+        let mut edges = self.node_adjacency(txn, id, Direction::Out)?;
+        edges.extend(self.node_adjacency(txn, id, Direction::In)?);
+        edges.sort_unstable_by_key(|(_, (_, edge_id))| *edge_id);
+        edges.dedup_by_key(|(_, (_, edge_id))| *edge_id);
+        for (_, (_, edge_id)) in edges {
+            if !self.trash.contains(txn, &edge_id)? {
+                self.trash_edge_with(txn, &edge_id, deleted_at, Some(*id))?;
+            }
+        }
+
+        self.trash.put(
+            txn,
+            id,
+            &Tombstone {
+                target: ChangeTarget::Node,
+                label: node.label.clone(),
+                deleted_at,
+                with: None,
+            },
+        )?;
+        self.cdc.record(
+            txn,
+            ChangeEvent::new(ChangeOp::Delete, ChangeTarget::Node, *id, &node.label),
+        )?;
+        self.stats.node_removed(txn, &node.label)
+    }
+
+    /// Moves an edge to the trash, see [`Trash`]
+    pub fn trash_edge(
+        &self,
+        txn: &mut RwTxn,
+        id: &u128,
+        deleted_at: i64,
+    ) -> Result<(), GraphError> {
+        if self.trash.contains(txn, id)? {
+            return Err(GraphError::EdgeNotFound);
+        }
+        self.trash_edge_with(txn, id, deleted_at, None)
+    }
+
+    fn trash_edge_with(
+        &self,
+        txn: &mut RwTxn,
+        id: &u128,
+        deleted_at: i64,
+        with: Option<u128>,
+    ) -> Result<(), GraphError> {
+        let bytes = self
+            .edges_db
+            .get(txn, Self::edge_key(id))?
+            .ok_or(GraphError::EdgeNotFound)?;
+        let edge = self.decode_edge(bytes, *id)?;
+        self.trash.put(
+            txn,
+            id,
+            &Tombstone {
+                target: ChangeTarget::Edge,
+                label: edge.label.clone(),
+                deleted_at,
+                with,
+            },
+        )?;
+        self.cdc.record(
+            txn,
+            ChangeEvent::new(ChangeOp::Delete, ChangeTarget::Edge, *id, &edge.label),
+        )?;
+        self.stats.edge_removed(txn, &edge)
+    }
+
+    /// Takes a node or edge out of the trash, along with the edges moved to the trash
+    /// with a node.
+    ///
+    /// A node can't be restored while another node has one of its unique values, and an
+    /// edge can't be restored while one of its nodes is in the trash. Edges moved to
+    /// the trash with a node whose other node is in the trash as well stay in it, and are
+    /// restored with that node instead.
+    pub fn restore_trashed(&self, txn: &mut RwTxn, id: &u128) -> Result<(), GraphError> {
+        self.restore_from_trash(txn, id)?;
+        self.wal.log(txn, || Ok(WalOp::Restore(*id)))
+    }
+
+    /// Restores an element without journaling it in the write-ahead log
+    pub(crate) fn restore_from_trash(&self, txn: &mut RwTxn, id: &u128) -> Result<(), GraphError> {
+        let tombstone = self.trash.get(txn, id)?.ok_or_else(|| {
+            GraphError::New(format!(
+                "{} is not in the trash",
+                uuid::Uuid::from_u128(*id)
+            ))
+        })?;
+        match tombstone.target {
+            ChangeTarget::Node => {
+                // another node may have taken a unique value of the node while it was
+                // in the trash
+                let bytes = self.nodes_db.get(txn, id)?.ok_or(GraphError::NodeNotFound)?;
+                let node = self.decode_node(bytes, *id)?;
+                for index in self.unique_indices.iter() {
+                    if let Ok(value) = node.check_property(index) {
+                        self.check_unique(txn, index, value, id)?;
+                    }
+                }
+
+                self.trash.remove(txn, id)?;
+                self.cdc.record(
+                    txn,
+                    ChangeEvent::new(ChangeOp::Insert, ChangeTarget::Node, *id, &tombstone.label),
+                )?;
+                self.stats.node_added(txn, &tombstone.label)?;
+                if self.versions.is_versioned(&tombstone.label) {
+                    self.record_version(txn, &node, chrono::Utc::now().timestamp_millis())?;
+                }
+
+                let mut edges = self.node_adjacency(txn, id, Direction::Out)?;
+                edges.extend(self.node_adjacency(txn, id, Direction::In)?);
+                edges.sort_unstable_by_key(|(_, (_, edge_id))| *edge_id);
+                edges.dedup_by_key(|(_, (_, edge_id))| *edge_id);
+                for (_, (other_id, edge_id)) in edges {
+                    let Some(mut edge_tombstone) = self.trash.get(txn, &edge_id)? else {
+                        continue;
+                    };
+                    if edge_tombstone.with != Some(*id) {
+                        continue;
+                    }
+                    match self.trash.contains(txn, &other_id)? {
+                        true => {
+                            edge_tombstone.with = Some(other_id);
+                            self.trash.put(txn, &edge_id, &edge_tombstone)?;
+                        }
+                        false => self.restore_edge(txn, &edge_id, &edge_tombstone)?,
+                    }
+                }
+                Ok(())
+            }
+            _ => self.restore_edge(txn, id, &tombstone),
+        }
+    }
+
+    fn restore_edge(
+        &self,
+        txn: &mut RwTxn,
+        id: &u128,
+        tombstone: &Tombstone,
+    ) -> Result<(), GraphError> {
+        let bytes = self
+            .edges_db
+            .get(txn, Self::edge_key(id))?
+            .ok_or(GraphError::EdgeNotFound)?;
+        let edge = self.decode_edge(bytes, *id)?;
+        for node_id in [edge.from_node, edge.to_node] {
+            if self.trash.contains(txn, &node_id)? {
+                return Err(GraphError::New(format!(
+                    "Restore node {} before its edge {}",
+                    uuid::Uuid::from_u128(node_id),
+                    uuid::Uuid::from_u128(*id)
+                )));
+            }
+        }
+        self.trash.remove(txn, id)?;
+        self.cdc.record(
+            txn,
+            ChangeEvent::new(ChangeOp::Insert, ChangeTarget::Edge, *id, &tombstone.label),
+        )?;
+        self.stats.edge_added(txn, &edge)
+    }
+
+    /// Removes a node or edge in the trash for good, a node along with all its edges
+    pub fn purge_trashed(&self, txn: &mut RwTxn, id: &u128) -> Result<(), GraphError> {
+        let tombstone = self.trash.get(txn, id)?.ok_or_else(|| {
+            GraphError::New(format!(
+                "{} is not in the trash",
+                uuid::Uuid::from_u128(*id)
+            ))
+        })?;
+        match tombstone.target {
+            ChangeTarget::Node => {
+                self.remove_node(txn, id)?;
+                self.wal.log(txn, || Ok(WalOp::DropNode(*id)))
+            }
+            _ => {
+                self.remove_edge(txn, id)?;
+                self.wal.log(txn, || Ok(WalOp::DropEdge(*id)))
+            }
+        }
+    }
+
+    /// Purges the elements that have been in the trash for longer than the retention
+    /// window, in a single write transaction committed through the write-ahead log, so
+    /// it waits on the quorum of a cluster leader. Edges moved to the trash with a node
+    /// are purged along with it. Returns the number of purged nodes and edges.
+    pub fn purge_expired(&self) -> Result<usize, GraphError> {
+        let cutoff =
+            chrono::Utc::now().timestamp_millis() - self.trash.retention().as_millis() as i64;
+        let mut txn = self.graph_env.write_txn()?;
+        let expired = self
+            .trash
+            .list(&txn)?
+            .into_iter()
+            .filter(|(_, tombstone)| tombstone.deleted_at <= cutoff && tombstone.with.is_none())
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        let before = self.trash.trash_db.len(&txn)?;
+        for id in expired {
+            // gone already if it was an edge of a node purged before it
+            if self.trash.contains(&txn, &id)? {
+                self.purge_trashed(&mut txn, &id)?;
+            }
+        }
+        let purged = (before - self.trash.trash_db.len(&txn)?) as usize;
+        self.wal.commit(txn)?;
+        Ok(purged)
+    }
+}
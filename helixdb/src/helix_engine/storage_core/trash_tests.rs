@@ -0,0 +1,265 @@
+use std::sync::Arc;
+
+use heed3::RwTxn;
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                out::out::OutAdapter,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                    n_from_id::NFromIdAdapter,
+                    n_from_type::NFromTypeAdapter,
+                },
+                tr_val::Traversable,
+            },
+        },
+        storage_core::{
+            storage_core::HelixGraphStorage, storage_methods::StorageMethods, test_utils::open,
+            wal::Quorum,
+        },
+        types::GraphError,
+    },
+    props,
+};
+
+fn soft_delete_config() -> Config {
+    let mut config = Config::default();
+    config.soft_delete.enabled = true;
+    config
+}
+
+/// Three users following each other in a chain, returning the users and the edges
+fn chain(storage: &Arc<HelixGraphStorage>) -> (Vec<u128>, Vec<u128>) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let users = (0..3)
+        .map(|_| {
+            G::new_mut(Arc::clone(storage), &mut txn)
+                .add_n("user", None, None)
+                .collect_to_val()
+                .id()
+        })
+        .collect::<Vec<_>>();
+    let edges = users
+        .windows(2)
+        .map(|pair| {
+            G::new_mut(Arc::clone(storage), &mut txn)
+                .add_e("follows", None, pair[0], pair[1], true, EdgeType::Node)
+                .collect_to_val()
+                .id()
+        })
+        .collect();
+    txn.commit().unwrap();
+    (users, edges)
+}
+
+fn followed(storage: &Arc<HelixGraphStorage>, id: u128) -> Vec<u128> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    G::new(Arc::clone(storage), &txn)
+        .n_from_id(&id)
+        .out("follows", &EdgeType::Node)
+        .collect_to::<Vec<_>>()
+        .iter()
+        .map(|node| node.id())
+        .collect()
+}
+
+fn drop_node(storage: &Arc<HelixGraphStorage>, id: u128) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.drop_node(&mut txn, &id).unwrap();
+    txn.commit().unwrap();
+}
+
+fn trashed(storage: &Arc<HelixGraphStorage>) -> usize {
+    let txn = storage.graph_env.read_txn().unwrap();
+    storage.trash.list(&txn).unwrap().len()
+}
+
+#[test]
+fn test_dropped_node_is_hidden_until_restored() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, soft_delete_config());
+    let (users, edges) = chain(&storage);
+
+    drop_node(&storage, users[1]);
+    assert_eq!(trashed(&storage), 3);
+    assert!(followed(&storage, users[0]).is_empty());
+    // elements in the trash aren't missing to the integrity checker
+    assert!(storage.check_integrity(false).unwrap().issues.is_empty());
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(matches!(
+        storage.get_node(&txn, &users[1]),
+        Err(GraphError::NodeNotFound)
+    ));
+    assert!(matches!(
+        storage.get_edge(&txn, &edges[1]),
+        Err(GraphError::EdgeNotFound)
+    ));
+    let remaining = G::new(Arc::clone(&storage), &txn)
+        .n_from_type("user")
+        .collect_to::<Vec<_>>();
+    assert_eq!(remaining.len(), 2);
+    // the node is still stored
+    assert!(storage.nodes_db.get(&txn, &users[1]).unwrap().is_some());
+    drop(txn);
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.restore_trashed(&mut txn, &users[1]).unwrap();
+    txn.commit().unwrap();
+    assert_eq!(trashed(&storage), 0);
+    assert_eq!(followed(&storage, users[0]), vec![users[1]]);
+    assert_eq!(followed(&storage, users[1]), vec![users[2]]);
+}
+
+#[test]
+fn test_restore_rejects_taken_unique_value() {
+    let dir = TempDir::new().unwrap();
+    let mut config = soft_delete_config();
+    config.graph_config.unique_indices = Some(vec!["email".to_string()]);
+    let storage = open(&dir, config);
+    let add_user = |email: &str| {
+        let mut txn = storage.graph_env.write_txn().unwrap();
+        let id = G::new_mut(Arc::clone(&storage), &mut txn)
+            .add_n("user", Some(props! { "email" => email }), Some(&["email"]))
+            .collect_to_val()
+            .id();
+        txn.commit().unwrap();
+        id
+    };
+
+    let old = add_user("alice@example.com");
+    drop_node(&storage, old);
+    // the value is free while the node holding it is in the trash
+    let new = add_user("alice@example.com");
+
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let result = storage.restore_trashed(&mut txn, &old);
+    assert!(matches!(result, Err(GraphError::UniqueViolation { .. })));
+    drop(txn);
+    assert_eq!(trashed(&storage), 1);
+
+    // it can be restored once the value is free again
+    drop_node(&storage, new);
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.restore_trashed(&mut txn, &old).unwrap();
+    txn.commit().unwrap();
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.get_node(&txn, &old).is_ok());
+}
+
+#[test]
+fn test_edge_is_restored_after_its_nodes() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, soft_delete_config());
+    let (users, edges) = chain(&storage);
+
+    drop_node(&storage, users[0]);
+    drop_node(&storage, users[1]);
+
+    // the edge between the two stays in the trash until both are restored
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    assert!(storage.restore_trashed(&mut txn, &edges[0]).is_err());
+    storage.restore_trashed(&mut txn, &users[1]).unwrap();
+    assert!(storage.trash.contains(&txn, &edges[0]).unwrap());
+    assert!(!storage.trash.contains(&txn, &edges[1]).unwrap());
+    storage.restore_trashed(&mut txn, &users[0]).unwrap();
+    txn.commit().unwrap();
+
+    assert_eq!(trashed(&storage), 0);
+    assert_eq!(followed(&storage, users[0]), vec![users[1]]);
+}
+
+#[test]
+fn test_purge_removes_node_and_edges() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, soft_delete_config());
+    let (users, edges) = chain(&storage);
+
+    drop_node(&storage, users[1]);
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.purge_trashed(&mut txn, &users[1]).unwrap();
+    txn.commit().unwrap();
+
+    assert_eq!(trashed(&storage), 0);
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.nodes_db.get(&txn, &users[1]).unwrap().is_none());
+    for edge in &edges {
+        assert!(storage.edges_db.get(&txn, edge).unwrap().is_none());
+    }
+    drop(txn);
+    assert!(storage.check_integrity(false).unwrap().issues.is_empty());
+}
+
+#[test]
+fn test_purge_expired() {
+    let dir = TempDir::new().unwrap();
+    let mut config = soft_delete_config();
+    config.soft_delete.retention_secs = Some(0);
+    let storage = open(&dir, config);
+    let (users, _) = chain(&storage);
+
+    drop_node(&storage, users[2]);
+    assert_eq!(storage.purge_expired().unwrap(), 2);
+    assert_eq!(trashed(&storage), 0);
+    assert_eq!(storage.purge_expired().unwrap(), 0);
+    assert!(followed(&storage, users[1]).is_empty());
+    assert!(storage.check_integrity(false).unwrap().issues.is_empty());
+}
+
+/// Quorum of a cluster node that doesn't lead it
+struct Follower;
+
+impl Quorum for Follower {
+    fn commit(&self, _txn: RwTxn) -> Result<(), GraphError> {
+        Err(GraphError::NotLeader { leader: None })
+    }
+
+    fn is_leader(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn test_purge_expired_left_to_primary() {
+    let dir = TempDir::new().unwrap();
+    let mut config = soft_delete_config();
+    config.soft_delete.retention_secs = Some(0);
+    let storage = open(&dir, config);
+    let (users, _) = chain(&storage);
+    drop_node(&storage, users[2]);
+    assert!(storage.wal.is_primary());
+
+    // purges are committed through the quorum, which followers can't
+    let follower: Arc<dyn Quorum> = Arc::new(Follower);
+    storage.wal.set_quorum(Arc::downgrade(&follower)).unwrap();
+    assert!(!storage.wal.is_primary());
+    assert!(matches!(
+        storage.purge_expired(),
+        Err(GraphError::NotLeader { .. })
+    ));
+    assert_eq!(trashed(&storage), 2);
+
+    // replicas of a primary get its purges from its log
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, soft_delete_config());
+    storage.wal.set_following();
+    assert!(!storage.wal.is_primary());
+}
+
+#[test]
+fn test_drop_removes_without_soft_delete() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, Config::default());
+    let (users, _) = chain(&storage);
+
+    drop_node(&storage, users[1]);
+    assert_eq!(trashed(&storage), 0);
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.nodes_db.get(&txn, &users[1]).unwrap().is_none());
+}
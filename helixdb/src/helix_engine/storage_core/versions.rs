@@ -0,0 +1,163 @@
+//! Past states of the nodes of versioned labels.
+//!
+//! Every write of a node whose label is versioned records the node as written, keyed by
+//! its id and the time of the write, and dropping the node records that it was removed.
+//! The state of a node at a point in time is the last version recorded at or before it.
+//!
+//! History starts once a label is versioned: nodes written before have no versions until
+//! they are written again.
+
+use std::{collections::HashSet, ops::Bound};
+
+use crate::{
+    helix_engine::{storage_core::storage_core::HelixGraphStorage, types::GraphError},
+    helix_storage::heed3::{types::Bytes, Database, Env, RoTxn, RwTxn},
+    protocol::items::Node,
+};
+
+const DB_NODE_VERSIONS: &str = "node_versions"; // node id ++ millis since the epoch -> node
+
+pub struct Versions {
+    /// Encoded nodes by id and time of the write, empty for the removal of a node
+    pub versions_db: Database<Bytes, Bytes>,
+    labels: HashSet<String>,
+}
+
+impl Versions {
+    pub fn new(
+        graph_env: &Env,
+        wtxn: &mut RwTxn,
+        labels: &Option<Vec<String>>,
+    ) -> Result<Versions, GraphError> {
+        let versions_db: Database<Bytes, Bytes> = graph_env
+            .database_options()
+            .types::<Bytes, Bytes>()
+            .name(DB_NODE_VERSIONS)
+            .create(wtxn)?;
+        Ok(Versions {
+            versions_db,
+            labels: labels.iter().flatten().cloned().collect(),
+        })
+    }
+
+    /// Whether any label is versioned
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        !self.labels.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn is_versioned(&self, label: &str) -> bool {
+        self.labels.contains(label)
+    }
+
+    fn key(id: &u128, at: i64) -> [u8; 24] {
+        let mut key = [0u8; 24];
+        key[..16].copy_from_slice(&id.to_be_bytes());
+        // versions before the epoch sort first
+        key[16..].copy_from_slice(&(at.max(0) as u64).to_be_bytes());
+        key
+    }
+
+    fn time_of(key: &[u8]) -> Result<i64, GraphError> {
+        let bytes = key
+            .get(16..24)
+            .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+            .ok_or(GraphError::SliceLengthError)?;
+        Ok(u64::from_be_bytes(bytes) as i64)
+    }
+
+    /// Records a version of the node, replacing one recorded at the same millisecond
+    pub fn put(&self, txn: &mut RwTxn, id: &u128, at: i64, bytes: &[u8]) -> Result<(), GraphError> {
+        self.versions_db.put(txn, &Self::key(id, at), bytes)?;
+        Ok(())
+    }
+
+    /// The last version of the node recorded at or before the time, empty if the node
+    /// had been removed by then
+    pub fn get<'t>(
+        &self,
+        txn: &'t RoTxn,
+        id: &u128,
+        at: i64,
+    ) -> Result<Option<&'t [u8]>, GraphError> {
+        let lower = Self::key(id, 0);
+        let upper = Self::key(id, at);
+        let range = (Bound::Included(&lower[..]), Bound::Included(&upper[..]));
+        match self.versions_db.rev_range(txn, &range)?.next() {
+            Some(result) => Ok(Some(result?.1)),
+            None => Ok(None),
+        }
+    }
+
+    /// Versions of the node in the order they were recorded, with the time of each
+    pub fn list<'t>(&self, txn: &'t RoTxn, id: &u128) -> Result<Vec<(i64, &'t [u8])>, GraphError> {
+        let mut versions = Vec::new();
+        for result in self.versions_db.prefix_iter(txn, &id.to_be_bytes())? {
+            let (key, value) = result?;
+            versions.push((Self::time_of(key)?, value));
+        }
+        Ok(versions)
+    }
+}
+
+impl HelixGraphStorage {
+    /// Records the node as written at the time, milliseconds since the epoch, if its
+    /// label is versioned
+    pub fn record_version(&self, txn: &mut RwTxn, node: &Node, at: i64) -> Result<(), GraphError> {
+        if !self.versions.is_versioned(&node.label) {
+            return Ok(());
+        }
+        let bytes = self.encode_node(txn, node)?;
+        self.versions.put(txn, &node.id, at, &bytes)
+    }
+
+    /// Records the node as removed at the time if its label is versioned, before it is
+    /// dropped
+    pub fn record_removal(&self, txn: &mut RwTxn, id: &u128, at: i64) -> Result<(), GraphError> {
+        if !self.versions.is_enabled() {
+            return Ok(());
+        }
+        let label = match self.nodes_db.get(txn, id)? {
+            Some(bytes) => self.decode_node(bytes, *id)?.label,
+            None => return Ok(()),
+        };
+        if !self.versions.is_versioned(&label) {
+            return Ok(());
+        }
+        self.versions.put(txn, id, at, &[])
+    }
+
+    /// The state of the node at the time, `None` if it didn't exist then or hasn't been
+    /// written since its label is versioned. Fails for nodes of labels that aren't
+    /// versioned, which have no history.
+    pub fn node_as_of(&self, txn: &RoTxn, node: &Node, at: i64) -> Result<Option<Node>, GraphError> {
+        if !self.versions.is_versioned(&node.label) {
+            return Err(GraphError::New(format!(
+                "Nodes of label `{}` aren't versioned",
+                node.label
+            )));
+        }
+        match self.versions.get(txn, &node.id, at)? {
+            Some(bytes) if !bytes.is_empty() => Ok(Some(self.decode_node(bytes, node.id)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The versions of the node with the time each was recorded at, `None` where the
+    /// node was removed
+    pub fn node_history(
+        &self,
+        txn: &RoTxn,
+        id: &u128,
+    ) -> Result<Vec<(i64, Option<Node>)>, GraphError> {
+        self.versions
+            .list(txn, id)?
+            .into_iter()
+            .map(|(at, bytes)| match bytes.is_empty() {
+                true => Ok((at, None)),
+                false => Ok((at, Some(self.decode_node(bytes, *id)?))),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,169 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use chrono::{TimeZone, Utc};
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                source::{add_n::AddNAdapter, n_from_id::NFromIdAdapter},
+                tr_val::{Traversable, TraversalVal},
+                util::{as_of::AsOfAdapter, update::UpdateAdapter},
+            },
+        },
+        storage_core::{
+            storage_core::HelixGraphStorage, storage_methods::StorageMethods, test_utils::open,
+        },
+        types::GraphError,
+    },
+    props,
+    protocol::{items::Node, value::Value},
+};
+
+fn versioned_config() -> Config {
+    let mut config = Config::default();
+    config.graph_config.versioned_labels = Some(vec!["user".to_string()]);
+    config
+}
+
+/// The current time, after a pause so that writes either side of it are recorded at
+/// different milliseconds
+fn checkpoint() -> i64 {
+    thread::sleep(Duration::from_millis(5));
+    let now = Utc::now().timestamp_millis();
+    thread::sleep(Duration::from_millis(5));
+    now
+}
+
+fn add(storage: &Arc<HelixGraphStorage>, label: &str, name: i64) -> u128 {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let id = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n(label, Some(props! { "name" => name }), None)
+        .collect_to_val()
+        .id();
+    txn.commit().unwrap();
+    id
+}
+
+fn rename(storage: &Arc<HelixGraphStorage>, id: u128, name: i64) {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let node = G::new(Arc::clone(storage), &txn)
+        .n_from_id(&id)
+        .collect_to::<Vec<_>>();
+    G::new_mut_from(Arc::clone(storage), &mut txn, node)
+        .update(Some(props! { "name" => name }))
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+}
+
+fn name(node: &Node) -> Value {
+    node.properties.as_ref().unwrap()["name"].clone()
+}
+
+fn name_as_of(storage: &Arc<HelixGraphStorage>, id: u128, at: i64) -> Option<Value> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    let node = Node {
+        id,
+        label: "user".to_string(),
+        properties: None,
+        score: None,
+    };
+    storage
+        .node_as_of(&txn, &node, at)
+        .unwrap()
+        .map(|node| name(&node))
+}
+
+#[test]
+fn test_versions_of_written_and_dropped_node() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, versioned_config());
+
+    let before = checkpoint();
+    let user = add(&storage, "user", 1);
+    let added = checkpoint();
+    rename(&storage, user, 2);
+    let renamed = checkpoint();
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    storage.drop_node(&mut txn, &user).unwrap();
+    txn.commit().unwrap();
+    let dropped = checkpoint();
+
+    assert_eq!(name_as_of(&storage, user, before), None);
+    assert_eq!(name_as_of(&storage, user, added), Some(Value::I64(1)));
+    assert_eq!(name_as_of(&storage, user, renamed), Some(Value::I64(2)));
+    assert_eq!(name_as_of(&storage, user, dropped), None);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let history = storage.node_history(&txn, &user).unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|(_, node)| node.as_ref().map(name))
+            .collect::<Vec<_>>(),
+        vec![Some(Value::I64(1)), Some(Value::I64(2)), None]
+    );
+    assert!(history.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert!(history[0].0 > before && history[2].0 < dropped);
+}
+
+#[test]
+fn test_unversioned_labels_have_no_history() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, versioned_config());
+    let post = add(&storage, "post", 1);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert!(storage.node_history(&txn, &post).unwrap().is_empty());
+    let node = storage.get_node(&txn, &post).unwrap();
+    assert!(matches!(
+        storage.node_as_of(&txn, &node, Utc::now().timestamp_millis()),
+        Err(GraphError::New(_))
+    ));
+    let result = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&post)
+        .as_of(Utc::now())
+        .collect::<Result<Vec<_>, _>>();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_as_of_traversal() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, versioned_config());
+
+    let before = checkpoint();
+    let user = add(&storage, "user", 1);
+    let added = checkpoint();
+    rename(&storage, user, 2);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let names_as_of = |at: Value| {
+        G::new(Arc::clone(&storage), &txn)
+            .n_from_id(&user)
+            .as_of(at)
+            .collect_to::<Vec<_>>()
+            .into_iter()
+            .map(|item| match item {
+                TraversalVal::Node(node) => name(&node),
+                other => panic!("expected a node, got {:?}", other),
+            })
+            .collect::<Vec<_>>()
+    };
+    let added = Utc.timestamp_millis_opt(added).unwrap();
+    assert_eq!(names_as_of(added.into()), vec![Value::I64(1)]);
+    assert_eq!(names_as_of(added.to_rfc3339().into()), vec![Value::I64(1)]);
+    assert_eq!(names_as_of(Utc::now().into()), vec![Value::I64(2)]);
+    // the node didn't exist yet
+    let before = Utc.timestamp_millis_opt(before).unwrap();
+    assert!(names_as_of(before.into()).is_empty());
+
+    let result = G::new(Arc::clone(&storage), &txn)
+        .n_from_id(&user)
+        .as_of(true)
+        .collect::<Result<Vec<_>, _>>();
+    assert!(result.is_err());
+}
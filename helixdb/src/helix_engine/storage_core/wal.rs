@@ -0,0 +1,496 @@
+use crate::{
+    helix_engine::{
+        graph_core::config::{FsyncPolicy, WalConfig},
+        storage_core::encryption::Encryption,
+        types::GraphError,
+    },
+    helix_storage::heed3::{byteorder::BE, types::*, Database, Env, RoTxn, RwTxn, WithTls},
+    protocol::items::{Edge, Node},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex, OnceLock, Weak,
+    },
+    time::{Duration, Instant},
+};
+use twox_hash::XxHash32;
+
+const DB_WAL_PENDING: &str = "wal_pending"; // seq -> entry not yet written to the log file
+const DB_WAL_META: &str = "wal_meta"; // bookkeeping of the log
+const LAST_SEQ_KEY: &str = "last_seq"; // seq of the last entry contained in the database
+const LAST_TERM_KEY: &str = "last_term"; // term of that entry
+
+/// Name of the log file inside the log directory
+pub const WAL_FILE_NAME: &str = "helix.wal";
+
+/// A write to the graph as journaled in the log
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum WalOp {
+    /// Node id and encoded node, encrypted if encryption is enabled
+    PutNode(u128, Vec<u8>),
+    /// Edge id and encoded edge, encrypted if encryption is enabled
+    PutEdge(u128, Vec<u8>),
+    DropNode(u128),
+    DropEdge(u128),
+    /// Node moved to the trash along with its edges
+    TrashNode(u128),
+    TrashEdge(u128),
+    /// Node or edge taken out of the trash
+    Restore(u128),
+    /// Written by a newly elected cluster leader, committing the entries of earlier terms
+    /// it has along with it
+    Noop,
+}
+
+impl WalOp {
+    /// Journals a node, encrypted like it is in the nodes table so the log, its backups
+    /// and the replication stream don't hold it in the clear
+    pub fn put_node(encryption: &Encryption, node: &Node) -> Result<WalOp, GraphError> {
+        Ok(WalOp::PutNode(
+            node.id,
+            encryption.encrypt(node.id, node.encode_node()?)?,
+        ))
+    }
+
+    /// Journals an edge, encrypted like it is in the edges table
+    pub fn put_edge(encryption: &Encryption, edge: &Edge) -> Result<WalOp, GraphError> {
+        Ok(WalOp::PutEdge(
+            edge.id,
+            encryption.encrypt(edge.id, edge.encode_edge()?)?,
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WalEntry {
+    pub seq: u64,
+    /// Term of the cluster leader that wrote the entry, 0 outside of clusters
+    pub term: u64,
+    /// Milliseconds since the unix epoch
+    pub timestamp: i64,
+    pub op: WalOp,
+}
+
+impl WalEntry {
+    /// Decodes the payload of a framed entry, checking it against the checksum of its frame
+    pub fn from_frame(checksum: u32, payload: &[u8]) -> Result<WalEntry, GraphError> {
+        if XxHash32::oneshot(0, payload) != checksum {
+            return Err(GraphError::DecodeError(
+                "Checksum mismatch in write-ahead log entry".to_string(),
+            ));
+        }
+        Ok(bincode::deserialize(payload)?)
+    }
+}
+
+/// Replicates the entries journaled by write transactions before they are committed,
+/// e.g. the leader of a cluster to its followers
+pub trait Quorum: Send + Sync {
+    /// Commits the transaction once the entries it journaled are replicated, rolling it
+    /// back if they can't be
+    fn commit(&self, txn: RwTxn) -> Result<(), GraphError>;
+
+    /// Whether transactions can be committed through the quorum, i.e. the node leads it
+    fn is_leader(&self) -> bool;
+}
+
+/// Reader following the log file as entries are appended to it
+pub struct WalTail {
+    file: File,
+    /// End of the last intact entry read
+    offset: u64,
+}
+
+impl WalTail {
+    /// Reads the entries appended since the last call.
+    ///
+    /// An entry still being written is left for the next call.
+    pub fn next_entries(&mut self) -> Result<Vec<WalEntry>, GraphError> {
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        let (entries, len) = WriteAheadLog::read_frames(BufReader::new(&mut self.file))?;
+        self.offset += len;
+        Ok(entries)
+    }
+}
+
+struct WalFile {
+    file: File,
+    /// seq of the last entry in the file
+    last_seq: u64,
+    last_sync: Instant,
+}
+
+/// Write-ahead log of committed write transactions.
+///
+/// Entries are first written to a pending table inside the write transaction that
+/// made the change, so they commit or abort together with it. [`WriteAheadLog::ship`]
+/// then appends committed entries to the log file, which lives outside of the
+/// database and is kept across backups. Restoring a backup and replaying the log
+/// on top of it rolls the database forward to any point in time covered by the log.
+///
+/// Every entry in the file is framed as `[len: u32][xxhash32 of payload: u32][payload]`
+/// so a torn write at the end of the file can be detected and discarded.
+pub struct WriteAheadLog {
+    pub pending_db: Database<U64<BE>, Bytes>,
+    pub meta_db: Database<Str, U64<BE>>,
+    enabled: bool,
+    path: PathBuf,
+    fsync: FsyncPolicy,
+    fsync_interval: Duration,
+    file: Mutex<Option<WalFile>>,
+    /// Term new entries are tagged with
+    term: AtomicU64,
+    quorum: OnceLock<Weak<dyn Quorum>>,
+    /// Set once the storage applies the log of a primary
+    following: AtomicBool,
+}
+
+impl WriteAheadLog {
+    pub const DEFAULT_FSYNC_INTERVAL: Duration = Duration::from_millis(100);
+
+    pub fn new(
+        graph_env: &Env<WithTls>,
+        wtxn: &mut RwTxn,
+        db_path: &Path,
+        config: &WalConfig,
+    ) -> Result<WriteAheadLog, GraphError> {
+        let pending_db: Database<U64<BE>, Bytes> = graph_env
+            .database_options()
+            .types::<U64<BE>, Bytes>()
+            .name(DB_WAL_PENDING)
+            .create(wtxn)?;
+        let meta_db: Database<Str, U64<BE>> = graph_env
+            .database_options()
+            .types::<Str, U64<BE>>()
+            .name(DB_WAL_META)
+            .create(wtxn)?;
+
+        let dir = match &config.dir {
+            Some(dir) => PathBuf::from(dir),
+            None => db_path.to_path_buf(),
+        };
+        let path = dir.join(WAL_FILE_NAME);
+        let file = if config.enabled {
+            std::fs::create_dir_all(&dir)?;
+            Some(Self::open_file(&path)?)
+        } else {
+            None
+        };
+
+        Ok(WriteAheadLog {
+            pending_db,
+            meta_db,
+            enabled: config.enabled,
+            path,
+            fsync: config.fsync,
+            fsync_interval: config
+                .fsync_interval_ms
+                .map(Duration::from_millis)
+                .unwrap_or(Self::DEFAULT_FSYNC_INTERVAL),
+            file: Mutex::new(file),
+            term: AtomicU64::new(0),
+            quorum: OnceLock::new(),
+            following: AtomicBool::new(false),
+        })
+    }
+
+    /// Opens the log file, discarding a partially written entry at its end
+    fn open_file(path: &Path) -> Result<WalFile, GraphError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let (entries, valid_len) = Self::read_entries(&mut file)?;
+        if valid_len < file.metadata()?.len() {
+            file.set_len(valid_len)?;
+        }
+        Ok(WalFile {
+            file,
+            last_seq: entries.last().map(|entry| entry.seq).unwrap_or(0),
+            last_sync: Instant::now(),
+        })
+    }
+
+    /// Reads all intact entries of a log file along with the length of the intact part
+    fn read_entries(file: &mut File) -> Result<(Vec<WalEntry>, u64), GraphError> {
+        file.seek(SeekFrom::Start(0))?;
+        Self::read_frames(BufReader::new(file))
+    }
+
+    /// Reads intact entries until the end of the reader or the first torn or corrupt
+    /// one, along with the number of bytes they take up
+    fn read_frames<R: Read>(mut reader: R) -> Result<(Vec<WalEntry>, u64), GraphError> {
+        let mut entries = Vec::new();
+        let mut valid_len = 0u64;
+        let mut header = [0u8; 8];
+        loop {
+            match reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(GraphError::from(e)),
+            }
+            let len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+            let checksum = u32::from_le_bytes(header[4..8].try_into().unwrap());
+            let mut payload = vec![0u8; len];
+            match reader.read_exact(&mut payload) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(GraphError::from(e)),
+            }
+            match WalEntry::from_frame(checksum, &payload) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => break,
+            }
+            valid_len += (header.len() + len) as u64;
+        }
+        Ok((entries, valid_len))
+    }
+
+    /// Frames an encoded entry as it is written to the log file
+    pub fn frame(entry: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(entry.len() + 8);
+        frame.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&XxHash32::oneshot(0, entry).to_le_bytes());
+        frame.extend_from_slice(entry);
+        frame
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Path of the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The seq of the last entry whose changes are contained in the database
+    pub fn last_seq(&self, txn: &RoTxn) -> Result<u64, GraphError> {
+        Ok(self.meta_db.get(txn, LAST_SEQ_KEY)?.unwrap_or(0))
+    }
+
+    /// The term of the last entry whose changes are contained in the database
+    pub fn last_term(&self, txn: &RoTxn) -> Result<u64, GraphError> {
+        Ok(self.meta_db.get(txn, LAST_TERM_KEY)?.unwrap_or(0))
+    }
+
+    /// Tags the entries journaled from now on with the term of a cluster leader
+    pub fn set_term(&self, term: u64) {
+        self.term.store(term, Ordering::Relaxed);
+    }
+
+    /// Has write transactions committed through [`WriteAheadLog::commit`] wait on the
+    /// quorum. Fails to commit them once the quorum is dropped.
+    pub fn set_quorum(&self, quorum: Weak<dyn Quorum>) -> Result<(), GraphError> {
+        self.quorum
+            .set(quorum)
+            .map_err(|_| GraphError::New("The log already replicates to a quorum".to_string()))
+    }
+
+    /// Marks the storage as a replica applying the log of a primary, so it makes no
+    /// writes of its own
+    pub fn set_following(&self) {
+        self.following.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the storage makes writes of its own rather than getting them from a
+    /// primary or cluster leader, e.g. for background work like purging the trash
+    pub fn is_primary(&self) -> bool {
+        if self.following.load(Ordering::Relaxed) {
+            return false;
+        }
+        match self.quorum.get() {
+            Some(quorum) => quorum.upgrade().is_some_and(|quorum| quorum.is_leader()),
+            None => true,
+        }
+    }
+
+    /// Commits a write transaction, once its entries are replicated if the log
+    /// replicates to a quorum
+    pub fn commit(&self, txn: RwTxn) -> Result<(), GraphError> {
+        let Some(quorum) = self.quorum.get() else {
+            return Ok(txn.commit()?);
+        };
+        match quorum.upgrade() {
+            Some(quorum) => quorum.commit(txn),
+            None => Err(GraphError::NotLeader { leader: None }),
+        }
+    }
+
+    /// Journals an entry of another log under its own seq, for replicas applying the log
+    /// of their primary. The entry is only kept if the log is enabled, so the replica can
+    /// pass the log on, but its seq is recorded as contained in the database either way.
+    pub fn append(&self, txn: &mut RwTxn, entry: &WalEntry) -> Result<(), GraphError> {
+        self.meta_db.put(txn, LAST_SEQ_KEY, &entry.seq)?;
+        self.meta_db.put(txn, LAST_TERM_KEY, &entry.term)?;
+        if self.enabled {
+            self.pending_db
+                .put(txn, &entry.seq, &bincode::serialize(entry)?)?;
+        }
+        Ok(())
+    }
+
+    /// Follows the log file from its start, e.g. to stream it to replicas
+    pub fn tail(&self) -> Result<WalTail, GraphError> {
+        Ok(WalTail {
+            file: File::open(&self.path)?,
+            offset: 0,
+        })
+    }
+
+    /// Journals a write as part of the given write transaction.
+    ///
+    /// `op` is only built if the log is enabled, so callers don't pay for encoding
+    /// items otherwise.
+    pub fn log<F>(&self, txn: &mut RwTxn, op: F) -> Result<(), GraphError>
+    where
+        F: FnOnce() -> Result<WalOp, GraphError>,
+    {
+        if !self.enabled {
+            return Ok(());
+        }
+        let entry = WalEntry {
+            seq: self.last_seq(txn)? + 1,
+            term: self.term.load(Ordering::Relaxed),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            op: op()?,
+        };
+        self.meta_db.put(txn, LAST_SEQ_KEY, &entry.seq)?;
+        self.meta_db.put(txn, LAST_TERM_KEY, &entry.term)?;
+        self.pending_db
+            .put(txn, &entry.seq, &bincode::serialize(&entry)?)?;
+        Ok(())
+    }
+
+    /// Appends committed entries to the log file and removes them from the pending table.
+    ///
+    /// Returns the number of entries written.
+    pub fn ship(&self, graph_env: &Env<WithTls>) -> Result<usize, GraphError> {
+        if !self.enabled {
+            return Ok(0);
+        }
+        let mut guard = self.file.lock().unwrap();
+        let Some(wal_file) = guard.as_mut() else {
+            return Ok(0);
+        };
+
+        let (buffer, shipped, last_pending) = {
+            let txn = graph_env.read_txn()?;
+            let mut buffer = Vec::new();
+            let mut shipped = 0;
+            let mut last_pending = None;
+            for result in self.pending_db.iter(&txn)? {
+                let (seq, bytes) = result?;
+                last_pending = Some(seq);
+                // entries of a restored backup may already be in the log
+                if seq > wal_file.last_seq {
+                    buffer.extend_from_slice(&Self::frame(bytes));
+                    wal_file.last_seq = seq;
+                    shipped += 1;
+                }
+            }
+            (buffer, shipped, last_pending)
+        };
+        let Some(last_pending) = last_pending else {
+            return Ok(0);
+        };
+
+        wal_file.file.write_all(&buffer)?;
+        let should_sync = match self.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Interval => wal_file.last_sync.elapsed() >= self.fsync_interval,
+            FsyncPolicy::Never => false,
+        };
+        if should_sync {
+            wal_file.file.sync_data()?;
+            wal_file.last_sync = Instant::now();
+        }
+
+        let mut txn = graph_env.write_txn()?;
+        self.pending_db.delete_range(&mut txn, &(..=last_pending))?;
+        txn.commit()?;
+        Ok(shipped)
+    }
+
+    /// Forces the log file to disk regardless of the fsync policy
+    pub fn sync(&self) -> Result<(), GraphError> {
+        if let Some(wal_file) = self.file.lock().unwrap().as_mut() {
+            wal_file.file.sync_data()?;
+            wal_file.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Replays entries of the log file that aren't contained in the database yet.
+    ///
+    /// ## Arguments
+    ///
+    /// * `graph_env` - The environment the log belongs to
+    /// * `recover_to` - Only replay entries up to this unix timestamp in milliseconds.
+    ///   Entries after it are removed from the log since the history diverges from there.
+    /// * `apply` - Applies a single entry to the database
+    pub fn replay<F>(
+        &self,
+        graph_env: &Env<WithTls>,
+        recover_to: Option<i64>,
+        mut apply: F,
+    ) -> Result<usize, GraphError>
+    where
+        F: FnMut(&mut RwTxn, &WalEntry) -> Result<(), GraphError>,
+    {
+        if !self.enabled {
+            return Ok(0);
+        }
+        let mut guard = self.file.lock().unwrap();
+        let Some(wal_file) = guard.as_mut() else {
+            return Ok(0);
+        };
+        let (entries, _) = Self::read_entries(&mut wal_file.file)?;
+
+        let mut txn = graph_env.write_txn()?;
+        let mut last_seq = self.last_seq(&txn)?;
+        let mut last_term = self.last_term(&txn)?;
+        let mut replayed = 0;
+        let mut kept = 0;
+        for entry in entries.iter() {
+            if recover_to.is_some_and(|to| entry.timestamp > to) {
+                if entry.seq <= last_seq {
+                    return Err(GraphError::StorageError(format!(
+                        "Database already contains changes after the recovery point (seq {})",
+                        entry.seq
+                    )));
+                }
+                break;
+            }
+            kept += 1;
+            if entry.seq <= last_seq {
+                continue;
+            }
+            apply(&mut txn, entry)?;
+            last_seq = entry.seq;
+            last_term = entry.term;
+            replayed += 1;
+        }
+        self.meta_db.put(&mut txn, LAST_SEQ_KEY, &last_seq)?;
+        self.meta_db.put(&mut txn, LAST_TERM_KEY, &last_term)?;
+        txn.commit()?;
+
+        if kept < entries.len() {
+            // rewrite the log up to the recovery point
+            let mut file = OpenOptions::new().write(true).truncate(true).open(&self.path)?;
+            for entry in entries.iter().take(kept) {
+                file.write_all(&Self::frame(&bincode::serialize(entry)?))?;
+            }
+            file.sync_all()?;
+            *wal_file = Self::open_file(&self.path)?;
+        }
+        Ok(replayed)
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::{Config, FsyncPolicy},
+            ops::{g::G, source::add_n::AddNAdapter, tr_val::Traversable},
+        },
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+        types::GraphError,
+    },
+    props,
+};
+
+fn wal_config(log_dir: &Path, recover_to: Option<i64>) -> Config {
+    let mut config = Config::default();
+    config.wal.enabled = true;
+    config.wal.fsync = FsyncPolicy::Always;
+    config.wal.dir = Some(log_dir.to_str().unwrap().to_string());
+    config.wal.recover_to = recover_to;
+    config
+}
+
+fn add_person(storage: &Arc<HelixGraphStorage>, name: &str) -> u128 {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let node = G::new_mut(Arc::clone(storage), &mut txn)
+        .add_n("person", Some(props! { "name" => name }), None)
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+    node.first().unwrap().id()
+}
+
+fn has_node(storage: &HelixGraphStorage, id: &u128) -> bool {
+    let txn = storage.graph_env.read_txn().unwrap();
+    match storage.get_node(&txn, id) {
+        Ok(_) => true,
+        Err(GraphError::NodeNotFound) => false,
+        Err(e) => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn test_committed_writes_are_shipped() {
+    let db_dir = TempDir::new().unwrap();
+    let log_dir = TempDir::new().unwrap();
+    let storage = Arc::new(
+        HelixGraphStorage::new(db_dir.path().to_str().unwrap(), wal_config(log_dir.path(), None))
+            .unwrap(),
+    );
+
+    add_person(&storage, "John");
+    add_person(&storage, "Jane");
+
+    // aborted writes never reach the log
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let _ = G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n("person", None, None)
+        .collect_to::<Vec<_>>();
+    txn.abort();
+
+    assert_eq!(storage.wal.ship(&storage.graph_env).unwrap(), 2);
+    assert_eq!(storage.wal.ship(&storage.graph_env).unwrap(), 0);
+    assert!(std::fs::metadata(storage.wal.path()).unwrap().len() > 0);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.wal.last_seq(&txn).unwrap(), 2);
+    assert!(storage.wal.pending_db.is_empty(&txn).unwrap());
+}
+
+#[test]
+fn test_restore_rolls_forward() {
+    let db_dir = TempDir::new().unwrap();
+    let log_dir = TempDir::new().unwrap();
+    let backup_dir = TempDir::new().unwrap();
+    let restore_dir = TempDir::new().unwrap();
+
+    let storage = Arc::new(
+        HelixGraphStorage::new(db_dir.path().to_str().unwrap(), wal_config(log_dir.path(), None))
+            .unwrap(),
+    );
+    let john = add_person(&storage, "John");
+    assert_eq!(storage.backup(backup_dir.path()).unwrap(), 1);
+    let jane = add_person(&storage, "Jane");
+    drop(storage);
+
+    HelixGraphStorage::restore(backup_dir.path(), restore_dir.path()).unwrap();
+    let restored = HelixGraphStorage::new(
+        restore_dir.path().to_str().unwrap(),
+        wal_config(log_dir.path(), None),
+    )
+    .unwrap();
+    assert!(has_node(&restored, &john));
+    assert!(has_node(&restored, &jane));
+}
+
+#[test]
+fn test_point_in_time_recovery() {
+    let db_dir = TempDir::new().unwrap();
+    let log_dir = TempDir::new().unwrap();
+    let backup_dir = TempDir::new().unwrap();
+    let restore_dir = TempDir::new().unwrap();
+
+    let storage = Arc::new(
+        HelixGraphStorage::new(db_dir.path().to_str().unwrap(), wal_config(log_dir.path(), None))
+            .unwrap(),
+    );
+    storage.backup(backup_dir.path()).unwrap();
+    let john = add_person(&storage, "John");
+    thread::sleep(Duration::from_millis(5));
+    let recovery_point = chrono::Utc::now().timestamp_millis();
+    thread::sleep(Duration::from_millis(5));
+    let jane = add_person(&storage, "Jane");
+    drop(storage);
+
+    HelixGraphStorage::restore(backup_dir.path(), restore_dir.path()).unwrap();
+    let restored = HelixGraphStorage::new(
+        restore_dir.path().to_str().unwrap(),
+        wal_config(log_dir.path(), Some(recovery_point)),
+    )
+    .unwrap();
+    assert!(has_node(&restored, &john));
+    assert!(!has_node(&restored, &jane));
+
+    // the log diverges after the recovery point, so new writes continue from there
+    let restored = Arc::new(restored);
+    add_person(&restored, "Jim");
+    assert_eq!(restored.wal.ship(&restored.graph_env).unwrap(), 1);
+    let txn = restored.graph_env.read_txn().unwrap();
+    assert_eq!(restored.wal.last_seq(&txn).unwrap(), 2);
+}
+
+#[test]
+fn test_torn_tail_is_discarded() {
+    let db_dir = TempDir::new().unwrap();
+    let log_dir = TempDir::new().unwrap();
+
+    let storage = Arc::new(
+        HelixGraphStorage::new(db_dir.path().to_str().unwrap(), wal_config(log_dir.path(), None))
+            .unwrap(),
+    );
+    let john = add_person(&storage, "John");
+    storage.wal.ship(&storage.graph_env).unwrap();
+    let wal_path = storage.wal.path().to_path_buf();
+    drop(storage);
+
+    let intact_len = std::fs::metadata(&wal_path).unwrap().len();
+    let mut file = OpenOptions::new().append(true).open(&wal_path).unwrap();
+    file.write_all(&[42, 0, 0, 0, 1, 2, 3]).unwrap();
+    drop(file);
+
+    let storage = HelixGraphStorage::new(
+        db_dir.path().to_str().unwrap(),
+        wal_config(log_dir.path(), None),
+    )
+    .unwrap();
+    assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), intact_len);
+    assert!(has_node(&storage, &john));
+}
+
+#[test]
+fn test_replica_applies_tailed_entries() {
+    let db_dir = TempDir::new().unwrap();
+    let log_dir = TempDir::new().unwrap();
+    let primary = Arc::new(
+        HelixGraphStorage::new(
+            db_dir.path().to_str().unwrap(),
+            wal_config(log_dir.path(), None),
+        )
+        .unwrap(),
+    );
+    let replica_dir = TempDir::new().unwrap();
+    let replica =
+        HelixGraphStorage::new(replica_dir.path().to_str().unwrap(), Config::default()).unwrap();
+
+    let john = add_person(&primary, "John");
+    primary.wal.ship(&primary.graph_env).unwrap();
+    let mut tail = primary.wal.tail().unwrap();
+    let entries = tail.next_entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(replica.apply_replicated(&entries).unwrap(), 1);
+    assert!(has_node(&replica, &john));
+
+    // only entries appended since the last read are returned
+    let jane = add_person(&primary, "Jane");
+    let jack = add_person(&primary, "Jack");
+    primary.wal.ship(&primary.graph_env).unwrap();
+    let entries = tail.next_entries().unwrap();
+    assert_eq!(entries.len(), 2);
+
+    // a missing entry fails the whole batch
+    assert!(replica.apply_replicated(&entries[1..]).is_err());
+    assert!(!has_node(&replica, &jack));
+
+    // entries the replica already contains are skipped
+    let all = primary.wal.tail().unwrap().next_entries().unwrap();
+    assert_eq!(all.len(), 3);
+    assert_eq!(replica.apply_replicated(&all).unwrap(), 3);
+    assert!(has_node(&replica, &jane) && has_node(&replica, &jack));
+}
+
+#[test]
+fn test_replicated_entries_are_passed_on() {
+    let db_dir = TempDir::new().unwrap();
+    let log_dir = TempDir::new().unwrap();
+    let primary = Arc::new(
+        HelixGraphStorage::new(
+            db_dir.path().to_str().unwrap(),
+            wal_config(log_dir.path(), None),
+        )
+        .unwrap(),
+    );
+    let replica_dir = TempDir::new().unwrap();
+    let replica_log_dir = TempDir::new().unwrap();
+    let replica = HelixGraphStorage::new(
+        replica_dir.path().to_str().unwrap(),
+        wal_config(replica_log_dir.path(), None),
+    )
+    .unwrap();
+
+    let john = add_person(&primary, "John");
+    let jane = add_person(&primary, "Jane");
+    primary.wal.ship(&primary.graph_env).unwrap();
+    let entries = primary.wal.tail().unwrap().next_entries().unwrap();
+    assert_eq!(replica.apply_replicated(&entries).unwrap(), 2);
+
+    // the replica's own log has the entries under the primary's seqs, so it can take
+    // over from the primary
+    replica.wal.ship(&replica.graph_env).unwrap();
+    let passed_on = replica.wal.tail().unwrap().next_entries().unwrap();
+    assert_eq!(
+        passed_on.iter().map(|entry| entry.seq).collect::<Vec<_>>(),
+        entries.iter().map(|entry| entry.seq).collect::<Vec<_>>()
+    );
+    let copy_dir = TempDir::new().unwrap();
+    let copy =
+        HelixGraphStorage::new(copy_dir.path().to_str().unwrap(), Config::default()).unwrap();
+    assert_eq!(copy.apply_replicated(&passed_on).unwrap(), 2);
+    assert!(has_node(&copy, &john) && has_node(&copy, &jane));
+}
@@ -1,4 +1,5 @@
 use crate::{
+    helix_engine::graph_core::query_limits::QueryStats,
     helixc::parser::parser_methods::ParserError,
     protocol::traversal_value::TraversalValueError,
 };
@@ -32,7 +33,26 @@ pub enum GraphError {
     InvalidNode,
     ConfigFileNotFound,
     SliceLengthError,
-    ShortestPathNotFound
+    ShortestPathNotFound,
+    InvalidCursor(String),
+    CursorNotFound,
+    /// Another node already has the value for a unique index
+    UniqueViolation { index: String, value: String },
+    /// A node doesn't match the deployed schema in strict mode
+    SchemaViolation(String),
+    /// A write was sent to a read-only replica
+    ReadOnly,
+    /// The credentials of the request don't grant access to what it asked for
+    Forbidden(String),
+    /// A write was sent to a node of a cluster that isn't its leader, along with the
+    /// address of the leader if one is known
+    NotLeader {
+        leader: Option<String>,
+    },
+    /// A query went over one of its execution limits
+    QueryLimitExceeded(String),
+    /// A query was cancelled or ran past its deadline, along with what it did until then
+    QueryCancelled(QueryStats),
 }
 
 impl fmt::Display for GraphError {
@@ -62,6 +82,44 @@ impl fmt::Display for GraphError {
             GraphError::SliceLengthError => write!(f, "Slice length error"),
             GraphError::VectorError(msg) => write!(f, "Vector error: {}", msg),
             GraphError::ShortestPathNotFound => write!(f, "Shortest path not found"),
+            GraphError::InvalidCursor(cursor) => write!(f, "Invalid cursor: {}", cursor),
+            GraphError::CursorNotFound => write!(f, "Cursor not found or expired"),
+            GraphError::UniqueViolation { index, value } => {
+                write!(f, "Unique constraint violated: {} {} already exists", index, value)
+            }
+            GraphError::SchemaViolation(msg) => write!(f, "Schema violation: {}", msg),
+            GraphError::ReadOnly => {
+                write!(
+                    f,
+                    "Read-only replica, writes have to be sent to the primary"
+                )
+            }
+            GraphError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            GraphError::NotLeader {
+                leader: Some(leader),
+            } => {
+                write!(
+                    f,
+                    "Not the leader of the cluster, writes have to be sent to {}",
+                    leader
+                )
+            }
+            GraphError::NotLeader { leader: None } => {
+                write!(
+                    f,
+                    "The cluster has no leader, writes can be retried once one is elected"
+                )
+            }
+            GraphError::QueryLimitExceeded(limit) => {
+                write!(f, "Query limit exceeded: the query {}", limit)
+            }
+            GraphError::QueryCancelled(stats) => {
+                write!(
+                    f,
+                    "Query cancelled after {}ms, having visited {} items",
+                    stats.elapsed_ms, stats.visited
+                )
+            }
         }
     }
 }
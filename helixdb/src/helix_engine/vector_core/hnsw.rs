@@ -13,6 +13,8 @@ pub trait HNSW
     /// * `txn` - The transaction to use
     /// * `query` - The query vector
     /// * `k` - The number of nearest neighbors to search for
+    /// * `filter` - Filters the neighbors have to pass, checked against every vector before
+    ///   the index is walked so that `k` matching neighbors are still found
     ///
     /// # Returns
     ///
@@ -34,6 +36,7 @@ pub trait HNSW
     ///
     /// * `txn` - The transaction to use
     /// * `data` - The vector data
+    /// * `label` - The vector schema, which decides how the vector is quantized
     ///
     /// # Returns
     ///
@@ -42,6 +45,7 @@ pub trait HNSW
         &self,
         txn: &mut RwTxn,
         data: &[f64],
+        label: &str,
         fields: Option<Vec<(String, Value)>>,
     ) -> Result<HVector, VectorError>
     where
@@ -26,9 +26,12 @@ use rayon::prelude::*;
 
 type Filter = fn(&HVector, &RoTxn) -> bool;
 
+// the store outlives a single run so the search benchmarks can read the vectors the
+// insert benchmark wrote, it's kept in the temp dir rather than the working directory
 fn setup_db() -> HelixGraphStorage {
     let config = Config::new(16, 128, 768, 10);
-    let db = HelixGraphStorage::new("test-store/", config).unwrap();
+    let path = std::env::temp_dir().join("helix-hnsw-bench");
+    let db = HelixGraphStorage::new(path.to_str().unwrap(), config).unwrap();
     db
 }
 
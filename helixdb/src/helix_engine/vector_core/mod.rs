@@ -1,5 +1,6 @@
 pub mod vector;
 pub mod hnsw;
+pub mod quantization;
 pub mod vector_core;
 
 #[cfg(test)]
@@ -7,3 +8,6 @@ mod hnsw_tests;
 
 #[cfg(test)]
 mod vector_tests;
+
+#[cfg(test)]
+mod quantization_tests;
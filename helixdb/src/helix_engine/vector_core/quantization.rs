@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::helix_engine::types::VectorError;
+
+/// Most centroids of a subvector, so a centroid fits in a byte
+pub const PQ_CENTROIDS: usize = 256;
+/// Vectors a product quantization codebook is trained on if not configured
+pub const DEFAULT_TRAIN_SIZE: usize = 1024;
+const KMEANS_ITERATIONS: usize = 10;
+
+/// A vector compressed by a [`Quantization`](crate::helix_engine::graph_core::config::Quantization)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum QuantizedVector {
+    /// Each value as a byte between `min` and `min + 255 * scale`
+    Sq8 {
+        min: f32,
+        scale: f32,
+        codes: Vec<u8>,
+    },
+    /// The closest centroid of each subvector, in the codebook of the vector schema
+    Pq { label: String, codes: Vec<u8> },
+}
+
+impl QuantizedVector {
+    pub fn sq8(data: &[f64]) -> Self {
+        let min = data.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = data.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let scale = if max > min { (max - min) / 255.0 } else { 0.0 };
+        let codes = data
+            .iter()
+            .map(|value| match scale > 0.0 {
+                true => ((value - min) / scale).round() as u8,
+                false => 0,
+            })
+            .collect();
+        QuantizedVector::Sq8 {
+            min: min as f32,
+            scale: scale as f32,
+            codes,
+        }
+    }
+
+    /// The approximate values of the vector.
+    ///
+    /// Product quantized vectors need the codebook of their schema.
+    pub fn decode(&self, codebook: Option<&Codebook>) -> Result<Vec<f64>, VectorError> {
+        match self {
+            QuantizedVector::Sq8 { min, scale, codes } => Ok(codes
+                .iter()
+                .map(|code| *min as f64 + *code as f64 * *scale as f64)
+                .collect()),
+            QuantizedVector::Pq { label, codes } => match codebook {
+                Some(codebook) => codebook.decode(codes),
+                None => Err(VectorError::VectorCoreError(format!(
+                    "no codebook for vectors of `{}`",
+                    label
+                ))),
+            },
+        }
+    }
+}
+
+/// Centroids of each subvector used for product quantization
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Codebook {
+    pub dimensions: usize,
+    /// Subvector -> centroid -> values
+    pub centroids: Vec<Vec<Vec<f32>>>,
+}
+
+impl Codebook {
+    /// Clusters each subvector of the vectors with k-means, which must all have the same
+    /// number of dimensions
+    pub fn train(vectors: &[Vec<f64>], subvectors: usize) -> Result<Self, VectorError> {
+        let dimensions = match vectors.first() {
+            Some(vector) => vector.len(),
+            None => {
+                return Err(VectorError::VectorCoreError(
+                    "no vectors to train a codebook on".to_string(),
+                ))
+            }
+        };
+        if vectors.iter().any(|vector| vector.len() != dimensions) {
+            return Err(VectorError::InvalidVectorLength);
+        }
+        if subvectors == 0 || subvectors > dimensions {
+            return Err(VectorError::VectorCoreError(format!(
+                "can't split {} dimensions into {} subvectors",
+                dimensions, subvectors
+            )));
+        }
+
+        let centroids = subvector_ranges(dimensions, subvectors)
+            .into_iter()
+            .map(|range| {
+                let points = vectors
+                    .iter()
+                    .map(|vector| vector[range.clone()].iter().map(|v| *v as f32).collect())
+                    .collect::<Vec<Vec<f32>>>();
+                kmeans(&points, PQ_CENTROIDS.min(points.len()))
+            })
+            .collect();
+
+        Ok(Codebook {
+            dimensions,
+            centroids,
+        })
+    }
+
+    pub fn encode(&self, data: &[f64]) -> Result<Vec<u8>, VectorError> {
+        if data.len() != self.dimensions {
+            return Err(VectorError::InvalidVectorLength);
+        }
+        Ok(subvector_ranges(self.dimensions, self.centroids.len())
+            .into_iter()
+            .zip(&self.centroids)
+            .map(|(range, centroids)| {
+                let point = data[range].iter().map(|v| *v as f32).collect::<Vec<_>>();
+                closest(centroids, &point) as u8
+            })
+            .collect())
+    }
+
+    pub fn decode(&self, codes: &[u8]) -> Result<Vec<f64>, VectorError> {
+        if codes.len() != self.centroids.len() {
+            return Err(VectorError::InvalidVectorData);
+        }
+        let mut data = Vec::with_capacity(self.dimensions);
+        for (code, centroids) in codes.iter().zip(&self.centroids) {
+            match centroids.get(*code as usize) {
+                Some(centroid) => data.extend(centroid.iter().map(|v| *v as f64)),
+                None => return Err(VectorError::InvalidVectorData),
+            }
+        }
+        Ok(data)
+    }
+}
+
+/// Splits the dimensions into consecutive subvectors whose sizes differ by at most one
+fn subvector_ranges(dimensions: usize, subvectors: usize) -> Vec<Range<usize>> {
+    let (size, rest) = (dimensions / subvectors, dimensions % subvectors);
+    let mut start = 0;
+    (0..subvectors)
+        .map(|i| {
+            let end = start + size + usize::from(i < rest);
+            let range = start..end;
+            start = end;
+            range
+        })
+        .collect()
+}
+
+#[inline]
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+fn closest(centroids: &[Vec<f32>], point: &[f32]) -> usize {
+    centroids
+        .iter()
+        .map(|centroid| squared_distance(centroid, point))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Lloyd's k-means, starting from points spread evenly over the input so training is
+/// deterministic
+fn kmeans(points: &[Vec<f32>], k: usize) -> Vec<Vec<f32>> {
+    let mut centroids = (0..k)
+        .map(|i| points[i * points.len() / k].clone())
+        .collect::<Vec<_>>();
+    let dimensions = centroids.first().map_or(0, |c| c.len());
+
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![vec![0.0f32; dimensions]; k];
+        let mut counts = vec![0usize; k];
+        for point in points {
+            let i = closest(&centroids, point);
+            counts[i] += 1;
+            for (sum, value) in sums[i].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+        // empty clusters keep their centroid
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            if count > 0 {
+                *centroid = sum.into_iter().map(|v| v / count as f32).collect();
+            }
+        }
+    }
+    centroids
+}
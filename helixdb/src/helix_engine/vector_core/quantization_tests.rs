@@ -0,0 +1,194 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tempfile::TempDir;
+
+use super::{
+    hnsw::HNSW,
+    quantization::{Codebook, QuantizedVector},
+    vector::HVector,
+};
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::{Config, Quantization},
+            ops::{
+                g::G,
+                tr_val::{Traversable, TraversalVal},
+                vectors::{insert::InsertVAdapter, search::SearchVAdapter},
+            },
+        },
+        storage_core::storage_core::HelixGraphStorage,
+    },
+    helix_storage::heed3::RoTxn,
+};
+
+type Filter = fn(&HVector, &RoTxn) -> bool;
+
+fn open(dir: &TempDir, label: &str, quantization: Quantization) -> Arc<HelixGraphStorage> {
+    let mut config = Config::default();
+    config.vector_config.quantization = Some(HashMap::from([(label.to_string(), quantization)]));
+    Arc::new(HelixGraphStorage::new(dir.path().to_str().unwrap(), config).unwrap())
+}
+
+/// Deterministic vectors spread around a few directions
+fn test_vectors(count: usize, dimensions: usize) -> Vec<Vec<f64>> {
+    (0..count)
+        .map(|i| {
+            (0..dimensions)
+                .map(|j| ((i % 4 * dimensions + j) as f64).sin() + (i * j) as f64 * 1e-3)
+                .collect()
+        })
+        .collect()
+}
+
+fn insert(storage: &Arc<HelixGraphStorage>, label: &str, vectors: &[Vec<f64>]) -> Vec<HVector> {
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let inserted = vectors
+        .iter()
+        .map(|data| {
+            match G::new_mut(Arc::clone(storage), &mut txn)
+                .insert_v::<Filter>(data, label, None)
+                .collect_to_val()
+            {
+                TraversalVal::Vector(vector) => vector,
+                other => panic!("expected a vector, got {:?}", other),
+            }
+        })
+        .collect();
+    txn.commit().unwrap();
+    inserted
+}
+
+fn has_code(storage: &HelixGraphStorage, id: u128) -> bool {
+    let txn = storage.graph_env.read_txn().unwrap();
+    let key = [b"q:".as_slice(), &id.to_be_bytes()].concat();
+    storage
+        .vectors
+        .quantized_db
+        .get(&txn, &key)
+        .unwrap()
+        .is_some()
+}
+
+#[test]
+fn test_sq8_round_trip() {
+    let data = vec![-1.0, 0.5, 2.0, 0.0, 1.25];
+    let quantized = QuantizedVector::sq8(&data);
+    let decoded = quantized.decode(None).unwrap();
+    for (value, approx) in data.iter().zip(&decoded) {
+        assert!((value - approx).abs() <= 3.0 / 255.0 / 2.0 + 1e-6);
+    }
+
+    // constant vectors have no range to scale over
+    let decoded = QuantizedVector::sq8(&[0.5; 3]).decode(None).unwrap();
+    assert_eq!(decoded, vec![0.5; 3]);
+}
+
+#[test]
+fn test_pq_codebook() {
+    let vectors = test_vectors(64, 6);
+    let codebook = Codebook::train(&vectors, 4).unwrap();
+    // 6 dimensions split as 2, 2, 1, 1
+    assert_eq!(
+        codebook
+            .centroids
+            .iter()
+            .map(|centroids| centroids[0].len())
+            .collect::<Vec<_>>(),
+        vec![2, 2, 1, 1]
+    );
+
+    for vector in &vectors {
+        let codes = codebook.encode(vector).unwrap();
+        assert_eq!(codes.len(), 4);
+        let decoded = codebook.decode(&codes).unwrap();
+        for (value, approx) in vector.iter().zip(&decoded) {
+            assert!((value - approx).abs() < 0.1);
+        }
+    }
+
+    assert!(codebook.encode(&[1.0, 2.0]).is_err());
+    assert!(Codebook::train(&vectors, 7).is_err());
+}
+
+#[test]
+fn test_quantized_search_reranks_full_vectors() {
+    let dir = TempDir::new().unwrap();
+    let storage = open(&dir, "doc", Quantization::Sq8);
+    let vectors = test_vectors(100, 16);
+    let inserted = insert(&storage, "doc", &vectors);
+    assert!(inserted.iter().all(|vector| has_code(&storage, vector.id)));
+
+    // vectors of other schemas are stored in full only
+    let other = insert(&storage, "image", &test_vectors(1, 16));
+    assert!(!has_code(&storage, other[0].id));
+
+    let query = HVector::from_slice(0, vectors[7].clone());
+    let txn = storage.graph_env.read_txn().unwrap();
+    let results = G::new(Arc::clone(&storage), &txn)
+        .search_v::<Filter>(&vectors[7], 5, None)
+        .collect_to::<Vec<_>>();
+    assert_eq!(results.len(), 5);
+
+    let by_id = inserted
+        .iter()
+        .chain(&other)
+        .map(|vector| (vector.id, vector.get_data().to_vec()))
+        .collect::<HashMap<_, _>>();
+    let mut last = f64::NEG_INFINITY;
+    for result in results {
+        let TraversalVal::Vector(result) = result else {
+            panic!("expected a vector");
+        };
+        // the full vector is returned with its exact distance
+        assert_eq!(result.get_data(), by_id[&result.id].as_slice());
+        let distance = result.distance_to(&query).unwrap();
+        assert_eq!(result.get_distance(), distance);
+        assert!(distance >= last);
+        last = distance;
+    }
+}
+
+#[test]
+fn test_pq_codebook_is_trained_once_enough_vectors() {
+    let dir = TempDir::new().unwrap();
+    let quantization = Quantization::Pq {
+        subvectors: 4,
+        train_size: Some(20),
+    };
+    let storage = open(&dir, "doc", quantization);
+    let vectors = test_vectors(25, 8);
+
+    let first = insert(&storage, "doc", &vectors[..19]);
+    assert!(first.iter().all(|vector| !has_code(&storage, vector.id)));
+
+    // the 20th vector trains the codebook and quantizes the vectors before it
+    let rest = insert(&storage, "doc", &vectors[19..]);
+    assert!(first
+        .iter()
+        .chain(&rest)
+        .all(|vector| has_code(&storage, vector.id)));
+    {
+        let txn = storage.graph_env.read_txn().unwrap();
+        let pending = storage
+            .vectors
+            .quantized_db
+            .prefix_iter(&txn, b"pending:")
+            .unwrap()
+            .count();
+        assert_eq!(pending, 0);
+    }
+
+    // the codebook is read back when the database is opened again
+    drop(storage);
+    let storage = open(&dir, "doc", quantization);
+    let txn = storage.graph_env.read_txn().unwrap();
+    let results = storage
+        .vectors
+        .search::<Filter>(&txn, &vectors[3], 3, None, false)
+        .unwrap();
+    assert_eq!(results.len(), 3);
+    for result in results {
+        assert!(vectors.iter().any(|data| data == result.get_data()));
+    }
+}
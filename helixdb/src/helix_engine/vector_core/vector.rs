@@ -202,8 +202,8 @@ impl Filterable for HVector {
         &self.data
     }
 
-    fn score(&self) -> f64 {
-        self.get_distance()
+    fn score(&self) -> Option<f64> {
+        Some(self.get_distance())
     }
 
     fn properties_mut(&mut self) -> &mut Option<HashMap<String, Value>> {
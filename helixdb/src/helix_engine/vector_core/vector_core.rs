@@ -1,6 +1,12 @@
 use crate::helix_engine::{
+    graph_core::config::{Quantization, VectorConfig},
+    storage_core::namespaces::Namespaces,
     types::VectorError,
-    vector_core::{hnsw::HNSW, vector::HVector},
+    vector_core::{
+        hnsw::HNSW,
+        quantization::{Codebook, QuantizedVector, DEFAULT_TRAIN_SIZE},
+        vector::HVector,
+    },
 };
 use crate::protocol::value::Value;
 use crate::helix_storage::heed3::{
@@ -12,15 +18,21 @@ use rand::prelude::Rng;
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashSet},
+    collections::{BinaryHeap, HashMap, HashSet},
+    sync::{Arc, RwLock},
 };
 
 const DB_VECTORS: &str = "vectors"; // for vector data (v:)
 const DB_VECTOR_DATA: &str = "vector_data"; // for vector data (v:)
 
 const DB_HNSW_OUT_EDGES: &str = "hnsw_out_nodes"; // for hnsw out node data
+const DB_QUANTIZED_VECTORS: &str = "quantized_vectors"; // for quantized vectors and codebooks
 const VECTOR_PREFIX: &[u8] = b"v:";
 const ENTRY_POINT_KEY: &str = "entry_point";
+const QUANTIZED_PREFIX: &[u8] = b"q:";
+const CODEBOOK_PREFIX: &[u8] = b"codebook:";
+// vectors waiting for the codebook of their schema to be trained
+const PENDING_PREFIX: &[u8] = b"pending:";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HNSWConfig {
@@ -29,6 +41,8 @@ pub struct HNSWConfig {
     pub ef_construct: usize, // size of the dynamic candidate list for construction
     pub m_l: f64,            // level generation factor
     pub ef: usize,           // search param, num of cands to search
+    pub quantization: HashMap<String, Quantization>, // quantization by vector schema
+    pub rerank_factor: usize, // cands re-ranked against full vectors per result
 }
 
 impl HNSWConfig {
@@ -40,8 +54,19 @@ impl HNSWConfig {
             ef_construct: ef_construct.unwrap_or(128),
             m_l: 1.0 / (m as f64).ln(),
             ef: ef.unwrap_or(768),
+            quantization: HashMap::new(),
+            rerank_factor: 4,
         }
     }
+
+    pub fn from_config(config: &VectorConfig) -> Self {
+        let mut hnsw_config = Self::new(config.m, config.ef_construction, config.ef_search);
+        hnsw_config.quantization = config.quantization.clone().unwrap_or_default();
+        if let Some(rerank_factor) = config.rerank_factor {
+            hnsw_config.rerank_factor = rerank_factor;
+        }
+        hnsw_config
+    }
 }
 
 #[derive(PartialEq)]
@@ -169,20 +194,60 @@ pub struct VectorCore {
     pub vectors_db: Database<Bytes, Bytes>,
     pub vector_data_db: Database<Bytes, Bytes>,
     pub out_edges_db: Database<Bytes, Unit>,
+    pub quantized_db: Database<Bytes, Bytes>,
     pub config: HNSWConfig,
+    /// Product quantization codebooks by vector schema, as stored in `quantized_db`
+    codebooks: RwLock<HashMap<String, Arc<Codebook>>>,
+    /// Tag of the namespace the index is for, which the ids of its vectors start with
+    tag: Option<u16>,
 }
 
 impl VectorCore {
     pub fn new(env: &Env, txn: &mut RwTxn, config: HNSWConfig) -> Result<Self, VectorError> {
-        let vectors_db = env.create_database(txn, Some(DB_VECTORS))?;
-        let vector_data_db = env.create_database(txn, Some(DB_VECTOR_DATA))?;
-        let out_edges_db = env.create_database(txn, Some(DB_HNSW_OUT_EDGES))?;
+        Self::open(env, txn, config, None)
+    }
+
+    /// Opens the index of the vectors of a namespace, kept in databases of its own
+    pub fn new_in_namespace(
+        env: &Env,
+        txn: &mut RwTxn,
+        config: HNSWConfig,
+        namespace: &str,
+    ) -> Result<Self, VectorError> {
+        Self::open(env, txn, config, Some(namespace))
+    }
+
+    fn open(
+        env: &Env,
+        txn: &mut RwTxn,
+        config: HNSWConfig,
+        namespace: Option<&str>,
+    ) -> Result<Self, VectorError> {
+        let name = |db: &str| match namespace {
+            Some(namespace) => format!("{}:{}", namespace, db),
+            None => db.to_string(),
+        };
+        let vectors_db = env.create_database(txn, Some(&name(DB_VECTORS)))?;
+        let vector_data_db = env.create_database(txn, Some(&name(DB_VECTOR_DATA)))?;
+        let out_edges_db = env.create_database(txn, Some(&name(DB_HNSW_OUT_EDGES)))?;
+        let quantized_db: Database<Bytes, Bytes> =
+            env.create_database(txn, Some(&name(DB_QUANTIZED_VECTORS)))?;
+
+        let mut codebooks = HashMap::new();
+        for result in quantized_db.prefix_iter(txn, CODEBOOK_PREFIX)? {
+            let (key, value) = result?;
+            let label = String::from_utf8(key[CODEBOOK_PREFIX.len()..].to_vec())?;
+            codebooks.insert(label, Arc::new(bincode::deserialize(value)?));
+        }
 
         Ok(Self {
             vectors_db,
             vector_data_db,
             out_edges_db,
+            quantized_db,
             config,
+            codebooks: RwLock::new(codebooks),
+            tag: namespace.map(Namespaces::tag),
         })
     }
 
@@ -210,6 +275,179 @@ impl VectorCore {
         }
     }
 
+    #[inline(always)]
+    fn quantized_key(id: u128) -> Vec<u8> {
+        [QUANTIZED_PREFIX, &id.to_be_bytes()].concat()
+    }
+
+    #[inline(always)]
+    fn codebook_key(label: &str) -> Vec<u8> {
+        [CODEBOOK_PREFIX, label.as_bytes()].concat()
+    }
+
+    #[inline(always)]
+    fn pending_key(label: &str, id: Option<u128>) -> Vec<u8> {
+        match id {
+            Some(id) => [PENDING_PREFIX, label.as_bytes(), b":", &id.to_be_bytes()].concat(),
+            None => [PENDING_PREFIX, label.as_bytes(), b":"].concat(),
+        }
+    }
+
+    /// Generates the id of a new vector, in the range of the index's namespace
+    pub fn new_vector_id(&self) -> u128 {
+        let id = uuid::Uuid::new_v4().as_u128();
+        match self.tag {
+            Some(tag) => Namespaces::tag_id(tag, id),
+            None => id,
+        }
+    }
+
+    /// Stores a vector without linking it to its neighbours, so it isn't found by
+    /// searches until [`VectorCore::link`] is called for it
+    pub fn store(
+        &self,
+        txn: &mut RwTxn,
+        id: u128,
+        data: &[f64],
+        label: &str,
+        fields: Option<Vec<(String, Value)>>,
+    ) -> Result<HVector, VectorError> {
+        let mut vector = HVector::from_slice(0, data.to_vec());
+        vector.id = id;
+        self.put_vector(txn, &vector)?;
+        self.quantize(txn, &vector, label)?;
+
+        if let Some(fields) = fields {
+            self.vector_data_db.put(
+                txn,
+                &vector.get_id().to_be_bytes(),
+                &bincode::serialize(&fields)?,
+            )?;
+        }
+        Ok(vector)
+    }
+
+    /// Links a stored vector into the index at a random level, connecting it to its
+    /// nearest neighbours on every level up to it
+    pub fn link<F>(&self, txn: &mut RwTxn, mut query: HVector) -> Result<HVector, VectorError>
+    where
+        F: Fn(&HVector, &RoTxn) -> bool,
+    {
+        let new_level = self.get_new_level();
+        query.level = new_level;
+        if new_level > 0 {
+            self.put_vector(txn, &query)?;
+        }
+
+        let entry_point = match self.get_entry_point(txn) {
+            Ok(ep) => ep,
+            Err(_) => {
+                self.set_entry_point(txn, &query)?;
+                query.set_distance(0.0);
+                return Ok(query);
+            }
+        };
+
+        let l = entry_point.get_level();
+        let mut curr_ep = entry_point;
+        for level in (new_level + 1..=l).rev() {
+            let nearest =
+                self.search_level::<F>(txn, &query, &mut curr_ep, 1, level, None, None)?;
+            curr_ep = nearest.peek().unwrap().clone();
+        }
+
+        for level in (0..=l.min(new_level)).rev() {
+            let nearest = self.search_level::<F>(
+                txn,
+                &query,
+                &mut curr_ep,
+                self.config.ef_construct,
+                level,
+                None,
+                None,
+            )?;
+
+            curr_ep = nearest.peek().unwrap().clone();
+
+            let neighbors = self.select_neighbors::<F>(txn, &query, nearest, level, true, None)?;
+
+            self.set_neighbours(txn, query.get_id(), &neighbors, level)?;
+
+            for e in neighbors {
+                let id = e.get_id();
+                let e_conns = self.get_neighbors::<F>(txn, id, level, None)?;
+                if e_conns.len()
+                    > if level == 0 {
+                        self.config.m_max_0
+                    } else {
+                        self.config.m_max_0
+                    }
+                {
+                    let e_conns = BinaryHeap::from(e_conns);
+                    let e_new_conn =
+                        self.select_neighbors::<F>(txn, &query, e_conns, level, true, None)?;
+                    self.set_neighbours(txn, id, &e_new_conn, level)?;
+                }
+            }
+        }
+
+        if new_level > l {
+            self.set_entry_point(txn, &query)?;
+        }
+
+        Ok(query)
+    }
+
+    /// Links of the graph from or to a vector that isn't stored at their level, as
+    /// `(source id, level, sink id)`
+    pub fn dangling_links(&self, txn: &RoTxn) -> Result<Vec<(u128, usize, u128)>, VectorError> {
+        let mut dangling = Vec::new();
+        for result in self.out_edges_db.iter(txn)? {
+            let (key, _) = result?;
+            if key.len() != 40 {
+                continue;
+            }
+            let source_id = u128::from_be_bytes(key[0..16].try_into().unwrap());
+            let level = usize::from_be_bytes(key[16..24].try_into().unwrap());
+            let sink_id = u128::from_be_bytes(key[24..40].try_into().unwrap());
+            let missing = |id: u128| match self.get_vector(txn, id, level, false) {
+                Ok(_) => Ok(false),
+                Err(VectorError::VectorNotFound(_)) => Ok(true),
+                Err(e) => Err(e),
+            };
+            if missing(source_id)? || missing(sink_id)? {
+                dangling.push((source_id, level, sink_id));
+            }
+        }
+        Ok(dangling)
+    }
+
+    /// Removes a link of the graph, in the direction from `source_id` to `sink_id` only
+    pub fn unlink(
+        &self,
+        txn: &mut RwTxn,
+        source_id: u128,
+        level: usize,
+        sink_id: u128,
+    ) -> Result<(), VectorError> {
+        self.out_edges_db
+            .delete(txn, &Self::out_edges_key(source_id, level, Some(sink_id)))?;
+        Ok(())
+    }
+
+    /// Whether the entry point searches start at is missing while vectors are stored
+    pub fn entry_point_missing(&self, txn: &RoTxn) -> Result<bool, VectorError> {
+        match self.get_entry_point(txn) {
+            Ok(_) => Ok(false),
+            Err(VectorError::EntryPointNotFound) => Ok(self
+                .vectors_db
+                .prefix_iter(txn, VECTOR_PREFIX)?
+                .next()
+                .is_some()),
+            Err(e) => Err(e),
+        }
+    }
+
     #[inline]
     fn get_new_level(&self) -> usize {
         // TODO: look at using the XOR shift algorithm for random number generation
@@ -262,6 +500,203 @@ impl VectorCore {
         Ok(())
     }
 
+    /// Stores the quantized form of a vector if its schema is quantized.
+    ///
+    /// Vectors of a product quantized schema wait until there are enough of them to
+    /// train the codebook of the schema on, and are then all quantized at once.
+    fn quantize(&self, txn: &mut RwTxn, vector: &HVector, label: &str) -> Result<(), VectorError> {
+        let quantized = match self.config.quantization.get(label) {
+            None => return Ok(()),
+            Some(Quantization::Sq8) => QuantizedVector::sq8(vector.get_data()),
+            Some(Quantization::Pq {
+                subvectors,
+                train_size,
+            }) => {
+                if self
+                    .quantized_db
+                    .get(txn, &Self::codebook_key(label))?
+                    .is_none()
+                {
+                    self.quantized_db.put(
+                        txn,
+                        &Self::pending_key(label, Some(vector.get_id())),
+                        &[],
+                    )?;
+                    let pending = self
+                        .quantized_db
+                        .prefix_iter(txn, &Self::pending_key(label, None))?
+                        .count();
+                    if pending >= train_size.unwrap_or(DEFAULT_TRAIN_SIZE) {
+                        self.train_codebook(txn, label, *subvectors)?;
+                    }
+                    return Ok(());
+                }
+                QuantizedVector::Pq {
+                    label: label.to_string(),
+                    codes: self.codebook(txn, label)?.encode(vector.get_data())?,
+                }
+            }
+        };
+        self.quantized_db.put(
+            txn,
+            &Self::quantized_key(vector.get_id()),
+            &bincode::serialize(&quantized)?,
+        )?;
+        Ok(())
+    }
+
+    /// Trains the codebook of a schema on its pending vectors and quantizes them
+    fn train_codebook(
+        &self,
+        txn: &mut RwTxn,
+        label: &str,
+        subvectors: usize,
+    ) -> Result<(), VectorError> {
+        let prefix = Self::pending_key(label, None);
+        let ids = self
+            .quantized_db
+            .prefix_iter(txn, &prefix)?
+            .map(|result| {
+                let (key, _) = result?;
+                let id: [u8; 16] = key[prefix.len()..]
+                    .try_into()
+                    .map_err(|_| VectorError::InvalidVectorData)?;
+                Ok(u128::from_be_bytes(id))
+            })
+            .collect::<Result<Vec<_>, VectorError>>()?;
+        let vectors = ids
+            .iter()
+            .map(|id| Ok(self.get_vector(txn, *id, 0, true)?.get_data().to_vec()))
+            .collect::<Result<Vec<_>, VectorError>>()?;
+
+        let codebook = Codebook::train(&vectors, subvectors)?;
+        for (id, data) in ids.iter().zip(&vectors) {
+            let quantized = QuantizedVector::Pq {
+                label: label.to_string(),
+                codes: codebook.encode(data)?,
+            };
+            self.quantized_db.put(
+                txn,
+                &Self::quantized_key(*id),
+                &bincode::serialize(&quantized)?,
+            )?;
+            self.quantized_db
+                .delete(txn, &Self::pending_key(label, Some(*id)))?;
+        }
+        self.quantized_db.put(
+            txn,
+            &Self::codebook_key(label),
+            &bincode::serialize(&codebook)?,
+        )?;
+        // replaces any codebook of a write that was aborted
+        self.codebooks
+            .write()
+            .unwrap()
+            .insert(label.to_string(), Arc::new(codebook));
+        Ok(())
+    }
+
+    fn codebook(&self, txn: &RoTxn, label: &str) -> Result<Arc<Codebook>, VectorError> {
+        if let Some(codebook) = self.codebooks.read().unwrap().get(label) {
+            return Ok(Arc::clone(codebook));
+        }
+        match self.quantized_db.get(txn, &Self::codebook_key(label))? {
+            Some(bytes) => {
+                let codebook = Arc::new(bincode::deserialize::<Codebook>(bytes)?);
+                self.codebooks
+                    .write()
+                    .unwrap()
+                    .insert(label.to_string(), Arc::clone(&codebook));
+                Ok(codebook)
+            }
+            None => Err(VectorError::VectorCoreError(format!(
+                "no codebook for vectors of `{}`",
+                label
+            ))),
+        }
+    }
+
+    /// Gets a vector to compare while walking the index, which is approximated from its
+    /// quantized form if it has one
+    #[inline(always)]
+    fn get_search_vector(
+        &self,
+        txn: &RoTxn,
+        id: u128,
+        level: usize,
+    ) -> Result<HVector, VectorError> {
+        if self.config.quantization.is_empty() {
+            return self.get_vector(txn, id, level, true);
+        }
+        match self.quantized_db.get(txn, &Self::quantized_key(id))? {
+            Some(bytes) => {
+                let quantized = bincode::deserialize::<QuantizedVector>(bytes)?;
+                let data = match &quantized {
+                    QuantizedVector::Pq { label, .. } => {
+                        quantized.decode(Some(self.codebook(txn, label)?.as_ref()))?
+                    }
+                    QuantizedVector::Sq8 { .. } => quantized.decode(None)?,
+                };
+                let mut vector = HVector::from_slice(level, data);
+                vector.id = id;
+                Ok(vector)
+            }
+            None => self.get_vector(txn, id, level, true),
+        }
+    }
+
+    /// Ids of the vectors that pass every filter, with their properties loaded so the
+    /// filters can check them
+    fn allowed_ids<F>(&self, txn: &RoTxn, filter: &[F]) -> Result<HashSet<u128>, VectorError>
+    where
+        F: Fn(&HVector, &RoTxn) -> bool,
+    {
+        let mut allowed = HashSet::new();
+        let iter = self
+            .vectors_db
+            .lazily_decode_data()
+            .prefix_iter(txn, VECTOR_PREFIX)?;
+        for result in iter {
+            let (key, _) = result?;
+            let (id, level) = key[VECTOR_PREFIX.len()..].split_at(16);
+            // every vector is stored at level 0
+            if level.iter().any(|byte| *byte != 0) {
+                continue;
+            }
+            let mut vector = HVector::from_slice(0, vec![]);
+            vector.id = u128::from_be_bytes(
+                id.try_into()
+                    .map_err(|_| VectorError::ConversionError("invalid vector key".to_string()))?,
+            );
+            vector.properties = match self.vector_data_db.get(txn, id)? {
+                Some(bytes) => Some(bincode::deserialize(bytes)?),
+                None => None,
+            };
+            if filter.iter().all(|f| f(&vector, txn)) {
+                allowed.insert(vector.id);
+            }
+        }
+        Ok(allowed)
+    }
+
+    /// Recomputes the distances of the candidates with the full vectors and keeps the
+    /// `k` closest
+    fn rerank(
+        &self,
+        txn: &RoTxn,
+        query: &HVector,
+        candidates: Vec<HVector>,
+        k: usize,
+    ) -> Result<Vec<HVector>, VectorError> {
+        let mut reranked = BinaryHeap::with_capacity(candidates.len());
+        for candidate in candidates {
+            let mut vector = self.get_vector(txn, candidate.get_id(), 0, true)?;
+            vector.set_distance(vector.distance_to(query)?);
+            reranked.push(vector);
+        }
+        Ok(reranked.to_vec(k))
+    }
+
     #[inline(always)]
     fn get_neighbors<F>(
         &self,
@@ -292,7 +727,7 @@ impl VectorCore {
                 let neighbor_id = u128::from_be_bytes(arr);
 
                 if neighbor_id != id {
-                    if let Ok(vector) = self.get_vector(txn, neighbor_id, level, true) {
+                    if let Ok(vector) = self.get_search_vector(txn, neighbor_id, level) {
                         // TODO: look at implementing a macro that actually just runs each function rather than iterating through
                         if filter.is_none() || filter.unwrap().iter().all(|f| f(&vector, txn)) {
                             neighbors.push(vector);
@@ -393,6 +828,7 @@ impl VectorCore {
         ef: usize,
         level: usize,
         filter: Option<&[F]>,
+        allowed: Option<&HashSet<u128>>,
     ) -> Result<BinaryHeap<HVector>, VectorError>
     where
         F: Fn(&HVector, &RoTxn) -> bool,
@@ -400,13 +836,17 @@ impl VectorCore {
         let mut visited: HashSet<u128> = HashSet::new();
         let mut candidates: BinaryHeap<Candidate> = BinaryHeap::new();
         let mut results: BinaryHeap<HVector> = BinaryHeap::new();
+        // vectors that aren't allowed are still walked through to reach the ones that are
+        let is_allowed = |id: &u128| allowed.is_none_or(|allowed| allowed.contains(id));
 
         entry_point.set_distance(entry_point.distance_to(query)?);
         candidates.push(Candidate {
             id: entry_point.get_id(),
             distance: entry_point.get_distance(),
         });
-        results.push(entry_point.clone());
+        if is_allowed(&entry_point.get_id()) {
+            results.push(entry_point.clone());
+        }
         visited.insert(entry_point.get_id());
 
         while let Some(curr_cand) = candidates.pop() {
@@ -441,9 +881,11 @@ impl VectorCore {
                         id: neighbor.get_id(),
                         distance,
                     });
-                    results.push(neighbor);
-                    if results.len() > ef {
-                        results = results.take_inord(ef);
+                    if is_allowed(&neighbor.get_id()) {
+                        results.push(neighbor);
+                        if results.len() > ef {
+                            results = results.take_inord(ef);
+                        }
                     }
                 });
         }
@@ -490,6 +932,10 @@ impl HNSW for VectorCore {
         let query = HVector::from_slice(0, query.to_vec());
 
         let mut entry_point = self.get_entry_point(txn)?;
+        let allowed = match filter {
+            Some(filter) => Some(self.allowed_ids(txn, filter)?),
+            None => None,
+        };
 
         let ef = self.config.ef;
         let curr_level = entry_point.get_level();
@@ -505,6 +951,7 @@ impl HNSW for VectorCore {
                     true => filter,
                     false => None,
                 },
+                None,
             )?;
             if let Some(closest) = nearest.pop() {
                 entry_point = closest;
@@ -521,9 +968,17 @@ impl HNSW for VectorCore {
                 true => filter,
                 false => None,
             },
+            allowed.as_ref(),
         )?;
 
-        let mut results = candidates.to_vec_with_filter(k, filter, txn);
+        // every candidate is already allowed by the filter
+        let mut results = match self.config.quantization.is_empty() {
+            true => candidates.to_vec(k),
+            false => {
+                let candidates = candidates.to_vec(k * self.config.rerank_factor.max(1));
+                self.rerank(txn, &query, candidates, k)?
+            }
+        };
 
         for result in results.iter_mut() {
             result.properties = match self
@@ -542,83 +997,14 @@ impl HNSW for VectorCore {
         &self,
         txn: &mut RwTxn,
         data: &[f64],
+        label: &str,
         fields: Option<Vec<(String, Value)>>,
     ) -> Result<HVector, VectorError>
     where
         F: Fn(&HVector, &RoTxn) -> bool,
     {
-        let new_level = self.get_new_level();
-
-        let mut query = HVector::from_slice(0, data.to_vec());
-        self.put_vector(txn, &query)?;
-
-        query.level = new_level;
-        if new_level > 0 {
-            self.put_vector(txn, &query)?;
-        }
-
-        let entry_point = match self.get_entry_point(txn) {
-            Ok(ep) => ep,
-            Err(_) => {
-                self.set_entry_point(txn, &query)?;
-                query.set_distance(0.0);
-                return Ok(query);
-            }
-        };
-
-        let l = entry_point.get_level();
-        let mut curr_ep = entry_point;
-        for level in (new_level + 1..=l).rev() {
-            let nearest = self.search_level::<F>(txn, &query, &mut curr_ep, 1, level, None)?;
-            curr_ep = nearest.peek().unwrap().clone();
-        }
-
-        for level in (0..=l.min(new_level)).rev() {
-            let nearest = self.search_level::<F>(
-                txn,
-                &query,
-                &mut curr_ep,
-                self.config.ef_construct,
-                level,
-                None,
-            )?;
-
-            curr_ep = nearest.peek().unwrap().clone();
-
-            let neighbors = self.select_neighbors::<F>(txn, &query, nearest, level, true, None)?;
-
-            self.set_neighbours(txn, query.get_id(), &neighbors, level)?;
-
-            for e in neighbors {
-                let id = e.get_id();
-                let e_conns = self.get_neighbors::<F>(txn, id, level, None)?;
-                if e_conns.len()
-                    > if level == 0 {
-                        self.config.m_max_0
-                    } else {
-                        self.config.m_max_0
-                    }
-                {
-                    let e_conns = BinaryHeap::from(e_conns);
-                    let e_new_conn =
-                        self.select_neighbors::<F>(txn, &query, e_conns, level, true, None)?;
-                    self.set_neighbours(txn, id, &e_new_conn, level)?;
-                }
-            }
-        }
-
-        if new_level > l {
-            self.set_entry_point(txn, &query)?;
-        }
-
-        if let Some(fields) = fields {
-            self.vector_data_db.put(
-                txn,
-                &query.get_id().to_be_bytes(),
-                &bincode::serialize(&fields)?,
-            )?;
-        }
-        Ok(query)
+        let query = self.store(txn, self.new_vector_id(), data, label, fields)?;
+        self.link::<F>(txn, query)
     }
 
     fn get_all_vectors(
@@ -0,0 +1,182 @@
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use sonic_rs::{JsonContainerTrait, JsonValueTrait};
+use twox_hash::XxHash64;
+
+use crate::{
+    helix_engine::{
+        graph_core::config::{AuthConfig, JwtAlgorithm},
+        types::GraphError,
+    },
+    protocol::{request::Request, response::Response},
+};
+
+/// Header carrying a static API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Claim of a token the roles are read from unless configured otherwise
+pub const DEFAULT_ROLE_CLAIM: &str = "role";
+
+/// Checks the credentials of requests against the keys of an [`AuthConfig`]
+pub struct Authenticator {
+    api_keys: Vec<String>,
+    jwt: Option<(DecodingKey, Validation)>,
+    role_claim: String,
+    admin_keys: Vec<String>,
+    admin_role: Option<String>,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig) -> Result<Self, GraphError> {
+        let jwt = match &config.jwt {
+            Some(jwt) => {
+                let (key, algorithm) = match jwt.algorithm {
+                    JwtAlgorithm::HS256 => (
+                        DecodingKey::from_secret(jwt.key.as_bytes()),
+                        Algorithm::HS256,
+                    ),
+                    JwtAlgorithm::RS256 => (
+                        DecodingKey::from_rsa_pem(jwt.key.as_bytes()).map_err(|e| {
+                            GraphError::New(format!("Invalid RS256 public key: {}", e))
+                        })?,
+                        Algorithm::RS256,
+                    ),
+                };
+                let mut validation = Validation::new(algorithm);
+                // the issuer and audience are only checked if the token has them, unless
+                // they are required
+                let mut required = vec!["exp"];
+                if let Some(issuer) = &jwt.issuer {
+                    validation.set_issuer(&[issuer]);
+                    required.push("iss");
+                }
+                match &jwt.audience {
+                    Some(audience) => {
+                        validation.set_audience(&[audience]);
+                        required.push("aud");
+                    }
+                    None => validation.validate_aud = false,
+                }
+                validation.set_required_spec_claims(&required);
+                Some((key, validation))
+            }
+            None => None,
+        };
+        let role_claim = config
+            .jwt
+            .as_ref()
+            .and_then(|jwt| jwt.role_claim.clone())
+            .unwrap_or_else(|| DEFAULT_ROLE_CLAIM.to_string());
+        Ok(Self {
+            api_keys: config.api_keys.clone(),
+            jwt,
+            role_claim,
+            admin_keys: config.admin_keys.clone(),
+            admin_role: config.admin_role.clone(),
+        })
+    }
+
+    /// Accepts a request with a known API key or a valid bearer token, setting the
+    /// claims of the token on the request. Other requests get a 401 response.
+    pub fn authenticate(&self, request: &mut Request) -> Result<(), Response> {
+        if let Some(key) = request.headers.get(API_KEY_HEADER) {
+            if self
+                .api_keys
+                .iter()
+                .chain(&self.admin_keys)
+                .any(|api_key| constant_time_eq(api_key.as_bytes(), key.as_bytes()))
+            {
+                return Ok(());
+            }
+            return Err(unauthorized("Invalid API key"));
+        }
+
+        let token = request
+            .headers
+            .get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match (token, &self.jwt) {
+            (Some(token), Some((key, validation))) => {
+                // sonic_rs values can only be deserialized by sonic_rs itself
+                let claims = decode::<serde_json::Value>(token.trim(), key, validation)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| sonic_rs::to_value(&data.claims).map_err(|e| e.to_string()));
+                match claims {
+                    Ok(claims) => {
+                        request.claims = Some(claims);
+                        Ok(())
+                    }
+                    Err(e) => Err(unauthorized(&format!("Invalid token: {}", e))),
+                }
+            }
+            _ => Err(unauthorized("Missing credentials")),
+        }
+    }
+
+    /// Whether an authenticated request can use the `/admin` endpoints, having one of the
+    /// admin keys or a token granting the admin role
+    pub fn is_admin(&self, request: &Request) -> bool {
+        if let Some(key) = request.headers.get(API_KEY_HEADER) {
+            return self
+                .admin_keys
+                .iter()
+                .any(|admin_key| constant_time_eq(admin_key.as_bytes(), key.as_bytes()));
+        }
+        self.admin_role
+            .as_ref()
+            .is_some_and(|admin_role| self.roles(request).contains(admin_role))
+    }
+
+    /// Roles the token of an authenticated request grants, read from a claim holding
+    /// either a single role or an array of them. Requests authenticated with an API key
+    /// hold no role.
+    pub fn roles(&self, request: &Request) -> Vec<String> {
+        let Some(claim) = request
+            .claims
+            .as_ref()
+            .and_then(|claims| claims.get(&self.role_claim))
+        else {
+            return Vec::new();
+        };
+        match claim.as_str() {
+            Some(role) => vec![role.to_string()],
+            None => claim
+                .as_array()
+                .map(|roles| {
+                    roles
+                        .iter()
+                        .filter_map(|role| role.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Identifies who sent an authenticated request, for the audit log: the subject of its
+/// token, or a fingerprint of its API key so the key itself isn't recorded
+pub fn caller(request: &Request) -> Option<String> {
+    if let Some(key) = request.headers.get(API_KEY_HEADER) {
+        return Some(format!("key:{:016x}", XxHash64::oneshot(0, key.as_bytes())));
+    }
+    let claims = request.claims.as_ref()?;
+    claims
+        .get("sub")
+        .and_then(|sub| sub.as_str())
+        .map(str::to_string)
+}
+
+fn unauthorized(reason: &str) -> Response {
+    let mut response = Response::new();
+    response.status = 401;
+    response
+        .headers
+        .insert("WWW-Authenticate".to_string(), "Bearer".to_string());
+    response.body = format!("401 - Unauthorized: {}", reason).into_bytes();
+    response
+}
+
+/// Compares keys without returning early, so the time taken doesn't leak how much of a
+/// key was guessed right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
@@ -0,0 +1,4 @@
+pub mod auth;
+
+#[cfg(test)]
+mod auth_tests;
@@ -0,0 +1,396 @@
+use super::{
+    execute::{self, QueryResult},
+    packstream::BoltValue,
+};
+use crate::helix_engine::{
+    graph_core::{graph_core::HelixGraphEngine, query_limits::QueryGuard},
+    types::GraphError,
+};
+use crate::helix_gateway::{auth::auth::API_KEY_HEADER, router::router::HelixRouter};
+use crate::helix_transport::{Listener, Transport};
+use crate::helixc::cypher::cypher::CypherError;
+use crate::protocol::request::Request;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Bytes opening every Bolt connection, before the proposed versions
+const MAGIC: [u8; 4] = [0x60, 0x60, 0xB0, 0x17];
+/// Newest minor version of Bolt 4 spoken by the server
+const MINOR: u8 = 4;
+/// Largest chunk of a message, chunk sizes being 16 bit
+const MAX_CHUNK: usize = 0xFFFF;
+
+// signatures of the request messages
+const HELLO: u8 = 0x01;
+const GOODBYE: u8 = 0x02;
+const RESET: u8 = 0x0F;
+const RUN: u8 = 0x10;
+const BEGIN: u8 = 0x11;
+const COMMIT: u8 = 0x12;
+const ROLLBACK: u8 = 0x13;
+const DISCARD: u8 = 0x2F;
+const PULL: u8 = 0x3F;
+const ROUTE: u8 = 0x66;
+
+// signatures of the response messages
+const SUCCESS: u8 = 0x70;
+const RECORD: u8 = 0x71;
+const IGNORED: u8 = 0x7E;
+const FAILURE: u8 = 0x7F;
+
+/// Serves the Cypher subset of [`crate::helixc::cypher`] over Bolt 4, so the official
+/// Neo4j drivers can connect to Helix.
+///
+/// Queries are read only and run in a read transaction each, within the query limits of
+/// the graph and cancelled along with the router's queries; explicit transactions are
+/// accepted but don't group the queries run in them. Drivers authenticate with the API
+/// key of the gateway as the password of the basic scheme, or with a JWT as the
+/// credentials of the bearer scheme.
+pub struct BoltServer {
+    graph: Arc<HelixGraphEngine>,
+    router: Arc<HelixRouter>,
+}
+
+impl BoltServer {
+    pub fn new(graph: Arc<HelixGraphEngine>, router: Arc<HelixRouter>) -> Self {
+        Self { graph, router }
+    }
+
+    /// Serves connections on the address until binding or accepting fails
+    pub async fn serve<T: Transport>(
+        self,
+        address: SocketAddr,
+        transport: T,
+    ) -> Result<(), GraphError> {
+        let listener = transport
+            .bind(address)
+            .await
+            .map_err(|e| GraphError::New(format!("Failed to bind Bolt server: {}", e)))?;
+        let server = Arc::new(self);
+        let connections = AtomicU64::new(0);
+        loop {
+            let (stream, _) = listener
+                .accept()
+                .await
+                .map_err(|e| GraphError::New(format!("Bolt server failed: {}", e)))?;
+            let connection = Connection {
+                server: Arc::clone(&server),
+                id: format!("bolt-{}", connections.fetch_add(1, Ordering::Relaxed)),
+                authenticated: false,
+                failed: false,
+                result: None,
+            };
+            tokio::spawn(async move {
+                if let Err(e) = connection.run(stream).await {
+                    eprintln!("Bolt connection failed: {:?}", e);
+                }
+            });
+        }
+    }
+}
+
+struct Connection {
+    server: Arc<BoltServer>,
+    id: String,
+    authenticated: bool,
+    /// Whether a request failed, requests are then ignored until a `RESET`
+    failed: bool,
+    /// Records of the last query, until they are pulled or discarded
+    result: Option<VecDeque<Vec<BoltValue>>>,
+}
+
+impl Connection {
+    async fn run<S: AsyncRead + AsyncWrite + Unpin>(
+        mut self,
+        mut stream: S,
+    ) -> Result<(), GraphError> {
+        let mut handshake = [0u8; 20];
+        stream.read_exact(&mut handshake).await?;
+        if handshake[..4] != MAGIC {
+            return Err(GraphError::New("Not a Bolt connection".to_string()));
+        }
+        let version = handshake[4..]
+            .chunks(4)
+            .find_map(|proposal| negotiate(proposal.try_into().unwrap()));
+        let Some(minor) = version else {
+            // no common version, the client closes the connection
+            stream.write_all(&[0; 4]).await?;
+            return Ok(());
+        };
+        stream.write_all(&[0, 0, minor, 4]).await?;
+
+        loop {
+            let message = match read_message(&mut stream).await? {
+                Some(message) => message,
+                None => return Ok(()),
+            };
+            let BoltValue::Struct(signature, fields) = BoltValue::decode(&mut message.as_slice())?
+            else {
+                return Err(GraphError::DecodeError(
+                    "Bolt message that isn't a structure".to_string(),
+                ));
+            };
+            if signature == GOODBYE {
+                return Ok(());
+            }
+            for response in self.respond(signature, fields).await {
+                write_message(&mut stream, &response).await?;
+            }
+            stream.flush().await?;
+        }
+    }
+
+    /// The responses to a request, in order
+    async fn respond(&mut self, signature: u8, fields: Vec<BoltValue>) -> Vec<BoltValue> {
+        if signature == RESET {
+            self.failed = false;
+            self.result = None;
+            return vec![success(HashMap::new())];
+        }
+        if self.failed {
+            return vec![BoltValue::Struct(IGNORED, Vec::new())];
+        }
+        let responses = match (signature, self.authenticated) {
+            (HELLO, false) => self.hello(&fields),
+            (HELLO, true) => Err(failure(
+                "Neo.ClientError.Request.Invalid",
+                "HELLO was already sent",
+            )),
+            (_, false) => Err(failure(
+                "Neo.ClientError.Security.Unauthorized",
+                "HELLO must be sent first",
+            )),
+            (RUN, true) => self.run_query(fields).await,
+            (PULL, true) => self.pull(&fields),
+            (DISCARD, true) => {
+                self.result = None;
+                Ok(vec![success(HashMap::from([(
+                    "has_more".to_string(),
+                    BoltValue::Boolean(false),
+                )]))])
+            }
+            (BEGIN | COMMIT | ROLLBACK, true) => Ok(vec![success(HashMap::new())]),
+            (ROUTE, true) => Err(failure(
+                "Neo.ClientError.Request.Invalid",
+                "Helix has no routing table, connect with bolt:// instead of neo4j://",
+            )),
+            (signature, true) => Err(failure(
+                "Neo.ClientError.Request.Invalid",
+                &format!("Unknown message {:#04x}", signature),
+            )),
+        };
+        responses.unwrap_or_else(|failure| {
+            self.failed = true;
+            self.result = None;
+            vec![failure]
+        })
+    }
+
+    fn hello(&mut self, fields: &[BoltValue]) -> Result<Vec<BoltValue>, BoltValue> {
+        let extra = fields.first();
+        let field = |key| {
+            extra
+                .and_then(|extra| extra.get(key))
+                .and_then(BoltValue::as_str)
+        };
+        let mut headers = HashMap::new();
+        match (field("scheme"), field("credentials")) {
+            (Some("basic"), Some(credentials)) => {
+                headers.insert(API_KEY_HEADER.to_string(), credentials.to_string());
+            }
+            (Some("bearer"), Some(credentials)) => {
+                headers.insert(
+                    "authorization".to_string(),
+                    format!("Bearer {}", credentials),
+                );
+            }
+            _ => {}
+        }
+        let mut request = Request {
+            method: "POST".to_string(),
+            headers,
+            path: "/bolt".to_string(),
+            query: None,
+            version: "Bolt/4".to_string(),
+            body: Vec::new(),
+            claims: None,
+        };
+        if self.server.router.authenticate(&mut request).is_err() {
+            return Err(failure(
+                "Neo.ClientError.Security.Unauthorized",
+                "The client is unauthorized due to authentication failure.",
+            ));
+        }
+        self.authenticated = true;
+        Ok(vec![success(HashMap::from([
+            // drivers check the product of the server before running queries
+            (
+                "server".to_string(),
+                BoltValue::String("Neo4j/4.4.0".to_string()),
+            ),
+            (
+                "connection_id".to_string(),
+                BoltValue::String(self.id.clone()),
+            ),
+        ]))])
+    }
+
+    async fn run_query(&mut self, fields: Vec<BoltValue>) -> Result<Vec<BoltValue>, BoltValue> {
+        let mut fields = fields.into_iter();
+        let Some(BoltValue::String(query)) = fields.next() else {
+            return Err(failure(
+                "Neo.ClientError.Request.Invalid",
+                "RUN without a query",
+            ));
+        };
+        let params = match fields.next() {
+            Some(BoltValue::Map(params)) => params
+                .iter()
+                .map(|(key, value)| (key.clone(), execute::param_value(value)))
+                .collect(),
+            _ => HashMap::new(),
+        };
+        // queries block on the graph, so they are kept off the runtime's workers, and are
+        // run within the limits of a query like those of the router's routes
+        let storage = Arc::clone(&self.server.graph.storage);
+        let token = self.server.router.cancellation.child(None);
+        let result = tokio::task::spawn_blocking(move || {
+            let guard = QueryGuard::start(&storage.query_limits, token);
+            let result = execute::run(&storage, &query, &params);
+            guard.finish().map(|_| result)
+        })
+        .await
+        .map_err(|e| failure("Neo.DatabaseError.General.UnknownError", &e.to_string()))?
+        .map_err(|e| {
+            let code = match e {
+                GraphError::QueryCancelled(_) => "Neo.TransientError.Transaction.Terminated",
+                _ => "Neo.ClientError.Statement.ExecutionFailed",
+            };
+            failure(code, &e.to_string())
+        })?;
+        let QueryResult { fields, records } = result.map_err(|e| {
+            let code = match e {
+                CypherError::Unsupported(_) => "Neo.ClientError.Statement.NotSupported",
+                _ => "Neo.ClientError.Statement.SyntaxError",
+            };
+            failure(code, &e.to_string())
+        })?;
+        self.result = Some(records.into());
+        Ok(vec![success(HashMap::from([
+            (
+                "fields".to_string(),
+                BoltValue::List(fields.into_iter().map(BoltValue::String).collect()),
+            ),
+            ("t_first".to_string(), BoltValue::Integer(0)),
+        ]))])
+    }
+
+    fn pull(&mut self, fields: &[BoltValue]) -> Result<Vec<BoltValue>, BoltValue> {
+        let Some(records) = self.result.as_mut() else {
+            return Err(failure(
+                "Neo.ClientError.Request.Invalid",
+                "PULL without a query to pull from",
+            ));
+        };
+        // -1 pulls all the records
+        let n = fields
+            .first()
+            .and_then(|extra| extra.get("n"))
+            .and_then(BoltValue::as_int)
+            .filter(|n| *n >= 0)
+            .map_or(records.len(), |n| n as usize);
+        let mut responses: Vec<BoltValue> = records
+            .drain(..n.min(records.len()))
+            .map(|record| BoltValue::Struct(RECORD, vec![BoltValue::List(record)]))
+            .collect();
+        if records.is_empty() {
+            self.result = None;
+            responses.push(success(HashMap::from([
+                ("type".to_string(), BoltValue::String("r".to_string())),
+                ("t_last".to_string(), BoltValue::Integer(0)),
+            ])));
+        } else {
+            responses.push(success(HashMap::from([(
+                "has_more".to_string(),
+                BoltValue::Boolean(true),
+            )])));
+        }
+        Ok(responses)
+    }
+}
+
+/// The minor version of Bolt 4 to use for a proposal of the client, whose bytes are
+/// `[0, range, minor, major]` with the range counting the minor versions below it
+fn negotiate(proposal: [u8; 4]) -> Option<u8> {
+    let [_, range, minor, major] = proposal;
+    if major != 4 || minor.saturating_sub(range) > MINOR {
+        return None;
+    }
+    Some(minor.min(MINOR))
+}
+
+fn success(metadata: HashMap<String, BoltValue>) -> BoltValue {
+    BoltValue::Struct(SUCCESS, vec![BoltValue::Map(metadata)])
+}
+
+fn failure(code: &str, message: &str) -> BoltValue {
+    BoltValue::Struct(
+        FAILURE,
+        vec![BoltValue::Map(HashMap::from([
+            ("code".to_string(), BoltValue::String(code.to_string())),
+            (
+                "message".to_string(),
+                BoltValue::String(message.to_string()),
+            ),
+        ]))],
+    )
+}
+
+/// Reads the chunks of the next message, `None` once the client closed the connection.
+/// The empty messages clients send to keep connections alive are skipped.
+async fn read_message<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Option<Vec<u8>>, GraphError> {
+    let mut message = Vec::new();
+    loop {
+        let mut size = [0u8; 2];
+        match stream.read_exact(&mut size).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && message.is_empty() => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let size = u16::from_be_bytes(size) as usize;
+        if size == 0 {
+            if message.is_empty() {
+                continue;
+            }
+            return Ok(Some(message));
+        }
+        let start = message.len();
+        message.resize(start + size, 0);
+        stream.read_exact(&mut message[start..]).await?;
+    }
+}
+
+async fn write_message<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    message: &BoltValue,
+) -> Result<(), GraphError> {
+    let mut bytes = Vec::new();
+    message.encode(&mut bytes);
+    let mut framed = Vec::with_capacity(bytes.len() + 4);
+    for chunk in bytes.chunks(MAX_CHUNK) {
+        framed.extend((chunk.len() as u16).to_be_bytes());
+        framed.extend(chunk);
+    }
+    framed.extend([0, 0]);
+    stream.write_all(&framed).await?;
+    Ok(())
+}
@@ -0,0 +1,298 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::{
+    helix_engine::graph_core::{
+        config::Config,
+        graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+        ops::{g::G, source::add_n::AddNAdapter},
+        query_limits::CLOCK_INTERVAL,
+    },
+    helix_gateway::{
+        bolt::{bolt::BoltServer, packstream::BoltValue},
+        router::router::HelixRouter,
+    },
+    helix_transport::tokio_transport::TokioTransport,
+    props,
+};
+
+const MAGIC: [u8; 4] = [0x60, 0x60, 0xB0, 0x17];
+/// Bolt 4.4, as `[0, range, minor, major]`
+const BOLT_4_4: [u8; 4] = [0, 0, 4, 4];
+
+const HELLO: u8 = 0x01;
+const RESET: u8 = 0x0F;
+const RUN: u8 = 0x10;
+const PULL: u8 = 0x3F;
+const SUCCESS: u8 = 0x70;
+const RECORD: u8 = 0x71;
+const IGNORED: u8 = 0x7E;
+const FAILURE: u8 = 0x7F;
+
+fn engine(config: Config) -> Arc<HelixGraphEngine> {
+    Arc::new(
+        HelixGraphEngine::new(HelixGraphEngineOpts {
+            config,
+            ..HelixGraphEngineOpts::in_memory()
+        })
+        .unwrap(),
+    )
+}
+
+fn add_people(engine: &HelixGraphEngine, names: &[&str]) {
+    let mut txn = engine.storage.graph_env.write_txn().unwrap();
+    for name in names {
+        G::new_mut(Arc::clone(&engine.storage), &mut txn)
+            .add_n("person", Some(props! { "name" => *name }), None)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+}
+
+/// Starts a Bolt server on a free port
+async fn serve(engine: Arc<HelixGraphEngine>, router: Arc<HelixRouter>) -> SocketAddr {
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    tokio::spawn(BoltServer::new(engine, router).serve(addr, TokioTransport));
+    for _ in 0..100 {
+        if TcpStream::connect(addr).await.is_ok() {
+            return addr;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("Bolt server didn't start");
+}
+
+/// Connects proposing the versions, returning the connection and the agreed version
+async fn connect(addr: SocketAddr, versions: [[u8; 4]; 4]) -> (TcpStream, [u8; 4]) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(&MAGIC).await.unwrap();
+    stream.write_all(&versions.concat()).await.unwrap();
+    let mut version = [0; 4];
+    stream.read_exact(&mut version).await.unwrap();
+    (stream, version)
+}
+
+async fn send(stream: &mut TcpStream, signature: u8, fields: Vec<BoltValue>) {
+    let mut bytes = Vec::new();
+    BoltValue::Struct(signature, fields).encode(&mut bytes);
+    let mut framed = (bytes.len() as u16).to_be_bytes().to_vec();
+    framed.extend(bytes);
+    framed.extend([0, 0]);
+    stream.write_all(&framed).await.unwrap();
+}
+
+/// Reads the next message, returning its signature and fields
+async fn receive(stream: &mut TcpStream) -> (u8, Vec<BoltValue>) {
+    let mut message = Vec::new();
+    loop {
+        let mut size = [0; 2];
+        stream.read_exact(&mut size).await.unwrap();
+        let size = u16::from_be_bytes(size) as usize;
+        if size == 0 {
+            break;
+        }
+        let start = message.len();
+        message.resize(start + size, 0);
+        stream.read_exact(&mut message[start..]).await.unwrap();
+    }
+    match BoltValue::decode(&mut message.as_slice()).unwrap() {
+        BoltValue::Struct(signature, fields) => (signature, fields),
+        value => panic!("not a message: {:?}", value),
+    }
+}
+
+fn map(entries: &[(&str, BoltValue)]) -> BoltValue {
+    BoltValue::Map(
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect(),
+    )
+}
+
+/// Connects and sends a `HELLO` without credentials
+async fn hello(addr: SocketAddr) -> TcpStream {
+    let (mut stream, version) = connect(addr, [BOLT_4_4, [0; 4], [0; 4], [0; 4]]).await;
+    assert_eq!(version, BOLT_4_4);
+    let extra = map(&[
+        ("user_agent", BoltValue::String("test".to_string())),
+        ("scheme", BoltValue::String("none".to_string())),
+    ]);
+    send(&mut stream, HELLO, vec![extra]).await;
+    assert_eq!(receive(&mut stream).await.0, SUCCESS);
+    stream
+}
+
+async fn run(
+    stream: &mut TcpStream,
+    query: &str,
+    params: &[(&str, BoltValue)],
+) -> (u8, Vec<BoltValue>) {
+    let query = BoltValue::String(query.to_string());
+    send(stream, RUN, vec![query, map(params), map(&[])]).await;
+    receive(stream).await
+}
+
+/// The code of a `FAILURE`
+fn code(fields: &[BoltValue]) -> &str {
+    fields[0].get("code").and_then(BoltValue::as_str).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_handshake_negotiates_version() {
+    let addr = serve(
+        engine(Config::default()),
+        Arc::new(HelixRouter::new(None, None)),
+    )
+    .await;
+
+    // 5.0 first, then 4.4 down to 4.2
+    let (_, version) = connect(addr, [[0, 0, 0, 5], [0, 2, 4, 4], [0; 4], [0; 4]]).await;
+    assert_eq!(version, BOLT_4_4);
+
+    // newer minor versions of 4 are served as 4.4
+    let (_, version) = connect(addr, [[0, 2, 6, 4], [0; 4], [0; 4], [0; 4]]).await;
+    assert_eq!(version, BOLT_4_4);
+
+    // no version in common
+    let (_, version) = connect(addr, [[0, 0, 0, 5], [0, 0, 0, 3], [0; 4], [0; 4]]).await;
+    assert_eq!(version, [0; 4]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_run_and_pull_records() {
+    let engine = engine(Config::default());
+    add_people(&engine, &["carol", "alice", "bob"]);
+    let addr = serve(engine, Arc::new(HelixRouter::new(None, None))).await;
+    let mut stream = hello(addr).await;
+
+    let (signature, fields) = run(
+        &mut stream,
+        "MATCH (p:person) WHERE p.name <> $skip RETURN p.name AS name ORDER BY p.name",
+        &[("skip", BoltValue::String("bob".to_string()))],
+    )
+    .await;
+    assert_eq!(signature, SUCCESS);
+    assert_eq!(
+        fields[0].get("fields"),
+        Some(&BoltValue::List(vec![BoltValue::String(
+            "name".to_string()
+        )]))
+    );
+
+    // records are pulled in batches
+    send(
+        &mut stream,
+        PULL,
+        vec![map(&[("n", BoltValue::Integer(1))])],
+    )
+    .await;
+    let record = receive(&mut stream).await;
+    assert_eq!(
+        record,
+        (
+            RECORD,
+            vec![BoltValue::List(vec![BoltValue::String(
+                "alice".to_string()
+            )])]
+        )
+    );
+    let (signature, fields) = receive(&mut stream).await;
+    assert_eq!(signature, SUCCESS);
+    assert_eq!(fields[0].get("has_more"), Some(&BoltValue::Boolean(true)));
+
+    send(
+        &mut stream,
+        PULL,
+        vec![map(&[("n", BoltValue::Integer(-1))])],
+    )
+    .await;
+    let record = receive(&mut stream).await;
+    assert_eq!(
+        record,
+        (
+            RECORD,
+            vec![BoltValue::List(vec![BoltValue::String(
+                "carol".to_string()
+            )])]
+        )
+    );
+    let (signature, fields) = receive(&mut stream).await;
+    assert_eq!(signature, SUCCESS);
+    assert_eq!(fields[0].get("has_more"), None);
+
+    // nothing is left to pull
+    send(
+        &mut stream,
+        PULL,
+        vec![map(&[("n", BoltValue::Integer(-1))])],
+    )
+    .await;
+    assert_eq!(receive(&mut stream).await.0, FAILURE);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_failures_ignore_requests_until_reset() {
+    let addr = serve(
+        engine(Config::default()),
+        Arc::new(HelixRouter::new(None, None)),
+    )
+    .await;
+    let (mut stream, _) = connect(addr, [BOLT_4_4, [0; 4], [0; 4], [0; 4]]).await;
+
+    // queries can't be run before HELLO
+    let (signature, fields) = run(&mut stream, "MATCH (p:person) RETURN p", &[]).await;
+    assert_eq!(signature, FAILURE);
+    assert_eq!(code(&fields), "Neo.ClientError.Security.Unauthorized");
+    assert_eq!(
+        run(&mut stream, "MATCH (p:person) RETURN p", &[]).await.0,
+        IGNORED
+    );
+
+    send(&mut stream, RESET, Vec::new()).await;
+    assert_eq!(receive(&mut stream).await.0, SUCCESS);
+    send(&mut stream, HELLO, vec![map(&[])]).await;
+    assert_eq!(receive(&mut stream).await.0, SUCCESS);
+
+    // writes only run as deployed queries
+    let (signature, fields) = run(&mut stream, "CREATE (p:person {name: 'dave'})", &[]).await;
+    assert_eq!(signature, FAILURE);
+    assert_eq!(code(&fields), "Neo.ClientError.Statement.NotSupported");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_queries_run_within_query_limits() {
+    let mut config = Config::default();
+    config.query_limits.max_visited = Some(2);
+    let engine = engine(config);
+    add_people(&engine, &["alice", "bob", "carol", "dave"]);
+    let addr = serve(engine, Arc::new(HelixRouter::new(None, None))).await;
+    let mut stream = hello(addr).await;
+
+    let (signature, fields) = run(&mut stream, "MATCH (p:person) RETURN p.name", &[]).await;
+    assert_eq!(signature, FAILURE);
+    assert_eq!(code(&fields), "Neo.ClientError.Statement.ExecutionFailed");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_queries_cancelled_with_the_router() {
+    let engine = engine(Config::default());
+    // cancellation is checked every `CLOCK_INTERVAL` visited items
+    let names = vec!["alice"; CLOCK_INTERVAL as usize + 1];
+    add_people(&engine, &names);
+    let router = Arc::new(HelixRouter::new(None, None));
+    let addr = serve(engine, Arc::clone(&router)).await;
+    let mut stream = hello(addr).await;
+
+    router.cancel_queries();
+    let (signature, fields) = run(&mut stream, "MATCH (p:person) RETURN p.name", &[]).await;
+    assert_eq!(signature, FAILURE);
+    assert_eq!(code(&fields), "Neo.TransientError.Transaction.Terminated");
+}
@@ -0,0 +1,549 @@
+use super::packstream::BoltValue;
+use crate::helix_engine::{
+    graph_core::ops::{
+        g::G,
+        in_::in_e::InEdgesAdapter,
+        out::out_e::OutEdgesAdapter,
+        source::n_from_type::NFromTypeAdapter,
+        tr_val::{Traversable, TraversalVal},
+    },
+    storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    types::GraphError,
+};
+use crate::helix_storage::heed3::RoTxn;
+use crate::helixc::cypher::cypher::{
+    self, Clause, Condition, CypherError, NodePattern, Op, Operand, Pattern, RelPattern, Return,
+    ReturnExpr,
+};
+use crate::protocol::value::Value;
+use std::{cmp::Ordering, collections::HashMap, sync::Arc};
+
+/// Struct signatures of the graph values of PackStream
+const NODE: u8 = 0x4E;
+const RELATIONSHIP: u8 = 0x52;
+
+/// Variables bound by the patterns matched so far
+type Row = HashMap<String, TraversalVal>;
+
+/// Result of a query, the names of its columns and its records
+pub struct QueryResult {
+    pub fields: Vec<String>,
+    pub records: Vec<Vec<BoltValue>>,
+}
+
+/// Runs a read query of the Cypher subset of [`cypher`] against the graph.
+///
+/// Unlike the queries translated to HelixQL, patterns are matched at runtime, so
+/// conditions of a `WHERE` can combine any of the matched variables. Writes are rejected,
+/// they only run as deployed queries.
+pub fn run(
+    storage: &Arc<HelixGraphStorage>,
+    query: &str,
+    params: &HashMap<String, Value>,
+) -> Result<QueryResult, CypherError> {
+    let clauses = cypher::parse(query)?;
+    // the map can't be resized while the read transaction is open
+    let _hold = storage.map_size.hold();
+    let txn = storage.graph_env.read_txn().map_err(runtime)?;
+    let mut run = Run {
+        storage,
+        txn: &txn,
+        params,
+        anonymous: 0,
+    };
+    let mut rows = vec![Row::new()];
+    for clause in clauses.iter() {
+        match clause {
+            Clause::Match(pattern, condition) => {
+                rows = run.match_pattern(rows, pattern)?;
+                if let Some(condition) = condition {
+                    let mut matching = Vec::with_capacity(rows.len());
+                    for row in rows {
+                        if run.test(&row, condition)? {
+                            matching.push(row);
+                        }
+                    }
+                    rows = matching;
+                }
+            }
+            Clause::Create(_) | Clause::Delete(_) => {
+                return Err(CypherError::Unsupported(
+                    "writes over Bolt, translate the query to HelixQL and deploy it instead"
+                        .to_string(),
+                ))
+            }
+            Clause::Return(ret) => return run.project(rows, ret),
+        }
+    }
+    Ok(QueryResult {
+        fields: Vec::new(),
+        records: Vec::new(),
+    })
+}
+
+fn runtime(e: impl std::fmt::Display) -> CypherError {
+    CypherError::Unsupported(format!("query failed: {}", e))
+}
+
+struct Run<'a> {
+    storage: &'a Arc<HelixGraphStorage>,
+    txn: &'a RoTxn<'a>,
+    params: &'a HashMap<String, Value>,
+    /// Count of the unnamed elements of patterns, which are bound under generated names
+    anonymous: usize,
+}
+
+impl<'a> Run<'a> {
+    fn name(&mut self, variable: &Option<String>) -> String {
+        match variable {
+            Some(variable) => variable.clone(),
+            None => {
+                self.anonymous += 1;
+                // can't clash with a variable, which starts with a letter
+                format!(" {}", self.anonymous)
+            }
+        }
+    }
+
+    /// Extends each row with the bindings of every match of the pattern
+    fn match_pattern(
+        &mut self,
+        rows: Vec<Row>,
+        pattern: &Pattern,
+    ) -> Result<Vec<Row>, CypherError> {
+        let start = self.name(&pattern.start.variable);
+        let mut matched = Vec::new();
+        for row in rows {
+            let candidates = match row.get(&start) {
+                Some(bound) => vec![bound.clone()],
+                None => self.scan(&pattern.start)?,
+            };
+            for node in candidates {
+                if self.node_matches(&row, &node, &pattern.start)? {
+                    let mut row = row.clone();
+                    row.insert(start.clone(), node);
+                    matched.push(row);
+                }
+            }
+        }
+        let mut previous = start;
+        for (rel, node) in pattern.hops.iter() {
+            let edge_name = self.name(&rel.variable);
+            let node_name = self.name(&node.variable);
+            let mut extended = Vec::new();
+            for row in matched {
+                for (edge, other) in self.expand(&row[&previous], rel)? {
+                    if !self.node_matches(&row, &other, node)? {
+                        continue;
+                    }
+                    let same = |name: &String, value: &TraversalVal| {
+                        row.get(name).is_none_or(|bound| bound.id() == value.id())
+                    };
+                    if !same(&edge_name, &edge) || !same(&node_name, &other) {
+                        continue;
+                    }
+                    let mut row = row.clone();
+                    row.insert(edge_name.clone(), edge);
+                    row.insert(node_name.clone(), other);
+                    extended.push(row);
+                }
+            }
+            matched = extended;
+            previous = node_name;
+        }
+        Ok(matched)
+    }
+
+    /// The nodes of the label, or of the whole graph for a node pattern without one
+    fn scan(&self, node: &NodePattern) -> Result<Vec<TraversalVal>, CypherError> {
+        match &node.label {
+            Some(label) => G::new(Arc::clone(self.storage), self.txn)
+                .n_from_type(label)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(runtime),
+            None => self
+                .storage
+                .nodes_db
+                .iter(self.txn)
+                .map_err(runtime)?
+                .map(|entry| {
+                    let (id, bytes) = entry?;
+                    Ok(TraversalVal::Node(self.storage.decode_node(bytes, id)?))
+                })
+                .collect::<Result<Vec<_>, GraphError>>()
+                .map_err(runtime),
+        }
+    }
+
+    /// The edges of the relationship pattern from the node and the nodes at their other end
+    fn expand(
+        &self,
+        node: &TraversalVal,
+        rel: &RelPattern,
+    ) -> Result<Vec<(TraversalVal, TraversalVal)>, CypherError> {
+        let Some(label) = &rel.label else {
+            return Err(CypherError::Unsupported(
+                "relationships without a type, edges are only followed by label".to_string(),
+            ));
+        };
+        let from = G::new_from(Arc::clone(self.storage), self.txn, vec![node.clone()]);
+        let edges = match rel.outgoing {
+            true => from.out_e(label).collect::<Result<Vec<_>, _>>(),
+            false => from.in_e(label).collect::<Result<Vec<_>, _>>(),
+        }
+        .map_err(runtime)?;
+        let mut expanded = Vec::with_capacity(edges.len());
+        for edge in edges {
+            let TraversalVal::Edge(e) = &edge else {
+                continue;
+            };
+            let other = match rel.outgoing {
+                true => e.to_node,
+                false => e.from_node,
+            };
+            let other = self.storage.get_node(self.txn, &other).map_err(runtime)?;
+            let properties = rel
+                .properties
+                .iter()
+                .map(|(key, value)| {
+                    Ok((
+                        property(&edge, key),
+                        self.operand_value(&Row::new(), value)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, CypherError>>()?;
+            if properties
+                .iter()
+                .all(|(actual, expected)| equal(actual, expected))
+            {
+                expanded.push((edge, TraversalVal::Node(other)));
+            }
+        }
+        Ok(expanded)
+    }
+
+    fn node_matches(
+        &self,
+        row: &Row,
+        node: &TraversalVal,
+        pattern: &NodePattern,
+    ) -> Result<bool, CypherError> {
+        if let (Some(label), TraversalVal::Node(n)) = (&pattern.label, node) {
+            if n.label != *label {
+                return Ok(false);
+            }
+        }
+        for (key, value) in pattern.properties.iter() {
+            if !equal(&property(node, key), &self.operand_value(row, value)?) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn test(&self, row: &Row, condition: &Condition) -> Result<bool, CypherError> {
+        match condition {
+            Condition::And(conditions) => {
+                for condition in conditions {
+                    if !self.test(row, condition)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Condition::Or(conditions) => {
+                for condition in conditions {
+                    if self.test(row, condition)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Condition::Compare(left, op, right) => {
+                let (mut l, mut r) = (
+                    self.operand_value(row, left)?,
+                    self.operand_value(row, right)?,
+                );
+                // ids are integers to the drivers, and UUIDs to Helix
+                if let (Operand::Id(v), Some(Value::I64(_))) = (left, &r) {
+                    l = row.get(v).map(|item| Value::I64(bolt_id(item.id())));
+                }
+                if let (Some(Value::I64(_)), Operand::Id(v)) = (&l, right) {
+                    r = row.get(v).map(|item| Value::I64(bolt_id(item.id())));
+                }
+                let ordering = match (l, r) {
+                    (Some(l), Some(r)) => compare(&l, &r),
+                    _ => None,
+                };
+                Ok(match (op, ordering) {
+                    (_, None) => false,
+                    (Op::Eq, Some(o)) => o == Ordering::Equal,
+                    (Op::Neq, Some(o)) => o != Ordering::Equal,
+                    (Op::Lt, Some(o)) => o == Ordering::Less,
+                    (Op::Lte, Some(o)) => o != Ordering::Greater,
+                    (Op::Gt, Some(o)) => o == Ordering::Greater,
+                    (Op::Gte, Some(o)) => o != Ordering::Less,
+                })
+            }
+        }
+    }
+
+    /// The value of an operand in the row, `None` for a missing property, Cypher's null
+    fn operand_value(&self, row: &Row, operand: &Operand) -> Result<Option<Value>, CypherError> {
+        Ok(match operand {
+            Operand::Id(v) => Some(Value::String(self.bound(row, v)?.uuid())),
+            Operand::Property(v, key) => property(self.bound(row, v)?, key),
+            Operand::Parameter(name) => match self.params.get(name) {
+                Some(value) => Some(value.clone()),
+                None => return Err(CypherError::Syntax(format!("missing parameter ${}", name))),
+            },
+            Operand::String(s) => Some(Value::String(s.clone())),
+            Operand::Number(n) => match (n.parse::<i64>(), n.parse::<f64>()) {
+                (Ok(i), _) => Some(Value::I64(i)),
+                (_, Ok(f)) => Some(Value::F64(f)),
+                _ => return Err(CypherError::Syntax(format!("invalid number {}", n))),
+            },
+            Operand::Boolean(b) => Some(Value::Boolean(*b)),
+        })
+    }
+
+    fn bound<'r>(&self, row: &'r Row, variable: &str) -> Result<&'r TraversalVal, CypherError> {
+        row.get(variable)
+            .ok_or_else(|| CypherError::Syntax(format!("`{}` isn't bound", variable)))
+    }
+
+    /// The records of the `RETURN` clause, counting the rows of each group of the other
+    /// returned values if any of them is a `count()`
+    fn project(&self, mut rows: Vec<Row>, ret: &Return) -> Result<QueryResult, CypherError> {
+        if let Some((variable, key, descending)) = &ret.order_by {
+            let mut keyed = Vec::with_capacity(rows.len());
+            for row in rows {
+                let value = property(self.bound(&row, variable)?, key);
+                keyed.push((value, row));
+            }
+            keyed.sort_by(|(a, _), (b, _)| {
+                // nulls come last in ascending order, like in Cypher
+                let ordering = match (a, b) {
+                    (Some(a), Some(b)) => a.total_cmp(b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                };
+                if *descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+            rows = keyed.into_iter().map(|(_, row)| row).collect();
+        }
+
+        let fields = ret
+            .items
+            .iter()
+            .map(|(expr, alias)| match (expr, alias) {
+                (_, Some(alias)) => alias.clone(),
+                (ReturnExpr::Variable(v), None) => v.clone(),
+                (ReturnExpr::Property(v, key), None) => format!("{}.{}", v, key),
+                (ReturnExpr::Count(v), None) => format!("count({})", v),
+            })
+            .collect();
+        let aggregated = ret
+            .items
+            .iter()
+            .any(|(expr, _)| matches!(expr, ReturnExpr::Count(_)));
+
+        let mut records: Vec<Vec<BoltValue>> = Vec::new();
+        // index of the record of each group, by the debug form of its values
+        let mut groups: HashMap<String, usize> = HashMap::new();
+        if aggregated
+            && rows.is_empty()
+            && ret
+                .items
+                .iter()
+                .all(|(e, _)| matches!(e, ReturnExpr::Count(_)))
+        {
+            records.push(vec![BoltValue::Integer(0); ret.items.len()]);
+        }
+        for row in rows.iter() {
+            let mut record = Vec::with_capacity(ret.items.len());
+            for (expr, _) in ret.items.iter() {
+                record.push(match expr {
+                    ReturnExpr::Variable(v) => graph_value(self.bound(row, v)?),
+                    ReturnExpr::Property(v, key) => property(self.bound(row, v)?, key)
+                        .map_or(BoltValue::Null, |value| bolt_value(&value)),
+                    ReturnExpr::Count(v) => {
+                        self.bound(row, v)?;
+                        BoltValue::Integer(1)
+                    }
+                });
+            }
+            if !aggregated {
+                records.push(record);
+                continue;
+            }
+            let key = format!(
+                "{:?}",
+                record
+                    .iter()
+                    .zip(ret.items.iter())
+                    .filter(|(_, (expr, _))| !matches!(expr, ReturnExpr::Count(_)))
+                    .map(|(value, _)| value)
+                    .collect::<Vec<_>>()
+            );
+            match groups.get(&key) {
+                Some(index) => {
+                    for (value, (expr, _)) in records[*index].iter_mut().zip(ret.items.iter()) {
+                        if let (ReturnExpr::Count(_), BoltValue::Integer(count)) = (expr, value) {
+                            *count += 1;
+                        }
+                    }
+                }
+                None => {
+                    groups.insert(key, records.len());
+                    records.push(record);
+                }
+            }
+        }
+
+        let skip = match &ret.skip {
+            Some(skip) => self.count(skip)?,
+            None => 0,
+        };
+        let limit = match &ret.limit {
+            Some(limit) => self.count(limit)?,
+            None => usize::MAX,
+        };
+        Ok(QueryResult {
+            fields,
+            records: records.into_iter().skip(skip).take(limit).collect(),
+        })
+    }
+
+    fn count(&self, operand: &Operand) -> Result<usize, CypherError> {
+        match self.operand_value(&Row::new(), operand)? {
+            Some(value) => match value.as_f64() {
+                Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+                _ => Err(CypherError::Syntax(format!(
+                    "SKIP and LIMIT take positive integers, not {}",
+                    value
+                ))),
+            },
+            None => Err(CypherError::Syntax(
+                "SKIP and LIMIT take integers".to_string(),
+            )),
+        }
+    }
+}
+
+fn property(item: &TraversalVal, key: &str) -> Option<Value> {
+    let properties = match item {
+        TraversalVal::Node(node) => node.properties.as_ref(),
+        TraversalVal::Edge(edge) => edge.properties.as_ref(),
+        _ => None,
+    };
+    properties?.get(key).cloned()
+}
+
+fn equal(actual: &Option<Value>, expected: &Option<Value>) -> bool {
+    match (actual, expected) {
+        (Some(actual), Some(expected)) => compare(actual, expected) == Some(Ordering::Equal),
+        _ => false,
+    }
+}
+
+/// Orders values of the same type, numbers of any type by their value
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    let comparable = std::mem::discriminant(a) == std::mem::discriminant(b)
+        || (a.as_f64().is_some() && b.as_f64().is_some());
+    comparable.then(|| a.total_cmp(b))
+}
+
+/// The integer id drivers see for a Helix id, Bolt 4 ids being 64 bit
+pub fn bolt_id(id: u128) -> i64 {
+    (((id >> 64) as u64 ^ id as u64) & i64::MAX as u64) as i64
+}
+
+fn graph_value(item: &TraversalVal) -> BoltValue {
+    let properties = |properties: &Option<HashMap<String, Value>>| {
+        BoltValue::Map(
+            properties
+                .iter()
+                .flatten()
+                .map(|(key, value)| (key.clone(), bolt_value(value)))
+                .collect(),
+        )
+    };
+    match item {
+        TraversalVal::Node(node) => BoltValue::Struct(
+            NODE,
+            vec![
+                BoltValue::Integer(bolt_id(node.id)),
+                BoltValue::List(vec![BoltValue::String(node.label.clone())]),
+                properties(&node.properties),
+            ],
+        ),
+        TraversalVal::Edge(edge) => BoltValue::Struct(
+            RELATIONSHIP,
+            vec![
+                BoltValue::Integer(bolt_id(edge.id)),
+                BoltValue::Integer(bolt_id(edge.from_node)),
+                BoltValue::Integer(bolt_id(edge.to_node)),
+                BoltValue::String(edge.label.clone()),
+                properties(&edge.properties),
+            ],
+        ),
+        _ => BoltValue::Null,
+    }
+}
+
+pub fn bolt_value(value: &Value) -> BoltValue {
+    match value {
+        Value::String(s) => BoltValue::String(s.clone()),
+        Value::Boolean(b) => BoltValue::Boolean(*b),
+        Value::I8(i) => BoltValue::Integer(*i as i64),
+        Value::I16(i) => BoltValue::Integer(*i as i64),
+        Value::I32(i) => BoltValue::Integer(*i as i64),
+        Value::I64(i) => BoltValue::Integer(*i),
+        Value::U8(u) => BoltValue::Integer(*u as i64),
+        Value::U16(u) => BoltValue::Integer(*u as i64),
+        Value::U32(u) => BoltValue::Integer(*u as i64),
+        // integers beyond the range of Bolt's are sent as strings
+        Value::U64(u) => {
+            i64::try_from(*u).map_or_else(|_| BoltValue::String(u.to_string()), BoltValue::Integer)
+        }
+        Value::U128(u) => {
+            i64::try_from(*u).map_or_else(|_| BoltValue::String(u.to_string()), BoltValue::Integer)
+        }
+        Value::F32(f) => BoltValue::Float(*f as f64),
+        Value::F64(f) => BoltValue::Float(*f),
+        Value::DateTime(d) => BoltValue::String(d.to_rfc3339()),
+        Value::Array(values) => BoltValue::List(values.iter().map(bolt_value).collect()),
+        Value::Object(fields) => BoltValue::Map(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), bolt_value(value)))
+                .collect(),
+        ),
+        Value::Empty => BoltValue::Null,
+    }
+}
+
+/// The value of a query parameter sent by a driver
+pub fn param_value(value: &BoltValue) -> Value {
+    match value {
+        BoltValue::Null | BoltValue::Struct(..) => Value::Empty,
+        BoltValue::Boolean(b) => Value::Boolean(*b),
+        BoltValue::Integer(i) => Value::I64(*i),
+        BoltValue::Float(f) => Value::F64(*f),
+        BoltValue::Bytes(bytes) => Value::Array(bytes.iter().map(|b| Value::U8(*b)).collect()),
+        BoltValue::String(s) => Value::String(s.clone()),
+        BoltValue::List(values) => Value::Array(values.iter().map(param_value).collect()),
+        BoltValue::Map(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), param_value(value)))
+                .collect(),
+        ),
+    }
+}
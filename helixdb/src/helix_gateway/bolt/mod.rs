@@ -0,0 +1,8 @@
+pub mod bolt;
+pub mod execute;
+pub mod packstream;
+
+#[cfg(test)]
+mod bolt_tests;
+#[cfg(test)]
+mod packstream_tests;
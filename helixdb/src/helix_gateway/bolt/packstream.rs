@@ -0,0 +1,207 @@
+use crate::helix_engine::types::GraphError;
+use std::collections::HashMap;
+
+/// A value of PackStream, the binary format of Bolt messages
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoltValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    Bytes(Vec<u8>),
+    String(String),
+    List(Vec<BoltValue>),
+    Map(HashMap<String, BoltValue>),
+    /// A structure, such as a message or a node, by its signature
+    Struct(u8, Vec<BoltValue>),
+}
+
+impl BoltValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BoltValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            BoltValue::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The entry of a map, `None` for other values
+    pub fn get(&self, key: &str) -> Option<&BoltValue> {
+        match self {
+            BoltValue::Map(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            BoltValue::Null => buf.push(0xC0),
+            BoltValue::Boolean(false) => buf.push(0xC2),
+            BoltValue::Boolean(true) => buf.push(0xC3),
+            BoltValue::Integer(i) => match *i {
+                -16..=127 => buf.push(*i as u8),
+                -128..=-17 => buf.extend([0xC8, *i as u8]),
+                -32_768..=32_767 => {
+                    buf.push(0xC9);
+                    buf.extend((*i as i16).to_be_bytes());
+                }
+                -2_147_483_648..=2_147_483_647 => {
+                    buf.push(0xCA);
+                    buf.extend((*i as i32).to_be_bytes());
+                }
+                _ => {
+                    buf.push(0xCB);
+                    buf.extend(i.to_be_bytes());
+                }
+            },
+            BoltValue::Float(f) => {
+                buf.push(0xC1);
+                buf.extend(f.to_be_bytes());
+            }
+            BoltValue::Bytes(bytes) => {
+                encode_size(buf, bytes.len(), None, [0xCC, 0xCD, 0xCE]);
+                buf.extend(bytes);
+            }
+            BoltValue::String(s) => {
+                encode_size(buf, s.len(), Some(0x80), [0xD0, 0xD1, 0xD2]);
+                buf.extend(s.as_bytes());
+            }
+            BoltValue::List(values) => {
+                encode_size(buf, values.len(), Some(0x90), [0xD4, 0xD5, 0xD6]);
+                for value in values {
+                    value.encode(buf);
+                }
+            }
+            BoltValue::Map(map) => {
+                encode_size(buf, map.len(), Some(0xA0), [0xD8, 0xD9, 0xDA]);
+                for (key, value) in map {
+                    BoltValue::String(key.clone()).encode(buf);
+                    value.encode(buf);
+                }
+            }
+            BoltValue::Struct(signature, fields) => {
+                buf.extend([0xB0 | fields.len() as u8, *signature]);
+                for field in fields {
+                    field.encode(buf);
+                }
+            }
+        }
+    }
+
+    /// Decodes the value at the start of `bytes`, advancing it past the value
+    pub fn decode(bytes: &mut &[u8]) -> Result<BoltValue, GraphError> {
+        let marker = take(bytes, 1)?[0];
+        let value = match marker {
+            0xC0 => BoltValue::Null,
+            0xC2 => BoltValue::Boolean(false),
+            0xC3 => BoltValue::Boolean(true),
+            0x00..=0x7F | 0xF0..=0xFF => BoltValue::Integer(marker as i8 as i64),
+            0xC8 => BoltValue::Integer(take(bytes, 1)?[0] as i8 as i64),
+            0xC9 => BoltValue::Integer(i16::from_be_bytes(take_array(bytes)?) as i64),
+            0xCA => BoltValue::Integer(i32::from_be_bytes(take_array(bytes)?) as i64),
+            0xCB => BoltValue::Integer(i64::from_be_bytes(take_array(bytes)?)),
+            0xC1 => BoltValue::Float(f64::from_be_bytes(take_array(bytes)?)),
+            0xCC..=0xCE => {
+                let len = decode_size(bytes, marker - 0xCC)?;
+                BoltValue::Bytes(take(bytes, len)?.to_vec())
+            }
+            0x80..=0x8F | 0xD0..=0xD2 => {
+                let len = match marker {
+                    0x80..=0x8F => (marker & 0x0F) as usize,
+                    _ => decode_size(bytes, marker - 0xD0)?,
+                };
+                BoltValue::String(String::from_utf8(take(bytes, len)?.to_vec())?)
+            }
+            0x90..=0x9F | 0xD4..=0xD6 => {
+                let len = match marker {
+                    0x90..=0x9F => (marker & 0x0F) as usize,
+                    _ => decode_size(bytes, marker - 0xD4)?,
+                };
+                BoltValue::List(
+                    (0..len)
+                        .map(|_| BoltValue::decode(bytes))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            0xA0..=0xAF | 0xD8..=0xDA => {
+                let len = match marker {
+                    0xA0..=0xAF => (marker & 0x0F) as usize,
+                    _ => decode_size(bytes, marker - 0xD8)?,
+                };
+                let mut map = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    let BoltValue::String(key) = BoltValue::decode(bytes)? else {
+                        return Err(GraphError::DecodeError(
+                            "PackStream map with a key that isn't a string".to_string(),
+                        ));
+                    };
+                    map.insert(key, BoltValue::decode(bytes)?);
+                }
+                BoltValue::Map(map)
+            }
+            0xB0..=0xBF => {
+                let signature = take(bytes, 1)?[0];
+                BoltValue::Struct(
+                    signature,
+                    (0..marker & 0x0F)
+                        .map(|_| BoltValue::decode(bytes))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            marker => {
+                return Err(GraphError::DecodeError(format!(
+                    "Unknown PackStream marker {:#04x}",
+                    marker
+                )))
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// Writes the marker of a sized value, the tiny marker if there is one and the size
+/// fits in it
+fn encode_size(buf: &mut Vec<u8>, len: usize, tiny: Option<u8>, markers: [u8; 3]) {
+    match (tiny, len) {
+        (Some(tiny), 0..=15) => buf.push(tiny | len as u8),
+        (_, 0..=0xFF) => buf.extend([markers[0], len as u8]),
+        (_, 0x100..=0xFFFF) => {
+            buf.push(markers[1]);
+            buf.extend((len as u16).to_be_bytes());
+        }
+        _ => {
+            buf.push(markers[2]);
+            buf.extend((len as u32).to_be_bytes());
+        }
+    }
+}
+
+/// Reads a size of 1, 2 or 4 bytes, for the first, second or third marker of a type
+fn decode_size(bytes: &mut &[u8], marker_index: u8) -> Result<usize, GraphError> {
+    Ok(match marker_index {
+        0 => take(bytes, 1)?[0] as usize,
+        1 => u16::from_be_bytes(take_array(bytes)?) as usize,
+        _ => u32::from_be_bytes(take_array(bytes)?) as usize,
+    })
+}
+
+fn take<'b>(bytes: &mut &'b [u8], len: usize) -> Result<&'b [u8], GraphError> {
+    if bytes.len() < len {
+        return Err(GraphError::DecodeError(
+            "Truncated PackStream value".to_string(),
+        ));
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn take_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], GraphError> {
+    Ok(take(bytes, N)?.try_into().unwrap())
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use crate::{helix_engine::types::GraphError, helix_gateway::bolt::packstream::BoltValue};
+
+fn encode(value: &BoltValue) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.encode(&mut buf);
+    buf
+}
+
+/// Decodes the bytes, checking that the whole input is read
+fn decode(bytes: &[u8]) -> Result<BoltValue, GraphError> {
+    let mut rest = bytes;
+    let value = BoltValue::decode(&mut rest)?;
+    assert!(rest.is_empty(), "{} bytes left over", rest.len());
+    Ok(value)
+}
+
+fn string(len: usize) -> BoltValue {
+    BoltValue::String("a".repeat(len))
+}
+
+#[test]
+fn test_values_round_trip() {
+    let map = HashMap::from([
+        ("name".to_string(), BoltValue::String("alice".to_string())),
+        ("age".to_string(), BoltValue::Integer(30)),
+        ("tags".to_string(), BoltValue::List(vec![BoltValue::Null])),
+    ]);
+    let values = [
+        BoltValue::Null,
+        BoltValue::Boolean(true),
+        BoltValue::Boolean(false),
+        BoltValue::Float(-1.5),
+        BoltValue::Bytes(vec![0, 1, 255]),
+        BoltValue::Bytes(vec![7; 300]),
+        string(0),
+        string(15),
+        string(16),
+        string(256),
+        string(70_000),
+        BoltValue::String("héllo".to_string()),
+        BoltValue::List((0..20).map(BoltValue::Integer).collect()),
+        BoltValue::Map(map.clone()),
+        BoltValue::Struct(0x4E, vec![BoltValue::Integer(1), BoltValue::Map(map)]),
+    ];
+    for value in values {
+        assert_eq!(decode(&encode(&value)).unwrap(), value);
+    }
+}
+
+#[test]
+fn test_integers_use_smallest_encoding() {
+    for (value, len) in [
+        (0, 1),
+        (127, 1),
+        (-16, 1),
+        (-17, 2),
+        (-128, 2),
+        (128, 3),
+        (-129, 3),
+        (32_767, 3),
+        (32_768, 5),
+        (-2_147_483_648, 5),
+        (2_147_483_648, 9),
+        (i64::MIN, 9),
+        (i64::MAX, 9),
+    ] {
+        let bytes = encode(&BoltValue::Integer(value));
+        assert_eq!(bytes.len(), len, "{}", value);
+        assert_eq!(decode(&bytes).unwrap(), BoltValue::Integer(value));
+    }
+}
+
+#[test]
+fn test_sizes_use_tiny_markers() {
+    assert_eq!(encode(&string(3))[0], 0x83);
+    assert_eq!(encode(&string(16))[..2], [0xD0, 16]);
+    assert_eq!(encode(&string(256))[..3], [0xD1, 1, 0]);
+    assert_eq!(encode(&BoltValue::List(Vec::new())), [0x90]);
+    assert_eq!(encode(&BoltValue::Map(HashMap::new())), [0xA0]);
+    assert_eq!(
+        encode(&BoltValue::Struct(0x70, vec![BoltValue::Null])),
+        [0xB1, 0x70, 0xC0]
+    );
+}
+
+#[test]
+fn test_decodes_one_value_at_a_time() {
+    let mut bytes = encode(&BoltValue::Integer(1));
+    bytes.extend(encode(&BoltValue::String("next".to_string())));
+    let mut rest = bytes.as_slice();
+    assert_eq!(BoltValue::decode(&mut rest).unwrap(), BoltValue::Integer(1));
+    assert_eq!(
+        BoltValue::decode(&mut rest).unwrap(),
+        BoltValue::String("next".to_string())
+    );
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn test_rejects_malformed_values() {
+    // truncated string, list and integer
+    assert!(decode(&[0x85, b'a', b'b']).is_err());
+    assert!(decode(&[0x92, 0x01]).is_err());
+    assert!(decode(&[0xCA, 0x00, 0x01]).is_err());
+    // a map key that isn't a string
+    assert!(decode(&[0xA1, 0x01, 0x01]).is_err());
+    // not a marker of PackStream
+    assert!(decode(&[0xE0]).is_err());
+    assert!(decode(&[]).is_err());
+}
@@ -1,6 +1,9 @@
 use crate::helix_engine::graph_core::graph_core::HelixGraphEngine;
 use crate::helix_engine::types::GraphError;
-use crate::helix_gateway::{router::router::HelixRouter, thread_pool::thread_pool::ThreadPool};
+use crate::helix_gateway::{
+    connection::limits::ConnectionLimits, router::router::HelixRouter,
+    thread_pool::thread_pool::ThreadPool,
+};
 use crate::helix_runtime::AsyncRuntime;
 use crate::helix_transport::{Listener, Transport};
 use chrono::{DateTime, Utc};
@@ -10,6 +13,7 @@ use std::{
     net::SocketAddr,
     sync::{Arc, Mutex},
 };
+use tokio::{io::BufReader, sync::watch};
 use uuid::Uuid;
 
 pub struct ConnectionHandler<R, T>
@@ -20,8 +24,12 @@ where
     pub address: String,
     pub active_connections: Arc<Mutex<HashMap<String, ClientConnection>>>,
     pub thread_pool: ThreadPool<R, T::Stream>,
+    /// Router the workers handle requests with, shared with servers of other protocols
+    pub router: Arc<HelixRouter>,
     pub runtime: R,
     transport: T,
+    /// Set once the gateway shuts down, which stops accepting connections and the workers
+    shutdown: watch::Sender<bool>,
 }
 
 pub struct ClientConnection {
@@ -41,18 +49,36 @@ where
         graph: Arc<HelixGraphEngine>,
         size: usize,
         router: HelixRouter,
+        limits: ConnectionLimits,
         runtime: R,
         transport: T,
     ) -> Result<Self, GraphError> {
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let router = Arc::new(router);
         Ok(Self {
             address: address.to_string(),
             active_connections: Arc::new(Mutex::new(HashMap::new())),
-            thread_pool: ThreadPool::new(size, graph, Arc::new(router), runtime.clone())?,
+            thread_pool: ThreadPool::new(
+                size,
+                graph,
+                Arc::clone(&router),
+                Arc::new(limits),
+                shutdown_rx,
+                runtime.clone(),
+            )?,
+            router,
             runtime,
             transport,
+            shutdown,
         })
     }
 
+    /// Stops accepting connections and tells the workers to close their connections
+    /// once the requests they are handling have been answered
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
     pub async fn accept_conns(&self) -> Result<<R as AsyncRuntime>::JoinHandle<()>, GraphError> {
         let addr: SocketAddr = self.address.parse().map_err(|e| {
             GraphError::GraphConnectionError(
@@ -66,12 +92,20 @@ where
         })?;
 
         let active_connections = Arc::clone(&self.active_connections);
-        let thread_pool_sender = self.thread_pool.sender.clone();
+        let queue = self.thread_pool.queue.clone();
+        let mut shutdown = self.shutdown.subscribe();
 
         let runtime = self.runtime.clone();
+        let accept_runtime = runtime.clone();
         let handle = runtime.spawn(async move {
+            let runtime = accept_runtime;
             loop {
-                match listener.accept().await {
+                // dropping the listener on shutdown refuses further connections
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    Ok(_) = shutdown.wait_for(|stop| *stop) => break,
+                };
+                match accepted {
                     Ok((stream, addr)) => {
                         let client_id = Uuid::new_v4().to_string();
                         let client = ClientConnection {
@@ -85,16 +119,13 @@ where
                             .unwrap()
                             .insert(client_id.clone(), client);
 
-                        match thread_pool_sender.send_async(stream).await {
-                            Ok(_) => (),
-                            Err(e) => {
-                                eprintln!(
-                                    "Error sending connection {} to thread pool: {}",
-                                    client_id, e
-                                );
-                                active_connections.lock().unwrap().remove(&client_id);
-                            }
-                        }
+                        // the connection only takes up a worker once its first
+                        // request has come in
+                        drop(runtime.spawn(queue.clone().wait_for_request(
+                            BufReader::new(stream),
+                            addr,
+                            runtime.clone(),
+                        )));
                     }
                     Err(e) => {
                         eprintln!("Error accepting connection: {}", e);
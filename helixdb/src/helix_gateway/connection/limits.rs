@@ -0,0 +1,130 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    helix_engine::graph_core::config::{LimitsConfig, RateLimitConfig},
+    protocol::request::Request,
+};
+
+/// Buckets tracked before the least recently used one is dropped for a new address
+pub(crate) const MAX_TRACKED_ADDRESSES: usize = 10_000;
+
+/// Limits enforced on the requests of every connection to the gateway
+pub struct ConnectionLimits {
+    /// Requests with larger bodies are rejected before their body is read, defaults to
+    /// [`Request::DEFAULT_MAX_BODY_SIZE`]
+    pub max_body_size: usize,
+    pub rate_limiter: Option<RateLimiter>,
+    /// Connections waiting for a worker beyond which new ones are rejected, defaults to
+    /// [`GatewayOpts::DEFAULT_QUEUE_DEPTH`](crate::helix_gateway::gateway::GatewayOpts)
+    pub max_queued: Option<usize>,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_body_size: Request::DEFAULT_MAX_BODY_SIZE,
+            rate_limiter: None,
+            max_queued: None,
+        }
+    }
+}
+
+impl ConnectionLimits {
+    pub fn from_config(config: &LimitsConfig) -> Self {
+        Self {
+            max_body_size: config
+                .max_body_bytes
+                .unwrap_or(Request::DEFAULT_MAX_BODY_SIZE),
+            rate_limiter: config.rate_limit.map(RateLimiter::new),
+            max_queued: config.max_queued_connections,
+        }
+    }
+}
+
+/// Token bucket rate limiter keyed by the IP address of the client
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<Buckets>,
+}
+
+#[derive(Default)]
+struct Buckets {
+    by_addr: HashMap<IpAddr, Bucket>,
+    /// The addresses ordered by when their bucket was last used
+    by_use: BTreeSet<(Instant, IpAddr)>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            rate: config.requests_per_second,
+            burst: config
+                .burst
+                .map_or(config.requests_per_second, |burst| burst as f64)
+                .max(1.0),
+            buckets: Mutex::new(Buckets::default()),
+        }
+    }
+
+    /// Takes a request from the bucket of the address, returning how long the client
+    /// should wait before retrying if the bucket is empty
+    pub fn check(&self, addr: IpAddr) -> Result<(), Duration> {
+        self.check_at(addr, Instant::now())
+    }
+
+    pub(crate) fn check_at(&self, addr: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets { by_addr, by_use } = &mut *buckets;
+        let bucket = match by_addr.get_mut(&addr) {
+            Some(bucket) => {
+                by_use.remove(&(bucket.updated, addr));
+                bucket.tokens = self.refill(bucket, now);
+                bucket
+            }
+            None => {
+                // the least recently used bucket is the one most likely to have refilled,
+                // and a full bucket is the same as no bucket
+                if by_addr.len() >= MAX_TRACKED_ADDRESSES {
+                    if let Some((_, oldest)) = by_use.pop_first() {
+                        by_addr.remove(&oldest);
+                    }
+                }
+                by_addr.entry(addr).or_insert(Bucket {
+                    tokens: self.burst,
+                    updated: now,
+                })
+            }
+        };
+        bucket.updated = now;
+        by_use.insert((now, addr));
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / self.rate;
+            Err(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX))
+        }
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.burst)
+    }
+}
+
+/// Value of the `Retry-After` header for a wait, in whole seconds rounded up so the
+/// client doesn't retry early
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+}
@@ -0,0 +1,111 @@
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    helix_engine::graph_core::config::RateLimitConfig,
+    helix_gateway::connection::limits::{retry_after_secs, RateLimiter, MAX_TRACKED_ADDRESSES},
+};
+
+fn limiter(requests_per_second: f64, burst: Option<u32>) -> RateLimiter {
+    RateLimiter::new(RateLimitConfig {
+        requests_per_second,
+        burst,
+    })
+}
+
+fn addr(n: u32) -> IpAddr {
+    IpAddr::V4(Ipv4Addr::from(n))
+}
+
+#[test]
+fn test_allows_burst_then_rejects() {
+    let limiter = limiter(1.0, Some(3));
+    let now = Instant::now();
+    for _ in 0..3 {
+        assert!(limiter.check_at(addr(1), now).is_ok());
+    }
+    assert!(limiter.check_at(addr(1), now).is_err());
+    // other addresses have their own bucket
+    assert!(limiter.check_at(addr(2), now).is_ok());
+}
+
+#[test]
+fn test_burst_defaults_to_rate() {
+    let limiter = limiter(2.0, None);
+    let now = Instant::now();
+    assert!(limiter.check_at(addr(1), now).is_ok());
+    assert!(limiter.check_at(addr(1), now).is_ok());
+    assert!(limiter.check_at(addr(1), now).is_err());
+}
+
+#[test]
+fn test_refills_at_rate_up_to_burst() {
+    let limiter = limiter(2.0, Some(2));
+    let now = Instant::now();
+    assert!(limiter.check_at(addr(1), now).is_ok());
+    assert!(limiter.check_at(addr(1), now).is_ok());
+    assert!(limiter.check_at(addr(1), now).is_err());
+
+    // half a second refills one request at 2 per second
+    let later = now + Duration::from_millis(500);
+    assert!(limiter.check_at(addr(1), later).is_ok());
+    assert!(limiter.check_at(addr(1), later).is_err());
+
+    // a long quiet period only refills up to the burst
+    let much_later = later + Duration::from_secs(60);
+    assert!(limiter.check_at(addr(1), much_later).is_ok());
+    assert!(limiter.check_at(addr(1), much_later).is_ok());
+    assert!(limiter.check_at(addr(1), much_later).is_err());
+}
+
+#[test]
+fn test_retry_after_is_time_until_next_token() {
+    let limiter = limiter(4.0, Some(1));
+    let now = Instant::now();
+    assert!(limiter.check_at(addr(1), now).is_ok());
+    let wait = limiter.check_at(addr(1), now).unwrap_err();
+    assert!((wait.as_secs_f64() - 0.25).abs() < 1e-9, "{:?}", wait);
+
+    // part of the token has refilled in the meantime
+    let wait = limiter
+        .check_at(addr(1), now + Duration::from_millis(100))
+        .unwrap_err();
+    assert!((wait.as_secs_f64() - 0.15).abs() < 1e-9, "{:?}", wait);
+
+    let slow = self::limiter(0.1, Some(1));
+    assert!(slow.check_at(addr(1), now).is_ok());
+    let wait = slow.check_at(addr(1), now).unwrap_err();
+    assert_eq!(retry_after_secs(wait), 10);
+}
+
+#[test]
+fn test_retry_after_rounds_up() {
+    assert_eq!(retry_after_secs(Duration::ZERO), 0);
+    assert_eq!(retry_after_secs(Duration::from_millis(250)), 1);
+    assert_eq!(retry_after_secs(Duration::from_secs(2)), 2);
+    assert_eq!(retry_after_secs(Duration::from_millis(2001)), 3);
+}
+
+#[test]
+fn test_evicts_least_recently_used_address() {
+    let limiter = limiter(1.0, Some(1));
+    let now = Instant::now();
+    let tick = |n: u32| now + Duration::from_nanos(n as u64);
+
+    // the first address empties its bucket, then the table fills up
+    assert!(limiter.check_at(addr(0), tick(0)).is_ok());
+    for n in 1..MAX_TRACKED_ADDRESSES as u32 {
+        assert!(limiter.check_at(addr(n), tick(n)).is_ok());
+    }
+    // using the first address again keeps its bucket
+    let last = MAX_TRACKED_ADDRESSES as u32;
+    assert!(limiter.check_at(addr(0), tick(last)).is_err());
+
+    // a new address drops the least recently used bucket, which is the second one
+    assert!(limiter.check_at(addr(last + 1), tick(last + 1)).is_ok());
+    assert!(limiter.check_at(addr(0), tick(last + 2)).is_err());
+    // the dropped address starts over with a full bucket
+    assert!(limiter.check_at(addr(1), tick(last + 3)).is_ok());
+}
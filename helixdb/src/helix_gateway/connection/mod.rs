@@ -1 +1,5 @@
-pub mod connection;
\ No newline at end of file
+pub mod connection;
+pub mod limits;
+
+#[cfg(test)]
+mod limits_tests;
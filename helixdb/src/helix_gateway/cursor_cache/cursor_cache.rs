@@ -0,0 +1,217 @@
+use crate::{
+    helix_engine::{graph_core::snapshot::Snapshot, types::GraphError},
+    helix_gateway::router::router::{HandlerInput, ReadHandlerFn},
+    protocol::{
+        pagination::{CursorToken, Page},
+        redaction::Redaction,
+        response::Response,
+        return_values::ReturnValue,
+    },
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// A paginated read query along with the snapshot of the graph its first page was read
+/// from. Every page re-runs the query against the snapshot, so the pages are consistent
+/// with each other even if the graph is mutated in between requests.
+pub struct Cursor {
+    snapshot: Snapshot,
+    input: HandlerInput,
+    handler: ReadHandlerFn,
+    /// Number of items returned by the previous pages
+    offset: usize,
+}
+
+impl Cursor {
+    pub fn new(snapshot: Snapshot, input: HandlerInput, handler: ReadHandlerFn) -> Self {
+        Self {
+            snapshot,
+            input,
+            handler,
+            offset: 0,
+        }
+    }
+
+    /// Path of the route the query was sent to, continuation pages must be requested
+    /// from the same route
+    pub fn path(&self) -> &str {
+        &self.input.request.path
+    }
+
+    /// Runs the query against the snapshot and returns its next page, along with whether
+    /// there are items left after it.
+    ///
+    /// The query is run with the roles of the current request, so sensitive fields are
+    /// redacted for the client asking for the page.
+    fn next_page(&mut self, page_size: usize) -> Result<(Vec<ReturnValue>, bool), GraphError> {
+        let input = self.input.clone();
+        let handler = self.handler;
+        let roles = Redaction::roles();
+        let items = self.snapshot.read(move |_, txn| {
+            Redaction::scope(roles, || handler(&input, txn)).map(page_items)
+        })?;
+        let total = items.len();
+        let end = total.min(self.offset + page_size);
+        let page = items
+            .into_iter()
+            .take(end)
+            .skip(self.offset)
+            .collect::<Vec<_>>();
+        self.offset = end;
+        Ok((page, end < total))
+    }
+}
+
+/// The items a result set is paginated over: the elements of the array if the query
+/// returns a single array, otherwise the whole result as a single item
+fn page_items(return_vals: HashMap<String, ReturnValue>) -> Vec<ReturnValue> {
+    if return_vals.len() == 1 {
+        if let Some(ReturnValue::Array(items)) = return_vals.values().next() {
+            return items.clone();
+        }
+    }
+    vec![ReturnValue::Object(return_vals)]
+}
+
+struct CursorEntry {
+    cursor: Cursor,
+    last_used: Instant,
+}
+
+/// Bounded cache of open cursors.
+///
+/// Each cursor pins the snapshot of the graph its query was first run against until it
+/// is exhausted, evicted or expires, so the number of cursors open at once is also
+/// bounded by `max_snapshots` in the config: the least recently used cursor is evicted
+/// to release its snapshot when a new one can't be pinned.
+///
+/// The least recently used cursor is also evicted once `capacity` is reached and cursors
+/// that have not been touched for `ttl` are dropped.
+pub struct CursorCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<CursorToken, CursorEntry>,
+    order: VecDeque<CursorToken>,
+}
+
+impl CursorCache {
+    pub const DEFAULT_CAPACITY: usize = 1024;
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        assert!(capacity > 0, "Cursor cache capacity must be more than 0");
+        Self {
+            capacity,
+            ttl,
+            entries: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Number of open cursors
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Takes an open cursor out of the cache to read its next page, it is put back with
+    /// [`Self::insert`] if it has pages left
+    pub fn take(&mut self, token: &CursorToken) -> Result<Cursor, GraphError> {
+        self.evict_expired();
+        self.order.retain(|t| t != token);
+        self.entries
+            .remove(token)
+            .map(|entry| entry.cursor)
+            .ok_or(GraphError::CursorNotFound)
+    }
+
+    /// Closes a cursor before it has been exhausted
+    pub fn remove(&mut self, token: &CursorToken) -> bool {
+        self.order.retain(|t| t != token);
+        self.entries.remove(token).is_some()
+    }
+
+    pub fn insert(&mut self, token: CursorToken, cursor: Cursor) {
+        self.evict_expired();
+        while self.entries.len() >= self.capacity && self.evict_oldest() {}
+        self.entries.insert(
+            token,
+            CursorEntry {
+                cursor,
+                last_used: Instant::now(),
+            },
+        );
+        self.order.push_back(token);
+    }
+
+    /// Closes the least recently used cursor, releasing its snapshot. Returns false if no
+    /// cursor is open
+    pub fn evict_oldest(&mut self) -> bool {
+        self.evict_expired();
+        match self.order.pop_front() {
+            Some(oldest) => self.entries.remove(&oldest).is_some(),
+            None => false,
+        }
+    }
+
+    fn evict_expired(&mut self) {
+        let ttl = self.ttl;
+        let entries = &mut self.entries;
+        entries.retain(|_, entry| entry.last_used.elapsed() < ttl);
+        self.order.retain(|token| entries.contains_key(token));
+    }
+}
+
+impl Default for CursorCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY, Self::DEFAULT_TTL)
+    }
+}
+
+/// Writes the next page of a cursor to the response, keeping the cursor open in the cache
+/// under `token` if it has pages left.
+///
+/// The cache isn't locked while the query runs, so other cursors can be read meanwhile.
+pub fn serve_page(
+    cursors: &Mutex<CursorCache>,
+    token: CursorToken,
+    mut cursor: Cursor,
+    page_size: usize,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let (items, more) = cursor.next_page(page_size)?;
+    let next = match more {
+        true => {
+            cursors.lock().unwrap().insert(token, cursor);
+            Some(token.to_string())
+        }
+        false => None,
+    };
+    response.set_page(&Page {
+        items,
+        cursor: next,
+    })
+}
+
+/// Writes the next page of the cursor of a continuation request to the response
+pub fn continue_cursor(
+    cursors: &Mutex<CursorCache>,
+    path: &str,
+    token: &CursorToken,
+    page_size: usize,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let cursor = cursors.lock().unwrap().take(token)?;
+    if cursor.path() != path {
+        // tokens are only valid on the route that opened them
+        cursors.lock().unwrap().insert(*token, cursor);
+        return Err(GraphError::CursorNotFound);
+    }
+    serve_page(cursors, *token, cursor, page_size, response)
+}
@@ -0,0 +1 @@
+pub mod cursor_cache;
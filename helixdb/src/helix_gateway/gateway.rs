@@ -1,10 +1,11 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use super::connection::connection::ConnectionHandler;
+use super::connection::{connection::ConnectionHandler, limits::ConnectionLimits};
 use crate::helix_runtime::AsyncRuntime;
-use super::router::router::{HandlerFn, HelixRouter};
+use super::router::router::{AsyncHandlerFn, HandlerFn, HelixRouter, TxHandlerFn};
 use crate::{
-    helix_engine::graph_core::graph_core::HelixGraphEngine, helix_gateway::mcp::mcp::MCPHandlerFn,
+    helix_engine::{graph_core::graph_core::HelixGraphEngine, types::GraphError},
+    helix_gateway::mcp::mcp::MCPHandlerFn,
 };
 use crate::helix_transport::Transport;
 
@@ -12,6 +13,15 @@ pub struct GatewayOpts {}
 
 impl GatewayOpts {
     pub const DEFAULT_POOL_SIZE: usize = 8;
+    /// Connections waiting for a worker beyond which new ones are rejected with a 503
+    pub const DEFAULT_QUEUE_DEPTH: usize = 1000;
+    /// How long an idle keep-alive connection is kept open waiting for the client's next
+    /// request, without holding a worker meanwhile
+    pub const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+    /// How long shutting down waits for requests being handled to finish
+    pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+    /// How long shutting down waits for the queries cancelled after the timeout to stop
+    pub const CANCEL_TIMEOUT: Duration = Duration::from_secs(1);
 }
 
 pub struct HelixGateway<R, T>
@@ -20,6 +30,7 @@ where
     T: Transport,
 {
     pub connection_handler: ConnectionHandler<R, T>,
+    pub graph: Arc<HelixGraphEngine>,
     pub runtime: R,
 }
 
@@ -29,23 +40,88 @@ where
     T: Transport,
     T::Stream: 'static,
 {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         address: &str,
         graph: Arc<HelixGraphEngine>,
         size: usize,
         routes: Option<HashMap<(String, String), HandlerFn>>,
+        async_routes: Option<HashMap<(String, String), AsyncHandlerFn>>,
         mcp_routes: Option<HashMap<(String, String), MCPHandlerFn>>,
+        tx_routes: Option<HashMap<String, TxHandlerFn>>,
         runtime: R,
         transport: T,
     ) -> HelixGateway<R, T> {
-        let router = HelixRouter::new(routes, mcp_routes);
-        let connection_handler =
-            ConnectionHandler::new(address, graph, size, router, runtime.clone(), transport)
-                .unwrap();
+        let router = HelixRouter::new(routes, mcp_routes)
+            .with_async_routes(async_routes.unwrap_or_default())
+            .with_tx_routes(tx_routes.unwrap_or_default());
+        let limits = ConnectionLimits::default();
+        Self::with_router(address, graph, size, router, limits, runtime, transport).await
+    }
+
+    /// Creates a gateway serving a router built by the caller, e.g. with middleware,
+    /// and enforcing the given limits on every connection
+    pub async fn with_router(
+        address: &str,
+        graph: Arc<HelixGraphEngine>,
+        size: usize,
+        router: HelixRouter,
+        limits: ConnectionLimits,
+        runtime: R,
+        transport: T,
+    ) -> HelixGateway<R, T> {
+        let connection_handler = ConnectionHandler::new(
+            address,
+            Arc::clone(&graph),
+            size,
+            router,
+            limits,
+            runtime.clone(),
+            transport,
+        )
+        .unwrap();
         println!("Gateway created");
         HelixGateway {
             connection_handler,
+            graph,
             runtime,
         }
     }
+
+    /// Stops accepting connections, waits up to `timeout` for the requests being handled
+    /// to finish and flushes the database to disk.
+    ///
+    /// Handlers commit their own write transactions, so everything committed by the
+    /// handlers that finished in time is on disk once this returns. The queries of
+    /// requests still running after the timeout are cancelled and their writes rolled
+    /// back. Change event subscribers and replicas are disconnected right away.
+    pub async fn shutdown(self, timeout: Duration) -> Result<(), GraphError> {
+        println!("Shutting down gateway...");
+        self.connection_handler.shutdown();
+
+        let workers = self.connection_handler.thread_pool.workers;
+        let streams = self.connection_handler.thread_pool.streams;
+        let mut drained = Box::pin(async move {
+            for worker in workers {
+                worker.handle.await;
+            }
+            // subscribers and replicas are streamed to until the gateway shuts down
+            streams.closed().await;
+        });
+        tokio::select! {
+            _ = &mut drained => println!("All connections closed"),
+            _ = self.runtime.sleep(timeout) => {
+                eprintln!("Timed out waiting for requests to finish after {:?}, cancelling them", timeout);
+                self.connection_handler.router.cancel_queries();
+                tokio::select! {
+                    _ = drained => println!("All connections closed"),
+                    _ = self.runtime.sleep(GatewayOpts::CANCEL_TIMEOUT) => {
+                        eprintln!("Abandoning requests that didn't stop once cancelled");
+                    }
+                }
+            }
+        }
+
+        self.graph.storage.flush()
+    }
 }
@@ -0,0 +1,488 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use heed3::RwTxn;
+use tempfile::TempDir;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+            ops::{
+                g::G,
+                source::{add_n::AddNAdapter, n_from_type::NFromTypeAdapter},
+            },
+            query_limits,
+        },
+        types::GraphError,
+    },
+    helix_gateway::{
+        connection::limits::ConnectionLimits,
+        gateway::{GatewayOpts, HelixGateway},
+        router::router::{HandlerFuture, HandlerInput, HelixRouter},
+    },
+    helix_runtime::tokio_runtime::TokioRuntime,
+    helix_transport::{tokio_transport::TokioTransport, ws_transport::WsTransport, Transport},
+    props,
+    protocol::response::Response,
+};
+
+fn echo(input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {
+    response.body = input.request.body.clone();
+    Ok(())
+}
+
+/// An address nothing is listening on
+fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+fn engine() -> Arc<HelixGraphEngine> {
+    Arc::new(HelixGraphEngine::new(HelixGraphEngineOpts::in_memory()).unwrap())
+}
+
+/// Starts serving a gateway with `size` workers on a free port
+async fn serve(
+    size: usize,
+    router: HelixRouter,
+    limits: ConnectionLimits,
+) -> (SocketAddr, HelixGateway<TokioRuntime, TokioTransport>) {
+    serve_with(engine(), size, router, limits, TokioTransport).await
+}
+
+async fn serve_with<T: Transport>(
+    graph: Arc<HelixGraphEngine>,
+    size: usize,
+    router: HelixRouter,
+    limits: ConnectionLimits,
+    transport: T,
+) -> (SocketAddr, HelixGateway<TokioRuntime, T>) {
+    let addr = free_addr();
+    let gateway = HelixGateway::with_router(
+        &addr.to_string(),
+        graph,
+        size,
+        router,
+        limits,
+        TokioRuntime,
+        transport,
+    )
+    .await;
+    drop(gateway.connection_handler.accept_conns().await.unwrap());
+    (addr, gateway)
+}
+
+// waits on something other than the graph before answering
+fn slow(input: HandlerInput) -> HandlerFuture {
+    Box::pin(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let mut response = Response::new();
+        response.body = input.request.body;
+        Ok(response)
+    })
+}
+
+fn echo_router() -> HelixRouter {
+    let mut router = HelixRouter::new(None, None);
+    router.add_route("POST", "/echo", echo);
+    router.add_async_route("POST", "/slow", slow);
+    router
+}
+
+fn post(path: &str, body: &str) -> String {
+    format!(
+        "POST {} HTTP/1.1\r\nConnection: keep-alive\r\nContent-Length: {}\r\n\r\n{}",
+        path,
+        body.len(),
+        body
+    )
+}
+
+/// Reads a response with a `Content-Length`, returning its status and body
+async fn read_response<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> (u16, String) {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    let status = line.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await.unwrap();
+    (status, String::from_utf8(body).unwrap())
+}
+
+async fn request(addr: SocketAddr, raw: &str) -> (u16, String) {
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    stream.write_all(raw.as_bytes()).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(2), read_response(&mut stream))
+        .await
+        .expect("response should not wait on idle connections")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_keep_alive_connection_serves_requests() {
+    let (addr, _gateway) = serve(1, echo_router(), ConnectionLimits::default()).await;
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    for body in ["first", "second", "third"] {
+        stream.write_all(post("/echo", body).as_bytes()).await.unwrap();
+        assert_eq!(read_response(&mut stream).await, (200, body.to_string()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_pipelined_requests_answered_in_order() {
+    let (addr, _gateway) = serve(1, echo_router(), ConnectionLimits::default()).await;
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    let pipelined = ["first", "second", "third"]
+        .iter()
+        .map(|body| post("/echo", body))
+        .collect::<String>();
+    stream.write_all(pipelined.as_bytes()).await.unwrap();
+    for body in ["first", "second", "third"] {
+        assert_eq!(read_response(&mut stream).await, (200, body.to_string()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_idle_keep_alive_connections_dont_hold_workers() {
+    let (addr, _gateway) = serve(2, echo_router(), ConnectionLimits::default()).await;
+
+    // more idle keep-alive clients than workers
+    let mut idle = Vec::new();
+    for _ in 0..4 {
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        stream.write_all(post("/echo", "idle").as_bytes()).await.unwrap();
+        assert_eq!(read_response(&mut stream).await.0, 200);
+        idle.push(stream);
+    }
+
+    // new clients are served right away rather than after the keep-alive timeout
+    assert_eq!(request(addr, &post("/echo", "new")).await, (200, "new".to_string()));
+
+    // and the idle clients can keep using their connections
+    for stream in idle.iter_mut() {
+        stream.write_all(post("/echo", "again").as_bytes()).await.unwrap();
+        assert_eq!(read_response(stream).await, (200, "again".to_string()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_async_routes_dont_hold_workers() {
+    let (addr, _gateway) = serve(1, echo_router(), ConnectionLimits::default()).await;
+
+    let mut waiting = Vec::new();
+    for _ in 0..3 {
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        stream.write_all(post("/slow", "slow").as_bytes()).await.unwrap();
+        waiting.push(stream);
+    }
+
+    // the only worker is free while the async handlers wait
+    let started = std::time::Instant::now();
+    assert_eq!(request(addr, &post("/echo", "fast")).await, (200, "fast".to_string()));
+    assert!(started.elapsed() < Duration::from_millis(400));
+
+    for stream in waiting.iter_mut() {
+        assert_eq!(read_response(stream).await, (200, "slow".to_string()));
+        // the connection carries on once the async handler has answered
+        stream.write_all(post("/echo", "next").as_bytes()).await.unwrap();
+        assert_eq!(read_response(stream).await, (200, "next".to_string()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_websocket_requests() {
+    let (addr, _gateway) = serve_with(
+        engine(),
+        1,
+        echo_router(),
+        ConnectionLimits::default(),
+        WsTransport,
+    )
+    .await;
+    let mut stream = BufReader::new(WsTransport.connect(addr).await.unwrap());
+    for body in ["first", "second"] {
+        stream.write_all(post("/echo", body).as_bytes()).await.unwrap();
+        stream.flush().await.unwrap();
+        assert_eq!(read_response(&mut stream).await, (200, body.to_string()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_websocket_pushes_change_events() {
+    let mut config = Config::default();
+    config.cdc = true;
+    let graph = Arc::new(
+        HelixGraphEngine::new(HelixGraphEngineOpts {
+            config,
+            ..HelixGraphEngineOpts::in_memory()
+        })
+        .unwrap(),
+    );
+    let (addr, _gateway) = serve_with(
+        Arc::clone(&graph),
+        1,
+        echo_router(),
+        ConnectionLimits::default(),
+        WsTransport,
+    )
+    .await;
+    let mut stream = BufReader::new(WsTransport.connect(addr).await.unwrap());
+    stream
+        .write_all(b"GET /subscribe HTTP/1.1\r\n\r\n")
+        .await
+        .unwrap();
+    stream.flush().await.unwrap();
+    let mut line = String::new();
+    stream.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("HTTP/1.1 200"), "{}", line);
+    while line.trim() != "" {
+        line.clear();
+        stream.read_line(&mut line).await.unwrap();
+    }
+
+    let mut txn = graph.storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&graph.storage), &mut txn)
+        .add_n("person", Some(props! { "name" => "alice" }), None)
+        .collect_to_val();
+    txn.commit().unwrap();
+
+    line.clear();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_line(&mut line))
+        .await
+        .expect("change event should be pushed to the client")
+        .unwrap();
+    assert!(line.contains("person"), "{}", line);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_idle_websocket_kept_open() {
+    let (addr, _gateway) = serve_with(
+        engine(),
+        1,
+        echo_router(),
+        ConnectionLimits::default(),
+        WsTransport,
+    )
+    .await;
+    let mut stream = BufReader::new(WsTransport.connect(addr).await.unwrap());
+    stream.write_all(post("/echo", "first").as_bytes()).await.unwrap();
+    stream.flush().await.unwrap();
+    assert_eq!(read_response(&mut stream).await, (200, "first".to_string()));
+
+    // idle for longer than plain keep-alive connections are kept open
+    tokio::time::sleep(GatewayOpts::KEEP_ALIVE_TIMEOUT + Duration::from_secs(1)).await;
+    stream.write_all(post("/echo", "later").as_bytes()).await.unwrap();
+    stream.flush().await.unwrap();
+    assert_eq!(read_response(&mut stream).await, (200, "later".to_string()));
+}
+
+// holds its worker while answering, without holding up the rest of the runtime
+fn busy(input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {
+    tokio::task::block_in_place(|| std::thread::sleep(Duration::from_millis(500)));
+    response.body = input.request.body.clone();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_full_queue_answers_503() {
+    let mut router = echo_router();
+    router.add_route("POST", "/busy", busy);
+    let stats = Arc::clone(&router.stats);
+    let limits = ConnectionLimits {
+        max_queued: Some(1),
+        ..ConnectionLimits::default()
+    };
+    let (addr, _gateway) = serve(1, router, limits).await;
+
+    // one connection holds the only worker and the next one fills the queue
+    let mut waiting = Vec::new();
+    for body in ["first", "second"] {
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        stream.write_all(post("/busy", body).as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        waiting.push(stream);
+    }
+    assert_eq!(stats.queue().waiting, 1);
+
+    let (status, body) = request(addr, &post("/echo", "turned away")).await;
+    assert_eq!(status, 503);
+    assert_eq!(body, "503 - Service Unavailable");
+    assert_eq!(stats.queue().shed, 1);
+
+    // the queued connection is still served once the worker is free
+    for (stream, body) in waiting.iter_mut().zip(["first", "second"]) {
+        assert_eq!(read_response(stream).await, (200, body.to_string()));
+    }
+    let queue = stats.queue();
+    assert_eq!(queue.waiting, 0);
+    // the second connection waited for the first to be answered
+    assert!(queue.max_wait_ms >= 200.0, "{:?}", queue);
+    assert!(queue.avg_wait_ms > 0.0 && queue.avg_wait_ms <= queue.max_wait_ms);
+}
+
+fn count_written(graph: &HelixGraphEngine) -> usize {
+    let txn = graph.storage.graph_env.read_txn().unwrap();
+    G::new(Arc::clone(&graph.storage), &txn)
+        .n_from_type("written")
+        .count()
+        .into()
+}
+
+fn write_tx(input: &HandlerInput, txn: &mut RwTxn, _: &mut Response) -> Result<(), GraphError> {
+    G::new_mut(Arc::clone(&input.graph.storage), txn)
+        .add_n("written", None, None)
+        .collect_to_val();
+    Ok(())
+}
+
+// commits a write once its worker has been held for a while
+fn slow_write(input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {
+    tokio::task::block_in_place(|| {
+        std::thread::sleep(Duration::from_millis(300));
+        input.run_write(response, write_tx)
+    })
+}
+
+// writes and then scans the graph until the query is cancelled
+fn stuck_tx(
+    input: &HandlerInput,
+    txn: &mut RwTxn,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    write_tx(input, txn, response)?;
+    loop {
+        G::new(Arc::clone(&input.graph.storage), txn)
+            .n_from_type("person")
+            .count();
+        query_limits::check()?;
+    }
+}
+
+fn stuck(input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {
+    tokio::task::block_in_place(|| input.run_write(response, stuck_tx))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_shutdown_drains_requests() {
+    let graph = engine();
+    let mut router = echo_router();
+    router.add_route("POST", "/write", slow_write);
+    let (addr, gateway) = serve_with(
+        Arc::clone(&graph),
+        1,
+        router,
+        ConnectionLimits::default(),
+        TokioTransport,
+    )
+    .await;
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    stream.write_all(post("/write", "").as_bytes()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    gateway.shutdown(Duration::from_secs(5)).await.unwrap();
+    // the request being handled finished and was answered before the connection closed
+    assert_eq!(count_written(&graph), 1);
+    assert_eq!(read_response(&mut stream).await.0, 200);
+    let mut rest = Vec::new();
+    assert_eq!(stream.read_to_end(&mut rest).await.unwrap(), 0);
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_shutdown_cancels_requests_after_timeout() {
+    let graph = engine();
+    let mut txn = graph.storage.graph_env.write_txn().unwrap();
+    for _ in 0..10 {
+        G::new_mut(Arc::clone(&graph.storage), &mut txn)
+            .add_n("person", Some(props! { "name" => "alice" }), None)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+    let mut router = echo_router();
+    router.add_route("POST", "/stuck", stuck);
+    let (addr, gateway) = serve_with(
+        Arc::clone(&graph),
+        1,
+        router,
+        ConnectionLimits::default(),
+        TokioTransport,
+    )
+    .await;
+    let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+    stream.write_all(post("/stuck", "").as_bytes()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let started = std::time::Instant::now();
+    gateway.shutdown(Duration::from_millis(200)).await.unwrap();
+    assert!(started.elapsed() < Duration::from_millis(200) + GatewayOpts::CANCEL_TIMEOUT);
+    // the query was cancelled and its write rolled back
+    assert_eq!(read_response(&mut stream).await.0, 408);
+    assert_eq!(count_written(&graph), 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_shutdown_closes_streams() {
+    let (dir, log_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    let mut config = Config::default();
+    config.cdc = true;
+    config.wal.enabled = true;
+    config.wal.dir = Some(log_dir.path().to_str().unwrap().to_string());
+    let graph = Arc::new(
+        HelixGraphEngine::new(HelixGraphEngineOpts {
+            config,
+            ..HelixGraphEngineOpts::with_path(dir.path().to_str().unwrap().to_string())
+        })
+        .unwrap(),
+    );
+    let (addr, gateway) = serve_with(
+        graph,
+        1,
+        echo_router(),
+        ConnectionLimits::default(),
+        TokioTransport,
+    )
+    .await;
+
+    // a change event subscriber and a replica, streamed to by tasks of their own
+    let mut streams = Vec::new();
+    for path in ["/subscribe", "/replicate?after=0"] {
+        let mut stream = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        let raw = format!("GET {} HTTP/1.1\r\n\r\n", path);
+        stream.write_all(raw.as_bytes()).await.unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("HTTP/1.1 200"), "{}", line);
+        streams.push(stream);
+    }
+    assert_eq!(gateway.connection_handler.thread_pool.streams.count(), 2);
+
+    tokio::time::timeout(Duration::from_secs(2), gateway.shutdown(Duration::from_secs(5)))
+        .await
+        .expect("shutdown shouldn't wait on the streams")
+        .unwrap();
+    for stream in streams.iter_mut() {
+        let mut rest = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), stream.read_to_end(&mut rest))
+            .await
+            .expect("the stream should be closed on shutdown")
+            .unwrap();
+    }
+}
@@ -0,0 +1,916 @@
+use crate::helix_engine::{
+    graph_core::{
+        graph_core::HelixGraphEngine,
+        ops::{
+            g::G,
+            in_::{in_::InAdapter, in_e::InEdgesAdapter, to_n::ToNAdapter},
+            out::{from_n::FromNAdapter, out::OutAdapter, out_e::OutEdgesAdapter},
+            source::{
+                add_e::EdgeType, e_from_type::EFromTypeAdapter, n_from_type::NFromTypeAdapter,
+            },
+            tr_val::{Traversable, TraversalVal},
+            util::{filter_ref::FilterRefAdapter, range::RangeAdapter},
+        },
+        traversal_iter::RoTraversalIterator,
+    },
+    storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    types::GraphError,
+};
+use crate::helix_storage::heed3::RoTxn;
+use crate::protocol::{request::Request, response::Response, value::Value};
+use serde::Deserialize;
+use serde_json::{json, Map, Number, Value as JsonValue};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Path of the endpoint running Gremlin traversals
+pub const GREMLIN_PATH: &str = "/gremlin";
+
+/// Body of a request to the Gremlin endpoint, as sent to the HTTP endpoint of Gremlin
+/// Server.
+///
+/// The traversal is either a script made of a single chain of steps or the GraphSON
+/// bytecode of a traversal, with the arguments of the script looked up in `bindings`.
+///
+/// ```json
+/// { "gremlin": "g.V().has('User', 'name', name).out('Follows').limit(10)",
+///   "bindings": { "name": "John" } }
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct GremlinRequest {
+    #[serde(default, rename = "requestId")]
+    pub request_id: Option<String>,
+    pub gremlin: JsonValue,
+    #[serde(default)]
+    pub bindings: HashMap<String, JsonValue>,
+}
+
+/// A step of a traversal and its arguments, e.g. `out('Follows')`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub name: String,
+    pub args: Vec<Arg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Value(Value),
+    /// A predicate such as `gt(30)`, by the name of its comparison
+    Predicate(String, Value),
+}
+
+type Traversers<'a> = Box<dyn Iterator<Item = Result<TraversalVal, GraphError>> + 'a>;
+
+/// Runs a Gremlin traversal read-only against the graph and writes its results to the
+/// response as GraphSON 3.0.
+///
+/// Only linear traversals over the steps graph_core has an equivalent of are supported:
+/// `V`, `E`, `has`, `hasLabel`, `hasId`, `out`, `in`, `both`, `outE`, `inE`, `bothE`,
+/// `outV`, `inV`, `limit`, `skip`, `range`, `dedup`, `count`, `values`, `valueMap`, `id`
+/// and `label`. Edges are only followed by label, like in HelixQL.
+pub fn handle(
+    graph_access: Arc<HelixGraphEngine>,
+    request: Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let gremlin: GremlinRequest = serde_json::from_slice(&request.body)
+        .map_err(|e| GraphError::New(format!("Invalid Gremlin request: {}", e)))?;
+    let request_id = gremlin
+        .request_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    response
+        .headers
+        .insert("Content-Type".to_string(), "application/json".to_string());
+
+    let steps = match &gremlin.gremlin {
+        JsonValue::String(script) => parse_script(script, &gremlin.bindings),
+        bytecode => parse_bytecode(bytecode),
+    };
+    let result = steps.and_then(|steps| {
+        let storage = Arc::clone(&graph_access.storage);
+        let txn = storage.graph_env.read_txn()?;
+        let data = traverse(&storage, &txn, &steps)?
+            .map(|item| item.map(|item| graphson(&item)))
+            .collect::<Result<Vec<_>, _>>();
+        data
+    });
+
+    let (status, code, message, data) = match result {
+        Ok(data) => (200, 200, String::new(), data),
+        Err(e) => (500, 597, e.to_string(), Vec::new()),
+    };
+    response.status = status;
+    response.body = serde_json::to_vec(&json!({
+        "requestId": request_id,
+        "status": { "message": message, "code": code, "attributes": g_map(Vec::new()) },
+        "result": { "data": g_list(data), "meta": g_map(Vec::new()) },
+    }))
+    .map_err(|e| GraphError::New(e.to_string()))?;
+    Ok(())
+}
+
+/// Builds the traversal of the steps, starting from the `V` or `E` step they begin with
+pub fn traverse<'a>(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &'a RoTxn<'a>,
+    steps: &'a [Step],
+) -> Result<Traversers<'a>, GraphError> {
+    let Some((source, steps)) = steps.split_first() else {
+        return Err(GraphError::New("Empty traversal".to_string()));
+    };
+    // a label right after the source narrows the scan to it
+    let label = steps
+        .first()
+        .and_then(|step| match (step.name.as_str(), &step.args[..]) {
+            ("hasLabel", [Arg::Value(Value::String(label))])
+            | ("has", [Arg::Value(Value::String(label)), _, _]) => Some(label.as_str()),
+            _ => None,
+        });
+    let ids = source
+        .args
+        .iter()
+        .map(|arg| parse_id(arg, &source.name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let g = G::new(Arc::clone(storage), txn);
+    let mut traversers: Traversers<'a> = match (source.name.as_str(), label) {
+        ("V", _) if !ids.is_empty() => {
+            let storage = Arc::clone(storage);
+            Box::new(
+                ids.into_iter()
+                    .filter_map(move |id| match storage.get_node(txn, &id) {
+                        Ok(node) => Some(Ok(TraversalVal::Node(node))),
+                        Err(GraphError::NodeNotFound) => None,
+                        Err(e) => Some(Err(e)),
+                    }),
+            )
+        }
+        ("E", _) if !ids.is_empty() => {
+            let storage = Arc::clone(storage);
+            Box::new(
+                ids.into_iter()
+                    .filter_map(move |id| match storage.get_edge(txn, &id) {
+                        Ok(edge) => Some(Ok(TraversalVal::Edge(edge))),
+                        Err(GraphError::EdgeNotFound) => None,
+                        Err(e) => Some(Err(e)),
+                    }),
+            )
+        }
+        ("V", Some(label)) => Box::new(g.n_from_type(label)),
+        ("E", Some(label)) => Box::new(g.e_from_type(label)),
+        ("V", None) => {
+            let storage = Arc::clone(storage);
+            Box::new(storage.nodes_db.iter(txn)?.map(move |entry| {
+                let (id, bytes) = entry?;
+                Ok(TraversalVal::Node(storage.decode_node(bytes, id)?))
+            }))
+        }
+        ("E", None) => {
+            let storage = Arc::clone(storage);
+            Box::new(storage.edges_db.iter(txn)?.map(move |entry| {
+                let (id, bytes) = entry?;
+                Ok(TraversalVal::Edge(storage.decode_edge(bytes, id)?))
+            }))
+        }
+        (name, _) => {
+            return Err(GraphError::New(format!(
+                "Traversals start with V() or E(), not {}()",
+                name
+            )))
+        }
+    };
+    for step in steps {
+        traversers = apply(storage, txn, traversers, step)?;
+    }
+    Ok(traversers)
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Out,
+    In,
+}
+
+fn apply<'a>(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &'a RoTxn<'a>,
+    traversers: Traversers<'a>,
+    step: &'a Step,
+) -> Result<Traversers<'a>, GraphError> {
+    let traversal = RoTraversalIterator {
+        inner: traversers,
+        storage: Arc::clone(storage),
+        txn,
+    };
+    let args = step.args.as_slice();
+    let traversers: Traversers<'a> = match step.name.as_str() {
+        "has" => {
+            let (label, key, predicate) = match args {
+                [Arg::Value(Value::String(key))] => (None, key, None),
+                [Arg::Value(Value::String(key)), value] => (None, key, Some(value)),
+                [Arg::Value(Value::String(label)), Arg::Value(Value::String(key)), value] => {
+                    (Some(label), key, Some(value))
+                }
+                _ => return Err(invalid_args(step)),
+            };
+            Box::new(traversal.filter_ref(move |item, _| {
+                let Ok(item) = item else { return Ok(true) };
+                if label.is_some_and(|label| !has_label(item, label)) {
+                    return Ok(false);
+                }
+                Ok(match (property(item, key), predicate) {
+                    (Some(value), Some(predicate)) => test(predicate, value),
+                    (value, _) => value.is_some(),
+                })
+            }))
+        }
+        "hasLabel" => {
+            let labels = strings(step)?;
+            Box::new(traversal.filter_ref(move |item, _| match item {
+                Ok(item) => Ok(labels.iter().any(|label| has_label(item, label))),
+                Err(_) => Ok(true),
+            }))
+        }
+        "hasId" => {
+            let ids = args
+                .iter()
+                .map(|arg| parse_id(arg, &step.name))
+                .collect::<Result<HashSet<_>, _>>()?;
+            Box::new(traversal.filter_ref(move |item, _| match item {
+                Ok(item @ (TraversalVal::Node(_) | TraversalVal::Edge(_))) => {
+                    Ok(ids.contains(&item.id()))
+                }
+                Ok(_) => Ok(false),
+                Err(_) => Ok(true),
+            }))
+        }
+        "out" => adjacent(traversal, strings(step)?, &[Direction::Out], false)?,
+        "in" => adjacent(traversal, strings(step)?, &[Direction::In], false)?,
+        "both" => adjacent(
+            traversal,
+            strings(step)?,
+            &[Direction::Out, Direction::In],
+            false,
+        )?,
+        "outE" => adjacent(traversal, strings(step)?, &[Direction::Out], true)?,
+        "inE" => adjacent(traversal, strings(step)?, &[Direction::In], true)?,
+        "bothE" => adjacent(
+            traversal,
+            strings(step)?,
+            &[Direction::Out, Direction::In],
+            true,
+        )?,
+        "outV" if args.is_empty() => Box::new(traversal.from_n()),
+        "inV" if args.is_empty() => Box::new(traversal.to_n()),
+        "limit" => Box::new(traversal.range(0, count_arg(step, 0)?)),
+        "skip" => Box::new(traversal.range(count_arg(step, 0)?, usize::MAX)),
+        "range" => Box::new(traversal.range(count_arg(step, 0)?, count_arg(step, 1)?)),
+        "dedup" if args.is_empty() => {
+            let mut seen = HashSet::new();
+            Box::new(traversal.filter(move |item| match item {
+                Ok(item @ (TraversalVal::Node(_) | TraversalVal::Edge(_))) => {
+                    seen.insert(item.id().to_string())
+                }
+                Ok(item) => seen.insert(format!("{:?}", item)),
+                Err(_) => true,
+            }))
+        }
+        "count" if args.is_empty() => Box::new(std::iter::once_with(move || {
+            let mut count = 0;
+            for item in traversal {
+                item?;
+                count += 1;
+            }
+            Ok(TraversalVal::Value(Value::I64(count)))
+        })),
+        "values" => {
+            let keys = strings(step)?;
+            Box::new(traversal.flat_map(move |item| {
+                let values = match item {
+                    Ok(item) => properties(&item, &keys)
+                        .into_iter()
+                        .map(|(_, value)| Ok(TraversalVal::Value(value)))
+                        .collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                values.into_iter()
+            }))
+        }
+        "valueMap" => {
+            let keys = strings(step)?;
+            Box::new(traversal.map(move |item| {
+                let values = properties(&item?, &keys)
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::Array(vec![value])))
+                    .collect();
+                Ok(TraversalVal::Value(Value::Object(values)))
+            }))
+        }
+        "id" if args.is_empty() => Box::new(traversal.map(|item| match item? {
+            item @ (TraversalVal::Node(_) | TraversalVal::Edge(_) | TraversalVal::Vector(_)) => {
+                Ok(TraversalVal::Value(Value::String(item.uuid())))
+            }
+            _ => Err(GraphError::New("id() of a value".to_string())),
+        })),
+        "label" if args.is_empty() => Box::new(traversal.map(|item| match item? {
+            TraversalVal::Node(node) => Ok(TraversalVal::Value(Value::String(node.label))),
+            TraversalVal::Edge(edge) => Ok(TraversalVal::Value(Value::String(edge.label))),
+            _ => Err(GraphError::New("label() of a value".to_string())),
+        })),
+        "addV" | "addE" | "property" | "drop" | "mergeV" | "mergeE" => {
+            return Err(GraphError::New(format!(
+                "{}() writes to the graph, the Gremlin endpoint is read-only",
+                step.name
+            )))
+        }
+        // the steps above that only accept no arguments
+        "outV" | "inV" | "dedup" | "count" | "id" | "label" => return Err(invalid_args(step)),
+        name => return Err(GraphError::New(format!("Unsupported step {}()", name))),
+    };
+    Ok(traversers)
+}
+
+/// Follows the edges of the labels from each traverser, to the nodes at their other end
+/// or to the edges themselves
+fn adjacent<'a>(
+    traversal: RoTraversalIterator<'a, Traversers<'a>>,
+    labels: Vec<&'a str>,
+    directions: &'static [Direction],
+    edges: bool,
+) -> Result<Traversers<'a>, GraphError> {
+    if labels.is_empty() {
+        return Err(GraphError::New(
+            "Edges are only followed by label, name the labels to follow".to_string(),
+        ));
+    }
+    let RoTraversalIterator {
+        inner,
+        storage,
+        txn,
+    } = traversal;
+    Ok(Box::new(inner.flat_map(move |item| -> Traversers<'a> {
+        let item = match item {
+            Ok(item) => item,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+        let storage = Arc::clone(&storage);
+        let labels = labels.clone();
+        Box::new(directions.iter().flat_map(move |direction| {
+            let storage = Arc::clone(&storage);
+            let item = item.clone();
+            labels.clone().into_iter().flat_map(move |label| {
+                let from = G::new_from(Arc::clone(&storage), txn, vec![item.clone()]);
+                let traversers: Traversers<'a> = match (direction, edges) {
+                    (Direction::Out, false) => Box::new(from.out(label, &EdgeType::Node)),
+                    (Direction::In, false) => Box::new(from.in_(label, &EdgeType::Node)),
+                    (Direction::Out, true) => Box::new(from.out_e(label)),
+                    (Direction::In, true) => Box::new(from.in_e(label)),
+                };
+                traversers
+            })
+        }))
+    })))
+}
+
+fn invalid_args(step: &Step) -> GraphError {
+    GraphError::New(format!(
+        "Invalid arguments to {}(): {:?}",
+        step.name, step.args
+    ))
+}
+
+/// The arguments of a step that takes strings, such as labels or keys
+fn strings(step: &Step) -> Result<Vec<&str>, GraphError> {
+    step.args
+        .iter()
+        .map(|arg| match arg {
+            Arg::Value(Value::String(s)) => Ok(s.as_str()),
+            _ => Err(invalid_args(step)),
+        })
+        .collect()
+}
+
+/// A count argument of a step, where `-1` stands for no limit
+fn count_arg(step: &Step, index: usize) -> Result<usize, GraphError> {
+    match step.args.get(index) {
+        Some(Arg::Value(value)) => match value.as_f64() {
+            Some(n) if n < 0.0 => Ok(usize::MAX),
+            Some(n) if n.fract() == 0.0 => Ok(n as usize),
+            _ => Err(invalid_args(step)),
+        },
+        _ => Err(invalid_args(step)),
+    }
+}
+
+fn parse_id(arg: &Arg, step: &str) -> Result<u128, GraphError> {
+    match arg {
+        Arg::Value(Value::String(id)) => Ok(uuid::Uuid::parse_str(id)?.as_u128()),
+        Arg::Value(Value::U128(id)) => Ok(*id),
+        _ => Err(GraphError::New(format!(
+            "The ids of {}() are UUIDs, not {:?}",
+            step, arg
+        ))),
+    }
+}
+
+fn has_label(item: &TraversalVal, label: &str) -> bool {
+    match item {
+        TraversalVal::Node(node) => node.label == label,
+        TraversalVal::Edge(edge) => edge.label == label,
+        _ => false,
+    }
+}
+
+fn property<'i>(item: &'i TraversalVal, key: &str) -> Option<&'i Value> {
+    let properties = match item {
+        TraversalVal::Node(node) => node.properties.as_ref(),
+        TraversalVal::Edge(edge) => edge.properties.as_ref(),
+        TraversalVal::Vector(vector) => vector.properties.as_ref(),
+        _ => None,
+    };
+    properties?.get(key)
+}
+
+/// The properties of an item with the keys, or all of them if no keys are given
+fn properties(item: &TraversalVal, keys: &[&str]) -> Vec<(String, Value)> {
+    let properties = match item {
+        TraversalVal::Node(node) => node.properties.as_ref(),
+        TraversalVal::Edge(edge) => edge.properties.as_ref(),
+        TraversalVal::Vector(vector) => vector.properties.as_ref(),
+        _ => None,
+    };
+    let Some(properties) = properties else {
+        return Vec::new();
+    };
+    match keys {
+        [] => properties
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        keys => keys
+            .iter()
+            .filter_map(|key| Some((key.to_string(), properties.get(*key)?.clone())))
+            .collect(),
+    }
+}
+
+/// Whether a property passes the value or predicate it is compared with
+fn test(predicate: &Arg, value: &Value) -> bool {
+    let (name, expected) = match predicate {
+        Arg::Value(expected) => ("eq", expected),
+        Arg::Predicate(name, expected) => (name.as_str(), expected),
+    };
+    // values of different types are never equal or ordered, except for numbers
+    let ordering = |expected: &Value| {
+        let comparable = std::mem::discriminant(value) == std::mem::discriminant(expected)
+            || (value.as_f64().is_some() && expected.as_f64().is_some());
+        comparable.then(|| value.total_cmp(expected))
+    };
+    let within = |values: &Value| match values {
+        Value::Array(values) => values.iter().any(|v| ordering(v) == Some(Ordering::Equal)),
+        value => ordering(value) == Some(Ordering::Equal),
+    };
+    match name {
+        "eq" => ordering(expected) == Some(Ordering::Equal),
+        "neq" => ordering(expected) != Some(Ordering::Equal),
+        "lt" => ordering(expected) == Some(Ordering::Less),
+        "lte" => matches!(ordering(expected), Some(Ordering::Less | Ordering::Equal)),
+        "gt" => ordering(expected) == Some(Ordering::Greater),
+        "gte" => matches!(
+            ordering(expected),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        "within" => within(expected),
+        "without" => !within(expected),
+        "between" => match expected {
+            Value::Array(bounds) if bounds.len() == 2 => {
+                matches!(
+                    ordering(&bounds[0]),
+                    Some(Ordering::Greater | Ordering::Equal)
+                ) && ordering(&bounds[1]) == Some(Ordering::Less)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+const PREDICATES: [&str; 9] = [
+    "eq", "neq", "lt", "lte", "gt", "gte", "within", "without", "between",
+];
+
+// ---------------------------------------------------------------------
+// Bytecode
+// ---------------------------------------------------------------------
+
+/// Reads the steps of GraphSON bytecode, `{"@type": "g:Bytecode", "@value": {"step": [...]}}`
+pub fn parse_bytecode(bytecode: &JsonValue) -> Result<Vec<Step>, GraphError> {
+    let bytecode = match bytecode.get("@type") {
+        Some(JsonValue::String(t)) if t == "g:Bytecode" => &bytecode["@value"],
+        _ => bytecode,
+    };
+    if bytecode
+        .get("source")
+        .and_then(JsonValue::as_array)
+        .is_some_and(|source| !source.is_empty())
+    {
+        return Err(GraphError::New(
+            "Traversal source steps such as withSideEffect() aren't supported".to_string(),
+        ));
+    }
+    let Some(steps) = bytecode.get("step").and_then(JsonValue::as_array) else {
+        return Err(GraphError::New("Bytecode without steps".to_string()));
+    };
+    steps
+        .iter()
+        .map(|step| {
+            let instruction = step.as_array().map(Vec::as_slice).unwrap_or_default();
+            let Some((JsonValue::String(name), args)) = instruction.split_first() else {
+                return Err(GraphError::New(format!("Invalid bytecode step {}", step)));
+            };
+            Ok(Step {
+                name: name.clone(),
+                args: args.iter().map(untype_arg).collect::<Result<_, _>>()?,
+            })
+        })
+        .collect()
+}
+
+fn untype_arg(value: &JsonValue) -> Result<Arg, GraphError> {
+    match value.get("@type").and_then(JsonValue::as_str) {
+        Some("g:P") => {
+            let predicate = &value["@value"];
+            match predicate["predicate"].as_str() {
+                Some(name) if PREDICATES.contains(&name) => Ok(Arg::Predicate(
+                    name.to_string(),
+                    untype(&predicate["value"])?,
+                )),
+                _ => Err(GraphError::New(format!(
+                    "Unsupported predicate {}",
+                    predicate
+                ))),
+            }
+        }
+        _ => Ok(Arg::Value(untype(value)?)),
+    }
+}
+
+/// The value of a typed GraphSON value
+fn untype(value: &JsonValue) -> Result<Value, GraphError> {
+    let (Some(JsonValue::String(ty)), Some(inner)) = (value.get("@type"), value.get("@value"))
+    else {
+        return Ok(Value::from(value.clone()));
+    };
+    match ty.as_str() {
+        "g:List" | "g:Set" => Ok(Value::Array(
+            inner
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(untype)
+                .collect::<Result<_, _>>()?,
+        )),
+        "g:Map" => {
+            let entries = inner.as_array().map(Vec::as_slice).unwrap_or_default();
+            let mut map = HashMap::new();
+            for entry in entries.chunks(2) {
+                if let [key, value] = entry {
+                    map.insert(untype(key)?.to_string(), untype(value)?);
+                }
+            }
+            Ok(Value::Object(map))
+        }
+        "g:Int32" | "g:Int64" | "g:Float" | "g:Double" | "g:UUID" | "g:T" | "gx:Byte"
+        | "gx:Int16" => Ok(Value::from(inner.clone())),
+        ty => Err(GraphError::New(format!("Unsupported GraphSON type {}", ty))),
+    }
+}
+
+// ---------------------------------------------------------------------
+// Scripts
+// ---------------------------------------------------------------------
+
+/// Reads the steps of a script made of a single traversal, such as
+/// `g.V().has('name', 'John').out('Follows')`, looking up variables in the bindings
+pub fn parse_script(
+    script: &str,
+    bindings: &HashMap<String, JsonValue>,
+) -> Result<Vec<Step>, GraphError> {
+    let mut parser = ScriptParser {
+        chars: script.chars().collect(),
+        pos: 0,
+        bindings,
+    };
+    parser.skip_whitespace();
+    if parser.identifier() != "g" {
+        return Err(parser.error("a traversal starting with g"));
+    }
+    let mut steps = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => break,
+            Some(';') => {
+                parser.pos += 1;
+                parser.skip_whitespace();
+                match parser.peek() {
+                    None => break,
+                    Some(_) => return Err(parser.error("a single traversal")),
+                }
+            }
+            Some('.') => {
+                parser.pos += 1;
+                let name = parser.identifier();
+                if name.is_empty() {
+                    return Err(parser.error("a step"));
+                }
+                parser.skip_whitespace();
+                parser.expect('(')?;
+                let args = parser.args(')')?;
+                steps.push(Step { name, args });
+            }
+            Some(_) => return Err(parser.error("`.`")),
+        }
+    }
+    Ok(steps)
+}
+
+struct ScriptParser<'b> {
+    chars: Vec<char>,
+    pos: usize,
+    bindings: &'b HashMap<String, JsonValue>,
+}
+
+impl ScriptParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, expected: &str) -> GraphError {
+        GraphError::New(format!(
+            "Invalid Gremlin script, expected {} at character {}",
+            expected, self.pos
+        ))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), GraphError> {
+        match self.peek() {
+            Some(next) if next == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error(&format!("`{}`", c))),
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// The arguments of a call or list, up to the closing character
+    fn args(&mut self, close: char) -> Result<Vec<Arg>, GraphError> {
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.arg()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(args);
+                }
+                _ => return Err(self.error(&format!("`,` or `{}`", close))),
+            }
+        }
+    }
+
+    fn arg(&mut self) -> Result<Arg, GraphError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => {
+                self.pos += 1;
+                let mut s = String::new();
+                loop {
+                    match self.peek() {
+                        Some('\\') => {
+                            self.pos += 1;
+                            s.extend(self.peek());
+                        }
+                        Some(c) if c == quote => break,
+                        Some(c) => s.push(c),
+                        None => return Err(self.error("the end of the string")),
+                    }
+                    self.pos += 1;
+                }
+                self.pos += 1;
+                Ok(Arg::Value(Value::String(s)))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' => {
+                let start = self.pos;
+                self.pos += 1;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E'))
+                {
+                    self.pos += 1;
+                }
+                let number = self.chars[start..self.pos].iter().collect::<String>();
+                // type suffixes of Groovy numbers, e.g. 10L
+                if self
+                    .peek()
+                    .is_some_and(|c| matches!(c, 'l' | 'L' | 'd' | 'D' | 'f' | 'F'))
+                {
+                    self.pos += 1;
+                }
+                match (number.parse::<i64>(), number.parse::<f64>()) {
+                    (Ok(i), _) => Ok(Arg::Value(Value::I64(i))),
+                    (_, Ok(f)) => Ok(Arg::Value(Value::F64(f))),
+                    _ => Err(self.error("a number")),
+                }
+            }
+            Some('[') => {
+                self.pos += 1;
+                let values = self
+                    .args(']')?
+                    .into_iter()
+                    .map(|arg| match arg {
+                        Arg::Value(value) => Ok(value),
+                        Arg::Predicate(..) => Err(self.error("a value")),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Arg::Value(Value::Array(values)))
+            }
+            _ => {
+                let mut name = self.identifier();
+                // P.gt(30) is gt(30)
+                if name == "P" && self.peek() == Some('.') {
+                    self.pos += 1;
+                    name = self.identifier();
+                }
+                self.skip_whitespace();
+                if self.peek() == Some('(') {
+                    self.pos += 1;
+                    if !PREDICATES.contains(&name.as_str()) {
+                        return Err(GraphError::New(format!("Unsupported predicate {}", name)));
+                    }
+                    let mut values = self
+                        .args(')')?
+                        .into_iter()
+                        .map(|arg| match arg {
+                            Arg::Value(value) => Ok(value),
+                            Arg::Predicate(..) => Err(self.error("a value")),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let value = match values.len() {
+                        1 if !matches!(name.as_str(), "within" | "without" | "between") => {
+                            values.remove(0)
+                        }
+                        1 if matches!(values[0], Value::Array(_)) => values.remove(0),
+                        _ => Value::Array(values),
+                    };
+                    return Ok(Arg::Predicate(name, value));
+                }
+                match name.as_str() {
+                    "" => Err(self.error("an argument")),
+                    "true" => Ok(Arg::Value(Value::Boolean(true))),
+                    "false" => Ok(Arg::Value(Value::Boolean(false))),
+                    name => match self.bindings.get(name) {
+                        Some(value) => Ok(Arg::Value(Value::from(value.clone()))),
+                        None => Err(GraphError::New(format!("Unbound variable {}", name))),
+                    },
+                }
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------
+// GraphSON
+// ---------------------------------------------------------------------
+
+fn typed(ty: &str, value: JsonValue) -> JsonValue {
+    json!({ "@type": ty, "@value": value })
+}
+
+fn g_list(values: Vec<JsonValue>) -> JsonValue {
+    typed("g:List", JsonValue::Array(values))
+}
+
+fn g_map(entries: Vec<(JsonValue, JsonValue)>) -> JsonValue {
+    typed(
+        "g:Map",
+        JsonValue::Array(entries.into_iter().flat_map(|(k, v)| [k, v]).collect()),
+    )
+}
+
+/// A traverser as GraphSON 3.0, with ids written as UUID strings
+fn graphson(item: &TraversalVal) -> JsonValue {
+    match item {
+        TraversalVal::Node(node) => {
+            let properties = node
+                .properties
+                .iter()
+                .flatten()
+                .map(|(key, value)| {
+                    let property = typed(
+                        "g:VertexProperty",
+                        json!({
+                            "id": format!("{}.{}", item.uuid(), key),
+                            "label": key,
+                            "value": graphson_value(value),
+                        }),
+                    );
+                    (key.clone(), JsonValue::Array(vec![property]))
+                })
+                .collect::<Map<_, _>>();
+            typed(
+                "g:Vertex",
+                json!({ "id": item.uuid(), "label": node.label, "properties": properties }),
+            )
+        }
+        TraversalVal::Edge(edge) => {
+            let properties = edge
+                .properties
+                .iter()
+                .flatten()
+                .map(|(key, value)| {
+                    let property = typed(
+                        "g:Property",
+                        json!({ "key": key, "value": graphson_value(value) }),
+                    );
+                    (key.clone(), property)
+                })
+                .collect::<Map<_, _>>();
+            typed(
+                "g:Edge",
+                json!({
+                    "id": item.uuid(),
+                    "label": edge.label,
+                    "outV": uuid::Uuid::from_u128(edge.from_node).to_string(),
+                    "inV": uuid::Uuid::from_u128(edge.to_node).to_string(),
+                    "properties": properties,
+                }),
+            )
+        }
+        TraversalVal::Vector(_) => {
+            typed("g:Vertex", json!({ "id": item.uuid(), "label": "vector" }))
+        }
+        TraversalVal::Value(value) => graphson_value(value),
+        _ => JsonValue::Null,
+    }
+}
+
+fn graphson_value(value: &Value) -> JsonValue {
+    match value {
+        Value::String(s) => JsonValue::String(s.clone()),
+        Value::Boolean(b) => JsonValue::Bool(*b),
+        Value::I8(i) => typed("g:Int32", json!(i)),
+        Value::I16(i) => typed("g:Int32", json!(i)),
+        Value::I32(i) => typed("g:Int32", json!(i)),
+        Value::U8(u) => typed("g:Int32", json!(u)),
+        Value::U16(u) => typed("g:Int32", json!(u)),
+        Value::I64(i) => typed("g:Int64", json!(i)),
+        Value::U32(u) => typed("g:Int64", json!(u)),
+        Value::U64(u) => typed("g:Int64", json!(u)),
+        Value::U128(u) => typed("gx:BigInteger", JsonValue::String(u.to_string())),
+        Value::F32(f) => typed(
+            "g:Float",
+            Number::from_f64(*f as f64).map_or(JsonValue::Null, JsonValue::Number),
+        ),
+        Value::F64(f) => typed(
+            "g:Double",
+            Number::from_f64(*f).map_or(JsonValue::Null, JsonValue::Number),
+        ),
+        Value::DateTime(d) => typed("g:Date", json!(d.timestamp_millis())),
+        Value::Array(values) => g_list(values.iter().map(graphson_value).collect()),
+        Value::Object(fields) => g_map(
+            fields
+                .iter()
+                .map(|(key, value)| (JsonValue::String(key.clone()), graphson_value(value)))
+                .collect(),
+        ),
+        Value::Empty => JsonValue::Null,
+    }
+}
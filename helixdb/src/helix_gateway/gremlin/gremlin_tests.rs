@@ -0,0 +1,285 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+            ops::{
+                g::G,
+                source::{
+                    add_e::{AddEAdapter, EdgeType},
+                    add_n::AddNAdapter,
+                },
+                tr_val::{Traversable, TraversalVal},
+            },
+        },
+        types::GraphError,
+    },
+    helix_gateway::gremlin::gremlin::{self, parse_bytecode, parse_script, Step, GREMLIN_PATH},
+    props,
+    protocol::{request::Request, response::Response, value::Value},
+};
+
+/// Alice (30) follows Bob (25) and Carol (35), who both follow Dave (40)
+fn graph() -> Arc<HelixGraphEngine> {
+    let engine = Arc::new(HelixGraphEngine::new(HelixGraphEngineOpts::in_memory()).unwrap());
+    let storage = &engine.storage;
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let mut user = |name: &str, age: i64| {
+        G::new_mut(Arc::clone(storage), &mut txn)
+            .add_n("User", Some(props! { "name" => name, "age" => age }), None)
+            .collect_to_val()
+            .id()
+    };
+    let (alice, bob, carol, dave) = (
+        user("alice", 30),
+        user("bob", 25),
+        user("carol", 35),
+        user("dave", 40),
+    );
+    for (from, to) in [(alice, bob), (alice, carol), (bob, dave), (carol, dave)] {
+        G::new_mut(Arc::clone(storage), &mut txn)
+            .add_e("Follows", None, from, to, true, EdgeType::Node)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+    engine
+}
+
+fn run(
+    engine: &HelixGraphEngine,
+    script: &str,
+    bindings: JsonValue,
+) -> Result<Vec<Value>, GraphError> {
+    let bindings: HashMap<String, JsonValue> = serde_json::from_value(bindings).unwrap();
+    run_steps(engine, &parse_script(script, &bindings)?)
+}
+
+fn run_steps(engine: &HelixGraphEngine, steps: &[Step]) -> Result<Vec<Value>, GraphError> {
+    let txn = engine.storage.graph_env.read_txn()?;
+    let values = gremlin::traverse(&engine.storage, &txn, steps)?
+        .map(|item| match item? {
+            TraversalVal::Value(value) => Ok(value),
+            item => Ok(Value::String(item.label().to_string())),
+        })
+        .collect();
+    values
+}
+
+/// The string values of a traversal, sorted
+fn strings(engine: &HelixGraphEngine, script: &str) -> Vec<String> {
+    let mut strings = run(engine, script, json!({}))
+        .unwrap()
+        .into_iter()
+        .map(|value| match value {
+            Value::String(s) => s,
+            value => panic!("not a string: {:?}", value),
+        })
+        .collect::<Vec<_>>();
+    strings.sort();
+    strings
+}
+
+fn error(engine: &HelixGraphEngine, script: &str) -> String {
+    match run(engine, script, json!({})) {
+        Ok(values) => panic!("{} succeeded with {:?}", script, values),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn test_has_out_chain() {
+    let engine = graph();
+    assert_eq!(
+        strings(
+            &engine,
+            "g.V().has('User', 'name', 'alice').out('Follows').values('name')"
+        ),
+        ["bob", "carol"]
+    );
+    assert_eq!(
+        strings(
+            &engine,
+            "g.V().has('name', 'dave').in('Follows').values('name')"
+        ),
+        ["bob", "carol"]
+    );
+    assert_eq!(
+        strings(
+            &engine,
+            "g.V().hasLabel('User').has('name', 'alice').out('Follows').out('Follows').dedup().values('name')"
+        ),
+        ["dave"]
+    );
+}
+
+#[test]
+fn test_has_predicates() {
+    let engine = graph();
+    assert_eq!(
+        strings(&engine, "g.V().has('User', 'age', gt(30)).values('name')"),
+        ["carol", "dave"]
+    );
+    assert_eq!(
+        strings(
+            &engine,
+            "g.V().has('age', P.within(25, 40)).out('Follows').values('name')"
+        ),
+        ["dave"]
+    );
+    assert_eq!(
+        strings(
+            &engine,
+            "g.V().has('name', without('alice', 'dave')).has('age', lte(30)).values('name')"
+        ),
+        ["bob"]
+    );
+}
+
+#[test]
+fn test_edge_steps() {
+    let engine = graph();
+    assert_eq!(
+        strings(
+            &engine,
+            "g.V().has('name', 'alice').outE('Follows').inV().values('name')"
+        ),
+        ["bob", "carol"]
+    );
+    assert_eq!(
+        strings(
+            &engine,
+            "g.V().has('name', 'dave').inE('Follows').outV().values('name')"
+        ),
+        ["bob", "carol"]
+    );
+    assert_eq!(
+        run(&engine, "g.E().hasLabel('Follows').count()", json!({})).unwrap(),
+        [Value::I64(4)]
+    );
+}
+
+#[test]
+fn test_bindings_and_paging() {
+    let engine = graph();
+    let counted = run(
+        &engine,
+        "g.V().has('User', 'name', name).out('Follows').count()",
+        json!({ "name": "alice" }),
+    )
+    .unwrap();
+    assert_eq!(counted, [Value::I64(2)]);
+    assert_eq!(
+        run(
+            &engine,
+            "g.V().hasLabel('User').limit(3).count()",
+            json!({})
+        )
+        .unwrap(),
+        [Value::I64(3)]
+    );
+    assert_eq!(
+        run(
+            &engine,
+            "g.V().hasLabel('User').range(1, 3).count()",
+            json!({})
+        )
+        .unwrap(),
+        [Value::I64(2)]
+    );
+    assert!(error(&engine, "g.V().has('name', missing)").contains("Unbound variable missing"));
+}
+
+#[test]
+fn test_bytecode_matches_script() {
+    let engine = graph();
+    let gt_30 = json!({
+        "@type": "g:P",
+        "@value": { "predicate": "gt", "value": { "@type": "g:Int32", "@value": 30 } }
+    });
+    let bytecode = json!({
+        "@type": "g:Bytecode",
+        "@value": {
+            "step": [
+                ["V"],
+                ["has", "User", "age", gt_30],
+                ["out", "Follows"],
+                ["limit", { "@type": "g:Int64", "@value": 10 }],
+                ["values", "name"]
+            ]
+        }
+    });
+    let script = "g.V().has('User', 'age', gt(30)).out('Follows').limit(10).values('name')";
+    let from_bytecode = run_steps(&engine, &parse_bytecode(&bytecode).unwrap()).unwrap();
+    let from_script = run(&engine, script, json!({})).unwrap();
+    assert_eq!(from_bytecode, from_script);
+    assert_eq!(from_script, [Value::String("dave".to_string())]);
+}
+
+#[test]
+fn test_unsupported_steps_rejected() {
+    let engine = graph();
+    assert!(error(&engine, "g.V().out('Follows').path()").contains("Unsupported step path()"));
+    assert!(error(&engine, "g.V().repeat()").contains("Unsupported step repeat()"));
+    assert!(
+        error(&engine, "g.V().has('name', regex('a.*'))").contains("Unsupported predicate regex")
+    );
+    assert!(error(&engine, "g.out()").contains("start with V() or E()"));
+    // the steps without arguments reject any
+    assert!(error(&engine, "g.V().count(1)").contains("count"));
+    // and writes are rejected by the read-only endpoint
+    assert!(error(&engine, "g.V().has('name', 'alice').drop()").contains("read-only"));
+    assert!(error(&engine, "g.V().addV('User')").contains("read-only"));
+    // as are scripts of more than a single traversal
+    assert!(error(&engine, "g.V(); g.E()").contains("a single traversal"));
+
+    let bytecode = json!({ "source": [["withSideEffect", "a", 1]], "step": [["V"]] });
+    assert!(parse_bytecode(&bytecode).is_err());
+}
+
+#[test]
+fn test_endpoint_reports_errors_as_graphson() {
+    let engine = graph();
+    let request = |body: JsonValue| Request {
+        method: "POST".to_string(),
+        headers: HashMap::new(),
+        path: GREMLIN_PATH.to_string(),
+        query: None,
+        version: "HTTP/1.1".to_string(),
+        body: serde_json::to_vec(&body).unwrap(),
+        claims: None,
+    };
+
+    let mut response = Response::new();
+    gremlin::handle(
+        Arc::clone(&engine),
+        request(json!({ "gremlin": "g.V().has('name', 'alice').out('Follows').count()", "requestId": "r1" })),
+        &mut response,
+    )
+    .unwrap();
+    assert_eq!(response.status, 200);
+    let body: JsonValue = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(body["requestId"], "r1");
+    assert_eq!(body["status"]["code"], 200);
+    assert_eq!(
+        body["result"]["data"]["@value"].as_array().unwrap().len(),
+        1
+    );
+
+    let mut response = Response::new();
+    gremlin::handle(
+        Arc::clone(&engine),
+        request(json!({ "gremlin": "g.V().path()" })),
+        &mut response,
+    )
+    .unwrap();
+    assert_eq!(response.status, 500);
+    let body: JsonValue = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(body["status"]["code"], 597);
+    assert!(body["status"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Unsupported step path()"));
+}
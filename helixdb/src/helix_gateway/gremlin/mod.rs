@@ -0,0 +1,4 @@
+pub mod gremlin;
+
+#[cfg(test)]
+mod gremlin_tests;
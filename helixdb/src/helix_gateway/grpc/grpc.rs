@@ -0,0 +1,192 @@
+use crate::helix_engine::{graph_core::graph_core::HelixGraphEngine, types::GraphError};
+use crate::helix_gateway::router::router::{set_error, HelixRouter};
+use crate::protocol::{request::Request, response::Response};
+use futures_util::stream::{self, Stream};
+use prost_types::{value::Kind, ListValue, Struct};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::{collections::HashMap, net::SocketAddr, pin::Pin, sync::Arc};
+use tonic::{Code, Status};
+
+pub mod proto {
+    tonic::include_proto!("helix");
+}
+
+use proto::{
+    helix_server::{Helix, HelixServer},
+    ExecuteRequest, ReturnValue,
+};
+
+/// Serves the queries of the router over gRPC, for clients of other languages that
+/// want typed access to them.
+///
+/// Queries go through the same authentication, middleware and handlers as the ones sent
+/// over HTTP, with the request metadata standing in for the headers. The connection
+/// limits of the gateway don't apply, tonic limits the size of messages instead.
+pub struct GrpcService {
+    graph: Arc<HelixGraphEngine>,
+    router: Arc<HelixRouter>,
+}
+
+impl GrpcService {
+    pub fn new(graph: Arc<HelixGraphEngine>, router: Arc<HelixRouter>) -> Self {
+        Self { graph, router }
+    }
+
+    /// Serves the service on the address until the server fails
+    pub async fn serve(self, address: SocketAddr) -> Result<(), GraphError> {
+        tonic::transport::Server::builder()
+            .add_service(HelixServer::new(self))
+            .serve(address)
+            .await
+            .map_err(|e| GraphError::New(format!("gRPC server failed: {}", e)))
+    }
+
+    /// Runs the query through the router like a `POST /<query_name>` request
+    async fn run(&self, request: Request) -> Result<Response, Status> {
+        let graph = Arc::clone(&self.graph);
+        if self.router.is_async_route(&request) {
+            return Ok(self
+                .router
+                .handle_async(graph, request)
+                .await
+                .unwrap_or_else(|e| {
+                    let mut response = Response::new();
+                    set_error(&mut response, e);
+                    response
+                }));
+        }
+        // handlers block on the graph, so they are kept off the runtime's workers
+        let router = Arc::clone(&self.router);
+        tokio::task::spawn_blocking(move || router.dispatch(graph, request))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+#[tonic::async_trait]
+impl Helix for GrpcService {
+    type ExecuteStream = Pin<Box<dyn Stream<Item = Result<ReturnValue, Status>> + Send>>;
+
+    async fn execute(
+        &self,
+        request: tonic::Request<ExecuteRequest>,
+    ) -> Result<tonic::Response<Self::ExecuteStream>, Status> {
+        let (metadata, _, execute) = request.into_parts();
+        let headers = metadata
+            .into_headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                Some((name.as_str().to_string(), value.to_string()))
+            })
+            .collect::<HashMap<_, _>>();
+        let params = JsonValue::Object(execute.params.map(struct_to_json).unwrap_or_default());
+        let request = Request {
+            method: "POST".to_string(),
+            headers,
+            path: format!("/{}", execute.query_name),
+            query: None,
+            version: "HTTP/2".to_string(),
+            body: serde_json::to_vec(&params).map_err(|e| Status::internal(e.to_string()))?,
+            claims: None,
+        };
+
+        let response = self.run(request).await?;
+        if response.status != 200 {
+            return Err(status(&response));
+        }
+        let body: JsonValue = serde_json::from_slice(&response.body)
+            .map_err(|e| Status::internal(format!("Query didn't return JSON: {}", e)))?;
+        let values = return_values(body).into_iter().map(Ok);
+        Ok(tonic::Response::new(Box::pin(stream::iter(values))))
+    }
+}
+
+/// The gRPC status of a response of the router with an error status code
+fn status(response: &Response) -> Status {
+    let code = match response.status {
+        400 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::NotFound,
+        409 => Code::AlreadyExists,
+        413 => Code::OutOfRange,
+        421 | 503 => Code::Unavailable,
+        422 => Code::FailedPrecondition,
+        429 => Code::ResourceExhausted,
+        _ => Code::Internal,
+    };
+    Status::new(code, String::from_utf8_lossy(&response.body).trim())
+}
+
+/// Splits the object returned by a query into its values, and returned lists into
+/// their items
+fn return_values(body: JsonValue) -> Vec<ReturnValue> {
+    let JsonValue::Object(object) = body else {
+        return vec![ReturnValue {
+            name: String::new(),
+            value: Some(json_to_value(body)),
+        }];
+    };
+    let mut values = Vec::new();
+    for (name, value) in object {
+        match value {
+            JsonValue::Array(items) => values.extend(items.into_iter().map(|item| ReturnValue {
+                name: name.clone(),
+                value: Some(json_to_value(item)),
+            })),
+            value => values.push(ReturnValue {
+                name,
+                value: Some(json_to_value(value)),
+            }),
+        }
+    }
+    values
+}
+
+fn json_to_value(value: JsonValue) -> prost_types::Value {
+    let kind = match value {
+        JsonValue::Null => Kind::NullValue(0),
+        JsonValue::Bool(b) => Kind::BoolValue(b),
+        JsonValue::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or_default()),
+        JsonValue::String(s) => Kind::StringValue(s),
+        JsonValue::Array(items) => Kind::ListValue(ListValue {
+            values: items.into_iter().map(json_to_value).collect(),
+        }),
+        JsonValue::Object(object) => Kind::StructValue(Struct {
+            fields: object
+                .into_iter()
+                .map(|(key, value)| (key, json_to_value(value)))
+                .collect(),
+        }),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
+
+fn struct_to_json(object: Struct) -> Map<String, JsonValue> {
+    object
+        .fields
+        .into_iter()
+        .map(|(key, value)| (key, value_to_json(value)))
+        .collect()
+}
+
+/// Numbers of protobuf values are all doubles, whole ones are passed on as integers so
+/// they can fill the integer parameters of the query
+fn value_to_json(value: prost_types::Value) -> JsonValue {
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => JsonValue::Null,
+        Some(Kind::BoolValue(b)) => JsonValue::Bool(b),
+        Some(Kind::NumberValue(n)) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+            JsonValue::Number(Number::from(n as i64))
+        }
+        Some(Kind::NumberValue(n)) => Number::from_f64(n)
+            .map(JsonValue::Number)
+            .unwrap_or(JsonValue::Null),
+        Some(Kind::StringValue(s)) => JsonValue::String(s),
+        Some(Kind::ListValue(list)) => {
+            JsonValue::Array(list.values.into_iter().map(value_to_json).collect())
+        }
+        Some(Kind::StructValue(object)) => JsonValue::Object(struct_to_json(object)),
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use prost_types::{value::Kind, Struct};
+use serde_json::{json, Value as JsonValue};
+use tonic::{transport::Channel, Code, Status};
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::AuthConfig,
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+        },
+        types::GraphError,
+    },
+    helix_gateway::{
+        auth::auth::{Authenticator, API_KEY_HEADER},
+        grpc::grpc::{
+            proto::{helix_client::HelixClient, ExecuteRequest},
+            GrpcService,
+        },
+        router::router::{HandlerInput, HelixRouter},
+    },
+    protocol::response::Response,
+};
+
+const API_KEY: &str = "test-key";
+
+fn greet(input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {
+    let params: JsonValue = sonic_rs::from_slice(&input.request.body)?;
+    response.body = sonic_rs::to_vec(&json!({
+        "greeting": format!("hello {}", params["name"].as_str().unwrap_or_default()),
+        "times": params["times"].as_i64(),
+        "friends": ["alice", "carol"],
+    }))?;
+    Ok(())
+}
+
+/// Serves the router over gRPC on a free port, requiring the API key
+async fn serve() -> HelixClient<Channel> {
+    let auth = Authenticator::new(&AuthConfig {
+        api_keys: vec![API_KEY.to_string()],
+        ..AuthConfig::default()
+    })
+    .unwrap();
+    let mut router = HelixRouter::new(None, None).with_auth(auth);
+    router.add_route("POST", "/greet", greet);
+    let graph = Arc::new(HelixGraphEngine::new(HelixGraphEngineOpts::in_memory()).unwrap());
+
+    let addr: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    tokio::spawn(GrpcService::new(graph, Arc::new(router)).serve(addr));
+    for _ in 0..50 {
+        if let Ok(client) = HelixClient::connect(format!("http://{}", addr)).await {
+            return client;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("gRPC server should be up");
+}
+
+/// Runs the query, collecting the streamed values by name
+async fn execute(
+    client: &mut HelixClient<Channel>,
+    api_key: Option<&str>,
+    query_name: &str,
+    params: Struct,
+) -> Result<BTreeMap<String, Vec<Kind>>, Status> {
+    let mut request = tonic::Request::new(ExecuteRequest {
+        query_name: query_name.to_string(),
+        params: Some(params),
+    });
+    if let Some(api_key) = api_key {
+        request
+            .metadata_mut()
+            .insert(API_KEY_HEADER, api_key.parse().unwrap());
+    }
+    let mut stream = client.execute(request).await?.into_inner();
+    let mut values = BTreeMap::<String, Vec<Kind>>::new();
+    while let Some(value) = stream.message().await? {
+        values
+            .entry(value.name)
+            .or_default()
+            .push(value.value.unwrap().kind.unwrap());
+    }
+    Ok(values)
+}
+
+fn params() -> Struct {
+    let value = |kind| prost_types::Value { kind: Some(kind) };
+    Struct {
+        fields: [
+            (
+                "name".to_string(),
+                value(Kind::StringValue("bob".to_string())),
+            ),
+            ("times".to_string(), value(Kind::NumberValue(3.0))),
+        ]
+        .into_iter()
+        .collect(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_route_called_through_service() {
+    let mut client = serve().await;
+    let values = execute(&mut client, Some(API_KEY), "greet", params())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        values["greeting"],
+        [Kind::StringValue("hello bob".to_string())]
+    );
+    // whole numbers reach the handler as integers
+    assert_eq!(values["times"], [Kind::NumberValue(3.0)]);
+    // lists are streamed an item at a time
+    assert_eq!(
+        values["friends"],
+        [
+            Kind::StringValue("alice".to_string()),
+            Kind::StringValue("carol".to_string())
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_service_enforces_auth() {
+    let mut client = serve().await;
+    for api_key in [None, Some("wrong-key")] {
+        let status = execute(&mut client, api_key, "greet", params())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated, "{:?}", api_key);
+    }
+
+    // authenticated requests get past auth to the router
+    let status = execute(&mut client, Some(API_KEY), "unknown", Struct::default())
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
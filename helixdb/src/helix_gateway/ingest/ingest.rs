@@ -0,0 +1,492 @@
+use crate::helix_engine::{
+    graph_core::{
+        graph_core::HelixGraphEngine,
+        ops::{
+            g::G,
+            source::{
+                add_e::{AddEAdapter, EdgeType},
+                add_n::AddNAdapter,
+            },
+            tr_val::{Traversable, TraversalVal},
+            util::update::UpdateAdapter,
+            vectors::insert::InsertVAdapter,
+        },
+    },
+    ingest_jobs::ingest_jobs::{IngestJob, JobStatus},
+    stats::stats::Direction,
+    storage_core::storage_core::HelixGraphStorage,
+    types::GraphError,
+    vector_core::vector::HVector,
+};
+use crate::helix_gateway::ingest::sync::find_node;
+use crate::helix_storage::heed3::{RoTxn, RwTxn};
+use crate::protocol::{items::Node, request::Request, response::Response, value::Value};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Path of the endpoint loading batches of nodes, edges and vectors into the graph
+pub const INGEST_PATH: &str = "/ingest";
+/// Path of the ingestion jobs, `/ingest/jobs/{id}` being the path of one and
+/// `/ingest/jobs/{id}/keys` the path of the keys of the nodes it loaded
+pub const INGEST_JOBS_PATH: &str = "/ingest/jobs";
+
+/// Items loaded together in one write transaction.
+///
+/// Edges connect nodes by their uuid, the nodes must already be in the graph or be
+/// loaded by an earlier batch.
+///
+/// Nodes with an `external_id` are upserted on that property, and `unique` edges are
+/// only added once between two nodes, so loading the same items again doesn't
+/// duplicate them.
+///
+/// ```json
+/// {
+///   "nodes": [{ "label": "User", "properties": { "name": "Alice", "email": "alice@example.com" },
+///               "external_id": "email" }],
+///   "edges": [{ "label": "Follows", "from": "<uuid>", "to": "<uuid>", "unique": true }],
+///   "vectors": [{ "label": "Embedding", "data": [0.1, 0.2], "properties": {} }],
+///   "job": { "id": "<uuid>", "source": "mapping.json", "positions": { "users.csv": 1000 } }
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct IngestBatch {
+    #[serde(default)]
+    pub nodes: Vec<IngestNode>,
+    #[serde(default)]
+    pub edges: Vec<IngestEdge>,
+    #[serde(default)]
+    pub vectors: Vec<IngestVector>,
+    /// Progress of the job the batch is part of, recorded along with its items
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<JobCheckpoint>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IngestNode {
+    pub label: String,
+    #[serde(default)]
+    pub properties: HashMap<String, Value>,
+    /// Property identifying the node in its source, which has to have a unique index.
+    /// The node of the label already holding the value is updated with the properties
+    /// instead of a new one being added.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IngestEdge {
+    pub label: String,
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub properties: HashMap<String, Value>,
+    /// Whether the edge is left out if its nodes are already joined by an edge of its
+    /// label, the uuid of that edge being returned instead
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unique: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IngestVector {
+    pub label: String,
+    pub data: Vec<f64>,
+    #[serde(default)]
+    pub properties: HashMap<String, Value>,
+}
+
+/// Where the items of a batch were read from, for the job to be resumed after them.
+///
+/// The job is started by its first batch.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JobCheckpoint {
+    pub id: String,
+    #[serde(default)]
+    pub source: String,
+    /// Stream => position of the last row read from it for the batch
+    #[serde(default)]
+    pub positions: BTreeMap<String, u64>,
+    /// `(label, id)` in the source of each node of the batch, for the edges of the job to
+    /// join on once it is resumed
+    #[serde(default)]
+    pub keys: Vec<Option<(String, String)>>,
+    /// Rows read for the batch that couldn't be turned into items
+    #[serde(default)]
+    pub errors: Vec<String>,
+    /// Whether the batch is the last of the job
+    #[serde(default)]
+    pub done: bool,
+}
+
+impl JobCheckpoint {
+    pub fn new(id: &str, source: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            source: source.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Moves the position of a stream to a row read for the batch
+    pub fn advance(&mut self, stream: &str, position: u64) {
+        match self.positions.get_mut(stream) {
+            Some(current) => *current = position,
+            None => {
+                self.positions.insert(stream.to_string(), position);
+            }
+        }
+    }
+
+    /// Whether there is progress to record even without items to load
+    pub fn is_pending(&self) -> bool {
+        !self.positions.is_empty() || !self.errors.is_empty() || self.done
+    }
+
+    /// Starts over for the next batch
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.keys.clear();
+        self.errors.clear();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    Node,
+    Edge,
+    Vector,
+}
+
+/// An item of a batch that couldn't be loaded
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IngestError {
+    pub kind: ItemKind,
+    /// Position of the item in its list of the batch
+    pub index: usize,
+    pub error: String,
+}
+
+/// The uuids of the items of a batch in the order they were sent, `None` for those
+/// that failed, along with why they failed
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct IngestResult {
+    pub nodes: Vec<Option<String>>,
+    pub edges: Vec<Option<String>>,
+    pub vectors: Vec<Option<String>>,
+    pub errors: Vec<IngestError>,
+}
+
+/// Loads a batch into the graph in one write transaction.
+///
+/// An item that fails doesn't fail the batch: the transaction is aborted and the batch
+/// loaded again without it, so the failed items are the only ones left out. Nodes are
+/// added to the secondary indices configured for the properties they have, and the
+/// uuid of an upserted node is the one of the node it updated.
+///
+/// The checkpoint of the batch's job is recorded in the same transaction, so a resumed
+/// job neither skips nor loads again the items of a batch.
+pub fn ingest(
+    storage: &Arc<HelixGraphStorage>,
+    batch: &IngestBatch,
+) -> Result<IngestResult, GraphError> {
+    let mut errors = Vec::new();
+    let mut skipped = HashSet::new();
+    loop {
+        let mut txn = storage.graph_env.write_txn()?;
+        match insert(storage, &mut txn, batch, &skipped) {
+            Ok(mut result) => {
+                result.errors = errors;
+                if let Some(checkpoint) = &batch.job {
+                    record_job(storage, &mut txn, batch, checkpoint, &result)?;
+                }
+                storage.wal.commit(txn)?;
+                return Ok(result);
+            }
+            Err((kind, index, e)) => {
+                txn.abort();
+                skipped.insert((kind, index));
+                errors.push(IngestError {
+                    kind,
+                    index,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Inserts the items of a batch that aren't skipped, stopping at the first that fails
+fn insert(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &mut RwTxn,
+    batch: &IngestBatch,
+    skipped: &HashSet<(ItemKind, usize)>,
+) -> Result<IngestResult, (ItemKind, usize, GraphError)> {
+    let mut result = IngestResult::default();
+
+    for (i, node) in batch.nodes.iter().enumerate() {
+        if skipped.contains(&(ItemKind::Node, i)) {
+            result.nodes.push(None);
+            continue;
+        }
+        let item = match upserted_node(storage, txn, node) {
+            Ok(Some(existing)) => {
+                G::new_mut_from(Arc::clone(storage), txn, vec![TraversalVal::Node(existing)])
+                    .update(properties(&node.properties))
+                    .try_collect_to::<Vec<_>>()
+            }
+            Ok(None) => {
+                let indices = storage
+                    .secondary_indices
+                    .keys()
+                    .filter(|index| node.properties.contains_key(index.as_str()))
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                G::new_mut(Arc::clone(storage), txn)
+                    .add_n(&node.label, properties(&node.properties), Some(&indices))
+                    .try_collect_to::<Vec<_>>()
+            }
+            Err(e) => Err(e),
+        };
+        result
+            .nodes
+            .push(Some(inserted(item).map_err(|e| (ItemKind::Node, i, e))?));
+    }
+
+    for (i, vector) in batch.vectors.iter().enumerate() {
+        if skipped.contains(&(ItemKind::Vector, i)) {
+            result.vectors.push(None);
+            continue;
+        }
+        let item = G::new_mut(Arc::clone(storage), txn)
+            .insert_v::<fn(&HVector, &RoTxn) -> bool>(
+                &vector.data,
+                &vector.label,
+                properties(&vector.properties),
+            )
+            .try_collect_to::<Vec<_>>();
+        result
+            .vectors
+            .push(Some(inserted(item).map_err(|e| (ItemKind::Vector, i, e))?));
+    }
+
+    for (i, edge) in batch.edges.iter().enumerate() {
+        if skipped.contains(&(ItemKind::Edge, i)) {
+            result.edges.push(None);
+            continue;
+        }
+        let add = |txn: &mut RwTxn| {
+            let from = parse_id(&edge.from)?;
+            let to = parse_id(&edge.to)?;
+            if edge.unique {
+                if let Some(id) = joining_edge(storage, txn, &edge.label, from, to)? {
+                    return Ok(uuid::Uuid::from_u128(id).to_string());
+                }
+            }
+            inserted(
+                G::new_mut(Arc::clone(storage), txn)
+                    .add_e(
+                        &edge.label,
+                        properties(&edge.properties),
+                        from,
+                        to,
+                        true,
+                        EdgeType::Node,
+                    )
+                    .try_collect_to::<Vec<_>>(),
+            )
+        };
+        result
+            .edges
+            .push(Some(add(txn).map_err(|e| (ItemKind::Edge, i, e))?));
+    }
+
+    Ok(result)
+}
+
+/// The node a node of a batch updates, if it has an external id that a node of its
+/// label already holds
+fn upserted_node(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    node: &IngestNode,
+) -> Result<Option<Node>, GraphError> {
+    let Some(key) = &node.external_id else {
+        return Ok(None);
+    };
+    if !storage.unique_indices.contains(key) {
+        return Err(GraphError::New(format!(
+            "{} has no unique index to upsert on",
+            key
+        )));
+    }
+    let value = node
+        .properties
+        .get(key)
+        .ok_or_else(|| GraphError::New(format!("No {} in the properties", key)))?;
+    find_node(storage, txn, &node.label, key, value)
+}
+
+/// The id of an edge of the label from one node to another, if there is one
+fn joining_edge(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    label: &str,
+    from: u128,
+    to: u128,
+) -> Result<Option<u128>, GraphError> {
+    let label = storage.dictionary.label_key(label);
+    for entry in storage.adjacency(txn, &from, &label, Direction::Out)? {
+        let (node_id, edge_id) = entry?;
+        if node_id == to {
+            return Ok(Some(edge_id));
+        }
+    }
+    Ok(None)
+}
+
+/// Adds what a batch loaded to the progress of its job
+fn record_job(
+    storage: &HelixGraphStorage,
+    txn: &mut RwTxn,
+    batch: &IngestBatch,
+    checkpoint: &JobCheckpoint,
+    result: &IngestResult,
+) -> Result<(), GraphError> {
+    let jobs = &storage.ingest_jobs;
+    let mut job = match jobs.get(txn, &checkpoint.id)? {
+        Some(job) => job,
+        None => IngestJob::new(&checkpoint.id, &checkpoint.source),
+    };
+    if job.status == JobStatus::Completed {
+        return Err(GraphError::New(format!(
+            "Ingestion job {} is already completed",
+            job.id
+        )));
+    }
+
+    for (key, id) in checkpoint.keys.iter().zip(result.nodes.iter()) {
+        if let (Some(key), Some(id)) = (key, id) {
+            jobs.put_key(txn, &job.id, key, parse_id(id)?)?;
+        }
+    }
+    job.positions.extend(checkpoint.positions.clone());
+    job.batches += 1;
+    job.nodes += result.nodes.iter().flatten().count() as u64;
+    job.edges += result.edges.iter().flatten().count() as u64;
+    job.vectors += result.vectors.iter().flatten().count() as u64;
+    for error in checkpoint.errors.iter() {
+        job.push_error(error.clone());
+    }
+    for error in result.errors.iter() {
+        let label = match error.kind {
+            ItemKind::Node => &batch.nodes[error.index].label,
+            ItemKind::Edge => &batch.edges[error.index].label,
+            ItemKind::Vector => &batch.vectors[error.index].label,
+        };
+        job.push_error(format!("{}: {}", label, error.error));
+    }
+    if checkpoint.done {
+        job.status = JobStatus::Completed;
+    }
+    job.updated_at = chrono::Utc::now().timestamp_millis();
+    jobs.put(txn, &job)
+}
+
+/// Loads the batch of the request and responds with its [`IngestResult`]
+pub fn handle(
+    graph_access: Arc<HelixGraphEngine>,
+    request: Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let batch: IngestBatch = sonic_rs::from_slice(&request.body)?;
+    let result = ingest(&graph_access.storage, &batch)?;
+    response
+        .headers
+        .insert("Content-Type".to_string(), "application/json".to_string());
+    response.body = sonic_rs::to_vec(&result)?;
+    Ok(())
+}
+
+/// Responds with every ingestion job, one job or the keys of the nodes it loaded, by
+/// the path of the request
+pub fn handle_jobs(
+    graph_access: Arc<HelixGraphEngine>,
+    request: Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    match jobs(&graph_access.storage, &request.path)? {
+        Some(body) => {
+            response
+                .headers
+                .insert("Content-Type".to_string(), "application/json".to_string());
+            response.body = body;
+        }
+        None => response.status = 404,
+    }
+    Ok(())
+}
+
+/// The JSON of the jobs at a path under [`INGEST_JOBS_PATH`], `None` if there is no job
+/// at the path
+pub fn jobs(storage: &HelixGraphStorage, path: &str) -> Result<Option<Vec<u8>>, GraphError> {
+    let txn = storage.graph_env.read_txn()?;
+    let path = path.trim_end_matches('/');
+    let Some(path) = path.strip_prefix(INGEST_JOBS_PATH) else {
+        return Ok(None);
+    };
+    if path.is_empty() {
+        return Ok(Some(sonic_rs::to_vec(&storage.ingest_jobs.list(&txn)?)?));
+    }
+    let path = path.trim_start_matches('/');
+    let (id, keys) = match path.strip_suffix("/keys") {
+        Some(id) => (id, true),
+        None => (path, false),
+    };
+    let Some(job) = storage.ingest_jobs.get(&txn, id)? else {
+        return Ok(None);
+    };
+    let body = match keys {
+        true => sonic_rs::to_vec(
+            &storage
+                .ingest_jobs
+                .keys(&txn, id)?
+                .into_iter()
+                .map(|(key, id)| (key, uuid::Uuid::from_u128(id).to_string()))
+                .collect::<Vec<_>>(),
+        )?,
+        false => sonic_rs::to_vec(&job)?,
+    };
+    Ok(Some(body))
+}
+
+fn properties(properties: &HashMap<String, Value>) -> Option<Vec<(String, Value)>> {
+    match properties.is_empty() {
+        true => None,
+        false => Some(
+            properties
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ),
+    }
+}
+
+/// The uuid of an inserted item, or why it wasn't inserted
+pub(crate) fn inserted(items: Result<Vec<TraversalVal>, GraphError>) -> Result<String, GraphError> {
+    match items?.first() {
+        Some(item @ (TraversalVal::Node(_) | TraversalVal::Edge(_) | TraversalVal::Vector(_))) => {
+            Ok(item.uuid())
+        }
+        _ => Err(GraphError::New("The item wasn't inserted".to_string())),
+    }
+}
+
+fn parse_id(id: &str) -> Result<u128, GraphError> {
+    uuid::Uuid::parse_str(id)
+        .map(|id| id.as_u128())
+        .map_err(|_| GraphError::New(format!("{} isn't a valid uuid", id)))
+}
@@ -0,0 +1,292 @@
+use crate::helix_engine::{
+    graph_core::{
+        graph_core::HelixGraphEngine,
+        ops::{
+            g::G,
+            source::{
+                add_e::{AddEAdapter, EdgeType},
+                add_n::AddNAdapter,
+            },
+            tr_val::TraversalVal,
+            util::update::UpdateAdapter,
+        },
+    },
+    stats::stats::Direction,
+    storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    types::GraphError,
+};
+use crate::helix_gateway::ingest::ingest::inserted;
+use crate::helix_storage::heed3::{RoTxn, RwTxn};
+use crate::protocol::{
+    filterable::Filterable, items::Node, request::Request, response::Response, value::Value,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Path of the endpoint applying the changes of a source database to the graph
+pub const SYNC_PATH: &str = "/sync";
+
+/// Changes applied together in one write transaction, in order.
+///
+/// Nodes are found by a key property rather than their uuid, so changes can be sent
+/// by a source that only knows its own keys, and applied more than once.
+///
+/// ```json
+/// {
+///   "changes": [
+///     { "op": "upsert", "label": "Users", "key": "id",
+///       "properties": { "id": 1, "name": "Alice" } },
+///     { "op": "upsert", "label": "Posts", "key": "id",
+///       "properties": { "id": 7, "author_id": 1 },
+///       "edges": [{ "label": "PostsToUsers",
+///                   "to": { "label": "Users", "key": "id", "value": 1 } }] },
+///     { "op": "delete", "label": "Users", "key": "id", "value": 2 }
+///   ]
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SyncBatch {
+    #[serde(default)]
+    pub changes: Vec<SyncChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SyncChange {
+    /// Adds the node with the value of `key` in `properties`, or merges `properties`
+    /// into the one already in the graph. Null properties are kept as empty values.
+    Upsert {
+        label: String,
+        key: String,
+        #[serde(default)]
+        properties: HashMap<String, Value>,
+        #[serde(default)]
+        edges: Vec<SyncEdge>,
+    },
+    /// Drops the node and its edges, if it is in the graph
+    Delete {
+        label: String,
+        key: String,
+        value: Value,
+    },
+}
+
+/// Replaces the outgoing edges of a label of an upserted node by one to `to`, or by
+/// none when `to` is null
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SyncEdge {
+    pub label: String,
+    #[serde(default)]
+    pub to: Option<NodeKey>,
+}
+
+/// A node by the value of its key property
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NodeKey {
+    pub label: String,
+    pub key: String,
+    pub value: Value,
+}
+
+/// A change of a batch that couldn't be applied
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SyncError {
+    /// Position of the change in the batch
+    pub index: usize,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SyncResult {
+    pub applied: usize,
+    pub errors: Vec<SyncError>,
+}
+
+/// Applies a batch of changes in one write transaction.
+///
+/// Like batches of the ingestion endpoint, a change that fails doesn't fail the batch:
+/// the transaction is aborted and the batch applied again without it.
+pub fn sync(storage: &Arc<HelixGraphStorage>, batch: &SyncBatch) -> Result<SyncResult, GraphError> {
+    let mut errors = Vec::new();
+    let mut skipped = HashSet::new();
+    'batch: loop {
+        let mut txn = storage.graph_env.write_txn()?;
+        for (i, change) in batch.changes.iter().enumerate() {
+            if skipped.contains(&i) {
+                continue;
+            }
+            if let Err(e) = apply(storage, &mut txn, change) {
+                txn.abort();
+                skipped.insert(i);
+                errors.push(SyncError {
+                    index: i,
+                    error: e.to_string(),
+                });
+                continue 'batch;
+            }
+        }
+        storage.wal.commit(txn)?;
+        return Ok(SyncResult {
+            applied: batch.changes.len() - errors.len(),
+            errors,
+        });
+    }
+}
+
+fn apply(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &mut RwTxn,
+    change: &SyncChange,
+) -> Result<(), GraphError> {
+    match change {
+        SyncChange::Upsert {
+            label,
+            key,
+            properties,
+            edges,
+        } => {
+            let value = properties
+                .get(key)
+                .filter(|value| **value != Value::Empty)
+                .ok_or_else(|| GraphError::New(format!("No {} in the properties", key)))?;
+            let id = match find_node(storage, txn, label, key, value)? {
+                Some(node) => inserted(
+                    G::new_mut_from(Arc::clone(storage), txn, vec![TraversalVal::Node(node)])
+                        .update(Some(
+                            properties
+                                .iter()
+                                .map(|(key, value)| (key.clone(), value.clone()))
+                                .collect(),
+                        ))
+                        .try_collect_to::<Vec<_>>(),
+                )?,
+                None => {
+                    let properties = properties
+                        .iter()
+                        .filter(|(_, value)| **value != Value::Empty)
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect::<Vec<_>>();
+                    let indices = storage
+                        .secondary_indices
+                        .keys()
+                        .filter(|index| properties.iter().any(|(key, _)| key == *index))
+                        .map(String::as_str)
+                        .collect::<Vec<_>>();
+                    inserted(
+                        G::new_mut(Arc::clone(storage), txn)
+                            .add_n(label, Some(properties), Some(&indices))
+                            .try_collect_to::<Vec<_>>(),
+                    )?
+                }
+            };
+            let id = uuid::Uuid::parse_str(&id)
+                .map_err(|e| GraphError::New(e.to_string()))?
+                .as_u128();
+            for edge in edges {
+                replace_edge(storage, txn, id, edge)?;
+            }
+            Ok(())
+        }
+        SyncChange::Delete { label, key, value } => {
+            if let Some(node) = find_node(storage, txn, label, key, value)? {
+                storage.drop_node(txn, &node.id)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Drops the outgoing edges of the label of a node, then adds the one of `edge`
+fn replace_edge(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &mut RwTxn,
+    from: u128,
+    edge: &SyncEdge,
+) -> Result<(), GraphError> {
+    let to = match &edge.to {
+        Some(to) => Some(
+            find_node(storage, txn, &to.label, &to.key, &to.value)?.ok_or_else(|| {
+                GraphError::New(format!(
+                    "No {} with {} {}",
+                    to.label,
+                    to.key,
+                    to.value.to_string()
+                ))
+            })?,
+        ),
+        None => None,
+    };
+
+    let label = storage.dictionary.label_key(&edge.label);
+    let mut edge_ids = Vec::new();
+    for entry in storage.adjacency(txn, &from, &label, Direction::Out)? {
+        let (_, edge_id) = entry?;
+        edge_ids.push(edge_id);
+    }
+    for edge_id in edge_ids {
+        storage.drop_edge(txn, &edge_id)?;
+    }
+
+    if let Some(to) = to {
+        inserted(
+            G::new_mut(Arc::clone(storage), txn)
+                .add_e(&edge.label, None, from, to.id, true, EdgeType::Node)
+                .try_collect_to::<Vec<_>>(),
+        )?;
+    }
+    Ok(())
+}
+
+/// The node of a label with a value of its key property.
+///
+/// The secondary index of the key is used when there is one, the nodes of the graph
+/// are scanned otherwise. Index entries aren't removed when a node is updated or
+/// dropped, so the node an entry points to is checked to still have the value.
+pub(crate) fn find_node(
+    storage: &HelixGraphStorage,
+    txn: &RoTxn,
+    label: &str,
+    key: &str,
+    value: &Value,
+) -> Result<Option<Node>, GraphError> {
+    let matches =
+        |node: &Node| node.label == label && node.check_property(key).is_ok_and(|v| v == value);
+    let Some(db) = storage.secondary_indices.get(key) else {
+        for node in storage.get_all_nodes(txn)? {
+            let node = node?;
+            if matches(&node) {
+                return Ok(Some(node));
+            }
+        }
+        return Ok(None);
+    };
+    if let Some(entries) = db.get_duplicates(txn, &bincode::serialize(value)?)? {
+        for entry in entries {
+            let (_, id) = entry?;
+            match storage.get_node(txn, &id) {
+                Ok(node) if matches(&node) => return Ok(Some(node)),
+                Ok(_) | Err(GraphError::NodeNotFound) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Applies the batch of the request and responds with its [`SyncResult`]
+pub fn handle(
+    graph_access: Arc<HelixGraphEngine>,
+    request: Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let batch: SyncBatch = sonic_rs::from_slice(&request.body)?;
+    let result = sync(&graph_access.storage, &batch)?;
+    response
+        .headers
+        .insert("Content-Type".to_string(), "application/json".to_string());
+    response.body = sonic_rs::to_vec(&result)?;
+    Ok(())
+}
@@ -7,11 +7,10 @@ use crate::helix_engine::graph_core::ops::source::add_e::EdgeType;
 use crate::helix_engine::graph_core::ops::source::e_from_type::{EFromType, EFromTypeAdapter};
 use crate::helix_engine::graph_core::ops::source::n_from_type::{NFromType, NFromTypeAdapter};
 use crate::helix_engine::graph_core::ops::tr_val::{Traversable, TraversalVal};
-use crate::helix_engine::storage_core::storage_core::HelixGraphStorage;
+use crate::helix_engine::stats::stats::Direction;
 use crate::helix_engine::types::GraphError;
 use crate::helix_gateway::mcp::mcp::{MCPConnection, McpBackend};
 use crate::helix_gateway::router::router::HandlerInput;
-use crate::protocol::label_hash::hash_label;
 use crate::protocol::response::Response;
 use get_routes::local_handler;
 use crate::helix_storage::heed3::RoTxn;
@@ -148,20 +147,14 @@ impl<'a> McpTools<'a> for McpBackend {
             .iter
             .clone()
             .filter_map(move |item| {
-                let edge_label_hash = hash_label(edge_label, None);
-                let prefix = HelixGraphStorage::out_edge_key(&item.id(), &edge_label_hash);
-                match db
-                    .out_edges_db
-                    .lazily_decode_data()
-                    .get_duplicates(&txn, &prefix)
-                {
-                    Ok(Some(iter)) => Some(OutNodesIterator {
+                let edge_label_hash = db.dictionary.label_key(edge_label);
+                match db.adjacency(txn, &item.id(), &edge_label_hash, Direction::Out) {
+                    Ok(iter) => Some(OutNodesIterator {
                         iter,
                         storage: Arc::clone(&db),
                         edge_type,
                         txn,
                     }),
-                    Ok(None) => None,
                     Err(e) => {
                         println!("{} Error getting out edges: {:?}", line!(), e);
                         // return Err(e);
@@ -193,19 +186,13 @@ impl<'a> McpTools<'a> for McpBackend {
             .iter
             .clone()
             .filter_map(move |item| {
-                let edge_label_hash = hash_label(edge_label, None);
-                let prefix = HelixGraphStorage::out_edge_key(&item.id(), &edge_label_hash);
-                match db
-                    .out_edges_db
-                    .lazily_decode_data()
-                    .get_duplicates(&txn, &prefix)
-                {
-                    Ok(Some(iter)) => Some(OutEdgesIterator {
+                let edge_label_hash = db.dictionary.label_key(edge_label);
+                match db.adjacency(txn, &item.id(), &edge_label_hash, Direction::Out) {
+                    Ok(iter) => Some(OutEdgesIterator {
                         iter,
                         storage: Arc::clone(&db),
                         txn,
                     }),
-                    Ok(None) => None,
                     Err(e) => {
                         println!("{} Error getting out edges: {:?}", line!(), e);
                         // return Err(e);
@@ -233,20 +220,14 @@ impl<'a> McpTools<'a> for McpBackend {
             .iter
             .clone()
             .filter_map(move |item| {
-                let edge_label_hash = hash_label(edge_label, None);
-                let prefix = HelixGraphStorage::in_edge_key(&item.id(), &edge_label_hash);
-                match db
-                    .in_edges_db
-                    .lazily_decode_data()
-                    .get_duplicates(&txn, &prefix)
-                {
-                    Ok(Some(iter)) => Some(InNodesIterator {
+                let edge_label_hash = db.dictionary.label_key(edge_label);
+                match db.adjacency(txn, &item.id(), &edge_label_hash, Direction::In) {
+                    Ok(iter) => Some(InNodesIterator {
                         iter,
                         storage: Arc::clone(&db),
                         edge_type,
                         txn,
                     }),
-                    Ok(None) => None,
                     Err(e) => {
                         println!("{} Error getting out edges: {:?}", line!(), e);
                         // return Err(e);
@@ -278,19 +259,13 @@ impl<'a> McpTools<'a> for McpBackend {
             .iter
             .clone()
             .filter_map(move |item| {
-                let edge_label_hash = hash_label(edge_label, None);
-                let prefix = HelixGraphStorage::in_edge_key(&item.id(), &edge_label_hash);
-                match db
-                    .in_edges_db
-                    .lazily_decode_data()
-                    .get_duplicates(&txn, &prefix)
-                {
-                    Ok(Some(iter)) => Some(InEdgesIterator {
+                let edge_label_hash = db.dictionary.label_key(edge_label);
+                match db.adjacency(txn, &item.id(), &edge_label_hash, Direction::In) {
+                    Ok(iter) => Some(InEdgesIterator {
                         iter,
                         storage: Arc::clone(&db),
                         txn,
                     }),
-                    Ok(None) => None,
                     Err(e) => {
                         println!("{} Error getting out edges: {:?}", line!(), e);
                         // return Err(e);
@@ -313,8 +288,14 @@ impl<'a> McpTools<'a> for McpBackend {
         let db = Arc::clone(&self.db);
 
         let iter = NFromType {
-            iter: db.nodes_db.lazily_decode_data().iter(txn).unwrap(),
+            iter: db
+                .nodes_db
+                .lazily_decode_data()
+                .range(txn, &db.namespaces.id_range(node_type))
+                .unwrap(),
             label: node_type,
+            storage: Arc::clone(&db),
+            txn,
         };
 
         let result = iter.take(100).collect::<Result<Vec<_>, _>>();
@@ -330,8 +311,14 @@ impl<'a> McpTools<'a> for McpBackend {
         let db = Arc::clone(&self.db);
 
         let iter = EFromType {
-            iter: db.edges_db.lazily_decode_data().iter(txn).unwrap(),
+            iter: db
+                .edges_db
+                .lazily_decode_data()
+                .range(txn, &db.namespaces.id_range(edge_type))
+                .unwrap(),
             label: edge_type,
+            storage: Arc::clone(&db),
+            txn,
         };
 
         let result = iter.take(100).collect::<Result<Vec<_>, _>>();
@@ -1,5 +1,19 @@
+pub mod auth;
+#[cfg(feature = "bolt")]
+pub mod bolt;
 pub mod connection;
+pub mod cursor_cache;
 pub mod gateway;
+#[cfg(test)]
+mod gateway_tests;
+pub mod gremlin;
+pub mod ingest;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod router;
+pub mod status;
+pub mod subscription;
 pub mod thread_pool;
-pub mod mcp;
\ No newline at end of file
+pub mod mcp;
+#[cfg(feature = "compiler")]
+pub mod query;
@@ -0,0 +1,4 @@
+pub mod query;
+
+#[cfg(test)]
+mod query_tests;
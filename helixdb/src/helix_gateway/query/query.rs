@@ -0,0 +1,252 @@
+use crate::helix_engine::{
+    graph_core::{graph_core::HelixGraphEngine, interpreter::Interpreter},
+    types::GraphError,
+};
+use crate::helix_gateway::auth::auth;
+use crate::helixc::{
+    analyzer::analyzer::{analyze, Diagnostic},
+    generator::generator_types::{Query, Source as GeneratedSource},
+    parser::helix_parser::{Content, HelixParser, HxFile, Source},
+};
+use crate::protocol::{request::Request, response::Response};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// Path of the endpoint running ad-hoc HelixQL queries
+pub const QUERY_PATH: &str = "/query";
+
+/// Body of a request to the query endpoint, the source of a single query and its
+/// parameters.
+///
+/// ```json
+/// { "query": "QUERY getUser(name: String) =>\n    user <- N<User>({name: name})\n    RETURN user",
+///   "params": { "name": "John" } }
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct QueryRequest {
+    pub query: String,
+    #[serde(default)]
+    pub params: JsonValue,
+}
+
+/// Path of the endpoint replacing the pushed queries of the instance
+pub const PUSH_PATH: &str = "/push";
+
+/// Body of a request to the push endpoint, the `.hx` query files of a project without
+/// its schema.
+///
+/// ```json
+/// { "files": [{ "name": "queries.hx", "content": "QUERY getUser(name: String) =>\n    ..." }] }
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct PushRequest {
+    pub files: Vec<PushedFile>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PushedFile {
+    pub name: String,
+    pub content: String,
+}
+
+/// Queries pushed to a running instance, served at `POST /<name>` through the
+/// interpreter in place of the deployed query of the same name.
+///
+/// Every push replaces the whole set, which is swapped in at once so requests never
+/// see queries of two pushes. Pushed queries last until the instance restarts,
+/// redeploy the project to build them into it.
+#[derive(Default)]
+pub struct PushedQueries {
+    queries: RwLock<Arc<HashMap<String, Arc<Query>>>>,
+}
+
+impl PushedQueries {
+    /// The pushed query of the given name
+    pub fn get(&self, name: &str) -> Option<Arc<Query>> {
+        self.queries.read().unwrap().get(name).cloned()
+    }
+
+    /// Checks the pushed files against the schema of the instance and swaps their
+    /// queries in, answering with their names.
+    ///
+    /// Files the analyzer rejects are answered with a 400 listing its diagnostics and
+    /// leave the queries being served as they are. Schemas can't be pushed, as the
+    /// data of the instance is migrated to its schema when it is deployed.
+    pub fn push(
+        &self,
+        schema: &str,
+        request: Request,
+        response: &mut Response,
+    ) -> Result<(), GraphError> {
+        let body: PushRequest = serde_json::from_slice(&request.body)
+            .map_err(|e| GraphError::New(format!("Invalid push request: {}", e)))?;
+        let files = || {
+            body.files
+                .iter()
+                .map(|file| HxFile {
+                    name: file.name.clone(),
+                    content: file.content.clone(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let pushed = match HelixParser::parse_source(&content(files())) {
+            Ok(source) => source,
+            Err(e) => return reject(response, vec![e.to_string()]),
+        };
+        if !pushed.node_schemas.is_empty()
+            || !pushed.edge_schemas.is_empty()
+            || !pushed.vector_schemas.is_empty()
+        {
+            return reject(
+                response,
+                vec!["schemas can't be pushed, redeploy the project to change them".to_string()],
+            );
+        }
+        let generated = match check(schema, files()) {
+            Ok(generated) => generated,
+            Err(errors) => return reject(response, errors),
+        };
+
+        let names = generated
+            .queries
+            .iter()
+            .map(|query| query.name.clone())
+            .collect::<Vec<_>>();
+        let queries = generated
+            .queries
+            .into_iter()
+            .map(|query| (query.name.clone(), Arc::new(query)))
+            .collect();
+        *self.queries.write().unwrap() = Arc::new(queries);
+
+        response
+            .headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        response.body = sonic_rs::to_vec(&serde_json::json!({ "queries": names }))?;
+        Ok(())
+    }
+}
+
+/// Checks the query of the request against the schema of the instance and runs it
+/// through the interpreter, without it being compiled or deployed.
+///
+/// Queries the analyzer rejects are answered with a 400 listing its diagnostics.
+/// Queries mutating the graph are rejected on instances that don't take writes of
+/// their own, such as replicas and cluster nodes, as they can't be told apart from
+/// reads before they are analyzed.
+pub fn handle(
+    graph_access: Arc<HelixGraphEngine>,
+    schema: &str,
+    read_only: bool,
+    request: Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let body: QueryRequest = serde_json::from_slice(&request.body)
+        .map_err(|e| GraphError::New(format!("Invalid query request: {}", e)))?;
+    let file = HxFile {
+        name: "query.hx".to_string(),
+        content: body.query,
+    };
+    let generated = match check(schema, vec![file]) {
+        Ok(generated) => generated,
+        Err(errors) => return reject(response, errors),
+    };
+    let [query] = generated.queries.as_slice() else {
+        return reject(
+            response,
+            vec![format!(
+                "expected a single QUERY, got {}",
+                generated.queries.len()
+            )],
+        );
+    };
+    let caller = auth::caller(&request);
+    run(
+        graph_access,
+        query,
+        &body.params,
+        caller,
+        read_only,
+        response,
+    )
+}
+
+/// Runs a pushed query with the parameters in the body of the request
+pub fn handle_pushed(
+    graph_access: Arc<HelixGraphEngine>,
+    query: &Query,
+    read_only: bool,
+    request: Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let params = match request.body.is_empty() {
+        true => JsonValue::Null,
+        false => serde_json::from_slice(&request.body)
+            .map_err(|e| GraphError::New(format!("Invalid parameters: {}", e)))?,
+    };
+    run(graph_access, query, &params, auth::caller(&request), read_only, response)
+}
+
+fn run(
+    graph_access: Arc<HelixGraphEngine>,
+    query: &Query,
+    params: &JsonValue,
+    caller: Option<String>,
+    read_only: bool,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    if query.is_mut && read_only {
+        return Err(GraphError::ReadOnly);
+    }
+    let return_vals = Interpreter::new(Arc::clone(&graph_access.storage), query, params)?
+        .with_caller(caller)
+        .run()?;
+    response
+        .headers
+        .insert("Content-Type".to_string(), "application/json".to_string());
+    response.body = sonic_rs::to_vec(&return_vals)?;
+    Ok(())
+}
+
+fn content(files: Vec<HxFile>) -> Content {
+    Content {
+        content: String::new(),
+        source: Source::default(),
+        files,
+    }
+}
+
+/// Parses and analyzes the files along with the schema, failing with the messages of
+/// the diagnostics if there are any
+fn check(schema: &str, mut files: Vec<HxFile>) -> Result<GeneratedSource, Vec<String>> {
+    let schema = HxFile {
+        name: "schema.hx".to_string(),
+        content: schema.to_string(),
+    };
+    files.insert(0, schema);
+    let content = content(files);
+    let source = HelixParser::parse_source(&content).map_err(|e| vec![e.to_string()])?;
+    let (diagnostics, generated) = analyze(&source);
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(diagnostics
+            .into_iter()
+            .filter(Diagnostic::is_error)
+            .map(|diag| match diag.hint {
+                Some(hint) => format!("{} ({})", diag.message, hint),
+                None => diag.message,
+            })
+            .collect());
+    }
+    Ok(generated)
+}
+
+fn reject(response: &mut Response, errors: Vec<String>) -> Result<(), GraphError> {
+    response.status = 400;
+    response.body = errors.join("\n").into_bytes();
+    Ok(())
+}
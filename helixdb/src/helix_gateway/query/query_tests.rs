@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    helix_engine::{
+        graph_core::graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+        types::GraphError,
+    },
+    helix_gateway::query::query::{handle, handle_pushed, PushedQueries},
+    protocol::{request::Request, response::Response},
+};
+
+const SCHEMA: &str = "N::User { name: String }";
+
+const ADD_USER: &str = "QUERY addUser(name: String) =>
+    user <- AddN<User>({name: name})
+    RETURN user";
+
+const USERS: &str = "QUERY users() =>
+    users <- N<User>
+    RETURN users";
+
+fn engine() -> Arc<HelixGraphEngine> {
+    Arc::new(HelixGraphEngine::new(HelixGraphEngineOpts::in_memory()).unwrap())
+}
+
+fn request(body: JsonValue) -> Request {
+    Request {
+        method: "POST".to_string(),
+        headers: HashMap::new(),
+        path: "/query".to_string(),
+        query: None,
+        version: "HTTP/1.1".to_string(),
+        body: serde_json::to_vec(&body).unwrap(),
+        claims: None,
+    }
+}
+
+/// Runs the query through the query endpoint, answering with the response body
+fn query(
+    graph: &Arc<HelixGraphEngine>,
+    read_only: bool,
+    query: &str,
+    params: JsonValue,
+) -> Result<JsonValue, GraphError> {
+    let mut response = Response::new();
+    let body = json!({ "query": query, "params": params });
+    handle(
+        Arc::clone(graph),
+        SCHEMA,
+        read_only,
+        request(body),
+        &mut response,
+    )?;
+    assert_eq!(
+        response.status,
+        200,
+        "{}",
+        String::from_utf8_lossy(&response.body)
+    );
+    Ok(serde_json::from_slice(&response.body).unwrap())
+}
+
+/// Pushes the query files, answering with the response
+fn push(pushed: &PushedQueries, files: &[&str]) -> Response {
+    let files = files
+        .iter()
+        .enumerate()
+        .map(|(i, content)| json!({ "name": format!("queries{}.hx", i), "content": content }))
+        .collect::<Vec<_>>();
+    let mut response = Response::new();
+    pushed
+        .push(SCHEMA, request(json!({ "files": files })), &mut response)
+        .unwrap();
+    response
+}
+
+#[test]
+fn test_replica_rejects_writes_as_read_only() {
+    let graph = engine();
+    let rejected = query(&graph, true, ADD_USER, json!({ "name": "alice" }));
+    assert!(matches!(rejected, Err(GraphError::ReadOnly)));
+
+    // nothing was written, and reads are still served
+    let users = query(&graph, true, USERS, JsonValue::Null).unwrap();
+    assert_eq!(users["users"], json!([]));
+}
+
+#[test]
+fn test_replica_rejects_pushed_writes_as_read_only() {
+    let graph = engine();
+    let pushed = PushedQueries::default();
+    assert_eq!(push(&pushed, &[ADD_USER]).status, 200);
+    let add_user = pushed.get("addUser").unwrap();
+
+    let run = |read_only| {
+        let params = request(json!({ "name": "alice" }));
+        handle_pushed(
+            Arc::clone(&graph),
+            &add_user,
+            read_only,
+            params,
+            &mut Response::new(),
+        )
+    };
+    assert!(matches!(run(true), Err(GraphError::ReadOnly)));
+    assert!(
+        query(&graph, false, USERS, JsonValue::Null).unwrap()["users"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    // the same query writes on a primary
+    run(false).unwrap();
+    let users = query(&graph, false, USERS, JsonValue::Null).unwrap();
+    assert_eq!(users["users"][0]["name"], "alice");
+}
+
+#[test]
+fn test_push_replaces_every_query() {
+    let pushed = PushedQueries::default();
+    let response = push(&pushed, &[ADD_USER, USERS]);
+    assert_eq!(response.status, 200);
+    let body: JsonValue = serde_json::from_slice(&response.body).unwrap();
+    let mut names = serde_json::from_value::<Vec<String>>(body["queries"].clone()).unwrap();
+    names.sort();
+    assert_eq!(names, ["addUser", "users"]);
+
+    // the next push swaps the whole set, dropping queries it doesn't have
+    assert_eq!(push(&pushed, &[USERS]).status, 200);
+    assert!(pushed.get("users").is_some());
+    assert!(pushed.get("addUser").is_none());
+}
+
+#[test]
+fn test_push_rejected_by_schema_keeps_queries() {
+    let pushed = PushedQueries::default();
+    assert_eq!(push(&pushed, &[USERS]).status, 200);
+
+    let unknown = "QUERY posts() =>
+    posts <- N<Post>
+    RETURN posts";
+    let response = push(&pushed, &[ADD_USER, unknown]);
+    assert_eq!(response.status, 400);
+    assert!(String::from_utf8_lossy(&response.body).contains("Post"));
+
+    let schema = "N::Post { title: String }";
+    let response = push(&pushed, &[schema, USERS]);
+    assert_eq!(response.status, 400);
+    assert!(String::from_utf8_lossy(&response.body).contains("schemas can't be pushed"));
+
+    // neither push touched the queries being served
+    assert!(pushed.get("users").is_some());
+    assert!(pushed.get("addUser").is_none());
+    assert!(pushed.get("posts").is_none());
+}
@@ -1 +1,4 @@
 pub mod router;
+
+#[cfg(test)]
+mod router_tests;
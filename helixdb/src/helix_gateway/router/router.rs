@@ -7,18 +7,148 @@
 
 // returns response
 
+#[cfg(feature = "compiler")]
+use crate::helix_gateway::query::query::{self, PushedQueries, PUSH_PATH, QUERY_PATH};
 use crate::{
-    helix_engine::{graph_core::graph_core::HelixGraphEngine, types::GraphError},
-    helix_gateway::mcp::mcp::{MCPHandlerFn, MCPToolInput, McpConnections},
+    helix_cluster::{
+        node::ClusterNode,
+        rpc::{is_cluster_path, LEADER_HEADER},
+    },
+    helix_engine::{
+        graph_core::{
+            graph_core::HelixGraphEngine,
+            query_limits::{self, CancellationToken, DetachedBudget, QueryGuard},
+        },
+        types::GraphError,
+    },
+    helix_gateway::{
+        auth::auth::{self, Authenticator},
+        cursor_cache::cursor_cache::{self as cursor_cache, Cursor, CursorCache},
+        gremlin::gremlin::{self, GREMLIN_PATH},
+        ingest::{
+            ingest::{self, INGEST_JOBS_PATH, INGEST_PATH},
+            sync::{self, SYNC_PATH},
+        },
+        mcp::mcp::{MCPHandlerFn, MCPToolInput},
+        status::status::{
+            self, RequestStats, ADMIN_AUDIT_PATH, ADMIN_PATH, ADMIN_STORAGE_PATH, ADMIN_TRASH_PATH,
+            STATUS_PATH,
+        },
+    },
 };
 use core::fmt;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
 
-use crate::protocol::{request::Request, response::Response};
+use crate::helix_storage::heed3::{RoTxn, RwTxn};
+use crate::protocol::{
+    pagination::{CursorToken, PageRequest},
+    redaction::Redaction,
+    request::Request,
+    response::Response,
+    return_values::ReturnValue,
+    transaction::{TransactionRequest, TransactionResponse, TRANSACTION_PATH},
+};
 
+#[derive(Clone)]
 pub struct HandlerInput {
     pub request: Request,
     pub graph: Arc<HelixGraphEngine>,
+    pub cursors: Arc<Mutex<CursorCache>>,
+}
+
+impl HandlerInput {
+    /// Claims of the JWT the request was authenticated with, if any
+    pub fn claims(&self) -> Option<&sonic_rs::Value> {
+        self.request.claims.as_ref()
+    }
+
+    /// Who sent the request, see [`auth::caller`]
+    pub fn caller(&self) -> Option<String> {
+        auth::caller(&self.request)
+    }
+
+    /// Runs a query writing to the graph in a write transaction of its own, or in one
+    /// shared with concurrent writes if group commit is enabled. The writes aren't
+    /// committed if the query fails or goes over its limits, and the query is recorded
+    /// in the audit log along with them.
+    ///
+    /// ## Arguments
+    ///
+    /// * `response` - The response to write to
+    /// * `handler` - Runs the query against the write transaction
+    pub fn run_write(
+        &self,
+        response: &mut Response,
+        handler: TxHandlerFn,
+    ) -> Result<(), GraphError> {
+        let storage = &self.graph.storage;
+        let query = self.request.path.trim_start_matches('/');
+        let Some(group_commit) = &storage.group_commit else {
+            let mut txn = storage.graph_env.write_txn()?;
+            storage.audited(&mut txn, query, &self.request.body, self.caller(), |txn| {
+                handler(self, txn, response)
+            })?;
+            query_limits::check()?;
+            return storage.wal.commit(txn);
+        };
+        let input = self.clone();
+        *response = group_commit.run(&storage.graph_env, &storage.wal, move |txn| {
+            let mut response = Response::new();
+            let storage = &input.graph.storage;
+            let query = input.request.path.trim_start_matches('/');
+            storage.audited(txn, query, &input.request.body, input.caller(), |txn| {
+                handler(&input, txn, &mut response)
+            })?;
+            query_limits::check()?;
+            Ok(response)
+        })?;
+        Ok(())
+    }
+
+    /// Returns the pagination parameters of the request, if the client asked for a
+    /// paginated response
+    pub fn page_request(&self) -> Result<Option<PageRequest>, GraphError> {
+        PageRequest::from_request(&self.request)
+    }
+
+    /// Runs a query reading the graph in a read transaction of its own and writes its
+    /// result to the response.
+    ///
+    /// If the client asked for a paginated response, the query is run against a snapshot
+    /// pinned for it instead and only the first page is written, along with the token
+    /// of a cursor reading the next pages from the same snapshot. If no more snapshots
+    /// can be pinned, the least recently used cursor is closed to make room.
+    ///
+    /// ## Arguments
+    ///
+    /// * `response` - The response to write to
+    /// * `handler` - Runs the query against the read transaction
+    pub fn run_read(&self, response: &mut Response, handler: ReadHandlerFn) -> Result<(), GraphError> {
+        let Some(page_request) = self.page_request()? else {
+            let txn = self.graph.storage.graph_env.read_txn()?;
+            let return_vals = handler(self, &txn)?;
+            response.body = sonic_rs::to_vec(&return_vals)?;
+            return Ok(());
+        };
+        let snapshot = loop {
+            match self.graph.snapshot() {
+                Ok(snapshot) => break snapshot,
+                // the least recently used cursor gives its snapshot up to the new one
+                Err(_) if self.graph.storage.snapshots.is_full()
+                    && self.cursors.lock().unwrap().evict_oldest() => {}
+                Err(e) => return Err(e),
+            }
+        };
+        let token = CursorToken::new(snapshot.id());
+        let cursor = Cursor::new(snapshot, self.clone(), handler);
+        cursor_cache::serve_page(&self.cursors, token, cursor, page_request.page_size, response)
+    }
 }
 
 // basic type for function pointer
@@ -35,20 +165,173 @@ pub struct HandlerSubmission(pub Handler);
 pub struct Handler {
     pub name: &'static str,
     pub func: BasicHandlerFn,
+    /// Whether the handler writes to the graph, so it isn't served by read-only replicas
+    pub writes: bool,
 }
 
 impl Handler {
     pub const fn new(name: &'static str, func: BasicHandlerFn) -> Self {
-        Self { name, func }
+        Self {
+            name,
+            func,
+            writes: false,
+        }
+    }
+
+    /// Marks the handler as writing to the graph
+    pub const fn writes(self) -> Self {
+        Self {
+            writes: true,
+            ..self
+        }
     }
 }
 
 inventory::collect!(HandlerSubmission);
 
+/// Runs a read query against a read transaction owned by the caller, returning the values
+/// of its `RETURN`. Used to serve pages of the query from a pinned snapshot.
+pub type ReadHandlerFn =
+    fn(&HandlerInput, &RoTxn) -> Result<HashMap<String, ReturnValue>, GraphError>;
+
+/// Future of an async handler, resolving to the response of the request
+pub type HandlerFuture = Pin<Box<dyn Future<Output = Result<Response, GraphError>> + Send>>;
+
+/// Future of an async handler, polled within the limits of its query and the redaction
+/// scope of its request like sync handlers are run in [`HelixRouter::route`]
+struct Limited {
+    future: HandlerFuture,
+    /// Limits of the query, tracked on the thread polling the future while it is polled
+    budget: Option<DetachedBudget>,
+    roles: Vec<String>,
+}
+
+impl Future for Limited {
+    type Output = Result<Response, GraphError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(budget) = this.budget.take() {
+            budget.attach();
+        }
+        let poll = Redaction::scope(this.roles.clone(), || this.future.as_mut().poll(cx));
+        let poll = poll.map(|result| query_limits::check().and(result));
+        this.budget = Some(query_limits::detach());
+        poll
+    }
+}
+
+// basic type for async function pointer
+pub type BasicAsyncHandlerFn = fn(HandlerInput) -> HandlerFuture;
+
+/// Handler run as its own task on the gateway's runtime, for handlers waiting on I/O
+/// such as embedding text or calling other services, which would otherwise hold up a
+/// worker of the pool
+pub type AsyncHandlerFn = Arc<dyn Fn(HandlerInput) -> HandlerFuture + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub struct AsyncHandlerSubmission(pub AsyncHandler);
+
+#[derive(Clone, Debug)]
+pub struct AsyncHandler {
+    pub name: &'static str,
+    pub func: BasicAsyncHandlerFn,
+}
+
+impl AsyncHandler {
+    pub const fn new(name: &'static str, func: BasicAsyncHandlerFn) -> Self {
+        Self { name, func }
+    }
+}
+
+inventory::collect!(AsyncHandlerSubmission);
+
+/// Runs a query against a write transaction owned by the caller, which commits or
+/// aborts it. Used to run several queries in a single transaction.
+pub type TxHandlerFn =
+    fn(&HandlerInput, &mut RwTxn, &mut Response) -> Result<(), GraphError>;
+
+#[derive(Clone, Debug)]
+pub struct TxHandlerSubmission(pub TxHandler);
+
+#[derive(Clone, Debug)]
+pub struct TxHandler {
+    /// Name of the query the handler runs
+    pub name: &'static str,
+    pub func: TxHandlerFn,
+}
+
+impl TxHandler {
+    pub const fn new(name: &'static str, func: TxHandlerFn) -> Self {
+        Self { name, func }
+    }
+}
+
+inventory::collect!(TxHandlerSubmission);
+
+/// Middleware run around the handler of a request. It passes the request on with
+/// `next.run(request)`, or stops the chain by returning a response of its own.
+pub type Middleware = fn(Request, Next) -> Response;
+
+/// The rest of a middleware chain, ending in the handler of the route
+pub struct Next<'a> {
+    middleware: &'a [Middleware],
+    handler: &'a dyn Fn(Request) -> Response,
+}
+
+impl Next<'_> {
+    /// Runs the request through the remaining middleware and the handler
+    pub fn run(self, request: Request) -> Response {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => middleware(
+                request,
+                Next {
+                    middleware: rest,
+                    handler: self.handler,
+                },
+            ),
+            None => (self.handler)(request),
+        }
+    }
+}
+
 pub struct HelixRouter {
     /// Method+Path => Function
     pub routes: HashMap<(String, String), HandlerFn>,
+    /// Method+Path => Function returning a future run on the gateway's runtime
+    pub async_routes: HashMap<(String, String), AsyncHandlerFn>,
     pub mcp_routes: HashMap<(String, String), MCPHandlerFn>,
+    /// Query name => Function running the query inside a caller owned transaction
+    pub tx_routes: HashMap<String, TxHandlerFn>,
+    /// Names of the queries that write to the graph, recorded in the audit log when run
+    /// through the transaction endpoint
+    pub write_queries: HashSet<String>,
+    /// Open cursors of paginated responses
+    pub cursors: Arc<Mutex<CursorCache>>,
+    /// Middleware run for every route, in the order they were added
+    pub middleware: Vec<Middleware>,
+    /// Method+Path => Middleware run after the global middleware
+    pub route_middleware: HashMap<(String, String), Vec<Middleware>>,
+    /// Credentials checked before any middleware or handler, all requests are accepted
+    /// if unset
+    pub auth: Option<Authenticator>,
+    /// Method+Path of the routes that write to the graph along with the transaction
+    /// endpoint, set on read-only replicas and cluster nodes
+    pub write_routes: Option<HashSet<(String, String)>>,
+    /// Cluster the instance is a node of, writes are only taken while it leads it
+    pub cluster: Option<Arc<ClusterNode>>,
+    /// Requests handled, reported by the status endpoint
+    pub stats: Arc<RequestStats>,
+    /// Parent of the cancellation tokens of the queries being run, see
+    /// [`Self::cancel_queries`]
+    pub cancellation: CancellationToken,
+    /// Schema the queries of the query and push endpoints are checked against, the
+    /// endpoints are only served if set
+    #[cfg(feature = "compiler")]
+    pub query_schema: Option<Arc<str>>,
+    /// Queries pushed to the instance, served before the deployed routes
+    #[cfg(feature = "compiler")]
+    pub pushed_queries: Arc<PushedQueries>,
 }
 
 impl HelixRouter {
@@ -67,16 +350,279 @@ impl HelixRouter {
         };
         Self {
             routes: rts,
+            async_routes: HashMap::new(),
             mcp_routes: mcp_rts,
+            tx_routes: HashMap::new(),
+            write_queries: HashSet::new(),
+            cursors: Arc::new(Mutex::new(CursorCache::default())),
+            middleware: Vec::new(),
+            route_middleware: HashMap::new(),
+            auth: None,
+            write_routes: None,
+            cluster: None,
+            stats: Arc::new(RequestStats::default()),
+            cancellation: CancellationToken::new(),
+            #[cfg(feature = "compiler")]
+            query_schema: None,
+            #[cfg(feature = "compiler")]
+            pushed_queries: Arc::new(PushedQueries::default()),
         }
     }
 
+    /// Replace the cursor cache with one with the given capacity and time to live
+    pub fn with_cursor_cache(mut self, cursors: CursorCache) -> Self {
+        self.cursors = Arc::new(Mutex::new(cursors));
+        self
+    }
+
+    /// Set the queries that can be run through the transaction endpoint
+    pub fn with_tx_routes(mut self, tx_routes: HashMap<String, TxHandlerFn>) -> Self {
+        self.tx_routes = tx_routes;
+        self
+    }
+
+    /// Set the queries that write to the graph, among those of the transaction endpoint
+    pub fn with_write_queries(mut self, write_queries: HashSet<String>) -> Self {
+        self.write_queries = write_queries;
+        self
+    }
+
+    /// Set the routes whose handlers are run as tasks on the gateway's runtime
+    pub fn with_async_routes(
+        mut self,
+        async_routes: HashMap<(String, String), AsyncHandlerFn>,
+    ) -> Self {
+        self.async_routes = async_routes;
+        self
+    }
+
+    /// Reject requests without valid credentials
+    pub fn with_auth(mut self, auth: Authenticator) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Reject the given routes, which write to the graph, and the transaction endpoint,
+    /// for read-only replicas
+    pub fn with_read_only(mut self, write_routes: HashSet<(String, String)>) -> Self {
+        self.write_routes = Some(write_routes);
+        self
+    }
+
+    /// Serve the admin and internal routes of the cluster node, and only take writes to
+    /// the given routes and the transaction endpoint while the node leads the cluster
+    pub fn with_cluster(
+        mut self,
+        cluster: Arc<ClusterNode>,
+        write_routes: HashSet<(String, String)>,
+    ) -> Self {
+        self.cluster = Some(cluster);
+        self.write_routes = Some(write_routes);
+        self
+    }
+
+    /// Serve the endpoints running ad-hoc and pushed queries through the interpreter,
+    /// checked against the given schema
+    #[cfg(feature = "compiler")]
+    pub fn with_query_endpoint(mut self, schema: &str) -> Self {
+        self.query_schema = Some(Arc::from(schema));
+        self
+    }
+
+    /// Whether the request writes to the graph of a replica or cluster node
+    fn is_write(&self, request: &Request) -> bool {
+        match &self.write_routes {
+            Some(write_routes) => {
+                request.path == TRANSACTION_PATH
+                    || request.path == INGEST_PATH
+                    || request.path == SYNC_PATH
+                    // restoring or purging the trash and pruning the audit log
+                    || (request.method == "POST"
+                        && (request.path == ADMIN_TRASH_PATH || request.path == ADMIN_AUDIT_PATH))
+                    || write_routes.contains(&(request.method.clone(), request.path.clone()))
+            }
+            None => false,
+        }
+    }
+
+    /// Fails unless the request was authenticated as an admin, if requests are
+    /// authenticated
+    fn check_admin(&self, request: &Request) -> Result<(), GraphError> {
+        match &self.auth {
+            Some(auth) if !auth.is_admin(request) => Err(GraphError::Forbidden(format!(
+                "{} requires an admin key or role",
+                request.path
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if the request writes to the graph but the instance doesn't take writes
+    fn check_writable(&self, request: &Request) -> Result<(), GraphError> {
+        if !self.is_write(request) {
+            return Ok(());
+        }
+        match &self.cluster {
+            Some(cluster) => cluster.check_leader(),
+            None => Err(GraphError::ReadOnly),
+        }
+    }
+
+    /// Checks the credentials of a request, returning the response rejecting it if
+    /// they aren't valid
+    pub fn authenticate(&self, request: &mut Request) -> Result<(), Response> {
+        match &self.auth {
+            Some(auth) => auth.authenticate(request),
+            None => Ok(()),
+        }
+    }
+
+    /// Add middleware run for every route
+    pub fn add_middleware(&mut self, middleware: Middleware) {
+        self.middleware.push(middleware);
+    }
+
+    /// Add middleware run only for the given route, after the global middleware
+    pub fn add_route_middleware(&mut self, method: &str, path: &str, middleware: Middleware) {
+        self.route_middleware
+            .entry((method.to_uppercase(), path.to_string()))
+            .or_default()
+            .push(middleware);
+    }
+
     /// Add a route to the router
     pub fn add_route(&mut self, method: &str, path: &str, handler: BasicHandlerFn) {
         self.routes
             .insert((method.to_uppercase(), path.to_string()), Arc::new(handler));
     }
 
+    /// Add a route with an async handler to the router
+    pub fn add_async_route(&mut self, method: &str, path: &str, handler: BasicAsyncHandlerFn) {
+        self.async_routes
+            .insert((method.to_uppercase(), path.to_string()), Arc::new(handler));
+    }
+
+    /// Whether the request is handled by an async handler, which must be run with
+    /// [`Self::handle_async`] rather than [`Self::handle`]
+    pub fn is_async_route(&self, request: &Request) -> bool {
+        self.async_routes
+            .contains_key(&(request.method.clone(), request.path.clone()))
+    }
+
+    /// Starts handling a request with the async handler of its route.
+    ///
+    /// The handler is run within the limits of a query and with the roles of the
+    /// request like sync handlers, but isn't run through the middleware. The future isn't
+    /// tied to the router, so it can be spawned as a task of its own.
+    pub fn handle_async(
+        &self,
+        graph_access: Arc<HelixGraphEngine>,
+        mut request: Request,
+    ) -> HandlerFuture {
+        self.stats.record();
+        if let Err(response) = self.authenticate(&mut request) {
+            return Box::pin(async move { Ok(response) });
+        }
+        if let Err(e) = self.check_writable(&request) {
+            return Box::pin(async move { Err(e) });
+        }
+        let route_key = (request.method.clone(), request.path.clone());
+        let Some(handler) = self.async_routes.get(&route_key) else {
+            return Box::pin(async move {
+                Err(GraphError::New(format!(
+                    "No async handler for {} {}",
+                    route_key.0, route_key.1
+                )))
+            });
+        };
+
+        let roles = match &self.auth {
+            Some(auth) => auth.roles(&request),
+            None => Vec::new(),
+        };
+
+        // continuation pages are served straight from the cursor cache
+        match PageRequest::from_request(&request) {
+            Ok(Some(PageRequest {
+                cursor: Some(cursor),
+                page_size,
+            })) => {
+                let mut response = Response::new();
+                let page = Redaction::scope(roles, || {
+                    cursor_cache::continue_cursor(
+                        &self.cursors,
+                        &request.path,
+                        &cursor,
+                        page_size,
+                        &mut response,
+                    )
+                });
+                Box::pin(async move { page.map(|_| response) })
+            }
+            Err(e) => Box::pin(async move { Err(e) }),
+            Ok(_) => {
+                // the map can't be resized while the handler may have transactions open
+                let hold = graph_access.storage.map_size.hold();
+                let budget = {
+                    let _guard = QueryGuard::start(
+                        &graph_access.storage.query_limits,
+                        self.cancellation.child(None),
+                    );
+                    query_limits::detach()
+                };
+                let future = handler(HandlerInput {
+                    request,
+                    graph: graph_access,
+                    cursors: Arc::clone(&self.cursors),
+                });
+                let future = Limited {
+                    future,
+                    budget: Some(budget),
+                    roles,
+                };
+                Box::pin(async move {
+                    let _hold = hold;
+                    future.await
+                })
+            }
+        }
+    }
+
+    /// Authenticates a request and runs it through the global middleware and the
+    /// middleware of its route, ending in [`Self::handle`]. Errors of the handler are written to the response
+    /// with their status code.
+    ///
+    /// Async routes aren't run through the middleware, see [`Self::handle_async`].
+    pub fn dispatch(&self, graph_access: Arc<HelixGraphEngine>, mut request: Request) -> Response {
+        if request.path != STATUS_PATH {
+            self.stats.record();
+        }
+        if let Err(response) = self.authenticate(&mut request) {
+            return response;
+        }
+        // the map can't be resized while the request may have transactions open, the
+        // status and storage endpoints don't open any
+        let _hold = (request.path != STATUS_PATH && request.path != ADMIN_STORAGE_PATH)
+            .then(|| graph_access.storage.map_size.hold());
+        let route_key = (request.method.clone(), request.path.clone());
+        let middleware = match self.route_middleware.get(&route_key) {
+            Some(route_middleware) => [self.middleware.as_slice(), route_middleware].concat(),
+            None => self.middleware.clone(),
+        };
+        let handler = |request: Request| {
+            let mut response = Response::new();
+            if let Err(e) = self.handle(Arc::clone(&graph_access), request, &mut response) {
+                set_error(&mut response, e);
+            }
+            response
+        };
+        Next {
+            middleware: &middleware,
+            handler: &handler,
+        }
+        .run(request)
+    }
+
     /// Handle a request by finding the appropriate handler and executing it
     ///
     /// ## Arguments
@@ -95,12 +641,132 @@ impl HelixRouter {
         request: Request,
         response: &mut Response,
     ) -> Result<(), GraphError> {
+        let Some(cluster) = &self.cluster else {
+            self.check_writable(&request)?;
+            return self.route(graph_access, request, response);
+        };
+        if is_cluster_path(&request.path) {
+            return cluster.handle(&request, response);
+        }
+        if !self.is_write(&request) {
+            return self.route(graph_access, request, response);
+        }
+
+        // writes are committed through the write-ahead log, once a majority of the
+        // cluster stored them
+        cluster.check_leader()?;
+        self.route(graph_access, request, response)
+    }
+
+    /// Cancels the queries being run and any run after, whose traversals stop and
+    /// whose writes are rolled back. Used when the gateway shuts down.
+    pub fn cancel_queries(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Runs the handler of the request's route within the execution limits of a query,
+    /// failing with the limit it went over or its cancellation rather than the error it
+    /// may have led to. Sensitive fields are redacted for the roles of the request.
+    fn route(
+        &self,
+        graph_access: Arc<HelixGraphEngine>,
+        request: Request,
+        response: &mut Response,
+    ) -> Result<(), GraphError> {
+        let guard = QueryGuard::start(
+            &graph_access.storage.query_limits,
+            self.cancellation.child(None),
+        );
+        let roles = match &self.auth {
+            Some(auth) => auth.roles(&request),
+            None => Vec::new(),
+        };
+        let result = Redaction::scope(roles, || {
+            self.route_unlimited(graph_access, request, response)
+        });
+        guard.finish().and(result)
+    }
+
+    fn route_unlimited(
+        &self,
+        graph_access: Arc<HelixGraphEngine>,
+        request: Request,
+        response: &mut Response,
+    ) -> Result<(), GraphError> {
+        if request.method == "POST" && request.path == TRANSACTION_PATH {
+            return self.handle_transaction(graph_access, request, response);
+        }
+        if request.method == "POST" && request.path == GREMLIN_PATH {
+            return gremlin::handle(graph_access, request, response);
+        }
+        if request.method == "POST" && request.path == INGEST_PATH {
+            return ingest::handle(graph_access, request, response);
+        }
+        if request.method == "POST" && request.path == SYNC_PATH {
+            return sync::handle(graph_access, request, response);
+        }
+        if request.method == "GET" && request.path.starts_with(INGEST_JOBS_PATH) {
+            return ingest::handle_jobs(graph_access, request, response);
+        }
+        if request.method == "GET" && request.path == STATUS_PATH {
+            return status::handle(&graph_access, &self.stats, response);
+        }
+        if request.path.starts_with(ADMIN_PATH) {
+            self.check_admin(&request)?;
+        }
+        if request.path == ADMIN_STORAGE_PATH {
+            return status::handle_storage(&graph_access, &request, response);
+        }
+        if request.path == ADMIN_TRASH_PATH {
+            return status::handle_trash(&graph_access, &request, response);
+        }
+        if request.path == ADMIN_AUDIT_PATH {
+            return status::handle_audit(&graph_access, &request, response);
+        }
+        #[cfg(feature = "compiler")]
+        if let (Some(schema), "POST") = (&self.query_schema, request.method.as_str()) {
+            let read_only = self.write_routes.is_some();
+            match request.path.as_str() {
+                QUERY_PATH => {
+                    return query::handle(graph_access, schema, read_only, request, response)
+                }
+                PUSH_PATH => return self.pushed_queries.push(schema, request, response),
+                path => {
+                    if let Some(pushed) = self.pushed_queries.get(path.trim_start_matches('/')) {
+                        return query::handle_pushed(
+                            graph_access,
+                            &pushed,
+                            read_only,
+                            request,
+                            response,
+                        );
+                    }
+                }
+            }
+        }
+
         let route_key = (request.method.clone(), request.path.clone());
 
         if let Some(handler) = self.routes.get(&route_key) {
+            // continuation pages are served straight from the cursor cache
+            if let Some(PageRequest {
+                cursor: Some(cursor),
+                page_size,
+            }) = PageRequest::from_request(&request)?
+            {
+                return cursor_cache::continue_cursor(
+                    &self.cursors,
+                    &request.path,
+                    &cursor,
+                    page_size,
+                    response,
+                );
+            }
+
             let input = HandlerInput {
                 request,
                 graph: Arc::clone(&graph_access),
+                cursors: Arc::clone(&self.cursors),
             };
             return handler(&input, response);
         }
@@ -118,6 +784,99 @@ impl HelixRouter {
         response.body = b"404 - Not Found".to_vec();
         return Ok(());
     }
+
+    /// Runs all queries of a [`TransactionRequest`] in a single write transaction
+    ///
+    /// The transaction is only committed if every query succeeds. The first failing
+    /// query aborts it and its error is returned, so none of the writes are applied.
+    pub fn handle_transaction(
+        &self,
+        graph_access: Arc<HelixGraphEngine>,
+        request: Request,
+        response: &mut Response,
+    ) -> Result<(), GraphError> {
+        let tx_request: TransactionRequest = sonic_rs::from_slice(&request.body)?;
+        let storage = Arc::clone(&graph_access.storage);
+        let mut txn = storage.graph_env.write_txn()?;
+
+        let mut results = Vec::with_capacity(tx_request.queries.len());
+        for (i, query) in tx_request.queries.iter().enumerate() {
+            let Some(handler) = self.tx_routes.get(&query.query) else {
+                return Err(GraphError::New(format!(
+                    "Query {} ({}) not found, transaction rolled back",
+                    i, query.query
+                )));
+            };
+            let mut query_request = query.to_request(&request.version)?;
+            query_request.claims = request.claims.clone();
+            let input = HandlerInput {
+                request: query_request,
+                graph: Arc::clone(&graph_access),
+                cursors: Arc::clone(&self.cursors),
+            };
+            let mut query_response = Response::new();
+            let result = match self.write_queries.contains(&query.query) {
+                true => storage.audited(
+                    &mut txn,
+                    &query.query,
+                    &input.request.body,
+                    input.caller(),
+                    |txn| handler(&input, txn, &mut query_response),
+                ),
+                false => handler(&input, &mut txn, &mut query_response),
+            };
+            if let Err(e) = result {
+                // keep the error structured so it maps to the same status code
+                if let GraphError::UniqueViolation { .. } | GraphError::SchemaViolation(_) = e {
+                    return Err(e);
+                }
+                return Err(GraphError::New(format!(
+                    "Query {} ({}) failed, transaction rolled back: {}",
+                    i, query.query, e
+                )));
+            }
+            results.push(sonic_rs::from_slice::<sonic_rs::Value>(&query_response.body)?);
+        }
+
+        let committed = !tx_request.rollback;
+        if committed {
+            query_limits::check()?;
+            storage.wal.commit(txn)?;
+        } else {
+            txn.abort();
+        }
+        response.body = sonic_rs::to_vec(&TransactionResponse { results, committed })?;
+        Ok(())
+    }
+}
+
+/// Writes an error of a handler to the response, with the status code of its kind
+pub fn set_error(response: &mut Response, e: GraphError) {
+    eprintln!("Error handling request: {:?}", e);
+    if let GraphError::NotLeader {
+        leader: Some(leader),
+    } = &e
+    {
+        response
+            .headers
+            .insert(LEADER_HEADER.to_string(), leader.clone());
+    }
+    response.status = match e {
+        GraphError::UniqueViolation { .. } => 409,
+        GraphError::SchemaViolation(_) => 422,
+        GraphError::ReadOnly | GraphError::Forbidden(_) => 403,
+        GraphError::NotLeader { .. } => 421,
+        GraphError::QueryLimitExceeded(_) => 400,
+        GraphError::QueryCancelled(_) => 408,
+        GraphError::InvalidCursor(_) => 400,
+        GraphError::CursorNotFound => 404,
+        _ => 500,
+    };
+    response.body = match &e {
+        // the client gets what the query did before it was cancelled
+        GraphError::QueryCancelled(stats) => sonic_rs::to_vec(stats).unwrap_or_default(),
+        _ => format!("\n{:?}", e).into_bytes(),
+    };
 }
 
 #[derive(Debug)]
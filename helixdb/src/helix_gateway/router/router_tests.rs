@@ -0,0 +1,724 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use heed3::{RoTxn, RwTxn};
+use jsonwebtoken::{encode, get_current_timestamp, Algorithm, EncodingKey, Header};
+use sonic_rs::{JsonContainerTrait, JsonValueTrait};
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::{AuthConfig, Config, JwtAlgorithm, JwtConfig},
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+            snapshot::Snapshots,
+            ops::{
+                g::G,
+                source::{add_n::AddNAdapter, n_from_type::NFromTypeAdapter},
+                tr_val::TraversalVal,
+            },
+        },
+        storage_core::test_utils,
+        types::GraphError,
+    },
+    helix_gateway::{
+        auth::auth::Authenticator,
+        cursor_cache::cursor_cache::CursorCache,
+        router::router::{HandlerFuture, HandlerInput, HelixRouter, Next, TxHandlerFn},
+        status::status::{AuditResponse, ADMIN_AUDIT_PATH, ADMIN_STORAGE_PATH, ADMIN_TRASH_PATH},
+    },
+    props,
+    protocol::{
+        pagination::{CURSOR_HEADER, NEXT_CURSOR_HEADER, PAGE_SIZE_HEADER},
+        redaction::{Redaction, SensitiveField},
+        request::Request,
+        response::Response,
+        return_values::ReturnValue,
+        transaction::TRANSACTION_PATH,
+        value::Value,
+    },
+};
+
+fn engine() -> Arc<HelixGraphEngine> {
+    Arc::new(HelixGraphEngine::new(HelixGraphEngineOpts::in_memory()).unwrap())
+}
+
+fn add_people(engine: &HelixGraphEngine, names: &[&str]) {
+    let mut txn = engine.storage.graph_env.write_txn().unwrap();
+    for name in names {
+        G::new_mut(Arc::clone(&engine.storage), &mut txn)
+            .add_n("person", Some(props! { "name" => *name }), None)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+}
+
+// the shape of the handlers generated for read queries
+fn people(input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {
+    input.run_read(response, people_in_read)
+}
+
+fn people_in_read(
+    input: &HandlerInput,
+    txn: &RoTxn,
+) -> Result<HashMap<String, ReturnValue>, GraphError> {
+    let names = names(&input.graph, txn);
+    let mut return_vals = HashMap::new();
+    return_vals.insert(
+        "people".to_string(),
+        ReturnValue::Array(names.into_iter().map(ReturnValue::from).collect()),
+    );
+    Ok(return_vals)
+}
+
+/// Names of the people in the graph, sorted
+fn names(engine: &HelixGraphEngine, txn: &RoTxn) -> Vec<String> {
+    let mut names = G::new(Arc::clone(&engine.storage), txn)
+        .n_from_type("person")
+        .collect_to::<Vec<_>>()
+        .into_iter()
+        .filter_map(|val| match val {
+            TraversalVal::Node(node) => match node.properties?.remove("name")? {
+                Value::String(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+fn router(cursors: CursorCache) -> HelixRouter {
+    let mut router = HelixRouter::new(None, None).with_cursor_cache(cursors);
+    router.add_route("POST", "/people", people);
+    router.add_route("POST", "/others", people);
+    router
+}
+
+fn request(path: &str, headers: &[(&str, &str)]) -> Request {
+    Request {
+        method: "POST".to_string(),
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>(),
+        path: path.to_string(),
+        query: None,
+        version: "HTTP/1.1".to_string(),
+        body: Vec::new(),
+        claims: None,
+    }
+}
+
+/// Items of a page along with the token of the next one
+fn page(response: &Response) -> (Vec<String>, Option<String>) {
+    assert_eq!(response.status, 200, "{}", String::from_utf8_lossy(&response.body));
+    let body: sonic_rs::Value = sonic_rs::from_slice(&response.body).unwrap();
+    let items = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item.as_str().unwrap().to_string())
+        .collect();
+    (items, response.headers.get(NEXT_CURSOR_HEADER).cloned())
+}
+
+#[test]
+fn test_read_without_pagination() {
+    let engine = engine();
+    add_people(&engine, &["alice", "bob", "carol"]);
+    let router = router(CursorCache::default());
+
+    let response = router.dispatch(Arc::clone(&engine), request("/people", &[]));
+    assert_eq!(response.status, 200);
+    let body: sonic_rs::Value = sonic_rs::from_slice(&response.body).unwrap();
+    assert_eq!(body["people"].as_array().unwrap().len(), 3);
+    assert!(response.headers.get(NEXT_CURSOR_HEADER).is_none());
+    assert!(router.cursors.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_read_pages_from_snapshot() {
+    let engine = engine();
+    add_people(&engine, &["alice", "bob", "carol", "dave", "erin"]);
+    let router = router(CursorCache::default());
+
+    let first = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(PAGE_SIZE_HEADER, "2")]),
+    );
+    let (items, cursor) = page(&first);
+    assert_eq!(items, ["alice", "bob"]);
+    let cursor = cursor.expect("first page should have a continuation token");
+    assert_eq!(router.cursors.lock().unwrap().len(), 1);
+
+    // the later pages are read from the graph as it was when the first page was
+    add_people(&engine, &["adam"]);
+
+    let second = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(PAGE_SIZE_HEADER, "2"), (CURSOR_HEADER, &cursor)]),
+    );
+    let (items, next) = page(&second);
+    assert_eq!(items, ["carol", "dave"]);
+    assert_eq!(next.as_deref(), Some(cursor.as_str()));
+
+    let last = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(PAGE_SIZE_HEADER, "2"), (CURSOR_HEADER, &cursor)]),
+    );
+    let (items, next) = page(&last);
+    assert_eq!(items, ["erin"]);
+    assert!(next.is_none());
+    assert!(router.cursors.lock().unwrap().is_empty());
+
+    // the cursor is closed once exhausted
+    let response = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(CURSOR_HEADER, &cursor)]),
+    );
+    assert_eq!(response.status, 404);
+}
+
+#[test]
+fn test_cursor_expires() {
+    let engine = engine();
+    add_people(&engine, &["alice", "bob", "carol"]);
+    let router = router(CursorCache::new(16, Duration::from_millis(50)));
+
+    let first = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(PAGE_SIZE_HEADER, "1")]),
+    );
+    let (_, cursor) = page(&first);
+    let cursor = cursor.unwrap();
+
+    thread::sleep(Duration::from_millis(100));
+    let response = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(CURSOR_HEADER, &cursor)]),
+    );
+    assert_eq!(response.status, 404);
+    assert!(router.cursors.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_cursor_only_valid_on_its_route() {
+    let engine = engine();
+    add_people(&engine, &["alice", "bob", "carol"]);
+    let router = router(CursorCache::default());
+
+    let first = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(PAGE_SIZE_HEADER, "1")]),
+    );
+    let (_, cursor) = page(&first);
+    let cursor = cursor.unwrap();
+
+    let response = router.dispatch(
+        Arc::clone(&engine),
+        request("/others", &[(CURSOR_HEADER, &cursor)]),
+    );
+    assert_eq!(response.status, 404);
+
+    // the cursor is still open on the route that opened it
+    let response = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(PAGE_SIZE_HEADER, "1"), (CURSOR_HEADER, &cursor)]),
+    );
+    assert_eq!(page(&response).0, ["bob"]);
+}
+
+#[test]
+fn test_oldest_cursor_evicted_once_snapshots_run_out() {
+    let engine = engine();
+    add_people(&engine, &["alice", "bob", "carol"]);
+    let router = router(CursorCache::default());
+
+    let opened = Snapshots::DEFAULT_MAX + 8;
+    let cursors: Vec<String> = (0..opened)
+        .map(|_| {
+            let response = router.dispatch(
+                Arc::clone(&engine),
+                request("/people", &[(PAGE_SIZE_HEADER, "1")]),
+            );
+            assert_eq!(response.status, 200);
+            page(&response).1.expect("every page should have a continuation token")
+        })
+        .collect();
+    assert_eq!(router.cursors.lock().unwrap().len(), Snapshots::DEFAULT_MAX);
+    assert_eq!(engine.storage.snapshots.pinned(), Snapshots::DEFAULT_MAX);
+
+    // the least recently used cursors were closed to release their snapshots
+    let response = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(CURSOR_HEADER, &cursors[0])]),
+    );
+    assert_eq!(response.status, 404);
+
+    let response = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(PAGE_SIZE_HEADER, "1"), (CURSOR_HEADER, &cursors[opened - 1])]),
+    );
+    assert_eq!(page(&response).0, ["bob"]);
+}
+
+#[test]
+fn test_invalid_cursor_rejected() {
+    let engine = engine();
+    let router = router(CursorCache::default());
+    let response = router.dispatch(
+        Arc::clone(&engine),
+        request("/people", &[(CURSOR_HEADER, "not-a-cursor")]),
+    );
+    assert_eq!(response.status, 400);
+}
+
+const SECRET: &str = "test-secret";
+
+fn with_auth(router: HelixRouter) -> HelixRouter {
+    let auth = Authenticator::new(&AuthConfig {
+        jwt: Some(JwtConfig {
+            algorithm: JwtAlgorithm::HS256,
+            key: SECRET.to_string(),
+            issuer: None,
+            audience: None,
+            role_claim: None,
+        }),
+        admin_role: Some("admin".to_string()),
+        ..AuthConfig::default()
+    })
+    .unwrap();
+    router.with_auth(auth)
+}
+
+fn bearer(role: &str) -> String {
+    let claims = serde_json::json!({
+        "sub": "alice",
+        "role": role,
+        "exp": get_current_timestamp() + 3600,
+    });
+    let key = EncodingKey::from_secret(SECRET.as_bytes());
+    let token = encode(&Header::new(Algorithm::HS256), &claims, &key).unwrap();
+    format!("Bearer {}", token)
+}
+
+// answers with the roles it is run with, after yielding so it is polled more than once
+fn roles(_input: HandlerInput) -> HandlerFuture {
+    Box::pin(async move {
+        tokio::task::yield_now().await;
+        let mut response = Response::new();
+        response.body = Redaction::roles().join(",").into_bytes();
+        Ok(response)
+    })
+}
+
+fn count_people(input: HandlerInput) -> HandlerFuture {
+    Box::pin(async move {
+        tokio::task::yield_now().await;
+        let storage = Arc::clone(&input.graph.storage);
+        let txn = storage.graph_env.read_txn()?;
+        let count = G::new(Arc::clone(&storage), &txn)
+            .n_from_type("person")
+            .count();
+        let mut response = Response::new();
+        response.body = count.to_string().into_bytes();
+        Ok(response)
+    })
+}
+
+#[tokio::test]
+async fn test_async_route_runs_with_request_roles() {
+    let engine = engine();
+    let mut router = with_auth(HelixRouter::new(None, None));
+    router.add_async_route("POST", "/roles", roles);
+
+    let token = bearer("admin");
+    let request = request("/roles", &[("authorization", &token)]);
+    let response = router.handle_async(Arc::clone(&engine), request).await.unwrap();
+    assert_eq!(response.body, b"admin");
+    // the roles don't outlive the request
+    assert!(Redaction::roles().is_empty());
+}
+
+#[tokio::test]
+async fn test_async_route_runs_within_query_limits() {
+    let mut config = Config::default();
+    config.query_limits.max_visited = Some(2);
+    let engine = Arc::new(
+        HelixGraphEngine::new(HelixGraphEngineOpts {
+            config,
+            ..HelixGraphEngineOpts::in_memory()
+        })
+        .unwrap(),
+    );
+    add_people(&engine, &["alice", "bob", "carol", "dave"]);
+    let mut router = HelixRouter::new(None, None);
+    router.add_async_route("POST", "/count", count_people);
+
+    let result = router
+        .handle_async(Arc::clone(&engine), request("/count", &[]))
+        .await;
+    assert!(
+        matches!(result, Err(GraphError::QueryLimitExceeded(_))),
+        "{:?}",
+        result.map(|response| response.body)
+    );
+}
+
+// the shape of the handlers generated for the transaction endpoint
+fn add_person_tx(
+    input: &HandlerInput,
+    txn: &mut RwTxn,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let params: sonic_rs::Value = sonic_rs::from_slice(&input.request.body)?;
+    let name = params["name"].as_str().unwrap_or_default().to_string();
+    let node = G::new_mut(Arc::clone(&input.graph.storage), txn)
+        .add_n("person", Some(props! { "name" => name }), None)
+        .collect_to_val();
+    response.body = sonic_rs::to_vec(&ReturnValue::from(node))?;
+    Ok(())
+}
+
+fn people_tx(
+    input: &HandlerInput,
+    txn: &mut RwTxn,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    response.body = sonic_rs::to_vec(&names(&input.graph, txn))?;
+    Ok(())
+}
+
+fn fail_tx(_: &HandlerInput, _: &mut RwTxn, _: &mut Response) -> Result<(), GraphError> {
+    Err(GraphError::New("failed on purpose".to_string()))
+}
+
+fn tx_router() -> HelixRouter {
+    let tx_routes = HashMap::from([
+        ("addPerson".to_string(), add_person_tx as TxHandlerFn),
+        ("people".to_string(), people_tx as TxHandlerFn),
+        ("fail".to_string(), fail_tx as TxHandlerFn),
+    ]);
+    let write_queries = HashSet::from(["addPerson".to_string(), "fail".to_string()]);
+    HelixRouter::new(None, None)
+        .with_tx_routes(tx_routes)
+        .with_write_queries(write_queries)
+}
+
+fn transaction(engine: &Arc<HelixGraphEngine>, router: &HelixRouter, body: &str) -> Response {
+    let mut request = request(TRANSACTION_PATH, &[]);
+    request.body = body.as_bytes().to_vec();
+    router.dispatch(Arc::clone(engine), request)
+}
+
+fn stored_names(engine: &HelixGraphEngine) -> Vec<String> {
+    let txn = engine.storage.graph_env.read_txn().unwrap();
+    names(engine, &txn)
+}
+
+#[test]
+fn test_transaction_commits_every_query() {
+    let engine = engine();
+    let response = transaction(
+        &engine,
+        &tx_router(),
+        r#"{"queries": [
+            {"query": "addPerson", "params": {"name": "alice"}},
+            {"query": "addPerson", "params": {"name": "bob"}}
+        ]}"#,
+    );
+    assert_eq!(response.status, 200, "{}", String::from_utf8_lossy(&response.body));
+    let body: sonic_rs::Value = sonic_rs::from_slice(&response.body).unwrap();
+    assert!(body["committed"].as_bool().unwrap());
+    assert_eq!(body["results"].as_array().unwrap().len(), 2);
+    assert_eq!(stored_names(&engine), ["alice", "bob"]);
+}
+
+#[test]
+fn test_failing_query_rolls_back_transaction() {
+    let engine = engine();
+    let response = transaction(
+        &engine,
+        &tx_router(),
+        r#"{"queries": [
+            {"query": "addPerson", "params": {"name": "alice"}},
+            {"query": "fail"},
+            {"query": "addPerson", "params": {"name": "bob"}}
+        ]}"#,
+    );
+    assert_eq!(response.status, 500);
+    let body = String::from_utf8_lossy(&response.body);
+    assert!(body.contains("Query 1 (fail) failed"), "{}", body);
+    // the writes of the queries before the failing one are rolled back too
+    assert!(stored_names(&engine).is_empty());
+}
+
+#[test]
+fn test_unknown_query_rolls_back_transaction() {
+    let engine = engine();
+    let response = transaction(
+        &engine,
+        &tx_router(),
+        r#"{"queries": [
+            {"query": "addPerson", "params": {"name": "alice"}},
+            {"query": "removeEveryone"}
+        ]}"#,
+    );
+    assert_eq!(response.status, 500);
+    let body = String::from_utf8_lossy(&response.body);
+    assert!(body.contains("Query 1 (removeEveryone) not found"), "{}", body);
+    assert!(stored_names(&engine).is_empty());
+}
+
+#[test]
+fn test_read_queries_in_transaction() {
+    let mut config = Config::default();
+    config.audit.enabled = true;
+    config.wal.enabled = true;
+    let engine = Arc::new(
+        HelixGraphEngine::new(HelixGraphEngineOpts {
+            config,
+            ..HelixGraphEngineOpts::in_memory()
+        })
+        .unwrap(),
+    );
+    add_people(&engine, &["carol"]);
+    let response = transaction(
+        &engine,
+        &tx_router(),
+        r#"{"queries": [
+            {"query": "people"},
+            {"query": "addPerson", "params": {"name": "alice"}},
+            {"query": "people"}
+        ]}"#,
+    );
+    assert_eq!(response.status, 200, "{}", String::from_utf8_lossy(&response.body));
+    let body: sonic_rs::Value = sonic_rs::from_slice(&response.body).unwrap();
+    let results = body["results"].as_array().unwrap();
+    let read = |i: usize| -> Vec<String> { sonic_rs::from_value(&results[i]).unwrap() };
+    // reads see the writes of the queries before them
+    assert_eq!(read(0), ["carol"]);
+    assert_eq!(read(2), ["alice", "carol"]);
+
+    // only the write is recorded in the audit log
+    let txn = engine.storage.graph_env.read_txn().unwrap();
+    let audited = engine.storage.audit.list(&txn, 0, 10).unwrap();
+    assert_eq!(
+        audited.iter().map(|entry| entry.query.as_str()).collect::<Vec<_>>(),
+        ["addPerson"]
+    );
+}
+
+#[cfg(feature = "compiler")]
+#[test]
+fn test_pushed_query_overrides_deployed_route() {
+    let engine = engine();
+    add_people(&engine, &["alice", "bob"]);
+    let router = router(CursorCache::default()).with_query_endpoint("N::User { name: String }");
+
+    let response = router.dispatch(Arc::clone(&engine), request("/people", &[]));
+    let body: sonic_rs::Value = sonic_rs::from_slice(&response.body).unwrap();
+    assert_eq!(body["people"].as_array().unwrap().len(), 2);
+
+    let mut push = request("/push", &[]);
+    push.body = serde_json::to_vec(&serde_json::json!({ "files": [{
+        "name": "queries.hx",
+        "content": "QUERY people() =>\n    count <- N<User>::COUNT\n    RETURN count",
+    }] }))
+    .unwrap();
+    let response = router.dispatch(Arc::clone(&engine), push);
+    assert_eq!(response.status, 200, "{}", String::from_utf8_lossy(&response.body));
+
+    // the pushed query is served in place of the deployed one, the others are untouched
+    let response = router.dispatch(Arc::clone(&engine), request("/people", &[]));
+    assert_eq!(response.status, 200, "{}", String::from_utf8_lossy(&response.body));
+    let body: sonic_rs::Value = sonic_rs::from_slice(&response.body).unwrap();
+    assert_eq!(body["count"].as_u64(), Some(0));
+    assert!(body.get("people").is_none());
+    let response = router.dispatch(Arc::clone(&engine), request("/others", &[]));
+    let body: sonic_rs::Value = sonic_rs::from_slice(&response.body).unwrap();
+    assert_eq!(body["people"].as_array().unwrap().len(), 2);
+}
+
+/// Names of the middleware a request went through, in the order they ran
+const TRACE_HEADER: &str = "x-trace";
+
+fn traced(mut request: Request, next: Next, name: &str) -> Response {
+    let trace = request.headers.entry(TRACE_HEADER.to_string()).or_default();
+    trace.push_str(name);
+    trace.push(',');
+    let mut response = next.run(request);
+    // the response comes back through the middleware in reverse
+    let trace = response.headers.entry(TRACE_HEADER.to_string()).or_default();
+    trace.push_str(name);
+    trace.push(',');
+    response
+}
+
+fn first(request: Request, next: Next) -> Response {
+    traced(request, next, "first")
+}
+
+fn second(request: Request, next: Next) -> Response {
+    traced(request, next, "second")
+}
+
+fn on_route(request: Request, next: Next) -> Response {
+    traced(request, next, "route")
+}
+
+fn deny(_request: Request, _next: Next) -> Response {
+    let mut response = Response::new();
+    response.status = 403;
+    response.body = b"denied".to_vec();
+    response
+}
+
+fn trace(input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {
+    let trace = input.request.headers.get(TRACE_HEADER).cloned();
+    response.body = trace.unwrap_or_default().into_bytes();
+    Ok(())
+}
+
+fn trace_router() -> HelixRouter {
+    let mut router = HelixRouter::new(None, None);
+    router.add_route("POST", "/trace", trace);
+    router.add_route("POST", "/other", trace);
+    router
+}
+
+#[test]
+fn test_global_middleware_runs_before_route_middleware() {
+    let engine = engine();
+    let mut router = trace_router();
+    // added out of order, route middleware still runs after every global one
+    router.add_middleware(first);
+    router.add_route_middleware("POST", "/trace", on_route);
+    router.add_middleware(second);
+
+    let response = router.dispatch(Arc::clone(&engine), request("/trace", &[]));
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, b"first,second,route,");
+    assert_eq!(response.headers[TRACE_HEADER], "route,second,first,");
+
+    // other routes only go through the global middleware
+    let response = router.dispatch(Arc::clone(&engine), request("/other", &[]));
+    assert_eq!(response.body, b"first,second,");
+}
+
+#[test]
+fn test_middleware_short_circuits_chain() {
+    let engine = engine();
+    let mut router = trace_router();
+    router.add_middleware(first);
+    router.add_route_middleware("POST", "/trace", deny);
+    router.add_route_middleware("POST", "/trace", on_route);
+
+    // neither the middleware after it nor the handler run
+    let response = router.dispatch(Arc::clone(&engine), request("/trace", &[]));
+    assert_eq!(response.status, 403);
+    assert_eq!(response.body, b"denied");
+    assert_eq!(response.headers[TRACE_HEADER], "first,");
+
+    let response = router.dispatch(Arc::clone(&engine), request("/other", &[]));
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, b"first,");
+}
+
+#[test]
+fn test_replica_rejects_admin_writes() {
+    let engine = engine();
+    let router = HelixRouter::new(None, None).with_read_only(HashSet::new());
+
+    // restoring or purging the trash and pruning the audit log write to the graph
+    for path in [ADMIN_TRASH_PATH, ADMIN_AUDIT_PATH] {
+        let response = router.dispatch(Arc::clone(&engine), request(path, &[]));
+        assert_eq!(response.status, 403, "{}", path);
+
+        let mut listing = request(path, &[]);
+        listing.method = "GET".to_string();
+        let response = router.dispatch(Arc::clone(&engine), listing);
+        assert_eq!(response.status, 200, "{}", path);
+    }
+}
+
+inventory::submit! { SensitiveField { label: "person", field: "salary", roles: &["hr"] } }
+
+/// An engine keeping an audit log for an hour, which recorded a person being added
+fn audited_engine(dir: &TempDir) -> Arc<HelixGraphEngine> {
+    let mut config = Config::default();
+    config.wal.enabled = true;
+    config.audit.enabled = true;
+    config.audit.retention_secs = Some(3600);
+    let engine = Arc::new(HelixGraphEngine::with_storage(test_utils::open(dir, config)));
+    let storage = &engine.storage;
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    let parameters = br#"{"name":"alice","salary":100}"#;
+    storage
+        .audited(&mut txn, "addPerson", parameters, None, |txn| {
+            G::new_mut(Arc::clone(storage), txn)
+                .add_n("person", Some(props! { "name" => "alice" }), None)
+                .collect_to_val();
+            Ok(())
+        })
+        .unwrap();
+    txn.commit().unwrap();
+    engine
+}
+
+fn admin_request(path: &str, role: &str, body: Option<&str>) -> Request {
+    let token = bearer(role);
+    let mut request = request(path, &[("authorization", &token)]);
+    match body {
+        Some(body) => request.body = body.as_bytes().to_vec(),
+        None => request.method = "GET".to_string(),
+    }
+    request
+}
+
+#[test]
+fn test_admin_endpoints_require_admin_role() {
+    let dir = TempDir::new().unwrap();
+    let engine = audited_engine(&dir);
+    let router = with_auth(HelixRouter::new(None, None));
+
+    for path in [ADMIN_STORAGE_PATH, ADMIN_TRASH_PATH, ADMIN_AUDIT_PATH] {
+        let response = router.dispatch(Arc::clone(&engine), admin_request(path, "hr", None));
+        assert_eq!(response.status, 403, "{}", path);
+        let response = router.dispatch(Arc::clone(&engine), admin_request(path, "admin", None));
+        assert_eq!(response.status, 200, "{}", path);
+    }
+    // nor can others prune the audit log
+    let request = admin_request(ADMIN_AUDIT_PATH, "hr", Some(r#"{"max_entries":0}"#));
+    assert_eq!(router.dispatch(Arc::clone(&engine), request).status, 403);
+}
+
+#[test]
+fn test_audit_entries_kept_for_retention_and_redacted() {
+    let dir = TempDir::new().unwrap();
+    let engine = audited_engine(&dir);
+    let router = with_auth(HelixRouter::new(None, None));
+    let audit = |body| {
+        let response = router.dispatch(
+            Arc::clone(&engine),
+            admin_request(ADMIN_AUDIT_PATH, "admin", body),
+        );
+        assert_eq!(response.status, 200, "{}", String::from_utf8_lossy(&response.body));
+        sonic_rs::from_slice::<AuditResponse>(&response.body).unwrap()
+    };
+
+    // entries younger than the configured retention aren't pruned, whatever is asked for
+    let pruned = audit(Some(r#"{"retention_secs":0,"max_entries":0}"#));
+    assert_eq!(pruned.pruned, 0);
+
+    // the admin role doesn't grant access to the sensitive parameters
+    let listed = audit(None);
+    assert_eq!(listed.entries.len(), 1);
+    assert_eq!(listed.entries[0].parameters, r#"{"name":"alice"}"#);
+}
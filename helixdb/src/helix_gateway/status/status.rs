@@ -0,0 +1,401 @@
+use crate::helix_engine::{
+    graph_core::graph_core::HelixGraphEngine,
+    storage_core::{audit::AuditEntry, map_size::MapUsage, trash::TrashedItem},
+    types::GraphError,
+};
+use crate::protocol::{redaction, request::Request, response::Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Path of the endpoint reporting the health and metrics of the instance
+pub const STATUS_PATH: &str = "/status";
+
+/// Prefix of the admin endpoints, only served to requests with an admin key or role if
+/// the gateway authenticates requests
+pub const ADMIN_PATH: &str = "/admin/";
+
+/// Path of the admin endpoint reporting the usage of the memory map of the database,
+/// which a `POST` to grows right away
+pub const ADMIN_STORAGE_PATH: &str = "/admin/storage";
+
+/// Path of the admin endpoint listing the soft deleted nodes and edges, which a `POST`
+/// restores or purges
+pub const ADMIN_TRASH_PATH: &str = "/admin/trash";
+
+/// Path of the admin endpoint listing the entries of the audit log, which a `POST`
+/// prunes
+pub const ADMIN_AUDIT_PATH: &str = "/admin/audit";
+
+/// Entries listed by a request to [`ADMIN_AUDIT_PATH`] unless it sets a `limit`
+const DEFAULT_AUDIT_LIMIT: usize = 100;
+
+/// Seconds the request rate is averaged over
+const RATE_WINDOW: usize = 60;
+
+/// Requests handled by the gateway, in total and over the last [`RATE_WINDOW`] seconds
+pub struct RequestStats {
+    started: Instant,
+    started_at: DateTime<Utc>,
+    total: AtomicU64,
+    /// Second since start => requests in it, by the second modulo the window
+    window: Mutex<[(u64, u64); RATE_WINDOW]>,
+    /// Connections queued for a worker of the pool
+    enqueued: AtomicU64,
+    /// Connections turned away because the queue was full
+    shed: AtomicU64,
+    /// Connections taken off the queue, along with the microseconds they waited in
+    /// total and at most
+    dequeued: AtomicU64,
+    wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+}
+
+impl Default for RequestStats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: Utc::now(),
+            total: AtomicU64::new(0),
+            window: Mutex::new([(0, 0); RATE_WINDOW]),
+            enqueued: AtomicU64::new(0),
+            shed: AtomicU64::new(0),
+            dequeued: AtomicU64::new(0),
+            wait_micros: AtomicU64::new(0),
+            max_wait_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RequestStats {
+    /// Counts a request handled by the gateway
+    pub fn record(&self) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        let second = self.started.elapsed().as_secs();
+        let mut window = self.window.lock().unwrap();
+        let bucket = &mut window[second as usize % RATE_WINDOW];
+        if bucket.0 != second {
+            *bucket = (second, 0);
+        }
+        bucket.1 += 1;
+    }
+
+    /// Counts a connection queued for a worker
+    pub fn enqueue(&self) {
+        self.enqueued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a connection taken off the queue by a worker after waiting for `wait`
+    pub fn dequeue(&self, wait: Duration) {
+        let wait = wait.as_micros() as u64;
+        self.dequeued.fetch_add(1, Ordering::Relaxed);
+        self.wait_micros.fetch_add(wait, Ordering::Relaxed);
+        self.max_wait_micros.fetch_max(wait, Ordering::Relaxed);
+    }
+
+    /// Counts a connection turned away because the queue was full
+    pub fn shed(&self) {
+        self.shed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn queue(&self) -> QueueStatus {
+        let dequeued = self.dequeued.load(Ordering::Relaxed);
+        let wait_micros = self.wait_micros.load(Ordering::Relaxed);
+        QueueStatus {
+            // a connection can be taken off the queue before it is counted as queued
+            waiting: self
+                .enqueued
+                .load(Ordering::Relaxed)
+                .saturating_sub(dequeued),
+            shed: self.shed.load(Ordering::Relaxed),
+            avg_wait_ms: match dequeued {
+                0 => 0.0,
+                _ => wait_micros as f64 / dequeued as f64 / 1000.0,
+            },
+            max_wait_ms: self.max_wait_micros.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+
+    /// Requests per second over the last [`RATE_WINDOW`] seconds, or since the start
+    /// if it's more recent
+    pub fn rate(&self) -> f64 {
+        let now = self.started.elapsed().as_secs();
+        let oldest = now.saturating_sub(RATE_WINDOW as u64 - 1);
+        let requests = self
+            .window
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(second, _)| *second >= oldest && *second <= now)
+            .map(|(_, count)| count)
+            .sum::<u64>();
+        requests as f64 / (now - oldest + 1) as f64
+    }
+}
+
+/// Health and metrics of an instance, the body of a [`STATUS_PATH`] response
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Status {
+    pub status: String,
+    pub started_at: String,
+    pub uptime_secs: u64,
+    /// Requests handled since the start, not counting those for the status
+    pub requests: u64,
+    pub requests_per_sec: f64,
+    /// Size of the data of the instance on disk
+    pub storage_bytes: u64,
+    pub version: String,
+    /// Connections waiting for a worker, missing from instances predating it
+    #[serde(default)]
+    pub queue: QueueStatus,
+    /// Usage of the memory map of the database, missing from instances predating it
+    #[serde(default)]
+    pub map: MapUsage,
+}
+
+/// Connections queued for the workers of the gateway
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct QueueStatus {
+    /// Connections currently waiting for a worker
+    pub waiting: u64,
+    /// Connections turned away with a 503 because the queue was full
+    pub shed: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
+}
+
+/// Reports the status of the instance
+pub fn handle(
+    graph_access: &HelixGraphEngine,
+    stats: &RequestStats,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let storage = &graph_access.storage;
+    let map = storage.map_size.usage(&storage.graph_env)?;
+    let status = Status {
+        // the map is filling up and hasn't been grown (yet)
+        status: match map.alert {
+            true => "warning".to_string(),
+            false => "ok".to_string(),
+        },
+        started_at: stats.started_at.to_rfc3339(),
+        uptime_secs: stats.started.elapsed().as_secs(),
+        requests: stats.total.load(Ordering::Relaxed),
+        requests_per_sec: stats.rate(),
+        storage_bytes: graph_access.storage.graph_env.real_disk_size()?,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        queue: stats.queue(),
+        map,
+    };
+    response
+        .headers
+        .insert("Content-Type".to_string(), "application/json".to_string());
+    response.body = sonic_rs::to_vec(&status)?;
+    Ok(())
+}
+
+/// Usage of the memory map of an instance, the body of an [`ADMIN_STORAGE_PATH`] response
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StorageStatus {
+    pub map: MapUsage,
+    /// Whether the map is grown in the background
+    pub auto_resize: bool,
+    /// Size the map was grown to by a `POST`, unset if it was already at its largest size
+    pub grown_to: Option<usize>,
+}
+
+/// Reports the usage of the memory map, growing it first for a `POST`
+pub fn handle_storage(
+    graph_access: &HelixGraphEngine,
+    request: &Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let storage = &graph_access.storage;
+    let grown_to = match request.method.as_str() {
+        "GET" => None,
+        "POST" => storage.map_size.grow(&storage.graph_env, true)?,
+        method => {
+            return Err(GraphError::New(format!(
+                "{} is not supported by {}",
+                method, ADMIN_STORAGE_PATH
+            )))
+        }
+    };
+    let status = StorageStatus {
+        map: storage.map_size.usage(&storage.graph_env)?,
+        auto_resize: storage.map_size.is_enabled(),
+        grown_to,
+    };
+    response
+        .headers
+        .insert("Content-Type".to_string(), "application/json".to_string());
+    response.body = sonic_rs::to_vec(&status)?;
+    Ok(())
+}
+
+/// What a `POST` to [`ADMIN_TRASH_PATH`] does with the elements
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashAction {
+    Restore,
+    Purge,
+}
+
+/// Body of a `POST` to [`ADMIN_TRASH_PATH`]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrashRequest {
+    pub action: TrashAction,
+    pub ids: Vec<String>,
+}
+
+/// Lists the elements in the trash, restoring or purging the requested ones first for a
+/// `POST`. The elements of a request are restored or purged in a single transaction, so
+/// none are if one of them can't be.
+pub fn handle_trash(
+    graph_access: &HelixGraphEngine,
+    request: &Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let storage = &graph_access.storage;
+    match request.method.as_str() {
+        "GET" => {}
+        "POST" => {
+            let trash_request: TrashRequest = sonic_rs::from_slice(&request.body)?;
+            let mut txn = storage.graph_env.write_txn()?;
+            for id in &trash_request.ids {
+                let id = uuid::Uuid::parse_str(id)?.as_u128();
+                match trash_request.action {
+                    TrashAction::Restore => storage.restore_trashed(&mut txn, &id)?,
+                    TrashAction::Purge => storage.purge_trashed(&mut txn, &id)?,
+                }
+            }
+            storage.wal.commit(txn)?;
+        }
+        method => {
+            return Err(GraphError::New(format!(
+                "{} is not supported by {}",
+                method, ADMIN_TRASH_PATH
+            )))
+        }
+    }
+    let txn = storage.graph_env.read_txn()?;
+    let items = storage
+        .trash
+        .list(&txn)?
+        .into_iter()
+        .map(|(id, tombstone)| TrashedItem::new(id, tombstone))
+        .collect::<Vec<_>>();
+    response
+        .headers
+        .insert("Content-Type".to_string(), "application/json".to_string());
+    response.body = sonic_rs::to_vec(&items)?;
+    Ok(())
+}
+
+/// Body of a `POST` to [`ADMIN_AUDIT_PATH`], the configured retention and number of
+/// entries kept apply to the fields left unset. Entries younger than the configured
+/// retention are never pruned, whatever the body asks for.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AuditPruneRequest {
+    pub retention_secs: Option<u64>,
+    pub max_entries: Option<u64>,
+}
+
+/// Response of [`ADMIN_AUDIT_PATH`]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditResponse {
+    pub entries: Vec<AuditEntry>,
+    /// Entries removed by a `POST`
+    pub pruned: usize,
+}
+
+/// Lists the entries of the audit log following the seq of an `after` query parameter,
+/// up to a `limit` of them, optionally only those of a `query` or a `caller`. A `POST`
+/// prunes the log first, with the retention and number of entries kept of its body.
+/// Parameters named like a sensitive field are left out unless the roles of the request
+/// grant access to it.
+pub fn handle_audit(
+    graph_access: &HelixGraphEngine,
+    request: &Request,
+    response: &mut Response,
+) -> Result<(), GraphError> {
+    let storage = &graph_access.storage;
+    let pruned = match request.method.as_str() {
+        "GET" => 0,
+        "POST" => {
+            let prune_request: AuditPruneRequest = match request.body.is_empty() {
+                true => AuditPruneRequest::default(),
+                false => sonic_rs::from_slice(&request.body)?,
+            };
+            // the configured retention is the shortest one can ask for, `None` being
+            // shorter than any
+            let kept_for = storage.audit.retention();
+            storage.prune_audit_log_with(
+                prune_request
+                    .retention_secs
+                    .map(Duration::from_secs)
+                    .max(kept_for),
+                prune_request.max_entries.or(storage.audit.max_entries()),
+                kept_for,
+            )?
+        }
+        method => {
+            return Err(GraphError::New(format!(
+                "{} is not supported by {}",
+                method, ADMIN_AUDIT_PATH
+            )))
+        }
+    };
+
+    let param = |name: &str| request.query_param(name);
+    let parse = |name: &str| -> Result<Option<u64>, GraphError> {
+        param(name)
+            .map(|value| {
+                value.parse::<u64>().map_err(|_| {
+                    GraphError::New(format!("Invalid `{}` parameter: {}", name, value))
+                })
+            })
+            .transpose()
+    };
+    let after = parse("after")?.unwrap_or(0);
+    let limit = parse("limit")?.map_or(DEFAULT_AUDIT_LIMIT, |limit| limit as usize);
+    let (query, caller) = (param("query"), param("caller"));
+    let (query, caller) = (query.as_deref(), caller.as_deref());
+
+    let txn = storage.graph_env.read_txn()?;
+    let mut entries = match (query, caller) {
+        (None, None) => storage.audit.list(&txn, after, limit)?,
+        // filtered entries are scanned in pages until enough of them match
+        _ => {
+            let mut entries = Vec::new();
+            let mut after = after;
+            while entries.len() < limit {
+                let page = storage.audit.list(&txn, after, DEFAULT_AUDIT_LIMIT)?;
+                let Some(last) = page.last() else {
+                    break;
+                };
+                after = last.seq;
+                entries.extend(page.into_iter().filter(|entry| {
+                    query.is_none_or(|query| entry.query == query)
+                        && caller.is_none_or(|caller| entry.caller.as_deref() == Some(caller))
+                }));
+            }
+            entries.truncate(limit);
+            entries
+        }
+    };
+    response
+        .headers
+        .insert("Content-Type".to_string(), "application/json".to_string());
+    for entry in &mut entries {
+        entry.parameters = redaction::redact_parameters(&entry.parameters);
+    }
+    response.body = sonic_rs::to_vec(&AuditResponse { entries, pruned })?;
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod subscription;
+
+#[cfg(test)]
+mod subscription_tests;
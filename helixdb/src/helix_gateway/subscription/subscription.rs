@@ -0,0 +1,45 @@
+use crate::helix_engine::cdc::cdc::Subscription;
+use crate::helix_engine::graph_core::graph_core::HelixGraphEngine;
+use crate::protocol::request::Request;
+use crate::protocol::response::Response;
+use crate::protocol::return_values::ReturnValue;
+use tokio::io::{AsyncWrite, AsyncWriteExt, Result};
+
+/// Path clients connect to in order to stream change events
+pub const SUBSCRIBE_PATH: &str = "/subscribe";
+
+/// Subscribes to the change log of the graph for a `/subscribe` request.
+///
+/// An optional `label` query parameter restricts the subscription to items with that
+/// label. Returns `None` if change data capture isn't enabled.
+pub fn subscribe(graph: &HelixGraphEngine, request: &Request) -> Option<Subscription> {
+    if !graph.storage.cdc.is_enabled() {
+        return None;
+    }
+    let label = request.query_param("label");
+    Some(graph.storage.cdc.subscribe(label.as_deref()))
+}
+
+/// Streams change events to the client as newline delimited JSON until the client
+/// disconnects or the subscription is closed.
+pub async fn stream_events<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    subscription: Subscription,
+) -> Result<()> {
+    let header = format!(
+        "HTTP/1.1 200 {}\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n",
+        Response::status_message(200)
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.flush().await?;
+
+    while let Ok(event) = subscription.receiver.recv_async().await {
+        let mut line = sonic_rs::to_vec(&ReturnValue::from(event)).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        })?;
+        line.push(b'\n');
+        stream.write_all(&line).await?;
+        stream.flush().await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use crate::{
+    helix_engine::{
+        cdc::cdc::Subscription,
+        graph_core::{
+            config::Config,
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+            ops::{g::G, source::add_n::AddNAdapter},
+        },
+    },
+    helix_gateway::subscription::subscription::subscribe,
+    protocol::request::Request,
+};
+
+fn engine(cdc: bool) -> HelixGraphEngine {
+    let mut config = Config::default();
+    config.cdc = cdc;
+    HelixGraphEngine::new(HelixGraphEngineOpts {
+        config,
+        ..HelixGraphEngineOpts::in_memory()
+    })
+    .unwrap()
+}
+
+fn request(query: Option<&str>) -> Request {
+    Request {
+        method: "GET".to_string(),
+        headers: HashMap::new(),
+        path: "/subscribe".to_string(),
+        query: query.map(|query| query.to_string()),
+        version: "HTTP/1.1".to_string(),
+        body: Vec::new(),
+        claims: None,
+    }
+}
+
+fn add_nodes(engine: &HelixGraphEngine, labels: &[&str]) {
+    let mut txn = engine.storage.graph_env.write_txn().unwrap();
+    for label in labels {
+        G::new_mut(Arc::clone(&engine.storage), &mut txn)
+            .add_n(label, None, None)
+            .collect_to_val();
+    }
+    txn.commit().unwrap();
+}
+
+/// Labels of the next `count` events pushed to the subscriber
+fn labels(subscription: &Subscription, count: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| {
+            subscription
+                .receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("change event should be pushed to the subscriber")
+                .label
+        })
+        .collect()
+}
+
+#[test]
+fn test_subscribers_filtered_by_decoded_label() {
+    let engine = engine(true);
+    let filtered = subscribe(&engine, &request(Some("label=team%20member"))).unwrap();
+    let unfiltered = subscribe(&engine, &request(None)).unwrap();
+    assert_eq!(filtered.label_filter.as_deref(), Some("team member"));
+    assert_eq!(unfiltered.label_filter, None);
+
+    add_nodes(&engine, &["person", "team member", "company"]);
+
+    assert_eq!(labels(&filtered, 1), ["team member"]);
+    assert_eq!(labels(&unfiltered, 3), ["person", "team member", "company"]);
+    // nothing else matches the filter
+    assert!(filtered
+        .receiver
+        .recv_timeout(Duration::from_millis(200))
+        .is_err());
+}
+
+#[test]
+fn test_no_subscription_without_cdc() {
+    let engine = engine(false);
+    assert!(subscribe(&engine, &request(Some("label=person"))).is_none());
+}
@@ -1,14 +1,197 @@
 use crate::helix_engine::graph_core::graph_core::HelixGraphEngine;
-use flume::{Receiver, Sender};
+use flume::{Receiver, Sender, TrySendError};
+use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::watch;
 use crate::helix_runtime::AsyncRuntime;
 
-use crate::helix_gateway::router::router::{HelixRouter, RouterError};
+use crate::helix_gateway::connection::limits::{retry_after_secs, ConnectionLimits};
+use crate::helix_gateway::gateway::GatewayOpts;
+use crate::helix_gateway::router::router::{set_error, HelixRouter, RouterError};
+use crate::helix_gateway::status::status::RequestStats;
+use crate::helix_gateway::subscription::subscription::{stream_events, subscribe, SUBSCRIBE_PATH};
+use crate::helix_replication::primary::{replicate, stream_log, REPLICATE_PATH};
 use crate::protocol::request::Request;
 use crate::protocol::response::Response;
 
 use crate::helix_transport::Stream;
 
+/// A client connection with a request ready to be read, waiting for a worker
+pub struct Connection<S> {
+    /// The same buffered reader is kept for the whole connection so bytes of pipelined
+    /// requests aren't lost between requests
+    pub reader: BufReader<S>,
+    pub addr: SocketAddr,
+    pub queued_at: Instant,
+}
+
+/// Hands connections to the workers of the pool once their client has sent a request.
+///
+/// Workers only hold on to a connection while answering the requests it has sent, idle
+/// keep-alive connections wait for their next request in a task of their own.
+pub struct ConnectionQueue<S> {
+    sender: Sender<Connection<S>>,
+    stats: Arc<RequestStats>,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl<S> Clone for ConnectionQueue<S> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            stats: Arc::clone(&self.stats),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
+
+impl<S: Stream + 'static> ConnectionQueue<S> {
+    /// Waits for the client to send its next request and queues the connection for a
+    /// worker.
+    ///
+    /// The connection is closed if the client sends nothing for the idle timeout of the
+    /// stream or the gateway shuts down meanwhile, and is answered with a 503 if the
+    /// queue is full.
+    pub async fn wait_for_request<R: AsyncRuntime>(
+        mut self,
+        mut reader: BufReader<S>,
+        addr: SocketAddr,
+        runtime: R,
+    ) {
+        let idle_timeout = reader.get_ref().idle_timeout();
+        let timed_out = async {
+            match idle_timeout {
+                Some(timeout) => runtime.sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        let readable = tokio::select! {
+            filled = reader.fill_buf() => matches!(filled, Ok(buf) if !buf.is_empty()),
+            _ = timed_out => false,
+            Ok(_) = self.shutdown.wait_for(|stop| *stop) => false,
+        };
+        if !readable {
+            return;
+        }
+        // connections are turned away rather than queued without bound when the
+        // workers can't keep up
+        let connection = Connection {
+            reader,
+            addr,
+            queued_at: Instant::now(),
+        };
+        match self.sender.try_send(connection) {
+            Ok(()) => self.stats.enqueue(),
+            Err(TrySendError::Full(mut connection)) => {
+                self.stats.shed();
+                let _ = overloaded().send(&mut connection.reader).await;
+            }
+            // the workers have stopped
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+/// Writes the response to the client, returning whether the connection is kept open
+/// for the client's next request. The connection is closed after the response if the
+/// gateway is shutting down.
+async fn send_response<S: Stream>(
+    conn: &mut BufReader<S>,
+    mut response: Response,
+    keep_alive: bool,
+    shutdown: &watch::Receiver<bool>,
+) -> bool {
+    let keep_alive = keep_alive && !*shutdown.borrow();
+    response.headers.insert(
+        "Connection".to_string(),
+        if keep_alive { "keep-alive" } else { "close" }.to_string(),
+    );
+
+    if let Err(e) = response.send(conn).await {
+        eprintln!("Error sending response: {:?}", e);
+        match e.kind() {
+            std::io::ErrorKind::BrokenPipe => {
+                eprintln!("Client disconnected before response could be sent");
+            }
+            std::io::ErrorKind::ConnectionReset => {
+                eprintln!("Connection was reset by peer");
+            }
+            _ => {
+                eprintln!("Unexpected error type: {:?}", e);
+            }
+        }
+        return false;
+    }
+    keep_alive
+}
+
+/// Response to connections turned away because every worker is busy and the queue is
+/// full
+fn overloaded() -> Response {
+    let mut response = Response::new();
+    response.status = 503;
+    response.body = b"503 - Service Unavailable".to_vec();
+    response
+        .headers
+        .insert("Retry-After".to_string(), "1".to_string());
+    response
+        .headers
+        .insert("Connection".to_string(), "close".to_string());
+    response
+}
+
+/// Connections streamed to from tasks of their own rather than by a worker, those of
+/// change event subscribers and replicas.
+///
+/// The tasks close their connections once the gateway shuts down, which waits for them
+/// with [`Self::closed`].
+#[derive(Clone)]
+pub struct OpenStreams {
+    count: Arc<watch::Sender<usize>>,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl OpenStreams {
+    fn new(shutdown: watch::Receiver<bool>) -> Self {
+        Self {
+            count: Arc::new(watch::Sender::new(0)),
+            shutdown,
+        }
+    }
+
+    /// Runs the stream in a task of its own until it ends or the gateway shuts down,
+    /// dropping its connection either way
+    fn spawn<R, F>(&self, runtime: &R, stream: F)
+    where
+        R: AsyncRuntime,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.count.send_modify(|count| *count += 1);
+        let count = Arc::clone(&self.count);
+        let mut shutdown = self.shutdown.clone();
+        drop(runtime.spawn(async move {
+            tokio::select! {
+                _ = stream => {}
+                Ok(_) = shutdown.wait_for(|stop| *stop) => {}
+            }
+            count.send_modify(|count| *count -= 1);
+        }));
+    }
+
+    /// Number of streams still open
+    pub fn count(&self) -> usize {
+        *self.count.borrow()
+    }
+
+    /// Waits for every stream to be closed
+    pub async fn closed(&self) {
+        let _ = self.count.subscribe().wait_for(|count| *count == 0).await;
+    }
+}
+
 pub struct Worker<R: AsyncRuntime, S: Stream> {
     pub id: usize,
     pub handle: <R as AsyncRuntime>::JoinHandle<()>,
@@ -17,50 +200,164 @@ pub struct Worker<R: AsyncRuntime, S: Stream> {
 }
 
 impl<R: AsyncRuntime + Clone + Send + Sync + 'static, S: Stream + 'static> Worker<R, S> {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         id: usize,
         graph_access: Arc<HelixGraphEngine>,
         router: Arc<HelixRouter>,
-        rx: Receiver<S>,
+        limits: Arc<ConnectionLimits>,
+        queue: ConnectionQueue<S>,
+        streams: OpenStreams,
+        rx: Receiver<Connection<S>>,
         runtime: R,
     ) -> Worker<R, S> {
+        let mut shutdown = queue.shutdown.clone();
+        let worker_runtime = runtime.clone();
         let handle = runtime.spawn(async move {
+            let runtime = worker_runtime;
             loop {
-                let mut conn = match rx.recv_async().await {
-                    Ok(stream) => stream,
-                    Err(e) => {
-                        eprintln!("Error receiving connection: {:?}", e);
-                        continue;
-                    }
+                // connections still queued on shutdown are closed without being read
+                let connection = tokio::select! {
+                    conn = rx.recv_async() => match conn {
+                        Ok(conn) => conn,
+                        Err(e) => {
+                            eprintln!("Error receiving connection: {:?}", e);
+                            continue;
+                        }
+                    },
+                    Ok(_) = shutdown.wait_for(|stop| *stop) => break,
                 };
+                router.stats.dequeue(connection.queued_at.elapsed());
+                let Connection {
+                    reader: mut conn,
+                    addr,
+                    ..
+                } = connection;
 
-                let request = match Request::from_stream(&mut conn).await {
-                    Ok(request) => request,
-                    Err(e) => {
-                        eprintln!("Error parsing request: {:?}", e);
-                        continue;
+                // pipelined requests are answered in order before the connection is let go
+                loop {
+                    // a request that has started coming in has to be complete within
+                    // the keep-alive timeout, whatever the idle timeout of the stream
+                    let read = Request::from_reader_with_limit(&mut conn, limits.max_body_size);
+                    let request = tokio::select! {
+                        request = read => request,
+                        _ = runtime.sleep(GatewayOpts::KEEP_ALIVE_TIMEOUT) => break,
+                        Ok(_) = shutdown.wait_for(|stop| *stop) => break,
+                    };
+                    let mut request = match request {
+                        Ok(Some(request)) => request,
+                        // client closed the connection
+                        Ok(None) => break,
+                        Err(e) => {
+                            eprintln!("Error parsing request: {:?}", e);
+                            let mut response = Response::new();
+                            response.status = match e.kind() {
+                                std::io::ErrorKind::FileTooLarge => 413,
+                                _ => 400,
+                            };
+                            response.body = format!("Invalid request: {}", e).into_bytes();
+                            response
+                                .headers
+                                .insert("Connection".to_string(), "close".to_string());
+                            let _ = response.send(&mut conn).await;
+                            break;
+                        }
+                    };
+                    let keep_alive = request.keep_alive();
+                    if let Some(rate_limiter) = &limits.rate_limiter {
+                        if let Err(retry_after) = rate_limiter.check(addr.ip()) {
+                            let mut response = Response::new();
+                            response.status = 429;
+                            response.body = b"429 - Too Many Requests".to_vec();
+                            response.headers.insert(
+                                "Retry-After".to_string(),
+                                retry_after_secs(retry_after).to_string(),
+                            );
+                            response.headers.insert(
+                                "Connection".to_string(),
+                                if keep_alive { "keep-alive" } else { "close" }.to_string(),
+                            );
+                            if response.send(&mut conn).await.is_err() || !keep_alive {
+                                break;
+                            }
+                            continue;
+                        }
                     }
-                };
-
-                let mut response = Response::new();
-                if let Err(e) = router.handle(Arc::clone(&graph_access), request, &mut response) {
-                    eprintln!("Error handling request: {:?}", e);
-                    response.status = 500;
-                    response.body = format!("\n{:?}", e).into_bytes();
-                }
-
-                if let Err(e) = response.send(&mut conn).await {
-                    eprintln!("Error sending response: {:?}", e);
-                    match e.kind() {
-                        std::io::ErrorKind::BrokenPipe => {
-                            eprintln!("Client disconnected before response could be sent");
+                    if request.path == SUBSCRIBE_PATH {
+                        if let Err(mut response) = router.authenticate(&mut request) {
+                            let _ = response.send(&mut conn).await;
+                            break;
                         }
-                        std::io::ErrorKind::ConnectionReset => {
-                            eprintln!("Connection was reset by peer");
+                        match subscribe(&graph_access, &request) {
+                            Some(subscription) => {
+                                // the subscriber holds on to the connection, so it is
+                                // streamed to from its own task to free up this worker
+                                streams.spawn(&runtime, async move {
+                                    if let Err(e) = stream_events(&mut conn, subscription).await {
+                                        eprintln!("Change event stream closed: {:?}", e);
+                                    }
+                                });
+                            }
+                            None => {
+                                let mut response = Response::new();
+                                response.status = 404;
+                                let _ = response.send(&mut conn).await;
+                            }
+                        }
+                        break;
+                    }
+
+                    if request.path == REPLICATE_PATH {
+                        if let Err(mut response) = router.authenticate(&mut request) {
+                            let _ = response.send(&mut conn).await;
+                            break;
                         }
-                        _ => {
-                            eprintln!("Unexpected error type: {:?}", e);
+                        match replicate(&graph_access, &request) {
+                            // streamed from its own task like change events
+                            Ok(log) => streams.spawn(&runtime, async move {
+                                if let Err(e) = stream_log(&mut conn, log).await {
+                                    eprintln!("Replication stream closed: {:?}", e);
+                                }
+                            }),
+                            Err(mut response) => {
+                                let _ = response.send(&mut conn).await;
+                            }
                         }
+                        break;
+                    }
+
+                    if router.is_async_route(&request) {
+                        // the handler may wait on I/O, so it is run and answered from a
+                        // task of its own, which carries on with the connection after
+                        let handler = router.handle_async(Arc::clone(&graph_access), request);
+                        let queue = queue.clone();
+                        let task_runtime = runtime.clone();
+                        drop(runtime.spawn(async move {
+                            let response = handler.await.unwrap_or_else(|e| {
+                                let mut response = Response::new();
+                                set_error(&mut response, e);
+                                response
+                            });
+                            let shutdown = queue.shutdown.clone();
+                            if send_response(&mut conn, response, keep_alive, &shutdown).await {
+                                queue.wait_for_request(conn, addr, task_runtime).await;
+                            }
+                        }));
+                        break;
+                    }
+
+                    let response = router.dispatch(Arc::clone(&graph_access), request);
+                    if !send_response(&mut conn, response, keep_alive, &shutdown).await {
+                        break;
+                    }
+                    if conn.buffer().is_empty() {
+                        // the worker is freed up while the client is idle
+                        drop(runtime.spawn(queue.clone().wait_for_request(
+                            conn,
+                            addr,
+                            runtime.clone(),
+                        )));
+                        break;
                     }
                 }
             }
@@ -76,7 +373,10 @@ impl<R: AsyncRuntime + Clone + Send + Sync + 'static, S: Stream + 'static> Worke
 }
 
 pub struct ThreadPool<R: AsyncRuntime, S: Stream> {
-    pub sender: Sender<S>,
+    /// Queue of the connections waiting for a worker
+    pub queue: ConnectionQueue<S>,
+    /// Connections streamed to by tasks the workers spawned
+    pub streams: OpenStreams,
     pub num_unused_workers: Mutex<usize>,
     pub num_used_workers: Mutex<usize>,
     pub workers: Vec<Worker<R, S>>,
@@ -87,6 +387,8 @@ impl<R: AsyncRuntime + Clone + Send + Sync + 'static, S: Stream + 'static> Threa
         size: usize,
         graph: Arc<HelixGraphEngine>,
         router: Arc<HelixRouter>,
+        limits: Arc<ConnectionLimits>,
+        shutdown: watch::Receiver<bool>,
         runtime: R,
     ) -> Result<ThreadPool<R, S>, RouterError> {
         assert!(
@@ -95,13 +397,25 @@ impl<R: AsyncRuntime + Clone + Send + Sync + 'static, S: Stream + 'static> Threa
             size
         );
 
-        let (tx, rx) = flume::bounded::<S>(1000);
+        let depth = limits
+            .max_queued
+            .unwrap_or(GatewayOpts::DEFAULT_QUEUE_DEPTH);
+        let (tx, rx) = flume::bounded::<Connection<S>>(depth);
+        let streams = OpenStreams::new(shutdown.clone());
+        let queue = ConnectionQueue {
+            sender: tx,
+            stats: Arc::clone(&router.stats),
+            shutdown,
+        };
         let mut workers = Vec::with_capacity(size);
         for id in 0..size {
             workers.push(Worker::new(
                 id,
                 Arc::clone(&graph),
                 Arc::clone(&router),
+                Arc::clone(&limits),
+                queue.clone(),
+                streams.clone(),
                 rx.clone(),
                 runtime.clone(),
             ));
@@ -109,7 +423,8 @@ impl<R: AsyncRuntime + Clone + Send + Sync + 'static, S: Stream + 'static> Threa
         println!("Thread pool initialized with {} workers", workers.len());
 
         Ok(ThreadPool {
-            sender: tx,
+            queue,
+            streams,
             num_unused_workers: Mutex::new(size),
             num_used_workers: Mutex::new(0),
             runtime: runtime,
@@ -0,0 +1,5 @@
+pub mod primary;
+pub mod replica;
+
+#[cfg(test)]
+mod replication_tests;
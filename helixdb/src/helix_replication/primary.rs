@@ -0,0 +1,158 @@
+use crate::helix_engine::graph_core::graph_core::HelixGraphEngine;
+use crate::helix_engine::storage_core::wal::{WalEntry, WalTail, WriteAheadLog};
+use crate::helix_engine::types::GraphError;
+use crate::protocol::request::Request;
+use crate::protocol::response::Response;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncWrite, AsyncWriteExt, Result};
+
+/// Path replicas connect to in order to follow the write-ahead log
+pub const REPLICATE_PATH: &str = "/replicate";
+/// How often the log file is checked for new entries
+pub const POLL_INTERVAL: Duration = HelixGraphEngine::WAL_SHIP_INTERVAL;
+/// Longest time without sending anything to a replica, an empty frame is sent instead so
+/// both sides notice a dead connection
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The write-ahead log of the primary, positioned after the last entry a replica has
+pub struct LogStream {
+    tail: WalTail,
+    /// Entries read while checking the log covers the replica
+    backlog: Vec<WalEntry>,
+    /// seq of the last entry sent
+    last_seq: u64,
+}
+
+impl LogStream {
+    /// Reads the entries appended to the log file that the replica doesn't have yet
+    fn next_entries(&mut self) -> std::io::Result<Vec<WalEntry>> {
+        let mut entries = std::mem::take(&mut self.backlog);
+        entries.extend(
+            self.tail
+                .next_entries()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?,
+        );
+        entries.retain(|entry| entry.seq > self.last_seq);
+        if let Some(entry) = entries.last() {
+            self.last_seq = entry.seq;
+        }
+        Ok(entries)
+    }
+}
+
+/// Opens the write-ahead log for a `/replicate` request.
+///
+/// The `after` query parameter is the seq of the last entry the replica contains, the
+/// replica is sent every entry after it. Returns the response rejecting the request if
+/// the log isn't enabled, doesn't go back far enough or the replica is ahead of the
+/// primary, in which case the replica has to be seeded from a backup of the primary. The
+/// same goes for primaries with data written before the log was enabled, which isn't in
+/// the log.
+pub fn replicate(
+    graph: &HelixGraphEngine,
+    request: &Request,
+) -> std::result::Result<LogStream, Response> {
+    let error = |status: u16, message: String| {
+        let mut response = Response::new();
+        response.status = status;
+        response.body = message.into_bytes();
+        response
+    };
+    if !graph.storage.wal.is_enabled() {
+        return Err(error(
+            404,
+            "404 - Write-ahead log isn't enabled".to_string(),
+        ));
+    }
+    let after = request
+        .query
+        .as_deref()
+        .and_then(|query| {
+            query
+                .split('&')
+                .filter_map(|param| param.split_once('='))
+                .find(|(key, _)| *key == "after")
+                .map(|(_, value)| value.parse::<u64>())
+        })
+        .unwrap_or(Ok(0))
+        .map_err(|e| error(400, format!("Invalid `after`: {}", e)))?;
+    let last_seq = {
+        // the request isn't dispatched by the router, which would hold the map for it
+        let _hold = graph.storage.map_size.hold();
+        graph
+            .storage
+            .graph_env
+            .read_txn()
+            .map_err(GraphError::from)
+            .and_then(|txn| graph.storage.wal.last_seq(&txn))
+            .map_err(|e| error(500, format!("Error reading write-ahead log: {}", e)))?
+    };
+    // e.g. a deposed leader of a cluster with writes no other node received
+    if after > last_seq {
+        return Err(error(
+            409,
+            format!(
+                "The replica is at entry {}, ahead of the primary at entry {}",
+                after, last_seq
+            ),
+        ));
+    }
+
+    let mut tail = graph
+        .storage
+        .wal
+        .tail()
+        .map_err(|e| error(500, format!("Error opening write-ahead log: {}", e)))?;
+    let mut backlog = tail
+        .next_entries()
+        .map_err(|e| error(500, format!("Error reading write-ahead log: {}", e)))?;
+    backlog.retain(|entry| entry.seq > after);
+    match backlog.first() {
+        Some(entry) if entry.seq > after + 1 => Err(error(
+            409,
+            format!(
+                "Write-ahead log starts at entry {}, the replica is at entry {}",
+                entry.seq, after
+            ),
+        )),
+        _ => Ok(LogStream {
+            tail,
+            backlog,
+            last_seq: after,
+        }),
+    }
+}
+
+/// Streams the entries of the log to a replica as they are written, framed as in the
+/// log file, until the replica disconnects.
+pub async fn stream_log<W: AsyncWrite + Unpin>(stream: &mut W, mut log: LogStream) -> Result<()> {
+    let header = format!(
+        "HTTP/1.1 200 {}\r\nContent-Type: application/octet-stream\r\nConnection: close\r\n\r\n",
+        Response::status_message(200)
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.flush().await?;
+
+    let mut last_sent = Instant::now();
+    loop {
+        let entries = log.next_entries()?;
+        if entries.is_empty() {
+            if last_sent.elapsed() >= HEARTBEAT_INTERVAL {
+                stream.write_all(&WriteAheadLog::frame(&[])).await?;
+                stream.flush().await?;
+                last_sent = Instant::now();
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+            continue;
+        }
+        let mut buffer = Vec::new();
+        for entry in entries.iter() {
+            let payload = bincode::serialize(entry)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+            buffer.extend_from_slice(&WriteAheadLog::frame(&payload));
+        }
+        stream.write_all(&buffer).await?;
+        stream.flush().await?;
+        last_sent = Instant::now();
+    }
+}
@@ -0,0 +1,135 @@
+use crate::helix_engine::storage_core::{storage_core::HelixGraphStorage, wal::WalEntry};
+use crate::helix_engine::types::GraphError;
+use crate::helix_gateway::auth::auth::API_KEY_HEADER;
+use crate::helix_replication::primary::{HEARTBEAT_INTERVAL, REPLICATE_PATH};
+use crate::helix_transport::Transport;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+/// Follows the write-ahead log of a primary and applies its entries to the storage of a
+/// read-only replica.
+///
+/// Only writes journaled in the log are replicated, which doesn't include vectors.
+pub struct Replica<T: Transport> {
+    primary: SocketAddr,
+    api_key: Option<String>,
+    storage: Arc<HelixGraphStorage>,
+    transport: T,
+}
+
+impl<T: Transport> Replica<T> {
+    /// How long to wait before reconnecting after the connection to the primary failed
+    pub const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+    /// The primary is considered gone if it doesn't send anything for this long
+    pub const READ_TIMEOUT: Duration = HEARTBEAT_INTERVAL.saturating_mul(3);
+    /// Most entries applied in a single write transaction
+    pub const MAX_BATCH_SIZE: usize = 1024;
+
+    pub fn new(
+        primary: SocketAddr,
+        api_key: Option<String>,
+        storage: Arc<HelixGraphStorage>,
+        transport: T,
+    ) -> Self {
+        Self {
+            primary,
+            api_key,
+            storage,
+            transport,
+        }
+    }
+
+    /// Follows the primary for as long as the replica runs, reconnecting whenever the
+    /// connection is lost
+    pub async fn run(self) {
+        // writes are left to the primary from now on
+        self.storage.wal.set_following();
+        loop {
+            if let Err(e) = self.follow().await {
+                eprintln!("Lost connection to primary {}: {}", self.primary, e);
+            }
+            tokio::time::sleep(Self::RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Requests the entries after the last applied one and applies them until the
+    /// connection fails
+    pub async fn follow(&self) -> Result<(), GraphError> {
+        let after = {
+            let _hold = self.storage.map_size.hold();
+            let txn = self.storage.graph_env.read_txn()?;
+            self.storage.wal.last_seq(&txn)?
+        };
+        let stream = self.transport.connect(self.primary).await?;
+        let mut stream = BufReader::new(stream);
+
+        let mut request = format!(
+            "GET {}?after={} HTTP/1.1\r\nHost: {}\r\n",
+            REPLICATE_PATH, after, self.primary
+        );
+        if let Some(api_key) = &self.api_key {
+            request.push_str(&format!("{}: {}\r\n", API_KEY_HEADER, api_key));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+        stream.flush().await?;
+
+        // the status line followed by the headers
+        let mut status_line = String::new();
+        stream.read_line(&mut status_line).await?;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if stream.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+                break;
+            }
+        }
+        if status_line.split_whitespace().nth(1) != Some("200") {
+            let mut body = Vec::new();
+            let _ = stream.read_to_end(&mut body).await;
+            return Err(GraphError::New(format!(
+                "Primary refused replication: {} {}",
+                status_line.trim(),
+                String::from_utf8_lossy(&body)
+            )));
+        }
+        println!("Replicating from {} after entry {}", self.primary, after);
+
+        let mut batch = Vec::new();
+        loop {
+            let entry = tokio::time::timeout(Self::READ_TIMEOUT, read_entry(&mut stream))
+                .await
+                .map_err(|_| GraphError::New("Timed out waiting for the primary".to_string()))??;
+            if let Some(entry) = entry {
+                batch.push(entry);
+            }
+            // entries that already arrived are applied together
+            if !batch.is_empty()
+                && (stream.buffer().is_empty() || batch.len() >= Self::MAX_BATCH_SIZE)
+            {
+                let hold = self.storage.map_size.hold();
+                self.storage.apply_replicated(&batch)?;
+                drop(hold);
+                batch.clear();
+            }
+        }
+    }
+}
+
+/// Reads the next framed entry, `None` for heartbeats
+async fn read_entry<R: AsyncBufReadExt + Unpin>(
+    stream: &mut R,
+) -> Result<Option<WalEntry>, GraphError> {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).await?;
+    let len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+    let checksum = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    if len == 0 {
+        return Ok(None);
+    }
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+    Ok(Some(WalEntry::from_frame(checksum, &payload)?))
+}
@@ -0,0 +1,140 @@
+use std::{net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::{Config, FsyncPolicy},
+            graph_core::{HelixGraphEngine, HelixGraphEngineOpts},
+            ops::{g::G, source::n_from_type::NFromTypeAdapter, tr_val::Traversable},
+        },
+        storage_core::{storage_core::HelixGraphStorage, test_utils},
+    },
+    helix_gateway::{
+        connection::limits::ConnectionLimits, gateway::HelixGateway, router::router::HelixRouter,
+    },
+    helix_replication::replica::Replica,
+    helix_runtime::tokio_runtime::TokioRuntime,
+    helix_transport::tokio_transport::TokioTransport,
+};
+
+fn wal_config(log_dir: &Path) -> Config {
+    let mut config = Config::default();
+    config.wal.enabled = true;
+    config.wal.fsync = FsyncPolicy::Always;
+    config.wal.dir = Some(log_dir.to_str().unwrap().to_string());
+    config
+}
+
+/// A primary keeping its write-ahead log in `dir`, served on a free port
+async fn primary(
+    dir: &TempDir,
+    config: Config,
+) -> (
+    Arc<HelixGraphEngine>,
+    SocketAddr,
+    HelixGateway<TokioRuntime, TokioTransport>,
+) {
+    let graph = Arc::new(
+        HelixGraphEngine::new(HelixGraphEngineOpts {
+            config,
+            ..HelixGraphEngineOpts::with_path(dir.path().to_str().unwrap().to_string())
+        })
+        .unwrap(),
+    );
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let gateway = HelixGateway::with_router(
+        &addr.to_string(),
+        Arc::clone(&graph),
+        1,
+        HelixRouter::new(None, None),
+        ConnectionLimits::default(),
+        TokioRuntime,
+        TokioTransport,
+    )
+    .await;
+    drop(gateway.connection_handler.accept_conns().await.unwrap());
+    (graph, addr, gateway)
+}
+
+fn user_ids(storage: &Arc<HelixGraphStorage>) -> Vec<u128> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    let mut ids = G::new(Arc::clone(storage), &txn)
+        .n_from_type("user")
+        .collect_to::<Vec<_>>()
+        .iter()
+        .map(|node| node.id())
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+fn last_seq(storage: &HelixGraphStorage) -> u64 {
+    let txn = storage.graph_env.read_txn().unwrap();
+    storage.wal.last_seq(&txn).unwrap()
+}
+
+/// Waits for the replica to hold the same users as the primary
+async fn caught_up(replica: &Arc<HelixGraphStorage>, primary: &Arc<HelixGraphStorage>) {
+    let expected = user_ids(primary);
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while user_ids(replica) != expected {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("replica should catch up with the primary");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_replica_applies_stream_and_resumes() {
+    let (primary_dir, primary_log) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    let (graph, addr, _gateway) = primary(&primary_dir, wal_config(primary_log.path())).await;
+    let (replica_dir, replica_log) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    let storage = test_utils::open(&replica_dir, wal_config(replica_log.path()));
+    let follow = || {
+        let replica = Replica::new(addr, None, Arc::clone(&storage), TokioTransport);
+        tokio::spawn(async move { replica.follow().await })
+    };
+
+    // writes made before and while the replica follows are both applied
+    test_utils::add_users(&graph.storage, 3);
+    let following = follow();
+    caught_up(&storage, &graph.storage).await;
+    test_utils::add_users(&graph.storage, 2);
+    caught_up(&storage, &graph.storage).await;
+    assert_eq!(user_ids(&storage).len(), 5);
+
+    // nothing arrives while disconnected
+    following.abort();
+    assert!(following.await.unwrap_err().is_cancelled());
+    test_utils::add_users(&graph.storage, 4);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(user_ids(&storage).len(), 5);
+
+    // the replica picks up after the last entry it applied, without gaps or repeats
+    let following = follow();
+    caught_up(&storage, &graph.storage).await;
+    assert_eq!(user_ids(&storage).len(), 9);
+    assert_eq!(last_seq(&storage), last_seq(&graph.storage));
+    following.abort();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_primary_without_log_refuses_replica() {
+    let dir = TempDir::new().unwrap();
+    let (_graph, addr, _gateway) = primary(&dir, Config::default()).await;
+    let (replica_dir, replica_log) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    let storage = test_utils::open(&replica_dir, wal_config(replica_log.path()));
+
+    let replica = Replica::new(addr, None, storage, TokioTransport);
+    let error = tokio::time::timeout(Duration::from_secs(5), replica.follow())
+        .await
+        .expect("the refusal should end following")
+        .unwrap_err();
+    assert!(error.to_string().contains("404"), "{}", error);
+}
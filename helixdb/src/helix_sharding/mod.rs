@@ -0,0 +1,2 @@
+pub mod shard_map;
+pub mod shard_router;
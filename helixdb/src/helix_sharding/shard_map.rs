@@ -0,0 +1,55 @@
+use crate::helix_engine::{graph_core::config::ShardingConfig, types::GraphError};
+use twox_hash::XxHash64;
+
+/// Which shard of a graph split across instances owns each node
+#[derive(Debug, Clone)]
+pub struct ShardMap {
+    /// Index of the local shard
+    pub shard: usize,
+    /// Addresses of the gateways of all shards
+    pub addresses: Vec<String>,
+}
+
+impl ShardMap {
+    pub fn new(config: &ShardingConfig) -> Result<ShardMap, GraphError> {
+        if config.shard >= config.shards.len() {
+            return Err(GraphError::New(format!(
+                "Shard {} isn't one of the {} shards",
+                config.shard,
+                config.shards.len()
+            )));
+        }
+        Ok(ShardMap {
+            shard: config.shard,
+            addresses: config.shards.clone(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// Index of the shard owning the node with the id
+    pub fn shard_of(&self, id: u128) -> usize {
+        (XxHash64::oneshot(0, &id.to_be_bytes()) % self.addresses.len() as u64) as usize
+    }
+
+    pub fn is_local(&self, id: u128) -> bool {
+        self.shard_of(id) == self.shard
+    }
+
+    /// Generates ids until one is owned by the local shard, which takes as many tries
+    /// as there are shards on average
+    pub fn local_id(&self, mut generate: impl FnMut() -> u128) -> u128 {
+        loop {
+            let id = generate();
+            if self.is_local(id) {
+                return id;
+            }
+        }
+    }
+}
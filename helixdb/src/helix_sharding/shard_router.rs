@@ -0,0 +1,233 @@
+use crate::helix_cluster::rpc::call_with_timeout;
+use crate::helix_engine::{
+    graph_core::{
+        graph_core::HelixGraphEngine,
+        ops::{g::G, source::n_from_type::NFromTypeAdapter, vectors::search::SearchVAdapter},
+    },
+    storage_core::storage_methods::StorageMethods,
+    types::GraphError,
+    vector_core::vector::HVector,
+};
+use crate::helix_gateway::router::router::{AsyncHandlerFn, HandlerInput};
+use crate::helix_sharding::shard_map::ShardMap;
+use crate::helix_storage::heed3::RoTxn;
+use crate::helix_transport::Transport;
+use crate::protocol::{response::Response, return_values::ReturnValue};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use sonic_rs::{JsonContainerTrait, JsonValueTrait, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Path returning the nodes of a label from every shard
+pub const SCAN_PATH: &str = "/shards/nodes";
+/// Path returning the closest vectors over all shards
+pub const SEARCH_PATH: &str = "/shards/search";
+/// Path returning a node from the shard that owns it
+pub const NODE_PATH: &str = "/shards/node";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScanRequest {
+    pub label: String,
+    /// Only scan the shard receiving the request, set on the requests it scatters
+    #[serde(default)]
+    pub local: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchRequest {
+    pub vector: Vec<f64>,
+    pub k: usize,
+    /// Only search the shard receiving the request, set on the requests it scatters
+    #[serde(default)]
+    pub local: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NodeRequest {
+    pub id: String,
+}
+
+/// Serves requests over a graph split across instances by sending them on to the other
+/// shards and merging their results with those of the local shard.
+///
+/// Scans of a label and vector searches go to every shard, lookups of a node by id only
+/// to the shard owning it. Queries still run against the local shard only, so edges are
+/// only followed between nodes of the same shard.
+pub struct ShardRouter<T: Transport> {
+    shards: ShardMap,
+    api_key: Option<String>,
+    transport: T,
+}
+
+impl<T: Transport> ShardRouter<T> {
+    /// Longest time a shard waits for the answers of the others
+    pub const SCATTER_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(shards: ShardMap, api_key: Option<String>, transport: T) -> Self {
+        Self {
+            shards,
+            api_key,
+            transport,
+        }
+    }
+
+    /// The scatter-gather routes, to be served as async routes as they wait on the other
+    /// shards
+    pub fn routes(self: Arc<Self>) -> HashMap<(String, String), AsyncHandlerFn> {
+        let scan = Arc::clone(&self);
+        let search = Arc::clone(&self);
+        let node = self;
+        let routes: [(&str, AsyncHandlerFn); 3] = [
+            (
+                SCAN_PATH,
+                Arc::new(move |input| {
+                    let router = Arc::clone(&scan);
+                    Box::pin(async move { router.scan(input).await })
+                }),
+            ),
+            (
+                SEARCH_PATH,
+                Arc::new(move |input| {
+                    let router = Arc::clone(&search);
+                    Box::pin(async move { router.search(input).await })
+                }),
+            ),
+            (
+                NODE_PATH,
+                Arc::new(move |input| {
+                    let router = Arc::clone(&node);
+                    Box::pin(async move { router.node(input).await })
+                }),
+            ),
+        ];
+        routes
+            .into_iter()
+            .map(|(path, handler)| (("POST".to_string(), path.to_string()), handler))
+            .collect()
+    }
+
+    /// Nodes of the label on all shards, in the order of their ids
+    async fn scan(&self, input: HandlerInput) -> Result<Response, GraphError> {
+        let request: ScanRequest = sonic_rs::from_slice(&input.request.body)?;
+        let mut nodes = {
+            let txn = input.graph.storage.graph_env.read_txn()?;
+            let nodes = G::new(Arc::clone(&input.graph.storage), &txn)
+                .n_from_type(&request.label)
+                .collect_to::<Vec<_>>();
+            items(sonic_rs::to_value(&ReturnValue::from(nodes))?)
+        };
+        if !request.local {
+            let request = ScanRequest {
+                local: true,
+                ..request
+            };
+            for answer in self.scatter(SCAN_PATH, &request).await? {
+                nodes.extend(items(answer["nodes"].clone()));
+            }
+            nodes.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+        }
+        respond("nodes", nodes)
+    }
+
+    /// The `k` vectors closest to the vector over all shards, closest first
+    async fn search(&self, input: HandlerInput) -> Result<Response, GraphError> {
+        let request: SearchRequest = sonic_rs::from_slice(&input.request.body)?;
+        let mut vectors = local_search(&input.graph, &request)?;
+        if !request.local {
+            let request = SearchRequest {
+                local: true,
+                ..request
+            };
+            for answer in self.scatter(SEARCH_PATH, &request).await? {
+                vectors.extend(items(answer["vectors"].clone()));
+            }
+            // ranked by their score, the same way each shard ranks its own vectors
+            let score = |vector: &Value| vector["score"].as_f64().unwrap_or(f64::INFINITY);
+            vectors.sort_by(|a, b| score(a).total_cmp(&score(b)));
+            vectors.truncate(request.k);
+        }
+        respond("vectors", vectors)
+    }
+
+    /// The node with the id, from whichever shard owns it
+    async fn node(&self, input: HandlerInput) -> Result<Response, GraphError> {
+        let request: NodeRequest = sonic_rs::from_slice(&input.request.body)?;
+        let id = uuid::Uuid::parse_str(&request.id)
+            .map_err(|e| GraphError::ConversionError(format!("Invalid id: {}", e)))?
+            .as_u128();
+        let shard = self.shards.shard_of(id);
+        if shard != self.shards.shard {
+            let answer: Value = call_with_timeout(
+                &self.transport,
+                &self.shards.addresses[shard],
+                NODE_PATH,
+                self.api_key.as_deref(),
+                &request,
+                Self::SCATTER_TIMEOUT,
+            )
+            .await?;
+            let mut response = Response::new();
+            response.body = sonic_rs::to_vec(&answer)?;
+            return Ok(response);
+        }
+        let node = {
+            let txn = input.graph.storage.graph_env.read_txn()?;
+            input.graph.storage.get_node(&txn, &id)?
+        };
+        let mut response = Response::new();
+        response.body = sonic_rs::to_vec(&HashMap::from([("node", ReturnValue::from(node))]))?;
+        Ok(response)
+    }
+
+    /// Sends the request to every other shard. Fails if any of them does, rather than
+    /// returning partial results.
+    async fn scatter<Req: Serialize>(
+        &self,
+        path: &str,
+        request: &Req,
+    ) -> Result<Vec<Value>, GraphError> {
+        let calls = self
+            .shards
+            .addresses
+            .iter()
+            .enumerate()
+            .filter(|(shard, _)| *shard != self.shards.shard)
+            .map(|(_, address)| {
+                call_with_timeout(
+                    &self.transport,
+                    address,
+                    path,
+                    self.api_key.as_deref(),
+                    request,
+                    Self::SCATTER_TIMEOUT,
+                )
+            });
+        join_all(calls).await.into_iter().collect()
+    }
+}
+
+fn local_search(
+    graph: &HelixGraphEngine,
+    request: &SearchRequest,
+) -> Result<Vec<Value>, GraphError> {
+    let txn = graph.storage.graph_env.read_txn()?;
+    let vectors = G::new(Arc::clone(&graph.storage), &txn)
+        .search_v::<fn(&HVector, &RoTxn) -> bool>(&request.vector, request.k, None)
+        .collect_to::<Vec<_>>();
+    Ok(items(sonic_rs::to_value(&ReturnValue::from(vectors))?))
+}
+
+fn items(value: Value) -> Vec<Value> {
+    match value.as_array() {
+        Some(array) => array.iter().cloned().collect(),
+        None => Vec::new(),
+    }
+}
+
+fn respond(key: &str, items: Vec<Value>) -> Result<Response, GraphError> {
+    let mut response = Response::new();
+    response.body = sonic_rs::to_vec(&HashMap::from([(key, items)]))?;
+    Ok(response)
+}
@@ -1,3 +0,0 @@
-pub mod heed3 {
-    pub use ::heed3::*;
-} 
\ No newline at end of file
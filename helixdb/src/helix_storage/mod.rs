@@ -0,0 +1,16 @@
+pub mod heed3 {
+    pub use ::heed3::*;
+}
+
+/// Where the LMDB environment of a graph is kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// Persisted to disk at the configured path
+    #[default]
+    Lmdb,
+    /// LMDB in a temporary directory that is removed once the storage is dropped.
+    ///
+    /// The directory is placed on `/dev/shm` when it exists, so the data stays in RAM.
+    /// Useful for tests and ephemeral workloads.
+    InMemory,
+}
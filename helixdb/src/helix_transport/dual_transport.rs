@@ -0,0 +1,134 @@
+use super::{Listener, Stream, Transport};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Transport that serves two transports side by side.
+///
+/// The primary transport is bound to the address handed to [`Transport::bind`] while the
+/// secondary transport is bound to `secondary_addr`. Connections accepted by either
+/// listener are handed out by the same [`DualListener`], so a single gateway can serve
+/// e.g. plain TCP and WebSocket clients concurrently.
+#[derive(Clone)]
+pub struct DualTransport<A, B> {
+    pub primary: A,
+    pub secondary: B,
+    pub secondary_addr: SocketAddr,
+}
+
+impl<A: Transport, B: Transport> DualTransport<A, B> {
+    pub fn new(primary: A, secondary: B, secondary_addr: SocketAddr) -> Self {
+        Self {
+            primary,
+            secondary,
+            secondary_addr,
+        }
+    }
+}
+
+impl<A: Transport, B: Transport> Transport for DualTransport<A, B> {
+    type Listener = DualListener<A::Listener, B::Listener>;
+    type Stream = DualStream<A::Stream, B::Stream>;
+
+    fn bind(&self, addr: SocketAddr) -> impl Future<Output = io::Result<Self::Listener>> + Send {
+        async move {
+            let primary = self.primary.bind(addr).await?;
+            let secondary = self.secondary.bind(self.secondary_addr).await?;
+            Ok(DualListener { primary, secondary })
+        }
+    }
+
+    /// Connects through the primary transport
+    fn connect(&self, addr: SocketAddr) -> impl Future<Output = io::Result<Self::Stream>> + Send {
+        async move { Ok(DualStream::Primary(self.primary.connect(addr).await?)) }
+    }
+}
+
+pub struct DualListener<A, B> {
+    pub primary: A,
+    pub secondary: B,
+}
+
+impl<A: Listener, B: Listener> Listener for DualListener<A, B> {
+    type Stream = DualStream<A::Stream, B::Stream>;
+
+    /// Accepts the next connection of whichever listener is ready first.
+    ///
+    /// Both inner `accept` futures must be cancel safe.
+    fn accept(&self) -> impl Future<Output = io::Result<(Self::Stream, SocketAddr)>> + Send {
+        async move {
+            tokio::select! {
+                conn = self.primary.accept() => {
+                    conn.map(|(stream, addr)| (DualStream::Primary(stream), addr))
+                }
+                conn = self.secondary.accept() => {
+                    conn.map(|(stream, addr)| (DualStream::Secondary(stream), addr))
+                }
+            }
+        }
+    }
+
+    /// Returns the address of the primary listener
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.primary.local_addr()
+    }
+}
+
+/// A stream accepted by either side of a [`DualListener`]
+pub enum DualStream<A, B> {
+    Primary(A),
+    Secondary(B),
+}
+
+impl<A: Stream, B: Stream> Stream for DualStream<A, B> {
+    fn idle_timeout(&self) -> Option<Duration> {
+        match self {
+            DualStream::Primary(stream) => stream.idle_timeout(),
+            DualStream::Secondary(stream) => stream.idle_timeout(),
+        }
+    }
+}
+
+impl<A: AsyncRead + Unpin, B: AsyncRead + Unpin> AsyncRead for DualStream<A, B> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            DualStream::Primary(stream) => Pin::new(stream).poll_read(cx, buf),
+            DualStream::Secondary(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<A: AsyncWrite + Unpin, B: AsyncWrite + Unpin> AsyncWrite for DualStream<A, B> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            DualStream::Primary(stream) => Pin::new(stream).poll_write(cx, buf),
+            DualStream::Secondary(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            DualStream::Primary(stream) => Pin::new(stream).poll_flush(cx),
+            DualStream::Secondary(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            DualStream::Primary(stream) => Pin::new(stream).poll_shutdown(cx),
+            DualStream::Secondary(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
@@ -1,16 +1,23 @@
+pub mod dual_transport;
 pub mod tokio_transport;
+pub mod ws_transport;
 
 use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-/// A trait for objects that can be read from and written to asynchronously.
-///
-/// This is a marker trait that is automatically implemented for any type that
-/// implements `AsyncRead`, `AsyncWrite`, `Send`, `Sync`, and `Unpin`.
-pub trait Stream: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
-impl<T: AsyncRead + AsyncWrite + Send + Sync + Unpin> Stream for T {}
+use crate::helix_gateway::gateway::GatewayOpts;
+
+/// A trait for connections that can be read from and written to asynchronously.
+pub trait Stream: AsyncRead + AsyncWrite + Send + Sync + Unpin {
+    /// How long the connection is kept open while the client sends no request, `None`
+    /// to keep it open until the client closes it
+    fn idle_timeout(&self) -> Option<Duration> {
+        Some(GatewayOpts::KEEP_ALIVE_TIMEOUT)
+    }
+}
 
 /// A trait for listeners that can accept incoming connections.
 pub trait Listener: Send + Sync {
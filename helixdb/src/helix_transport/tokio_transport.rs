@@ -1,4 +1,4 @@
-use super::{Listener, Transport};
+use super::{Listener, Stream, Transport};
 use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
@@ -7,6 +7,8 @@ use tokio::net::{TcpListener, TcpStream};
 #[derive(Clone)]
 pub struct TokioTransport;
 
+impl Stream for TcpStream {}
+
 impl Transport for TokioTransport {
     type Listener = TokioListener;
     type Stream = TcpStream;
@@ -0,0 +1,236 @@
+use super::{Listener, Stream, Transport};
+use flume::{Receiver, Sender};
+use futures_util::{Sink, Stream as FuturesStream};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{
+    accept_async, client_async,
+    tungstenite::{Error as WsError, Message},
+    WebSocketStream,
+};
+
+/// WebSocket transport.
+///
+/// Each WebSocket message carries exactly one request in the gateway's HTTP/1.1 framing
+/// and every response is delivered back as a single binary message. Because the
+/// underlying connection stays open, clients can keep sending requests over it and the
+/// server can push messages to the client at any time.
+#[derive(Clone)]
+pub struct WsTransport;
+
+impl WsTransport {
+    /// Maximum time a client has to complete the opening handshake
+    pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+    /// Number of handshaken connections that can wait to be accepted
+    pub const ACCEPT_BACKLOG: usize = 1024;
+}
+
+impl Transport for WsTransport {
+    type Listener = WsListener;
+    type Stream = WsStream<TcpStream>;
+
+    fn bind(&self, addr: SocketAddr) -> impl Future<Output = io::Result<Self::Listener>> + Send {
+        async move {
+            let listener = TcpListener::bind(addr).await?;
+            let local_addr = listener.local_addr()?;
+            let (tx, rx) = flume::bounded(Self::ACCEPT_BACKLOG);
+
+            // handshakes are run off the accept path so a slow client can't stall
+            // other connections and `accept` stays cancel safe
+            let accept_loop = tokio::spawn(WsListener::accept_loop(listener, tx));
+
+            Ok(WsListener {
+                rx,
+                local_addr,
+                accept_loop,
+            })
+        }
+    }
+
+    fn connect(&self, addr: SocketAddr) -> impl Future<Output = io::Result<Self::Stream>> + Send {
+        async move {
+            let stream = TcpStream::connect(addr).await?;
+            let (ws, _) = client_async(format!("ws://{}/", addr), stream)
+                .await
+                .map_err(ws_to_io_error)?;
+            Ok(WsStream::new(ws))
+        }
+    }
+}
+
+pub struct WsListener {
+    rx: Receiver<(WsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+    accept_loop: tokio::task::JoinHandle<()>,
+}
+
+impl WsListener {
+    async fn accept_loop(listener: TcpListener, tx: Sender<(WsStream<TcpStream>, SocketAddr)>) {
+        loop {
+            let (stream, addr) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    eprintln!("Error accepting websocket connection: {}", e);
+                    continue;
+                }
+            };
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(WsTransport::HANDSHAKE_TIMEOUT, accept_async(stream))
+                    .await
+                {
+                    Ok(Ok(ws)) => {
+                        let _ = tx.send_async((WsStream::new(ws), addr)).await;
+                    }
+                    Ok(Err(e)) => eprintln!("Websocket handshake with {} failed: {}", addr, e),
+                    Err(_) => eprintln!("Websocket handshake with {} timed out", addr),
+                }
+            });
+        }
+    }
+}
+
+impl Drop for WsListener {
+    fn drop(&mut self) {
+        self.accept_loop.abort();
+    }
+}
+
+impl Listener for WsListener {
+    type Stream = WsStream<TcpStream>;
+
+    fn accept(&self) -> impl Future<Output = io::Result<(Self::Stream, SocketAddr)>> + Send {
+        async move {
+            self.rx.recv_async().await.map_err(|_| {
+                io::Error::new(io::ErrorKind::BrokenPipe, "Websocket listener was closed")
+            })
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Byte stream over a WebSocket connection.
+///
+/// Reads yield the payloads of incoming text and binary messages back to back.
+/// Writes are buffered and sent as a single binary message on flush.
+pub struct WsStream<S> {
+    inner: WebSocketStream<S>,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    write_buf: Vec<u8>,
+    closed: bool,
+}
+
+impl<S> WsStream<S> {
+    pub fn new(inner: WebSocketStream<S>) -> Self {
+        Self {
+            inner,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            write_buf: Vec::new(),
+            closed: false,
+        }
+    }
+}
+
+/// WebSocket clients keep their connection open to be pushed messages, so it isn't
+/// closed while they are idle. Tungstenite answers their pings and a dead peer is noticed
+/// by the next write to it.
+impl<S: AsyncRead + AsyncWrite + Send + Sync + Unpin> Stream for WsStream<S> {
+    fn idle_timeout(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for WsStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if self.read_pos < self.read_buf.len() {
+                let n = buf.remaining().min(self.read_buf.len() - self.read_pos);
+                let start = self.read_pos;
+                buf.put_slice(&self.read_buf[start..start + n]);
+                self.read_pos += n;
+                return Poll::Ready(Ok(()));
+            }
+            if self.closed {
+                return Poll::Ready(Ok(()));
+            }
+
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => match message {
+                    Message::Text(_) | Message::Binary(_) => {
+                        self.read_buf = message.into_data().to_vec();
+                        self.read_pos = 0;
+                    }
+                    Message::Close(_) => self.closed = true,
+                    // pings are answered by tungstenite itself
+                    Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+                },
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(ws_to_io_error(e))),
+                Poll::Ready(None) => self.closed = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for WsStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.write_buf.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.write_buf.is_empty() {
+            match Pin::new(&mut self.inner).poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(ws_to_io_error(e))),
+                Poll::Pending => return Poll::Pending,
+            }
+            let payload = std::mem::take(&mut self.write_buf);
+            if let Err(e) = Pin::new(&mut self.inner).start_send(Message::binary(payload)) {
+                return Poll::Ready(Err(ws_to_io_error(e)));
+            }
+        }
+        Pin::new(&mut self.inner)
+            .poll_flush(cx)
+            .map_err(ws_to_io_error)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.as_mut().poll_flush(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+        Pin::new(&mut self.inner)
+            .poll_close(cx)
+            .map_err(ws_to_io_error)
+    }
+}
+
+fn ws_to_io_error(error: WsError) -> io::Error {
+    match error {
+        WsError::Io(e) => e,
+        WsError::ConnectionClosed | WsError::AlreadyClosed => {
+            io::Error::new(io::ErrorKind::ConnectionReset, error.to_string())
+        }
+        e => io::Error::new(io::ErrorKind::Other, e.to_string()),
+    }
+}
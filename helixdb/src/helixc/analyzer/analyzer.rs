@@ -7,6 +7,7 @@ use crate::{
     helixc::{
         generator::{
             bool_op::{BoolOp, Eq, Gt, Gte, Lt, Lte, Neq},
+            expression::GeneratedExpression,
             generator_types::{
                 Assignment as GeneratedAssignment, BoExp, Drop as GeneratedDrop,
                 ForEach as GeneratedForEach, ForLoopInVariable, ForVariable, IdentifierType,
@@ -14,21 +15,30 @@ use crate::{
                 ReturnValueExpr, Source as GeneratedSource, Statement as GeneratedStatement,
             },
             object_remapping_generation::{
-                ExcludeField, FieldRemapping, IdentifierRemapping, ObjectRemapping, Remapping,
-                RemappingType, TraversalRemapping, ValueRemapping,
+                CoalesceRemapping, CoalesceValue, ExcludeField, ExpressionRemapping,
+                FieldRemapping, IdentifierRemapping, ObjectRemapping, Remapping, RemappingType,
+                TraversalRemapping, ValueRemapping,
             },
+            parameter_binding::unbound_keys,
             source_steps::{
-                AddE, AddN, AddV, EFromID, EFromType, NFromID, NFromIndex, NFromType, SearchBM25,
+                AddE, AddN, AddV, Analytics as GeneratedAnalytics,
+                AnalyticsAlgorithm as GeneratedAnalyticsAlgorithm, EFromID, EFromIndex, EFromType,
+                EFromTypeWhere, HybridSearch as GeneratedHybridSearch, NFromID, NFromIndex,
+                NFromType, NFromTypeOrdered, NFromTypeWhere, SearchBM25,
                 SearchVector as GeneratedSearchVector, SourceStep,
             },
             traversal_steps::{
-                In as GeneratedIn, InE as GeneratedInE, Out as GeneratedOut, OutE as GeneratedOutE,
-                SearchVectorStep, ShortestPath as GeneratedShortestPath, ShouldCollect,
+                In as GeneratedIn, InCount as GeneratedInCount, InE as GeneratedInE,
+                OrderBy as GeneratedOrderBy, Out as GeneratedOut, OutCount as GeneratedOutCount,
+                AsOf, OutE as GeneratedOutE, PropertyPath as GeneratedPropertyPath, Range,
+                SearchVectorStep, ShortestPath as GeneratedShortestPath,
+                ShortestPathWeighted as GeneratedShortestPathWeighted, ShouldCollect,
                 Step as GeneratedStep, Traversal as GeneratedTraversal, TraversalType, Where,
                 WhereExists, WhereRef,
             },
             utils::{
-                GenRef, GeneratedType, GeneratedValue, RustType as GeneratedRustType, Separator,
+                GenRef, GeneratedType, GeneratedValue, Order as GeneratedOrder,
+                RustType as GeneratedRustType, Separator,
             },
         },
         parser::{
@@ -44,7 +54,16 @@ use std::{
     ops::{ControlFlow, Deref},
 };
 
-use super::{fix::Fix, pretty};
+use super::{
+    fix::Fix,
+    pretty,
+    usage::{expression_uses, statement_uses},
+};
+
+/// Pseudo-field of object accesses reading the score an element was ranked by
+const SCORE_FIELD: &str = "_score";
+/// Field every node, edge and vector has without declaring it
+const ID_FIELD: &str = "id";
 
 /// A single diagnostic to be surfaced to the editor.
 #[derive(Debug, Clone)]
@@ -88,12 +107,19 @@ impl Diagnostic {
     pub fn render(&self, src: &str, filepath: &str) -> String {
         pretty::render(self, src, filepath)
     }
+
+    /// Whether the diagnostic stops the queries from being compiled, warnings don't
+    pub fn is_error(&self) -> bool {
+        matches!(self.severity, DiagnosticSeverity::Error)
+    }
 }
 
 pub fn analyze(src: &Source) -> (Vec<Diagnostic>, GeneratedSource) {
     let mut ctx = Ctx::new(src);
+    ctx.check_files();
     ctx.check_schema();
     ctx.check_queries();
+    ctx.lint_queries();
     (ctx.diagnostics, ctx.output)
 }
 
@@ -108,6 +134,7 @@ struct Ctx<'a> {
     node_fields: HashMap<&'a str, HashMap<&'a str, &'a Field>>,
     edge_fields: HashMap<&'a str, HashMap<&'a str, &'a Field>>,
     vector_fields: HashMap<&'a str, HashMap<&'a str, &'a Field>>,
+    enum_map: HashMap<&'a str, &'a EnumSchema>,
     diagnostics: Vec<Diagnostic>,
     output: GeneratedSource,
 }
@@ -168,15 +195,153 @@ impl<'a> Ctx<'a> {
             node_fields,
             edge_fields,
             vector_fields,
+            enum_map: src
+                .enum_schemas
+                .iter()
+                .map(|e| (e.name.1.as_str(), e))
+                .collect(),
             src,
             diagnostics: Vec::new(),
             output,
         }
     }
 
+    // ---------- Pass #0: files --------------------------
+    /// Validate the imports between files and that nothing is declared twice.
+    fn check_files(&mut self) {
+        let src = self.src;
+        let mut imported: HashMap<&str, &Import> = HashMap::new();
+        let mut graph: HashMap<&str, Vec<&Import>> = HashMap::new();
+        for import in &src.imports {
+            let imports = graph.entry(import.from.as_str()).or_default();
+            if imports.iter().any(|other| other.file == import.file) {
+                self.push_schema_err(
+                    import.loc.clone(),
+                    format!("`{}` is already imported", import.path),
+                    Some("remove this import".to_string()),
+                );
+                continue;
+            }
+            imports.push(import);
+            match imported.get(import.file.as_str()) {
+                Some(other) if other.namespace != import.namespace => {
+                    self.push_schema_err(
+                        import.loc.clone(),
+                        format!(
+                            "`{}` is imported in another namespace by {}",
+                            import.path, other.from
+                        ),
+                        Some("import the file as the same namespace everywhere".to_string()),
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    imported.insert(import.file.as_str(), import);
+                }
+            }
+        }
+        let mut done = HashSet::new();
+        for import in &src.imports {
+            self.check_import_cycles(&graph, import.from.as_str(), &mut Vec::new(), &mut done);
+        }
+
+        // the files of a project are compiled together, so names are shared between them
+        let names = src
+            .node_schemas
+            .iter()
+            .map(|n| (format!("N::{}", n.name.1), &n.loc))
+            .chain(
+                src.edge_schemas
+                    .iter()
+                    .map(|e| (format!("E::{}", e.name.1), &e.loc)),
+            )
+            .chain(
+                src.vector_schemas
+                    .iter()
+                    .map(|v| (format!("V::{}", v.name), &v.loc)),
+            )
+            .chain(
+                src.enum_schemas
+                    .iter()
+                    .map(|e| (format!("ENUM {}", e.name.1), &e.loc)),
+            )
+            .chain(
+                src.queries
+                    .iter()
+                    .map(|q| (format!("QUERY {}", q.name), &q.loc)),
+            );
+        let mut declared: HashMap<String, &Loc> = HashMap::new();
+        for (name, loc) in names {
+            match declared.get(&name) {
+                Some(first) => {
+                    let msg = format!(
+                        "`{}` is already declared on line {} of {}",
+                        name,
+                        first.start.line,
+                        first.filepath.as_deref().unwrap_or("the source")
+                    );
+                    self.push_schema_err(
+                        loc.clone(),
+                        msg,
+                        Some("rename or remove one of the declarations".to_string()),
+                    );
+                }
+                None => {
+                    declared.insert(name, loc);
+                }
+            }
+        }
+    }
+
+    /// Walks the imports from `file`, reporting the imports back to a file on `path`
+    fn check_import_cycles(
+        &mut self,
+        graph: &HashMap<&'a str, Vec<&'a Import>>,
+        file: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) {
+        if done.contains(file) {
+            return;
+        }
+        path.push(file);
+        for import in graph.get(file).into_iter().flatten() {
+            let target = import.file.as_str();
+            match path.iter().position(|f| *f == target) {
+                Some(start) => {
+                    let cycle = path[start..]
+                        .iter()
+                        .chain([&target])
+                        .copied()
+                        .collect::<Vec<_>>()
+                        .join(" -> ");
+                    self.push_schema_err(
+                        import.loc.clone(),
+                        format!("import cycle {}", cycle),
+                        Some("move what the files share into a file both import".to_string()),
+                    );
+                }
+                None => self.check_import_cycles(graph, target, path, done),
+            }
+        }
+        path.pop();
+        done.insert(file);
+    }
+
     // ---------- Pass #1: schema --------------------------
     /// Validate that every edge references declared node types.
     fn check_schema(&mut self) {
+        let src = self.src;
+        let namespaces = src
+            .node_schemas
+            .iter()
+            .map(|n| (n.name.1.as_str(), n.namespace.as_deref()))
+            .chain(
+                src.vector_schemas
+                    .iter()
+                    .map(|v| (v.name.as_str(), v.namespace.as_deref())),
+            )
+            .collect::<HashMap<_, _>>();
         for edge in &self.src.edge_schemas {
             if !self.node_set.contains(edge.from.1.as_str())
                 && !self.vector_set.contains(edge.from.1.as_str())
@@ -196,6 +361,22 @@ impl<'a> Ctx<'a> {
                     Some(format!("Declare `N::{}` before this edge", edge.to.1)),
                 );
             }
+            // the data of a namespace is kept apart, so edges can't leave it
+            for (loc, endpoint) in [&edge.from, &edge.to] {
+                match namespaces.get(endpoint.as_str()) {
+                    Some(namespace) if *namespace != edge.namespace.as_deref() => {
+                        self.push_schema_err(
+                            loc.clone(),
+                            format!(
+                                "`{}` isn't in the namespace of the edge `{}`",
+                                endpoint, edge.name.1
+                            ),
+                            Some("declare both ends of the edge in its namespace".to_string()),
+                        );
+                    }
+                    _ => {}
+                }
+            }
             edge.properties.as_ref().map(|v| {
                 v.iter().for_each(|f| {
                     if f.name.to_lowercase() == "id" {
@@ -205,6 +386,13 @@ impl<'a> Ctx<'a> {
                             Some("rename the field to something else".to_string()),
                         );
                     }
+                    if f.is_unique() {
+                        self.push_schema_err(
+                            f.loc.clone(),
+                            format!("edge field `{}` can't be `UNIQUE`", f.name),
+                            Some("use `INDEX` instead, edge indices allow duplicates".to_string()),
+                        );
+                    }
                 })
             });
             self.output.edges.push(edge.clone().into());
@@ -233,6 +421,132 @@ impl<'a> Ctx<'a> {
             });
             self.output.vectors.push(vector.clone().into());
         }
+        for enum_schema in &self.src.enum_schemas {
+            let name = enum_schema.name.1.as_str();
+            if self.node_set.contains(name)
+                || self.vector_set.contains(name)
+                || self.edge_map.contains_key(name)
+            {
+                self.push_schema_err(
+                    enum_schema.name.0.clone(),
+                    format!("`{}` is already the name of a schema", name),
+                    Some("rename the enum".to_string()),
+                );
+            }
+            if enum_schema.variants.is_empty() {
+                self.push_schema_err(
+                    enum_schema.loc.clone(),
+                    format!("enum `{}` has no variants", name),
+                    Some("add the values the enum can take".to_string()),
+                );
+            }
+            // stored as their index, which fits in a byte
+            if enum_schema.variants.len() > u8::MAX as usize + 1 {
+                self.push_schema_err(
+                    enum_schema.loc.clone(),
+                    format!("enum `{}` has more than 256 variants", name),
+                    Some("use a `String` field instead".to_string()),
+                );
+            }
+            let mut seen = HashSet::new();
+            for (loc, variant) in &enum_schema.variants {
+                if !seen.insert(variant.as_str()) {
+                    self.push_schema_err(
+                        loc.clone(),
+                        format!("`{}` is already a variant of enum `{}`", variant, name),
+                        Some("remove the duplicate variant".to_string()),
+                    );
+                }
+            }
+            self.output.enums.push(enum_schema.clone().into());
+        }
+        let properties = self
+            .output
+            .nodes
+            .iter_mut()
+            .flat_map(|n| n.properties.iter_mut())
+            .chain(
+                self.output
+                    .edges
+                    .iter_mut()
+                    .flat_map(|e| e.properties.iter_mut()),
+            )
+            .chain(
+                self.output
+                    .vectors
+                    .iter_mut()
+                    .flat_map(|v| v.properties.iter_mut()),
+            );
+        for property in properties {
+            resolve_enum_type(&self.enum_map, &mut property.field_type);
+        }
+    }
+
+    /// The enum a field or parameter of this type takes the variants of
+    fn enum_schema(&self, field_type: &FieldType) -> Option<&'a EnumSchema> {
+        match field_type {
+            FieldType::Identifier(name) => self.enum_map.get(name.as_str()).copied(),
+            _ => None,
+        }
+    }
+
+    /// Checks a string literal compared with an enum field is one of its variants,
+    /// returning whether the comparison was with one
+    fn check_compared_enum(
+        &mut self,
+        q: &Query,
+        field_type: &FieldType,
+        op: &BooleanOpType,
+    ) -> bool {
+        let Some(enum_schema) = self.enum_schema(field_type) else {
+            return false;
+        };
+        match op {
+            BooleanOpType::LessThanOrEqual(expr)
+            | BooleanOpType::LessThan(expr)
+            | BooleanOpType::GreaterThanOrEqual(expr)
+            | BooleanOpType::GreaterThan(expr)
+            | BooleanOpType::Equal(expr)
+            | BooleanOpType::NotEqual(expr) => match &expr.expr {
+                ExpressionType::StringLiteral(s) => {
+                    self.check_enum_literal(q, &expr.loc, enum_schema, &Value::String(s.clone()));
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Checks a literal stored in or compared with an enum field is one of its variants
+    fn check_enum_literal(
+        &mut self,
+        q: &Query,
+        loc: &Loc,
+        enum_schema: &EnumSchema,
+        value: &Value,
+    ) {
+        let is_variant = match value {
+            Value::String(s) => enum_schema.variants.iter().any(|(_, variant)| variant == s),
+            _ => false,
+        };
+        if !is_variant {
+            let variants = enum_schema
+                .variants
+                .iter()
+                .map(|(_, variant)| format!("`\"{}\"`", variant))
+                .collect::<Vec<_>>();
+            self.push_query_err(
+                q,
+                loc.clone(),
+                format!(
+                    "`{}` is not a variant of enum `{}`",
+                    loc.span.trim(),
+                    enum_schema.name.1
+                ),
+                format!("use one of {}", variants.join(", ")),
+            );
+        }
     }
 
     // ---------- Pass #2: queries -------------------------
@@ -242,6 +556,207 @@ impl<'a> Ctx<'a> {
         }
     }
 
+    // -----------------------------------------------------
+    // Lints
+    // -----------------------------------------------------
+    fn lint_queries(&mut self) {
+        for q in &self.src.queries {
+            let mut returned = HashSet::new();
+            for ret in &q.return_values {
+                expression_uses(ret, &mut returned);
+            }
+            self.lint_statements(q, &q.statements, &returned);
+        }
+    }
+
+    /// Warns of the variables assigned in `statements` that are never read after and of
+    /// the statements reading a variable whose items were dropped before them, `used_after`
+    /// being the names read once the statements are done
+    fn lint_statements(
+        &mut self,
+        q: &'a Query,
+        statements: &'a [Statement],
+        used_after: &HashSet<&'a str>,
+    ) {
+        // the names read after each statement
+        let mut later_uses = vec![used_after.clone(); statements.len()];
+        for i in (1..statements.len()).rev() {
+            let mut uses = later_uses[i].clone();
+            statement_uses(&statements[i], &mut uses);
+            later_uses[i - 1] = uses;
+        }
+
+        let mut dropped: HashSet<&str> = HashSet::new();
+        for (statement, used_after) in statements.iter().zip(&later_uses) {
+            let mut uses = HashSet::new();
+            statement_uses(statement, &mut uses);
+            if let Some(name) = dropped.iter().find(|name| uses.contains(*name)) {
+                self.push_query_warn(
+                    q,
+                    statement.loc.clone(),
+                    format!("statement is unreachable, `{}` was dropped before it", name),
+                    format!(
+                        "the items of `{}` no longer exist, remove the statement",
+                        name
+                    ),
+                    Some(Fix::new(
+                        None,
+                        Some(statement.loc.clone()),
+                        Some(String::new()),
+                    )),
+                );
+                continue;
+            }
+            match &statement.statement {
+                StatementType::Assignment(assignment) => {
+                    dropped.remove(assignment.variable.as_str());
+                    if !used_after.contains(assignment.variable.as_str()) {
+                        self.lint_unused_variable(q, statement, assignment);
+                    }
+                }
+                StatementType::ForLoop(for_loop) => {
+                    self.lint_statements(q, &for_loop.statements, used_after)
+                }
+                StatementType::Drop(Expression {
+                    expr: ExpressionType::Identifier(name),
+                    ..
+                }) => {
+                    dropped.insert(name.as_str());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn lint_unused_variable(&mut self, q: &Query, statement: &Statement, assignment: &Assignment) {
+        let message = format!("variable `{}` is never used", assignment.variable);
+        let value = &assignment.value;
+        match &value.expr {
+            // adding the items is a statement of its own, only the variable goes
+            ExpressionType::AddNode(_)
+            | ExpressionType::AddEdge(_)
+            | ExpressionType::AddVector(_)
+            | ExpressionType::BatchAddVector(_) => {
+                let fix = statement
+                    .loc
+                    .span
+                    .trim_end()
+                    .strip_suffix(value.loc.span.trim_end())
+                    .map(|binding| {
+                        let span = match statement.loc.start.line == statement.loc.end.line {
+                            true => Some(statement.loc.clone()),
+                            false => None,
+                        };
+                        let binding = Loc::new(
+                            statement.loc.filepath.clone(),
+                            statement.loc.start,
+                            value.loc.start,
+                            binding.to_string(),
+                        );
+                        Fix::new(span, Some(binding), Some(String::new()))
+                    });
+                self.push_query_warn(
+                    q,
+                    statement.loc.clone(),
+                    message,
+                    "use it in a later statement or RETURN, or remove the assignment",
+                    fix,
+                );
+            }
+            // the statement has to stay for what it writes
+            ExpressionType::Traversal(traversal)
+                if traversal.steps.iter().any(|step| {
+                    matches!(step.step, StepType::Update(_) | StepType::AddEdge(_))
+                }) =>
+            {
+                self.push_query_warn(
+                    q,
+                    statement.loc.clone(),
+                    message,
+                    "use it in a later statement or RETURN",
+                    None,
+                );
+            }
+            _ => {
+                self.push_query_warn(
+                    q,
+                    statement.loc.clone(),
+                    message,
+                    "use it in a later statement or RETURN, or remove the statement",
+                    Some(Fix::new(
+                        None,
+                        Some(statement.loc.clone()),
+                        Some(String::new()),
+                    )),
+                );
+            }
+        }
+    }
+
+    /// Checks the default value of a parameter matches its type
+    fn check_param_default(&mut self, q: &Query, param: &Parameter, default: &ValueType) {
+        let ValueType::Literal { value, loc } = default else {
+            return;
+        };
+        if param.is_optional {
+            self.push_query_err(
+                q,
+                loc.clone(),
+                format!(
+                    "parameter `{}` is optional but has a default value",
+                    param.name.1
+                ),
+                "remove the `?`",
+            );
+            return;
+        }
+        let matches = match (&param.param_type.1, value) {
+            (FieldType::I8 | FieldType::I16 | FieldType::I32 | FieldType::I64, Value::I64(_)) => {
+                true
+            }
+            (
+                FieldType::U8 | FieldType::U16 | FieldType::U32 | FieldType::U64 | FieldType::U128,
+                Value::I64(i),
+            ) => *i >= 0,
+            (FieldType::F32 | FieldType::F64, Value::I64(_) | Value::F64(_)) => true,
+            (FieldType::String, Value::String(_)) => true,
+            (FieldType::Boolean, Value::Boolean(_)) => true,
+            (
+                FieldType::Uuid
+                | FieldType::Date
+                | FieldType::Array(_)
+                | FieldType::Identifier(_)
+                | FieldType::Object(_),
+                _,
+            ) => {
+                self.push_query_err(
+                    q,
+                    loc.clone(),
+                    format!(
+                        "parameter `{}` of type `{}` can't have a default value",
+                        param.name.1, param.param_type.1
+                    ),
+                    "make it optional with `?` instead",
+                );
+                return;
+            }
+            _ => false,
+        };
+        if !matches {
+            self.push_query_err(
+                q,
+                loc.clone(),
+                format!(
+                    "default value `{}` doesn't match type `{}` of parameter `{}`",
+                    loc.span.trim(),
+                    param.param_type.1,
+                    param.name.1
+                ),
+                format!("use a `{}` value", param.param_type.1),
+            );
+        }
+    }
+
     fn check_query(&mut self, q: &'a Query) {
         let mut query = GeneratedQuery::default();
         query.name = q.name.clone();
@@ -251,7 +766,9 @@ impl<'a> Ctx<'a> {
         for param in &q.parameters {
             if let FieldType::Identifier(ref id) = param.param_type.1 {
                 if self.is_valid_identifier(q, param.param_type.0.clone(), id.as_str()) {
-                    if !self.node_set.contains(id.as_str()) {
+                    if !self.node_set.contains(id.as_str())
+                        && !self.enum_map.contains_key(id.as_str())
+                    {
                         self.push_query_err(
                             q,
                             param.param_type.0.clone(),
@@ -261,6 +778,9 @@ impl<'a> Ctx<'a> {
                     }
                 }
             }
+            if let Some(default) = &param.default_value {
+                self.check_param_default(q, param, default);
+            }
             // constructs parameters and sub‑parameters for generator
             GeneratedParameter::unwrap_param(
                 param.clone(),
@@ -269,12 +789,31 @@ impl<'a> Ctx<'a> {
             );
         }
 
+        let parameters = query.parameters.iter_mut().chain(
+            query
+                .sub_parameters
+                .iter_mut()
+                .flat_map(|(_, parameters)| parameters.iter_mut()),
+        );
+        for parameter in parameters {
+            resolve_enum_type(&self.enum_map, &mut parameter.field_type);
+        }
+
         // -------------------------------------------------
         // Statement‑by‑statement walk
         // -------------------------------------------------
         let mut scope: HashMap<&str, Type> = HashMap::new();
         for param in &q.parameters {
-            scope.insert(param.name.1.as_str(), Type::from(&param.param_type.1));
+            let ty = match self.enum_schema(&param.param_type.1) {
+                // compared with enum fields like the strings they're stored as
+                Some(_) => Type::Scalar(param.param_type.1.clone()),
+                None => Type::from(&param.param_type.1),
+            };
+            let ty = match param.is_optional {
+                true => Type::Optional(Box::new(ty)),
+                false => ty,
+            };
+            scope.insert(param.name.1.as_str(), ty);
         }
         for stmt in &q.statements {
             let statement = self.walk_statements(&mut scope, q, &mut query, stmt);
@@ -304,17 +843,23 @@ impl<'a> Ctx<'a> {
             );
         }
         for ret in &q.return_values {
-            let (_, stmt) = self.infer_expr_type(ret, &mut scope, q, None, Some(&mut query));
-
-            assert!(stmt.is_some(), "RETURN value should be a valid expression");
-            match stmt.unwrap() {
+            let (ty, stmt) = self.infer_expr_type(ret, &mut scope, q, None, Some(&mut query));
+            // expressions without a statement have already been reported
+            let Some(stmt) = stmt else {
+                continue;
+            };
+            match stmt {
                 GeneratedStatement::Traversal(traversal) => match &traversal.source_step.inner() {
                     SourceStep::Identifier(v) => {
                         self.is_valid_identifier(q, ret.loc.clone(), v.inner().as_str());
-                        query.return_values.push(ReturnValue::new_named(
-                            v.clone(),
-                            ReturnValueExpr::Traversal(traversal.clone()),
-                        ));
+                        let ts_type = items_to_ts(&ty, is_remapped(ret));
+                        query.return_values.push(
+                            ReturnValue::new_named(
+                                v.clone(),
+                                ReturnValueExpr::Traversal(traversal.clone()),
+                            )
+                            .with_ts_type(ts_type),
+                        );
                     }
                     _ => {
                         query.return_values.push(ReturnValue::new_unnamed(
@@ -337,23 +882,37 @@ impl<'a> Ctx<'a> {
                         }
                     };
                     match identifier_end_type {
-                        Type::Scalar(_) => {
-                            query
-                                .return_values
-                                .push(ReturnValue::new_named_literal(id.clone(), id.clone()));
+                        Type::Scalar(_) | Type::Boolean => {
+                            query.return_values.push(
+                                ReturnValue::new_named_literal(id.clone(), id.clone())
+                                    .with_ts_type(identifier_end_type.to_ts()),
+                            );
                         }
                         _ => {
-                            query.return_values.push(ReturnValue::new_named(
-                                id.clone(),
-                                ReturnValueExpr::Identifier(id.clone()),
-                            ));
+                            // the items are remapped if the traversal assigned to the
+                            // variable remaps them
+                            let remapped = q.statements.iter().any(|stmt| {
+                                matches!(
+                                    &stmt.statement,
+                                    StatementType::Assignment(assignment)
+                                        if assignment.variable == *id.inner()
+                                            && is_remapped(&assignment.value)
+                                )
+                            });
+                            query.return_values.push(
+                                ReturnValue::new_named(
+                                    id.clone(),
+                                    ReturnValueExpr::Identifier(id.clone()),
+                                )
+                                .with_ts_type(items_to_ts(&identifier_end_type, remapped)),
+                            );
                         }
                     }
                 }
                 GeneratedStatement::Literal(l) => {
-                    query
-                        .return_values
-                        .push(ReturnValue::new_literal(l.clone(), l.clone()));
+                    query.return_values.push(
+                        ReturnValue::new_literal(l.clone(), l.clone()).with_ts_type(ty.to_ts()),
+                    );
                 }
                 GeneratedStatement::Empty => query.return_values = vec![],
                 _ => {
@@ -366,6 +925,18 @@ impl<'a> Ctx<'a> {
                 }
             }
         }
+        // the steps only take the parameters as values, their keys are fixed by the query
+        for unbound in unbound_keys(&query) {
+            self.push_query_err(
+                q,
+                q.loc.clone(),
+                format!(
+                    "{} `{}` isn't a literal in the generated code",
+                    unbound.kind, unbound.key
+                ),
+                "this is a bug in the compiler, please report it",
+            );
+        }
         self.output.queries.push(query);
     }
 
@@ -492,7 +1063,9 @@ impl<'a> Ctx<'a> {
 
             AddNode(add) => {
                 if let Some(ref ty) = add.node_type {
-                    if !self.node_set.contains(ty.as_str()) {
+                    if !self.node_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &add.loc, "label", ty)
+                    {
                         self.push_query_err(
                             q,
                             add.loc.clone(),
@@ -502,13 +1075,14 @@ impl<'a> Ctx<'a> {
                     }
                     let label = GenRef::Literal(ty.clone());
 
-                    let node_in_schema = self
+                    // unknown node types are reported above and have no schema properties
+                    let schema_properties = self
                         .output
                         .nodes
                         .iter()
                         .find(|n| n.name == ty.as_str())
-                        .unwrap()
-                        .clone();
+                        .map(|n| n.properties.clone())
+                        .unwrap_or_default();
 
                     // Validate fields if both type and fields are present
                     if let Some(fields) = &add.fields {
@@ -540,16 +1114,14 @@ impl<'a> Ctx<'a> {
                                         };
                                     }
                                     ValueType::Literal { value, loc } => {
-                                        // check against type
-                                        let field_type = self
-                                            .node_fields
-                                            .get(ty.as_str())
-                                            .unwrap()
-                                            .get(field_name.as_str())
-                                            .unwrap()
-                                            .field_type
-                                            .clone();
-                                        if field_type != *value {
+                                        // check against type, unknown fields are reported above
+                                        let Some(field) = field_set.get(field_name.as_str()) else {
+                                            continue;
+                                        };
+                                        let field_type = field.field_type.clone();
+                                        if let Some(enum_schema) = self.enum_schema(&field_type) {
+                                            self.check_enum_literal(q, loc, enum_schema, value);
+                                        } else if field_type != *value {
                                             self.push_query_err(
                                                  q,
                                                  loc.clone(),
@@ -569,38 +1141,16 @@ impl<'a> Ctx<'a> {
                                     field_name.clone(),
                                     match value {
                                         ValueType::Literal { value, loc } => {
-                                            println!(
-                                                "value: {:?}, {}",
-                                                self.node_fields
-                                                    .get(ty.as_str())
-                                                    .unwrap()
-                                                    .get(field_name.as_str())
-                                                    .unwrap()
-                                                    .field_type,
-                                                self.node_fields
-                                                    .get(ty.as_str())
-                                                    .unwrap()
-                                                    .get(field_name.as_str())
-                                                    .unwrap()
-                                                    .field_type
-                                                    == FieldType::Date
-                                            );
                                             match self
                                                 .node_fields
                                                 .get(ty.as_str())
-                                                .unwrap()
-                                                .get(field_name.as_str())
-                                                .unwrap()
-                                                .field_type
-                                                == FieldType::Date
+                                                .and_then(|fields| fields.get(field_name.as_str()))
+                                                .is_some_and(|field| field.field_type == FieldType::Date)
                                             {
                                                 true => match Date::new(value) {
-                                                    Ok(date) => {
-                                                        println!("date: {}", date.to_rfc3339());
-                                                        GeneratedValue::Literal(GenRef::Literal(
-                                                            date.to_rfc3339(),
-                                                        ))
-                                                    }
+                                                    Ok(date) => GeneratedValue::Literal(
+                                                        GenRef::from(Value::from(date)),
+                                                    ),
                                                     Err(e) => {
                                                         self.push_query_err(
                                                             q,
@@ -642,8 +1192,7 @@ impl<'a> Ctx<'a> {
                             })
                             .collect();
 
-                        let default_properties = node_in_schema
-                            .properties
+                        let default_properties = schema_properties
                             .iter()
                             .filter_map(|p| p.default_value.clone().map(|v| (p.name.clone(), v)))
                             .collect::<Vec<(String, GeneratedValue)>>();
@@ -655,11 +1204,10 @@ impl<'a> Ctx<'a> {
                         }
 
                         let secondary_indices = {
-                            let secondary_indices = node_in_schema
-                                .properties
+                            let secondary_indices = schema_properties
                                 .iter()
                                 .filter_map(|p| {
-                                    matches!(p.is_index, FieldPrefix::Index)
+                                    p.is_index.is_indexed()
                                         .then_some(p.name.clone())
                                 })
                                 .collect::<Vec<_>>();
@@ -697,7 +1245,9 @@ impl<'a> Ctx<'a> {
             }
             AddEdge(add) => {
                 if let Some(ref ty) = add.edge_type {
-                    if !self.edge_map.contains_key(ty.as_str()) {
+                    if !self.edge_map.contains_key(ty.as_str())
+                        && !self.check_param_derived_name(q, &add.loc, "label", ty)
+                    {
                         self.push_query_err(
                             q,
                             add.loc.clone(),
@@ -743,16 +1293,15 @@ impl<'a> Ctx<'a> {
                                             };
                                         }
                                         ValueType::Literal { value, loc } => {
-                                            // check against type
-                                            let field_type = self
-                                                .edge_fields
-                                                .get(ty.as_str())
-                                                .unwrap()
-                                                .get(field_name.as_str())
-                                                .unwrap()
-                                                .field_type
-                                                .clone();
-                                            if field_type != *value {
+                                            // check against type, unknown fields are reported above
+                                            let Some(field) = field_set.get(field_name.as_str()) else {
+                                                continue;
+                                            };
+                                            let field_type = field.field_type.clone();
+                                            if let Some(enum_schema) = self.enum_schema(&field_type)
+                                            {
+                                                self.check_enum_literal(q, loc, enum_schema, value);
+                                            } else if field_type != *value {
                                                 self.push_query_err(
                                                      q,
                                                      loc.clone(),
@@ -776,15 +1325,12 @@ impl<'a> Ctx<'a> {
                                                     match self
                                                         .edge_fields
                                                         .get(ty.as_str())
-                                                        .unwrap()
-                                                        .get(field_name.as_str())
-                                                        .unwrap()
-                                                        .field_type
-                                                        == FieldType::Date
+                                                        .and_then(|fields| fields.get(field_name.as_str()))
+                                                        .is_some_and(|field| field.field_type == FieldType::Date)
                                                     {
                                                         true => match Date::new(value) {
                                                             Ok(date) => GeneratedValue::Literal(
-                                                                GenRef::Literal(date.to_rfc3339()),
+                                                                GenRef::from(Value::from(date)),
                                                             ),
                                                             Err(e) => {
                                                                 self.push_query_err(
@@ -836,6 +1382,7 @@ impl<'a> Ctx<'a> {
                         Some(id) => match id {
                             IdType::Identifier { value, loc } => {
                                 self.is_valid_identifier(q, loc.clone(), value.as_str());
+                                self.check_required_param(q, loc, value);
                                 self.gen_id_access_or_param(q, value.as_str())
                             }
                             IdType::Literal { value, loc } => {
@@ -857,6 +1404,7 @@ impl<'a> Ctx<'a> {
                         Some(id) => match id {
                             IdType::Identifier { value, loc } => {
                                 self.is_valid_identifier(q, loc.clone(), value.as_str());
+                                self.check_required_param(q, loc, value);
                                 self.gen_id_access_or_param(q, value.as_str())
                             }
                             IdType::Literal { value, loc } => {
@@ -902,7 +1450,9 @@ impl<'a> Ctx<'a> {
             }
             AddVector(add) => {
                 if let Some(ref ty) = add.vector_type {
-                    if !self.vector_set.contains(ty.as_str()) {
+                    if !self.vector_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &add.loc, "label", ty)
+                    {
                         self.push_query_err(
                             q,
                             add.loc.clone(),
@@ -945,16 +1495,15 @@ impl<'a> Ctx<'a> {
                                             };
                                         }
                                         ValueType::Literal { value, loc } => {
-                                            // check against type
-                                            let field_type = self
-                                                .vector_fields
-                                                .get(ty.as_str())
-                                                .unwrap()
-                                                .get(field_name.as_str())
-                                                .unwrap()
-                                                .field_type
-                                                .clone();
-                                            if field_type != *value {
+                                            // check against type, unknown fields are reported above
+                                            let Some(field) = field_set.get(field_name.as_str()) else {
+                                                continue;
+                                            };
+                                            let field_type = field.field_type.clone();
+                                            if let Some(enum_schema) = self.enum_schema(&field_type)
+                                            {
+                                                self.check_enum_literal(q, loc, enum_schema, value);
+                                            } else if field_type != *value {
                                                 self.push_query_err(
                                                      q,
                                                      loc.clone(),
@@ -978,15 +1527,12 @@ impl<'a> Ctx<'a> {
                                                 match self
                                                     .vector_fields
                                                     .get(ty.as_str())
-                                                    .unwrap()
-                                                    .get(field_name.as_str())
-                                                    .unwrap()
-                                                    .field_type
-                                                    == FieldType::Date
+                                                    .and_then(|fields| fields.get(field_name.as_str()))
+                                                    .is_some_and(|field| field.field_type == FieldType::Date)
                                                 {
                                                     true => match Date::new(value) {
                                                         Ok(date) => GeneratedValue::Literal(
-                                                            GenRef::Literal(date.to_rfc3339()),
+                                                            GenRef::from(Value::from(date)),
                                                         ),
                                                         Err(e) => {
                                                             self.push_query_err(
@@ -1042,6 +1588,7 @@ impl<'a> Ctx<'a> {
                             ))),
                             VectorData::Identifier(i) => {
                                 self.is_valid_identifier(q, add.loc.clone(), i.as_str());
+                                self.check_required_param(q, &add.loc, i);
                                 // TODO: if in params then do data.i else i
                                 GeneratedValue::Identifier(GenRef::Ref(format!("data.{}", i)))
                             }
@@ -1086,7 +1633,9 @@ impl<'a> Ctx<'a> {
             // }
             SearchVector(sv) => {
                 if let Some(ref ty) = sv.vector_type {
-                    if !self.vector_set.contains(ty.as_str()) {
+                    if !self.vector_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &sv.loc, "label", ty)
+                    {
                         self.push_query_err(
                             q,
                             sv.loc.clone(),
@@ -1105,6 +1654,7 @@ impl<'a> Ctx<'a> {
                     ))),
                     Some(VectorData::Identifier(i)) => {
                         self.is_valid_identifier(q, sv.loc.clone(), i.as_str());
+                        self.check_required_param(q, &sv.loc, i);
                         // if is in params then use data.
                         if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
                             GeneratedValue::Identifier(GenRef::Ref(format!(
@@ -1165,6 +1715,7 @@ impl<'a> Ctx<'a> {
                         }
                         EvaluatesToNumberType::Identifier(i) => {
                             self.is_valid_identifier(q, sv.loc.clone(), i.as_str());
+                            self.check_required_param(q, &sv.loc, i);
                             // is param
                             if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
                                 GeneratedValue::Identifier(GenRef::Std(format!(
@@ -1207,35 +1758,24 @@ impl<'a> Ctx<'a> {
                         );
                         // Where/boolean ops don't change the element type,
                         // so `cur_ty` stays the same.
-                        assert!(stmt.is_some());
-                        let stmt = stmt.unwrap();
-                        let mut gen_traversal = GeneratedTraversal {
-                            traversal_type: TraversalType::NestedFrom(GenRef::Std("v".to_string())),
-                            steps: vec![],
-                            should_collect: ShouldCollect::ToVec,
-                            source_step: Separator::Empty(SourceStep::Anonymous),
-                        };
+                        // evaluated on each vector before the index is walked
                         match stmt {
-                            GeneratedStatement::Traversal(tr) => {
-                                gen_traversal
-                                    .steps
-                                    .push(Separator::Period(GeneratedStep::Where(Where::Ref(
-                                        WhereRef {
-                                            expr: BoExp::Expr(tr),
-                                        },
-                                    ))));
+                            Some(GeneratedStatement::Traversal(tr)) => {
+                                Some(vec![BoExp::Expr(tr)])
                             }
-                            GeneratedStatement::BoExp(expr) => {
-                                gen_traversal
-                                    .steps
-                                    .push(Separator::Period(GeneratedStep::Where(match expr {
-                                        BoExp::Exists(tr) => Where::Exists(WhereExists { tr }),
-                                        _ => Where::Ref(WhereRef { expr }),
-                                    })));
+                            Some(GeneratedStatement::BoExp(expr)) => Some(vec![expr]),
+                            // errors in the filter are reported already
+                            None => None,
+                            Some(_) => {
+                                self.push_query_err(
+                                    q,
+                                    expr.loc.clone(),
+                                    "`PREFILTER` must be a condition on the vectors".to_string(),
+                                    "filter with an anonymous traversal, e.g. `PREFILTER(_::{field}::EQ(value))`",
+                                );
+                                None
                             }
-                            _ => unreachable!(),
                         }
-                        Some(vec![BoExp::Expr(gen_traversal)])
                     }
                     None => None,
                 };
@@ -1248,7 +1788,12 @@ impl<'a> Ctx<'a> {
                         steps: vec![],
                         should_collect: ShouldCollect::ToVec,
                         source_step: Separator::Period(SourceStep::SearchVector(
-                            GeneratedSearchVector { vec, k, pre_filter },
+                            GeneratedSearchVector {
+                                vec,
+                                k,
+                                pre_filter,
+                                label: self.namespaced_vector(&sv.vector_type),
+                            },
                         )),
                     })),
                 )
@@ -1259,15 +1804,21 @@ impl<'a> Ctx<'a> {
                     .map(|expr| {
                         let (_, stmt) =
                             self.infer_expr_type(expr, scope, q, parent_ty.clone(), None);
-                        assert!(
-                            stmt.is_some(),
-                            "incorrect stmt should've been caught by `infer_expr_type`"
-                        );
-
-                        match stmt.unwrap() {
-                            GeneratedStatement::BoExp(expr) => expr,
-                            GeneratedStatement::Traversal(tr) => BoExp::Expr(tr),
-                            _ => unreachable!(),
+                        match stmt {
+                            Some(GeneratedStatement::BoExp(expr)) => expr,
+                            Some(GeneratedStatement::Traversal(tr)) => BoExp::Expr(tr),
+                            Some(_) => {
+                                self.push_query_err(
+                                    q,
+                                    expr.loc.clone(),
+                                    "`AND` can only combine conditions".to_string(),
+                                    "combine `EXISTS`, boolean operations, `AND` or `OR`",
+                                );
+                                // placeholder, queries with errors aren't generated
+                                BoExp::And(Vec::new())
+                            }
+                            // expressions without a statement have already been reported
+                            None => BoExp::And(Vec::new()),
                         }
                     })
                     .collect::<Vec<_>>();
@@ -1282,14 +1833,21 @@ impl<'a> Ctx<'a> {
                     .map(|expr| {
                         let (_, stmt) =
                             self.infer_expr_type(expr, scope, q, parent_ty.clone(), None);
-                        assert!(
-                            stmt.is_some(),
-                            "incorrect stmt should've been caught by `infer_expr_type`"
-                        );
-                        match stmt.unwrap() {
-                            GeneratedStatement::BoExp(expr) => expr,
-                            GeneratedStatement::Traversal(tr) => BoExp::Expr(tr),
-                            _ => unreachable!(),
+                        match stmt {
+                            Some(GeneratedStatement::BoExp(expr)) => expr,
+                            Some(GeneratedStatement::Traversal(tr)) => BoExp::Expr(tr),
+                            Some(_) => {
+                                self.push_query_err(
+                                    q,
+                                    expr.loc.clone(),
+                                    "`OR` can only combine conditions".to_string(),
+                                    "combine `EXISTS`, boolean operations, `AND` or `OR`",
+                                );
+                                // placeholder, queries with errors aren't generated
+                                BoExp::And(Vec::new())
+                            }
+                            // expressions without a statement have already been reported
+                            None => BoExp::And(Vec::new()),
                         }
                     })
                     .collect::<Vec<_>>();
@@ -1299,41 +1857,97 @@ impl<'a> Ctx<'a> {
                 )
             }
             Exists(expr) => {
+                let is_nested = parent_ty.is_some();
                 let (_, stmt) = self.infer_expr_type(expr, scope, q, parent_ty, gen_query);
-                assert!(stmt.is_some());
-                assert!(matches!(stmt, Some(GeneratedStatement::Traversal(_))));
-                let expr = match stmt.unwrap() {
-                    GeneratedStatement::Traversal(mut tr) => {
-                        tr.traversal_type =
-                            TraversalType::NestedFrom(GenRef::Std("val".to_string()));
+                let expr = match stmt {
+                    Some(GeneratedStatement::Traversal(mut tr)) => {
+                        // `x <- EXISTS(N<User>)` starts from its own source
+                        if is_nested {
+                            tr.traversal_type =
+                                TraversalType::NestedFrom(GenRef::Std("val".to_string()));
+                        }
+                        tr.should_collect = ShouldCollect::No;
                         tr
                     }
-                    _ => unreachable!(),
+                    Some(_) => {
+                        self.push_query_err(
+                            q,
+                            expression.loc.clone(),
+                            "`EXISTS` can only check a traversal".to_string(),
+                            "check a traversal, e.g. `EXISTS(_::Out<Follows>)`",
+                        );
+                        return (Type::Boolean, None);
+                    }
+                    // expressions without a statement have already been reported
+                    None => return (Type::Boolean, None),
                 };
                 (
                     Type::Boolean,
                     Some(GeneratedStatement::BoExp(BoExp::Exists(expr))),
                 )
             }
-            Empty => (Type::Unknown, Some(GeneratedStatement::Empty)),
-            BM25Search(bm25_search) => {
-                // TODO: look into how best do type checking for type passed in
-                if let Some(ref ty) = bm25_search.type_arg {
-                    if !self.node_set.contains(ty.as_str()) {
+            Not(expr) => {
+                let (_, stmt) = self.infer_expr_type(expr, scope, q, parent_ty, gen_query);
+                let expr = match stmt {
+                    Some(GeneratedStatement::BoExp(expr)) => expr,
+                    Some(GeneratedStatement::Traversal(tr)) => BoExp::Expr(tr),
+                    _ => {
                         self.push_query_err(
                             q,
-                            bm25_search.loc.clone(),
-                            format!("vector type `{}` has not been declared", ty),
-                            format!("add a `V::{}` schema first", ty),
+                            expression.loc.clone(),
+                            "`NOT` can only negate a condition".to_string(),
+                            "negate `EXISTS`, a boolean operation, `AND` or `OR`",
                         );
+                        // placeholder, queries with errors aren't generated
+                        BoExp::And(Vec::new())
                     }
-                }
-                let vec = match &bm25_search.data {
+                };
+                (
+                    Type::Boolean,
+                    Some(GeneratedStatement::BoExp(BoExp::Not(Box::new(expr)))),
+                )
+            }
+            Empty => (Type::Unknown, Some(GeneratedStatement::Empty)),
+            // `WHERE(LOWER(_::{name})::EQ(name))`, evaluated for the element bound to `val`
+            Compared { expr, op } => {
+                let parent_ty = parent_ty.unwrap_or(Type::Unknown);
+                let (subject_ty, expr) =
+                    self.check_expression(q, scope, expr, Some("val"), &parent_ty);
+                let op = self.gen_compared_op(q, scope, op, subject_ty, Some("val"), &parent_ty);
+                (
+                    Type::Boolean,
+                    Some(GeneratedStatement::BoExp(BoExp::Compared { expr, op })),
+                )
+            }
+            // `x <- IF (count)::GT(0) THEN "some" ELSE "none"`, evaluated for the query
+            Conditional { .. } => {
+                let (ty, expr) = self.check_expression(q, scope, expression, None, &Type::Unknown);
+                (
+                    ty.map(Type::Scalar).unwrap_or(Type::Unknown),
+                    Some(GeneratedStatement::Expression(expr)),
+                )
+            }
+            BM25Search(bm25_search) => {
+                // TODO: look into how best do type checking for type passed in
+                if let Some(ref ty) = bm25_search.type_arg {
+                    if !self.node_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &bm25_search.loc, "label", ty)
+                    {
+                        self.push_query_err(
+                            q,
+                            bm25_search.loc.clone(),
+                            format!("vector type `{}` has not been declared", ty),
+                            format!("add a `V::{}` schema first", ty),
+                        );
+                    }
+                }
+                let vec = match &bm25_search.data {
                     Some(ValueType::Literal { value, loc }) => {
                         GeneratedValue::Literal(GenRef::Std(value.to_string()))
                     }
                     Some(ValueType::Identifier { value: i, loc }) => {
                         self.is_valid_identifier(q, bm25_search.loc.clone(), i.as_str());
+                        self.check_required_param(q, &bm25_search.loc, i);
                         // if is in params then use data.
                         if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
                             GeneratedValue::Identifier(GenRef::Ref(format!(
@@ -1394,6 +2008,7 @@ impl<'a> Ctx<'a> {
                         }
                         EvaluatesToNumberType::Identifier(i) => {
                             self.is_valid_identifier(q, bm25_search.loc.clone(), i.as_str());
+                            self.check_required_param(q, &bm25_search.loc, i);
                             // is param
                             if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
                                 GeneratedValue::Identifier(GenRef::Std(format!(
@@ -1440,9 +2055,104 @@ impl<'a> Ctx<'a> {
                     })),
                 )
             }
+            HybridSearch(hs) => {
+                if let Some(ref ty) = hs.node_type {
+                    if !self.node_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &hs.loc, "label", ty)
+                    {
+                        self.push_query_err(
+                            q,
+                            hs.loc.clone(),
+                            format!("node type `{}` has not been declared", ty),
+                            format!("add a `N::{}` schema first", ty),
+                        );
+                    }
+                }
+                let vec = match &hs.vector {
+                    Some(VectorData::Vector(v)) => GeneratedValue::Literal(GenRef::Ref(format!(
+                        "[{}]",
+                        v.iter()
+                            .map(|f| f.to_string())
+                            .collect::<Vec<String>>()
+                            .join(",")
+                    ))),
+                    Some(VectorData::Identifier(i)) => self.gen_search_arg(q, scope, &hs.loc, i),
+                    None => {
+                        self.push_query_err(
+                            q,
+                            hs.loc.clone(),
+                            "`HybridSearch` must have a vector to search by".to_string(),
+                            "add a vector",
+                        );
+                        GeneratedValue::Unknown
+                    }
+                };
+                let query = match &hs.query {
+                    Some(ValueType::Literal { value, .. }) => {
+                        GeneratedValue::Literal(GenRef::Std(value.to_string()))
+                    }
+                    Some(ValueType::Identifier { value: i, .. }) => {
+                        self.gen_search_arg(q, scope, &hs.loc, i)
+                    }
+                    _ => {
+                        self.push_query_err(
+                            q,
+                            hs.loc.clone(),
+                            "`HybridSearch` must have a text to search by".to_string(),
+                            "add a text",
+                        );
+                        GeneratedValue::Unknown
+                    }
+                };
+                let k = match hs.k.as_ref().map(|k| &k.value) {
+                    Some(EvaluatesToNumberType::I32(i)) => {
+                        GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                    }
+                    Some(EvaluatesToNumberType::Identifier(i)) => {
+                        self.is_valid_identifier(q, hs.loc.clone(), i.as_str());
+                        self.check_required_param(q, &hs.loc, i);
+                        // is param
+                        if q.parameters.iter().any(|p| p.name.1 == *i) {
+                            GeneratedValue::Identifier(GenRef::Std(format!("data.{} as usize", i)))
+                        } else {
+                            GeneratedValue::Identifier(GenRef::Std(i.to_string()))
+                        }
+                    }
+                    _ => {
+                        self.push_query_err(
+                            q,
+                            hs.loc.clone(),
+                            "`HybridSearch` must have a limit of nodes to return".to_string(),
+                            "add a limit",
+                        );
+                        GeneratedValue::Unknown
+                    }
+                };
+
+                let hybrid_search = GeneratedHybridSearch {
+                    label: GenRef::Literal(hs.node_type.clone().unwrap_or_default()),
+                    vec,
+                    query,
+                    k,
+                };
+                (
+                    Type::Nodes(hs.node_type.clone()),
+                    Some(GeneratedStatement::Traversal(GeneratedTraversal {
+                        traversal_type: TraversalType::Ref,
+                        steps: vec![],
+                        should_collect: ShouldCollect::ToVec,
+                        source_step: Separator::Period(SourceStep::HybridSearch(hybrid_search)),
+                    })),
+                )
+            }
             _ => {
-                println!("Unknown expression: {:?}", expr);
-                todo!()
+                self.push_query_err(
+                    q,
+                    expression.loc.clone(),
+                    "this expression can't be used here".to_string(),
+                    "use a traversal, a search or an added item instead",
+                );
+                (Type::Unknown, None)
             }
         }
     }
@@ -1462,7 +2172,9 @@ impl<'a> Ctx<'a> {
         let mut previous_step = None;
         let mut cur_ty = match &tr.start {
             StartNode::Node { node_type, ids } => {
-                if !self.node_set.contains(node_type.as_str()) {
+                if !self.node_set.contains(node_type.as_str())
+                    && !self.check_param_derived_name(q, &tr.loc, "label", node_type)
+                {
                     self.push_query_err(
                         q,
                         tr.loc.clone(),
@@ -1497,10 +2209,32 @@ impl<'a> Ctx<'a> {
                                                 }
                                             }
                                         }
-                                        None => unreachable!(),
+                                        None => {
+                                            let index = index.to_string();
+                                            if !self.check_param_derived_name(
+                                                q,
+                                                &loc,
+                                                "index name",
+                                                &index,
+                                            ) {
+                                                self.push_query_err(
+                                                    q,
+                                                    loc.clone(),
+                                                    format!(
+                                                        "`{}` is not a field of node type `{}`",
+                                                        index, node_type
+                                                    ),
+                                                    format!(
+                                                        "check the schema of N::{} for the field name",
+                                                        node_type
+                                                    ),
+                                                );
+                                            }
+                                        }
                                     }
                                 }
-                                None => unreachable!(),
+                                // unknown node types are reported above
+                                None => {}
                             };
                             gen_traversal.source_step = Separator::Period(SourceStep::NFromIndex(
                                 NFromIndex {
@@ -1518,6 +2252,7 @@ impl<'a> Ctx<'a> {
                                     }),
                                     key: GenRef::Ref(match *value {
                                         ValueType::Identifier { value: i, loc } => {
+                                            self.check_required_param(q, &loc, &i);
                                             if self.is_valid_identifier(q, loc.clone(), i.as_str())
                                             {
                                                 if !scope.contains_key(i.as_str()) {
@@ -1535,7 +2270,7 @@ impl<'a> Ctx<'a> {
                                             format!("data.{}", i)
                                         }
                                         ValueType::Literal { value, loc } => match value {
-                                            Value::String(s) => s,
+                                            Value::String(s) => format!("\"{}\"", s),
                                             Value::I8(i) => i.to_string(),
                                             Value::I16(i) => i.to_string(),
                                             Value::I32(i) => i.to_string(),
@@ -1556,6 +2291,7 @@ impl<'a> Ctx<'a> {
                             ));
                         }
                         IdType::Identifier { value: i, loc } => {
+                            self.check_required_param(q, &loc, &i);
                             if self.is_valid_identifier(q, loc.clone(), i.as_str()) {
                                 if !scope.contains_key(i.as_str()) {
                                     self.push_query_err(
@@ -1594,7 +2330,9 @@ impl<'a> Ctx<'a> {
                 Type::Nodes(Some(node_type.to_string()))
             }
             StartNode::Edge { edge_type, ids } => {
-                if !self.edge_map.contains_key(edge_type.as_str()) {
+                if !self.edge_map.contains_key(edge_type.as_str())
+                    && !self.check_param_derived_name(q, &tr.loc, "label", edge_type)
+                {
                     self.push_query_err(
                         q,
                         tr.loc.clone(),
@@ -1604,29 +2342,32 @@ impl<'a> Ctx<'a> {
                 }
                 if let Some(ids) = ids {
                     assert!(ids.len() == 1, "multiple ids not supported yet");
-                    gen_traversal.source_step = Separator::Period(SourceStep::EFromID(EFromID {
-                        id: match ids[0].clone() {
-                            IdType::Identifier { value: i, loc } => {
-                                if self.is_valid_identifier(q, loc.clone(), i.as_str()) {
-                                    if !scope.contains_key(i.as_str()) {
-                                        self.push_query_err(
-                                            q,
-                                            loc,
-                                            format!("variable named `{}` is not in scope", i),
-                                            format!(
-                                                "declare {} in the current scope or fix the typo",
-                                                i
-                                            ),
-                                        );
-                                    }
-                                }
-                                GenRef::Std(format!("data.{}", i))
+                    gen_traversal.source_step = Separator::Period(match ids[0].clone() {
+                        IdType::ByIndex { index, value, loc } => SourceStep::EFromIndex(
+                            self.gen_edge_index(q, scope, edge_type, *index, *value, loc),
+                        ),
+                        IdType::Identifier { value: i, loc } => {
+                            self.check_required_param(q, &loc, &i);
+                            if self.is_valid_identifier(q, loc.clone(), i.as_str())
+                                && !scope.contains_key(i.as_str())
+                            {
+                                self.push_query_err(
+                                    q,
+                                    loc,
+                                    format!("variable named `{}` is not in scope", i),
+                                    format!("declare {} in the current scope or fix the typo", i),
+                                );
                             }
-                            IdType::Literal { value: s, loc } => GenRef::Std(s),
-                            _ => unreachable!(),
-                        },
-                        label: GenRef::Literal(edge_type.clone()),
-                    }));
+                            SourceStep::EFromID(EFromID {
+                                id: GenRef::Std(format!("data.{}", i)),
+                                label: GenRef::Literal(edge_type.clone()),
+                            })
+                        }
+                        IdType::Literal { value: s, loc: _ } => SourceStep::EFromID(EFromID {
+                            id: GenRef::Std(s),
+                            label: GenRef::Literal(edge_type.clone()),
+                        }),
+                    });
                 } else {
                     gen_traversal.source_step =
                         Separator::Period(SourceStep::EFromType(EFromType {
@@ -1664,13 +2405,91 @@ impl<'a> Ctx<'a> {
                     false => Type::Unknown,
                 }
             }
+            StartNode::Analytics(analytics) => {
+                if let Some(ref edge_type) = analytics.edge_type {
+                    if !self.edge_map.contains_key(edge_type.as_str())
+                        && !self.check_param_derived_name(q, &analytics.loc, "label", edge_type)
+                    {
+                        self.push_query_err(
+                            q,
+                            analytics.loc.clone(),
+                            format!("unknown edge type `{}`", edge_type),
+                            format!("declare E::{} in the schema first", edge_type),
+                        );
+                    }
+                }
+                let arg = match &analytics.arg {
+                    Some(arg) => match &arg.value {
+                        EvaluatesToNumberType::I32(i) if *i >= 0 => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::Identifier(i) => {
+                            self.is_valid_identifier(q, arg.loc.clone(), i.as_str());
+                            self.check_required_param(q, &arg.loc, i);
+                            // is param
+                            if q.parameters.iter().any(|p| p.name.1 == *i) {
+                                GeneratedValue::Identifier(GenRef::Std(format!(
+                                    "data.{} as usize",
+                                    i
+                                )))
+                            } else {
+                                GeneratedValue::Identifier(GenRef::Std(format!("{} as usize", i)))
+                            }
+                        }
+                        _ => {
+                            self.push_query_err(
+                                q,
+                                arg.loc.clone(),
+                                "analytics argument must be a non-negative integer".to_string(),
+                                "use a non-negative integer or an integer parameter",
+                            );
+                            GeneratedValue::Unknown
+                        }
+                    },
+                    None => GeneratedValue::Unknown,
+                };
+                gen_traversal.source_step =
+                    Separator::Period(SourceStep::Analytics(GeneratedAnalytics {
+                        label: analytics.edge_type.clone().map(GenRef::Literal),
+                        algorithm: match analytics.algorithm {
+                            AnalyticsAlgorithm::PageRank => {
+                                GeneratedAnalyticsAlgorithm::PageRank(arg)
+                            }
+                            AnalyticsAlgorithm::Betweenness => {
+                                GeneratedAnalyticsAlgorithm::Betweenness(arg)
+                            }
+                            AnalyticsAlgorithm::Components => {
+                                GeneratedAnalyticsAlgorithm::Components
+                            }
+                            AnalyticsAlgorithm::Communities => {
+                                GeneratedAnalyticsAlgorithm::Communities(arg)
+                            }
+                        },
+                    }));
+                // communities are written back to the nodes
+                gen_traversal.traversal_type = match analytics.algorithm {
+                    AnalyticsAlgorithm::Communities => TraversalType::Mut,
+                    _ => TraversalType::Ref,
+                };
+                Type::Nodes(None)
+            }
             // anonymous will be the traversal type rather than the start type
             StartNode::Anonymous => {
-                let parent = parent_ty.unwrap();
                 gen_traversal.traversal_type =
                     TraversalType::Nested(GenRef::Std("val".to_string())); // TODO: ensure this default is stable
                 gen_traversal.source_step = Separator::Empty(SourceStep::Anonymous);
-                parent
+                match parent_ty {
+                    Some(parent) => parent,
+                    None => {
+                        self.push_query_err(
+                            q,
+                            tr.loc.clone(),
+                            "anonymous traversal `_` has nothing to traverse from".to_string(),
+                            "only use `_` inside a step of another traversal",
+                        );
+                        Type::Unknown
+                    }
+                }
             }
         };
 
@@ -1771,32 +2590,75 @@ impl<'a> Ctx<'a> {
                         self.infer_expr_type(expr, scope, q, Some(cur_ty.clone()), None);
                     // Where/boolean ops don't change the element type,
                     // so `cur_ty` stays the same.
-                    assert!(stmt.is_some());
-                    let stmt = stmt.unwrap();
-                    match stmt {
-                        GeneratedStatement::Traversal(tr) => {
-                            gen_traversal
-                                .steps
-                                .push(Separator::Period(GeneratedStep::Where(Where::Ref(
-                                    WhereRef {
-                                        expr: BoExp::Expr(tr),
-                                    },
-                                ))));
+                    let expr = match stmt {
+                        Some(GeneratedStatement::Traversal(tr)) => BoExp::Expr(tr),
+                        Some(GeneratedStatement::BoExp(expr)) => expr,
+                        Some(_) => {
+                            self.push_query_err(
+                                q,
+                                expr.loc.clone(),
+                                "`WHERE` must be given a condition".to_string(),
+                                "filter with a traversal ending in a comparison, or `EXISTS`, `AND`, `OR` or `NOT`",
+                            );
+                            continue;
+                        }
+                        // expressions without a statement have already been reported
+                        None => continue,
+                    };
+                    // a filter on properties right after the source is checked against
+                    // the stored items, which are only decoded if they pass it
+                    let source = match gen_traversal.source_step.inner() {
+                        SourceStep::NFromType(NFromType { label })
+                            if i == 0 && expr.reads_only_properties() =>
+                        {
+                            Some(SourceStep::NFromTypeWhere(NFromTypeWhere {
+                                label: label.clone(),
+                                filter: Box::new(expr.clone()),
+                                lookups: self.index_lookups(label.inner(), &expr),
+                            }))
                         }
-                        GeneratedStatement::BoExp(expr) => {
-                            gen_traversal
-                                .steps
-                                .push(Separator::Period(GeneratedStep::Where(match expr {
-                                    BoExp::Exists(tr) => Where::Exists(WhereExists { tr }),
-                                    _ => Where::Ref(WhereRef { expr }),
-                                })));
+                        SourceStep::EFromType(EFromType { label })
+                            if i == 0 && expr.reads_only_properties() =>
+                        {
+                            Some(SourceStep::EFromTypeWhere(EFromTypeWhere {
+                                label: label.clone(),
+                                filter: Box::new(expr.clone()),
+                            }))
                         }
-                        _ => unreachable!(),
+                        _ => None,
+                    };
+                    match source {
+                        Some(source) => gen_traversal.source_step = Separator::Period(source),
+                        None => gen_traversal
+                            .steps
+                            .push(Separator::Period(GeneratedStep::Where(match expr {
+                                BoExp::Exists(tr) => Where::Exists(WhereExists { tr }),
+                                _ => Where::Ref(WhereRef { expr }),
+                            }))),
                     }
                 }
                 StepType::BooleanOperation(b_op) => {
-                    let step = previous_step.unwrap();
+                    // a comparison starting the traversal compares the items themselves
+                    let step = previous_step.clone();
+                    // a value computed by an expression, compared by value across numeric types
+                    let mut computed = None;
                     let property_type = match &b_op.op {
+                        BooleanOpType::LessThanOrEqual(expr)
+                        | BooleanOpType::LessThan(expr)
+                        | BooleanOpType::GreaterThanOrEqual(expr)
+                        | BooleanOpType::GreaterThan(expr)
+                        | BooleanOpType::Equal(expr)
+                        | BooleanOpType::NotEqual(expr)
+                            if is_computed(expr) =>
+                        {
+                            let (ty, value) =
+                                self.check_expression(q, scope, expr, Some("val"), &cur_ty);
+                            computed = Some(value);
+                            match ty {
+                                Some(ty) => ty,
+                                None => return cur_ty.clone(),
+                            }
+                        }
                         BooleanOpType::LessThanOrEqual(expr)
                         | BooleanOpType::LessThan(expr)
                         | BooleanOpType::GreaterThanOrEqual(expr)
@@ -1805,6 +2667,13 @@ impl<'a> Ctx<'a> {
                         | BooleanOpType::NotEqual(expr) => {
                             match self.infer_expr_type(expr, scope, q, Some(cur_ty.clone()), None) {
                                 (Type::Scalar(ft), _) => ft.clone(),
+                                (Type::Optional(_), _) if matches!(&expr.expr, ExpressionType::Identifier(i) if self.is_param(q, i)) =>
+                                {
+                                    if let ExpressionType::Identifier(i) = &expr.expr {
+                                        self.check_required_param(q, &expr.loc, i);
+                                    }
+                                    return cur_ty.clone();
+                                }
                                 (field_type, _) => {
                                     self.push_query_err(
                                         q,
@@ -1818,14 +2687,37 @@ impl<'a> Ctx<'a> {
                         }
                         _ => return cur_ty.clone(),
                     };
+                    // a string literal may be compared with a date field if it holds a date
+                    let date_literal = match &b_op.op {
+                        BooleanOpType::LessThanOrEqual(expr)
+                        | BooleanOpType::LessThan(expr)
+                        | BooleanOpType::GreaterThanOrEqual(expr)
+                        | BooleanOpType::GreaterThan(expr)
+                        | BooleanOpType::Equal(expr)
+                        | BooleanOpType::NotEqual(expr) => match &expr.expr {
+                            ExpressionType::StringLiteral(s) => {
+                                Date::new(&Value::String(s.clone())).ok()
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    let mut compares_date = false;
 
                     // get type of field name
                     let field_name = match step {
-                        StepType::Object(obj) => {
-                            let fields = obj.fields;
-                            assert!(fields.len() == 1);
-                            Some(fields[0].value.value.clone())
-                        }
+                        Some(StepType::Object(obj)) => match obj.fields.as_slice() {
+                            [field] => Some(field.value.value.clone()),
+                            _ => {
+                                self.push_query_err(
+                                    q,
+                                    obj.loc.clone(),
+                                    "only a single property can be compared".to_string(),
+                                    "select the property to compare with `::{field}`",
+                                );
+                                None
+                            }
+                        },
                         _ => None,
                     };
                     if let Some(FieldValueType::Identifier(field_name)) = &field_name {
@@ -1836,7 +2728,20 @@ impl<'a> Ctx<'a> {
                                 if let Some(field_set) = field_set {
                                     match field_set.get(field_name.as_str()) {
                                         Some(field) => {
-                                            if field.field_type != property_type {
+                                            compares_date = field.field_type == FieldType::Date;
+                                            let compares_enum = self.check_compared_enum(
+                                                q,
+                                                &field.field_type,
+                                                &b_op.op,
+                                            );
+                                            let compares_computed = computed.is_some()
+                                                && value_kind(&field.field_type)
+                                                    == value_kind(&property_type);
+                                            let compatible = field.field_type == property_type
+                                                || (compares_date && date_literal.is_some())
+                                                || compares_enum
+                                                || compares_computed;
+                                            if !compatible {
                                                 self.push_query_err(
                                                     q,
                                                     b_op.loc.clone(),
@@ -1864,7 +2769,20 @@ impl<'a> Ctx<'a> {
                                 if let Some(field_set) = field_set {
                                     match field_set.get(field_name.as_str()) {
                                         Some(field) => {
-                                            if field.field_type != property_type {
+                                            compares_date = field.field_type == FieldType::Date;
+                                            let compares_enum = self.check_compared_enum(
+                                                q,
+                                                &field.field_type,
+                                                &b_op.op,
+                                            );
+                                            let compares_computed = computed.is_some()
+                                                && value_kind(&field.field_type)
+                                                    == value_kind(&property_type);
+                                            let compatible = field.field_type == property_type
+                                                || (compares_date && date_literal.is_some())
+                                                || compares_enum
+                                                || compares_computed;
+                                            if !compatible {
                                                 self.push_query_err(
                                                     q,
                                                     b_op.loc.clone(),
@@ -1892,7 +2810,20 @@ impl<'a> Ctx<'a> {
                                 if let Some(field_set) = field_set {
                                     match field_set.get(field_name.as_str()) {
                                         Some(field) => {
-                                            if field.field_type != property_type {
+                                            compares_date = field.field_type == FieldType::Date;
+                                            let compares_enum = self.check_compared_enum(
+                                                q,
+                                                &field.field_type,
+                                                &b_op.op,
+                                            );
+                                            let compares_computed = computed.is_some()
+                                                && value_kind(&field.field_type)
+                                                    == value_kind(&property_type);
+                                            let compatible = field.field_type == property_type
+                                                || (compares_date && date_literal.is_some())
+                                                || compares_enum
+                                                || compares_computed;
+                                            if !compatible {
                                                 self.push_query_err(
                                                     q,
                                                     b_op.loc.clone(),
@@ -1941,10 +2872,18 @@ impl<'a> Ctx<'a> {
                                 ExpressionType::FloatLiteral(f) => {
                                     GeneratedValue::Primitive(GenRef::Std(f.to_string()))
                                 }
+                                ExpressionType::StringLiteral(s) => {
+                                    gen_compared_string(s, compares_date, date_literal)
+                                }
                                 ExpressionType::Identifier(i) => {
                                     self.is_valid_identifier(q, expr.loc.clone(), i.as_str());
+                                    self.check_required_param(q, &expr.loc, i);
                                     self.gen_identifier_or_param(q, i.as_str())
                                 }
+                                ExpressionType::Arithmetic { .. }
+                                | ExpressionType::Function { .. } => {
+                                    GeneratedValue::Computed(Box::new(computed.take().unwrap()))
+                                }
                                 _ => unreachable!("Cannot reach here"),
                             };
                             BoolOp::Lte(Lte { value: v })
@@ -1957,10 +2896,18 @@ impl<'a> Ctx<'a> {
                                 ExpressionType::FloatLiteral(f) => {
                                     GeneratedValue::Primitive(GenRef::Std(f.to_string()))
                                 }
+                                ExpressionType::StringLiteral(s) => {
+                                    gen_compared_string(s, compares_date, date_literal)
+                                }
                                 ExpressionType::Identifier(i) => {
                                     self.is_valid_identifier(q, expr.loc.clone(), i.as_str());
+                                    self.check_required_param(q, &expr.loc, i);
                                     self.gen_identifier_or_param(q, i.as_str())
                                 }
+                                ExpressionType::Arithmetic { .. }
+                                | ExpressionType::Function { .. } => {
+                                    GeneratedValue::Computed(Box::new(computed.take().unwrap()))
+                                }
                                 _ => unreachable!("Cannot reach here"),
                             };
                             BoolOp::Lt(Lt { value: v })
@@ -1973,10 +2920,18 @@ impl<'a> Ctx<'a> {
                                 ExpressionType::FloatLiteral(f) => {
                                     GeneratedValue::Primitive(GenRef::Std(f.to_string()))
                                 }
+                                ExpressionType::StringLiteral(s) => {
+                                    gen_compared_string(s, compares_date, date_literal)
+                                }
                                 ExpressionType::Identifier(i) => {
                                     self.is_valid_identifier(q, expr.loc.clone(), i.as_str());
+                                    self.check_required_param(q, &expr.loc, i);
                                     self.gen_identifier_or_param(q, i.as_str())
                                 }
+                                ExpressionType::Arithmetic { .. }
+                                | ExpressionType::Function { .. } => {
+                                    GeneratedValue::Computed(Box::new(computed.take().unwrap()))
+                                }
                                 _ => unreachable!("Cannot reach here"),
                             };
                             BoolOp::Gte(Gte { value: v })
@@ -1989,10 +2944,18 @@ impl<'a> Ctx<'a> {
                                 ExpressionType::FloatLiteral(f) => {
                                     GeneratedValue::Primitive(GenRef::Std(f.to_string()))
                                 }
+                                ExpressionType::StringLiteral(s) => {
+                                    gen_compared_string(s, compares_date, date_literal)
+                                }
                                 ExpressionType::Identifier(i) => {
                                     self.is_valid_identifier(q, expr.loc.clone(), i.as_str());
+                                    self.check_required_param(q, &expr.loc, i);
                                     self.gen_identifier_or_param(q, i.as_str())
                                 }
+                                ExpressionType::Arithmetic { .. }
+                                | ExpressionType::Function { .. } => {
+                                    GeneratedValue::Computed(Box::new(computed.take().unwrap()))
+                                }
                                 _ => unreachable!("Cannot reach here"),
                             };
                             BoolOp::Gt(Gt { value: v })
@@ -2009,12 +2972,17 @@ impl<'a> Ctx<'a> {
                                     GeneratedValue::Primitive(GenRef::Std(f.to_string()))
                                 }
                                 ExpressionType::StringLiteral(s) => {
-                                    GeneratedValue::Primitive(GenRef::Literal(s.to_string()))
+                                    gen_compared_string(s, compares_date, date_literal)
                                 }
                                 ExpressionType::Identifier(i) => {
                                     self.is_valid_identifier(q, expr.loc.clone(), i.as_str());
+                                    self.check_required_param(q, &expr.loc, i);
                                     self.gen_identifier_or_param(q, i.as_str())
                                 }
+                                ExpressionType::Arithmetic { .. }
+                                | ExpressionType::Function { .. } => {
+                                    GeneratedValue::Computed(Box::new(computed.take().unwrap()))
+                                }
                                 other => {
                                     println!("ID {:?}", other);
                                     panic!("expr be primitive or value")
@@ -2034,12 +3002,17 @@ impl<'a> Ctx<'a> {
                                     GeneratedValue::Primitive(GenRef::Std(f.to_string()))
                                 }
                                 ExpressionType::StringLiteral(s) => {
-                                    GeneratedValue::Primitive(GenRef::Literal(s.to_string()))
+                                    gen_compared_string(s, compares_date, date_literal)
                                 }
                                 ExpressionType::Identifier(i) => {
                                     self.is_valid_identifier(q, expr.loc.clone(), i.as_str());
+                                    self.check_required_param(q, &expr.loc, i);
                                     self.gen_identifier_or_param(q, i.as_str())
                                 }
+                                ExpressionType::Arithmetic { .. }
+                                | ExpressionType::Function { .. } => {
+                                    GeneratedValue::Computed(Box::new(computed.take().unwrap()))
+                                }
                                 _ => unreachable!("Cannot reach here"),
                             };
                             BoolOp::Neq(Neq { value: v })
@@ -2057,7 +3030,8 @@ impl<'a> Ctx<'a> {
                     match tr.steps.iter().nth_back(1) {
                         Some(step) => match &step.step {
                             StepType::Node(gs) => {
-                                let node_ty = gs.get_item_type().unwrap();
+                                // steps without a type, e.g. `FromN`, have no fields to check
+                                let node_ty = gs.get_item_type().unwrap_or_default();
                                 let field_set = self.node_fields.get(node_ty.as_str()).cloned();
                                 if let Some(field_set) = field_set {
                                     for FieldAddition { key, value, loc } in &update.fields {
@@ -2077,7 +3051,8 @@ impl<'a> Ctx<'a> {
                             }
 
                             StepType::Edge(gs) => {
-                                let edge_ty = gs.get_item_type().unwrap();
+                                // steps without a type, e.g. `FromN`, have no fields to check
+                                let edge_ty = gs.get_item_type().unwrap_or_default();
                                 let field_set = self.edge_fields.get(edge_ty.as_str()).cloned();
                                 if let Some(field_set) = field_set {
                                     for FieldAddition { key, value, loc } in &update.fields {
@@ -2169,12 +3144,16 @@ impl<'a> Ctx<'a> {
                                                 field.value.loc.clone(),
                                                 i.as_str(),
                                             );
+                                            self.check_required_param(q, &field.value.loc, i);
                                             self.gen_identifier_or_param(q, i.as_str())
                                         }
                                         FieldValueType::Literal(l) => match l {
                                             Value::String(s) => {
                                                 GeneratedValue::Literal(GenRef::Literal(s.clone()))
                                             }
+                                            Value::Array(_) | Value::Object(_) => {
+                                                GeneratedValue::Literal(GenRef::from(l.clone()))
+                                            }
                                             other => GeneratedValue::Primitive(GenRef::Std(
                                                 other.to_string(),
                                             )),
@@ -2186,6 +3165,7 @@ impl<'a> Ctx<'a> {
                                                     e.loc.clone(),
                                                     i.as_str(),
                                                 );
+                                                self.check_required_param(q, &e.loc, i);
                                                 self.gen_identifier_or_param(q, i.as_str())
                                             }
                                             ExpressionType::StringLiteral(i) => {
@@ -2204,14 +3184,35 @@ impl<'a> Ctx<'a> {
                                                     i.to_string(),
                                                 ))
                                             }
-                                            v => {
-                                                println!("ID {:?}", v);
-                                                panic!("expr be primitive or value")
+                                            ExpressionType::BooleanLiteral(i) => {
+                                                GeneratedValue::Primitive(GenRef::Std(
+                                                    i.to_string(),
+                                                ))
+                                            }
+                                            _ => {
+                                                self.push_query_err(
+                                                    q,
+                                                    e.loc.clone(),
+                                                    format!(
+                                                        "`{}` can't be updated to this expression",
+                                                        field.key
+                                                    ),
+                                                    "use a literal or an identifier",
+                                                );
+                                                GeneratedValue::Unknown
                                             }
                                         },
-                                        v => {
-                                            println!("{:?}", v);
-                                            panic!("Should be primitive or value")
+                                        _ => {
+                                            self.push_query_err(
+                                                q,
+                                                field.loc.clone(),
+                                                format!(
+                                                    "`{}` can't be updated to this value",
+                                                    field.key
+                                                ),
+                                                "use a literal or an identifier",
+                                            );
+                                            GeneratedValue::Unknown
                                         }
                                     },
                                 )
@@ -2224,7 +3225,9 @@ impl<'a> Ctx<'a> {
 
                 StepType::AddEdge(add) => {
                     if let Some(ref ty) = add.edge_type {
-                        if !self.edge_map.contains_key(ty.as_str()) {
+                        if !self.edge_map.contains_key(ty.as_str())
+                            && !self.check_param_derived_name(q, &add.loc, "label", ty)
+                        {
                             self.push_query_err(
                                 q,
                                 add.loc.clone(),
@@ -2237,7 +3240,84 @@ impl<'a> Ctx<'a> {
                     excluded.clear();
                 }
 
-                StepType::Range(_) => { /* doesn't affect type */ }
+                StepType::Range((start, end)) => {
+                    let start = self.gen_range_bound(q, start);
+                    let end = self.gen_range_bound(q, end);
+                    gen_traversal
+                        .steps
+                        .push(Separator::Period(GeneratedStep::Range(Range {
+                            start,
+                            end,
+                        })));
+                }
+                StepType::Limit((offset, limit)) => {
+                    let start = self.gen_range_bound(q, offset);
+                    let end =
+                        GenRef::Std(format!("{} + {}", start, self.gen_range_bound(q, limit)));
+                    gen_traversal
+                        .steps
+                        .push(Separator::Period(GeneratedStep::Range(Range {
+                            start,
+                            end,
+                        })));
+                }
+                StepType::AsOf(at) => {
+                    if !matches!(cur_ty, Type::Nodes(_)) {
+                        self.push_query_err(
+                            q,
+                            graph_step.loc.clone(),
+                            "`AsOf` is only valid on nodes".to_string(),
+                            "apply `AsOf` to nodes of a versioned label",
+                        );
+                    }
+                    let at = self.gen_point_in_time(q, at);
+                    gen_traversal
+                        .steps
+                        .push(Separator::Period(GeneratedStep::AsOf(AsOf { at })));
+                }
+                StepType::OrderBy(order_by) => {
+                    self.validate_order_by(&cur_ty, order_by, q);
+                    // a range right after only needs the items up to its end sorted,
+                    // its bounds are checked with the range itself
+                    let limit = match tr.steps.get(i + 1).map(|s| &s.step) {
+                        Some(StepType::Range((_, end))) => Self::range_bound(q, end),
+                        Some(StepType::Limit((offset, limit))) => {
+                            match (Self::range_bound(q, offset), Self::range_bound(q, limit)) {
+                                (Some(offset), Some(limit)) => {
+                                    Some(GenRef::Std(format!("{} + {}", offset, limit)))
+                                }
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    let property = GenRef::Literal(order_by.field.clone());
+                    let order = match order_by.order {
+                        Order::Asc => GeneratedOrder::Asc,
+                        Order::Desc => GeneratedOrder::Desc,
+                    };
+                    match gen_traversal.source_step.inner() {
+                        // all nodes of a type can be read in order from an index instead
+                        SourceStep::NFromType(NFromType { label }) if i == 0 => {
+                            gen_traversal.source_step =
+                                Separator::Period(SourceStep::NFromTypeOrdered(NFromTypeOrdered {
+                                    label: label.clone(),
+                                    property,
+                                    order,
+                                    limit,
+                                }));
+                        }
+                        _ => gen_traversal
+                            .steps
+                            .push(Separator::Period(GeneratedStep::OrderBy(
+                                GeneratedOrderBy {
+                                    property,
+                                    order,
+                                    limit,
+                                },
+                            ))),
+                    }
+                }
                 StepType::Closure(cl) => {
                     if i != number_of_steps {
                         self.push_query_err(
@@ -2247,8 +3327,9 @@ impl<'a> Ctx<'a> {
                             "move the closure to the end of the traversal",
                         );
                     }
-                    // Add identifier to a temporary scope so inner uses pass
-                    scope.insert(cl.identifier.as_str(), cur_ty.clone()); // If true then already exists so return error
+                    // Add identifier to a temporary scope so inner uses pass, restoring
+                    // whatever it shadowed once the closure is checked
+                    let shadowed = scope.insert(cl.identifier.as_str(), cur_ty.clone());
                     let obj = &cl.object;
                     self.validate_object(
                         &cur_ty,
@@ -2270,7 +3351,10 @@ impl<'a> Ctx<'a> {
                     //         variable_name: cl.identifier.clone(),
                     //         remappings: (),
                     //     })));
-                    scope.remove(cl.identifier.as_str());
+                    match shadowed {
+                        Some(ty) => scope.insert(cl.identifier.as_str(), ty),
+                        None => scope.remove(cl.identifier.as_str()),
+                    };
                     // gen_traversal.traversal_type =
                     //     TraversalType::Nested(GenRef::Std(var));
                 }
@@ -2323,8 +3407,95 @@ impl<'a> Ctx<'a> {
         }
     }
 
-    fn validate_exclude(
-        &mut self,
+    /// Checks that the field to order by exists on the current type
+    fn validate_order_by(&mut self, cur_ty: &Type, order_by: &OrderBy, q: &'a Query) {
+        let (field_set, type_kind, type_name) = match cur_ty {
+            Type::Nodes(Some(ty)) => (self.node_fields.get(ty.as_str()), "node", ty),
+            Type::Edges(Some(ty)) => (self.edge_fields.get(ty.as_str()), "edge", ty),
+            _ => return,
+        };
+        if let Some(field_set) = field_set {
+            if !field_set.contains_key(order_by.field.as_str()) {
+                self.push_query_err(
+                    q,
+                    order_by.loc.clone(),
+                    format!(
+                        "`{}` is not a field of {} `{}`",
+                        order_by.field, type_kind, type_name
+                    ),
+                    "check the schema field names",
+                );
+            }
+        }
+    }
+
+    /// Generates a bound of a range if it is a non-negative integer
+    fn range_bound(q: &Query, expr: &Expression) -> Option<GenRef<String>> {
+        match &expr.expr {
+            ExpressionType::IntegerLiteral(i) if *i >= 0 => Some(GenRef::Std(i.to_string())),
+            // is param
+            ExpressionType::Identifier(i) if q.parameters.iter().any(|p| p.name.1 == *i) => {
+                Some(GenRef::Std(format!("data.{} as usize", i)))
+            }
+            ExpressionType::Identifier(i) => Some(GenRef::Std(format!("{} as usize", i))),
+            _ => None,
+        }
+    }
+
+    /// Generates the point in time of `AsOf`, which must be a date string, seconds since
+    /// the epoch or a parameter holding either or a date
+    fn gen_point_in_time(&mut self, q: &'a Query, expr: &Expression) -> GenRef<String> {
+        match &expr.expr {
+            ExpressionType::StringLiteral(s) => {
+                if Date::new(&Value::from(s.as_str())).is_err() {
+                    self.push_query_err(
+                        q,
+                        expr.loc.clone(),
+                        format!("`{}` is not a date", s),
+                        "use an RFC 3339 date such as `2024-01-01T00:00:00Z`",
+                    );
+                }
+                GenRef::Literal(s.clone())
+            }
+            ExpressionType::IntegerLiteral(i) => GenRef::Std(i.to_string()),
+            ExpressionType::Identifier(i) if q.parameters.iter().any(|p| p.name.1 == *i) => {
+                self.check_required_param(q, &expr.loc, i);
+                GenRef::Std(format!("data.{}.clone()", i))
+            }
+            _ => {
+                self.push_query_err(
+                    q,
+                    expr.loc.clone(),
+                    "`AsOf` takes a point in time".to_string(),
+                    "use a date string, seconds since the epoch or a parameter",
+                );
+                GenRef::Unknown
+            }
+        }
+    }
+
+    /// Generates a bound of a range, which must be a non-negative integer
+    fn gen_range_bound(&mut self, q: &'a Query, expr: &Expression) -> GenRef<String> {
+        if let ExpressionType::Identifier(i) = &expr.expr {
+            self.is_valid_identifier(q, expr.loc.clone(), i.as_str());
+            self.check_required_param(q, &expr.loc, i);
+        }
+        match Self::range_bound(q, expr) {
+            Some(bound) => bound,
+            None => {
+                self.push_query_err(
+                    q,
+                    expr.loc.clone(),
+                    "range bounds must be non-negative integers".to_string(),
+                    "use a non-negative integer or an integer parameter",
+                );
+                GenRef::Unknown
+            }
+        }
+    }
+
+    fn validate_exclude(
+        &mut self,
         cur_ty: &Type,
         tr: &Traversal,
         ex: &Exclude,
@@ -2379,7 +3550,7 @@ impl<'a> Ctx<'a> {
             _ => {
                 self.push_query_err(
                     q,
-                    ex.fields[0].0.clone(),
+                    ex.loc.clone(),
                     "cannot access properties on this type".to_string(),
                     "exclude is only valid on nodes, edges and vectors",
                 );
@@ -2408,20 +3579,40 @@ impl<'a> Ctx<'a> {
                     {
                         match &obj.fields[0].value.value {
                             FieldValueType::Identifier(lit) => {
-                                self.is_valid_identifier(
+                                self.validate_object_fields(
+                                    obj,
+                                    &field_set,
+                                    excluded,
+                                    q,
+                                    node_ty,
+                                    "node",
+                                    Some(tr.loc.clone()),
+                                );
+                                let step = self.gen_property_fetch(
                                     q,
                                     obj.fields[0].value.loc.clone(),
+                                    cur_ty,
                                     lit.as_str(),
                                 );
-                                // gen_traversal.steps.push(Separator::Period(
-                                //     GeneratedStep::PropertyFetch(GenRef::Literal(lit.clone())),
-                                // ));
-                                gen_traversal.steps.push(Separator::Period(
-                                    GeneratedStep::PropertyFetch(GenRef::Literal(lit.clone())),
-                                ));
+                                gen_traversal.steps.push(Separator::Period(step));
                             }
                             _ => unreachable!(),
                         }
+                    } else if let [FieldAddition {
+                        value:
+                            FieldValue {
+                                value: FieldValueType::PropertyPath(path),
+                                ..
+                            },
+                        ..
+                    }] = obj.fields.as_slice()
+                    {
+                        self.check_property_path(q, cur_ty, path);
+                        gen_traversal
+                            .steps
+                            .push(Separator::Period(GeneratedStep::PropertyPath(
+                                GeneratedPropertyPath::from(path),
+                            )));
                     } else if obj.fields.len() > 0 {
                         // if there are multiple fields then it is a field remapping
                         // push object remapping where
@@ -2453,21 +3644,11 @@ impl<'a> Ctx<'a> {
                         // error
                         self.push_query_err(
                             q,
-                            obj.fields[0].value.loc.clone(),
+                            obj.loc.clone(),
                             "node object must have at least one field".to_string(),
                             "node object must have at least one field".to_string(),
                         );
                     }
-
-                    // self.validate_object_fields(
-                    //     obj,
-                    //     &field_set,
-                    //     &excluded,
-                    //     q,
-                    //     node_ty,
-                    //     "node",
-                    //     Some(tr.loc.clone()),
-                    // );
                 }
             }
             Type::Edges(Some(edge_ty)) => {
@@ -2479,20 +3660,40 @@ impl<'a> Ctx<'a> {
                     {
                         match &obj.fields[0].value.value {
                             FieldValueType::Identifier(lit) => {
-                                self.is_valid_identifier(
+                                self.validate_object_fields(
+                                    obj,
+                                    &field_set,
+                                    excluded,
+                                    q,
+                                    edge_ty,
+                                    "edge",
+                                    Some(tr.loc.clone()),
+                                );
+                                let step = self.gen_property_fetch(
                                     q,
                                     obj.fields[0].value.loc.clone(),
+                                    cur_ty,
                                     lit.as_str(),
                                 );
-                                // gen_traversal.steps.push(Separator::Period(
-                                //     GeneratedStep::PropertyFetch(GenRef::Literal(lit.clone())),
-                                // ));
-                                gen_traversal.steps.push(Separator::Period(
-                                    GeneratedStep::PropertyFetch(GenRef::Literal(lit.clone())),
-                                ));
+                                gen_traversal.steps.push(Separator::Period(step));
                             }
                             _ => unreachable!(),
                         };
+                    } else if let [FieldAddition {
+                        value:
+                            FieldValue {
+                                value: FieldValueType::PropertyPath(path),
+                                ..
+                            },
+                        ..
+                    }] = obj.fields.as_slice()
+                    {
+                        self.check_property_path(q, cur_ty, path);
+                        gen_traversal
+                            .steps
+                            .push(Separator::Period(GeneratedStep::PropertyPath(
+                                GeneratedPropertyPath::from(path),
+                            )));
                     } else if obj.fields.len() > 0 {
                         // if there are multiple fields then it is a field remapping
                         // push object remapping where
@@ -2501,7 +3702,7 @@ impl<'a> Ctx<'a> {
                             q,
                             false,
                             scope,
-                            "item",
+                            var_name.unwrap_or("item"),
                             cur_ty.clone(),
                         );
                         // gen_traversal
@@ -2514,21 +3715,11 @@ impl<'a> Ctx<'a> {
                         // error
                         self.push_query_err(
                             q,
-                            obj.fields[0].value.loc.clone(),
+                            obj.loc.clone(),
                             "edge object must have at least one field".to_string(),
                             "edge object must have at least one field".to_string(),
                         );
                     }
-
-                    // self.validate_object_fields(
-                    //     obj,
-                    //     &field_set,
-                    //     &excluded,
-                    //     q,
-                    //     node_ty,
-                    //     "node",
-                    //     Some(tr.loc.clone()),
-                    // );
                 }
             }
             Type::Vector(Some(vector_ty)) => {
@@ -2543,6 +3734,37 @@ impl<'a> Ctx<'a> {
                         "vector",
                         Some(tr.loc.clone()),
                     );
+                    // if there is only one field then it is a property access
+                    if let [FieldAddition {
+                        value:
+                            FieldValue {
+                                value: FieldValueType::Identifier(lit),
+                                ..
+                            },
+                        ..
+                    }] = obj.fields.as_slice()
+                    {
+                        let step = self.gen_property_fetch(
+                            q,
+                            obj.fields[0].value.loc.clone(),
+                            cur_ty,
+                            lit.as_str(),
+                        );
+                        gen_traversal.steps.push(Separator::Period(step));
+                    } else if !obj.fields.is_empty() {
+                        // if there are multiple fields then it is a field remapping
+                        let remapping = self.parse_object_remapping(
+                            &obj.fields,
+                            q,
+                            false,
+                            scope,
+                            var_name.unwrap_or("item"),
+                            cur_ty.clone(),
+                        );
+                        gen_traversal
+                            .steps
+                            .push(Separator::Period(GeneratedStep::Remapping(remapping)));
+                    }
                 }
             }
             Type::Anonymous(ty) => {
@@ -2561,7 +3783,7 @@ impl<'a> Ctx<'a> {
             _ => {
                 self.push_query_err(
                     q,
-                    obj.fields[0].value.loc.clone(),
+                    obj.loc.clone(),
                     "cannot access properties on this type".to_string(),
                     "property access is only valid on nodes, edges and vectors",
                 );
@@ -2588,17 +3810,18 @@ impl<'a> Ctx<'a> {
                     .push(Separator::Period(GeneratedStep::OutE(GeneratedOutE {
                         label: GenRef::Literal(label.clone()),
                     })));
-                let edge = self.edge_map.get(label.as_str());
-                if edge.is_none() {
-                    self.push_query_err(
-                        q,
-                        gs.loc.clone(),
-                        format!("Edge of type `{}` does not exist", label),
-                        "check the schema for valid edge types",
-                    );
+                let Some(edge) = self.edge_map.get(label.as_str()).copied() else {
+                    if !self.check_param_derived_name(q, &gs.loc, "label", label) {
+                        self.push_query_err(
+                            q,
+                            gs.loc.clone(),
+                            format!("Edge of type `{}` does not exist", label),
+                            "check the schema for valid edge types",
+                        );
+                    }
                     return None;
-                }
-                match edge.unwrap().from.1 == node_label.clone() {
+                };
+                match edge.from.1 == node_label.clone() {
                     true => Some(Type::Edges(Some(label.to_string()))),
                     false => {
                         self.push_query_err(
@@ -2620,18 +3843,19 @@ impl<'a> Ctx<'a> {
                     .push(Separator::Period(GeneratedStep::InE(GeneratedInE {
                         label: GenRef::Literal(label.clone()),
                     })));
-                let edge = self.edge_map.get(label.as_str());
-                if edge.is_none() {
-                    self.push_query_err(
-                        q,
-                        gs.loc.clone(),
-                        format!("Edge of type `{}` does not exist", label),
-                        "check the schema for valid edge types",
-                    );
+                let Some(edge) = self.edge_map.get(label.as_str()).copied() else {
+                    if !self.check_param_derived_name(q, &gs.loc, "label", label) {
+                        self.push_query_err(
+                            q,
+                            gs.loc.clone(),
+                            format!("Edge of type `{}` does not exist", label),
+                            "check the schema for valid edge types",
+                        );
+                    }
                     return None;
-                }
+                };
 
-                match edge.unwrap().to.1 == node_label.clone() {
+                match edge.to.1 == node_label.clone() {
                     true => Some(Type::Edges(Some(label.to_string()))),
                     false => {
                         self.push_query_err(
@@ -2645,6 +3869,54 @@ impl<'a> Ctx<'a> {
                 }
             }
 
+            // Node‑to‑Count
+            (
+                OutCount(label) | InCount(label),
+                Type::Nodes(Some(node_label)) | Type::Vector(Some(node_label)),
+            ) => {
+                let outgoing = matches!(gs.step, OutCount(_));
+                let label_ref = GenRef::Literal(label.clone());
+                traversal.steps.push(Separator::Period(match outgoing {
+                    true => GeneratedStep::OutCount(GeneratedOutCount { label: label_ref }),
+                    false => GeneratedStep::InCount(GeneratedInCount { label: label_ref }),
+                }));
+                // the count is answered from the adjacency lists, nothing is collected
+                traversal.should_collect = ShouldCollect::No;
+                let edge = match self.edge_map.get(label.as_str()) {
+                    Some(edge) => edge,
+                    None => {
+                        if !self.check_param_derived_name(q, &gs.loc, "label", label) {
+                            self.push_query_err(
+                                q,
+                                gs.loc.clone(),
+                                format!("Edge of type `{}` does not exist", label),
+                                "check the schema for valid edge types",
+                            );
+                        }
+                        return None;
+                    }
+                };
+                let (end, direction) = match outgoing {
+                    true => (&edge.from.1, "outgoing"),
+                    false => (&edge.to.1, "incoming"),
+                };
+                match end == node_label {
+                    true => Some(Type::Scalar(FieldType::I64)),
+                    false => {
+                        self.push_query_err(
+                            q,
+                            gs.loc.clone(),
+                            format!(
+                                "Edge of type `{}` exists but it is not a valid {} edge type for node of type `{}`",
+                                label, direction, node_label
+                            ),
+                            "check the schema for valid edge types",
+                        );
+                        None
+                    }
+                }
+            }
+
             // Node‑to‑Node
             (Out(label), Type::Nodes(Some(node_label)) | Type::Vector(Some(node_label))) => {
                 let edge_type = match self.edge_map.get(label.as_str()) {
@@ -2654,11 +3926,20 @@ impl<'a> Ctx<'a> {
                         } else if self.vector_set.contains(edge.to.1.as_str()) {
                             EdgeType::Vec
                         } else {
-                            panic!("Edge of type `{}` does not exist", label);
+                            // the undeclared endpoint is reported with the schema
+                            return None;
                         }
                     }
                     None => {
-                        unreachable!() // throw error
+                        if !self.check_param_derived_name(q, &gs.loc, "label", label) {
+                            self.push_query_err(
+                                q,
+                                gs.loc.clone(),
+                                format!("Edge of type `{}` does not exist", label),
+                                "check the schema for valid edge types",
+                            );
+                        }
+                        return None;
                     }
                 };
                 traversal
@@ -2667,23 +3948,23 @@ impl<'a> Ctx<'a> {
                         edge_type: GenRef::Ref(edge_type.to_string()),
                         label: GenRef::Literal(label.clone()),
                     })));
-                let edge = self.edge_map.get(label.as_str());
-                // assert!(edge.is_some()); // make sure is caught
-                if edge.is_none() {
-                    self.push_query_err(
-                        q,
-                        gs.loc.clone(),
-                        format!("Edge of type `{}` does not exist", label),
-                        "check the schema for valid edge types",
-                    );
+                let Some(edge) = self.edge_map.get(label.as_str()).copied() else {
+                    if !self.check_param_derived_name(q, &gs.loc, "label", label) {
+                        self.push_query_err(
+                            q,
+                            gs.loc.clone(),
+                            format!("Edge of type `{}` does not exist", label),
+                            "check the schema for valid edge types",
+                        );
+                    }
                     return None;
-                }
-                match edge.unwrap().from.1 == node_label.clone() {
+                };
+                match edge.from.1 == node_label.clone() {
                     true => {
                         if EdgeType::Node == edge_type {
-                            Some(Type::Nodes(Some(edge.unwrap().to.1.clone())))
+                            Some(Type::Nodes(Some(edge.to.1.clone())))
                         } else if EdgeType::Vec == edge_type {
-                            Some(Type::Vector(Some(edge.unwrap().to.1.clone())))
+                            Some(Type::Vector(Some(edge.to.1.clone())))
                         } else {
                             None
                         }
@@ -2711,11 +3992,20 @@ impl<'a> Ctx<'a> {
                         } else if self.vector_set.contains(edge.from.1.as_str()) {
                             EdgeType::Vec
                         } else {
-                            panic!("Edge of type `{}` does not exist", label);
+                            // the undeclared endpoint is reported with the schema
+                            return None;
                         }
                     }
                     None => {
-                        unreachable!()
+                        if !self.check_param_derived_name(q, &gs.loc, "label", label) {
+                            self.push_query_err(
+                                q,
+                                gs.loc.clone(),
+                                format!("Edge of type `{}` does not exist", label),
+                                "check the schema for valid edge types",
+                            );
+                        }
+                        return None;
                     }
                 };
 
@@ -2725,24 +4015,24 @@ impl<'a> Ctx<'a> {
                         edge_type: GenRef::Ref(edge_type.to_string()),
                         label: GenRef::Literal(label.clone()),
                     })));
-                let edge = self.edge_map.get(label.as_str());
-                // assert!(edge.is_some());
-                if edge.is_none() {
-                    self.push_query_err(
-                        q,
-                        gs.loc.clone(),
-                        format!("Edge of type `{}` does not exist", label),
-                        "check the schema for valid edge types",
-                    );
+                let Some(edge) = self.edge_map.get(label.as_str()).copied() else {
+                    if !self.check_param_derived_name(q, &gs.loc, "label", label) {
+                        self.push_query_err(
+                            q,
+                            gs.loc.clone(),
+                            format!("Edge of type `{}` does not exist", label),
+                            "check the schema for valid edge types",
+                        );
+                    }
                     return None;
-                }
+                };
 
-                match edge.unwrap().to.1 == node_label.clone() {
+                match edge.to.1 == node_label.clone() {
                     true => {
                         if EdgeType::Node == edge_type {
-                            Some(Type::Nodes(Some(edge.unwrap().to.1.clone())))
+                            Some(Type::Nodes(Some(edge.from.1.clone())))
                         } else if EdgeType::Vec == edge_type {
-                            Some(Type::Vector(Some(edge.unwrap().to.1.clone())))
+                            Some(Type::Vector(Some(edge.from.1.clone())))
                         } else {
                             None
                         }
@@ -2789,6 +4079,41 @@ impl<'a> Ctx<'a> {
                     Some(type_arg) => Some(GenRef::Literal(type_arg)),
                     None => None,
                 };
+                let max_depth = match &sp.max_depth {
+                    Some(max_depth) => match &max_depth.value {
+                        EvaluatesToNumberType::I32(i) if *i >= 0 => {
+                            Some(GeneratedValue::Primitive(GenRef::Std(i.to_string())))
+                        }
+                        EvaluatesToNumberType::Identifier(i) => {
+                            self.is_valid_identifier(q, max_depth.loc.clone(), i.as_str());
+                            self.check_required_param(q, &max_depth.loc, i);
+                            // is param
+                            if q.parameters.iter().any(|p| p.name.1 == *i) {
+                                Some(GeneratedValue::Identifier(GenRef::Std(format!(
+                                    "data.{} as usize",
+                                    i
+                                ))))
+                            } else {
+                                Some(GeneratedValue::Identifier(GenRef::Std(format!(
+                                    "{} as usize",
+                                    i
+                                ))))
+                            }
+                        }
+                        _ => {
+                            self.push_query_err(
+                                q,
+                                max_depth.loc.clone(),
+                                "`ShortestPath` max_depth must be a non-negative integer"
+                                    .to_string(),
+                                "use a non-negative integer or an integer parameter",
+                            );
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let weight_property = sp.weight_property.clone().map(GenRef::Literal);
                 // check edge type is valid
                 traversal
                     .steps
@@ -2799,18 +4124,71 @@ impl<'a> Ctx<'a> {
                                 label: type_arg,
                                 from: Some(GenRef::from(from)),
                                 to: Some(GenRef::from(to)),
+                                max_depth,
+                                weight_property,
                             },
                             (Some(from), None) => GeneratedShortestPath {
                                 label: type_arg,
                                 from: Some(GenRef::from(from)),
                                 to: None,
+                                max_depth,
+                                weight_property,
                             },
                             (None, Some(to)) => GeneratedShortestPath {
                                 label: type_arg,
                                 from: None,
                                 to: Some(GenRef::from(to)),
+                                max_depth,
+                                weight_property,
                             },
-                            (None, None) => panic!("Invalid shortest path"),
+                            // `to_from` in the grammar always parses a `To` or a `From`
+                            (None, None) => {
+                                unreachable!("ShortestPath parsed without a From or a To")
+                            }
+                        },
+                    )));
+                Some(Type::Unknown)
+            }
+            (ShortestPathWeighted(sp), Type::Nodes(_)) => {
+                if let Some(ref type_arg) = sp.type_arg {
+                    match self.edge_fields.get(type_arg.as_str()) {
+                        Some(field_set) => {
+                            if !field_set.contains_key(sp.weight_property.as_str()) {
+                                self.push_query_err(
+                                    q,
+                                    sp.loc.clone(),
+                                    format!(
+                                        "`{}` is not a field of edge `{}`",
+                                        sp.weight_property, type_arg
+                                    ),
+                                    "check the schema field names",
+                                );
+                            }
+                        }
+                        None => {
+                            if !self.check_param_derived_name(q, &sp.loc, "label", type_arg) {
+                                self.push_query_err(
+                                    q,
+                                    sp.loc.clone(),
+                                    format!(
+                                        "`ShortestPathWeighted<{}>` refers to unknown edge type",
+                                        type_arg
+                                    ),
+                                    "declare the edge schema first",
+                                );
+                            }
+                        }
+                    }
+                }
+                traversal
+                    .steps
+                    .push(Separator::Period(GeneratedStep::ShortestPathWeighted(
+                        GeneratedShortestPathWeighted {
+                            label: sp.type_arg.clone().map(GenRef::Literal),
+                            from: sp.from.clone().map(GenRef::from),
+                            to: sp.to.clone().map(GenRef::from),
+                            weight_property: GenRef::Literal(sp.weight_property.clone()),
+                            heuristic_property: sp.heuristic_property.clone().map(GenRef::Literal),
                         },
                     )));
                 Some(Type::Unknown)
@@ -2829,7 +4207,9 @@ impl<'a> Ctx<'a> {
                         );
                 }
                 if let Some(ref ty) = sv.vector_type {
-                    if !self.vector_set.contains(ty.as_str()) {
+                    if !self.vector_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &sv.loc, "label", ty)
+                    {
                         self.push_query_err(
                             q,
                             sv.loc.clone(),
@@ -2848,6 +4228,7 @@ impl<'a> Ctx<'a> {
                     ))),
                     Some(VectorData::Identifier(i)) => {
                         self.is_valid_identifier(q, sv.loc.clone(), i.as_str());
+                        self.check_required_param(q, &sv.loc, i);
                         // if is in params then use data.
                         if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
                             GeneratedValue::Identifier(GenRef::Ref(format!(
@@ -2907,6 +4288,7 @@ impl<'a> Ctx<'a> {
                         }
                         EvaluatesToNumberType::Identifier(i) => {
                             self.is_valid_identifier(q, sv.loc.clone(), i.as_str());
+                            self.check_required_param(q, &sv.loc, i);
                             // is param
                             if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
                                 GeneratedValue::Identifier(GenRef::Std(format!(
@@ -3004,6 +4386,12 @@ impl<'a> Ctx<'a> {
             (Type::Edges(Some(span)), GraphStepType::Out(_) | GraphStepType::In(_)) => {
                 format!("use `FromN` or `ToN` to traverse nodes from `{}`", span)
             }
+            (Type::Edges(Some(span)), GraphStepType::OutCount(_) | GraphStepType::InCount(_)) => {
+                format!(
+                    "use `FromN` or `ToN` to traverse nodes from `{}` before counting their edges",
+                    span
+                )
+            }
 
             (_, _) => {
                 println!(
@@ -3042,7 +4430,10 @@ impl<'a> Ctx<'a> {
                 match &value.value {
                     FieldValueType::Identifier(identifier) => {
                         if self.is_valid_identifier(q, value.loc.clone(), identifier.as_str()) {
-                            if !field_set.contains_key(identifier.as_str()) {
+                            if identifier != SCORE_FIELD
+                                && identifier != ID_FIELD
+                                && !field_set.contains_key(identifier.as_str())
+                            {
                                 self.push_query_err(
                                     q,
                                     value.loc.clone(),
@@ -3160,8 +4551,20 @@ impl<'a> Ctx<'a> {
                                     new_value: inner_traversal,
                                 })
                             }
-                            ExpressionType::Exists(exists) => {
-                                todo!()
+                            ExpressionType::Arithmetic { .. }
+                            | ExpressionType::Function { .. }
+                            | ExpressionType::Conditional { .. }
+                            | ExpressionType::Exists(_)
+                            | ExpressionType::Not(_)
+                            | ExpressionType::And(_)
+                            | ExpressionType::Or(_) => {
+                                let (_, value) =
+                                    self.check_expression(q, scope, expr, Some(var_name), &parent_ty);
+                                RemappingType::ExpressionRemapping(ExpressionRemapping {
+                                    variable_name: var_name.to_string(),
+                                    new_field: key.clone(),
+                                    value,
+                                })
                             }
                             ExpressionType::BooleanLiteral(bo_lit) => {
                                 RemappingType::ValueRemapping(ValueRemapping {
@@ -3220,6 +4623,8 @@ impl<'a> Ctx<'a> {
                                             .contains_key(identifier.as_str()), ty.as_str()),
                                         _ => unreachable!(),
                                     };
+                                    let is_valid_field =
+                                        is_valid_field || identifier == SCORE_FIELD;
                                     match is_valid_field {
                                         true => {
                                             RemappingType::TraversalRemapping(TraversalRemapping {
@@ -3233,8 +4638,11 @@ impl<'a> Ctx<'a> {
                                                         SourceStep::Anonymous,
                                                     ),
                                                     steps: vec![Separator::Period(
-                                                        GeneratedStep::PropertyFetch(
-                                                            GenRef::Literal(identifier.to_string()),
+                                                        self.gen_property_fetch(
+                                                            q,
+                                                            expr.loc.clone(),
+                                                            &parent_ty,
+                                                            identifier.as_str(),
                                                         ),
                                                     )],
                                                     should_collect: ShouldCollect::ToVec,
@@ -3305,6 +4713,8 @@ impl<'a> Ctx<'a> {
                                     ty.as_str()),
                                 _ => unreachable!(),
                             };
+                            let is_valid_field =
+                                is_valid_field || identifier == SCORE_FIELD || identifier == ID_FIELD;
                             match is_valid_field {
                                 true => RemappingType::TraversalRemapping(TraversalRemapping {
                                     variable_name: var_name.to_string(),
@@ -3315,9 +4725,12 @@ impl<'a> Ctx<'a> {
                                         )),
                                         source_step: Separator::Empty(SourceStep::Anonymous),
                                         steps: vec![Separator::Period(
-                                            GeneratedStep::PropertyFetch(GenRef::Literal(
-                                                identifier.to_string(),
-                                            )),
+                                            self.gen_property_fetch(
+                                                q,
+                                                value.loc.clone(),
+                                                &parent_ty,
+                                                identifier.as_str(),
+                                            ),
                                         )],
                                         should_collect: ShouldCollect::ToVec,
                                     },
@@ -3337,6 +4750,36 @@ impl<'a> Ctx<'a> {
                             }
                         }
                     }
+                    FieldValueType::PropertyPath(path) => {
+                        self.check_property_path(q, &parent_ty, path);
+                        RemappingType::TraversalRemapping(TraversalRemapping {
+                            variable_name: var_name.to_string(),
+                            new_field: key.clone(),
+                            new_value: GeneratedTraversal {
+                                traversal_type: TraversalType::NestedFrom(GenRef::Std(
+                                    var_name.to_string(),
+                                )),
+                                source_step: Separator::Empty(SourceStep::Anonymous),
+                                steps: vec![Separator::Period(GeneratedStep::PropertyPath(
+                                    GeneratedPropertyPath::from(path),
+                                ))],
+                                should_collect: ShouldCollect::ToVec,
+                            },
+                        })
+                    }
+                    // a traversal that may yield nothing is a coalesce of just that traversal
+                    FieldValueType::Optional(traversal) => {
+                        let (_, traversal) =
+                            self.gen_nested_traversal(traversal, scope, q, var_name, &parent_ty);
+                        RemappingType::CoalesceRemapping(CoalesceRemapping {
+                            variable_name: var_name.to_string(),
+                            new_field: key.clone(),
+                            values: vec![CoalesceValue::Traversal(Box::new(traversal))],
+                        })
+                    }
+                    FieldValueType::Coalesce(args) => RemappingType::CoalesceRemapping(
+                        self.gen_coalesce(args, q, scope, var_name, &parent_ty, key),
+                    ),
                     // if the field value is another object or closure then recurse (sub mapping would go where traversal would go)
                     FieldValueType::Fields(fields) => {
                         let remapping = self.parse_object_remapping(
@@ -3376,1389 +4819,3430 @@ impl<'a> Ctx<'a> {
         }
     }
 
-    fn walk_statements(
+    /// Checks a traversal that is run for every item of the remapped traversal, returning
+    /// the type of what it yields
+    fn gen_nested_traversal(
         &mut self,
+        traversal: &'a Traversal,
         scope: &mut HashMap<&'a str, Type>,
         q: &'a Query,
-        query: &mut GeneratedQuery,
-        statement: &'a Statement,
-    ) -> Option<GeneratedStatement> {
-        use StatementType::*;
-        match &statement.statement {
-            Assignment(assign) => {
-                if scope.contains_key(assign.variable.as_str()) {
+        var_name: &str,
+        parent_ty: &Type,
+    ) -> (Type, GeneratedTraversal) {
+        let mut inner_traversal = GeneratedTraversal::default();
+        let ty = self.check_traversal(
+            traversal,
+            scope,
+            q,
+            Some(parent_ty.clone()),
+            &mut inner_traversal,
+            None,
+        );
+        inner_traversal.traversal_type = match &traversal.start {
+            StartNode::Identifier(name) if name != var_name => {
+                TraversalType::FromVar(GenRef::Std(name.to_string()))
+            }
+            _ => TraversalType::NestedFrom(GenRef::Std(var_name.to_string())),
+        };
+
+        // a traversal ending in a single property access yields that property
+        let ty = match traversal.steps.last().map(|step| &step.step) {
+            Some(StepType::Object(obj)) if obj.fields.len() == 1 => {
+                match &obj.fields[0].value.value {
+                    FieldValueType::Identifier(field) => {
+                        self.field_type(&ty, field.as_str()).unwrap_or(ty)
+                    }
+                    _ => ty,
+                }
+            }
+            _ => ty,
+        };
+        (ty, inner_traversal)
+    }
+
+    /// Checks the indices and nested fields of a property path against the schema,
+    /// returning the type of the value it reaches
+    fn check_property_path(
+        &mut self,
+        q: &'a Query,
+        ty: &Type,
+        path: &PropertyPath,
+    ) -> Option<FieldType> {
+        let (fields, type_name) = match ty.non_null() {
+            Type::Nodes(Some(name)) => (self.node_fields.get(name.as_str()), name),
+            Type::Edges(Some(name)) => (self.edge_fields.get(name.as_str()), name),
+            Type::Vector(Some(name)) => (self.vector_fields.get(name.as_str()), name),
+            _ => return None,
+        };
+        let mut field_type = match fields.and_then(|fields| fields.get(path.field.as_str())) {
+            Some(field) => field.field_type.clone(),
+            None => {
+                self.push_query_err(
+                    q,
+                    path.loc.clone(),
+                    format!("`{}` is not a field of type `{}`", path.field, type_name),
+                    "check the schema field names",
+                );
+                return None;
+            }
+        };
+        // the part of the path checked so far, for messages
+        let mut checked = path.field.clone();
+        for segment in &path.path {
+            field_type = match (field_type, segment) {
+                (FieldType::Array(inner), PathSegment::Index(_)) => *inner,
+                (FieldType::Object(fields), PathSegment::Field(field)) => match fields.get(field) {
+                    Some(field_type) => field_type.clone(),
+                    None => {
+                        self.push_query_err(
+                            q,
+                            path.loc.clone(),
+                            format!("`{}` is not a field of `{}`", field, checked),
+                            "check the fields of the object in the schema",
+                        );
+                        return None;
+                    }
+                },
+                (field_type, PathSegment::Index(_)) => {
                     self.push_query_err(
                         q,
-                        assign.loc.clone(),
-                        format!("variable `{}` is already declared", assign.variable),
-                        "rename the new variable or remove the previous definition",
+                        path.loc.clone(),
+                        format!("cannot index into `{}` of type `{}`", checked, field_type),
+                        "only arrays can be indexed",
                     );
+                    return None;
                 }
+                (field_type, PathSegment::Field(field)) => {
+                    self.push_query_err(
+                        q,
+                        path.loc.clone(),
+                        format!(
+                            "cannot access `{}` on `{}` of type `{}`",
+                            field, checked, field_type
+                        ),
+                        "only objects have fields",
+                    );
+                    return None;
+                }
+            };
+            match segment {
+                PathSegment::Field(field) => checked = format!("{}.{}", checked, field),
+                PathSegment::Index(index) => checked = format!("{}[{}]", checked, index),
+            }
+        }
+        Some(field_type)
+    }
 
-                let (rhs_ty, stmt) =
-                    self.infer_expr_type(&assign.value, scope, q, None, Some(query));
-                scope.insert(assign.variable.as_str(), rhs_ty);
-                assert!(stmt.is_some(), "Assignment statement should be generated");
+    fn field_type(&self, ty: &Type, field: &str) -> Option<Type> {
+        let fields = match ty.non_null() {
+            Type::Nodes(Some(name)) => self.node_fields.get(name.as_str()),
+            Type::Edges(Some(name)) => self.edge_fields.get(name.as_str()),
+            Type::Vector(Some(name)) => self.vector_fields.get(name.as_str()),
+            _ => None,
+        }?;
+        fields.get(field).map(|field| Type::from(&field.field_type))
+    }
 
-                let assignment = GeneratedStatement::Assignment(GeneratedAssignment {
-                    variable: GenRef::Std(assign.variable.clone()),
-                    value: Box::new(stmt.unwrap()),
-                });
-                // query.statements.push(assignment.clone());
-                Some(assignment)
+    /// Checks an arithmetic or string expression evaluated for the elements of type `ty`
+    /// bound to `var`, or for the query if there's no element, returning the type of its
+    /// value if it checks
+    fn check_expression(
+        &mut self,
+        q: &'a Query,
+        scope: &mut HashMap<&'a str, Type>,
+        expr: &'a Expression,
+        var: Option<&str>,
+        ty: &Type,
+    ) -> (Option<FieldType>, GeneratedExpression) {
+        let unknown = || GeneratedExpression::Value(GeneratedValue::Unknown);
+        match &expr.expr {
+            ExpressionType::IntegerLiteral(i) => (
+                Some(FieldType::I32),
+                GeneratedExpression::Value(GeneratedValue::Primitive(GenRef::Std(i.to_string()))),
+            ),
+            ExpressionType::FloatLiteral(f) => (
+                Some(FieldType::F64),
+                GeneratedExpression::Value(GeneratedValue::Primitive(GenRef::Std(f.to_string()))),
+            ),
+            ExpressionType::StringLiteral(s) => (
+                Some(FieldType::String),
+                GeneratedExpression::Value(GeneratedValue::Literal(GenRef::Literal(s.clone()))),
+            ),
+            ExpressionType::BooleanLiteral(b) => (
+                Some(FieldType::Boolean),
+                GeneratedExpression::Value(GeneratedValue::Primitive(GenRef::Std(b.to_string()))),
+            ),
+            // a parameter, or a field of the elements written without `_::{}`
+            ExpressionType::Identifier(name) if self.is_param(q, name) => {
+                self.check_required_param(q, &expr.loc, name);
+                let param_type = q
+                    .parameters
+                    .iter()
+                    .find(|param| param.name.1 == *name)
+                    .map(|param| param.param_type.1.clone());
+                (
+                    param_type,
+                    GeneratedExpression::Value(self.gen_identifier_or_param(q, name)),
+                )
             }
-
-            AddNode(add) => {
-                if let Some(ref ty) = add.node_type {
-                    if !self.node_set.contains(ty.as_str()) {
+            // a variable holding a value, unless the elements have a field of that name
+            ExpressionType::Identifier(name)
+                if self.field_type(ty, name).is_none()
+                    && matches!(scope.get(name.as_str()), Some(Type::Scalar(_))) =>
+            {
+                let Some(Type::Scalar(var_type)) = scope.get(name.as_str()) else {
+                    unreachable!()
+                };
+                (
+                    Some(var_type.clone()),
+                    GeneratedExpression::Value(GeneratedValue::Identifier(GenRef::Std(format!(
+                        "{}.clone()",
+                        name
+                    )))),
+                )
+            }
+            ExpressionType::Identifier(field) => {
+                self.check_expression_field(q, expr, var, ty, field)
+            }
+            ExpressionType::Traversal(tr) => {
+                let field = match (&tr.start, tr.steps.as_slice()) {
+                    (StartNode::Anonymous, [step]) => match &step.step {
+                        StepType::Object(obj) if obj.fields.len() == 1 => {
+                            match &obj.fields[0].value.value {
+                                FieldValueType::Identifier(field) => Some(field),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match field {
+                    Some(field) => self.check_expression_field(q, expr, var, ty, field),
+                    None => {
                         self.push_query_err(
                             q,
-                            add.loc.clone(),
-                            format!("`AddN<{}>` refers to unknown node type", ty),
-                            "declare the node schema first",
+                            expr.loc.clone(),
+                            "expressions can only compute with properties of the element"
+                                .to_string(),
+                            "use a property `_::{field}`, a parameter or a literal",
                         );
+                        (None, unknown())
                     }
-                    let label = GenRef::Literal(ty.clone());
-
-                    let node_in_schema = self
-                        .output
-                        .nodes
-                        .iter()
-                        .find(|n| n.name == ty.as_str())
-                        .unwrap()
-                        .clone();
-
-                    // Validate fields if both type and fields are present
-                    if let Some(fields) = &add.fields {
-                        // Get the field set before validation
-                        // TODO: Check field types
-                        let field_set = self.node_fields.get(ty.as_str()).cloned();
-                        if let Some(field_set) = field_set {
-                            for (field_name, value) in fields {
-                                if !field_set.contains_key(field_name.as_str()) {
-                                    self.push_query_err(
-                                        q,
-                                        add.loc.clone(),
-                                        format!("`{}` is not a field of node `{}`", field_name, ty),
-                                        "check the schema field names",
-                                    );
-                                }
-                                match value {
-                                    ValueType::Identifier { value, loc } => {
-                                        if self.is_valid_identifier(q, loc.clone(), value.as_str())
-                                        {
-                                            if !scope.contains_key(value.as_str()) {
-                                                self.push_query_err(
-                                                    q,
-                                                    loc.clone(),
-                                                    format!("`{}` is not in scope", value),
-                                                    "declare it earlier or fix the typo",
-                                                );
-                                            }
-                                        };
-                                    }
-                                    ValueType::Literal { value, loc } => {
-                                        // check against type
-                                        let field_type = self
-                                            .node_fields
-                                            .get(ty.as_str())
-                                            .unwrap()
-                                            .get(field_name.as_str())
-                                            .unwrap()
-                                            .field_type
-                                            .clone();
-                                        if field_type != *value {
-                                            self.push_query_err(
-                                                 q,
-                                                 loc.clone(),
-                                                 format!("value `{}` is of type `{}`, which does not match type {} declared in the schema for field `{}` on node type `{}`", value, GenRef::from(value.clone()), field_type, field_name, ty),
-                                                 "ensure the value is of the same type as the field declared in the schema".to_string(),
-                                             );
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                        let mut properties: HashMap<String, GeneratedValue> = fields
-                            .iter()
-                            .map(|(field_name, value)| {
-                                (
-                                    field_name.clone(),
-                                    match value {
-                                        ValueType::Literal { value, loc } => {
-                                            match self
-                                                .node_fields
-                                                .get(ty.as_str())
-                                                .unwrap()
-                                                .get(field_name.as_str())
-                                                .unwrap()
-                                                .field_type
-                                                == FieldType::Date
-                                            {
-                                                true => match Date::new(value) {
-                                                    Ok(date) => GeneratedValue::Literal(
-                                                        GenRef::Literal(date.to_rfc3339()),
-                                                    ),
-                                                    Err(e) => {
-                                                        self.push_query_err(
-                                                            q,
-                                                            loc.clone(),
-                                                            e.to_string(),
-                                                            "ensure the value is a valid date",
-                                                        );
-                                                        GeneratedValue::Unknown
-                                                    }
-                                                },
-                                                false => GeneratedValue::Literal(GenRef::from(
-                                                    value.clone(),
-                                                )),
-                                            }
-                                        }
-                                        ValueType::Identifier { value, loc } => {
-                                            self.is_valid_identifier(
-                                                q,
-                                                loc.clone(),
-                                                value.as_str(),
-                                            );
-                                            // when doing object field access would need to include object here
-                                            GeneratedValue::Identifier(GenRef::Std(format!(
-                                                "data.{}.clone()",
-                                                value.clone()
-                                            )))
-                                        }
-                                        v => {
-                                            self.push_query_err(
-                                                q,
-                                                add.loc.clone(),
-                                                format!("`{:?}` is not a valid field value", v),
-                                                "use a literal or identifier",
-                                            );
-                                            GeneratedValue::Unknown
-                                        }
-                                    },
-                                )
-                            })
-                            .collect();
-
-                        let default_properties = node_in_schema
-                            .properties
-                            .iter()
-                            .filter_map(|p| p.default_value.clone().map(|v| (p.name.clone(), v)))
-                            .collect::<Vec<(String, GeneratedValue)>>();
-
-                        for (field_name, default_value) in default_properties {
-                            if !properties.contains_key(field_name.as_str()) {
-                                properties.insert(field_name, default_value);
-                            }
-                        }
-
-                        let secondary_indices = {
-                            let secondary_indices = node_in_schema
-                                .properties
-                                .iter()
-                                .filter_map(|p| {
-                                    matches!(p.is_index, FieldPrefix::Index)
-                                        .then_some(p.name.clone())
-                                })
-                                .collect::<Vec<_>>();
-                            match secondary_indices.is_empty() {
-                                true => None,
-                                false => Some(secondary_indices),
-                            }
-                        };
-
-                        let add_n = AddN {
-                            label,
-                            properties: Some(properties.into_iter().collect()),
-                            secondary_indices,
+                }
+            }
+            ExpressionType::Arithmetic { op, lhs, rhs } => {
+                let (lhs_ty, lhs) = self.check_expression(q, scope, lhs, var, ty);
+                let (rhs_ty, rhs) = self.check_expression(q, scope, rhs, var, ty);
+                let generated = GeneratedExpression::Arithmetic {
+                    op: *op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                };
+                let (Some(lhs_ty), Some(rhs_ty)) = (lhs_ty, rhs_ty) else {
+                    return (None, generated);
+                };
+                let result = match op {
+                    ArithmeticOp::Add
+                        if lhs_ty == FieldType::String || rhs_ty == FieldType::String =>
+                    {
+                        let is_text = |ft: &FieldType| {
+                            matches!(value_kind(ft), Some("string" | "number" | "boolean"))
                         };
-
-                        let stmt = GeneratedStatement::Traversal(GeneratedTraversal {
-                            source_step: Separator::Period(SourceStep::AddN(add_n)),
-                            steps: vec![],
-                            traversal_type: TraversalType::Mut,
-                            should_collect: ShouldCollect::ToVec,
-                        });
-                        query.is_mut = true;
-
-                        return Some(stmt);
+                        (is_text(&lhs_ty) && is_text(&rhs_ty)).then_some(FieldType::String)
+                    }
+                    _ if value_kind(&lhs_ty) != Some("number")
+                        || value_kind(&rhs_ty) != Some("number") =>
+                    {
+                        None
+                    }
+                    ArithmeticOp::Div => Some(FieldType::F64),
+                    _ if is_float(&lhs_ty) || is_float(&rhs_ty) => Some(FieldType::F64),
+                    _ => Some(FieldType::I64),
+                };
+                if result.is_none() {
+                    self.push_query_err(
+                        q,
+                        expr.loc.clone(),
+                        format!("`{}` can't be applied to `{}` and `{}`", op, lhs_ty, rhs_ty),
+                        match op {
+                            ArithmeticOp::Add => "`+` adds numbers or concatenates strings",
+                            _ => "`-`, `*` and `/` only apply to numbers",
+                        },
+                    );
+                }
+                (result, generated)
+            }
+            ExpressionType::Function { function, arg } => {
+                let (arg_ty, arg) = self.check_expression(q, scope, arg, var, ty);
+                let generated = GeneratedExpression::Function {
+                    function: *function,
+                    arg: Box::new(arg),
+                };
+                let Some(arg_ty) = arg_ty else {
+                    return (None, generated);
+                };
+                let (result, expected) = match function {
+                    ExpressionFunction::Lower | ExpressionFunction::Upper => (
+                        (arg_ty == FieldType::String).then_some(FieldType::String),
+                        "a string",
+                    ),
+                    ExpressionFunction::Len => (
+                        matches!(arg_ty, FieldType::String | FieldType::Array(_))
+                            .then_some(FieldType::I64),
+                        "a string or an array",
+                    ),
+                    ExpressionFunction::Round => (
+                        (value_kind(&arg_ty) == Some("number")).then_some(FieldType::I64),
+                        "a number",
+                    ),
+                };
+                if result.is_none() {
+                    self.push_query_err(
+                        q,
+                        expr.loc.clone(),
+                        format!("`{}` takes {}, not `{}`", function, expected, arg_ty),
+                        format!("pass {} to `{}`", expected, function),
+                    );
+                }
+                (result, generated)
+            }
+            // `EXISTS(_::Out<Follows>)` as a boolean value
+            ExpressionType::Exists(_)
+            | ExpressionType::Not(_)
+            | ExpressionType::And(_)
+            | ExpressionType::Or(_) => {
+                let condition = self.check_condition(q, scope, expr, var, ty);
+                (
+                    Some(FieldType::Boolean),
+                    GeneratedExpression::Condition {
+                        element: var.map(|var| GenRef::Std(var.to_string())),
+                        condition: Box::new(condition),
+                    },
+                )
+            }
+            ExpressionType::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.check_condition(q, scope, condition, var, ty);
+                let (then_ty, then) = self.check_expression(q, scope, then, var, ty);
+                let (otherwise_ty, otherwise) = self.check_expression(q, scope, otherwise, var, ty);
+                let generated = GeneratedExpression::Conditional {
+                    element: var.map(|var| GenRef::Std(var.to_string())),
+                    condition: Box::new(condition),
+                    then: Box::new(then),
+                    otherwise: Box::new(otherwise),
+                };
+                let (Some(then_ty), Some(otherwise_ty)) = (then_ty, otherwise_ty) else {
+                    return (None, generated);
+                };
+                let result = if then_ty == otherwise_ty {
+                    Some(then_ty.clone())
+                } else if value_kind(&then_ty) == Some("number")
+                    && value_kind(&otherwise_ty) == Some("number")
+                {
+                    match is_float(&then_ty) || is_float(&otherwise_ty) {
+                        true => Some(FieldType::F64),
+                        false => Some(FieldType::I64),
                     }
+                } else {
+                    None
+                };
+                if result.is_none() {
+                    self.push_query_err(
+                        q,
+                        expr.loc.clone(),
+                        format!(
+                            "branches of `IF` are of types `{}` and `{}`",
+                            then_ty, otherwise_ty
+                        ),
+                        "make both branches evaluate to the same kind of value",
+                    );
                 }
+                (result, generated)
+            }
+            _ => {
                 self.push_query_err(
                     q,
-                    add.loc.clone(),
-                    "`AddN` must have a node type".to_string(),
-                    "add a node type",
+                    expr.loc.clone(),
+                    "invalid operand of an expression".to_string(),
+                    "use a property `_::{field}`, a parameter or a literal",
                 );
-                None
+                (None, unknown())
             }
+        }
+    }
 
-            AddEdge(add) => {
-                if let Some(ref ty) = add.edge_type {
-                    if !self.edge_map.contains_key(ty.as_str()) {
-                        self.push_query_err(
-                            q,
-                            add.loc.clone(),
-                            format!("`AddE<{}>` refers to unknown edge type", ty),
-                            "declare the edge schema first",
-                        );
-                    }
-                    let label = GenRef::Literal(ty.clone());
-                    // Validate fields if both type and fields are present
-                    let properties = match &add.fields {
-                        Some(fields) => {
-                            // Get the field set before validation
-                            let field_set = self.edge_fields.get(ty.as_str()).cloned();
-                            if let Some(field_set) = field_set {
-                                for (field_name, value) in fields {
-                                    if !field_set.contains_key(field_name.as_str()) {
-                                        self.push_query_err(
-                                            q,
-                                            add.loc.clone(),
-                                            format!(
-                                                "`{}` is not a field of edge `{}`",
-                                                field_name, ty
-                                            ),
-                                            "check the schema field names",
-                                        );
-                                    }
-                                    match value {
-                                        ValueType::Identifier { value, loc } => {
-                                            if self.is_valid_identifier(
-                                                q,
-                                                loc.clone(),
-                                                value.as_str(),
-                                            ) {
-                                                if !scope.contains_key(value.as_str()) {
-                                                    self.push_query_err(
-                                                        q,
-                                                        loc.clone(),
-                                                        format!("`{}` is not in scope", value),
-                                                        "declare it earlier or fix the typo",
-                                                    );
-                                                }
-                                            };
-                                        }
-                                        ValueType::Literal { value, loc } => {
-                                            // check against type
-                                            let field_type = self
-                                                .edge_fields
-                                                .get(ty.as_str())
-                                                .unwrap()
-                                                .get(field_name.as_str())
-                                                .unwrap()
-                                                .field_type
-                                                .clone();
-                                            if field_type != *value {
-                                                self.push_query_err(
-                                                     q,
-                                                     loc.clone(),
-                                                     format!("value `{}` is of type `{}`, which does not match type {} declared in the schema for field `{}` on node type `{}`", value, GenRef::from(value.clone()), field_type, field_name, ty),
-                                                     "ensure the value is of the same type as the field declared in the schema".to_string(),
-                                                 );
-                                            }
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                            }
-                            Some(
-                                fields
-                                    .iter()
-                                    .map(|(field_name, value)| {
-                                        (
-                                            field_name.clone(),
-                                            match value {
-                                                ValueType::Literal { value, loc } => {
-                                                    match self
-                                                        .edge_fields
-                                                        .get(ty.as_str())
-                                                        .unwrap()
-                                                        .get(field_name.as_str())
-                                                        .unwrap()
-                                                        .field_type
-                                                        == FieldType::Date
-                                                    {
-                                                        true => match Date::new(value) {
-                                                            Ok(date) => GeneratedValue::Literal(
-                                                                GenRef::Literal(date.to_rfc3339()),
-                                                            ),
-                                                            Err(e) => {
-                                                                self.push_query_err(
-                                                                    q,
-                                                                    loc.clone(),
-                                                                    e.to_string(),
-                                                                    "ensure the value is a valid date",
-                                                                );
-                                                                GeneratedValue::Unknown
-                                                            }
-                                                        },
-                                                        false => GeneratedValue::Literal(GenRef::from(
-                                                            value.clone(),
-                                                        )),
-                                                    }
-                                                }
-                                                ValueType::Identifier { value, loc } => {
-                                                    self.is_valid_identifier(
-                                                        q,
-                                                        loc.clone(),
-                                                        value.as_str(),
-                                                    );
-                                                    GeneratedValue::Identifier(GenRef::Std(
-                                                        format!("data.{}.clone()", value.clone()), // keep track of used variables
-                                                    ))
-                                                }
-                                                v => {
-                                                    self.push_query_err(
-                                                        q,
-                                                        add.loc.clone(),
-                                                        format!(
-                                                            "`{:?}` is not a valid field value",
-                                                            v
-                                                        ),
-                                                        "use a literal or identifier",
-                                                    );
-                                                    GeneratedValue::Unknown
-                                                }
-                                            },
-                                        )
-                                    })
-                                    .collect(),
-                            )
-                        }
-                        None => None,
-                    };
-                    let to = match &add.connection.to_id {
-                        Some(id) => match id {
-                            IdType::Identifier { value, loc } => {
-                                self.is_valid_identifier(q, loc.clone(), value.as_str());
-                                self.gen_id_access_or_param(q, value.as_str())
-                            }
-                            IdType::Literal { value, loc } => {
-                                GeneratedValue::Literal(GenRef::Literal(value.clone()))
-                            }
-                            _ => unreachable!(),
-                        },
-                        _ => {
-                            self.push_query_err(
-                                q,
-                                add.loc.clone(),
-                                "`AddE` must have a to id".to_string(),
-                                "add a to id",
-                            );
-                            GeneratedValue::Unknown
-                        }
-                    };
-                    let from = match &add.connection.from_id {
-                        Some(id) => match id {
-                            IdType::Identifier { value, loc } => {
-                                self.is_valid_identifier(q, loc.clone(), value.as_str());
-                                self.gen_id_access_or_param(q, value.as_str())
-                            }
-                            IdType::Literal { value, loc } => {
-                                GeneratedValue::Literal(GenRef::Literal(value.clone()))
-                            }
-                            _ => unreachable!(),
-                        },
-                        _ => {
-                            self.push_query_err(
-                                q,
-                                add.loc.clone(),
-                                "`AddE` must have a from id".to_string(),
-                                "add a from id",
-                            );
-                            GeneratedValue::Unknown
-                        }
-                    };
-                    let add_e = AddE {
-                        to,
-                        from,
-                        label,
-                        properties,
-                        // secondary_indices: None, // TODO: Add secondary indices by checking against labeled `INDEX` fields in schema
-                    };
-                    let stmt = GeneratedStatement::Traversal(GeneratedTraversal {
-                        source_step: Separator::Period(SourceStep::AddE(add_e)),
-                        steps: vec![],
-                        traversal_type: TraversalType::Mut,
-                        should_collect: ShouldCollect::ToVec,
-                    });
-                    query.is_mut = true;
-                    // query.statements.push(stmt.clone());
-                    return Some(stmt);
-                }
+    /// A property of the elements an expression is evaluated for
+    fn check_expression_field(
+        &mut self,
+        q: &'a Query,
+        expr: &Expression,
+        var: Option<&str>,
+        ty: &Type,
+        field: &str,
+    ) -> (Option<FieldType>, GeneratedExpression) {
+        let Some(var) = var else {
+            self.push_query_err(
+                q,
+                expr.loc.clone(),
+                format!(
+                    "`{}` is not a parameter or a variable holding a value",
+                    field
+                ),
+                "properties can only be read where there is an element, e.g. in a remapping",
+            );
+            return (None, GeneratedExpression::Value(GeneratedValue::Unknown));
+        };
+        let generated = GeneratedExpression::Property {
+            variable: GenRef::Std(var.to_string()),
+            property: GenRef::Literal(field.to_string()),
+        };
+        let (fields, type_name) = match ty.non_null() {
+            Type::Nodes(Some(name)) => (self.node_fields.get(name.as_str()), name),
+            Type::Edges(Some(name)) => (self.edge_fields.get(name.as_str()), name),
+            Type::Vector(Some(name)) => (self.vector_fields.get(name.as_str()), name),
+            // the fields of untyped elements aren't known
+            _ => return (None, generated),
+        };
+        match fields.and_then(|fields| fields.get(field)) {
+            Some(field) => (Some(field.field_type.clone()), generated),
+            None => {
                 self.push_query_err(
                     q,
-                    add.loc.clone(),
-                    "`AddE` must have an edge type".to_string(),
-                    "add an edge type",
+                    expr.loc.clone(),
+                    format!(
+                        "`{}` is not a field of type `{}` or a parameter",
+                        field, type_name
+                    ),
+                    "check the schema field names or the parameters of the query",
                 );
-                None
+                (None, generated)
             }
+        }
+    }
 
-            AddVector(add) => {
-                if let Some(ref ty) = add.vector_type {
-                    if !self.vector_set.contains(ty.as_str()) {
+    /// Checks a boolean operation comparing a computed value of type `subject`, the
+    /// value compared to is computed as well
+    fn gen_compared_op(
+        &mut self,
+        q: &'a Query,
+        scope: &mut HashMap<&'a str, Type>,
+        op: &'a BooleanOp,
+        subject: Option<FieldType>,
+        var: Option<&str>,
+        ty: &Type,
+    ) -> BoolOp {
+        let expr = match &op.op {
+            BooleanOpType::LessThanOrEqual(expr)
+            | BooleanOpType::LessThan(expr)
+            | BooleanOpType::GreaterThanOrEqual(expr)
+            | BooleanOpType::GreaterThan(expr)
+            | BooleanOpType::Equal(expr)
+            | BooleanOpType::NotEqual(expr) => expr,
+            BooleanOpType::And(_) | BooleanOpType::Or(_) => {
+                self.push_query_err(
+                    q,
+                    op.loc.clone(),
+                    format!(
+                        "boolean operation `{}` cannot be applied to a computed value",
+                        op.loc.span
+                    ),
+                    "compare the value with `EQ`, `NEQ`, `GT`, `GTE`, `LT` or `LTE`",
+                );
+                return BoolOp::Eq(Eq {
+                    value: GeneratedValue::Unknown,
+                });
+            }
+        };
+        let (value_ty, value) = self.check_expression(q, scope, expr, var, ty);
+        if let (Some(subject), Some(value_ty)) = (&subject, &value_ty) {
+            if value_kind(subject) != value_kind(value_ty) {
+                self.push_query_err(
+                    q,
+                    op.loc.clone(),
+                    format!(
+                        "computed value of type `{}` is compared to a value of type `{}`",
+                        subject, value_ty
+                    ),
+                    "compare strings with strings and numbers with numbers",
+                );
+            }
+        }
+        let value = GeneratedValue::Computed(Box::new(value));
+        match &op.op {
+            BooleanOpType::LessThanOrEqual(_) => BoolOp::Lte(Lte { value }),
+            BooleanOpType::LessThan(_) => BoolOp::Lt(Lt { value }),
+            BooleanOpType::GreaterThanOrEqual(_) => BoolOp::Gte(Gte { value }),
+            BooleanOpType::GreaterThan(_) => BoolOp::Gt(Gt { value }),
+            BooleanOpType::NotEqual(_) => BoolOp::Neq(Neq { value }),
+            _ => BoolOp::Eq(Eq { value }),
+        }
+    }
+
+    /// Checks the condition of an `IF` evaluated for the elements of type `ty` bound to
+    /// `var`, or for the query if there's no element
+    fn check_condition(
+        &mut self,
+        q: &'a Query,
+        scope: &mut HashMap<&'a str, Type>,
+        condition: &'a Expression,
+        var: Option<&str>,
+        ty: &Type,
+    ) -> BoExp {
+        match &condition.expr {
+            ExpressionType::And(exprs) | ExpressionType::Or(exprs) => {
+                let exprs = exprs
+                    .iter()
+                    .map(|expr| self.check_condition(q, scope, expr, var, ty))
+                    .collect();
+                match &condition.expr {
+                    ExpressionType::And(_) => BoExp::And(exprs),
+                    _ => BoExp::Or(exprs),
+                }
+            }
+            ExpressionType::Compared { expr, op } => {
+                let (subject_ty, expr) = self.check_expression(q, scope, expr, var, ty);
+                let op = self.gen_compared_op(q, scope, op, subject_ty, var, ty);
+                BoExp::Compared { expr, op }
+            }
+            ExpressionType::Exists(expr) => {
+                let parent_ty = var.map(|_| ty.clone());
+                let (_, stmt) = self.infer_expr_type(expr, scope, q, parent_ty, None);
+                let mut tr = match stmt {
+                    Some(GeneratedStatement::Traversal(tr)) => tr,
+                    _ => GeneratedTraversal::default(),
+                };
+                // traversals of the element start from it, others from their own source
+                if var.is_some() {
+                    tr.traversal_type = TraversalType::NestedFrom(GenRef::Std("val".to_string()));
+                }
+                tr.should_collect = ShouldCollect::No;
+                BoExp::Exists(tr)
+            }
+            ExpressionType::Not(expr) => {
+                BoExp::Not(Box::new(self.check_condition(q, scope, expr, var, ty)))
+            }
+            // `_::{field}::GT(0)`
+            ExpressionType::Traversal(tr)
+                if var.is_some()
+                    && matches!(
+                        tr.steps.last().map(|step| &step.step),
+                        Some(StepType::BooleanOperation(_))
+                    ) =>
+            {
+                let mut gen_traversal = GeneratedTraversal::default();
+                self.check_traversal(tr, scope, q, Some(ty.clone()), &mut gen_traversal, None);
+                BoExp::Expr(gen_traversal)
+            }
+            // a boolean value, e.g. a parameter, compared to `true`
+            _ => {
+                let (value_ty, expr) = self.check_expression(q, scope, condition, var, ty);
+                if let Some(value_ty) = value_ty.filter(|ty| *ty != FieldType::Boolean) {
+                    self.push_query_err(
+                        q,
+                        condition.loc.clone(),
+                        format!("condition of `IF` is of type `{}`, not a boolean", value_ty),
+                        "compare a value, e.g. `_::{field}::GT(0)`, or use `EXISTS`, `AND` or `OR`",
+                    );
+                }
+                let value = GeneratedExpression::Value(GeneratedValue::Primitive(GenRef::Std(
+                    "true".to_string(),
+                )));
+                BoExp::Compared {
+                    expr,
+                    op: BoolOp::Eq(Eq {
+                        value: GeneratedValue::Computed(Box::new(value)),
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Checks a lookup of edges through the index over one of their fields,
+    /// `E<Type>({field: value})`, and builds its source step
+    fn gen_edge_index(
+        &mut self,
+        q: &'a Query,
+        scope: &HashMap<&'a str, Type>,
+        edge_type: &str,
+        index: IdType,
+        value: ValueType,
+        loc: Loc,
+    ) -> EFromIndex {
+        let index = match index {
+            IdType::Identifier { value, loc: _ } => value,
+            _ => {
+                self.push_query_err(
+                    q,
+                    loc.clone(),
+                    "index type must be an identifier, got literal".to_string(),
+                    "use a field of the edge that has been indexed with `INDEX` instead",
+                );
+                String::new()
+            }
+        };
+        let field = self
+            .edge_fields
+            .get(edge_type)
+            .and_then(|fields| fields.get(index.as_str()))
+            .copied();
+        match field {
+            Some(field) if !field.is_indexed() => self.push_query_err(
+                q,
+                loc.clone(),
+                format!(
+                    "field `{}` has not been indexed for edge type `{}`",
+                    index, edge_type
+                ),
+                format!(
+                    "use a field that has been indexed with `INDEX` in the schema for edge type `{}`",
+                    edge_type
+                ),
+            ),
+            Some(field) => {
+                if let ValueType::Literal { value, loc } = &value {
+                    if !field.field_type.eq(value) {
                         self.push_query_err(
                             q,
-                            add.loc.clone(),
-                            format!("vector type `{}` has not been declared", ty),
-                            format!("add a `V::{}` schema first", ty),
+                            loc.clone(),
+                            format!(
+                                "value `{}` is of type `{}`, expected `{}`",
+                                value.to_string(),
+                                value,
+                                field.field_type
+                            ),
+                            format!("use a value of type `{}`", field.field_type),
                         );
                     }
-                    // Validate vector fields
-                    let (label, properties) = match &add.fields {
-                        Some(fields) => {
-                            let field_set = self.vector_fields.get(ty.as_str()).cloned();
-                            if let Some(field_set) = field_set {
-                                for (field_name, value) in fields {
-                                    if !field_set.contains_key(field_name.as_str()) {
-                                        self.push_query_err(
-                                            q,
-                                            add.loc.clone(),
-                                            format!(
-                                                "`{}` is not a field of vector `{}`",
-                                                field_name, ty
-                                            ),
-                                            "check the schema field names",
-                                        );
-                                    }
-                                    match value {
-                                        ValueType::Identifier { value, loc } => {
-                                            if self.is_valid_identifier(
-                                                q,
-                                                loc.clone(),
-                                                value.as_str(),
-                                            ) {
-                                                if !scope.contains_key(value.as_str()) {
-                                                    self.push_query_err(
-                                                        q,
-                                                        loc.clone(),
-                                                        format!("`{}` is not in scope", value),
-                                                        "declare it earlier or fix the typo",
-                                                    );
-                                                }
-                                            };
-                                        }
-                                        ValueType::Literal { value, loc } => {
-                                            // check against type
-                                            let field_type = self
-                                                .vector_fields
-                                                .get(ty.as_str())
-                                                .unwrap()
-                                                .get(field_name.as_str())
-                                                .unwrap()
-                                                .field_type
-                                                .clone();
-                                            if field_type != *value {
-                                                self.push_query_err(
-                                                     q,
-                                                     loc.clone(),
-                                                     format!("value `{}` is of type `{}`, which does not match type {} declared in the schema for field `{}` on node type `{}`", value, GenRef::from(value.clone()), field_type, field_name, ty),
-                                                     "ensure the value is of the same type as the field declared in the schema".to_string(),
-                                                 );
-                                            }
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                            }
-                            let label = GenRef::Literal(ty.clone());
-                            let properties = fields
-                                .iter()
-                                .map(|(field_name, value)| {
-                                    (
-                                        field_name.clone(),
-                                        match value {
-                                            ValueType::Literal { value, loc } => {
-                                                match self
-                                                    .vector_fields
-                                                    .get(ty.as_str())
-                                                    .unwrap()
-                                                    .get(field_name.as_str())
-                                                    .unwrap()
-                                                    .field_type
-                                                    == FieldType::Date
-                                                {
-                                                    true => match Date::new(value) {
-                                                        Ok(date) => GeneratedValue::Literal(
-                                                            GenRef::Literal(date.to_rfc3339()),
-                                                        ),
-                                                        Err(e) => {
-                                                            self.push_query_err(
-                                                                q,
-                                                                loc.clone(),
-                                                                e.to_string(),
-                                                                "ensure the value is a valid date",
-                                                            );
-                                                            GeneratedValue::Unknown
-                                                        }
-                                                    },
-                                                    false => GeneratedValue::Literal(GenRef::from(
-                                                        value.clone(),
-                                                    )),
-                                                }
-                                            }
-                                            ValueType::Identifier { value, loc } => {
-                                                self.is_valid_identifier(
-                                                    q,
-                                                    loc.clone(),
-                                                    value.as_str(),
-                                                );
-                                                GeneratedValue::Identifier(GenRef::Std(format!(
-                                                    "data.{}.clone()",
-                                                    value.clone()
-                                                )))
-                                            }
-                                            v => {
-                                                self.push_query_err(
-                                                    q,
-                                                    add.loc.clone(),
-                                                    format!("`{:?}` is not a valid field value", v),
-                                                    "use a literal or identifier",
-                                                );
-                                                GeneratedValue::Unknown
-                                            }
-                                        },
-                                    )
-                                })
-                                .collect();
-                            (label, Some(properties))
-                        }
-                        None => (GenRef::Literal(ty.clone()), None),
-                    };
-                    if let Some(vec_data) = &add.data {
-                        let vec = match vec_data {
-                            VectorData::Vector(v) => GeneratedValue::Literal(GenRef::Ref(format!(
-                                "[{}]",
-                                v.iter()
-                                    .map(|f| f.to_string())
-                                    .collect::<Vec<String>>()
-                                    .join(",")
-                            ))),
-                            VectorData::Identifier(i) => {
-                                self.is_valid_identifier(q, add.loc.clone(), i.as_str());
-                                // TODO: if in params then do data.i else i
-                                GeneratedValue::Identifier(GenRef::Ref(format!("data.{}", i)))
-                            }
-                        };
-                        let add_v = AddV {
-                            vec,
-                            label,
-                            properties,
-                        };
-                        let stmt = GeneratedStatement::Traversal(GeneratedTraversal {
-                            source_step: Separator::Period(SourceStep::AddV(add_v)),
-                            steps: vec![],
-                            traversal_type: TraversalType::Mut,
-                            should_collect: ShouldCollect::ToVec,
-                        });
-                        query.is_mut = true;
-                        return Some(stmt);
-                    }
                 }
+            }
+            // unknown edge types are reported by the caller
+            None if self.edge_map.contains_key(edge_type)
+                && !index.is_empty()
+                && !self.check_param_derived_name(q, &loc, "index name", &index) =>
+            {
                 self.push_query_err(
                     q,
-                    add.loc.clone(),
-                    "`AddV` must have a vector type".to_string(),
-                    "add a vector type",
+                    loc.clone(),
+                    format!("`{}` is not a field of edge type `{}`", index, edge_type),
+                    format!("check the schema of E::{} for the field name", edge_type),
                 );
-                None
             }
-            BatchAddVector(add) => {
-                if let Some(ref ty) = add.vector_type {
-                    if !self.vector_set.contains(ty.as_str()) {
+            None => {}
+        }
+
+        let key = match value {
+            ValueType::Identifier { value: i, loc } => {
+                self.check_required_param(q, &loc, &i);
+                if self.is_valid_identifier(q, loc.clone(), i.as_str())
+                    && !scope.contains_key(i.as_str())
+                {
+                    self.push_query_err(
+                        q,
+                        loc,
+                        format!("variable named `{}` is not in scope", i),
+                        format!("declare {} in the current scope or fix the typo", i),
+                    );
+                }
+                format!("data.{}", i)
+            }
+            ValueType::Literal {
+                value: Value::String(s),
+                loc: _,
+            } => format!("\"{}\"", s),
+            ValueType::Literal { value, loc: _ } => value.to_string(),
+            ValueType::Object { .. } => unreachable!(),
+        };
+        EFromIndex {
+            index: GenRef::Literal(index),
+            key: GenRef::Ref(key),
+        }
+    }
+
+    fn gen_coalesce(
+        &mut self,
+        args: &'a [FieldValue],
+        q: &'a Query,
+        scope: &mut HashMap<&'a str, Type>,
+        var_name: &str,
+        parent_ty: &Type,
+        key: &str,
+    ) -> CoalesceRemapping {
+        let mut values = Vec::with_capacity(args.len());
+        let mut first_ty: Option<Type> = None;
+        let mut never_none = false;
+        for arg in args {
+            let (ty, value) = match self.gen_coalesce_value(arg, q, scope, var_name, parent_ty) {
+                Some(value) => value,
+                None => continue,
+            };
+            if never_none {
+                self.push_query_warn(
+                    q,
+                    arg.loc.clone(),
+                    "`Coalesce` argument is never used".to_string(),
+                    "the arguments before it are never `NONE`, remove it",
+                    None,
+                );
+                break;
+            }
+            never_none = !matches!(ty, Type::Optional(_));
+            match &first_ty {
+                Some(first_ty) if !first_ty.is_compatible(&ty) => {
+                    self.push_query_err(
+                        q,
+                        arg.loc.clone(),
+                        format!(
+                            "`Coalesce` argument is {} `{}` but the first one is {} `{}`",
+                            ty.kind_str(),
+                            ty.get_type_name(),
+                            first_ty.kind_str(),
+                            first_ty.get_type_name()
+                        ),
+                        "make all arguments of `Coalesce` the same type",
+                    );
+                }
+                Some(_) => {}
+                None => first_ty = Some(ty),
+            }
+            values.push(value);
+        }
+
+        CoalesceRemapping {
+            variable_name: var_name.to_string(),
+            new_field: key.to_string(),
+            values,
+        }
+    }
+
+    /// Checks a single argument of `Coalesce`, traversals and properties are
+    /// `Type::Optional` as they may yield nothing
+    fn gen_coalesce_value(
+        &mut self,
+        arg: &'a FieldValue,
+        q: &'a Query,
+        scope: &mut HashMap<&'a str, Type>,
+        var_name: &str,
+        parent_ty: &Type,
+    ) -> Option<(Type, CoalesceValue)> {
+        let expr = match &arg.value {
+            FieldValueType::Traversal(traversal) => {
+                let (ty, traversal) =
+                    self.gen_nested_traversal(traversal, scope, q, var_name, parent_ty);
+                return Some((
+                    Type::Optional(Box::new(ty)),
+                    CoalesceValue::Traversal(Box::new(traversal)),
+                ));
+            }
+            FieldValueType::Expression(expr) => &expr.expr,
+            _ => unreachable!(),
+        };
+        match expr {
+            ExpressionType::Traversal(traversal) => {
+                let (ty, traversal) =
+                    self.gen_nested_traversal(traversal, scope, q, var_name, parent_ty);
+                Some((
+                    Type::Optional(Box::new(ty)),
+                    CoalesceValue::Traversal(Box::new(traversal)),
+                ))
+            }
+            ExpressionType::Identifier(identifier) => {
+                if !self.is_valid_identifier(q, arg.loc.clone(), identifier.as_str()) {
+                    return None;
+                }
+                match self.field_type(parent_ty, identifier.as_str()) {
+                    Some(ty) => Some((
+                        Type::Optional(Box::new(ty)),
+                        CoalesceValue::Traversal(Box::new(GeneratedTraversal {
+                            traversal_type: TraversalType::NestedFrom(GenRef::Std(
+                                var_name.to_string(),
+                            )),
+                            source_step: Separator::Empty(SourceStep::Anonymous),
+                            steps: vec![Separator::Period(GeneratedStep::PropertyFetch(
+                                GenRef::Literal(identifier.to_string()),
+                            ))],
+                            should_collect: ShouldCollect::ToVec,
+                        })),
+                    )),
+                    None => {
                         self.push_query_err(
                             q,
-                            add.loc.clone(),
-                            format!("vector type `{}` has not been declared", ty),
-                            "add a `V::{}` schema first",
+                            arg.loc.clone(),
+                            format!(
+                                "`{}` is not a field of {} `{}`",
+                                identifier,
+                                parent_ty.kind_str(),
+                                parent_ty.get_type_name()
+                            ),
+                            "check the schema field names",
                         );
+                        None
                     }
                 }
+            }
+            ExpressionType::StringLiteral(string) => Some((
+                Type::Scalar(FieldType::String),
+                CoalesceValue::Literal(GenRef::Literal(string.clone())),
+            )),
+            ExpressionType::IntegerLiteral(integer) => Some((
+                Type::Scalar(FieldType::I32),
+                CoalesceValue::Literal(GenRef::Std(integer.to_string())),
+            )),
+            ExpressionType::FloatLiteral(float) => Some((
+                Type::Scalar(FieldType::F64),
+                CoalesceValue::Literal(GenRef::Std(format!("{:?}", float))),
+            )),
+            ExpressionType::BooleanLiteral(boolean) => Some((
+                Type::Scalar(FieldType::Boolean),
+                CoalesceValue::Literal(GenRef::Std(boolean.to_string())),
+            )),
+            _ => {
+                self.push_query_err(
+                    q,
+                    arg.loc.clone(),
+                    "invalid `Coalesce` argument".to_string(),
+                    "use a traversal, a field name or a literal",
+                );
                 None
             }
+        }
+    }
 
-            Drop(expr) => {
-                // Nothing special right now; still type‑check sub‑expressions
-                query.is_mut = true;
-                let (_, stmt) = self.infer_expr_type(expr, scope, q, None, Some(query));
-                // query.statements.push(stmt.clone().unwrap());
-                assert!(stmt.is_some());
-                if let Some(GeneratedStatement::Traversal(mut tr)) = stmt {
-                    tr.should_collect = ShouldCollect::No;
-                    // tr.traversal_type = TraversalType::Mut;
-                    Some(GeneratedStatement::Drop(GeneratedDrop { expression: tr }))
-                } else {
-                    panic!("Drop should only be applied to traversals");
+    fn walk_statements(
+        &mut self,
+        scope: &mut HashMap<&'a str, Type>,
+        q: &'a Query,
+        query: &mut GeneratedQuery,
+        statement: &'a Statement,
+    ) -> Option<GeneratedStatement> {
+        use StatementType::*;
+        match &statement.statement {
+            Assignment(assign) => {
+                if scope.contains_key(assign.variable.as_str()) {
+                    self.push_query_err(
+                        q,
+                        assign.loc.clone(),
+                        format!("variable `{}` is already declared", assign.variable),
+                        "rename the new variable or remove the previous definition",
+                    );
                 }
+
+                let (rhs_ty, stmt) =
+                    self.infer_expr_type(&assign.value, scope, q, None, Some(query));
+                scope.insert(assign.variable.as_str(), rhs_ty);
+                // expressions without a statement have already been reported
+                let stmt = stmt?;
+
+                let assignment = GeneratedStatement::Assignment(GeneratedAssignment {
+                    variable: GenRef::Std(assign.variable.clone()),
+                    value: Box::new(stmt),
+                });
+                // query.statements.push(assignment.clone());
+                Some(assignment)
             }
 
-            SearchVector(sv) => {
-                if let Some(ref ty) = sv.vector_type {
-                    if !self.vector_set.contains(ty.as_str()) {
+            AddNode(add) => {
+                if let Some(ref ty) = add.node_type {
+                    if !self.node_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &add.loc, "label", ty)
+                    {
                         self.push_query_err(
                             q,
-                            sv.loc.clone(),
-                            format!("vector type `{}` has not been declared", ty),
-                            format!("add a `V::{}` schema first", ty),
+                            add.loc.clone(),
+                            format!("`AddN<{}>` refers to unknown node type", ty),
+                            "declare the node schema first",
                         );
                     }
-                }
-                let vec = match &sv.data {
-                    Some(VectorData::Vector(v)) => GeneratedValue::Literal(GenRef::Ref(format!(
-                        "[{}]",
-                        v.iter()
-                            .map(|f| f.to_string())
-                            .collect::<Vec<String>>()
-                            .join(",")
-                    ))),
-                    Some(VectorData::Identifier(i)) => {
-                        self.is_valid_identifier(q, sv.loc.clone(), i.as_str());
-                        // if is in params then use data.
-                        if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
-                            GeneratedValue::Identifier(GenRef::Ref(format!(
-                                "data.{}",
-                                i.to_string()
-                            )))
-                        } else if let Some(_) = scope.get(i.as_str()) {
-                            GeneratedValue::Identifier(GenRef::Ref(i.to_string()))
-                        } else {
-                            self.push_query_err(
-                                q,
-                                sv.loc.clone(),
-                                format!("variable named `{}` is not in scope", i),
-                                "declare {} in the current scope or fix the typo",
-                            );
-                            GeneratedValue::Unknown
-                        }
-                    }
-                    _ => {
-                        self.push_query_err(
-                            q,
-                            sv.loc.clone(),
-                            "`SearchVector` must have a vector data".to_string(),
-                            "add a vector data",
-                        );
-                        GeneratedValue::Unknown
-                    }
-                };
-                let k = match &sv.k {
-                    Some(k) => match &k.value {
-                        EvaluatesToNumberType::I8(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
-                        EvaluatesToNumberType::I16(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
-                        EvaluatesToNumberType::I32(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
-                        EvaluatesToNumberType::I64(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
+                    let label = GenRef::Literal(ty.clone());
 
-                        EvaluatesToNumberType::U8(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
-                        EvaluatesToNumberType::U16(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
-                        EvaluatesToNumberType::U32(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
-                        EvaluatesToNumberType::U64(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
-                        EvaluatesToNumberType::U128(i) => {
-                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
-                        }
-                        EvaluatesToNumberType::Identifier(i) => {
-                            self.is_valid_identifier(q, sv.loc.clone(), i.as_str());
-                            // is param
-                            if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
-                                GeneratedValue::Identifier(GenRef::Std(format!(
-                                    "data.{} as usize",
-                                    i
-                                )))
-                            } else {
-                                GeneratedValue::Identifier(GenRef::Std(i.to_string()))
+                    // unknown node types are reported above and have no schema properties
+                    let schema_properties = self
+                        .output
+                        .nodes
+                        .iter()
+                        .find(|n| n.name == ty.as_str())
+                        .map(|n| n.properties.clone())
+                        .unwrap_or_default();
+
+                    // Validate fields if both type and fields are present
+                    if let Some(fields) = &add.fields {
+                        // Get the field set before validation
+                        // TODO: Check field types
+                        let field_set = self.node_fields.get(ty.as_str()).cloned();
+                        if let Some(field_set) = field_set {
+                            for (field_name, value) in fields {
+                                if !field_set.contains_key(field_name.as_str()) {
+                                    self.push_query_err(
+                                        q,
+                                        add.loc.clone(),
+                                        format!("`{}` is not a field of node `{}`", field_name, ty),
+                                        "check the schema field names",
+                                    );
+                                }
+                                match value {
+                                    ValueType::Identifier { value, loc } => {
+                                        if self.is_valid_identifier(q, loc.clone(), value.as_str())
+                                        {
+                                            if !scope.contains_key(value.as_str()) {
+                                                self.push_query_err(
+                                                    q,
+                                                    loc.clone(),
+                                                    format!("`{}` is not in scope", value),
+                                                    "declare it earlier or fix the typo",
+                                                );
+                                            }
+                                        };
+                                    }
+                                    ValueType::Literal { value, loc } => {
+                                        // check against type, unknown fields are reported above
+                                        let Some(field) = field_set.get(field_name.as_str()) else {
+                                            continue;
+                                        };
+                                        let field_type = field.field_type.clone();
+                                        if let Some(enum_schema) = self.enum_schema(&field_type) {
+                                            self.check_enum_literal(q, loc, enum_schema, value);
+                                        } else if field_type != *value {
+                                            self.push_query_err(
+                                                 q,
+                                                 loc.clone(),
+                                                 format!("value `{}` is of type `{}`, which does not match type {} declared in the schema for field `{}` on node type `{}`", value, GenRef::from(value.clone()), field_type, field_name, ty),
+                                                 "ensure the value is of the same type as the field declared in the schema".to_string(),
+                                             );
+                                        }
+                                    }
+                                    _ => {}
+                                }
                             }
                         }
-                        _ => {
-                            self.push_query_err(
-                                q,
-                                sv.loc.clone(),
-                                "`SearchVector` must have a limit of vectors to return".to_string(),
-                                "add a limit",
-                            );
-                            GeneratedValue::Unknown
-                        }
-                    },
-                    None => {
-                        self.push_query_err(
-                            q,
-                            sv.loc.clone(),
-                            "`SearchV` must have a limit of vectors to return".to_string(),
-                            "add a limit",
-                        );
-                        GeneratedValue::Unknown
-                    }
-                };
+                        let mut properties: HashMap<String, GeneratedValue> = fields
+                            .iter()
+                            .map(|(field_name, value)| {
+                                (
+                                    field_name.clone(),
+                                    match value {
+                                        ValueType::Literal { value, loc } => {
+                                            match self
+                                                .node_fields
+                                                .get(ty.as_str())
+                                                .and_then(|fields| fields.get(field_name.as_str()))
+                                                .is_some_and(|field| field.field_type == FieldType::Date)
+                                            {
+                                                true => match Date::new(value) {
+                                                    Ok(date) => GeneratedValue::Literal(
+                                                        GenRef::from(Value::from(date)),
+                                                    ),
+                                                    Err(e) => {
+                                                        self.push_query_err(
+                                                            q,
+                                                            loc.clone(),
+                                                            e.to_string(),
+                                                            "ensure the value is a valid date",
+                                                        );
+                                                        GeneratedValue::Unknown
+                                                    }
+                                                },
+                                                false => GeneratedValue::Literal(GenRef::from(
+                                                    value.clone(),
+                                                )),
+                                            }
+                                        }
+                                        ValueType::Identifier { value, loc } => {
+                                            self.is_valid_identifier(
+                                                q,
+                                                loc.clone(),
+                                                value.as_str(),
+                                            );
+                                            // when doing object field access would need to include object here
+                                            GeneratedValue::Identifier(GenRef::Std(format!(
+                                                "data.{}.clone()",
+                                                value.clone()
+                                            )))
+                                        }
+                                        v => {
+                                            self.push_query_err(
+                                                q,
+                                                add.loc.clone(),
+                                                format!("`{:?}` is not a valid field value", v),
+                                                "use a literal or identifier",
+                                            );
+                                            GeneratedValue::Unknown
+                                        }
+                                    },
+                                )
+                            })
+                            .collect();
 
-                let pre_filter: Option<Vec<BoExp>> = match &sv.pre_filter {
-                    Some(expr) => {
-                        let (_, stmt) = self.infer_expr_type(
-                            expr,
-                            scope,
-                            q,
-                            Some(Type::Vector(sv.vector_type.clone())),
-                            None,
-                        );
-                        // Where/boolean ops don't change the element type,
-                        // so `cur_ty` stays the same.
-                        assert!(stmt.is_some());
-                        let stmt = stmt.unwrap();
-                        let mut gen_traversal = GeneratedTraversal {
-                            traversal_type: TraversalType::NestedFrom(GenRef::Std("v".to_string())),
-                            steps: vec![],
-                            should_collect: ShouldCollect::ToVec,
-                            source_step: Separator::Empty(SourceStep::Anonymous),
-                        };
-                        match stmt {
-                            GeneratedStatement::Traversal(tr) => {
-                                gen_traversal
-                                    .steps
-                                    .push(Separator::Period(GeneratedStep::Where(Where::Ref(
-                                        WhereRef {
-                                            expr: BoExp::Expr(tr),
-                                        },
-                                    ))));
-                            }
-                            GeneratedStatement::BoExp(expr) => {
-                                gen_traversal
-                                    .steps
-                                    .push(Separator::Period(GeneratedStep::Where(match expr {
-                                        BoExp::Exists(tr) => Where::Exists(WhereExists { tr }),
-                                        _ => Where::Ref(WhereRef { expr }),
-                                    })));
+                        let default_properties = schema_properties
+                            .iter()
+                            .filter_map(|p| p.default_value.clone().map(|v| (p.name.clone(), v)))
+                            .collect::<Vec<(String, GeneratedValue)>>();
+
+                        for (field_name, default_value) in default_properties {
+                            if !properties.contains_key(field_name.as_str()) {
+                                properties.insert(field_name, default_value);
                             }
-                            _ => unreachable!(),
                         }
-                        Some(vec![BoExp::Expr(gen_traversal)])
-                    }
-                    None => None,
-                };
 
-                // Search returns nodes that contain the vectors
+                        let secondary_indices = {
+                            let secondary_indices = schema_properties
+                                .iter()
+                                .filter_map(|p| {
+                                    p.is_index.is_indexed()
+                                        .then_some(p.name.clone())
+                                })
+                                .collect::<Vec<_>>();
+                            match secondary_indices.is_empty() {
+                                true => None,
+                                false => Some(secondary_indices),
+                            }
+                        };
 
-                Some(GeneratedStatement::Traversal(GeneratedTraversal {
-                    traversal_type: TraversalType::Ref,
-                    steps: vec![],
-                    should_collect: ShouldCollect::ToVec,
-                    source_step: Separator::Period(SourceStep::SearchVector(
-                        GeneratedSearchVector { vec, k, pre_filter },
-                    )),
-                }))
-            }
+                        let add_n = AddN {
+                            label,
+                            properties: Some(properties.into_iter().collect()),
+                            secondary_indices,
+                        };
 
-            ForLoop(fl) => {
-                // Ensure the collection exists
-                if !scope.contains_key(fl.in_variable.1.as_str()) {
-                    self.push_query_err(
-                        q,
-                        fl.loc.clone(),
-                        format!("`{}` is not defined in the current scope", fl.in_variable.1),
-                        "add a statement assigning it before the loop",
-                    );
-                }
-                // Add loop vars to new child scope and walk the body
-                let mut body_scope = HashMap::new();
-                let mut for_loop_in_variable: ForLoopInVariable = ForLoopInVariable::Empty;
-                // find param from fl.in_variable
-                let param = q.parameters.iter().find(|p| p.name.1 == fl.in_variable.1);
+                        let stmt = GeneratedStatement::Traversal(GeneratedTraversal {
+                            source_step: Separator::Period(SourceStep::AddN(add_n)),
+                            steps: vec![],
+                            traversal_type: TraversalType::Mut,
+                            should_collect: ShouldCollect::ToVec,
+                        });
+                        query.is_mut = true;
 
-                if param.is_none() {
-                    match scope.contains_key(fl.in_variable.1.as_str()) {
-                        true => {
-                            self.is_valid_identifier(q, fl.loc.clone(), fl.in_variable.1.as_str());
-                            for_loop_in_variable = ForLoopInVariable::Identifier(GenRef::Std(
-                                fl.in_variable.1.clone(),
-                            ));
-                        }
-                        false => {
-                            self.push_query_err(
-                                q,
-                                fl.loc.clone(),
-                                format!("`{}` is not a parameter", fl.in_variable.1),
-                                "add a parameter to the query",
-                            );
-                        }
+                        return Some(stmt);
                     }
-                } else {
-                    for_loop_in_variable =
-                        ForLoopInVariable::Parameter(GenRef::Std(fl.in_variable.1.clone()));
                 }
-                let mut for_variable: ForVariable = ForVariable::Empty;
+                self.push_query_err(
+                    q,
+                    add.loc.clone(),
+                    "`AddN` must have a node type".to_string(),
+                    "add a node type",
+                );
+                None
+            }
 
-                match &fl.variable {
-                    ForLoopVars::Identifier { name, loc: _ } => {
-                        self.is_valid_identifier(q, fl.loc.clone(), name.as_str());
-                        // body_scope.insert(name.as_str(), Type::Unknown);
-                        // scope.insert(name.as_str(), Type::Unknown);
-                        for_variable = ForVariable::Identifier(GenRef::Std(name.clone()));
-                    }
-                    ForLoopVars::ObjectAccess {
-                        name: _,
-                        field: _,
-                        loc: _,
-                    } => {
-                        // body_scope.insert(name.as_str(), Type::Unknown);
-                        // for_variable =
-                        //     ForVariable::ObjectDestructure(vec![GenRef::Std(name.clone())]);
-                        unreachable!()
+            AddEdge(add) => {
+                if let Some(ref ty) = add.edge_type {
+                    if !self.edge_map.contains_key(ty.as_str())
+                        && !self.check_param_derived_name(q, &add.loc, "label", ty)
+                    {
+                        self.push_query_err(
+                            q,
+                            add.loc.clone(),
+                            format!("`AddE<{}>` refers to unknown edge type", ty),
+                            "declare the edge schema first",
+                        );
                     }
-                    ForLoopVars::ObjectDestructuring { fields, loc } => {
-                        // TODO: check if fields are valid
-                        match &param {
-                            Some(p) => {
-                                for_loop_in_variable =
-                                    ForLoopInVariable::Parameter(GenRef::Std(p.name.1.clone()));
-                                match &p.param_type.1 {
-                                    FieldType::Array(inner) => match inner.as_ref() {
-                                        FieldType::Object(param_fields) => {
-                                            for (field_loc, field_name) in fields {
-                                                if !param_fields.contains_key(field_name.as_str()) {
+                    let label = GenRef::Literal(ty.clone());
+                    // Validate fields if both type and fields are present
+                    let properties = match &add.fields {
+                        Some(fields) => {
+                            // Get the field set before validation
+                            let field_set = self.edge_fields.get(ty.as_str()).cloned();
+                            if let Some(field_set) = field_set {
+                                for (field_name, value) in fields {
+                                    if !field_set.contains_key(field_name.as_str()) {
+                                        self.push_query_err(
+                                            q,
+                                            add.loc.clone(),
+                                            format!(
+                                                "`{}` is not a field of edge `{}`",
+                                                field_name, ty
+                                            ),
+                                            "check the schema field names",
+                                        );
+                                    }
+                                    match value {
+                                        ValueType::Identifier { value, loc } => {
+                                            if self.is_valid_identifier(
+                                                q,
+                                                loc.clone(),
+                                                value.as_str(),
+                                            ) {
+                                                if !scope.contains_key(value.as_str()) {
                                                     self.push_query_err(
-                                                                q,
-                                                                field_loc.clone(),
-                                                                format!("`{}` is not a field of the inner type of `{}`", field_name, fl.in_variable.1),
-                                                                format!("check the object fields of the parameter `{}`", fl.in_variable.1),
-                                                            );
+                                                        q,
+                                                        loc.clone(),
+                                                        format!("`{}` is not in scope", value),
+                                                        "declare it earlier or fix the typo",
+                                                    );
                                                 }
-                                                body_scope
-                                                    .insert(field_name.as_str(), Type::Unknown);
-                                                scope.insert(field_name.as_str(), Type::Unknown);
+                                            };
+                                        }
+                                        ValueType::Literal { value, loc } => {
+                                            // check against type, unknown fields are reported above
+                                            let Some(field) = field_set.get(field_name.as_str()) else {
+                                                continue;
+                                            };
+                                            let field_type = field.field_type.clone();
+                                            if let Some(enum_schema) = self.enum_schema(&field_type)
+                                            {
+                                                self.check_enum_literal(q, loc, enum_schema, value);
+                                            } else if field_type != *value {
+                                                self.push_query_err(
+                                                     q,
+                                                     loc.clone(),
+                                                     format!("value `{}` is of type `{}`, which does not match type {} declared in the schema for field `{}` on node type `{}`", value, GenRef::from(value.clone()), field_type, field_name, ty),
+                                                     "ensure the value is of the same type as the field declared in the schema".to_string(),
+                                                 );
                                             }
-                                            for_variable = ForVariable::ObjectDestructure(
-                                                fields
-                                                    .iter()
-                                                    .map(|(_, f)| GenRef::Std(f.clone()))
-                                                    .collect(),
-                                            );
                                         }
-                                        _ => {
-                                            self.push_query_err(
+                                        _ => {}
+                                    }
+                                }
+                            }
+                            Some(
+                                fields
+                                    .iter()
+                                    .map(|(field_name, value)| {
+                                        (
+                                            field_name.clone(),
+                                            match value {
+                                                ValueType::Literal { value, loc } => {
+                                                    match self
+                                                        .edge_fields
+                                                        .get(ty.as_str())
+                                                        .and_then(|fields| fields.get(field_name.as_str()))
+                                                        .is_some_and(|field| field.field_type == FieldType::Date)
+                                                    {
+                                                        true => match Date::new(value) {
+                                                            Ok(date) => GeneratedValue::Literal(
+                                                                GenRef::from(Value::from(date)),
+                                                            ),
+                                                            Err(e) => {
+                                                                self.push_query_err(
+                                                                    q,
+                                                                    loc.clone(),
+                                                                    e.to_string(),
+                                                                    "ensure the value is a valid date",
+                                                                );
+                                                                GeneratedValue::Unknown
+                                                            }
+                                                        },
+                                                        false => GeneratedValue::Literal(GenRef::from(
+                                                            value.clone(),
+                                                        )),
+                                                    }
+                                                }
+                                                ValueType::Identifier { value, loc } => {
+                                                    self.is_valid_identifier(
                                                         q,
-                                                        fl.in_variable.0.clone(),
-                                                        format!("the inner type of `{}` is not an object", fl.in_variable.1),
-                                                        "object destructuring only works with arrays of objects",
+                                                        loc.clone(),
+                                                        value.as_str(),
                                                     );
-                                        }
-                                    },
+                                                    GeneratedValue::Identifier(GenRef::Std(
+                                                        format!("data.{}.clone()", value.clone()), // keep track of used variables
+                                                    ))
+                                                }
+                                                v => {
+                                                    self.push_query_err(
+                                                        q,
+                                                        add.loc.clone(),
+                                                        format!(
+                                                            "`{:?}` is not a valid field value",
+                                                            v
+                                                        ),
+                                                        "use a literal or identifier",
+                                                    );
+                                                    GeneratedValue::Unknown
+                                                }
+                                            },
+                                        )
+                                    })
+                                    .collect(),
+                            )
+                        }
+                        None => None,
+                    };
+                    let to = match &add.connection.to_id {
+                        Some(id) => match id {
+                            IdType::Identifier { value, loc } => {
+                                self.is_valid_identifier(q, loc.clone(), value.as_str());
+                                self.gen_id_access_or_param(q, value.as_str())
+                            }
+                            IdType::Literal { value, loc } => {
+                                GeneratedValue::Literal(GenRef::Literal(value.clone()))
+                            }
+                            _ => unreachable!(),
+                        },
+                        _ => {
+                            self.push_query_err(
+                                q,
+                                add.loc.clone(),
+                                "`AddE` must have a to id".to_string(),
+                                "add a to id",
+                            );
+                            GeneratedValue::Unknown
+                        }
+                    };
+                    let from = match &add.connection.from_id {
+                        Some(id) => match id {
+                            IdType::Identifier { value, loc } => {
+                                self.is_valid_identifier(q, loc.clone(), value.as_str());
+                                self.gen_id_access_or_param(q, value.as_str())
+                            }
+                            IdType::Literal { value, loc } => {
+                                GeneratedValue::Literal(GenRef::Literal(value.clone()))
+                            }
+                            _ => unreachable!(),
+                        },
+                        _ => {
+                            self.push_query_err(
+                                q,
+                                add.loc.clone(),
+                                "`AddE` must have a from id".to_string(),
+                                "add a from id",
+                            );
+                            GeneratedValue::Unknown
+                        }
+                    };
+                    let add_e = AddE {
+                        to,
+                        from,
+                        label,
+                        properties,
+                        // secondary_indices: None, // TODO: Add secondary indices by checking against labeled `INDEX` fields in schema
+                    };
+                    let stmt = GeneratedStatement::Traversal(GeneratedTraversal {
+                        source_step: Separator::Period(SourceStep::AddE(add_e)),
+                        steps: vec![],
+                        traversal_type: TraversalType::Mut,
+                        should_collect: ShouldCollect::ToVec,
+                    });
+                    query.is_mut = true;
+                    // query.statements.push(stmt.clone());
+                    return Some(stmt);
+                }
+                self.push_query_err(
+                    q,
+                    add.loc.clone(),
+                    "`AddE` must have an edge type".to_string(),
+                    "add an edge type",
+                );
+                None
+            }
+
+            AddVector(add) => {
+                if let Some(ref ty) = add.vector_type {
+                    if !self.vector_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &add.loc, "label", ty)
+                    {
+                        self.push_query_err(
+                            q,
+                            add.loc.clone(),
+                            format!("vector type `{}` has not been declared", ty),
+                            format!("add a `V::{}` schema first", ty),
+                        );
+                    }
+                    // Validate vector fields
+                    let (label, properties) = match &add.fields {
+                        Some(fields) => {
+                            let field_set = self.vector_fields.get(ty.as_str()).cloned();
+                            if let Some(field_set) = field_set {
+                                for (field_name, value) in fields {
+                                    if !field_set.contains_key(field_name.as_str()) {
+                                        self.push_query_err(
+                                            q,
+                                            add.loc.clone(),
+                                            format!(
+                                                "`{}` is not a field of vector `{}`",
+                                                field_name, ty
+                                            ),
+                                            "check the schema field names",
+                                        );
+                                    }
+                                    match value {
+                                        ValueType::Identifier { value, loc } => {
+                                            if self.is_valid_identifier(
+                                                q,
+                                                loc.clone(),
+                                                value.as_str(),
+                                            ) {
+                                                if !scope.contains_key(value.as_str()) {
+                                                    self.push_query_err(
+                                                        q,
+                                                        loc.clone(),
+                                                        format!("`{}` is not in scope", value),
+                                                        "declare it earlier or fix the typo",
+                                                    );
+                                                }
+                                            };
+                                        }
+                                        ValueType::Literal { value, loc } => {
+                                            // check against type, unknown fields are reported above
+                                            let Some(field) = field_set.get(field_name.as_str()) else {
+                                                continue;
+                                            };
+                                            let field_type = field.field_type.clone();
+                                            if let Some(enum_schema) = self.enum_schema(&field_type)
+                                            {
+                                                self.check_enum_literal(q, loc, enum_schema, value);
+                                            } else if field_type != *value {
+                                                self.push_query_err(
+                                                     q,
+                                                     loc.clone(),
+                                                     format!("value `{}` is of type `{}`, which does not match type {} declared in the schema for field `{}` on node type `{}`", value, GenRef::from(value.clone()), field_type, field_name, ty),
+                                                     "ensure the value is of the same type as the field declared in the schema".to_string(),
+                                                 );
+                                            }
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                            }
+                            let label = GenRef::Literal(ty.clone());
+                            let properties = fields
+                                .iter()
+                                .map(|(field_name, value)| {
+                                    (
+                                        field_name.clone(),
+                                        match value {
+                                            ValueType::Literal { value, loc } => {
+                                                match self
+                                                    .vector_fields
+                                                    .get(ty.as_str())
+                                                    .and_then(|fields| fields.get(field_name.as_str()))
+                                                    .is_some_and(|field| field.field_type == FieldType::Date)
+                                                {
+                                                    true => match Date::new(value) {
+                                                        Ok(date) => GeneratedValue::Literal(
+                                                            GenRef::from(Value::from(date)),
+                                                        ),
+                                                        Err(e) => {
+                                                            self.push_query_err(
+                                                                q,
+                                                                loc.clone(),
+                                                                e.to_string(),
+                                                                "ensure the value is a valid date",
+                                                            );
+                                                            GeneratedValue::Unknown
+                                                        }
+                                                    },
+                                                    false => GeneratedValue::Literal(GenRef::from(
+                                                        value.clone(),
+                                                    )),
+                                                }
+                                            }
+                                            ValueType::Identifier { value, loc } => {
+                                                self.is_valid_identifier(
+                                                    q,
+                                                    loc.clone(),
+                                                    value.as_str(),
+                                                );
+                                                GeneratedValue::Identifier(GenRef::Std(format!(
+                                                    "data.{}.clone()",
+                                                    value.clone()
+                                                )))
+                                            }
+                                            v => {
+                                                self.push_query_err(
+                                                    q,
+                                                    add.loc.clone(),
+                                                    format!("`{:?}` is not a valid field value", v),
+                                                    "use a literal or identifier",
+                                                );
+                                                GeneratedValue::Unknown
+                                            }
+                                        },
+                                    )
+                                })
+                                .collect();
+                            (label, Some(properties))
+                        }
+                        None => (GenRef::Literal(ty.clone()), None),
+                    };
+                    if let Some(vec_data) = &add.data {
+                        let vec = match vec_data {
+                            VectorData::Vector(v) => GeneratedValue::Literal(GenRef::Ref(format!(
+                                "[{}]",
+                                v.iter()
+                                    .map(|f| f.to_string())
+                                    .collect::<Vec<String>>()
+                                    .join(",")
+                            ))),
+                            VectorData::Identifier(i) => {
+                                self.is_valid_identifier(q, add.loc.clone(), i.as_str());
+                                self.check_required_param(q, &add.loc, i);
+                                // TODO: if in params then do data.i else i
+                                GeneratedValue::Identifier(GenRef::Ref(format!("data.{}", i)))
+                            }
+                        };
+                        let add_v = AddV {
+                            vec,
+                            label,
+                            properties,
+                        };
+                        let stmt = GeneratedStatement::Traversal(GeneratedTraversal {
+                            source_step: Separator::Period(SourceStep::AddV(add_v)),
+                            steps: vec![],
+                            traversal_type: TraversalType::Mut,
+                            should_collect: ShouldCollect::ToVec,
+                        });
+                        query.is_mut = true;
+                        return Some(stmt);
+                    }
+                }
+                self.push_query_err(
+                    q,
+                    add.loc.clone(),
+                    "`AddV` must have a vector type".to_string(),
+                    "add a vector type",
+                );
+                None
+            }
+            BatchAddVector(add) => {
+                if let Some(ref ty) = add.vector_type {
+                    if !self.vector_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &add.loc, "label", ty)
+                    {
+                        self.push_query_err(
+                            q,
+                            add.loc.clone(),
+                            format!("vector type `{}` has not been declared", ty),
+                            "add a `V::{}` schema first",
+                        );
+                    }
+                }
+                None
+            }
+
+            Drop(expr) => {
+                // Nothing special right now; still type‑check sub‑expressions
+                query.is_mut = true;
+                let (_, stmt) = self.infer_expr_type(expr, scope, q, None, Some(query));
+                // query.statements.push(stmt.clone().unwrap());
+                match stmt {
+                    Some(GeneratedStatement::Traversal(mut tr)) => {
+                        tr.should_collect = ShouldCollect::No;
+                        // tr.traversal_type = TraversalType::Mut;
+                        Some(GeneratedStatement::Drop(GeneratedDrop { expression: tr }))
+                    }
+                    // `DROP user` drops the items the variable holds
+                    Some(GeneratedStatement::Identifier(var)) => {
+                        Some(GeneratedStatement::Drop(GeneratedDrop {
+                            expression: GeneratedTraversal {
+                                traversal_type: TraversalType::FromVar(var.clone()),
+                                source_step: Separator::Empty(SourceStep::Identifier(var)),
+                                steps: vec![],
+                                should_collect: ShouldCollect::No,
+                            },
+                        }))
+                    }
+                    // the variable isn't in scope, which is reported already
+                    None => None,
+                    Some(_) => {
+                        self.push_query_err(
+                            q,
+                            expr.loc.clone(),
+                            "`DROP` can only be applied to a traversal or a variable".to_string(),
+                            "drop the items of a traversal, e.g. `DROP N<User>(id)`",
+                        );
+                        None
+                    }
+                }
+            }
+
+            SearchVector(sv) => {
+                if let Some(ref ty) = sv.vector_type {
+                    if !self.vector_set.contains(ty.as_str())
+                        && !self.check_param_derived_name(q, &sv.loc, "label", ty)
+                    {
+                        self.push_query_err(
+                            q,
+                            sv.loc.clone(),
+                            format!("vector type `{}` has not been declared", ty),
+                            format!("add a `V::{}` schema first", ty),
+                        );
+                    }
+                }
+                let vec = match &sv.data {
+                    Some(VectorData::Vector(v)) => GeneratedValue::Literal(GenRef::Ref(format!(
+                        "[{}]",
+                        v.iter()
+                            .map(|f| f.to_string())
+                            .collect::<Vec<String>>()
+                            .join(",")
+                    ))),
+                    Some(VectorData::Identifier(i)) => {
+                        self.is_valid_identifier(q, sv.loc.clone(), i.as_str());
+                        self.check_required_param(q, &sv.loc, i);
+                        // if is in params then use data.
+                        if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
+                            GeneratedValue::Identifier(GenRef::Ref(format!(
+                                "data.{}",
+                                i.to_string()
+                            )))
+                        } else if let Some(_) = scope.get(i.as_str()) {
+                            GeneratedValue::Identifier(GenRef::Ref(i.to_string()))
+                        } else {
+                            self.push_query_err(
+                                q,
+                                sv.loc.clone(),
+                                format!("variable named `{}` is not in scope", i),
+                                "declare {} in the current scope or fix the typo",
+                            );
+                            GeneratedValue::Unknown
+                        }
+                    }
+                    _ => {
+                        self.push_query_err(
+                            q,
+                            sv.loc.clone(),
+                            "`SearchVector` must have a vector data".to_string(),
+                            "add a vector data",
+                        );
+                        GeneratedValue::Unknown
+                    }
+                };
+                let k = match &sv.k {
+                    Some(k) => match &k.value {
+                        EvaluatesToNumberType::I8(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::I16(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::I32(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::I64(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+
+                        EvaluatesToNumberType::U8(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::U16(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::U32(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::U64(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::U128(i) => {
+                            GeneratedValue::Primitive(GenRef::Std(i.to_string()))
+                        }
+                        EvaluatesToNumberType::Identifier(i) => {
+                            self.is_valid_identifier(q, sv.loc.clone(), i.as_str());
+                            self.check_required_param(q, &sv.loc, i);
+                            // is param
+                            if let Some(_) = q.parameters.iter().find(|p| p.name.1 == *i) {
+                                GeneratedValue::Identifier(GenRef::Std(format!(
+                                    "data.{} as usize",
+                                    i
+                                )))
+                            } else {
+                                GeneratedValue::Identifier(GenRef::Std(i.to_string()))
+                            }
+                        }
+                        _ => {
+                            self.push_query_err(
+                                q,
+                                sv.loc.clone(),
+                                "`SearchVector` must have a limit of vectors to return".to_string(),
+                                "add a limit",
+                            );
+                            GeneratedValue::Unknown
+                        }
+                    },
+                    None => {
+                        self.push_query_err(
+                            q,
+                            sv.loc.clone(),
+                            "`SearchV` must have a limit of vectors to return".to_string(),
+                            "add a limit",
+                        );
+                        GeneratedValue::Unknown
+                    }
+                };
+
+                let pre_filter: Option<Vec<BoExp>> = match &sv.pre_filter {
+                    Some(expr) => {
+                        let (_, stmt) = self.infer_expr_type(
+                            expr,
+                            scope,
+                            q,
+                            Some(Type::Vector(sv.vector_type.clone())),
+                            None,
+                        );
+                        // Where/boolean ops don't change the element type,
+                        // so `cur_ty` stays the same.
+                        // evaluated on each vector before the index is walked
+                        match stmt {
+                            Some(GeneratedStatement::Traversal(tr)) => {
+                                Some(vec![BoExp::Expr(tr)])
+                            }
+                            Some(GeneratedStatement::BoExp(expr)) => Some(vec![expr]),
+                            // errors in the filter are reported already
+                            None => None,
+                            Some(_) => {
+                                self.push_query_err(
+                                    q,
+                                    expr.loc.clone(),
+                                    "`PREFILTER` must be a condition on the vectors".to_string(),
+                                    "filter with an anonymous traversal, e.g. `PREFILTER(_::{field}::EQ(value))`",
+                                );
+                                None
+                            }
+                        }
+                    }
+                    None => None,
+                };
+
+                // Search returns nodes that contain the vectors
+
+                Some(GeneratedStatement::Traversal(GeneratedTraversal {
+                    traversal_type: TraversalType::Ref,
+                    steps: vec![],
+                    should_collect: ShouldCollect::ToVec,
+                    source_step: Separator::Period(SourceStep::SearchVector(
+                        GeneratedSearchVector {
+                            vec,
+                            k,
+                            pre_filter,
+                            label: self.namespaced_vector(&sv.vector_type),
+                        },
+                    )),
+                }))
+            }
+
+            ForLoop(fl) => {
+                // Ensure the collection exists
+                if !scope.contains_key(fl.in_variable.1.as_str()) {
+                    self.push_query_err(
+                        q,
+                        fl.loc.clone(),
+                        format!("`{}` is not defined in the current scope", fl.in_variable.1),
+                        "add a statement assigning it before the loop",
+                    );
+                }
+                // Add loop vars to new child scope and walk the body
+                let mut body_scope = HashMap::new();
+                let mut for_loop_in_variable: ForLoopInVariable = ForLoopInVariable::Empty;
+                // find param from fl.in_variable
+                let param = q.parameters.iter().find(|p| p.name.1 == fl.in_variable.1);
+
+                if param.is_none() {
+                    match scope.contains_key(fl.in_variable.1.as_str()) {
+                        true => {
+                            self.is_valid_identifier(q, fl.loc.clone(), fl.in_variable.1.as_str());
+                            for_loop_in_variable = ForLoopInVariable::Identifier(GenRef::Std(
+                                fl.in_variable.1.clone(),
+                            ));
+                        }
+                        false => {
+                            self.push_query_err(
+                                q,
+                                fl.loc.clone(),
+                                format!("`{}` is not a parameter", fl.in_variable.1),
+                                "add a parameter to the query",
+                            );
+                        }
+                    }
+                } else {
+                    self.check_required_param(q, &fl.in_variable.0, &fl.in_variable.1);
+                    for_loop_in_variable =
+                        ForLoopInVariable::Parameter(GenRef::Std(fl.in_variable.1.clone()));
+                }
+                let mut for_variable: ForVariable = ForVariable::Empty;
+
+                match &fl.variable {
+                    ForLoopVars::Identifier { name, loc: _ } => {
+                        self.is_valid_identifier(q, fl.loc.clone(), name.as_str());
+                        // body_scope.insert(name.as_str(), Type::Unknown);
+                        // scope.insert(name.as_str(), Type::Unknown);
+                        for_variable = ForVariable::Identifier(GenRef::Std(name.clone()));
+                    }
+                    ForLoopVars::ObjectAccess {
+                        name: _,
+                        field: _,
+                        loc,
+                    } => {
+                        // body_scope.insert(name.as_str(), Type::Unknown);
+                        // for_variable =
+                        //     ForVariable::ObjectDestructure(vec![GenRef::Std(name.clone())]);
+                        self.push_query_err(
+                            q,
+                            loc.clone(),
+                            "`FOR` loops can't iterate over a field access".to_string(),
+                            "destructure the fields with `FOR {a, b} IN ...` instead",
+                        );
+                    }
+                    ForLoopVars::ObjectDestructuring { fields, loc } => {
+                        // TODO: check if fields are valid
+                        match &param {
+                            Some(p) => {
+                                for_loop_in_variable =
+                                    ForLoopInVariable::Parameter(GenRef::Std(p.name.1.clone()));
+                                match &p.param_type.1 {
+                                    FieldType::Array(inner) => match inner.as_ref() {
+                                        FieldType::Object(param_fields) => {
+                                            for (field_loc, field_name) in fields {
+                                                if !param_fields.contains_key(field_name.as_str()) {
+                                                    self.push_query_err(
+                                                                q,
+                                                                field_loc.clone(),
+                                                                format!("`{}` is not a field of the inner type of `{}`", field_name, fl.in_variable.1),
+                                                                format!("check the object fields of the parameter `{}`", fl.in_variable.1),
+                                                            );
+                                                }
+                                                body_scope
+                                                    .insert(field_name.as_str(), Type::Unknown);
+                                                scope.insert(field_name.as_str(), Type::Unknown);
+                                            }
+                                            for_variable = ForVariable::ObjectDestructure(
+                                                fields
+                                                    .iter()
+                                                    .map(|(_, f)| GenRef::Std(f.clone()))
+                                                    .collect(),
+                                            );
+                                        }
+                                        _ => {
+                                            self.push_query_err(
+                                                        q,
+                                                        fl.in_variable.0.clone(),
+                                                        format!("the inner type of `{}` is not an object", fl.in_variable.1),
+                                                        "object destructuring only works with arrays of objects",
+                                                    );
+                                        }
+                                    },
+
+                                    _ => {
+                                        self.push_query_err(
+                                            q,
+                                            fl.in_variable.0.clone(),
+                                            format!("`{}` is not an array", fl.in_variable.1),
+                                            "object destructuring only works with arrays of objects",
+                                        );
+                                    }
+                                }
+                            }
+                            None => match scope.contains_key(fl.in_variable.1.as_str()) {
+                                true => {
+                                    // TODO: Check fields
+                                    for_variable = ForVariable::ObjectDestructure(
+                                        fields
+                                            .iter()
+                                            .map(|(_, f)| {
+                                                let name = f.as_str();
+                                                // adds non-param fields to scope
+                                                body_scope.insert(name, Type::Unknown);
+                                                scope.insert(name, Type::Unknown);
+
+                                                GenRef::Std(name.to_string())
+                                            })
+                                            .collect(),
+                                    );
+                                }
+                                false => {
+                                    self.push_query_err(
+                                        q,
+                                        fl.in_variable.0.clone(),
+                                        format!(
+                                            "`{}` is not defined in the current scope",
+                                            fl.in_variable.1
+                                        ),
+                                        "add a statement assigning it before the loop",
+                                    );
+                                }
+                            },
+                        }
+                    }
+                }
+                let mut statements = Vec::new();
+                for body_stmt in &fl.statements {
+                    // Recursive walk (but without infinite nesting for now)
+
+                    let stmt = self.walk_statements(scope, q, query, body_stmt);
+                    if stmt.is_some() {
+                        statements.push(stmt.unwrap());
+                    }
+                }
+                // body_scope.iter().for_each(|(k, _)| {
+                //     scope.remove(k);
+                // });
+
+                let stmt = GeneratedStatement::ForEach(GeneratedForEach {
+                    for_variables: for_variable,
+                    in_variable: for_loop_in_variable,
+                    statements: statements,
+                });
+                Some(stmt)
+            }
+
+            _ => {
+                /* SearchVector handled above; others TBD */
+                None
+            }
+        }
+    }
+
+    /// The vector type if it is declared in a namespace, whose vectors are searched in
+    /// an index of their own
+    fn namespaced_vector(&self, vector_type: &Option<String>) -> Option<String> {
+        let vector_type = vector_type.as_ref()?;
+        self.src
+            .vector_schemas
+            .iter()
+            .find(|v| &v.name == vector_type && v.namespace.is_some())
+            .map(|v| v.name.clone())
+    }
+
+    fn is_valid_identifier(&mut self, q: &Query, loc: Loc, name: &str) -> bool {
+        match name {
+            "true" | "false" | "NONE" | "String" | "Boolean" | "F32" | "F64" | "I8" | "I16"
+            | "I32" | "I64" | "U8" | "U16" | "U32" | "U64" | "U128" | "Uuid" | "Date"
+            | "DateTime" => {
+                self.push_query_err(
+                    q,
+                    loc.clone(),
+                    format!("`{}` is not a valid identifier", name),
+                    "use a valid identifier",
+                );
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Labels and index names are written into the generated code as literals, so they are
+    /// fixed by the query and can't be taken from one of its parameters. Reports `name` if
+    /// it names a parameter, returning whether it did.
+    fn check_param_derived_name(&mut self, q: &Query, loc: &Loc, kind: &str, name: &str) -> bool {
+        if !q.parameters.iter().any(|param| param.name.1 == name) {
+            return false;
+        }
+        self.push_query_err(
+            q,
+            loc.clone(),
+            format!("{} `{}` is derived from parameter `{}`", kind, name, name),
+            format!(
+                "{}s are fixed when the query is compiled, use one declared in the schema",
+                kind
+            ),
+        );
+        true
+    }
+
+    /// The indexed properties of a node type a filter requires to equal a value that can
+    /// be looked up in their index as is: a parameter, which has the property's type, or
+    /// a string literal compared with a string property
+    fn index_lookups(&self, label: &str, filter: &BoExp) -> Vec<(String, GeneratedValue)> {
+        let Some(fields) = self.node_fields.get(label) else {
+            return Vec::new();
+        };
+        filter
+            .required_equalities()
+            .into_iter()
+            .filter(|(property, value)| match fields.get(property.as_str()) {
+                Some(field) if field.is_indexed() => match value {
+                    // dates and enums may be stored in another form than the parameter holds
+                    GeneratedValue::Parameter(_) => {
+                        field.field_type != FieldType::Date
+                            && self.enum_schema(&field.field_type).is_none()
+                    }
+                    GeneratedValue::Primitive(GenRef::Literal(_)) => {
+                        field.field_type == FieldType::String
+                    }
+                    _ => false,
+                },
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Fetches a property of the elements, or the score they were ranked by for the
+    /// `_score` pseudo-field
+    fn gen_property_fetch(&mut self, q: &Query, loc: Loc, ty: &Type, field: &str) -> GeneratedStep {
+        match field {
+            SCORE_FIELD => {
+                if let Type::Edges(_) = ty {
+                    self.push_query_err(
+                        q,
+                        loc,
+                        "edges don't have a score".to_string(),
+                        "`_score` is only set on the nodes and vectors returned by a search",
+                    );
+                }
+                GeneratedStep::Score
+            }
+            _ => GeneratedStep::PropertyFetch(GenRef::Literal(field.to_string())),
+        }
+    }
+
+    /// A parameter or variable passed by reference to a search
+    fn gen_search_arg(
+        &mut self,
+        q: &Query,
+        scope: &HashMap<&'a str, Type>,
+        loc: &Loc,
+        name: &str,
+    ) -> GeneratedValue {
+        self.is_valid_identifier(q, loc.clone(), name);
+        self.check_required_param(q, loc, name);
+        if self.is_param(q, name) {
+            GeneratedValue::Identifier(GenRef::Ref(format!("data.{}", name)))
+        } else if scope.contains_key(name) {
+            GeneratedValue::Identifier(GenRef::Ref(name.to_string()))
+        } else {
+            self.push_query_err(
+                q,
+                loc.clone(),
+                format!("variable named `{}` is not in scope", name),
+                "declare it in the current scope or fix the typo",
+            );
+            GeneratedValue::Unknown
+        }
+    }
+
+    /// Optional parameters are `NONE` when the request leaves them out, so they can only
+    /// be used as property values, which are then left unset
+    fn check_required_param(&mut self, q: &Query, loc: &Loc, name: &str) {
+        if q.parameters
+            .iter()
+            .any(|p| p.name.1 == name && p.is_optional)
+        {
+            self.push_query_err(
+                q,
+                loc.clone(),
+                format!(
+                    "optional parameter `{}` is used where a value is required",
+                    name
+                ),
+                format!(
+                    "give `{}` a default value instead of making it optional",
+                    name
+                ),
+            );
+        }
+    }
+
+    fn is_param(&self, q: &Query, name: &str) -> bool {
+        q.parameters.iter().find(|p| p.name.1 == *name).is_some()
+    }
+
+    fn gen_identifier_or_param(&self, q: &Query, name: &str) -> GeneratedValue {
+        println!("{:?}", name);
+        if self.is_param(q, name) {
+            println!("{:?}", name);
+            GeneratedValue::Parameter(GenRef::Ref(format!("data.{}", name)))
+        } else {
+            GeneratedValue::Identifier(GenRef::Std(name.to_string()))
+        }
+    }
+
+    fn gen_id_access_or_param(&self, q: &Query, name: &str) -> GeneratedValue {
+        if self.is_param(q, name) {
+            GeneratedValue::Parameter(GenRef::DeRef(format!("data.{}", name)))
+        } else {
+            GeneratedValue::Identifier(GenRef::Std(format!("{}.id()", name.to_string())))
+        }
+    }
+}
+
+/// Arithmetic and functions, which compute their value rather than fetch it
+fn is_computed(expr: &Expression) -> bool {
+    matches!(
+        expr.expr,
+        ExpressionType::Arithmetic { .. } | ExpressionType::Function { .. }
+    )
+}
+
+/// What kind of value a field holds as far as expressions are concerned, values of
+/// different kinds don't compare
+fn value_kind(ft: &FieldType) -> Option<&'static str> {
+    match ft {
+        FieldType::String => Some("string"),
+        FieldType::Boolean => Some("boolean"),
+        FieldType::F32
+        | FieldType::F64
+        | FieldType::I8
+        | FieldType::I16
+        | FieldType::I32
+        | FieldType::I64
+        | FieldType::U8
+        | FieldType::U16
+        | FieldType::U32
+        | FieldType::U64
+        | FieldType::U128 => Some("number"),
+        _ => None,
+    }
+}
+
+fn is_float(ft: &FieldType) -> bool {
+    matches!(ft, FieldType::F32 | FieldType::F64)
+}
+
+/// Marks the types naming an enum of the schema, which are generated as Rust enums
+fn resolve_enum_type(enums: &HashMap<&str, &EnumSchema>, ty: &mut GeneratedType) {
+    match ty {
+        GeneratedType::Variable(name) if enums.contains_key(name.inner().as_str()) => {
+            *ty = GeneratedType::Enum(name.clone());
+        }
+        GeneratedType::Vec(inner) => resolve_enum_type(enums, inner),
+        _ => {}
+    }
+}
+
+/// Generates a string literal a property is compared with, as a date if the property
+/// is a date field so the comparison is by time rather than by text
+fn gen_compared_string(s: &str, compares_date: bool, date: Option<Date>) -> GeneratedValue {
+    match date {
+        Some(date) if compares_date => GeneratedValue::Literal(GenRef::from(Value::from(date))),
+        _ => GeneratedValue::Primitive(GenRef::Literal(s.to_string())),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Type {
+    Nodes(Option<String>),
+    Edges(Option<String>),
+    Vector(Option<String>),
+    Scalar(FieldType),
+    Anonymous(Box<Type>),
+    /// May be `NONE`
+    Optional(Box<Type>),
+    Boolean,
+    Unknown,
+}
+
+impl Type {
+    fn kind_str(&self) -> &'static str {
+        match self {
+            Type::Nodes(_) => "nodes",
+            Type::Edges(_) => "edges",
+            Type::Vector(_) => "vectors",
+            Type::Scalar(_) => "scalar",
+            Type::Boolean => "boolean",
+            Type::Unknown => "unknown",
+            Type::Anonymous(ty) => ty.kind_str(),
+            Type::Optional(ty) => ty.kind_str(),
+        }
+    }
+
+    fn get_type_name(&self) -> String {
+        match self {
+            Type::Nodes(Some(name)) => name.clone(),
+            Type::Edges(Some(name)) => name.clone(),
+            Type::Vector(Some(name)) => name.clone(),
+            Type::Scalar(ft) => ft.to_string(),
+            Type::Anonymous(ty) => ty.get_type_name(),
+            Type::Optional(ty) => ty.get_type_name(),
+            Type::Boolean => "boolean".to_string(),
+            Type::Unknown => "unknown".to_string(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// TypeScript type of a value of this type as clients receive it, items of the schema
+    /// being typed by the interfaces generated for them
+    fn to_ts(&self) -> String {
+        match self {
+            Type::Nodes(Some(name)) | Type::Edges(Some(name)) | Type::Vector(Some(name)) => {
+                name.clone()
+            }
+            Type::Scalar(ft) => GeneratedType::from(ft.clone()).to_ts(),
+            Type::Anonymous(ty) => ty.to_ts(),
+            Type::Optional(ty) => format!("{} | null", ty.to_ts()),
+            Type::Boolean => "boolean".to_string(),
+            _ => "any".to_string(),
+        }
+    }
+
+    /// Recursively strip <code>Anonymous</code> layers and return the base type.
+    fn base(&self) -> &Type {
+        match self {
+            Type::Anonymous(inner) => inner.base(),
+            _ => self,
+        }
+    }
+
+    /// Same, but returns an owned clone for convenience.
+    fn cloned_base(&self) -> Type {
+        match self {
+            Type::Anonymous(inner) => inner.cloned_base(),
+            _ => self.clone(),
+        }
+    }
+
+    /// Strip <code>Anonymous</code> and <code>Optional</code> layers.
+    fn non_null(&self) -> &Type {
+        match self {
+            Type::Anonymous(inner) | Type::Optional(inner) => inner.non_null(),
+            _ => self,
+        }
+    }
+
+    /// Whether values of both types can stand in for each other, regardless of whether
+    /// either may be `NONE`. Numbers of any width are compatible.
+    fn is_compatible(&self, other: &Type) -> bool {
+        use FieldType::*;
+        match (self.non_null(), other.non_null()) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (
+                Type::Scalar(F32 | F64 | I8 | I16 | I32 | I64 | U8 | U16 | U32 | U64 | U128),
+                Type::Scalar(F32 | F64 | I8 | I16 | I32 | I64 | U8 | U16 | U32 | U64 | U128),
+            ) => true,
+            (Type::Scalar(a), Type::Scalar(b)) => a == b,
+            (a, b) => a.kind_str() == b.kind_str(),
+        }
+    }
+}
+
+impl<'a> From<&'a FieldType> for Type {
+    fn from(ft: &'a FieldType) -> Self {
+        use FieldType::*;
+        match ft {
+            String | Boolean | F32 | F64 | I8 | I16 | I32 | I64 | U8 | U16 | U32 | U64 | U128
+            | Uuid | Date => Type::Scalar(ft.clone()),
+            Array(_) | Object(_) | Identifier(_) => Type::Unknown,
+        }
+    }
+}
+
+/// Whether the items of the traversal are remapped by its steps, which gives them
+/// another shape than the one of their schema
+fn is_remapped(expr: &Expression) -> bool {
+    match &expr.expr {
+        ExpressionType::Traversal(tr) => tr.steps.iter().any(|step| {
+            matches!(
+                step.step,
+                StepType::Object(_) | StepType::Closure(_) | StepType::Exclude(_)
+            )
+        }),
+        _ => false,
+    }
+}
+
+/// TypeScript type of the items of a traversal returned to clients, which are always
+/// sent as a list
+fn items_to_ts(ty: &Type, remapped: bool) -> String {
+    match remapped {
+        true => "Array<Record<string, any>>".to_string(),
+        false => format!("Array<{}>", ty.to_ts()),
+    }
+}
+
+// ---------------------------------
+// Tests
+// ---------------------------------
+#[cfg(test)]
+mod analyzer_tests {
+    use super::*;
+    use crate::helixc::parser::helix_parser::HelixParser;
+
+    /// Convenience helper – parse text and return diagnostics.
+    fn run(src: &str) -> Vec<Diagnostic> {
+        let input = write_to_temp_file(vec![src]);
+        let parsed = HelixParser::parse_source(&input)
+            .expect("parser should succeed – these tests are for the analyzer");
+        analyze(&parsed).0
+    }
+
+    #[test]
+    fn reports_unknown_node_in_edge() {
+        let hx = r#"
+            E::Likes {
+                From: User,
+                To: Post,
+                Properties: {}
+            }
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("not a declared node type")),
+            "expected a diagnostic about undeclared node types, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn detects_redeclared_variable() {
+        let hx = r#"
+            N::User { Name: String }
+
+            QUERY dupVar() =>
+                u <- N<User>
+                u <- N<User>
+                RETURN u
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.iter().any(|d| d.message.contains("already declared")),
+            "expected a diagnostic about variable redeclaration, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn flags_invalid_property_access() {
+        let hx = r#"
+            N::User { name: String }
+
+            QUERY badField() =>
+                u <- N<User>
+                n <- u::{age}
+                RETURN n
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("is not a field of node")),
+            "expected a diagnostic about invalid property access, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn traversal_step_order_enforced() {
+        let hx = r#"
+            N::User { name: String }
+
+            QUERY wrongStep() =>
+                e <- N<User>::FromN   // OutN on nodes is illegal (needs Edge)
+                RETURN e
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("cannot follow a step that returns")),
+            "expected a diagnostic about illegal step ordering, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn clean_query_produces_no_diagnostics() {
+        let hx = r#"
+            N::User { name: String }
+            N::Post { title: String }
+            E::Wrote {
+                From: User,
+                To: Post,
+                Properties: {}
+            }
+
+            QUERY ok(hey: User) =>
+                u <- N<User>
+                p <- u::Out<Wrote>
+                RETURN p::!{title}::{title}
+        "#;
+        let diags = run(hx);
+        for d in diags.iter() {
+            println!("{}", d.render(hx, "query.hx"));
+        }
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validates_edge_properties() {
+        let hx = r#"
+            N::User { name: String }
+            N::Post { title: String }
+            E::Wrote {
+                From: User,
+                To: Post,
+                Properties: {
+                    date: String,
+                    likes: I32
+                }
+            }
+
+            QUERY badEdgeField() =>
+                e <- N<User>::OutE<Wrote>
+                n <- e::{invalid_field}
+                RETURN n
+        "#;
+        let diags = run(hx);
+        for d in diags.iter() {
+            println!("{}", d.render(hx, "query.hx"));
+        }
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("is not a field of edge")),
+            "expected a diagnostic about invalid edge property access, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validates_node_properties() {
+        let hx = r#"
+            N::User { name: String }
+            N::Post { title: String }
+
+            QUERY badNodeField() =>
+                n <- N<User>::{invalid_field}
+                RETURN n
+        "#;
+        let diags = run(hx);
+        for d in diags.iter() {
+            println!("{}", d.render(hx, "query.hx"));
+        }
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("is not a field of edge")),
+            "expected a diagnostic about invalid edge property access, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validates_vector_properties() {
+        let hx = r#"
+            V::UserEmbedding {
+                content: String
+            }
+            
+            QUERY badVectorField(vec: [F64], content: String) =>
+                v <- AddV<UserEmbedding>(vec,{content: content})
+                RETURN v
+        "#;
+        let diags = run(hx);
+        for d in diags.iter() {
+            println!("{}", d.render(hx, "query.hx"));
+        }
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("is not a field of vector")),
+            "expected a diagnostic about invalid vector property access, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn handles_untyped_nodes() {
+        let hx = r#"
+            N::User { name: String }
+
+            QUERY untypedNode() =>
+                u <- N<User>::{some_field}
+                RETURN n
+        "#;
+        let diags = run(hx);
+        for d in diags.iter() {
+            println!("{}", d.render(hx, "query.hx"));
+        }
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics for untyped node access, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn respects_excluded_fields() {
+        let hx = r#"
+            N::User { name: String, age: I32 }
+
+            QUERY excludedField() =>
+                u <- N<User>
+                n <- u::!{name}::{name}
+                RETURN n
+        "#;
+        let diags = run(hx);
+        for d in diags.iter() {
+            println!("{}", d.render(hx, "query.hx"));
+        }
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("was previously excluded")),
+            "expected a diagnostic about accessing excluded field, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validates_add_node_fields() {
+        let hx = r#"
+            N::User { name: String }
+
+            QUERY badAddNodeField() =>
+                n <- AddN<User>({invalid_field: "test"})
+                RETURN n
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("is not a field of node")),
+            "expected a diagnostic about invalid node field, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validates_add_edge_fields() {
+        let hx = r#"
+            N::User { name: String }
+            N::Post { title: String }
+            E::Wrote {
+                From: User,
+                To: Post,
+                Properties: {
+                    date: String
+                }
+            }
+
+            QUERY badAddEdgeField() =>
+                n1 <- AddN<User>({name: "test"})
+                n2 <- AddN<Post>({title: "test"})
+                e <- AddE<Wrote>({invalid_field: "test"})::To(n1)::From(n2)
+                RETURN e
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("is not a field of edge")),
+            "expected a diagnostic about invalid edge field, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validates_add_vector_fields() {
+        let hx = r#"
+            V::UserEmbedding {
+                content: String
+            }
+            
+            QUERY badAddVectorField() =>
+                v <- AddV<UserEmbedding>([1.0, 2.0], {invalid_field: "test"})
+                RETURN v
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("is not a valid vector field")),
+            "expected a diagnostic about invalid vector field, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validate_boolean_comparison() {
+        let hx = r#"
+            N::User { name: String }
+
+            QUERY booleanComparison() =>
+                a <- N<User>::WHERE(_::{name}::EQ(10))
+                RETURN a
+        "#;
+        let diags = run(hx);
+        for d in diags.iter() {
+            println!("{}", d.render(hx, "query.hx"));
+        }
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validates_coalesce_arguments() {
+        let hx = r#"
+            N::User { name: String, nickname: String, age: I32 }
+            E::Knows { From: User, To: User, Properties: {} }
+
+            QUERY coalesce() =>
+                u <- N<User>::{
+                    friend: Optional(_::Out<Knows>::{name}),
+                    display: Coalesce(nickname, _::Out<Knows>::{name}, "anonymous"),
+                }
+                RETURN u
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
+
+        let hx = r#"
+            N::User { name: String, nickname: String, age: I32 }
+
+            QUERY coalesceMismatch() =>
+                u <- N<User>::{ display: Coalesce(nickname, age) }
+                RETURN u
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("`Coalesce` argument is scalar `I32`")),
+            "expected a diagnostic about mismatched arguments, got: {:?}",
+            diags
+        );
+
+        let hx = r#"
+            N::User { name: String, nickname: String }
+
+            QUERY coalesceUnused() =>
+                u <- N<User>::{ display: Coalesce(nickname, "anonymous", name) }
+                RETURN u
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("`Coalesce` argument is never used")),
+            "expected a diagnostic about the unused argument, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn validates_edge_index_lookups() {
+        let hx = r#"
+            N::User { name: String }
+            E::Knows { From: User, To: User, Properties: { INDEX since: I32, note: String } }
+
+            QUERY byYear(year: I32) =>
+                e <- E<Knows>({since: year})
+                RETURN e
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
+
+        let hx = r#"
+            N::User { name: String }
+            E::Knows { From: User, To: User, Properties: { INDEX since: I32, note: String } }
+
+            QUERY byNote() =>
+                e <- E<Knows>({note: "hi"})
+                RETURN e
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.iter().any(|d| d
+                .message
+                .contains("has not been indexed for edge type `Knows`")),
+            "expected a diagnostic about the unindexed field, got: {:?}",
+            diags
+        );
+
+        let hx = r#"
+            N::User { name: String }
+            E::Knows { From: User, To: User, Properties: { UNIQUE since: I32 } }
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("edge field `since` can't be `UNIQUE`")),
+            "expected a diagnostic about the unique edge field, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn compares_dates_with_date_strings() {
+        let hx = r#"
+            N::Post { created_at: DateTime, title: String }
+
+            QUERY recent() =>
+                posts <- N<Post>::WHERE(_::{created_at}::GT("2024-01-01"))
+                RETURN posts
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
+
+        let hx = r#"
+            N::Post { created_at: Date, title: String }
+
+            QUERY recent() =>
+                posts <- N<Post>::WHERE(_::{created_at}::LT("last week"))
+                RETURN posts
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.iter().any(|d| d
+                .message
+                .contains("which does not match type of compared value")),
+            "expected a diagnostic about the invalid date, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn search_vector_pre_filter_checks_vector_fields() {
+        let hx = r#"
+            V::Doc { category: String }
+
+            QUERY search(vec: [F64], category: String) =>
+                docs <- SearchV<Doc>(vec, 5)::PREFILTER(_::{category}::EQ(category))
+                RETURN docs
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
+
+        let hx = r#"
+            V::Doc { category: String }
+
+            QUERY search(vec: [F64], category: String) =>
+                docs <- SearchV<Doc>(vec, 5)::PREFILTER(_::{topic}::EQ(category))
+                RETURN docs
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.iter().any(|d| d.message.contains("topic")),
+            "expected a diagnostic about the unknown field, got: {:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn search_vector_pre_filter_must_be_a_condition() {
+        let hx = r#"
+            V::Doc { category: String }
+
+            QUERY search(vec: [F64], flag: Boolean) =>
+                docs <- SearchV<Doc>(vec, 5)::PREFILTER(flag)
+                RETURN docs
+        "#;
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert!(
+            messages.iter().any(|m| m.contains("`PREFILTER` must be a condition")),
+            "{:?}",
+            messages
+        );
+    }
+
+    #[test]
+    fn hybrid_search_returns_nodes_of_its_type() {
+        let hx = r#"
+            N::Doc { title: String }
+
+            QUERY search(vec: [F64], text: String) =>
+                docs <- HybridSearch<Doc>(vec, text, 10)
+                RETURN docs::{title}
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
+
+        let hx = r#"
+            N::Doc { title: String }
+
+            QUERY search(vec: [F64]) =>
+                docs <- HybridSearch<Post>(vec, words, 10)
+                RETURN docs
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("`Post` has not been declared")),
+            "expected a diagnostic about the undeclared node type, got: {:?}",
+            diags
+        );
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("`words` is not in scope")),
+            "expected a diagnostic about the unknown text, got: {:?}",
+            diags
+        );
+    }
 
-                                    _ => {
-                                        self.push_query_err(
-                                            q,
-                                            fl.in_variable.0.clone(),
-                                            format!("`{}` is not an array", fl.in_variable.1),
-                                            "object destructuring only works with arrays of objects",
-                                        );
-                                    }
-                                }
-                            }
-                            None => match scope.contains_key(fl.in_variable.1.as_str()) {
-                                true => {
-                                    // TODO: Check fields
-                                    for_variable = ForVariable::ObjectDestructure(
-                                        fields
-                                            .iter()
-                                            .map(|(_, f)| {
-                                                let name = f.as_str();
-                                                // adds non-param fields to scope
-                                                body_scope.insert(name, Type::Unknown);
-                                                scope.insert(name, Type::Unknown);
+    #[test]
+    fn score_is_readable_on_searched_elements() {
+        let hx = r#"
+            V::Doc { category: String }
+            N::Page { title: String }
+
+            QUERY search(vec: [F64], text: String) =>
+                docs <- SearchV<Doc>(vec, 5)
+                pages <- HybridSearch<Page>(vec, text, 5)
+                scores <- docs::{_score}
+                RETURN docs::{category, distance: _score}, pages::{title, _score}, scores
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
 
-                                                GenRef::Std(name.to_string())
-                                            })
-                                            .collect(),
-                                    );
-                                }
-                                false => {
-                                    self.push_query_err(
-                                        q,
-                                        fl.in_variable.0.clone(),
-                                        format!(
-                                            "`{}` is not defined in the current scope",
-                                            fl.in_variable.1
-                                        ),
-                                        "add a statement assigning it before the loop",
-                                    );
-                                }
-                            },
-                        }
-                    }
-                }
-                let mut statements = Vec::new();
-                for body_stmt in &fl.statements {
-                    // Recursive walk (but without infinite nesting for now)
+        let hx = r#"
+            N::Page { title: String }
+            E::Links { From: Page, To: Page, Properties: {} }
 
-                    let stmt = self.walk_statements(scope, q, query, body_stmt);
-                    if stmt.is_some() {
-                        statements.push(stmt.unwrap());
-                    }
-                }
-                // body_scope.iter().for_each(|(k, _)| {
-                //     scope.remove(k);
-                // });
+            QUERY links() =>
+                links <- N<Page>::OutE<Links>
+                RETURN links::{_score}
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.message.contains("edges don't have a score")),
+            "expected a diagnostic about the edge score, got: {:?}",
+            diags
+        );
+    }
 
-                let stmt = GeneratedStatement::ForEach(GeneratedForEach {
-                    for_variables: for_variable,
-                    in_variable: for_loop_in_variable,
-                    statements: statements,
-                });
-                Some(stmt)
+    #[test]
+    fn reports_edge_across_namespaces() {
+        let hx = r#"
+            N::User {
+                name: String
             }
 
-            _ => {
-                /* SearchVector handled above; others TBD */
-                None
+            NAMESPACE blog {
+                N::Post {
+                    title: String
+                }
+                E::Wrote {
+                    From: User,
+                    To: Post,
+                }
             }
-        }
+        "#;
+        let diags = run(hx);
+        assert!(
+            diags.iter().any(|d| d
+                .message
+                .contains("isn't in the namespace of the edge `Wrote`")),
+            "expected a diagnostic about the edge leaving its namespace, got: {:?}",
+            diags
+        );
     }
 
-    fn is_valid_identifier(&mut self, q: &Query, loc: Loc, name: &str) -> bool {
-        match name {
-            "true" | "false" | "NONE" | "String" | "Boolean" | "F32" | "F64" | "I8" | "I16"
-            | "I32" | "I64" | "U8" | "U16" | "U32" | "U64" | "U128" | "Uuid" | "Date" => {
-                self.push_query_err(
-                    q,
-                    loc.clone(),
-                    format!("`{}` is not a valid identifier", name),
-                    "use a valid identifier",
-                );
-                false
-            }
-            _ => true,
-        }
+    /// Parses files named as given, without resolving their imports from disk
+    fn run_files(files: &[(&str, &str)]) -> Vec<Diagnostic> {
+        let input = Content {
+            content: String::new(),
+            source: Source::default(),
+            files: files
+                .iter()
+                .map(|(name, content)| HxFile {
+                    name: name.to_string(),
+                    content: content.to_string(),
+                })
+                .collect(),
+        };
+        analyze(&HelixParser::parse_source(&input).unwrap()).0
     }
 
-    fn is_param(&self, q: &Query, name: &str) -> bool {
-        q.parameters.iter().find(|p| p.name.1 == *name).is_some()
+    #[test]
+    fn reports_import_cycle() {
+        let diags = run_files(&[
+            (
+                "cfg/schema.hx",
+                "import \"users/users.hx\"\nN::Post { title: String }",
+            ),
+            (
+                "cfg/users/users.hx",
+                "import \"../schema.hx\"\nN::User { name: String }",
+            ),
+        ]);
+        let cycles = diags
+            .iter()
+            .filter(|d| d.message.starts_with("import cycle"))
+            .collect::<Vec<_>>();
+        assert_eq!(cycles.len(), 1, "{:?}", diags);
+        assert_eq!(
+            cycles[0].message,
+            "import cycle cfg/schema.hx -> cfg/users/users.hx -> cfg/schema.hx"
+        );
     }
 
-    fn gen_identifier_or_param(&self, q: &Query, name: &str) -> GeneratedValue {
-        println!("{:?}", name);
-        if self.is_param(q, name) {
-            println!("{:?}", name);
-            GeneratedValue::Parameter(GenRef::Ref(format!("data.{}", name)))
-        } else {
-            GeneratedValue::Identifier(GenRef::Std(name.to_string()))
-        }
+    #[test]
+    fn reports_duplicate_imports_and_declarations() {
+        let diags = run_files(&[
+            (
+                "schema.hx",
+                "import \"users.hx\" as accounts\nimport \"./users.hx\"\nN::User { name: String }",
+            ),
+            ("queries.hx", "import \"users.hx\""),
+            ("users.hx", "N::User { email: String }"),
+        ]);
+        let messages = diags.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+        assert!(
+            messages.contains(&"`./users.hx` is already imported"),
+            "{:?}",
+            messages
+        );
+        assert!(
+            messages.contains(&"`users.hx` is imported in another namespace by schema.hx"),
+            "{:?}",
+            messages
+        );
+        // names are shared by the namespaces as well
+        assert!(
+            messages.contains(&"`N::User` is already declared on line 3 of schema.hx"),
+            "{:?}",
+            messages
+        );
     }
 
-    fn gen_id_access_or_param(&self, q: &Query, name: &str) -> GeneratedValue {
-        if self.is_param(q, name) {
-            GeneratedValue::Parameter(GenRef::DeRef(format!("data.{}", name)))
-        } else {
-            GeneratedValue::Identifier(GenRef::Std(format!("{}.id()", name.to_string())))
+    #[test]
+    fn checks_parameter_defaults() {
+        let hx = r#"
+            N::User { name: String, age: U32 }
+
+            QUERY findUsers(limit: I64 = 50, min: U32 = -1, name: String = 3, id: ID = "x", nick: String? = "a") =>
+                users <- N<User>::RANGE(0, limit)
+                RETURN users
+        "#;
+        let diags = run(hx);
+        assert_eq!(diags.len(), 4, "{:?}", diags);
+        for message in [
+            "default value `-1` doesn't match type `U32` of parameter `min`",
+            "default value `3` doesn't match type `String` of parameter `name`",
+            "parameter `id` of type `ID` can't have a default value",
+            "parameter `nick` is optional but has a default value",
+        ] {
+            assert!(
+                diags.iter().any(|d| d.message.contains(message)),
+                "missing `{}` in {:?}",
+                message,
+                diags
+            );
         }
     }
-}
 
-#[derive(Debug, Clone)]
-enum Type {
-    Nodes(Option<String>),
-    Edges(Option<String>),
-    Vector(Option<String>),
-    Scalar(FieldType),
-    Anonymous(Box<Type>),
-    Boolean,
-    Unknown,
-}
+    #[test]
+    fn reports_optional_parameter_used_as_value() {
+        let hx = r#"
+            N::User { name: String, age: U32 }
 
-impl Type {
-    fn kind_str(&self) -> &'static str {
-        match self {
-            Type::Nodes(_) => "nodes",
-            Type::Edges(_) => "edges",
-            Type::Vector(_) => "vectors",
-            Type::Scalar(_) => "scalar",
-            Type::Boolean => "boolean",
-            Type::Unknown => "unknown",
-            Type::Anonymous(ty) => ty.kind_str(),
-        }
+            QUERY findUsers(limit: I64?, name: String?) =>
+                users <- N<User>::WHERE(_::{name}::EQ(name))::RANGE(0, limit)
+                user <- AddN<User>({name: name})
+                RETURN users, user
+        "#;
+        let diags = run(hx);
+        assert_eq!(diags.len(), 2, "{:?}", diags);
+        assert!(diags[0]
+            .message
+            .contains("optional parameter `name` is used where a value is required"));
+        assert!(diags[1]
+            .message
+            .contains("optional parameter `limit` is used where a value is required"));
     }
 
-    fn get_type_name(&self) -> String {
-        match self {
-            Type::Nodes(Some(name)) => name.clone(),
-            Type::Edges(Some(name)) => name.clone(),
-            Type::Vector(Some(name)) => name.clone(),
-            Type::Scalar(ft) => ft.to_string(),
-            Type::Anonymous(ty) => ty.get_type_name(),
-            Type::Boolean => "boolean".to_string(),
-            Type::Unknown => "unknown".to_string(),
-            _ => unreachable!(),
-        }
-    }
+    #[test]
+    fn generates_parameter_defaults() {
+        let hx = r#"
+            N::User { name: String, score: F64 }
 
-    /// Recursively strip <code>Anonymous</code> layers and return the base type.
-    fn base(&self) -> &Type {
-        match self {
-            Type::Anonymous(inner) => inner.base(),
-            _ => self,
-        }
+            QUERY findUsers(limit: I64 = 50, min: F64 = 1, name: String?) =>
+                users <- N<User>::WHERE(_::{score}::GTE(min))::RANGE(0, limit)
+                user <- AddN<User>({name: name})
+                RETURN users, user
+        "#;
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
+        assert!(
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
+            diags
+        );
+
+        let code = source.to_string();
+        assert!(code.contains("#[serde(default = \"findUsers_limit_default\")]\npub limit: i64"));
+        assert!(code.contains("fn findUsers_limit_default() -> i64 {\n    50\n}"));
+        assert!(code.contains("fn findUsers_min_default() -> f64 {\n    1.0\n}"));
+        assert!(code.contains("#[serde(default)]\npub name: Option<String>"));
     }
 
-    /// Same, but returns an owned clone for convenience.
-    fn cloned_base(&self) -> Type {
-        match self {
-            Type::Anonymous(inner) => inner.cloned_base(),
-            _ => self.clone(),
-        }
+    #[test]
+    fn reports_labels_derived_from_parameters() {
+        let hx = r#"
+            N::User { INDEX email: String, name: String }
+            E::Follows { From: User, To: User }
+
+            QUERY byLabel(Label: String, field: String, Rel: String, email: String) =>
+                users <- N<Label>
+                byField <- N<User>({field: email})
+                byEmail <- N<User>({email: email})
+                followed <- byEmail::Out<Rel>
+                RETURN users, byField, followed
+        "#;
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].contains("label `Label` is derived from parameter `Label`"));
+        assert!(messages[1].contains("index name `field` is derived from parameter `field`"));
+        assert!(messages[2].contains("label `Rel` is derived from parameter `Rel`"));
     }
-}
 
-impl<'a> From<&'a FieldType> for Type {
-    fn from(ft: &'a FieldType) -> Self {
-        use FieldType::*;
-        match ft {
-            String | Boolean | F32 | F64 | I8 | I16 | I32 | I64 | U8 | U16 | U32 | U64 | U128
-            | Uuid | Date => Type::Scalar(ft.clone()),
-            Array(_) | Object(_) | Identifier(_) => Type::Unknown,
-        }
+    #[test]
+    fn finds_keys_not_bound_as_literals() {
+        use crate::helixc::generator::{
+            generator_types::{Query, Statement},
+            parameter_binding::unbound_keys,
+            source_steps::{NFromType, SourceStep},
+            traversal_steps::Traversal,
+            utils::{GenRef, Separator},
+        };
+
+        let traversal = |label: GenRef<String>| {
+            Statement::Traversal(Traversal {
+                source_step: Separator::Period(SourceStep::NFromType(NFromType { label })),
+                ..Default::default()
+            })
+        };
+        let query = Query {
+            name: "byLabel".to_string(),
+            statements: vec![
+                traversal(GenRef::Literal("User".to_string())),
+                traversal(GenRef::Std("data.label".to_string())),
+                traversal(GenRef::Literal("User\", \"".to_string())),
+            ],
+            parameters: vec![],
+            sub_parameters: vec![],
+            return_values: vec![],
+            is_mut: false,
+        };
+        let keys = unbound_keys(&query)
+            .into_iter()
+            .map(|unbound| (unbound.kind, unbound.key))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                ("label", "data.label".to_string()),
+                ("label", "\"User\", \"\"".to_string()),
+            ]
+        );
     }
-}
 
-// ---------------------------------
-// Tests
-// ---------------------------------
-#[cfg(test)]
-mod analyzer_tests {
-    use super::*;
-    use crate::helixc::parser::helix_parser::HelixParser;
+    #[test]
+    fn validates_enum_literals() {
+        let hx = r#"
+            ENUM Status { Active, Suspended, Deleted }
+            N::User { name: String, status: Status }
+
+            QUERY addUser(name: String, status: Status) =>
+                user <- AddN<User>({name: name, status: status})
+                other <- AddN<User>({name: name, status: "Active"})
+                RETURN user, other
+
+            QUERY badStatus(name: String) =>
+                user <- AddN<User>({name: name, status: "Pending"})
+                users <- N<User>::WHERE(_::{status}::EQ("Gone"))
+                RETURN user, users
+
+            QUERY byStatus(status: Status) =>
+                users <- N<User>::WHERE(_::{status}::EQ(status))
+                active <- N<User>::WHERE(_::{status}::NEQ("Deleted"))
+                RETURN users, active
+        "#;
+        let diags = run(hx);
+        assert_eq!(diags.len(), 2, "{:?}", diags);
+        assert!(diags[0]
+            .message
+            .contains("`\"Pending\"` is not a variant of enum `Status`"));
+        assert!(diags[1]
+            .message
+            .contains("`\"Gone\"` is not a variant of enum `Status`"));
+        assert_eq!(
+            diags[0].hint.as_deref(),
+            Some("use one of `\"Active\"`, `\"Suspended\"`, `\"Deleted\"`")
+        );
+    }
 
-    /// Convenience helper – parse text and return diagnostics.
-    fn run(src: &str) -> Vec<Diagnostic> {
-        let input = write_to_temp_file(vec![src]);
-        let parsed = HelixParser::parse_source(&input)
-            .expect("parser should succeed – these tests are for the analyzer");
-        analyze(&parsed).0
+    #[test]
+    fn reports_invalid_enums() {
+        let hx = r#"
+            ENUM Status { Active, Active }
+            ENUM User { Admin }
+            N::User { name: String }
+        "#;
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "`Active` is already a variant of enum `Status`",
+                "`User` is already the name of a schema",
+            ]
+        );
     }
 
     #[test]
-    fn reports_unknown_node_in_edge() {
+    fn generates_enums() {
+        use crate::helixc::generator::tsdisplay::ToTypeScript;
+
         let hx = r#"
-            E::Likes {
-                From: User,
-                To: Post,
-                Properties: {}
-            }
+            ENUM Status { Active, Suspended }
+            N::User { name: String, status: Status }
+
+            QUERY addUser(name: String, status: Status) =>
+                user <- AddN<User>({name: name, status: status})
+                RETURN user
         "#;
-        let diags = run(hx);
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("not a declared node type")),
-            "expected a diagnostic about undeclared node types, got: {:?}",
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
             diags
         );
+
+        let code = source.to_string();
+        assert!(code.contains("pub enum Status {\n    Active,\n    Suspended,\n}"));
+        assert!(code.contains("pub status: Status,"));
+        assert!(code.contains(
+            "inventory::submit! { EnumField { label: \"User\", field: \"status\", variants: Status::VARIANTS } }"
+        ));
+        let ts = source.to_typescript();
+        assert!(ts.contains("export type Status = \"Active\" | \"Suspended\";"));
+        assert!(ts.contains("  status: Status;\n"));
     }
 
     #[test]
-    fn detects_redeclared_variable() {
+    fn generates_sensitive_fields() {
+        use crate::helixc::generator::tsdisplay::ToTypeScript;
+
         let hx = r#"
-            N::User { Name: String }
+            N::User { name: String, ssn: String @sensitive(admin, hr), notes: String @sensitive }
 
-            QUERY dupVar() =>
-                u <- N<User>
-                u <- N<User>
-                RETURN u
+            QUERY getUser(id: ID) =>
+                user <- N<User>(id)
+                RETURN user
         "#;
-        let diags = run(hx);
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
-            diags.iter().any(|d| d.message.contains("already declared")),
-            "expected a diagnostic about variable redeclaration, got: {:?}",
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
             diags
         );
+
+        let code = source.to_string();
+        assert!(code.contains(
+            "inventory::submit! { SensitiveField { label: \"User\", field: \"ssn\", roles: &[\"admin\", \"hr\"] } }"
+        ));
+        assert!(code.contains(
+            "inventory::submit! { SensitiveField { label: \"User\", field: \"notes\", roles: &[] } }"
+        ));
+        assert!(!code.contains("field: \"name\""));
+        let ts = source.to_typescript();
+        assert!(ts.contains("  name: string;\n"));
+        assert!(ts.contains("  ssn?: string;\n"));
     }
 
     #[test]
-    fn flags_invalid_property_access() {
+    fn checks_expressions() {
         let hx = r#"
-            N::User { name: String }
+            N::Item { name: String, price: F64, quantity: I32, tags: [String] }
 
-            QUERY badField() =>
-                u <- N<User>
-                n <- u::{age}
-                RETURN n
+            QUERY getItems(min: F64) =>
+                items <- N<Item>::WHERE(LOWER(_::{price})::EQ("a"))::WHERE(ROUND(_::{price})::GT("a"))
+                cheap <- N<Item>::WHERE(_::{price}::LT(_::{name} * 2))
+                RETURN items::{total: _::{price} * _::{quantity}, label: _::{name} + " x" + quantity, n: LEN(tags), missing: _::{weight} + 1}, cheap
         "#;
-        let diags = run(hx);
-        assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("is not a field of node")),
-            "expected a diagnostic about invalid property access, got: {:?}",
-            diags
-        );
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        let expected = [
+            "`LOWER` takes a string, not `F64`",
+            "computed value of type `I64` is compared to a value of type `String`",
+            "`*` can't be applied to `String` and `I32`",
+            "`weight` is not a field of type `Item` or a parameter",
+        ];
+        assert_eq!(messages.len(), expected.len(), "{:?}", messages);
+        for (message, expected) in messages.iter().zip(expected) {
+            assert!(message.contains(expected), "{:?}", messages);
+        }
     }
 
     #[test]
-    fn traversal_step_order_enforced() {
+    fn generates_expressions() {
         let hx = r#"
-            N::User { name: String }
+            N::Item { name: String, price: F64, quantity: I32 }
 
-            QUERY wrongStep() =>
-                e <- N<User>::FromN   // OutN on nodes is illegal (needs Edge)
-                RETURN e
+            QUERY getItems(name: String) =>
+                items <- N<Item>::WHERE(LOWER(_::{name})::EQ(name))
+                RETURN items::{total: _::{price} * _::{quantity}}
         "#;
-        let diags = run(hx);
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("cannot follow a step that returns")),
-            "expected a diagnostic about illegal step ordering, got: {:?}",
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
             diags
         );
+
+        let code = source.to_string();
+        assert!(code.contains(
+            "Some(&expression::lower(expression::property(&val, \"name\"))).map_or(false, |v| *v == Computed(Value::from(&data.name)))"
+        ));
+        assert!(code.contains(
+            "expression_remapping!(remapping_vals, item.clone(), \"total\" => expression::mul(expression::property(&item, \"price\"), expression::property(&item, \"quantity\")))"
+        ));
     }
 
     #[test]
-    fn clean_query_produces_no_diagnostics() {
+    fn generates_index_lookups_for_indexed_equalities() {
         let hx = r#"
-            N::User { name: String }
-            N::Post { title: String }
-            E::Wrote {
-                From: User,
-                To: Post,
-                Properties: {}
-            }
+            N::User { INDEX email: String, INDEX age: I32, name: String }
 
-            QUERY ok(hey: User) =>
-                u <- N<User>
-                p <- u::Out<Wrote>
-                RETURN p::!{title}::{title}
+            QUERY findUser(email: String, age: I32) =>
+                users <- N<User>::WHERE(AND(_::{email}::EQ(email), _::{age}::EQ(age), _::{name}::EQ("bob")))
+                RETURN users
+
+            QUERY findName() =>
+                users <- N<User>::WHERE(OR(_::{email}::EQ("a@b.c"), _::{name}::EQ("bob")))
+                RETURN users
         "#;
-        let diags = run(hx);
-        for d in diags.iter() {
-            println!("{}", d.render(hx, "query.hx"));
-        }
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
             diags.is_empty(),
             "expected no diagnostics, got: {:?}",
             diags
         );
+        let code = source.to_string();
+        // the unindexed name is only checked by the filter
+        assert!(
+            code.contains(
+                "n_from_type_where_indexed(\"User\", &[(\"email\", Value::from(&data.email)), (\"age\", Value::from(&data.age))], |val, txn|"
+            ),
+            "{}",
+            code
+        );
+        // a property compared under an `OR` isn't required to equal the value
+        assert!(
+            code.contains("n_from_type_where(\"User\", |val, txn|"),
+            "{}",
+            code
+        );
     }
 
     #[test]
-    fn validates_edge_properties() {
+    fn generates_edge_counts() {
         let hx = r#"
             N::User { name: String }
             N::Post { title: String }
-            E::Wrote {
-                From: User,
-                To: Post,
-                Properties: {
-                    date: String,
-                    likes: I32
-                }
-            }
+            E::Follows { From: User, To: User, Properties: {} }
+            E::Wrote { From: User, To: Post, Properties: {} }
 
-            QUERY badEdgeField() =>
-                e <- N<User>::OutE<Wrote>
-                n <- e::{invalid_field}
-                RETURN n
+            QUERY getCounts(id: ID) =>
+                following <- N<User>(id)::OutCount<Follows>
+                followers <- N<User>(id)::InCount<Follows>
+                RETURN following, followers
         "#;
-        let diags = run(hx);
-        for d in diags.iter() {
-            println!("{}", d.render(hx, "query.hx"));
-        }
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("is not a field of edge")),
-            "expected a diagnostic about invalid edge property access, got: {:?}",
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
             diags
         );
-    }
+        let code = source.to_string();
+        assert!(code.contains(".out_count(\"Follows\"))?"), "{}", code);
+        assert!(code.contains(".in_count(\"Follows\")"), "{}", code);
+        assert!(!code.contains(".out(\"Follows\""), "{}", code);
 
-    #[test]
-    fn validates_node_properties() {
         let hx = r#"
             N::User { name: String }
             N::Post { title: String }
+            E::Wrote { From: User, To: Post, Properties: {} }
 
-            QUERY badNodeField() =>
-                n <- N<User>::{invalid_field}
-                RETURN n
+            QUERY getCounts(id: ID) =>
+                wrote <- N<User>(id)::InCount<Wrote>
+                RETURN wrote
         "#;
-        let diags = run(hx);
-        for d in diags.iter() {
-            println!("{}", d.render(hx, "query.hx"));
-        }
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].contains("not a valid incoming edge type for node of type `User`"));
+    }
+
+    #[test]
+    fn generates_as_of() {
+        let hx = r#"
+            N::Account { balance: I64 }
+
+            QUERY getAccount(id: ID, at: Date) =>
+                account <- N<Account>(id)::AsOf(at)
+                opened <- N<Account>(id)::AsOf("2024-01-01T00:00:00Z")
+                RETURN account, opened
+        "#;
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("is not a field of edge")),
-            "expected a diagnostic about invalid edge property access, got: {:?}",
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
             diags
         );
+        let code = source.to_string();
+        assert!(code.contains(".as_of(data.at.clone())"), "{}", code);
+        assert!(code.contains(".as_of(\"2024-01-01T00:00:00Z\")"), "{}", code);
+
+        let hx = r#"
+            N::Account { balance: I64 }
+            E::Owns { From: Account, To: Account, Properties: {} }
+
+            QUERY getAccount(id: ID) =>
+                owned <- N<Account>(id)::OutE<Owns>::AsOf("yesterday")
+                RETURN owned
+        "#;
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].contains("`AsOf` is only valid on nodes"));
+        assert!(messages[1].contains("`yesterday` is not a date"));
     }
 
     #[test]
-    fn validates_vector_properties() {
+    fn checks_conditionals() {
         let hx = r#"
-            V::UserEmbedding {
-                content: String
-            }
-            
-            QUERY badVectorField(vec: [F64], content: String) =>
-                v <- AddV<UserEmbedding>(vec,{content: content})
-                RETURN v
+            N::Item { name: String, price: F64, stock: I32 }
+
+            QUERY getItems(min: F64) =>
+                total <- N<Item>::COUNT
+                label <- IF min THEN "a" ELSE "b"
+                price <- IF (total)::GT(0) THEN _::{price} ELSE 0
+                items <- N<Item>::{tier: IF _::{price}::GT(min) THEN "high" ELSE 1, name: IF (_::{stock})::GT(0) THEN name ELSE "none"}
+                RETURN label, price, items
         "#;
-        let diags = run(hx);
-        for d in diags.iter() {
-            println!("{}", d.render(hx, "query.hx"));
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        let expected = [
+            "condition of `IF` is of type `F64`, not a boolean",
+            "`price` is not a parameter or a variable holding a value",
+            "branches of `IF` are of types `String` and `I32`",
+        ];
+        assert_eq!(messages.len(), expected.len(), "{:?}", messages);
+        for (message, expected) in messages.iter().zip(expected) {
+            assert!(message.contains(expected), "{:?}", messages);
         }
-        assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("is not a field of vector")),
-            "expected a diagnostic about invalid vector property access, got: {:?}",
-            diags
-        );
     }
 
     #[test]
-    fn handles_untyped_nodes() {
+    fn generates_conditionals() {
         let hx = r#"
-            N::User { name: String }
+            N::Item { name: String, price: F64 }
 
-            QUERY untypedNode() =>
-                u <- N<User>::{some_field}
-                RETURN n
+            QUERY getItems(min: F64, premium: Boolean) =>
+                total <- N<Item>::COUNT
+                label <- IF (total)::GT(10) THEN "many" ELSE IF premium THEN "premium" ELSE "few"
+                items <- N<Item>::{tier: IF _::{price}::GT(min) THEN "high" ELSE "low"}
+                RETURN label, items
         "#;
-        let diags = run(hx);
-        for d in diags.iter() {
-            println!("{}", d.render(hx, "query.hx"));
-        }
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
             diags.is_empty(),
-            "expected no diagnostics for untyped node access, got: {:?}",
+            "expected no diagnostics, got: {:?}",
             diags
         );
+
+        let code = source.to_string();
+        assert!(code.contains(
+            "let label = if Some(&Value::from(total.clone())).map_or(false, |v| *v > Computed(Value::from(10))) { Value::from(\"many\") } else { if Some(&Value::from(&data.premium)).map_or(false, |v| *v == Computed(Value::from(true))) { Value::from(\"premium\") } else { Value::from(\"few\") } }"
+        ));
+        assert!(code.contains(
+            "expression_remapping!(remapping_vals, item.clone(), \"tier\" => { let val = &item; if val"
+        ));
+        assert!(code.contains("{ Value::from(\"high\") } else { Value::from(\"low\") } })?;"));
     }
 
     #[test]
-    fn respects_excluded_fields() {
+    fn generates_exists() {
         let hx = r#"
-            N::User { name: String, age: I32 }
-
-            QUERY excludedField() =>
-                u <- N<User>
-                n <- u::!{name}::{name}
-                RETURN n
+            N::Item { name: String }
+            E::Similar { From: Item, To: Item, Properties: {} }
+
+            QUERY getItems() =>
+                any <- EXISTS(N<Item>)
+                lonely <- N<Item>::WHERE(NOT EXISTS(_::Out<Similar>))
+                items <- N<Item>::{related: EXISTS(_::Out<Similar>)}
+                RETURN any, lonely, items
         "#;
-        let diags = run(hx);
-        for d in diags.iter() {
-            println!("{}", d.render(hx, "query.hx"));
-        }
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("was previously excluded")),
-            "expected a diagnostic about accessing excluded field, got: {:?}",
+            diags.is_empty(),
+            "expected no diagnostics, got: {:?}",
             diags
         );
+
+        let code = source.to_string();
+        // the traversals stop at the first item
+        assert!(code.contains(
+            "let any = G::new(Arc::clone(&db), &txn)\n.n_from_type(\"Item\").next().is_some();"
+        ));
+        assert!(code.contains(
+            "Ok(!(G::new_from(Arc::clone(&db), &txn, vec![val.clone()])\n\n.out(\"Similar\",&EdgeType::Node).next().is_some()))"
+        ));
+        assert!(code.contains(
+            "expression_remapping!(remapping_vals, item.clone(), \"related\" => { let val = &item; Value::from(G::new_from(Arc::clone(&db), &txn, vec![val.clone()])"
+        ));
+        assert!(code.contains("ReturnValue::from(Value::from(any))"));
     }
 
     #[test]
-    fn validates_add_node_fields() {
+    fn rejects_negated_values() {
         let hx = r#"
-            N::User { name: String }
+            N::Item { name: String }
 
-            QUERY badAddNodeField() =>
-                n <- AddN<User>({invalid_field: "test"})
-                RETURN n
+            QUERY getItems(name: String) =>
+                items <- N<Item>::WHERE(NOT(name))
+                RETURN items
         "#;
-        let diags = run(hx);
-        assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("is not a field of node")),
-            "expected a diagnostic about invalid node field, got: {:?}",
-            diags
-        );
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].contains("`NOT` can only negate a condition"));
     }
 
     #[test]
-    fn validates_add_edge_fields() {
+    fn checks_fields_in_closures() {
         let hx = r#"
             N::User { name: String }
-            N::Post { title: String }
-            E::Wrote {
-                From: User,
-                To: Post,
-                Properties: {
-                    date: String
-                }
-            }
+            E::Follows { From: User, To: User, Properties: { since: I32 } }
 
-            QUERY badAddEdgeField() =>
-                n1 <- AddN<User>({name: "test"})
-                n2 <- AddN<Post>({title: "test"})
-                e <- AddE<Wrote>({invalid_field: "test"})::To(n1)::From(n2)
-                RETURN e
+            QUERY getUsers() =>
+                users <- N<User>::|u|{
+                    id: u::ID,
+                    age: u::{age},
+                    follows: u::OutE<Follows>::|f|{ since: f::{since}, until: f::{until} }
+                }
+                RETURN users
         "#;
-        let diags = run(hx);
-        assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("is not a field of edge")),
-            "expected a diagnostic about invalid edge field, got: {:?}",
-            diags
-        );
+        let messages = run(hx).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].contains("`age` is not a field of node `User`"));
+        assert!(messages[1].contains("`until` is not a field of edge `Follows`"));
     }
 
     #[test]
-    fn validates_add_vector_fields() {
+    fn warns_of_unused_variables() {
         let hx = r#"
-            V::UserEmbedding {
-                content: String
-            }
-            
-            QUERY badAddVectorField() =>
-                v <- AddV<UserEmbedding>([1.0, 2.0], {invalid_field: "test"})
-                RETURN v
+            N::User { name: String }
+
+            QUERY addUser(name: String) =>
+                count <- N<User>::COUNT
+                user <- AddN<User>({name: name})
+                users <- N<User>::WHERE(_::{name}::EQ(name))
+                RETURN users
         "#;
         let diags = run(hx);
-        assert!(
-            diags
-                .iter()
-                .any(|d| d.message.contains("is not a valid vector field")),
-            "expected a diagnostic about invalid vector field, got: {:?}",
-            diags
+        assert_eq!(diags.len(), 2, "{:?}", diags);
+        assert!(diags.iter().all(|d| !d.is_error()));
+        assert!(diags[0].message.contains("variable `count` is never used"));
+        assert_eq!(
+            diags[0]
+                .fix
+                .as_ref()
+                .unwrap()
+                .to_remove
+                .as_ref()
+                .unwrap()
+                .span
+                .trim_end(),
+            "count <- N<User>::COUNT"
+        );
+        assert!(diags[1].message.contains("variable `user` is never used"));
+        assert_eq!(
+            diags[1]
+                .fix
+                .as_ref()
+                .unwrap()
+                .to_remove
+                .as_ref()
+                .unwrap()
+                .span,
+            "user <- "
         );
     }
 
     #[test]
-    fn validate_boolean_comparison() {
+    fn warns_of_statements_after_drop() {
         let hx = r#"
             N::User { name: String }
+            E::Follows { From: User, To: User }
 
-            QUERY booleanComparison() =>
-                a <- N<User>::WHERE(_::{name}::EQ(10))
-                RETURN a
+            QUERY dropUser(id: ID) =>
+                user <- N<User>(id)
+                DROP user
+                followers <- user::In<Follows>
+                RETURN followers
         "#;
         let diags = run(hx);
-        for d in diags.iter() {
-            println!("{}", d.render(hx, "query.hx"));
-        }
+        assert_eq!(diags.len(), 1, "{:?}", diags);
+        assert!(!diags[0].is_error());
+        assert!(diags[0]
+            .message
+            .contains("statement is unreachable, `user` was dropped before it"));
+    }
+
+    #[test]
+    fn generates_typescript_client() {
+        use crate::helixc::generator::tsdisplay::ToTypeScript;
+
+        let hx = r#"
+            N::User { name: String, age: U32 }
+
+            QUERY getUsers(name: String) =>
+                users <- N<User>
+                count <- N<User>::COUNT
+                names <- N<User>::{name}
+                RETURN users, count, names
+        "#;
+        let input = write_to_temp_file(vec![hx]);
+        let parsed = HelixParser::parse_source(&input).unwrap();
+        let (diags, source) = analyze(&parsed);
         assert!(
             diags.is_empty(),
             "expected no diagnostics, got: {:?}",
             diags
         );
+
+        let ts = source.to_typescript();
+        assert!(ts.contains("export interface User {\n  id: string;\n  label: string;\n"));
+        assert!(ts.contains("  age: number;\n"));
+        assert!(ts.contains("export interface getUsersInput {\n  name: string;\n}"));
+        assert!(ts.contains("  users: Array<User>;\n"));
+        assert!(ts.contains("  count: number;\n"));
+        assert!(ts.contains("  names: Array<Record<string, any>>;\n"));
+        assert!(ts.contains(
+            "export function getUsers(options: HelixOptions, input: getUsersInput): Promise<getUsersResult>"
+        ));
     }
 }
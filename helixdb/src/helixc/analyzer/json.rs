@@ -0,0 +1,90 @@
+//! Machine-readable rendering of `Diagnostic`s for CI annotations and editor tooling.
+//!
+//! Produces an array of objects, e.g.
+//! ```text
+//! [{"file": "schema.hx", "range": {"start": {"line": 12, "column": 9},
+//!   "end": {"line": 12, "column": 13}}, "severity": "error",
+//!   "message": "unknown node type `Post`", "hint": "declare `N::Post` above", "fix": null}]
+//! ```
+//! Lines and columns start at 1, the end of a range being exclusive.
+
+use serde::Serialize;
+
+use super::analyzer::{Diagnostic, DiagnosticSeverity};
+use crate::helixc::parser::location::{Loc, Span};
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    file: Option<&'a str>,
+    range: Range,
+    severity: &'static str,
+    message: &'a str,
+    hint: Option<&'a str>,
+    fix: Option<JsonFix<'a>>,
+}
+
+/// Replaces `range` with `replacement`, inserting it at the start of the diagnostic
+/// when there is no range
+#[derive(Serialize)]
+struct JsonFix<'a> {
+    range: Option<Range>,
+    replacement: &'a str,
+}
+
+#[derive(Serialize)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+#[derive(Serialize)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl From<&Loc> for Range {
+    fn from(loc: &Loc) -> Self {
+        Range {
+            start: Position::from(loc.start),
+            end: Position::from(loc.end),
+        }
+    }
+}
+
+impl From<Span> for Position {
+    fn from(span: Span) -> Self {
+        // spans count columns from 2
+        Position {
+            line: span.line,
+            column: span.column.saturating_sub(1).max(1),
+        }
+    }
+}
+
+/// Render the diagnostics as a JSON array.
+///
+/// * `filepath` – file of the diagnostics that don't have one of their own.
+pub fn render(diags: &[Diagnostic], filepath: Option<&str>) -> String {
+    let diags = diags
+        .iter()
+        .map(|diag| JsonDiagnostic {
+            file: diag.filepath.as_deref().or(filepath),
+            range: Range::from(&diag.location),
+            severity: match diag.severity {
+                DiagnosticSeverity::Error => "error",
+                DiagnosticSeverity::Warning => "warning",
+                DiagnosticSeverity::Info => "info",
+                DiagnosticSeverity::Hint => "hint",
+                DiagnosticSeverity::Empty => "note",
+            },
+            message: &diag.message,
+            hint: diag.hint.as_deref(),
+            fix: diag.fix.as_ref().map(|fix| JsonFix {
+                range: fix.to_remove.as_ref().map(Range::from),
+                replacement: fix.to_add.as_deref().unwrap_or(""),
+            }),
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string_pretty(&diags).unwrap()
+}
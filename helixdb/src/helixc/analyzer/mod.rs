@@ -1,4 +1,6 @@
 pub mod analyzer;
 pub mod pretty;
 pub mod fix;
+pub mod json;
 pub mod types;
+pub mod usage;
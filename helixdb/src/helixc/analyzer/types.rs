@@ -4,15 +4,19 @@ use crate::helixc::{
     generator::{
         generator_types::{
             Assignment as GeneratedAssignment, EdgeSchema as GeneratedEdgeSchema,
-            NodeSchema as GeneratedNodeSchema, Parameter as GeneratedParameter, SchemaProperty,
-            Statement as GeneratedStatement, VectorSchema as GeneratedVectorSchema,
+            EnumSchema as GeneratedEnumSchema, NodeSchema as GeneratedNodeSchema,
+            Parameter as GeneratedParameter, SchemaProperty, Statement as GeneratedStatement,
+            VectorSchema as GeneratedVectorSchema,
+        },
+        traversal_steps::{
+            PathSegment as GeneratedPathSegment, PropertyPath as GeneratedPropertyPath,
+            Traversal as GeneratedTraversal,
         },
-        traversal_steps::Traversal as GeneratedTraversal,
         utils::{GenRef, GeneratedType, GeneratedValue, RustType as GeneratedRustType},
     },
     parser::helix_parser::{
-        Assignment, DefaultValue, EdgeSchema, FieldPrefix, FieldType, NodeSchema, Parameter,
-        VectorSchema,
+        Assignment, DefaultValue, EdgeSchema, EnumSchema, FieldPrefix, FieldType, NodeSchema,
+        Parameter, PathSegment, PropertyPath, ValueType, VectorSchema,
     },
 };
 
@@ -28,6 +32,7 @@ impl From<NodeSchema> for GeneratedNodeSchema {
                     field_type: f.field_type.into(),
                     default_value: f.defaults.map(|d| d.into()),
                     is_index: f.prefix,
+                    sensitive: f.sensitive,
                 })
                 .collect(),
         }
@@ -48,6 +53,7 @@ impl From<EdgeSchema> for GeneratedEdgeSchema {
                         field_type: f.field_type.into(),
                         default_value: f.defaults.map(|d| d.into()),
                         is_index: f.prefix,
+                        sensitive: f.sensitive,
                     })
                     .collect()
             }),
@@ -55,6 +61,19 @@ impl From<EdgeSchema> for GeneratedEdgeSchema {
     }
 }
 
+impl From<EnumSchema> for GeneratedEnumSchema {
+    fn from(generated: EnumSchema) -> Self {
+        GeneratedEnumSchema {
+            name: generated.name.1,
+            variants: generated
+                .variants
+                .into_iter()
+                .map(|(_, variant)| variant)
+                .collect(),
+        }
+    }
+}
+
 impl From<VectorSchema> for GeneratedVectorSchema {
     fn from(generated: VectorSchema) -> Self {
         GeneratedVectorSchema {
@@ -67,6 +86,7 @@ impl From<VectorSchema> for GeneratedVectorSchema {
                     field_type: f.field_type.into(),
                     default_value: f.defaults.map(|d| d.into()),
                     is_index: f.prefix,
+                    sensitive: f.sensitive,
                 })
                 .collect(),
         }
@@ -79,11 +99,17 @@ impl GeneratedParameter {
         parameters: &mut Vec<GeneratedParameter>,
         sub_parameters: &mut Vec<(String, Vec<GeneratedParameter>)>,
     ) {
+        let default_value = match &param.default_value {
+            Some(ValueType::Literal { value, .. }) => Some(value.clone()),
+            _ => None,
+        };
         match param.param_type.1 {
             FieldType::Identifier(ref id) => {
                 parameters.push(GeneratedParameter {
                     name: param.name.1,
                     field_type: GeneratedType::Variable(GenRef::Std(id.clone())),
+                    is_optional: param.is_optional,
+                    default_value,
                 });
             }
             FieldType::Array(inner) => match inner.as_ref() {
@@ -94,12 +120,16 @@ impl GeneratedParameter {
                         field_type: GeneratedType::Vec(Box::new(GeneratedType::Object(
                             GenRef::Std(format!("{}Data", param.name.1)),
                         ))),
+                        is_optional: param.is_optional,
+                        default_value,
                     });
                 }
                 param_type => {
                     parameters.push(GeneratedParameter {
                         name: param.name.1,
                         field_type: GeneratedType::Vec(Box::new(param_type.clone().into())),
+                        is_optional: param.is_optional,
+                        default_value,
                     });
                 }
             },
@@ -111,12 +141,16 @@ impl GeneratedParameter {
                         "{}Data",
                         param.name.1
                     ))),
+                    is_optional: param.is_optional,
+                    default_value,
                 });
             }
             param_type => {
                 parameters.push(GeneratedParameter {
                     name: param.name.1,
                     field_type: param_type.into(),
+                    is_optional: param.is_optional,
+                    default_value,
                 });
             }
         }
@@ -140,6 +174,8 @@ fn unwrap_object(
                             "{}Data",
                             field_name
                         ))),
+                        is_optional: false,
+                        default_value: None,
                     }
                 }
                 FieldType::Array(inner) => match inner.as_ref() {
@@ -150,16 +186,22 @@ fn unwrap_object(
                             field_type: GeneratedType::Vec(Box::new(GeneratedType::Object(
                                 GenRef::Std(format!("{}Data", field_name)),
                             ))),
+                            is_optional: false,
+                            default_value: None,
                         }
                     }
                     _ => GeneratedParameter {
                         name: field_name.clone(),
                         field_type: GeneratedType::from(field_type.clone()),
+                        is_optional: false,
+                        default_value: None,
                     },
                 },
                 _ => GeneratedParameter {
                     name: field_name.clone(),
                     field_type: GeneratedType::from(field_type.clone()),
+                    is_optional: false,
+                    default_value: None,
                 },
             })
             .collect(),
@@ -186,6 +228,8 @@ impl From<FieldType> for GeneratedType {
             FieldType::Date => GeneratedType::RustType(GeneratedRustType::Date),
             FieldType::Array(inner) => GeneratedType::Vec(Box::new(GeneratedType::from(*inner))),
             FieldType::Identifier(ref id) => GeneratedType::Variable(GenRef::Std(id.clone())),
+            // nested objects of a schema are stored as object values
+            FieldType::Object(_) => GeneratedType::Variable(GenRef::Std("Value".to_string())),
             // FieldType::Object(obj) => GeneratedType::Object(
             //     obj.iter()
             //         .map(|(name, field_type)| {
@@ -218,9 +262,33 @@ impl From<DefaultValue> for GeneratedValue {
             DefaultValue::U128(i) => GeneratedValue::Primitive(GenRef::Std(i.to_string())),
             DefaultValue::Boolean(b) => GeneratedValue::Primitive(GenRef::Std(b.to_string())),
             DefaultValue::Now => GeneratedValue::Primitive(GenRef::Std(
-                "chrono::Utc::now().to_rfc3339()".to_string(),
+                "chrono::Utc::now()".to_string(),
+            )),
+            DefaultValue::Uuid => GeneratedValue::Primitive(GenRef::Std(
+                "uuid::Uuid::new_v4().to_string()".to_string(),
+            )),
+            DefaultValue::Ulid => GeneratedValue::Primitive(GenRef::Std(
+                "helixdb::protocol::items::ulid()".to_string(),
             )),
             DefaultValue::Empty => GeneratedValue::Unknown,
         }
     }
 }
+
+impl From<&PropertyPath> for GeneratedPropertyPath {
+    fn from(path: &PropertyPath) -> Self {
+        GeneratedPropertyPath {
+            property: GenRef::Literal(path.field.clone()),
+            path: path
+                .path
+                .iter()
+                .map(|segment| match segment {
+                    PathSegment::Field(field) => {
+                        GeneratedPathSegment::Field(GenRef::Literal(field.clone()))
+                    }
+                    PathSegment::Index(index) => GeneratedPathSegment::Index(*index),
+                })
+                .collect(),
+        }
+    }
+}
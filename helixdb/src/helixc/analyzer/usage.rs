@@ -0,0 +1,278 @@
+//! Names of the variables and parameters read by the parts of a query.
+//!
+//! Field names and closure parameters are collected along with them as the parser
+//! doesn't tell them apart, so a name may be reported as used when it isn't but never
+//! the other way around.
+
+use std::collections::HashSet;
+
+use crate::helixc::parser::helix_parser::*;
+
+pub fn statement_uses<'a>(statement: &'a Statement, uses: &mut HashSet<&'a str>) {
+    match &statement.statement {
+        StatementType::Assignment(assignment) => expression_uses(&assignment.value, uses),
+        StatementType::AddVector(add) => add_vector_uses(add, uses),
+        StatementType::AddNode(add) => add_node_uses(add, uses),
+        StatementType::AddEdge(add) => add_edge_uses(add, uses),
+        StatementType::Drop(expr) => expression_uses(expr, uses),
+        StatementType::SearchVector(search) => search_vector_uses(search, uses),
+        StatementType::BatchAddVector(add) => batch_add_vector_uses(add, uses),
+        StatementType::BM25Search(search) => bm25_search_uses(search, uses),
+        StatementType::ForLoop(for_loop) => {
+            uses.insert(for_loop.in_variable.1.as_str());
+            for statement in &for_loop.statements {
+                statement_uses(statement, uses);
+            }
+        }
+    }
+}
+
+pub fn expression_uses<'a>(expression: &'a Expression, uses: &mut HashSet<&'a str>) {
+    match &expression.expr {
+        ExpressionType::Traversal(traversal) => traversal_uses(traversal, uses),
+        ExpressionType::Identifier(name) => {
+            uses.insert(name.as_str());
+        }
+        ExpressionType::Exists(expr) | ExpressionType::Not(expr) => expression_uses(expr, uses),
+        ExpressionType::BatchAddVector(add) => batch_add_vector_uses(add, uses),
+        ExpressionType::AddVector(add) => add_vector_uses(add, uses),
+        ExpressionType::AddNode(add) => add_node_uses(add, uses),
+        ExpressionType::AddEdge(add) => add_edge_uses(add, uses),
+        ExpressionType::And(exprs) | ExpressionType::Or(exprs) => {
+            for expr in exprs {
+                expression_uses(expr, uses);
+            }
+        }
+        ExpressionType::SearchVector(search) => search_vector_uses(search, uses),
+        ExpressionType::BM25Search(search) => bm25_search_uses(search, uses),
+        ExpressionType::HybridSearch(search) => {
+            if let Some(vector) = &search.vector {
+                vector_data_uses(vector, uses);
+            }
+            if let Some(query) = &search.query {
+                value_uses(query, uses);
+            }
+            if let Some(k) = &search.k {
+                number_uses(k, uses);
+            }
+        }
+        ExpressionType::Arithmetic { lhs, rhs, .. } => {
+            expression_uses(lhs, uses);
+            expression_uses(rhs, uses);
+        }
+        ExpressionType::Function { arg, .. } => expression_uses(arg, uses),
+        ExpressionType::Compared { expr, op } => {
+            expression_uses(expr, uses);
+            boolean_op_uses(op, uses);
+        }
+        ExpressionType::Conditional {
+            condition,
+            then,
+            otherwise,
+        } => {
+            expression_uses(condition, uses);
+            expression_uses(then, uses);
+            expression_uses(otherwise, uses);
+        }
+        ExpressionType::StringLiteral(_)
+        | ExpressionType::IntegerLiteral(_)
+        | ExpressionType::FloatLiteral(_)
+        | ExpressionType::BooleanLiteral(_)
+        | ExpressionType::Empty => {}
+    }
+}
+
+fn traversal_uses<'a>(traversal: &'a Traversal, uses: &mut HashSet<&'a str>) {
+    match &traversal.start {
+        StartNode::Node { ids, .. } | StartNode::Edge { ids, .. } => {
+            for id in ids.iter().flatten() {
+                id_uses(id, uses);
+            }
+        }
+        StartNode::Identifier(name) => {
+            uses.insert(name.as_str());
+        }
+        StartNode::Analytics(analytics) => {
+            if let Some(arg) = &analytics.arg {
+                number_uses(arg, uses);
+            }
+        }
+        StartNode::Anonymous => {}
+    }
+    for step in &traversal.steps {
+        step_uses(step, uses);
+    }
+}
+
+fn step_uses<'a>(step: &'a Step, uses: &mut HashSet<&'a str>) {
+    match &step.step {
+        StepType::Node(graph_step) | StepType::Edge(graph_step) => match &graph_step.step {
+            GraphStepType::ShortestPath(path) => {
+                for id in path.from.iter().chain(path.to.iter()) {
+                    id_uses(id, uses);
+                }
+                if let Some(max_depth) = &path.max_depth {
+                    number_uses(max_depth, uses);
+                }
+            }
+            GraphStepType::ShortestPathWeighted(path) => {
+                for id in path.from.iter().chain(path.to.iter()) {
+                    id_uses(id, uses);
+                }
+            }
+            GraphStepType::SearchVector(search) => search_vector_uses(search, uses),
+            _ => {}
+        },
+        StepType::Where(expr) => expression_uses(expr, uses),
+        StepType::BooleanOperation(op) => boolean_op_uses(op, uses),
+        StepType::Update(update) => fields_uses(&update.fields, uses),
+        StepType::Object(object) => fields_uses(&object.fields, uses),
+        StepType::Closure(closure) => fields_uses(&closure.object.fields, uses),
+        StepType::Range((start, end)) | StepType::Limit((start, end)) => {
+            expression_uses(start, uses);
+            expression_uses(end, uses);
+        }
+        StepType::AsOf(at) => expression_uses(at, uses),
+        StepType::AddEdge(add) => add_edge_uses(add, uses),
+        StepType::Count | StepType::Exclude(_) | StepType::OrderBy(_) => {}
+    }
+}
+
+fn boolean_op_uses<'a>(op: &'a BooleanOp, uses: &mut HashSet<&'a str>) {
+    match &op.op {
+        BooleanOpType::And(exprs) | BooleanOpType::Or(exprs) => {
+            for expr in exprs {
+                expression_uses(expr, uses);
+            }
+        }
+        BooleanOpType::GreaterThan(expr)
+        | BooleanOpType::GreaterThanOrEqual(expr)
+        | BooleanOpType::LessThan(expr)
+        | BooleanOpType::LessThanOrEqual(expr)
+        | BooleanOpType::Equal(expr)
+        | BooleanOpType::NotEqual(expr) => expression_uses(expr, uses),
+    }
+}
+
+fn fields_uses<'a>(fields: &'a [FieldAddition], uses: &mut HashSet<&'a str>) {
+    for field in fields {
+        field_value_uses(&field.value, uses);
+    }
+}
+
+fn field_value_uses<'a>(value: &'a FieldValue, uses: &mut HashSet<&'a str>) {
+    match &value.value {
+        FieldValueType::Traversal(traversal) | FieldValueType::Optional(traversal) => {
+            traversal_uses(traversal, uses)
+        }
+        FieldValueType::Expression(expr) => expression_uses(expr, uses),
+        FieldValueType::Fields(fields) => fields_uses(fields, uses),
+        FieldValueType::Identifier(name) => {
+            uses.insert(name.as_str());
+        }
+        FieldValueType::Coalesce(values) => {
+            for value in values {
+                field_value_uses(value, uses);
+            }
+        }
+        FieldValueType::Literal(_) | FieldValueType::PropertyPath(_) | FieldValueType::Empty => {}
+    }
+}
+
+fn add_node_uses<'a>(add: &'a AddNode, uses: &mut HashSet<&'a str>) {
+    for value in add.fields.iter().flat_map(|fields| fields.values()) {
+        value_uses(value, uses);
+    }
+}
+
+fn add_edge_uses<'a>(add: &'a AddEdge, uses: &mut HashSet<&'a str>) {
+    for value in add.fields.iter().flat_map(|fields| fields.values()) {
+        value_uses(value, uses);
+    }
+    for id in add
+        .connection
+        .from_id
+        .iter()
+        .chain(add.connection.to_id.iter())
+    {
+        id_uses(id, uses);
+    }
+}
+
+fn add_vector_uses<'a>(add: &'a AddVector, uses: &mut HashSet<&'a str>) {
+    if let Some(data) = &add.data {
+        vector_data_uses(data, uses);
+    }
+    for value in add.fields.iter().flat_map(|fields| fields.values()) {
+        value_uses(value, uses);
+    }
+}
+
+fn batch_add_vector_uses<'a>(add: &'a BatchAddVector, uses: &mut HashSet<&'a str>) {
+    if let Some(name) = &add.vec_identifier {
+        uses.insert(name.as_str());
+    }
+    for value in add.fields.iter().flat_map(|fields| fields.values()) {
+        value_uses(value, uses);
+    }
+}
+
+fn search_vector_uses<'a>(search: &'a SearchVector, uses: &mut HashSet<&'a str>) {
+    if let Some(data) = &search.data {
+        vector_data_uses(data, uses);
+    }
+    if let Some(k) = &search.k {
+        number_uses(k, uses);
+    }
+    if let Some(pre_filter) = &search.pre_filter {
+        expression_uses(pre_filter, uses);
+    }
+}
+
+fn bm25_search_uses<'a>(search: &'a BM25Search, uses: &mut HashSet<&'a str>) {
+    if let Some(data) = &search.data {
+        value_uses(data, uses);
+    }
+    if let Some(k) = &search.k {
+        number_uses(k, uses);
+    }
+}
+
+fn value_uses<'a>(value: &'a ValueType, uses: &mut HashSet<&'a str>) {
+    match value {
+        ValueType::Identifier { value, .. } => {
+            uses.insert(value.as_str());
+        }
+        ValueType::Object { fields, .. } => {
+            for value in fields.values() {
+                value_uses(value, uses);
+            }
+        }
+        ValueType::Literal { .. } => {}
+    }
+}
+
+fn id_uses<'a>(id: &'a IdType, uses: &mut HashSet<&'a str>) {
+    match id {
+        IdType::Identifier { value, .. } => {
+            uses.insert(value.as_str());
+        }
+        IdType::ByIndex { index, value, .. } => {
+            id_uses(index, uses);
+            value_uses(value, uses);
+        }
+        IdType::Literal { .. } => {}
+    }
+}
+
+fn vector_data_uses<'a>(data: &'a VectorData, uses: &mut HashSet<&'a str>) {
+    if let VectorData::Identifier(name) = data {
+        uses.insert(name.as_str());
+    }
+}
+
+fn number_uses<'a>(number: &'a EvaluatesToNumber, uses: &mut HashSet<&'a str>) {
+    if let EvaluatesToNumberType::Identifier(name) = &number.value {
+        uses.insert(name.as_str());
+    }
+}
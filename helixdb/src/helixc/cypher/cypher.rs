@@ -0,0 +1,1142 @@
+use crate::helixc::{
+    analyzer::analyzer::{analyze, Diagnostic, DiagnosticSeverity},
+    generator::generator_types::Source as GeneratedSource,
+    parser::helix_parser::{Content, FieldType, HelixParser, HxFile, Query, Source},
+};
+use pest::{iterators::Pair, Parser as PestParser};
+use pest_derive::Parser;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+#[derive(Parser)]
+#[grammar = "helixc/cypher/cypher.pest"]
+pub struct CypherParser;
+
+pub enum CypherError {
+    /// The query isn't valid Cypher of the supported subset
+    Syntax(String),
+    /// The query is valid Cypher that has no HelixQL translation
+    Unsupported(String),
+    /// The translated query was rejected by the analyzer
+    Invalid(Vec<Diagnostic>),
+}
+
+impl fmt::Display for CypherError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CypherError::Syntax(e) => write!(f, "Cypher syntax error: {}", e),
+            CypherError::Unsupported(e) => write!(f, "Unsupported Cypher: {}", e),
+            CypherError::Invalid(diagnostics) => {
+                let messages = diagnostics
+                    .iter()
+                    .map(|d| d.message.as_str())
+                    .collect::<Vec<_>>();
+                write!(f, "Invalid query: {}", messages.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for CypherError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<pest::error::Error<Rule>> for CypherError {
+    fn from(e: pest::error::Error<Rule>) -> Self {
+        CypherError::Syntax(e.to_string())
+    }
+}
+
+fn unsupported<T>(msg: impl Into<String>) -> Result<T, CypherError> {
+    Err(CypherError::Unsupported(msg.into()))
+}
+
+/// Translates a Cypher query into a HelixQL query named `name`.
+///
+/// Supports `MATCH` over chains of directed relationships with `WHERE` comparisons of
+/// properties, `CREATE`, `DELETE` and `RETURN` of variables, properties and `count()`
+/// with `ORDER BY`, `SKIP` and `LIMIT`. The types of the `$parameters` are taken from
+/// the properties of `schema` they are compared with or assigned to.
+///
+/// Variables are bound one after the other along a pattern, so a `WHERE` on a later
+/// variable doesn't narrow down the ones before it as it would in Cypher.
+pub fn translate(name: &str, cypher: &str, schema: &Source) -> Result<String, CypherError> {
+    let clauses = parse(cypher)?;
+    let mut translator = Translator::new(schema, &clauses);
+    let mut returned = None;
+    for clause in clauses.iter() {
+        match clause {
+            Clause::Match(pattern, condition) => translator.match_clause(pattern, condition)?,
+            Clause::Create(patterns) => {
+                for pattern in patterns {
+                    translator.create(pattern)?;
+                }
+            }
+            Clause::Delete(variables) => translator.delete(variables)?,
+            Clause::Return(ret) => returned = Some(translator.return_clause(ret)?),
+        }
+    }
+    // HelixQL queries always return something, those of Cypher may not
+    let returned = returned.unwrap_or_else(|| "\"SUCCESS\"".to_string());
+
+    let parameters = translator
+        .parameters
+        .iter()
+        .map(|(name, ty)| format!("{}: {}", name, ty))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut query = format!("QUERY {}({}) =>\n", name, parameters);
+    for statement in translator.statements.iter() {
+        query.push_str(&format!("    {}\n", statement));
+    }
+    query.push_str(&format!("    RETURN {}\n", returned));
+    Ok(query)
+}
+
+/// Translates a Cypher query and parses the translation, so it can be analyzed and
+/// generated along with the HelixQL queries of `schema`
+pub fn lower(name: &str, cypher: &str, schema: &Source) -> Result<Query, CypherError> {
+    let helixql = translate(name, cypher, schema)?;
+    let content = Content {
+        content: String::new(),
+        source: Source::default(),
+        files: vec![HxFile {
+            name: format!("{}.cypher", name),
+            content: helixql,
+        }],
+    };
+    let mut source = HelixParser::parse_source(&content)
+        .map_err(|e| CypherError::Syntax(format!("translation didn't parse: {}", e)))?;
+    source
+        .queries
+        .pop()
+        .ok_or_else(|| CypherError::Syntax("translation has no query".to_string()))
+}
+
+/// Parses a Cypher query into its clauses
+pub fn parse(cypher: &str) -> Result<Vec<Clause>, CypherError> {
+    let pair = CypherParser::parse(Rule::query, cypher)?
+        .next()
+        .ok_or_else(|| CypherError::Syntax("empty query".to_string()))?;
+    pair.into_inner()
+        .filter(|p| p.as_rule() != Rule::EOI)
+        .map(parse_clause)
+        .collect()
+}
+
+/// Lowers a Cypher query into the generator's representation of `schema` and the query
+pub fn compile(name: &str, cypher: &str, schema: &Source) -> Result<GeneratedSource, CypherError> {
+    let query = lower(name, cypher, schema)?;
+    let mut source = schema.clone();
+    source.source.push_str(&query.original_query);
+    source.queries.push(query);
+    let (diagnostics, generated) = analyze(&source);
+    let errors = diagnostics
+        .into_iter()
+        .filter(|d| matches!(d.severity, DiagnosticSeverity::Error))
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(CypherError::Invalid(errors));
+    }
+    Ok(generated)
+}
+
+// ---------------------------------------------------------------------
+// Syntax tree of the supported subset
+// ---------------------------------------------------------------------
+
+pub enum Clause {
+    Match(Pattern, Option<Condition>),
+    Create(Vec<Pattern>),
+    Delete(Vec<String>),
+    Return(Return),
+}
+
+pub struct Pattern {
+    pub start: NodePattern,
+    pub hops: Vec<(RelPattern, NodePattern)>,
+}
+
+#[derive(Default)]
+pub struct NodePattern {
+    pub variable: Option<String>,
+    pub label: Option<String>,
+    pub properties: Vec<(String, Operand)>,
+}
+
+pub struct RelPattern {
+    /// Whether the relationship points away from the node before it
+    pub outgoing: bool,
+    pub variable: Option<String>,
+    pub label: Option<String>,
+    pub properties: Vec<(String, Operand)>,
+}
+
+#[derive(Clone)]
+pub enum Operand {
+    Id(String),
+    Property(String, String),
+    Parameter(String),
+    String(String),
+    Number(String),
+    Boolean(bool),
+}
+
+#[derive(Clone, Copy)]
+pub enum Op {
+    Eq,
+    Neq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl Op {
+    pub fn helixql(self) -> &'static str {
+        match self {
+            Op::Eq => "EQ",
+            Op::Neq => "NEQ",
+            Op::Lt => "LT",
+            Op::Lte => "LTE",
+            Op::Gt => "GT",
+            Op::Gte => "GTE",
+        }
+    }
+
+    /// The operator comparing the operands the other way around
+    pub fn mirrored(self) -> Op {
+        match self {
+            Op::Lt => Op::Gt,
+            Op::Lte => Op::Gte,
+            Op::Gt => Op::Lt,
+            Op::Gte => Op::Lte,
+            op => op,
+        }
+    }
+}
+
+pub enum Condition {
+    Compare(Operand, Op, Operand),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+}
+
+pub struct Return {
+    pub items: Vec<(ReturnExpr, Option<String>)>,
+    pub order_by: Option<(String, String, bool)>,
+    pub skip: Option<Operand>,
+    pub limit: Option<Operand>,
+}
+
+pub enum ReturnExpr {
+    Variable(String),
+    Property(String, String),
+    Count(String),
+}
+
+fn parse_clause(pair: Pair<Rule>) -> Result<Clause, CypherError> {
+    match pair.as_rule() {
+        Rule::match_clause => {
+            let mut pattern = None;
+            let mut condition = None;
+            for p in pair.into_inner() {
+                match p.as_rule() {
+                    Rule::pattern => pattern = Some(parse_pattern(p)?),
+                    Rule::where_clause => {
+                        let expr = p.into_inner().find(|p| p.as_rule() == Rule::or_expr);
+                        condition = expr.map(parse_condition).transpose()?;
+                    }
+                    _ => {}
+                }
+            }
+            let pattern = pattern
+                .ok_or_else(|| CypherError::Syntax("MATCH without a pattern".to_string()))?;
+            Ok(Clause::Match(pattern, condition))
+        }
+        Rule::create_clause => Ok(Clause::Create(
+            pair.into_inner()
+                .filter(|p| p.as_rule() == Rule::pattern)
+                .map(parse_pattern)
+                .collect::<Result<_, _>>()?,
+        )),
+        Rule::delete_clause => Ok(Clause::Delete(
+            pair.into_inner()
+                .filter(|p| p.as_rule() == Rule::variable)
+                .map(|p| p.as_str().to_string())
+                .collect(),
+        )),
+        Rule::return_clause => {
+            let mut ret = Return {
+                items: Vec::new(),
+                order_by: None,
+                skip: None,
+                limit: None,
+            };
+            for p in pair.into_inner() {
+                match p.as_rule() {
+                    Rule::return_item => {
+                        let mut inner = p.into_inner();
+                        let expr = inner.next().unwrap();
+                        let expr = match expr.as_rule() {
+                            Rule::count => ReturnExpr::Count(last_variable(expr)),
+                            Rule::property => {
+                                let (variable, key) = parse_property(expr);
+                                ReturnExpr::Property(variable, key)
+                            }
+                            _ => ReturnExpr::Variable(expr.as_str().to_string()),
+                        };
+                        let alias = inner
+                            .find(|p| p.as_rule() == Rule::variable)
+                            .map(|p| p.as_str().to_string());
+                        ret.items.push((expr, alias));
+                    }
+                    Rule::order_by => {
+                        let mut property = None;
+                        let mut descending = false;
+                        for p in p.into_inner() {
+                            match p.as_rule() {
+                                Rule::property => property = Some(parse_property(p)),
+                                Rule::DESC => descending = true,
+                                _ => {}
+                            }
+                        }
+                        let (variable, key) = property.unwrap();
+                        ret.order_by = Some((variable, key, descending));
+                    }
+                    Rule::skip => ret.skip = Some(parse_bound(p)),
+                    Rule::limit => ret.limit = Some(parse_bound(p)),
+                    _ => {}
+                }
+            }
+            Ok(Clause::Return(ret))
+        }
+        rule => Err(CypherError::Syntax(format!("unexpected {:?}", rule))),
+    }
+}
+
+fn last_variable(pair: Pair<Rule>) -> String {
+    pair.into_inner()
+        .filter(|p| p.as_rule() == Rule::variable)
+        .last()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default()
+}
+
+fn parse_property(pair: Pair<Rule>) -> (String, String) {
+    let mut inner = pair.into_inner();
+    let variable = inner.next().unwrap().as_str().to_string();
+    let key = inner.next().unwrap().as_str().to_string();
+    (variable, key)
+}
+
+fn parse_bound(pair: Pair<Rule>) -> Operand {
+    let value = pair
+        .into_inner()
+        .find(|p| matches!(p.as_rule(), Rule::integer | Rule::parameter))
+        .unwrap();
+    parse_operand(value)
+}
+
+fn parse_pattern(pair: Pair<Rule>) -> Result<Pattern, CypherError> {
+    let mut inner = pair.into_inner();
+    let start = parse_node(inner.next().unwrap());
+    let mut hops = Vec::new();
+    while let (Some(relationship), Some(node)) = (inner.next(), inner.next()) {
+        hops.push((parse_relationship(relationship), parse_node(node)));
+    }
+    Ok(Pattern { start, hops })
+}
+
+fn parse_node(pair: Pair<Rule>) -> NodePattern {
+    let mut node = NodePattern::default();
+    for p in pair.into_inner() {
+        match p.as_rule() {
+            Rule::variable => node.variable = Some(p.as_str().to_string()),
+            Rule::label => node.label = Some(p.as_str().to_string()),
+            Rule::properties => node.properties = parse_properties(p),
+            _ => {}
+        }
+    }
+    node
+}
+
+fn parse_relationship(pair: Pair<Rule>) -> RelPattern {
+    let direction = pair.into_inner().next().unwrap();
+    let mut rel = RelPattern {
+        outgoing: direction.as_rule() == Rule::outgoing,
+        variable: None,
+        label: None,
+        properties: Vec::new(),
+    };
+    let detail = direction.into_inner().next().unwrap();
+    for p in detail.into_inner() {
+        match p.as_rule() {
+            Rule::variable => rel.variable = Some(p.as_str().to_string()),
+            Rule::label => rel.label = Some(p.as_str().to_string()),
+            Rule::properties => rel.properties = parse_properties(p),
+            _ => {}
+        }
+    }
+    rel
+}
+
+fn parse_properties(pair: Pair<Rule>) -> Vec<(String, Operand)> {
+    pair.into_inner()
+        .map(|p| {
+            let mut inner = p.into_inner();
+            let key = inner.next().unwrap().as_str().to_string();
+            (key, parse_operand(inner.next().unwrap()))
+        })
+        .collect()
+}
+
+fn parse_condition(pair: Pair<Rule>) -> Result<Condition, CypherError> {
+    match pair.as_rule() {
+        Rule::or_expr | Rule::and_expr => {
+            let is_or = pair.as_rule() == Rule::or_expr;
+            let mut conditions = pair
+                .into_inner()
+                .filter(|p| !matches!(p.as_rule(), Rule::AND | Rule::OR))
+                .map(parse_condition)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(match conditions.len() {
+                1 => conditions.pop().unwrap(),
+                _ if is_or => Condition::Or(conditions),
+                _ => Condition::And(conditions),
+            })
+        }
+        Rule::comparison => {
+            let mut inner = pair.into_inner();
+            let left = parse_operand(inner.next().unwrap());
+            let op = match inner.next().unwrap().as_str() {
+                "=" => Op::Eq,
+                "<>" => Op::Neq,
+                "<" => Op::Lt,
+                "<=" => Op::Lte,
+                ">" => Op::Gt,
+                _ => Op::Gte,
+            };
+            let right = parse_operand(inner.next().unwrap());
+            Ok(Condition::Compare(left, op, right))
+        }
+        rule => Err(CypherError::Syntax(format!("unexpected {:?}", rule))),
+    }
+}
+
+fn parse_operand(pair: Pair<Rule>) -> Operand {
+    match pair.as_rule() {
+        Rule::id_of => Operand::Id(last_variable(pair)),
+        Rule::property => {
+            let (variable, key) = parse_property(pair);
+            Operand::Property(variable, key)
+        }
+        Rule::parameter => {
+            Operand::Parameter(pair.into_inner().next().unwrap().as_str().to_string())
+        }
+        Rule::string => Operand::String(pair.into_inner().next().unwrap().as_str().to_string()),
+        Rule::boolean => Operand::Boolean(pair.as_str().eq_ignore_ascii_case("true")),
+        _ => Operand::Number(pair.as_str().to_string()),
+    }
+}
+
+// ---------------------------------------------------------------------
+// Translation
+// ---------------------------------------------------------------------
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Node,
+    Edge,
+}
+
+struct Binding {
+    kind: Kind,
+    label: String,
+    /// How `AddE` refers to the node, set if the variable holds a single node
+    reference: Option<String>,
+}
+
+struct Translator<'a> {
+    schema: &'a Source,
+    bound: HashMap<String, Binding>,
+    /// Variables named in the query, which generated names mustn't collide with
+    named: HashSet<String>,
+    /// Parameters and their HelixQL types, in the order they are first used
+    parameters: Vec<(String, String)>,
+    statements: Vec<String>,
+    generated: usize,
+}
+
+impl<'a> Translator<'a> {
+    fn new(schema: &'a Source, clauses: &[Clause]) -> Self {
+        let mut named = HashSet::new();
+        for clause in clauses {
+            let patterns = match clause {
+                Clause::Match(pattern, _) => std::slice::from_ref(pattern),
+                Clause::Create(patterns) => patterns.as_slice(),
+                _ => &[],
+            };
+            for pattern in patterns {
+                named.extend(pattern.start.variable.clone());
+                for (rel, node) in pattern.hops.iter() {
+                    named.extend(rel.variable.clone());
+                    named.extend(node.variable.clone());
+                }
+            }
+            if let Clause::Return(ret) = clause {
+                named.extend(ret.items.iter().filter_map(|(_, alias)| alias.clone()));
+            }
+        }
+        Self {
+            schema,
+            bound: HashMap::new(),
+            named,
+            parameters: Vec::new(),
+            statements: Vec::new(),
+            generated: 0,
+        }
+    }
+
+    /// A name for an element of a pattern that the query doesn't name
+    fn generate_name(&mut self, kind: Kind) -> String {
+        loop {
+            self.generated += 1;
+            let name = match kind {
+                Kind::Node => format!("node{}", self.generated),
+                Kind::Edge => format!("edge{}", self.generated),
+            };
+            if !self.named.contains(&name) && !self.bound.contains_key(&name) {
+                return name;
+            }
+        }
+    }
+
+    fn field_type(&self, kind: Kind, label: &str, key: &str) -> Result<FieldType, CypherError> {
+        let fields = match kind {
+            Kind::Node => self
+                .schema
+                .node_schemas
+                .iter()
+                .find(|schema| schema.name.1 == label)
+                .map(|schema| schema.fields.as_slice()),
+            Kind::Edge => self
+                .schema
+                .edge_schemas
+                .iter()
+                .find(|schema| schema.name.1 == label)
+                .map(|schema| schema.properties.as_deref().unwrap_or_default()),
+        };
+        let Some(fields) = fields else {
+            return unsupported(format!("`{}` isn't declared in the schema", label));
+        };
+        if key == "id" {
+            return Ok(FieldType::Uuid);
+        }
+        match fields.iter().find(|field| field.name == key) {
+            Some(field) => Ok(field.field_type.clone()),
+            None => unsupported(format!("`{}` isn't a property of `{}`", key, label)),
+        }
+    }
+
+    /// Declares the parameter with the type, failing if it is used with another type
+    fn parameter(&mut self, name: &str, ty: String) -> Result<String, CypherError> {
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return unsupported(format!("parameter `${}` has to start with a letter", name));
+        }
+        match self.parameters.iter().find(|(n, _)| n == name) {
+            Some((_, existing)) if *existing != ty => unsupported(format!(
+                "parameter `${}` is used as both {} and {}",
+                name, existing, ty
+            )),
+            Some(_) => Ok(name.to_string()),
+            None => {
+                self.parameters.push((name.to_string(), ty));
+                Ok(name.to_string())
+            }
+        }
+    }
+
+    /// The HelixQL value of an operand compared with or assigned to a field of the type
+    fn value(&mut self, operand: &Operand, field_type: &FieldType) -> Result<String, CypherError> {
+        match operand {
+            Operand::Parameter(name) => {
+                let ty = helixql_type(field_type)?;
+                self.parameter(name, ty)
+            }
+            Operand::String(s) if s.contains('"') || s.contains('\\') => {
+                unsupported("strings with quotes or backslashes")
+            }
+            Operand::String(s) => Ok(format!("\"{}\"", s)),
+            Operand::Number(n) if n.starts_with('-') => unsupported("negative numbers"),
+            Operand::Number(n) => Ok(n.clone()),
+            Operand::Boolean(b) => Ok(b.to_string()),
+            Operand::Id(_) | Operand::Property(_, _) => {
+                unsupported("comparing properties with each other, compare them with values")
+            }
+        }
+    }
+
+    /// A range bound, `SKIP` or `LIMIT`
+    fn bound(&mut self, operand: &Operand) -> Result<String, CypherError> {
+        match operand {
+            Operand::Parameter(name) => self.parameter(name, "I64".to_string()),
+            operand => self.value(operand, &FieldType::I64),
+        }
+    }
+
+    /// The variable a comparison filters and the HelixQL check of it on `_`
+    fn comparison(
+        &mut self,
+        left: &Operand,
+        op: Op,
+        right: &Operand,
+    ) -> Result<(String, String), CypherError> {
+        let (subject, op, value) = match (left, right) {
+            (Operand::Property(..) | Operand::Id(_), _) => (left, op, right),
+            (_, Operand::Property(..) | Operand::Id(_)) => (right, op.mirrored(), left),
+            _ => return unsupported("comparisons without a property"),
+        };
+        let (variable, key) = match subject {
+            Operand::Property(variable, key) => (variable, key.as_str()),
+            Operand::Id(variable) => (variable, "id"),
+            _ => unreachable!(),
+        };
+        let Some(binding) = self.bound.get(variable) else {
+            return unsupported(format!("`{}` isn't bound by the MATCH", variable));
+        };
+        let field_type = self.field_type(binding.kind, &binding.label.clone(), key)?;
+        let value = self.value(value, &field_type)?;
+        let property = match key {
+            "id" => "ID".to_string(),
+            key => format!("{{{}}}", key),
+        };
+        Ok((
+            variable.clone(),
+            format!("_::{}::{}({})", property, op.helixql(), value),
+        ))
+    }
+
+    /// The variable a condition filters and the HelixQL check of it, conditions combined
+    /// with `OR` have to filter the same variable
+    fn condition(&mut self, condition: &Condition) -> Result<(String, String), CypherError> {
+        let (conditions, combinator) = match condition {
+            Condition::Compare(left, op, right) => return self.comparison(left, *op, right),
+            Condition::And(conditions) => (conditions, "AND"),
+            Condition::Or(conditions) => (conditions, "OR"),
+        };
+        let mut variable: Option<String> = None;
+        let mut checks = Vec::new();
+        for condition in conditions {
+            let (v, check) = self.condition(condition)?;
+            if variable.as_ref().is_some_and(|variable| *variable != v) {
+                return unsupported(format!(
+                    "{} of conditions on different variables within OR",
+                    combinator
+                ));
+            }
+            variable = Some(v);
+            checks.push(check);
+        }
+        Ok((
+            variable.unwrap_or_default(),
+            format!("{}({})", combinator, checks.join(", ")),
+        ))
+    }
+
+    fn bind(&mut self, name: &str, kind: Kind, label: &str) -> Result<(), CypherError> {
+        if self.bound.contains_key(name) {
+            return unsupported(format!(
+                "`{}` is bound twice, patterns can't return to a variable",
+                name
+            ));
+        }
+        self.bound.insert(
+            name.to_string(),
+            Binding {
+                kind,
+                label: label.to_string(),
+                reference: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// The labels of the nodes an edge type connects
+    fn edge_ends(&self, label: &str) -> Result<(String, String), CypherError> {
+        match self
+            .schema
+            .edge_schemas
+            .iter()
+            .find(|schema| schema.name.1 == label)
+        {
+            Some(schema) => Ok((schema.from.1.clone(), schema.to.1.clone())),
+            None => unsupported(format!("`{}` isn't declared in the schema", label)),
+        }
+    }
+
+    fn match_clause(
+        &mut self,
+        pattern: &Pattern,
+        condition: &Option<Condition>,
+    ) -> Result<(), CypherError> {
+        // name and bind every element of the pattern, so the conditions know their types
+        let start_bound = pattern
+            .start
+            .variable
+            .as_ref()
+            .is_some_and(|v| self.bound.contains_key(v));
+        let start = match &pattern.start.variable {
+            Some(variable) => variable.clone(),
+            None => self.generate_name(Kind::Node),
+        };
+        if !start_bound {
+            let Some(label) = &pattern.start.label else {
+                return unsupported("the first node of a MATCH needs a label");
+            };
+            self.bind(&start, Kind::Node, label)?;
+        }
+        let mut hops = Vec::new();
+        let mut previous = start.clone();
+        for (rel, node) in pattern.hops.iter() {
+            let Some(rel_label) = &rel.label else {
+                return unsupported("relationships need a type");
+            };
+            let (from, to) = self.edge_ends(rel_label)?;
+            let (expected, other_end) = match rel.outgoing {
+                true => (to, from),
+                false => (from, to),
+            };
+            if self.bound[&previous].label != other_end {
+                return unsupported(format!(
+                    "`{}` doesn't connect `{}` nodes that way",
+                    rel_label, self.bound[&previous].label
+                ));
+            }
+            if node.label.as_ref().is_some_and(|label| *label != expected) {
+                return unsupported(format!(
+                    "`{}` only leads to `{}` nodes",
+                    rel_label, expected
+                ));
+            }
+            let edge = match (&rel.variable, rel.properties.is_empty()) {
+                (Some(variable), _) => Some(variable.clone()),
+                (None, false) => Some(self.generate_name(Kind::Edge)),
+                (None, true) => None,
+            };
+            if let Some(edge) = &edge {
+                self.bind(edge, Kind::Edge, rel_label)?;
+            }
+            let name = match &node.variable {
+                Some(variable) => variable.clone(),
+                None => self.generate_name(Kind::Node),
+            };
+            self.bind(&name, Kind::Node, &expected)?;
+            hops.push((rel, rel_label.clone(), edge, node, name.clone()));
+            previous = name;
+        }
+
+        // the checks of each variable, from the inline properties and the WHERE clause
+        let mut checks: HashMap<String, Vec<String>> = HashMap::new();
+        let mut inline = vec![(&start, &pattern.start.properties)];
+        for (rel, _, edge, node, name) in hops.iter() {
+            if let Some(edge) = edge {
+                inline.push((edge, &rel.properties));
+            }
+            inline.push((name, &node.properties));
+        }
+        for (variable, properties) in inline {
+            for (key, value) in properties.iter() {
+                let property = Operand::Property(variable.clone(), key.clone());
+                let (variable, check) = self.comparison(&property, Op::Eq, value)?;
+                checks.entry(variable).or_default().push(check);
+            }
+        }
+        // looking the first node up by its id beats scanning its label
+        let mut lookup = None;
+        let conjuncts = match condition {
+            Some(Condition::And(conditions)) => conditions.iter().collect(),
+            Some(condition) => vec![condition],
+            None => vec![],
+        };
+        for conjunct in conjuncts {
+            match conjunct {
+                Condition::Compare(Operand::Id(variable), Op::Eq, value)
+                | Condition::Compare(value, Op::Eq, Operand::Id(variable))
+                    if *variable == start && !start_bound && lookup.is_none() =>
+                {
+                    lookup = Some(self.value(value, &FieldType::Uuid)?);
+                }
+                conjunct => {
+                    let (variable, check) = self.condition(conjunct)?;
+                    checks.entry(variable).or_default().push(check);
+                }
+            }
+        }
+        let mut filter = |variable: &str| match checks.remove(variable) {
+            None => String::new(),
+            Some(mut checks) if checks.len() == 1 => format!("::WHERE({})", checks.remove(0)),
+            Some(checks) => format!("::WHERE(AND({}))", checks.join(", ")),
+        };
+
+        if start_bound {
+            if !filter(&start).is_empty() {
+                return unsupported(format!(
+                    "`{}` can only be filtered by the MATCH binding it",
+                    start
+                ));
+            }
+        } else {
+            let label = self.bound[&start].label.clone();
+            let source = match &lookup {
+                Some(id) => format!("N<{}>({})", label, id),
+                None => format!("N<{}>", label),
+            };
+            let statement = format!("{} <- {}{}", start, source, filter(&start));
+            self.statements.push(statement);
+        }
+        let mut previous = start.clone();
+        for (rel, label, edge, _, name) in hops.iter() {
+            let (edges, nodes, adjacent) = match rel.outgoing {
+                true => ("OutE", "ToN", "Out"),
+                false => ("InE", "FromN", "In"),
+            };
+            match edge {
+                Some(edge) => {
+                    let statement = format!(
+                        "{} <- {}::{}<{}>{}",
+                        edge,
+                        previous,
+                        edges,
+                        label,
+                        filter(edge)
+                    );
+                    self.statements.push(statement);
+                    let statement = format!("{} <- {}::{}{}", name, edge, nodes, filter(name));
+                    self.statements.push(statement);
+                }
+                None => {
+                    let statement = format!(
+                        "{} <- {}::{}<{}>{}",
+                        name,
+                        previous,
+                        adjacent,
+                        label,
+                        filter(name)
+                    );
+                    self.statements.push(statement);
+                }
+            }
+            previous = name.clone();
+        }
+        if let Some(variable) = checks.into_keys().next() {
+            return unsupported(format!(
+                "`{}` can only be filtered by the MATCH binding it",
+                variable
+            ));
+        }
+        // a node looked up by id is a single node that edges can be created from
+        if let Some(id) = lookup {
+            self.bound.get_mut(&start).unwrap().reference = Some(id);
+        }
+        Ok(())
+    }
+
+    /// Creates the node unless it is bound already, and returns how `AddE` refers to it
+    fn create_node(&mut self, node: &NodePattern) -> Result<String, CypherError> {
+        if let Some(binding) = node.variable.as_ref().and_then(|v| self.bound.get(v)) {
+            if node.label.is_some() || !node.properties.is_empty() {
+                return unsupported(format!(
+                    "`{}` is bound already and can't be created again",
+                    node.variable.as_ref().unwrap()
+                ));
+            }
+            return match &binding.reference {
+                Some(reference) => Ok(reference.clone()),
+                None => unsupported(format!(
+                    "relationships can only be created from nodes created by the query or \
+                     matched by id, `{}` may be several nodes",
+                    node.variable.as_ref().unwrap()
+                )),
+            };
+        }
+        let Some(label) = &node.label else {
+            return unsupported("created nodes need a label");
+        };
+        let name = match &node.variable {
+            Some(variable) => variable.clone(),
+            None => self.generate_name(Kind::Node),
+        };
+        let fields = self.fields(Kind::Node, label, &node.properties)?;
+        self.statements
+            .push(format!("{} <- AddN<{}>{}", name, label, fields));
+        self.bind(&name, Kind::Node, label)?;
+        self.bound.get_mut(&name).unwrap().reference = Some(name.clone());
+        Ok(name)
+    }
+
+    /// The fields of a created item, as the argument of `AddN` or `AddE`
+    fn fields(
+        &mut self,
+        kind: Kind,
+        label: &str,
+        properties: &[(String, Operand)],
+    ) -> Result<String, CypherError> {
+        if properties.is_empty() {
+            return Ok(String::new());
+        }
+        let mut fields = Vec::new();
+        for (key, value) in properties {
+            let field_type = self.field_type(kind, label, key)?;
+            fields.push(format!("{}: {}", key, self.value(value, &field_type)?));
+        }
+        Ok(format!("({{{}}})", fields.join(", ")))
+    }
+
+    fn create(&mut self, pattern: &Pattern) -> Result<(), CypherError> {
+        let mut previous = self.create_node(&pattern.start)?;
+        for (rel, node) in pattern.hops.iter() {
+            let next = self.create_node(node)?;
+            let Some(label) = &rel.label else {
+                return unsupported("created relationships need a type");
+            };
+            let (from, to) = match rel.outgoing {
+                true => (&previous, &next),
+                false => (&next, &previous),
+            };
+            let fields = self.fields(Kind::Edge, label, &rel.properties)?;
+            let add = format!("AddE<{}>{}::From({})::To({})", label, fields, from, to);
+            match &rel.variable {
+                Some(variable) => {
+                    self.statements.push(format!("{} <- {}", variable, add));
+                    self.bind(variable, Kind::Edge, label)?;
+                }
+                None => self.statements.push(add),
+            }
+            previous = next;
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, variables: &[String]) -> Result<(), CypherError> {
+        for variable in variables {
+            if !self.bound.contains_key(variable) {
+                return unsupported(format!("`{}` isn't bound", variable));
+            }
+            self.statements.push(format!("DROP {}", variable));
+        }
+        Ok(())
+    }
+
+    /// The returned values, with the statements computing them
+    fn return_clause(&mut self, ret: &Return) -> Result<String, CypherError> {
+        // the properties returned of each variable, remapped into one object per item
+        let mut returned: Vec<(String, Vec<String>)> = Vec::new();
+        let mut counts = Vec::new();
+        for (expr, alias) in ret.items.iter() {
+            let variable = match expr {
+                ReturnExpr::Variable(v) | ReturnExpr::Property(v, _) | ReturnExpr::Count(v) => v,
+            };
+            let Some(binding) = self.bound.get(variable) else {
+                return unsupported(format!("`{}` isn't bound", variable));
+            };
+            let (kind, label) = (binding.kind, binding.label.clone());
+            match expr {
+                ReturnExpr::Count(_) => {
+                    let name = match alias {
+                        Some(alias) => alias.clone(),
+                        None => format!("{}_count", variable),
+                    };
+                    self.statements
+                        .push(format!("{} <- {}::COUNT", name, variable));
+                    counts.push(name);
+                }
+                ReturnExpr::Variable(_) if alias.is_some() => {
+                    return unsupported("aliasing a whole variable, alias its properties instead")
+                }
+                _ => {
+                    let field = match (expr, alias) {
+                        (ReturnExpr::Property(_, key), Some(alias)) => {
+                            self.field_type(kind, &label, key)?;
+                            Some(format!("{}: {}", alias, key))
+                        }
+                        (ReturnExpr::Property(_, key), None) => {
+                            self.field_type(kind, &label, key)?;
+                            Some(key.clone())
+                        }
+                        _ => None,
+                    };
+                    match returned.iter_mut().find(|(v, _)| v == variable) {
+                        Some((_, fields)) if fields.is_empty() || field.is_none() => {
+                            return unsupported(format!(
+                                "returning `{}` both whole and by its properties",
+                                variable
+                            ))
+                        }
+                        Some((_, fields)) => fields.extend(field),
+                        None => returned.push((variable.clone(), field.into_iter().collect())),
+                    }
+                }
+            }
+        }
+
+        let mut steps = String::new();
+        if let Some((variable, key, descending)) = &ret.order_by {
+            if returned.len() != 1 || returned[0].0 != *variable {
+                return unsupported("ORDER BY a property of the only variable returned");
+            }
+            let binding = &self.bound[variable];
+            self.field_type(binding.kind, &binding.label.clone(), key)?;
+            let order = if *descending { "Desc" } else { "Asc" };
+            steps.push_str(&format!("::OrderBy({}, {})", key, order));
+        }
+        match (&ret.skip, &ret.limit) {
+            (Some(skip), Some(limit)) => {
+                let (skip, limit) = (self.bound(skip)?, self.bound(limit)?);
+                steps.push_str(&format!("::Range({}, {})", skip, limit));
+            }
+            (None, Some(limit)) => {
+                steps.push_str(&format!("::RANGE(0, {})", self.bound(limit)?));
+            }
+            (Some(_), None) => return unsupported("SKIP without LIMIT"),
+            (None, None) => {}
+        }
+
+        let mut values = Vec::new();
+        for (variable, fields) in returned {
+            let remapping = match fields.is_empty() {
+                true => String::new(),
+                false => format!("::{{{}}}", fields.join(", ")),
+            };
+            values.push(match (steps.is_empty(), remapping.is_empty()) {
+                (true, true) => variable,
+                // steps can't follow the returned variable directly, so the result of
+                // them is named after it
+                _ => {
+                    let name = format!("{}_result", variable);
+                    self.statements
+                        .push(format!("{} <- {}{}{}", name, variable, steps, remapping));
+                    name
+                }
+            });
+        }
+        values.extend(counts);
+        Ok(values.join(", "))
+    }
+}
+
+/// The HelixQL type of a parameter of a field of the type
+fn helixql_type(field_type: &FieldType) -> Result<String, CypherError> {
+    match field_type {
+        FieldType::Uuid => Ok("ID".to_string()),
+        FieldType::Array(inner) => Ok(format!("[{}]", helixql_type(inner)?)),
+        FieldType::Identifier(_) | FieldType::Object(_) => {
+            unsupported(format!("parameters of type {}", field_type))
+        }
+        field_type => Ok(field_type.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helixc::parser::helix_parser::write_to_temp_file;
+
+    const SCHEMA: &str = r#"
+        N::User {
+            name: String,
+            age: U32
+        }
+        N::Post {
+            title: String
+        }
+        E::Follows {
+            From: User,
+            To: User,
+            Properties: {
+                since: String
+            }
+        }
+        E::Wrote {
+            From: User,
+            To: Post,
+        }
+    "#;
+
+    fn schema() -> Source {
+        HelixParser::parse_source(&write_to_temp_file(vec![SCHEMA])).unwrap()
+    }
+
+    #[test]
+    fn translates_match_where_return() {
+        let query = translate(
+            "olderFollowers",
+            "MATCH (u:User {name: $name})<-[:Follows]-(f:User) WHERE f.age >= 30 RETURN f.name AS follower LIMIT 10",
+            &schema(),
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "QUERY olderFollowers(name: String) =>\n    \
+             u <- N<User>::WHERE(_::{name}::EQ(name))\n    \
+             f <- u::In<Follows>::WHERE(_::{age}::GTE(30))\n    \
+             f_result <- f::RANGE(0, 10)::{follower: name}\n    \
+             RETURN f_result\n"
+        );
+        assert!(compile("olderFollowers", "MATCH (u:User) RETURN u", &schema()).is_ok());
+    }
+
+    #[test]
+    fn translates_create_from_matched_id() {
+        let query = translate(
+            "writePost",
+            "MATCH (u:User) WHERE id(u) = $user CREATE (u)-[:Wrote]->(p:Post {title: $title}) RETURN p",
+            &schema(),
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "QUERY writePost(user: ID, title: String) =>\n    \
+             u <- N<User>(user)\n    \
+             p <- AddN<Post>({title: title})\n    \
+             AddE<Wrote>::From(user)::To(p)\n    \
+             RETURN p\n"
+        );
+        compile(
+            "writePost",
+            "MATCH (u:User) WHERE id(u) = $user CREATE (u)-[:Wrote]->(p:Post {title: $title}) RETURN p",
+            &schema(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_what_helixql_cannot_express() {
+        let schema = schema();
+        let unsupported = |cypher: &str| {
+            matches!(
+                translate("q", cypher, &schema),
+                Err(CypherError::Unsupported(_))
+            )
+        };
+        // conditions on different variables can't be combined with OR
+        assert!(unsupported(
+            "MATCH (u:User)-[:Follows]->(f:User) WHERE u.age > 1 OR f.age > 1 RETURN f"
+        ));
+        // Follows only connects users
+        assert!(unsupported("MATCH (u:User)-[:Follows]->(p:Post) RETURN p"));
+        // the matched users may be many
+        assert!(unsupported(
+            "MATCH (u:User) CREATE (u)-[:Follows]->(f:User)"
+        ));
+        assert!(unsupported("MATCH (u:User) RETURN u.email"));
+        assert!(matches!(
+            translate("q", "MATCH (u:User RETURN u", &schema),
+            Err(CypherError::Syntax(_))
+        ));
+    }
+}
@@ -0,0 +1,564 @@
+//! Property tests feeding the parser and the analyzer random schemas and queries.
+//!
+//! Programs are generated from fragments of the grammar, picking schema names, fields,
+//! steps and literals that may or may not fit together, so most of them parse but fail
+//! to analyze in all sorts of ways. A second strategy strings tokens together at random
+//! to reach the parser's handling of inputs that almost parse. Neither the parser nor
+//! the analyzer may panic on any of them, and every diagnostic must point inside the
+//! file it was reported for.
+//!
+//! Generated programs are built from a list of choices, which proptest shrinks to the
+//! smallest program failing. Run more cases with `PROPTEST_CASES`.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use proptest::prelude::*;
+
+use super::{
+    analyzer::analyzer::{analyze, Diagnostic},
+    parser::helix_parser::{write_to_temp_file, HelixParser},
+};
+
+const TYPES: &[&str] = &["User", "Post", "Doc", "Follows", "Status", "Missing"];
+const FIELDS: &[&str] = &["name", "age", "email", "text", "created", "tags", "nope"];
+const VARS: &[&str] = &["u", "p", "e", "v", "docs", "id", "name", "limit", "x"];
+const NAMED_TYPES: &[&str] = &[
+    "String", "Boolean", "F32", "F64", "I8", "I32", "I64", "U8", "U32", "U64", "U128", "Date",
+    "DateTime", "ID",
+];
+const LITERALS: &[&str] = &[
+    "1", "0", "42", "1.5", "0.0", "true", "false", "\"a\"", "\"\"", "NONE", "NOW", "UUID", "ULID",
+];
+
+/// Makes the choices of a generated program from a list of bytes, picking the first
+/// option of every choice once they run out
+struct Gen<'a> {
+    choices: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Gen<'a> {
+    fn new(choices: &'a [u8]) -> Self {
+        Self { choices, pos: 0 }
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        let choice = self.choices.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        choice as usize % n
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick(&mut self, options: &[&'static str]) -> &'static str {
+        options[self.below(options.len())]
+    }
+
+    fn list(&mut self, max: usize, mut item: impl FnMut(&mut Self) -> String) -> Vec<String> {
+        let len = self.below(max + 1);
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    fn program(&mut self) -> String {
+        let mut program = self.list(4, Self::schema).join("\n");
+        program.push('\n');
+        program.push_str(&self.list(3, Self::query).join("\n"));
+        program
+    }
+
+    fn schema(&mut self) -> String {
+        let name = self.pick(TYPES);
+        match self.below(5) {
+            0 | 1 => format!("N::{} {{ {} }}", name, self.fields(2)),
+            2 => format!(
+                "E::{} {{ From: {}, To: {}, Properties: {{ {} }} }}",
+                name,
+                self.pick(TYPES),
+                self.pick(TYPES),
+                self.fields(2)
+            ),
+            3 => format!("V::{} {{ {} }}", name, self.fields(2)),
+            _ => format!(
+                "ENUM {} {{ {} }}",
+                name,
+                self.list(3, |g| g.pick(TYPES).into()).join(", ")
+            ),
+        }
+    }
+
+    fn fields(&mut self, depth: usize) -> String {
+        self.list(3, |g| g.field(depth)).join(", ")
+    }
+
+    fn field(&mut self, depth: usize) -> String {
+        let prefix = self.pick(&["", "", "INDEX ", "UNIQUE INDEX "]);
+        let mut field = format!(
+            "{}{}: {}",
+            prefix,
+            self.pick(FIELDS),
+            self.field_type(depth)
+        );
+        if self.chance(25) {
+            field.push_str(&format!(" DEFAULT {}", self.pick(LITERALS)));
+        }
+        if self.chance(10) {
+            field.push_str(" @sensitive(admin)");
+        }
+        field
+    }
+
+    fn field_type(&mut self, depth: usize) -> String {
+        match self.below(6) {
+            0 if depth > 0 => format!("[{}]", self.field_type(depth - 1)),
+            1 if depth > 0 => format!("{{ {} }}", self.fields(depth - 1)),
+            2 => self.pick(TYPES).to_string(),
+            _ => self.pick(NAMED_TYPES).to_string(),
+        }
+    }
+
+    fn query(&mut self) -> String {
+        let params = self.list(3, |g| {
+            let mut param = format!("{}: {}", g.pick(VARS), g.field_type(1));
+            if g.chance(20) {
+                param.push('?');
+            }
+            if g.chance(15) {
+                param.push_str(&format!(
+                    " = {}",
+                    g.pick(&["1", "-2", "1.5", "true", "\"a\""])
+                ));
+            }
+            param
+        });
+        let body = self.list(4, |g| g.statement(2)).join("\n    ");
+        let returns = self.list(2, |g| g.value(2));
+        let returns = match returns.is_empty() {
+            true => self.pick(VARS).to_string(),
+            false => returns.join(", "),
+        };
+        format!(
+            "QUERY q{}({}) =>\n    {}\n    RETURN {}",
+            self.below(4),
+            params.join(", "),
+            body,
+            returns
+        )
+    }
+
+    fn statement(&mut self, depth: usize) -> String {
+        match self.below(8) {
+            0 => format!("DROP {}", self.traversal(depth)),
+            1 if depth > 0 => format!(
+                "FOR {} IN {} {{ {} }}",
+                self.pick(&["x", "{a, b}", "p.name"]),
+                self.pick(VARS),
+                self.list(2, |g| g.statement(depth - 1)).join(" ")
+            ),
+            2 => format!(
+                "{} <- IF {} THEN {} ELSE {}",
+                self.pick(VARS),
+                self.boolean(depth),
+                self.pick(LITERALS),
+                self.pick(LITERALS)
+            ),
+            3 => self.add(depth),
+            _ => format!("{} <- {}", self.pick(VARS), self.value(depth)),
+        }
+    }
+
+    /// Anything a variable can be assigned or a query can return
+    fn value(&mut self, depth: usize) -> String {
+        match self.below(10) {
+            0 => self.pick(LITERALS).to_string(),
+            1 => self.pick(VARS).to_string(),
+            2 => self.add(depth),
+            3 => format!(
+                "SearchV<{}>({}, {})",
+                self.pick(TYPES),
+                self.pick(&["[1.0, 2.0]", "v", "name"]),
+                self.pick(&["10", "limit", "name"])
+            ),
+            4 => format!(
+                "SearchBM25<{}>({}, {})",
+                self.pick(TYPES),
+                self.pick(&["\"q\"", "name"]),
+                self.pick(&["5", "limit"])
+            ),
+            5 => format!("{}{}", self.pick(VARS), self.steps(depth)),
+            _ => self.traversal(depth),
+        }
+    }
+
+    fn add(&mut self, depth: usize) -> String {
+        match self.below(4) {
+            0 => format!("AddN<{}>({})", self.pick(TYPES), self.create(depth)),
+            1 => format!(
+                "AddE<{}>({})::From({})::To({})",
+                self.pick(TYPES),
+                self.create(depth),
+                self.pick(VARS),
+                self.pick(VARS)
+            ),
+            2 => format!(
+                "AddV<{}>({}, {})",
+                self.pick(TYPES),
+                self.pick(&["[1.0]", "v", "docs"]),
+                self.create(depth)
+            ),
+            _ => format!("BatchAddV<{}>({})", self.pick(TYPES), self.pick(VARS)),
+        }
+    }
+
+    fn create(&mut self, depth: usize) -> String {
+        let fields = self.list(3, |g| {
+            let value = match g.below(4) {
+                0 if depth > 0 => format!("_{}", g.steps(depth - 1)),
+                1 => format!("[{}]", g.list(2, |g| g.pick(LITERALS).into()).join(", ")),
+                2 => g.pick(VARS).to_string(),
+                _ => g.pick(LITERALS).to_string(),
+            };
+            format!("{}: {}", g.pick(FIELDS), value)
+        });
+        match fields.is_empty() {
+            true => String::new(),
+            false => format!("{{{}}}", fields.join(", ")),
+        }
+    }
+
+    fn traversal(&mut self, depth: usize) -> String {
+        let start = self.pick(&["N", "E", "V", "N", "N"]);
+        let types = match self.chance(80) {
+            true => format!("<{}>", self.pick(TYPES)),
+            false => String::new(),
+        };
+        let args = match self.below(4) {
+            0 => format!("({})", self.pick(VARS)),
+            1 => format!(
+                "({{{}: {}}})",
+                self.pick(FIELDS),
+                self.pick(&["name", "\"a\"", "1"])
+            ),
+            _ => String::new(),
+        };
+        format!("{}{}{}{}", start, types, args, self.steps(depth))
+    }
+
+    /// Steps following a start, possibly ending in a comparison or an update
+    fn steps(&mut self, depth: usize) -> String {
+        let mut steps: String = self.list(3, |g| format!("::{}", g.step(depth))).concat();
+        match self.below(6) {
+            0 => steps.push_str(&format!("::{}", self.comparison(depth))),
+            1 => steps.push_str(&format!(
+                "::UPDATE({{{}: {}}})",
+                self.pick(FIELDS),
+                self.pick(LITERALS)
+            )),
+            _ => {}
+        }
+        steps
+    }
+
+    fn step(&mut self, depth: usize) -> String {
+        let edge = self.pick(TYPES);
+        match self.below(16) {
+            0 => format!("Out<{}>", edge),
+            1 => format!("In<{}>", edge),
+            2 => format!("OutE<{}>", edge),
+            3 => format!("InE<{}>", edge),
+            4 => self.pick(&["FromN", "ToN", "COUNT", "ID"]).to_string(),
+            5 => format!("OutCount<{}>", edge),
+            6 => format!("WHERE({})", self.boolean(depth)),
+            7 => format!(
+                "{}({}, {})",
+                self.pick(&["RANGE", "Range"]),
+                self.pick(&["0", "1", "limit", "1.5"]),
+                self.pick(&["10", "limit", "\"a\""])
+            ),
+            8 => format!(
+                "OrderBy({}{})",
+                self.pick(FIELDS),
+                self.pick(&["", ", Asc", ", Desc"])
+            ),
+            9 => format!("!{{{}}}", self.pick(FIELDS)),
+            10 => format!("|{}|{}", self.pick(VARS), self.object(depth)),
+            11 => format!("ShortestPath<{}>::To({})", edge, self.pick(VARS)),
+            12 => format!(
+                "AsOf({})",
+                self.pick(&["\"2024-01-01\"", "1", "name", "true"])
+            ),
+            13 => format!("AddE<{}>::To({})", edge, self.pick(VARS)),
+            _ => self.object(depth),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> String {
+        let mut fields = self.list(3, |g| g.mapping(depth));
+        if self.chance(20) {
+            fields.push("..".to_string());
+        }
+        format!("{{{}}}", fields.join(", "))
+    }
+
+    fn mapping(&mut self, depth: usize) -> String {
+        let field = self.pick(FIELDS);
+        match self.below(9) {
+            0 => field.to_string(),
+            1 => format!("{}.{}[0]", field, self.pick(FIELDS)),
+            2 => "_score".to_string(),
+            3 if depth > 0 => format!("{}: _{}", field, self.steps(depth - 1)),
+            4 if depth > 0 => format!("{}: Optional(_{})", field, self.steps(depth - 1)),
+            5 => format!(
+                "{}: Coalesce(_::{{{}}}, {})",
+                field,
+                self.pick(FIELDS),
+                self.pick(LITERALS)
+            ),
+            6 => format!("{}: {}", field, self.expression()),
+            7 if depth > 0 => format!("{}: {}", field, self.object(depth - 1)),
+            _ => format!("{}: {}", field, self.pick(FIELDS)),
+        }
+    }
+
+    fn boolean(&mut self, depth: usize) -> String {
+        match self.below(7) {
+            0 if depth > 0 => format!("EXISTS(_{})", self.steps(depth - 1)),
+            1 if depth > 0 => format!("NOT({})", self.boolean(depth - 1)),
+            2 if depth > 0 => format!(
+                "{}({})",
+                self.pick(&["AND", "OR"]),
+                self.list(2, |g| g.boolean(depth - 1)).join(", ")
+            ),
+            3 => self.pick(&["true", "name", "x"]).to_string(),
+            4 => format!("{}::{}", self.expression(), self.comparison(0)),
+            _ => format!("_::{{{}}}::{}", self.pick(FIELDS), self.comparison(depth)),
+        }
+    }
+
+    fn comparison(&mut self, depth: usize) -> String {
+        let operand = match self.below(4) {
+            0 if depth > 0 => format!("_{}", self.steps(depth - 1)),
+            1 => self.expression(),
+            2 => self.pick(VARS).to_string(),
+            _ => self.pick(LITERALS).to_string(),
+        };
+        format!(
+            "{}({})",
+            self.pick(&["GT", "GTE", "LT", "LTE", "EQ", "NEQ"]),
+            operand
+        )
+    }
+
+    fn expression(&mut self) -> String {
+        let operand = format!("_::{{{}}}", self.pick(FIELDS));
+        match self.below(3) {
+            0 => format!(
+                "{}({})",
+                self.pick(&["LOWER", "UPPER", "LEN", "ROUND"]),
+                operand
+            ),
+            1 => format!(
+                "({} {} {})",
+                operand,
+                self.pick(&["+", "-", "*", "/"]),
+                self.pick(LITERALS)
+            ),
+            _ => format!("{} + {}", operand, self.pick(&["1", "\"a\"", "limit"])),
+        }
+    }
+}
+
+/// Tokens strung together at random
+const TOKENS: &[&str] = &[
+    "N::",
+    "E::",
+    "V::",
+    "ENUM",
+    "QUERY",
+    "RETURN",
+    "=>",
+    "<-",
+    "::",
+    "{",
+    "}",
+    "(",
+    ")",
+    "<",
+    ">",
+    "[",
+    "]",
+    ",",
+    ":",
+    "?",
+    "=",
+    "_",
+    "..",
+    "|",
+    "!",
+    "N",
+    "E",
+    "V",
+    "User",
+    "Follows",
+    "name",
+    "u",
+    "From:",
+    "To:",
+    "Properties",
+    "String",
+    "I32",
+    "ID",
+    "INDEX",
+    "UNIQUE",
+    "DEFAULT",
+    "NOW",
+    "@sensitive",
+    "AddN",
+    "AddE",
+    "AddV",
+    "DROP",
+    "FOR",
+    "IN",
+    "IF",
+    "THEN",
+    "ELSE",
+    "Out",
+    "In",
+    "OutE",
+    "WHERE",
+    "EXISTS",
+    "NOT",
+    "AND",
+    "OR",
+    "EQ",
+    "GT",
+    "COUNT",
+    "RANGE",
+    "OrderBy",
+    "UPDATE",
+    "SearchV",
+    "Coalesce",
+    "Optional",
+    "LOWER",
+    "+",
+    "*",
+    "1",
+    "1.5",
+    "-",
+    "true",
+    "\"a\"",
+    "NONE",
+    "import",
+    "NAMESPACE",
+];
+
+fn parse_and_analyze(program: &str) -> Result<(), String> {
+    let input = write_to_temp_file(vec![program]);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        HelixParser::parse_source(&input)
+            .ok()
+            .map(|source| analyze(&source).0)
+    }));
+    let diagnostics = match result {
+        Ok(diagnostics) => diagnostics.unwrap_or_default(),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            return Err(format!("panicked: {}", message));
+        }
+    };
+    for diagnostic in &diagnostics {
+        check_location(diagnostic, program, &input.files[0].name)?;
+    }
+    Ok(())
+}
+
+/// Checks the diagnostic points at a range of the file it was reported for
+fn check_location(diagnostic: &Diagnostic, program: &str, filepath: &str) -> Result<(), String> {
+    let loc = &diagnostic.location;
+    let invalid = |reason: &str| {
+        Err(format!(
+            "{} for `{}` at {:?}",
+            reason, diagnostic.message, loc
+        ))
+    };
+    if let Some(path) = diagnostic.filepath.as_ref().or(loc.filepath.as_ref()) {
+        if path != filepath {
+            return invalid("unknown file");
+        }
+    }
+    let lines = program.split('\n').collect::<Vec<_>>();
+    // columns count from 1 and are one past the character they point at, which may be
+    // the end of the line
+    let in_file = |line: usize, column: usize| {
+        line >= 1
+            && line <= lines.len()
+            && column >= 1
+            && column <= lines[line - 1].chars().count() + 2
+    };
+    if !in_file(loc.start.line, loc.start.column) || !in_file(loc.end.line, loc.end.column) {
+        return invalid("location outside the file");
+    }
+    if (loc.start.line, loc.start.column) > (loc.end.line, loc.end.column) {
+        return invalid("location ends before it starts");
+    }
+    Ok(())
+}
+
+/// Programs the generators found panicking, kept as regressions
+#[test]
+fn found_programs_are_handled() {
+    let programs = [
+        "N::User",
+        "N::User { age: I32 DEFAULT 1.5 }",
+        "N::User { age: U8 DEFAULT 300 }",
+        "N::User { name: String DEFAULT NONE }",
+        "N::User { tags: { INDEX name: String } }",
+        "E::Follows { From: User, To: Missing }\nN::User\nQUERY q() =>\n    u <- N<User>::Out<Follows>\n    RETURN u",
+        "N::User\nQUERY q() =>\n    u <- _::Out<Follows>\n    RETURN u",
+        "N::User\nQUERY q() =>\n    u <- 1\n    RETURN u",
+        "N::User\nQUERY q() =>\n    RETURN x",
+        "N::User\nQUERY q() =>\n    u <- AddN<Missing>({nope: 1})\n    RETURN u",
+        "N::User { name: String }\nQUERY q() =>\n    u <- AddN<User>({nope: 1})\n    RETURN u",
+        "N::User { name: String }\nQUERY q() =>\n    u <- N<User>::UPDATE({name: true})\n    RETURN u",
+        "N::User\nQUERY q() =>\n    u <- N<User>::GT(1)\n    RETURN u",
+        "N::User\nQUERY q() =>\n    u <- N<User>::WHERE(x)\n    RETURN u",
+        "N::User\nQUERY q() =>\n    u <- N<User>::WHERE(AND(x, true))\n    RETURN u",
+        "N::User\nQUERY q() =>\n    u <- N<User>::WHERE(EXISTS(x))\n    RETURN u",
+        "N::User\nQUERY q() =>\n    u <- N<User>::COUNT::{}\n    RETURN u",
+        "N::User\nQUERY q(limit: I32) =>\n    u <- SearchBM25<User>(\"q\", limit)\n    RETURN u",
+        "QUERY q(p: [User]) =>\n    FOR p.name IN p { }\n    RETURN p",
+    ];
+    for program in programs {
+        if let Err(e) = parse_and_analyze(program) {
+            panic!("{}\n--- program ---\n{}", e, program);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn generated_programs_are_handled(choices in prop::collection::vec(any::<u8>(), 0..400)) {
+        let program = Gen::new(&choices).program();
+        if let Err(e) = parse_and_analyze(&program) {
+            return Err(TestCaseError::fail(format!("{}\n--- program ---\n{}", e, program)));
+        }
+    }
+
+    #[test]
+    fn token_soup_is_handled(tokens in prop::collection::vec(prop::sample::select(TOKENS), 0..60)) {
+        let program = tokens.join(" ");
+        if let Err(e) = parse_and_analyze(&program) {
+            return Err(TestCaseError::fail(format!("{}\n--- program ---\n{}", e, program)));
+        }
+    }
+}
@@ -27,6 +27,21 @@ impl Display for BoolOp {
         write!(f, "map_or(false, |v| *v{})", s)
     }
 }
+impl BoolOp {
+    /// The value the operation compares to
+    pub fn value(&self) -> &GeneratedValue {
+        match self {
+            BoolOp::Gt(gt) => &gt.value,
+            BoolOp::Gte(gte) => &gte.value,
+            BoolOp::Lt(lt) => &lt.value,
+            BoolOp::Lte(lte) => &lte.value,
+            BoolOp::Eq(eq) => &eq.value,
+            BoolOp::Neq(neq) => &neq.value,
+            BoolOp::Contains(contains) => &contains.value,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Gt {
     pub value: GeneratedValue,
@@ -0,0 +1,149 @@
+use core::fmt;
+use std::fmt::Display;
+
+use crate::helixc::parser::helix_parser::{ArithmeticOp, ExpressionFunction};
+
+use super::{
+    generator_types::BoExp,
+    utils::{GenRef, GeneratedValue},
+};
+
+/// An arithmetic or string expression, generated as code computing a `Value` with the
+/// functions of `protocol::expression`
+#[derive(Clone)]
+pub enum GeneratedExpression {
+    /// A property of the element the expression is evaluated for, empty if it isn't set or
+    /// is redacted for the request
+    Property {
+        variable: GenRef<String>,
+        property: GenRef<String>,
+    },
+    /// A literal or a parameter
+    Value(GeneratedValue),
+    Arithmetic {
+        op: ArithmeticOp,
+        lhs: Box<GeneratedExpression>,
+        rhs: Box<GeneratedExpression>,
+    },
+    Function {
+        function: ExpressionFunction,
+        arg: Box<GeneratedExpression>,
+    },
+    /// A condition as a boolean value, e.g. `EXISTS`, evaluated for the element if there
+    /// is one like the condition of an `IF`
+    Condition {
+        element: Option<GenRef<String>>,
+        condition: Box<BoExp>,
+    },
+    /// `IF`, with the condition evaluated for the element if there is one. Conditions
+    /// are generated like `WHERE`s, so the element is bound to `val` for them
+    Conditional {
+        element: Option<GenRef<String>>,
+        condition: Box<BoExp>,
+        then: Box<GeneratedExpression>,
+        otherwise: Box<GeneratedExpression>,
+    },
+}
+impl Display for GeneratedExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratedExpression::Property { variable, property } => write!(
+                f,
+                "expression::property(&{}, {})",
+                variable, property
+            ),
+            GeneratedExpression::Value(value) => write!(f, "Value::from({})", value),
+            GeneratedExpression::Arithmetic { op, lhs, rhs } => {
+                let op = match op {
+                    ArithmeticOp::Add => "add",
+                    ArithmeticOp::Sub => "sub",
+                    ArithmeticOp::Mul => "mul",
+                    ArithmeticOp::Div => "div",
+                };
+                write!(f, "expression::{}({}, {})", op, lhs, rhs)
+            }
+            GeneratedExpression::Function { function, arg } => {
+                let function = match function {
+                    ExpressionFunction::Lower => "lower",
+                    ExpressionFunction::Upper => "upper",
+                    ExpressionFunction::Len => "len",
+                    ExpressionFunction::Round => "round",
+                };
+                write!(f, "expression::{}({})", function, arg)
+            }
+            GeneratedExpression::Condition { element, condition } => match element {
+                Some(element) => write!(
+                    f,
+                    "{{ let val = &{}; Value::from({}) }}",
+                    element, condition
+                ),
+                None => write!(f, "Value::from({})", condition),
+            },
+            GeneratedExpression::Conditional {
+                element,
+                condition,
+                then,
+                otherwise,
+            } => match element {
+                Some(element) => write!(
+                    f,
+                    "{{ let val = &{}; if {} {{ {} }} else {{ {} }} }}",
+                    element, condition, then, otherwise
+                ),
+                None => write!(
+                    f,
+                    "if {} {{ {} }} else {{ {} }}",
+                    condition, then, otherwise
+                ),
+            },
+        }
+    }
+}
+
+impl GeneratedExpression {
+    /// The properties the expression reads
+    pub fn properties(&self) -> Vec<&GenRef<String>> {
+        match self {
+            GeneratedExpression::Property { property, .. } => vec![property],
+            GeneratedExpression::Value(_) | GeneratedExpression::Condition { .. } => vec![],
+            GeneratedExpression::Arithmetic { lhs, rhs, .. } => {
+                let mut properties = lhs.properties();
+                properties.extend(rhs.properties());
+                properties
+            }
+            GeneratedExpression::Function { arg, .. } => arg.properties(),
+            GeneratedExpression::Conditional {
+                then, otherwise, ..
+            } => {
+                let mut properties = then.properties();
+                properties.extend(otherwise.properties());
+                properties
+            }
+        }
+    }
+
+    /// The conditions of the `IF`s of the expression
+    pub fn conditions(&self) -> Vec<&BoExp> {
+        match self {
+            GeneratedExpression::Property { .. } | GeneratedExpression::Value(_) => vec![],
+            GeneratedExpression::Condition { condition, .. } => vec![condition.as_ref()],
+            GeneratedExpression::Arithmetic { lhs, rhs, .. } => {
+                let mut conditions = lhs.conditions();
+                conditions.extend(rhs.conditions());
+                conditions
+            }
+            GeneratedExpression::Function { arg, .. } => arg.conditions(),
+            GeneratedExpression::Conditional {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                let mut conditions = vec![condition.as_ref()];
+                conditions.extend(then.conditions());
+                conditions.extend(otherwise.conditions());
+                conditions
+            }
+        }
+    }
+}
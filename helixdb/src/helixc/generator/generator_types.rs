@@ -8,15 +8,20 @@ use std::{
 use crate::{helixc::parser::helix_parser::FieldPrefix, protocol::value::Value};
 
 use super::{
-    traversal_steps::{ShouldCollect, Traversal},
+    bool_op::BoolOp,
+    expression::GeneratedExpression,
+    traversal_steps::{Step, Traversal, TraversalType},
     tsdisplay::ToTypeScript,
-    utils::{write_headers, write_properties, GenRef, GeneratedType, GeneratedValue},
+    utils::{
+        ts_key, write_headers, write_ts_headers, GenRef, GeneratedType, GeneratedValue, RustType,
+    },
 };
 
 pub struct Source {
     pub nodes: Vec<NodeSchema>,
     pub edges: Vec<EdgeSchema>,
     pub vectors: Vec<VectorSchema>,
+    pub enums: Vec<EnumSchema>,
     pub queries: Vec<Query>,
     pub src: String,
 }
@@ -26,6 +31,7 @@ impl Default for Source {
             nodes: vec![],
             edges: vec![],
             vectors: vec![],
+            enums: vec![],
             queries: vec![],
             src: "".to_string(),
         }
@@ -34,6 +40,9 @@ impl Default for Source {
 impl Display for Source {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}\n", write_headers())?;
+        for enum_schema in &self.enums {
+            writeln!(f, "{}", enum_schema)?;
+        }
         write!(
             f,
             "{}",
@@ -64,6 +73,47 @@ impl Display for Source {
                 .join("\n")
         )?;
         write!(f, "\n")?;
+        // the enum fields, stored as the index of their variant
+        let enum_fields = self
+            .nodes
+            .iter()
+            .map(|n| (&n.name, &n.properties))
+            .chain(self.edges.iter().map(|e| (&e.name, &e.properties)));
+        for (label, properties) in enum_fields {
+            for property in properties {
+                if let GeneratedType::Enum(name) = &property.field_type {
+                    writeln!(
+                        f,
+                        "inventory::submit! {{ EnumField {{ label: \"{}\", field: \"{}\", variants: {}::VARIANTS }} }}",
+                        label, property.name, name
+                    )?;
+                }
+            }
+        }
+        // the sensitive fields, left out of responses to the roles not listed
+        let sensitive_fields = self
+            .nodes
+            .iter()
+            .map(|n| (&n.name, &n.properties))
+            .chain(self.edges.iter().map(|e| (&e.name, &e.properties)))
+            .chain(self.vectors.iter().map(|v| (&v.name, &v.properties)));
+        for (label, properties) in sensitive_fields {
+            for property in properties {
+                if let Some(roles) = &property.sensitive {
+                    writeln!(
+                        f,
+                        "inventory::submit! {{ SensitiveField {{ label: \"{}\", field: \"{}\", roles: &[{}] }} }}",
+                        label,
+                        property.name,
+                        roles
+                            .iter()
+                            .map(|r| format!("\"{}\"", r))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )?;
+                }
+            }
+        }
         write!(
             f,
             "{}",
@@ -76,6 +126,28 @@ impl Display for Source {
     }
 }
 
+impl ToTypeScript for Source {
+    fn to_typescript(&self) -> String {
+        let mut result = write_ts_headers();
+        for enum_schema in &self.enums {
+            result.push_str(&format!("\n{}", enum_schema.to_typescript()));
+        }
+        for node in &self.nodes {
+            result.push_str(&format!("\n{}", node.to_typescript()));
+        }
+        for edge in &self.edges {
+            result.push_str(&format!("\n{}", edge.to_typescript()));
+        }
+        for vector in &self.vectors {
+            result.push_str(&format!("\n{}", vector.to_typescript()));
+        }
+        for query in &self.queries {
+            result.push_str(&format!("\n{}", query.to_typescript()));
+        }
+        result
+    }
+}
+
 #[derive(Clone)]
 pub struct NodeSchema {
     pub name: String,
@@ -92,20 +164,12 @@ impl Display for NodeSchema {
 }
 impl ToTypeScript for NodeSchema {
     fn to_typescript(&self) -> String {
-        let mut result = format!("interface {} {{\n", self.name);
+        let mut result = format!("export interface {} {{\n", self.name);
         result.push_str("  id: string;\n");
-
-        for property in &self.properties {
-            result.push_str(&format!(
-                "  {}: {};\n",
-                property.name,
-                match &property.field_type {
-                    GeneratedType::RustType(t) => t.to_ts(),
-                    _ => unreachable!(),
-                }
-            ));
-        }
-
+        result.push_str("  label: string;\n");
+        // set on nodes returned by a search
+        result.push_str("  score?: number;\n");
+        result.push_str(&properties_to_ts(&self.properties));
         result.push_str("}\n");
         result
     }
@@ -131,26 +195,74 @@ impl Display for EdgeSchema {
 }
 impl ToTypeScript for VectorSchema {
     fn to_typescript(&self) -> String {
-        let mut result = format!("interface {} {{\n", self.name);
+        let mut result = format!("export interface {} {{\n", self.name);
         result.push_str("  id: string;\n");
+        result.push_str("  label: string;\n");
         result.push_str("  data: Array<number>;\n");
-
-        for property in &self.properties {
-            result.push_str(&format!(
-                "  {}: {};\n",
-                property.name,
-                match &property.field_type {
-                    GeneratedType::RustType(t) => t.to_ts(),
-                    _ => unreachable!(),
-                }
-            ));
-        }
-
+        result.push_str("  score: number;\n");
+        result.push_str(&properties_to_ts(&self.properties));
         result.push_str("}\n");
         result
     }
 }
 #[derive(Clone)]
+pub struct EnumSchema {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+impl Display for EnumSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]"
+        )?;
+        writeln!(f, "pub enum {} {{", self.name)?;
+        for variant in &self.variants {
+            writeln!(f, "    {},", variant)?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "impl {} {{", self.name)?;
+        writeln!(
+            f,
+            "    pub const VARIANTS: &'static [&'static str] = &[{}];",
+            self.variants
+                .iter()
+                .map(|v| format!("\"{}\"", v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+        writeln!(f, "    pub fn as_str(&self) -> &'static str {{")?;
+        writeln!(f, "        Self::VARIANTS[*self as usize]")?;
+        writeln!(f, "    }}\n}}")?;
+        // stored and compared as the name of the variant
+        writeln!(f, "impl From<{}> for Value {{", self.name)?;
+        writeln!(f, "    fn from(value: {}) -> Self {{", self.name)?;
+        writeln!(f, "        Value::String(value.as_str().to_string())")?;
+        writeln!(f, "    }}\n}}")?;
+        writeln!(f, "impl PartialEq<{}> for Value {{", self.name)?;
+        writeln!(f, "    fn eq(&self, other: &{}) -> bool {{", self.name)?;
+        writeln!(
+            f,
+            "        matches!(self, Value::String(s) if s == other.as_str())"
+        )?;
+        writeln!(f, "    }}\n}}")?;
+        writeln!(f, "impl PartialEq<&{}> for Value {{", self.name)?;
+        writeln!(f, "    fn eq(&self, other: &&{}) -> bool {{", self.name)?;
+        writeln!(f, "        *self == **other")?;
+        writeln!(f, "    }}\n}}")
+    }
+}
+impl ToTypeScript for EnumSchema {
+    fn to_typescript(&self) -> String {
+        let variants = self
+            .variants
+            .iter()
+            .map(|v| format!("\"{}\"", v))
+            .collect::<Vec<_>>();
+        format!("export type {} = {};\n", self.name, variants.join(" | "))
+    }
+}
+
 pub struct VectorSchema {
     pub name: String,
     pub properties: Vec<SchemaProperty>,
@@ -166,29 +278,30 @@ impl Display for VectorSchema {
 }
 impl ToTypeScript for EdgeSchema {
     fn to_typescript(&self) -> String {
-        let properties_str = self
-            .properties
-            .iter()
-            .map(|p| {
-                format!(
-                    "    {}: {}",
-                    p.name,
-                    match &p.field_type {
-                        GeneratedType::RustType(t) => t.to_ts(),
-                        _ => unreachable!(),
-                    }
-                )
-            })
-            .collect::<Vec<_>>()
-            .join(";");
-
-        format!(
-            "interface {} {{\n  id: string;\n  from: {};\n  to: {};\n  properties: {{\n\t{}\n}};\n}}\n",
-            self.name, self.from, self.to, properties_str
-        )
+        let mut result = format!("export interface {} {{\n", self.name);
+        result.push_str("  id: string;\n");
+        result.push_str("  label: string;\n");
+        // ids of the nodes the edge connects, of types `from` and `to`
+        result.push_str("  from_node: string;\n");
+        result.push_str("  to_node: string;\n");
+        result.push_str(&properties_to_ts(&self.properties));
+        result.push_str("}\n");
+        result
     }
 }
 
+/// Properties are sent alongside the fields every item has, sensitive ones only to the
+/// roles granted access to them
+fn properties_to_ts(properties: &[SchemaProperty]) -> String {
+    properties
+        .iter()
+        .map(|p| match p.sensitive {
+            Some(_) => format!("  {}?: {};\n", ts_key(&p.name), p.field_type.to_ts()),
+            None => format!("  {}: {};\n", ts_key(&p.name), p.field_type.to_ts()),
+        })
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct SchemaProperty {
     pub name: String,
@@ -196,6 +309,8 @@ pub struct SchemaProperty {
     pub default_value: Option<GeneratedValue>,
     // pub is_optional: bool,
     pub is_index: FieldPrefix,
+    /// Roles the property is returned to if it is sensitive
+    pub sensitive: Option<Vec<String>>,
 }
 
 pub struct Query {
@@ -210,12 +325,31 @@ impl Display for Query {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // prints sub parameter structs (e.g. (docs: {doc: String, id: String}))
         for (name, parameters) in &self.sub_parameters {
-            writeln!(f, "#[derive(Serialize, Deserialize)]")?;
+            writeln!(f, "#[derive(Serialize, Deserialize, Clone)]")?;
             write!(f, "pub struct {} {{\n", name)?;
             for parameter in parameters {
                 write!(f, "    pub {}: {},\n", parameter.name, parameter.field_type)?;
             }
             write!(f, "}}\n")?;
+            // so object parameters can be stored as properties
+            if parameters
+                .iter()
+                .any(|p| matches!(p.field_type, GeneratedType::Variable(_)))
+            {
+                continue;
+            }
+            writeln!(f, "impl From<{}> for Value {{", name)?;
+            writeln!(f, "    fn from(data: {}) -> Self {{", name)?;
+            writeln!(
+                f,
+                "        Value::Object(HashMap::from([{}]))",
+                parameters
+                    .iter()
+                    .map(|p| format!("(\"{0}\".to_string(), Value::from(data.{0}))", p.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+            writeln!(f, "    }}\n}}")?;
         }
         // prints top level parameters (e.g. (docs: {doc: String, id: String}))
         if !self.parameters.is_empty() {
@@ -226,18 +360,115 @@ impl Display for Query {
                 "{}",
                 self.parameters
                     .iter()
-                    .map(|p| format!("{}", p))
+                    .map(|p| match p.default_value {
+                        Some(_) => format!(
+                            "#[serde(default = \"{}\")]\n{}",
+                            p.default_fn(&self.name),
+                            p
+                        ),
+                        None => format!("{}", p),
+                    })
                     .collect::<Vec<_>>()
                     .join(",\n")
             )?;
             write!(f, "\n}}\n")?;
+            // values of parameters left out of the request
+            for p in &self.parameters {
+                if let Some(value) = &p.default_value {
+                    writeln!(
+                        f,
+                        "fn {}() -> {} {{\n    {}\n}}",
+                        p.default_fn(&self.name),
+                        p.field_type,
+                        p.default_expr(value)
+                    )?;
+                }
+            }
         }
 
-        write!(f, "#[handler]\n")?; // Handler macro
+        // Handler macro, marking handlers of mutations so replicas don't serve them
+        match self.is_mut {
+            true => writeln!(f, "#[handler(writes)]")?,
+            false => writeln!(f, "#[handler]")?,
+        }
 
         // prints the function signature
         write!(f, "pub fn {} (input: &HandlerInput, response: &mut Response) -> Result<(), GraphError> {{\n", self.name)?;
 
+        // if mut then run the query in a write txn, possibly shared with concurrent writes
+        // if not then run it in a read txn, or a pinned snapshot if the client pages
+        // through the results
+        if self.is_mut {
+            writeln!(f, "    input.run_write(response, {}_in_txn)", self.name)?;
+            writeln!(f, "}}")?;
+        } else {
+            writeln!(f, "    input.run_read(response, {}_in_read)", self.name)?;
+            writeln!(f, "}}")?;
+            writeln!(f, "pub fn {}_in_read (input: &HandlerInput, txn: &RoTxn) -> Result<HashMap<String, ReturnValue>, GraphError> {{", self.name)?;
+            self.fmt_body(f, QueryOutput::ReturnValues)?;
+        }
+
+        // prints the variant run against a caller owned write txn, used by the
+        // transaction endpoint
+        writeln!(f, "#[tx_handler({})]", self.name)?;
+        writeln!(f, "pub fn {}_in_txn (input: &HandlerInput, mut txn: &mut RwTxn, response: &mut Response) -> Result<(), GraphError> {{", self.name)?;
+        self.fmt_body(f, QueryOutput::Response)
+    }
+}
+impl ToTypeScript for Query {
+    /// Interfaces of the parameters and the returned values of the query, and the
+    /// function sending it to an instance
+    fn to_typescript(&self) -> String {
+        let mut result = String::new();
+        for (name, parameters) in &self.sub_parameters {
+            result.push_str(&format!("export interface {} {{\n", name));
+            for parameter in parameters {
+                result.push_str(&parameter.to_typescript());
+            }
+            result.push_str("}\n\n");
+        }
+        if !self.parameters.is_empty() {
+            result.push_str(&format!("export interface {}Input {{\n", self.name));
+            for parameter in &self.parameters {
+                result.push_str(&parameter.to_typescript());
+            }
+            result.push_str("}\n\n");
+        }
+
+        result.push_str(&format!("export interface {}Result {{\n", self.name));
+        for return_value in &self.return_values {
+            if let Some(name) = return_value.name() {
+                result.push_str(&format!("  {}: {};\n", ts_key(name), return_value.ts_type));
+            }
+        }
+        result.push_str("}\n\n");
+
+        match self.parameters.is_empty() {
+            true => result.push_str(&format!(
+                "export function {0}(options: HelixOptions): Promise<{0}Result> {{\n  return runQuery(options, \"{0}\", {{}});\n}}\n",
+                self.name
+            )),
+            false => result.push_str(&format!(
+                "export function {0}(options: HelixOptions, input: {0}Input): Promise<{0}Result> {{\n  return runQuery(options, \"{0}\", input);\n}}\n",
+                self.name
+            )),
+        }
+        result
+    }
+}
+/// What the function of a query does with the values of its `RETURN`
+#[derive(Clone, Copy, PartialEq)]
+enum QueryOutput {
+    /// Writes them to the response
+    Response,
+    /// Returns them to the caller, which pages through them
+    ReturnValues,
+}
+
+impl Query {
+    /// Prints the rest of a query function after its signature, run against the
+    /// transaction passed to the function
+    fn fmt_body(&self, f: &mut fmt::Formatter<'_>, output: QueryOutput) -> fmt::Result {
         // prints basic query items
         if !self.parameters.is_empty() {
             write!(
@@ -255,13 +486,6 @@ impl Display for Query {
         )?;
 
         writeln!(f, "let db = Arc::clone(&input.graph.storage);")?;
-        // if mut then get write txn
-        // if not then get read txn
-        if self.is_mut {
-            writeln!(f, "let mut txn = db.graph_env.write_txn().unwrap();")?;
-        } else {
-            writeln!(f, "let txn = db.graph_env.read_txn().unwrap();")?;
-        }
 
         // prints each statement
         for statement in &self.statements {
@@ -278,16 +502,14 @@ impl Display for Query {
             }
         }
 
-        // commit the transaction
-        // if self.is_mut {
-            writeln!(f, "    txn.commit().unwrap();")?;
-        // }/
-        // closes the handler function
-        write!(
-            f,
-            "    response.body = sonic_rs::to_vec(&return_vals).unwrap();\n"
-        )?;
-        write!(f, "    Ok(())\n")?;
+        // closes the function
+        match output {
+            QueryOutput::Response => write!(
+                f,
+                "    response.body = sonic_rs::to_vec(&return_vals).unwrap();\n    Ok(())\n"
+            )?,
+            QueryOutput::ReturnValues => write!(f, "    Ok(return_vals)\n")?,
+        }
         write!(f, "}}\n")
     }
 }
@@ -307,10 +529,49 @@ impl Default for Query {
 pub struct Parameter {
     pub name: String,
     pub field_type: GeneratedType,
+    pub is_optional: bool,
+    pub default_value: Option<Value>,
 }
 impl Display for Parameter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "pub {}: {}", self.name, self.field_type)
+        match self.is_optional {
+            true => write!(
+                f,
+                "#[serde(default)]\npub {}: Option<{}>",
+                self.name, self.field_type
+            ),
+            false => write!(f, "pub {}: {}", self.name, self.field_type),
+        }
+    }
+}
+impl ToTypeScript for Parameter {
+    fn to_typescript(&self) -> String {
+        match self.is_optional || self.default_value.is_some() {
+            true => format!("  {}?: {};\n", ts_key(&self.name), self.field_type.to_ts()),
+            false => format!("  {}: {};\n", ts_key(&self.name), self.field_type.to_ts()),
+        }
+    }
+}
+impl Parameter {
+    /// Name of the function serde calls for the value of a parameter left out of the request
+    fn default_fn(&self, query: &str) -> String {
+        format!("{}_{}_default", query, self.name)
+    }
+
+    /// The default value as a Rust expression of the parameter's type
+    fn default_expr(&self, value: &Value) -> String {
+        let is_float = matches!(
+            self.field_type,
+            GeneratedType::RustType(RustType::F32 | RustType::F64)
+        );
+        match value {
+            Value::I64(i) if is_float => format!("{:?}", *i as f64),
+            Value::String(s) => format!("{:?}.to_string()", s),
+            Value::F64(f) => format!("{:?}", f),
+            Value::I64(i) => i.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            _ => unreachable!("checked by the analyzer"),
+        }
     }
 }
 
@@ -323,6 +584,8 @@ pub enum Statement {
     Literal(GenRef<String>),
     Identifier(GenRef<String>),
     BoExp(BoExp),
+    /// A value computed when the statement runs, e.g. an `IF`
+    Expression(GeneratedExpression),
     Empty,
 }
 impl Display for Statement {
@@ -335,6 +598,7 @@ impl Display for Statement {
             Statement::Literal(literal) => write!(f, "{}", literal),
             Statement::Identifier(identifier) => write!(f, "{}", identifier),
             Statement::BoExp(bo) => write!(f, "{}", bo),
+            Statement::Expression(expr) => write!(f, "{}", expr),
             Statement::Empty => write!(f, ""),
         }
     }
@@ -444,7 +708,13 @@ pub enum BoExp {
     And(Vec<BoExp>),
     Or(Vec<BoExp>),
     Exists(Traversal),
+    Not(Box<BoExp>),
     Expr(Traversal),
+    /// An expression compared by a boolean operation, e.g. `LOWER(_::{name})::EQ(name)`
+    Compared {
+        expr: GeneratedExpression,
+        op: BoolOp,
+    },
 }
 impl Display for BoExp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -463,8 +733,84 @@ impl Display for BoExp {
                     .collect::<Vec<_>>();
                 write!(f, "{}", tr.join(" || "))
             }
-            BoExp::Exists(traversal) => write!(f, "{}", traversal),
+            // stops at the first item the traversal yields
+            BoExp::Exists(traversal) => write!(f, "{}.next().is_some()", traversal),
+            BoExp::Not(expr) => write!(f, "!({})", expr),
             BoExp::Expr(traversal) => write!(f, "{}", traversal),
+            BoExp::Compared { expr, op } => write!(f, "Some(&{}).{}", expr, op),
+        }
+    }
+}
+impl BoExp {
+    /// Whether the expression only compares properties of the item it filters to values
+    /// known up front, so it can be checked against the stored item before decoding it
+    pub fn reads_only_properties(&self) -> bool {
+        match self {
+            BoExp::And(exprs) | BoExp::Or(exprs) => {
+                exprs.iter().all(|expr| expr.reads_only_properties())
+            }
+            BoExp::Not(expr) => expr.reads_only_properties(),
+            BoExp::Expr(traversal) => {
+                matches!(&traversal.traversal_type, TraversalType::Nested(nested) if nested.inner() == "val")
+                    && traversal.steps.iter().all(|step| match step.inner() {
+                        Step::PropertyFetch(_) => true,
+                        Step::BoolOp(op) => !matches!(op.value(), GeneratedValue::Computed(_)),
+                        _ => false,
+                    })
+            }
+            BoExp::Exists(_) | BoExp::Compared { .. } => false,
+        }
+    }
+
+    /// The properties compared by an expression only reading properties, `None` if it
+    /// reads others or one of them isn't named by a literal
+    pub fn compared_properties(&self) -> Option<Vec<String>> {
+        match self {
+            BoExp::And(exprs) | BoExp::Or(exprs) => {
+                let mut properties = Vec::new();
+                for expr in exprs {
+                    properties.extend(expr.compared_properties()?);
+                }
+                Some(properties)
+            }
+            BoExp::Not(expr) => expr.compared_properties(),
+            BoExp::Expr(_) if !self.reads_only_properties() => None,
+            BoExp::Expr(traversal) => traversal
+                .steps
+                .iter()
+                .filter_map(|step| match step.inner() {
+                    Step::PropertyFetch(property) => Some(property.literal().cloned()),
+                    _ => None,
+                })
+                .collect(),
+            BoExp::Exists(_) | BoExp::Compared { .. } => None,
+        }
+    }
+
+    /// The properties an expression only reading properties requires to equal a value,
+    /// with the values. Properties compared under an `OR` or `NOT` aren't required to.
+    pub fn required_equalities(&self) -> Vec<(String, GeneratedValue)> {
+        match self {
+            BoExp::And(exprs) => exprs
+                .iter()
+                .flat_map(|expr| expr.required_equalities())
+                .collect(),
+            BoExp::Expr(traversal) if self.reads_only_properties() => {
+                let steps = traversal
+                    .steps
+                    .iter()
+                    .map(|step| step.inner())
+                    .collect::<Vec<_>>();
+                match steps[..] {
+                    [Step::PropertyFetch(property), Step::BoolOp(BoolOp::Eq(eq))] => property
+                        .literal()
+                        .map(|property| (property.clone(), eq.value.clone()))
+                        .into_iter()
+                        .collect(),
+                    _ => Vec::new(),
+                }
+            }
+            _ => Vec::new(),
         }
     }
 }
@@ -472,6 +818,8 @@ impl Display for BoExp {
 pub struct ReturnValue {
     pub value: ReturnValueExpr,
     pub return_type: ReturnType,
+    /// TypeScript type of the value as clients receive it
+    pub ts_type: String,
 }
 impl Display for ReturnValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -501,28 +849,47 @@ impl Display for ReturnValue {
 }
 
 impl ReturnValue {
+    /// Key of the value in the returned object, unnamed values aren't returned yet
+    pub fn name(&self) -> Option<&String> {
+        match &self.return_type {
+            ReturnType::Literal(name)
+            | ReturnType::NamedLiteral(name)
+            | ReturnType::NamedExpr(name) => Some(name.inner()),
+            ReturnType::UnnamedExpr => None,
+        }
+    }
+
+    pub fn with_ts_type(mut self, ts_type: String) -> Self {
+        self.ts_type = ts_type;
+        self
+    }
+
     pub fn new_literal(name: GenRef<String>, value: GenRef<String>) -> Self {
         Self {
             value: ReturnValueExpr::Value(value.clone()),
             return_type: ReturnType::Literal(name),
+            ts_type: "any".to_string(),
         }
     }
     pub fn new_named_literal(name: GenRef<String>, value: GenRef<String>) -> Self {
         Self {
             value: ReturnValueExpr::Value(value.clone()),
             return_type: ReturnType::NamedLiteral(name),
+            ts_type: "any".to_string(),
         }
     }
     pub fn new_named(name: GenRef<String>, value: ReturnValueExpr) -> Self {
         Self {
             value,
             return_type: ReturnType::NamedExpr(name),
+            ts_type: "any".to_string(),
         }
     }
     pub fn new_unnamed(value: ReturnValueExpr) -> Self {
         Self {
             value,
             return_type: ReturnType::UnnamedExpr,
+            ts_type: "any".to_string(),
         }
     }
 }
@@ -1,6 +1,8 @@
 pub mod bool_op;
+pub mod expression;
 pub mod generator_types;
 pub mod object_remapping_generation;
+pub mod parameter_binding;
 pub mod source_steps;
 pub mod traversal_steps;
 pub mod tsdisplay;
@@ -1,7 +1,7 @@
 use core::fmt;
 use std::fmt::Display;
 
-use super::{traversal_steps::Traversal, utils::GenRef};
+use super::{expression::GeneratedExpression, traversal_steps::Traversal, utils::GenRef};
 
 /// This is for creating a new field where the result is a traversal
 #[derive(Clone)]
@@ -20,6 +20,64 @@ impl Display for TraversalRemapping {
     }
 }
 
+/// This is for creating a new field computed by an expression, left empty if it can't
+/// be computed for the item
+#[derive(Clone)]
+pub struct ExpressionRemapping {
+    pub variable_name: String,
+    pub new_field: String,
+    pub value: GeneratedExpression,
+}
+impl Display for ExpressionRemapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expression_remapping!(remapping_vals, {}.clone(), \"{}\" => {})",
+            self.variable_name, self.new_field, self.value
+        )
+    }
+}
+
+/// This is for creating a new field from the first of several values that yields
+/// anything, or `NONE` if none do
+#[derive(Clone)]
+pub struct CoalesceRemapping {
+    pub variable_name: String,
+    pub new_field: String,
+    pub values: Vec<CoalesceValue>,
+}
+impl Display for CoalesceRemapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "coalesce_remapping!(remapping_vals, {}.clone(), \"{}\" => {})",
+            self.variable_name,
+            self.new_field,
+            self.values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+#[derive(Clone)]
+pub enum CoalesceValue {
+    Traversal(Box<Traversal>),
+    Literal(GenRef<String>),
+}
+impl Display for CoalesceValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoalesceValue::Traversal(traversal) => write!(f, "{}", traversal),
+            CoalesceValue::Literal(value) => {
+                write!(f, "vec![TraversalVal::Value(Value::from({}))]", value)
+            }
+        }
+    }
+}
+
 /// This is used for renaming fields
 #[derive(Clone)]
 pub struct FieldRemapping {
@@ -191,6 +249,8 @@ pub enum RemappingType {
     ClosureFieldRemapping(ClosureFieldRemapping),
     ExcludeField(ExcludeField),
     TraversalRemapping(TraversalRemapping),
+    CoalesceRemapping(CoalesceRemapping),
+    ExpressionRemapping(ExpressionRemapping),
     ValueRemapping(ValueRemapping),
     IdentifierRemapping(IdentifierRemapping),
     Spread,
@@ -204,6 +264,8 @@ impl Display for RemappingType {
             RemappingType::ClosureFieldRemapping(r) => write!(f, "{}", r),
             RemappingType::ExcludeField(r) => write!(f, "{}", r),
             RemappingType::TraversalRemapping(r) => write!(f, "{}", r),
+            RemappingType::CoalesceRemapping(r) => write!(f, "{}", r),
+            RemappingType::ExpressionRemapping(r) => write!(f, "{}", r),
             RemappingType::ValueRemapping(r) => write!(f, "{}", r),
             RemappingType::IdentifierRemapping(r) => write!(f, "{}", r),
             RemappingType::Spread => write!(f, ""),
@@ -0,0 +1,260 @@
+//! Checks the generated queries only hand their parameters to the steps as values.
+//!
+//! Labels, index names and property names are written into the generated code as string
+//! literals, so they have to be fixed by the query. Parameters are read from the
+//! deserialized input of the query, `data.<name>`, and bound as values of their type,
+//! they are never spliced into a key.
+
+use super::{
+    bool_op::BoolOp,
+    expression::GeneratedExpression,
+    generator_types::{BoExp, Query, Statement},
+    object_remapping_generation::{CoalesceValue, Remapping, RemappingType},
+    source_steps::{AddE, AddN, AddV, SourceStep},
+    traversal_steps::{Step, Traversal, Where},
+    utils::{GenRef, GeneratedValue},
+};
+
+/// A label or key of a generated step that isn't a fixed literal
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnboundKey {
+    /// What the key names, e.g. `label` or `index name`
+    pub kind: &'static str,
+    /// The key as it would be written into the generated code
+    pub key: String,
+}
+
+/// Returns the labels and keys of the steps of the query that aren't fixed literals
+pub fn unbound_keys(query: &Query) -> Vec<UnboundKey> {
+    let mut unbound = Vec::new();
+    for statement in &query.statements {
+        check_statement(statement, &mut unbound);
+    }
+    unbound
+}
+
+/// Whether the key is a literal that can't close the string it is written in
+fn is_bound(key: &GenRef<String>) -> bool {
+    match key {
+        GenRef::Literal(key) => key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+fn check_key(kind: &'static str, key: &GenRef<String>, unbound: &mut Vec<UnboundKey>) {
+    if !is_bound(key) {
+        unbound.push(UnboundKey {
+            kind,
+            key: key.to_string(),
+        });
+    }
+}
+
+fn check_property_names(
+    properties: &Option<Vec<(String, GeneratedValue)>>,
+    unbound: &mut Vec<UnboundKey>,
+) {
+    for (name, _) in properties.iter().flatten() {
+        check_key("property", &GenRef::Literal(name.clone()), unbound);
+    }
+}
+
+fn check_statement(statement: &Statement, unbound: &mut Vec<UnboundKey>) {
+    match statement {
+        Statement::Assignment(assignment) => check_statement(&assignment.value, unbound),
+        Statement::Drop(drop) => check_traversal(&drop.expression, unbound),
+        Statement::Traversal(traversal) => check_traversal(traversal, unbound),
+        Statement::ForEach(for_each) => {
+            for statement in &for_each.statements {
+                check_statement(statement, unbound);
+            }
+        }
+        Statement::BoExp(expr) => check_bo_exp(expr, unbound),
+        Statement::Expression(expr) => check_expression(expr, unbound),
+        Statement::Literal(_) | Statement::Identifier(_) | Statement::Empty => {}
+    }
+}
+
+fn check_bo_exp(expr: &BoExp, unbound: &mut Vec<UnboundKey>) {
+    match expr {
+        BoExp::And(exprs) | BoExp::Or(exprs) => {
+            for expr in exprs {
+                check_bo_exp(expr, unbound);
+            }
+        }
+        BoExp::Exists(traversal) | BoExp::Expr(traversal) => check_traversal(traversal, unbound),
+        BoExp::Not(expr) => check_bo_exp(expr, unbound),
+        BoExp::Compared { expr, op } => {
+            check_expression(expr, unbound);
+            check_bool_op(op, unbound);
+        }
+    }
+}
+
+fn check_expression(expr: &GeneratedExpression, unbound: &mut Vec<UnboundKey>) {
+    for property in expr.properties() {
+        check_key("property", property, unbound);
+    }
+    for condition in expr.conditions() {
+        check_bo_exp(condition, unbound);
+    }
+}
+
+/// Values compared by a boolean operation may be computed from properties
+fn check_bool_op(op: &BoolOp, unbound: &mut Vec<UnboundKey>) {
+    if let GeneratedValue::Computed(expr) = op.value() {
+        check_expression(expr, unbound);
+    }
+}
+
+fn check_traversal(traversal: &Traversal, unbound: &mut Vec<UnboundKey>) {
+    check_source_step(traversal.source_step.inner(), unbound);
+    for step in &traversal.steps {
+        check_step(step.inner(), unbound);
+    }
+}
+
+fn check_source_step(step: &SourceStep, unbound: &mut Vec<UnboundKey>) {
+    match step {
+        SourceStep::AddN(AddN {
+            label,
+            properties,
+            secondary_indices,
+        }) => {
+            check_key("label", label, unbound);
+            check_property_names(properties, unbound);
+            for index in secondary_indices.iter().flatten() {
+                check_key("index name", &GenRef::Literal(index.clone()), unbound);
+            }
+        }
+        SourceStep::AddE(AddE {
+            label, properties, ..
+        })
+        | SourceStep::AddV(AddV {
+            label, properties, ..
+        }) => {
+            check_key("label", label, unbound);
+            check_property_names(properties, unbound);
+        }
+        SourceStep::SearchV(search_v) => {
+            check_property_names(&search_v.properties, unbound);
+            for expr in &search_v.f {
+                check_bo_exp(expr, unbound);
+            }
+        }
+        SourceStep::NFromID(n_from_id) => check_key("label", &n_from_id.label, unbound),
+        SourceStep::NFromIndex(n_from_index) => {
+            check_key("index name", &n_from_index.index, unbound)
+        }
+        SourceStep::NFromType(n_from_type) => check_key("label", &n_from_type.label, unbound),
+        SourceStep::NFromTypeOrdered(n_from_type) => {
+            check_key("label", &n_from_type.label, unbound);
+            check_key("property", &n_from_type.property, unbound);
+        }
+        SourceStep::NFromTypeWhere(n_from_type) => {
+            check_key("label", &n_from_type.label, unbound);
+            check_bo_exp(&n_from_type.filter, unbound);
+        }
+        SourceStep::EFromID(e_from_id) => check_key("label", &e_from_id.label, unbound),
+        SourceStep::EFromIndex(e_from_index) => {
+            check_key("index name", &e_from_index.index, unbound)
+        }
+        SourceStep::EFromType(e_from_type) => check_key("label", &e_from_type.label, unbound),
+        SourceStep::EFromTypeWhere(e_from_type) => {
+            check_key("label", &e_from_type.label, unbound);
+            check_bo_exp(&e_from_type.filter, unbound);
+        }
+        SourceStep::SearchVector(search_vector) => {
+            if let Some(label) = &search_vector.label {
+                check_key("label", &GenRef::Literal(label.clone()), unbound);
+            }
+            for expr in search_vector.pre_filter.iter().flatten() {
+                check_bo_exp(expr, unbound);
+            }
+        }
+        SourceStep::SearchBM25(search_bm25) => check_key("label", &search_bm25.type_arg, unbound),
+        SourceStep::HybridSearch(hybrid_search) => {
+            check_key("label", &hybrid_search.label, unbound)
+        }
+        SourceStep::Analytics(analytics) => {
+            if let Some(label) = &analytics.label {
+                check_key("label", label, unbound);
+            }
+        }
+        SourceStep::Identifier(_) | SourceStep::Anonymous | SourceStep::Empty => {}
+    }
+}
+
+fn check_step(step: &Step, unbound: &mut Vec<UnboundKey>) {
+    match step {
+        Step::Out(out) => check_key("label", &out.label, unbound),
+        Step::In(in_) => check_key("label", &in_.label, unbound),
+        Step::OutE(out_e) => check_key("label", &out_e.label, unbound),
+        Step::InE(in_e) => check_key("label", &in_e.label, unbound),
+        Step::OutCount(out_count) => check_key("label", &out_count.label, unbound),
+        Step::InCount(in_count) => check_key("label", &in_count.label, unbound),
+        Step::Where(Where::Exists(exists)) => check_traversal(&exists.tr, unbound),
+        Step::Where(Where::Ref(where_ref)) => check_bo_exp(&where_ref.expr, unbound),
+        Step::Where(Where::Mut(where_mut)) => check_bo_exp(&where_mut.expr, unbound),
+        Step::OrderBy(order_by) => check_key("property", &order_by.property, unbound),
+        Step::PropertyFetch(property) => check_key("property", property, unbound),
+        Step::PropertyPath(path) => check_key("property", &path.property, unbound),
+        Step::Remapping(remapping) => check_remapping(remapping, unbound),
+        Step::BoolOp(op) => check_bool_op(op, unbound),
+        Step::ShortestPath(shortest_path) => {
+            if let Some(label) = &shortest_path.label {
+                check_key("label", label, unbound);
+            }
+            if let Some(weight) = &shortest_path.weight_property {
+                check_key("property", weight, unbound);
+            }
+        }
+        Step::ShortestPathWeighted(shortest_path) => {
+            if let Some(label) = &shortest_path.label {
+                check_key("label", label, unbound);
+            }
+            check_key("property", &shortest_path.weight_property, unbound);
+            if let Some(heuristic) = &shortest_path.heuristic_property {
+                check_key("property", heuristic, unbound);
+            }
+        }
+        Step::Count
+        | Step::Dedup
+        | Step::FromN
+        | Step::ToN
+        | Step::Range(_)
+        | Step::AsOf(_)
+        | Step::Score
+        | Step::SearchVector(_) => {}
+    }
+}
+
+fn check_remapping(remapping: &Remapping, unbound: &mut Vec<UnboundKey>) {
+    for remapping in &remapping.remappings {
+        match remapping {
+            RemappingType::ObjectRemapping(object) => check_remapping(&object.remapping, unbound),
+            RemappingType::ClosureFieldRemapping(closure) => {
+                check_remapping(&closure.remapping, unbound)
+            }
+            RemappingType::TraversalRemapping(traversal) => {
+                check_traversal(&traversal.new_value, unbound)
+            }
+            RemappingType::ExpressionRemapping(expression) => {
+                check_expression(&expression.value, unbound)
+            }
+            RemappingType::CoalesceRemapping(coalesce) => {
+                for value in &coalesce.values {
+                    if let CoalesceValue::Traversal(traversal) = value {
+                        check_traversal(traversal, unbound);
+                    }
+                }
+            }
+            RemappingType::FieldRemapping(_)
+            | RemappingType::ExcludeField(_)
+            | RemappingType::ValueRemapping(_)
+            | RemappingType::IdentifierRemapping(_)
+            | RemappingType::Spread
+            | RemappingType::Empty => {}
+        }
+    }
+}
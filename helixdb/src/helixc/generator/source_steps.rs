@@ -5,7 +5,7 @@ use crate::helixc::generator::utils::{write_properties, write_secondary_indices}
 
 use super::{
     generator_types::BoExp,
-    utils::{GenRef, GeneratedValue},
+    utils::{write_limit, GenRef, GeneratedValue, Order},
 };
 
 #[derive(Clone)]
@@ -18,10 +18,16 @@ pub enum SourceStep {
     NFromID(NFromID),
     NFromIndex(NFromIndex),
     NFromType(NFromType),
+    NFromTypeOrdered(NFromTypeOrdered),
+    NFromTypeWhere(NFromTypeWhere),
     EFromID(EFromID),
+    EFromIndex(EFromIndex),
     EFromType(EFromType),
+    EFromTypeWhere(EFromTypeWhere),
     SearchVector(SearchVector),
     SearchBM25(SearchBM25),
+    HybridSearch(HybridSearch),
+    Analytics(Analytics),
     Anonymous,
     Empty,
 }
@@ -128,6 +134,62 @@ impl Display for NFromType {
     }
 }
 
+/// Nodes of a type ordered by a property, read from its index if there is one
+#[derive(Clone)]
+pub struct NFromTypeOrdered {
+    pub label: GenRef<String>,
+    pub property: GenRef<String>,
+    pub order: Order,
+    pub limit: Option<GenRef<String>>,
+}
+impl Display for NFromTypeOrdered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "n_from_type_ordered({}, {}, HelixOrder::{}, {})",
+            self.label,
+            self.property,
+            self.order,
+            write_limit(&self.limit)
+        )
+    }
+}
+
+/// Nodes of a type kept by a `WHERE` only reading their properties, which is checked
+/// against the stored nodes before they are decoded
+#[derive(Clone)]
+pub struct NFromTypeWhere {
+    pub label: GenRef<String>,
+    pub filter: Box<BoExp>,
+    /// Indexed properties the filter requires to equal a value, which the nodes may be
+    /// looked up by instead of scanning the type
+    pub lookups: Vec<(String, GeneratedValue)>,
+}
+impl Display for NFromTypeWhere {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.lookups.is_empty() {
+            true => write!(
+                f,
+                "n_from_type_where({}, |val, txn| Ok({}))",
+                self.label, self.filter
+            ),
+            false => {
+                let lookups = self
+                    .lookups
+                    .iter()
+                    .map(|(index, value)| format!("(\"{}\", Value::from({}))", index, value))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    "n_from_type_where_indexed({}, &[{}], |val, txn| Ok({}))",
+                    self.label, lookups, self.filter
+                )
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EFromID {
     pub id: GenRef<String>,
@@ -149,6 +211,23 @@ impl Display for EFromType {
     }
 }
 
+/// Edges of a type kept by a `WHERE` only reading their properties, which is checked
+/// against the stored edges before they are decoded
+#[derive(Clone)]
+pub struct EFromTypeWhere {
+    pub label: GenRef<String>,
+    pub filter: Box<BoExp>,
+}
+impl Display for EFromTypeWhere {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "e_from_type_where({}, |val, txn| Ok({}))",
+            self.label, self.filter
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct SearchBM25 {
     pub type_arg: GenRef<String>,
@@ -162,6 +241,59 @@ impl Display for SearchBM25 {
     }
 }
 
+#[derive(Clone)]
+pub struct HybridSearch {
+    pub label: GenRef<String>,
+    pub vec: GeneratedValue,
+    pub query: GeneratedValue,
+    pub k: GeneratedValue,
+}
+
+impl Display for HybridSearch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "hybrid_search({}, {}, {}, {})",
+            self.label, self.vec, self.query, self.k
+        )
+    }
+}
+
+#[derive(Clone)]
+pub enum AnalyticsAlgorithm {
+    PageRank(GeneratedValue),
+    Betweenness(GeneratedValue),
+    Components,
+    Communities(GeneratedValue),
+}
+
+#[derive(Clone)]
+pub struct Analytics {
+    pub label: Option<GenRef<String>>,
+    pub algorithm: AnalyticsAlgorithm,
+}
+
+impl Display for Analytics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = self
+            .label
+            .clone()
+            .map_or("None".to_string(), |label| format!("Some({})", label));
+        match &self.algorithm {
+            AnalyticsAlgorithm::PageRank(iterations) => {
+                write!(f, "page_rank({}, {})", label, iterations)
+            }
+            AnalyticsAlgorithm::Betweenness(samples) => {
+                write!(f, "betweenness({}, {})", label, samples)
+            }
+            AnalyticsAlgorithm::Components => write!(f, "connected_components({})", label),
+            AnalyticsAlgorithm::Communities(iterations) => {
+                write!(f, "detect_communities({}, {})", label, iterations)
+            }
+        }
+    }
+}
+
 impl Display for SourceStep {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -173,10 +305,16 @@ impl Display for SourceStep {
             SourceStep::NFromID(n_from_id) => write!(f, "{}", n_from_id),
             SourceStep::NFromIndex(n_from_index) => write!(f, "{}", n_from_index),
             SourceStep::NFromType(n_from_type) => write!(f, "{}", n_from_type),
+            SourceStep::NFromTypeOrdered(n_from_type) => write!(f, "{}", n_from_type),
+            SourceStep::NFromTypeWhere(n_from_type) => write!(f, "{}", n_from_type),
             SourceStep::EFromID(e_from_id) => write!(f, "{}", e_from_id),
+            SourceStep::EFromIndex(e_from_index) => write!(f, "{}", e_from_index),
             SourceStep::EFromType(e_from_type) => write!(f, "{}", e_from_type),
+            SourceStep::EFromTypeWhere(e_from_type) => write!(f, "{}", e_from_type),
             SourceStep::SearchVector(search_vector) => write!(f, "{}", search_vector),
             SourceStep::SearchBM25(search_bm25) => write!(f, "{}", search_bm25),
+            SourceStep::HybridSearch(hybrid_search) => write!(f, "{}", hybrid_search),
+            SourceStep::Analytics(analytics) => write!(f, "{}", analytics),
             SourceStep::Anonymous => write!(f, ""),
             SourceStep::Empty => panic!("Should not be empty"),
         }
@@ -188,26 +326,40 @@ pub struct SearchVector {
     pub vec: GeneratedValue,
     pub k: GeneratedValue,
     pub pre_filter: Option<Vec<BoExp>>,
+    /// Vector type declared in a namespace, searched in the index of the namespace
+    pub label: Option<String>,
 }
 
 impl Display for SearchVector {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (method, label) = match &self.label {
+            Some(label) => ("search_v_of", format!("\"{}\", ", label)),
+            None => ("search_v", String::new()),
+        };
         match &self.pre_filter {
+            // the filters are checked against every vector before the index is walked
             Some(pre_filter) => write!(
                 f,
-                "search_v::<fn(&HVector, &RoTxn) -> bool>({}, {}, Some(&[{}]))",
+                "{}({}{}, {}, Some(&[{}]))",
+                method,
+                label,
                 self.vec,
                 self.k,
                 pre_filter
                     .iter()
-                    .map(|f| format!("|v: &HVector, txn: &RoTxn| {}", f))
+                    .map(|expr| {
+                        format!(
+                            "|val: &HVector, txn: &RoTxn| {{ let val = TraversalVal::Vector(val.clone()); {} }}",
+                            expr
+                        )
+                    })
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
             None => write!(
                 f,
-                "search_v::<fn(&HVector, &RoTxn) -> bool>({}, {}, None)",
-                self.vec, self.k
+                "{}::<fn(&HVector, &RoTxn) -> bool>({}{}, {}, None)",
+                method, label, self.vec, self.k
             ),
         }
     }
@@ -224,3 +376,15 @@ impl Display for NFromIndex {
         write!(f, "n_from_index({}, {})", self.index, self.key)
     }
 }
+
+#[derive(Clone)]
+pub struct EFromIndex {
+    pub index: GenRef<String>,
+    pub key: GenRef<String>,
+}
+
+impl Display for EFromIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "e_from_index({}, {})", self.index, self.key)
+    }
+}
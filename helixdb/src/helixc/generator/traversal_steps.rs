@@ -1,4 +1,4 @@
-use crate::helixc::generator::utils::write_properties;
+use crate::helixc::generator::utils::{write_limit, write_properties};
 
 use super::{
     bool_op::BoolOp,
@@ -74,8 +74,79 @@ pub struct Traversal {
     pub should_collect: ShouldCollect,
 }
 
+impl Traversal {
+    /// The properties read by a traversal only handing back properties of its items or a
+    /// count of them, which are all the items it reads need decoded with
+    pub fn projection(&self) -> Option<Vec<String>> {
+        if !matches!(self.traversal_type, TraversalType::Ref) {
+            return None;
+        }
+        let mut properties = Vec::new();
+        match self.source_step.inner() {
+            SourceStep::NFromID(_)
+            | SourceStep::NFromType(_)
+            | SourceStep::EFromID(_)
+            | SourceStep::EFromType(_) => {}
+            SourceStep::NFromTypeOrdered(ordered) => {
+                properties.push(ordered.property.literal()?.clone())
+            }
+            SourceStep::NFromTypeWhere(n_from_type) => {
+                properties.extend(n_from_type.filter.compared_properties()?)
+            }
+            SourceStep::EFromTypeWhere(e_from_type) => {
+                properties.extend(e_from_type.filter.compared_properties()?)
+            }
+            _ => return None,
+        }
+        match self.steps.last()?.inner() {
+            Step::PropertyFetch(_) | Step::Count | Step::OutCount(_) | Step::InCount(_) => {}
+            _ => return None,
+        }
+        for step in &self.steps {
+            match step.inner() {
+                Step::Out(_)
+                | Step::In(_)
+                | Step::OutE(_)
+                | Step::InE(_)
+                | Step::FromN
+                | Step::ToN
+                | Step::Range(_)
+                | Step::Dedup
+                | Step::Count
+                | Step::OutCount(_)
+                | Step::InCount(_) => {}
+                Step::PropertyFetch(property) => properties.push(property.literal()?.clone()),
+                Step::OrderBy(order_by) => properties.push(order_by.property.literal()?.clone()),
+                Step::Where(Where::Ref(where_ref)) => {
+                    properties.extend(where_ref.expr.compared_properties()?)
+                }
+                _ => return None,
+            }
+        }
+        properties.sort();
+        properties.dedup();
+        Some(properties)
+    }
+}
+
 impl Display for Traversal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the items are decoded with only the properties read, so the traversal has to
+        // be run to its end within the projection's scope
+        let projection = match (&self.should_collect, self.steps.last().map(|s| s.inner())) {
+            (ShouldCollect::ToVec | ShouldCollect::ToVal, _)
+            | (ShouldCollect::No, Some(Step::Count | Step::OutCount(_) | Step::InCount(_))) => {
+                self.projection()
+            }
+            _ => None,
+        };
+        if let Some(properties) = &projection {
+            let properties = properties
+                .iter()
+                .map(|property| format!("{:?}", property))
+                .collect::<Vec<_>>();
+            write!(f, "Projection::scope(&[{}], || ", properties.join(", "))?;
+        }
         match &self.traversal_type {
             TraversalType::FromVar(var) => {
                 write!(f, "G::new_from(Arc::clone(&db), &txn, {}.clone())", var)?;
@@ -133,11 +204,27 @@ impl Display for Traversal {
                                                                              // scrappy
                 )?;
                 write!(f, "\n    .update({})", write_properties(&properties))?;
-                write!(f, "\n    .collect_to::<Vec<_>>()")?;
+                write!(f, "\n    .try_collect_to::<Vec<_>>()?")?;
                 write!(f, "}}")?;
             }
         }
-        write!(f, "{}", self.should_collect)
+        match (&self.traversal_type, &self.should_collect) {
+            // failed writes (e.g. unique violations) abort the query
+            (TraversalType::Mut, ShouldCollect::ToVec) => {
+                write!(f, ".try_collect_to::<Vec<_>>()?")?
+            }
+            _ => write!(f, "{}", self.should_collect)?,
+        }
+        if projection.is_some() {
+            write!(f, ")")?;
+        }
+        // the degree lookups of the edge counts can fail
+        if let (ShouldCollect::No, Some(Step::OutCount(_) | Step::InCount(_))) =
+            (&self.should_collect, self.steps.last().map(|s| s.inner()))
+        {
+            write!(f, "?")?;
+        }
+        Ok(())
     }
 }
 impl Default for Traversal {
@@ -157,6 +244,8 @@ pub enum Step {
     In(In),
     OutE(OutE),
     InE(InE),
+    OutCount(OutCount),
+    InCount(InCount),
     FromN,
     ToN,
 
@@ -165,6 +254,7 @@ pub enum Step {
     Where(Where),
     Range(Range),
     OrderBy(OrderBy),
+    AsOf(AsOf),
     Dedup,
 
     // bool ops
@@ -172,6 +262,8 @@ pub enum Step {
 
     // property
     PropertyFetch(GenRef<String>),
+    PropertyPath(PropertyPath),
+    Score,
 
     // object
     Remapping(Remapping),
@@ -181,6 +273,7 @@ pub enum Step {
 
     // shortest path
     ShortestPath(ShortestPath),
+    ShortestPathWeighted(ShortestPathWeighted),
 
     // search vector
     SearchVector(SearchVectorStep),
@@ -193,17 +286,23 @@ impl Display for Step {
             Step::FromN => write!(f, "from_n()"),
             Step::ToN => write!(f, "to_n()"),
             Step::PropertyFetch(property) => write!(f, "check_property({})", property),
+            Step::PropertyPath(path) => write!(f, "{}", path),
+            Step::Score => write!(f, "check_score()"),
 
             Step::Out(out) => write!(f, "{}", out),
             Step::In(in_) => write!(f, "{}", in_),
             Step::OutE(out_e) => write!(f, "{}", out_e),
             Step::InE(in_e) => write!(f, "{}", in_e),
+            Step::OutCount(out_count) => write!(f, "{}", out_count),
+            Step::InCount(in_count) => write!(f, "{}", in_count),
             Step::Where(where_) => write!(f, "{}", where_),
             Step::Range(range) => write!(f, "{}", range),
             Step::OrderBy(order_by) => write!(f, "{}", order_by),
+            Step::AsOf(as_of) => write!(f, "{}", as_of),
             Step::BoolOp(bool_op) => write!(f, "{}", bool_op),
             Step::Remapping(remapping) => write!(f, "{}", remapping),
             Step::ShortestPath(shortest_path) => write!(f, "{}", shortest_path),
+            Step::ShortestPathWeighted(shortest_path) => write!(f, "{}", shortest_path),
             Step::SearchVector(search_vector) => write!(f, "{}", search_vector),
         }
     }
@@ -216,17 +315,23 @@ impl Debug for Step {
             Step::FromN => write!(f, "FromN"),
             Step::ToN => write!(f, "ToN"),
             Step::PropertyFetch(property) => write!(f, "check_property({})", property),
+            Step::PropertyPath(path) => write!(f, "{}", path),
+            Step::Score => write!(f, "check_score()"),
 
             Step::Out(out) => write!(f, "Out"),
             Step::In(in_) => write!(f, "In"),
             Step::OutE(out_e) => write!(f, "OutE"),
             Step::InE(in_e) => write!(f, "InE"),
+            Step::OutCount(_) => write!(f, "OutCount"),
+            Step::InCount(_) => write!(f, "InCount"),
             Step::Where(where_) => write!(f, "Where"),
             Step::Range(range) => write!(f, "Range"),
             Step::OrderBy(order_by) => write!(f, "OrderBy"),
+            Step::AsOf(_) => write!(f, "AsOf"),
             Step::BoolOp(bool_op) => write!(f, "Bool"),
             Step::Remapping(remapping) => write!(f, "Remapping"),
             Step::ShortestPath(shortest_path) => write!(f, "ShortestPath"),
+            Step::ShortestPathWeighted(_) => write!(f, "ShortestPathWeighted"),
             Step::SearchVector(search_vector) => write!(f, "SearchVector"),
         }
     }
@@ -274,6 +379,26 @@ impl Display for InE {
     }
 }
 
+#[derive(Clone)]
+pub struct OutCount {
+    pub label: GenRef<String>,
+}
+impl Display for OutCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "out_count({})", self.label)
+    }
+}
+
+#[derive(Clone)]
+pub struct InCount {
+    pub label: GenRef<String>,
+}
+impl Display for InCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "in_count({})", self.label)
+    }
+}
+
 #[derive(Clone)]
 pub enum Where {
     Exists(WhereExists),
@@ -300,7 +425,7 @@ impl Display for WhereExists {
             f,
             "filter_ref(|val, txn|{{
                 if let Ok(val) = val {{ 
-                    Ok({}.count().gt(&0))
+                    Ok({}.next().is_some())
                 }} else {{
                     Ok(false)
                 }}
@@ -350,14 +475,67 @@ impl Display for Range {
     }
 }
 
+#[derive(Clone)]
+pub struct AsOf {
+    /// Date, date string or seconds since the epoch
+    pub at: GenRef<String>,
+}
+impl Display for AsOf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "as_of({})", self.at)
+    }
+}
+
 #[derive(Clone)]
 pub struct OrderBy {
-    pub property: String,
+    pub property: GenRef<String>,
     pub order: Order,
+    pub limit: Option<GenRef<String>>,
 }
 impl Display for OrderBy {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "order_by({}, HelixOrder::{})", self.property, self.order)
+        write!(
+            f,
+            "order_by({}, HelixOrder::{}, {})",
+            self.property,
+            self.order,
+            write_limit(&self.limit)
+        )
+    }
+}
+
+/// Fetches an element of an array or a field of a nested object in a property
+#[derive(Clone)]
+pub struct PropertyPath {
+    pub property: GenRef<String>,
+    pub path: Vec<PathSegment>,
+}
+impl Display for PropertyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "check_property_path({}, &[{}])",
+            self.property,
+            self.path
+                .iter()
+                .map(|segment| segment.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+#[derive(Clone)]
+pub enum PathSegment {
+    Field(GenRef<String>),
+    Index(usize),
+}
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Field(field) => write!(f, "PathSegment::Field({})", field),
+            PathSegment::Index(index) => write!(f, "PathSegment::Index({})", index),
+        }
     }
 }
 
@@ -366,12 +544,49 @@ pub struct ShortestPath {
     pub label: Option<GenRef<String>>,
     pub from: Option<GenRef<String>>,
     pub to: Option<GenRef<String>>,
+    pub max_depth: Option<GeneratedValue>,
+    pub weight_property: Option<GenRef<String>>,
 }
 impl Display for ShortestPath {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "shortest_path({}, {}, {})",
+            "shortest_path({}, {}, {}, {}, {})",
+            self.label
+                .clone()
+                .map_or("None".to_string(), |label| format!("Some({})", label)),
+            self.from
+                .clone()
+                .map_or("None".to_string(), |from| format!("Some(&{})", from)),
+            self.to
+                .clone()
+                .map_or("None".to_string(), |to| format!("Some(&{})", to)),
+            self.max_depth
+                .clone()
+                .map_or("None".to_string(), |max_depth| format!(
+                    "Some({})",
+                    max_depth
+                )),
+            self.weight_property
+                .clone()
+                .map_or("None".to_string(), |weight| format!("Some({})", weight))
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct ShortestPathWeighted {
+    pub label: Option<GenRef<String>>,
+    pub from: Option<GenRef<String>>,
+    pub to: Option<GenRef<String>>,
+    pub weight_property: GenRef<String>,
+    pub heuristic_property: Option<GenRef<String>>,
+}
+impl Display for ShortestPathWeighted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "shortest_path_weighted({}, {}, {}, {}, {})",
             self.label
                 .clone()
                 .map_or("None".to_string(), |label| format!("Some({})", label)),
@@ -380,7 +595,14 @@ impl Display for ShortestPath {
                 .map_or("None".to_string(), |from| format!("Some(&{})", from)),
             self.to
                 .clone()
-                .map_or("None".to_string(), |to| format!("Some(&{})", to))
+                .map_or("None".to_string(), |to| format!("Some(&{})", to)),
+            self.weight_property,
+            self.heuristic_property
+                .clone()
+                .map_or("None".to_string(), |heuristic| format!(
+                    "Some({})",
+                    heuristic
+                ))
         )
     }
 }
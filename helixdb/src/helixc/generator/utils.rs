@@ -5,7 +5,7 @@ use std::{
 
 use crate::helixc::parser::helix_parser::IdType;
 
-use super::tsdisplay::ToTypeScript;
+use super::expression::GeneratedExpression;
 
 #[derive(Clone)]
 pub enum GenRef<T>
@@ -68,6 +68,14 @@ where
             GenRef::Id(t) => panic!("Cannot get inner of unknown"),
         }
     }
+
+    /// The value if it is written as a literal
+    pub fn literal(&self) -> Option<&T> {
+        match self {
+            GenRef::Literal(t) => Some(t),
+            _ => None,
+        }
+    }
 }
 impl<T> Debug for GenRef<T>
 where
@@ -128,6 +136,13 @@ impl Display for Order {
     }
 }
 
+pub fn write_limit(limit: &Option<GenRef<String>>) -> String {
+    match limit {
+        Some(limit) => format!("Some({})", limit),
+        None => "None".to_string(),
+    }
+}
+
 pub fn write_properties(properties: &Option<Vec<(String, GeneratedValue)>>) -> String {
     match properties {
         Some(properties) => format!(
@@ -163,6 +178,8 @@ pub enum GeneratedValue {
     Identifier(GenRef<String>),
     Primitive(GenRef<String>),
     Parameter(GenRef<String>),
+    /// A value computed by an expression, compared by value across numeric types
+    Computed(Box<GeneratedExpression>),
     Unknown,
 }
 
@@ -173,6 +190,7 @@ impl Display for GeneratedValue {
             GeneratedValue::Primitive(value) => write!(f, "{}", value),
             GeneratedValue::Identifier(value) => write!(f, "{}", value),
             GeneratedValue::Parameter(value) => write!(f, "{}", value),
+            GeneratedValue::Computed(expr) => write!(f, "Computed({})", expr),
             GeneratedValue::Unknown => write!(f, ""),
         }
     }
@@ -184,6 +202,7 @@ impl Debug for GeneratedValue {
             GeneratedValue::Primitive(value) => write!(f, "Primitive({})", value),
             GeneratedValue::Identifier(value) => write!(f, "Identifier({})", value),
             GeneratedValue::Parameter(value) => write!(f, "Parameter({})", value),
+            GeneratedValue::Computed(expr) => write!(f, "Computed({})", expr),
             GeneratedValue::Unknown => write!(f, "Unknown"),
         }
     }
@@ -195,6 +214,8 @@ pub enum GeneratedType {
     Vec(Box<GeneratedType>),
     Object(GenRef<String>),
     Variable(GenRef<String>),
+    /// A type declared with `ENUM`, generated as a Rust enum
+    Enum(GenRef<String>),
 }
 
 impl Display for GeneratedType {
@@ -203,10 +224,23 @@ impl Display for GeneratedType {
             GeneratedType::RustType(t) => write!(f, "{}", t),
             GeneratedType::Vec(t) => write!(f, "Vec<{}>", t),
             GeneratedType::Variable(v) => write!(f, "{}", v),
+            GeneratedType::Enum(e) => write!(f, "{}", e),
             GeneratedType::Object(o) => write!(f, "{}", o),
         }
     }
 }
+impl GeneratedType {
+    pub fn to_ts(&self) -> String {
+        match self {
+            GeneratedType::RustType(t) => t.to_ts(),
+            GeneratedType::Vec(t) => format!("Array<{}>", t.to_ts()),
+            // object parameters get an interface of their own
+            GeneratedType::Object(o) => o.inner().clone(),
+            GeneratedType::Variable(_) => "any".to_string(),
+            GeneratedType::Enum(e) => e.inner().clone(),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub enum RustType {
@@ -264,7 +298,7 @@ impl RustType {
             RustType::F64 => "number",
             RustType::Bool => "boolean",
             RustType::Uuid => "string", // do thee
-            RustType::Date => "Date",   // do thee
+            RustType::Date => "string", // sent as RFC 3339 strings
         };
         s.to_string()
     }
@@ -303,34 +337,42 @@ impl<T: Display> Separator<T> {
 pub fn write_headers() -> String {
     r#"
 
-use crate::helix_storage::heed3::RoTxn;
-use get_routes::handler;
-use helixdb::{field_remapping, identifier_remapping, traversal_remapping, exclude_field};
+use helixdb::helix_storage::heed3::{RoTxn, RwTxn};
+use get_routes::{handler, tx_handler};
+use helixdb::{field_remapping, identifier_remapping, traversal_remapping, coalesce_remapping, expression_remapping, exclude_field};
 use helixdb::helix_engine::vector_core::vector::HVector;
 use helixdb::{
     helix_engine::graph_core::ops::{
         g::G,
-        in_::{in_::InAdapter, in_e::InEdgesAdapter, to_n::ToNAdapter},
-        out::{from_n::FromNAdapter, out::OutAdapter, out_e::OutEdgesAdapter},
+        in_::{in_::InAdapter, in_count::InCountAdapter, in_e::InEdgesAdapter, to_n::ToNAdapter},
+        out::{
+            from_n::FromNAdapter, out::OutAdapter, out_count::OutCountAdapter,
+            out_e::OutEdgesAdapter,
+        },
         source::{
             add_e::{AddEAdapter, EdgeType},
             add_n::AddNAdapter,
             e_from_id::EFromIdAdapter,
+            e_from_index::EFromIndexAdapter,
             e_from_type::EFromTypeAdapter,
             n_from_id::NFromIdAdapter,
             n_from_type::NFromTypeAdapter,
+            n_from_type_ordered::NFromTypeOrderedAdapter,
             n_from_index::NFromIndexAdapter,
         },
         tr_val::{Traversable, TraversalVal},
         util::{
-            dedup::DedupAdapter, filter_mut::FilterMut,
+            as_of::AsOfAdapter, dedup::DedupAdapter, filter_mut::FilterMut,
             filter_ref::FilterRefAdapter, range::RangeAdapter, update::UpdateAdapter,
             map::MapAdapter, paths::ShortestPathAdapter, props::PropsAdapter, drop::Drop,
+            order_by::{HelixOrder, OrderByAdapter},
         },
         vectors::{insert::InsertVAdapter, search::SearchVAdapter, brute_force_search::BruteForceSearchVAdapter},
-        bm25::search_bm25::SearchBM25Adapter,
+        bm25::{search_bm25::SearchBM25Adapter, hybrid_search::HybridSearchAdapter},
+        analytics::analytics::{AnalyticsAdapter, CommunitiesAdapter},
         
     },
+    helix_engine::graph_core::projection::Projection,
     helix_engine::types::GraphError,
     helix_gateway::router::router::HandlerInput,
     node_matches, props,
@@ -339,7 +381,7 @@ use helixdb::{
     protocol::response::Response,
     protocol::traversal_value::TraversalValue,
     protocol::{
-        filterable::Filterable, remapping::Remapping, return_values::ReturnValue, value::Value, id::ID,
+        enums::EnumField, expression::{self, Computed}, filterable::Filterable, redaction::SensitiveField, remapping::Remapping, return_values::ReturnValue, value::{PathSegment, Value}, id::ID,
     },
 };
 use sonic_rs::{Deserialize, Serialize};
@@ -351,3 +393,50 @@ use chrono::{DateTime, Utc};
     "#
     .to_string()
 }
+
+/// Start of the generated TypeScript client, the helper the query functions send their
+/// requests with
+pub fn write_ts_headers() -> String {
+    r#"// Generated by `helix compile --target ts` from the schema and queries, changes
+// to it are overwritten when it is generated again
+
+export interface HelixOptions {
+  /** Address of the instance, e.g. http://localhost:6969 */
+  url: string;
+  /** Sent as the x-api-key header of every request if set */
+  apiKey?: string;
+}
+
+async function runQuery<T>(options: HelixOptions, query: string, input: unknown): Promise<T> {
+  const headers: Record<string, string> = { "Content-Type": "application/json" };
+  if (options.apiKey) {
+    headers["x-api-key"] = options.apiKey;
+  }
+  const response = await fetch(`${options.url}/${query}`, {
+    method: "POST",
+    headers,
+    body: JSON.stringify(input),
+  });
+  if (!response.ok) {
+    throw new Error(`${query} failed with ${response.status}: ${await response.text()}`);
+  }
+  return (await response.json()) as T;
+}
+"#
+    .to_string()
+}
+
+/// The name as a key of a TypeScript interface, quoted if it isn't an identifier
+pub fn ts_key(name: &str) -> String {
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    match is_identifier {
+        true => name.to_string(),
+        false => format!("{:?}", name),
+    }
+}
@@ -1,3 +1,7 @@
 pub mod analyzer;
+#[cfg(feature = "cypher")]
+pub mod cypher;
+#[cfg(test)]
+pub mod fuzz_tests;
 pub mod generator;
 pub mod parser;
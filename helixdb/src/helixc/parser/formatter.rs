@@ -0,0 +1,1382 @@
+//! Pretty-printer for `.hx` files, writing the schemas and queries of a file back from
+//! their AST in a single layout.
+//!
+//! Definitions are separated by a blank line, fields and statements get a line each
+//! and are indented by four spaces. Fields Helix keeps unordered, the object types of
+//! the schema and the fields of object literals, are sorted by name, edges are
+//! written `From` first. Comments aren't part of the AST, they're read from the text
+//! and kept before or after the definition, field or statement they were next to.
+
+use super::{helix_parser::*, location::Loc, parser_methods::ParserError};
+use crate::protocol::value::Value;
+use std::collections::HashMap;
+
+const INDENT: &str = "    ";
+/// Objects longer than this on one line get a line per field
+const MAX_OBJECT_WIDTH: usize = 80;
+
+/// Formats the schemas and queries of a `.hx` file
+pub fn format_hx(name: &str, content: &str) -> Result<String, ParserError> {
+    let source = parse(name, content)?;
+    let mut formatter = Formatter::new(content);
+    formatter.source(&source)?;
+    let formatted = formatter.finish();
+    parse(name, &formatted).map_err(|e| {
+        ParserError::from(format!(
+            "{} was formatted into invalid HelixQL: {}",
+            name, e
+        ))
+    })?;
+    Ok(formatted)
+}
+
+/// Writes the schemas of a source as one file, for the schema of a project whose
+/// `schema.hx` imports others to be read on its own
+pub fn format_schemas(source: &Source) -> Result<String, ParserError> {
+    let schemas = Source {
+        queries: Vec::new(),
+        imports: Vec::new(),
+        ..source.clone()
+    };
+    let mut formatter = Formatter::new("");
+    formatter.source(&schemas)?;
+    Ok(formatter.finish())
+}
+
+fn parse(name: &str, content: &str) -> Result<Source, ParserError> {
+    HelixParser::parse_source(&Content {
+        content: String::new(),
+        source: Source::default(),
+        files: vec![HxFile {
+            name: name.to_string(),
+            content: content.to_string(),
+        }],
+    })
+}
+
+struct Comment {
+    line: usize,
+    text: String,
+}
+
+/// The `//` comments of a file, skipping the ones in string literals
+fn comments(content: &str) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let (mut line, mut in_string, mut at) = (1, false, 0);
+    while let Some(c) = content[at..].chars().next() {
+        if c == '/' && !in_string && content[at..].starts_with("//") {
+            let end = content[at..]
+                .find('\n')
+                .map_or(content.len(), |end| at + end);
+            comments.push(Comment {
+                line,
+                text: content[at..end].trim_end().to_string(),
+            });
+            at = end;
+            continue;
+        }
+        match c {
+            '\n' => line += 1,
+            '"' => in_string = !in_string,
+            _ => {}
+        }
+        at += c.len_utf8();
+    }
+    comments
+}
+
+/// A definition at the top of a file
+enum Item<'a> {
+    Import(&'a Import),
+    Node(&'a NodeSchema),
+    Edge(&'a EdgeSchema),
+    Vector(&'a VectorSchema),
+    Enum(&'a EnumSchema),
+    Query(&'a Query),
+}
+
+impl<'a> Item<'a> {
+    fn loc(&self) -> &'a Loc {
+        match self {
+            Item::Import(import) => &import.loc,
+            Item::Node(node) => &node.loc,
+            Item::Edge(edge) => &edge.loc,
+            Item::Vector(vector) => &vector.loc,
+            Item::Enum(enum_schema) => &enum_schema.loc,
+            Item::Query(query) => &query.loc,
+        }
+    }
+
+    fn namespace(&self) -> Option<&'a str> {
+        match self {
+            Item::Node(node) => node.namespace.as_deref(),
+            Item::Edge(edge) => edge.namespace.as_deref(),
+            Item::Vector(vector) => vector.namespace.as_deref(),
+            Item::Import(_) | Item::Enum(_) | Item::Query(_) => None,
+        }
+    }
+
+    /// Where the item is in the source, the files of a source kept apart
+    fn position(&self) -> (Option<&'a str>, usize, usize) {
+        let loc = self.loc();
+        (loc.filepath.as_deref(), loc.start.line, loc.start.column)
+    }
+}
+
+struct Formatter {
+    out: String,
+    comments: Vec<Comment>,
+    /// The first comment not written yet
+    next: usize,
+    /// Line of the source the last thing written ended on, to keep blank lines
+    last_line: usize,
+    /// A block was just opened, so no blank line is kept before its first line
+    opened: bool,
+    /// Line of the last field or statement written, whose comment is written after it
+    /// once nothing else on the line follows
+    pending: Option<usize>,
+}
+
+impl Formatter {
+    fn new(content: &str) -> Self {
+        Self {
+            out: String::new(),
+            comments: comments(content),
+            next: 0,
+            last_line: 0,
+            opened: false,
+            pending: None,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.comments_before(usize::MAX, 0);
+        let mut out = self.out.trim_end().to_string();
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+
+    fn line(&mut self, indent: usize, text: &str) {
+        self.flush_pending();
+        self.out.push_str(&INDENT.repeat(indent));
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.opened = false;
+    }
+
+    /// Writes the first line of a block starting on `line`, whose first field or
+    /// statement is on `first`
+    fn open(&mut self, indent: usize, text: &str, line: usize, first: usize) {
+        self.line(indent, text);
+        if first > line {
+            self.trailing(line);
+        }
+        self.last_line = line;
+        self.opened = true;
+    }
+
+    fn close(&mut self, indent: usize, line: usize) {
+        self.comments_before(line, indent + 1);
+        self.line(indent, "}");
+        self.trailing(line);
+        self.last_line = line;
+    }
+
+    fn blank(&mut self) {
+        self.flush_pending();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.opened {
+            self.out.push('\n');
+        }
+    }
+
+    /// Keeps a blank line the source had before `line`
+    fn gap(&mut self, line: usize) {
+        if self.last_line != 0 && line > self.last_line + 1 {
+            self.blank();
+        }
+    }
+
+    /// Writes the comments before `line` on lines of their own
+    fn comments_before(&mut self, line: usize, indent: usize) {
+        self.flush_pending();
+        while let Some(comment) = self.comments.get(self.next) {
+            if comment.line >= line {
+                break;
+            }
+            let (comment_line, text) = (comment.line, comment.text.clone());
+            self.gap(comment_line);
+            self.line(indent, &text);
+            self.last_line = comment_line;
+            self.next += 1;
+        }
+    }
+
+    /// Writes the comment at the end of `line` at the end of the last line written
+    fn trailing(&mut self, line: usize) {
+        if let Some(comment) = self.comments.get(self.next) {
+            if comment.line == line {
+                self.out.pop();
+                self.out.push(' ');
+                self.out.push_str(&comment.text);
+                self.out.push('\n');
+                self.next += 1;
+            }
+        }
+    }
+
+    fn flush_pending(&mut self) {
+        if let Some(line) = self.pending.take() {
+            self.trailing(line);
+        }
+    }
+
+    /// Writes something without comments of its own on the source lines `start` to
+    /// `end`, the comments inside it are moved before it
+    fn leaf(&mut self, indent: usize, start: usize, end: usize, text: &str) {
+        // the comment at the end of the line is this one's
+        if self.pending == Some(start) {
+            self.pending = None;
+        }
+        self.comments_before(end, indent);
+        self.gap(start);
+        self.line(indent, text);
+        self.pending = Some(end);
+        self.last_line = end;
+    }
+
+    fn source(&mut self, source: &Source) -> Result<(), ParserError> {
+        let mut items = source
+            .imports
+            .iter()
+            .map(Item::Import)
+            .chain(source.node_schemas.iter().map(Item::Node))
+            .chain(source.edge_schemas.iter().map(Item::Edge))
+            .chain(source.vector_schemas.iter().map(Item::Vector))
+            .chain(source.enum_schemas.iter().map(Item::Enum))
+            .chain(source.queries.iter().map(Item::Query))
+            .collect::<Vec<_>>();
+        items.sort_by_key(Item::position);
+
+        let mut namespace = None;
+        for item in items {
+            if item.namespace() != namespace {
+                if namespace.is_some() {
+                    self.line(0, "}");
+                }
+                namespace = item.namespace();
+                if let Some(name) = namespace {
+                    self.blank();
+                    let line = item.loc().start.line;
+                    self.comments_before(line, 0);
+                    self.gap(line);
+                    self.line(0, &format!("NAMESPACE {} {{", name));
+                    self.opened = true;
+                }
+            }
+            let indent = namespace.map_or(0, |_| 1);
+            // imports are written as a group of lines
+            if !matches!(item, Item::Import(_)) {
+                self.blank();
+            }
+            match item {
+                Item::Import(import) => self.import(import),
+                Item::Node(node) => self.schema(
+                    indent,
+                    &format!("N::{}", node.name.1),
+                    &node.fields,
+                    &node.loc,
+                ),
+                Item::Vector(vector) => self.schema(
+                    indent,
+                    &format!("V::{}", vector.name),
+                    &vector.fields,
+                    &vector.loc,
+                ),
+                Item::Edge(edge) => self.edge(indent, edge),
+                Item::Enum(enum_schema) => self.enum_schema(enum_schema),
+                Item::Query(query) => self.query(query)?,
+            }
+        }
+        if namespace.is_some() {
+            self.line(0, "}");
+        }
+        Ok(())
+    }
+
+    fn import(&mut self, import: &Import) {
+        let text = match &import.namespace {
+            Some(namespace) => format!("import {} as {}", quote(&import.path), namespace),
+            None => format!("import {}", quote(&import.path)),
+        };
+        self.leaf(0, import.loc.start.line, end_line(&import.loc), &text);
+    }
+
+    fn schema(&mut self, indent: usize, name: &str, fields: &[Field], loc: &Loc) {
+        let (start, end) = (loc.start.line, end_line(loc));
+        let first = fields.first().map_or(end, |field| field.loc.start.line);
+        self.comments_before(start, indent);
+        self.gap(start);
+        self.open(indent, &format!("{} {{", name), start, first);
+        self.fields(indent + 1, fields);
+        self.close(indent, end);
+    }
+
+    fn edge(&mut self, indent: usize, edge: &EdgeSchema) {
+        let (start, end) = (edge.loc.start.line, end_line(&edge.loc));
+        self.comments_before(start, indent);
+        self.gap(start);
+        let first = edge.from.0.start.line;
+        self.open(indent, &format!("E::{} {{", edge.name.1), start, first);
+        for (key, (loc, name)) in [("From", &edge.from), ("To", &edge.to)] {
+            let text = format!("{}: {},", key, name);
+            self.leaf(indent + 1, loc.start.line, end_line(loc), &text);
+        }
+        if let Some(properties) = &edge.properties {
+            match properties.first() {
+                Some(field) => {
+                    self.comments_before(field.loc.start.line, indent + 1);
+                    self.line(indent + 1, "Properties: {");
+                    self.opened = true;
+                    self.fields(indent + 2, properties);
+                    self.line(indent + 1, "}");
+                }
+                None => self.line(indent + 1, "Properties: {}"),
+            }
+        }
+        self.close(indent, end);
+    }
+
+    fn enum_schema(&mut self, enum_schema: &EnumSchema) {
+        let loc = &enum_schema.loc;
+        let (start, end) = (loc.start.line, end_line(loc));
+        let first = enum_schema
+            .variants
+            .first()
+            .map_or(end, |(loc, _)| loc.start.line);
+        self.comments_before(start, 0);
+        self.gap(start);
+        self.open(0, &format!("ENUM {} {{", enum_schema.name.1), start, first);
+        for (loc, variant) in &enum_schema.variants {
+            let text = format!("{},", variant);
+            self.leaf(1, loc.start.line, end_line(loc), &text);
+        }
+        self.close(0, end);
+    }
+
+    fn fields(&mut self, indent: usize, fields: &[Field]) {
+        for field in fields {
+            let prefix = match field.prefix {
+                FieldPrefix::Unique => "UNIQUE INDEX ",
+                FieldPrefix::Index => "INDEX ",
+                FieldPrefix::Optional | FieldPrefix::Empty => "",
+            };
+            let default = match &field.defaults {
+                Some(default) if !matches!(default, DefaultValue::Empty) => {
+                    format!(" DEFAULT {}", default_value(default))
+                }
+                _ => String::new(),
+            };
+            let sensitive = match &field.sensitive {
+                Some(roles) if roles.is_empty() => " @sensitive".to_string(),
+                Some(roles) => format!(" @sensitive({})", roles.join(", ")),
+                None => String::new(),
+            };
+            let text = format!(
+                "{}{}: {}{}{},",
+                prefix,
+                field.name,
+                field_type(&field.field_type),
+                default,
+                sensitive
+            );
+            self.leaf(indent, field.loc.start.line, end_line(&field.loc), &text);
+        }
+    }
+
+    fn query(&mut self, query: &Query) -> Result<(), ParserError> {
+        let start = query.loc.start.line;
+        self.comments_before(start, 0);
+        self.gap(start);
+        let parameters = query
+            .parameters
+            .iter()
+            .map(|parameter| {
+                let mut parameter_def = format!(
+                    "{}: {}",
+                    parameter.name.1,
+                    field_type(&parameter.param_type.1)
+                );
+                if parameter.is_optional {
+                    parameter_def.push('?');
+                }
+                if let Some(default) = &parameter.default_value {
+                    parameter_def.push_str(&format!(" = {}", value_type(default, 0)));
+                }
+                parameter_def
+            })
+            .collect::<Vec<_>>();
+        let values = query
+            .return_values
+            .iter()
+            .map(|value| expression(value, 1))
+            .collect::<Result<Vec<_>, _>>()?;
+        let end = end_line(&query.loc);
+        let (return_start, return_end) =
+            match (query.return_values.first(), query.return_values.last()) {
+                (Some(first), Some(last)) => (first.loc.start.line, end_line(&last.loc)),
+                _ => (end, end),
+            };
+        let first = query
+            .statements
+            .first()
+            .map_or(return_start, |statement| statement.loc.start.line);
+        self.open(
+            0,
+            &format!("QUERY {}({}) =>", query.name, parameters.join(", ")),
+            start,
+            first,
+        );
+        self.statements(1, &query.statements)?;
+        self.leaf(
+            1,
+            return_start,
+            return_end,
+            &format!("RETURN {}", values.join(", ")),
+        );
+        Ok(())
+    }
+
+    fn statements(&mut self, indent: usize, statements: &[Statement]) -> Result<(), ParserError> {
+        for statement in statements {
+            let (start, end) = (statement.loc.start.line, end_line(&statement.loc));
+            match &statement.statement {
+                StatementType::ForLoop(for_loop) => {
+                    let variable = match &for_loop.variable {
+                        ForLoopVars::Identifier { name, .. } => name.clone(),
+                        ForLoopVars::ObjectAccess { name, field, .. } => {
+                            format!("{}.{}", name, field)
+                        }
+                        ForLoopVars::ObjectDestructuring { fields, .. } => format!(
+                            "{{{}}}",
+                            fields
+                                .iter()
+                                .map(|(_, field)| field.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    };
+                    self.comments_before(start, indent);
+                    self.gap(start);
+                    self.open(
+                        indent,
+                        &format!("FOR {} IN {} {{", variable, for_loop.in_variable.1),
+                        start,
+                        for_loop
+                            .statements
+                            .first()
+                            .map_or(end, |statement| statement.loc.start.line),
+                    );
+                    self.statements(indent + 1, &for_loop.statements)?;
+                    self.close(indent, end);
+                }
+                statement => {
+                    let text = self::statement(statement, indent)?;
+                    self.leaf(indent, start, end, &text);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The last line of `loc`, whose span may end with the whitespace and comments after it
+fn end_line(loc: &Loc) -> usize {
+    let lines = loc.span.lines().collect::<Vec<_>>();
+    let code = lines
+        .iter()
+        .rposition(|line| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with("//")
+        })
+        .unwrap_or(0);
+    loc.start.line + code
+}
+
+fn statement(statement: &StatementType, indent: usize) -> Result<String, ParserError> {
+    Ok(match statement {
+        StatementType::Assignment(assignment) => format!(
+            "{} <- {}",
+            assignment.variable,
+            expression(&assignment.value, indent)?
+        ),
+        StatementType::AddVector(add) => add_vector(add, indent),
+        StatementType::AddNode(add) => add_node(add, indent),
+        StatementType::AddEdge(add) => add_edge(add, indent),
+        StatementType::Drop(expr) => format!("DROP {}", expression(expr, indent)?),
+        StatementType::SearchVector(search) => search_vector(search, indent)?,
+        StatementType::BatchAddVector(add) => batch_add_vector(add),
+        StatementType::BM25Search(search) => bm25_search(search, indent),
+        StatementType::ForLoop(_) => unreachable!("for loops are written as blocks"),
+    })
+}
+
+fn expression(expr: &Expression, indent: usize) -> Result<String, ParserError> {
+    Ok(match &expr.expr {
+        ExpressionType::Traversal(traversal) => self::traversal(traversal, indent)?,
+        ExpressionType::Identifier(name) => name.clone(),
+        ExpressionType::StringLiteral(s) => quote(s),
+        ExpressionType::IntegerLiteral(i) => i.to_string(),
+        ExpressionType::FloatLiteral(f) => float(f.to_string()),
+        ExpressionType::BooleanLiteral(b) => b.to_string(),
+        ExpressionType::Exists(expr) => format!("EXISTS({})", expression(expr, indent)?),
+        ExpressionType::Not(expr) => match expr.expr {
+            ExpressionType::Exists(_) => format!("NOT {}", expression(expr, indent)?),
+            _ => format!("NOT({})", expression(expr, indent)?),
+        },
+        ExpressionType::BatchAddVector(add) => batch_add_vector(add),
+        ExpressionType::AddVector(add) => add_vector(add, indent),
+        ExpressionType::AddNode(add) => add_node(add, indent),
+        ExpressionType::AddEdge(add) => add_edge(add, indent),
+        ExpressionType::And(exprs) => format!("AND({})", expressions(exprs, indent)?),
+        ExpressionType::Or(exprs) => format!("OR({})", expressions(exprs, indent)?),
+        ExpressionType::SearchVector(search) => search_vector(search, indent)?,
+        ExpressionType::BM25Search(search) => bm25_search(search, indent),
+        ExpressionType::HybridSearch(search) => format!(
+            "HybridSearch<{}>({}, {}, {})",
+            search.node_type.as_deref().unwrap_or_default(),
+            search.vector.as_ref().map(vector_data).unwrap_or_default(),
+            search
+                .query
+                .as_ref()
+                .map(|query| value_type(query, indent))
+                .unwrap_or_default(),
+            search.k.as_ref().map(number).unwrap_or_default()
+        ),
+        ExpressionType::Arithmetic { op, lhs, rhs } => {
+            let lhs = operand(lhs, precedence(*op), false, indent)?;
+            let rhs = operand(rhs, precedence(*op), true, indent)?;
+            format!("{} {} {}", lhs, op, rhs)
+        }
+        ExpressionType::Function { function, arg } => {
+            format!("{}({})", function, expression(arg, indent)?)
+        }
+        ExpressionType::Compared { expr: subject, op } => {
+            let subject = match subject.expr {
+                ExpressionType::Function { .. } => expression(subject, indent)?,
+                _ => format!("({})", expression(subject, indent)?),
+            };
+            format!("{}::{}", subject, boolean_op(op, indent)?)
+        }
+        ExpressionType::Conditional {
+            condition,
+            then,
+            otherwise,
+        } => format!(
+            "IF {} THEN {} ELSE {}",
+            expression(condition, indent)?,
+            expression(then, indent)?,
+            expression(otherwise, indent)?
+        ),
+        ExpressionType::Empty => "NONE".to_string(),
+    })
+}
+
+fn precedence(op: ArithmeticOp) -> u8 {
+    match op {
+        ArithmeticOp::Add | ArithmeticOp::Sub => 0,
+        ArithmeticOp::Mul | ArithmeticOp::Div => 1,
+    }
+}
+
+/// An operand of an arithmetic expression, parenthesized where the operators are
+/// left-associative and `*` and `/` bind tighter than `+` and `-`
+fn operand(
+    expr: &Expression,
+    parent: u8,
+    is_rhs: bool,
+    indent: usize,
+) -> Result<String, ParserError> {
+    let out = expression(expr, indent)?;
+    Ok(match &expr.expr {
+        ExpressionType::Arithmetic { op, .. }
+            if precedence(*op) < parent || (is_rhs && precedence(*op) == parent) =>
+        {
+            format!("({})", out)
+        }
+        _ => out,
+    })
+}
+
+fn boolean_op(op: &BooleanOp, indent: usize) -> Result<String, ParserError> {
+    let (name, expr) = match &op.op {
+        BooleanOpType::And(exprs) => return Ok(format!("AND({})", expressions(exprs, indent)?)),
+        BooleanOpType::Or(exprs) => return Ok(format!("OR({})", expressions(exprs, indent)?)),
+        BooleanOpType::GreaterThan(expr) => ("GT", expr),
+        BooleanOpType::GreaterThanOrEqual(expr) => ("GTE", expr),
+        BooleanOpType::LessThan(expr) => ("LT", expr),
+        BooleanOpType::LessThanOrEqual(expr) => ("LTE", expr),
+        BooleanOpType::Equal(expr) => ("EQ", expr),
+        BooleanOpType::NotEqual(expr) => ("NEQ", expr),
+    };
+    Ok(format!("{}({})", name, expression(expr, indent)?))
+}
+
+fn expressions(exprs: &[Expression], indent: usize) -> Result<String, ParserError> {
+    Ok(exprs
+        .iter()
+        .map(|expr| expression(expr, indent))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", "))
+}
+
+fn traversal(traversal: &Traversal, indent: usize) -> Result<String, ParserError> {
+    let mut out = match &traversal.start {
+        StartNode::Node { node_type, ids } => format!("N{}{}", type_arg(node_type), id_args(ids)),
+        StartNode::Edge { edge_type, ids } => format!("E{}{}", type_arg(edge_type), id_args(ids)),
+        StartNode::Identifier(name) => name.clone(),
+        StartNode::Analytics(analytics) => {
+            let algorithm = match analytics.algorithm {
+                AnalyticsAlgorithm::PageRank => "PageRank",
+                AnalyticsAlgorithm::Betweenness => "Betweenness",
+                AnalyticsAlgorithm::Components => "Components",
+                AnalyticsAlgorithm::Communities => "Communities",
+            };
+            format!(
+                "Analytics::{}{}{}",
+                algorithm,
+                analytics
+                    .edge_type
+                    .as_deref()
+                    .map(type_arg)
+                    .unwrap_or_default(),
+                analytics
+                    .arg
+                    .as_ref()
+                    .map(|arg| format!("({})", number(arg)))
+                    .unwrap_or_default()
+            )
+        }
+        // traversals starting from vectors are parsed as anonymous ones as well
+        StartNode::Anonymous if traversal.loc.span.starts_with('_') => "_".to_string(),
+        StartNode::Anonymous => {
+            return Err(ParserError::from(format!(
+                "Can't format `{}` on line {}",
+                traversal.loc.span.trim(),
+                traversal.loc.start.line
+            )))
+        }
+    };
+    for step in &traversal.steps {
+        out.push_str("::");
+        out.push_str(&self::step(step, indent)?);
+    }
+    Ok(out)
+}
+
+fn step(step: &Step, indent: usize) -> Result<String, ParserError> {
+    Ok(match &step.step {
+        StepType::Node(step) | StepType::Edge(step) => graph_step(step, indent)?,
+        StepType::Where(expr) => format!("WHERE({})", expression(expr, indent)?),
+        StepType::BooleanOperation(op) => boolean_op(op, indent)?,
+        StepType::Count => "COUNT".to_string(),
+        StepType::Update(update) => format!(
+            "UPDATE({})",
+            object(field_additions(&update.fields, indent + 1)?, indent)
+        ),
+        // `::ID` is parsed as the remapping `::{id}`
+        StepType::Object(remapping) if is_id(remapping) => "ID".to_string(),
+        StepType::Object(remapping) => object_step(remapping, indent)?,
+        StepType::Exclude(exclude) => {
+            let mut fields = exclude
+                .fields
+                .iter()
+                .filter(|(_, field)| !field.starts_with(".."))
+                .map(|(_, field)| field.clone())
+                .collect::<Vec<_>>();
+            if exclude
+                .fields
+                .iter()
+                .any(|(_, field)| field.starts_with(".."))
+            {
+                fields.push("..".to_string());
+            }
+            format!("!{}", object(fields, indent))
+        }
+        StepType::Closure(closure) => format!(
+            "|{}|{}",
+            closure.identifier,
+            object_step(&closure.object, indent)?
+        ),
+        StepType::Range((start, end)) => format!(
+            "RANGE({}, {})",
+            expression(start, indent)?,
+            expression(end, indent)?
+        ),
+        StepType::Limit((start, end)) => format!(
+            "Range({}, {})",
+            expression(start, indent)?,
+            expression(end, indent)?
+        ),
+        StepType::OrderBy(order_by) => format!(
+            "OrderBy({}, {})",
+            order_by.field,
+            match order_by.order {
+                Order::Asc => "Asc",
+                Order::Desc => "Desc",
+            }
+        ),
+        StepType::AsOf(at) => format!("AsOf({})", expression(at, indent)?),
+        StepType::AddEdge(add) => add_edge(add, indent),
+    })
+}
+
+fn graph_step(step: &GraphStep, indent: usize) -> Result<String, ParserError> {
+    Ok(match &step.step {
+        GraphStepType::Out(label) => format!("Out<{}>", label),
+        GraphStepType::In(label) => format!("In<{}>", label),
+        GraphStepType::FromN => "FromN".to_string(),
+        GraphStepType::ToN => "ToN".to_string(),
+        GraphStepType::OutE(label) => format!("OutE<{}>", label),
+        GraphStepType::InE(label) => format!("InE<{}>", label),
+        GraphStepType::OutCount(label) => format!("OutCount<{}>", label),
+        GraphStepType::InCount(label) => format!("InCount<{}>", label),
+        GraphStepType::ShortestPath(path) => {
+            let mut options = Vec::new();
+            if let Some(max_depth) = &path.max_depth {
+                options.push(format!("max_depth: {}", number(max_depth)));
+            }
+            if let Some(weight) = &path.weight_property {
+                options.push(format!("weight: {}", weight));
+            }
+            format!(
+                "ShortestPath{}{}{}",
+                path.type_arg.as_deref().map(type_arg).unwrap_or_default(),
+                if options.is_empty() {
+                    String::new()
+                } else {
+                    format!("({})", options.join(", "))
+                },
+                connection(&path.from, &path.to)
+            )
+        }
+        GraphStepType::ShortestPathWeighted(path) => format!(
+            "ShortestPathWeighted{}(weight: {}{}){}",
+            path.type_arg.as_deref().map(type_arg).unwrap_or_default(),
+            path.weight_property,
+            path.heuristic_property
+                .as_ref()
+                .map(|heuristic| format!(", heuristic: {}", heuristic))
+                .unwrap_or_default(),
+            connection(&path.from, &path.to)
+        ),
+        GraphStepType::SearchVector(search) => search_vector(search, indent)?,
+    })
+}
+
+fn is_id(remapping: &Object) -> bool {
+    !remapping.should_spread
+        && matches!(
+            remapping.fields.as_slice(),
+            [FieldAddition {
+                key,
+                value: FieldValue {
+                    value: FieldValueType::Identifier(field),
+                    ..
+                },
+                ..
+            }] if key == "id" && field == "id"
+        )
+}
+
+fn object_step(remapping: &Object, indent: usize) -> Result<String, ParserError> {
+    let mut fields = field_additions(&remapping.fields, indent + 1)?;
+    if remapping.should_spread {
+        fields.push("..".to_string());
+    }
+    Ok(object(fields, indent))
+}
+
+fn field_additions(fields: &[FieldAddition], indent: usize) -> Result<Vec<String>, ParserError> {
+    fields
+        .iter()
+        .map(|field| {
+            Ok(match &field.value.value {
+                // `{name}` and `{tags[0]}` take the name of the property
+                FieldValueType::Identifier(name) if *name == field.key => name.clone(),
+                FieldValueType::PropertyPath(path) if path.name() == field.key => {
+                    property_path(path)
+                }
+                _ => format!("{}: {}", field.key, field_value(&field.value, indent)?),
+            })
+        })
+        .collect()
+}
+
+fn field_value(value: &FieldValue, indent: usize) -> Result<String, ParserError> {
+    Ok(match &value.value {
+        FieldValueType::Traversal(traversal) => self::traversal(traversal, indent)?,
+        FieldValueType::Expression(expr) => expression(expr, indent)?,
+        FieldValueType::Fields(fields) => object(field_additions(fields, indent + 1)?, indent),
+        FieldValueType::Literal(value) => literal(value, indent),
+        FieldValueType::Identifier(name) => name.clone(),
+        FieldValueType::Optional(traversal) => {
+            format!("Optional({})", self::traversal(traversal, indent)?)
+        }
+        FieldValueType::Coalesce(values) => format!(
+            "Coalesce({})",
+            values
+                .iter()
+                .map(|value| field_value(value, indent))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(", ")
+        ),
+        FieldValueType::PropertyPath(path) => property_path(path),
+        FieldValueType::Empty => "NONE".to_string(),
+    })
+}
+
+fn property_path(path: &PropertyPath) -> String {
+    path.path
+        .iter()
+        .fold(path.field.clone(), |mut out, segment| {
+            match segment {
+                PathSegment::Field(field) => {
+                    out.push('.');
+                    out.push_str(field);
+                }
+                PathSegment::Index(index) => out.push_str(&format!("[{}]", index)),
+            }
+            out
+        })
+}
+
+fn add_node(add: &AddNode, indent: usize) -> String {
+    format!(
+        "AddN<{}>{}",
+        add.node_type.as_deref().unwrap_or_default(),
+        add.fields
+            .as_ref()
+            .map(|fields| format!("({})", create_fields(fields, indent)))
+            .unwrap_or_default()
+    )
+}
+
+fn add_edge(add: &AddEdge, indent: usize) -> String {
+    format!(
+        "AddE<{}>{}{}",
+        add.edge_type.as_deref().unwrap_or_default(),
+        add.fields
+            .as_ref()
+            .map(|fields| format!("({})", create_fields(fields, indent)))
+            .unwrap_or_default(),
+        connection(&add.connection.from_id, &add.connection.to_id)
+    )
+}
+
+fn add_vector(add: &AddVector, indent: usize) -> String {
+    let mut args = add.data.iter().map(vector_data).collect::<Vec<_>>();
+    if let Some(fields) = &add.fields {
+        args.push(create_fields(fields, indent));
+    }
+    format!(
+        "AddV<{}>({})",
+        add.vector_type.as_deref().unwrap_or_default(),
+        args.join(", ")
+    )
+}
+
+fn batch_add_vector(add: &BatchAddVector) -> String {
+    format!(
+        "BatchAddV<{}>({})",
+        add.vector_type.as_deref().unwrap_or_default(),
+        add.vec_identifier.as_deref().unwrap_or_default()
+    )
+}
+
+fn search_vector(search: &SearchVector, indent: usize) -> Result<String, ParserError> {
+    Ok(format!(
+        "SearchV<{}>({}, {}){}",
+        search.vector_type.as_deref().unwrap_or_default(),
+        search.data.as_ref().map(vector_data).unwrap_or_default(),
+        search.k.as_ref().map(number).unwrap_or_default(),
+        match &search.pre_filter {
+            Some(filter) => format!("::PREFILTER({})", expression(filter, indent)?),
+            None => String::new(),
+        }
+    ))
+}
+
+fn bm25_search(search: &BM25Search, indent: usize) -> String {
+    format!(
+        "SearchBM25<{}>({}, {})",
+        search.type_arg.as_deref().unwrap_or_default(),
+        search
+            .data
+            .as_ref()
+            .map(|data| value_type(data, indent))
+            .unwrap_or_default(),
+        search.k.as_ref().map(number).unwrap_or_default()
+    )
+}
+
+fn connection(from: &Option<IdType>, to: &Option<IdType>) -> String {
+    let mut out = String::new();
+    if let Some(from) = from {
+        out.push_str(&format!("::From({})", id(from)));
+    }
+    if let Some(to) = to {
+        out.push_str(&format!("::To({})", id(to)));
+    }
+    out
+}
+
+/// The fields of an item added, in the order they were written in
+fn create_fields(fields: &HashMap<String, ValueType>, indent: usize) -> String {
+    let mut fields = fields.iter().collect::<Vec<_>>();
+    fields.sort_by_key(|(key, value)| {
+        let loc = value_type_loc(value);
+        (loc.start.line, loc.start.column, key.as_str())
+    });
+    object(
+        fields
+            .into_iter()
+            .map(|(key, value)| format!("{}: {}", key, value_type(value, indent + 1)))
+            .collect(),
+        indent,
+    )
+}
+
+fn value_type_loc(value: &ValueType) -> &Loc {
+    match value {
+        ValueType::Literal { loc, .. }
+        | ValueType::Identifier { loc, .. }
+        | ValueType::Object { loc, .. } => loc,
+    }
+}
+
+fn value_type(value: &ValueType, indent: usize) -> String {
+    match value {
+        ValueType::Literal { value, .. } => literal(value, indent),
+        ValueType::Identifier { value, .. } => value.clone(),
+        ValueType::Object { fields, .. } => create_fields(fields, indent),
+    }
+}
+
+fn literal(value: &Value, indent: usize) -> String {
+    match value {
+        Value::String(s) => quote(s),
+        Value::F32(f) => float(f.to_string()),
+        Value::F64(f) => float(f.to_string()),
+        Value::I8(i) => i.to_string(),
+        Value::I16(i) => i.to_string(),
+        Value::I32(i) => i.to_string(),
+        Value::I64(i) => i.to_string(),
+        Value::U8(i) => i.to_string(),
+        Value::U16(i) => i.to_string(),
+        Value::U32(i) => i.to_string(),
+        Value::U64(i) => i.to_string(),
+        Value::U128(i) => i.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Array(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .map(|value| literal(value, indent))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(fields) => {
+            let mut fields = fields.iter().collect::<Vec<_>>();
+            fields.sort_by_key(|(key, _)| *key);
+            object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| format!("{}: {}", key, literal(value, indent + 1)))
+                    .collect(),
+                indent,
+            )
+        }
+        Value::Empty => "NONE".to_string(),
+        Value::DateTime(date) => quote(&date.to_rfc3339()),
+    }
+}
+
+fn default_value(default: &DefaultValue) -> String {
+    match default {
+        DefaultValue::Now => "NOW".to_string(),
+        DefaultValue::Uuid => "UUID".to_string(),
+        DefaultValue::Ulid => "ULID".to_string(),
+        DefaultValue::String(s) => quote(s),
+        DefaultValue::F32(f) => float(f.to_string()),
+        DefaultValue::F64(f) => float(f.to_string()),
+        DefaultValue::I8(i) => i.to_string(),
+        DefaultValue::I16(i) => i.to_string(),
+        DefaultValue::I32(i) => i.to_string(),
+        DefaultValue::I64(i) => i.to_string(),
+        DefaultValue::U8(i) => i.to_string(),
+        DefaultValue::U16(i) => i.to_string(),
+        DefaultValue::U32(i) => i.to_string(),
+        DefaultValue::U64(i) => i.to_string(),
+        DefaultValue::U128(i) => i.to_string(),
+        DefaultValue::Boolean(b) => b.to_string(),
+        DefaultValue::Empty => String::new(),
+    }
+}
+
+fn field_type(field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Array(item) => format!("[{}]", self::field_type(item)),
+        FieldType::Object(fields) => {
+            let mut fields = fields.iter().collect::<Vec<_>>();
+            fields.sort_by_key(|(key, _)| *key);
+            format!(
+                "{{{}}}",
+                fields
+                    .into_iter()
+                    .map(|(name, field_type)| format!("{}: {}", name, self::field_type(field_type)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        // `Date` is parsed into the same type
+        FieldType::Date => "DateTime".to_string(),
+        field_type => field_type.to_string(),
+    }
+}
+
+fn number(number: &EvaluatesToNumber) -> String {
+    match &number.value {
+        EvaluatesToNumberType::I8(i) => i.to_string(),
+        EvaluatesToNumberType::I16(i) => i.to_string(),
+        EvaluatesToNumberType::I32(i) => i.to_string(),
+        EvaluatesToNumberType::I64(i) => i.to_string(),
+        EvaluatesToNumberType::U8(i) => i.to_string(),
+        EvaluatesToNumberType::U16(i) => i.to_string(),
+        EvaluatesToNumberType::U32(i) => i.to_string(),
+        EvaluatesToNumberType::U64(i) => i.to_string(),
+        EvaluatesToNumberType::U128(i) => i.to_string(),
+        EvaluatesToNumberType::F32(f) => float(f.to_string()),
+        EvaluatesToNumberType::F64(f) => float(f.to_string()),
+        EvaluatesToNumberType::Identifier(name) => name.clone(),
+    }
+}
+
+fn vector_data(data: &VectorData) -> String {
+    match data {
+        VectorData::Vector(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .map(|value| float(value.to_string()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        VectorData::Identifier(name) => name.clone(),
+    }
+}
+
+fn id(id: &IdType) -> String {
+    match id {
+        IdType::Literal { value, .. } => quote(value),
+        IdType::Identifier { value, .. } => value.clone(),
+        IdType::ByIndex { index, value, .. } => {
+            format!("{{{}: {}}}", self::id(index), value_type(value, 0))
+        }
+    }
+}
+
+fn id_args(ids: &Option<Vec<IdType>>) -> String {
+    match ids {
+        Some(ids) => format!("({})", ids.iter().map(id).collect::<Vec<_>>().join(", ")),
+        None => String::new(),
+    }
+}
+
+fn type_arg(name: &str) -> String {
+    if name.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", name)
+    }
+}
+
+/// `{a, b}` on one line, or a line per field when that's too long
+fn object(fields: Vec<String>, indent: usize) -> String {
+    let flat = format!("{{{}}}", fields.join(", "));
+    if flat.len() <= MAX_OBJECT_WIDTH && !flat.contains('\n') {
+        return flat;
+    }
+    let inner = INDENT.repeat(indent + 1);
+    format!(
+        "{{\n{}\n{}}}",
+        fields
+            .iter()
+            .map(|field| format!("{}{}", inner, field))
+            .collect::<Vec<_>>()
+            .join(",\n"),
+        INDENT.repeat(indent)
+    )
+}
+
+/// Some strings are kept in the AST with their quotes and others without, a string
+/// literal can't contain quotes so both are told apart
+fn quote(s: &str) -> String {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        s.to_string()
+    } else {
+        format!("\"{}\"", s)
+    }
+}
+
+/// Floats need a fractional part to be parsed as floats again
+fn float(mut f: String) -> String {
+    if !f.contains('.') {
+        f.push_str(".0");
+    }
+    f
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_schema() {
+        let input = r#"// users
+N::User{UNIQUE INDEX email:String,INDEX  name : String , // the name
+  tags: [String], meta: {b: I32, a: String}, created:Date DEFAULT NOW, score: F64 DEFAULT 1.50
+}
+E::Follows{From:User,To:User,Properties:{since:I64}}
+"#;
+        let expected = r#"// users
+N::User {
+    UNIQUE INDEX email: String,
+    INDEX name: String, // the name
+    tags: [String],
+    meta: {a: String, b: I32},
+    created: DateTime DEFAULT NOW,
+    score: F64 DEFAULT 1.5,
+}
+
+E::Follows {
+    From: User,
+    To: User,
+    Properties: {
+        since: I64,
+    }
+}
+"#;
+        assert_eq!(format_hx("schema.hx", input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_namespace() {
+        let input = r#"
+NAMESPACE billing {
+  N::Invoice { total: F64 }
+}
+N::User { name: String }
+"#;
+        let expected = r#"NAMESPACE billing {
+    N::Invoice {
+        total: F64,
+    }
+}
+
+N::User {
+    name: String,
+}
+"#;
+        assert_eq!(format_hx("schema.hx", input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_enum() {
+        let input = "ENUM Status{Active,Suspended, // banned for a while\n Deleted}\nN::User { status: Status }\n";
+        let expected = r#"ENUM Status {
+    Active,
+    Suspended, // banned for a while
+    Deleted,
+}
+
+N::User {
+    status: Status,
+}
+"#;
+        assert_eq!(format_hx("schema.hx", input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_sensitive() {
+        let input = "N::User { ssn: String @sensitive( admin,hr ), salary: I64 DEFAULT 0 @sensitive }\n";
+        let expected = r#"N::User {
+    ssn: String @sensitive(admin, hr),
+    salary: I64 DEFAULT 0 @sensitive,
+}
+"#;
+        assert_eq!(format_hx("schema.hx", input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_query() {
+        let input = r#"QUERY get(id: ID, emails: [String], limit:I64=50, name : String ?) => // get one
+  u <- N<User>(id)::{name, email, posts: _::Out<Posted>::{title, body}, count: _::Out<Posted>::COUNT}
+
+
+    // add
+    n <- AddN<User>({name: "x", email: emails})
+    e <- AddE<Follows>::To(u)::From(n)
+    FOR {a, b} IN items {
+      x <- N<User>({email: a})::UPDATE({name: b})
+    }
+    o <- N<User>::WHERE(_::{name}::EQ("a // b"))::OrderBy(name)::RANGE(0, 10)::ID
+    RETURN u, n // done
+"#;
+        let expected = r#"QUERY get(id: ID, emails: [String], limit: I64 = 50, name: String?) => // get one
+    u <- N<User>(id)::{
+        name,
+        email,
+        posts: _::Out<Posted>::{title, body},
+        count: _::Out<Posted>::COUNT
+    }
+
+    // add
+    n <- AddN<User>({name: "x", email: emails})
+    e <- AddE<Follows>::From(n)::To(u)
+    FOR {a, b} IN items {
+        x <- N<User>({email: a})::UPDATE({name: b})
+    }
+    o <- N<User>::WHERE(_::{name}::EQ("a // b"))::OrderBy(name, Asc)::RANGE(0, 10)::ID
+    RETURN u, n // done
+"#;
+        let output = format_hx("queries.hx", input).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(format_hx("queries.hx", &output).unwrap(), output);
+    }
+
+    #[test]
+    fn test_format_expressions() {
+        let input = r#"QUERY get(min: F64) =>
+    items <- N<Item>::WHERE(LOWER( _::{name} )::EQ("a"))::WHERE((_::{price}+1)::GT(min*2))
+    RETURN items::{total: (_::{price}*_::{count})+1, net: (_::{price}-1)*2, rest: _::{a}-(_::{b}-_::{c}), name: UPPER(name)}
+"#;
+        let expected = r#"QUERY get(min: F64) =>
+    items <- N<Item>::WHERE(LOWER(_::{name})::EQ("a"))::WHERE((_::{price} + 1)::GT(min * 2))
+    RETURN items::{
+        total: _::{price} * _::{count} + 1,
+        net: (_::{price} - 1) * 2,
+        rest: _::{a} - (_::{b} - _::{c}),
+        name: UPPER(name)
+    }
+"#;
+        let output = format_hx("queries.hx", input).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(format_hx("queries.hx", &output).unwrap(), output);
+    }
+
+    #[test]
+    fn test_format_conditionals() {
+        let input = r#"QUERY get(min: F64) =>
+    label <- IF (min)::GT(1)   THEN "a"
+        ELSE IF AND(EXISTS(N<Item>), true) THEN "b" ELSE "c"
+    RETURN label, N<Item>::{tier: IF _::{price}::GT(min) THEN _::{price}*2 ELSE 0}
+"#;
+        let expected = r#"QUERY get(min: F64) =>
+    label <- IF (min)::GT(1) THEN "a" ELSE IF AND(EXISTS(N<Item>), true) THEN "b" ELSE "c"
+    RETURN label, N<Item>::{tier: IF _::{price}::GT(min) THEN _::{price} * 2 ELSE 0}
+"#;
+        let output = format_hx("queries.hx", input).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(format_hx("queries.hx", &output).unwrap(), output);
+    }
+
+    #[test]
+    fn test_format_negation() {
+        let input = r#"QUERY get() =>
+    items <- N<Item>::WHERE(NOT   EXISTS(_::Out<Similar>))::WHERE(NOT(OR(_::{a}::EQ(1),_::{b}::EQ(2))))
+    RETURN items::{related: NOT EXISTS(_::In<Similar>)}
+"#;
+        let expected = r#"QUERY get() =>
+    items <- N<Item>::WHERE(NOT EXISTS(_::Out<Similar>))::WHERE(NOT(OR(_::{a}::EQ(1), _::{b}::EQ(2))))
+    RETURN items::{related: NOT EXISTS(_::In<Similar>)}
+"#;
+        let output = format_hx("queries.hx", input).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(format_hx("queries.hx", &output).unwrap(), output);
+    }
+
+    #[test]
+    fn test_format_imports() {
+        let input = "import   \"users.hx\"  as users // accounts\nimport \"posts.hx\"\nN::Tag{name: String}\n";
+        let expected = r#"import "users.hx" as users // accounts
+import "posts.hx"
+
+N::Tag {
+    name: String,
+}
+"#;
+        assert_eq!(format_hx("schema.hx", input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_schemas_of_files() {
+        let source = HelixParser::parse_source(&Content {
+            content: String::new(),
+            source: Source::default(),
+            files: vec![
+                HxFile {
+                    name: "schema.hx".to_string(),
+                    content: "import \"billing.hx\" as billing\n// users\nN::User {}".to_string(),
+                },
+                HxFile {
+                    name: "billing.hx".to_string(),
+                    content: "N::Invoice { total: F64 }\nE::Bills { From: Invoice, To: Invoice }"
+                        .to_string(),
+                },
+            ],
+        })
+        .unwrap();
+        // the files are written in the order of their names
+        let expected = r#"NAMESPACE billing {
+    N::Invoice {
+        total: F64,
+    }
+
+    E::Bills {
+        From: Invoice,
+        To: Invoice,
+    }
+}
+
+N::User {
+}
+"#;
+        assert_eq!(format_schemas(&source).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_errors() {
+        assert!(format_hx("queries.hx", "QUERY get(id: ID) =>\n    u <- N<User>(id\n").is_err());
+        let error = format_hx(
+            "queries.hx",
+            "QUERY get(id: ID) =>\n    d <- V<Doc>(id)\n    RETURN d\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Lex error: Can't format `V<Doc>(id)` on line 2"
+        );
+    }
+
+    #[test]
+    fn test_comments_in_strings() {
+        let comments = comments("a <- \"// no\" // yes\n\"multi\n// no\" // last");
+        let lines = comments
+            .iter()
+            .map(|c| (c.line, c.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![(1, "// yes"), (3, "// last")]);
+    }
+}
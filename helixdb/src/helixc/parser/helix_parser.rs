@@ -12,7 +12,9 @@ use pest_derive::Parser;
 use std::{
     collections::{HashMap, HashSet},
     fmt::{Debug, Display},
+    fs,
     io::Write,
+    path::{Component, Path, PathBuf},
 };
 
 #[derive(Parser)]
@@ -32,6 +34,65 @@ pub struct HxFile {
     pub content: String,
 }
 
+impl Content {
+    /// Adds the files imported by the files of the content, and the files those import,
+    /// reading them relative to the file importing them. Files are added once however
+    /// often they're imported, cycles are left for the analyzer to report.
+    pub fn resolve_imports(&mut self) -> Result<(), ParserError> {
+        let mut loaded = self
+            .files
+            .iter()
+            .map(|file| normalize_path(&file.name))
+            .collect::<HashSet<_>>();
+        let mut next = 0;
+        while next < self.files.len() {
+            let importer = &self.files[next];
+            let mut imported = Vec::new();
+            for path in HelixParser::imported_paths(importer)? {
+                let name = import_path(&importer.name, &path);
+                if loaded.insert(name.clone()) {
+                    let content = fs::read_to_string(&name).map_err(|e| {
+                        ParserError::from(format!(
+                            "Can't read `{}` imported by {}: {}",
+                            path, importer.name, e
+                        ))
+                    })?;
+                    imported.push(HxFile { name, content });
+                }
+            }
+            self.files.extend(imported);
+            next += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Name of the file `path` refers to when imported by the file named `importer`
+pub fn import_path(importer: &str, path: &str) -> String {
+    let dir = Path::new(importer).parent().unwrap_or(Path::new(""));
+    normalize_path(&dir.join(path).to_string_lossy())
+}
+
+/// Drops the `.` and resolvable `..` of a path, so one file is known by one name
+pub fn normalize_path(path: &str) -> String {
+    let mut normalized = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized.to_string_lossy().into_owned()
+}
+
 impl Default for HelixParser {
     fn default() -> Self {
         HelixParser {
@@ -40,7 +101,9 @@ impl Default for HelixParser {
                 node_schemas: Vec::new(),
                 edge_schemas: Vec::new(),
                 vector_schemas: Vec::new(),
+                enum_schemas: Vec::new(),
                 queries: Vec::new(),
+                imports: Vec::new(),
             },
         }
     }
@@ -53,7 +116,9 @@ pub struct Source {
     pub node_schemas: Vec<NodeSchema>,
     pub edge_schemas: Vec<EdgeSchema>,
     pub vector_schemas: Vec<VectorSchema>,
+    pub enum_schemas: Vec<EnumSchema>,
     pub queries: Vec<Query>,
+    pub imports: Vec<Import>,
 }
 
 impl Default for Source {
@@ -63,14 +128,40 @@ impl Default for Source {
             node_schemas: Vec::new(),
             edge_schemas: Vec::new(),
             vector_schemas: Vec::new(),
+            enum_schemas: Vec::new(),
             queries: Vec::new(),
+            imports: Vec::new(),
         }
     }
 }
+
+/// `import "users.hx"` at the top of a file
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// Path of the imported file as written, relative to the importing file
+    pub path: String,
+    /// Name of the importing file, as resolved by `import_path`
+    pub from: String,
+    /// Name of the imported file, as resolved by `import_path`
+    pub file: String,
+    /// Namespace the schemas of the imported file are declared in
+    pub namespace: Option<String>,
+    pub loc: Loc,
+}
 #[derive(Debug, Clone)]
 pub struct NodeSchema {
     pub name: (Loc, String),
     pub fields: Vec<Field>,
+    /// `NAMESPACE` block the schema is declared in
+    pub namespace: Option<String>,
+    pub loc: Loc,
+}
+
+/// `ENUM Status { Active, Suspended }`, a field type taking one of the variants
+#[derive(Debug, Clone)]
+pub struct EnumSchema {
+    pub name: (Loc, String),
+    pub variants: Vec<(Loc, String)>,
     pub loc: Loc,
 }
 
@@ -78,6 +169,8 @@ pub struct NodeSchema {
 pub struct VectorSchema {
     pub name: String,
     pub fields: Vec<Field>,
+    /// `NAMESPACE` block the schema is declared in
+    pub namespace: Option<String>,
     pub loc: Loc,
 }
 
@@ -87,6 +180,8 @@ pub struct EdgeSchema {
     pub from: (Loc, String),
     pub to: (Loc, String),
     pub properties: Option<Vec<Field>>,
+    /// `NAMESPACE` block the schema is declared in
+    pub namespace: Option<String>,
     pub loc: Loc,
 }
 
@@ -96,17 +191,28 @@ pub struct Field {
     pub defaults: Option<DefaultValue>,
     pub name: String,
     pub field_type: FieldType,
+    /// Roles the field is returned to if it is annotated `@sensitive`, none unless the
+    /// annotation lists them
+    pub sensitive: Option<Vec<String>>,
     pub loc: Loc,
 }
 impl Field {
     pub fn is_indexed(&self) -> bool {
         self.prefix.is_indexed()
     }
+
+    pub fn is_unique(&self) -> bool {
+        self.prefix.is_unique()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum DefaultValue {
     Now,
+    /// A random UUID generated for every item
+    Uuid,
+    /// A ULID generated for every item
+    Ulid,
     String(String),
     F32(f32),
     F64(f64),
@@ -126,15 +232,18 @@ pub enum DefaultValue {
 #[derive(Debug, Clone)]
 pub enum FieldPrefix {
     Index,
+    /// Indexed and at most one node may have a given value
+    Unique,
     Optional,
     Empty,
 }
 impl FieldPrefix {
     pub fn is_indexed(&self) -> bool {
-        match self {
-            FieldPrefix::Index => true,
-            _ => false,
-        }
+        matches!(self, FieldPrefix::Index | FieldPrefix::Unique)
+    }
+
+    pub fn is_unique(&self) -> bool {
+        matches!(self, FieldPrefix::Unique)
     }
 }
 
@@ -286,6 +395,10 @@ pub struct Query {
 pub struct Parameter {
     pub name: (Loc, String),
     pub param_type: (Loc, FieldType),
+    /// Declared `name: T?`, the request may leave it out
+    pub is_optional: bool,
+    /// Value used when the request leaves the parameter out, `name: T = value`
+    pub default_value: Option<ValueType>,
     pub loc: Loc,
 }
 
@@ -355,6 +468,8 @@ pub enum ExpressionType {
     FloatLiteral(f64),
     BooleanLiteral(bool),
     Exists(Box<Expression>),
+    /// `NOT EXISTS(traversal)` or `NOT(condition)`
+    Not(Box<Expression>),
     BatchAddVector(BatchAddVector),
     AddVector(AddVector),
     AddNode(AddNode),
@@ -363,9 +478,71 @@ pub enum ExpressionType {
     Or(Vec<Expression>),
     SearchVector(SearchVector),
     BM25Search(BM25Search),
+    HybridSearch(HybridSearch),
+    /// `a + b`, `a - b`, `a * b` or `a / b`, `+` also concatenating strings
+    Arithmetic {
+        op: ArithmeticOp,
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
+    /// `LOWER(a)`, `UPPER(a)`, `LEN(a)` or `ROUND(a)`
+    Function {
+        function: ExpressionFunction,
+        arg: Box<Expression>,
+    },
+    /// Arithmetic or function compared by a boolean operation, e.g.
+    /// `LOWER(_::{name})::EQ("alice")`
+    Compared {
+        expr: Box<Expression>,
+        op: BooleanOp,
+    },
+    /// `IF condition THEN a ELSE b`, `b` possibly being another conditional
+    Conditional {
+        condition: Box<Expression>,
+        then: Box<Expression>,
+        otherwise: Box<Expression>,
+    },
     Empty,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Display for ArithmeticOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArithmeticOp::Add => write!(f, "+"),
+            ArithmeticOp::Sub => write!(f, "-"),
+            ArithmeticOp::Mul => write!(f, "*"),
+            ArithmeticOp::Div => write!(f, "/"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpressionFunction {
+    Lower,
+    Upper,
+    Len,
+    Round,
+}
+
+impl Display for ExpressionFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpressionFunction::Lower => write!(f, "LOWER"),
+            ExpressionFunction::Upper => write!(f, "UPPER"),
+            ExpressionFunction::Len => write!(f, "LEN"),
+            ExpressionFunction::Round => write!(f, "ROUND"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Traversal {
     pub start: StartNode,
@@ -392,9 +569,29 @@ pub enum StartNode {
         ids: Option<Vec<IdType>>,
     },
     Identifier(String),
+    Analytics(Analytics),
     Anonymous,
 }
 
+/// Graph algorithm run over all nodes at the start of a traversal
+#[derive(Debug, Clone)]
+pub struct Analytics {
+    pub loc: Loc,
+    pub algorithm: AnalyticsAlgorithm,
+    /// Only follow edges of this type
+    pub edge_type: Option<String>,
+    /// Iterations of PageRank and label propagation or samples of betweenness
+    pub arg: Option<EvaluatesToNumber>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalyticsAlgorithm {
+    PageRank,
+    Betweenness,
+    Components,
+    Communities,
+}
+
 #[derive(Debug, Clone)]
 pub struct Step {
     pub loc: Loc,
@@ -413,6 +610,11 @@ pub enum StepType {
     Exclude(Exclude),
     Closure(Closure),
     Range((Expression, Expression)),
+    /// Offset and limit of `::Range`
+    Limit((Expression, Expression)),
+    OrderBy(OrderBy),
+    /// State of versioned nodes at a point in time
+    AsOf(Expression),
     AddEdge(AddEdge),
 }
 impl PartialEq<StepType> for StepType {
@@ -428,11 +630,27 @@ impl PartialEq<StepType> for StepType {
             (&StepType::Exclude(_), &StepType::Exclude(_)) => true,
             (&StepType::Closure(_), &StepType::Closure(_)) => true,
             (&StepType::Range(_), &StepType::Range(_)) => true,
+            (&StepType::Limit(_), &StepType::Limit(_)) => true,
+            (&StepType::OrderBy(_), &StepType::OrderBy(_)) => true,
+            (&StepType::AsOf(_), &StepType::AsOf(_)) => true,
             (&StepType::AddEdge(_), &StepType::AddEdge(_)) => true,
             _ => false,
         }
     }
 }
+#[derive(Debug, Clone)]
+pub struct OrderBy {
+    pub loc: Loc,
+    pub field: String,
+    pub order: Order,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
 #[derive(Debug, Clone)]
 pub struct FieldAddition {
     pub key: String,
@@ -453,9 +671,43 @@ pub enum FieldValueType {
     Fields(Vec<FieldAddition>),
     Literal(Value),
     Identifier(String),
+    /// Traversal whose field is `NONE` when it yields nothing
+    Optional(Box<Traversal>),
+    /// The first argument that yields anything, otherwise `NONE`
+    Coalesce(Vec<FieldValue>),
+    /// Element of an array or field of a nested object in a property, e.g. `tags[0]`
+    PropertyPath(PropertyPath),
     Empty,
 }
 
+#[derive(Debug, Clone)]
+pub struct PropertyPath {
+    pub loc: Loc,
+    pub field: String,
+    pub path: Vec<PathSegment>,
+}
+
+impl PropertyPath {
+    /// The name the value is given when remapped without a key, the last field of
+    /// the path
+    pub fn name(&self) -> &str {
+        self.path
+            .iter()
+            .rev()
+            .find_map(|segment| match segment {
+                PathSegment::Field(field) => Some(field.as_str()),
+                PathSegment::Index(_) => None,
+            })
+            .unwrap_or(self.field.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
 #[derive(Debug, Clone)]
 pub struct GraphStep {
     pub loc: Loc,
@@ -473,7 +725,13 @@ pub enum GraphStepType {
     OutE(String),
     InE(String),
 
+    /// Number of outgoing edges of a label, without reading the adjacent nodes
+    OutCount(String),
+    /// Number of incoming edges of a label, without reading the adjacent nodes
+    InCount(String),
+
     ShortestPath(ShortestPath),
+    ShortestPathWeighted(ShortestPathWeighted),
     SearchVector(SearchVector),
 }
 impl GraphStep {
@@ -495,6 +753,18 @@ pub struct ShortestPath {
     pub from: Option<IdType>,
     pub to: Option<IdType>,
     pub type_arg: Option<String>,
+    pub max_depth: Option<EvaluatesToNumber>,
+    pub weight_property: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ShortestPathWeighted {
+    pub loc: Loc,
+    pub from: Option<IdType>,
+    pub to: Option<IdType>,
+    pub type_arg: Option<String>,
+    pub weight_property: String,
+    pub heuristic_property: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -538,6 +808,15 @@ pub struct BM25Search {
     pub k: Option<EvaluatesToNumber>,
 }
 
+#[derive(Debug, Clone)]
+pub struct HybridSearch {
+    pub loc: Loc,
+    pub node_type: Option<String>,
+    pub vector: Option<VectorData>,
+    pub query: Option<ValueType>,
+    pub k: Option<EvaluatesToNumber>,
+}
+
 #[derive(Debug, Clone)]
 pub struct EvaluatesToNumber {
     pub loc: Loc,
@@ -727,7 +1006,9 @@ impl HelixParser {
             node_schemas: Vec::new(),
             edge_schemas: Vec::new(),
             vector_schemas: Vec::new(),
+            enum_schemas: Vec::new(),
             queries: Vec::new(),
+            imports: Vec::new(),
         };
 
         input.files.iter().try_for_each(|file| {
@@ -752,6 +1033,10 @@ impl HelixParser {
             let mut remaining = HashSet::new();
             for pair in pairs {
                 match pair.as_rule() {
+                    Rule::import_def => {
+                        let import = parser.parse_import_def(pair, file.name.clone());
+                        parser.source.imports.push(import);
+                    }
                     Rule::node_def => {
                         let node_schema = parser.parse_node_def(pair, file.name.clone())?;
                         parser.source.node_schemas.push(node_schema);
@@ -764,6 +1049,13 @@ impl HelixParser {
                         let vector_schema = parser.parse_vector_def(pair, file.name.clone())?;
                         parser.source.vector_schemas.push(vector_schema);
                     }
+                    Rule::enum_def => {
+                        let enum_schema = parser.parse_enum_def(pair, file.name.clone());
+                        parser.source.enum_schemas.push(enum_schema);
+                    }
+                    Rule::namespace_def => {
+                        parser.parse_namespace_def(pair, file.name.clone())?;
+                    }
                     Rule::query_def => {
                         // parser.source.queries.push(parser.parse_query_def(pairs.next().unwrap())?),
                         remaining.insert(pair);
@@ -785,13 +1077,68 @@ impl HelixParser {
             source.node_schemas.extend(parser.source.node_schemas);
             source.edge_schemas.extend(parser.source.edge_schemas);
             source.vector_schemas.extend(parser.source.vector_schemas);
+            source.enum_schemas.extend(parser.source.enum_schemas);
             source.queries.extend(parser.source.queries);
+            source.imports.extend(parser.source.imports);
             Ok(())
         })?;
 
+        // the schemas of a file imported `as` a namespace are declared in it, unless
+        // they're in a namespace block of their own
+        for import in &source.imports {
+            let Some(namespace) = &import.namespace else {
+                continue;
+            };
+            let imported = |loc: &Loc| {
+                loc.filepath
+                    .as_deref()
+                    .is_some_and(|name| normalize_path(name) == import.file)
+            };
+            for node in &mut source.node_schemas {
+                if node.namespace.is_none() && imported(&node.loc) {
+                    node.namespace = Some(namespace.clone());
+                }
+            }
+            for edge in &mut source.edge_schemas {
+                if edge.namespace.is_none() && imported(&edge.loc) {
+                    edge.namespace = Some(namespace.clone());
+                }
+            }
+            for vector in &mut source.vector_schemas {
+                if vector.namespace.is_none() && imported(&vector.loc) {
+                    vector.namespace = Some(namespace.clone());
+                }
+            }
+        }
+
         Ok(source)
     }
 
+    /// The paths imported at the top of a file, as written
+    fn imported_paths(file: &HxFile) -> Result<Vec<String>, ParserError> {
+        let pair = HelixParser::parse(Rule::imports, &file.content)?
+            .next()
+            .ok_or_else(|| ParserError::from("Empty input"))?;
+        Ok(pair
+            .into_inner()
+            .filter_map(|import| import.into_inner().next())
+            .map(|path| path.as_str().trim_matches('"').to_string())
+            .collect())
+    }
+
+    fn parse_import_def(&self, pair: Pair<Rule>, filepath: String) -> Import {
+        let loc = pair.loc_with_filepath(filepath.clone());
+        let mut pairs = pair.into_inner();
+        let path = pairs.next().unwrap().as_str().trim_matches('"').to_string();
+        Import {
+            file: import_path(&filepath, &path),
+            from: normalize_path(&filepath),
+            namespace: pairs.next().map(|namespace| namespace.as_str().to_string()),
+            path,
+            loc,
+        }
+    }
+
     fn parse_node_def(
         &self,
         pair: Pair<Rule>,
@@ -799,14 +1146,31 @@ impl HelixParser {
     ) -> Result<NodeSchema, ParserError> {
         let mut pairs = pair.clone().into_inner();
         let name = pairs.next().unwrap().as_str().to_string();
-        let fields = self.parse_node_body(pairs.next().unwrap())?;
+        // the body is optional, a schema without one has no fields
+        let fields = match pairs.next() {
+            Some(body) => self.parse_node_body(body)?,
+            None => Vec::new(),
+        };
         Ok(NodeSchema {
             name: (pair.loc(), name),
             fields,
+            namespace: None,
             loc: pair.loc_with_filepath(filepath),
         })
     }
 
+    fn parse_enum_def(&self, pair: Pair<Rule>, filepath: String) -> EnumSchema {
+        let mut pairs = pair.clone().into_inner();
+        let name = pairs.next().unwrap();
+        EnumSchema {
+            name: (name.loc(), name.as_str().to_string()),
+            variants: pairs
+                .map(|variant| (variant.loc(), variant.as_str().to_string()))
+                .collect(),
+            loc: pair.loc_with_filepath(filepath),
+        }
+    }
+
     fn parse_vector_def(
         &self,
         pair: Pair<Rule>,
@@ -814,14 +1178,50 @@ impl HelixParser {
     ) -> Result<VectorSchema, ParserError> {
         let mut pairs = pair.clone().into_inner();
         let name = pairs.next().unwrap().as_str().to_string();
-        let fields = self.parse_node_body(pairs.next().unwrap())?;
+        // the body is optional, a schema without one has no fields
+        let fields = match pairs.next() {
+            Some(body) => self.parse_node_body(body)?,
+            None => Vec::new(),
+        };
         Ok(VectorSchema {
             name,
             fields,
+            namespace: None,
             loc: pair.loc_with_filepath(filepath),
         })
     }
 
+    /// Adds the schemas of the block to the source, marked with the namespace
+    fn parse_namespace_def(
+        &mut self,
+        pair: Pair<Rule>,
+        filepath: String,
+    ) -> Result<(), ParserError> {
+        let mut pairs = pair.into_inner();
+        let namespace = pairs.next().unwrap().as_str().to_string();
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::node_def => {
+                    let mut node_schema = self.parse_node_def(pair, filepath.clone())?;
+                    node_schema.namespace = Some(namespace.clone());
+                    self.source.node_schemas.push(node_schema);
+                }
+                Rule::edge_def => {
+                    let mut edge_schema = self.parse_edge_def(pair, filepath.clone())?;
+                    edge_schema.namespace = Some(namespace.clone());
+                    self.source.edge_schemas.push(edge_schema);
+                }
+                Rule::vector_def => {
+                    let mut vector_schema = self.parse_vector_def(pair, filepath.clone())?;
+                    vector_schema.namespace = Some(namespace.clone());
+                    self.source.vector_schemas.push(vector_schema);
+                }
+                _ => return Err(ParserError::from("Unexpected rule encountered")),
+            }
+        }
+        Ok(())
+    }
+
     fn parse_node_body(&self, pair: Pair<Rule>) -> Result<Vec<Field>, ParserError> {
         let field_defs = pair
             .into_inner()
@@ -878,16 +1278,10 @@ impl HelixParser {
             }
             Rule::object => {
                 let mut fields = HashMap::new();
+                // object fields are field defs, so may also carry a prefix or a default
                 for field in field.into_inner().next().unwrap().into_inner() {
-                    let (field_name, field_type) = {
-                        let mut field_pair = field.clone().into_inner();
-                        (
-                            field_pair.next().unwrap().as_str().to_string(),
-                            field_pair.next().unwrap().into_inner().next().unwrap(),
-                        )
-                    };
-                    let field_type = self.parse_field_type(field_type, Some(&self.source))?;
-                    fields.insert(field_name, field_type);
+                    let field = self.parse_field_def(field)?;
+                    fields.insert(field.name, field.field_type);
                 }
                 Ok(FieldType::Object(fields))
             }
@@ -902,12 +1296,16 @@ impl HelixParser {
 
     fn parse_field_def(&self, pair: Pair<Rule>) -> Result<Field, ParserError> {
         let mut pairs = pair.clone().into_inner();
-        // structure is index? ~ identifier ~ ":" ~ param_type
+        // structure is (unique | index)? ~ identifier ~ ":" ~ param_type ~ default? ~ sensitive?
         let prefix: FieldPrefix = match pairs.clone().next().unwrap().as_rule() {
             Rule::index => {
                 pairs.next().unwrap();
                 FieldPrefix::Index
             }
+            Rule::unique => {
+                pairs.next().unwrap();
+                FieldPrefix::Unique
+            }
             // Rule::optional => {
             //     pairs.next().unwrap();
             //     FieldPrefix::Optional
@@ -921,61 +1319,66 @@ impl HelixParser {
             Some(&self.source),
         )?;
 
-        let defaults = match pairs.next() {
+        let defaults = match pairs.peek() {
             Some(pair) => {
                 if pair.as_rule() == Rule::default {
+                    pairs.next();
                     let default_value = match pair.into_inner().next() {
-                        Some(pair) => match pair.as_rule() {
-                            Rule::string_literal => DefaultValue::String(pair.as_str().to_string()),
-                            Rule::float => {
-                                match field_type {
-                                    FieldType::F32 => {
-                                        DefaultValue::F32(pair.as_str().parse::<f32>().unwrap())
-                                    }
-                                    FieldType::F64 => {
-                                        DefaultValue::F64(pair.as_str().parse::<f64>().unwrap())
-                                    }
-                                    _ => unreachable!(), // throw error
+                        Some(pair) => {
+                            let value = pair.as_str();
+                            let invalid = || {
+                                ParserError::from(format!(
+                                    "Default value `{}` doesn't match the type of field `{}`",
+                                    value, name
+                                ))
+                            };
+                            match (pair.as_rule(), &field_type) {
+                                (Rule::string_literal, _) => {
+                                    DefaultValue::String(value.to_string())
                                 }
-                            }
-                            Rule::integer => {
-                                match field_type {
-                                    FieldType::I8 => {
-                                        DefaultValue::I8(pair.as_str().parse::<i8>().unwrap())
-                                    }
-                                    FieldType::I16 => {
-                                        DefaultValue::I16(pair.as_str().parse::<i16>().unwrap())
-                                    }
-                                    FieldType::I32 => {
-                                        DefaultValue::I32(pair.as_str().parse::<i32>().unwrap())
-                                    }
-                                    FieldType::I64 => {
-                                        DefaultValue::I64(pair.as_str().parse::<i64>().unwrap())
-                                    }
-                                    FieldType::U8 => {
-                                        DefaultValue::U8(pair.as_str().parse::<u8>().unwrap())
-                                    }
-                                    FieldType::U16 => {
-                                        DefaultValue::U16(pair.as_str().parse::<u16>().unwrap())
-                                    }
-                                    FieldType::U32 => {
-                                        DefaultValue::U32(pair.as_str().parse::<u32>().unwrap())
-                                    }
-                                    FieldType::U64 => {
-                                        DefaultValue::U64(pair.as_str().parse::<u64>().unwrap())
-                                    }
-                                    FieldType::U128 => {
-                                        DefaultValue::U128(pair.as_str().parse::<u128>().unwrap())
-                                    }
-                                    _ => unreachable!(), // throw error
+                                (Rule::float, FieldType::F32) => {
+                                    DefaultValue::F32(value.parse().map_err(|_| invalid())?)
                                 }
+                                (Rule::float, FieldType::F64) => {
+                                    DefaultValue::F64(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::I8) => {
+                                    DefaultValue::I8(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::I16) => {
+                                    DefaultValue::I16(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::I32) => {
+                                    DefaultValue::I32(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::I64) => {
+                                    DefaultValue::I64(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::U8) => {
+                                    DefaultValue::U8(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::U16) => {
+                                    DefaultValue::U16(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::U32) => {
+                                    DefaultValue::U32(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::U64) => {
+                                    DefaultValue::U64(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::integer, FieldType::U128) => {
+                                    DefaultValue::U128(value.parse().map_err(|_| invalid())?)
+                                }
+                                (Rule::now, _) => DefaultValue::Now,
+                                (Rule::uuid, _) => DefaultValue::Uuid,
+                                (Rule::ulid, _) => DefaultValue::Ulid,
+                                (Rule::none, _) => DefaultValue::Empty,
+                                (Rule::boolean, _) => {
+                                    DefaultValue::Boolean(value.parse().map_err(|_| invalid())?)
+                                }
+                                _ => return Err(invalid()),
                             }
-                            Rule::now => DefaultValue::Now,
-                            Rule::boolean => {
-                                DefaultValue::Boolean(pair.as_str().parse::<bool>().unwrap())
-                            }
-                            _ => unreachable!(), // throw error
-                        },
+                        }
                         None => DefaultValue::Empty,
                     };
                     Some(default_value)
@@ -986,11 +1389,19 @@ impl HelixParser {
             None => None,
         };
 
+        // the roles listed by a `@sensitive` annotation
+        let sensitive = pairs.next().map(|pair| {
+            pair.into_inner()
+                .map(|role| role.as_str().to_string())
+                .collect()
+        });
+
         Ok(Field {
             prefix,
             defaults,
             name,
             field_type,
+            sensitive,
             loc: pair.loc(),
         })
     }
@@ -1023,6 +1434,7 @@ impl HelixParser {
             from,
             to,
             properties,
+            namespace: None,
             loc: pair.loc_with_filepath(filepath),
         })
     }
@@ -1056,6 +1468,33 @@ impl HelixParser {
         })
     }
 
+    /// Parses the default value of a parameter, whose type is checked by the analyzer
+    fn parse_param_default(&self, pair: Pair<Rule>) -> Result<ValueType, ParserError> {
+        let loc = pair.loc();
+        let mut inner = pair.into_inner().peekable();
+        let sign = match inner.next_if(|pair| pair.as_rule() == Rule::negative) {
+            Some(_) => -1,
+            None => 1,
+        };
+        let pair = inner.next().unwrap();
+        let value = match pair.as_rule() {
+            Rule::float => pair
+                .as_str()
+                .parse::<f64>()
+                .map(|f| Value::F64(sign as f64 * f))
+                .map_err(|_| ParserError::from("Invalid float value"))?,
+            Rule::integer => pair
+                .as_str()
+                .parse::<i64>()
+                .map(|i| Value::I64(sign * i))
+                .map_err(|_| ParserError::from("Invalid integer value"))?,
+            Rule::boolean => Value::Boolean(pair.as_str() == "true"),
+            Rule::string_literal => Value::String(pair.as_str().trim_matches('"').to_string()),
+            _ => return Err(ParserError::from("Unexpected rule encountered")),
+        };
+        Ok(ValueType::new(value, loc))
+    }
+
     fn parse_parameters(&self, pair: Pair<Rule>) -> Result<Vec<Parameter>, ParserError> {
         let mut seen = HashSet::new();
         pair.clone()
@@ -1082,11 +1521,23 @@ impl HelixParser {
                     param_pair,
                     Some(&self.source),
                 )?;
+                let (mut is_optional, mut default_value) = (false, None);
+                for pair in inner.skip(1) {
+                    match pair.as_rule() {
+                        Rule::optional_param => is_optional = true,
+                        Rule::param_default => {
+                            default_value = Some(self.parse_param_default(pair)?);
+                        }
+                        _ => return Err(ParserError::from("Unexpected rule encountered")),
+                    }
+                }
 
                 if seen.insert(name.1.clone()) {
                     Ok(Parameter {
                         name,
                         param_type: (param_type_location, param_type),
+                        is_optional,
+                        default_value,
                         loc: pair.loc(),
                     })
                 } else {
@@ -1180,15 +1631,23 @@ impl HelixParser {
                 )));
             }
         };
-        let k = pairs.next().unwrap().as_str().to_string();
+        let k = pairs.next().unwrap();
+        let k = EvaluatesToNumber {
+            loc: k.loc(),
+            value: match k.as_rule() {
+                Rule::identifier => EvaluatesToNumberType::Identifier(k.as_str().to_string()),
+                _ => EvaluatesToNumberType::U32(
+                    k.as_str()
+                        .parse::<u32>()
+                        .map_err(|_| ParserError::from("Invalid integer value"))?,
+                ),
+            },
+        };
         Ok(BM25Search {
             loc: pair.loc(),
             type_arg: Some(vector_type),
             data: Some(query),
-            k: Some(EvaluatesToNumber {
-                loc: pair.loc(),
-                value: EvaluatesToNumberType::U32(k.parse::<u32>().unwrap()),
-            }),
+            k: Some(k),
         })
     }
 
@@ -1385,6 +1844,73 @@ impl HelixParser {
         })
     }
 
+    fn parse_hybrid_search(&self, pair: Pair<Rule>) -> Result<HybridSearch, ParserError> {
+        let mut node_type = None;
+        let mut vector = None;
+        let mut query = None;
+        let mut k = None;
+        for p in pair.clone().into_inner() {
+            match p.as_rule() {
+                Rule::identifier_upper => {
+                    node_type = Some(p.as_str().to_string());
+                }
+                Rule::vector_data => match p.clone().into_inner().next().unwrap().as_rule() {
+                    Rule::identifier => {
+                        vector = Some(VectorData::Identifier(p.as_str().to_string()));
+                    }
+                    Rule::vec_literal => {
+                        vector = Some(VectorData::Vector(self.parse_vec_literal(p)?));
+                    }
+                    _ => unreachable!(),
+                },
+                // the text comes before the limit
+                Rule::string_literal => {
+                    query = Some(ValueType::Literal {
+                        value: Value::String(p.as_str().to_string()),
+                        loc: p.loc(),
+                    });
+                }
+                Rule::identifier if query.is_none() => {
+                    query = Some(ValueType::Identifier {
+                        value: p.as_str().to_string(),
+                        loc: p.loc(),
+                    });
+                }
+                Rule::identifier => {
+                    k = Some(EvaluatesToNumber {
+                        loc: p.loc(),
+                        value: EvaluatesToNumberType::Identifier(p.as_str().to_string()),
+                    });
+                }
+                Rule::integer => {
+                    k = Some(EvaluatesToNumber {
+                        loc: p.loc(),
+                        value: EvaluatesToNumberType::I32(
+                            p.as_str()
+                                .to_string()
+                                .parse::<i32>()
+                                .map_err(|_| ParserError::from("Invalid integer value"))?,
+                        ),
+                    });
+                }
+                _ => {
+                    return Err(ParserError::from(format!(
+                        "Unexpected rule in HybridSearch: {:?}",
+                        p.as_rule()
+                    )))
+                }
+            }
+        }
+
+        Ok(HybridSearch {
+            loc: pair.loc(),
+            node_type,
+            vector,
+            query,
+            k,
+        })
+    }
+
     fn parse_vec_literal(&self, pair: Pair<Rule>) -> Result<Vec<f64>, ParserError> {
         let pairs = pair.into_inner();
         let mut vec = Vec::new();
@@ -1441,6 +1967,10 @@ impl HelixParser {
                     .to_string();
 
                 let prop_val = match pairs.next() {
+                    Some(p) if matches!(p.as_rule(), Rule::array_literal | Rule::create_field) => {
+                        let loc = p.loc();
+                        ValueType::new(self.parse_literal(p)?, loc)
+                    }
                     Some(p) => {
                         let value_pair = p
                             .into_inner()
@@ -1571,7 +2101,11 @@ impl HelixParser {
     fn parse_get_statement(&self, pair: Pair<Rule>) -> Result<Assignment, ParserError> {
         let mut pairs = pair.clone().into_inner();
         let variable = pairs.next().unwrap().as_str().to_string();
-        let value = self.parse_expression(pairs.next().unwrap())?;
+        let value = pairs.next().unwrap();
+        let value = match value.as_rule() {
+            Rule::conditional => self.parse_conditional(value)?,
+            _ => self.parse_expression(value)?,
+        };
 
         Ok(Assignment {
             variable,
@@ -1633,15 +2167,27 @@ impl HelixParser {
                 loc: expression.loc(),
                 expr: ExpressionType::BooleanLiteral(expression.as_str() == "true"),
             }),
-            Rule::exists => Ok(Expression {
+            Rule::exists => self.parse_exists(expression),
+            Rule::not => self.parse_not(expression),
+            Rule::identifier => Ok(Expression {
                 loc: expression.loc(),
-                expr: ExpressionType::Exists(Box::new(Expression {
-                    loc: expression.loc(),
-                    expr: ExpressionType::Traversal(Box::new(
-                        self.parse_anon_traversal(expression.into_inner().next().unwrap())?,
-                    )),
-                })),
+                expr: ExpressionType::Identifier(expression.as_str().to_string()),
+            }),
+            Rule::traversal | Rule::id_traversal => Ok(Expression {
+                loc: expression.loc(),
+                expr: ExpressionType::Traversal(Box::new(self.parse_traversal(expression)?)),
             }),
+            Rule::compared_expression => {
+                let mut pairs = expression.clone().into_inner();
+                let expr = self.parse_arithmetic(pairs.next().unwrap())?;
+                Ok(Expression {
+                    loc: expression.loc(),
+                    expr: ExpressionType::Compared {
+                        expr: Box::new(expr),
+                        op: self.parse_bool_operation(pairs.next().unwrap())?,
+                    },
+                })
+            }
 
             _ => unreachable!(),
         }
@@ -1674,24 +2220,16 @@ impl HelixParser {
                 loc: pair.loc(),
                 expr: ExpressionType::StringLiteral(self.parse_string_literal(pair)?),
             }),
-            Rule::exists => {
-                let traversal = pair
-                    .clone()
-                    .into_inner()
-                    .next()
-                    .ok_or_else(|| ParserError::from("Missing exists traversal"))?;
-                Ok(Expression {
-                    loc: pair.loc(),
-                    expr: ExpressionType::Exists(Box::new(Expression {
-                        loc: pair.loc(),
-                        expr: ExpressionType::Traversal(Box::new(match traversal.as_rule() {
-                            Rule::traversal => self.parse_traversal(traversal)?,
-                            Rule::id_traversal => self.parse_traversal(traversal)?,
-                            _ => unreachable!(),
-                        })),
-                    })),
-                })
-            }
+            Rule::exists => self.parse_exists(pair),
+            Rule::not => self.parse_not(pair),
+            Rule::and => Ok(Expression {
+                loc: pair.loc(),
+                expr: ExpressionType::And(self.parse_expression_vec(pair.into_inner())?),
+            }),
+            Rule::or => Ok(Expression {
+                loc: pair.loc(),
+                expr: ExpressionType::Or(self.parse_expression_vec(pair.into_inner())?),
+            }),
             Rule::integer => pair
                 .as_str()
                 .parse()
@@ -1741,6 +2279,10 @@ impl HelixParser {
                 loc: pair.loc(),
                 expr: ExpressionType::BM25Search(self.parse_bm25_search(pair)?),
             }),
+            Rule::hybrid_search => Ok(Expression {
+                loc: pair.loc(),
+                expr: ExpressionType::HybridSearch(self.parse_hybrid_search(pair)?),
+            }),
             _ => Err(ParserError::from(format!(
                 "Unexpected expression type: {:?}",
                 pair.as_rule()
@@ -1748,20 +2290,163 @@ impl HelixParser {
         }
     }
 
-    fn parse_string_literal(&self, pair: Pair<Rule>) -> Result<String, ParserError> {
-        let inner = pair
-            .into_inner()
-            .next()
-            .ok_or_else(|| ParserError::from("Empty string literal"))?;
-
-        let mut literal = inner.as_str().to_string();
-        literal.retain(|c| c != '"');
-        Ok(literal)
-    }
-
-    fn parse_traversal(&self, pair: Pair<Rule>) -> Result<Traversal, ParserError> {
-        let mut pairs = pair.clone().into_inner();
-        let start = self.parse_start_node(pairs.next().unwrap())?;
+    /// Parses an arithmetic or string expression, with `*` and `/` binding tighter than `+`
+    /// and `-` and operators of the same precedence applied from left to right
+    fn parse_arithmetic(&self, pair: Pair<Rule>) -> Result<Expression, ParserError> {
+        let loc = pair.loc();
+        let expr = match pair.as_rule() {
+            Rule::expression => return self.parse_arithmetic(pair.into_inner().next().unwrap()),
+            Rule::expression_sum | Rule::expression_product => {
+                let mut pairs = pair.into_inner();
+                let mut expr = self.parse_arithmetic(pairs.next().unwrap())?;
+                while let Some(op) = pairs.next() {
+                    let op = match op.as_str() {
+                        "+" => ArithmeticOp::Add,
+                        "-" => ArithmeticOp::Sub,
+                        "*" => ArithmeticOp::Mul,
+                        _ => ArithmeticOp::Div,
+                    };
+                    let rhs = self.parse_arithmetic(pairs.next().unwrap())?;
+                    expr = Expression {
+                        loc: loc.clone(),
+                        expr: ExpressionType::Arithmetic {
+                            op,
+                            lhs: Box::new(expr),
+                            rhs: Box::new(rhs),
+                        },
+                    };
+                }
+                return Ok(expr);
+            }
+            Rule::function_call => {
+                let mut pairs = pair.into_inner();
+                let function = match pairs.next().unwrap().as_str() {
+                    "LOWER" => ExpressionFunction::Lower,
+                    "UPPER" => ExpressionFunction::Upper,
+                    "LEN" => ExpressionFunction::Len,
+                    _ => ExpressionFunction::Round,
+                };
+                ExpressionType::Function {
+                    function,
+                    arg: Box::new(self.parse_arithmetic(pairs.next().unwrap())?),
+                }
+            }
+            Rule::anonymous_traversal => {
+                ExpressionType::Traversal(Box::new(self.parse_anon_traversal(pair)?))
+            }
+            Rule::string_literal => ExpressionType::StringLiteral(self.parse_string_literal(pair)?),
+            Rule::integer => ExpressionType::IntegerLiteral(
+                pair.as_str()
+                    .parse()
+                    .map_err(|_| ParserError::from("Invalid integer literal"))?,
+            ),
+            Rule::float => ExpressionType::FloatLiteral(
+                pair.as_str()
+                    .parse()
+                    .map_err(|_| ParserError::from("Invalid float literal"))?,
+            ),
+            Rule::identifier => ExpressionType::Identifier(pair.as_str().to_string()),
+            rule => {
+                return Err(ParserError::from(format!(
+                    "Unexpected rule in expression: {:?}",
+                    rule
+                )))
+            }
+        };
+        Ok(Expression { loc, expr })
+    }
+
+    fn parse_exists(&self, pair: Pair<Rule>) -> Result<Expression, ParserError> {
+        let traversal = pair
+            .clone()
+            .into_inner()
+            .next()
+            .ok_or_else(|| ParserError::from("Missing exists traversal"))?;
+        Ok(Expression {
+            loc: pair.loc(),
+            expr: ExpressionType::Exists(Box::new(Expression {
+                loc: pair.loc(),
+                expr: ExpressionType::Traversal(Box::new(match traversal.as_rule() {
+                    Rule::traversal | Rule::id_traversal => self.parse_traversal(traversal)?,
+                    _ => self.parse_anon_traversal(traversal)?,
+                })),
+            })),
+        })
+    }
+
+    /// Parses `NOT EXISTS(traversal)` or `NOT(condition)`
+    fn parse_not(&self, pair: Pair<Rule>) -> Result<Expression, ParserError> {
+        let inner = pair
+            .clone()
+            .into_inner()
+            .next()
+            .ok_or_else(|| ParserError::from("Missing negated condition"))?;
+        let negated = match inner.as_rule() {
+            Rule::exists => self.parse_exists(inner)?,
+            Rule::anonymous_traversal => Expression {
+                loc: inner.loc(),
+                expr: ExpressionType::Traversal(Box::new(self.parse_anon_traversal(inner)?)),
+            },
+            _ => self.parse_boolean_expression(inner)?,
+        };
+        Ok(Expression {
+            loc: pair.loc(),
+            expr: ExpressionType::Not(Box::new(negated)),
+        })
+    }
+
+    /// Parses `IF condition THEN a ELSE b`
+    fn parse_conditional(&self, pair: Pair<Rule>) -> Result<Expression, ParserError> {
+        let loc = pair.loc();
+        let mut pairs = pair.into_inner();
+        let condition = pairs.next().unwrap();
+        let condition = match condition.as_rule() {
+            Rule::anonymous_traversal => Expression {
+                loc: condition.loc(),
+                expr: ExpressionType::Traversal(Box::new(self.parse_anon_traversal(condition)?)),
+            },
+            _ => self.parse_boolean_expression(condition)?,
+        };
+        let mut branches = pairs.map(|branch| match branch.as_rule() {
+            Rule::conditional => self.parse_conditional(branch),
+            Rule::boolean => Ok(Expression {
+                loc: branch.loc(),
+                expr: ExpressionType::BooleanLiteral(branch.as_str() == "true"),
+            }),
+            _ => self.parse_arithmetic(branch),
+        });
+        Ok(Expression {
+            loc,
+            expr: ExpressionType::Conditional {
+                condition: Box::new(condition),
+                then: Box::new(branches.next().unwrap()?),
+                otherwise: Box::new(branches.next().unwrap()?),
+            },
+        })
+    }
+
+    /// Parses the value a boolean operation compares to
+    fn parse_compared_value(&self, pair: Pair<Rule>) -> Result<Expression, ParserError> {
+        match pair.as_rule() {
+            Rule::expression => self.parse_arithmetic(pair),
+            _ => self.parse_expression(pair),
+        }
+    }
+
+    fn parse_string_literal(&self, pair: Pair<Rule>) -> Result<String, ParserError> {
+        let inner = pair
+            .into_inner()
+            .next()
+            .ok_or_else(|| ParserError::from("Empty string literal"))?;
+
+        let mut literal = inner.as_str().to_string();
+        literal.retain(|c| c != '"');
+        Ok(literal)
+    }
+
+    fn parse_traversal(&self, pair: Pair<Rule>) -> Result<Traversal, ParserError> {
+        let mut pairs = pair.clone().into_inner();
+        let start = self.parse_start_node(pairs.next().unwrap())?;
         let steps = pairs
             .map(|p| self.parse_step(p))
             .collect::<Result<Vec<_>, _>>()?;
@@ -1822,68 +2507,7 @@ impl HelixParser {
                             );
                         }
                         Rule::by_index => {
-                            ids = Some({
-                                let mut pairs: Pairs<'_, Rule> = p.clone().into_inner();
-                                let index = match pairs.next().unwrap().clone().into_inner().next()
-                                {
-                                    Some(id) => match id.as_rule() {
-                                        Rule::identifier => IdType::Identifier {
-                                            value: id.as_str().to_string(),
-                                            loc: id.loc(),
-                                        },
-                                        Rule::string_literal => IdType::Literal {
-                                            value: id.as_str().to_string(),
-                                            loc: id.loc(),
-                                        },
-                                        other => {
-                                            panic!(
-                                                "Should be identifier or string literal: {:?}",
-                                                other
-                                            )
-                                        }
-                                    },
-                                    None => return Err(ParserError::from("Missing index")),
-                                };
-                                let value = match pairs.next().unwrap().into_inner().next() {
-                                    Some(val) => match val.as_rule() {
-                                        Rule::identifier => ValueType::Identifier {
-                                            value: val.as_str().to_string(),
-                                            loc: val.loc(),
-                                        },
-                                        Rule::string_literal => ValueType::Literal {
-                                            value: Value::from(val.as_str()),
-                                            loc: val.loc(),
-                                        },
-                                        Rule::integer => ValueType::Literal {
-                                            value: Value::from(
-                                                val.as_str().parse::<i64>().unwrap(),
-                                            ),
-                                            loc: val.loc(),
-                                        },
-                                        Rule::float => ValueType::Literal {
-                                            value: Value::from(
-                                                val.as_str().parse::<f64>().unwrap(),
-                                            ),
-                                            loc: val.loc(),
-                                        },
-                                        Rule::boolean => ValueType::Literal {
-                                            value: Value::from(
-                                                val.as_str().parse::<bool>().unwrap(),
-                                            ),
-                                            loc: val.loc(),
-                                        },
-                                        other => {
-                                            panic!("Should be identifier or string literal")
-                                        }
-                                    },
-                                    _ => unreachable!(),
-                                };
-                                vec![IdType::ByIndex {
-                                    index: Box::new(index),
-                                    value: Box::new(value),
-                                    loc: p.loc(),
-                                }]
-                            })
+                            ids = Some(vec![self.parse_by_index(p)?]);
                         }
                         _ => unreachable!(),
                     }
@@ -1922,16 +2546,114 @@ impl HelixParser {
                                     .collect::<Vec<_>>(),
                             );
                         }
+                        Rule::by_index => {
+                            ids = Some(vec![self.parse_by_index(p)?]);
+                        }
                         _ => unreachable!(),
                     }
                 }
                 Ok(StartNode::Edge { edge_type, ids })
             }
+            Rule::start_analytics => {
+                let pair = pair.into_inner().next().unwrap();
+                let algorithm = match pair.as_rule() {
+                    Rule::page_rank => AnalyticsAlgorithm::PageRank,
+                    Rule::betweenness => AnalyticsAlgorithm::Betweenness,
+                    Rule::components => AnalyticsAlgorithm::Components,
+                    Rule::communities => AnalyticsAlgorithm::Communities,
+                    _ => unreachable!(),
+                };
+                let mut edge_type = None;
+                let mut arg = None;
+                for p in pair.clone().into_inner() {
+                    match p.as_rule() {
+                        Rule::type_args => {
+                            edge_type = Some(p.into_inner().next().unwrap().as_str().to_string());
+                        }
+                        Rule::integer => {
+                            arg = Some(EvaluatesToNumber {
+                                loc: p.loc(),
+                                value: EvaluatesToNumberType::I32(
+                                    p.as_str()
+                                        .parse::<i32>()
+                                        .map_err(|_| ParserError::from("Invalid integer value"))?,
+                                ),
+                            });
+                        }
+                        Rule::identifier => {
+                            arg = Some(EvaluatesToNumber {
+                                loc: p.loc(),
+                                value: EvaluatesToNumberType::Identifier(p.as_str().to_string()),
+                            });
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+                Ok(StartNode::Analytics(Analytics {
+                    loc: pair.loc(),
+                    algorithm,
+                    edge_type,
+                    arg,
+                }))
+            }
             Rule::identifier => Ok(StartNode::Identifier(pair.as_str().to_string())),
             _ => Ok(StartNode::Anonymous),
         }
     }
 
+    fn parse_by_index(&self, pair: Pair<Rule>) -> Result<IdType, ParserError> {
+        let mut pairs: Pairs<'_, Rule> = pair.clone().into_inner();
+        let index = match pairs.next().unwrap().clone().into_inner().next() {
+            Some(id) => match id.as_rule() {
+                Rule::identifier => IdType::Identifier {
+                    value: id.as_str().to_string(),
+                    loc: id.loc(),
+                },
+                Rule::string_literal => IdType::Literal {
+                    value: id.as_str().to_string(),
+                    loc: id.loc(),
+                },
+                other => {
+                    panic!("Should be identifier or string literal: {:?}", other)
+                }
+            },
+            None => return Err(ParserError::from("Missing index")),
+        };
+        let value = match pairs.next().unwrap().into_inner().next() {
+            Some(val) => match val.as_rule() {
+                Rule::identifier => ValueType::Identifier {
+                    value: val.as_str().to_string(),
+                    loc: val.loc(),
+                },
+                Rule::string_literal => ValueType::Literal {
+                    value: Value::from(val.as_str()),
+                    loc: val.loc(),
+                },
+                Rule::integer => ValueType::Literal {
+                    value: Value::from(val.as_str().parse::<i64>().unwrap()),
+                    loc: val.loc(),
+                },
+                Rule::float => ValueType::Literal {
+                    value: Value::from(val.as_str().parse::<f64>().unwrap()),
+                    loc: val.loc(),
+                },
+                Rule::boolean => ValueType::Literal {
+                    value: Value::from(val.as_str().parse::<bool>().unwrap()),
+                    loc: val.loc(),
+                },
+                _ => {
+                    panic!("Should be identifier or string literal")
+                }
+            },
+            _ => unreachable!(),
+        };
+        Ok(IdType::ByIndex {
+            index: Box::new(index),
+            value: Box::new(value),
+            loc: pair.loc(),
+        })
+    }
+
     fn parse_step(&self, pair: Pair<Rule>) -> Result<Step, ParserError> {
         let inner = pair.clone().into_inner().next().unwrap();
         match inner.as_rule() {
@@ -1955,6 +2677,25 @@ impl HelixParser {
                 loc: inner.loc(),
                 step: StepType::Range(self.parse_range(pair)?),
             }),
+            Rule::limit_step => Ok(Step {
+                loc: inner.loc(),
+                step: StepType::Limit(self.parse_range(pair)?),
+            }),
+            Rule::order_by => Ok(Step {
+                loc: inner.loc(),
+                step: StepType::OrderBy(self.parse_order_by(inner)?),
+            }),
+            Rule::as_of => Ok(Step {
+                loc: inner.loc(),
+                step: StepType::AsOf(
+                    self.parse_expression(
+                        inner
+                            .into_inner()
+                            .next()
+                            .ok_or_else(|| ParserError::from("Missing point in time"))?,
+                    )?,
+                ),
+            }),
 
             Rule::bool_operations => Ok(Step {
                 loc: inner.loc(),
@@ -2013,6 +2754,21 @@ impl HelixParser {
         Ok((start, end))
     }
 
+    fn parse_order_by(&self, pair: Pair<Rule>) -> Result<OrderBy, ParserError> {
+        let loc = pair.loc();
+        let mut inner = pair.into_inner();
+        let field = inner
+            .next()
+            .ok_or_else(|| ParserError::from("Missing order by field"))?
+            .as_str()
+            .to_string();
+        let order = match inner.next().map(|p| p.as_str()) {
+            Some("Desc") => Order::Desc,
+            _ => Order::Asc,
+        };
+        Ok(OrderBy { loc, field, order })
+    }
+
     fn parse_graph_step(&self, pair: Pair<Rule>) -> GraphStep {
         let types = |pair: &Pair<Rule>| {
             pair.clone()
@@ -2066,6 +2822,20 @@ impl HelixParser {
                     step: GraphStepType::InE(types),
                 }
             }
+            Rule::out_count => {
+                let types = types(&pair);
+                GraphStep {
+                    loc: pair.loc(),
+                    step: GraphStepType::OutCount(types),
+                }
+            }
+            Rule::in_count => {
+                let types = types(&pair);
+                GraphStep {
+                    loc: pair.loc(),
+                    step: GraphStepType::InCount(types),
+                }
+            }
             Rule::from_n => GraphStep {
                 loc: pair.loc(),
                 step: GraphStepType::FromN,
@@ -2116,6 +2886,42 @@ impl HelixParser {
                         _ => (type_arg, from, to),
                     },
                 );
+                let (mut max_depth, mut weight_property) = (None, None);
+                if let Some(options) = pair
+                    .clone()
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::path_options)
+                {
+                    for option in options.into_inner() {
+                        match option.as_rule() {
+                            Rule::max_depth => {
+                                let value = option.into_inner().next().unwrap();
+                                max_depth = Some(EvaluatesToNumber {
+                                    loc: value.loc(),
+                                    value: match value.as_rule() {
+                                        Rule::integer => EvaluatesToNumberType::I32(
+                                            value
+                                                .as_str()
+                                                .parse::<i32>()
+                                                .map_err(|_| {
+                                                    ParserError::from("Invalid integer value")
+                                                })
+                                                .unwrap(), // TODO: change to error
+                                        ),
+                                        _ => EvaluatesToNumberType::Identifier(
+                                            value.as_str().to_string(),
+                                        ),
+                                    },
+                                });
+                            }
+                            Rule::weight_property => {
+                                weight_property =
+                                    Some(option.into_inner().next().unwrap().as_str().to_string());
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                }
 
                 // TODO: add error handling and check about IdType as might not always be data.
                 // possibly use stack to keep track of variables and use them via precedence and then check on type
@@ -2133,6 +2939,52 @@ impl HelixParser {
                             loc: pair.loc(),
                         }),
                         type_arg,
+                        max_depth,
+                        weight_property,
+                    }),
+                }
+            }
+            Rule::shortest_path_weighted => {
+                let (mut type_arg, mut from, mut to) = (None, None, None);
+                let (mut weight_property, mut heuristic_property) = (String::new(), None);
+                for p in pair.clone().into_inner() {
+                    match p.as_rule() {
+                        Rule::type_args => {
+                            type_arg = Some(p.into_inner().next().unwrap().as_str().to_string())
+                        }
+                        Rule::weight_property => {
+                            weight_property = p.into_inner().next().unwrap().as_str().to_string()
+                        }
+                        Rule::heuristic_property => {
+                            heuristic_property =
+                                Some(p.into_inner().next().unwrap().as_str().to_string())
+                        }
+                        Rule::to_from => {
+                            if let Some(p) = p.into_inner().next() {
+                                let id = Some(IdType::Identifier {
+                                    value: p.clone().into_inner().next().unwrap().as_str().to_string(),
+                                    loc: pair.loc(),
+                                });
+                                match p.as_rule() {
+                                    Rule::to => to = id,
+                                    Rule::from => from = id,
+                                    _ => unreachable!(),
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                GraphStep {
+                    loc: pair.loc(),
+                    step: GraphStepType::ShortestPathWeighted(ShortestPathWeighted {
+                        loc: pair.loc(),
+                        from,
+                        to,
+                        type_arg,
+                        weight_property,
+                        heuristic_property,
                     }),
                 }
             }
@@ -2153,37 +3005,37 @@ impl HelixParser {
             Rule::GT => BooleanOp {
                 loc: pair.loc(),
                 op: BooleanOpType::GreaterThan(Box::new(
-                    self.parse_expression(inner.into_inner().next().unwrap())?,
+                    self.parse_compared_value(inner.into_inner().next().unwrap())?,
                 )),
             },
             Rule::GTE => BooleanOp {
                 loc: pair.loc(),
                 op: BooleanOpType::GreaterThanOrEqual(Box::new(
-                    self.parse_expression(inner.into_inner().next().unwrap())?,
+                    self.parse_compared_value(inner.into_inner().next().unwrap())?,
                 )),
             },
             Rule::LT => BooleanOp {
                 loc: pair.loc(),
                 op: BooleanOpType::LessThan(Box::new(
-                    self.parse_expression(inner.into_inner().next().unwrap())?,
+                    self.parse_compared_value(inner.into_inner().next().unwrap())?,
                 )),
             },
             Rule::LTE => BooleanOp {
                 loc: pair.loc(),
                 op: BooleanOpType::LessThanOrEqual(Box::new(
-                    self.parse_expression(inner.into_inner().next().unwrap())?,
+                    self.parse_compared_value(inner.into_inner().next().unwrap())?,
                 )),
             },
             Rule::EQ => BooleanOp {
                 loc: pair.loc(),
                 op: BooleanOpType::Equal(Box::new(
-                    self.parse_expression(inner.into_inner().next().unwrap())?,
+                    self.parse_compared_value(inner.into_inner().next().unwrap())?,
                 )),
             },
             Rule::NEQ => BooleanOp {
                 loc: pair.loc(),
                 op: BooleanOpType::NotEqual(Box::new(
-                    self.parse_expression(inner.into_inner().next().unwrap())?,
+                    self.parse_compared_value(inner.into_inner().next().unwrap())?,
                 )),
             },
             _ => return Err(ParserError::from("Invalid boolean operation")),
@@ -2209,7 +3061,7 @@ impl HelixParser {
             },
             Rule::anonymous_traversal => FieldValue {
                 loc: value_pair.loc(),
-                value: FieldValueType::Traversal(Box::new(self.parse_traversal(value_pair)?)),
+                value: FieldValueType::Traversal(Box::new(self.parse_anon_traversal(value_pair)?)),
             },
             Rule::object_step => FieldValue {
                 loc: value_pair.loc(),
@@ -2250,6 +3102,10 @@ impl HelixParser {
                 loc: value_pair.loc(),
                 value: FieldValueType::Empty,
             },
+            Rule::array_literal | Rule::create_field => FieldValue {
+                loc: value_pair.loc(),
+                value: FieldValueType::Literal(self.parse_literal(value_pair)?),
+            },
             Rule::mapping_field => FieldValue {
                 loc: value_pair.loc(),
                 value: FieldValueType::Fields(self.parse_field_additions(value_pair)?),
@@ -2280,7 +3136,7 @@ impl HelixParser {
             },
             Rule::anonymous_traversal => FieldValue {
                 loc: value_pair.loc(),
-                value: FieldValueType::Traversal(Box::new(self.parse_traversal(value_pair)?)),
+                value: FieldValueType::Traversal(Box::new(self.parse_anon_traversal(value_pair)?)),
             },
             Rule::object_step => FieldValue {
                 loc: value_pair.loc(),
@@ -2318,6 +3174,10 @@ impl HelixParser {
                 loc: value_pair.loc(),
                 value: FieldValueType::Empty,
             },
+            Rule::array_literal | Rule::create_field => FieldValue {
+                loc: value_pair.loc(),
+                value: FieldValueType::Literal(self.parse_literal(value_pair)?),
+            },
             Rule::mapping_field => FieldValue {
                 loc: value_pair.loc(),
                 value: FieldValueType::Fields(self.parse_field_additions(value_pair)?),
@@ -2351,9 +3211,38 @@ impl HelixParser {
                 continue;
             }
             let mut pairs = p.clone().into_inner();
-            let prop_key = pairs.next().unwrap().as_str().to_string();
+            let key_pair = pairs.next().unwrap();
+            if key_pair.as_rule() == Rule::property_path {
+                let path = self.parse_property_path(key_pair)?;
+                fields.push(FieldAddition {
+                    loc: p.loc(),
+                    key: path.name().to_string(),
+                    value: FieldValue {
+                        loc: path.loc.clone(),
+                        value: FieldValueType::PropertyPath(path),
+                    },
+                });
+                continue;
+            }
+            let prop_key = key_pair.as_str().to_string();
             let field_addition = match pairs.next() {
                 Some(p) => match p.as_rule() {
+                    Rule::property_path => FieldValue {
+                        loc: p.loc(),
+                        value: FieldValueType::PropertyPath(self.parse_property_path(p)?),
+                    },
+                    Rule::score_field => FieldValue {
+                        loc: p.loc(),
+                        value: FieldValueType::Identifier(p.as_str().to_string()),
+                    },
+                    Rule::conditional => FieldValue {
+                        loc: p.loc(),
+                        value: FieldValueType::Expression(self.parse_conditional(p)?),
+                    },
+                    Rule::expression => FieldValue {
+                        loc: p.loc(),
+                        value: FieldValueType::Expression(self.parse_arithmetic(p)?),
+                    },
                     Rule::evaluates_to_anything => FieldValue {
                         loc: p.loc(),
                         value: FieldValueType::Expression(self.parse_expression(p)?),
@@ -2362,6 +3251,16 @@ impl HelixParser {
                         loc: p.loc(),
                         value: FieldValueType::Traversal(Box::new(self.parse_anon_traversal(p)?)),
                     },
+                    Rule::optional => FieldValue {
+                        loc: p.loc(),
+                        value: FieldValueType::Optional(Box::new(
+                            self.parse_anon_traversal(p.into_inner().next().unwrap())?,
+                        )),
+                    },
+                    Rule::coalesce => FieldValue {
+                        loc: p.loc(),
+                        value: FieldValueType::Coalesce(self.parse_coalesce(p)?),
+                    },
                     Rule::mapping_field => FieldValue {
                         loc: p.loc(),
                         value: FieldValueType::Fields(self.parse_field_additions(p)?),
@@ -2394,6 +3293,84 @@ impl HelixParser {
         })
     }
 
+    fn parse_property_path(&self, pair: Pair<Rule>) -> Result<PropertyPath, ParserError> {
+        let loc = pair.loc();
+        let mut pairs = pair.into_inner();
+        let field = pairs.next().unwrap().as_str().to_string();
+        let path = pairs
+            .map(|p| {
+                let segment = p.into_inner().next().unwrap();
+                match segment.as_rule() {
+                    Rule::identifier => Ok(PathSegment::Field(segment.as_str().to_string())),
+                    _ => segment
+                        .as_str()
+                        .parse()
+                        .map(PathSegment::Index)
+                        .map_err(|_| ParserError::from("Invalid array index")),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(PropertyPath { loc, field, path })
+    }
+
+    /// Parses a literal value, where array and object literals may only contain
+    /// other literals
+    fn parse_literal(&self, pair: Pair<Rule>) -> Result<Value, ParserError> {
+        match pair.as_rule() {
+            Rule::evaluates_to_anything => self.parse_literal(pair.into_inner().next().unwrap()),
+            Rule::string_literal => Ok(Value::String(self.parse_string_literal(pair)?)),
+            Rule::integer => pair
+                .as_str()
+                .parse()
+                .map(Value::I32)
+                .map_err(|_| ParserError::from("Invalid integer literal")),
+            Rule::float => pair
+                .as_str()
+                .parse()
+                .map(Value::F64)
+                .map_err(|_| ParserError::from("Invalid float literal")),
+            Rule::boolean => Ok(Value::Boolean(pair.as_str() == "true")),
+            Rule::none => Ok(Value::Empty),
+            Rule::array_literal => pair
+                .into_inner()
+                .map(|p| self.parse_literal(p))
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            Rule::create_field => pair
+                .into_inner()
+                .map(|p| {
+                    let mut pairs = p.into_inner();
+                    let key = pairs.next().unwrap().as_str().to_string();
+                    Ok((key, self.parse_literal(pairs.next().unwrap())?))
+                })
+                .collect::<Result<_, _>>()
+                .map(Value::Object),
+            _ => Err(ParserError::from(format!(
+                "`{}` is not a literal, arrays and objects may only contain literals",
+                pair.as_str()
+            ))),
+        }
+    }
+
+    fn parse_coalesce(&self, pair: Pair<Rule>) -> Result<Vec<FieldValue>, ParserError> {
+        pair.into_inner()
+            .map(|p| match p.as_rule() {
+                Rule::anonymous_traversal => Ok(FieldValue {
+                    loc: p.loc(),
+                    value: FieldValueType::Traversal(Box::new(self.parse_anon_traversal(p)?)),
+                }),
+                Rule::evaluates_to_anything => Ok(FieldValue {
+                    loc: p.loc(),
+                    value: FieldValueType::Expression(self.parse_expression(p)?),
+                }),
+                _ => Err(ParserError::from(format!(
+                    "Unexpected Coalesce argument: {:?}",
+                    p.as_rule()
+                ))),
+            })
+            .collect()
+    }
+
     fn parse_closure(&self, pair: Pair<Rule>) -> Result<Closure, ParserError> {
         let mut pairs = pair.clone().into_inner();
         let identifier = pairs.next().unwrap().as_str().to_string();
@@ -2439,6 +3416,123 @@ mod tests {
         assert_eq!(schema.fields.len(), 2);
     }
 
+    #[test]
+    fn test_parse_generated_defaults() {
+        let input = r#"
+        N::User {
+            key: ID DEFAULT UUID,
+            slug: String DEFAULT ULID,
+            created_at: Date DEFAULT NOW
+        }
+        "#;
+
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        let fields = &result.node_schemas[0].fields;
+        assert!(matches!(fields[0].defaults, Some(DefaultValue::Uuid)));
+        assert!(matches!(fields[1].defaults, Some(DefaultValue::Ulid)));
+        assert!(matches!(fields[2].defaults, Some(DefaultValue::Now)));
+    }
+
+    #[test]
+    fn test_parse_imports() {
+        let input = Content {
+            content: String::new(),
+            source: Source::default(),
+            files: vec![
+                HxFile {
+                    name: "./cfg/schema.hx".to_string(),
+                    content: "import \"billing/invoices.hx\" as billing\nimport \"../shared.hx\"\nN::User {}".to_string(),
+                },
+                HxFile {
+                    name: "cfg/billing/invoices.hx".to_string(),
+                    content: "N::Invoice {}\nNAMESPACE audit {\n    N::Entry {}\n}".to_string(),
+                },
+            ],
+        };
+        let result = HelixParser::parse_source(&input).unwrap();
+        let imports = result
+            .imports
+            .iter()
+            .map(|i| (i.from.as_str(), i.file.as_str(), i.namespace.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            imports,
+            vec![
+                ("cfg/schema.hx", "cfg/billing/invoices.hx", Some("billing")),
+                ("cfg/schema.hx", "shared.hx", None),
+            ]
+        );
+        let namespaces = result
+            .node_schemas
+            .iter()
+            .map(|n| (n.name.1.as_str(), n.namespace.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            namespaces,
+            vec![
+                ("User", None),
+                ("Invoice", Some("billing")),
+                ("Entry", Some("audit"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_imports() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("users")).unwrap();
+        fs::write(
+            dir.path().join("users/users.hx"),
+            "import \"../posts.hx\"\nN::User {}",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("posts.hx"),
+            "import \"users/users.hx\"\nN::Post {}",
+        )
+        .unwrap();
+        let schema = dir.path().join("schema.hx").to_string_lossy().into_owned();
+        let mut input = Content {
+            content: String::new(),
+            source: Source::default(),
+            files: vec![HxFile {
+                name: schema.clone(),
+                content: "import \"users/users.hx\"\nimport \"posts.hx\"".to_string(),
+            }],
+        };
+
+        // the files importing each other are read once
+        input.resolve_imports().unwrap();
+        let names = input
+            .files
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                schema.as_str(),
+                &import_path(&schema, "users/users.hx"),
+                &import_path(&schema, "posts.hx"),
+            ]
+        );
+        assert_eq!(
+            HelixParser::parse_source(&input)
+                .unwrap()
+                .node_schemas
+                .len(),
+            2
+        );
+
+        input.files[2].content = "import \"missing.hx\"".to_string();
+        assert!(input
+            .resolve_imports()
+            .unwrap_err()
+            .to_string()
+            .contains("Can't read `missing.hx`"));
+    }
+
     #[test]
     fn test_parse_edge_schema() {
         let input = r#"
@@ -2466,6 +3560,39 @@ mod tests {
         matches!(properties[0].field_type, FieldType::F64);
     }
 
+    #[test]
+    fn test_parse_namespace() {
+        let input = r#"
+        N::Admin {
+            name: String
+        }
+
+        NAMESPACE blog {
+            N::Post {
+                title: String
+            }
+            E::Replies {
+                From: Post,
+                To: Post,
+            }
+            V::Embedding {
+                model: String
+            }
+        }
+        "#;
+
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        assert_eq!(result.node_schemas.len(), 2);
+        let admin = &result.node_schemas[0];
+        assert_eq!(admin.namespace, None);
+        let post = &result.node_schemas[1];
+        assert_eq!(post.name.1, "Post");
+        assert_eq!(post.namespace.as_deref(), Some("blog"));
+        assert_eq!(result.edge_schemas[0].namespace.as_deref(), Some("blog"));
+        assert_eq!(result.vector_schemas[0].namespace.as_deref(), Some("blog"));
+    }
+
     #[test]
     fn test_parse_edge_schema_no_props() {
         let input = r#"
@@ -2538,6 +3665,59 @@ mod tests {
         assert_eq!(query.return_values.len(), 2);
     }
 
+    #[test]
+    fn test_query_with_optional_parameters() {
+        let input = r#"
+        QUERY findUsers(limit: I64 = 50, min: F64 = 0.5, name: String?, tag: String = "a") =>
+            users <- N<User>::RANGE(0, limit)
+            RETURN users
+        "#;
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        let parameters = &result.queries[0].parameters;
+        assert_eq!(parameters.len(), 4);
+        let defaults = parameters
+            .iter()
+            .map(|p| match &p.default_value {
+                Some(ValueType::Literal { value, .. }) => Some(value.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(matches!(defaults[0], Some(Value::I64(50))));
+        assert!(matches!(defaults[1], Some(Value::F64(f)) if f == 0.5));
+        assert!(defaults[2].is_none());
+        assert!(matches!(&defaults[3], Some(Value::String(s)) if s == "a"));
+        assert_eq!(
+            parameters.iter().map(|p| p.is_optional).collect::<Vec<_>>(),
+            vec![false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn test_enum_definition() {
+        let input = r#"
+        ENUM Status { Active, Suspended, }
+        N::User { status: Status }
+        "#;
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        assert_eq!(result.enum_schemas.len(), 1);
+        let schema = &result.enum_schemas[0];
+        assert_eq!(schema.name.1, "Status");
+        assert_eq!(
+            schema
+                .variants
+                .iter()
+                .map(|(_, v)| v.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Active", "Suspended"]
+        );
+        assert!(matches!(
+            &result.node_schemas[0].fields[0].field_type,
+            FieldType::Identifier(name) if name == "Status"
+        ));
+    }
+
     #[test]
     fn test_node_definition() {
         let input = r#"
@@ -2743,6 +3923,279 @@ mod tests {
         assert_eq!(query.statements.len(), 1);
     }
 
+    #[test]
+    fn test_array_and_object_literals() {
+        let input = r#"
+    QUERY addUser() =>
+        user <- AddN<User>({tags: ["a", "b"], address: {city: "Paris", zip: 75000}})
+        RETURN user
+    "#;
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        let fields = match &result.queries[0].statements[0].statement {
+            StatementType::Assignment(Assignment {
+                value:
+                    Expression {
+                        expr: ExpressionType::AddNode(add),
+                        ..
+                    },
+                ..
+            }) => add.fields.clone().unwrap(),
+            _ => panic!("expected AddN"),
+        };
+        assert!(matches!(
+            &fields["tags"],
+            ValueType::Literal { value: Value::Array(tags), .. }
+                if *tags == vec![Value::from("a"), Value::from("b")]
+        ));
+        assert!(matches!(
+            &fields["address"],
+            ValueType::Literal { value: Value::Object(address), .. }
+                if address["city"] == Value::from("Paris") && address["zip"] == Value::I32(75000)
+        ));
+    }
+
+    #[test]
+    fn test_property_path() {
+        let input = r#"
+    QUERY getUser() =>
+        user <- N<User>::{tags[0], town: address.city}
+        RETURN user
+    "#;
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        let steps = match &result.queries[0].statements[0].statement {
+            StatementType::Assignment(Assignment {
+                value:
+                    Expression {
+                        expr: ExpressionType::Traversal(tr),
+                        ..
+                    },
+                ..
+            }) => tr.steps.clone(),
+            _ => panic!("expected traversal"),
+        };
+        let fields = match &steps[0].step {
+            StepType::Object(obj) => obj.fields.clone(),
+            _ => panic!("expected object step"),
+        };
+        assert_eq!(fields[0].key, "tags");
+        assert!(matches!(
+            &fields[0].value.value,
+            FieldValueType::PropertyPath(path)
+                if path.field == "tags" && path.path == vec![PathSegment::Index(0)]
+        ));
+        assert_eq!(fields[1].key, "town");
+        assert!(matches!(
+            &fields[1].value.value,
+            FieldValueType::PropertyPath(path)
+                if path.field == "address"
+                    && path.path == vec![PathSegment::Field("city".to_string())]
+        ));
+    }
+
+    #[test]
+    fn test_expressions() {
+        let input = r#"
+    QUERY getItems(min: F64) =>
+        items <- N<Item>::WHERE(LOWER(_::{name})::EQ("a"))::{total: _::{price} + _::{tax} * 2, len: LEN(name)}
+        cheap <- N<Item>::WHERE(_::{price}::LT(min - 1))
+        RETURN items, cheap
+    "#;
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        let steps = |i: usize| match &result.queries[0].statements[i].statement {
+            StatementType::Assignment(Assignment {
+                value:
+                    Expression {
+                        expr: ExpressionType::Traversal(tr),
+                        ..
+                    },
+                ..
+            }) => tr.steps.clone(),
+            _ => panic!("expected traversal"),
+        };
+        let steps_of_items = steps(0);
+        match &steps_of_items[0].step {
+            StepType::Where(expr) => match &expr.expr {
+                ExpressionType::Compared { expr, op } => {
+                    assert!(matches!(
+                        expr.expr,
+                        ExpressionType::Function {
+                            function: ExpressionFunction::Lower,
+                            ..
+                        }
+                    ));
+                    assert!(matches!(op.op, BooleanOpType::Equal(_)));
+                }
+                other => panic!("expected compared expression, got {:?}", other),
+            },
+            _ => panic!("expected where step"),
+        }
+        let fields = match &steps_of_items[1].step {
+            StepType::Object(obj) => obj.fields.clone(),
+            _ => panic!("expected object step"),
+        };
+        // `*` binds tighter than `+`
+        match &fields[0].value.value {
+            FieldValueType::Expression(Expression {
+                expr: ExpressionType::Arithmetic { op, rhs, .. },
+                ..
+            }) => {
+                assert_eq!(*op, ArithmeticOp::Add);
+                assert!(matches!(
+                    rhs.expr,
+                    ExpressionType::Arithmetic {
+                        op: ArithmeticOp::Mul,
+                        ..
+                    }
+                ));
+            }
+            other => panic!("expected arithmetic, got {:?}", other),
+        }
+        assert!(matches!(
+            &fields[1].value.value,
+            FieldValueType::Expression(Expression {
+                expr: ExpressionType::Function {
+                    function: ExpressionFunction::Len,
+                    ..
+                },
+                ..
+            })
+        ));
+        let compared = match &steps(1)[0].step {
+            StepType::Where(expr) => match &expr.expr {
+                ExpressionType::Traversal(tr) => tr.steps[1].step.clone(),
+                other => panic!("expected traversal, got {:?}", other),
+            },
+            _ => panic!("expected where step"),
+        };
+        match &compared {
+            StepType::BooleanOperation(BooleanOp {
+                op: BooleanOpType::LessThan(value),
+                ..
+            }) => assert!(matches!(
+                value.expr,
+                ExpressionType::Arithmetic {
+                    op: ArithmeticOp::Sub,
+                    ..
+                }
+            )),
+            _ => panic!("expected boolean operation"),
+        }
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let input = r#"
+    QUERY getItems(premium: Boolean) =>
+        label <- IF premium THEN "premium" ELSE IF (LEN(name))::GT(3) THEN "long" ELSE "short"
+        items <- N<Item>::{tier: IF _::{price}::GT(100) THEN _::{price} * 2 ELSE false}
+        RETURN label, items
+    "#;
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        let value = |i: usize| match &result.queries[0].statements[i].statement {
+            StatementType::Assignment(assignment) => assignment.value.clone(),
+            _ => panic!("expected assignment"),
+        };
+        match value(0).expr {
+            ExpressionType::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                assert!(matches!(condition.expr, ExpressionType::Identifier(_)));
+                assert!(matches!(then.expr, ExpressionType::StringLiteral(_)));
+                // `ELSE IF` is a conditional in the else branch
+                match otherwise.expr {
+                    ExpressionType::Conditional { condition, .. } => {
+                        assert!(matches!(condition.expr, ExpressionType::Compared { .. }))
+                    }
+                    other => panic!("expected conditional, got {:?}", other),
+                }
+            }
+            other => panic!("expected conditional, got {:?}", other),
+        }
+        let fields = match value(1).expr {
+            ExpressionType::Traversal(tr) => match &tr.steps[0].step {
+                StepType::Object(obj) => obj.fields.clone(),
+                _ => panic!("expected object step"),
+            },
+            other => panic!("expected traversal, got {:?}", other),
+        };
+        match &fields[0].value.value {
+            FieldValueType::Expression(Expression {
+                expr:
+                    ExpressionType::Conditional {
+                        condition,
+                        then,
+                        otherwise,
+                    },
+                ..
+            }) => {
+                assert!(matches!(condition.expr, ExpressionType::Traversal(_)));
+                assert!(matches!(then.expr, ExpressionType::Arithmetic { .. }));
+                assert!(matches!(
+                    otherwise.expr,
+                    ExpressionType::BooleanLiteral(false)
+                ));
+            }
+            other => panic!("expected conditional, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_not_exists() {
+        let input = r#"
+    QUERY getItems() =>
+        any <- NOT EXISTS(N<Item>)
+        items <- N<Item>::WHERE(NOT(_::{price}::GT(1)))::{related: EXISTS(_::Out<Similar>)}
+        RETURN any, items
+    "#;
+        let input = write_to_temp_file(vec![input]);
+        let result = HelixParser::parse_source(&input).unwrap();
+        let value = |i: usize| match &result.queries[0].statements[i].statement {
+            StatementType::Assignment(assignment) => assignment.value.clone(),
+            _ => panic!("expected assignment"),
+        };
+        match value(0).expr {
+            ExpressionType::Not(expr) => match expr.expr {
+                ExpressionType::Exists(expr) => match expr.expr {
+                    ExpressionType::Traversal(tr) => {
+                        assert!(matches!(tr.start, StartNode::Node { .. }))
+                    }
+                    other => panic!("expected traversal, got {:?}", other),
+                },
+                other => panic!("expected exists, got {:?}", other),
+            },
+            other => panic!("expected negation, got {:?}", other),
+        }
+        let steps = match value(1).expr {
+            ExpressionType::Traversal(tr) => tr.steps.clone(),
+            other => panic!("expected traversal, got {:?}", other),
+        };
+        match &steps[0].step {
+            StepType::Where(expr) => match &expr.expr {
+                ExpressionType::Not(expr) => {
+                    assert!(matches!(expr.expr, ExpressionType::Traversal(_)))
+                }
+                other => panic!("expected negation, got {:?}", other),
+            },
+            _ => panic!("expected where step"),
+        }
+        match &steps[1].step {
+            StepType::Object(obj) => assert!(matches!(
+                obj.fields[0].value.value,
+                FieldValueType::Expression(Expression {
+                    expr: ExpressionType::Exists(_),
+                    ..
+                })
+            )),
+            _ => panic!("expected object step"),
+        }
+    }
+
     #[test]
     fn test_add_edge_query() {
         let input = r#"
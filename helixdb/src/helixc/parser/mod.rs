@@ -1,3 +1,4 @@
+pub mod formatter;
 pub mod helix_parser;
 pub mod location;
 pub mod parser_methods;
@@ -0,0 +1,816 @@
+use crate::{
+    helix_engine::ingest_jobs::ingest_jobs::IngestJob,
+    helix_gateway::ingest::ingest::{
+        IngestBatch, IngestEdge, IngestNode, IngestResult, JobCheckpoint, INGEST_PATH,
+    },
+    ingestion_engine::{
+        ingest_jobs::JobState,
+        neo4j_ingestion::{typed_value, CsvRecords, PropertyKind},
+        sql_ingestion::IngestionError,
+    },
+    protocol::value::Value,
+};
+use parquet::{
+    file::reader::{FileReader, SerializedFileReader},
+    record::Field,
+};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    thread,
+};
+
+/// Declarative mapping of the rows of CSV and Parquet files to nodes and edges.
+///
+/// ```json
+/// {
+///   "nodes": [
+///     { "file": "users.csv", "label": "User", "id": "user_id", "external_id": "email",
+///       "properties": { "email": "email", "zip": { "name": "zipCode", "type": "String" } } },
+///     { "file": "products.parquet", "label": "Product", "id": "sku" }
+///   ],
+///   "edges": [
+///     { "file": "orders.csv", "label": "Bought",
+///       "from": { "label": "User", "column": "user_id" },
+///       "to": { "label": "Product", "column": "sku" },
+///       "properties": { "qty": "quantity" }, "unique": true }
+///   ]
+/// }
+/// ```
+///
+/// Files are relative to the mapping file. Properties map a column to a property,
+/// every column being mapped to a property of its own name when they are left out.
+/// Edges join a row to the nodes whose `id` column holds the values of its `from` and
+/// `to` columns, a file can be mapped to both nodes and edges.
+///
+/// Nodes with an `external_id` are upserted on that property, which needs a unique
+/// index, and `unique` edges are only added once between two nodes. Ingesting the files
+/// again then updates the graph rather than duplicating it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Mapping {
+    #[serde(default)]
+    pub nodes: Vec<NodeMapping>,
+    #[serde(default)]
+    pub edges: Vec<EdgeMapping>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeMapping {
+    pub file: String,
+    pub label: String,
+    /// Column identifying the node, for edges to join on
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Property the nodes are upserted on
+    #[serde(default)]
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub properties: Option<HashMap<String, PropertyMapping>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EdgeMapping {
+    pub file: String,
+    pub label: String,
+    pub from: JoinMapping,
+    pub to: JoinMapping,
+    #[serde(default)]
+    pub properties: Option<HashMap<String, PropertyMapping>>,
+    /// Whether nodes are joined by one edge at most
+    #[serde(default)]
+    pub unique: bool,
+}
+
+/// A column holding the id of a node of `label`
+#[derive(Debug, Clone, Deserialize)]
+pub struct JoinMapping {
+    pub label: String,
+    pub column: String,
+}
+
+/// Property a column is mapped to, with the type its values are read as. Untyped CSV
+/// columns are typed by their values
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PropertyMapping {
+    Name(String),
+    Typed {
+        name: String,
+        #[serde(rename = "type")]
+        helix_type: String,
+    },
+}
+
+impl PropertyMapping {
+    fn name(&self) -> &str {
+        match self {
+            PropertyMapping::Name(name) | PropertyMapping::Typed { name, .. } => name,
+        }
+    }
+
+    fn kind(&self) -> Result<PropertyKind, IngestionError> {
+        match self {
+            PropertyMapping::Name(_) => Ok(PropertyKind::Inferred),
+            PropertyMapping::Typed { name, helix_type } => match helix_type.as_str() {
+                "I64" => Ok(PropertyKind::Integer),
+                "F64" => Ok(PropertyKind::Float),
+                "Boolean" => Ok(PropertyKind::Boolean),
+                "String" => Ok(PropertyKind::String),
+                _ => Err(IngestionError::MappingError(format!(
+                    "Property {} has type {}, expected I64, F64, Boolean or String",
+                    name, helix_type
+                ))),
+            },
+        }
+    }
+}
+
+/// Format of a mapped file, by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Csv,
+    Parquet,
+}
+
+impl FileFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" => Some(FileFormat::Csv),
+            "parquet" | "pq" => Some(FileFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// A value of a row, as text for CSV files and typed for Parquet files
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Text(String),
+    Typed(Value),
+}
+
+/// The position of a row in its file, with its columns by name or why it couldn't be
+/// read
+type Row = (usize, Result<HashMap<String, Cell>, String>);
+
+/// Counts of an ingestion, with the rows that couldn't be loaded
+#[derive(Debug, Default)]
+pub struct FileReport {
+    pub nodes: usize,
+    pub edges: usize,
+    pub errors: Vec<String>,
+}
+
+/// A batch read from a file, for the ingestor to send
+enum Chunk {
+    Nodes {
+        rows: Rows,
+        /// `(label, id)` of each node that edges can join on
+        keys: Vec<Option<(String, String)>>,
+        nodes: Vec<IngestNode>,
+    },
+    Edges {
+        rows: Rows,
+        edges: Vec<IngestEdge>,
+    },
+    Error(String),
+}
+
+/// The rows of a file read for a chunk
+struct Rows {
+    /// Stream of the job the file is read as
+    stream: String,
+    /// Position of the last row
+    position: u64,
+    /// Rows that couldn't be read
+    errors: Vec<String>,
+}
+
+impl Rows {
+    fn new(stream: String) -> Self {
+        Rows {
+            stream,
+            position: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// The rows read so far, starting over for the next chunk
+    fn take(&mut self) -> Rows {
+        Rows {
+            stream: self.stream.clone(),
+            position: self.position,
+            errors: std::mem::take(&mut self.errors),
+        }
+    }
+}
+
+/// Loads CSV and Parquet files into a running instance through its ingestion endpoint,
+/// following a [`Mapping`].
+///
+/// Every mapped file is read on its own thread, the rows being sent to the instance in
+/// batches that are each written in one transaction. Nodes are all loaded before the
+/// edges joining them.
+///
+/// Ingestions started as a job checkpoint the rows of every mapping they loaded, and
+/// can be resumed after the last batch the instance loaded.
+pub struct FileIngestor {
+    pub instance: String,
+    pub batch_size: usize,
+    pub mapping: Mapping,
+    /// `(label, id)` of a mapped node => its uuid in the instance
+    pub id_mappings: HashMap<(String, String), String>,
+    pub job: Option<JobState>,
+    /// Path of the mapping file, the source of the job
+    source: String,
+    base_dir: PathBuf,
+    client: Client,
+}
+
+impl FileIngestor {
+    /// Reads the mapping file at `path`, checking the files it maps have the columns it
+    /// maps
+    pub fn new(
+        path: &str,
+        instance: Option<String>,
+        batch_size: usize,
+    ) -> Result<Self, IngestionError> {
+        let path = Path::new(path);
+        let mapping = fs::read_to_string(path).map_err(|e| {
+            IngestionError::MappingError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let mapping: Mapping = serde_json::from_str(&mapping).map_err(|e| {
+            IngestionError::MappingError(format!("Invalid mapping {}: {}", path.display(), e))
+        })?;
+
+        let ingestor = FileIngestor {
+            instance: instance.unwrap_or("http://localhost:6969".to_string()),
+            batch_size: batch_size.max(1),
+            mapping,
+            id_mappings: HashMap::new(),
+            job: None,
+            source: fs::canonicalize(path)
+                .unwrap_or(path.to_path_buf())
+                .display()
+                .to_string(),
+            base_dir: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            client: Client::new(),
+        };
+        ingestor.validate()?;
+        Ok(ingestor)
+    }
+
+    /// Checkpoints the batches of the ingestion as a new job of the instance, returning
+    /// its id
+    pub fn start_job(&mut self) -> String {
+        let job = JobState::start(&self.source);
+        let id = job.id.clone();
+        self.job = Some(job);
+        id
+    }
+
+    /// Resumes a job of the instance, skipping the rows it loaded and joining edges on
+    /// the nodes it loaded
+    pub fn resume(&mut self, id: &str) -> Result<IngestJob, IngestionError> {
+        let (job, progress) = JobState::resume(&self.client, &self.instance, id, &self.source)?;
+        self.id_mappings = job.keys(&self.client, &self.instance)?;
+        self.job = Some(job);
+        Ok(progress)
+    }
+
+    fn validate(&self) -> Result<(), IngestionError> {
+        let mapping_error =
+            |file: &str, e: String| Err(IngestionError::MappingError(format!("{}: {}", file, e)));
+        for node in self.mapping.nodes.iter() {
+            let columns = self.columns(&node.file)?;
+            if let Some(id) = node.id.as_ref().filter(|id| !columns.contains(id)) {
+                return mapping_error(&node.file, format!("no id column {}", id));
+            }
+            check_properties(&node.file, &node.properties, &columns)?;
+            if let Some(external_id) = &node.external_id {
+                let mapped = match &node.properties {
+                    Some(properties) => properties.values().any(|p| p.name() == external_id),
+                    None => columns.contains(external_id),
+                };
+                if !mapped {
+                    return mapping_error(
+                        &node.file,
+                        format!("external id {} isn't a mapped property", external_id),
+                    );
+                }
+            }
+        }
+        for edge in self.mapping.edges.iter() {
+            let columns = self.columns(&edge.file)?;
+            for join in [&edge.from, &edge.to] {
+                if !columns.contains(&join.column) {
+                    return mapping_error(&edge.file, format!("no column {}", join.column));
+                }
+                if !self
+                    .mapping
+                    .nodes
+                    .iter()
+                    .any(|node| node.label == join.label && node.id.is_some())
+                {
+                    return mapping_error(
+                        &edge.file,
+                        format!("{} isn't mapped to nodes with an id", join.label),
+                    );
+                }
+            }
+            check_properties(&edge.file, &edge.properties, &columns)?;
+        }
+        Ok(())
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.base_dir.join(file)
+    }
+
+    /// The names of the columns of a mapped file
+    fn columns(&self, file: &str) -> Result<Vec<String>, IngestionError> {
+        let path = self.path(file);
+        match FileFormat::from_path(&path) {
+            Some(FileFormat::Csv) => Ok(CsvRecords::open(&path)?
+                .next()
+                .transpose()?
+                .map(|(_, fields)| fields)
+                .unwrap_or_default()),
+            Some(FileFormat::Parquet) => Ok(parquet_reader(&path)?
+                .metadata()
+                .file_metadata()
+                .schema()
+                .get_fields()
+                .iter()
+                .map(|field| field.name().to_string())
+                .collect()),
+            None => Err(IngestionError::MappingError(format!(
+                "{}: only CSV and Parquet files can be ingested",
+                file
+            ))),
+        }
+    }
+
+    /// Reads the rows of a mapped file
+    fn rows(&self, file: &str) -> Result<Box<dyn Iterator<Item = Row>>, String> {
+        let path = self.path(file);
+        match FileFormat::from_path(&path) {
+            Some(FileFormat::Csv) => {
+                let mut records = CsvRecords::open(&path).map_err(|e| e.to_string())?;
+                let header = match records.next() {
+                    Some(Ok((_, header))) => header,
+                    Some(Err(e)) => return Err(e.to_string()),
+                    None => return Ok(Box::new(std::iter::empty())),
+                };
+                let file = file.to_string();
+                let mut last = 1;
+                Ok(Box::new(records.map(move |record| {
+                    let (line, fields) = match record {
+                        Ok(record) => record,
+                        Err(e) => return (last + 1, Err(e.to_string())),
+                    };
+                    last = line;
+                    if fields.len() != header.len() {
+                        let e = format!(
+                            "{}:{}: expected {} columns, got {}",
+                            file,
+                            line,
+                            header.len(),
+                            fields.len()
+                        );
+                        return (line, Err(e));
+                    }
+                    let cells = header
+                        .iter()
+                        .cloned()
+                        .zip(fields)
+                        .filter(|(_, field)| !field.is_empty())
+                        .map(|(column, field)| (column, Cell::Text(field)))
+                        .collect();
+                    (line, Ok(cells))
+                })))
+            }
+            Some(FileFormat::Parquet) => {
+                let rows = parquet_reader(&path).map_err(|e| e.to_string())?;
+                let file = file.to_string();
+                Ok(Box::new(rows.into_iter().enumerate().map(
+                    move |(i, row)| {
+                        let row = match row {
+                            Ok(row) => row,
+                            Err(e) => return (i + 1, Err(format!("{}:{}: {}", file, i + 1, e))),
+                        };
+                        let cells = row
+                            .get_column_iter()
+                            .filter_map(|(column, field)| {
+                                parquet_value(field)
+                                    .map(|value| (column.clone(), Cell::Typed(value)))
+                            })
+                            .collect();
+                        (i + 1, Ok(cells))
+                    },
+                )))
+            }
+            None => Err(format!(
+                "{}: only CSV and Parquet files can be ingested",
+                file
+            )),
+        }
+    }
+
+    /// Loads the nodes of every mapped file, then the edges joining them
+    pub fn ingest(&mut self) -> Result<FileReport, IngestionError> {
+        let mut report = FileReport::default();
+
+        // nodes loaded before the job was resumed are joined on as well
+        let mut ids = std::mem::take(&mut self.id_mappings);
+        self.read_in_parallel(
+            self.mapping.nodes.len(),
+            |i, sender| self.read_nodes(&self.mapping.nodes[i], sender),
+            |chunk, report| self.send_chunk(chunk, &mut ids, report),
+            &mut report,
+        )?;
+        self.id_mappings = ids;
+
+        // edges have no ids to keep
+        let mut ids = HashMap::new();
+        self.read_in_parallel(
+            self.mapping.edges.len(),
+            |i, sender| self.read_edges(&self.mapping.edges[i], sender),
+            |chunk, report| self.send_chunk(chunk, &mut ids, report),
+            &mut report,
+        )?;
+
+        if let Some(job) = &self.job {
+            let mut checkpoint = job.checkpoint();
+            checkpoint.done = true;
+            self.send_batch(&IngestBatch {
+                job: Some(checkpoint),
+                ..Default::default()
+            })?;
+        }
+        Ok(report)
+    }
+
+    /// Whether a row of a stream was loaded before the job was resumed
+    fn is_loaded(&self, stream: &str, line: usize) -> bool {
+        self.job
+            .as_ref()
+            .is_some_and(|job| job.is_loaded(stream, line as u64))
+    }
+
+    /// The checkpoint of the job for the rows of a chunk, if the ingestion is a job
+    fn checkpoint(&self, rows: &Rows, keys: &[Option<(String, String)>]) -> Option<JobCheckpoint> {
+        let mut checkpoint = self.job.as_ref()?.checkpoint();
+        checkpoint.advance(&rows.stream, rows.position);
+        checkpoint.keys = keys.to_vec();
+        checkpoint.errors = rows.errors.clone();
+        Some(checkpoint)
+    }
+
+    /// Runs a reader per mapped file, sending the chunks they read as they come.
+    /// Readers stop when sending fails, as the channel is dropped with the receiver.
+    fn read_in_parallel(
+        &self,
+        files: usize,
+        read: impl Fn(usize, &flume::Sender<Chunk>) + Sync,
+        mut send: impl FnMut(Chunk, &mut FileReport) -> Result<(), IngestionError>,
+        report: &mut FileReport,
+    ) -> Result<(), IngestionError> {
+        thread::scope(|scope| {
+            let (sender, receiver) = flume::bounded(files * 2);
+            for i in 0..files {
+                let sender = sender.clone();
+                let read = &read;
+                scope.spawn(move || read(i, &sender));
+            }
+            drop(sender);
+            for chunk in receiver.iter() {
+                send(chunk, report)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn read_nodes(&self, mapping: &NodeMapping, sender: &flume::Sender<Chunk>) {
+        let rows = match self.rows(&mapping.file) {
+            Ok(rows) => rows,
+            Err(e) => {
+                let _ = sender.send(Chunk::Error(e));
+                return;
+            }
+        };
+        let stream = stream(&mapping.label, &mapping.file);
+        let mut read = Rows::new(stream.clone());
+        let mut keys = Vec::new();
+        let mut nodes = Vec::new();
+        for (line, row) in rows {
+            if self.is_loaded(&stream, line) {
+                continue;
+            }
+            read.position = line as u64;
+            let node = row.and_then(|mut cells| {
+                let key = match &mapping.id {
+                    Some(id) => match cells.get(id) {
+                        Some(cell) => Some((mapping.label.clone(), key_text(cell)?)),
+                        None => return Err(format!("{}:{}: no id", mapping.file, line)),
+                    },
+                    None => None,
+                };
+                let properties = properties(&mapping.properties, &mut cells, &[])
+                    .map_err(|e| format!("{}:{}: {}", mapping.file, line, e))?;
+                Ok((key, properties))
+            });
+            match node {
+                Ok((key, properties)) => {
+                    keys.push(key);
+                    nodes.push(IngestNode {
+                        label: mapping.label.clone(),
+                        properties,
+                        external_id: mapping.external_id.clone(),
+                    });
+                }
+                Err(e) => read.errors.push(e),
+            }
+            if nodes.len() + read.errors.len() >= self.batch_size {
+                let chunk = Chunk::Nodes {
+                    rows: read.take(),
+                    keys: std::mem::take(&mut keys),
+                    nodes: std::mem::take(&mut nodes),
+                };
+                if sender.send(chunk).is_err() {
+                    return;
+                }
+            }
+        }
+        if !nodes.is_empty() || !read.errors.is_empty() {
+            let _ = sender.send(Chunk::Nodes {
+                rows: read,
+                keys,
+                nodes,
+            });
+        }
+    }
+
+    fn read_edges(&self, mapping: &EdgeMapping, sender: &flume::Sender<Chunk>) {
+        let rows = match self.rows(&mapping.file) {
+            Ok(rows) => rows,
+            Err(e) => {
+                let _ = sender.send(Chunk::Error(e));
+                return;
+            }
+        };
+        let join_columns = [mapping.from.column.clone(), mapping.to.column.clone()];
+        let stream = stream(&mapping.label, &mapping.file);
+        let mut read = Rows::new(stream.clone());
+        let mut edges = Vec::new();
+        for (line, row) in rows {
+            if self.is_loaded(&stream, line) {
+                continue;
+            }
+            read.position = line as u64;
+            let edge = row.and_then(|mut cells| {
+                let located = |e: String| format!("{}:{}: {}", mapping.file, line, e);
+                let join = |join: &JoinMapping| {
+                    let id = cells
+                        .get(&join.column)
+                        .ok_or_else(|| located(format!("no {}", join.column)))
+                        .and_then(|cell| key_text(cell).map_err(located))?;
+                    self.id_mappings
+                        .get(&(join.label.clone(), id.clone()))
+                        .cloned()
+                        .ok_or_else(|| located(format!("unknown {} {}", join.label, id)))
+                };
+                let from = join(&mapping.from)?;
+                let to = join(&mapping.to)?;
+                let properties =
+                    properties(&mapping.properties, &mut cells, &join_columns).map_err(located)?;
+                Ok(IngestEdge {
+                    label: mapping.label.clone(),
+                    from,
+                    to,
+                    properties,
+                    unique: mapping.unique,
+                })
+            });
+            match edge {
+                Ok(edge) => edges.push(edge),
+                Err(e) => read.errors.push(e),
+            }
+            if edges.len() + read.errors.len() >= self.batch_size {
+                let chunk = Chunk::Edges {
+                    rows: read.take(),
+                    edges: std::mem::take(&mut edges),
+                };
+                if sender.send(chunk).is_err() {
+                    return;
+                }
+            }
+        }
+        if !edges.is_empty() || !read.errors.is_empty() {
+            let _ = sender.send(Chunk::Edges { rows: read, edges });
+        }
+    }
+
+    fn send_chunk(
+        &self,
+        chunk: Chunk,
+        ids: &mut HashMap<(String, String), String>,
+        report: &mut FileReport,
+    ) -> Result<(), IngestionError> {
+        match chunk {
+            Chunk::Nodes { rows, keys, nodes } => {
+                report.errors.extend(rows.errors.iter().cloned());
+                let batch = IngestBatch {
+                    job: self.checkpoint(&rows, &keys),
+                    nodes,
+                    ..Default::default()
+                };
+                if batch.nodes.is_empty() && batch.job.is_none() {
+                    return Ok(());
+                }
+                let result = self.send_batch(&batch)?;
+                for (key, id) in keys.into_iter().zip(result.nodes) {
+                    if let Some(id) = id {
+                        if let Some(key) = key {
+                            ids.insert(key, id);
+                        }
+                        report.nodes += 1;
+                    }
+                }
+                for error in result.errors {
+                    let label = &batch.nodes[error.index].label;
+                    report.errors.push(format!("{}: {}", label, error.error));
+                }
+                println!(
+                    "Sent batch of {} nodes (total: {})",
+                    batch.nodes.len(),
+                    report.nodes
+                );
+            }
+            Chunk::Edges { rows, edges } => {
+                report.errors.extend(rows.errors.iter().cloned());
+                let batch = IngestBatch {
+                    job: self.checkpoint(&rows, &[]),
+                    edges,
+                    ..Default::default()
+                };
+                if batch.edges.is_empty() && batch.job.is_none() {
+                    return Ok(());
+                }
+                let result = self.send_batch(&batch)?;
+                report.edges += result.edges.iter().flatten().count();
+                for error in result.errors {
+                    let label = &batch.edges[error.index].label;
+                    report.errors.push(format!("{}: {}", label, error.error));
+                }
+                println!(
+                    "Sent batch of {} edges (total: {})",
+                    batch.edges.len(),
+                    report.edges
+                );
+            }
+            Chunk::Error(e) => report.errors.push(e),
+        }
+        Ok(())
+    }
+
+    fn send_batch(&self, batch: &IngestBatch) -> Result<IngestResult, IngestionError> {
+        let url = format!("{}{}", self.instance, INGEST_PATH);
+        let response = self.client.post(&url).json(batch).send().map_err(|e| {
+            IngestionError::HttpError(format!("Failed to send batch to {}: {}", url, e))
+        })?;
+        if !response.status().is_success() {
+            return Err(IngestionError::HttpError(format!(
+                "Request to {} failed with status: {}",
+                url,
+                response.status()
+            )));
+        }
+        response.json().map_err(|e| {
+            IngestionError::HttpError(format!("Failed to parse ingestion response: {}", e))
+        })
+    }
+}
+
+/// Stream of the job the rows of a mapping are read as
+fn stream(label: &str, file: &str) -> String {
+    format!("{}:{}", label, file)
+}
+
+fn check_properties(
+    file: &str,
+    properties: &Option<HashMap<String, PropertyMapping>>,
+    columns: &[String],
+) -> Result<(), IngestionError> {
+    for (column, property) in properties.iter().flatten() {
+        if !columns.contains(column) {
+            return Err(IngestionError::MappingError(format!(
+                "{}: no column {}",
+                file, column
+            )));
+        }
+        property.kind()?;
+    }
+    Ok(())
+}
+
+/// The properties of a row, every column but the excluded ones when the mapping has
+/// no properties
+fn properties(
+    mapping: &Option<HashMap<String, PropertyMapping>>,
+    cells: &mut HashMap<String, Cell>,
+    excluded: &[String],
+) -> Result<HashMap<String, Value>, String> {
+    match mapping {
+        Some(mapping) => mapping
+            .iter()
+            .filter_map(|(column, property)| {
+                let cell = cells.remove(column)?;
+                let kind = property.kind().map_err(|e| e.to_string());
+                Some(kind.and_then(|kind| {
+                    cell_value(cell, kind).map(|value| (property.name().to_string(), value))
+                }))
+            })
+            .collect(),
+        None => cells
+            .drain()
+            .filter(|(column, _)| !excluded.contains(column))
+            .map(|(column, cell)| {
+                cell_value(cell, PropertyKind::Inferred).map(|value| (column, value))
+            })
+            .collect(),
+    }
+}
+
+/// The value of a cell as `kind`, typed Parquet values being converted when they are
+/// of another type
+fn cell_value(cell: Cell, kind: PropertyKind) -> Result<Value, String> {
+    match (cell, kind) {
+        (Cell::Text(text), kind) => typed_value(kind, &text),
+        (Cell::Typed(value), PropertyKind::Inferred)
+        | (Cell::Typed(value @ Value::I64(_)), PropertyKind::Integer)
+        | (Cell::Typed(value @ Value::F64(_)), PropertyKind::Float)
+        | (Cell::Typed(value @ Value::Boolean(_)), PropertyKind::Boolean)
+        | (Cell::Typed(value @ Value::String(_)), PropertyKind::String) => Ok(value),
+        (Cell::Typed(value @ (Value::Array(_) | Value::Object(_))), _) => {
+            Err(format!("{} values can't be converted", value))
+        }
+        (Cell::Typed(value), kind) => typed_value(kind, &value.to_string()),
+    }
+}
+
+/// A cell as the id of a node
+fn key_text(cell: &Cell) -> Result<String, String> {
+    match cell {
+        Cell::Text(text) => Ok(text.clone()),
+        Cell::Typed(value @ (Value::Array(_) | Value::Object(_) | Value::Empty)) => {
+            Err(format!("{} values can't be ids", value))
+        }
+        Cell::Typed(value) => Ok(value.to_string()),
+    }
+}
+
+fn parquet_reader(path: &Path) -> Result<SerializedFileReader<File>, IngestionError> {
+    let file = File::open(path).map_err(|e| {
+        IngestionError::MappingError(format!("Failed to open {}: {}", path.display(), e))
+    })?;
+    SerializedFileReader::new(file).map_err(|e| {
+        IngestionError::MappingError(format!("Failed to read {}: {}", path.display(), e))
+    })
+}
+
+/// A Parquet field as a value, nulls having none
+fn parquet_value(field: &Field) -> Option<Value> {
+    let value = match field {
+        Field::Null => return None,
+        Field::Bool(b) => Value::Boolean(*b),
+        Field::Byte(i) => Value::I64(*i as i64),
+        Field::Short(i) => Value::I64(*i as i64),
+        Field::Int(i) => Value::I64(*i as i64),
+        Field::Long(i) => Value::I64(*i),
+        Field::UByte(u) => Value::I64(*u as i64),
+        Field::UShort(u) => Value::I64(*u as i64),
+        Field::UInt(u) => Value::I64(*u as i64),
+        Field::ULong(u) => Value::U64(*u),
+        Field::Float16(f) => Value::F64(f64::from(*f)),
+        Field::Float(f) => Value::F64(*f as f64),
+        Field::Double(f) => Value::F64(*f),
+        Field::Str(s) => Value::String(s.clone()),
+        Field::Bytes(bytes) => Value::String(String::from_utf8_lossy(bytes.data()).to_string()),
+        Field::TimestampMillis(ms) => {
+            Value::DateTime(chrono::DateTime::from_timestamp_millis(*ms)?)
+        }
+        Field::TimestampMicros(us) => {
+            Value::DateTime(chrono::DateTime::from_timestamp_micros(*us)?)
+        }
+        Field::ListInternal(list) => {
+            Value::Array(list.elements().iter().filter_map(parquet_value).collect())
+        }
+        // dates, decimals, structs and maps as their text
+        field => Value::String(field.to_string()),
+    };
+    Some(value)
+}
@@ -0,0 +1,406 @@
+use crate::{
+    helix_engine::{
+        graph_core::config::Config,
+        ingest_jobs::ingest_jobs::JobStatus,
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    },
+    ingestion_engine::{
+        file_ingestion::FileIngestor,
+        neo4j_tests::{serve_ingest, serve_storage},
+    },
+    protocol::{filterable::Filterable, value::Value},
+};
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use std::{
+    fs,
+    fs::File,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tempfile::TempDir;
+
+fn write_users_csv(dir: &TempDir) {
+    fs::write(
+        dir.path().join("users.csv"),
+        "user_id,name,zip,age\n\
+         u1,Alice,01234,31\n\
+         u2,\"Bob, B\",98765,\n",
+    )
+    .unwrap();
+}
+
+/// Products `p1` and `p2` with their prices
+fn write_products_parquet(dir: &TempDir) {
+    let schema = Arc::new(
+        parse_message_type(
+            "message product {
+                REQUIRED BYTE_ARRAY sku (UTF8);
+                REQUIRED DOUBLE price;
+                OPTIONAL INT64 stock;
+            }",
+        )
+        .unwrap(),
+    );
+    let file = File::create(dir.path().join("products.parquet")).unwrap();
+    let mut writer =
+        SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::default())).unwrap();
+    let mut row_group = writer.next_row_group().unwrap();
+
+    let mut column = row_group.next_column().unwrap().unwrap();
+    column
+        .typed::<ByteArrayType>()
+        .write_batch(&[ByteArray::from("p1"), ByteArray::from("p2")], None, None)
+        .unwrap();
+    column.close().unwrap();
+    let mut column = row_group.next_column().unwrap().unwrap();
+    column
+        .typed::<DoubleType>()
+        .write_batch(&[9.5, 20.0], None, None)
+        .unwrap();
+    column.close().unwrap();
+    // p2 has no stock
+    let mut column = row_group.next_column().unwrap().unwrap();
+    column
+        .typed::<Int64Type>()
+        .write_batch(&[3], Some(&[1, 0]), None)
+        .unwrap();
+    column.close().unwrap();
+
+    row_group.close().unwrap();
+    writer.close().unwrap();
+}
+
+fn write_mapping(dir: &TempDir, mapping: &str) -> String {
+    let path = dir.path().join("mapping.json");
+    fs::write(&path, mapping).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+const MAPPING: &str = r#"{
+    "nodes": [
+        {
+            "file": "users.csv",
+            "label": "User",
+            "id": "user_id",
+            "properties": {
+                "name": "name",
+                "zip": { "name": "zipCode", "type": "String" },
+                "age": { "name": "age", "type": "I64" }
+            }
+        },
+        { "file": "products.parquet", "label": "Product", "id": "sku" }
+    ],
+    "edges": [
+        {
+            "file": "orders.csv",
+            "label": "Bought",
+            "from": { "label": "User", "column": "user_id" },
+            "to": { "label": "Product", "column": "sku" }
+        }
+    ]
+}"#;
+
+#[test]
+fn test_ingest_csv_and_parquet() {
+    let dir = TempDir::new().unwrap();
+    write_users_csv(&dir);
+    write_products_parquet(&dir);
+    fs::write(
+        dir.path().join("orders.csv"),
+        "user_id,sku,qty\nu1,p1,2\nu2,p2,1\nu3,p1,5\n",
+    )
+    .unwrap();
+    let (url, batches) = serve_ingest();
+
+    let mut ingestor = FileIngestor::new(&write_mapping(&dir, MAPPING), Some(url), 10).unwrap();
+    let report = ingestor.ingest().unwrap();
+    assert_eq!(report.nodes, 4);
+    assert_eq!(report.edges, 2);
+    assert_eq!(
+        report.errors,
+        vec!["orders.csv:4: unknown User u3".to_string()]
+    );
+
+    let batches = batches.lock().unwrap();
+    let nodes = batches
+        .iter()
+        .flat_map(|batch| batch.nodes.iter())
+        .collect::<Vec<_>>();
+    let alice = nodes
+        .iter()
+        .find(|node| node.properties.get("name") == Some(&Value::String("Alice".to_string())))
+        .unwrap();
+    assert_eq!(alice.label, "User");
+    // typed as a string, keeping its leading zero
+    assert_eq!(
+        alice.properties.get("zipCode"),
+        Some(&Value::String("01234".to_string()))
+    );
+    // the server reads positive integers back as unsigned
+    assert_eq!(alice.properties.get("age"), Some(&Value::U64(31)));
+    // unmapped columns are left out
+    assert!(!alice.properties.contains_key("user_id"));
+
+    let p2 = nodes
+        .iter()
+        .find(|node| node.properties.get("sku") == Some(&Value::String("p2".to_string())))
+        .unwrap();
+    assert_eq!(p2.label, "Product");
+    assert_eq!(p2.properties.get("price"), Some(&Value::F64(20.0)));
+    assert!(!p2.properties.contains_key("stock"));
+
+    let edges = batches
+        .iter()
+        .flat_map(|batch| batch.edges.iter())
+        .collect::<Vec<_>>();
+    let bought = edges
+        .iter()
+        .find(|edge| edge.from == ingestor.id_mappings[&("User".to_string(), "u1".to_string())])
+        .unwrap();
+    assert_eq!(
+        bought.to,
+        ingestor.id_mappings[&("Product".to_string(), "p1".to_string())]
+    );
+    // join columns aren't properties of the edge
+    assert_eq!(
+        bought.properties,
+        [("qty".to_string(), Value::U64(2))].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_batches() {
+    let dir = TempDir::new().unwrap();
+    let rows = (0..25).map(|i| format!("{}\n", i)).collect::<String>();
+    fs::write(dir.path().join("items.csv"), format!("n\n{}", rows)).unwrap();
+    let (url, batches) = serve_ingest();
+
+    let mapping = r#"{ "nodes": [{ "file": "items.csv", "label": "Item" }] }"#;
+    let mut ingestor = FileIngestor::new(&write_mapping(&dir, mapping), Some(url), 10).unwrap();
+    let report = ingestor.ingest().unwrap();
+    assert_eq!(report.nodes, 25);
+    assert!(report.errors.is_empty());
+
+    let sizes = batches
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|batch| batch.nodes.len())
+        .collect::<Vec<_>>();
+    assert_eq!(sizes, vec![10, 10, 5]);
+    // nodes without an id column aren't kept for edges
+    assert!(ingestor.id_mappings.is_empty());
+}
+
+#[test]
+fn test_invalid_rows_are_reported() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("users.csv"),
+        "user_id,name,zip,age\nu1,Alice,01234,old\nu2,Bob\n",
+    )
+    .unwrap();
+    let (url, _) = serve_ingest();
+
+    let mapping = r#"{ "nodes": [{
+        "file": "users.csv",
+        "label": "User",
+        "properties": { "age": { "name": "age", "type": "I64" } }
+    }] }"#;
+    let mut ingestor = FileIngestor::new(&write_mapping(&dir, mapping), Some(url), 10).unwrap();
+    let report = ingestor.ingest().unwrap();
+    assert_eq!(report.nodes, 0);
+    let mut errors = report.errors;
+    errors.sort();
+    assert_eq!(
+        errors,
+        vec![
+            "users.csv:2: `old` isn't a valid integer".to_string(),
+            "users.csv:3: expected 4 columns, got 2".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_resume_job() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("users.csv"),
+        "user_id,name,zip,age\n\
+         u1,Alice,01234,31\n\
+         u2,Bob,98765,\n\
+         u3,Carol\n\
+         u4,Dan,11111,40\n\
+         u5,Eve,22222,25\n",
+    )
+    .unwrap();
+    write_products_parquet(&dir);
+    fs::write(
+        dir.path().join("orders.csv"),
+        "user_id,sku,qty\nu1,p1,2\nu5,p2,1\nu9,p1,5\n",
+    )
+    .unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(
+        HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), Config::default()).unwrap(),
+    );
+    // the instance goes away after two batches, of whichever files were read first
+    let batches = Arc::new(AtomicUsize::new(2));
+    let url = serve_storage(Arc::clone(&storage), Arc::clone(&batches));
+    let mapping = write_mapping(&dir, MAPPING);
+
+    let mut ingestor = FileIngestor::new(&mapping, Some(url.clone()), 2).unwrap();
+    let id = ingestor.start_job();
+    assert!(ingestor.ingest().is_err());
+
+    batches.store(usize::MAX, Ordering::SeqCst);
+    let mut ingestor = FileIngestor::new(&mapping, Some(url), 2).unwrap();
+    let job = ingestor.resume(&id).unwrap();
+    assert_eq!(job.batches, 2);
+    assert_eq!(ingestor.id_mappings.len() as u64, job.nodes);
+    let report = ingestor.ingest().unwrap();
+    assert_eq!(report.nodes as u64 + job.nodes, 6);
+
+    // every row is loaded or reported once over both runs
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.nodes_db.len(&txn).unwrap(), 6);
+    assert_eq!(storage.edges_db.len(&txn).unwrap(), 2);
+    let job = storage.ingest_jobs.get(&txn, &id).unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Completed);
+    assert_eq!((job.nodes, job.edges), (6, 2));
+    let mut errors = job.errors;
+    errors.sort();
+    assert_eq!(
+        errors,
+        vec![
+            "orders.csv:4: unknown User u9".to_string(),
+            "users.csv:4: expected 4 columns, got 2".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_ingest_again_upserts() {
+    let dir = TempDir::new().unwrap();
+    write_users_csv(&dir);
+    write_products_parquet(&dir);
+    fs::write(
+        dir.path().join("orders.csv"),
+        "user_id,sku,qty\nu1,p1,2\nu2,p2,1\n",
+    )
+    .unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = Config::default();
+    config.graph_config.unique_indices = Some(vec!["userId".to_string(), "sku".to_string()]);
+    let storage =
+        Arc::new(HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap());
+    let url = serve_storage(Arc::clone(&storage), Arc::new(AtomicUsize::new(usize::MAX)));
+    let mapping = write_mapping(
+        &dir,
+        r#"{
+            "nodes": [
+                {
+                    "file": "users.csv",
+                    "label": "User",
+                    "id": "user_id",
+                    "external_id": "userId",
+                    "properties": { "user_id": "userId", "name": "name" }
+                },
+                { "file": "products.parquet", "label": "Product", "id": "sku", "external_id": "sku" }
+            ],
+            "edges": [
+                {
+                    "file": "orders.csv",
+                    "label": "Bought",
+                    "from": { "label": "User", "column": "user_id" },
+                    "to": { "label": "Product", "column": "sku" },
+                    "unique": true
+                }
+            ]
+        }"#,
+    );
+
+    let mut ingestor = FileIngestor::new(&mapping, Some(url.clone()), 10).unwrap();
+    ingestor.ingest().unwrap();
+    let first = ingestor.id_mappings.clone();
+
+    // Alice was renamed since
+    fs::write(
+        dir.path().join("users.csv"),
+        "user_id,name,zip,age\nu1,Alicia,01234,31\nu2,Bob,98765,\n",
+    )
+    .unwrap();
+    let mut ingestor = FileIngestor::new(&mapping, Some(url), 10).unwrap();
+    let report = ingestor.ingest().unwrap();
+    assert_eq!((report.nodes, report.edges), (4, 2));
+    assert!(report.errors.is_empty());
+    assert_eq!(ingestor.id_mappings, first);
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.nodes_db.len(&txn).unwrap(), 4);
+    assert_eq!(storage.edges_db.len(&txn).unwrap(), 2);
+    let alice = uuid::Uuid::parse_str(&first[&("User".to_string(), "u1".to_string())])
+        .unwrap()
+        .as_u128();
+    let alice = storage.get_node(&txn, &alice).unwrap();
+    assert_eq!(
+        alice.check_property("name").unwrap(),
+        &Value::String("Alicia".to_string())
+    );
+}
+
+#[test]
+fn test_invalid_mappings() {
+    let dir = TempDir::new().unwrap();
+    write_users_csv(&dir);
+    let error = |mapping: &str| {
+        FileIngestor::new(&write_mapping(&dir, mapping), None, 10)
+            .err()
+            .unwrap()
+            .to_string()
+    };
+
+    let e = error(r#"{ "nodes": [{ "file": "users.csv", "label": "User", "id": "id" }] }"#);
+    assert!(e.contains("no id column id"));
+
+    let e = error(
+        r#"{ "nodes": [{
+            "file": "users.csv",
+            "label": "User",
+            "properties": { "age": { "name": "age", "type": "Int" } }
+        }] }"#,
+    );
+    assert!(e.contains("expected I64, F64, Boolean or String"));
+
+    let e = error(
+        r#"{
+            "nodes": [{ "file": "users.csv", "label": "User" }],
+            "edges": [{
+                "file": "users.csv",
+                "label": "Knows",
+                "from": { "label": "User", "column": "user_id" },
+                "to": { "label": "User", "column": "name" }
+            }]
+        }"#,
+    );
+    assert!(e.contains("User isn't mapped to nodes with an id"));
+
+    let e = error(
+        r#"{ "nodes": [{
+            "file": "users.csv",
+            "label": "User",
+            "external_id": "user_id",
+            "properties": { "user_id": "userId" }
+        }] }"#,
+    );
+    assert!(e.contains("external id user_id isn't a mapped property"));
+
+    let e = error(r#"{ "nodes": [{ "file": "users.json", "label": "User" }] }"#);
+    assert!(e.contains("only CSV and Parquet files"));
+}
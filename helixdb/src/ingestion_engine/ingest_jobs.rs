@@ -0,0 +1,113 @@
+use crate::{
+    helix_engine::ingest_jobs::ingest_jobs::{IngestJob, JobStatus},
+    helix_gateway::ingest::ingest::{JobCheckpoint, INGEST_JOBS_PATH},
+    ingestion_engine::sql_ingestion::IngestionError,
+};
+use reqwest::{blocking::Client, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap};
+
+/// The job of an instance an ingestor checkpoints its batches as, for an interrupted
+/// ingestion to be resumed where it was left off rather than started over.
+///
+/// Ingestors read their source as streams of rows, e.g. one per file, and send the
+/// position of the last row read for a batch along with it. A resumed job skips the
+/// rows up to the positions of the batches loaded before.
+#[derive(Debug, Clone)]
+pub struct JobState {
+    pub id: String,
+    /// What the job ingests, a job is only resumed for the same source
+    pub source: String,
+    /// Stream => position of the last row of it that was loaded
+    pub positions: BTreeMap<String, u64>,
+}
+
+impl JobState {
+    /// A new job of the source
+    pub fn start(source: &str) -> Self {
+        JobState {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: source.to_string(),
+            positions: BTreeMap::new(),
+        }
+    }
+
+    /// Fetches a job of the instance to resume it, failing if it ingests another source
+    /// or is completed
+    pub fn resume(
+        client: &Client,
+        instance: &str,
+        id: &str,
+        source: &str,
+    ) -> Result<(Self, IngestJob), IngestionError> {
+        let job: IngestJob = get(client, instance, &format!("{}/{}", INGEST_JOBS_PATH, id))?;
+        if job.source != source {
+            return Err(IngestionError::JobError(format!(
+                "Job {} ingests {}, not {}",
+                id, job.source, source
+            )));
+        }
+        if job.status == JobStatus::Completed {
+            return Err(IngestionError::JobError(format!(
+                "Job {} is already completed",
+                id
+            )));
+        }
+        let state = JobState {
+            id: job.id.clone(),
+            source: job.source.clone(),
+            positions: job.positions.clone(),
+        };
+        Ok((state, job))
+    }
+
+    /// The uuids of the nodes the job loaded by their `(label, id)` in the source
+    pub fn keys(
+        &self,
+        client: &Client,
+        instance: &str,
+    ) -> Result<HashMap<(String, String), String>, IngestionError> {
+        let keys: Vec<((String, String), String)> = get(
+            client,
+            instance,
+            &format!("{}/{}/keys", INGEST_JOBS_PATH, self.id),
+        )?;
+        Ok(keys.into_iter().collect())
+    }
+
+    /// Whether the row at a position of a stream was loaded before the job was resumed
+    pub fn is_loaded(&self, stream: &str, position: u64) -> bool {
+        self.positions
+            .get(stream)
+            .is_some_and(|loaded| position <= *loaded)
+    }
+
+    /// An empty checkpoint of the job, for the next batch
+    pub fn checkpoint(&self) -> JobCheckpoint {
+        JobCheckpoint::new(&self.id, &self.source)
+    }
+}
+
+fn get<T: DeserializeOwned>(
+    client: &Client,
+    instance: &str,
+    path: &str,
+) -> Result<T, IngestionError> {
+    let url = format!("{}{}", instance, path);
+    let response = client.get(&url).send().map_err(|e| {
+        IngestionError::HttpError(format!("Failed to send request to {}: {}", url, e))
+    })?;
+    match response.status() {
+        status if status.is_success() => response.json().map_err(|e| {
+            IngestionError::HttpError(format!("Failed to parse response of {}: {}", url, e))
+        }),
+        StatusCode::NOT_FOUND => Err(IngestionError::JobError(format!(
+            "No ingestion job at {}",
+            url
+        ))),
+        status => Err(IngestionError::HttpError(format!(
+            "Request to {} failed with status: {}",
+            url, status
+        ))),
+    }
+}
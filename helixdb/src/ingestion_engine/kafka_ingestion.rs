@@ -0,0 +1,564 @@
+use crate::{
+    helix_engine::{
+        graph_core::ops::{
+            g::G,
+            source::{
+                add_e::{AddEAdapter, EdgeType},
+                add_n::AddNAdapter,
+            },
+            vectors::insert::InsertVAdapter,
+        },
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+        types::GraphError,
+        vector_core::vector::HVector,
+    },
+    helix_gateway::ingest::ingest::inserted,
+    helix_storage::heed3::{RoTxn, RwTxn},
+    ingestion_engine::sql_ingestion::IngestionError,
+    protocol::{filterable::Filterable, value::Value},
+};
+use rdkafka::{
+    consumer::{BaseConsumer, CommitMode, Consumer},
+    ClientConfig, Message as _, Offset, TopicPartitionList,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Time watermarks are fetched from the brokers for, after every batch
+const WATERMARK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Topics to consume and how their messages are written to the graph.
+///
+/// ```json
+/// {
+///   "brokers": "localhost:9092",
+///   "group_id": "helix",
+///   "topics": [
+///     { "topic": "users", "op": "add_n", "label": "User",
+///       "properties": { "name": "/name", "userId": "/id" } },
+///     { "topic": "follows", "op": "add_e", "label": "Follows",
+///       "from": { "field": "/follower", "index": "userId" },
+///       "to": { "field": "/followed", "index": "userId" } },
+///     { "topic": "embeddings", "op": "add_v", "label": "Embedding", "vector": "/embedding" }
+///   ]
+/// }
+/// ```
+///
+/// Messages are JSON objects, fields being JSON pointers into them or the names of
+/// their top level fields. Without `properties` every top level field is a property,
+/// but for the ones an edge or vector is built from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KafkaConfig {
+    pub brokers: String,
+    pub group_id: String,
+    pub topics: Vec<TopicMapping>,
+    /// Most messages written in one transaction
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Longest a batch waits to fill up before it is written
+    #[serde(default = "default_batch_timeout_ms")]
+    pub batch_timeout_ms: u64,
+    /// librdkafka properties of the consumer, such as `security.protocol`
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+}
+
+fn default_batch_size() -> usize {
+    1000
+}
+
+fn default_batch_timeout_ms() -> u64 {
+    500
+}
+
+impl KafkaConfig {
+    pub fn from_file(path: &Path) -> Result<Self, IngestionError> {
+        let config = fs::read_to_string(path).map_err(|e| {
+            IngestionError::MappingError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        serde_json::from_str(&config).map_err(|e| {
+            IngestionError::MappingError(format!("Invalid config {}: {}", path.display(), e))
+        })
+    }
+}
+
+/// The operation the messages of a topic are mapped to
+#[derive(Debug, Clone, Deserialize)]
+pub struct TopicMapping {
+    pub topic: String,
+    #[serde(flatten)]
+    pub operation: Operation,
+}
+
+/// An item added for every message, with its properties by the field they are read from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    AddN {
+        label: String,
+        #[serde(default)]
+        properties: Option<HashMap<String, String>>,
+    },
+    AddE {
+        label: String,
+        from: NodeRef,
+        to: NodeRef,
+        #[serde(default)]
+        properties: Option<HashMap<String, String>>,
+    },
+    AddV {
+        label: String,
+        /// Field holding the array of the vector
+        vector: String,
+        #[serde(default)]
+        properties: Option<HashMap<String, String>>,
+    },
+}
+
+/// A node an edge connects, by the uuid in `field`, or by the value in `field` of
+/// the secondary index `index`
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeRef {
+    pub field: String,
+    #[serde(default)]
+    pub index: Option<String>,
+}
+
+/// A consumed message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub payload: Vec<u8>,
+}
+
+/// The messages of a batch that were written and the ones that couldn't be, by their
+/// position in the batch
+#[derive(Debug, Default)]
+pub struct BatchResult {
+    /// Uuids of the items written
+    pub written: Vec<String>,
+    pub errors: Vec<(usize, String)>,
+}
+
+/// Writes a batch of messages in one write transaction.
+///
+/// A message that fails doesn't fail the batch: the transaction is aborted and the
+/// batch written again without it, like batches of the ingestion endpoint.
+pub fn write_batch(
+    storage: &Arc<HelixGraphStorage>,
+    operations: &HashMap<String, Operation>,
+    messages: &[Message],
+) -> Result<BatchResult, GraphError> {
+    let mut errors = Vec::new();
+    let mut skipped = HashSet::new();
+    'batch: loop {
+        // the map can't be resized while the write transaction is open
+        let _hold = storage.map_size.hold();
+        let mut txn = storage.graph_env.write_txn()?;
+        let mut written = Vec::new();
+        for (i, message) in messages.iter().enumerate() {
+            if skipped.contains(&i) {
+                continue;
+            }
+            let result = match operations.get(&message.topic) {
+                Some(operation) => apply(storage, &mut txn, operation, &message.payload),
+                None => Err(GraphError::New(format!(
+                    "No mapping for topic {}",
+                    message.topic
+                ))),
+            };
+            match result {
+                Ok(id) => written.push(id),
+                Err(e) => {
+                    txn.abort();
+                    skipped.insert(i);
+                    errors.push((i, e.to_string()));
+                    continue 'batch;
+                }
+            }
+        }
+        txn.commit()?;
+        return Ok(BatchResult { written, errors });
+    }
+}
+
+/// Adds the item a message is mapped to, returning its uuid
+pub fn apply(
+    storage: &Arc<HelixGraphStorage>,
+    txn: &mut RwTxn,
+    operation: &Operation,
+    payload: &[u8],
+) -> Result<String, GraphError> {
+    let payload: serde_json::Value = serde_json::from_slice(payload)
+        .map_err(|e| GraphError::New(format!("Invalid message: {}", e)))?;
+    match operation {
+        Operation::AddN { label, properties } => {
+            let properties = message_properties(&payload, properties, &[])?;
+            let indices = storage
+                .secondary_indices
+                .keys()
+                .filter(|index| properties.iter().any(|(key, _)| key == *index))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            inserted(
+                G::new_mut(Arc::clone(storage), txn)
+                    .add_n(label, some(properties), Some(&indices))
+                    .try_collect_to::<Vec<_>>(),
+            )
+        }
+        Operation::AddE {
+            label,
+            from,
+            to,
+            properties,
+        } => {
+            let properties = message_properties(&payload, properties, &[&from.field, &to.field])?;
+            let from = node_id(storage, txn, &payload, from)?;
+            let to = node_id(storage, txn, &payload, to)?;
+            inserted(
+                G::new_mut(Arc::clone(storage), txn)
+                    .add_e(label, some(properties), from, to, true, EdgeType::Node)
+                    .try_collect_to::<Vec<_>>(),
+            )
+        }
+        Operation::AddV {
+            label,
+            vector,
+            properties,
+        } => {
+            let data = field(&payload, vector)
+                .and_then(|data| data.as_array())
+                .and_then(|data| data.iter().map(|x| x.as_f64()).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| GraphError::New(format!("No vector in {}", vector)))?;
+            let properties = message_properties(&payload, properties, &[vector])?;
+            inserted(
+                G::new_mut(Arc::clone(storage), txn)
+                    .insert_v::<fn(&HVector, &RoTxn) -> bool>(&data, label, some(properties))
+                    .try_collect_to::<Vec<_>>(),
+            )
+        }
+    }
+}
+
+/// A field of a message by its JSON pointer or top level name
+fn field<'a>(payload: &'a serde_json::Value, name: &str) -> Option<&'a serde_json::Value> {
+    match name.starts_with('/') {
+        true => payload.pointer(name),
+        false => payload.get(name),
+    }
+    .filter(|value| !value.is_null())
+}
+
+/// The properties of a message, every top level field but the excluded ones when the
+/// mapping has none. Fields the message doesn't have are left out
+fn message_properties(
+    payload: &serde_json::Value,
+    mapping: &Option<HashMap<String, String>>,
+    excluded: &[&String],
+) -> Result<Vec<(String, Value)>, GraphError> {
+    let to_value = |value: &serde_json::Value| {
+        serde_json::from_value::<Value>(value.clone())
+            .map_err(|e| GraphError::New(format!("Invalid property: {}", e)))
+    };
+    match mapping {
+        Some(mapping) => mapping
+            .iter()
+            .filter_map(|(name, path)| field(payload, path).map(|value| (name, value)))
+            .map(|(name, value)| Ok((name.clone(), to_value(value)?)))
+            .collect(),
+        None => payload
+            .as_object()
+            .ok_or_else(|| GraphError::New("Messages must be JSON objects".to_string()))?
+            .iter()
+            .filter(|(name, value)| {
+                !value.is_null()
+                    && !excluded
+                        .iter()
+                        .any(|excluded| excluded.trim_start_matches('/') == name.as_str())
+            })
+            .map(|(name, value)| Ok((name.clone(), to_value(value)?)))
+            .collect(),
+    }
+}
+
+fn some(properties: Vec<(String, Value)>) -> Option<Vec<(String, Value)>> {
+    match properties.is_empty() {
+        true => None,
+        false => Some(properties),
+    }
+}
+
+/// The id of the node a message refers to.
+///
+/// Index entries aren't removed when a node is updated or dropped, so the node an entry
+/// points to is checked to still have the value.
+fn node_id(
+    storage: &HelixGraphStorage,
+    txn: &RwTxn,
+    payload: &serde_json::Value,
+    node: &NodeRef,
+) -> Result<u128, GraphError> {
+    let value = field(payload, &node.field)
+        .ok_or_else(|| GraphError::New(format!("No node in {}", node.field)))?;
+    let Some(index) = &node.index else {
+        let id = value
+            .as_str()
+            .ok_or_else(|| GraphError::New(format!("{} isn't a uuid", node.field)))?;
+        return uuid::Uuid::parse_str(id)
+            .map(|id| id.as_u128())
+            .map_err(|_| GraphError::New(format!("{} isn't a valid uuid", id)));
+    };
+    let db = storage
+        .secondary_indices
+        .get(index)
+        .ok_or_else(|| GraphError::New(format!("Secondary Index {} not found", index)))?;
+    let value = serde_json::from_value::<Value>(value.clone())
+        .map_err(|e| GraphError::New(format!("Invalid {}: {}", node.field, e)))?;
+    if let Some(entries) = db.get_duplicates(txn, &bincode::serialize(&value)?)? {
+        for entry in entries {
+            let (_, id) = entry?;
+            match storage.get_node(txn, &id) {
+                Ok(node) if node.check_property(index).is_ok_and(|v| *v == value) => return Ok(id),
+                Ok(_) | Err(GraphError::NodeNotFound) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Err(GraphError::New(format!(
+        "No node with {} {}",
+        index,
+        value.to_string()
+    )))
+}
+
+/// Position of the consumer in a partition and how far behind its end it is
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PartitionLag {
+    pub topic: String,
+    pub partition: i32,
+    /// Offset of the next message to consume, as committed
+    pub committed: i64,
+    pub high_watermark: i64,
+    pub lag: i64,
+}
+
+/// Metrics of a consumer, as reported by [`KafkaMetrics::status`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KafkaStatus {
+    pub consumed: u64,
+    pub failed: u64,
+    pub batches: u64,
+    /// Messages left to consume across every partition
+    pub lag: i64,
+    pub partitions: Vec<PartitionLag>,
+}
+
+/// Counts of the messages consumed and the lag of the partitions they come from,
+/// updated as batches are committed
+#[derive(Default)]
+pub struct KafkaMetrics {
+    consumed: AtomicU64,
+    failed: AtomicU64,
+    batches: AtomicU64,
+    partitions: Mutex<BTreeMap<(String, i32), PartitionLag>>,
+}
+
+impl KafkaMetrics {
+    /// Counts a committed batch
+    pub fn record_batch(&self, consumed: u64, failed: u64) {
+        self.consumed.fetch_add(consumed, Ordering::Relaxed);
+        self.failed.fetch_add(failed, Ordering::Relaxed);
+        self.batches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the committed offset of a partition against its high watermark
+    pub fn record_lag(&self, topic: &str, partition: i32, committed: i64, high_watermark: i64) {
+        self.partitions.lock().unwrap().insert(
+            (topic.to_string(), partition),
+            PartitionLag {
+                topic: topic.to_string(),
+                partition,
+                committed,
+                high_watermark,
+                lag: (high_watermark - committed).max(0),
+            },
+        );
+    }
+
+    /// Messages left to consume across every partition
+    pub fn lag(&self) -> i64 {
+        self.partitions
+            .lock()
+            .unwrap()
+            .values()
+            .map(|partition| partition.lag)
+            .sum()
+    }
+
+    pub fn status(&self) -> KafkaStatus {
+        let partitions = self
+            .partitions
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        KafkaStatus {
+            consumed: self.consumed.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            batches: self.batches.load(Ordering::Relaxed),
+            lag: partitions.iter().map(|partition| partition.lag).sum(),
+            partitions,
+        }
+    }
+}
+
+/// Consumes the topics of a [`KafkaConfig`] into the graph.
+///
+/// Auto commit is off: the offsets of a batch are committed only once the transaction
+/// writing it has committed, so messages are written at least once. Messages that
+/// can't be written are logged and counted as failed, and their offsets committed
+/// with the batch so they don't hold up their partition.
+pub struct KafkaIngestor {
+    storage: Arc<HelixGraphStorage>,
+    consumer: BaseConsumer,
+    operations: HashMap<String, Operation>,
+    batch_size: usize,
+    batch_timeout: Duration,
+    metrics: Arc<KafkaMetrics>,
+}
+
+impl KafkaIngestor {
+    /// Creates the consumer and subscribes it to the topics of the config
+    pub fn new(
+        storage: Arc<HelixGraphStorage>,
+        config: KafkaConfig,
+    ) -> Result<Self, IngestionError> {
+        let mut client_config = ClientConfig::new();
+        for (key, value) in config.properties.iter() {
+            client_config.set(key, value);
+        }
+        let consumer: BaseConsumer = client_config
+            .set("bootstrap.servers", &config.brokers)
+            .set("group.id", &config.group_id)
+            .set("enable.auto.commit", "false")
+            .set("enable.auto.offset.store", "false")
+            .create()
+            .map_err(kafka_error)?;
+
+        let operations = config
+            .topics
+            .into_iter()
+            .map(|mapping| (mapping.topic, mapping.operation))
+            .collect::<HashMap<_, _>>();
+        let topics = operations.keys().map(String::as_str).collect::<Vec<_>>();
+        consumer.subscribe(&topics).map_err(kafka_error)?;
+
+        Ok(KafkaIngestor {
+            storage,
+            consumer,
+            operations,
+            batch_size: config.batch_size.max(1),
+            batch_timeout: Duration::from_millis(config.batch_timeout_ms),
+            metrics: Arc::new(KafkaMetrics::default()),
+        })
+    }
+
+    pub fn metrics(&self) -> Arc<KafkaMetrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Consumes batches until `shutdown` is set, the batch being consumed when it is
+    /// set is written and committed first
+    pub fn run(&self, shutdown: &AtomicBool) -> Result<(), IngestionError> {
+        while !shutdown.load(Ordering::Relaxed) {
+            let messages = self.poll_batch()?;
+            if messages.is_empty() {
+                continue;
+            }
+            let result = write_batch(&self.storage, &self.operations, &messages)
+                .map_err(IngestionError::GraphError)?;
+            for (i, e) in result.errors.iter() {
+                let message = &messages[*i];
+                println!(
+                    "Failed to write message {}/{}@{}: {}",
+                    message.topic, message.partition, message.offset, e
+                );
+            }
+            self.commit(&messages)?;
+            self.metrics
+                .record_batch(result.written.len() as u64, result.errors.len() as u64);
+        }
+        Ok(())
+    }
+
+    /// Polls messages until the batch is full or its timeout runs out
+    fn poll_batch(&self) -> Result<Vec<Message>, IngestionError> {
+        let mut messages = Vec::new();
+        let deadline = Instant::now() + self.batch_timeout;
+        while messages.len() < self.batch_size {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            match self.consumer.poll(remaining) {
+                Some(Ok(message)) => messages.push(Message {
+                    topic: message.topic().to_string(),
+                    partition: message.partition(),
+                    offset: message.offset(),
+                    payload: message.payload().unwrap_or_default().to_vec(),
+                }),
+                Some(Err(e)) => return Err(kafka_error(e)),
+                None => break,
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Commits the offsets following the last message of every partition of a batch,
+    /// then updates the lag of those partitions
+    fn commit(&self, messages: &[Message]) -> Result<(), IngestionError> {
+        let mut next_offsets = BTreeMap::new();
+        for message in messages {
+            let offset = next_offsets
+                .entry((message.topic.as_str(), message.partition))
+                .or_insert(message.offset + 1);
+            *offset = (*offset).max(message.offset + 1);
+        }
+
+        let mut offsets = TopicPartitionList::new();
+        for ((topic, partition), offset) in next_offsets.iter() {
+            offsets
+                .add_partition_offset(topic, *partition, Offset::Offset(*offset))
+                .map_err(kafka_error)?;
+        }
+        self.consumer
+            .commit(&offsets, CommitMode::Sync)
+            .map_err(kafka_error)?;
+
+        for ((topic, partition), offset) in next_offsets {
+            // lag is only a metric, it is left as it was when the brokers don't answer
+            if let Ok((_, high)) =
+                self.consumer
+                    .fetch_watermarks(topic, partition, WATERMARK_TIMEOUT)
+            {
+                self.metrics.record_lag(topic, partition, offset, high);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn kafka_error(e: rdkafka::error::KafkaError) -> IngestionError {
+    IngestionError::KafkaError(e.to_string())
+}
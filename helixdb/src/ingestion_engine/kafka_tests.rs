@@ -0,0 +1,188 @@
+use crate::{
+    helix_engine::{
+        graph_core::config::Config,
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    },
+    ingestion_engine::kafka_ingestion::{
+        write_batch, KafkaConfig, KafkaMetrics, Message, Operation, PartitionLag,
+    },
+    protocol::value::Value,
+};
+use std::{collections::HashMap, sync::Arc};
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"{
+    "brokers": "localhost:9092",
+    "group_id": "helix",
+    "batch_size": 2,
+    "topics": [
+        { "topic": "users", "op": "add_n", "label": "User",
+          "properties": { "name": "/profile/name", "userId": "id" } },
+        { "topic": "follows", "op": "add_e", "label": "Follows",
+          "from": { "field": "/follower", "index": "userId" },
+          "to": { "field": "followed", "index": "userId" } },
+        { "topic": "likes", "op": "add_e", "label": "Likes",
+          "from": { "field": "from" }, "to": { "field": "to" } },
+        { "topic": "embeddings", "op": "add_v", "label": "Embedding", "vector": "/embedding" }
+    ]
+}"#;
+
+fn setup() -> (Arc<HelixGraphStorage>, HashMap<String, Operation>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = Config::default();
+    config.graph_config.secondary_indices = Some(vec!["userId".to_string()]);
+    let storage = HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap();
+
+    let config: KafkaConfig = serde_json::from_str(CONFIG).unwrap();
+    assert_eq!(config.batch_size, 2);
+    assert_eq!(config.batch_timeout_ms, 500);
+    let operations = config
+        .topics
+        .into_iter()
+        .map(|mapping| (mapping.topic, mapping.operation))
+        .collect();
+    (Arc::new(storage), operations, temp_dir)
+}
+
+fn message(topic: &str, offset: i64, payload: &str) -> Message {
+    Message {
+        topic: topic.to_string(),
+        partition: 0,
+        offset,
+        payload: payload.as_bytes().to_vec(),
+    }
+}
+
+fn parse_id(id: &str) -> u128 {
+    uuid::Uuid::parse_str(id).unwrap().as_u128()
+}
+
+#[test]
+fn test_write_nodes_and_edges() {
+    let (storage, operations, _temp_dir) = setup();
+
+    let users = write_batch(
+        &storage,
+        &operations,
+        &[
+            message(
+                "users",
+                0,
+                r#"{"id": 1, "profile": {"name": "Alice"}, "age": 30}"#,
+            ),
+            message("users", 1, r#"{"id": 2, "profile": {"name": "Bob"}}"#),
+        ],
+    )
+    .unwrap();
+    assert!(users.errors.is_empty());
+    assert_eq!(users.written.len(), 2);
+
+    // edges join on the index, within the batch writing their nodes as well
+    let follows = write_batch(
+        &storage,
+        &operations,
+        &[
+            message("users", 2, r#"{"id": 3, "profile": {"name": "Carol"}}"#),
+            message(
+                "follows",
+                0,
+                r#"{"follower": 1, "followed": 3, "since": 2020}"#,
+            ),
+            message(
+                "likes",
+                0,
+                &format!(
+                    r#"{{"from": "{}", "to": "{}"}}"#,
+                    users.written[1], users.written[0]
+                ),
+            ),
+        ],
+    )
+    .unwrap();
+    assert!(follows.errors.is_empty());
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let alice = storage
+        .get_node(&txn, &parse_id(&users.written[0]))
+        .unwrap();
+    let properties = alice.properties.unwrap();
+    assert_eq!(
+        properties.get("name"),
+        Some(&Value::String("Alice".to_string()))
+    );
+    // fields that aren't mapped are left out
+    assert!(!properties.contains_key("age"));
+
+    let follows = storage
+        .get_edge(&txn, &parse_id(&follows.written[1]))
+        .unwrap();
+    assert_eq!(follows.label, "Follows");
+    assert_eq!(follows.from_node, parse_id(&users.written[0]));
+    // edges without mapped properties take every field but the ones of their nodes
+    let properties = follows.properties.unwrap();
+    assert_eq!(properties.len(), 1);
+    assert!(properties.contains_key("since"));
+}
+
+#[test]
+fn test_failed_messages_are_skipped() {
+    let (storage, operations, _temp_dir) = setup();
+
+    let result = write_batch(
+        &storage,
+        &operations,
+        &[
+            message("users", 0, r#"{"id": 1, "profile": {"name": "Alice"}}"#),
+            message("users", 1, "not json"),
+            message("follows", 0, r#"{"follower": 1, "followed": 9}"#),
+            message("orders", 0, "{}"),
+            message("embeddings", 0, r#"{"embedding": [0.1, 0.2, 0.3]}"#),
+            message("embeddings", 1, r#"{"embedding": "none"}"#),
+        ],
+    )
+    .unwrap();
+    assert_eq!(result.written.len(), 2);
+    let errors = result
+        .errors
+        .iter()
+        .map(|(i, e)| (*i, e.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        vec![1, 2, 3, 5]
+    );
+    assert!(errors[0].1.contains("Invalid message"));
+    assert!(errors[1].1.contains("No node with userId 9"));
+    assert!(errors[2].1.contains("No mapping for topic orders"));
+    assert!(errors[3].1.contains("No vector in /embedding"));
+
+    // the messages written before a failure are written once
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.nodes_db.len(&txn).unwrap(), 1);
+    assert_eq!(storage.edges_db.len(&txn).unwrap(), 0);
+}
+
+#[test]
+fn test_metrics() {
+    let metrics = KafkaMetrics::default();
+    metrics.record_batch(10, 1);
+    metrics.record_batch(5, 0);
+    metrics.record_lag("users", 0, 15, 20);
+    metrics.record_lag("users", 1, 8, 8);
+    metrics.record_lag("users", 0, 18, 20);
+
+    let status = metrics.status();
+    assert_eq!((status.consumed, status.failed, status.batches), (15, 1, 2));
+    assert_eq!(status.lag, 2);
+    assert_eq!(metrics.lag(), 2);
+    assert_eq!(
+        status.partitions[0],
+        PartitionLag {
+            topic: "users".to_string(),
+            partition: 0,
+            committed: 18,
+            high_watermark: 20,
+            lag: 2,
+        }
+    );
+}
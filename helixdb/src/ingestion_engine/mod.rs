@@ -1,8 +1,30 @@
 pub mod sql_ingestion;
+pub mod sql_mapping;
 pub mod postgres_ingestion;
+pub mod postgres_cdc;
+pub mod neo4j_ingestion;
+pub mod file_ingestion;
+pub mod ingest_jobs;
+#[cfg(feature = "kafka")]
+pub mod kafka_ingestion;
 
 #[cfg(test)]
 pub mod sqlite_tests;
 
 #[cfg(test)]
 pub mod postgres_tests;
+
+#[cfg(test)]
+pub mod sql_mapping_tests;
+
+#[cfg(test)]
+pub mod postgres_cdc_tests;
+
+#[cfg(test)]
+pub mod neo4j_tests;
+
+#[cfg(test)]
+pub mod file_tests;
+
+#[cfg(all(test, feature = "kafka"))]
+pub mod kafka_tests;
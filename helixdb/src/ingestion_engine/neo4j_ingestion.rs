@@ -0,0 +1,846 @@
+use crate::{
+    helix_engine::ingest_jobs::ingest_jobs::IngestJob,
+    helix_gateway::ingest::ingest::{
+        IngestBatch, IngestEdge, IngestNode, IngestResult, JobCheckpoint, INGEST_PATH,
+    },
+    ingestion_engine::{
+        ingest_jobs::JobState,
+        sql_ingestion::{to_camel_case, EdgeSchema, GraphSchema, IngestionError},
+    },
+    protocol::value::Value,
+};
+use reqwest::blocking::Client;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+/// Where the value of a column of a Neo4j CSV export goes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    /// Id of a node, unique within its id space. Named id columns are also kept as a
+    /// property of the node
+    Id {
+        space: String,
+        property: Option<String>,
+    },
+    Labels,
+    StartId {
+        space: String,
+    },
+    EndId {
+        space: String,
+    },
+    Type,
+    Ignore,
+    Property {
+        name: String,
+        kind: PropertyKind,
+        array: bool,
+    },
+}
+
+/// Type of a property column, from its header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyKind {
+    Integer,
+    Float,
+    Boolean,
+    String,
+    /// Untyped columns of APOC exports, typed by their values
+    Inferred,
+}
+
+/// A row of a Neo4j CSV export
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    Node {
+        /// `(id space, id)`
+        key: (String, String),
+        label: String,
+        properties: HashMap<String, Value>,
+    },
+    Relationship {
+        start: (String, String),
+        end: (String, String),
+        label: String,
+        properties: HashMap<String, Value>,
+    },
+}
+
+/// A CSV file of an export with the columns of its header
+#[derive(Debug, Clone)]
+struct ExportFile {
+    path: PathBuf,
+    columns: Vec<Column>,
+}
+
+/// Counts of an ingestion, with the items that couldn't be loaded
+#[derive(Debug, Default)]
+pub struct Neo4jReport {
+    pub nodes: usize,
+    pub relationships: usize,
+    pub errors: Vec<String>,
+}
+
+/// Loads a Neo4j CSV export into a running instance through its ingestion endpoint.
+///
+/// Both exports Neo4j writes are read:
+/// - `neo4j-admin database import` files, with headers such as
+///   `personId:ID(Person),name,age:int,:LABEL` and `:START_ID(Person),:END_ID(Movie),:TYPE`
+/// - `apoc.export.csv.all` files, nodes and relationships in one file with `_id`, `_labels`,
+///   `_start`, `_end` and `_type` columns
+///
+/// Labels and relationship types are camel cased like tables are by the SQL ingestors, a
+/// node with several labels takes its first one. Headers must be the first line of their
+/// file.
+///
+/// Files are read in the order of their names, so an ingestion started as a job can be
+/// resumed after the last record the instance loaded.
+pub struct Neo4jIngestor {
+    pub instance: String,
+    pub batch_size: usize,
+    pub graph_schema: GraphSchema,
+    /// `(id space, id)` of a node of the export => its uuid in the instance
+    pub id_mappings: HashMap<(String, String), String>,
+    pub job: Option<JobState>,
+    /// Path of the export, the source of the job
+    source: String,
+    files: Vec<ExportFile>,
+    client: Client,
+}
+
+impl Neo4jIngestor {
+    /// Reads the headers of the export at `path`, either a CSV file or a directory of them
+    pub fn new(
+        path: &str,
+        instance: Option<String>,
+        batch_size: usize,
+    ) -> Result<Self, IngestionError> {
+        let path = Path::new(path);
+        if path.extension().is_some_and(|ext| ext == "dump") {
+            return Err(IngestionError::MappingError(
+                "neo4j-admin dumps hold the store files of a database, export it to CSV with \
+                 `CALL apoc.export.csv.all(\"export.csv\", {})` and ingest the CSV file instead"
+                    .to_string(),
+            ));
+        }
+        let paths = match path.is_dir() {
+            true => {
+                let mut paths = fs::read_dir(path)
+                    .map_err(io_error)?
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+                    .collect::<Vec<_>>();
+                paths.sort();
+                paths
+            }
+            false => vec![path.to_path_buf()],
+        };
+        if paths.is_empty() {
+            return Err(IngestionError::MappingError(format!(
+                "No CSV files found in {}",
+                path.display()
+            )));
+        }
+
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let header = CsvRecords::open(&path)?
+                    .next()
+                    .transpose()?
+                    .map(|(_, fields)| fields)
+                    .unwrap_or_default();
+                Ok(ExportFile {
+                    columns: parse_header(&header),
+                    path,
+                })
+            })
+            .collect::<Result<Vec<_>, IngestionError>>()?;
+
+        Ok(Neo4jIngestor {
+            instance: instance.unwrap_or("http://localhost:6969".to_string()),
+            batch_size: batch_size.max(1),
+            graph_schema: GraphSchema::new(),
+            id_mappings: HashMap::new(),
+            job: None,
+            source: fs::canonicalize(path)
+                .unwrap_or(path.to_path_buf())
+                .display()
+                .to_string(),
+            files,
+            client: Client::new(),
+        })
+    }
+
+    /// Checkpoints the batches of the ingestion as a new job of the instance, returning
+    /// its id
+    pub fn start_job(&mut self) -> String {
+        let job = JobState::start(&self.source);
+        let id = job.id.clone();
+        self.job = Some(job);
+        id
+    }
+
+    /// Resumes a job of the instance, skipping the records it loaded and joining
+    /// relationships on the nodes it loaded
+    pub fn resume(&mut self, id: &str) -> Result<IngestJob, IngestionError> {
+        let (job, progress) = JobState::resume(&self.client, &self.instance, id, &self.source)?;
+        self.id_mappings = job.keys(&self.client, &self.instance)?;
+        self.job = Some(job);
+        Ok(progress)
+    }
+
+    /// Reads every record of the export, nodes before relationships, along with the
+    /// stream of the job it is read from and its line
+    fn for_each_record(
+        &self,
+        mut f: impl FnMut(Record, &str, u64) -> Result<(), IngestionError>,
+        relationships: bool,
+    ) -> Result<(), IngestionError> {
+        for file in self.files.iter() {
+            let is_node_file = file.columns.iter().any(|c| matches!(c, Column::Id { .. }));
+            let is_relationship_file = file
+                .columns
+                .iter()
+                .any(|c| matches!(c, Column::StartId { .. }));
+            if (relationships && !is_relationship_file) || (!relationships && !is_node_file) {
+                continue;
+            }
+            let stream = format!(
+                "{}:{}",
+                if relationships {
+                    "relationships"
+                } else {
+                    "nodes"
+                },
+                file.path.file_name().unwrap_or_default().to_string_lossy()
+            );
+            for row in CsvRecords::open(&file.path)?.skip(1) {
+                let (line, fields) = row?;
+                let record = parse_record(&file.columns, fields).map_err(|e| {
+                    IngestionError::MappingError(format!("{}:{}: {}", file.path.display(), line, e))
+                })?;
+                match (&record, relationships) {
+                    (Record::Node { .. }, false) | (Record::Relationship { .. }, true) => {
+                        f(record, &stream, line as u64)?
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the schema of the export from the labels, relationship types and
+    /// properties of its records
+    pub fn extract_schema(&mut self) -> Result<(), IngestionError> {
+        let mut nodes: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        let mut labels: HashMap<(String, String), String> = HashMap::new();
+        self.for_each_record(
+            |record, _, _| {
+                if let Record::Node {
+                    key,
+                    label,
+                    properties,
+                } = record
+                {
+                    merge_types(nodes.entry(label.clone()).or_default(), &properties);
+                    labels.insert(key, label);
+                }
+                Ok(())
+            },
+            false,
+        )?;
+
+        let mut edges: BTreeMap<String, (String, String, BTreeMap<String, String>)> =
+            BTreeMap::new();
+        self.for_each_record(
+            |record, _, _| {
+                if let Record::Relationship {
+                    start,
+                    end,
+                    label,
+                    properties,
+                } = record
+                {
+                    let (Some(from), Some(to)) = (labels.get(&start), labels.get(&end)) else {
+                        return Ok(());
+                    };
+                    let edge = edges
+                        .entry(label.clone())
+                        .or_insert_with(|| (from.clone(), to.clone(), BTreeMap::new()));
+                    if (&edge.0, &edge.1) != (from, to) {
+                        println!(
+                            "{} connects {} to {} as well as {} to {}, the schema only has the first",
+                            label, from, to, edge.0, edge.1
+                        );
+                    }
+                    merge_types(&mut edge.2, &properties);
+                }
+                Ok(())
+            },
+            true,
+        )?;
+
+        self.graph_schema.nodes = nodes
+            .into_iter()
+            .map(|(label, properties)| (label, properties.into_iter().collect()))
+            .collect();
+        self.graph_schema.edges = edges
+            .into_iter()
+            .map(|(label, (from, to, properties))| {
+                (
+                    label,
+                    EdgeSchema {
+                        from,
+                        to,
+                        properties: properties.into_iter().collect(),
+                    },
+                )
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// The schema of the export as a `schema.hx` file
+    pub fn schema_hx(&self) -> String {
+        let mut schema = String::new();
+        let mut nodes = self.graph_schema.nodes.iter().collect::<Vec<_>>();
+        nodes.sort_by(|a, b| a.0.cmp(b.0));
+        for (label, properties) in nodes {
+            schema.push_str(&format!("N::{} {{\n", label));
+            for (name, helix_type) in properties {
+                schema.push_str(&format!("    {}: {},\n", name, helix_type));
+            }
+            schema.push_str("}\n\n");
+        }
+        let mut edges = self.graph_schema.edges.iter().collect::<Vec<_>>();
+        edges.sort_by(|a, b| a.0.cmp(b.0));
+        for (label, edge) in edges {
+            schema.push_str(&format!("E::{} {{\n", label));
+            schema.push_str(&format!("    From: {},\n", edge.from));
+            schema.push_str(&format!("    To: {},\n", edge.to));
+            schema.push_str("    Properties: {\n");
+            for (name, helix_type) in edge.properties.iter() {
+                schema.push_str(&format!("        {}: {},\n", name, helix_type));
+            }
+            schema.push_str("    }\n}\n\n");
+        }
+        schema
+    }
+
+    /// Writes the schema of the export to `schema.hx` in the output directory
+    pub fn create_schema(&self, output_dir: &str) -> Result<PathBuf, IngestionError> {
+        let path = Path::new(output_dir).join("schema.hx");
+        let mut file = File::create(&path).map_err(io_error)?;
+        file.write_all(self.schema_hx().as_bytes())
+            .map_err(io_error)?;
+        Ok(path)
+    }
+
+    /// Loads the nodes of the export in batches, then the relationships between them
+    pub fn ingest(&mut self) -> Result<Neo4jReport, IngestionError> {
+        let mut report = Neo4jReport::default();
+
+        let mut keys = Vec::new();
+        let mut batch = self.new_batch();
+        // nodes loaded before the job was resumed are joined on as well
+        let mut ids = std::mem::take(&mut self.id_mappings);
+        self.for_each_record(
+            |record, stream, line| {
+                if self.is_loaded(stream, line) {
+                    return Ok(());
+                }
+                if let Some(job) = &mut batch.job {
+                    job.advance(stream, line);
+                }
+                if let Record::Node {
+                    key,
+                    label,
+                    properties,
+                } = record
+                {
+                    keys.push(key);
+                    batch.nodes.push(IngestNode {
+                        label,
+                        properties,
+                        external_id: None,
+                    });
+                    if batch.nodes.len() >= self.batch_size {
+                        self.send_nodes(&mut batch, &mut keys, &mut ids, &mut report)?;
+                    }
+                }
+                Ok(())
+            },
+            false,
+        )?;
+        self.send_nodes(&mut batch, &mut keys, &mut ids, &mut report)?;
+        self.id_mappings = ids;
+
+        let mut batch = self.new_batch();
+        self.for_each_record(
+            |record, stream, line| {
+                if self.is_loaded(stream, line) {
+                    return Ok(());
+                }
+                if let Some(job) = &mut batch.job {
+                    job.advance(stream, line);
+                }
+                if let Record::Relationship {
+                    start,
+                    end,
+                    label,
+                    properties,
+                } = record
+                {
+                    let (from, to) =
+                        match (self.id_mappings.get(&start), self.id_mappings.get(&end)) {
+                            (Some(from), Some(to)) => (from.clone(), to.clone()),
+                            (None, _) => {
+                                let e = format!("{}: unknown node {}", label, start.1);
+                                push_error(&mut batch, &mut report, e);
+                                return Ok(());
+                            }
+                            (_, None) => {
+                                let e = format!("{}: unknown node {}", label, end.1);
+                                push_error(&mut batch, &mut report, e);
+                                return Ok(());
+                            }
+                        };
+                    batch.edges.push(IngestEdge {
+                        label,
+                        from,
+                        to,
+                        properties,
+                        unique: false,
+                    });
+                    if batch.edges.len() >= self.batch_size {
+                        self.send_relationships(&mut batch, &mut report)?;
+                    }
+                }
+                Ok(())
+            },
+            true,
+        )?;
+        if let Some(job) = &mut batch.job {
+            job.done = true;
+        }
+        self.send_relationships(&mut batch, &mut report)?;
+
+        Ok(report)
+    }
+
+    /// An empty batch, with an empty checkpoint if the ingestion is a job
+    fn new_batch(&self) -> IngestBatch {
+        IngestBatch {
+            job: self.job.as_ref().map(JobState::checkpoint),
+            ..Default::default()
+        }
+    }
+
+    /// Whether a record was loaded before the job was resumed
+    fn is_loaded(&self, stream: &str, line: u64) -> bool {
+        self.job
+            .as_ref()
+            .is_some_and(|job| job.is_loaded(stream, line))
+    }
+
+    fn send_nodes(
+        &self,
+        batch: &mut IngestBatch,
+        keys: &mut Vec<(String, String)>,
+        ids: &mut HashMap<(String, String), String>,
+        report: &mut Neo4jReport,
+    ) -> Result<(), IngestionError> {
+        if batch.nodes.is_empty() && !is_pending(batch) {
+            return Ok(());
+        }
+        if let Some(job) = &mut batch.job {
+            job.keys = keys.iter().cloned().map(Some).collect();
+        }
+        let result = self.send_batch(batch)?;
+        for (key, id) in keys.drain(..).zip(result.nodes) {
+            if let Some(id) = id {
+                ids.insert(key, id);
+                report.nodes += 1;
+            }
+        }
+        for error in result.errors {
+            let label = &batch.nodes[error.index].label;
+            report.errors.push(format!("{}: {}", label, error.error));
+        }
+        if !batch.nodes.is_empty() {
+            println!(
+                "Sent batch of {} nodes (total: {})",
+                batch.nodes.len(),
+                report.nodes
+            );
+        }
+        batch.nodes.clear();
+        if let Some(job) = &mut batch.job {
+            job.clear();
+        }
+        Ok(())
+    }
+
+    fn send_relationships(
+        &self,
+        batch: &mut IngestBatch,
+        report: &mut Neo4jReport,
+    ) -> Result<(), IngestionError> {
+        if batch.edges.is_empty() && !is_pending(batch) {
+            return Ok(());
+        }
+        let result = self.send_batch(batch)?;
+        report.relationships += result.edges.iter().flatten().count();
+        for error in result.errors {
+            let label = &batch.edges[error.index].label;
+            report.errors.push(format!("{}: {}", label, error.error));
+        }
+        if !batch.edges.is_empty() {
+            println!(
+                "Sent batch of {} relationships (total: {})",
+                batch.edges.len(),
+                report.relationships
+            );
+        }
+        batch.edges.clear();
+        if let Some(job) = &mut batch.job {
+            job.clear();
+        }
+        Ok(())
+    }
+
+    fn send_batch(&self, batch: &IngestBatch) -> Result<IngestResult, IngestionError> {
+        let url = format!("{}{}", self.instance, INGEST_PATH);
+        let response = self.client.post(&url).json(batch).send().map_err(|e| {
+            IngestionError::HttpError(format!("Failed to send batch to {}: {}", url, e))
+        })?;
+        if !response.status().is_success() {
+            return Err(IngestionError::HttpError(format!(
+                "Request to {} failed with status: {}",
+                url,
+                response.status()
+            )));
+        }
+        response.json().map_err(|e| {
+            IngestionError::HttpError(format!("Failed to parse ingestion response: {}", e))
+        })
+    }
+}
+
+/// Whether the checkpoint of a batch has progress to record even without items
+fn is_pending(batch: &IngestBatch) -> bool {
+    batch.job.as_ref().is_some_and(JobCheckpoint::is_pending)
+}
+
+/// Reports an item that couldn't be loaded, recording it with the job of the batch
+fn push_error(batch: &mut IngestBatch, report: &mut Neo4jReport, error: String) {
+    if let Some(job) = &mut batch.job {
+        job.errors.push(error.clone());
+    }
+    report.errors.push(error);
+}
+
+/// Reads the columns of a header, APOC exports being told apart by their `_id` or
+/// `_start` columns
+pub fn parse_header(fields: &[String]) -> Vec<Column> {
+    let apoc = fields
+        .iter()
+        .any(|field| field == "_id" || field == "_start");
+    fields
+        .iter()
+        .map(|field| match apoc {
+            true => match field.as_str() {
+                "_id" => Column::Id {
+                    space: String::new(),
+                    property: None,
+                },
+                "_labels" => Column::Labels,
+                "_start" => Column::StartId {
+                    space: String::new(),
+                },
+                "_end" => Column::EndId {
+                    space: String::new(),
+                },
+                "_type" => Column::Type,
+                name => Column::Property {
+                    name: property_name(name),
+                    kind: PropertyKind::Inferred,
+                    array: false,
+                },
+            },
+            false => admin_column(field),
+        })
+        .collect()
+}
+
+/// Reads a column of a `neo4j-admin database import` header, `name:type`
+fn admin_column(field: &str) -> Column {
+    let (name, spec) = match field.rsplit_once(':') {
+        Some((name, spec)) => (name, spec),
+        None => {
+            return Column::Property {
+                name: property_name(field),
+                kind: PropertyKind::String,
+                array: false,
+            }
+        }
+    };
+    // `ID(Person)` => (`ID`, `Person`)
+    let (spec, space) = match spec.split_once('(') {
+        Some((spec, space)) => (spec, space.trim_end_matches(')').to_string()),
+        None => (spec, String::new()),
+    };
+    match spec.to_uppercase().as_str() {
+        "ID" => Column::Id {
+            space,
+            property: (!name.is_empty()).then(|| property_name(name)),
+        },
+        "LABEL" => Column::Labels,
+        "START_ID" => Column::StartId { space },
+        "END_ID" => Column::EndId { space },
+        "TYPE" => Column::Type,
+        "IGNORE" => Column::Ignore,
+        _ => {
+            let spec = spec.to_lowercase();
+            let (spec, array) = match spec.strip_suffix("[]") {
+                Some(spec) => (spec.to_string(), true),
+                None => (spec, false),
+            };
+            let kind = match spec.as_str() {
+                "int" | "long" | "short" | "byte" => PropertyKind::Integer,
+                "float" | "double" => PropertyKind::Float,
+                "boolean" => PropertyKind::Boolean,
+                _ => PropertyKind::String,
+            };
+            Column::Property {
+                name: property_name(name),
+                kind,
+                array,
+            }
+        }
+    }
+}
+
+/// Reads a row of an export by the columns of its file.
+///
+/// Rows with a start and end are relationships, those with an id nodes. Empty cells
+/// leave their property unset.
+pub fn parse_record(columns: &[Column], fields: Vec<String>) -> Result<Record, String> {
+    if fields.len() != columns.len() {
+        return Err(format!(
+            "expected {} columns, got {}",
+            columns.len(),
+            fields.len()
+        ));
+    }
+    let mut key = None;
+    let mut start = None;
+    let mut end = None;
+    let mut label = None;
+    let mut properties = HashMap::new();
+    for (column, field) in columns.iter().zip(fields) {
+        if field.is_empty() {
+            continue;
+        }
+        match column {
+            Column::Id { space, property } => {
+                if let Some(property) = property {
+                    properties.insert(property.clone(), Value::String(field.clone()));
+                }
+                key = Some((space.clone(), field));
+            }
+            Column::StartId { space } => start = Some((space.clone(), field)),
+            Column::EndId { space } => end = Some((space.clone(), field)),
+            Column::Labels => {
+                label = field
+                    .split([':', ';'])
+                    .find(|label| !label.is_empty())
+                    .map(to_camel_case)
+            }
+            Column::Type => label = Some(to_camel_case(&field)),
+            Column::Ignore => {}
+            Column::Property { name, kind, array } => {
+                let value = match array {
+                    true => Value::Array(
+                        field
+                            .split(';')
+                            .map(|element| typed_value(*kind, element))
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    false => typed_value(*kind, &field)?,
+                };
+                properties.insert(name.clone(), value);
+            }
+        }
+    }
+
+    match (key, start, end) {
+        (_, Some(start), Some(end)) => Ok(Record::Relationship {
+            start,
+            end,
+            label: label.ok_or("relationships need a type")?,
+            properties,
+        }),
+        (Some(key), None, None) => Ok(Record::Node {
+            key,
+            label: label.ok_or("nodes need a label")?,
+            properties,
+        }),
+        _ => Err("rows need an id, or a start and an end".to_string()),
+    }
+}
+
+pub(crate) fn typed_value(kind: PropertyKind, text: &str) -> Result<Value, String> {
+    let invalid = |kind: &str| format!("`{}` isn't a valid {}", text, kind);
+    match kind {
+        PropertyKind::Integer => text.parse().map(Value::I64).map_err(|_| invalid("integer")),
+        PropertyKind::Float => text.parse().map(Value::F64).map_err(|_| invalid("float")),
+        PropertyKind::Boolean => match text.to_lowercase().as_str() {
+            "true" => Ok(Value::Boolean(true)),
+            "false" => Ok(Value::Boolean(false)),
+            _ => Err(invalid("boolean")),
+        },
+        PropertyKind::String => Ok(Value::String(text.to_string())),
+        PropertyKind::Inferred => {
+            if let Ok(integer) = text.parse::<i64>() {
+                return Ok(Value::I64(integer));
+            }
+            if let Ok(float) = text.parse::<f64>() {
+                return Ok(Value::F64(float));
+            }
+            match text {
+                "true" => return Ok(Value::Boolean(true)),
+                "false" => return Ok(Value::Boolean(false)),
+                _ => {}
+            }
+            // APOC writes lists as JSON
+            if text.starts_with('[') {
+                if let Ok(array @ Value::Array(_)) = serde_json::from_str::<Value>(text) {
+                    return Ok(array);
+                }
+            }
+            Ok(Value::String(text.to_string()))
+        }
+    }
+}
+
+/// Adds the types of the properties of an item, a property holding values of
+/// different types becoming a string
+fn merge_types(types: &mut BTreeMap<String, String>, properties: &HashMap<String, Value>) {
+    for (name, value) in properties {
+        let helix_type = helix_type(value);
+        types
+            .entry(name.clone())
+            .and_modify(|existing| {
+                if *existing != helix_type {
+                    *existing = "String".to_string();
+                }
+            })
+            .or_insert(helix_type);
+    }
+}
+
+fn helix_type(value: &Value) -> String {
+    match value {
+        Value::I64(_) => "I64".to_string(),
+        Value::F64(_) => "F64".to_string(),
+        Value::Boolean(_) => "Boolean".to_string(),
+        Value::Array(values) => format!(
+            "[{}]",
+            values
+                .first()
+                .map(helix_type)
+                .unwrap_or_else(|| "String".to_string())
+        ),
+        _ => "String".to_string(),
+    }
+}
+
+/// A property name the schema accepts, starting with a letter and made of letters,
+/// digits and underscores
+fn property_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>();
+    match name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        true => name,
+        false => format!("p{}", name),
+    }
+}
+
+fn io_error(e: std::io::Error) -> IngestionError {
+    IngestionError::MappingError(e.to_string())
+}
+
+/// The records of a CSV file with the line each starts on, quoted fields spanning
+/// lines
+pub(crate) struct CsvRecords {
+    lines: std::iter::Enumerate<std::io::Lines<BufReader<File>>>,
+}
+
+impl CsvRecords {
+    pub(crate) fn open(path: &Path) -> Result<Self, IngestionError> {
+        let file = File::open(path).map_err(|e| {
+            IngestionError::MappingError(format!("Failed to open {}: {}", path.display(), e))
+        })?;
+        Ok(CsvRecords {
+            lines: BufReader::new(file).lines().enumerate(),
+        })
+    }
+}
+
+impl Iterator for CsvRecords {
+    type Item = Result<(usize, Vec<String>), IngestionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut start = None;
+        loop {
+            let (i, line) = match self.lines.next() {
+                Some((i, Ok(line))) => (i, line),
+                Some((_, Err(e))) => return Some(Err(io_error(e))),
+                // a record still quoted at the end of the file is ended there
+                None if start.is_some() => {
+                    fields.push(field);
+                    return Some(Ok((start.unwrap_or_default() + 1, fields)));
+                }
+                None => return None,
+            };
+            if start.is_none() && line.trim().is_empty() {
+                continue;
+            }
+            if start.is_some() {
+                field.push('\n');
+            }
+            start.get_or_insert(i);
+            let mut chars = line.trim_end_matches('\r').chars().peekable();
+            while let Some(c) = chars.next() {
+                match (c, quoted) {
+                    ('"', true) if chars.peek() == Some(&'"') => {
+                        field.push('"');
+                        chars.next();
+                    }
+                    ('"', _) => quoted = !quoted,
+                    (',', false) => fields.push(std::mem::take(&mut field)),
+                    (c, _) => field.push(c),
+                }
+            }
+            if !quoted {
+                fields.push(field);
+                return Some(Ok((start.unwrap_or_default() + 1, fields)));
+            }
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use crate::{
+    helix_engine::{
+        graph_core::config::Config, ingest_jobs::ingest_jobs::JobStatus,
+        storage_core::storage_core::HelixGraphStorage,
+    },
+    helix_gateway::ingest::ingest::{self, IngestBatch, IngestResult},
+    ingestion_engine::neo4j_ingestion::{
+        parse_header, parse_record, Column, Neo4jIngestor, PropertyKind, Record,
+    },
+    protocol::value::Value,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+use tempfile::TempDir;
+
+fn header(fields: &[&str]) -> Vec<String> {
+    fields.iter().map(|field| field.to_string()).collect()
+}
+
+/// An export in the `neo4j-admin database import` format
+fn write_admin_export(dir: &TempDir) {
+    fs::write(
+        dir.path().join("people.csv"),
+        "personId:ID(Person),name,age:int,tags:string[],:LABEL\n\
+         p1,Alice,31,a;b,Person;Actor\n\
+         p2,\"Bob, \"\"B\"\"\",27,,Person\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("movies.csv"),
+        "movieId:ID(Movie),title,rating:float,:LABEL\n\
+         m1,\"The\nMatrix\",8.7,Movie\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("acted_in.csv"),
+        ":START_ID(Person),:END_ID(Movie),roles,:TYPE\n\
+         p1,m1,Neo,ACTED_IN\n\
+         p2,m1,Morpheus,ACTED_IN\n\
+         p3,m1,Trinity,ACTED_IN\n",
+    )
+    .unwrap();
+}
+
+/// Serves the ingestion endpoint, recording the batches sent to it and answering
+/// with a uuid for every item
+pub(crate) fn serve_ingest() -> (String, Arc<Mutex<Vec<IngestBatch>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&batches);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = BufReader::new(stream.unwrap());
+            loop {
+                let mut content_length = 0;
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    if stream.read_line(&mut line).unwrap_or(0) == 0 {
+                        return;
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                stream.read_exact(&mut body).unwrap();
+                let batch: IngestBatch = serde_json::from_slice(&body).unwrap();
+                let uuid = || Some(uuid::Uuid::new_v4().to_string());
+                let result = IngestResult {
+                    nodes: batch.nodes.iter().map(|_| uuid()).collect(),
+                    edges: batch.edges.iter().map(|_| uuid()).collect(),
+                    vectors: Vec::new(),
+                    errors: Vec::new(),
+                };
+                received.lock().unwrap().push(batch);
+                let body = serde_json::to_string(&result).unwrap();
+                write!(
+                    stream.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        }
+    });
+    (url, batches)
+}
+
+/// Serves the ingestion endpoint and the ingestion jobs of a graph, failing batches once
+/// `batches` of them were loaded as if the instance went away
+pub(crate) fn serve_storage(storage: Arc<HelixGraphStorage>, batches: Arc<AtomicUsize>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let storage = Arc::clone(&storage);
+            let batches = Arc::clone(&batches);
+            // connections are served at once, as every ingestor keeps its own open
+            thread::spawn(move || {
+                let mut stream = BufReader::new(stream.unwrap());
+                loop {
+                    let mut request_line = String::new();
+                    if stream.read_line(&mut request_line).unwrap_or(0) == 0 {
+                        break;
+                    }
+                    let mut content_length = 0;
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        stream.read_line(&mut line).unwrap();
+                        if line == "\r\n" {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    stream.read_exact(&mut body).unwrap();
+
+                    let path = request_line.split(' ').nth(1).unwrap();
+                    let body = match request_line.starts_with("POST") {
+                        true => {
+                            match batches.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                                n.checked_sub(1)
+                            }) {
+                                Ok(_) => {
+                                    let batch: IngestBatch = serde_json::from_slice(&body).unwrap();
+                                    Some(
+                                        serde_json::to_vec(
+                                            &ingest::ingest(&storage, &batch).unwrap(),
+                                        )
+                                        .unwrap(),
+                                    )
+                                }
+                                Err(_) => None,
+                            }
+                        }
+                        false => ingest::jobs(&storage, path).unwrap(),
+                    };
+                    let status = match body {
+                        Some(_) => "200 OK",
+                        None if request_line.starts_with("POST") => "500 Internal Server Error",
+                        None => "404 Not Found",
+                    };
+                    let body = body.unwrap_or_default();
+                    write!(
+                    stream.get_mut(),
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                    .unwrap();
+                    stream.get_mut().write_all(&body).unwrap();
+                }
+            });
+        }
+    });
+    url
+}
+
+#[test]
+fn test_parse_admin_header() {
+    let columns = parse_header(&header(&[
+        "personId:ID(Person)",
+        "name",
+        "age:int",
+        "scores:double[]",
+        ":LABEL",
+        "skip:IGNORE",
+    ]));
+    assert_eq!(
+        columns,
+        vec![
+            Column::Id {
+                space: "Person".to_string(),
+                property: Some("personId".to_string()),
+            },
+            Column::Property {
+                name: "name".to_string(),
+                kind: PropertyKind::String,
+                array: false,
+            },
+            Column::Property {
+                name: "age".to_string(),
+                kind: PropertyKind::Integer,
+                array: false,
+            },
+            Column::Property {
+                name: "scores".to_string(),
+                kind: PropertyKind::Float,
+                array: true,
+            },
+            Column::Labels,
+            Column::Ignore,
+        ]
+    );
+
+    let columns = parse_header(&header(&[":START_ID(Person)", ":END_ID(Movie)", ":TYPE"]));
+    assert_eq!(
+        columns,
+        vec![
+            Column::StartId {
+                space: "Person".to_string()
+            },
+            Column::EndId {
+                space: "Movie".to_string()
+            },
+            Column::Type,
+        ]
+    );
+}
+
+#[test]
+fn test_parse_apoc_records() {
+    let columns = parse_header(&header(&[
+        "_id", "_labels", "name", "born", "tags", "_start", "_end", "_type", "since",
+    ]));
+    let row = |fields: &[&str]| header(fields);
+
+    let node = parse_record(
+        &columns,
+        row(&[
+            "0",
+            ":Person:Actor",
+            "Keanu",
+            "1964",
+            "[\"a\",\"b\"]",
+            "",
+            "",
+            "",
+            "",
+        ]),
+    )
+    .unwrap();
+    assert_eq!(
+        node,
+        Record::Node {
+            key: (String::new(), "0".to_string()),
+            label: "Person".to_string(),
+            properties: HashMap::from([
+                ("name".to_string(), Value::String("Keanu".to_string())),
+                ("born".to_string(), Value::I64(1964)),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![
+                        Value::String("a".to_string()),
+                        Value::String("b".to_string())
+                    ])
+                ),
+            ]),
+        }
+    );
+
+    let relationship = parse_record(
+        &columns,
+        row(&["", "", "", "", "", "0", "1", "KNOWS_WELL", "2.5"]),
+    )
+    .unwrap();
+    assert_eq!(
+        relationship,
+        Record::Relationship {
+            start: (String::new(), "0".to_string()),
+            end: (String::new(), "1".to_string()),
+            label: "KnowsWell".to_string(),
+            properties: HashMap::from([("since".to_string(), Value::F64(2.5))]),
+        }
+    );
+
+    assert!(parse_record(&columns, row(&["", "", "x", "", "", "", "", "", ""])).is_err());
+    assert!(parse_record(&columns, row(&["0"])).is_err());
+}
+
+#[test]
+fn test_parse_invalid_typed_value() {
+    let columns = parse_header(&header(&["id:ID", "age:int", ":LABEL"]));
+    let e = parse_record(&columns, header(&["1", "old", "Person"])).unwrap_err();
+    assert!(e.contains("isn't a valid integer"));
+}
+
+#[test]
+fn test_extract_schema() {
+    let dir = TempDir::new().unwrap();
+    write_admin_export(&dir);
+
+    let mut ingestor = Neo4jIngestor::new(dir.path().to_str().unwrap(), None, 10).unwrap();
+    ingestor.extract_schema().unwrap();
+    assert_eq!(
+        ingestor.schema_hx(),
+        "N::Movie {\n    movieId: String,\n    rating: F64,\n    title: String,\n}\n\n\
+         N::Person {\n    age: I64,\n    name: String,\n    personId: String,\n    tags: [String],\n}\n\n\
+         E::ActedIn {\n    From: Person,\n    To: Movie,\n    Properties: {\n        roles: String,\n    }\n}\n\n"
+    );
+
+    let path = ingestor
+        .create_schema(dir.path().to_str().unwrap())
+        .unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), ingestor.schema_hx());
+}
+
+#[test]
+fn test_ingest() {
+    let dir = TempDir::new().unwrap();
+    write_admin_export(&dir);
+    let (url, batches) = serve_ingest();
+
+    let mut ingestor = Neo4jIngestor::new(dir.path().to_str().unwrap(), Some(url), 2).unwrap();
+    let report = ingestor.ingest().unwrap();
+    assert_eq!(report.nodes, 3);
+    assert_eq!(report.relationships, 2);
+    // p3 isn't in the export
+    assert_eq!(report.errors, vec!["ActedIn: unknown node p3".to_string()]);
+
+    let batches = batches.lock().unwrap();
+    // files are read in name order, batches of 2 items
+    let node_labels = batches
+        .iter()
+        .flat_map(|batch| batch.nodes.iter().map(|node| node.label.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(node_labels, vec!["Movie", "Person", "Person"]);
+    let edges = batches
+        .iter()
+        .flat_map(|batch| batch.edges.iter())
+        .collect::<Vec<_>>();
+    assert_eq!(edges.len(), 2);
+    assert_eq!(
+        edges[0].from,
+        ingestor.id_mappings[&("Person".to_string(), "p1".to_string())]
+    );
+    assert_eq!(
+        edges[0].to,
+        ingestor.id_mappings[&("Movie".to_string(), "m1".to_string())]
+    );
+    let bob = batches
+        .iter()
+        .flat_map(|batch| batch.nodes.iter())
+        .find(|node| node.properties.get("personId") == Some(&Value::String("p2".to_string())))
+        .unwrap();
+    assert_eq!(
+        bob.properties.get("name"),
+        Some(&Value::String("Bob, \"B\"".to_string()))
+    );
+}
+
+#[test]
+fn test_dump_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let dump = dir.path().join("neo4j.dump");
+    fs::write(&dump, b"").unwrap();
+    let e = Neo4jIngestor::new(dump.to_str().unwrap(), None, 10)
+        .err()
+        .unwrap();
+    assert!(e.to_string().contains("apoc.export.csv.all"));
+}
+
+#[test]
+fn test_resume_job() {
+    let dir = TempDir::new().unwrap();
+    write_admin_export(&dir);
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(
+        HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), Config::default()).unwrap(),
+    );
+    // the instance goes away after the first batch, of the movie and Alice
+    let batches = Arc::new(AtomicUsize::new(1));
+    let url = serve_storage(Arc::clone(&storage), Arc::clone(&batches));
+    let path = dir.path().to_str().unwrap();
+
+    let mut ingestor = Neo4jIngestor::new(path, Some(url.clone()), 2).unwrap();
+    let id = ingestor.start_job();
+    assert!(ingestor.ingest().is_err());
+
+    batches.store(usize::MAX, Ordering::SeqCst);
+    let mut ingestor = Neo4jIngestor::new(path, Some(url.clone()), 2).unwrap();
+    let job = ingestor.resume(&id).unwrap();
+    assert_eq!((job.batches, job.nodes), (1, 2));
+    assert_eq!(ingestor.id_mappings.len(), 2);
+    let report = ingestor.ingest().unwrap();
+    assert_eq!(report.nodes, 1);
+    assert_eq!(report.relationships, 2);
+
+    // every node is loaded once, relationships join on nodes of either run
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.nodes_db.len(&txn).unwrap(), 3);
+    assert_eq!(storage.edges_db.len(&txn).unwrap(), 2);
+    let job = storage.ingest_jobs.get(&txn, &id).unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Completed);
+    assert_eq!((job.nodes, job.edges), (3, 2));
+    assert_eq!(job.errors, vec!["ActedIn: unknown node p3".to_string()]);
+    drop(txn);
+
+    // completed jobs, and jobs of other sources, aren't resumed
+    let mut ingestor = Neo4jIngestor::new(path, Some(url.clone()), 2).unwrap();
+    assert!(ingestor.resume(&id).is_err());
+    let movies = dir.path().join("movies.csv");
+    let mut ingestor = Neo4jIngestor::new(movies.to_str().unwrap(), Some(url), 2).unwrap();
+    let e = ingestor.resume(&id).unwrap_err();
+    assert!(e.to_string().contains("not"));
+}
@@ -0,0 +1,743 @@
+use crate::{
+    helix_gateway::ingest::sync::{
+        NodeKey, SyncBatch, SyncChange, SyncEdge, SyncResult, SYNC_PATH,
+    },
+    ingestion_engine::{
+        postgres_ingestion::{to_camel_case, IngestionError, PostgresIngestor, TableSchema},
+        sql_mapping::edge_label,
+    },
+    protocol::value::Value,
+};
+use reqwest::Client;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+/// Logical decoding output plugin of a replication slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputPlugin {
+    /// The plugin built into Postgres, streaming the tables of a publication
+    Pgoutput,
+    /// The `wal2json` extension, streaming every table
+    Wal2json,
+}
+
+impl OutputPlugin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputPlugin::Pgoutput => "pgoutput",
+            OutputPlugin::Wal2json => "wal2json",
+        }
+    }
+}
+
+impl FromStr for OutputPlugin {
+    type Err = IngestionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pgoutput" => Ok(OutputPlugin::Pgoutput),
+            "wal2json" => Ok(OutputPlugin::Wal2json),
+            _ => Err(IngestionError::MappingError(format!(
+                "Unknown output plugin `{}`, expected pgoutput or wal2json",
+                s
+            ))),
+        }
+    }
+}
+
+/// Where the changes of the database are read from
+#[derive(Debug, Clone)]
+pub struct ReplicationOptions {
+    /// Logical replication slot, created along with an initial snapshot if it doesn't exist
+    pub slot: String,
+    pub plugin: OutputPlugin,
+    /// Publication a `pgoutput` slot streams, created for all tables if it doesn't exist
+    pub publication: String,
+    /// Time waited for new changes once the slot has none
+    pub poll_interval: Duration,
+}
+
+/// A row change decoded from a replication slot.
+///
+/// Columns Postgres doesn't send, such as unchanged TOASTed values of an update, are
+/// left out. `old` holds the replica identity of the row before the change, when it is
+/// sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    Insert {
+        table: String,
+        columns: Vec<(String, Value)>,
+    },
+    Update {
+        table: String,
+        old: Vec<(String, Value)>,
+        columns: Vec<(String, Value)>,
+    },
+    Delete {
+        table: String,
+        old: Vec<(String, Value)>,
+    },
+}
+
+/// Decodes a change of a `wal2json` slot, in its `format-version` 2.
+///
+/// Transaction boundaries, messages and truncates decode to no change.
+pub fn decode_wal2json(data: &str) -> Result<Vec<RowChange>, IngestionError> {
+    let change: serde_json::Value = serde_json::from_str(data)
+        .map_err(|e| IngestionError::MappingError(format!("Invalid wal2json change: {}", e)))?;
+    let table = || {
+        change["table"].as_str().map(String::from).ok_or_else(|| {
+            IngestionError::MappingError(format!("No table in wal2json change {}", data))
+        })
+    };
+    let columns = |field: &str| {
+        change[field]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|column| {
+                let name = column["name"].as_str()?;
+                Some((name.to_string(), json_value(&column["value"])))
+            })
+            .collect::<Vec<_>>()
+    };
+    match change["action"].as_str() {
+        Some("I") => Ok(vec![RowChange::Insert {
+            table: table()?,
+            columns: columns("columns"),
+        }]),
+        Some("U") => Ok(vec![RowChange::Update {
+            table: table()?,
+            old: columns("identity"),
+            columns: columns("columns"),
+        }]),
+        Some("D") => Ok(vec![RowChange::Delete {
+            table: table()?,
+            old: columns("identity"),
+        }]),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// A table as described by the relation messages of `pgoutput`
+#[derive(Debug, Clone)]
+struct Relation {
+    name: String,
+    /// Names and type oids of the columns
+    columns: Vec<(String, u32)>,
+}
+
+/// Decodes the messages of a `pgoutput` slot, in version 1 of its protocol.
+///
+/// Rows only carry the oid of their table, which is described by a relation message
+/// sent before its first row, so the decoder keeps the relations it has seen.
+#[derive(Debug, Default)]
+pub struct PgoutputDecoder {
+    relations: HashMap<u32, Relation>,
+}
+
+impl PgoutputDecoder {
+    pub fn decode(&mut self, data: &[u8]) -> Result<Vec<RowChange>, IngestionError> {
+        let mut reader = Reader { data, position: 0 };
+        match reader.u8()? {
+            b'R' => {
+                let oid = reader.u32()?;
+                let _namespace = reader.string()?;
+                let name = reader.string()?;
+                let _replica_identity = reader.u8()?;
+                let count = reader.u16()?;
+                let mut columns = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let _flags = reader.u8()?;
+                    let column = reader.string()?;
+                    let type_oid = reader.u32()?;
+                    let _type_modifier = reader.u32()?;
+                    columns.push((column, type_oid));
+                }
+                self.relations.insert(oid, Relation { name, columns });
+                Ok(Vec::new())
+            }
+            b'I' => {
+                let relation = self.relation(reader.u32()?)?;
+                reader.expect(b'N')?;
+                Ok(vec![RowChange::Insert {
+                    table: relation.name.clone(),
+                    columns: reader.tuple(relation)?,
+                }])
+            }
+            b'U' => {
+                let relation = self.relation(reader.u32()?)?;
+                let old = match reader.u8()? {
+                    b'K' | b'O' => {
+                        let old = reader.tuple(relation)?;
+                        reader.expect(b'N')?;
+                        old
+                    }
+                    b'N' => Vec::new(),
+                    tag => return Err(unexpected(tag)),
+                };
+                Ok(vec![RowChange::Update {
+                    table: relation.name.clone(),
+                    old,
+                    columns: reader.tuple(relation)?,
+                }])
+            }
+            b'D' => {
+                let relation = self.relation(reader.u32()?)?;
+                match reader.u8()? {
+                    b'K' | b'O' => Ok(vec![RowChange::Delete {
+                        table: relation.name.clone(),
+                        old: reader.tuple(relation)?,
+                    }]),
+                    tag => Err(unexpected(tag)),
+                }
+            }
+            // begin, commit, origin, type and truncate messages
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn relation(&self, oid: u32) -> Result<&Relation, IngestionError> {
+        self.relations.get(&oid).ok_or_else(|| {
+            IngestionError::MappingError(format!("No relation message for table {}", oid))
+        })
+    }
+}
+
+fn unexpected(tag: u8) -> IngestionError {
+    IngestionError::MappingError(format!(
+        "Unexpected tag `{}` in pgoutput message",
+        tag as char
+    ))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], IngestionError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + len)
+            .ok_or_else(|| {
+                IngestionError::MappingError("Truncated pgoutput message".to_string())
+            })?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, IngestionError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, IngestionError> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, IngestionError> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn expect(&mut self, tag: u8) -> Result<(), IngestionError> {
+        match self.u8()? {
+            t if t == tag => Ok(()),
+            t => Err(unexpected(t)),
+        }
+    }
+
+    /// A null terminated string
+    fn string(&mut self) -> Result<String, IngestionError> {
+        let len = self.data[self.position..]
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| IngestionError::MappingError("Unterminated string".to_string()))?;
+        let string = String::from_utf8_lossy(self.bytes(len)?).into_owned();
+        self.position += 1;
+        Ok(string)
+    }
+
+    /// The columns of a row in the text format, nulls as empty values and unchanged
+    /// TOASTed values left out
+    fn tuple(&mut self, relation: &Relation) -> Result<Vec<(String, Value)>, IngestionError> {
+        let count = self.u16()? as usize;
+        let mut columns = Vec::with_capacity(count);
+        for i in 0..count {
+            let (name, type_oid) = relation.columns.get(i).ok_or_else(|| {
+                IngestionError::MappingError(format!(
+                    "Row of {} has more columns than its relation",
+                    relation.name
+                ))
+            })?;
+            match self.u8()? {
+                b'n' => columns.push((name.clone(), Value::Empty)),
+                b'u' => {}
+                b't' => {
+                    let len = self.u32()? as usize;
+                    let text = String::from_utf8_lossy(self.bytes(len)?);
+                    columns.push((name.clone(), text_value(*type_oid, &text)));
+                }
+                tag => return Err(unexpected(tag)),
+            }
+        }
+        Ok(columns)
+    }
+}
+
+/// A column in the text format of Postgres by the oid of its type.
+///
+/// Numbers are read as JSON numbers, so they get the same value as in a snapshot
+/// taken with `row_to_json`.
+fn text_value(type_oid: u32, text: &str) -> Value {
+    const BOOL: u32 = 16;
+    const NUMBERS: [u32; 7] = [20, 21, 23, 26, 700, 701, 1700];
+    const JSON: [u32; 2] = [114, 3802];
+    match type_oid {
+        BOOL => Value::Boolean(text == "t"),
+        oid if NUMBERS.contains(&oid) || JSON.contains(&oid) => {
+            match serde_json::from_str::<serde_json::Value>(text) {
+                Ok(value) if JSON.contains(&oid) || value.is_number() => json_value(&value),
+                _ => Value::String(text.to_string()),
+            }
+        }
+        _ => Value::String(text.to_string()),
+    }
+}
+
+pub(crate) fn json_value(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Empty,
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => Value::I64(i),
+            (None, Some(u)) => Value::U64(u),
+            _ => Value::F64(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::String(s.clone()),
+        _ => serde_json::from_value(value.clone())
+            .unwrap_or_else(|_| Value::String(value.to_string())),
+    }
+}
+
+/// How the rows of the tables map to nodes, found by their primary key, and their
+/// foreign keys to edges
+#[derive(Debug, Clone, Default)]
+pub struct SyncMapping {
+    tables: HashMap<String, TableMapping>,
+}
+
+#[derive(Debug, Clone)]
+struct TableMapping {
+    label: String,
+    /// The primary key, tables without a single column one can't be synced
+    key: Option<String>,
+    edges: Vec<EdgeMapping>,
+}
+
+#[derive(Debug, Clone)]
+struct EdgeMapping {
+    column: String,
+    label: String,
+    to: String,
+    to_key: String,
+}
+
+impl SyncMapping {
+    /// Tables are labelled in camel case, and edges after the tables they connect as
+    /// by [`edge_label`].
+    pub fn new(schemas: &[TableSchema]) -> Self {
+        let tables = schemas
+            .iter()
+            .map(|schema| {
+                let edges = schema
+                    .foreign_keys
+                    .iter()
+                    .map(|fk| {
+                        let shared = schema
+                            .foreign_keys
+                            .iter()
+                            .filter(|other| other.to_table == fk.to_table)
+                            .count()
+                            > 1;
+                        let label =
+                            edge_label(&fk.from_table, &fk.from_column, &fk.to_table, shared);
+                        EdgeMapping {
+                            column: fk.from_column.clone(),
+                            label,
+                            to: to_camel_case(&fk.to_table),
+                            to_key: fk.to_column.clone(),
+                        }
+                    })
+                    .collect();
+                let key = match schema.primary_keys.as_slice() {
+                    [key] => Some(key.clone()),
+                    _ => None,
+                };
+                (
+                    schema.name.clone(),
+                    TableMapping {
+                        label: to_camel_case(&schema.name),
+                        key,
+                        edges,
+                    },
+                )
+            })
+            .collect();
+        SyncMapping { tables }
+    }
+
+    /// The changes of the graph a row change maps to. An update of the primary key of
+    /// a row drops the node of its old key before upserting the new one.
+    pub fn changes(&self, change: &RowChange) -> Result<Vec<SyncChange>, String> {
+        match change {
+            RowChange::Insert { table, columns } => Ok(vec![self.upsert(table, columns)?]),
+            RowChange::Update {
+                table,
+                old,
+                columns,
+            } => {
+                let (mapping, key) = self.table(table)?;
+                let old_key = old.iter().find(|(name, _)| name == key);
+                let new_key = columns.iter().find(|(name, _)| name == key);
+                let mut changes = Vec::new();
+                if let (Some((_, old)), Some((_, new))) = (old_key, new_key) {
+                    if old != new {
+                        changes.push(SyncChange::Delete {
+                            label: mapping.label.clone(),
+                            key: key.clone(),
+                            value: old.clone(),
+                        });
+                    }
+                }
+                changes.push(self.upsert(table, columns)?);
+                Ok(changes)
+            }
+            RowChange::Delete { table, old } => {
+                let (mapping, key) = self.table(table)?;
+                let (_, value) = old
+                    .iter()
+                    .find(|(name, _)| name == key)
+                    .ok_or_else(|| format!("Delete from {} without its {}", table, key))?;
+                Ok(vec![SyncChange::Delete {
+                    label: mapping.label.clone(),
+                    key: key.clone(),
+                    value: value.clone(),
+                }])
+            }
+        }
+    }
+
+    fn upsert(&self, table: &str, columns: &[(String, Value)]) -> Result<SyncChange, String> {
+        let (mapping, key) = self.table(table)?;
+        let edges = mapping
+            .edges
+            .iter()
+            .filter_map(|edge| {
+                let (_, value) = columns.iter().find(|(name, _)| *name == edge.column)?;
+                Some(SyncEdge {
+                    label: edge.label.clone(),
+                    to: match value {
+                        Value::Empty => None,
+                        value => Some(NodeKey {
+                            label: edge.to.clone(),
+                            key: edge.to_key.clone(),
+                            value: value.clone(),
+                        }),
+                    },
+                })
+            })
+            .collect();
+        Ok(SyncChange::Upsert {
+            label: mapping.label.clone(),
+            key: key.clone(),
+            properties: columns.iter().cloned().collect(),
+            edges,
+        })
+    }
+
+    fn table(&self, table: &str) -> Result<(&TableMapping, &String), String> {
+        let mapping = self
+            .tables
+            .get(table)
+            .ok_or_else(|| format!("Table {} isn't in the public schema", table))?;
+        let key = mapping
+            .key
+            .as_ref()
+            .ok_or_else(|| format!("Table {} has no single column primary key", table))?;
+        Ok((mapping, key))
+    }
+}
+
+impl PostgresIngestor {
+    /// Keeps the instance in sync with the database until `shutdown` is set.
+    ///
+    /// Changes are peeked from the replication slot in batches, sent to the sync
+    /// endpoint of the instance, and only then is the slot advanced past them, so
+    /// every change is applied at least once; applying one again leaves the graph as
+    /// it was. Changes that can't be applied are logged and skipped so they don't hold
+    /// up the slot. When the slot is created, the rows already in the tables are sent
+    /// first.
+    ///
+    /// Nodes are found by their primary key, which is best given a secondary index on
+    /// the instance: without one every change scans the nodes of the graph.
+    pub async fn replicate(
+        &mut self,
+        options: &ReplicationOptions,
+        shutdown: &AtomicBool,
+    ) -> Result<(), IngestionError> {
+        let schemas = self.extract_schema().await?;
+        let mapping = SyncMapping::new(&schemas);
+        let client = Client::new();
+
+        if self.create_slot(options).await? {
+            println!("Created replication slot {}", options.slot);
+            self.snapshot(&schemas, &mapping, &client).await?;
+        }
+
+        let mut decoder = PgoutputDecoder::default();
+        while !shutdown.load(Ordering::Relaxed) {
+            let rows = self.peek_changes(options).await?;
+            let Some((lsn, _)) = rows.last() else {
+                tokio::time::sleep(options.poll_interval).await;
+                continue;
+            };
+
+            let mut changes = Vec::new();
+            for (_, data) in rows.iter() {
+                let row_changes = match options.plugin {
+                    OutputPlugin::Pgoutput => decoder.decode(data)?,
+                    OutputPlugin::Wal2json => decode_wal2json(&String::from_utf8_lossy(data))?,
+                };
+                for change in row_changes.iter() {
+                    match mapping.changes(change) {
+                        Ok(change) => changes.extend(change),
+                        Err(e) => println!("Skipped change: {}", e),
+                    }
+                }
+            }
+            if !changes.is_empty() {
+                let count = changes.len();
+                let result = self.send_sync(&client, &SyncBatch { changes }).await?;
+                for error in result.errors.iter() {
+                    println!("Failed to apply change {}: {}", error.index, error.error);
+                }
+                println!(
+                    "Synced {} of {} changes up to {}",
+                    result.applied, count, lsn
+                );
+            }
+
+            self.pg_client
+                .query(
+                    "SELECT pg_replication_slot_advance($1, $2::text::pg_lsn)",
+                    &[&options.slot, lsn],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Creates the slot, and the publication of a `pgoutput` slot, returning whether
+    /// the slot was created
+    async fn create_slot(&self, options: &ReplicationOptions) -> Result<bool, IngestionError> {
+        let slot = self
+            .pg_client
+            .query_opt(
+                "SELECT plugin::text FROM pg_replication_slots WHERE slot_name = $1",
+                &[&options.slot],
+            )
+            .await?;
+        if let Some(slot) = slot {
+            let plugin: String = slot.get(0);
+            if plugin != options.plugin.name() {
+                return Err(IngestionError::MappingError(format!(
+                    "Replication slot {} uses {}, not {}",
+                    options.slot,
+                    plugin,
+                    options.plugin.name()
+                )));
+            }
+            return Ok(false);
+        }
+
+        if options.plugin == OutputPlugin::Pgoutput {
+            let publication = self
+                .pg_client
+                .query_opt(
+                    "SELECT 1 FROM pg_publication WHERE pubname = $1",
+                    &[&options.publication],
+                )
+                .await?;
+            if publication.is_none() {
+                self.pg_client
+                    .execute(
+                        &format!("CREATE PUBLICATION {} FOR ALL TABLES", options.publication),
+                        &[],
+                    )
+                    .await?;
+            }
+        }
+        self.pg_client
+            .query(
+                "SELECT pg_create_logical_replication_slot($1, $2)",
+                &[&options.slot, &options.plugin.name()],
+            )
+            .await?;
+        Ok(true)
+    }
+
+    /// Sends every row of the tables as upserts: their nodes first, then their edges
+    /// once the nodes they point to are all in the graph
+    async fn snapshot(
+        &self,
+        schemas: &[TableSchema],
+        mapping: &SyncMapping,
+        client: &Client,
+    ) -> Result<(), IngestionError> {
+        for edges in [false, true] {
+            for schema in schemas {
+                if edges && schema.foreign_keys.is_empty() {
+                    continue;
+                }
+                if let Err(e) = mapping.table(&schema.name) {
+                    if !edges {
+                        println!("Skipped table: {}", e);
+                    }
+                    continue;
+                }
+                let rows = self
+                    .pg_client
+                    .query(
+                        &format!("SELECT row_to_json(t)::text FROM {} t", schema.name),
+                        &[],
+                    )
+                    .await?;
+                for chunk in rows.chunks(self.batch_size.max(1)) {
+                    let mut changes = Vec::with_capacity(chunk.len());
+                    for row in chunk {
+                        let Ok(serde_json::Value::Object(row)) =
+                            serde_json::from_str(row.get::<_, &str>(0))
+                        else {
+                            continue;
+                        };
+                        let change = RowChange::Insert {
+                            table: schema.name.clone(),
+                            columns: row
+                                .iter()
+                                .map(|(name, value)| (name.clone(), json_value(value)))
+                                .collect(),
+                        };
+                        let change = mapping
+                            .changes(&change)
+                            .map_err(IngestionError::MappingError)?;
+                        changes.extend(
+                            change
+                                .into_iter()
+                                .map(|change| snapshot_pass(change, edges)),
+                        );
+                    }
+                    if changes.is_empty() {
+                        continue;
+                    }
+                    let result = self.send_sync(client, &SyncBatch { changes }).await?;
+                    for error in result.errors.iter() {
+                        println!("Failed to sync row of {}: {}", schema.name, error.error);
+                    }
+                    println!(
+                        "Sent snapshot of {} {} of table {}",
+                        result.applied,
+                        match edges {
+                            true => "edges",
+                            false => "rows",
+                        },
+                        schema.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The next changes of the slot, without consuming them, with the LSN of each
+    async fn peek_changes(
+        &self,
+        options: &ReplicationOptions,
+    ) -> Result<Vec<(String, Vec<u8>)>, IngestionError> {
+        let limit = self.batch_size.max(1) as i32;
+        let rows = match options.plugin {
+            OutputPlugin::Pgoutput => {
+                self.pg_client
+                    .query(
+                        "SELECT lsn::text, data FROM pg_logical_slot_peek_binary_changes(\
+                         $1, NULL, $2, 'proto_version', '1', 'publication_names', $3)",
+                        &[&options.slot, &limit, &options.publication],
+                    )
+                    .await?
+            }
+            OutputPlugin::Wal2json => {
+                self.pg_client
+                    .query(
+                        "SELECT lsn::text, convert_to(data, 'UTF8') FROM \
+                         pg_logical_slot_peek_changes($1, NULL, $2, 'format-version', '2')",
+                        &[&options.slot, &limit],
+                    )
+                    .await?
+            }
+        };
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    async fn send_sync(
+        &self,
+        client: &Client,
+        batch: &SyncBatch,
+    ) -> Result<SyncResult, IngestionError> {
+        let url = format!("{}{}", self.instance, SYNC_PATH);
+        let response = client.post(&url).json(batch).send().await.map_err(|e| {
+            IngestionError::HttpError(format!("Failed to send changes to {}: {}", url, e))
+        })?;
+        if !response.status().is_success() {
+            return Err(IngestionError::HttpError(format!(
+                "Request to {} failed with status: {}",
+                url,
+                response.status()
+            )));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| IngestionError::HttpError(format!("Failed to parse sync response: {}", e)))
+    }
+}
+
+/// The part of an upsert a pass of the snapshot sends: its properties without its
+/// edges, or its edges with only its key
+fn snapshot_pass(change: SyncChange, edges: bool) -> SyncChange {
+    match change {
+        SyncChange::Upsert {
+            label,
+            key,
+            mut properties,
+            edges: row_edges,
+        } => {
+            if edges {
+                properties.retain(|name, _| *name == key);
+            }
+            SyncChange::Upsert {
+                label,
+                key,
+                properties,
+                edges: match edges {
+                    true => row_edges,
+                    false => Vec::new(),
+                },
+            }
+        }
+        change => change,
+    }
+}
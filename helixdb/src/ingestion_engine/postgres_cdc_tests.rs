@@ -0,0 +1,458 @@
+use crate::{
+    helix_engine::{
+        graph_core::config::Config,
+        storage_core::{storage_core::HelixGraphStorage, storage_methods::StorageMethods},
+    },
+    helix_gateway::ingest::sync::{sync, NodeKey, SyncBatch, SyncChange, SyncEdge},
+    ingestion_engine::{
+        postgres_cdc::{decode_wal2json, PgoutputDecoder, RowChange, SyncMapping},
+        postgres_ingestion::{ColumnInfo, ForeignKey, TableSchema},
+    },
+    protocol::{filterable::Filterable, items::Node, value::Value},
+};
+use std::{collections::HashMap, sync::Arc};
+use tempfile::TempDir;
+
+fn columns(columns: &[(&str, Value)]) -> Vec<(String, Value)> {
+    columns
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect()
+}
+
+#[test]
+fn test_decode_wal2json() {
+    let insert = decode_wal2json(
+        r#"{"action":"I","schema":"public","table":"users",
+            "columns":[{"name":"id","type":"integer","value":1},
+                       {"name":"name","type":"text","value":"Alice"},
+                       {"name":"score","type":"numeric","value":2.5},
+                       {"name":"email","type":"text","value":null}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        insert,
+        vec![RowChange::Insert {
+            table: "users".to_string(),
+            columns: columns(&[
+                ("id", Value::I64(1)),
+                ("name", Value::String("Alice".to_string())),
+                ("score", Value::F64(2.5)),
+                ("email", Value::Empty),
+            ]),
+        }]
+    );
+
+    let update = decode_wal2json(
+        r#"{"action":"U","schema":"public","table":"users",
+            "columns":[{"name":"id","type":"integer","value":2}],
+            "identity":[{"name":"id","type":"integer","value":1}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        update,
+        vec![RowChange::Update {
+            table: "users".to_string(),
+            old: columns(&[("id", Value::I64(1))]),
+            columns: columns(&[("id", Value::I64(2))]),
+        }]
+    );
+
+    let delete = decode_wal2json(
+        r#"{"action":"D","schema":"public","table":"users",
+            "identity":[{"name":"id","type":"integer","value":2}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        delete,
+        vec![RowChange::Delete {
+            table: "users".to_string(),
+            old: columns(&[("id", Value::I64(2))]),
+        }]
+    );
+
+    // transaction boundaries aren't changes
+    assert!(decode_wal2json(r#"{"action":"B"}"#).unwrap().is_empty());
+    assert!(decode_wal2json("not json").is_err());
+}
+
+fn cstring(message: &mut Vec<u8>, s: &str) {
+    message.extend_from_slice(s.as_bytes());
+    message.push(0);
+}
+
+fn text(message: &mut Vec<u8>, s: &str) {
+    message.push(b't');
+    message.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    message.extend_from_slice(s.as_bytes());
+}
+
+#[test]
+fn test_decode_pgoutput() {
+    let mut decoder = PgoutputDecoder::default();
+
+    // a row of a table that wasn't described yet can't be decoded
+    let mut insert = vec![b'I'];
+    insert.extend_from_slice(&16384u32.to_be_bytes());
+    insert.push(b'N');
+    insert.extend_from_slice(&4u16.to_be_bytes());
+    text(&mut insert, "1");
+    text(&mut insert, "Alice");
+    text(&mut insert, "t");
+    insert.push(b'n');
+    assert!(decoder.decode(&insert).is_err());
+
+    let mut relation = vec![b'R'];
+    relation.extend_from_slice(&16384u32.to_be_bytes());
+    cstring(&mut relation, "public");
+    cstring(&mut relation, "users");
+    relation.push(b'd');
+    relation.extend_from_slice(&4u16.to_be_bytes());
+    for (name, oid) in [("id", 23u32), ("name", 25), ("active", 16), ("bio", 25)] {
+        relation.push(0);
+        cstring(&mut relation, name);
+        relation.extend_from_slice(&oid.to_be_bytes());
+        relation.extend_from_slice(&(-1i32).to_be_bytes());
+    }
+    assert!(decoder.decode(&relation).unwrap().is_empty());
+
+    assert_eq!(
+        decoder.decode(&insert).unwrap(),
+        vec![RowChange::Insert {
+            table: "users".to_string(),
+            columns: columns(&[
+                ("id", Value::I64(1)),
+                ("name", Value::String("Alice".to_string())),
+                ("active", Value::Boolean(true)),
+                ("bio", Value::Empty),
+            ]),
+        }]
+    );
+
+    // the key changes and bio, TOASTed, is unchanged
+    let mut update = vec![b'U'];
+    update.extend_from_slice(&16384u32.to_be_bytes());
+    update.push(b'K');
+    update.extend_from_slice(&4u16.to_be_bytes());
+    text(&mut update, "1");
+    update.extend_from_slice(b"nnn");
+    update.push(b'N');
+    update.extend_from_slice(&4u16.to_be_bytes());
+    text(&mut update, "2");
+    text(&mut update, "Alice");
+    text(&mut update, "f");
+    update.push(b'u');
+    assert_eq!(
+        decoder.decode(&update).unwrap(),
+        vec![RowChange::Update {
+            table: "users".to_string(),
+            old: columns(&[
+                ("id", Value::I64(1)),
+                ("name", Value::Empty),
+                ("active", Value::Empty),
+                ("bio", Value::Empty),
+            ]),
+            columns: columns(&[
+                ("id", Value::I64(2)),
+                ("name", Value::String("Alice".to_string())),
+                ("active", Value::Boolean(false)),
+            ]),
+        }]
+    );
+
+    // begin messages aren't changes
+    assert!(decoder.decode(&[b'B', 0, 0]).unwrap().is_empty());
+    assert!(decoder.decode(&insert[..8]).is_err());
+}
+
+fn schemas() -> Vec<TableSchema> {
+    let column = |name: &str| ColumnInfo {
+        name: name.to_string(),
+        data_type: "Integer".to_string(),
+        sql_type: "integer".to_string(),
+        is_nullable: true,
+    };
+    let foreign_key = |from_column: &str, to_table: &str| ForeignKey {
+        from_table: "blog_posts".to_string(),
+        from_column: from_column.to_string(),
+        to_table: to_table.to_string(),
+        to_column: "id".to_string(),
+    };
+    vec![
+        TableSchema {
+            name: "users".to_string(),
+            columns: vec![column("id")],
+            primary_keys: vec!["id".to_string()],
+            foreign_keys: vec![],
+        },
+        TableSchema {
+            name: "blog_posts".to_string(),
+            columns: vec![column("id"), column("author_id"), column("editor_id")],
+            primary_keys: vec!["id".to_string()],
+            foreign_keys: vec![
+                foreign_key("author_id", "users"),
+                foreign_key("editor_id", "users"),
+            ],
+        },
+        TableSchema {
+            name: "tags".to_string(),
+            columns: vec![column("post_id"), column("tag")],
+            primary_keys: vec!["post_id".to_string(), "tag".to_string()],
+            foreign_keys: vec![],
+        },
+    ]
+}
+
+#[test]
+fn test_sync_mapping() {
+    let mapping = SyncMapping::new(&schemas());
+
+    let changes = mapping
+        .changes(&RowChange::Update {
+            table: "blog_posts".to_string(),
+            old: columns(&[("id", Value::I64(7))]),
+            columns: columns(&[
+                ("id", Value::I64(8)),
+                ("author_id", Value::I64(1)),
+                ("editor_id", Value::Empty),
+            ]),
+        })
+        .unwrap();
+    assert_eq!(
+        changes[0],
+        SyncChange::Delete {
+            label: "BlogPosts".to_string(),
+            key: "id".to_string(),
+            value: Value::I64(7),
+        }
+    );
+    let SyncChange::Upsert {
+        label,
+        key,
+        properties,
+        edges,
+    } = &changes[1]
+    else {
+        panic!("expected an upsert, got {:?}", changes[1]);
+    };
+    assert_eq!((label.as_str(), key.as_str()), ("BlogPosts", "id"));
+    assert_eq!(properties.get("author_id"), Some(&Value::I64(1)));
+    // both foreign keys point to users, so their edges are named after their columns
+    assert_eq!(
+        edges,
+        &vec![
+            SyncEdge {
+                label: "BlogPostsAuthorIdToUsers".to_string(),
+                to: Some(NodeKey {
+                    label: "Users".to_string(),
+                    key: "id".to_string(),
+                    value: Value::I64(1),
+                }),
+            },
+            SyncEdge {
+                label: "BlogPostsEditorIdToUsers".to_string(),
+                to: None,
+            },
+        ]
+    );
+
+    // an update keeping its key is a single upsert
+    let changes = mapping
+        .changes(&RowChange::Update {
+            table: "users".to_string(),
+            old: Vec::new(),
+            columns: columns(&[("id", Value::I64(1))]),
+        })
+        .unwrap();
+    assert_eq!(changes.len(), 1);
+
+    let e = mapping
+        .changes(&RowChange::Delete {
+            table: "tags".to_string(),
+            old: columns(&[("post_id", Value::I64(1))]),
+        })
+        .unwrap_err();
+    assert!(e.contains("no single column primary key"));
+    let e = mapping
+        .changes(&RowChange::Insert {
+            table: "audit".to_string(),
+            columns: Vec::new(),
+        })
+        .unwrap_err();
+    assert!(e.contains("isn't in the public schema"));
+}
+
+fn setup(indices: Option<Vec<String>>) -> (Arc<HelixGraphStorage>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = Config::default();
+    config.graph_config.secondary_indices = indices;
+    let storage = HelixGraphStorage::new(temp_dir.path().to_str().unwrap(), config).unwrap();
+    (Arc::new(storage), temp_dir)
+}
+
+fn upsert(label: &str, properties: &[(&str, Value)], edges: Vec<SyncEdge>) -> SyncChange {
+    SyncChange::Upsert {
+        label: label.to_string(),
+        key: "id".to_string(),
+        properties: columns(properties).into_iter().collect::<HashMap<_, _>>(),
+        edges,
+    }
+}
+
+fn author(id: u64) -> Vec<SyncEdge> {
+    vec![SyncEdge {
+        label: "PostsToUsers".to_string(),
+        to: Some(NodeKey {
+            label: "Users".to_string(),
+            key: "id".to_string(),
+            value: Value::U64(id),
+        }),
+    }]
+}
+
+fn nodes(storage: &HelixGraphStorage, label: &str) -> Vec<Node> {
+    let txn = storage.graph_env.read_txn().unwrap();
+    storage
+        .get_all_nodes(&txn)
+        .unwrap()
+        .map(|node| node.unwrap())
+        .filter(|node| node.label == label)
+        .collect()
+}
+
+fn test_sync(indices: Option<Vec<String>>) {
+    let (storage, _temp_dir) = setup(indices);
+
+    let result = sync(
+        &storage,
+        &SyncBatch {
+            changes: vec![
+                upsert(
+                    "Users",
+                    &[
+                        ("id", Value::U64(1)),
+                        ("name", Value::String("Alice".to_string())),
+                        ("email", Value::Empty),
+                    ],
+                    vec![],
+                ),
+                upsert("Users", &[("id", Value::U64(2))], vec![]),
+                // posts share the key of users
+                upsert("Posts", &[("id", Value::U64(1))], author(1)),
+                upsert("Posts", &[("id", Value::U64(2))], author(9)),
+            ],
+        },
+    )
+    .unwrap();
+    assert_eq!(result.applied, 3);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].index, 3);
+    assert!(result.errors[0].error.contains("No Users with id 9"));
+    // nulls of new nodes are left out
+    assert!(!nodes(&storage, "Users")[0]
+        .properties
+        .as_ref()
+        .unwrap()
+        .contains_key("email"));
+
+    // applying changes again updates the nodes they already added
+    let result = sync(
+        &storage,
+        &SyncBatch {
+            changes: vec![
+                upsert(
+                    "Users",
+                    &[
+                        ("id", Value::U64(1)),
+                        ("name", Value::Empty),
+                        ("age", Value::U64(30)),
+                    ],
+                    vec![],
+                ),
+                upsert("Posts", &[("id", Value::U64(1))], author(2)),
+                upsert("Posts", &[("id", Value::U64(1))], author(2)),
+            ],
+        },
+    )
+    .unwrap();
+    assert_eq!(result.applied, 3);
+    let users = nodes(&storage, "Users");
+    assert_eq!(users.len(), 2);
+    let alice = users
+        .iter()
+        .find(|node| {
+            node.check_property("id")
+                .is_ok_and(|id| *id == Value::U64(1))
+        })
+        .unwrap();
+    assert_eq!(alice.check_property("name").unwrap(), &Value::Empty);
+    assert_eq!(alice.check_property("age").unwrap(), &Value::U64(30));
+
+    // the edge of the post was replaced, not added
+    let post = &nodes(&storage, "Posts")[0];
+    let txn = storage.graph_env.read_txn().unwrap();
+    let edges = storage
+        .get_all_edges(&txn)
+        .unwrap()
+        .map(|edge| edge.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0].from_node, post.id);
+    assert_ne!(edges[0].to_node, alice.id);
+    drop(txn);
+
+    // deleting a node drops its edges, deleting it again does nothing
+    let delete = SyncChange::Delete {
+        label: "Users".to_string(),
+        key: "id".to_string(),
+        value: Value::U64(2),
+    };
+    let result = sync(
+        &storage,
+        &SyncBatch {
+            changes: vec![delete.clone(), delete],
+        },
+    )
+    .unwrap();
+    assert_eq!(result.applied, 2);
+    assert_eq!(nodes(&storage, "Users").len(), 1);
+    assert_eq!(nodes(&storage, "Posts").len(), 1);
+    let txn = storage.graph_env.read_txn().unwrap();
+    assert_eq!(storage.edges_db.len(&txn).unwrap(), 0);
+}
+
+#[test]
+fn test_sync_with_index() {
+    test_sync(Some(vec!["id".to_string()]));
+}
+
+#[test]
+fn test_sync_without_index() {
+    test_sync(None);
+}
+
+#[test]
+fn test_sync_batch_json() {
+    let batch: SyncBatch = serde_json::from_str(
+        r#"{ "changes": [
+            { "op": "upsert", "label": "Users", "key": "id", "properties": { "id": 1, "bio": null } },
+            { "op": "delete", "label": "Users", "key": "id", "value": 2 }
+        ] }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        batch.changes,
+        vec![
+            upsert(
+                "Users",
+                &[("id", Value::U64(1)), ("bio", Value::Empty)],
+                vec![]
+            ),
+            SyncChange::Delete {
+                label: "Users".to_string(),
+                key: "id".to_string(),
+                value: Value::U64(2),
+            },
+        ]
+    );
+}
@@ -1,6 +1,15 @@
-use crate::helix_engine::{
-    types::GraphError,
-    vector_core::vector::HVector,
+use crate::{
+    helix_engine::{
+        types::GraphError,
+        vector_core::vector::HVector,
+    },
+    helix_gateway::ingest::ingest::{IngestBatch, IngestResult, INGEST_PATH},
+    ingestion_engine::{
+        postgres_cdc::json_value,
+        sql_ingestion::IngestionError as SqlIngestionError,
+        sql_mapping::{select, SqlForeignKey, SqlLoader, SqlMapping, SqlReport, SqlTable},
+    },
+    protocol::value::Value as HelixValue,
 };
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
@@ -122,6 +131,8 @@ pub struct TableSchema {
 pub struct ColumnInfo {
     pub name: String,
     pub data_type: String,
+    /// The type of the column in Postgres, `data_type` being its Helix type
+    pub sql_type: String,
     pub is_nullable: bool,
 }
 
@@ -316,6 +327,7 @@ impl PostgresIngestor {
                 columns.push(ColumnInfo {
                     name: col_name.clone(),
                     data_type: map_sql_type_to_helix_type(&data_type),
+                    sql_type: data_type,
                     is_nullable: is_nullable == "YES",
                 });
             }
@@ -1148,6 +1160,135 @@ impl PostgresIngestor {
         Ok(())
     }
 
+    /// The tables of the database, for a [`SqlMapping`] to be inferred from
+    pub async fn tables(&mut self) -> Result<Vec<SqlTable>, IngestionError> {
+        Ok(self
+            .extract_schema()
+            .await?
+            .into_iter()
+            .map(|schema| SqlTable {
+                name: schema.name,
+                columns: schema
+                    .columns
+                    .into_iter()
+                    .map(|column| (column.name, column.sql_type))
+                    .collect(),
+                primary_keys: schema.primary_keys,
+                foreign_keys: schema
+                    .foreign_keys
+                    .into_iter()
+                    .map(|fk| SqlForeignKey {
+                        column: fk.from_column,
+                        to_table: fk.to_table,
+                        to_column: fk.to_column,
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// Loads the tables of a mapping into the instance through its ingestion endpoint,
+    /// in batches that are each written in one transaction
+    pub async fn load(&mut self, mapping: &SqlMapping) -> Result<SqlReport, IngestionError> {
+        let mapping_error = |e: SqlIngestionError| IngestionError::MappingError(e.to_string());
+        mapping
+            .validate(&self.tables().await?)
+            .map_err(mapping_error)?;
+        let client = reqwest::Client::new();
+        let mut loader = SqlLoader::new(mapping);
+        for table in mapping.tables.iter() {
+            let columns = SqlLoader::node_columns(table);
+            for rows in self.select_rows(&table.table, &columns).await? {
+                let (batch, keys) = loader.nodes(table, rows);
+                if batch.nodes.is_empty() {
+                    continue;
+                }
+                let result = self.send_batch(&client, &batch).await?;
+                loader.nodes_loaded(&batch, keys, result);
+                println!(
+                    "Sent batch of {} nodes for table {} (total: {})",
+                    batch.nodes.len(),
+                    table.table,
+                    loader.report.nodes
+                );
+            }
+        }
+        for edge in mapping.edges.iter() {
+            let columns = loader.edge_columns(edge).map_err(mapping_error)?;
+            for rows in self.select_rows(&edge.table, &columns).await? {
+                let batch = loader.edges(edge, rows);
+                if batch.edges.is_empty() {
+                    continue;
+                }
+                let result = self.send_batch(&client, &batch).await?;
+                loader.edges_loaded(&batch, result);
+                println!(
+                    "Sent batch of {} {} edges (total: {})",
+                    batch.edges.len(),
+                    edge.label,
+                    loader.report.edges
+                );
+            }
+        }
+        Ok(loader.report)
+    }
+
+    /// The columns of the rows of a table in batches. Rows are read as JSON, for their
+    /// values not to depend on the types of the columns.
+    async fn select_rows(
+        &self,
+        table: &str,
+        columns: &[String],
+    ) -> Result<Vec<Vec<Vec<HelixValue>>>, IngestionError> {
+        let rows = self
+            .pg_client
+            .query(
+                &format!(
+                    "SELECT row_to_json(t)::text FROM ({}) t",
+                    select(table, columns)
+                ),
+                &[],
+            )
+            .await?;
+        Ok(rows
+            .chunks(self.batch_size.max(1))
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|row| {
+                        let row: serde_json::Map<String, serde_json::Value> =
+                            serde_json::from_str(row.get::<_, &str>(0)).unwrap_or_default();
+                        columns
+                            .iter()
+                            .map(|column| row.get(column).map_or(HelixValue::Empty, json_value))
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    async fn send_batch(
+        &self,
+        client: &reqwest::Client,
+        batch: &IngestBatch,
+    ) -> Result<IngestResult, IngestionError> {
+        let url = format!("{}{}", self.instance, INGEST_PATH);
+        let response = client.post(&url).json(batch).send().await.map_err(|e| {
+            IngestionError::HttpError(format!("Failed to send batch to {}: {}", url, e))
+        })?;
+        if !response.status().is_success() {
+            return Err(IngestionError::HttpError(format!(
+                "Request to {} failed with status: {}",
+                url,
+                response.status()
+            )));
+        }
+        response.json().await.map_err(|e| {
+            IngestionError::HttpError(format!("Failed to parse ingestion response: {}", e))
+        })
+    }
+
     fn parse_vector_string(vector_str: &str) -> Result<Vec<f64>, IngestionError> {
         let cleaned = vector_str.trim_matches(|c| c == '[' || c == ']');
         if cleaned.is_empty() {
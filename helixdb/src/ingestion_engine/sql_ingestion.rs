@@ -1,4 +1,11 @@
-use crate::helix_engine::types::GraphError;
+use crate::{
+    helix_engine::types::GraphError,
+    helix_gateway::ingest::ingest::{IngestBatch, IngestResult, INGEST_PATH},
+    ingestion_engine::sql_mapping::{
+        select, SqlForeignKey, SqlLoader, SqlMapping, SqlReport, SqlTable,
+    },
+    protocol::value::Value as HelixValue,
+};
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 use rusqlite::{
@@ -22,6 +29,8 @@ pub enum IngestionError {
     GraphError(GraphError),
     MappingError(String),
     HttpError(String),
+    KafkaError(String),
+    JobError(String),
 }
 
 impl fmt::Display for IngestionError {
@@ -31,6 +40,8 @@ impl fmt::Display for IngestionError {
             IngestionError::GraphError(e) => write!(f, "{}", e),
             IngestionError::MappingError(e) => write!(f, "{}", e),
             IngestionError::HttpError(e) => write!(f, "{}", e),
+            IngestionError::KafkaError(e) => write!(f, "{}", e),
+            IngestionError::JobError(e) => write!(f, "{}", e),
         }
     }
 }
@@ -875,7 +886,7 @@ impl SqliteIngestor {
         Ok(())
     }
 
-    pub fn ingest(&mut self) -> Result<(), IngestionError> {
+    pub fn ingest(&mut self, output_dir: &str) -> Result<(), IngestionError> {
         let schemas = self.extract_schema()?;
 
         // for schema in &schemas {
@@ -887,16 +898,11 @@ impl SqliteIngestor {
 
         // if --dump flag is set, dump the ingestion stats to a file
         // path = ./helix_ingestion.json
-        let path = Path::new("./");
-        // create the file if it doesn't exist
-        if !path.exists() {
-            let mut file = File::create(path).unwrap();
-            file.write_all(b"{}").unwrap();
-        }
+        let path = Path::new(output_dir);
         self.dump_to_json(path.to_str().unwrap())?;
 
         // create the schema file
-        let schema_path = Path::new("./schema.hx");
+        let schema_path = Path::new(output_dir).join("schema.hx");
         println!("Creating schema file at {}", schema_path.to_str().unwrap());
         self.create_schemas(schema_path.to_str().unwrap())?;
         println!(
@@ -905,6 +911,138 @@ impl SqliteIngestor {
         );
         Ok(())
     }
+
+    /// The tables of the database, for a [`SqlMapping`] to be inferred from
+    pub fn tables(&mut self) -> Result<Vec<SqlTable>, IngestionError> {
+        Ok(self
+            .extract_schema()?
+            .into_iter()
+            .map(|schema| {
+                let mut primary_keys = schema.primary_keys.into_iter().collect::<Vec<_>>();
+                primary_keys.sort();
+                SqlTable {
+                    name: schema.name,
+                    columns: schema
+                        .columns
+                        .into_iter()
+                        .map(|column| (column.name, column.data_type))
+                        .collect(),
+                    primary_keys,
+                    foreign_keys: schema
+                        .foreign_keys
+                        .into_iter()
+                        .map(|fk| SqlForeignKey {
+                            column: fk.from_column,
+                            to_table: fk.to_table,
+                            to_column: fk.to_column,
+                        })
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+
+    /// Loads the tables of a mapping into the instance through its ingestion endpoint,
+    /// in batches that are each written in one transaction
+    pub fn load(&mut self, mapping: &SqlMapping) -> Result<SqlReport, IngestionError> {
+        mapping.validate(&self.tables()?)?;
+        let client = Client::new();
+        let mut loader = SqlLoader::new(mapping);
+        for table in mapping.tables.iter() {
+            let columns = SqlLoader::node_columns(table);
+            self.for_each_batch(&select(&table.table, &columns), columns.len(), |rows| {
+                let (batch, keys) = loader.nodes(table, rows);
+                if batch.nodes.is_empty() {
+                    return Ok(());
+                }
+                let result = self.send_batch(&client, &batch)?;
+                loader.nodes_loaded(&batch, keys, result);
+                println!(
+                    "Sent batch of {} nodes for table {} (total: {})",
+                    batch.nodes.len(),
+                    table.table,
+                    loader.report.nodes
+                );
+                Ok(())
+            })?;
+        }
+        for edge in mapping.edges.iter() {
+            let columns = loader.edge_columns(edge)?;
+            self.for_each_batch(&select(&edge.table, &columns), columns.len(), |rows| {
+                let batch = loader.edges(edge, rows);
+                if batch.edges.is_empty() {
+                    return Ok(());
+                }
+                let result = self.send_batch(&client, &batch)?;
+                loader.edges_loaded(&batch, result);
+                println!(
+                    "Sent batch of {} {} edges (total: {})",
+                    batch.edges.len(),
+                    edge.label,
+                    loader.report.edges
+                );
+                Ok(())
+            })?;
+        }
+        Ok(loader.report)
+    }
+
+    /// Runs a query, reading its rows in batches
+    fn for_each_batch(
+        &self,
+        query: &str,
+        columns: usize,
+        mut f: impl FnMut(Vec<Vec<HelixValue>>) -> Result<(), IngestionError>,
+    ) -> Result<(), IngestionError> {
+        let mut stmt = self.sqlite_conn.prepare(query)?;
+        let mut rows = stmt.query(params![])?;
+        let mut batch = Vec::new();
+        while let Some(row) = rows.next()? {
+            batch.push(
+                (0..columns)
+                    .map(|i| row.get(i).map(helix_value))
+                    .collect::<SqliteResult<Vec<_>>>()?,
+            );
+            if batch.len() >= self.batch_size.max(1) {
+                f(std::mem::take(&mut batch))?;
+            }
+        }
+        if !batch.is_empty() {
+            f(batch)?;
+        }
+        Ok(())
+    }
+
+    fn send_batch(
+        &self,
+        client: &Client,
+        batch: &IngestBatch,
+    ) -> Result<IngestResult, IngestionError> {
+        let url = format!("{}{}", self.instance, INGEST_PATH);
+        let response = client.post(&url).json(batch).send().map_err(|e| {
+            IngestionError::HttpError(format!("Failed to send batch to {}: {}", url, e))
+        })?;
+        if !response.status().is_success() {
+            return Err(IngestionError::HttpError(format!(
+                "Request to {} failed with status: {}",
+                url,
+                response.status()
+            )));
+        }
+        response.json().map_err(|e| {
+            IngestionError::HttpError(format!("Failed to parse ingestion response: {}", e))
+        })
+    }
+}
+
+fn helix_value(value: RusqliteValue) -> HelixValue {
+    match value {
+        RusqliteValue::Null => HelixValue::Empty,
+        RusqliteValue::Integer(i) => HelixValue::I64(i),
+        RusqliteValue::Real(f) => HelixValue::F64(f),
+        RusqliteValue::Text(s) => HelixValue::String(s),
+        RusqliteValue::Blob(b) => HelixValue::String(String::from_utf8_lossy(&b).into_owned()),
+    }
 }
 
 pub fn to_camel_case(s: &str) -> String {
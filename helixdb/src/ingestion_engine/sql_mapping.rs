@@ -0,0 +1,712 @@
+use crate::{
+    helix_gateway::ingest::ingest::{IngestBatch, IngestEdge, IngestNode, IngestResult},
+    ingestion_engine::{
+        neo4j_ingestion::{typed_value, PropertyKind},
+        sql_ingestion::{to_camel_case, IngestionError},
+    },
+    protocol::value::Value,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// File the mapping of a database is written to, for the user to edit
+pub const MAPPING_FILE: &str = "mapping.json";
+/// File the report of a mapping is written to
+pub const REPORT_FILE: &str = "mapping_report.txt";
+
+/// Name the primary key column `id` is loaded as, `id` being reserved for the id of
+/// the node
+const ID_PROPERTY: &str = "source_id";
+
+/// A table as read from the schema of a SQL database
+#[derive(Debug, Clone)]
+pub struct SqlTable {
+    pub name: String,
+    /// Name and SQL type of each column, in order
+    pub columns: Vec<(String, String)>,
+    pub primary_keys: Vec<String>,
+    pub foreign_keys: Vec<SqlForeignKey>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SqlForeignKey {
+    pub column: String,
+    pub to_table: String,
+    pub to_column: String,
+}
+
+/// How the tables of a SQL database map to nodes, and their foreign keys to edges.
+///
+/// The mapping is inferred from the schema of the database and written out along with
+/// the `schema.hx` it generates and a report of it, for the user to review and edit
+/// before the tables are loaded.
+///
+/// ```json
+/// {
+///   "tables": [
+///     { "table": "users", "label": "Users", "id": "id",
+///       "properties": [
+///         { "column": "id", "name": "source_id", "sql_type": "INTEGER", "type": "I64" },
+///         { "column": "name", "name": "name", "sql_type": "TEXT", "type": "String" }
+///       ],
+///       "skipped": [{ "column": "avatar", "sql_type": "BLOB", "reason": "no Helix type for BLOB" }] }
+///   ],
+///   "edges": [
+///     { "label": "PostsToUsers", "table": "posts", "column": "user_id", "to": "users" }
+///   ]
+/// }
+/// ```
+///
+/// Tables and edges left out of the mapping aren't loaded, and skipped columns are only
+/// reported, a column being loaded by moving it to the properties. Edges join the row
+/// of their table to the row of `to` whose `id` their column holds.
+///
+/// A table with an `external_id`, such as `"external_id": "source_id"`, has its nodes
+/// upserted on that property, which needs a unique index, and its edges added once, so
+/// loading the database again updates the graph rather than duplicating it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SqlMapping {
+    #[serde(default)]
+    pub tables: Vec<TableMapping>,
+    #[serde(default)]
+    pub edges: Vec<ForeignKeyMapping>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableMapping {
+    pub table: String,
+    pub label: String,
+    /// Column identifying the rows, for edges to join on
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Property the nodes are upserted on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub properties: Vec<ColumnMapping>,
+    /// Columns that aren't loaded, with why
+    #[serde(default)]
+    pub skipped: Vec<SkippedColumn>,
+}
+
+/// A column loaded as a property of `type`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub column: String,
+    pub name: String,
+    #[serde(default)]
+    pub sql_type: String,
+    #[serde(rename = "type")]
+    pub helix_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkippedColumn {
+    pub column: String,
+    pub sql_type: String,
+    pub reason: String,
+}
+
+/// A foreign key loaded as edges from the rows of `table` to the rows of `to`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignKeyMapping {
+    pub label: String,
+    pub table: String,
+    pub column: String,
+    pub to: String,
+}
+
+impl SqlMapping {
+    /// Tables are labelled in camel case, and edges after the tables they connect as in
+    /// a sync of the database. Foreign keys are only mapped to edges when they point to
+    /// the primary key of a table, and both tables have a single column one.
+    ///
+    /// Columns are typed after their SQL type, those Helix has no type for being
+    /// skipped along with the foreign keys mapped to edges.
+    pub fn infer(tables: &[SqlTable]) -> Self {
+        let ids = tables
+            .iter()
+            .filter_map(|table| Some((table.name.as_str(), single_key(table)?)))
+            .collect::<HashMap<_, _>>();
+
+        let mut mapping = SqlMapping::default();
+        for table in tables {
+            let id = single_key(table);
+            let mut edges = HashMap::new();
+            let mut foreign_keys = table.foreign_keys.iter().collect::<Vec<_>>();
+            foreign_keys.sort_by_key(|fk| {
+                table
+                    .columns
+                    .iter()
+                    .position(|(column, _)| *column == fk.column)
+            });
+            for fk in foreign_keys {
+                if id.is_none() || ids.get(fk.to_table.as_str()) != Some(&fk.to_column.as_str()) {
+                    continue;
+                }
+                let shared = table
+                    .foreign_keys
+                    .iter()
+                    .filter(|other| other.to_table == fk.to_table)
+                    .count()
+                    > 1;
+                let label = edge_label(&table.name, &fk.column, &fk.to_table, shared);
+                edges.insert(fk.column.as_str(), (label.clone(), fk));
+                mapping.edges.push(ForeignKeyMapping {
+                    label,
+                    table: table.name.clone(),
+                    column: fk.column.clone(),
+                    to: fk.to_table.clone(),
+                });
+            }
+
+            let mut names = HashSet::new();
+            let mut properties = Vec::new();
+            let mut skipped = Vec::new();
+            for (column, sql_type) in table.columns.iter() {
+                let reason = match (edges.get(column.as_str()), helix_type(sql_type)) {
+                    (Some((label, fk)), _) if Some(column.as_str()) != id => format!(
+                        "foreign key to {}.{}, mapped to E::{}",
+                        fk.to_table, fk.to_column, label
+                    ),
+                    (_, Some(helix_type)) => {
+                        properties.push(ColumnMapping {
+                            column: column.clone(),
+                            name: property_name(column, &mut names),
+                            sql_type: sql_type.clone(),
+                            helix_type: helix_type.to_string(),
+                        });
+                        continue;
+                    }
+                    (_, None) if sql_type.is_empty() => "no declared type".to_string(),
+                    (_, None) => format!("no Helix type for {}", sql_type),
+                };
+                skipped.push(SkippedColumn {
+                    column: column.clone(),
+                    sql_type: sql_type.clone(),
+                    reason,
+                });
+            }
+
+            mapping.tables.push(TableMapping {
+                table: table.name.clone(),
+                label: to_camel_case(&table.name),
+                id: id.map(String::from),
+                external_id: None,
+                properties,
+                skipped,
+            });
+        }
+        mapping
+    }
+
+    /// Reads a mapping written by [`SqlMapping::write`], as edited by the user
+    pub fn read(path: &str) -> Result<Self, IngestionError> {
+        let mapping = fs::read_to_string(path)
+            .map_err(|e| IngestionError::MappingError(format!("Failed to read {}: {}", path, e)))?;
+        serde_json::from_str(&mapping)
+            .map_err(|e| IngestionError::MappingError(format!("Invalid mapping {}: {}", path, e)))
+    }
+
+    /// Writes the `schema.hx` of the mapping, the mapping and its report to the output
+    /// directory, returning their paths
+    pub fn write(&self, output_dir: &str) -> Result<Vec<PathBuf>, IngestionError> {
+        let mapping = serde_json::to_string_pretty(self).map_err(|e| {
+            IngestionError::MappingError(format!("Failed to serialize mapping: {}", e))
+        })?;
+        [
+            ("schema.hx", self.schema_hx()),
+            (MAPPING_FILE, mapping),
+            (REPORT_FILE, self.report()),
+        ]
+        .into_iter()
+        .map(|(file, content)| {
+            let path = Path::new(output_dir).join(file);
+            fs::write(&path, content).map_err(|e| {
+                IngestionError::MappingError(format!("Failed to write {}: {}", path.display(), e))
+            })?;
+            Ok(path)
+        })
+        .collect()
+    }
+
+    /// Checks the mapping against the tables of the database, for an edited mapping to
+    /// be rejected before anything is loaded
+    pub fn validate(&self, tables: &[SqlTable]) -> Result<(), IngestionError> {
+        let error = |e: String| Err(IngestionError::MappingError(e));
+        let mut labels = HashSet::new();
+        for mapping in self.tables.iter() {
+            let Some(table) = tables.iter().find(|table| table.name == mapping.table) else {
+                return error(format!("No table {}", mapping.table));
+            };
+            if !is_identifier(&mapping.label, true) {
+                return error(format!(
+                    "{}: label {} must start with an uppercase letter and only hold letters, digits and underscores",
+                    mapping.table, mapping.label
+                ));
+            }
+            if !labels.insert(mapping.label.as_str()) {
+                return error(format!(
+                    "{}: label {} is taken",
+                    mapping.table, mapping.label
+                ));
+            }
+            let has_column = |column: &str| table.columns.iter().any(|(name, _)| name == column);
+            if let Some(id) = mapping.id.as_deref().filter(|id| !has_column(id)) {
+                return error(format!("{}: no column {}", mapping.table, id));
+            }
+            let mut names = HashSet::new();
+            for property in mapping.properties.iter() {
+                if !has_column(&property.column) {
+                    return error(format!("{}: no column {}", mapping.table, property.column));
+                }
+                if !is_identifier(&property.name, false) || property.name.eq_ignore_ascii_case("id")
+                {
+                    return error(format!(
+                        "{}: {} isn't a valid property name",
+                        mapping.table, property.name
+                    ));
+                }
+                if !names.insert(property.name.as_str()) {
+                    return error(format!(
+                        "{}: property {} is mapped twice",
+                        mapping.table, property.name
+                    ));
+                }
+                if kind(&property.helix_type).is_none() {
+                    return error(format!(
+                        "{}: property {} has type {}, expected I64, F64, Boolean or String",
+                        mapping.table, property.name, property.helix_type
+                    ));
+                }
+            }
+            if let Some(external_id) = &mapping.external_id {
+                if !names.contains(external_id.as_str()) {
+                    return error(format!(
+                        "{}: external id {} isn't a mapped property",
+                        mapping.table, external_id
+                    ));
+                }
+            }
+        }
+
+        let mut labels = HashSet::new();
+        for edge in self.edges.iter() {
+            for table in [&edge.table, &edge.to] {
+                match self.table(table) {
+                    Some(TableMapping { id: Some(_), .. }) => {}
+                    Some(_) => {
+                        return error(format!("{}: {} has no id to join on", edge.label, table))
+                    }
+                    None => return error(format!("{}: table {} isn't mapped", edge.label, table)),
+                }
+            }
+            let table = tables.iter().find(|table| table.name == edge.table);
+            if !table
+                .is_some_and(|table| table.columns.iter().any(|(name, _)| *name == edge.column))
+            {
+                return error(format!("{}: no column {}", edge.table, edge.column));
+            }
+            if !is_identifier(&edge.label, true) {
+                return error(format!(
+                    "{}.{}: label {} must start with an uppercase letter and only hold letters, digits and underscores",
+                    edge.table, edge.column, edge.label
+                ));
+            }
+            if !labels.insert(edge.label.as_str()) {
+                return error(format!(
+                    "{}.{}: label {} is taken",
+                    edge.table, edge.column, edge.label
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn table(&self, table: &str) -> Option<&TableMapping> {
+        self.tables.iter().find(|mapping| mapping.table == table)
+    }
+
+    /// The schema of the mapped tables as a `schema.hx` file
+    pub fn schema_hx(&self) -> String {
+        let mut schema = String::new();
+        for table in self.tables.iter() {
+            schema.push_str(&format!("N::{} {{\n", table.label));
+            for property in table.properties.iter() {
+                schema.push_str(&format!(
+                    "    {}: {},\n",
+                    property.name, property.helix_type
+                ));
+            }
+            schema.push_str("}\n\n");
+        }
+        for edge in self.edges.iter() {
+            let label = |table: &str| {
+                self.table(table)
+                    .map_or(table.to_string(), |table| table.label.clone())
+            };
+            schema.push_str(&format!("E::{} {{\n", edge.label));
+            schema.push_str(&format!("    From: {},\n", label(&edge.table)));
+            schema.push_str(&format!("    To: {},\n", label(&edge.to)));
+            schema.push_str("    Properties: {\n    }\n}\n\n");
+        }
+        schema
+    }
+
+    /// What the tables, foreign keys and columns of the database are mapped to, in
+    /// plain text
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        for table in self.tables.iter() {
+            report.push_str(&format!("Table {} => N::{}\n", table.table, table.label));
+            match &table.id {
+                Some(id) => report.push_str(&format!("  rows identified by {}\n", id)),
+                None => {
+                    report.push_str("  no single column primary key, no edges can join its rows\n")
+                }
+            }
+            if let Some(external_id) = &table.external_id {
+                report.push_str(&format!("  nodes upserted on {}\n", external_id));
+            }
+            for property in table.properties.iter() {
+                report.push_str(&format!(
+                    "  {} {} => {}: {}\n",
+                    property.column, property.sql_type, property.name, property.helix_type
+                ));
+            }
+            for column in table.skipped.iter() {
+                report.push_str(&format!(
+                    "  {} {} skipped: {}\n",
+                    column.column, column.sql_type, column.reason
+                ));
+            }
+            report.push('\n');
+        }
+        report.push_str("Foreign keys\n");
+        if self.edges.is_empty() {
+            report.push_str("  None\n");
+        }
+        for edge in self.edges.iter() {
+            report.push_str(&format!(
+                "  {}.{} => E::{} from {} to {}\n",
+                edge.table, edge.column, edge.label, edge.table, edge.to
+            ));
+        }
+        report
+    }
+}
+
+/// Label of the edges of a foreign key, after the tables it connects such as
+/// `PostsToUsers`. Foreign keys of a table to the same table by different columns also
+/// take the name of their column, such as `PostsEditorIdToUsers`.
+pub fn edge_label(table: &str, column: &str, to_table: &str, shared: bool) -> String {
+    match shared {
+        true => format!(
+            "{}{}To{}",
+            to_camel_case(table),
+            to_camel_case(column),
+            to_camel_case(to_table)
+        ),
+        false => format!("{}To{}", to_camel_case(table), to_camel_case(to_table)),
+    }
+}
+
+/// The Helix type the values of a SQL type are loaded as, following the type affinity
+/// of SQLite for the types it doesn't name. Dates, times and JSON are loaded as text.
+pub fn helix_type(sql_type: &str) -> Option<&'static str> {
+    let sql_type = sql_type.to_uppercase();
+    // lengths and precisions, such as in VARCHAR(255), don't change the type
+    let sql_type = sql_type.split('(').next().unwrap_or_default().trim();
+    match sql_type {
+        "" | "ARRAY" | "USER-DEFINED" | "BYTEA" | "POINT" => None,
+        "BOOLEAN" | "BOOL" | "BIT" => Some("Boolean"),
+        "DATE" | "DATETIME" | "INTERVAL" | "UUID" | "JSON" | "JSONB" | "URL" => Some("String"),
+        t if t.starts_with("TIME") => Some("String"),
+        t if t.ends_with("[]") || t.contains("BLOB") => None,
+        t if t.contains("INT") || t.contains("SERIAL") => Some("I64"),
+        t if t.contains("CHAR") || t.contains("CLOB") || t.contains("TEXT") => Some("String"),
+        t if t.contains("REAL")
+            || t.contains("FLOA")
+            || t.contains("DOUB")
+            || t.contains("NUMERIC")
+            || t.contains("DECIMAL") =>
+        {
+            Some("F64")
+        }
+        _ => None,
+    }
+}
+
+fn single_key(table: &SqlTable) -> Option<&str> {
+    match table.primary_keys.as_slice() {
+        [key] => Some(key),
+        _ => None,
+    }
+}
+
+/// The name of the property a column is loaded as, made a valid and unique field name
+fn property_name(column: &str, names: &mut HashSet<String>) -> String {
+    let mut name = match column.eq_ignore_ascii_case("id") {
+        true => ID_PROPERTY.to_string(),
+        false => column
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c,
+                false => '_',
+            })
+            .collect(),
+    };
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert_str(0, "c_");
+    }
+    while !names.insert(name.clone()) {
+        name.push('_');
+    }
+    name
+}
+
+/// Whether a name is a valid field name, or label if it starts in uppercase
+fn is_identifier(name: &str, upper: bool) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| match upper {
+        true => c.is_ascii_uppercase(),
+        false => c.is_ascii_alphabetic(),
+    }) && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn kind(helix_type: &str) -> Option<PropertyKind> {
+    match helix_type {
+        "I64" => Some(PropertyKind::Integer),
+        "F64" => Some(PropertyKind::Float),
+        "Boolean" => Some(PropertyKind::Boolean),
+        "String" => Some(PropertyKind::String),
+        _ => None,
+    }
+}
+
+/// Counts of a load, with the rows that couldn't be loaded
+#[derive(Debug, Default)]
+pub struct SqlReport {
+    pub nodes: usize,
+    pub edges: usize,
+    pub errors: Vec<String>,
+}
+
+/// Turns the rows of the tables of a [`SqlMapping`] into batches for the ingestion
+/// endpoint, keeping the uuids of the nodes loaded for the edges to join on.
+///
+/// Ingestors select the columns of the loader from their database, and send the
+/// batches it makes of the rows. Every table is loaded before the edges.
+pub struct SqlLoader<'a> {
+    pub mapping: &'a SqlMapping,
+    /// `(table, id)` of a loaded row => the uuid of its node
+    pub ids: HashMap<(String, String), String>,
+    pub report: SqlReport,
+}
+
+impl<'a> SqlLoader<'a> {
+    pub fn new(mapping: &'a SqlMapping) -> Self {
+        SqlLoader {
+            mapping,
+            ids: HashMap::new(),
+            report: SqlReport::default(),
+        }
+    }
+
+    /// The columns of a table to select for its nodes, its id first
+    pub fn node_columns(table: &TableMapping) -> Vec<String> {
+        table
+            .id
+            .iter()
+            .cloned()
+            .chain(
+                table
+                    .properties
+                    .iter()
+                    .map(|property| property.column.clone()),
+            )
+            .collect()
+    }
+
+    /// The columns of the table of an edge to select for it, the id of the row and the
+    /// id of the row it points to
+    pub fn edge_columns(&self, edge: &ForeignKeyMapping) -> Result<Vec<String>, IngestionError> {
+        let id = self
+            .mapping
+            .table(&edge.table)
+            .and_then(|table| table.id.clone())
+            .ok_or_else(|| {
+                IngestionError::MappingError(format!("{}: {} has no id", edge.label, edge.table))
+            })?;
+        Ok(vec![id, edge.column.clone()])
+    }
+
+    /// The nodes of rows of the [`SqlLoader::node_columns`] of a table, with the
+    /// `(table, id)` of each. Rows that can't be converted are reported and left out.
+    pub fn nodes(
+        &mut self,
+        table: &TableMapping,
+        rows: Vec<Vec<Value>>,
+    ) -> (IngestBatch, Vec<Option<(String, String)>>) {
+        let mut batch = IngestBatch::default();
+        let mut keys = Vec::new();
+        for mut row in rows {
+            let key = match table.id {
+                Some(_) if !row.is_empty() => match key_text(&row.remove(0)) {
+                    Ok(key) => Some((table.table.clone(), key)),
+                    Err(e) => {
+                        self.report.errors.push(format!("{}: {}", table.table, e));
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            let properties = table
+                .properties
+                .iter()
+                .zip(row)
+                .filter(|(_, value)| *value != Value::Empty)
+                .map(|(property, value)| {
+                    let kind = kind(&property.helix_type).unwrap_or(PropertyKind::Inferred);
+                    column_value(value, kind).map(|value| (property.name.clone(), value))
+                })
+                .collect::<Result<HashMap<_, _>, _>>();
+            match properties {
+                Ok(properties) => {
+                    batch.nodes.push(IngestNode {
+                        label: table.label.clone(),
+                        properties,
+                        external_id: table.external_id.clone(),
+                    });
+                    keys.push(key);
+                }
+                Err(e) => self.report.errors.push(match key {
+                    Some((table, id)) => format!("{} {}: {}", table, id, e),
+                    None => format!("{}: {}", table.table, e),
+                }),
+            }
+        }
+        (batch, keys)
+    }
+
+    /// Keeps the uuids of the nodes of a batch the instance loaded
+    pub fn nodes_loaded(
+        &mut self,
+        batch: &IngestBatch,
+        keys: Vec<Option<(String, String)>>,
+        result: IngestResult,
+    ) {
+        for (key, id) in keys.into_iter().zip(result.nodes) {
+            if let Some(id) = id {
+                if let Some(key) = key {
+                    self.ids.insert(key, id);
+                }
+                self.report.nodes += 1;
+            }
+        }
+        for error in result.errors {
+            let label = &batch.nodes[error.index].label;
+            self.report
+                .errors
+                .push(format!("{}: {}", label, error.error));
+        }
+    }
+
+    /// The edges of rows of the [`SqlLoader::edge_columns`] of an edge, between the
+    /// nodes loaded for the rows. Rows with a null foreign key have no edge, and rows of
+    /// a table with an external id only have one.
+    pub fn edges(&mut self, edge: &ForeignKeyMapping, rows: Vec<Vec<Value>>) -> IngestBatch {
+        let mut batch = IngestBatch::default();
+        let unique = self
+            .mapping
+            .table(&edge.table)
+            .is_some_and(|table| table.external_id.is_some());
+        for row in rows {
+            let [from, to] = <[Value; 2]>::try_from(row).unwrap_or([Value::Empty, Value::Empty]);
+            if to == Value::Empty {
+                continue;
+            }
+            let node = |table: &str, value: &Value| {
+                let key = key_text(value)?;
+                self.ids
+                    .get(&(table.to_string(), key.clone()))
+                    .cloned()
+                    .ok_or_else(|| format!("no loaded {} row {}", table, key))
+            };
+            match (node(&edge.table, &from), node(&edge.to, &to)) {
+                (Ok(from), Ok(to)) => batch.edges.push(IngestEdge {
+                    label: edge.label.clone(),
+                    from,
+                    to,
+                    properties: HashMap::new(),
+                    unique,
+                }),
+                (Err(e), _) | (_, Err(e)) => self
+                    .report
+                    .errors
+                    .push(format!("{}.{}: {}", edge.table, edge.column, e)),
+            }
+        }
+        batch
+    }
+
+    /// Counts the edges of a batch the instance loaded
+    pub fn edges_loaded(&mut self, batch: &IngestBatch, result: IngestResult) {
+        self.report.edges += result.edges.iter().flatten().count();
+        for error in result.errors {
+            let label = &batch.edges[error.index].label;
+            self.report
+                .errors
+                .push(format!("{}: {}", label, error.error));
+        }
+    }
+}
+
+/// Selects columns of a table, quoting their names
+pub fn select(table: &str, columns: &[String]) -> String {
+    let quote = |name: &str| format!("\"{}\"", name.replace('"', "\"\""));
+    format!(
+        "SELECT {} FROM {}",
+        columns
+            .iter()
+            .map(|column| quote(column))
+            .collect::<Vec<_>>()
+            .join(", "),
+        quote(table)
+    )
+}
+
+/// A value of a column as `kind`
+fn column_value(value: Value, kind: PropertyKind) -> Result<Value, String> {
+    match (value, kind) {
+        (value @ Value::I64(_), PropertyKind::Integer)
+        | (value @ Value::F64(_), PropertyKind::Float)
+        | (value @ Value::Boolean(_), PropertyKind::Boolean)
+        | (value @ Value::String(_), PropertyKind::String) => Ok(value),
+        // SQLite keeps booleans as integers
+        (Value::I64(i), PropertyKind::Boolean) => Ok(Value::Boolean(i != 0)),
+        (value @ (Value::Array(_) | Value::Object(_)), PropertyKind::String) => {
+            serde_json::to_string(&value)
+                .map(Value::String)
+                .map_err(|e| e.to_string())
+        }
+        (value @ (Value::Array(_) | Value::Object(_) | Value::Empty), _) => {
+            Err(format!("{} values can't be converted", value))
+        }
+        (value, kind) => typed_value(kind, &value.to_string()),
+    }
+}
+
+/// A value of a column as the id of a row
+fn key_text(value: &Value) -> Result<String, String> {
+    match value {
+        Value::Array(_) | Value::Object(_) | Value::Empty => {
+            Err(format!("{} values can't be ids", value))
+        }
+        value => Ok(value.to_string()),
+    }
+}
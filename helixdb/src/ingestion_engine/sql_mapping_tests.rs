@@ -0,0 +1,326 @@
+use crate::{
+    ingestion_engine::{
+        neo4j_tests::serve_ingest,
+        sql_ingestion::{GraphSchema, SqliteIngestor},
+        sql_mapping::{helix_type, SqlMapping, MAPPING_FILE, REPORT_FILE},
+        sqlite_tests::create_mock_sqlite_db,
+    },
+    protocol::value::Value,
+};
+use rusqlite::Connection;
+use std::{collections::HashMap, fs};
+use tempfile::TempDir;
+
+fn ingestor(conn: Connection, instance: &str) -> SqliteIngestor {
+    SqliteIngestor {
+        sqlite_conn: conn,
+        instance: instance.to_string(),
+        batch_size: 8,
+        id_mappings: HashMap::new(),
+        graph_schema: GraphSchema::new(),
+    }
+}
+
+/// Posts with an author and an editor, tags of posts with a composite key, and columns
+/// of types Helix has no type for. A post points to an author that doesn't exist.
+fn create_blog_db() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        r#"
+        PRAGMA foreign_keys = OFF;
+        CREATE TABLE authors (id INTEGER PRIMARY KEY, name VARCHAR(64), avatar BLOB, notes);
+        CREATE TABLE posts (
+            id INTEGER PRIMARY KEY,
+            title TEXT,
+            "published at" DATETIME,
+            draft BOOLEAN,
+            author_id INTEGER REFERENCES authors(id),
+            editor_id INTEGER REFERENCES authors(id)
+        );
+        CREATE TABLE post_tags (
+            post_id INTEGER REFERENCES posts(id),
+            tag TEXT,
+            PRIMARY KEY (post_id, tag)
+        );
+        INSERT INTO authors VALUES (1, 'Ada', x'00', 'first'), (2, 'Grace', NULL, NULL);
+        INSERT INTO posts VALUES
+            (1, 'Hello', '2024-01-01 10:00:00', 0, 1, 2),
+            (2, 'Draft', NULL, 1, 2, NULL),
+            (3, 'Orphan', NULL, 0, 9, NULL);
+        INSERT INTO post_tags VALUES (1, 'intro');
+        "#,
+    )
+    .unwrap();
+    conn
+}
+
+#[test]
+fn test_helix_types() {
+    assert_eq!(helix_type("INTEGER"), Some("I64"));
+    assert_eq!(helix_type("bigint"), Some("I64"));
+    assert_eq!(helix_type("VARCHAR(255)"), Some("String"));
+    assert_eq!(helix_type("character varying"), Some("String"));
+    assert_eq!(helix_type("timestamp without time zone"), Some("String"));
+    assert_eq!(helix_type("interval"), Some("String"));
+    assert_eq!(helix_type("DECIMAL(10, 2)"), Some("F64"));
+    assert_eq!(helix_type("double precision"), Some("F64"));
+    assert_eq!(helix_type("boolean"), Some("Boolean"));
+    assert_eq!(helix_type("BLOB"), None);
+    assert_eq!(helix_type("bytea"), None);
+    assert_eq!(helix_type("ARRAY"), None);
+    assert_eq!(helix_type(""), None);
+}
+
+#[test]
+fn test_infer_mapping() {
+    let mut ingestor = ingestor(create_blog_db(), "http://localhost:6969");
+    let tables = ingestor.tables().unwrap();
+    let mapping = SqlMapping::infer(&tables);
+    mapping.validate(&tables).unwrap();
+
+    let authors = &mapping.tables[0];
+    assert_eq!(
+        (authors.label.as_str(), authors.id.as_deref()),
+        ("Authors", Some("id"))
+    );
+    let properties = authors
+        .properties
+        .iter()
+        .map(|p| (p.column.as_str(), p.name.as_str(), p.helix_type.as_str()))
+        .collect::<Vec<_>>();
+    // `id` is reserved for the id of the node
+    assert_eq!(
+        properties,
+        vec![("id", "source_id", "I64"), ("name", "name", "String")]
+    );
+    let skipped = authors
+        .skipped
+        .iter()
+        .map(|c| (c.column.as_str(), c.reason.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        skipped,
+        vec![
+            ("avatar", "no Helix type for BLOB"),
+            ("notes", "no declared type")
+        ]
+    );
+
+    let posts = &mapping.tables[1];
+    let names = posts
+        .properties
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["source_id", "title", "published_at", "draft"]);
+    assert_eq!(
+        posts.skipped[0].reason,
+        "foreign key to authors.id, mapped to E::PostsAuthorIdToAuthors"
+    );
+
+    // edges can't join the rows of a table without a single column primary key
+    let tags = &mapping.tables[2];
+    assert_eq!(tags.id, None);
+    assert!(tags.skipped.is_empty());
+    let edges = mapping
+        .edges
+        .iter()
+        .map(|e| (e.label.as_str(), e.column.as_str(), e.to.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        edges,
+        vec![
+            ("PostsAuthorIdToAuthors", "author_id", "authors"),
+            ("PostsEditorIdToAuthors", "editor_id", "authors"),
+        ]
+    );
+
+    let schema = mapping.schema_hx();
+    assert!(schema.contains("N::Posts {\n    source_id: I64,\n    title: String,\n"));
+    assert!(schema.contains(
+        "E::PostsEditorIdToAuthors {\n    From: Posts,\n    To: Authors,\n    Properties: {\n    }\n}"
+    ));
+    let report = mapping.report();
+    assert!(report.contains("Table posts => N::Posts\n  rows identified by id\n"));
+    assert!(report.contains("  published at DATETIME => published_at: String\n"));
+    assert!(report.contains("  avatar BLOB skipped: no Helix type for BLOB\n"));
+    assert!(
+        report.contains("  posts.author_id => E::PostsAuthorIdToAuthors from posts to authors\n")
+    );
+}
+
+#[test]
+fn test_written_mapping_is_read_back() {
+    let dir = TempDir::new().unwrap();
+    let output_dir = dir.path().to_str().unwrap();
+    let mut ingestor = ingestor(create_blog_db(), "http://localhost:6969");
+    let mapping = SqlMapping::infer(&ingestor.tables().unwrap());
+
+    let files = mapping.write(output_dir).unwrap();
+    assert_eq!(files.len(), 3);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("schema.hx")).unwrap(),
+        mapping.schema_hx()
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join(REPORT_FILE)).unwrap(),
+        mapping.report()
+    );
+    let path = dir.path().join(MAPPING_FILE);
+    assert_eq!(SqlMapping::read(path.to_str().unwrap()).unwrap(), mapping);
+}
+
+#[test]
+fn test_load_mapping() {
+    let (url, batches) = serve_ingest();
+    let mut ingestor = ingestor(create_mock_sqlite_db(None).unwrap(), &url);
+    let mapping = SqlMapping::infer(&ingestor.tables().unwrap());
+
+    let report = ingestor.load(&mapping).unwrap();
+    assert_eq!(report.nodes, 40);
+    assert_eq!(report.edges, 20);
+    assert!(report.errors.is_empty());
+
+    let batches = batches.lock().unwrap();
+    // nodes are sent in batches, every table before the edges
+    let sizes = batches
+        .iter()
+        .map(|batch| (batch.nodes.len(), batch.edges.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sizes,
+        vec![
+            (8, 0),
+            (8, 0),
+            (4, 0),
+            (8, 0),
+            (8, 0),
+            (4, 0),
+            (0, 8),
+            (0, 8),
+            (0, 4)
+        ]
+    );
+    let user = batches[3]
+        .nodes
+        .iter()
+        .find(|node| {
+            node.properties.get("name") == Some(&Value::String("Heather Pittman".to_string()))
+        })
+        .unwrap();
+    assert_eq!(user.label, "Users");
+    // the server reads positive integers back as unsigned
+    assert_eq!(user.properties.get("source_id"), Some(&Value::U64(1)));
+    assert_eq!(user.properties.get("age"), Some(&Value::U64(28)));
+    assert!(!user.properties.contains_key("parent_id"));
+    assert!(batches[6]
+        .edges
+        .iter()
+        .all(|edge| edge.label == "UsersToParents"));
+}
+
+#[test]
+fn test_load_edited_mapping() {
+    let (url, batches) = serve_ingest();
+    let mut ingestor = ingestor(create_blog_db(), &url);
+    let mut mapping = SqlMapping::infer(&ingestor.tables().unwrap());
+
+    // tags aren't loaded, titles are renamed and the editors are left out
+    mapping.tables.retain(|table| table.table != "post_tags");
+    mapping.tables[1].label = "Post".to_string();
+    mapping.tables[1].properties[1].name = "heading".to_string();
+    mapping.edges.retain(|edge| edge.column != "editor_id");
+    mapping.edges[0].label = "WrittenBy".to_string();
+    mapping.tables[1].external_id = Some("source_id".to_string());
+    mapping.validate(&ingestor.tables().unwrap()).unwrap();
+    assert!(mapping.report().contains("  nodes upserted on source_id\n"));
+
+    let report = ingestor.load(&mapping).unwrap();
+    assert_eq!(report.nodes, 5);
+    assert_eq!(report.edges, 2);
+    assert_eq!(
+        report.errors,
+        vec!["posts.author_id: no loaded authors row 9".to_string()]
+    );
+
+    let batches = batches.lock().unwrap();
+    let posts = batches
+        .iter()
+        .flat_map(|batch| batch.nodes.iter())
+        .filter(|node| node.label == "Post")
+        .collect::<Vec<_>>();
+    assert_eq!(posts.len(), 3);
+    assert_eq!(posts[0].external_id.as_deref(), Some("source_id"));
+    assert_eq!(
+        posts[0].properties.get("heading"),
+        Some(&Value::String("Hello".to_string()))
+    );
+    // SQLite keeps booleans as integers
+    assert_eq!(
+        posts[1].properties.get("draft"),
+        Some(&Value::Boolean(true))
+    );
+    // null columns are left out
+    assert!(!posts[1].properties.contains_key("published_at"));
+    let edge = batches
+        .iter()
+        .flat_map(|batch| batch.edges.iter())
+        .next()
+        .unwrap();
+    assert_eq!(edge.label, "WrittenBy");
+    // posts are upserted, so their edges are only added once
+    assert!(edge.unique);
+}
+
+#[test]
+fn test_invalid_mappings() {
+    let mut ingestor = ingestor(create_blog_db(), "http://localhost:6969");
+    let tables = ingestor.tables().unwrap();
+    let mapping = SqlMapping::infer(&tables);
+    let error = |edit: fn(&mut SqlMapping)| {
+        let mut mapping = mapping.clone();
+        edit(&mut mapping);
+        mapping.validate(&tables).unwrap_err().to_string()
+    };
+
+    assert_eq!(
+        error(|m| m.tables[0].properties[1].column = "email".to_string()),
+        "authors: no column email"
+    );
+    assert_eq!(
+        error(|m| m.tables[0].properties[1].helix_type = "Date".to_string()),
+        "authors: property name has type Date, expected I64, F64, Boolean or String"
+    );
+    assert_eq!(
+        error(|m| m.tables[0].properties[1].name = "id".to_string()),
+        "authors: id isn't a valid property name"
+    );
+    assert_eq!(
+        error(|m| m.tables[0].external_id = Some("email".to_string())),
+        "authors: external id email isn't a mapped property"
+    );
+    assert_eq!(
+        error(|m| m.tables[1].label = "Authors".to_string()),
+        "posts: label Authors is taken"
+    );
+    assert_eq!(
+        error(|m| m.tables[0].table = "writers".to_string()),
+        "No table writers"
+    );
+    assert_eq!(
+        error(|m| {
+            m.tables.remove(0);
+        }),
+        "PostsAuthorIdToAuthors: table authors isn't mapped"
+    );
+    assert_eq!(
+        error(|m| m.tables[0].id = None),
+        "PostsAuthorIdToAuthors: authors has no id to join on"
+    );
+    assert!(ingestor
+        .load(&SqlMapping {
+            tables: vec![],
+            edges: mapping.edges.clone(),
+        })
+        .is_err());
+}
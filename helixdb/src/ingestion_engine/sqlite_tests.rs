@@ -319,9 +319,9 @@ fn test_ingest_basics() {
 
 #[test]
 fn test_dump_to_json_basic() {
-    // Create a temporary file path for the JSONL output
-    let temp_dir = std::env::temp_dir();
-    let output_path = temp_dir;
+    // Create a temporary directory for the JSONL output
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_path = temp_dir.path();
     let output_path_str = output_path.to_str().unwrap();
 
     // Create a mock database and ingestor
@@ -378,9 +378,9 @@ fn test_dump_to_json_basic() {
 
 #[test]
 fn test_dump_to_json_content() {
-    // Create a temporary file path for the JSONL output
-    let temp_dir = std::env::temp_dir();
-    let output_path = temp_dir;
+    // Create a temporary directory for the JSONL output
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_path = temp_dir.path();
     let output_path_str = output_path.to_str().unwrap();
 
     // Create a mock database and ingestor
@@ -468,9 +468,9 @@ fn test_dump_to_json_content() {
 
 #[test]
 fn test_dump_to_json_node_properties() {
-    // Create a temporary file path for the JSONL output
-    let temp_dir = std::env::temp_dir();
-    let output_path = temp_dir;
+    // Create a temporary directory for the JSONL output
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_path = temp_dir.path();
     let output_path_str = output_path.to_str().unwrap();
 
     // Create a mock database and ingestor
@@ -571,9 +571,9 @@ fn test_dump_to_json_node_properties() {
 
 #[test]
 fn test_dump_to_json_edge_relationships() {
-    // Create a temporary file path for the JSONL output
-    let temp_dir = std::env::temp_dir();
-    let output_path = temp_dir;
+    // Create a temporary directory for the JSONL output
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_path = temp_dir.path();
     let output_path_str = output_path.to_str().unwrap();
 
     // Create a mock database and ingestor
@@ -671,7 +671,12 @@ fn test_ingest_basic() {
         graph_schema: GraphSchema::new(),
     };
 
-    ingestor.ingest().expect("Failed to ingest");
+    let temp_dir = tempfile::tempdir().unwrap();
+    ingestor
+        .ingest(temp_dir.path().to_str().unwrap())
+        .expect("Failed to ingest");
+    assert!(temp_dir.path().join("ingestion.jsonl").exists());
+    assert!(temp_dir.path().join("schema.hx").exists());
 }
 
 #[test]
@@ -1,5 +1,8 @@
+pub mod helix_cluster;
 pub mod helix_engine;
 pub mod helix_gateway;
+pub mod helix_replication;
+pub mod helix_sharding;
 #[cfg(feature = "compiler")]
 pub mod helixc;
 #[cfg(feature = "ingestion")]
@@ -0,0 +1,86 @@
+//! Fields of an `ENUM` type declared in the schema.
+//!
+//! Their values are strings holding the name of a variant, which are stored as the index
+//! of the variant instead. The generated queries submit the enum fields of the schema, so
+//! the nodes and edges are encoded and decoded with them without a schema at hand.
+
+use super::value::Value;
+use std::{collections::HashMap, sync::LazyLock};
+
+/// A field of a node or edge label taking the variants of an enum
+pub struct EnumField {
+    pub label: &'static str,
+    pub field: &'static str,
+    pub variants: &'static [&'static str],
+}
+
+inventory::collect!(EnumField);
+
+type Variants = &'static [&'static str];
+
+/// Label -> field -> variants
+static ENUM_FIELDS: LazyLock<HashMap<&'static str, HashMap<&'static str, Variants>>> =
+    LazyLock::new(|| {
+        let mut fields: HashMap<&str, HashMap<&str, Variants>> = HashMap::new();
+        for enum_field in inventory::iter::<EnumField> {
+            fields
+                .entry(enum_field.label)
+                .or_default()
+                .insert(enum_field.field, enum_field.variants);
+        }
+        fields
+    });
+
+/// Replaces the variant names of the enum fields of the label by their index, `None` if
+/// the label has no enum fields.
+///
+/// Strings that aren't a variant are kept as they are.
+pub fn encode(
+    label: &str,
+    properties: &HashMap<String, Value>,
+) -> Option<HashMap<String, Value>> {
+    let fields = ENUM_FIELDS.get(label)?;
+    let encoded = properties
+        .iter()
+        .map(|(name, value)| {
+            let index = match (fields.get(name.as_str()), value) {
+                (Some(variants), Value::String(s)) => variants.iter().position(|v| v == s),
+                _ => None,
+            };
+            let value = match index {
+                Some(index) => Value::U8(index as u8),
+                None => value.clone(),
+            };
+            (name.clone(), value)
+        })
+        .collect();
+    Some(encoded)
+}
+
+/// Replaces the variant indices of the enum fields of the label by the variant names
+pub fn decode(label: &str, properties: &mut HashMap<String, Value>) {
+    let Some(fields) = ENUM_FIELDS.get(label) else {
+        return;
+    };
+    for (name, variants) in fields {
+        if let Some(value) = properties.get_mut(*name) {
+            decode_variant(variants, value);
+        }
+    }
+}
+
+/// Replaces the variant index of a single property by the variant name, if the field is
+/// an enum field of the label
+pub fn decode_field(label: &str, field: &str, value: &mut Value) {
+    if let Some(variants) = ENUM_FIELDS.get(label).and_then(|fields| fields.get(field)) {
+        decode_variant(variants, value);
+    }
+}
+
+fn decode_variant(variants: Variants, value: &mut Value) {
+    if let Value::U8(index) = value {
+        if let Some(variant) = variants.get(*index as usize) {
+            *value = Value::String(variant.to_string());
+        }
+    }
+}
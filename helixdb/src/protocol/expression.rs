@@ -0,0 +1,159 @@
+//! Values computed by the arithmetic and string expressions of queries, e.g.
+//! `{total: _::{price} * _::{quantity}}` or `WHERE(LOWER(_::{name})::EQ("alice"))`.
+//!
+//! The operations never fail. An operand of the wrong type, a missing property, an
+//! overflow or a division by zero yields `Value::Empty`, which leaves a remapped field
+//! empty and doesn't match any predicate.
+
+use super::{filterable::Filterable, redaction, value::Value};
+use crate::helix_engine::graph_core::ops::tr_val::TraversalVal;
+use std::cmp::Ordering;
+
+/// A property of the element, empty if it isn't set or is a sensitive field the request
+/// has no access to, so it can't be read under another name
+pub fn property(item: &TraversalVal, field: &str) -> Value {
+    let (label, value) = match item {
+        TraversalVal::Node(node) => (node.label.as_str(), node.check_property(field)),
+        TraversalVal::Edge(edge) => (edge.label.as_str(), edge.check_property(field)),
+        TraversalVal::Vector(vec) => (vec.label(), vec.check_property(field)),
+        _ => return Value::Empty,
+    };
+    match value {
+        Ok(value) if !redaction::is_hidden(label, field) => value.clone(),
+        _ => Value::Empty,
+    }
+}
+
+/// Adds numbers or concatenates strings, a number or boolean concatenated with a string
+/// being written as text
+pub fn add(a: Value, b: Value) -> Value {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Value::String(a + &b),
+        (Value::String(a), b) if is_text(&b) => Value::String(a + &b.to_string()),
+        (a, Value::String(b)) if is_text(&a) => Value::String(a.to_string() + &b),
+        (a, b) => arithmetic(&a, &b, i64::checked_add, |a, b| a + b),
+    }
+}
+
+pub fn sub(a: Value, b: Value) -> Value {
+    arithmetic(&a, &b, i64::checked_sub, |a, b| a - b)
+}
+
+pub fn mul(a: Value, b: Value) -> Value {
+    arithmetic(&a, &b, i64::checked_mul, |a, b| a * b)
+}
+
+/// Divides numbers, always giving a float
+pub fn div(a: Value, b: Value) -> Value {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) if b != 0.0 => Value::F64(a / b),
+        _ => Value::Empty,
+    }
+}
+
+pub fn lower(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(s.to_lowercase()),
+        _ => Value::Empty,
+    }
+}
+
+pub fn upper(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(s.to_uppercase()),
+        _ => Value::Empty,
+    }
+}
+
+/// The number of characters of a string or elements of an array
+pub fn len(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::I64(s.chars().count() as i64),
+        Value::Array(values) => Value::I64(values.len() as i64),
+        _ => Value::Empty,
+    }
+}
+
+/// Rounds a number to the nearest integer, halfway cases away from zero
+pub fn round(value: Value) -> Value {
+    if let Some(i) = as_i64(&value) {
+        return Value::I64(i);
+    }
+    match value.as_f64() {
+        Some(f) if f.is_finite() && f.abs() < i64::MAX as f64 => Value::I64(f.round() as i64),
+        _ => Value::Empty,
+    }
+}
+
+/// A computed value a property is compared to. Numbers compare by value across numeric
+/// types, and nothing compares to an empty value or a value of another kind.
+pub struct Computed(pub Value);
+
+impl PartialEq<Computed> for Value {
+    fn eq(&self, other: &Computed) -> bool {
+        compare(self, &other.0) == Some(Ordering::Equal)
+    }
+
+    // values that don't compare aren't unequal either, so `NEQ` doesn't match them
+    #[allow(clippy::partialeq_ne_impl)]
+    fn ne(&self, other: &Computed) -> bool {
+        matches!(
+            compare(self, &other.0),
+            Some(Ordering::Less | Ordering::Greater)
+        )
+    }
+}
+
+impl PartialOrd<Computed> for Value {
+    fn partial_cmp(&self, other: &Computed) -> Option<Ordering> {
+        compare(self, &other.0)
+    }
+}
+
+/// How a value compares to a computed one, `None` if they don't compare
+pub fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Empty, _) | (_, Value::Empty) => None,
+        (Value::String(_), Value::String(_))
+        | (Value::Boolean(_), Value::Boolean(_))
+        | (Value::DateTime(_), Value::DateTime(_) | Value::String(_))
+        | (Value::String(_), Value::DateTime(_)) => Some(a.total_cmp(b)),
+        _ if a.as_f64().is_some() && b.as_f64().is_some() => Some(a.total_cmp(b)),
+        _ => None,
+    }
+}
+
+fn is_text(value: &Value) -> bool {
+    value.as_f64().is_some() || matches!(value, Value::Boolean(_))
+}
+
+fn as_i64(value: &Value) -> Option<i64> {
+    match *value {
+        Value::I8(i) => Some(i as i64),
+        Value::I16(i) => Some(i as i64),
+        Value::I32(i) => Some(i as i64),
+        Value::I64(i) => Some(i),
+        Value::U8(u) => Some(u as i64),
+        Value::U16(u) => Some(u as i64),
+        Value::U32(u) => Some(u as i64),
+        Value::U64(u) => i64::try_from(u).ok(),
+        Value::U128(u) => i64::try_from(u).ok(),
+        _ => None,
+    }
+}
+
+/// Integers give an integer, failing on overflow, and any float gives a float
+fn arithmetic(
+    a: &Value,
+    b: &Value,
+    int_op: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+) -> Value {
+    if let (Some(a), Some(b)) = (as_i64(a), as_i64(b)) {
+        return int_op(a, b).map_or(Value::Empty, Value::I64);
+    }
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => Value::F64(float_op(a, b)),
+        _ => Value::Empty,
+    }
+}
@@ -39,7 +39,7 @@ pub trait Filterable {
     fn properties(self) -> Option<HashMap<String, Value>>;
 
     fn vector_data(&self) -> &[f64];
-    fn score(&self) -> f64;
+    fn score(&self) -> Option<f64>;
 
     fn properties_mut(&mut self) -> &mut Option<HashMap<String, Value>>;
 
@@ -102,8 +102,8 @@ impl Filterable for Node {
     }
 
     #[inline(always)]
-    fn score(&self) -> f64 {
-        unreachable!()
+    fn score(&self) -> Option<f64> {
+        self.score
     }
 
     #[inline(always)]
@@ -204,8 +204,8 @@ impl Filterable for Edge {
     }
 
     #[inline(always)]
-    fn score(&self) -> f64 {
-        unreachable!()
+    fn score(&self) -> Option<f64> {
+        None
     }
 
     #[inline(always)]
@@ -7,15 +7,37 @@ use serde::{
     Deserializer, Serializer,
 };
 use sonic_rs::{Deserialize, Serialize};
-// pub type ID = String;
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+use uuid::Uuid;
+
+/// Id of a node, edge or vector, kept as the `u128` storage uses and only formatted as
+/// a UUID when written out
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[repr(transparent)]
-// #[serde(transparent)]
 pub struct ID(u128);
 impl ID {
     pub fn inner(&self) -> u128 {
         self.0
     }
+
+    /// Formats the id as a hyphenated UUID into the buffer, without allocating
+    #[inline]
+    pub fn encode<'b>(&self, buffer: &'b mut [u8; 36]) -> &'b str {
+        Uuid::from_u128(self.0).hyphenated().encode_lower(buffer)
+    }
+}
+
+impl fmt::Display for ID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.encode(&mut [0; 36]))
+    }
+}
+
+impl std::str::FromStr for ID {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(ID(Uuid::parse_str(s)?.as_u128()))
+    }
 }
 
 impl Serialize for ID {
@@ -23,7 +45,7 @@ impl Serialize for ID {
     where
         S: Serializer,
     {
-        serializer.serialize_u128(self.0)
+        serializer.serialize_str(self.encode(&mut [0; 36]))
     }
 }
 
@@ -40,10 +62,7 @@ impl<'de> Visitor<'de> for IDVisitor {
     where
         E: serde::de::Error,
     {
-        match uuid::Uuid::parse_str(v) {
-            Ok(uuid) => Ok(ID(uuid.as_u128())),
-            Err(e) => Err(E::custom(e.to_string())),
-        }
+        v.parse().map_err(|e: uuid::Error| E::custom(e.to_string()))
     }
 }
 impl<'de> Deserialize<'de> for ID {
@@ -0,0 +1,467 @@
+//! Borrowed views of stored nodes and edges, reading their fields straight from the
+//! encoded bytes.
+//!
+//! Decoding a node or edge builds its whole property map, which steps only looking at its
+//! label or a couple of its properties, such as filters, throw away right after. A view
+//! instead finds the fields where bincode laid them out and only decodes a property the
+//! first time it is read.
+//!
+//! The encoding is bincode's default: lengths as little endian u64s, an `Option` as a
+//! byte tag, and an enum variant as a little endian u32 index followed by its data.
+//! Items written with interned strings hold the little endian u32 id of their label and
+//! property names instead of the strings.
+
+use std::{borrow::Cow, cell::OnceCell, collections::HashMap, ops::Range, sync::Arc};
+
+use super::{
+    enums,
+    items::{Edge, Node},
+    value::Value,
+};
+use crate::helix_engine::{
+    storage_core::{
+        compression::decompress,
+        dictionary::{is_interned, Dictionary},
+    },
+    types::GraphError,
+};
+
+/// View of a stored node, decoding its properties as they are read
+pub struct NodeView<'a> {
+    item: Encoded<'a>,
+}
+
+impl<'a> NodeView<'a> {
+    /// Views a stored node, which may have been compressed or interned by the storage.
+    /// Encrypted nodes are viewed through `HelixGraphStorage::node_view`, which decrypts
+    /// them first.
+    pub fn new(
+        bytes: impl Into<Cow<'a, [u8]>>,
+        id: u128,
+        dictionary: &'a Dictionary,
+    ) -> Result<NodeView<'a>, GraphError> {
+        let bytes = decompress(bytes)?;
+        let interned = is_interned(&bytes).then_some(dictionary);
+        let mut reader = Reader::new(&bytes, interned);
+        let label = reader.label()?;
+        let properties = reader.pos;
+        Ok(NodeView {
+            item: Encoded::new(id, bytes, label, properties, interned),
+        })
+    }
+
+    pub fn id(&self) -> u128 {
+        self.item.id
+    }
+
+    pub fn label(&self) -> &str {
+        self.item.label()
+    }
+
+    /// Decodes the property, failing like `Filterable::check_property` if there is none
+    pub fn check_property(&self, key: &str) -> Result<&Value, GraphError> {
+        self.item.property(key)
+    }
+
+    /// Decodes the whole node
+    pub fn decode(&self) -> Result<Node, GraphError> {
+        match self.item.interned {
+            Some(_) => Ok(Node {
+                id: self.item.id,
+                label: self.label().to_string(),
+                properties: self.item.project(None)?,
+                score: None,
+            }),
+            None => Node::decode_node(&self.item.bytes, self.item.id),
+        }
+    }
+
+    /// Decodes the node with only the given properties
+    pub fn decode_only(&self, fields: &[&str]) -> Result<Node, GraphError> {
+        Ok(Node {
+            id: self.item.id,
+            label: self.label().to_string(),
+            properties: self.item.project(Some(fields))?,
+            score: None,
+        })
+    }
+}
+
+/// View of a stored edge, decoding its properties as they are read
+pub struct EdgeView<'a> {
+    item: Encoded<'a>,
+    from_node: u128,
+    to_node: u128,
+}
+
+impl<'a> EdgeView<'a> {
+    /// Views a stored edge, which may have been compressed or interned by the storage.
+    /// Encrypted edges are viewed through `HelixGraphStorage::edge_view`, which decrypts
+    /// them first.
+    pub fn new(
+        bytes: impl Into<Cow<'a, [u8]>>,
+        id: u128,
+        dictionary: &'a Dictionary,
+    ) -> Result<EdgeView<'a>, GraphError> {
+        let bytes = decompress(bytes)?;
+        let interned = is_interned(&bytes).then_some(dictionary);
+        let mut reader = Reader::new(&bytes, interned);
+        let label = reader.label()?;
+        let from_node = reader.u128()?;
+        let to_node = reader.u128()?;
+        let properties = reader.pos;
+        Ok(EdgeView {
+            item: Encoded::new(id, bytes, label, properties, interned),
+            from_node,
+            to_node,
+        })
+    }
+
+    pub fn id(&self) -> u128 {
+        self.item.id
+    }
+
+    pub fn label(&self) -> &str {
+        self.item.label()
+    }
+
+    pub fn from_node(&self) -> u128 {
+        self.from_node
+    }
+
+    pub fn to_node(&self) -> u128 {
+        self.to_node
+    }
+
+    /// Decodes the property, failing like `Filterable::check_property` if there is none
+    pub fn check_property(&self, key: &str) -> Result<&Value, GraphError> {
+        self.item.property(key)
+    }
+
+    /// Decodes the whole edge
+    pub fn decode(&self) -> Result<Edge, GraphError> {
+        match self.item.interned {
+            Some(_) => Ok(Edge {
+                id: self.item.id,
+                label: self.label().to_string(),
+                from_node: self.from_node,
+                to_node: self.to_node,
+                properties: self.item.project(None)?,
+            }),
+            None => Edge::decode_edge(&self.item.bytes, self.item.id),
+        }
+    }
+
+    /// Decodes the edge with only the given properties
+    pub fn decode_only(&self, fields: &[&str]) -> Result<Edge, GraphError> {
+        Ok(Edge {
+            id: self.item.id,
+            label: self.label().to_string(),
+            from_node: self.from_node,
+            to_node: self.to_node,
+            properties: self.item.project(Some(fields))?,
+        })
+    }
+}
+
+/// Label of a viewed item
+enum Label {
+    /// Where the label is in the encoded bytes
+    Encoded(Range<usize>),
+    Interned(Arc<str>),
+}
+
+/// A property found in the encoded bytes
+struct Property {
+    key: Range<usize>,
+    value: Range<usize>,
+    decoded: OnceCell<Value>,
+}
+
+struct Encoded<'a> {
+    id: u128,
+    bytes: Cow<'a, [u8]>,
+    label: Label,
+    /// Where the properties start
+    properties_at: usize,
+    /// Found the first time a property is read, `None` if the item has no properties
+    properties: OnceCell<Option<Vec<Property>>>,
+    /// Dictionary of the item's property names if it was written with interned strings
+    interned: Option<&'a Dictionary>,
+}
+
+impl<'a> Encoded<'a> {
+    fn new(
+        id: u128,
+        bytes: Cow<'a, [u8]>,
+        label: Label,
+        properties_at: usize,
+        interned: Option<&'a Dictionary>,
+    ) -> Self {
+        Encoded {
+            id,
+            bytes,
+            label,
+            properties_at,
+            properties: OnceCell::new(),
+            interned,
+        }
+    }
+
+    fn label(&self) -> &str {
+        match &self.label {
+            // checked to be UTF-8 when the view was made
+            Label::Encoded(range) => {
+                std::str::from_utf8(&self.bytes[range.clone()]).unwrap_or_default()
+            }
+            Label::Interned(label) => label,
+        }
+    }
+
+    /// The key as held by the encoded properties, `None` if no property can have it
+    fn encoded_key<'k>(&self, key: &'k str) -> Option<Cow<'k, [u8]>> {
+        match self.interned {
+            Some(dictionary) => Some(Cow::Owned(dictionary.id(key)?.to_le_bytes().to_vec())),
+            None => Some(Cow::Borrowed(key.as_bytes())),
+        }
+    }
+
+    fn property(&self, key: &str) -> Result<&Value, GraphError> {
+        let not_found = || GraphError::ConversionError(format!("Property {} not found", key));
+        let encoded = self.encoded_key(key).ok_or_else(not_found)?;
+        let property = self
+            .properties()?
+            .iter()
+            .flatten()
+            .find(|property| self.key(property) == &*encoded)
+            .ok_or_else(not_found)?;
+        if let Some(value) = property.decoded.get() {
+            return Ok(value);
+        }
+        let value = self.decode_value(property, key)?;
+        Ok(property.decoded.get_or_init(|| value))
+    }
+
+    /// The properties with one of the given keys, all of them if `None`
+    fn project(
+        &self,
+        fields: Option<&[&str]>,
+    ) -> Result<Option<HashMap<String, Value>>, GraphError> {
+        let Some(properties) = self.properties()? else {
+            return Ok(None);
+        };
+        let fields = fields.map(|fields| {
+            fields
+                .iter()
+                .filter_map(|field| Some((*field, self.encoded_key(field)?)))
+                .collect::<Vec<_>>()
+        });
+        let mut projected = HashMap::with_capacity(match &fields {
+            Some(fields) => fields.len(),
+            None => properties.len(),
+        });
+        for property in properties {
+            let key: Cow<str> = match &fields {
+                Some(fields) => match fields
+                    .iter()
+                    .find(|(_, encoded)| **encoded == *self.key(property))
+                {
+                    Some((field, _)) => Cow::Borrowed(field),
+                    None => continue,
+                },
+                None => self.key_name(property)?,
+            };
+            let value = match property.decoded.get() {
+                Some(value) => value.clone(),
+                None => self.decode_value(property, &key)?,
+            };
+            projected.insert(key.into_owned(), value);
+        }
+        Ok(Some(projected))
+    }
+
+    fn properties(&self) -> Result<&Option<Vec<Property>>, GraphError> {
+        match self.properties.get() {
+            Some(properties) => Ok(properties),
+            None => {
+                let properties = self.find_properties()?;
+                Ok(self.properties.get_or_init(|| properties))
+            }
+        }
+    }
+
+    fn key(&self, property: &Property) -> &[u8] {
+        &self.bytes[property.key.clone()]
+    }
+
+    /// Name of the property, looked up in the dictionary if it was interned
+    fn key_name(&self, property: &Property) -> Result<Cow<'_, str>, GraphError> {
+        let key = self.key(property);
+        match self.interned {
+            Some(dictionary) => {
+                let id = u32::from_le_bytes(key.try_into().unwrap());
+                Ok(Cow::Owned(dictionary.string(id)?.to_string()))
+            }
+            // checked to be UTF-8 when the properties were found
+            None => Ok(Cow::Borrowed(std::str::from_utf8(key).unwrap_or_default())),
+        }
+    }
+
+    fn decode_value(&self, property: &Property, key: &str) -> Result<Value, GraphError> {
+        let mut value = bincode::deserialize::<Value>(&self.bytes[property.value.clone()])
+            .map_err(|e| {
+                GraphError::ConversionError(format!("Error deserializing property: {}", e))
+            })?;
+        enums::decode_field(self.label(), key, &mut value);
+        Ok(value)
+    }
+
+    /// Finds where the key and value of each property are, without decoding them
+    fn find_properties(&self) -> Result<Option<Vec<Property>>, GraphError> {
+        let mut reader = Reader::new(&self.bytes, self.interned);
+        reader.pos = self.properties_at;
+        if reader.u8()? == 0 {
+            return Ok(None);
+        }
+        let len = reader.len()?;
+        let mut properties = Vec::with_capacity(len.min(reader.remaining()));
+        for _ in 0..len {
+            let key = reader.key()?;
+            let start = reader.pos;
+            reader.skip_value()?;
+            properties.push(Property {
+                key,
+                value: start..reader.pos,
+                decoded: OnceCell::new(),
+            });
+        }
+        Ok(Some(properties))
+    }
+}
+
+/// Reads the fields of a node or edge encoded by bincode
+struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+    /// Dictionary of the item if it was written with interned strings
+    interned: Option<&'b Dictionary>,
+}
+
+impl<'b> Reader<'b> {
+    fn new(bytes: &'b [u8], interned: Option<&'b Dictionary>) -> Self {
+        // interned items start with a marker
+        let pos = match interned {
+            Some(_) => 4,
+            None => 0,
+        };
+        Reader {
+            bytes,
+            pos,
+            interned,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<Range<usize>, GraphError> {
+        if len > self.remaining() {
+            return Err(GraphError::ConversionError(
+                "Error reading item: unexpected end of data".to_string(),
+            ));
+        }
+        self.pos += len;
+        Ok(self.pos - len..self.pos)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], GraphError> {
+        let range = self.take(N)?;
+        Ok(self.bytes[range].try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, GraphError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, GraphError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u128(&mut self) -> Result<u128, GraphError> {
+        Ok(u128::from_le_bytes(self.array()?))
+    }
+
+    fn len(&mut self) -> Result<usize, GraphError> {
+        Ok(u64::from_le_bytes(self.array()?) as usize)
+    }
+
+    /// A UTF-8 string, returning where its bytes are
+    fn str(&mut self) -> Result<Range<usize>, GraphError> {
+        let len = self.len()?;
+        let range = self.take(len)?;
+        std::str::from_utf8(&self.bytes[range.clone()])
+            .map_err(|e| GraphError::ConversionError(format!("Error reading item: {}", e)))?;
+        Ok(range)
+    }
+
+    /// The label, looked up in the dictionary if it was interned
+    fn label(&mut self) -> Result<Label, GraphError> {
+        match self.interned {
+            Some(dictionary) => Ok(Label::Interned(dictionary.string(self.u32()?)?)),
+            None => Ok(Label::Encoded(self.str()?)),
+        }
+    }
+
+    /// The name of a property, returning where it is
+    fn key(&mut self) -> Result<Range<usize>, GraphError> {
+        match self.interned {
+            Some(_) => self.take(4),
+            None => self.str(),
+        }
+    }
+
+    /// Skips a `Value`, its variants being laid out in the order they are declared in
+    fn skip_value(&mut self) -> Result<(), GraphError> {
+        let size = match self.u32()? {
+            // String
+            0 => self.len()?,
+            // I8, U8, Boolean
+            3 | 7 | 12 => 1,
+            // I16, U16
+            4 | 8 => 2,
+            // F32, I32, U32
+            1 | 5 | 9 => 4,
+            // F64, I64, U64, DateTime
+            2 | 6 | 10 | 16 => 8,
+            // U128
+            11 => 16,
+            // Array
+            13 => {
+                for _ in 0..self.len()? {
+                    self.skip_value()?;
+                }
+                0
+            }
+            // Object
+            14 => {
+                for _ in 0..self.len()? {
+                    let len = self.len()?;
+                    self.take(len)?;
+                    self.skip_value()?;
+                }
+                0
+            }
+            // Empty
+            15 => 0,
+            variant => {
+                return Err(GraphError::ConversionError(format!(
+                    "Error reading item: unknown value variant {}",
+                    variant
+                )))
+            }
+        };
+        self.take(size)?;
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
-use super::value::{properties_format, Value};
-use crate::helix_engine::types::GraphError;
+use super::{enums, value::Value};
+use crate::helix_engine::{storage_core::compression::decompress, types::GraphError};
 use bincode::Options;
 use sonic_rs::{Deserialize, Serialize};
 use std::{cmp::Ordering, collections::HashMap};
@@ -14,6 +14,9 @@ pub struct Node {
     pub label: String,
     #[serde(default)]
     pub properties: Option<HashMap<String, Value>>,
+    /// Relevance of the node to the search that returned it, which isn't stored
+    #[serde(skip)]
+    pub score: Option<f64>,
 }
 
 impl Eq for Node {}
@@ -33,13 +36,18 @@ impl PartialOrd for Node {
 impl Node {
     pub const NUM_PROPERTIES: usize = 2;
 
+    /// Decodes a stored node, which may have been compressed by the storage
     pub fn decode_node(bytes: &[u8], id: u128) -> Result<Node, GraphError> {
-        match bincode::deserialize::<Node>(bytes) {
-            Ok(node) => {
+        match bincode::deserialize::<Node>(&decompress(bytes)?) {
+            Ok(mut node) => {
+                if let Some(properties) = &mut node.properties {
+                    enums::decode(&node.label, properties);
+                }
                 let node = Node {
                     id,
                     label: node.label,
                     properties: node.properties,
+                    score: None,
                 };
                 Ok(node) // ERROR REACHING END OF FILE EARLs
             }
@@ -50,9 +58,22 @@ impl Node {
         }
     }
 
+    /// Encodes the node to be stored, enum fields as the index of their variant
     pub fn encode_node(&self) -> Result<Vec<u8>, GraphError> {
-        bincode::serialize(&self)
-            .map_err(|e| GraphError::ConversionError(format!("Error serializing node: {}", e)))
+        let encoded = match &self.properties {
+            Some(properties) => enums::encode(&self.label, properties),
+            None => None,
+        };
+        match encoded {
+            Some(properties) => bincode::serialize(&Node {
+                id: self.id,
+                label: self.label.clone(),
+                properties: Some(properties),
+                score: None,
+            }),
+            None => bincode::serialize(&self),
+        }
+        .map_err(|e| GraphError::ConversionError(format!("Error serializing node: {}", e)))
     }
 }
 
@@ -110,9 +131,13 @@ impl PartialOrd for Edge {
 impl Edge {
     pub const NUM_PROPERTIES: usize = 4;
 
+    /// Decodes a stored edge, which may have been compressed by the storage
     pub fn decode_edge(bytes: &[u8], id: u128) -> Result<Edge, GraphError> {
-        match bincode::deserialize::<Edge>(bytes) {
-            Ok(edge) => {
+        match bincode::deserialize::<Edge>(&decompress(bytes)?) {
+            Ok(mut edge) => {
+                if let Some(properties) = &mut edge.properties {
+                    enums::decode(&edge.label, properties);
+                }
                 let edge = Edge {
                     id,
                     label: edge.label,
@@ -129,9 +154,23 @@ impl Edge {
         }
     }
 
+    /// Encodes the edge to be stored, enum fields as the index of their variant
     pub fn encode_edge(&self) -> Result<Vec<u8>, GraphError> {
-        bincode::serialize(self)
-            .map_err(|e| GraphError::ConversionError(format!("Error serializing edge: {}", e)))
+        let encoded = match &self.properties {
+            Some(properties) => enums::encode(&self.label, properties),
+            None => None,
+        };
+        match encoded {
+            Some(properties) => bincode::serialize(&Edge {
+                id: self.id,
+                label: self.label.clone(),
+                from_node: self.from_node,
+                to_node: self.to_node,
+                properties: Some(properties),
+            }),
+            None => bincode::serialize(self),
+        }
+        .map_err(|e| GraphError::ConversionError(format!("Error serializing edge: {}", e)))
     }
 }
 
@@ -167,3 +206,15 @@ impl std::fmt::Debug for Edge {
 pub fn v6_uuid() -> u128 {
     Uuid::now_v6(&[1, 2, 3, 4, 5, 6]).as_u128()
 }
+
+/// Generates a ULID, a 48 bit millisecond timestamp followed by 80 random bits in
+/// Crockford's base32, so ids sort by the time they were generated at
+pub fn ulid() -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    let millis = chrono::Utc::now().timestamp_millis() as u128 & ((1 << 48) - 1);
+    let id = (millis << 80) | (rand::random::<u128>() >> 48);
+    (0..26)
+        .rev()
+        .map(|i| ALPHABET[((id >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
@@ -1,13 +1,23 @@
 pub mod count;
 pub mod date;
+pub mod enums;
+pub mod expression;
 pub mod filterable;
 pub mod id;
+pub mod item_view;
 pub mod items;
 pub mod label_hash;
+pub mod pagination;
+pub mod redaction;
+#[cfg(test)]
+pub mod redaction_tests;
 pub mod remapping;
 pub mod request;
+#[cfg(test)]
+mod request_tests;
 pub mod response;
 pub mod return_values;
 pub mod serdes;
+pub mod transaction;
 pub mod traversal_value;
 pub mod value;
@@ -0,0 +1,105 @@
+use super::{request::Request, return_values::ReturnValue};
+use crate::helix_engine::types::GraphError;
+use sonic_rs::Serialize;
+use std::{fmt, str::FromStr};
+
+/// Request header carrying the continuation token of a previous page
+pub const CURSOR_HEADER: &str = "x-helix-cursor";
+/// Request header carrying the number of items to return per page
+pub const PAGE_SIZE_HEADER: &str = "x-helix-page-size";
+/// Response header carrying the continuation token for the next page
+pub const NEXT_CURSOR_HEADER: &str = "X-Helix-Next-Cursor";
+
+/// Opaque continuation token handed back to clients so they can fetch the next page
+/// of a result set.
+///
+/// The token carries the id of the [`Snapshot`](crate::helix_engine::graph_core::snapshot::Snapshot)
+/// the cursor keeps pinned, every page of the cursor being read from it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CursorToken {
+    snapshot_id: u64,
+    nonce: u64,
+}
+
+impl CursorToken {
+    pub fn new(snapshot_id: usize) -> Self {
+        Self {
+            snapshot_id: snapshot_id as u64,
+            nonce: rand::random::<u64>(),
+        }
+    }
+
+    /// The id of the snapshot the pages are read from
+    pub fn snapshot_id(&self) -> u64 {
+        self.snapshot_id
+    }
+}
+
+impl fmt::Display for CursorToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}{:016x}", self.snapshot_id, self.nonce)
+    }
+}
+
+impl FromStr for CursorToken {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 32 || !s.is_ascii() {
+            return Err(GraphError::InvalidCursor(s.to_string()));
+        }
+        let snapshot_id = u64::from_str_radix(&s[0..16], 16)
+            .map_err(|_| GraphError::InvalidCursor(s.to_string()))?;
+        let nonce = u64::from_str_radix(&s[16..32], 16)
+            .map_err(|_| GraphError::InvalidCursor(s.to_string()))?;
+        Ok(Self { snapshot_id, nonce })
+    }
+}
+
+/// A single page of a result set.
+///
+/// `cursor` is `None` once the result set has been exhausted.
+#[derive(Serialize, Debug, Clone)]
+pub struct Page {
+    pub items: Vec<ReturnValue>,
+    pub cursor: Option<String>,
+}
+
+/// Pagination parameters sent by the client through the request headers
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageRequest {
+    pub cursor: Option<CursorToken>,
+    pub page_size: usize,
+}
+
+impl PageRequest {
+    pub const DEFAULT_PAGE_SIZE: usize = 100;
+    pub const MAX_PAGE_SIZE: usize = 10_000;
+
+    /// Reads the pagination headers of a request
+    ///
+    /// Returns `Ok(None)` if the client did not ask for a paginated response.
+    pub fn from_request(request: &Request) -> Result<Option<PageRequest>, GraphError> {
+        let cursor = request.headers.get(CURSOR_HEADER);
+        let page_size = request.headers.get(PAGE_SIZE_HEADER);
+        if cursor.is_none() && page_size.is_none() {
+            return Ok(None);
+        }
+
+        let cursor = match cursor {
+            Some(cursor) => Some(cursor.parse::<CursorToken>()?),
+            None => None,
+        };
+        let page_size = match page_size {
+            Some(size) => size.parse::<usize>().map_err(|_| {
+                GraphError::ConversionError(format!("Invalid page size: {}", size))
+            })?,
+            None => Self::DEFAULT_PAGE_SIZE,
+        };
+
+        Ok(Some(PageRequest {
+            cursor,
+            page_size: page_size.clamp(1, Self::MAX_PAGE_SIZE),
+        }))
+    }
+}
@@ -0,0 +1,124 @@
+//! Fields annotated `@sensitive` in the schema.
+//!
+//! They are left out of the responses to requests whose roles weren't granted access to
+//! them by the annotation, e.g. `ssn: String @sensitive(admin, hr)`. The generated
+//! queries submit the sensitive fields of the schema, and the router runs each request
+//! in a [`Redaction::scope`] holding the roles it was authenticated with. Outside a scope
+//! no role is held, so the fields are always left out.
+//!
+//! Sensitive fields are still written, indexed and matched by index lookups, only the
+//! values returned, remapped or read by property steps and expressions are redacted.
+
+use serde_json::Value as JsonValue;
+use std::{cell::RefCell, collections::HashMap, sync::LazyLock};
+
+/// A field of a node, edge or vector label only returned to the roles listed
+pub struct SensitiveField {
+    pub label: &'static str,
+    pub field: &'static str,
+    pub roles: &'static [&'static str],
+}
+
+inventory::collect!(SensitiveField);
+
+type Roles = &'static [&'static str];
+
+/// Label -> field -> roles granted access
+static SENSITIVE_FIELDS: LazyLock<HashMap<&'static str, HashMap<&'static str, Roles>>> =
+    LazyLock::new(|| {
+        let mut fields: HashMap<&str, HashMap<&str, Roles>> = HashMap::new();
+        for sensitive_field in inventory::iter::<SensitiveField> {
+            fields
+                .entry(sensitive_field.label)
+                .or_default()
+                .insert(sensitive_field.field, sensitive_field.roles);
+        }
+        fields
+    });
+
+thread_local! {
+    static ROLES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+pub struct Redaction;
+
+impl Redaction {
+    /// Runs `f` with the roles of the request it handles, the roles of any enclosing
+    /// scope being restored afterwards
+    pub fn scope<T>(roles: Vec<String>, f: impl FnOnce() -> T) -> T {
+        struct Restore(Vec<String>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                ROLES.with(|cell| *cell.borrow_mut() = std::mem::take(&mut self.0));
+            }
+        }
+        let _restore = Restore(ROLES.with(|cell| cell.replace(roles)));
+        f()
+    }
+
+    /// Roles of the current scope, to run work on another thread with them
+    pub fn roles() -> Vec<String> {
+        ROLES.with(|cell| cell.borrow().clone())
+    }
+}
+
+/// Whether the field of the label is left out for the roles of the current thread
+#[inline]
+pub fn is_hidden(label: &str, field: &str) -> bool {
+    match SENSITIVE_FIELDS
+        .get(label)
+        .and_then(|fields| fields.get(field))
+    {
+        Some(roles) => !has_access(roles),
+        None => false,
+    }
+}
+
+/// Removes the fields of the label the roles of the current thread can't access
+pub fn redact<V>(label: &str, fields: &mut HashMap<String, V>) {
+    let Some(sensitive) = SENSITIVE_FIELDS.get(label) else {
+        return;
+    };
+    for (field, roles) in sensitive {
+        if fields.contains_key(*field) && !has_access(roles) {
+            fields.remove(*field);
+        }
+    }
+}
+
+/// Removes the fields of JSON parameters, at any depth, named like a sensitive field of
+/// any label the roles of the current thread can't access, e.g. for the parameters of the
+/// queries recorded in the audit log. Parameters that aren't JSON are left as they are.
+pub fn redact_parameters(parameters: &str) -> String {
+    fn redact_value(value: &mut JsonValue) {
+        match value {
+            JsonValue::Object(fields) => {
+                fields.retain(|field, _| {
+                    !SENSITIVE_FIELDS.values().any(|sensitive| {
+                        sensitive
+                            .get(field.as_str())
+                            .is_some_and(|roles| !has_access(roles))
+                    })
+                });
+                fields.values_mut().for_each(redact_value);
+            }
+            JsonValue::Array(values) => values.iter_mut().for_each(redact_value),
+            _ => {}
+        }
+    }
+    match serde_json::from_str::<JsonValue>(parameters) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) => parameters.to_string(),
+    }
+}
+
+fn has_access(roles: Roles) -> bool {
+    ROLES.with(|cell| {
+        cell.borrow()
+            .iter()
+            .any(|role| roles.contains(&role.as_str()))
+    })
+}
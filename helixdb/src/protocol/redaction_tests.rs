@@ -0,0 +1,195 @@
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+
+use tempfile::TempDir;
+
+use crate::{
+    helix_engine::{
+        graph_core::{
+            config::Config,
+            ops::{
+                g::G,
+                source::{add_n::AddNAdapter, n_from_type::NFromTypeAdapter},
+                tr_val::{Traversable, TraversalVal},
+                util::props::PropsAdapter,
+            },
+        },
+        storage_core::storage_core::HelixGraphStorage,
+        types::GraphError,
+    },
+    expression_remapping, props,
+    protocol::{
+        expression,
+        items::Node,
+        redaction::{redact_parameters, Redaction, SensitiveField},
+        remapping::{Remapping, ResponseRemapping},
+        return_values::ReturnValue,
+        value::Value,
+    },
+};
+
+inventory::submit! { SensitiveField { label: "patient", field: "ssn", roles: &["doctor", "admin"] } }
+
+fn patient() -> Node {
+    Node {
+        id: 1,
+        label: "patient".to_string(),
+        properties: Some(HashMap::from([
+            ("name".to_string(), Value::from("alice")),
+            ("ssn".to_string(), Value::from("123-45-6789")),
+        ])),
+        score: None,
+    }
+}
+
+fn fields(value: ReturnValue) -> Vec<String> {
+    let ReturnValue::Object(object) = value else {
+        panic!("expected an object, got {:?}", value);
+    };
+    let mut fields = object.into_keys().collect::<Vec<_>>();
+    fields.sort();
+    fields
+}
+
+fn roles(roles: &[&str]) -> Vec<String> {
+    roles.iter().map(|role| role.to_string()).collect()
+}
+
+#[test]
+fn test_sensitive_fields_are_left_out() {
+    assert_eq!(
+        fields(ReturnValue::from(patient())),
+        ["id", "label", "name"]
+    );
+    Redaction::scope(roles(&["nurse"]), || {
+        assert_eq!(
+            fields(ReturnValue::from(patient())),
+            ["id", "label", "name"]
+        );
+    });
+    Redaction::scope(roles(&["nurse", "doctor"]), || {
+        assert_eq!(
+            fields(ReturnValue::from(patient())),
+            ["id", "label", "name", "ssn"]
+        );
+        // the roles of an enclosing scope are restored
+        Redaction::scope(Vec::new(), || {
+            assert_eq!(
+                fields(ReturnValue::from(patient())),
+                ["id", "label", "name"]
+            );
+        });
+        assert_eq!(fields(ReturnValue::from(patient())).len(), 4);
+    });
+
+    // other labels aren't redacted
+    let mut node = patient();
+    node.label = "person".to_string();
+    assert_eq!(fields(ReturnValue::from(node)).len(), 4);
+}
+
+#[test]
+fn test_remapped_sensitive_fields_are_left_out() {
+    let remapped = || {
+        let remapping = Remapping::new(
+            false,
+            Some("social".to_string()),
+            Some(ReturnValue::from("123-45-6789")),
+        );
+        let remappings = RefCell::new(HashMap::from([(
+            1,
+            ResponseRemapping::new(HashMap::from([("ssn".to_string(), remapping)]), true),
+        )]));
+        let ReturnValue::Array(values) = ReturnValue::from_traversal_value_array_with_mixin(
+            vec![TraversalVal::Node(patient())],
+            remappings.borrow_mut(),
+        ) else {
+            unreachable!()
+        };
+        fields(values.into_iter().next().unwrap())
+    };
+    assert_eq!(remapped(), ["id", "label", "name"]);
+    Redaction::scope(roles(&["admin"]), || {
+        assert_eq!(remapped(), ["id", "label", "name", "social"]);
+    });
+}
+
+#[test]
+fn test_sensitive_fields_read_as_empty_by_expressions() {
+    let aliased = || {
+        let remapping_vals = RefCell::new(HashMap::new());
+        let item = TraversalVal::Node(patient());
+        let item = expression_remapping!(
+            remapping_vals,
+            item.clone(),
+            "digits" => expression::len(expression::property(&item, "ssn"))
+        )
+        .unwrap();
+        ReturnValue::from_traversal_value_array_with_mixin(vec![item], remapping_vals.borrow_mut())
+    };
+    let ReturnValue::Array(values) = aliased() else {
+        unreachable!()
+    };
+    let ReturnValue::Object(object) = &values[0] else {
+        unreachable!()
+    };
+    assert!(!object.contains_key("ssn"));
+    assert!(matches!(object.get("digits"), None | Some(ReturnValue::Empty)));
+
+    Redaction::scope(roles(&["doctor"]), || {
+        let ReturnValue::Array(values) = aliased() else {
+            unreachable!()
+        };
+        let ReturnValue::Object(object) = &values[0] else {
+            unreachable!()
+        };
+        assert!(matches!(
+            object.get("digits"),
+            Some(ReturnValue::Value(Value::I64(11)))
+        ));
+    });
+}
+
+#[test]
+fn test_property_steps_read_sensitive_fields_as_unset() {
+    let dir = TempDir::new().unwrap();
+    let storage =
+        Arc::new(HelixGraphStorage::new(dir.path().to_str().unwrap(), Config::default()).unwrap());
+    let mut txn = storage.graph_env.write_txn().unwrap();
+    G::new_mut(Arc::clone(&storage), &mut txn)
+        .add_n(
+            "patient",
+            Some(props! { "name" => "alice", "ssn" => "123-45-6789" }),
+            None,
+        )
+        .collect_to::<Vec<_>>();
+    txn.commit().unwrap();
+
+    let txn = storage.graph_env.read_txn().unwrap();
+    let read = |property: &str| {
+        G::new(Arc::clone(&storage), &txn)
+            .n_from_type("patient")
+            .check_property(property)
+            .collect_to::<Vec<_>>()
+    };
+    assert_eq!(read("name").len(), 1);
+    assert!(read("ssn").is_empty());
+    Redaction::scope(roles(&["doctor"]), || {
+        assert!(matches!(
+            read("ssn").as_slice(),
+            [TraversalVal::Value(Value::String(ssn))] if ssn == "123-45-6789"
+        ));
+    });
+}
+
+#[test]
+fn test_sensitive_parameters_are_left_out() {
+    let parameters = r#"{"name":"alice","ssn":"123-45-6789","visits":[{"ssn":"987-65-4321"}]}"#;
+    assert_eq!(
+        redact_parameters(parameters),
+        r#"{"name":"alice","visits":[{}]}"#
+    );
+    Redaction::scope(roles(&["doctor"]), || {
+        assert_eq!(redact_parameters(parameters), parameters);
+    });
+    assert_eq!(redact_parameters("not json"), "not json");
+}
@@ -1,15 +1,22 @@
 use std::collections::HashMap;
-use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader, Result};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader, Result};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Request {
     pub method: String,
     pub headers: HashMap<String, String>,
     pub path: String,
+    pub query: Option<String>,
+    pub version: String,
     pub body: Vec<u8>,
+    /// Claims of the JWT the request was authenticated with
+    pub claims: Option<sonic_rs::Value>,
 }
 
 impl Request {
+    /// Largest body a request can have unless another limit is configured, 32 MiB
+    pub const DEFAULT_MAX_BODY_SIZE: usize = 32 * 1024 * 1024;
+
     /// Parse a request from a stream
     ///
     /// # Example
@@ -24,21 +31,62 @@ impl Request {
     /// ```
     pub async fn from_stream<R: AsyncRead + Unpin>(stream: &mut R) -> Result<Request> {
         let mut reader = BufReader::new(stream);
+        match Self::from_reader(&mut reader).await? {
+            Some(request) => Ok(request),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Connection closed before request was sent",
+            )),
+        }
+    }
+
+    /// Parse the next request from a buffered reader
+    ///
+    /// The reader is left positioned at the start of the next request so it can be
+    /// reused for subsequent requests on a keep-alive connection.
+    ///
+    /// Returns `Ok(None)` if the connection was closed before any bytes were received.
+    /// Bodies are limited to [`Request::DEFAULT_MAX_BODY_SIZE`].
+    pub async fn from_reader<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Request>> {
+        Self::from_reader_with_limit(reader, Self::DEFAULT_MAX_BODY_SIZE).await
+    }
+
+    /// Parse the next request from a buffered reader, failing with
+    /// [`std::io::ErrorKind::FileTooLarge`] if the body is larger than `max_body_size`
+    /// bytes. The body isn't read in that case, so the connection can't be reused.
+    pub async fn from_reader_with_limit<R: AsyncBufRead + Unpin>(
+        reader: &mut R,
+        max_body_size: usize,
+    ) -> Result<Option<Request>> {
         let mut first_line = String::new();
-        reader.read_line(&mut first_line).await?;
+        // skip stray line breaks left between pipelined requests
+        loop {
+            first_line.clear();
+            if reader.read_line(&mut first_line).await? == 0 {
+                return Ok(None);
+            }
+            if !first_line.trim().is_empty() {
+                break;
+            }
+        }
 
-        // Get method and path
+        // Get method, path and version
         let mut parts = first_line.trim().split_whitespace();
         let method = parts.next()
             .ok_or_else(|| std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 format!("Missing HTTP method: {}", first_line)
             ))?.to_string();
-        let path = parts.next()
+        let target = parts.next()
             .ok_or_else(|| std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 format!("Missing path: {}", first_line)
-            ))?.to_string();
+            ))?;
+        let version = parts.next().unwrap_or("HTTP/1.0").to_string();
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (target.to_string(), None),
+        };
 
         // Parse headers
         let mut headers = HashMap::new();
@@ -58,38 +106,180 @@ impl Request {
         }
 
         // Read body
-        let mut body = Vec::new();
-        if let Some(length) = headers.get("content-length") {
-            if let Ok(length) = length.parse::<usize>() {
-                let mut buffer = vec![0; length];
-                match tokio::time::timeout(
-                    std::time::Duration::from_secs(5),
-                    reader.read_exact(&mut buffer)
-                ).await {
-                    Ok(Ok(_)) => body = buffer,
-                    Ok(Err(e)) => {
-                        eprintln!("Error reading body: {}", e);
-                        return Err(std::io::Error::new(
-                            std::io::ErrorKind::Other,
-                            "Error reading body"
-                        ));
-                    },
-                    Err(_) => {
-                        eprintln!("Timeout reading body");
-                        return Err(std::io::Error::new(
-                            std::io::ErrorKind::TimedOut,
-                            "Timeout reading body"
-                        ));
-                    }
-                }
+        let is_chunked = headers
+            .get("transfer-encoding")
+            .is_some_and(|encoding| encoding.to_lowercase().contains("chunked"));
+        let body = match tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            Self::read_body(reader, &headers, is_chunked, max_body_size),
+        )
+        .await
+        {
+            Ok(Ok(body)) => body,
+            Ok(Err(e)) => {
+                eprintln!("Error reading body: {}", e);
+                return Err(e);
             }
-        }
+            Err(_) => {
+                eprintln!("Timeout reading body");
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "Timeout reading body"
+                ));
+            }
+        };
 
-        Ok(Request {
+        Ok(Some(Request {
             method,
             headers,
             path,
+            query,
+            version,
             body,
+            claims: None,
+        }))
+    }
+
+    /// Whether the connection should be kept open after responding to this request
+    ///
+    /// HTTP/1.1 connections are persistent unless the client sends `Connection: close`,
+    /// HTTP/1.0 connections are closed unless the client sends `Connection: keep-alive`.
+    pub fn keep_alive(&self) -> bool {
+        let connection = self
+            .headers
+            .get("connection")
+            .map(|value| value.to_lowercase());
+        match connection.as_deref() {
+            Some("close") => false,
+            Some("keep-alive") => true,
+            _ => self.version.eq_ignore_ascii_case("HTTP/1.1"),
+        }
+    }
+
+    /// Value of a query string parameter, percent-decoded, or `None` if it isn't given
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        self.query.as_deref().and_then(|query| {
+            query
+                .split('&')
+                .filter_map(|param| param.split_once('='))
+                .find(|(key, _)| percent_decode(key) == name)
+                .map(|(_, value)| percent_decode(value))
         })
     }
-}
\ No newline at end of file
+
+    async fn read_body<R: AsyncBufRead + Unpin>(
+        reader: &mut R,
+        headers: &HashMap<String, String>,
+        is_chunked: bool,
+        max_body_size: usize,
+    ) -> Result<Vec<u8>> {
+        if is_chunked {
+            return Self::read_chunked_body(reader, max_body_size).await;
+        }
+        let length = match headers.get("content-length") {
+            Some(length) => length.parse::<usize>().map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Invalid content length: {}", length),
+                )
+            })?,
+            None => return Ok(Vec::new()),
+        };
+        check_body_size(length, max_body_size)?;
+        let mut buffer = vec![0; length];
+        reader.read_exact(&mut buffer).await?;
+        Ok(buffer)
+    }
+
+    async fn read_chunked_body<R: AsyncBufRead + Unpin>(
+        reader: &mut R,
+        max_body_size: usize,
+    ) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Connection closed in the middle of a chunked body",
+                ));
+            }
+            // chunk extensions after ';' are ignored
+            let size = line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size.trim(), 16).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Invalid chunk size: {}", line.trim()),
+                )
+            })?;
+
+            if size == 0 {
+                // discard trailers up to the terminating empty line
+                loop {
+                    line.clear();
+                    let bytes_read = reader.read_line(&mut line).await?;
+                    if bytes_read == 0 || line.eq("\r\n") || line.eq("\n") {
+                        return Ok(body);
+                    }
+                }
+            }
+
+            // the size is untrusted, so the body is only grown once it is known to fit
+            let start = body.len();
+            let end = start.checked_add(size).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Chunk size overflows the body length: {}", line.trim()),
+                )
+            })?;
+            check_body_size(end, max_body_size)?;
+            body.resize(end, 0);
+            reader.read_exact(&mut body[start..]).await?;
+
+            // every chunk is terminated by a CRLF
+            line.clear();
+            reader.read_line(&mut line).await?;
+        }
+    }
+}
+
+fn check_body_size(size: usize, max_body_size: usize) -> Result<()> {
+    match size > max_body_size {
+        true => Err(std::io::Error::new(
+            std::io::ErrorKind::FileTooLarge,
+            format!(
+                "Request body of {} bytes exceeds the limit of {} bytes",
+                size, max_body_size
+            ),
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Decodes `%XX` escapes and `+` as a space, as in `application/x-www-form-urlencoded` query
+/// strings. Malformed escapes are kept as they are.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match bytes
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+            {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,87 @@
+use std::io::ErrorKind;
+
+use tokio::io::BufReader;
+
+use crate::protocol::request::Request;
+
+async fn read(raw: &str, max_body_size: usize) -> std::io::Result<Option<Request>> {
+    let mut reader = BufReader::new(raw.as_bytes());
+    Request::from_reader_with_limit(&mut reader, max_body_size).await
+}
+
+#[tokio::test]
+async fn test_reads_body_within_limit() {
+    let request = read("POST /q HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody", 4)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(request.body, b"body");
+}
+
+#[tokio::test]
+async fn test_rejects_content_length_over_limit() {
+    let err = read("POST /q HTTP/1.1\r\nContent-Length: 5\r\n\r\nbody!", 4)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FileTooLarge);
+}
+
+#[tokio::test]
+async fn test_default_limit_rejects_huge_content_length() {
+    // nothing is allocated for a body that would go over the default limit
+    let mut reader =
+        BufReader::new("POST /q HTTP/1.1\r\nContent-Length: 99999999999999\r\n\r\n".as_bytes());
+    let err = Request::from_reader(&mut reader).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FileTooLarge);
+}
+
+#[tokio::test]
+async fn test_reads_chunked_body() {
+    let request = read(
+        "POST /q HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nbo\r\n2;ext=1\r\ndy\r\n0\r\n\r\n",
+        4,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(request.body, b"body");
+}
+
+#[tokio::test]
+async fn test_rejects_chunks_over_limit() {
+    let err = read(
+        "POST /q HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nbo\r\n3\r\ndy!\r\n0\r\n\r\n",
+        4,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FileTooLarge);
+}
+
+#[tokio::test]
+async fn test_rejects_overflowing_chunk_size() {
+    let err = read(
+        "POST /q HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nbo\r\nffffffffffffffff\r\n",
+        usize::MAX,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn test_query_params_are_percent_decoded() {
+    let request = read(
+        "GET /subscribe?label=team%20member&q=a+b%2Bc&bad=100%&x%3Dy=1 HTTP/1.1\r\n\r\n",
+        0,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(request.query_param("label").as_deref(), Some("team member"));
+    assert_eq!(request.query_param("q").as_deref(), Some("a b+c"));
+    // malformed escapes are kept as they are
+    assert_eq!(request.query_param("bad").as_deref(), Some("100%"));
+    assert_eq!(request.query_param("x=y").as_deref(), Some("1"));
+    assert_eq!(request.query_param("missing"), None);
+}
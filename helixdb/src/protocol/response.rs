@@ -1,3 +1,5 @@
+use super::pagination::{Page, NEXT_CURSOR_HEADER};
+use crate::helix_engine::types::GraphError;
 use std::collections::HashMap;
 use tokio::io::{AsyncWrite, AsyncWriteExt, Result};
 #[derive(Debug)]
@@ -8,6 +10,9 @@ pub struct Response {
 }
 
 impl Response {
+    /// Size of the chunks written when the response uses chunked transfer encoding
+    pub const CHUNK_SIZE: usize = 16 * 1024;
+
     /// Create a new response
     pub fn new() -> Response {
         let mut headers = HashMap::new();
@@ -21,6 +26,26 @@ impl Response {
         }
     }
 
+    /// Write a page of results to the response body
+    ///
+    /// The continuation token (if any) is written both to the body and to the
+    /// `X-Helix-Next-Cursor` header so clients can follow it without parsing the body.
+    pub fn set_page(&mut self, page: &Page) -> std::result::Result<(), GraphError> {
+        self.body = sonic_rs::to_vec(page)?;
+        self.headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        match &page.cursor {
+            Some(cursor) => {
+                self.headers
+                    .insert(NEXT_CURSOR_HEADER.to_string(), cursor.clone());
+            }
+            None => {
+                self.headers.remove(NEXT_CURSOR_HEADER);
+            }
+        }
+        Ok(())
+    }
+
     /// Send response back via stream
     ///
     /// # Example
@@ -45,18 +70,14 @@ impl Response {
     /// assert!(data.contains("Hello World"));
 
     pub async fn send<W: AsyncWrite + Unpin>(&mut self, stream: &mut W) -> Result<()> {
-        let status_message = match self.status {
-            200 => "OK",
-            404 => {
-                self.body = b"404 - Route Not Found\n".to_vec();
-                "Not Found"
-            }
-            500 => {
-                // self.body = b"500 - Internal Server Error\n".to_vec();
-                "Internal Server Error"
-            }
-            _ => "Unknown",
-        };
+        if self.status == 404 {
+            self.body = b"404 - Route Not Found\n".to_vec();
+        }
+        let status_message = Self::status_message(self.status);
+        let is_chunked = self
+            .headers
+            .get("Transfer-Encoding")
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"));
         let mut writer = tokio::io::BufWriter::new(stream);
 
         // Write status line
@@ -71,13 +92,51 @@ impl Response {
                 .await.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("Error writing header: {}", e)))?;
         }
 
-        writer
-            .write_all(format!("Content-Length: {}\r\n\r\n", self.body.len()).as_bytes())
-            .await?;
+        if is_chunked {
+            writer.write_all(b"\r\n").await?;
+
+            // Write body as a series of chunks followed by the terminating zero sized chunk
+            for chunk in self.body.chunks(Self::CHUNK_SIZE) {
+                writer
+                    .write_all(format!("{:X}\r\n", chunk.len()).as_bytes())
+                    .await?;
+                writer.write_all(chunk).await?;
+                writer.write_all(b"\r\n").await?;
+            }
+            writer.write_all(b"0\r\n\r\n").await?;
+        } else {
+            writer
+                .write_all(format!("Content-Length: {}\r\n\r\n", self.body.len()).as_bytes())
+                .await?;
 
-        // Write body
-        writer.write_all(&self.body).await?;
+            // Write body
+            writer.write_all(&self.body).await?;
+        }
         writer.flush().await?;
         Ok(())
     }
+
+    /// Reason phrase for a status code
+    pub fn status_message(status: u16) -> &'static str {
+        match status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            421 => "Misdirected Request",
+            429 => "Too Many Requests",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            _ => "Unknown",
+        }
+    }
 }
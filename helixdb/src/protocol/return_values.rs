@@ -1,7 +1,9 @@
 use super::{
     count::Count,
     filterable::{Filterable, FilterableType},
+    id::ID,
     items::{Edge, Node},
+    redaction,
     remapping::{Remapping, ResponseRemapping},
     value::Value,
 };
@@ -16,6 +18,8 @@ pub enum ReturnValue {
     Array(Vec<ReturnValue>),
     Object(HashMap<String, ReturnValue>),
     Value(Value),
+    /// Formatted as a UUID string when serialized
+    Id(ID),
     Empty,
 }
 
@@ -26,6 +30,7 @@ impl Serialize for ReturnValue {
     {
         match self {
             ReturnValue::Value(value) => value.serialize(serializer),
+            ReturnValue::Id(id) => id.serialize(serializer),
             ReturnValue::Object(object) => object.serialize(serializer),
             ReturnValue::Array(array) => array.serialize(serializer),
             ReturnValue::Empty => serializer.serialize_none(),
@@ -45,6 +50,12 @@ impl From<&Value> for ReturnValue {
     }
 }
 
+impl From<ID> for ReturnValue {
+    fn from(id: ID) -> Self {
+        ReturnValue::Id(id)
+    }
+}
+
 impl From<Count> for ReturnValue {
     fn from(count: Count) -> Self {
         ReturnValue::Value(Value::I32(count.value() as i32))
@@ -124,22 +135,29 @@ where
             None => 0,
         };
         let mut properties = match item.type_name() {
-            FilterableType::Node => HashMap::with_capacity(Node::NUM_PROPERTIES + length),
+            FilterableType::Node => {
+                let mut properties = HashMap::with_capacity(Node::NUM_PROPERTIES + 1 + length);
+                // nodes returned by a search carry how well they matched it
+                if let Some(score) = item.score() {
+                    properties.insert("score".to_string(), ReturnValue::from(score));
+                }
+                properties
+            }
             FilterableType::Edge => {
                 let mut properties = HashMap::with_capacity(Edge::NUM_PROPERTIES + length);
                 properties.insert(
                     "from_node".to_string(),
-                    ReturnValue::from(item.from_node_uuid()),
+                    ReturnValue::from(ID::from(item.from_node())),
                 );
                 properties.insert(
                     "to_node".to_string(),
-                    ReturnValue::from(item.to_node_uuid()),
+                    ReturnValue::from(ID::from(item.to_node())),
                 );
                 properties
             }
             FilterableType::Vector => {
                 let data = item.vector_data();
-                let score = item.score();
+                let score = item.score().unwrap_or_default();
 
                 let mut return_value = HashMap::with_capacity(2 + length);
                 return_value.insert("data".to_string(), ReturnValue::from(data));
@@ -147,19 +165,21 @@ where
                 return_value
             }
         };
-        properties.insert("id".to_string(), ReturnValue::from(item.uuid()));
-        properties.insert(
-            "label".to_string(),
-            ReturnValue::from(item.label().to_string()),
-        );
+        properties.insert("id".to_string(), ReturnValue::from(ID::from(*item.id())));
+        let label = item.label().to_string();
         if item.properties_ref().is_some() {
+            let mut item_properties = item.properties().unwrap();
+            redaction::redact(&label, &mut item_properties);
             properties.extend(
-                item.properties()
-                    .unwrap()
+                item_properties
                     .into_iter()
                     .map(|(k, v)| (k, ReturnValue::from(v))),
             );
         }
+        // a property named label is returned in its place
+        properties
+            .entry("label".to_string())
+            .or_insert_with(|| ReturnValue::from(label));
         ReturnValue::Object(properties)
     }
 }
@@ -193,6 +213,8 @@ impl ReturnValue {
     {
         let id = item.id();
         if let Some(m) = mixin.get_mut(&id) {
+            // remapped values of sensitive fields are left out like the fields themselves
+            redaction::redact(item.label(), &mut m.remappings);
             if m.should_spread {
                 ReturnValue::from(item).mixin_remapping(&mut m.remappings)
             } else {
@@ -0,0 +1,68 @@
+use super::request::Request;
+use crate::helix_engine::types::GraphError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Path of the endpoint running several queries in a single transaction
+pub const TRANSACTION_PATH: &str = "/transaction";
+
+/// Body of a request to the transaction endpoint.
+///
+/// All queries run in order against the same write transaction, so later queries see
+/// the writes of earlier ones. The transaction is committed once every query has
+/// succeeded and rolled back as soon as one of them fails.
+///
+/// ```json
+/// {
+///     "queries": [
+///         { "query": "addUser", "params": { "name": "John" } },
+///         { "query": "getUsers" }
+///     ]
+/// }
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct TransactionRequest {
+    pub queries: Vec<TransactionQuery>,
+    /// Roll the transaction back after running all queries instead of committing it,
+    /// e.g. to check whether a set of writes would succeed
+    #[serde(default)]
+    pub rollback: bool,
+}
+
+/// A single query of a [`TransactionRequest`]
+#[derive(Deserialize, Debug)]
+pub struct TransactionQuery {
+    /// Name of the query as it is routed outside of transactions
+    pub query: String,
+    /// The parameters of the query, the body it would be sent with on its own
+    #[serde(default)]
+    pub params: Option<sonic_rs::Value>,
+}
+
+impl TransactionQuery {
+    /// Builds the request the query would have been sent as on its own
+    pub fn to_request(&self, version: &str) -> Result<Request, GraphError> {
+        let body = match &self.params {
+            Some(params) => sonic_rs::to_vec(params)?,
+            None => b"{}".to_vec(),
+        };
+        Ok(Request {
+            method: "POST".to_string(),
+            headers: HashMap::new(),
+            path: format!("/{}", self.query),
+            query: None,
+            version: version.to_string(),
+            body,
+            claims: None,
+        })
+    }
+}
+
+/// Response of the transaction endpoint
+#[derive(Serialize, Debug)]
+pub struct TransactionResponse {
+    /// The responses of the queries in the order they were run
+    pub results: Vec<sonic_rs::Value>,
+    /// Whether the transaction was committed or rolled back on request
+    pub committed: bool,
+}
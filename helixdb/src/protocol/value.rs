@@ -1,4 +1,5 @@
 use crate::{helix_engine::types::GraphError, helixc::generator::utils::GenRef};
+use chrono::{DateTime, Utc};
 use serde::{
     de::{DeserializeSeed, VariantAccess, Visitor},
     Deserializer, Serializer,
@@ -11,7 +12,7 @@ use std::{
     fmt::{self, Display},
 };
 
-use super::id::ID;
+use super::{date::Date, id::ID};
 
 /// A flexible value type that can represent various property values in nodes and edges.
 /// Handles both JSON and binary serialisation formats via custom implementaions of the Serialize and Deserialize traits.
@@ -33,7 +34,17 @@ pub enum Value {
     Array(Vec<Value>),
     Object(HashMap<String, Value>),
     Empty,
+    /// A point in time, kept to the microsecond
+    DateTime(DateTime<Utc>),
 }
+
+/// A step into a nested value, either a field of an object or an element of an array
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathSegment<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
 impl Value {
     pub fn to_string(&self) -> String {
         match self {
@@ -50,9 +61,87 @@ impl Value {
             Value::U64(u) => u.to_string(),
             Value::U128(u) => u.to_string(),
             Value::Boolean(b) => b.to_string(),
+            Value::DateTime(d) => d.to_rfc3339(),
             _ => panic!("Not primitive"),
         }
     }
+
+    /// The value as a float if it is numeric
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::F32(f) => Some(*f as f64),
+            Value::F64(f) => Some(*f),
+            Value::I8(i) => Some(*i as f64),
+            Value::I16(i) => Some(*i as f64),
+            Value::I32(i) => Some(*i as f64),
+            Value::I64(i) => Some(*i as f64),
+            Value::U8(u) => Some(*u as f64),
+            Value::U16(u) => Some(*u as f64),
+            Value::U32(u) => Some(*u as f64),
+            Value::U64(u) => Some(*u as f64),
+            Value::U128(u) => Some(*u as f64),
+            _ => None,
+        }
+    }
+
+    /// The value as a point in time, parsing strings and reading integers as seconds
+    /// since the epoch like a `Date` field does
+    pub fn as_date_time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Value::DateTime(d) => Some(*d),
+            Value::String(_) | Value::I64(_) | Value::U64(_) => {
+                Date::new(self).ok().map(|date| *date.inner())
+            }
+            _ => None,
+        }
+    }
+
+    /// Orders values of any type. Numbers compare by value across numeric types and
+    /// come before strings, then dates, then booleans, then everything else, which
+    /// compares equal. Dates compare with strings holding a date by the time.
+    pub fn total_cmp(&self, other: &Value) -> Ordering {
+        fn rank(value: &Value) -> u8 {
+            match value {
+                Value::String(_) => 1,
+                Value::DateTime(_) => 2,
+                Value::Boolean(_) => 3,
+                value if value.as_f64().is_some() => 0,
+                _ => 4,
+            }
+        }
+        match (self, other) {
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
+            (Value::DateTime(a), Value::String(_)) => match other.as_date_time() {
+                Some(b) => a.cmp(&b),
+                None => rank(self).cmp(&rank(other)),
+            },
+            (Value::String(_), Value::DateTime(b)) => match self.as_date_time() {
+                Some(a) => a.cmp(b),
+                None => rank(self).cmp(&rank(other)),
+            },
+            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+            // exact for integers too large for a float
+            (Value::I64(a), Value::I64(b)) => a.cmp(b),
+            (Value::U64(a), Value::U64(b)) => a.cmp(b),
+            (Value::U128(a), Value::U128(b)) => a.cmp(b),
+            _ => match (self.as_f64(), other.as_f64()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => rank(self).cmp(&rank(other)),
+            },
+        }
+    }
+
+    /// Follows a path of object fields and array indices into the value, returning
+    /// `None` if any step doesn't exist
+    pub fn get_path(&self, path: &[PathSegment]) -> Option<&Value> {
+        path.iter()
+            .try_fold(self, |value, segment| match (value, segment) {
+                (Value::Object(fields), PathSegment::Field(field)) => fields.get(*field),
+                (Value::Array(values), PathSegment::Index(index)) => values.get(*index),
+                _ => None,
+            })
+    }
 }
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -73,6 +162,7 @@ impl Display for Value {
             Value::Array(_) => write!(f, "Array"),
             Value::Object(_) => write!(f, "Object"),
             Value::Empty => write!(f, "Empty"),
+            Value::DateTime(_) => write!(f, "DateTime"),
         }
     }
 }
@@ -155,6 +245,75 @@ impl PartialOrd<i32> for Value {
         }
     }
 }
+impl PartialEq<DateTime<Utc>> for Value {
+    fn eq(&self, other: &DateTime<Utc>) -> bool {
+        self.as_date_time().as_ref() == Some(other)
+    }
+}
+
+impl PartialOrd<DateTime<Utc>> for Value {
+    fn partial_cmp(&self, other: &DateTime<Utc>) -> Option<Ordering> {
+        self.as_date_time()?.partial_cmp(other)
+    }
+}
+
+impl PartialEq<&DateTime<Utc>> for Value {
+    fn eq(&self, other: &&DateTime<Utc>) -> bool {
+        self == *other
+    }
+}
+
+impl PartialOrd<&DateTime<Utc>> for Value {
+    fn partial_cmp(&self, other: &&DateTime<Utc>) -> Option<Ordering> {
+        self.partial_cmp(*other)
+    }
+}
+
+impl PartialEq<&String> for Value {
+    fn eq(&self, other: &&String) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<&i64> for Value {
+    fn eq(&self, other: &&i64) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<&f64> for Value {
+    fn eq(&self, other: &&f64) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<&bool> for Value {
+    fn eq(&self, other: &&bool) -> bool {
+        self == *other
+    }
+}
+
+impl PartialOrd<&i64> for Value {
+    fn partial_cmp(&self, other: &&i64) -> Option<Ordering> {
+        self.partial_cmp(*other)
+    }
+}
+
+impl PartialOrd<&f64> for Value {
+    fn partial_cmp(&self, other: &&f64) -> Option<Ordering> {
+        self.partial_cmp(*other)
+    }
+}
+
+impl PartialOrd<&str> for Value {
+    fn partial_cmp(&self, other: &&str) -> Option<Ordering> {
+        match self {
+            Value::String(s) => s.as_str().partial_cmp(*other),
+            _ => None,
+        }
+    }
+}
+
 impl PartialOrd<f64> for Value {
     fn partial_cmp(&self, other: &f64) -> Option<Ordering> {
         match self {
@@ -203,6 +362,7 @@ impl Serialize for Value {
                     map.end()
                 }
                 Value::Empty => serializer.serialize_none(),
+                Value::DateTime(d) => d.to_rfc3339().serialize(serializer),
             }
         } else {
             match self {
@@ -226,6 +386,12 @@ impl Serialize for Value {
                     serializer.serialize_newtype_variant("Value", 14, "Object", obj)
                 }
                 Value::Empty => serializer.serialize_unit_variant("Value", 15, "Empty"),
+                Value::DateTime(d) => serializer.serialize_newtype_variant(
+                    "Value",
+                    16,
+                    "DateTime",
+                    &ordered_micros(d),
+                ),
             }
         }
     }
@@ -248,7 +414,7 @@ impl<'de> Deserialize<'de> for Value {
 
             #[inline]
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a string, number, boolean, array, null, or Value enum")
+                formatter.write_str("a string, number, boolean, array, object, null, or Value enum")
             }
 
             #[inline]
@@ -371,6 +537,15 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::Empty)
             }
 
+            /// Handles JSON nulls, which self-describing formats give as unit
+            #[inline]
+            fn visit_unit<E>(self) -> Result<Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Value::Empty)
+            }
+
             /// Handles array values by recursively deserialising each element
             fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
             where
@@ -383,6 +558,18 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::Array(values))
             }
 
+            /// Handles object values by recursively deserialising each entry
+            fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut values = HashMap::new();
+                while let Some((key, value)) = map.next_entry()? {
+                    values.insert(key, value);
+                }
+                Ok(Value::Object(values))
+            }
+
             /// Handles binary format deserialisation using numeric indices to identify variants
             /// Maps indices 0-5 to corresponding Value enum variants
             fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
@@ -410,9 +597,13 @@ impl<'de> Deserialize<'de> for Value {
                         variant_data.unit_variant()?;
                         Ok(Value::Empty)
                     }
+                    16 => match from_ordered_micros(variant_data.newtype_variant()?) {
+                        Some(d) => Ok(Value::DateTime(d)),
+                        None => Err(serde::de::Error::custom("date out of range")),
+                    },
                     _ => Err(serde::de::Error::invalid_value(
                         serde::de::Unexpected::Unsigned(variant_idx as u64),
-                        &"variant index 0 through 5",
+                        &"variant index 0 through 16",
                     )),
                 }
             }
@@ -458,7 +649,7 @@ impl<'de> Deserialize<'de> for Value {
                 "Value",
                 &[
                     "String", "F32", "F64", "I8", "I16", "I32", "I64", "U8", "U16", "U32", "U64",
-                    "U128", "Boolean", "Array", "Object", "Empty",
+                    "U128", "Boolean", "Array", "Object", "Empty", "DateTime",
                 ],
                 ValueVisitor,
             )
@@ -466,6 +657,16 @@ impl<'de> Deserialize<'de> for Value {
     }
 }
 
+/// Microseconds since the epoch as big-endian bytes with the sign bit flipped, so the
+/// encoded dates, and index keys made from them, sort bytewise in time order
+fn ordered_micros(date: &DateTime<Utc>) -> [u8; 8] {
+    ((date.timestamp_micros() as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+fn from_ordered_micros(bytes: [u8; 8]) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_micros((u64::from_be_bytes(bytes) ^ (1 << 63)) as i64)
+}
+
 /// Module for custom serialisation of property hashmaps
 /// Ensures consistent handling of Value enum serialisation within property maps
 pub mod properties_format {
@@ -601,10 +802,24 @@ impl From<u128> for Value {
     }
 }
 
-impl From<Vec<Value>> for Value {
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    #[inline]
+    fn from(v: Vec<T>) -> Self {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    #[inline]
+    fn from(v: HashMap<String, T>) -> Self {
+        Value::Object(v.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
     #[inline]
-    fn from(v: Vec<Value>) -> Self {
-        Value::Array(v)
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Value::Empty, Into::into)
     }
 }
 
@@ -652,6 +867,20 @@ impl From<JsonValue> for Value {
     }
 }
 
+impl From<DateTime<Utc>> for Value {
+    #[inline]
+    fn from(date: DateTime<Utc>) -> Self {
+        Value::DateTime(date)
+    }
+}
+
+impl From<Date> for Value {
+    #[inline]
+    fn from(date: Date) -> Self {
+        Value::DateTime(*date.inner())
+    }
+}
+
 impl From<ID> for Value {
     #[inline]
     fn from(id: ID) -> Self {
@@ -712,9 +941,36 @@ impl From<Value> for GenRef<String> {
             Value::U32(u) => GenRef::Std(format!("{}", u)),
             Value::U64(u) => GenRef::Std(format!("{}", u)),
             Value::U128(u) => GenRef::Std(format!("{}", u)),
-            Value::Array(a) => unimplemented!(),
-            Value::Object(o) => unimplemented!(),
+            Value::Array(a) => GenRef::Std(format!(
+                "Value::Array(vec![{}])",
+                a.into_iter().map(gen_value).collect::<Vec<_>>().join(", ")
+            )),
+            Value::Object(o) => {
+                // sorted so the generated code is stable
+                let mut fields = o.into_iter().collect::<Vec<_>>();
+                fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+                GenRef::Std(format!(
+                    "Value::Object(HashMap::from([{}]))",
+                    fields
+                        .into_iter()
+                        .map(|(k, v)| format!("(\"{}\".to_string(), {})", k, gen_value(v)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            }
             Value::Empty => GenRef::Literal("".to_string()),
+            Value::DateTime(d) => GenRef::Std(format!(
+                "\"{}\".parse::<DateTime<Utc>>().unwrap()",
+                d.to_rfc3339()
+            )),
         }
     }
 }
+
+/// Generates the code for a value nested in an array or object literal
+fn gen_value(v: Value) -> String {
+    match v {
+        Value::Empty => "Value::Empty".to_string(),
+        v => format!("Value::from({})", GenRef::from(v)),
+    }
+}